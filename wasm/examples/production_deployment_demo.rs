@@ -6,6 +6,9 @@
 use wasm::webassembly_2_0::*;
 use wasm::security_advanced::*;
 use wasm::developer_tools::*;
+use wasm::monitoring_advanced::{
+    ExportFormat, Metric, MetricMetadata, MetricType, MetricValue, MetricsCollector, MetricsConfig,
+};
 use wasm::types::*;
 use serde::{Serialize, Deserialize};
 use thiserror::Error;
@@ -69,6 +72,8 @@ pub struct ProductionWasmService {
     pub status: ServiceStatus,
     /// 性能监控器
     pub performance_monitor: PerformanceMonitor,
+    /// Prometheus 指标收集器
+    pub metrics_collector: MetricsCollector,
     /// 请求计数器
     pub request_counter: Arc<Mutex<u64>>,
     /// 错误计数器
@@ -130,6 +135,12 @@ impl ProductionWasmService {
             security_manager: AdvancedSecurityManager::new(),
             dev_tools: DeveloperToolsManager::new(),
             performance_monitor: PerformanceMonitor::new(),
+            metrics_collector: MetricsCollector::new(MetricsConfig {
+                enabled: true,
+                collection_interval: Duration::from_secs(5),
+                retention_period: Duration::from_secs(3600),
+                export_format: ExportFormat::Prometheus,
+            }),
             request_counter: Arc::new(Mutex::new(0)),
             error_counter: Arc::new(Mutex::new(0)),
             status: ServiceStatus::Starting,
@@ -157,6 +168,9 @@ impl ProductionWasmService {
         
         // 启动 HTTP 服务器
         self.start_http_server().await?;
+
+        // 启动 Prometheus 指标端点
+        self.start_metrics_endpoint().await?;
         
         self.status = ServiceStatus::Running;
         println!("✅ 服务启动完成");
@@ -449,6 +463,8 @@ impl ProductionWasmService {
             operation_type: OperationType::FunctionCall,
             parameters: HashMap::new(),
             call_stack: Vec::new(),
+            thread_id: None,
+            principal: None,
         };
 
         // 执行安全检查
@@ -494,6 +510,61 @@ impl ProductionWasmService {
         Ok(())
     }
 
+    /// 启动 Prometheus 指标端点，可通过 `curl localhost:9100/metrics` 抓取
+    /// Start the Prometheus metrics endpoint, scrapeable with `curl localhost:9100/metrics`
+    async fn start_metrics_endpoint(&mut self) -> Result<(), ServiceError> {
+        if !self.config.monitoring_config.metrics_enabled {
+            return Ok(());
+        }
+
+        let (addr, _handle) = self.metrics_collector.serve_metrics("0.0.0.0:9100").await
+            .map_err(|e| ServiceError::NetworkError(e.to_string()))?;
+        println!("📈 Prometheus 指标端点: http://{}/metrics", addr);
+        Ok(())
+    }
+
+    /// 将当前性能指标与请求计数发布到 Prometheus 指标收集器
+    /// Publish current performance metrics and request counts to the Prometheus collector
+    pub fn publish_metrics(&self) -> Result<(), ServiceError> {
+        let metadata = |description: &str, unit: Option<&str>| MetricMetadata {
+            description: description.to_string(),
+            unit: unit.map(str::to_string),
+            help: None,
+        };
+        let labels: HashMap<String, String> = [("service".to_string(), self.config.service_name.clone())].into();
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or_default();
+
+        let mut metrics = Vec::new();
+        for (name, value) in self.get_metrics() {
+            let unit = match name.as_str() {
+                "memory_usage" => Some("bytes"),
+                "cpu_usage" => Some("percent"),
+                _ => None,
+            };
+            let metadata = metadata(&name, unit);
+            metrics.push((name, MetricType::Gauge, MetricValue::Float(value), metadata));
+        }
+        let requests = *self.request_counter.lock().unwrap() as i64;
+        let errors = *self.error_counter.lock().unwrap() as i64;
+        metrics.push(("requests_total".to_string(), MetricType::Counter, MetricValue::Integer(requests), metadata("已处理的请求数", None)));
+        metrics.push(("errors_total".to_string(), MetricType::Counter, MetricValue::Integer(errors), metadata("处理失败的请求数", None)));
+
+        for (name, metric_type, value, metadata) in metrics {
+            self.metrics_collector.record_metric(Metric {
+                name,
+                metric_type,
+                value,
+                labels: labels.clone(),
+                timestamp,
+                metadata,
+            }).map_err(|e| ServiceError::ResourceError(e.to_string()))?;
+        }
+        Ok(())
+    }
+
     /// 记录性能指标
     /// Record performance metrics
     #[allow(dead_code)]
@@ -655,6 +726,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         let status = service.get_status();
         let metrics = service.get_metrics();
         let security_report = service.get_security_report();
+        service.publish_metrics()?;
         
         println!("  第 {} 次检查:", i + 1);
         println!("    服务状态: {:?}", status);
@@ -2,12 +2,21 @@
 //!
 //! 本模块提供了基于机器学习和人工智能的智能优化功能
 
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, PoisonError, TryLockError};
 use std::time::Duration;
 use chrono::{DateTime, Utc};
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rayon::prelude::*;
+use sha2::{Digest, Sha256};
 use thiserror::Error;
+use crate::monitoring_advanced::PerformanceMetric;
 
 /// AI 优化引擎
 /// AI Optimization Engine
@@ -16,8 +25,14 @@ pub struct AiOptimizationEngine {
     pub models: HashMap<String, Box<dyn MachineLearningModel>>,
     /// 优化策略
     pub strategies: Vec<Box<dyn AiOptimizationStrategy>>,
-    /// 训练数据
+    /// 训练数据（原始特征）
     pub training_data: Arc<Mutex<Vec<TrainingDataPoint>>>,
+    /// 每个模型训练时拟合的特征缩放器，预测时对输入做同样的变换
+    pub scalers: HashMap<String, FeatureScaler>,
+    /// 每个模型的训练状态，训练期间也可以通过共享的句柄读取
+    pub model_states: Arc<Mutex<HashMap<String, ModelState>>>,
+    /// 特征名，按特征向量中的顺序，用于解释预测
+    pub feature_names: Vec<String>,
     /// 配置
     pub config: AiOptimizationConfig,
 }
@@ -27,12 +42,593 @@ pub struct AiOptimizationEngine {
 pub trait MachineLearningModel: Send + Sync {
     /// 预测
     fn predict(&self, input: &ModelInput) -> Result<ModelOutput, AiError>;
+    /// 批量预测，结果按输入顺序排列，与逐个调用 `predict` 的结果相同
+    fn predict_batch(&self, inputs: &[ModelInput]) -> Result<Vec<ModelOutput>, AiError> {
+        inputs.iter().map(|input| self.predict(input)).collect()
+    }
     /// 训练
     fn train(&mut self, data: &[TrainingDataPoint]) -> Result<(), AiError>;
+    /// 训练一轮并返回这一轮的平均训练损失，引擎用它按轮训练和早停
+    fn train_epoch(&mut self, data: &[TrainingDataPoint], options: &EpochOptions) -> Result<f64, AiError> {
+        let _ = (data, options);
+        Err(AiError::NotSupported(format!("模型 {} 不支持按轮训练", self.get_name())))
+    }
     /// 评估
     fn evaluate(&self, test_data: &[TrainingDataPoint]) -> Result<ModelMetrics, AiError>;
     /// 获取模型名称
     fn get_name(&self) -> String;
+    /// 把模型结构和参数保存到文件
+    fn save(&self, path: &Path) -> Result<(), AiError> {
+        let _ = path;
+        Err(AiError::NotSupported(format!("模型 {} 不支持保存", self.get_name())))
+    }
+    /// 从文件加载参数，文件中的结构必须与模型声明的结构一致
+    fn load(&mut self, path: &Path) -> Result<(), AiError> {
+        let _ = path;
+        Err(AiError::NotSupported(format!("模型 {} 不支持加载", self.get_name())))
+    }
+    /// 复制模型，交叉验证在副本上训练以保证各折互不影响
+    fn clone_model(&self) -> Result<Box<dyn MachineLearningModel>, AiError> {
+        Err(AiError::NotSupported(format!("模型 {} 不支持复制", self.get_name())))
+    }
+    /// 应用模型自身的超参数，学习率和批次大小由引擎在按轮训练时传入；
+    /// 默认实现不接受树参数
+    fn apply_hyperparameters(&mut self, params: &Hyperparameters) -> Result<(), AiError> {
+        if params.max_depth.is_some() || params.min_samples_leaf.is_some() {
+            return Err(AiError::NotSupported(format!("模型 {} 不支持树参数", self.get_name())));
+        }
+        Ok(())
+    }
+}
+
+/// 单轮训练的参数
+/// Per-epoch training options
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct EpochOptions {
+    /// 学习率
+    pub learning_rate: f64,
+    /// 每次更新使用的样本数
+    pub batch_size: usize,
+}
+
+impl Default for EpochOptions {
+    fn default() -> Self {
+        Self { learning_rate: 0.01, batch_size: 32 }
+    }
+}
+
+/// 引擎驱动训练的过程记录
+/// Training Report
+///
+/// 轮次从 0 开始编号，`best_epoch` 是两条损失曲线中的下标。
+/// Epochs are numbered from zero; `best_epoch` indexes both loss curves.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TrainingReport {
+    /// 实际训练的轮数
+    pub epochs_run: u32,
+    /// 验证损失最低的轮次，训练结束后模型恢复为这一轮的参数
+    pub best_epoch: u32,
+    /// 每轮的训练损失
+    pub train_loss_curve: Vec<f64>,
+    /// 每轮的验证损失，没有验证集时为空
+    pub val_loss_curve: Vec<f64>,
+    /// 是否因验证损失连续 `early_stopping_patience` 轮没有改善而提前停止
+    pub stopped_early: bool,
+}
+
+/// 打乱数据并按比例切分为 `(训练集, 验证集)`，相同的种子得到相同的切分
+/// Shuffle and split into `(train, validation)`; the same seed gives the same split
+pub fn split(
+    data: &[TrainingDataPoint],
+    ratio: f64,
+    seed: u64,
+) -> Result<(Vec<TrainingDataPoint>, Vec<TrainingDataPoint>), AiError> {
+    if !(0.0..=1.0).contains(&ratio) {
+        return Err(AiError::DataError(format!("训练集比例必须在 0 到 1 之间，实际为 {}", ratio)));
+    }
+    let indices = shuffled_indices(data.len(), seed);
+    let train_len = (data.len() as f64 * ratio).round() as usize;
+    let (train, validation) = indices.split_at(train_len);
+    Ok((
+        train.iter().map(|&index| data[index].clone()).collect(),
+        validation.iter().map(|&index| data[index].clone()).collect(),
+    ))
+}
+
+/// 打乱数据并划分为 `k` 折，各折大小最多相差 1
+/// Shuffle and partition into `k` folds whose sizes differ by at most one
+pub fn k_fold(data: &[TrainingDataPoint], k: usize, seed: u64) -> Result<KFold<'_>, AiError> {
+    if k < 2 || k > data.len() {
+        return Err(AiError::DataError(format!("折数必须在 2 到样本数 {} 之间，实际为 {}", data.len(), k)));
+    }
+    Ok(KFold { data, indices: shuffled_indices(data.len(), seed), k, next: 0 })
+}
+
+/// 用种子打乱的下标序列
+fn shuffled_indices(len: usize, seed: u64) -> Vec<usize> {
+    let mut indices: Vec<usize> = (0..len).collect();
+    indices.shuffle(&mut StdRng::seed_from_u64(seed));
+    indices
+}
+
+/// k 折划分，依次产出每一折
+/// K-fold partition yielding each fold in turn
+#[derive(Debug, Clone)]
+pub struct KFold<'a> {
+    data: &'a [TrainingDataPoint],
+    indices: Vec<usize>,
+    k: usize,
+    next: usize,
+}
+
+/// k 折中的一折
+/// One fold of a k-fold partition
+#[derive(Debug, Clone)]
+pub struct Fold {
+    /// 折序号
+    pub index: usize,
+    /// 训练集
+    pub train: Vec<TrainingDataPoint>,
+    /// 验证集
+    pub validation: Vec<TrainingDataPoint>,
+    /// 验证集样本在原数据中的下标
+    pub validation_indices: Vec<usize>,
+}
+
+impl Iterator for KFold<'_> {
+    type Item = Fold;
+
+    fn next(&mut self) -> Option<Fold> {
+        if self.next == self.k {
+            return None;
+        }
+        let index = self.next;
+        self.next += 1;
+
+        // 前 len % k 折各多分一个样本
+        let (len, k) = (self.indices.len(), self.k);
+        let bound = |fold: usize| fold * (len / k) + fold.min(len % k);
+        let (start, end) = (bound(index), bound(index + 1));
+        let pick = |indices: &[usize]| indices.iter().map(|&i| self.data[i].clone()).collect::<Vec<_>>();
+        let train_indices: Vec<usize> = self.indices[..start].iter().chain(&self.indices[end..]).copied().collect();
+        Some(Fold {
+            index,
+            train: pick(&train_indices),
+            validation: pick(&self.indices[start..end]),
+            validation_indices: self.indices[start..end].to_vec(),
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.k - self.next;
+        (remaining, Some(remaining))
+    }
+}
+
+impl ExactSizeIterator for KFold<'_> {}
+
+/// 交叉验证结果
+/// Cross Validation Report
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrossValidationReport {
+    /// 模型名称
+    pub model_name: String,
+    /// 每一折在验证集上的指标
+    pub folds: Vec<ModelMetrics>,
+    /// 各折指标的平均值
+    pub mean: ModelMetrics,
+    /// 各折指标的总体标准差
+    pub std_dev: ModelMetrics,
+}
+
+impl CrossValidationReport {
+    /// 由各折指标汇总
+    pub fn from_folds(model_name: String, folds: Vec<ModelMetrics>) -> Self {
+        let count = folds.len().max(1) as f64;
+        let aggregate = |field: fn(&ModelMetrics) -> f64| {
+            let mean = folds.iter().map(field).sum::<f64>() / count;
+            let variance = folds.iter().map(|metrics| (field(metrics) - mean).powi(2)).sum::<f64>() / count;
+            (mean, variance.sqrt())
+        };
+        let (accuracy, precision, recall, f1_score, loss) = (
+            aggregate(|m| m.accuracy),
+            aggregate(|m| m.precision),
+            aggregate(|m| m.recall),
+            aggregate(|m| m.f1_score),
+            aggregate(|m| m.loss),
+        );
+
+        Self {
+            model_name,
+            folds,
+            mean: ModelMetrics {
+                accuracy: accuracy.0,
+                precision: precision.0,
+                recall: recall.0,
+                f1_score: f1_score.0,
+                loss: loss.0,
+            },
+            std_dev: ModelMetrics {
+                accuracy: accuracy.1,
+                precision: precision.1,
+                recall: recall.1,
+                f1_score: f1_score.1,
+                loss: loss.1,
+            },
+        }
+    }
+}
+
+/// 模型文件的格式版本
+/// Model file format version
+pub const MODEL_FORMAT_VERSION: u32 = 1;
+
+/// 模型文件内容：格式版本、模型类型和名称，以及各模型自己的状态
+#[derive(Serialize, Deserialize)]
+struct SavedModel<T> {
+    format_version: u32,
+    kind: String,
+    name: String,
+    state: T,
+}
+
+/// 临时文件序号，同一进程内并发写入同一模型文件时各用各的临时文件
+static TEMPORARY_FILE_SEQUENCE: AtomicU64 = AtomicU64::new(0);
+
+/// 以 JSON 写入模型文件，先写同目录下唯一的临时文件再替换，避免留下半个文件
+fn write_model_file<T: Serialize>(path: &Path, kind: &str, name: &str, state: T) -> Result<(), AiError> {
+    let saved = SavedModel { format_version: MODEL_FORMAT_VERSION, kind: kind.to_string(), name: name.to_string(), state };
+    let json = serde_json::to_vec_pretty(&saved).map_err(|e| AiError::PersistenceError(e.to_string()))?;
+    let file_name = path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+    let temporary = path.with_file_name(format!(
+        ".{}.{}-{}.tmp",
+        file_name,
+        std::process::id(),
+        TEMPORARY_FILE_SEQUENCE.fetch_add(1, Ordering::Relaxed)
+    ));
+    std::fs::write(&temporary, json)
+        .and_then(|_| std::fs::rename(&temporary, path))
+        .map_err(|e| {
+            let _ = std::fs::remove_file(&temporary);
+            AiError::PersistenceError(format!("{}: {}", path.display(), e))
+        })
+}
+
+/// 读取模型文件并检查格式版本、模型类型和名称
+fn read_model_file<T: DeserializeOwned>(path: &Path, kind: &str, name: &str) -> Result<T, AiError> {
+    let bytes = std::fs::read(path).map_err(|e| AiError::PersistenceError(format!("{}: {}", path.display(), e)))?;
+    let saved: SavedModel<serde_json::Value> = serde_json::from_slice(&bytes)
+        .map_err(|e| AiError::PersistenceError(format!("{}: 文件已损坏: {}", path.display(), e)))?;
+    if saved.format_version != MODEL_FORMAT_VERSION {
+        return Err(AiError::PersistenceError(format!(
+            "{}: 不支持的格式版本 {}（当前为 {}）", path.display(), saved.format_version, MODEL_FORMAT_VERSION
+        )));
+    }
+    if saved.kind != kind || saved.name != name {
+        return Err(AiError::PersistenceError(format!(
+            "{}: 文件属于 {} 模型 {}，而不是 {} 模型 {}", path.display(), saved.kind, saved.name, kind, name
+        )));
+    }
+    serde_json::from_value(saved.state).map_err(|e| AiError::PersistenceError(format!("{}: 文件已损坏: {}", path.display(), e)))
+}
+
+/// 模型对应的缩放器文件路径
+fn scaler_path(model_path: &Path) -> PathBuf {
+    model_path.with_extension("scaler.json")
+}
+
+/// 模型对应的训练状态文件路径
+fn state_path(model_path: &Path) -> PathBuf {
+    model_path.with_extension("state.json")
+}
+
+/// 特征缩放方式
+/// Feature Scaling Mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ScalingMode {
+    /// 标准化为零均值、单位方差（z-score）
+    Standardize,
+    /// 线性缩放到 [0, 1]
+    MinMax,
+}
+
+/// 特征缩放器，按 `(x - offset) / scale` 变换每个特征
+/// Feature Scaler transforming each feature as `(x - offset) / scale`
+///
+/// 方差（或取值范围）为 0 的特征记为 `offset = 0, scale = 1`，变换后保持原值而不是产生 NaN。
+/// Features with zero variance (or range) get `offset = 0, scale = 1` and pass through unchanged instead of
+/// producing NaN.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FeatureScaler {
+    /// 缩放方式
+    pub mode: ScalingMode,
+    /// 每个特征减去的偏移量
+    pub offsets: Vec<f64>,
+    /// 每个特征除以的缩放量
+    pub scales: Vec<f64>,
+}
+
+impl FeatureScaler {
+    /// 缩放器文件中的类型标识
+    const KIND: &'static str = "feature_scaler";
+
+    /// 在训练数据上拟合缩放器
+    pub fn fit(mode: ScalingMode, data: &[TrainingDataPoint]) -> Result<Self, AiError> {
+        let first = data.first().ok_or_else(|| AiError::DataError("拟合缩放器的数据为空".to_string()))?;
+        let feature_count = first.input.features.len();
+        if let Some(index) = data.iter().position(|point| point.input.features.len() != feature_count) {
+            return Err(AiError::DataError(format!("第 {} 个样本需要 {} 个特征", index, feature_count)));
+        }
+
+        let column = |feature: usize| data.iter().map(move |point| point.input.features[feature]);
+        let (offsets, scales) = (0..feature_count)
+            .map(|feature| {
+                let (offset, scale) = match mode {
+                    ScalingMode::Standardize => {
+                        let mean = column(feature).sum::<f64>() / data.len() as f64;
+                        let variance = column(feature).map(|x| (x - mean).powi(2)).sum::<f64>() / data.len() as f64;
+                        (mean, variance.sqrt())
+                    }
+                    ScalingMode::MinMax => {
+                        let min = column(feature).fold(f64::INFINITY, f64::min);
+                        let max = column(feature).fold(f64::NEG_INFINITY, f64::max);
+                        (min, max - min)
+                    }
+                };
+                if scale > f64::EPSILON * offset.abs().max(1.0) && scale.is_finite() { (offset, scale) } else { (0.0, 1.0) }
+            })
+            .unzip();
+        Ok(Self { mode, offsets, scales })
+    }
+
+    /// 变换特征向量
+    pub fn transform(&self, features: &[f64]) -> Result<Vec<f64>, AiError> {
+        self.check_len(features)?;
+        Ok(features.iter().zip(self.offsets.iter().zip(&self.scales)).map(|(x, (offset, scale))| (x - offset) / scale).collect())
+    }
+
+    /// 逆变换，还原原始尺度的特征
+    pub fn inverse_transform(&self, features: &[f64]) -> Result<Vec<f64>, AiError> {
+        self.check_len(features)?;
+        Ok(features.iter().zip(self.offsets.iter().zip(&self.scales)).map(|(x, (offset, scale))| x * scale + offset).collect())
+    }
+
+    /// 变换模型输入，元数据保持不变
+    pub fn transform_input(&self, input: &ModelInput) -> Result<ModelInput, AiError> {
+        Ok(ModelInput { features: self.transform(&input.features)?, metadata: input.metadata.clone() })
+    }
+
+    /// 变换一组训练数据的输入特征
+    pub fn transform_points(&self, data: &[TrainingDataPoint]) -> Result<Vec<TrainingDataPoint>, AiError> {
+        data.iter()
+            .map(|point| Ok(TrainingDataPoint { input: self.transform_input(&point.input)?, ..point.clone() }))
+            .collect()
+    }
+
+    fn check_len(&self, features: &[f64]) -> Result<(), AiError> {
+        if features.len() != self.offsets.len() {
+            return Err(AiError::DataError(format!("缩放器需要 {} 个特征，实际为 {}", self.offsets.len(), features.len())));
+        }
+        Ok(())
+    }
+}
+
+/// 批量加载模型的结果
+/// Result of loading the engine's models
+#[derive(Debug, Clone, Default)]
+pub struct ModelLoadReport {
+    /// 成功加载的模型
+    pub loaded: Vec<String>,
+    /// 没有找到模型文件的模型
+    pub missing: Vec<String>,
+    /// 被跳过的损坏或不匹配的文件，每条说明一个文件
+    pub warnings: Vec<String>,
+}
+
+/// 批量保存模型的结果
+/// Result of saving the engine's models
+#[derive(Debug, Clone, Default)]
+pub struct ModelSaveReport {
+    /// 已保存的模型文件
+    pub saved: Vec<PathBuf>,
+    /// 不支持保存而被跳过的模型
+    pub unsupported: Vec<String>,
+}
+
+/// 模型的训练状态
+/// Model Training State
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ModelState {
+    /// 尚未训练
+    Untrained,
+    /// 正在训练
+    Training,
+    /// 已训练
+    Trained {
+        /// 训练完成的时间
+        at: DateTime<Utc>,
+        /// 训练集的数据点个数
+        data_points: usize,
+        /// 训练结束时在验证集（没有验证集时为训练集）上的指标
+        metrics: ModelMetrics,
+    },
+}
+
+impl ModelState {
+    /// 模型文件旁保存状态时使用的类型标识
+    const KIND: &'static str = "model_state";
+
+    /// 是否已训练完成
+    pub fn is_trained(&self) -> bool {
+        matches!(self, ModelState::Trained { .. })
+    }
+
+    /// 状态说明，用于日志
+    fn describe(&self) -> &'static str {
+        match self {
+            ModelState::Untrained => "尚未训练",
+            ModelState::Training => "正在训练",
+            ModelState::Trained { .. } => "已训练",
+        }
+    }
+}
+
+/// 一组超参数
+/// Hyperparameters
+///
+/// 学习率和批次大小用于按轮训练，树模型的参数为 `None` 时保持模型当前的设置。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Hyperparameters {
+    /// 学习率
+    pub learning_rate: f64,
+    /// 训练批次大小
+    pub batch_size: usize,
+    /// 树的最大深度
+    pub max_depth: Option<usize>,
+    /// 叶子节点的最小样本数
+    pub min_samples_leaf: Option<usize>,
+}
+
+impl Hyperparameters {
+    /// 对应的单轮训练参数
+    pub fn epoch_options(&self) -> EpochOptions {
+        EpochOptions { learning_rate: self.learning_rate, batch_size: self.batch_size }
+    }
+}
+
+/// 超参数网格
+/// Parameter Grid
+///
+/// 每个维度为空时使用引擎配置（学习率、批次大小）或模型当前的设置（树参数）。
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ParamGrid {
+    /// 候选学习率
+    pub learning_rates: Vec<f64>,
+    /// 候选批次大小
+    pub batch_sizes: Vec<usize>,
+    /// 候选最大深度
+    pub max_depths: Vec<usize>,
+    /// 候选叶子最小样本数
+    pub min_samples_leaf: Vec<usize>,
+}
+
+impl ParamGrid {
+    /// 设置候选学习率
+    pub fn with_learning_rates(mut self, learning_rates: Vec<f64>) -> Self {
+        self.learning_rates = learning_rates;
+        self
+    }
+
+    /// 设置候选批次大小
+    pub fn with_batch_sizes(mut self, batch_sizes: Vec<usize>) -> Self {
+        self.batch_sizes = batch_sizes;
+        self
+    }
+
+    /// 设置候选最大深度
+    pub fn with_max_depths(mut self, max_depths: Vec<usize>) -> Self {
+        self.max_depths = max_depths;
+        self
+    }
+
+    /// 设置候选叶子最小样本数
+    pub fn with_min_samples_leaf(mut self, min_samples_leaf: Vec<usize>) -> Self {
+        self.min_samples_leaf = min_samples_leaf;
+        self
+    }
+
+    /// 网格中的全部组合，空维度取 `config` 中的值或保持模型设置
+    pub fn combinations(&self, config: &AiOptimizationConfig) -> Vec<Hyperparameters> {
+        fn or_default<T: Copy>(values: &[T], default: T) -> Vec<T> {
+            if values.is_empty() { vec![default] } else { values.to_vec() }
+        }
+        let depths: Vec<Option<usize>> = or_default(&self.max_depths.iter().copied().map(Some).collect::<Vec<_>>(), None);
+        let leaves: Vec<Option<usize>> = or_default(&self.min_samples_leaf.iter().copied().map(Some).collect::<Vec<_>>(), None);
+
+        let mut combinations = Vec::new();
+        for learning_rate in or_default(&self.learning_rates, config.learning_rate) {
+            for batch_size in or_default(&self.batch_sizes, config.batch_size) {
+                for max_depth in &depths {
+                    for min_samples_leaf in &leaves {
+                        combinations.push(Hyperparameters {
+                            learning_rate,
+                            batch_size,
+                            max_depth: *max_depth,
+                            min_samples_leaf: *min_samples_leaf,
+                        });
+                    }
+                }
+            }
+        }
+        combinations
+    }
+}
+
+/// 搜索的取消标志，可以在其他线程中取消
+/// Search Cancellation
+#[derive(Debug, Clone, Default)]
+pub struct SearchCancellation {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl SearchCancellation {
+    /// 创建未取消的标志
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 取消搜索，正在训练的一折完成后停止，未完成全部折的组合不计入结果
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    /// 是否已取消
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+}
+
+/// 网格搜索选项
+/// Grid Search Options
+#[derive(Debug, Clone, Default)]
+pub struct GridSearchOptions {
+    /// 是否把最佳组合应用到引擎配置和已注册的模型
+    pub apply_best: bool,
+    /// 取消标志
+    pub cancellation: Option<SearchCancellation>,
+}
+
+/// 单个组合的搜索结果
+/// Grid Search Result
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GridSearchResult {
+    /// 超参数
+    pub params: Hyperparameters,
+    /// 交叉验证结果
+    pub cross_validation: CrossValidationReport,
+}
+
+/// 网格搜索报告
+/// Grid Search Report
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GridSearchReport {
+    /// 模型名称
+    pub model_name: String,
+    /// 已评估的组合，按平均验证损失从低到高排列
+    pub results: Vec<GridSearchResult>,
+    /// 网格中的组合总数
+    pub total_combinations: usize,
+    /// 是否被取消，取消时 `results` 只包含已完成的组合
+    pub cancelled: bool,
+    /// 最佳组合是否已应用
+    pub applied: bool,
+}
+
+impl GridSearchReport {
+    /// 平均验证损失最低的组合
+    pub fn best(&self) -> Option<&GridSearchResult> {
+        self.results.first()
+    }
 }
 
 /// 模型输入
@@ -55,6 +651,80 @@ pub struct ModelOutput {
     pub confidence: f64,
     /// 解释
     pub explanation: Option<String>,
+    /// 各特征对本次预测的贡献，按绝对值从大到小排列
+    #[serde(default)]
+    pub contributions: Vec<FeatureContribution>,
+}
+
+impl ModelOutput {
+    /// 由模型的概述和特征贡献构造输出，解释中列出贡献最大的 [`EXPLANATION_TOP_K`] 个特征
+    fn explained(predictions: Vec<f64>, confidence: f64, summary: String, mut contributions: Vec<FeatureContribution>) -> Self {
+        contributions.sort_by(|a, b| b.value.abs().total_cmp(&a.value.abs()));
+        let explanation = Some(format!("{}{}{}", summary, CONTRIBUTORS_SEPARATOR, describe_contributions(&contributions)));
+        Self { predictions, confidence, explanation, contributions }
+    }
+
+    /// 贡献最大的特征
+    pub fn top_contributor(&self) -> Option<&FeatureContribution> {
+        self.contributions.first().filter(|contribution| contribution.value != 0.0)
+    }
+
+    /// 用特征名替换解释中的特征下标
+    pub fn name_features(&mut self, names: &[String]) {
+        for contribution in &mut self.contributions {
+            contribution.feature_name = names.get(contribution.feature_index).cloned();
+        }
+        if let Some(explanation) = &mut self.explanation
+            && let Some(position) = explanation.rfind(CONTRIBUTORS_SEPARATOR)
+        {
+            explanation.truncate(position + CONTRIBUTORS_SEPARATOR.len());
+            explanation.push_str(&describe_contributions(&self.contributions));
+        }
+    }
+}
+
+/// 解释中列出的贡献最大的特征个数
+pub const EXPLANATION_TOP_K: usize = 3;
+
+/// 解释中模型概述与特征贡献之间的分隔
+const CONTRIBUTORS_SEPARATOR: &str = "; 主要因素: ";
+
+/// 列出贡献最大的几个特征，`contributions` 已按绝对值排序
+fn describe_contributions(contributions: &[FeatureContribution]) -> String {
+    let top: Vec<String> = contributions.iter()
+        .filter(|contribution| contribution.value != 0.0)
+        .take(EXPLANATION_TOP_K)
+        .map(|contribution| format!("{} {:+.4}", contribution.label(), contribution.value))
+        .collect();
+    if top.is_empty() { "无".to_string() } else { top.join(", ") }
+}
+
+/// 特征贡献
+/// Feature Contribution
+///
+/// 线性模型为系数 × 特征值，树模型为累积的不纯度下降占比，神经网络为输出对该输入的梯度。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FeatureContribution {
+    /// 特征下标
+    pub feature_index: usize,
+    /// 特征名，由引擎按特征提取器的指标名填入
+    pub feature_name: Option<String>,
+    /// 贡献值
+    pub value: f64,
+}
+
+impl FeatureContribution {
+    /// 特征名，没有名字时为 `x<下标>`
+    pub fn label(&self) -> String {
+        self.feature_name.clone().unwrap_or_else(|| format!("x{}", self.feature_index))
+    }
+}
+
+/// 把每个特征的数值包装为贡献
+fn contributions_of(values: impl IntoIterator<Item = f64>) -> Vec<FeatureContribution> {
+    values.into_iter().enumerate()
+        .map(|(feature_index, value)| FeatureContribution { feature_index, feature_name: None, value })
+        .collect()
 }
 
 /// 训练数据点
@@ -98,6 +768,28 @@ pub trait AiOptimizationStrategy: Send + Sync {
     fn get_priority(&self) -> OptimizationPriority;
     /// 是否需要训练
     fn requires_training(&self) -> bool;
+    /// 是否适用于给定的上下文，不适用的策略在引擎优化时被跳过
+    fn is_applicable(&self, context: &OptimizationContext) -> bool {
+        let _ = context;
+        true
+    }
+    /// 需要模型打分的候选配置（指标名到值），引擎用同名模型批量预测后调用 [`Self::apply_scores`]
+    fn candidate_configurations(&self, context: &OptimizationContext) -> Vec<HashMap<String, f64>> {
+        let _ = context;
+        Vec::new()
+    }
+    /// 根据候选配置的预测结果调整优化结果，`scores` 与 `candidates` 一一对应
+    fn apply_scores(&self, result: &mut OptimizationResult, candidates: &[HashMap<String, f64>], scores: &[ModelOutput]) {
+        let _ = (result, candidates, scores);
+    }
+    /// 用模型对当前指标的解释补充结果，默认在每条建议后注明贡献最大的特征
+    fn annotate(&self, result: &mut OptimizationResult, explanation: &ModelOutput) {
+        if let Some(top) = explanation.top_contributor() {
+            for recommendation in &mut result.recommendations {
+                recommendation.description.push_str(&format!("（主要因素: {}）", top.label()));
+            }
+        }
+    }
 }
 
 /// 优化上下文
@@ -456,6 +1148,12 @@ pub struct AiOptimizationConfig {
     pub model_save_path: String,
     /// 数据保留时间
     pub data_retention_period: Duration,
+    /// 切分训练集和验证集时使用的随机种子
+    pub split_seed: u64,
+    /// 训练时留作验证集的比例
+    pub validation_split: f64,
+    /// 特征缩放方式，`None` 表示直接使用原始特征
+    pub feature_scaling: Option<ScalingMode>,
 }
 
 impl AiOptimizationEngine {
@@ -465,12 +1163,16 @@ impl AiOptimizationEngine {
             models: HashMap::new(),
             strategies: Vec::new(),
             training_data: Arc::new(Mutex::new(Vec::new())),
+            scalers: HashMap::new(),
+            model_states: Arc::new(Mutex::new(HashMap::new())),
+            feature_names: Vec::new(),
             config,
         }
     }
 
     /// 添加机器学习模型
     pub fn add_model(&mut self, name: String, model: Box<dyn MachineLearningModel>) {
+        self.model_states.lock().unwrap().insert(name.clone(), ModelState::Untrained);
         self.models.insert(name, model);
     }
 
@@ -480,15 +1182,136 @@ impl AiOptimizationEngine {
     }
 
     /// 训练模型
-    pub fn train_models(&mut self) -> Result<(), AiError> {
-        let training_data = self.training_data.lock().unwrap();
-        
-        for (name, model) in &mut self.models {
-            println!("训练模型: {}", name);
-            model.train(&training_data)?;
+    ///
+    /// 按 `validation_split` 切出验证集后逐个调用 [`Self::train_model`]。
+    pub fn train_models(&mut self) -> Result<HashMap<String, TrainingReport>, AiError> {
+        if !(0.0..1.0).contains(&self.config.validation_split) {
+            return Err(AiError::ConfigurationError(format!(
+                "验证集比例必须在 [0, 1) 内，实际为 {}", self.config.validation_split
+            )));
         }
-        
-        Ok(())
+        let training_data = self.training_data.lock().unwrap().clone();
+        let (train, validation) = split(&training_data, 1.0 - self.config.validation_split, self.config.split_seed)?;
+
+        let names: Vec<String> = self.models.keys().cloned().collect();
+        let mut reports = HashMap::new();
+        for name in names {
+            log::info!("训练模型: {}", name);
+            let report = self.train_model(&name, &train, &validation)?;
+            reports.insert(name, report);
+        }
+        Ok(reports)
+    }
+
+    /// 在给定的训练集上训练模型，用验证集早停
+    ///
+    /// 配置了特征缩放时先在训练集上拟合缩放器，缩放器随模型保存。支持按轮训练的模型最多训练
+    /// `max_epochs` 轮，验证损失连续 `early_stopping_patience` 轮没有改善时停止（0 表示不早停），
+    /// 结束后恢复为验证损失最低那一轮的参数；没有验证集时以训练损失为准。其他模型调用一次 `train`。
+    ///
+    /// 训练期间模型状态为 [`ModelState::Training`]，成功后为 [`ModelState::Trained`]，失败时恢复原状态。
+    pub fn train_model(
+        &mut self,
+        model_name: &str,
+        train: &[TrainingDataPoint],
+        validation: &[TrainingDataPoint],
+    ) -> Result<TrainingReport, AiError> {
+        if !self.models.contains_key(model_name) {
+            return Err(AiError::ModelNotFound(model_name.to_string()));
+        }
+        let previous = self.model_states.lock().unwrap()
+            .insert(model_name.to_string(), ModelState::Training)
+            .unwrap_or(ModelState::Untrained);
+        let (state, result) = match self.fit_registered_model(model_name, train, validation) {
+            Ok((report, metrics)) => {
+                (ModelState::Trained { at: Utc::now(), data_points: train.len(), metrics }, Ok(report))
+            }
+            Err(e) => (previous, Err(e)),
+        };
+        self.model_states.lock().unwrap().insert(model_name.to_string(), state);
+        result
+    }
+
+    /// 训练已注册的模型，返回训练报告和训练结束时的指标
+    fn fit_registered_model(
+        &mut self,
+        model_name: &str,
+        train: &[TrainingDataPoint],
+        validation: &[TrainingDataPoint],
+    ) -> Result<(TrainingReport, ModelMetrics), AiError> {
+        let model = self.models.get_mut(model_name).ok_or_else(|| AiError::ModelNotFound(model_name.to_string()))?;
+        let scaler = self.config.feature_scaling.map(|mode| FeatureScaler::fit(mode, train)).transpose()?;
+        let (train, validation) = match &scaler {
+            Some(scaler) => (scaler.transform_points(train)?, scaler.transform_points(validation)?),
+            None => (train.to_vec(), validation.to_vec()),
+        };
+        let options = EpochOptions { learning_rate: self.config.learning_rate, batch_size: self.config.batch_size };
+        let report = fit_model(model, &train, &validation, &options, self.config.max_epochs, self.config.early_stopping_patience)?;
+        let metrics = model.evaluate(if validation.is_empty() { &train } else { &validation })?;
+
+        match scaler {
+            Some(scaler) => self.scalers.insert(model_name.to_string(), scaler),
+            None => self.scalers.remove(model_name),
+        };
+        Ok((report, metrics))
+    }
+
+    /// 用模型预测，模型训练时拟合了缩放器的会先变换输入；解释中的特征用 `feature_names` 命名
+    pub fn predict(&self, model_name: &str, input: &ModelInput) -> Result<ModelOutput, AiError> {
+        let model = self.models.get(model_name).ok_or_else(|| AiError::ModelNotFound(model_name.to_string()))?;
+        let mut output = match self.scalers.get(model_name) {
+            Some(scaler) => model.predict(&scaler.transform_input(input)?)?,
+            None => model.predict(input)?,
+        };
+        if !self.feature_names.is_empty() {
+            output.name_features(&self.feature_names);
+        }
+        Ok(output)
+    }
+
+    /// 批量预测，结果按输入顺序排列，与逐个调用 [`Self::predict`] 的结果相同
+    pub fn predict_batch(&self, model_name: &str, inputs: &[ModelInput]) -> Result<Vec<ModelOutput>, AiError> {
+        let model = self.models.get(model_name).ok_or_else(|| AiError::ModelNotFound(model_name.to_string()))?;
+        let mut outputs = match self.scalers.get(model_name) {
+            Some(scaler) => {
+                let scaled = inputs.iter().map(|input| scaler.transform_input(input)).collect::<Result<Vec<_>, _>>()?;
+                model.predict_batch(&scaled)?
+            }
+            None => model.predict_batch(inputs)?,
+        };
+        if !self.feature_names.is_empty() {
+            for output in &mut outputs {
+                output.name_features(&self.feature_names);
+            }
+        }
+        Ok(outputs)
+    }
+
+    /// 按 `feature_names` 把候选配置转换为特征并批量预测
+    fn score_candidates(&self, model_name: &str, candidates: &[HashMap<String, f64>]) -> Result<Vec<ModelOutput>, AiError> {
+        if self.feature_names.is_empty() {
+            return Err(AiError::ConfigurationError("没有设置特征名，无法把候选配置转换为特征".to_string()));
+        }
+        let inputs = candidates.iter()
+            .map(|candidate| {
+                let features = self.feature_names.iter()
+                    .map(|name| candidate.get(name).copied().ok_or_else(|| AiError::DataError(format!("候选配置缺少特征 {}", name))))
+                    .collect::<Result<Vec<f64>, AiError>>()?;
+                Ok(ModelInput { features, metadata: HashMap::new() })
+            })
+            .collect::<Result<Vec<_>, AiError>>()?;
+        self.predict_batch(model_name, &inputs)
+    }
+
+    /// 用上下文中的当前指标作为特征解释模型的预测，缺少任一特征指标时返回 `None`
+    fn explain_context(&self, model_name: &str, context: &OptimizationContext) -> Option<ModelOutput> {
+        if self.feature_names.is_empty() {
+            return None;
+        }
+        let features = self.feature_names.iter()
+            .map(|name| context.current_metrics.get(name).copied())
+            .collect::<Option<Vec<f64>>>()?;
+        self.predict(model_name, &ModelInput { features, metadata: HashMap::new() }).ok()
     }
 
     /// 执行智能优化
@@ -496,25 +1319,53 @@ impl AiOptimizationEngine {
         let mut results = Vec::new();
         
         for strategy in &self.strategies {
+            if !strategy.is_applicable(context) {
+                continue;
+            }
             if strategy.requires_training() {
-                // 检查模型是否已训练
-                if !self.is_model_trained(&strategy.get_name()) {
-                    continue;
+                // 策略依赖同名模型，模型训练完成前跳过
+                let name = strategy.get_name();
+                let state = self.model_states.lock().unwrap().get(&name).cloned();
+                match state {
+                    Some(state) if state.is_trained() => {}
+                    Some(state) => {
+                        log::info!("跳过优化策略 {}: 模型{}", name, state.describe());
+                        continue;
+                    }
+                    None => {
+                        log::warn!("跳过优化策略 {}: 没有注册同名模型", name);
+                        continue;
+                    }
                 }
             }
             
             match strategy.optimize(context) {
-                Ok(result) => results.push(result),
+                Ok(mut result) => {
+                    if strategy.requires_training() {
+                        let name = strategy.get_name();
+                        let candidates = strategy.candidate_configurations(context);
+                        if !candidates.is_empty() {
+                            match self.score_candidates(&name, &candidates) {
+                                Ok(scores) => strategy.apply_scores(&mut result, &candidates, &scores),
+                                Err(e) => log::warn!("优化策略 {} 的候选配置打分失败: {:?}", name, e),
+                            }
+                        }
+                        if let Some(explanation) = self.explain_context(&name, context) {
+                            strategy.annotate(&mut result, &explanation);
+                        }
+                    }
+                    results.push(result);
+                }
                 Err(e) => {
-                    eprintln!("优化策略 {} 执行失败: {:?}", strategy.get_name(), e);
+                    log::warn!("优化策略 {} 执行失败: {:?}", strategy.get_name(), e);
                 }
             }
         }
         
         // 按优先级和置信度排序
         results.sort_by(|a, b| {
-            b.confidence.partial_cmp(&a.confidence).unwrap()
-                .then_with(|| b.expected_improvement.partial_cmp(&a.expected_improvement).unwrap())
+            b.confidence.total_cmp(&a.confidence)
+                .then_with(|| b.expected_improvement.total_cmp(&a.expected_improvement))
         });
         
         Ok(results)
@@ -522,10 +1373,9 @@ impl AiOptimizationEngine {
 
     /// 添加训练数据
     pub fn add_training_data(&self, data_point: TrainingDataPoint) {
-        let mut training_data = self.training_data.lock().unwrap();
-        training_data.push(data_point);
-        
-        // 清理过期数据
+        self.training_data.lock().unwrap().push(data_point);
+
+        // 清理过期数据（先释放上面的锁，清理时会重新加锁）
         self.cleanup_old_data();
     }
 
@@ -536,40 +1386,306 @@ impl AiOptimizationEngine {
         training_data.retain(|data| data.timestamp > cutoff_time);
     }
 
-    /// 检查模型是否已训练
-    fn is_model_trained(&self, model_name: &str) -> bool {
-        self.models.contains_key(model_name)
+    /// 所有模型的训练状态，供状态接口使用
+    pub fn model_states(&self) -> HashMap<String, ModelState> {
+        self.model_states.lock().unwrap().clone()
+    }
+
+    /// 把所有模型保存到 `model_save_path`，每个模型一个文件，缩放器和训练状态保存在旁边的文件中
+    ///
+    /// 不支持保存的模型被跳过并记入报告，其他错误立即返回。
+    pub fn save_all_models(&self) -> Result<ModelSaveReport, AiError> {
+        let directory = Path::new(&self.config.model_save_path);
+        std::fs::create_dir_all(directory)
+            .map_err(|e| AiError::PersistenceError(format!("{}: {}", directory.display(), e)))?;
+
+        let mut report = ModelSaveReport::default();
+        for (name, model) in &self.models {
+            let path = self.model_path(name);
+            match model.save(&path) {
+                Ok(()) => {}
+                Err(AiError::NotSupported(reason)) => {
+                    log::warn!("跳过保存模型 {}: {}", name, reason);
+                    report.unsupported.push(name.clone());
+                    continue;
+                }
+                Err(e) => return Err(e),
+            }
+            let scaler_path = scaler_path(&path);
+            match self.scalers.get(name) {
+                Some(scaler) => write_model_file(&scaler_path, FeatureScaler::KIND, &model.get_name(), scaler)?,
+                // 删除上次保存留下的缩放器，避免加载时误用
+                None if scaler_path.exists() => std::fs::remove_file(&scaler_path)
+                    .map_err(|e| AiError::PersistenceError(format!("{}: {}", scaler_path.display(), e)))?,
+                None => {}
+            }
+            let state = self.model_states.lock().unwrap().get(name).cloned().unwrap_or(ModelState::Untrained);
+            write_model_file(&state_path(&path), ModelState::KIND, &model.get_name(), state)?;
+            report.saved.push(path);
+        }
+        report.saved.sort();
+        report.unsupported.sort();
+        Ok(report)
+    }
+
+    /// 从 `model_save_path` 加载已注册的模型，损坏或结构不匹配的文件被跳过并记入警告
+    ///
+    /// 训练状态随模型一起恢复；没有状态文件的模型按未训练处理，依赖它的策略在重新训练前不会运行。
+    pub fn load_models(&mut self) -> ModelLoadReport {
+        let mut report = ModelLoadReport::default();
+        let paths: Vec<(String, PathBuf)> = self.models.keys().map(|name| (name.clone(), self.model_path(name))).collect();
+        for (name, path) in paths {
+            if !path.exists() {
+                report.missing.push(name);
+                continue;
+            }
+            let model = self.models.get_mut(&name).expect("model is registered");
+            let scaler_path = scaler_path(&path);
+            let scaler = match scaler_path.exists() {
+                true => match read_model_file::<FeatureScaler>(&scaler_path, FeatureScaler::KIND, &model.get_name()) {
+                    Ok(scaler) => Some(scaler),
+                    Err(e) => {
+                        report.warnings.push(format!("跳过模型 {}: {}", name, e));
+                        continue;
+                    }
+                },
+                false => None,
+            };
+            let state_path = state_path(&path);
+            let state = match state_path.exists() {
+                true => match read_model_file::<ModelState>(&state_path, ModelState::KIND, &model.get_name()) {
+                    Ok(state) => state,
+                    Err(e) => {
+                        report.warnings.push(format!("跳过模型 {}: {}", name, e));
+                        continue;
+                    }
+                },
+                false => ModelState::Untrained,
+            };
+            match model.load(&path) {
+                Ok(()) => {
+                    match scaler {
+                        Some(scaler) => self.scalers.insert(name.clone(), scaler),
+                        None => self.scalers.remove(&name),
+                    };
+                    self.model_states.lock().unwrap().insert(name.clone(), state);
+                    report.loaded.push(name);
+                }
+                Err(e) => report.warnings.push(format!("跳过模型 {}: {}", name, e)),
+            }
+        }
+        report.loaded.sort();
+        report.missing.sort();
+        report
+    }
+
+    /// 模型文件路径，名称中的路径分隔符等字符被替换
+    ///
+    /// 替换过字符的名称追加原名称的哈希，`"a/b"` 和 `"a_b"` 不会写到同一个文件。
+    fn model_path(&self, model_name: &str) -> PathBuf {
+        let mut file_name: String = model_name.chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+            .collect();
+        if file_name != model_name {
+            let digest = Sha256::digest(model_name.as_bytes());
+            file_name.push('-');
+            file_name.extend(digest[..8].iter().map(|byte| format!("{:02x}", byte)));
+        }
+        Path::new(&self.config.model_save_path).join(format!("{}.json", file_name))
+    }
+
+    /// 用当前训练数据对模型做 k 折交叉验证，每折在模型副本上训练，不影响已注册的模型
+    ///
+    /// 每折与 [`train_models`](Self::train_models) 一样按轮训练：最多 `max_epochs` 轮，按训练损失早停，
+    /// 使用引擎配置的学习率和批次大小；不支持按轮训练的模型调用一次 `train`。
+    pub fn cross_validate(&self, model_name: &str, k: usize) -> Result<CrossValidationReport, AiError> {
+        let model = self.models.get(model_name).ok_or_else(|| AiError::ModelNotFound(model_name.to_string()))?;
+        let training_data = self.training_data.lock().unwrap().clone();
+        let options = EpochOptions { learning_rate: self.config.learning_rate, batch_size: self.config.batch_size };
+        let report = self.cross_validate_model(model_name, model.as_ref(), &training_data, k, &options, None)?;
+        Ok(report.expect("cross-validation without cancellation always completes"))
+    }
+
+    /// 在网格中的每个超参数组合下交叉验证模型，结果按平均验证损失排序
+    ///
+    /// 每个组合在模型副本上评估。取消后返回已完成的组合；`apply_best` 为真且搜索完整结束时，
+    /// 把最佳组合的学习率和批次大小写入引擎配置，并把树参数应用到已注册的模型。
+    pub fn grid_search(
+        &mut self,
+        model_name: &str,
+        param_grid: &ParamGrid,
+        k_folds: usize,
+        options: &GridSearchOptions,
+    ) -> Result<GridSearchReport, AiError> {
+        let model = self.models.get(model_name).ok_or_else(|| AiError::ModelNotFound(model_name.to_string()))?;
+        let training_data = self.training_data.lock().unwrap().clone();
+        let combinations = param_grid.combinations(&self.config);
+
+        let mut results = Vec::with_capacity(combinations.len());
+        let mut cancelled = false;
+        for params in &combinations {
+            if options.cancellation.as_ref().is_some_and(SearchCancellation::is_cancelled) {
+                cancelled = true;
+                break;
+            }
+            let mut candidate = model.clone_model()?;
+            candidate.apply_hyperparameters(params)?;
+            let cross_validation = self.cross_validate_model(
+                model_name, candidate.as_ref(), &training_data, k_folds, &params.epoch_options(), options.cancellation.as_ref(),
+            )?;
+            match cross_validation {
+                Some(cross_validation) => results.push(GridSearchResult { params: params.clone(), cross_validation }),
+                None => {
+                    cancelled = true;
+                    break;
+                }
+            }
+        }
+        results.sort_by(|a, b| a.cross_validation.mean.loss.total_cmp(&b.cross_validation.mean.loss));
+
+        let mut report = GridSearchReport {
+            model_name: model_name.to_string(),
+            results,
+            total_combinations: combinations.len(),
+            cancelled,
+            applied: false,
+        };
+        if let Some(best) = report.best().filter(|_| options.apply_best && !cancelled) {
+            let params = best.params.clone();
+            self.models.get_mut(model_name).expect("model is registered").apply_hyperparameters(&params)?;
+            self.config.learning_rate = params.learning_rate;
+            self.config.batch_size = params.batch_size;
+            report.applied = true;
+        }
+        Ok(report)
+    }
+
+    /// 在给定数据上对模型做 k 折交叉验证，每折在副本上按 `options` 训练
+    ///
+    /// 每折开始前检查取消标志，取消时返回 `None`。
+    fn cross_validate_model(
+        &self,
+        model_name: &str,
+        model: &dyn MachineLearningModel,
+        data: &[TrainingDataPoint],
+        k: usize,
+        options: &EpochOptions,
+        cancellation: Option<&SearchCancellation>,
+    ) -> Result<Option<CrossValidationReport>, AiError> {
+        let mut folds = Vec::with_capacity(k);
+        for fold in k_fold(data, k, self.config.split_seed)? {
+            if cancellation.is_some_and(SearchCancellation::is_cancelled) {
+                return Ok(None);
+            }
+            // 缩放器只在本折的训练集上拟合，验证集不参与
+            let (train, validation) = match self.config.feature_scaling {
+                Some(mode) => {
+                    let scaler = FeatureScaler::fit(mode, &fold.train)?;
+                    (scaler.transform_points(&fold.train)?, scaler.transform_points(&fold.validation)?)
+                }
+                None => (fold.train, fold.validation),
+            };
+            // 验证集只用于评估，训练时按训练损失早停
+            let mut candidate = model.clone_model()?;
+            fit_model(&mut candidate, &train, &[], options, self.config.max_epochs, self.config.early_stopping_patience)?;
+            folds.push(candidate.evaluate(&validation)?);
+        }
+        Ok(Some(CrossValidationReport::from_folds(model_name.to_string(), folds)))
     }
 
     /// 获取模型性能指标
     pub fn get_model_metrics(&self, model_name: &str, test_data: &[TrainingDataPoint]) -> Result<ModelMetrics, AiError> {
         if let Some(model) = self.models.get(model_name) {
-            model.evaluate(test_data)
+            match self.scalers.get(model_name) {
+                Some(scaler) => model.evaluate(&scaler.transform_points(test_data)?),
+                None => model.evaluate(test_data),
+            }
         } else {
             Err(AiError::ModelNotFound(model_name.to_string()))
         }
     }
 }
 
-/// 神经网络模型
+/// 按轮训练模型，最多 `max_epochs` 轮，监控损失连续 `patience` 轮没有改善时停止（0 表示不早停），
+/// 结束后恢复为监控损失最低那一轮的参数。有验证集时监控验证损失，否则监控训练损失；
+/// 不支持按轮训练的模型调用一次 `train`。
+fn fit_model(
+    model: &mut Box<dyn MachineLearningModel>,
+    train: &[TrainingDataPoint],
+    validation: &[TrainingDataPoint],
+    options: &EpochOptions,
+    max_epochs: u32,
+    patience: u32,
+) -> Result<TrainingReport, AiError> {
+    let mut report = TrainingReport::default();
+    let mut best: Option<(f64, Box<dyn MachineLearningModel>)> = None;
+    let mut epochs_without_improvement = 0;
+    for epoch in 0..max_epochs {
+        let train_loss = match model.train_epoch(train, options) {
+            Ok(loss) => loss,
+            Err(AiError::NotSupported(_)) if epoch == 0 => {
+                model.train(train)?;
+                report = TrainingReport {
+                    epochs_run: 1,
+                    train_loss_curve: vec![model.evaluate(train)?.loss],
+                    ..TrainingReport::default()
+                };
+                if !validation.is_empty() {
+                    report.val_loss_curve.push(model.evaluate(validation)?.loss);
+                }
+                break;
+            }
+            Err(e) => return Err(e),
+        };
+        report.epochs_run += 1;
+        report.train_loss_curve.push(train_loss);
+        let monitored = if validation.is_empty() {
+            train_loss
+        } else {
+            let loss = model.evaluate(validation)?.loss;
+            report.val_loss_curve.push(loss);
+            loss
+        };
+
+        if best.as_ref().is_none_or(|(best_loss, _)| monitored < *best_loss) {
+            best = Some((monitored, model.clone_model()?));
+            report.best_epoch = epoch;
+            epochs_without_improvement = 0;
+        } else {
+            epochs_without_improvement += 1;
+            if patience > 0 && epochs_without_improvement >= patience {
+                report.stopped_early = true;
+                break;
+            }
+        }
+    }
+    if let Some((_, best_model)) = best {
+        *model = best_model;
+    }
+    Ok(report)
+}
+
+/// 神经网络模型
 /// Neural Network Model
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct NeuralNetworkModel {
     /// 模型名称
     pub name: String,
     /// 层数
     pub layers: Vec<NeuralLayer>,
-    /// 权重
+    /// 每层的权重，按 `[神经元][输入]` 行优先展开；为空表示尚未训练
     pub weights: Vec<Vec<f64>>,
-    /// 偏置
+    /// 各层的偏置依次拼接
     pub biases: Vec<f64>,
     /// 激活函数
     pub activation_function: ActivationFunction,
+    /// 初始化权重使用的随机种子
+    pub seed: u64,
 }
 
 /// 神经网络层
 /// Neural Network Layer
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NeuralLayer {
     /// 神经元数量
     pub neuron_count: usize,
@@ -579,7 +1695,7 @@ pub struct NeuralLayer {
 
 /// 激活函数
 /// Activation Function
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum ActivationFunction {
     /// ReLU
     ReLU,
@@ -595,62 +1711,52 @@ pub enum ActivationFunction {
 
 impl MachineLearningModel for NeuralNetworkModel {
     fn predict(&self, input: &ModelInput) -> Result<ModelOutput, AiError> {
-        // 简化的神经网络预测实现
-        let mut activations = input.features.clone();
-        
-        for (layer_idx, layer) in self.layers.iter().enumerate() {
-            let mut new_activations = vec![0.0; layer.neuron_count];
-            
-            for (neuron_idx, activation) in new_activations.iter_mut().enumerate() {
-                let mut sum = 0.0;
-                for (input_idx, input_val) in activations.iter().enumerate() {
-                    if layer_idx < self.weights.len() && input_idx < self.weights[layer_idx].len() {
-                        sum += input_val * self.weights[layer_idx][input_idx];
-                    }
-                }
-                
-                if neuron_idx < self.biases.len() {
-                    sum += self.biases[neuron_idx];
-                }
-                
-                *activation = self.apply_activation_function(sum, &layer.activation_function);
-            }
-            
-            activations = new_activations;
-        }
-        
-        Ok(ModelOutput {
-            predictions: activations,
-            confidence: 0.8, // 简化的置信度计算
-            explanation: Some("基于神经网络的预测".to_string()),
-        })
+        let (pre_activations, activations) = self.forward(&input.features)?;
+        Ok(self.explain(input.features.len(), &pre_activations, activations))
+    }
+
+    /// 整批输入逐层前向传播
+    fn predict_batch(&self, inputs: &[ModelInput]) -> Result<Vec<ModelOutput>, AiError> {
+        let rows: Vec<&[f64]> = inputs.iter().map(|input| input.features.as_slice()).collect();
+        Ok(self.forward_batch(&rows)?.into_iter().zip(&rows)
+            .map(|((pre_activations, activations), features)| self.explain(features.len(), &pre_activations, activations))
+            .collect())
     }
 
     fn train(&mut self, data: &[TrainingDataPoint]) -> Result<(), AiError> {
-        // 简化的训练实现
-        println!("训练神经网络模型: {}", self.name);
-        
-        for epoch in 0..100 { // 简化的训练循环
-            let mut total_loss = 0.0;
-            
-            for data_point in data {
-                let prediction = self.predict(&data_point.input)?;
-                let loss = self.calculate_loss(&prediction.predictions, &data_point.target);
-                total_loss += loss;
-                
-                // 简化的反向传播
-                self.backpropagate(&data_point.input, &data_point.target);
+        // 单独训练时使用默认参数；由引擎驱动时按配置的轮数训练并早停
+        let options = EpochOptions::default();
+        for _ in 0..Self::DEFAULT_EPOCHS {
+            self.train_epoch(data, &options)?;
+        }
+        Ok(())
+    }
+
+    fn train_epoch(&mut self, data: &[TrainingDataPoint], options: &EpochOptions) -> Result<f64, AiError> {
+        let first = data.first().ok_or_else(|| AiError::DataError("训练数据为空".to_string()))?;
+        if self.weights.is_empty() {
+            self.initialize(first.input.features.len());
+        }
+
+        let mut total_loss = 0.0;
+        for batch in data.chunks(options.batch_size.max(1)) {
+            let mut weight_gradients: Vec<Vec<f64>> = self.weights.iter().map(|weights| vec![0.0; weights.len()]).collect();
+            let mut bias_gradients = vec![0.0; self.biases.len()];
+            for data_point in batch {
+                total_loss += self.backpropagate(data_point, &mut weight_gradients, &mut bias_gradients)?;
             }
-            
-            let avg_loss = total_loss / data.len() as f64;
-            println!("Epoch {}: 平均损失 = {:.4}", epoch + 1, avg_loss);
-            
-            if avg_loss < 0.01 {
-                break;
+
+            let step = options.learning_rate / batch.len() as f64;
+            for (weights, gradients) in self.weights.iter_mut().zip(&weight_gradients) {
+                for (weight, gradient) in weights.iter_mut().zip(gradients) {
+                    *weight -= step * gradient;
+                }
+            }
+            for (bias, gradient) in self.biases.iter_mut().zip(&bias_gradients) {
+                *bias -= step * gradient;
             }
         }
-        
-        Ok(())
+        Ok(total_loss / data.len() as f64)
     }
 
     fn evaluate(&self, test_data: &[TrainingDataPoint]) -> Result<ModelMetrics, AiError> {
@@ -685,9 +1791,60 @@ impl MachineLearningModel for NeuralNetworkModel {
     fn get_name(&self) -> String {
         self.name.clone()
     }
+
+    fn save(&self, path: &Path) -> Result<(), AiError> {
+        write_model_file(path, Self::KIND, &self.name, NeuralNetworkState {
+            layers: self.layers.clone(),
+            weights: self.weights.clone(),
+            biases: self.biases.clone(),
+            activation_function: self.activation_function.clone(),
+        })
+    }
+
+    fn load(&mut self, path: &Path) -> Result<(), AiError> {
+        let state: NeuralNetworkState = read_model_file(path, Self::KIND, &self.name)?;
+        if state.layers.len() != self.layers.len() {
+            return Err(AiError::ArchitectureMismatch(format!(
+                "模型 {} 声明了 {} 层，文件中为 {} 层", self.name, self.layers.len(), state.layers.len()
+            )));
+        }
+        for (index, (declared, saved)) in self.layers.iter().zip(&state.layers).enumerate() {
+            if declared.neuron_count != saved.neuron_count || declared.activation_function != saved.activation_function {
+                return Err(AiError::ArchitectureMismatch(format!(
+                    "模型 {} 第 {} 层声明为 {} 个神经元 ({:?})，文件中为 {} 个神经元 ({:?})",
+                    self.name, index, declared.neuron_count, declared.activation_function,
+                    saved.neuron_count, saved.activation_function
+                )));
+            }
+        }
+        self.check_parameters(&state.weights, &state.biases)?;
+
+        self.weights = state.weights;
+        self.biases = state.biases;
+        self.activation_function = state.activation_function;
+        Ok(())
+    }
+
+    fn clone_model(&self) -> Result<Box<dyn MachineLearningModel>, AiError> {
+        Ok(Box::new(self.clone()))
+    }
+}
+
+/// 神经网络模型文件中保存的状态
+#[derive(Serialize, Deserialize)]
+struct NeuralNetworkState {
+    layers: Vec<NeuralLayer>,
+    weights: Vec<Vec<f64>>,
+    biases: Vec<f64>,
+    activation_function: ActivationFunction,
 }
 
 impl NeuralNetworkModel {
+    /// 模型文件中的类型标识
+    const KIND: &'static str = "neural_network";
+    /// 单独调用 `train` 时的训练轮数
+    const DEFAULT_EPOCHS: u32 = 100;
+
     /// 创建新的神经网络模型
     pub fn new(name: String, layers: Vec<NeuralLayer>) -> Self {
         Self {
@@ -696,9 +1853,107 @@ impl NeuralNetworkModel {
             weights: Vec::new(),
             biases: Vec::new(),
             activation_function: ActivationFunction::ReLU,
+            seed: 0,
         }
     }
 
+    /// 设置初始化权重的随机种子
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// 按输入维度用 Xavier 均匀分布初始化权重，偏置置 0
+    fn initialize(&mut self, input_count: usize) {
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        let mut fan_in = input_count;
+        self.weights = self.layers.iter()
+            .map(|layer| {
+                let limit = (6.0 / (fan_in + layer.neuron_count).max(1) as f64).sqrt();
+                let weights = (0..layer.neuron_count * fan_in).map(|_| rng.random_range(-limit..=limit)).collect();
+                fan_in = layer.neuron_count;
+                weights
+            })
+            .collect();
+        self.biases = vec![0.0; self.layers.iter().map(|layer| layer.neuron_count).sum()];
+    }
+
+    /// 检查参数与层结构一致：每层的权重数为神经元数 × 上一层的输出数，偏置数为神经元总数；
+    /// 第一层的输入数由它的权重数推出，未训练的状态没有任何参数
+    fn check_parameters(&self, weights: &[Vec<f64>], biases: &[f64]) -> Result<(), AiError> {
+        if weights.is_empty() && biases.is_empty() {
+            return Ok(());
+        }
+        let invalid = |message: String| Err(AiError::InvalidState(format!("神经网络 {}: {}", self.name, message)));
+        if weights.len() != self.layers.len() {
+            return invalid(format!("有 {} 层权重，声明了 {} 层", weights.len(), self.layers.len()));
+        }
+        let neuron_count: usize = self.layers.iter().map(|layer| layer.neuron_count).sum();
+        if biases.len() != neuron_count {
+            return invalid(format!("有 {} 个偏置，共 {} 个神经元", biases.len(), neuron_count));
+        }
+        let mut fan_in = match self.layers[0].neuron_count {
+            0 => 0,
+            neurons => weights[0].len() / neurons,
+        };
+        for (index, (layer, weights)) in self.layers.iter().zip(weights).enumerate() {
+            if weights.len() != layer.neuron_count * fan_in {
+                return invalid(format!(
+                    "第 {} 层有 {} 个权重，与 {} 个神经元和 {} 个输入不匹配", index, weights.len(), layer.neuron_count, fan_in
+                ));
+            }
+            fan_in = layer.neuron_count;
+        }
+        Ok(())
+    }
+
+    /// 前向传播，返回每层的加权输入和 `[输入, 各层输出...]`
+    #[allow(clippy::type_complexity)]
+    fn forward(&self, features: &[f64]) -> Result<(Vec<Vec<f64>>, Vec<Vec<f64>>), AiError> {
+        Ok(self.forward_batch(&[features])?.pop().expect("one row in, one row out"))
+    }
+
+    /// 逐层对整批输入做前向传播，按输入顺序返回每个输入的加权输入和各层输出
+    #[allow(clippy::type_complexity)]
+    fn forward_batch(&self, rows: &[&[f64]]) -> Result<Vec<(Vec<Vec<f64>>, Vec<Vec<f64>>)>, AiError> {
+        if self.weights.is_empty() {
+            return Err(AiError::PredictionError(format!("神经网络 {} 尚未训练", self.name)));
+        }
+        if self.weights.len() != self.layers.len()
+            || self.biases.len() != self.layers.iter().map(|layer| layer.neuron_count).sum::<usize>()
+        {
+            return Err(AiError::PredictionError(format!("神经网络 {} 的参数与层结构不一致", self.name)));
+        }
+
+        let mut passes: Vec<(Vec<Vec<f64>>, Vec<Vec<f64>>)> = rows.iter()
+            .map(|features| (Vec::with_capacity(self.layers.len()), vec![features.to_vec()]))
+            .collect();
+        let mut bias_offset = 0;
+        for (index, (layer, weights)) in self.layers.iter().zip(&self.weights).enumerate() {
+            let biases = &self.biases[bias_offset..bias_offset + layer.neuron_count];
+            for (pre_activations, activations) in &mut passes {
+                let inputs = activations.last().expect("input layer");
+                if weights.len() != layer.neuron_count * inputs.len() {
+                    return Err(AiError::PredictionError(format!(
+                        "第 {} 层有 {} 个权重，与 {} 个神经元和 {} 个输入不匹配",
+                        index, weights.len(), layer.neuron_count, inputs.len()
+                    )));
+                }
+                let sums: Vec<f64> = biases.iter().enumerate()
+                    .map(|(neuron, bias)| {
+                        let row = &weights[neuron * inputs.len()..(neuron + 1) * inputs.len()];
+                        bias + row.iter().zip(inputs).map(|(w, x)| w * x).sum::<f64>()
+                    })
+                    .collect();
+                let outputs = sums.iter().map(|sum| self.apply_activation_function(*sum, &layer.activation_function)).collect();
+                pre_activations.push(sums);
+                activations.push(outputs);
+            }
+            bias_offset += layer.neuron_count;
+        }
+        Ok(passes)
+    }
+
     /// 应用激活函数
     fn apply_activation_function(&self, x: f64, activation: &ActivationFunction) -> f64 {
         match activation {
@@ -719,11 +1974,102 @@ impl NeuralNetworkModel {
         loss / predictions.len() as f64
     }
 
-    /// 反向传播
-    #[allow(unused_variables)]
-    fn backpropagate(&mut self, input: &ModelInput, target: &[f64]) {
-        // 简化的反向传播实现
-        // 实际应用中应该实现完整的反向传播算法
+    /// 激活函数对加权输入的导数
+    fn activation_derivative(&self, x: f64, activation: &ActivationFunction) -> f64 {
+        match activation {
+            ActivationFunction::ReLU => if x > 0.0 { 1.0 } else { 0.0 },
+            ActivationFunction::Sigmoid => {
+                let y = 1.0 / (1.0 + (-x).exp());
+                y * (1.0 - y)
+            }
+            ActivationFunction::Tanh => 1.0 - x.tanh().powi(2),
+            ActivationFunction::LeakyReLU => if x > 0.0 { 1.0 } else { 0.01 },
+            ActivationFunction::Softmax => x.exp(), // 与简化的前向实现一致
+        }
+    }
+
+    /// 反向传播一个样本的均方误差，把梯度累加到给定的缓冲区，返回该样本的损失
+    fn backpropagate(
+        &self,
+        data_point: &TrainingDataPoint,
+        weight_gradients: &mut [Vec<f64>],
+        bias_gradients: &mut [f64],
+    ) -> Result<f64, AiError> {
+        let (pre_activations, activations) = self.forward(&data_point.input.features)?;
+        let outputs = activations.last().expect("output layer");
+        if outputs.len() != data_point.target.len() {
+            return Err(AiError::DataError(format!(
+                "神经网络输出 {} 个值，目标值有 {} 个", outputs.len(), data_point.target.len()
+            )));
+        }
+
+        let output_layer = self.layers.len() - 1;
+        let mut deltas: Vec<f64> = outputs.iter().zip(&data_point.target).zip(&pre_activations[output_layer])
+            .map(|((output, target), sum)| {
+                2.0 * (output - target) / outputs.len() as f64
+                    * self.activation_derivative(*sum, &self.layers[output_layer].activation_function)
+            })
+            .collect();
+        let mut bias_offset = bias_gradients.len();
+        for index in (0..self.layers.len()).rev() {
+            let inputs = &activations[index];
+            bias_offset -= self.layers[index].neuron_count;
+            for (neuron, delta) in deltas.iter().enumerate() {
+                bias_gradients[bias_offset + neuron] += delta;
+                let row = &mut weight_gradients[index][neuron * inputs.len()..(neuron + 1) * inputs.len()];
+                for (gradient, input) in row.iter_mut().zip(inputs) {
+                    *gradient += delta * input;
+                }
+            }
+            if index > 0 {
+                deltas = (0..inputs.len())
+                    .map(|input| {
+                        let sum: f64 = deltas.iter().enumerate()
+                            .map(|(neuron, delta)| delta * self.weights[index][neuron * inputs.len() + input])
+                            .sum();
+                        sum * self.activation_derivative(pre_activations[index - 1][input], &self.layers[index - 1].activation_function)
+                    })
+                    .collect();
+            }
+        }
+
+        Ok(self.calculate_loss(outputs, &data_point.target))
+    }
+
+    /// 由一次前向传播的结果构造输出，贡献为第一个输出对各输入的梯度
+    fn explain(&self, input_count: usize, pre_activations: &[Vec<f64>], mut activations: Vec<Vec<f64>>) -> ModelOutput {
+        ModelOutput::explained(
+            activations.pop().unwrap_or_default(),
+            0.8, // 简化的置信度计算
+            "基于神经网络的预测，按第一个输出对输入的梯度".to_string(),
+            contributions_of(self.input_gradients(input_count, pre_activations)),
+        )
+    }
+
+    /// 第一个输出对各输入特征的梯度
+    fn input_gradients(&self, feature_count: usize, pre_activations: &[Vec<f64>]) -> Vec<f64> {
+        let output_layer = self.layers.len() - 1;
+        let mut deltas: Vec<f64> = pre_activations[output_layer].iter().enumerate()
+            .map(|(neuron, sum)| match neuron {
+                0 => self.activation_derivative(*sum, &self.layers[output_layer].activation_function),
+                _ => 0.0,
+            })
+            .collect();
+        for index in (0..self.layers.len()).rev() {
+            let input_count = if index == 0 { feature_count } else { self.layers[index - 1].neuron_count };
+            deltas = (0..input_count)
+                .map(|input| {
+                    let sum: f64 = deltas.iter().enumerate()
+                        .map(|(neuron, delta)| delta * self.weights[index][neuron * input_count + input])
+                        .sum();
+                    match index {
+                        0 => sum,
+                        _ => sum * self.activation_derivative(pre_activations[index - 1][input], &self.layers[index - 1].activation_function),
+                    }
+                })
+                .collect();
+        }
+        deltas
     }
 
     /// 检查预测是否正确
@@ -742,138 +2088,3499 @@ impl NeuralNetworkModel {
     }
 }
 
-/// 性能优化策略
-/// Performance Optimization Strategy
-#[derive(Debug)]
-pub struct PerformanceOptimizationStrategy;
+/// 线性回归模型
+/// Linear Regression Model
+///
+/// 特征数不超过 `normal_equation_max_features` 时用正规方程求闭式解，否则（或矩阵奇异时）
+/// 在标准化后的特征上做批量梯度下降。每个输出维度各有一组系数，样本按 `weight` 加权。
+/// Fits in closed form via the normal equation for small feature counts, otherwise (or when the system is
+/// singular) by batch gradient descent on standardized features. Each output has its own coefficients and
+/// samples are weighted by `weight`.
+#[derive(Debug, Clone)]
+pub struct LinearRegressionModel {
+    /// 模型名称
+    pub name: String,
+    /// 岭回归正则化系数，0 表示普通最小二乘；不作用于截距
+    pub ridge_lambda: f64,
+    /// 使用正规方程的最大特征数
+    pub normal_equation_max_features: usize,
+    /// 梯度下降学习率（作用于标准化后的特征）
+    pub learning_rate: f64,
+    /// 梯度下降最大迭代次数
+    pub max_iterations: usize,
+    /// 每个输出维度的特征系数
+    coefficients: Vec<Vec<f64>>,
+    /// 每个输出维度的截距
+    intercepts: Vec<f64>,
+    /// 训练集上的 R²，用作预测置信度
+    training_r2: f64,
+    /// 最近一次训练使用的求解方法
+    solver: Option<LinearSolver>,
+}
 
-#[allow(unused_variables)]
-impl AiOptimizationStrategy for PerformanceOptimizationStrategy {
-    fn optimize(&self, context: &OptimizationContext) -> Result<OptimizationResult, AiError> {
-        let recommendations = vec![
-            OptimizationRecommendation {
-                recommendation_type: RecommendationType::AlgorithmOptimization,
-                description: "优化算法复杂度".to_string(),
-                expected_benefit: 0.25,
-                implementation_cost: ImplementationCost::Medium,
-                time_horizon: TimeHorizon::MediumTerm,
-                dependencies: Vec::new(),
-            },
-            OptimizationRecommendation {
-                recommendation_type: RecommendationType::ParameterTuning,
-                description: "调整系统参数".to_string(),
-                expected_benefit: 0.15,
-                implementation_cost: ImplementationCost::Low,
-                time_horizon: TimeHorizon::ShortTerm,
-                dependencies: Vec::new(),
-            },
-        ];
+/// 线性回归求解方法
+/// Linear Regression Solver
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LinearSolver {
+    /// 正规方程闭式解
+    NormalEquation,
+    /// 批量梯度下降
+    GradientDescent,
+}
 
-        Ok(OptimizationResult {
-            strategy_name: "Performance Optimization".to_string(),
-            recommendations,
-            expected_improvement: 0.40,
-            confidence: 0.85,
-            implementation_difficulty: ImplementationDifficulty::Medium,
-            risk_assessment: RiskAssessment {
-                risk_level: RiskLevel::Low,
-                risk_factors: Vec::new(),
-                mitigation_measures: vec!["渐进式部署".to_string()],
-                risk_probability: 0.1,
-                risk_impact: 0.2,
-            },
-        })
+impl MachineLearningModel for LinearRegressionModel {
+    fn predict(&self, input: &ModelInput) -> Result<ModelOutput, AiError> {
+        let predictions = self.predict_values(&input.features)?;
+        Ok(self.explain(predictions, &input.features, self.summary()))
+    }
+
+    /// 批量预测：先统一检查所有输入的维度，把输入按行打包成特征矩阵，每个输出维度做一次矩阵-向量乘法
+    ///
+    /// 每行的累加顺序与 [`predict`](Self::predict) 相同，结果逐位一致；模型方程只格式化一次。
+    fn predict_batch(&self, inputs: &[ModelInput]) -> Result<Vec<ModelOutput>, AiError> {
+        let Some(first) = self.coefficients.first() else {
+            return Err(AiError::PredictionError(format!("模型 {} 尚未训练", self.name)));
+        };
+        let feature_count = first.len();
+        if let Some(input) = inputs.iter().find(|input| input.features.len() != feature_count) {
+            return Err(AiError::PredictionError(format!("需要 {} 个特征，实际为 {}", feature_count, input.features.len())));
+        }
+
+        let matrix: Vec<f64> = inputs.iter().flat_map(|input| input.features.iter().copied()).collect();
+        let row = |index: usize| &matrix[index * feature_count..(index + 1) * feature_count];
+        let mut predictions = vec![Vec::with_capacity(self.coefficients.len()); inputs.len()];
+        for (coefficients, intercept) in self.coefficients.iter().zip(&self.intercepts) {
+            for (index, output) in predictions.iter_mut().enumerate() {
+                output.push(intercept + coefficients.iter().zip(row(index)).map(|(c, x)| c * x).sum::<f64>());
+            }
+        }
+        let summary = self.summary();
+        Ok(predictions.into_iter().enumerate()
+            .map(|(index, predictions)| self.explain(predictions, row(index), summary.clone()))
+            .collect())
+    }
+
+    fn train(&mut self, data: &[TrainingDataPoint]) -> Result<(), AiError> {
+        let (feature_count, output_count) = Self::dimensions(data)?;
+        if data.iter().any(|point| !point.weight.is_finite() || point.weight < 0.0) || data.iter().all(|point| point.weight == 0.0) {
+            return Err(AiError::DataError("样本权重必须为非负数且不能全为 0".to_string()));
+        }
+
+        let closed_form = if feature_count <= self.normal_equation_max_features {
+            self.fit_normal_equation(data, feature_count, output_count)
+        } else {
+            None
+        };
+        let (solver, (coefficients, intercepts)) = match closed_form {
+            Some(solution) => (LinearSolver::NormalEquation, solution),
+            None => (LinearSolver::GradientDescent, self.fit_gradient_descent(data, feature_count, output_count)),
+        };
+        if coefficients.iter().flatten().chain(&intercepts).any(|value| !value.is_finite()) {
+            return Err(AiError::TrainingError("梯度下降发散，请降低学习率".to_string()));
+        }
+
+        self.coefficients = coefficients;
+        self.intercepts = intercepts;
+        self.solver = Some(solver);
+        self.training_r2 = self.fit_statistics(data)?.1;
+        Ok(())
+    }
+
+    fn evaluate(&self, test_data: &[TrainingDataPoint]) -> Result<ModelMetrics, AiError> {
+        let (rmse, r2) = self.fit_statistics(test_data)?;
+        Ok(regression_metrics(rmse, r2))
     }
 
-    #[allow(unused_variables)]
     fn get_name(&self) -> String {
-        "Performance Optimization".to_string()
+        self.name.clone()
     }
 
-    fn get_priority(&self) -> OptimizationPriority {
-        OptimizationPriority::High
+    fn save(&self, path: &Path) -> Result<(), AiError> {
+        write_model_file(path, Self::KIND, &self.name, LinearRegressionState {
+            ridge_lambda: self.ridge_lambda,
+            normal_equation_max_features: self.normal_equation_max_features,
+            learning_rate: self.learning_rate,
+            max_iterations: self.max_iterations,
+            coefficients: self.coefficients.clone(),
+            intercepts: self.intercepts.clone(),
+            training_r2: self.training_r2,
+            solver: self.solver,
+        })
     }
 
-    fn requires_training(&self) -> bool {
-        true
+    fn load(&mut self, path: &Path) -> Result<(), AiError> {
+        let state: LinearRegressionState = read_model_file(path, Self::KIND, &self.name)?;
+        if state.coefficients.len() != state.intercepts.len()
+            || state.coefficients.windows(2).any(|pair| pair[0].len() != pair[1].len())
+        {
+            return Err(AiError::ArchitectureMismatch(format!(
+                "模型 {} 的文件中系数和截距的维度不一致", self.name
+            )));
+        }
+
+        self.ridge_lambda = state.ridge_lambda;
+        self.normal_equation_max_features = state.normal_equation_max_features;
+        self.learning_rate = state.learning_rate;
+        self.max_iterations = state.max_iterations;
+        self.coefficients = state.coefficients;
+        self.intercepts = state.intercepts;
+        self.training_r2 = state.training_r2;
+        self.solver = state.solver;
+        Ok(())
+    }
+
+    fn clone_model(&self) -> Result<Box<dyn MachineLearningModel>, AiError> {
+        Ok(Box::new(self.clone()))
     }
 }
 
-/// 成本优化策略
-/// Cost Optimization Strategy
-#[derive(Debug)]
-pub struct CostOptimizationStrategy;
+/// 线性回归模型文件中保存的状态
+#[derive(Serialize, Deserialize)]
+struct LinearRegressionState {
+    ridge_lambda: f64,
+    normal_equation_max_features: usize,
+    learning_rate: f64,
+    max_iterations: usize,
+    coefficients: Vec<Vec<f64>>,
+    intercepts: Vec<f64>,
+    training_r2: f64,
+    solver: Option<LinearSolver>,
+}
 
-#[allow(unused_variables)]
-impl AiOptimizationStrategy for CostOptimizationStrategy {
-    fn optimize(&self, context: &OptimizationContext) -> Result<OptimizationResult, AiError> {
-        let recommendations = vec![
-            OptimizationRecommendation {
-                recommendation_type: RecommendationType::ResourceAllocation,
-                description: "优化资源分配".to_string(),
-                expected_benefit: 0.30,
-                implementation_cost: ImplementationCost::Low,
-                time_horizon: TimeHorizon::ShortTerm,
-                dependencies: Vec::new(),
-            },
-            OptimizationRecommendation {
-                recommendation_type: RecommendationType::CacheStrategy,
-                description: "优化缓存策略".to_string(),
-                expected_benefit: 0.20,
-                implementation_cost: ImplementationCost::Medium,
-                time_horizon: TimeHorizon::MediumTerm,
-                dependencies: Vec::new(),
-            },
-        ];
+impl LinearRegressionModel {
+    /// 模型文件中的类型标识
+    const KIND: &'static str = "linear_regression";
 
-        Ok(OptimizationResult {
-            strategy_name: "Cost Optimization".to_string(),
-            recommendations,
-            expected_improvement: 0.50,
-            confidence: 0.90,
-            implementation_difficulty: ImplementationDifficulty::Easy,
-            risk_assessment: RiskAssessment {
-                risk_level: RiskLevel::Low,
-                risk_factors: Vec::new(),
-                mitigation_measures: vec!["监控资源使用".to_string()],
-                risk_probability: 0.05,
-                risk_impact: 0.1,
-            },
-        })
+    /// 创建新的线性回归模型
+    pub fn new(name: String) -> Self {
+        Self {
+            name,
+            ridge_lambda: 0.0,
+            normal_equation_max_features: 64,
+            learning_rate: 0.1,
+            max_iterations: 5_000,
+            coefficients: Vec::new(),
+            intercepts: Vec::new(),
+            training_r2: 0.0,
+            solver: None,
+        }
     }
 
-    #[allow(unused_variables)]
-    fn get_name(&self) -> String {
-        "Cost Optimization".to_string()
+    /// 设置岭回归正则化系数
+    pub fn with_ridge(mut self, lambda: f64) -> Self {
+        self.ridge_lambda = lambda.max(0.0);
+        self
     }
 
-    fn get_priority(&self) -> OptimizationPriority {
-        OptimizationPriority::Medium
+    /// 设置使用正规方程的最大特征数
+    pub fn with_normal_equation_max_features(mut self, max_features: usize) -> Self {
+        self.normal_equation_max_features = max_features;
+        self
     }
 
-    fn requires_training(&self) -> bool {
-        false
+    /// 设置梯度下降的学习率和最大迭代次数
+    pub fn with_gradient_descent(mut self, learning_rate: f64, max_iterations: usize) -> Self {
+        self.learning_rate = learning_rate;
+        self.max_iterations = max_iterations;
+        self
     }
-}
 
-/// 错误类型定义
-/// Error Type Definitions
+    /// 每个输出维度的特征系数，`coefficients()[输出][特征]`
+    /// Feature coefficients per output, indexed `[output][feature]`
+    pub fn coefficients(&self) -> &[Vec<f64>] {
+        &self.coefficients
+    }
 
-#[derive(Debug, Error)]
-pub enum AiError {
-    /// 模型未找到
-    #[error("模型未找到: {0}")]
-    ModelNotFound(String),
-    /// 训练错误
-    #[error("训练错误: {0}")]
-    TrainingError(String),
-    /// 预测错误
-    #[error("预测错误: {0}")]
-    PredictionError(String),
-    /// 配置错误
-    #[error("配置错误: {0}")]
-    ConfigurationError(String),
-    /// 数据错误
-    #[error("数据错误: {0}")]
-    DataError(String),
+    /// 每个输出维度的截距
+    pub fn intercepts(&self) -> &[f64] {
+        &self.intercepts
+    }
+
+    /// 最近一次训练使用的求解方法，未训练时为 `None`
+    pub fn solver(&self) -> Option<LinearSolver> {
+        self.solver
+    }
+
+    /// 计算预测值
+    fn predict_values(&self, features: &[f64]) -> Result<Vec<f64>, AiError> {
+        let Some(first) = self.coefficients.first() else {
+            return Err(AiError::PredictionError(format!("模型 {} 尚未训练", self.name)));
+        };
+        if features.len() != first.len() {
+            return Err(AiError::PredictionError(format!("需要 {} 个特征，实际为 {}", first.len(), features.len())));
+        }
+
+        Ok(self.coefficients.iter().zip(&self.intercepts)
+            .map(|(coefficients, intercept)| intercept + coefficients.iter().zip(features).map(|(c, x)| c * x).sum::<f64>())
+            .collect())
+    }
+
+    /// 模型方程，作为解释的概述
+    fn summary(&self) -> String {
+        let terms: Vec<String> = self.coefficients.iter().zip(&self.intercepts).enumerate()
+            .map(|(output, (coefficients, intercept))| {
+                let features: String = coefficients.iter().enumerate()
+                    .map(|(feature, coefficient)| format!(" {:+.4}·x{}", coefficient, feature))
+                    .collect();
+                format!("y{} = {:.4}{}", output, intercept, features)
+            })
+            .collect();
+        format!("线性回归: {}", terms.join("; "))
+    }
+
+    /// 按第一个输出的系数 × 特征值计算贡献
+    fn explain(&self, predictions: Vec<f64>, features: &[f64], summary: String) -> ModelOutput {
+        let contributions = self.coefficients.first()
+            .map(|coefficients| contributions_of(coefficients.iter().zip(features).map(|(c, x)| c * x)))
+            .unwrap_or_default();
+        ModelOutput::explained(predictions, self.training_r2.clamp(0.0, 1.0), summary, contributions)
+    }
+
+    /// 检查训练数据的维度一致，返回 `(特征数, 输出数)`
+    fn dimensions(data: &[TrainingDataPoint]) -> Result<(usize, usize), AiError> {
+        let first = data.first().ok_or_else(|| AiError::DataError("训练数据为空".to_string()))?;
+        let (feature_count, output_count) = (first.input.features.len(), first.target.len());
+        if output_count == 0 {
+            return Err(AiError::DataError("目标值为空".to_string()));
+        }
+        if let Some(index) = data.iter().position(|point| {
+            point.input.features.len() != feature_count || point.target.len() != output_count
+        }) {
+            return Err(AiError::DataError(format!(
+                "第 {} 个样本的维度与第一个样本不一致（需要 {} 个特征、{} 个目标值）",
+                index, feature_count, output_count
+            )));
+        }
+        Ok((feature_count, output_count))
+    }
+
+    /// 解加权正规方程 (XᵀWX + λI)β = XᵀWy，矩阵奇异时返回 `None`
+    #[allow(clippy::type_complexity)]
+    fn fit_normal_equation(&self, data: &[TrainingDataPoint], feature_count: usize, output_count: usize) -> Option<(Vec<Vec<f64>>, Vec<f64>)> {
+        // 第 0 列是截距项
+        let size = feature_count + 1;
+        let mut gram = vec![vec![0.0; size]; size];
+        let mut moments = vec![vec![0.0; output_count]; size];
+        for point in data {
+            let row: Vec<f64> = std::iter::once(1.0).chain(point.input.features.iter().copied()).collect();
+            for i in 0..size {
+                for j in 0..size {
+                    gram[i][j] += point.weight * row[i] * row[j];
+                }
+                for (output, target) in point.target.iter().enumerate() {
+                    moments[i][output] += point.weight * row[i] * target;
+                }
+            }
+        }
+        for (i, row) in gram.iter_mut().enumerate().skip(1) {
+            row[i] += self.ridge_lambda;
+        }
+
+        let solution = solve_linear_system(gram, moments)?;
+        let intercepts = solution[0].clone();
+        let coefficients = (0..output_count)
+            .map(|output| solution[1..].iter().map(|row| row[output]).collect())
+            .collect();
+        Some((coefficients, intercepts))
+    }
+
+    /// 在标准化后的特征上做批量梯度下降，再换算回原始尺度
+    #[allow(clippy::type_complexity)]
+    fn fit_gradient_descent(&self, data: &[TrainingDataPoint], feature_count: usize, output_count: usize) -> (Vec<Vec<f64>>, Vec<f64>) {
+        let total_weight: f64 = data.iter().map(|point| point.weight).sum();
+        let means: Vec<f64> = (0..feature_count)
+            .map(|feature| data.iter().map(|point| point.weight * point.input.features[feature]).sum::<f64>() / total_weight)
+            .collect();
+        // 常数特征的标准差记为 1，其系数保持为 0
+        let scales: Vec<f64> = (0..feature_count)
+            .map(|feature| {
+                let variance = data.iter()
+                    .map(|point| point.weight * (point.input.features[feature] - means[feature]).powi(2))
+                    .sum::<f64>() / total_weight;
+                if variance > f64::EPSILON { variance.sqrt() } else { 1.0 }
+            })
+            .collect();
+        let standardized: Vec<Vec<f64>> = data.iter()
+            .map(|point| (0..feature_count).map(|feature| (point.input.features[feature] - means[feature]) / scales[feature]).collect())
+            .collect();
+
+        let mut weights = vec![vec![0.0; feature_count]; output_count];
+        let mut biases = vec![0.0; output_count];
+        for _ in 0..self.max_iterations {
+            let mut weight_gradients = vec![vec![0.0; feature_count]; output_count];
+            let mut bias_gradients = vec![0.0; output_count];
+            for (point, row) in data.iter().zip(&standardized) {
+                for output in 0..output_count {
+                    let prediction = biases[output] + weights[output].iter().zip(row).map(|(w, x)| w * x).sum::<f64>();
+                    let error = point.weight * (prediction - point.target[output]) / total_weight;
+                    bias_gradients[output] += error;
+                    for (gradient, x) in weight_gradients[output].iter_mut().zip(row) {
+                        *gradient += error * x;
+                    }
+                }
+            }
+
+            let mut step = 0.0f64;
+            for output in 0..output_count {
+                for (weight, gradient) in weights[output].iter_mut().zip(&weight_gradients[output]) {
+                    let gradient = gradient + self.ridge_lambda / total_weight * *weight;
+                    *weight -= self.learning_rate * gradient;
+                    step = step.max(gradient.abs());
+                }
+                biases[output] -= self.learning_rate * bias_gradients[output];
+                step = step.max(bias_gradients[output].abs());
+            }
+            if step < 1e-10 || !step.is_finite() {
+                break;
+            }
+        }
+
+        let coefficients: Vec<Vec<f64>> = weights.iter()
+            .map(|weights| weights.iter().zip(&scales).map(|(w, scale)| w / scale).collect())
+            .collect();
+        let intercepts = biases.iter().zip(&coefficients)
+            .map(|(bias, coefficients)| bias - coefficients.iter().zip(&means).map(|(c, mean)| c * mean).sum::<f64>())
+            .collect();
+        (coefficients, intercepts)
+    }
+
+    /// 计算 `(RMSE, R²)`
+    fn fit_statistics(&self, data: &[TrainingDataPoint]) -> Result<(f64, f64), AiError> {
+        regression_statistics(data, self.intercepts.len(), |features| self.predict_values(features))
+    }
+}
+
+/// 计算回归模型的 `(RMSE, R²)`，多输出时对所有输出汇总
+fn regression_statistics(
+    data: &[TrainingDataPoint],
+    output_count: usize,
+    predict: impl Fn(&[f64]) -> Result<Vec<f64>, AiError>,
+) -> Result<(f64, f64), AiError> {
+    if data.is_empty() {
+        return Err(AiError::DataError("评估数据为空".to_string()));
+    }
+    let mut means = vec![0.0; output_count];
+    for point in data {
+        if point.target.len() != output_count {
+            return Err(AiError::DataError(format!("需要 {} 个目标值，实际为 {}", output_count, point.target.len())));
+        }
+        for (mean, target) in means.iter_mut().zip(&point.target) {
+            *mean += target / data.len() as f64;
+        }
+    }
+
+    let (mut residual, mut total) = (0.0, 0.0);
+    for point in data {
+        let predictions = predict(&point.input.features)?;
+        for ((prediction, target), mean) in predictions.iter().zip(&point.target).zip(&means) {
+            residual += (prediction - target).powi(2);
+            total += (target - mean).powi(2);
+        }
+    }
+
+    let rmse = (residual / (data.len() * output_count) as f64).sqrt();
+    let r2 = if total > 0.0 { 1.0 - residual / total } else if residual == 0.0 { 1.0 } else { 0.0 };
+    Ok((rmse, r2))
+}
+
+/// 回归模型的指标：`loss` 为 RMSE，准确率取截断到 [0, 1] 的 R²
+fn regression_metrics(rmse: f64, r2: f64) -> ModelMetrics {
+    let accuracy = r2.clamp(0.0, 1.0);
+    ModelMetrics {
+        accuracy,
+        precision: accuracy, // 回归模型没有分类意义上的精确率和召回率
+        recall: accuracy,
+        f1_score: accuracy,
+        loss: rmse,
+    }
+}
+
+/// 用部分主元高斯消元解 `A·X = B`（`B` 可有多列），矩阵奇异时返回 `None`
+fn solve_linear_system(mut a: Vec<Vec<f64>>, mut b: Vec<Vec<f64>>) -> Option<Vec<Vec<f64>>> {
+    let n = a.len();
+    let scale = a.iter().flatten().fold(0.0f64, |max, value| max.max(value.abs())).max(1.0);
+    for column in 0..n {
+        let pivot = (column..n).max_by(|&i, &j| a[i][column].abs().total_cmp(&a[j][column].abs()))?;
+        if a[pivot][column].abs() <= scale * 1e-12 {
+            return None;
+        }
+        a.swap(column, pivot);
+        b.swap(column, pivot);
+        let (pivot_a, pivot_b) = (a[column].clone(), b[column].clone());
+        for row in column + 1..n {
+            let factor = a[row][column] / pivot_a[column];
+            if factor == 0.0 {
+                continue;
+            }
+            for (value, pivot_value) in a[row][column..].iter_mut().zip(&pivot_a[column..]) {
+                *value -= factor * pivot_value;
+            }
+            for (value, pivot_value) in b[row].iter_mut().zip(&pivot_b) {
+                *value -= factor * pivot_value;
+            }
+        }
+    }
+
+    for column in (0..n).rev() {
+        for k in 0..b[column].len() {
+            let sum: f64 = (column + 1..n).map(|j| a[column][j] * b[j][k]).sum();
+            b[column][k] = (b[column][k] - sum) / a[column][column];
+        }
+    }
+    Some(b)
+}
+
+/// 决策树节点
+/// Decision Tree Node
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum TreeNode {
+    /// 叶子节点，保存每个输出的加权平均值
+    Leaf {
+        /// 预测值
+        values: Vec<f64>,
+    },
+    /// 内部节点，`features[feature] <= threshold` 时走左子树
+    Split {
+        /// 特征下标
+        feature: usize,
+        /// 分裂阈值
+        threshold: f64,
+        /// 左子树
+        left: Box<TreeNode>,
+        /// 右子树
+        right: Box<TreeNode>,
+    },
+}
+
+impl TreeNode {
+    /// 沿树查找叶子节点的预测值
+    fn predict(&self, features: &[f64]) -> &[f64] {
+        match self {
+            TreeNode::Leaf { values } => values,
+            TreeNode::Split { feature, threshold, left, right } => {
+                if features[*feature] <= *threshold { left.predict(features) } else { right.predict(features) }
+            }
+        }
+    }
+
+    /// 叶子节点的输出维度
+    fn predict_len(&self) -> usize {
+        match self {
+            TreeNode::Leaf { values } => values.len(),
+            TreeNode::Split { left, .. } => left.predict_len(),
+        }
+    }
+
+    /// 检查分裂特征下标都小于特征数、所有叶子的输出维度一致，加载的树在预测时不会越界
+    fn validate(&self, feature_count: usize, output_count: usize) -> Result<(), String> {
+        match self {
+            TreeNode::Leaf { values } if values.len() != output_count => {
+                Err(format!("叶子节点有 {} 个输出，应为 {}", values.len(), output_count))
+            }
+            TreeNode::Leaf { .. } => Ok(()),
+            TreeNode::Split { feature, .. } if *feature >= feature_count => {
+                Err(format!("分裂特征下标 {} 超出特征数 {}", feature, feature_count))
+            }
+            TreeNode::Split { left, right, .. } => {
+                left.validate(feature_count, output_count)?;
+                right.validate(feature_count, output_count)
+            }
+        }
+    }
+}
+
+/// 决策树回归模型（CART）
+/// Decision Tree Model (CART regression)
+///
+/// 每次选择使加权平方误差下降最多的特征和阈值分裂，直到达到最大深度、叶子样本数不足或无法再降低误差。
+/// Each split picks the feature and threshold with the largest drop in weighted squared error, until the maximum
+/// depth is reached, a leaf would get too few samples, or no split reduces the error.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DecisionTreeModel {
+    /// 模型名称
+    pub name: String,
+    /// 最大深度，根节点深度为 0
+    pub max_depth: usize,
+    /// 每个叶子至少包含的样本数
+    pub min_samples_leaf: usize,
+    /// 每次分裂随机考察的特征数，`None` 表示考察全部特征
+    pub max_features: Option<usize>,
+    /// 随机种子，只在 `max_features` 生效时使用
+    pub seed: u64,
+    /// 根节点，未训练时为 `None`
+    root: Option<TreeNode>,
+    /// 归一化的特征重要性
+    importances: Vec<f64>,
+    /// 训练集上的 R²，用作预测置信度
+    training_r2: f64,
+}
+
+impl MachineLearningModel for DecisionTreeModel {
+    fn predict(&self, input: &ModelInput) -> Result<ModelOutput, AiError> {
+        Ok(ModelOutput { predictions: self.predict_values(&input.features)?, ..self.explanation() })
+    }
+
+    fn predict_batch(&self, inputs: &[ModelInput]) -> Result<Vec<ModelOutput>, AiError> {
+        let explanation = self.explanation();
+        inputs.iter()
+            .map(|input| Ok(ModelOutput { predictions: self.predict_values(&input.features)?, ..explanation.clone() }))
+            .collect()
+    }
+
+    fn train(&mut self, data: &[TrainingDataPoint]) -> Result<(), AiError> {
+        let (feature_count, _) = tree_dimensions(data)?;
+        let mut importances = vec![0.0; feature_count];
+        let indices: Vec<usize> = (0..data.len()).collect();
+        self.fit_indices(data, indices, &mut importances);
+        self.importances = normalized(importances);
+        self.training_r2 = regression_statistics(data, data[0].target.len(), |features| self.predict_values(features))?.1;
+        Ok(())
+    }
+
+    fn evaluate(&self, test_data: &[TrainingDataPoint]) -> Result<ModelMetrics, AiError> {
+        let output_count = self.root.as_ref().map(|root| root.predict_len()).unwrap_or(0);
+        let (rmse, r2) = regression_statistics(test_data, output_count, |features| self.predict_values(features))?;
+        Ok(regression_metrics(rmse, r2))
+    }
+
+    fn get_name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn save(&self, path: &Path) -> Result<(), AiError> {
+        write_model_file(path, Self::KIND, &self.name, self)
+    }
+
+    fn load(&mut self, path: &Path) -> Result<(), AiError> {
+        let loaded: Self = read_model_file(path, Self::KIND, &self.name)?;
+        loaded.validate_structure(loaded.importances.len())?;
+        *self = loaded;
+        Ok(())
+    }
+
+    fn clone_model(&self) -> Result<Box<dyn MachineLearningModel>, AiError> {
+        Ok(Box::new(self.clone()))
+    }
+
+    fn apply_hyperparameters(&mut self, params: &Hyperparameters) -> Result<(), AiError> {
+        if let Some(max_depth) = params.max_depth {
+            self.max_depth = max_depth;
+        }
+        if let Some(min_samples_leaf) = params.min_samples_leaf {
+            self.min_samples_leaf = min_samples_leaf.max(1);
+        }
+        Ok(())
+    }
+}
+
+impl DecisionTreeModel {
+    /// 模型文件中的类型标识
+    const KIND: &'static str = "decision_tree";
+
+    /// 检查树的特征数与 `feature_count` 一致、分裂特征下标和叶子输出维度有效
+    fn validate_structure(&self, feature_count: usize) -> Result<(), AiError> {
+        let mismatch = |reason: String| AiError::ArchitectureMismatch(format!("模型 {} 的文件中{}", self.name, reason));
+        if self.importances.len() != feature_count {
+            return Err(mismatch(format!("有 {} 个特征重要性，应为 {}", self.importances.len(), feature_count)));
+        }
+        match &self.root {
+            Some(root) => root.validate(feature_count, root.predict_len()).map_err(mismatch),
+            None => Ok(()),
+        }
+    }
+
+    /// 创建新的决策树模型
+    pub fn new(name: String) -> Self {
+        Self {
+            name,
+            max_depth: 8,
+            min_samples_leaf: 1,
+            max_features: None,
+            seed: 0,
+            root: None,
+            importances: Vec::new(),
+            training_r2: 0.0,
+        }
+    }
+
+    /// 设置最大深度
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// 设置每个叶子至少包含的样本数
+    pub fn with_min_samples_leaf(mut self, min_samples_leaf: usize) -> Self {
+        self.min_samples_leaf = min_samples_leaf.max(1);
+        self
+    }
+
+    /// 设置每次分裂随机考察的特征数
+    pub fn with_max_features(mut self, max_features: usize) -> Self {
+        self.max_features = Some(max_features.max(1));
+        self
+    }
+
+    /// 设置随机种子
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// 归一化的特征重要性（各特征带来的误差下降占比），未训练时为空
+    /// Normalized feature importances (share of the error reduction per feature); empty until trained
+    pub fn feature_importances(&self) -> &[f64] {
+        &self.importances
+    }
+
+    /// 与输入无关的解释（特征重要性），预测值为空
+    fn explanation(&self) -> ModelOutput {
+        ModelOutput::explained(
+            Vec::new(),
+            self.training_r2.clamp(0.0, 1.0),
+            "决策树，按特征重要性".to_string(),
+            contributions_of(self.importances.iter().copied()),
+        )
+    }
+
+    /// 计算预测值
+    fn predict_values(&self, features: &[f64]) -> Result<Vec<f64>, AiError> {
+        let root = self.root.as_ref().ok_or_else(|| AiError::PredictionError(format!("模型 {} 尚未训练", self.name)))?;
+        if features.len() != self.importances.len() {
+            return Err(AiError::PredictionError(format!(
+                "需要 {} 个特征，实际为 {}", self.importances.len(), features.len()
+            )));
+        }
+        Ok(root.predict(features).to_vec())
+    }
+
+    /// 在 `indices` 选出的样本（可重复）上生长整棵树，误差下降累加到未归一化的 `importances`
+    fn fit_indices(&mut self, data: &[TrainingDataPoint], indices: Vec<usize>, importances: &mut [f64]) {
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        self.root = Some(self.grow(data, indices, 0, &mut rng, importances));
+    }
+
+    /// 递归生长子树
+    fn grow(
+        &self,
+        data: &[TrainingDataPoint],
+        mut indices: Vec<usize>,
+        depth: usize,
+        rng: &mut StdRng,
+        importances: &mut [f64],
+    ) -> TreeNode {
+        let node = NodeStatistics::of(data, &indices);
+        let min_leaf = self.min_samples_leaf.max(1);
+        if depth >= self.max_depth || indices.len() < 2 * min_leaf || node.squared_error() <= f64::EPSILON {
+            return TreeNode::Leaf { values: node.means() };
+        }
+
+        let feature_count = importances.len();
+        let candidates: Vec<usize> = match self.max_features {
+            Some(count) if count < feature_count => rand::seq::index::sample(rng, feature_count, count).into_vec(),
+            _ => (0..feature_count).collect(),
+        };
+
+        // (误差, 特征, 分裂位置, 阈值)
+        let mut best: Option<(f64, usize, usize, f64)> = None;
+        for feature in candidates {
+            indices.sort_by(|&a, &b| data[a].input.features[feature].total_cmp(&data[b].input.features[feature]));
+            let mut left = NodeStatistics::empty(node.sums.len());
+            for position in 1..indices.len() {
+                left.add(&data[indices[position - 1]]);
+                let (previous, next) = (
+                    data[indices[position - 1]].input.features[feature],
+                    data[indices[position]].input.features[feature],
+                );
+                if position < min_leaf || indices.len() - position < min_leaf || previous == next {
+                    continue;
+                }
+                let error = left.squared_error() + node.minus(&left).squared_error();
+                if best.is_none_or(|(best_error, ..)| error < best_error) {
+                    best = Some((error, feature, position, previous + (next - previous) / 2.0));
+                }
+            }
+        }
+
+        match best {
+            Some((error, feature, position, threshold)) if node.squared_error() - error > f64::EPSILON => {
+                importances[feature] += node.squared_error() - error;
+                indices.sort_by(|&a, &b| data[a].input.features[feature].total_cmp(&data[b].input.features[feature]));
+                let right = indices.split_off(position);
+                TreeNode::Split {
+                    feature,
+                    threshold,
+                    left: Box::new(self.grow(data, indices, depth + 1, rng, importances)),
+                    right: Box::new(self.grow(data, right, depth + 1, rng, importances)),
+                }
+            }
+            _ => TreeNode::Leaf { values: node.means() },
+        }
+    }
+}
+
+/// 节点内样本的加权和，用于快速计算平方误差
+#[derive(Debug, Clone)]
+struct NodeStatistics {
+    weight: f64,
+    sums: Vec<f64>,
+    squares: Vec<f64>,
+}
+
+impl NodeStatistics {
+    fn empty(output_count: usize) -> Self {
+        Self { weight: 0.0, sums: vec![0.0; output_count], squares: vec![0.0; output_count] }
+    }
+
+    fn of(data: &[TrainingDataPoint], indices: &[usize]) -> Self {
+        let mut statistics = Self::empty(data[indices[0]].target.len());
+        for &index in indices {
+            statistics.add(&data[index]);
+        }
+        statistics
+    }
+
+    fn add(&mut self, point: &TrainingDataPoint) {
+        self.weight += point.weight;
+        for ((sum, square), target) in self.sums.iter_mut().zip(&mut self.squares).zip(&point.target) {
+            *sum += point.weight * target;
+            *square += point.weight * target * target;
+        }
+    }
+
+    fn minus(&self, other: &Self) -> Self {
+        Self {
+            weight: self.weight - other.weight,
+            sums: self.sums.iter().zip(&other.sums).map(|(a, b)| a - b).collect(),
+            squares: self.squares.iter().zip(&other.squares).map(|(a, b)| a - b).collect(),
+        }
+    }
+
+    /// 加权平方误差之和（对所有输出求和）
+    fn squared_error(&self) -> f64 {
+        if self.weight <= 0.0 {
+            return 0.0;
+        }
+        self.sums.iter().zip(&self.squares).map(|(sum, square)| (square - sum * sum / self.weight).max(0.0)).sum()
+    }
+
+    fn means(&self) -> Vec<f64> {
+        self.sums.iter().map(|sum| if self.weight > 0.0 { sum / self.weight } else { 0.0 }).collect()
+    }
+}
+
+/// 检查树模型的训练数据，返回 `(特征数, 输出数)`
+fn tree_dimensions(data: &[TrainingDataPoint]) -> Result<(usize, usize), AiError> {
+    let (feature_count, output_count) = LinearRegressionModel::dimensions(data)?;
+    if data.iter().any(|point| !point.weight.is_finite() || point.weight < 0.0) || data.iter().all(|point| point.weight == 0.0) {
+        return Err(AiError::DataError("样本权重必须为非负数且不能全为 0".to_string()));
+    }
+    Ok((feature_count, output_count))
+}
+
+/// 归一化为和为 1，全为 0 时保持不变
+fn normalized(values: Vec<f64>) -> Vec<f64> {
+    let total: f64 = values.iter().sum();
+    if total > 0.0 { values.iter().map(|value| value / total).collect() } else { values }
+}
+
+/// 随机森林回归模型
+/// Random Forest Model
+///
+/// 每棵树在有放回抽样的样本上训练，每次分裂随机考察一部分特征，预测取所有树的平均值。
+/// Each tree is trained on a bootstrap sample and considers a random subset of features at each split; the
+/// prediction averages all trees.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RandomForestModel {
+    /// 模型名称
+    pub name: String,
+    /// 树的数量
+    pub tree_count: usize,
+    /// 每棵树的最大深度
+    pub max_depth: usize,
+    /// 每个叶子至少包含的样本数
+    pub min_samples_leaf: usize,
+    /// 每次分裂随机考察的特征数，`None` 表示特征数的平方根
+    pub max_features: Option<usize>,
+    /// 随机种子，决定抽样和特征选择
+    pub seed: u64,
+    /// 训练好的树
+    trees: Vec<DecisionTreeModel>,
+    /// 归一化的特征重要性
+    importances: Vec<f64>,
+    /// 训练集上的 R²，用作预测置信度
+    training_r2: f64,
+}
+
+impl MachineLearningModel for RandomForestModel {
+    fn predict(&self, input: &ModelInput) -> Result<ModelOutput, AiError> {
+        Ok(ModelOutput { predictions: self.predict_values(&input.features)?, ..self.explanation() })
+    }
+
+    /// 各输入在 rayon 线程池中并行预测，解释只生成一次
+    fn predict_batch(&self, inputs: &[ModelInput]) -> Result<Vec<ModelOutput>, AiError> {
+        let explanation = self.explanation();
+        inputs.par_iter()
+            .map(|input| Ok(ModelOutput { predictions: self.predict_values(&input.features)?, ..explanation.clone() }))
+            .collect()
+    }
+
+    fn train(&mut self, data: &[TrainingDataPoint]) -> Result<(), AiError> {
+        let (feature_count, output_count) = tree_dimensions(data)?;
+        if self.tree_count == 0 {
+            return Err(AiError::ConfigurationError("随机森林至少需要一棵树".to_string()));
+        }
+        let max_features = self.max_features.unwrap_or_else(|| (feature_count as f64).sqrt().ceil() as usize).max(1);
+
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        let mut importances = vec![0.0; feature_count];
+        self.trees = (0..self.tree_count)
+            .map(|_| {
+                let sample: Vec<usize> = (0..data.len()).map(|_| rng.random_range(0..data.len())).collect();
+                let mut tree = DecisionTreeModel::new(format!("{}-tree", self.name))
+                    .with_max_depth(self.max_depth)
+                    .with_min_samples_leaf(self.min_samples_leaf)
+                    .with_max_features(max_features)
+                    .with_seed(rng.random());
+                let mut tree_importances = vec![0.0; feature_count];
+                tree.fit_indices(data, sample, &mut tree_importances);
+                tree.importances = normalized(tree_importances);
+                for (total, value) in importances.iter_mut().zip(&tree.importances) {
+                    *total += value;
+                }
+                tree
+            })
+            .collect();
+        self.importances = normalized(importances);
+        self.training_r2 = regression_statistics(data, output_count, |features| self.predict_values(features))?.1;
+        Ok(())
+    }
+
+    fn evaluate(&self, test_data: &[TrainingDataPoint]) -> Result<ModelMetrics, AiError> {
+        let output_count = self.trees.first().and_then(|tree| tree.root.as_ref()).map(|root| root.predict_len()).unwrap_or(0);
+        let (rmse, r2) = regression_statistics(test_data, output_count, |features| self.predict_values(features))?;
+        Ok(regression_metrics(rmse, r2))
+    }
+
+    fn get_name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn save(&self, path: &Path) -> Result<(), AiError> {
+        write_model_file(path, Self::KIND, &self.name, self)
+    }
+
+    fn load(&mut self, path: &Path) -> Result<(), AiError> {
+        let loaded: Self = read_model_file(path, Self::KIND, &self.name)?;
+        loaded.validate_structure(loaded.importances.len())?;
+        *self = loaded;
+        Ok(())
+    }
+
+    fn clone_model(&self) -> Result<Box<dyn MachineLearningModel>, AiError> {
+        Ok(Box::new(self.clone()))
+    }
+
+    fn apply_hyperparameters(&mut self, params: &Hyperparameters) -> Result<(), AiError> {
+        if let Some(max_depth) = params.max_depth {
+            self.max_depth = max_depth;
+        }
+        if let Some(min_samples_leaf) = params.min_samples_leaf {
+            self.min_samples_leaf = min_samples_leaf.max(1);
+        }
+        Ok(())
+    }
+}
+
+impl RandomForestModel {
+    /// 模型文件中的类型标识
+    const KIND: &'static str = "random_forest";
+
+    /// 检查每棵树的结构有效，且特征数和输出维度与整片森林一致
+    fn validate_structure(&self, feature_count: usize) -> Result<(), AiError> {
+        let output_count = self.trees.first().and_then(|tree| tree.root.as_ref()).map(|root| root.predict_len());
+        for tree in &self.trees {
+            tree.validate_structure(feature_count)?;
+            if tree.root.as_ref().map(|root| root.predict_len()) != output_count {
+                return Err(AiError::ArchitectureMismatch(format!("模型 {} 的文件中各棵树的输出维度不一致", self.name)));
+            }
+        }
+        Ok(())
+    }
+
+    /// 创建新的随机森林模型
+    pub fn new(name: String) -> Self {
+        Self {
+            name,
+            tree_count: 50,
+            max_depth: 8,
+            min_samples_leaf: 1,
+            max_features: None,
+            seed: 0,
+            trees: Vec::new(),
+            importances: Vec::new(),
+            training_r2: 0.0,
+        }
+    }
+
+    /// 设置树的数量
+    pub fn with_tree_count(mut self, tree_count: usize) -> Self {
+        self.tree_count = tree_count;
+        self
+    }
+
+    /// 设置每棵树的最大深度
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// 设置每个叶子至少包含的样本数
+    pub fn with_min_samples_leaf(mut self, min_samples_leaf: usize) -> Self {
+        self.min_samples_leaf = min_samples_leaf.max(1);
+        self
+    }
+
+    /// 设置每次分裂随机考察的特征数
+    pub fn with_max_features(mut self, max_features: usize) -> Self {
+        self.max_features = Some(max_features.max(1));
+        self
+    }
+
+    /// 设置随机种子
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// 各棵树特征重要性的平均值，未训练时为空
+    /// Feature importances averaged over the trees; empty until trained
+    pub fn feature_importances(&self) -> &[f64] {
+        &self.importances
+    }
+
+    /// 与输入无关的解释（特征重要性），预测值为空
+    fn explanation(&self) -> ModelOutput {
+        ModelOutput::explained(
+            Vec::new(),
+            self.training_r2.clamp(0.0, 1.0),
+            format!("随机森林（{} 棵树），按特征重要性", self.trees.len()),
+            contributions_of(self.importances.iter().copied()),
+        )
+    }
+
+    /// 计算所有树预测值的平均值
+    fn predict_values(&self, features: &[f64]) -> Result<Vec<f64>, AiError> {
+        let (first, rest) = self.trees.split_first()
+            .ok_or_else(|| AiError::PredictionError(format!("模型 {} 尚未训练", self.name)))?;
+        let mut sums = first.predict_values(features)?;
+        for tree in rest {
+            for (sum, value) in sums.iter_mut().zip(tree.predict_values(features)?) {
+                *sum += value;
+            }
+        }
+        Ok(sums.iter().map(|sum| sum / self.trees.len() as f64).collect())
+    }
+}
+
+/// 性能优化策略
+/// Performance Optimization Strategy
+#[derive(Debug)]
+pub struct PerformanceOptimizationStrategy;
+
+#[allow(unused_variables)]
+impl AiOptimizationStrategy for PerformanceOptimizationStrategy {
+    fn optimize(&self, context: &OptimizationContext) -> Result<OptimizationResult, AiError> {
+        let recommendations = vec![
+            OptimizationRecommendation {
+                recommendation_type: RecommendationType::AlgorithmOptimization,
+                description: "优化算法复杂度".to_string(),
+                expected_benefit: 0.25,
+                implementation_cost: ImplementationCost::Medium,
+                time_horizon: TimeHorizon::MediumTerm,
+                dependencies: Vec::new(),
+            },
+            OptimizationRecommendation {
+                recommendation_type: RecommendationType::ParameterTuning,
+                description: "调整系统参数".to_string(),
+                expected_benefit: 0.15,
+                implementation_cost: ImplementationCost::Low,
+                time_horizon: TimeHorizon::ShortTerm,
+                dependencies: Vec::new(),
+            },
+        ];
+
+        Ok(OptimizationResult {
+            strategy_name: "Performance Optimization".to_string(),
+            recommendations,
+            expected_improvement: 0.40,
+            confidence: 0.85,
+            implementation_difficulty: ImplementationDifficulty::Medium,
+            risk_assessment: RiskAssessment {
+                risk_level: RiskLevel::Low,
+                risk_factors: Vec::new(),
+                mitigation_measures: vec!["渐进式部署".to_string()],
+                risk_probability: 0.1,
+                risk_impact: 0.2,
+            },
+        })
+    }
+
+    #[allow(unused_variables)]
+    fn get_name(&self) -> String {
+        "Performance Optimization".to_string()
+    }
+
+    fn get_priority(&self) -> OptimizationPriority {
+        OptimizationPriority::High
+    }
+
+    fn requires_training(&self) -> bool {
+        true
+    }
+}
+
+/// 成本优化策略
+/// Cost Optimization Strategy
+#[derive(Debug)]
+pub struct CostOptimizationStrategy;
+
+#[allow(unused_variables)]
+impl AiOptimizationStrategy for CostOptimizationStrategy {
+    fn optimize(&self, context: &OptimizationContext) -> Result<OptimizationResult, AiError> {
+        let recommendations = vec![
+            OptimizationRecommendation {
+                recommendation_type: RecommendationType::ResourceAllocation,
+                description: "优化资源分配".to_string(),
+                expected_benefit: 0.30,
+                implementation_cost: ImplementationCost::Low,
+                time_horizon: TimeHorizon::ShortTerm,
+                dependencies: Vec::new(),
+            },
+            OptimizationRecommendation {
+                recommendation_type: RecommendationType::CacheStrategy,
+                description: "优化缓存策略".to_string(),
+                expected_benefit: 0.20,
+                implementation_cost: ImplementationCost::Medium,
+                time_horizon: TimeHorizon::MediumTerm,
+                dependencies: Vec::new(),
+            },
+        ];
+
+        Ok(OptimizationResult {
+            strategy_name: "Cost Optimization".to_string(),
+            recommendations,
+            expected_improvement: 0.50,
+            confidence: 0.90,
+            implementation_difficulty: ImplementationDifficulty::Easy,
+            risk_assessment: RiskAssessment {
+                risk_level: RiskLevel::Low,
+                risk_factors: Vec::new(),
+                mitigation_measures: vec!["监控资源使用".to_string()],
+                risk_probability: 0.05,
+                risk_impact: 0.1,
+            },
+        })
+    }
+
+    #[allow(unused_variables)]
+    fn get_name(&self) -> String {
+        "Cost Optimization".to_string()
+    }
+
+    fn get_priority(&self) -> OptimizationPriority {
+        OptimizationPriority::Medium
+    }
+
+    fn requires_training(&self) -> bool {
+        false
+    }
+}
+
+/// 内存使用量指标名（字节），与监控系统采集的指标名一致
+/// Memory usage metric name (bytes), matching the monitoring system
+pub const MEMORY_USAGE_METRIC: &str = "memory_usage";
+
+/// 内存优化策略
+/// Memory Optimization Strategy
+///
+/// 按工作负载的内存使用模式给出建议。当前和历史快照中 `memory_usage` 的峰值与
+/// `resource_constraints.memory_limit` 比较得到余量，越接近限制，预期改进和置信度越高。
+/// Recommends by memory usage pattern. The peak `memory_usage` from the current metrics and historical snapshots
+/// is compared with `resource_constraints.memory_limit`; the closer to the limit, the higher the expected
+/// improvement and confidence.
+#[derive(Debug)]
+pub struct MemoryOptimizationStrategy;
+
+impl AiOptimizationStrategy for MemoryOptimizationStrategy {
+    fn optimize(&self, context: &OptimizationContext) -> Result<OptimizationResult, AiError> {
+        let pressure = MemoryPressure::of(context);
+        let severity = pressure.severity();
+        let headroom = pressure.describe_headroom();
+        let scaled = |benefit: f64| benefit * (0.3 + 0.7 * severity);
+        let recommendation = |recommendation_type, description: String, benefit: f64, cost, time_horizon, dependencies: &[&str]| {
+            OptimizationRecommendation {
+                recommendation_type,
+                description,
+                expected_benefit: scaled(benefit),
+                implementation_cost: cost,
+                time_horizon,
+                dependencies: dependencies.iter().map(|dependency| dependency.to_string()).collect(),
+            }
+        };
+
+        let (recommendations, base_improvement, implementation_difficulty) = match context.workload_characteristics.memory_usage_pattern {
+            MemoryUsagePattern::Growing => {
+                let exhaustion = pressure.hours_until_limit()
+                    .map(|hours| format!("，按当前增长速度约 {:.1} 小时后达到限制", hours))
+                    .unwrap_or_default();
+                let cache_reduction = match (pressure.peak, pressure.limit) {
+                    (Some(peak), Some(limit)) if peak > limit * MemoryPressure::TARGET_UTILIZATION => format!(
+                        "缩小缓存容量，至少释放 {} 使峰值回到限制的 {:.0}% 以内",
+                        mib(peak - limit * MemoryPressure::TARGET_UTILIZATION), MemoryPressure::TARGET_UTILIZATION * 100.0
+                    ),
+                    _ => format!("缩小缓存容量以减缓内存增长（{}）", headroom),
+                };
+                (vec![
+                    recommendation(
+                        RecommendationType::AlgorithmOptimization,
+                        format!("排查内存泄漏：用内存泄漏检测器定位长期未释放的分配（{}{}）", headroom, exhaustion),
+                        0.25,
+                        ImplementationCost::Medium,
+                        TimeHorizon::ShortTerm,
+                        &["security_advanced::MemoryMonitor::detect_memory_leaks"],
+                    ),
+                    recommendation(
+                        RecommendationType::CacheStrategy,
+                        cache_reduction,
+                        0.10,
+                        ImplementationCost::Low,
+                        TimeHorizon::ShortTerm,
+                        &[],
+                    ),
+                ], 0.35, ImplementationDifficulty::Medium)
+            }
+            MemoryUsagePattern::Volatile => {
+                let heap_limit = match (pressure.peak, pressure.limit) {
+                    (Some(peak), Some(limit)) => format!(
+                        "调整 MemoryLimits：把 max_heap_size 设为约 {}（峰值的 125%，不超过限制 {}）",
+                        mib((peak * 1.25).min(limit)), mib(limit)
+                    ),
+                    _ => format!("调整 MemoryLimits 中的 max_heap_size 以容纳分配峰值（{}）", headroom),
+                };
+                (vec![
+                    recommendation(
+                        RecommendationType::AlgorithmOptimization,
+                        format!("使用对象池或 arena 分配平滑分配峰值，减少频繁分配和释放（{}）", headroom),
+                        0.15,
+                        ImplementationCost::Medium,
+                        TimeHorizon::MediumTerm,
+                        &[],
+                    ),
+                    recommendation(
+                        RecommendationType::ParameterTuning,
+                        heap_limit,
+                        0.10,
+                        ImplementationCost::Low,
+                        TimeHorizon::ShortTerm,
+                        &["security_advanced::MemoryLimits"],
+                    ),
+                ], 0.25, ImplementationDifficulty::Medium)
+            }
+            MemoryUsagePattern::Cyclic => (vec![
+                recommendation(
+                    RecommendationType::ResourceAllocation,
+                    format!("在内存使用的低谷期定时压缩整理内存，回收周期峰值后的碎片（{}）", headroom),
+                    0.15,
+                    ImplementationCost::Low,
+                    TimeHorizon::ShortTerm,
+                    &[],
+                ),
+            ], 0.15, ImplementationDifficulty::Easy),
+            MemoryUsagePattern::Stable => (Vec::new(), 0.0, ImplementationDifficulty::Easy),
+        };
+
+        let growing = matches!(context.workload_characteristics.memory_usage_pattern, MemoryUsagePattern::Growing);
+        let risk_factors = if growing {
+            vec![RiskFactor {
+                risk_type: RiskType::Stability,
+                description: format!("内存持续增长，可能超出限制（{}）", headroom),
+                probability: 0.2 + 0.6 * severity,
+                impact: 0.8,
+            }]
+        } else {
+            Vec::new()
+        };
+
+        Ok(OptimizationResult {
+            strategy_name: self.get_name(),
+            recommendations,
+            expected_improvement: scaled(base_improvement),
+            // 缺少内存指标或限制时无法量化余量，置信度较低
+            confidence: if pressure.utilization().is_some() { 0.5 + 0.4 * severity } else { 0.3 },
+            implementation_difficulty,
+            risk_assessment: RiskAssessment {
+                risk_level: if growing { RiskLevel::Medium } else { RiskLevel::Low },
+                risk_factors,
+                mitigation_measures: vec!["先在预发布环境验证内存变化".to_string()],
+                risk_probability: if growing { 0.2 + 0.6 * severity } else { 0.1 },
+                risk_impact: if growing { 0.8 } else { 0.2 },
+            },
+        })
+    }
+
+    fn get_name(&self) -> String {
+        "Memory Optimization".to_string()
+    }
+
+    fn get_priority(&self) -> OptimizationPriority {
+        OptimizationPriority::Medium
+    }
+
+    fn requires_training(&self) -> bool {
+        false
+    }
+}
+
+/// 当前内存使用与资源约束的对比
+struct MemoryPressure {
+    /// 当前和历史快照中的峰值（字节）
+    peak: Option<f64>,
+    /// 内存限制（字节），0 视为未设置
+    limit: Option<f64>,
+    /// 按历史快照估计的每小时增长量（字节）
+    growth_per_hour: Option<f64>,
+}
+
+impl MemoryPressure {
+    /// 建议把峰值控制在限制的这个比例以内
+    const TARGET_UTILIZATION: f64 = 0.7;
+
+    fn of(context: &OptimizationContext) -> Self {
+        let current = context.current_metrics.get(MEMORY_USAGE_METRIC).copied();
+        let mut history: Vec<(DateTime<Utc>, f64)> = context.historical_data.iter()
+            .filter_map(|snapshot| Some((snapshot.timestamp, *snapshot.metrics.get(MEMORY_USAGE_METRIC)?)))
+            .collect();
+        history.sort_by_key(|(timestamp, _)| *timestamp);
+
+        let growth_per_hour = match (history.first(), history.last()) {
+            (Some((start, first)), Some((end, last))) if end > start => hourly_rate(*start, *first, *end, *last),
+            _ => None,
+        };
+        let peak = history.iter().map(|(_, usage)| *usage).chain(current).reduce(f64::max);
+        let limit = Some(context.resource_constraints.memory_limit as f64).filter(|limit| *limit > 0.0);
+        Self { peak, limit, growth_per_hour }
+    }
+
+    /// 峰值占限制的比例
+    fn utilization(&self) -> Option<f64> {
+        Some(self.peak? / self.limit?)
+    }
+
+    /// 压力程度：使用率不超过 50% 时为 0，达到限制时为 1
+    fn severity(&self) -> f64 {
+        self.utilization().map(|utilization| ((utilization - 0.5) / 0.5).clamp(0.0, 1.0)).unwrap_or(0.0)
+    }
+
+    /// 以当前增长速度达到限制还需要的小时数
+    fn hours_until_limit(&self) -> Option<f64> {
+        let growth = self.growth_per_hour.filter(|growth| *growth > 0.0)?;
+        Some(((self.limit? - self.peak?) / growth).max(0.0))
+    }
+
+    fn describe_headroom(&self) -> String {
+        match (self.peak, self.limit) {
+            (Some(peak), Some(limit)) if peak > limit => format!(
+                "峰值 {} 已超出内存限制 {}（{:.0}%）", mib(peak), mib(limit), peak / limit * 100.0
+            ),
+            (Some(peak), Some(limit)) => format!(
+                "峰值 {}，占内存限制 {} 的 {:.0}%，余量 {}", mib(peak), mib(limit), peak / limit * 100.0, mib(limit - peak)
+            ),
+            (None, Some(limit)) => format!("内存限制 {}，缺少 {} 指标", mib(limit), MEMORY_USAGE_METRIC),
+            (_, None) => "未设置内存限制".to_string(),
+        }
+    }
+}
+
+/// 以 MiB 显示字节数
+fn mib(bytes: f64) -> String {
+    format!("{:.1} MiB", bytes / (1024.0 * 1024.0))
+}
+
+/// 估计趋势时时间跨度的下限（小时），间隔极短的快照不会得出无穷大的速率
+const MIN_TREND_HOURS: f64 = 1.0 / 60.0;
+
+/// 两次观测之间每小时的变化量；时间跨度按毫秒折算为小数小时，不低于 [`MIN_TREND_HOURS`]
+fn hourly_rate(start: DateTime<Utc>, first: f64, end: DateTime<Utc>, last: f64) -> Option<f64> {
+    let hours = ((end - start).num_milliseconds() as f64 / 3_600_000.0).max(MIN_TREND_HOURS);
+    Some((last - first) / hours).filter(|rate| rate.is_finite())
+}
+
+/// 第 95 百分位延迟指标名（毫秒）
+/// p95 latency metric name (milliseconds)
+pub const LATENCY_P95_METRIC: &str = "latency_p95_ms";
+
+/// 第 99 百分位延迟指标名（毫秒）
+/// p99 latency metric name (milliseconds)
+pub const LATENCY_P99_METRIC: &str = "latency_p99_ms";
+
+/// 延迟优化策略
+/// Latency Optimization Strategy
+///
+/// 只在存在延迟目标时生效。取 p99（缺失时取 p95）与目标值比较，每条建议的预期收益按需要削减的
+/// 延迟比例计算；结果的预期改进再按延迟目标在所有目标中的权重占比加权。
+/// Only applies when a latency goal is present. The p99 latency (p95 when p99 is missing) is compared with the
+/// goal's target; each recommendation's benefit follows the fraction of latency that has to be cut, and the
+/// overall improvement is weighted by the latency goals' share of all goal weights.
+#[derive(Debug)]
+pub struct LatencyOptimizationStrategy;
+
+impl AiOptimizationStrategy for LatencyOptimizationStrategy {
+    fn optimize(&self, context: &OptimizationContext) -> Result<OptimizationResult, AiError> {
+        let (target, goal_share) = latency_goal(context)
+            .ok_or_else(|| AiError::ConfigurationError("上下文中没有延迟优化目标".to_string()))?;
+        let tail = TailLatency::of(context);
+        let observed = tail.observed();
+        // 需要削减的延迟比例
+        let gap = observed.map(|latency| ((latency - target) / latency).clamp(0.0, 1.0)).unwrap_or(0.0);
+        let summary = match observed {
+            Some(latency) => format!("{} {:.1} ms，目标 {:.1} ms{}", tail.metric_name(), latency, target, tail.describe_trend()),
+            None => format!("缺少 {}/{} 指标，目标 {:.1} ms", LATENCY_P99_METRIC, LATENCY_P95_METRIC, target),
+        };
+
+        let workload = &context.workload_characteristics;
+        let mut recommendations = Vec::new();
+        if gap > 0.0 {
+            let repeated_reads = matches!(workload.data_access_pattern, DataAccessPattern::Hotspot | DataAccessPattern::Locality)
+                || matches!(workload.request_pattern, RequestPattern::Periodic | RequestPattern::Trending);
+            if repeated_reads {
+                recommendations.push(OptimizationRecommendation {
+                    recommendation_type: RecommendationType::CacheStrategy,
+                    description: format!("为读多写少的路由启用网关响应缓存（设置 cache_ttl），让重复请求不再访问上游（{}）", summary),
+                    expected_benefit: 0.5 * gap,
+                    implementation_cost: ImplementationCost::Low,
+                    time_horizon: TimeHorizon::ShortTerm,
+                    dependencies: vec!["api_gateway::Route::cache_ttl".to_string()],
+                });
+            }
+            if matches!(workload.computational_complexity, ComputationalComplexity::High | ComputationalComplexity::VeryHigh) {
+                recommendations.push(OptimizationRecommendation {
+                    recommendation_type: RecommendationType::AlgorithmOptimization,
+                    description: format!("启用 SIMD 指令和尾调用优化，缩短计算密集路径的执行时间（{}）", summary),
+                    expected_benefit: 0.3 * gap,
+                    implementation_cost: ImplementationCost::Medium,
+                    time_horizon: TimeHorizon::MediumTerm,
+                    dependencies: vec![
+                        "webassembly_2_0::WebAssembly2Features::SimdInstructions".to_string(),
+                        "webassembly_2_0::WebAssembly2Features::TailCallOptimization".to_string(),
+                    ],
+                });
+            }
+            if matches!(workload.concurrency_level, ConcurrencyLevel::VeryHigh) {
+                recommendations.push(OptimizationRecommendation {
+                    recommendation_type: RecommendationType::LoadBalancing,
+                    description: format!("复用上游连接池并改用最少连接负载均衡，避免请求排在繁忙实例后面（{}）", summary),
+                    expected_benefit: 0.3 * gap,
+                    implementation_cost: ImplementationCost::Low,
+                    time_horizon: TimeHorizon::ShortTerm,
+                    dependencies: vec!["api_gateway::LoadBalancingStrategy::LeastConnections".to_string()],
+                });
+            }
+        }
+
+        // 各建议的收益按独立作用合并
+        let combined = 1.0 - recommendations.iter().map(|r| 1.0 - r.expected_benefit).product::<f64>();
+        let confidence = match (observed, tail.trend_per_hour) {
+            (Some(_), Some(_)) => 0.75,
+            (Some(_), None) => 0.6,
+            (None, _) => 0.3,
+        };
+
+        Ok(OptimizationResult {
+            strategy_name: self.get_name(),
+            recommendations,
+            expected_improvement: combined * goal_share,
+            confidence,
+            implementation_difficulty: ImplementationDifficulty::Medium,
+            risk_assessment: RiskAssessment {
+                risk_level: RiskLevel::Low,
+                risk_factors: Vec::new(),
+                mitigation_measures: vec!["逐条启用并对比尾延迟".to_string()],
+                risk_probability: 0.1,
+                risk_impact: 0.2,
+            },
+        })
+    }
+
+    fn get_name(&self) -> String {
+        "Latency Optimization".to_string()
+    }
+
+    fn get_priority(&self) -> OptimizationPriority {
+        OptimizationPriority::High
+    }
+
+    fn requires_training(&self) -> bool {
+        false
+    }
+
+    fn is_applicable(&self, context: &OptimizationContext) -> bool {
+        latency_goal(context).is_some()
+    }
+}
+
+/// 返回最严格的延迟目标值和所有延迟目标在全部目标中的权重占比
+fn latency_goal(context: &OptimizationContext) -> Option<(f64, f64)> {
+    let latency_goals: Vec<&OptimizationGoal> = context.optimization_goals.iter()
+        .filter(|goal| matches!(goal.goal_type, OptimizationGoalType::Latency))
+        .collect();
+    let target = latency_goals.iter().map(|goal| goal.target_value).reduce(f64::min)?;
+    let latency_weight: f64 = latency_goals.iter().map(|goal| goal.weight.max(0.0)).sum();
+    let total_weight: f64 = context.optimization_goals.iter().map(|goal| goal.weight.max(0.0)).sum();
+    let share = if total_weight > 0.0 { latency_weight / total_weight } else { 1.0 };
+    Some((target, share))
+}
+
+/// 当前尾延迟和历史趋势
+struct TailLatency {
+    /// 使用的指标名和当前值
+    current: Option<(&'static str, f64)>,
+    /// 按历史快照估计的每小时变化量（毫秒）
+    trend_per_hour: Option<f64>,
+}
+
+impl TailLatency {
+    fn of(context: &OptimizationContext) -> Self {
+        let pick = |metrics: &HashMap<String, f64>| {
+            [LATENCY_P99_METRIC, LATENCY_P95_METRIC].into_iter()
+                .find_map(|name| metrics.get(name).map(|value| (name, *value)))
+        };
+        let mut history: Vec<(DateTime<Utc>, &'static str, f64)> = context.historical_data.iter()
+            .filter_map(|snapshot| pick(&snapshot.metrics).map(|(name, value)| (snapshot.timestamp, name, value)))
+            .collect();
+        history.sort_by_key(|(timestamp, ..)| *timestamp);
+
+        // 当前指标缺失时使用最近一次快照
+        let current = pick(&context.current_metrics).or_else(|| history.last().map(|(_, name, value)| (*name, *value)));
+        let trend_per_hour = current.and_then(|(metric, _)| {
+            let same_metric: Vec<&(DateTime<Utc>, &'static str, f64)> = history.iter().filter(|(_, name, _)| *name == metric).collect();
+            match (same_metric.first(), same_metric.last()) {
+                (Some((start, _, first)), Some((end, _, last))) if end > start => hourly_rate(*start, *first, *end, *last),
+                _ => None,
+            }
+        });
+        Self { current, trend_per_hour }
+    }
+
+    fn observed(&self) -> Option<f64> {
+        self.current.map(|(_, value)| value)
+    }
+
+    fn metric_name(&self) -> &'static str {
+        self.current.map(|(name, _)| name).unwrap_or(LATENCY_P99_METRIC)
+    }
+
+    fn describe_trend(&self) -> String {
+        match self.trend_per_hour {
+            Some(trend) if trend > 0.0 => format!("，近期每小时上升 {:.1} ms", trend),
+            Some(trend) if trend < 0.0 => format!("，近期每小时下降 {:.1} ms", -trend),
+            _ => String::new(),
+        }
+    }
+}
+
+impl PerformanceSnapshot {
+    /// 由监控系统的性能指标构造快照
+    ///
+    /// 同名指标取时间戳最新的值，快照时间为所有指标中最新的时间戳（秒）。
+    pub fn from_metrics(metrics: &[PerformanceMetric], workload: WorkloadCharacteristics) -> Self {
+        let mut latest: HashMap<&str, &PerformanceMetric> = HashMap::new();
+        for metric in metrics {
+            let entry = latest.entry(metric.name.as_str()).or_insert(metric);
+            if metric.timestamp >= entry.timestamp {
+                *entry = metric;
+            }
+        }
+        let timestamp = metrics.iter().map(|metric| metric.timestamp).max()
+            .and_then(|secs| DateTime::from_timestamp(i64::try_from(secs).ok()?, 0))
+            .unwrap_or_else(Utc::now);
+        Self {
+            timestamp,
+            metrics: latest.into_iter().map(|(name, metric)| (name.to_string(), metric.value)).collect(),
+            configuration: HashMap::new(),
+            workload,
+        }
+    }
+}
+
+/// 快照特征提取器
+/// Snapshot Feature Extractor
+///
+/// 决定快照中哪些指标作为特征、哪些作为目标，顺序即向量中的顺序。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotFeatureExtractor {
+    /// 作为特征的指标名
+    pub feature_metrics: Vec<String>,
+    /// 作为目标的指标名
+    pub target_metrics: Vec<String>,
+}
+
+impl SnapshotFeatureExtractor {
+    /// 创建提取器
+    pub fn new(feature_metrics: Vec<String>, target_metrics: Vec<String>) -> Self {
+        Self { feature_metrics, target_metrics }
+    }
+
+    /// 把快照转换为训练数据点，快照的配置参数作为输入元数据
+    pub fn extract(&self, snapshot: &PerformanceSnapshot) -> Result<TrainingDataPoint, AiError> {
+        let values = |names: &[String]| -> Result<Vec<f64>, AiError> {
+            names.iter().map(|name| {
+                snapshot.metrics.get(name).copied()
+                    .ok_or_else(|| AiError::DataError(format!("快照缺少指标 {}", name)))
+            }).collect()
+        };
+        Ok(TrainingDataPoint {
+            input: ModelInput { features: values(&self.feature_metrics)?, metadata: snapshot.configuration.clone() },
+            target: values(&self.target_metrics)?,
+            weight: 1.0,
+            timestamp: snapshot.timestamp,
+        })
+    }
+}
+
+/// 在线学习配置
+/// Online Learning Configuration
+#[derive(Debug, Clone)]
+pub struct OnlineLearningConfig {
+    /// 累积多少个新数据点后重新训练，0 表示不按数量触发
+    pub retrain_after: usize,
+    /// 用于检测漂移的模型，`None` 表示不检测漂移
+    pub drift_model: Option<String>,
+    /// 最近数据点的平均预测误差超过该值时视为漂移
+    pub drift_threshold: f64,
+    /// 计算平均预测误差的数据点个数
+    pub drift_window: usize,
+}
+
+impl Default for OnlineLearningConfig {
+    fn default() -> Self {
+        Self { retrain_after: 100, drift_model: None, drift_threshold: 1.0, drift_window: 20 }
+    }
+}
+
+/// 触发重新训练的原因
+/// Retrain Trigger
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RetrainTrigger {
+    /// 新数据点达到阈值
+    NewData,
+    /// 预测误差漂移
+    Drift,
+}
+
+/// 在线学习统计
+/// Online Learning Statistics
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OnlineLearningStats {
+    /// 已接收的数据点
+    pub ingested: u64,
+    /// 因缺少指标被丢弃的快照
+    pub rejected: u64,
+    /// 因新数据触发的重新训练次数
+    pub data_triggers: u64,
+    /// 因漂移触发的重新训练次数
+    pub drift_triggers: u64,
+    /// 已完成的重新训练次数
+    pub retrains_completed: u64,
+    /// 失败的重新训练次数
+    pub retrains_failed: u64,
+    /// 最近一次重新训练的错误
+    pub last_error: Option<String>,
+}
+
+#[derive(Debug, Default)]
+struct OnlineLearningState {
+    stats: OnlineLearningStats,
+    /// 上次触发后新增的数据点
+    pending: usize,
+    /// 最近数据点的预测误差
+    recent_errors: std::collections::VecDeque<f64>,
+    retraining: bool,
+}
+
+/// 在线学习桥接器
+/// Online Learning Bridge
+///
+/// 把运行中系统的性能快照转换为训练数据，并在新数据足够多或预测误差漂移时在后台线程重新训练。
+/// 接收快照只会短暂持有训练数据的锁，引擎正在训练时跳过漂移检测，不会等待训练完成。
+pub struct OnlineLearningBridge {
+    engine: Arc<Mutex<AiOptimizationEngine>>,
+    training_data: Arc<Mutex<Vec<TrainingDataPoint>>>,
+    model_states: Arc<Mutex<HashMap<String, ModelState>>>,
+    retention: chrono::Duration,
+    extractor: SnapshotFeatureExtractor,
+    config: OnlineLearningConfig,
+    state: Arc<Mutex<OnlineLearningState>>,
+    worker: Mutex<Option<std::thread::JoinHandle<()>>>,
+}
+
+impl OnlineLearningBridge {
+    /// 创建桥接器，数据保留时间取自引擎配置，引擎的特征名取自提取器
+    pub fn new(
+        engine: Arc<Mutex<AiOptimizationEngine>>,
+        extractor: SnapshotFeatureExtractor,
+        config: OnlineLearningConfig,
+    ) -> Self {
+        let (training_data, model_states, retention) = {
+            let mut engine = engine.lock().unwrap_or_else(PoisonError::into_inner);
+            engine.feature_names = extractor.feature_metrics.clone();
+            (
+                Arc::clone(&engine.training_data),
+                Arc::clone(&engine.model_states),
+                chrono::Duration::from_std(engine.config.data_retention_period).unwrap_or(chrono::Duration::MAX),
+            )
+        };
+        Self {
+            engine,
+            training_data,
+            model_states,
+            retention,
+            extractor,
+            config,
+            state: Arc::new(Mutex::new(OnlineLearningState::default())),
+            worker: Mutex::new(None),
+        }
+    }
+
+    /// 接收一个快照
+    ///
+    /// 返回本次触发的重新训练原因；已有训练在进行时不会再次触发，累积的数据会在下一个快照时继续计数。
+    pub fn ingest(&self, snapshot: &PerformanceSnapshot) -> Result<Option<RetrainTrigger>, AiError> {
+        let point = match self.extractor.extract(snapshot) {
+            Ok(point) => point,
+            Err(e) => {
+                self.state.lock().unwrap_or_else(PoisonError::into_inner).stats.rejected += 1;
+                return Err(e);
+            }
+        };
+        let error = self.prediction_error(&point);
+        {
+            // 保留窗口以当前时间为准，时间戳错误的快照不会把已有数据挤出窗口
+            let mut training_data = self.training_data.lock().unwrap_or_else(PoisonError::into_inner);
+            let cutoff = Utc::now().checked_sub_signed(self.retention).unwrap_or(DateTime::<Utc>::MIN_UTC);
+            training_data.push(point);
+            training_data.retain(|data| data.timestamp > cutoff);
+        }
+
+        let trigger = {
+            let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+            state.stats.ingested += 1;
+            state.pending += 1;
+            if let Some(error) = error {
+                state.recent_errors.push_back(error);
+                while state.recent_errors.len() > self.config.drift_window {
+                    state.recent_errors.pop_front();
+                }
+            }
+            let drifted = self.config.drift_window > 0
+                && state.recent_errors.len() == self.config.drift_window
+                && state.recent_errors.iter().sum::<f64>() / self.config.drift_window as f64 > self.config.drift_threshold;
+            let trigger = if drifted {
+                Some(RetrainTrigger::Drift)
+            } else if self.config.retrain_after > 0 && state.pending >= self.config.retrain_after {
+                Some(RetrainTrigger::NewData)
+            } else {
+                None
+            };
+            match trigger {
+                Some(trigger) if !state.retraining => {
+                    state.retraining = true;
+                    state.pending = 0;
+                    state.recent_errors.clear();
+                    match trigger {
+                        RetrainTrigger::NewData => state.stats.data_triggers += 1,
+                        RetrainTrigger::Drift => state.stats.drift_triggers += 1,
+                    }
+                    Some(trigger)
+                }
+                _ => None,
+            }
+        };
+        if trigger.is_some() {
+            self.spawn_retraining();
+        }
+        Ok(trigger)
+    }
+
+    /// 接收监控系统的一组性能指标
+    pub fn ingest_metrics(
+        &self,
+        metrics: &[PerformanceMetric],
+        workload: WorkloadCharacteristics,
+    ) -> Result<Option<RetrainTrigger>, AiError> {
+        self.ingest(&PerformanceSnapshot::from_metrics(metrics, workload))
+    }
+
+    /// 依次接收快照流（例如 `mpsc::Receiver`），缺少指标的快照被跳过，返回触发的重新训练
+    pub fn consume<I>(&self, snapshots: I) -> Vec<RetrainTrigger>
+    where
+        I: IntoIterator<Item = PerformanceSnapshot>,
+    {
+        snapshots.into_iter().filter_map(|snapshot| self.ingest(&snapshot).ok().flatten()).collect()
+    }
+
+    /// 是否正在重新训练
+    pub fn is_retraining(&self) -> bool {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner).retraining
+    }
+
+    /// 等待当前的重新训练结束
+    pub fn wait_for_retraining(&self) {
+        let worker = self.worker.lock().unwrap_or_else(PoisonError::into_inner).take();
+        if let Some(worker) = worker {
+            let _ = worker.join();
+        }
+    }
+
+    /// 统计信息
+    pub fn stats(&self) -> OnlineLearningStats {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner).stats.clone()
+    }
+
+    /// 模型的训练状态，重新训练期间也不需要等待引擎
+    pub fn model_states(&self) -> HashMap<String, ModelState> {
+        self.model_states.lock().unwrap_or_else(PoisonError::into_inner).clone()
+    }
+
+    /// 共享的引擎
+    pub fn engine(&self) -> Arc<Mutex<AiOptimizationEngine>> {
+        Arc::clone(&self.engine)
+    }
+
+    /// 漂移模型对数据点的平均绝对误差；引擎忙或模型无法预测时返回 `None`
+    fn prediction_error(&self, point: &TrainingDataPoint) -> Option<f64> {
+        let model_name = self.config.drift_model.as_deref()?;
+        let output = {
+            let engine = match self.engine.try_lock() {
+                Ok(engine) => engine,
+                Err(TryLockError::Poisoned(poisoned)) => poisoned.into_inner(),
+                Err(TryLockError::WouldBlock) => return None,
+            };
+            engine.predict(model_name, &point.input).ok()?
+        };
+        if output.predictions.len() != point.target.len() || point.target.is_empty() {
+            return None;
+        }
+        let total: f64 = output.predictions.iter().zip(&point.target).map(|(p, t)| (p - t).abs()).sum();
+        Some(total / point.target.len() as f64)
+    }
+
+    fn spawn_retraining(&self) {
+        // 上一次训练已经结束（`retraining` 为假才会触发），回收它的线程
+        self.wait_for_retraining();
+        let engine = Arc::clone(&self.engine);
+        let guard = RetrainingGuard { state: Arc::clone(&self.state), finished: false };
+        let worker = std::thread::spawn(move || {
+            let mut guard = guard;
+            let result = engine.lock().unwrap_or_else(PoisonError::into_inner).train_models();
+            guard.finish(result.map(|_| ()));
+        });
+        *self.worker.lock().unwrap_or_else(PoisonError::into_inner) = Some(worker);
+    }
+}
+
+/// 重新训练线程持有的守卫：训练结束时记录结果；训练 panic 时在释放过程中记为失败，总会清除 `retraining` 标记
+struct RetrainingGuard {
+    state: Arc<Mutex<OnlineLearningState>>,
+    finished: bool,
+}
+
+impl RetrainingGuard {
+    fn finish(&mut self, result: Result<(), AiError>) {
+        self.finished = true;
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        match result {
+            Ok(()) => {
+                state.stats.retrains_completed += 1;
+                state.stats.last_error = None;
+            }
+            Err(e) => {
+                log::warn!("在线重新训练失败: {:?}", e);
+                state.stats.retrains_failed += 1;
+                state.stats.last_error = Some(e.to_string());
+            }
+        }
+        state.retraining = false;
+    }
+}
+
+impl Drop for RetrainingGuard {
+    fn drop(&mut self) {
+        if !self.finished {
+            self.finish(Err(AiError::TrainingError("重新训练线程 panic".to_string())));
+        }
+    }
+}
+
+/// 错误类型定义
+/// Error Type Definitions
+
+#[derive(Debug, Error)]
+pub enum AiError {
+    /// 模型未找到
+    #[error("模型未找到: {0}")]
+    ModelNotFound(String),
+    /// 训练错误
+    #[error("训练错误: {0}")]
+    TrainingError(String),
+    /// 预测错误
+    #[error("预测错误: {0}")]
+    PredictionError(String),
+    /// 配置错误
+    #[error("配置错误: {0}")]
+    ConfigurationError(String),
+    /// 数据错误
+    #[error("数据错误: {0}")]
+    DataError(String),
+    /// 模型不支持该操作
+    #[error("不支持的操作: {0}")]
+    NotSupported(String),
+    /// 模型保存或加载失败
+    #[error("持久化错误: {0}")]
+    PersistenceError(String),
+    /// 模型文件的结构与声明的结构不一致
+    #[error("模型结构不匹配: {0}")]
+    ArchitectureMismatch(String),
+    /// 模型文件中的参数与模型结构不一致
+    #[error("模型状态无效: {0}")]
+    InvalidState(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 确定性的伪随机噪声，范围约为 [-amplitude, amplitude]
+    fn noise(seed: usize, amplitude: f64) -> f64 {
+        ((seed as f64 * 12.9898).sin() * 43758.5453).fract() * amplitude
+    }
+
+    fn point(features: Vec<f64>, target: Vec<f64>) -> TrainingDataPoint {
+        TrainingDataPoint {
+            input: ModelInput { features, metadata: HashMap::new() },
+            target,
+            weight: 1.0,
+            timestamp: Utc::now(),
+        }
+    }
+
+    /// y = 2x₁ − 3x₂ + 1 加噪声
+    fn plane(range: std::ops::Range<usize>, amplitude: f64) -> Vec<TrainingDataPoint> {
+        range.map(|i| {
+            let (x1, x2) = ((i % 17) as f64 * 0.5, (i % 11) as f64 * 0.7 - 3.0);
+            point(vec![x1, x2], vec![2.0 * x1 - 3.0 * x2 + 1.0 + noise(i, amplitude)])
+        }).collect()
+    }
+
+    fn assert_plane_fitted(model: &LinearRegressionModel) {
+        let coefficients = model.coefficients();
+        assert_eq!(coefficients.len(), 1);
+        assert!((coefficients[0][0] - 2.0).abs() < 0.02, "{:?}", coefficients);
+        assert!((coefficients[0][1] + 3.0).abs() < 0.02, "{:?}", coefficients);
+        assert!((model.intercepts()[0] - 1.0).abs() < 0.05, "{:?}", model.intercepts());
+
+        let metrics = model.evaluate(&plane(200..260, 0.1)).unwrap();
+        assert!(metrics.loss < 0.1, "{:?}", metrics);
+        assert!(metrics.accuracy > 0.99, "{:?}", metrics);
+    }
+
+    #[test]
+    fn test_normal_equation_recovers_noisy_plane() {
+        let mut model = LinearRegressionModel::new("latency".to_string());
+        model.train(&plane(0..200, 0.1)).unwrap();
+
+        assert_eq!(model.solver(), Some(LinearSolver::NormalEquation));
+        assert_plane_fitted(&model);
+        let output = model.predict(&point(vec![1.0, 1.0], vec![]).input).unwrap();
+        assert!((output.predictions[0] - 0.0).abs() < 0.05);
+        assert!(output.explanation.unwrap().contains("+2.0"));
+    }
+
+    #[test]
+    fn test_gradient_descent_fallback_matches_closed_form() {
+        let mut model = LinearRegressionModel::new("latency".to_string()).with_normal_equation_max_features(1);
+        model.train(&plane(0..200, 0.1)).unwrap();
+
+        assert_eq!(model.solver(), Some(LinearSolver::GradientDescent));
+        assert_plane_fitted(&model);
+    }
+
+    #[test]
+    fn test_multi_output_and_collinear_features() {
+        // 第二个特征是第一个的两倍，普通最小二乘矩阵奇异，回退到梯度下降
+        let data: Vec<_> = (0..50).map(|i| {
+            let x = i as f64 / 10.0;
+            point(vec![x, 2.0 * x], vec![x + 1.0, -x])
+        }).collect();
+        let mut model = LinearRegressionModel::new("multi".to_string());
+        model.train(&data).unwrap();
+        assert_eq!(model.solver(), Some(LinearSolver::GradientDescent));
+        assert_eq!(model.coefficients().len(), 2);
+        assert!(model.evaluate(&data).unwrap().loss < 1e-3);
+
+        // 岭回归让矩阵可逆，并收缩系数
+        let mut ridge = LinearRegressionModel::new("multi".to_string()).with_ridge(1.0);
+        ridge.train(&data).unwrap();
+        assert_eq!(ridge.solver(), Some(LinearSolver::NormalEquation));
+        let prediction = ridge.predict(&point(vec![1.0, 2.0], vec![]).input).unwrap().predictions;
+        assert!((prediction[0] - 2.0).abs() < 0.1 && (prediction[1] + 1.0).abs() < 0.1, "{:?}", prediction);
+    }
+
+    #[test]
+    fn test_invalid_inputs_are_rejected() {
+        let mut model = LinearRegressionModel::new("empty".to_string());
+        assert!(matches!(model.predict(&point(vec![1.0], vec![]).input), Err(AiError::PredictionError(_))));
+        assert!(matches!(model.train(&[]), Err(AiError::DataError(_))));
+        assert!(matches!(
+            model.train(&[point(vec![1.0], vec![1.0]), point(vec![1.0, 2.0], vec![1.0])]),
+            Err(AiError::DataError(_))
+        ));
+
+        model.train(&plane(0..20, 0.0)).unwrap();
+        assert!(matches!(model.predict(&point(vec![1.0], vec![]).input), Err(AiError::PredictionError(_))));
+    }
+
+    fn engine(save_path: &Path) -> AiOptimizationEngine {
+        AiOptimizationEngine::new(AiOptimizationConfig {
+            enabled: true,
+            learning_rate: 0.01,
+            batch_size: 32,
+            max_epochs: 100,
+            early_stopping_patience: 10,
+            model_save_path: save_path.to_string_lossy().into_owned(),
+            data_retention_period: Duration::from_secs(3600),
+            split_seed: 7,
+            validation_split: 0.2,
+            feature_scaling: None,
+        })
+    }
+
+    fn layers(sizes: &[usize]) -> Vec<NeuralLayer> {
+        sizes.iter()
+            .map(|&neuron_count| NeuralLayer { neuron_count, activation_function: ActivationFunction::Tanh })
+            .collect()
+    }
+
+    fn trained_network() -> NeuralNetworkModel {
+        let mut network = NeuralNetworkModel::new("network".to_string(), layers(&[3, 1])).with_seed(3);
+        network.train(&plane(0..20, 0.0)).unwrap();
+        network
+    }
+
+    fn bits(output: ModelOutput) -> Vec<u64> {
+        output.predictions.iter().map(|value| value.to_bits()).collect()
+    }
+
+    #[test]
+    fn test_saved_models_reload_with_identical_predictions() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("models");
+        let mut linear = LinearRegressionModel::new("linear".to_string());
+        linear.train(&plane(0..100, 0.1)).unwrap();
+        let network = trained_network();
+        let inputs: Vec<ModelInput> = plane(100..110, 0.1).into_iter().map(|point| point.input).collect();
+        let expected: Vec<(Vec<u64>, Vec<u64>)> = inputs.iter()
+            .map(|input| (bits(linear.predict(input).unwrap()), bits(network.predict(input).unwrap())))
+            .collect();
+
+        let mut original = engine(&path);
+        original.add_model("latency/linear".to_string(), Box::new(linear));
+        original.add_model("network".to_string(), Box::new(network));
+        let mut underscored = LinearRegressionModel::new("latency_linear".to_string());
+        underscored.train(&plane(0..100, 0.5)).unwrap();
+        original.add_model("latency_linear".to_string(), Box::new(underscored));
+        let report = original.save_all_models().unwrap();
+        assert_eq!(report.saved.len(), 3);
+        assert!(report.unsupported.is_empty());
+        // 替换过字符的名称带哈希后缀，不会覆盖同名的下划线模型
+        assert!(path.join("latency_linear.json").exists());
+        assert_ne!(original.model_path("latency/linear"), original.model_path("latency_linear"));
+        assert!(report.saved.contains(&original.model_path("latency/linear")));
+        let leftovers: Vec<_> = std::fs::read_dir(&path).unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .filter(|name| name.ends_with(".tmp"))
+            .collect();
+        assert!(leftovers.is_empty(), "{:?}", leftovers);
+
+        let mut restored = engine(&path);
+        restored.add_model("latency/linear".to_string(), Box::new(LinearRegressionModel::new("linear".to_string())));
+        restored.add_model("network".to_string(), Box::new(NeuralNetworkModel::new("network".to_string(), layers(&[3, 1]))));
+        restored.add_model("fresh".to_string(), Box::new(LinearRegressionModel::new("fresh".to_string())));
+        let report = restored.load_models();
+        assert_eq!(report.loaded, ["latency/linear", "network"]);
+        assert_eq!(report.missing, ["fresh"]);
+        assert!(report.warnings.is_empty());
+
+        for (input, (linear_bits, network_bits)) in inputs.iter().zip(expected) {
+            assert_eq!(bits(restored.models["latency/linear"].predict(input).unwrap()), linear_bits);
+            assert_eq!(bits(restored.models["network"].predict(input).unwrap()), network_bits);
+        }
+    }
+
+    #[test]
+    fn test_mismatched_architecture_fails_and_corrupt_files_are_skipped() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("network.json");
+        trained_network().save(&file).unwrap();
+
+        let mut wider = NeuralNetworkModel::new("network".to_string(), layers(&[4, 1]));
+        let error = wider.load(&file).unwrap_err();
+        assert!(matches!(error, AiError::ArchitectureMismatch(_)));
+        assert!(error.to_string().contains("第 0 层声明为 4 个神经元"), "{}", error);
+        assert!(matches!(
+            NeuralNetworkModel::new("network".to_string(), layers(&[3])).load(&file),
+            Err(AiError::ArchitectureMismatch(_))
+        ));
+        assert!(matches!(
+            LinearRegressionModel::new("network".to_string()).load(&file),
+            Err(AiError::PersistenceError(_))
+        ));
+
+        let mut engine = engine(dir.path());
+        std::fs::write(dir.path().join("linear.json"), b"{ not json").unwrap();
+        engine.add_model("linear".to_string(), Box::new(LinearRegressionModel::new("linear".to_string())));
+        engine.add_model("network".to_string(), Box::new(NeuralNetworkModel::new("network".to_string(), layers(&[3, 1]))));
+        let report = engine.load_models();
+        assert_eq!(report.loaded, ["network"]);
+        assert_eq!(report.warnings.len(), 1);
+        assert!(report.warnings[0].contains("linear"), "{:?}", report.warnings);
+    }
+
+    #[test]
+    fn test_network_parameters_with_wrong_dimensions_are_rejected_on_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("network.json");
+        let network = trained_network();
+        network.save(&file).unwrap();
+        let saved: serde_json::Value = serde_json::from_slice(&std::fs::read(&file).unwrap()).unwrap();
+        let input = &plane(0..1, 0.0)[0].input;
+
+        let mut restored = NeuralNetworkModel::new("network".to_string(), layers(&[3, 1]));
+        restored.load(&file).unwrap();
+        // 每项删除 JSON 指针所指数组的最后一个元素
+        let corruptions = [
+            ("第 1 层有 2 个权重", "/state/weights/1"),
+            ("有 3 个偏置", "/state/biases"),
+            ("有 1 层权重", "/state/weights"),
+        ];
+        for (expected, pointer) in corruptions {
+            let mut corrupted = saved.clone();
+            corrupted.pointer_mut(pointer).unwrap().as_array_mut().unwrap().pop();
+            std::fs::write(&file, serde_json::to_vec(&corrupted).unwrap()).unwrap();
+            let error = restored.load(&file).unwrap_err();
+            assert!(matches!(&error, AiError::InvalidState(message) if message.contains(expected)), "{}", error);
+            // 加载失败时保留原来的参数
+            assert_eq!(bits(restored.predict(input).unwrap()), bits(network.predict(input).unwrap()));
+        }
+
+        // 未训练的网络保存后可以重新加载
+        NeuralNetworkModel::new("network".to_string(), layers(&[3, 1])).save(&file).unwrap();
+        restored.load(&file).unwrap();
+        assert!(matches!(restored.predict(input), Err(AiError::PredictionError(_))));
+    }
+
+    #[test]
+    fn test_models_without_persistence_report_not_supported() {
+        struct Constant;
+        impl MachineLearningModel for Constant {
+            fn predict(&self, _input: &ModelInput) -> Result<ModelOutput, AiError> {
+                Ok(ModelOutput { predictions: vec![1.0], confidence: 1.0, explanation: None, contributions: Vec::new() })
+            }
+            fn train(&mut self, _data: &[TrainingDataPoint]) -> Result<(), AiError> {
+                Ok(())
+            }
+            fn evaluate(&self, _test_data: &[TrainingDataPoint]) -> Result<ModelMetrics, AiError> {
+                Err(AiError::NotSupported("evaluate".to_string()))
+            }
+            fn get_name(&self) -> String {
+                "constant".to_string()
+            }
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let mut engine = engine(dir.path());
+        engine.add_model("constant".to_string(), Box::new(Constant));
+        engine.add_model("linear".to_string(), Box::new(LinearRegressionModel::new("linear".to_string())));
+        // 不支持保存的模型被跳过，不影响其他模型
+        let report = engine.save_all_models().unwrap();
+        assert_eq!(report.unsupported, ["constant"]);
+        assert_eq!(report.saved, [dir.path().join("linear.json")]);
+    }
+
+    #[test]
+    fn test_split_is_reproducible_and_respects_ratio() {
+        let data = plane(0..50, 0.0);
+        let (train, validation) = split(&data, 0.8, 42).unwrap();
+        assert_eq!((train.len(), validation.len()), (40, 10));
+
+        let targets = |points: &[TrainingDataPoint]| points.iter().map(|p| p.target[0].to_bits()).collect::<Vec<_>>();
+        let (again, _) = split(&data, 0.8, 42).unwrap();
+        assert_eq!(targets(&train), targets(&again));
+        let (other, _) = split(&data, 0.8, 43).unwrap();
+        assert_ne!(targets(&train), targets(&other));
+        assert!(matches!(split(&data, 1.5, 42), Err(AiError::DataError(_))));
+    }
+
+    #[test]
+    fn test_folds_partition_the_data() {
+        let data = plane(0..23, 0.0);
+        let folds: Vec<Fold> = k_fold(&data, 5, 1).unwrap().collect();
+        assert_eq!(folds.len(), 5);
+
+        let mut seen: Vec<usize> = folds.iter().flat_map(|fold| fold.validation_indices.clone()).collect();
+        seen.sort_unstable();
+        assert_eq!(seen, (0..23).collect::<Vec<_>>());
+        for fold in &folds {
+            assert!((4..=5).contains(&fold.validation.len()));
+            assert_eq!(fold.train.len() + fold.validation.len(), data.len());
+            // 同一折中没有样本同时出现在训练集和验证集里（特征在这组数据里互不相同）
+            for validation in &fold.validation {
+                assert!(!fold.train.iter().any(|train| train.input.features == validation.input.features));
+            }
+        }
+        assert!(matches!(k_fold(&data, 1, 1), Err(AiError::DataError(_))));
+        assert!(matches!(k_fold(&data, 24, 1), Err(AiError::DataError(_))));
+    }
+
+    #[test]
+    fn test_cross_validation_aggregates_independent_folds() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut engine = engine(dir.path());
+        for point in plane(0..60, 0.1) {
+            engine.add_training_data(point);
+        }
+        engine.add_model("linear".to_string(), Box::new(LinearRegressionModel::new("linear".to_string())));
+
+        let report = engine.cross_validate("linear", 4).unwrap();
+        assert_eq!(report.folds.len(), 4);
+        let mean_loss = report.folds.iter().map(|m| m.loss).sum::<f64>() / 4.0;
+        assert!((report.mean.loss - mean_loss).abs() < 1e-12);
+        assert!(report.mean.accuracy > 0.99 && report.mean.loss < 0.1, "{:?}", report.mean);
+        // 已注册的模型没有被训练
+        assert!(matches!(engine.models["linear"].predict(&plane(0..1, 0.0)[0].input), Err(AiError::PredictionError(_))));
+        assert!(matches!(engine.cross_validate("missing", 4), Err(AiError::ModelNotFound(_))));
+
+        let metrics = |loss: f64| ModelMetrics { accuracy: 0.5, precision: 0.5, recall: 0.5, f1_score: 0.5, loss };
+        let report = CrossValidationReport::from_folds("fixed".to_string(), vec![metrics(1.0), metrics(3.0)]);
+        assert_eq!((report.mean.loss, report.std_dev.loss), (2.0, 1.0));
+        assert_eq!((report.mean.accuracy, report.std_dev.accuracy), (0.5, 0.0));
+    }
+
+    /// 记录训练方式的模型：每轮损失递减，副本共享计数
+    #[derive(Clone)]
+    struct EpochCountingModel {
+        epochs: Arc<std::sync::atomic::AtomicUsize>,
+        full_trains: Arc<std::sync::atomic::AtomicUsize>,
+        epochs_run: usize,
+    }
+
+    impl MachineLearningModel for EpochCountingModel {
+        fn predict(&self, _input: &ModelInput) -> Result<ModelOutput, AiError> {
+            Ok(ModelOutput { predictions: vec![0.0], confidence: 1.0, explanation: None, contributions: Vec::new() })
+        }
+
+        fn train(&mut self, _data: &[TrainingDataPoint]) -> Result<(), AiError> {
+            self.full_trains.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+
+        fn train_epoch(&mut self, _data: &[TrainingDataPoint], _options: &EpochOptions) -> Result<f64, AiError> {
+            self.epochs.fetch_add(1, Ordering::SeqCst);
+            self.epochs_run += 1;
+            Ok(1.0 / self.epochs_run as f64)
+        }
+
+        fn evaluate(&self, _test_data: &[TrainingDataPoint]) -> Result<ModelMetrics, AiError> {
+            Ok(regression_metrics(1.0 / self.epochs_run.max(1) as f64, 0.0))
+        }
+
+        fn get_name(&self) -> String {
+            "epochs".to_string()
+        }
+
+        fn clone_model(&self) -> Result<Box<dyn MachineLearningModel>, AiError> {
+            Ok(Box::new(self.clone()))
+        }
+    }
+
+    #[test]
+    fn test_cross_validation_trains_folds_by_epoch() {
+        let mut engine = engine(Path::new("unused"));
+        engine.config.max_epochs = 7;
+        engine.training_data.lock().unwrap().extend(plane(0..30, 0.0));
+        let (epochs, full_trains) = (Arc::new(std::sync::atomic::AtomicUsize::new(0)), Arc::new(std::sync::atomic::AtomicUsize::new(0)));
+        engine.add_model("epochs".to_string(), Box::new(EpochCountingModel {
+            epochs: Arc::clone(&epochs),
+            full_trains: Arc::clone(&full_trains),
+            epochs_run: 0,
+        }));
+
+        // 与 train_models 一致：每折按轮训练 max_epochs 轮，而不是调用一次 train
+        let report = engine.cross_validate("epochs", 3).unwrap();
+        assert_eq!(epochs.load(Ordering::SeqCst), 3 * 7);
+        assert_eq!(full_trains.load(Ordering::SeqCst), 0);
+        assert!(report.folds.iter().all(|fold| (fold.loss - 1.0 / 7.0).abs() < 1e-12), "{:?}", report.folds);
+    }
+
+    /// 没有内部缩放的批量梯度下降线性模型，用来观察特征尺度对训练的影响
+    #[derive(Clone)]
+    struct PlainGradientModel {
+        weights: Vec<f64>,
+        bias: f64,
+    }
+
+    impl MachineLearningModel for PlainGradientModel {
+        fn predict(&self, input: &ModelInput) -> Result<ModelOutput, AiError> {
+            let value = self.bias + self.weights.iter().zip(&input.features).map(|(w, x)| w * x).sum::<f64>();
+            Ok(ModelOutput { predictions: vec![value], confidence: 1.0, explanation: None, contributions: Vec::new() })
+        }
+
+        fn train(&mut self, data: &[TrainingDataPoint]) -> Result<(), AiError> {
+            self.weights = vec![0.0; data[0].input.features.len()];
+            let n = data.len() as f64;
+            for _ in 0..500 {
+                let mut gradients = vec![0.0; self.weights.len()];
+                let mut bias_gradient = 0.0;
+                for point in data {
+                    let error = self.predict(&point.input)?.predictions[0] - point.target[0];
+                    bias_gradient += error / n;
+                    for (gradient, x) in gradients.iter_mut().zip(&point.input.features) {
+                        *gradient += error * x / n;
+                    }
+                }
+                self.bias -= 0.1 * bias_gradient;
+                for (weight, gradient) in self.weights.iter_mut().zip(gradients) {
+                    *weight -= 0.1 * gradient;
+                }
+            }
+            Ok(())
+        }
+
+        fn evaluate(&self, test_data: &[TrainingDataPoint]) -> Result<ModelMetrics, AiError> {
+            let squared: f64 = test_data.iter()
+                .map(|point| (self.predict(&point.input).unwrap().predictions[0] - point.target[0]).powi(2))
+                .sum();
+            let loss = (squared / test_data.len() as f64).sqrt();
+            Ok(ModelMetrics { accuracy: 0.0, precision: 0.0, recall: 0.0, f1_score: 0.0, loss })
+        }
+
+        fn get_name(&self) -> String {
+            "plain".to_string()
+        }
+
+        fn clone_model(&self) -> Result<Box<dyn MachineLearningModel>, AiError> {
+            Ok(Box::new(self.clone()))
+        }
+    }
+
+    /// 字节数（约 10⁶）和毫秒数（约 10）混合的特征
+    fn metric_points() -> Vec<TrainingDataPoint> {
+        (0..40).map(|i| {
+            let (bytes, millis) = (200_000.0 + (i * 37 % 40) as f64 * 20_000.0, 1.0 + (i % 7) as f64 * 3.0);
+            point(vec![bytes, millis], vec![bytes * 2e-6 + 0.5 * millis + 4.0])
+        }).collect()
+    }
+
+    #[test]
+    fn test_scaler_round_trips_and_guards_zero_variance() {
+        let data: Vec<_> = (0..10)
+            .map(|i| point(vec![i as f64 * 1000.0, 5.0, -(i as f64)], vec![0.0]))
+            .collect();
+        for mode in [ScalingMode::Standardize, ScalingMode::MinMax] {
+            let scaler = FeatureScaler::fit(mode, &data).unwrap();
+            for point in &data {
+                let scaled = scaler.transform(&point.input.features).unwrap();
+                assert!(scaled.iter().all(|value| value.is_finite()));
+                assert_eq!(scaled[1], 5.0, "zero-variance feature passes through");
+                let restored = scaler.inverse_transform(&scaled).unwrap();
+                for (restored, original) in restored.iter().zip(&point.input.features) {
+                    assert!((restored - original).abs() < 1e-9);
+                }
+            }
+        }
+
+        let standard = FeatureScaler::fit(ScalingMode::Standardize, &data).unwrap();
+        let column: Vec<f64> = data.iter().map(|p| standard.transform(&p.input.features).unwrap()[0]).collect();
+        assert!(column.iter().sum::<f64>().abs() < 1e-9);
+        assert!((column.iter().map(|x| x * x).sum::<f64>() / 10.0 - 1.0).abs() < 1e-9);
+        let min_max = FeatureScaler::fit(ScalingMode::MinMax, &data).unwrap();
+        assert_eq!(min_max.transform(&[9000.0, 5.0, 0.0]).unwrap(), [1.0, 5.0, 1.0]);
+        assert!(matches!(min_max.transform(&[1.0]), Err(AiError::DataError(_))));
+    }
+
+    #[test]
+    fn test_scaling_lets_gradient_training_converge() {
+        let dir = tempfile::tempdir().unwrap();
+        let train = |scaling: Option<ScalingMode>| {
+            let mut engine = engine(dir.path());
+            engine.config.feature_scaling = scaling;
+            for point in metric_points() {
+                engine.add_training_data(point);
+            }
+            engine.add_model("plain".to_string(), Box::new(PlainGradientModel { weights: Vec::new(), bias: 0.0 }));
+            engine.train_models().unwrap();
+            engine
+        };
+
+        // 原始尺度下同样的学习率会在第一步就发散
+        let raw = train(None);
+        let raw_loss = raw.get_model_metrics("plain", &metric_points()).unwrap().loss;
+        assert!(raw_loss.is_nan() || raw_loss >= 1.0, "unscaled training converged: {}", raw_loss);
+
+        let scaled = train(Some(ScalingMode::Standardize));
+        assert!(scaled.get_model_metrics("plain", &metric_points()).unwrap().loss < 0.01);
+        // 预测路径自动对原始输入做同样的变换
+        let probe = point(vec![500_000.0, 10.0], vec![]);
+        let prediction = scaled.predict("plain", &probe.input).unwrap().predictions[0];
+        assert!((prediction - 10.0).abs() < 0.05, "{}", prediction);
+        // 训练数据仍是原始特征
+        assert_eq!(scaled.training_data.lock().unwrap()[0].input.features, metric_points()[0].input.features);
+    }
+
+    #[test]
+    fn test_scaler_is_persisted_with_the_model() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut original = engine(dir.path());
+        original.config.feature_scaling = Some(ScalingMode::MinMax);
+        for point in metric_points() {
+            original.add_training_data(point);
+        }
+        original.add_model("linear".to_string(), Box::new(LinearRegressionModel::new("linear".to_string())));
+        original.train_models().unwrap();
+        original.save_all_models().unwrap();
+        assert!(dir.path().join("linear.scaler.json").exists());
+
+        let mut restored = engine(dir.path());
+        restored.add_model("linear".to_string(), Box::new(LinearRegressionModel::new("linear".to_string())));
+        assert_eq!(restored.load_models().loaded, ["linear"]);
+        assert_eq!(restored.scalers["linear"], original.scalers["linear"]);
+        let probe = point(vec![500_000.0, 10.0], vec![]);
+        assert_eq!(
+            bits(restored.predict("linear", &probe.input).unwrap()),
+            bits(original.predict("linear", &probe.input).unwrap())
+        );
+
+        std::fs::write(dir.path().join("linear.scaler.json"), b"[]").unwrap();
+        let mut corrupt = engine(dir.path());
+        corrupt.add_model("linear".to_string(), Box::new(LinearRegressionModel::new("linear".to_string())));
+        let report = corrupt.load_models();
+        assert!(report.loaded.is_empty() && report.warnings.len() == 1, "{:?}", report);
+    }
+
+    /// 训练集中目标值随一个微小特征正向变化，验证集中反向变化：
+    /// 先学到主特征时验证损失下降，之后慢慢拟合这个虚假特征，验证损失回升
+    fn overfitting_sets() -> (Vec<TrainingDataPoint>, Vec<TrainingDataPoint>) {
+        let sample = |i: usize, sign: f64| {
+            let (signal, spurious) = (1.0 + (i * 7 % 20) as f64 / 20.0, if i.is_multiple_of(2) { 0.1 } else { -0.1 });
+            point(vec![signal, spurious], vec![signal + sign * 5.0 * spurious])
+        };
+        ((0..20).map(|i| sample(i, 1.0)).collect(), (20..30).map(|i| sample(i, -1.0)).collect())
+    }
+
+    fn network_engine(max_epochs: u32, patience: u32) -> AiOptimizationEngine {
+        let mut engine = engine(Path::new("unused"));
+        engine.config.learning_rate = 0.1;
+        engine.config.max_epochs = max_epochs;
+        engine.config.early_stopping_patience = patience;
+        let linear_layer = vec![NeuralLayer { neuron_count: 1, activation_function: ActivationFunction::LeakyReLU }];
+        engine.add_model("network".to_string(), Box::new(NeuralNetworkModel::new("network".to_string(), linear_layer).with_seed(5)));
+        engine
+    }
+
+    #[test]
+    fn test_network_backpropagation_reduces_loss() {
+        let data = plane(0..40, 0.0);
+        let scaler = FeatureScaler::fit(ScalingMode::Standardize, &data).unwrap();
+        let data: Vec<_> = scaler.transform_points(&data).unwrap().into_iter()
+            .map(|point| TrainingDataPoint { target: vec![point.target[0] / 20.0], ..point })
+            .collect();
+        let mut network = NeuralNetworkModel::new("network".to_string(), vec![
+            NeuralLayer { neuron_count: 8, activation_function: ActivationFunction::Tanh },
+            NeuralLayer { neuron_count: 1, activation_function: ActivationFunction::Tanh },
+        ]);
+        let options = EpochOptions { learning_rate: 0.1, batch_size: 8 };
+        let first = network.train_epoch(&data, &options).unwrap();
+        let mut last = first;
+        for _ in 0..300 {
+            last = network.train_epoch(&data, &options).unwrap();
+        }
+        assert!(last < first / 20.0, "{} -> {}", first, last);
+        assert!(matches!(
+            NeuralNetworkModel::new("fresh".to_string(), layers(&[1])).predict(&data[0].input),
+            Err(AiError::PredictionError(_))
+        ));
+    }
+
+    #[test]
+    fn test_early_stopping_restores_best_validation_epoch() {
+        let (train, validation) = overfitting_sets();
+        let mut engine = network_engine(3000, 20);
+        let report = engine.train_model("network", &train, &validation).unwrap();
+
+        assert!(report.stopped_early, "{:?}", report.val_loss_curve.last());
+        assert!(report.epochs_run < 3000);
+        assert_eq!(report.epochs_run, report.best_epoch + 20 + 1);
+        assert_eq!(report.train_loss_curve.len(), report.epochs_run as usize);
+        assert_eq!(report.val_loss_curve.len(), report.epochs_run as usize);
+
+        let best = report.val_loss_curve[report.best_epoch as usize];
+        assert!(report.val_loss_curve.iter().all(|loss| *loss >= best));
+        assert!(*report.val_loss_curve.last().unwrap() > best);
+        // 训练损失在最佳轮次之后仍在下降，说明停止的原因是过拟合
+        assert!(*report.train_loss_curve.last().unwrap() < report.train_loss_curve[report.best_epoch as usize]);
+        // 恢复的是最佳轮次的参数，而不是最后一轮的
+        assert_eq!(engine.get_model_metrics("network", &validation).unwrap().loss.to_bits(), best.to_bits());
+    }
+
+    #[test]
+    fn test_max_epochs_caps_training_and_one_shot_models_report_once() {
+        let (train, validation) = overfitting_sets();
+        let mut engine = network_engine(15, 0);
+        let report = engine.train_model("network", &train, &validation).unwrap();
+        assert_eq!((report.epochs_run, report.stopped_early), (15, false));
+        assert_eq!(report.val_loss_curve.len(), 15);
+
+        engine.add_model("linear".to_string(), Box::new(LinearRegressionModel::new("linear".to_string())));
+        for point in plane(0..50, 0.1) {
+            engine.add_training_data(point);
+        }
+        let reports = engine.train_models().unwrap();
+        let linear = &reports["linear"];
+        assert_eq!((linear.epochs_run, linear.best_epoch, linear.stopped_early), (1, 0, false));
+        assert_eq!((linear.train_loss_curve.len(), linear.val_loss_curve.len()), (1, 1));
+        assert!(linear.val_loss_curve[0] < 0.1);
+        assert_eq!(reports["network"].epochs_run, 15);
+
+        engine.config.validation_split = 1.0;
+        assert!(matches!(engine.train_models(), Err(AiError::ConfigurationError(_))));
+    }
+
+    #[test]
+    fn test_single_tree_fits_step_function() {
+        let data: Vec<_> = (0..20)
+            .map(|i| point(vec![i as f64, (i % 3) as f64], vec![if i < 8 { 1.0 } else if i < 14 { 5.0 } else { -2.0 }]))
+            .collect();
+        let mut tree = DecisionTreeModel::new("step".to_string());
+        tree.train(&data).unwrap();
+        let metrics = tree.evaluate(&data).unwrap();
+        assert_eq!((metrics.loss, metrics.accuracy), (0.0, 1.0));
+        assert_eq!(tree.predict(&point(vec![10.5, 0.0], vec![]).input).unwrap().predictions, [5.0]);
+        assert_eq!(tree.feature_importances(), [1.0, 0.0]);
+
+        let mut stump = DecisionTreeModel::new("stump".to_string()).with_max_depth(0);
+        stump.train(&data).unwrap();
+        let mean = data.iter().map(|p| p.target[0]).sum::<f64>() / 20.0;
+        assert!((stump.predict(&data[0].input).unwrap().predictions[0] - mean).abs() < 1e-12);
+
+        let mut wide_leaves = DecisionTreeModel::new("wide".to_string()).with_min_samples_leaf(10);
+        wide_leaves.train(&data).unwrap();
+        assert!(wide_leaves.evaluate(&data).unwrap().loss > 0.0);
+    }
+
+    /// 两个信息特征共同决定的非线性目标，加上较大的噪声
+    fn noisy_surface(range: std::ops::Range<usize>) -> Vec<TrainingDataPoint> {
+        range.map(|i| {
+            let (x1, x2, x3) = (noise(i, 1.0) * 3.0, noise(i + 1000, 1.0) * 3.0, noise(i + 2000, 1.0));
+            let y = (x1 * 1.5).sin() * 2.0 + if x2 > 0.0 { 1.5 } else { -1.5 } + noise(i + 3000, 1.5);
+            point(vec![x1, x2, x3], vec![y])
+        }).collect()
+    }
+
+    #[test]
+    fn test_forest_generalizes_better_than_a_single_tree() {
+        let (train, validation) = (noisy_surface(0..300), noisy_surface(300..500));
+        let mut tree = DecisionTreeModel::new("tree".to_string()).with_max_depth(20);
+        tree.train(&train).unwrap();
+        let mut forest = RandomForestModel::new("forest".to_string()).with_max_depth(20).with_seed(11);
+        forest.train(&train).unwrap();
+
+        let (tree_loss, forest_loss) = (tree.evaluate(&validation).unwrap().loss, forest.evaluate(&validation).unwrap().loss);
+        assert!(forest_loss < tree_loss, "forest {} vs tree {}", forest_loss, tree_loss);
+        assert!(tree.evaluate(&train).unwrap().loss < 1e-9, "an unconstrained tree memorizes the noise");
+
+        // 相同的种子得到完全相同的模型
+        let mut again = RandomForestModel::new("forest".to_string()).with_max_depth(20).with_seed(11);
+        again.train(&train).unwrap();
+        for point in &validation {
+            assert_eq!(
+                bits(again.predict(&point.input).unwrap()),
+                bits(forest.predict(&point.input).unwrap())
+            );
+        }
+    }
+
+    #[test]
+    fn test_importances_favor_informative_feature_and_models_persist() {
+        let data: Vec<_> = (0..200)
+            .map(|i| {
+                let (signal, junk) = (noise(i, 1.0), noise(i + 500, 1.0));
+                point(vec![junk, signal], vec![if signal > 0.2 { 3.0 } else { 0.0 } + noise(i + 900, 0.1)])
+            })
+            .collect();
+        let mut tree = DecisionTreeModel::new("tree".to_string()).with_max_depth(4);
+        tree.train(&data).unwrap();
+        let mut forest = RandomForestModel::new("forest".to_string()).with_tree_count(20).with_max_features(2).with_seed(3);
+        forest.train(&data).unwrap();
+        for importances in [tree.feature_importances(), forest.feature_importances()] {
+            assert!((importances.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+            assert!(importances[1] > 0.8 && importances[0] < 0.2, "{:?}", importances);
+        }
+        assert!(forest.predict(&data[0].input).unwrap().explanation.unwrap().contains("x1"));
+
+        let dir = tempfile::tempdir().unwrap();
+        let (tree_file, forest_file) = (dir.path().join("tree.json"), dir.path().join("forest.json"));
+        tree.save(&tree_file).unwrap();
+        forest.save(&forest_file).unwrap();
+        let mut restored_tree = DecisionTreeModel::new("tree".to_string());
+        restored_tree.load(&tree_file).unwrap();
+        let mut restored_forest = RandomForestModel::new("forest".to_string());
+        restored_forest.load(&forest_file).unwrap();
+        for point in data.iter().take(20) {
+            assert_eq!(bits(restored_tree.predict(&point.input).unwrap()), bits(tree.predict(&point.input).unwrap()));
+            assert_eq!(bits(restored_forest.predict(&point.input).unwrap()), bits(forest.predict(&point.input).unwrap()));
+        }
+        assert!(matches!(RandomForestModel::new("forest".to_string()).predict(&data[0].input), Err(AiError::PredictionError(_))));
+
+        // 分裂特征下标超出特征数的文件在加载时被拒绝，而不是在预测时越界
+        fn corrupt_features(value: &mut serde_json::Value) {
+            match value {
+                serde_json::Value::Object(fields) => {
+                    if let Some(feature) = fields.get_mut("feature") {
+                        *feature = 7.into();
+                    }
+                    fields.values_mut().for_each(corrupt_features);
+                }
+                serde_json::Value::Array(items) => items.iter_mut().for_each(corrupt_features),
+                _ => {}
+            }
+        }
+        for file in [&tree_file, &forest_file] {
+            let mut saved: serde_json::Value = serde_json::from_slice(&std::fs::read(file).unwrap()).unwrap();
+            corrupt_features(&mut saved);
+            std::fs::write(file, serde_json::to_vec(&saved).unwrap()).unwrap();
+        }
+        assert!(matches!(restored_tree.load(&tree_file), Err(AiError::ArchitectureMismatch(message)) if message.contains("下标 7")));
+        assert!(matches!(restored_forest.load(&forest_file), Err(AiError::ArchitectureMismatch(_))));
+        // 加载失败时保留原来的模型
+        assert_eq!(bits(restored_tree.predict(&data[0].input).unwrap()), bits(tree.predict(&data[0].input).unwrap()));
+    }
+
+    const MIB: usize = 1024 * 1024;
+
+    fn memory_context(pattern: MemoryUsagePattern, usage: Option<usize>, limit: usize) -> OptimizationContext {
+        OptimizationContext {
+            current_metrics: usage.map(|usage| (MEMORY_USAGE_METRIC.to_string(), usage as f64)).into_iter().collect(),
+            historical_data: Vec::new(),
+            workload_characteristics: WorkloadCharacteristics {
+                request_pattern: RequestPattern::Uniform,
+                data_access_pattern: DataAccessPattern::Sequential,
+                computational_complexity: ComputationalComplexity::Medium,
+                concurrency_level: ConcurrencyLevel::Medium,
+                memory_usage_pattern: pattern,
+            },
+            resource_constraints: ResourceConstraints {
+                cpu_limit: 4.0,
+                memory_limit: limit,
+                network_bandwidth_limit: 0,
+                storage_limit: 0,
+                cost_limit: 0.0,
+            },
+            optimization_goals: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_memory_patterns_get_distinct_recommendations() {
+        let strategy = MemoryOptimizationStrategy;
+        let optimize = |pattern| strategy.optimize(&memory_context(pattern, Some(800 * MIB), 1024 * MIB)).unwrap();
+
+        let growing = optimize(MemoryUsagePattern::Growing);
+        assert!(matches!(
+            growing.recommendations.as_slice(),
+            [
+                OptimizationRecommendation { recommendation_type: RecommendationType::AlgorithmOptimization, .. },
+                OptimizationRecommendation { recommendation_type: RecommendationType::CacheStrategy, .. },
+            ]
+        ));
+        assert!(growing.recommendations[0].dependencies[0].contains("detect_memory_leaks"));
+        assert!(growing.recommendations[0].description.contains("余量 224.0 MiB"), "{}", growing.recommendations[0].description);
+        // 峰值 800 MiB 超过限制的 70%（716.8 MiB），至少释放 83.2 MiB
+        assert!(growing.recommendations[1].description.contains("83.2 MiB"), "{}", growing.recommendations[1].description);
+        assert_eq!(growing.risk_assessment.risk_level, RiskLevel::Medium);
+
+        let volatile = optimize(MemoryUsagePattern::Volatile);
+        assert!(volatile.recommendations[0].description.contains("arena"));
+        assert!(volatile.recommendations[1].description.contains("max_heap_size 设为约 1000.0 MiB"));
+        assert_eq!(volatile.recommendations[1].dependencies, ["security_advanced::MemoryLimits"]);
+
+        let cyclic = optimize(MemoryUsagePattern::Cyclic);
+        assert_eq!(cyclic.recommendations.len(), 1);
+        assert!(matches!(cyclic.recommendations[0].recommendation_type, RecommendationType::ResourceAllocation));
+        assert!(cyclic.recommendations[0].description.contains("定时压缩"));
+
+        let stable = optimize(MemoryUsagePattern::Stable);
+        assert!(stable.recommendations.is_empty());
+        assert_eq!(stable.expected_improvement, 0.0);
+        assert!(growing.expected_improvement > volatile.expected_improvement);
+        assert!(volatile.expected_improvement > cyclic.expected_improvement);
+    }
+
+    #[test]
+    fn test_memory_confidence_scales_with_pressure() {
+        let strategy = MemoryOptimizationStrategy;
+        let result = |usage, limit| strategy.optimize(&memory_context(MemoryUsagePattern::Growing, usage, limit)).unwrap();
+
+        let critical = result(Some(1000 * MIB), 1024 * MIB);
+        let moderate = result(Some(700 * MIB), 1024 * MIB);
+        let relaxed = result(Some(100 * MIB), 1024 * MIB);
+        let unknown = result(None, 1024 * MIB);
+        assert!(critical.confidence > moderate.confidence && moderate.confidence > relaxed.confidence);
+        assert!(relaxed.confidence > unknown.confidence);
+        assert!(critical.expected_improvement > moderate.expected_improvement);
+        assert!(moderate.expected_improvement > relaxed.expected_improvement);
+        assert!(unknown.recommendations[0].description.contains("缺少 memory_usage 指标"));
+        assert!(result(Some(100 * MIB), 0).recommendations[0].description.contains("未设置内存限制"));
+
+        // 历史快照给出增长速度，估计达到限制的时间
+        let mut context = memory_context(MemoryUsagePattern::Growing, Some(600 * MIB), 1024 * MIB);
+        let start = Utc::now() - chrono::Duration::hours(2);
+        for (hours, usage) in [(0, 400), (2, 600)] {
+            context.historical_data.push(PerformanceSnapshot {
+                timestamp: start + chrono::Duration::hours(hours),
+                metrics: HashMap::from([(MEMORY_USAGE_METRIC.to_string(), (usage * MIB) as f64)]),
+                configuration: HashMap::new(),
+                workload: context.workload_characteristics.clone(),
+            });
+        }
+        let projected = strategy.optimize(&context).unwrap();
+        assert!(projected.recommendations[0].description.contains("约 4.2 小时后达到限制"), "{}", projected.recommendations[0].description);
+    }
+
+    #[test]
+    fn test_memory_growth_over_short_windows_stays_finite() {
+        let mut context = memory_context(MemoryUsagePattern::Growing, Some(600 * MIB), 1024 * MIB);
+        let start = Utc::now() - chrono::Duration::minutes(1);
+        for (millis, usage) in [(0, 400), (500, 600)] {
+            context.historical_data.push(PerformanceSnapshot {
+                timestamp: start + chrono::Duration::milliseconds(millis),
+                metrics: HashMap::from([(MEMORY_USAGE_METRIC.to_string(), (usage * MIB) as f64)]),
+                configuration: HashMap::new(),
+                workload: context.workload_characteristics.clone(),
+            });
+        }
+        // 半秒内增长 200 MiB，按一分钟的下限折算为每小时 12000 MiB
+        let growth = MemoryPressure::of(&context).growth_per_hour.unwrap();
+        assert!((growth - (200 * 60 * MIB) as f64).abs() < 1e-3);
+
+        // 相同的时间戳没有趋势
+        context.historical_data[1].timestamp = context.historical_data[0].timestamp;
+        assert_eq!(MemoryPressure::of(&context).growth_per_hour, None);
+
+        // 指标为 NaN 时置信度也是 NaN，排序不会 panic
+        let mut engine = engine(Path::new("unused"));
+        engine.add_strategy(Box::new(MemoryOptimizationStrategy));
+        engine.add_strategy(Box::new(CostOptimizationStrategy));
+        context.historical_data.clear();
+        context.current_metrics.insert(MEMORY_USAGE_METRIC.to_string(), f64::NAN);
+        let results = engine.optimize(&context).unwrap();
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().any(|result| result.confidence.is_nan()));
+    }
+
+    fn latency_context(p99: Option<f64>, goals: Vec<OptimizationGoal>) -> OptimizationContext {
+        let mut context = memory_context(MemoryUsagePattern::Stable, None, 0);
+        context.workload_characteristics.data_access_pattern = DataAccessPattern::Hotspot;
+        context.workload_characteristics.computational_complexity = ComputationalComplexity::High;
+        context.workload_characteristics.concurrency_level = ConcurrencyLevel::VeryHigh;
+        if let Some(p99) = p99 {
+            context.current_metrics.insert(LATENCY_P99_METRIC.to_string(), p99);
+        }
+        context.optimization_goals = goals;
+        context
+    }
+
+    fn goal(goal_type: OptimizationGoalType, target_value: f64, weight: f64) -> OptimizationGoal {
+        OptimizationGoal { goal_type, target_value, weight, priority: OptimizationPriority::High }
+    }
+
+    #[test]
+    fn test_latency_strategy_only_applies_with_latency_goal() {
+        let strategy = LatencyOptimizationStrategy;
+        let without = latency_context(Some(200.0), vec![goal(OptimizationGoalType::Cost, 10.0, 1.0)]);
+        assert!(!strategy.is_applicable(&without));
+        assert!(matches!(strategy.optimize(&without), Err(AiError::ConfigurationError(_))));
+
+        let dir = tempfile::tempdir().unwrap();
+        let mut engine = engine(dir.path());
+        engine.add_strategy(Box::new(LatencyOptimizationStrategy));
+        engine.add_strategy(Box::new(CostOptimizationStrategy));
+        let names = |context| engine.optimize(context).unwrap().into_iter().map(|r| r.strategy_name).collect::<Vec<_>>();
+        assert_eq!(names(&without), ["Cost Optimization"]);
+
+        let with = latency_context(Some(200.0), vec![goal(OptimizationGoalType::Latency, 100.0, 1.0)]);
+        assert!(names(&with).contains(&"Latency Optimization".to_string()));
+        let result = strategy.optimize(&with).unwrap();
+        assert!(matches!(
+            result.recommendations.iter().map(|r| &r.recommendation_type).collect::<Vec<_>>().as_slice(),
+            [RecommendationType::CacheStrategy, RecommendationType::AlgorithmOptimization, RecommendationType::LoadBalancing]
+        ));
+        assert!(result.recommendations[0].description.contains("latency_p99_ms 200.0 ms，目标 100.0 ms"));
+        assert!(result.recommendations[1].dependencies.iter().any(|d| d.ends_with("SimdInstructions")));
+        assert!(result.recommendations[2].dependencies[0].ends_with("LeastConnections"));
+
+        // 已达到目标时没有建议
+        let met = strategy.optimize(&latency_context(Some(80.0), vec![goal(OptimizationGoalType::Latency, 100.0, 1.0)])).unwrap();
+        assert!(met.recommendations.is_empty());
+        assert_eq!(met.expected_improvement, 0.0);
+    }
+
+    #[test]
+    fn test_latency_benefit_scales_with_target_gap_and_goal_weight() {
+        let strategy = LatencyOptimizationStrategy;
+        let optimize = |target, goals: Vec<OptimizationGoal>| {
+            let mut goals = goals;
+            goals.push(goal(OptimizationGoalType::Latency, target, 1.0));
+            strategy.optimize(&latency_context(Some(200.0), goals)).unwrap()
+        };
+
+        // p99 200 ms：目标 150 ms 需要削减 25%，目标 50 ms 需要削减 75%
+        let small_gap = optimize(150.0, Vec::new());
+        let large_gap = optimize(50.0, Vec::new());
+        assert!((small_gap.recommendations[0].expected_benefit - 0.125).abs() < 1e-12);
+        assert!((large_gap.recommendations[0].expected_benefit - 0.375).abs() < 1e-12);
+        assert!((large_gap.recommendations[1].expected_benefit / small_gap.recommendations[1].expected_benefit - 3.0).abs() < 1e-9);
+        assert!(large_gap.expected_improvement > small_gap.expected_improvement);
+
+        // 另有同等权重的成本目标时，延迟结果的预期改进减半
+        let shared = optimize(50.0, vec![goal(OptimizationGoalType::Cost, 1.0, 1.0)]);
+        assert!((shared.expected_improvement - large_gap.expected_improvement / 2.0).abs() < 1e-12);
+        assert_eq!(shared.recommendations[0].expected_benefit, large_gap.recommendations[0].expected_benefit);
+
+        // 历史快照中的上升趋势出现在建议里并提高置信度
+        let mut context = latency_context(None, vec![goal(OptimizationGoalType::Latency, 100.0, 1.0)]);
+        let start = Utc::now() - chrono::Duration::hours(4);
+        for (hours, p99) in [(0, 120.0), (4, 180.0)] {
+            context.historical_data.push(PerformanceSnapshot {
+                timestamp: start + chrono::Duration::hours(hours),
+                metrics: HashMap::from([(LATENCY_P99_METRIC.to_string(), p99)]),
+                configuration: HashMap::new(),
+                workload: context.workload_characteristics.clone(),
+            });
+        }
+        let trending = strategy.optimize(&context).unwrap();
+        assert!(trending.recommendations[0].description.contains("180.0 ms，目标 100.0 ms，近期每小时上升 15.0 ms"));
+        assert!(trending.confidence > small_gap.confidence);
+
+        // 相隔不到一秒的快照按一分钟的下限折算，趋势仍是有限值
+        context.historical_data[1].timestamp = context.historical_data[0].timestamp + chrono::Duration::milliseconds(200);
+        assert!((TailLatency::of(&context).trend_per_hour.unwrap() - 3600.0).abs() < 1e-9);
+        let brief = strategy.optimize(&context).unwrap();
+        assert!(brief.recommendations[0].description.contains("近期每小时上升 3600.0 ms"), "{}", brief.recommendations[0].description);
+    }
+
+    /// 以半小时前为起点的快照，落在引擎一小时的保留窗口内
+    fn snapshot(seconds: i64, metrics: &[(&str, f64)]) -> PerformanceSnapshot {
+        PerformanceSnapshot {
+            timestamp: Utc::now() - chrono::Duration::minutes(30) + chrono::Duration::seconds(seconds),
+            metrics: metrics.iter().map(|(name, value)| (name.to_string(), *value)).collect(),
+            configuration: HashMap::from([("instance".to_string(), "edge-1".to_string())]),
+            workload: memory_context(MemoryUsagePattern::Stable, None, 0).workload_characteristics,
+        }
+    }
+
+    /// latency = 2·cpu + 1 + offset
+    fn load_snapshot(i: usize, offset: f64) -> PerformanceSnapshot {
+        let cpu = (i % 13) as f64;
+        snapshot(i as i64 * 60, &[("cpu", cpu), ("latency", 2.0 * cpu + 1.0 + offset)])
+    }
+
+    fn load_snapshot_at(offset: f64) -> impl Fn(usize) -> PerformanceSnapshot {
+        move |i| load_snapshot(i, offset)
+    }
+
+    fn online_bridge(model: Box<dyn MachineLearningModel>, config: OnlineLearningConfig) -> OnlineLearningBridge {
+        let mut engine = engine(Path::new("unused"));
+        engine.add_model("online".to_string(), model);
+        OnlineLearningBridge::new(
+            Arc::new(Mutex::new(engine)),
+            SnapshotFeatureExtractor::new(vec!["cpu".to_string()], vec!["latency".to_string()]),
+            config,
+        )
+    }
+
+    #[test]
+    fn test_snapshot_feature_extraction() {
+        let extractor = SnapshotFeatureExtractor::new(
+            vec!["memory".to_string(), "cpu".to_string()],
+            vec![LATENCY_P99_METRIC.to_string()],
+        );
+        let snapshot = snapshot(0, &[("cpu", 0.5), ("memory", 512.0), (LATENCY_P99_METRIC, 80.0), ("unused", 1.0)]);
+        let point = extractor.extract(&snapshot).unwrap();
+        assert_eq!(point.input.features, vec![512.0, 0.5]);
+        assert_eq!(point.target, vec![80.0]);
+        assert_eq!(point.timestamp, snapshot.timestamp);
+        assert_eq!(point.input.metadata["instance"], "edge-1");
+
+        let incomplete = PerformanceSnapshot { metrics: HashMap::from([("cpu".to_string(), 0.5)]), ..snapshot };
+        assert!(matches!(extractor.extract(&incomplete), Err(AiError::DataError(message)) if message.contains("memory")));
+
+        // 监控指标取每个名字最新的值
+        let workload = memory_context(MemoryUsagePattern::Stable, None, 0).workload_characteristics;
+        let metric = |name: &str, value: f64, timestamp: u64| PerformanceMetric {
+            name: name.to_string(),
+            value,
+            timestamp,
+            labels: HashMap::new(),
+            metadata: crate::monitoring_advanced::PerformanceMetadata {
+                min_value: value,
+                max_value: value,
+                avg_value: value,
+                percentiles: HashMap::new(),
+                sample_count: 1,
+            },
+        };
+        let converted = PerformanceSnapshot::from_metrics(
+            &[metric("cpu", 0.9, 1_700_000_060), metric("cpu", 0.4, 1_700_000_000), metric("latency", 7.0, 1_700_000_030)],
+            workload,
+        );
+        assert_eq!(converted.metrics, HashMap::from([("cpu".to_string(), 0.9), ("latency".to_string(), 7.0)]));
+        assert_eq!(converted.timestamp.timestamp(), 1_700_000_060);
+    }
+
+    #[test]
+    fn test_online_bridge_retrains_after_new_data() {
+        let bridge = online_bridge(
+            Box::new(LinearRegressionModel::new("online".to_string())),
+            OnlineLearningConfig { retrain_after: 5, ..OnlineLearningConfig::default() },
+        );
+        let mut triggers = Vec::new();
+        for i in 0..12 {
+            triggers.extend(bridge.ingest(&load_snapshot(i, 0.0)).unwrap());
+            bridge.wait_for_retraining();
+        }
+        assert_eq!(triggers, vec![RetrainTrigger::NewData; 2]);
+        assert!(bridge.ingest(&snapshot(720, &[("cpu", 1.0)])).is_err());
+
+        let stats = bridge.stats();
+        assert_eq!((stats.ingested, stats.rejected, stats.data_triggers, stats.drift_triggers), (12, 1, 2, 0));
+        assert_eq!((stats.retrains_completed, stats.retrains_failed), (2, 0));
+        let engine = bridge.engine();
+        let prediction = engine.lock().unwrap().predict("online", &ModelInput { features: vec![4.0], metadata: HashMap::new() }).unwrap();
+        assert!((prediction.predictions[0] - 9.0).abs() < 1e-6);
+
+        // 保留窗口（一小时）以当前时间为准：过期的快照不保留，时间戳超前的快照也不会挤掉已有数据
+        bridge.ingest(&load_snapshot_at(0.0)(0)).unwrap();
+        bridge.ingest(&snapshot(-7_200, &[("cpu", 1.0), ("latency", 3.0)])).unwrap();
+        bridge.ingest(&snapshot(86_400, &[("cpu", 1.0), ("latency", 3.0)])).unwrap();
+        let retained = engine.lock().unwrap().training_data.lock().unwrap().len();
+        assert_eq!(retained, 14);
+    }
+
+    #[test]
+    fn test_online_bridge_detects_drift() {
+        let bridge = online_bridge(
+            Box::new(LinearRegressionModel::new("online".to_string())),
+            OnlineLearningConfig {
+                retrain_after: 0,
+                drift_model: Some("online".to_string()),
+                drift_threshold: 1.0,
+                drift_window: 5,
+            },
+        );
+        // 模型训练前无法预测，不计入误差
+        let before_training: Vec<_> = (0..20).map(load_snapshot_at(0.0)).collect();
+        assert!(bridge.consume(before_training).is_empty());
+        bridge.engine().lock().unwrap().train_models().unwrap();
+
+        // 与模型一致的数据不触发漂移
+        assert!(bridge.consume((20..40).map(load_snapshot_at(0.0))).is_empty());
+
+        // 延迟整体上升 20：第一个偏移的点就使窗口平均误差超过阈值
+        let mut triggers = Vec::new();
+        for i in 40..45 {
+            triggers.push(bridge.ingest(&load_snapshot(i, 20.0)).unwrap());
+            bridge.wait_for_retraining();
+        }
+        assert_eq!(triggers[0], Some(RetrainTrigger::Drift));
+        assert_eq!(bridge.stats().drift_triggers, 1);
+        assert_eq!(bridge.stats().retrains_completed, 1);
+    }
+
+    /// 训练时阻塞，直到测试放行
+    struct GatedModel {
+        gate: Mutex<std::sync::mpsc::Receiver<()>>,
+    }
+
+    impl MachineLearningModel for GatedModel {
+        fn predict(&self, _input: &ModelInput) -> Result<ModelOutput, AiError> {
+            Ok(ModelOutput { predictions: vec![0.0], confidence: 1.0, explanation: None, contributions: Vec::new() })
+        }
+
+        fn train(&mut self, _data: &[TrainingDataPoint]) -> Result<(), AiError> {
+            self.gate.lock().unwrap().recv().map_err(|e| AiError::TrainingError(e.to_string()))
+        }
+
+        fn evaluate(&self, _test_data: &[TrainingDataPoint]) -> Result<ModelMetrics, AiError> {
+            Ok(ModelMetrics { accuracy: 0.0, precision: 0.0, recall: 0.0, f1_score: 0.0, loss: 0.0 })
+        }
+
+        fn get_name(&self) -> String {
+            "gated".to_string()
+        }
+    }
+
+    #[test]
+    fn test_online_bridge_ingests_while_retraining() {
+        let (release, gate) = std::sync::mpsc::channel();
+        let bridge = online_bridge(
+            Box::new(GatedModel { gate: Mutex::new(gate) }),
+            OnlineLearningConfig {
+                retrain_after: 3,
+                drift_model: Some("online".to_string()),
+                ..OnlineLearningConfig::default()
+            },
+        );
+        let triggers = bridge.consume((0..3).map(load_snapshot_at(0.0)));
+        assert_eq!(triggers, vec![RetrainTrigger::NewData]);
+        assert!(bridge.is_retraining());
+
+        // 训练被阻塞期间继续接收快照，且不会再次触发
+        let during = bridge.consume((3..10).map(load_snapshot_at(0.0)));
+        assert!(during.is_empty());
+        assert!(bridge.is_retraining());
+        // 后台线程拿到引擎后进入训练状态
+        let deadline = std::time::Instant::now() + Duration::from_secs(5);
+        while !matches!(bridge.model_states()["online"], ModelState::Training) {
+            assert!(std::time::Instant::now() < deadline, "retraining never started");
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(bridge.stats().ingested, 10);
+        assert_eq!(bridge.training_data.lock().unwrap().len(), 10);
+
+        release.send(()).unwrap();
+        bridge.wait_for_retraining();
+        assert!(!bridge.is_retraining());
+        assert!(bridge.model_states()["online"].is_trained());
+        assert_eq!(bridge.stats().retrains_completed, 1);
+
+        // 训练期间累积的数据在下一个快照时触发新的训练
+        assert_eq!(bridge.ingest(&load_snapshot(10, 0.0)).unwrap(), Some(RetrainTrigger::NewData));
+        release.send(()).unwrap();
+        bridge.wait_for_retraining();
+        assert_eq!(bridge.stats().retrains_completed, 2);
+    }
+
+    /// 训练时 panic 的模型
+    struct PanickingModel;
+
+    impl MachineLearningModel for PanickingModel {
+        fn predict(&self, _input: &ModelInput) -> Result<ModelOutput, AiError> {
+            Ok(ModelOutput { predictions: vec![0.0], confidence: 1.0, explanation: None, contributions: Vec::new() })
+        }
+
+        fn train(&mut self, _data: &[TrainingDataPoint]) -> Result<(), AiError> {
+            panic!("training blew up");
+        }
+
+        fn evaluate(&self, _test_data: &[TrainingDataPoint]) -> Result<ModelMetrics, AiError> {
+            Ok(ModelMetrics { accuracy: 0.0, precision: 0.0, recall: 0.0, f1_score: 0.0, loss: 0.0 })
+        }
+
+        fn get_name(&self) -> String {
+            "panicking".to_string()
+        }
+    }
+
+    #[test]
+    fn test_online_bridge_recovers_from_a_panicking_retrain() {
+        let bridge = online_bridge(
+            Box::new(PanickingModel),
+            OnlineLearningConfig {
+                retrain_after: 3,
+                drift_model: Some("online".to_string()),
+                ..OnlineLearningConfig::default()
+            },
+        );
+        assert_eq!(bridge.consume((0..3).map(load_snapshot_at(0.0))), vec![RetrainTrigger::NewData]);
+        bridge.wait_for_retraining();
+        assert!(!bridge.is_retraining());
+        let stats = bridge.stats();
+        assert_eq!((stats.retrains_completed, stats.retrains_failed), (0, 1));
+        assert!(stats.last_error.is_some_and(|error| error.contains("panic")));
+
+        // 引擎的锁已中毒，桥接器仍能接收快照并再次触发训练
+        assert_eq!(bridge.consume((3..6).map(load_snapshot_at(0.0))), vec![RetrainTrigger::NewData]);
+        bridge.wait_for_retraining();
+        assert_eq!(bridge.stats().retrains_failed, 2);
+        assert_eq!(bridge.stats().ingested, 6);
+    }
+
+    fn tree_search_engine() -> AiOptimizationEngine {
+        let mut engine = engine(Path::new("unused"));
+        engine.add_model("tree".to_string(), Box::new(DecisionTreeModel::new("tree".to_string())));
+        engine.training_data.lock().unwrap().extend(noisy_surface(0..120));
+        engine
+    }
+
+    #[test]
+    fn test_grid_search_ranks_every_combination() {
+        let grid = ParamGrid::default().with_max_depths(vec![0, 2, 6]).with_min_samples_leaf(vec![1, 10]);
+        let mut engine = tree_search_engine();
+        let report = engine.grid_search("tree", &grid, 3, &GridSearchOptions::default()).unwrap();
+
+        assert_eq!((report.results.len(), report.total_combinations), (6, 6));
+        assert!(!report.cancelled && !report.applied);
+        let mut evaluated: Vec<(Option<usize>, Option<usize>)> = report.results.iter()
+            .map(|result| (result.params.max_depth, result.params.min_samples_leaf))
+            .collect();
+        evaluated.sort();
+        let expected: Vec<_> = [0, 2, 6].into_iter()
+            .flat_map(|depth| [1, 10].map(|leaf| (Some(depth), Some(leaf))))
+            .collect();
+        assert_eq!(evaluated, expected);
+        assert!(report.results.windows(2).all(|pair| pair[0].cross_validation.mean.loss <= pair[1].cross_validation.mean.loss));
+        assert!(report.results.iter().all(|result| result.params.learning_rate == 0.01 && result.params.batch_size == 32));
+        let best = report.best().unwrap().clone();
+        assert_ne!(best.params.max_depth, Some(0));
+
+        // 未请求应用时已注册的模型保持默认深度
+        let before = engine.cross_validate("tree", 3).unwrap();
+        assert_ne!(before.mean.loss, best.cross_validation.mean.loss);
+
+        let applied = engine.grid_search("tree", &grid, 3, &GridSearchOptions { apply_best: true, cancellation: None }).unwrap();
+        assert!(applied.applied);
+        assert_eq!(applied.best().unwrap().params, best.params);
+        assert_eq!(engine.cross_validate("tree", 3).unwrap().mean.loss, best.cross_validation.mean.loss);
+
+        assert!(matches!(engine.grid_search("missing", &grid, 3, &GridSearchOptions::default()), Err(AiError::ModelNotFound(_))));
+    }
+
+    #[test]
+    fn test_grid_search_applies_learning_rate_and_batch_size() {
+        let mut engine = network_engine(20, 0);
+        engine.config.learning_rate = 0.05;
+        engine.config.feature_scaling = Some(ScalingMode::Standardize);
+        engine.training_data.lock().unwrap().extend(plane(0..40, 0.1));
+        let grid = ParamGrid::default().with_learning_rates(vec![0.0001, 0.1]).with_batch_sizes(vec![4, 40]);
+
+        let report = engine.grid_search("network", &grid, 4, &GridSearchOptions::default()).unwrap();
+        assert_eq!(report.results.len(), 4);
+        let best = report.best().unwrap().params.clone();
+        assert_eq!(best.learning_rate, 0.1);
+        assert_eq!((engine.config.learning_rate, engine.config.batch_size), (0.05, 32));
+
+        let report = engine.grid_search("network", &grid, 4, &GridSearchOptions { apply_best: true, cancellation: None }).unwrap();
+        assert!(report.applied);
+        assert_eq!((engine.config.learning_rate, engine.config.batch_size), (best.learning_rate, best.batch_size));
+
+        // 神经网络没有树参数
+        let tree_grid = ParamGrid::default().with_max_depths(vec![3]);
+        assert!(matches!(engine.grid_search("network", &tree_grid, 4, &GridSearchOptions::default()), Err(AiError::NotSupported(_))));
+    }
+
+    /// 预测训练目标均值的模型，训练指定次数后取消搜索
+    #[derive(Clone)]
+    struct CancellingModel {
+        mean: f64,
+        trains: Arc<std::sync::atomic::AtomicUsize>,
+        cancel_after: usize,
+        cancellation: SearchCancellation,
+    }
+
+    impl MachineLearningModel for CancellingModel {
+        fn predict(&self, _input: &ModelInput) -> Result<ModelOutput, AiError> {
+            Ok(ModelOutput { predictions: vec![self.mean], confidence: 1.0, explanation: None, contributions: Vec::new() })
+        }
+
+        fn train(&mut self, data: &[TrainingDataPoint]) -> Result<(), AiError> {
+            self.mean = data.iter().map(|point| point.target[0]).sum::<f64>() / data.len() as f64;
+            if self.trains.fetch_add(1, Ordering::SeqCst) + 1 >= self.cancel_after {
+                self.cancellation.cancel();
+            }
+            Ok(())
+        }
+
+        fn evaluate(&self, test_data: &[TrainingDataPoint]) -> Result<ModelMetrics, AiError> {
+            let (rmse, r2) = regression_statistics(test_data, 1, |_| Ok(vec![self.mean]))?;
+            Ok(regression_metrics(rmse, r2))
+        }
+
+        fn get_name(&self) -> String {
+            "cancelling".to_string()
+        }
+
+        fn clone_model(&self) -> Result<Box<dyn MachineLearningModel>, AiError> {
+            Ok(Box::new(self.clone()))
+        }
+    }
+
+    #[test]
+    fn test_grid_search_cancellation_reports_partial_results() {
+        let cancellation = SearchCancellation::new();
+        let trains = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let mut engine = engine(Path::new("unused"));
+        engine.training_data.lock().unwrap().extend(plane(0..30, 0.0));
+        engine.add_model("model".to_string(), Box::new(CancellingModel {
+            mean: 0.0,
+            trains: Arc::clone(&trains),
+            cancel_after: 4,
+            cancellation: cancellation.clone(),
+        }));
+        let grid = ParamGrid::default().with_learning_rates(vec![0.1, 0.01, 0.001]);
+        let options = GridSearchOptions { apply_best: true, cancellation: Some(cancellation.clone()) };
+
+        // 3 折：第二个组合训练完第一折时取消，剩下的折不再训练，未完成的组合不计入结果
+        let report = engine.grid_search("model", &grid, 3, &options).unwrap();
+        assert!(report.cancelled);
+        assert!(!report.applied);
+        assert_eq!((report.results.len(), report.total_combinations), (1, 3));
+        assert_eq!(report.results[0].params.learning_rate, 0.1);
+        assert_eq!(trains.load(Ordering::SeqCst), 4);
+        assert_eq!(engine.config.learning_rate, 0.01);
+
+        // 已取消的标志让搜索立即返回
+        let report = engine.grid_search("model", &grid, 3, &options).unwrap();
+        assert!(report.cancelled && report.results.is_empty());
+        assert_eq!(trains.load(Ordering::SeqCst), 4);
+    }
+
+    #[test]
+    fn test_training_state_gates_strategies_and_persists() {
+        let dir = tempfile::tempdir().unwrap();
+        let name = PerformanceOptimizationStrategy.get_name();
+        let build = || {
+            let mut engine = engine(dir.path());
+            engine.add_model(name.clone(), Box::new(LinearRegressionModel::new(name.clone())));
+            engine.add_strategy(Box::new(PerformanceOptimizationStrategy));
+            engine.add_strategy(Box::new(MemoryOptimizationStrategy));
+            engine
+        };
+        let context = memory_context(MemoryUsagePattern::Growing, Some(800 * MIB), 1024 * MIB);
+        let strategy_names = |engine: &AiOptimizationEngine| -> Vec<String> {
+            let mut names: Vec<String> = engine.optimize(&context).unwrap().into_iter().map(|result| result.strategy_name).collect();
+            names.sort();
+            names
+        };
+
+        // 模型训练前只有不需要训练的策略运行
+        let mut engine = build();
+        assert!(matches!(engine.model_states()[&name], ModelState::Untrained));
+        assert_eq!(strategy_names(&engine), ["Memory Optimization"]);
+
+        engine.training_data.lock().unwrap().extend(plane(0..40, 0.1));
+        let before = Utc::now();
+        engine.train_models().unwrap();
+        let (at, loss) = match &engine.model_states()[&name] {
+            ModelState::Trained { at, data_points, metrics } => {
+                assert_eq!(*data_points, 32);
+                assert!(*at >= before && metrics.loss < 0.2, "{:?}", metrics);
+                (*at, metrics.loss)
+            }
+            state => panic!("unexpected state {:?}", state),
+        };
+        assert_eq!(strategy_names(&engine), ["Memory Optimization", "Performance Optimization"]);
+
+        // 训练失败时保留原状态
+        assert!(engine.train_model(&name, &[], &[]).is_err());
+        assert!(engine.model_states()[&name].is_trained());
+
+        engine.save_all_models().unwrap();
+        let mut restored = build();
+        assert_eq!(restored.load_models().loaded, [name.as_str()]);
+        match &restored.model_states()[&name] {
+            ModelState::Trained { at: restored_at, data_points, metrics } => {
+                assert_eq!((*restored_at, *data_points, metrics.loss), (at, 32, loss));
+            }
+            state => panic!("unexpected state {:?}", state),
+        }
+        assert_eq!(strategy_names(&restored), ["Memory Optimization", "Performance Optimization"]);
+
+        // 旧版本保存的模型没有状态文件，按未训练处理
+        std::fs::remove_file(state_path(&engine.model_path(&name))).unwrap();
+        let mut legacy = build();
+        assert_eq!(legacy.load_models().loaded, [name.as_str()]);
+        assert!(matches!(legacy.model_states()[&name], ModelState::Untrained));
+        assert_eq!(strategy_names(&legacy), ["Memory Optimization"]);
+    }
+
+    #[test]
+    fn test_linear_explanation_reports_dominant_term() {
+        let mut engine = engine(Path::new("unused"));
+        engine.add_model("latency".to_string(), Box::new(LinearRegressionModel::new("latency".to_string())));
+        engine.training_data.lock().unwrap().extend(plane(0..200, 0.0));
+        engine.config.validation_split = 0.0;
+        engine.train_models().unwrap();
+
+        // y = 2x₀ − 3x₁ + 1：x₁ = 4 时 −3·4 远大于 2·1
+        let input = point(vec![1.0, 4.0], vec![]).input;
+        let output = engine.predict("latency", &input).unwrap();
+        let top = output.top_contributor().unwrap();
+        assert_eq!((top.feature_index, top.label()), (1, "x1".to_string()));
+        assert!((top.value + 12.0).abs() < 1e-6, "{:?}", output.contributions);
+        assert!((output.contributions[1].value - 2.0).abs() < 1e-6);
+        assert!(output.explanation.as_ref().unwrap().ends_with("主要因素: x1 -12.0000, x0 +2.0000"), "{:?}", output.explanation);
+
+        engine.feature_names = vec!["cpu".to_string(), "memory".to_string()];
+        let output = engine.predict("latency", &input).unwrap();
+        assert_eq!(output.top_contributor().unwrap().feature_name.as_deref(), Some("memory"));
+        assert!(output.explanation.unwrap().ends_with("主要因素: memory -12.0000, cpu +2.0000"));
+        let output = engine.predict("latency", &point(vec![5.0, 0.5], vec![]).input).unwrap();
+        assert_eq!(output.top_contributor().unwrap().label(), "cpu");
+    }
+
+    #[test]
+    fn test_network_and_tree_contributions() {
+        // 神经网络的贡献与数值梯度一致
+        let network = trained_network();
+        let features = vec![1.5, -0.5];
+        let output = network.predict(&ModelInput { features: features.clone(), metadata: HashMap::new() }).unwrap();
+        assert_eq!(output.contributions.len(), 2);
+        for contribution in &output.contributions {
+            let shifted = |delta: f64| {
+                let mut shifted = features.clone();
+                shifted[contribution.feature_index] += delta;
+                network.predict(&ModelInput { features: shifted, metadata: HashMap::new() }).unwrap().predictions[0]
+            };
+            let numeric = (shifted(1e-6) - shifted(-1e-6)) / 2e-6;
+            assert!((contribution.value - numeric).abs() < 1e-5, "{} vs {}", contribution.value, numeric);
+        }
+        assert!(output.contributions[0].value.abs() >= output.contributions[1].value.abs());
+
+        // 树模型报告特征重要性
+        let data = noisy_surface(0..300);
+        let mut tree = DecisionTreeModel::new("tree".to_string()).with_max_depth(4);
+        tree.train(&data).unwrap();
+        let output = tree.predict(&data[0].input).unwrap();
+        let mut importances: Vec<f64> = vec![0.0; 3];
+        for contribution in &output.contributions {
+            importances[contribution.feature_index] = contribution.value;
+        }
+        assert_eq!(importances, tree.feature_importances());
+        assert_ne!(output.top_contributor().unwrap().feature_index, 2);
+    }
+
+    #[test]
+    fn test_strategies_surface_top_contributor() {
+        let name = PerformanceOptimizationStrategy.get_name();
+        let mut engine = engine(Path::new("unused"));
+        engine.add_model(name.clone(), Box::new(LinearRegressionModel::new(name.clone())));
+        engine.add_strategy(Box::new(PerformanceOptimizationStrategy));
+        engine.feature_names = vec!["cpu".to_string(), "memory".to_string()];
+        engine.training_data.lock().unwrap().extend(plane(0..40, 0.0));
+        engine.train_models().unwrap();
+
+        let mut context = memory_context(MemoryUsagePattern::Stable, None, 0);
+        context.current_metrics = HashMap::from([("cpu".to_string(), 1.0), ("memory".to_string(), 4.0)]);
+        let results = engine.optimize(&context).unwrap();
+        assert!(!results[0].recommendations.is_empty());
+        assert!(results[0].recommendations.iter().all(|r| r.description.ends_with("（主要因素: memory）")));
+
+        // 缺少特征指标时无法解释，建议保持原样
+        context.current_metrics.remove("memory");
+        let results = engine.optimize(&context).unwrap();
+        assert!(results[0].recommendations.iter().all(|r| !r.description.contains("主要因素")));
+    }
+
+    fn assert_batch_matches(model: &dyn MachineLearningModel, inputs: &[ModelInput]) {
+        let batch = model.predict_batch(inputs).unwrap();
+        assert_eq!(batch.len(), inputs.len());
+        for (input, batched) in inputs.iter().zip(&batch) {
+            let single = model.predict(input).unwrap();
+            assert_eq!(bits(single.clone()), bits(batched.clone()), "{}", model.get_name());
+            assert_eq!(single.confidence.to_bits(), batched.confidence.to_bits());
+            assert_eq!(single.explanation, batched.explanation);
+            assert_eq!(single.contributions, batched.contributions);
+        }
+    }
+
+    #[test]
+    fn test_batch_prediction_matches_sequential() {
+        let inputs: Vec<ModelInput> = plane(300..400, 0.0).into_iter().map(|point| point.input).collect();
+        let mut linear = LinearRegressionModel::new("linear".to_string());
+        linear.train(&plane(0..200, 0.1)).unwrap();
+        assert_batch_matches(&linear, &inputs);
+        assert_batch_matches(&trained_network(), &inputs);
+        let mut plain = PlainGradientModel { weights: Vec::new(), bias: 0.0 };
+        plain.train(&plane(0..50, 0.0)).unwrap();
+        assert_batch_matches(&plain, &inputs);
+
+        let surface = noisy_surface(0..200);
+        let surface_inputs: Vec<ModelInput> = noisy_surface(500..600).into_iter().map(|point| point.input).collect();
+        let mut tree = DecisionTreeModel::new("tree".to_string()).with_max_depth(6);
+        tree.train(&surface).unwrap();
+        assert_batch_matches(&tree, &surface_inputs);
+        let mut forest = RandomForestModel::new("forest".to_string()).with_seed(3);
+        forest.train(&surface).unwrap();
+        assert_batch_matches(&forest, &surface_inputs);
+
+        // 任一输入的维度不对时整批失败
+        let mut mixed = inputs[..3].to_vec();
+        mixed[1].features.push(1.0);
+        assert!(matches!(linear.predict_batch(&mixed), Err(AiError::PredictionError(_))));
+        assert!(matches!(forest.predict_batch(&mixed), Err(AiError::PredictionError(_))));
+        assert!(linear.predict_batch(&[]).unwrap().is_empty());
+
+        // 引擎的批量预测同样应用缩放器和特征名
+        let mut engine = network_engine(30, 0);
+        engine.config.feature_scaling = Some(ScalingMode::Standardize);
+        engine.feature_names = vec!["cpu".to_string(), "memory".to_string()];
+        engine.training_data.lock().unwrap().extend(plane(0..40, 0.0));
+        engine.train_models().unwrap();
+        let batch = engine.predict_batch("network", &inputs).unwrap();
+        for (input, batched) in inputs.iter().zip(batch) {
+            let single = engine.predict("network", input).unwrap();
+            assert_eq!(bits(single.clone()), bits(batched.clone()));
+            assert_eq!(single.contributions, batched.contributions);
+            assert!(batched.contributions[0].feature_name.is_some());
+        }
+    }
+
+    /// 线性模型批量预测只格式化一次模型方程，特征越多、批次越大，省下的格式化开销越明显
+    #[test]
+    #[ignore = "耗时比较，结果取决于机器负载，使用 --ignored 运行"]
+    fn test_batch_prediction_is_faster_than_sequential() {
+        let wide = |i: usize| -> Vec<f64> { (0..20).map(|feature| noise(i * 20 + feature, 5.0)).collect() };
+        let data: Vec<TrainingDataPoint> = (0..200).map(|i| {
+            let features = wide(i);
+            let target = features.iter().enumerate().map(|(feature, x)| x * feature as f64).sum();
+            point(features, vec![target])
+        }).collect();
+        let mut model = LinearRegressionModel::new("wide".to_string());
+        model.train(&data).unwrap();
+        let inputs: Vec<ModelInput> = (0..5_000).map(|i| ModelInput { features: wide(i + 1_000), metadata: HashMap::new() }).collect();
+
+        let fastest = |run: &dyn Fn()| {
+            (0..3).map(|_| {
+                let start = std::time::Instant::now();
+                run();
+                start.elapsed()
+            }).min().unwrap()
+        };
+        let sequential = fastest(&|| {
+            for input in &inputs {
+                model.predict(input).unwrap();
+            }
+        });
+        let batch = fastest(&|| {
+            model.predict_batch(&inputs).unwrap();
+        });
+        assert!(batch < sequential, "batch {:?} vs sequential {:?}", batch, sequential);
+    }
+
+    /// 记录调用方式的线性模型
+    struct CountingModel {
+        inner: LinearRegressionModel,
+        single_calls: Arc<std::sync::atomic::AtomicUsize>,
+        batch_calls: Arc<std::sync::atomic::AtomicUsize>,
+    }
+
+    impl MachineLearningModel for CountingModel {
+        fn predict(&self, input: &ModelInput) -> Result<ModelOutput, AiError> {
+            self.single_calls.fetch_add(1, Ordering::SeqCst);
+            self.inner.predict(input)
+        }
+
+        fn predict_batch(&self, inputs: &[ModelInput]) -> Result<Vec<ModelOutput>, AiError> {
+            self.batch_calls.fetch_add(1, Ordering::SeqCst);
+            self.inner.predict_batch(inputs)
+        }
+
+        fn train(&mut self, data: &[TrainingDataPoint]) -> Result<(), AiError> {
+            self.inner.train(data)
+        }
+
+        fn evaluate(&self, test_data: &[TrainingDataPoint]) -> Result<ModelMetrics, AiError> {
+            self.inner.evaluate(test_data)
+        }
+
+        fn get_name(&self) -> String {
+            self.inner.get_name()
+        }
+    }
+
+    /// 在候选 CPU 配额中选出预测延迟最低的一个
+    struct CpuQuotaStrategy;
+
+    impl AiOptimizationStrategy for CpuQuotaStrategy {
+        fn optimize(&self, _context: &OptimizationContext) -> Result<OptimizationResult, AiError> {
+            let mut result = MemoryOptimizationStrategy.optimize(&memory_context(MemoryUsagePattern::Stable, None, 0))?;
+            result.strategy_name = self.get_name();
+            Ok(result)
+        }
+
+        fn get_name(&self) -> String {
+            "cpu_quota".to_string()
+        }
+
+        fn get_priority(&self) -> OptimizationPriority {
+            OptimizationPriority::Medium
+        }
+
+        fn requires_training(&self) -> bool {
+            true
+        }
+
+        fn candidate_configurations(&self, context: &OptimizationContext) -> Vec<HashMap<String, f64>> {
+            (0..500).map(|step| {
+                let mut candidate = context.current_metrics.clone();
+                candidate.insert("cpu".to_string(), step as f64 * 0.01);
+                candidate
+            }).collect()
+        }
+
+        fn apply_scores(&self, result: &mut OptimizationResult, candidates: &[HashMap<String, f64>], scores: &[ModelOutput]) {
+            let best = scores.iter().enumerate()
+                .min_by(|a, b| a.1.predictions[0].total_cmp(&b.1.predictions[0]))
+                .map(|(index, _)| index)
+                .unwrap();
+            result.recommendations.push(OptimizationRecommendation {
+                recommendation_type: RecommendationType::ParameterTuning,
+                description: format!("cpu={:.2}", candidates[best]["cpu"]),
+                expected_benefit: 0.1,
+                implementation_cost: ImplementationCost::Low,
+                time_horizon: TimeHorizon::ShortTerm,
+                dependencies: Vec::new(),
+            });
+        }
+    }
+
+    #[test]
+    fn test_optimize_scores_candidates_in_one_batch() {
+        let (single_calls, batch_calls) = (Arc::new(std::sync::atomic::AtomicUsize::new(0)), Arc::new(std::sync::atomic::AtomicUsize::new(0)));
+        let mut engine = engine(Path::new("unused"));
+        engine.add_model("cpu_quota".to_string(), Box::new(CountingModel {
+            inner: LinearRegressionModel::new("cpu_quota".to_string()),
+            single_calls: Arc::clone(&single_calls),
+            batch_calls: Arc::clone(&batch_calls),
+        }));
+        engine.add_strategy(Box::new(CpuQuotaStrategy));
+        engine.training_data.lock().unwrap().extend(plane(0..40, 0.0));
+        engine.train_models().unwrap();
+
+        // 没有特征名时无法打分，结果保持原样
+        let mut context = memory_context(MemoryUsagePattern::Stable, None, 0);
+        context.current_metrics = HashMap::from([("cpu".to_string(), 3.0), ("memory".to_string(), 1.0)]);
+        let unscored = engine.optimize(&context).unwrap();
+        assert!(unscored[0].recommendations.is_empty());
+
+        // y = 2·cpu − 3·memory + 1 在最小的 CPU 配额处最低
+        engine.feature_names = vec!["cpu".to_string(), "memory".to_string()];
+        single_calls.store(0, Ordering::SeqCst);
+        let results = engine.optimize(&context).unwrap();
+        assert_eq!(results[0].recommendations.len(), 1);
+        assert_eq!(results[0].recommendations[0].description, "cpu=0.00（主要因素: cpu）");
+        assert_eq!(batch_calls.load(Ordering::SeqCst), 1);
+        // 只有解释当前指标时单独预测一次
+        assert_eq!(single_calls.load(Ordering::SeqCst), 1);
+    }
 }
@@ -3,8 +3,18 @@
 //! 本模块提供了完整的 API 网关和微服务架构支持
 
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
+use crate::monitoring_advanced::{
+    BucketLayout, ExportFormat, HealthCheck, HealthCheckError, HealthCheckResult, HealthStatus, LogEntry, LogLevel,
+    MetricMetadata, MetricValue, MetricsCollector, MetricsConfig, StructuredLogger,
+};
+use crate::security_advanced::Principal;
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock, Weak};
+use std::time::{SystemTime, UNIX_EPOCH};
 use std::time::{Duration, Instant};
 use std::fmt;
 use thiserror::Error;
@@ -14,14 +24,32 @@ use thiserror::Error;
 pub struct ApiGatewayManager {
     /// 路由配置
     pub routes: Arc<Mutex<HashMap<String, Route>>>,
-    /// 中间件
-    pub middlewares: Vec<Box<dyn Middleware>>,
+    /// 已编译的路由模式，按优先级排序
+    route_patterns: Arc<Mutex<Vec<CompiledRoute>>>,
+    /// 全局中间件，按注册顺序执行
+    pub middlewares: Vec<Arc<dyn Middleware>>,
+    /// 按名称注册、供路由引用的中间件
+    named_middlewares: HashMap<String, Arc<dyn Middleware>>,
     /// 负载均衡器
     pub load_balancer: LoadBalancer,
     /// 限流器
     pub rate_limiter: RateLimiter,
     /// 缓存
     pub cache: Cache,
+    /// 上游客户端
+    pub upstream: Arc<dyn UpstreamClient>,
+    /// 网关计数
+    metrics: GatewayCounters,
+    /// 按路由和上游记录的请求指标
+    pub metrics_collector: Arc<MetricsCollector>,
+    /// 各路由进行中的请求数，按路由模板索引
+    route_in_flight: Mutex<HashMap<String, usize>>,
+    /// 后台主动健康检查任务
+    health_check_task: Mutex<Option<tokio::task::JoinHandle<()>>>,
+    /// 路由未配置 CORS 策略时使用的全局策略
+    cors: Option<CorsPolicy>,
+    /// 请求大小和超时的默认限制，路由可逐项覆盖
+    limits: RequestLimits,
 }
 
 /// 路由
@@ -34,15 +62,518 @@ pub struct Route {
     pub method: HttpMethod,
     /// 目标服务
     pub target_service: String,
-    /// 中间件
+    /// 路由中间件名称，在全局中间件之后按顺序执行
     pub middlewares: Vec<String>,
-    /// 超时
+    /// 上游超时，为零时不生效；`limits.upstream_timeout` 已设置时以后者为准
+    #[deprecated(note = "use `limits.upstream_timeout` instead")]
     pub timeout: Duration,
+    /// 覆盖网关默认值的请求大小和超时限制
+    pub limits: RouteLimits,
+    /// 上游响应未声明 `max-age` 时的缓存时长，`None` 表示不缓存这类响应
+    pub cache_ttl: Option<Duration>,
+    /// 转发前对请求体的转换
+    pub request_transform: Option<TransformSpec>,
+    /// 返回前对响应体和状态码的转换
+    pub response_transform: Option<TransformSpec>,
+    /// 上游失败时的重试策略，`None` 表示不重试
+    pub retry: Option<RetryPolicy>,
+    /// 跨域策略，`None` 时使用网关的全局策略
+    pub cors: Option<CorsPolicy>,
+    /// 流式路由（如服务端推送事件）的中继配置；`Upgrade: websocket` 请求在未配置时使用默认值
+    pub streaming: Option<StreamingConfig>,
+    /// 按权重在多个上游组之间分流，`None` 表示使用全部上游实例
+    pub split: Option<TrafficSplit>,
+}
+
+impl Route {
+    /// 路由的限制覆盖值，未设置 `limits.upstream_timeout` 时沿用已废弃的 `timeout`
+    /// The route's limit overrides, falling back to the deprecated `timeout` when `limits.upstream_timeout` is unset
+    #[allow(deprecated)]
+    pub fn limit_overrides(&self) -> RouteLimits {
+        let mut limits = self.limits.clone();
+        if limits.upstream_timeout.is_none() && !self.timeout.is_zero() {
+            limits.upstream_timeout = Some(self.timeout);
+        }
+        limits
+    }
+}
+
+/// 按权重把路由流量分给多个上游组（如同一模块的不同版本），用于灰度发布
+/// Weighted traffic split of a route across upstream groups, e.g. versions of one module, for canary rollouts
+///
+/// 配置 `sticky` 后按客户端键的哈希分配版本，同一客户端总是落到同一版本；调整权重时只有落在变动区间内的客户端
+/// 会换版本。`override_header` 的值为 `always` 时强制使用 `canary` 版本，为某个版本名时强制使用该版本。
+/// With `sticky` set a hash of the client key picks the version, so a client keeps hitting the same one and a
+/// weight change only moves the clients inside the shifted range. An `override_header` value of `always` forces
+/// the `canary` version and a version name forces that version.
+///
+/// 粘性哈希使用 FNV-1a，与进程和 Rust 版本无关，重启或多副本部署时同一客户端仍落到同一版本。
+/// 响应缓存按所选版本分别存放，不同版本的响应不会互相命中。
+/// The sticky hash is FNV-1a, independent of process and Rust version, so a client stays on its version across
+/// restarts and replicas. Cached responses are kept per chosen version and never served across versions.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TrafficSplit {
+    /// 上游组，权重之和不必为 100
+    pub groups: Vec<UpstreamGroup>,
+    /// 粘性分配使用的客户端键，`None` 表示每个请求独立随机分配
+    pub sticky: Option<StickyKey>,
+    /// 强制选择版本的请求头，如 `X-Canary`
+    pub override_header: Option<String>,
+    /// `override_header` 为 `always` 时使用的版本
+    pub canary: Option<String>,
+}
+
+/// 分流的一个上游组
+/// One upstream group of a traffic split
+#[derive(Debug, Clone, PartialEq)]
+pub struct UpstreamGroup {
+    /// 版本名，如 `v1`，用作指标标签
+    pub version: String,
+    /// 权重
+    pub weight: u32,
+    /// 组内上游实例的地址，在组内仍按负载均衡策略选择
+    pub instances: Vec<String>,
+}
+
+/// 粘性分流的客户端键
+/// Client key of a sticky traffic split
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StickyKey {
+    /// 客户端 IP
+    ClientIp,
+    /// 指定请求头的值，缺失时退回客户端 IP
+    Header(String),
+    /// 认证调用方的主体，未认证时退回客户端 IP
+    Principal,
+}
+
+/// 流式连接的中继配置
+/// Relay configuration of a streaming connection
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StreamingConfig {
+    /// 客户端发往上游的单帧最大字节数
+    pub max_client_frame: usize,
+    /// 上游发往客户端的单帧最大字节数
+    pub max_upstream_frame: usize,
+    /// 任一方向超过该时长没有收到帧时关闭连接
+    pub idle_timeout: Duration,
+    /// 每个方向的缓冲帧数
+    pub buffer: usize,
+}
+
+impl Default for StreamingConfig {
+    fn default() -> Self {
+        Self {
+            max_client_frame: 1024 * 1024,
+            max_upstream_frame: 1024 * 1024,
+            idle_timeout: Duration::from_secs(60),
+            buffer: 32,
+        }
+    }
+}
+
+/// 流式连接中的一帧
+/// A frame of a streaming connection
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StreamFrame {
+    /// 文本帧
+    Text(String),
+    /// 二进制帧或数据块
+    Binary(Vec<u8>),
+    /// 关闭帧，携带 WebSocket 关闭码和原因
+    Close { code: u16, reason: String },
+}
+
+/// 双向帧通道的一端
+/// One end of a bidirectional frame channel
+#[derive(Debug)]
+pub struct StreamChannel {
+    /// 发往对端的帧
+    pub sender: tokio::sync::mpsc::Sender<StreamFrame>,
+    /// 来自对端的帧
+    pub receiver: tokio::sync::mpsc::Receiver<StreamFrame>,
+}
+
+/// 中继帧的方向
+/// Direction a frame is relayed in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StreamDirection {
+    /// 客户端到上游
+    ClientToUpstream,
+    /// 上游到客户端
+    UpstreamToClient,
+}
+
+/// 流式连接结束的原因
+/// Why a streaming connection ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StreamCloseReason {
+    /// 客户端发送关闭帧或断开
+    ClientClosed,
+    /// 上游发送关闭帧或断开
+    UpstreamClosed,
+    /// 空闲超时
+    IdleTimeout,
+    /// 某个方向的帧超过大小限制
+    FrameTooLarge(StreamDirection),
+}
+
+/// 流式连接的中继统计
+/// Relay statistics of a streaming connection
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StreamSummary {
+    /// 转发给上游的帧数
+    pub frames_to_upstream: u64,
+    /// 转发给客户端的帧数
+    pub frames_to_client: u64,
+    /// 结束原因
+    pub close_reason: StreamCloseReason,
+}
+
+/// 已建立的流式连接
+/// An established streaming connection
+#[derive(Debug)]
+pub struct StreamSession {
+    /// 握手响应：WebSocket 为 101，流式路由为 200
+    pub response: Response,
+    /// 客户端一端的帧通道
+    pub channel: StreamChannel,
+    /// 中继任务，连接结束时返回统计
+    pub relay: tokio::task::JoinHandle<StreamSummary>,
+}
+
+/// 流式请求的处理结果
+/// Outcome of a streaming request
+#[derive(Debug)]
+pub enum StreamUpgrade {
+    /// 已升级，开始中继
+    Accepted(StreamSession),
+    /// 被中间件、路由或握手校验拒绝
+    Rejected(Response),
+}
+
+/// 跨域资源共享（CORS）策略
+/// Cross-origin resource sharing (CORS) policy
+///
+/// `allowed_origins` 中的每一项是完整的源（如 `https://app.example.com`）、匹配子域名的后缀模式
+/// （如 `https://*.example.com`）或 `*`。匹配只做整串或后缀比较，不做子串匹配；`*` 不能与
+/// `allow_credentials` 同时使用。
+/// Each entry of `allowed_origins` is a full origin (e.g. `https://app.example.com`), a subdomain suffix
+/// pattern (e.g. `https://*.example.com`) or `*`. Matching is whole-string or suffix only, never substring,
+/// and `*` cannot be combined with `allow_credentials`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CorsPolicy {
+    /// 允许的源
+    pub allowed_origins: Vec<String>,
+    /// 预检请求允许的方法
+    pub allowed_methods: Vec<HttpMethod>,
+    /// 预检请求允许的请求头，`*` 表示允许任意请求头
+    pub allowed_headers: Vec<String>,
+    /// 允许浏览器读取的响应头
+    pub exposed_headers: Vec<String>,
+    /// 是否允许携带凭据
+    pub allow_credentials: bool,
+    /// 预检结果的缓存时长
+    pub max_age: Option<Duration>,
+}
+
+/// 上游重试策略
+/// Upstream retry policy
+///
+/// 只重试连接错误和 5xx 响应。幂等方法（GET/HEAD/PUT/DELETE）总是可以重试，其他方法只有在
+/// 配置了 `idempotency_key_header` 且请求带有该头部时才会重试。每次重试都会重新经过负载均衡选择实例。
+/// Only connection errors and 5xx responses are retried. Idempotent methods (GET/HEAD/PUT/DELETE) are always
+/// retryable; other methods only when `idempotency_key_header` is configured and present on the request.
+/// Every retry goes through the load balancer again.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// 最多尝试次数，包含首次请求
+    pub max_attempts: u32,
+    /// 首次重试前的退避时长，之后每次翻倍
+    pub base_backoff: Duration,
+    /// 退避时长上限
+    pub max_backoff: Duration,
+    /// 随机抖动比例（0.0 ~ 1.0），实际退避在 `[backoff * (1 - jitter), backoff]` 之间
+    pub jitter: f64,
+    /// 整个请求（含全部尝试和退避）的截止时长
+    pub deadline: Option<Duration>,
+    /// 非幂等方法重试时要求携带的幂等键头部
+    pub idempotency_key_header: Option<String>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_backoff: Duration::from_millis(50),
+            max_backoff: Duration::from_secs(1),
+            jitter: 0.5,
+            deadline: None,
+            idempotency_key_header: None,
+        }
+    }
+}
+
+/// 请求大小和超时限制
+/// Request size and timeout limits
+///
+/// 网关持有一份默认值，路由通过 [`RouteLimits`] 逐项覆盖。请求截止时间覆盖全部重试和退避，
+/// 与 [`RetryPolicy::deadline`] 同时配置时取较短者。
+/// The gateway holds the defaults and routes override them field by field through [`RouteLimits`]. The request
+/// deadline covers every retry and backoff; when [`RetryPolicy::deadline`] is also set the shorter one wins.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RequestLimits {
+    /// 请求体最大字节数，超过时返回 413
+    pub max_body_bytes: usize,
+    /// 最多头部个数，超过时返回 431
+    pub max_header_count: usize,
+    /// 全部头部名称和值的最大总字节数，超过时返回 431
+    pub max_header_bytes: usize,
+    /// 单次上游请求等待响应的时长，超时返回 504
+    pub upstream_timeout: Duration,
+    /// 整个请求（含全部重试和退避）的截止时长，超时返回 504
+    pub request_deadline: Option<Duration>,
+}
+
+impl Default for RequestLimits {
+    fn default() -> Self {
+        Self {
+            max_body_bytes: 10 * 1024 * 1024,
+            max_header_count: 100,
+            max_header_bytes: 64 * 1024,
+            upstream_timeout: Duration::from_secs(30),
+            request_deadline: None,
+        }
+    }
+}
+
+/// 路由级别的限制覆盖，`None` 表示沿用网关默认值
+/// Per-route limit overrides; `None` keeps the gateway default
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RouteLimits {
+    /// 请求体最大字节数
+    pub max_body_bytes: Option<usize>,
+    /// 最多头部个数
+    pub max_header_count: Option<usize>,
+    /// 全部头部的最大总字节数
+    pub max_header_bytes: Option<usize>,
+    /// 单次上游请求的超时
+    pub upstream_timeout: Option<Duration>,
+    /// 整个请求的截止时长
+    pub request_deadline: Option<Duration>,
+}
+
+/// 声明式的 JSON 消息体转换
+/// Declarative JSON body transformation
+///
+/// 规则按顺序作用于 JSON 消息体（`Content-Type` 含 `json`），其他消息体原样通过；
+/// `status_map` 只对响应生效。字段路径用 `.` 分隔，如 `meta.version`。
+/// Rules apply in order to JSON bodies (a `Content-Type` containing `json`) while other bodies pass through
+/// untouched; `status_map` only affects responses. Field paths are dot separated, e.g. `meta.version`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TransformSpec {
+    /// 转换规则
+    pub rules: Vec<TransformRule>,
+    /// 状态码映射
+    pub status_map: HashMap<u16, u16>,
+}
+
+/// 单条转换规则
+/// A single transformation rule
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum TransformRule {
+    /// 重命名字段，源字段不存在时忽略
+    Rename { from: String, to: String },
+    /// 删除字段，字段不存在时忽略
+    Remove { path: String },
+    /// 设置字段，按需创建中间对象
+    Add { path: String, value: serde_json::Value },
+    /// 把整个消息体包装到信封字段中
+    Wrap { field: String },
+    /// 用信封字段的值替换整个消息体
+    Unwrap { field: String },
+}
+
+/// 路由路径模式
+/// Route path pattern
+///
+/// `{name}` 捕获单个路径段，`*rest` 捕获剩余的全部路径段（可以为空），只能出现在末尾。
+/// 匹配前会忽略末尾的 `/`，并对每个路径段做百分号解码。
+/// `{name}` captures a single segment and `*rest` captures the remaining segments (possibly none) and
+/// must come last. A trailing `/` is ignored and every segment is percent-decoded before matching.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RoutePattern {
+    /// 路径段
+    pub segments: Vec<PathSegment>,
+}
+
+/// 路径模式中的一段
+/// A segment of a path pattern
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PathSegment {
+    /// 字面量
+    Literal(String),
+    /// 单段参数
+    Param(String),
+    /// 尾部通配
+    Wildcard(String),
+}
+
+impl PathSegment {
+    /// 优先级等级：字面量最优先，其次参数，最后通配
+    /// Priority rank: literals first, then parameters, then wildcards
+    fn rank(&self) -> u8 {
+        match self {
+            PathSegment::Literal(_) => 0,
+            PathSegment::Param(_) => 1,
+            PathSegment::Wildcard(_) => 2,
+        }
+    }
+}
+
+impl RoutePattern {
+    /// 解析路径模式
+    /// Parse a path pattern
+    pub fn parse(path: &str) -> Result<Self, GatewayError> {
+        let mut segments = Vec::new();
+        let raw_segments = split_path(path);
+        for (index, raw) in raw_segments.iter().enumerate() {
+            let segment = if let Some(name) = raw.strip_prefix('{') {
+                let name = name.strip_suffix('}')
+                    .ok_or_else(|| GatewayError::RoutingError(format!("路径参数缺少 `}}`: {}", path)))?;
+                PathSegment::Param(validate_param_name(name, path)?)
+            } else if let Some(name) = raw.strip_prefix('*') {
+                if index + 1 != raw_segments.len() {
+                    return Err(GatewayError::RoutingError(format!("通配符 `*{}` 只能出现在路径末尾: {}", name, path)));
+                }
+                PathSegment::Wildcard(validate_param_name(name, path)?)
+            } else {
+                PathSegment::Literal(percent_decode(raw))
+            };
+            segments.push(segment);
+        }
+
+        let mut names: Vec<&str> = segments.iter()
+            .filter_map(|segment| match segment {
+                PathSegment::Param(name) | PathSegment::Wildcard(name) => Some(name.as_str()),
+                PathSegment::Literal(_) => None,
+            })
+            .collect();
+        names.sort_unstable();
+        if let Some(pair) = names.windows(2).find(|pair| pair[0] == pair[1]) {
+            return Err(GatewayError::RoutingError(format!("路径参数 `{}` 重复: {}", pair[0], path)));
+        }
+        Ok(Self { segments })
+    }
+
+    /// 匹配请求路径，成功时返回捕获的参数
+    /// Match a request path, returning the captured parameters on success
+    pub fn matches(&self, path: &str) -> Option<HashMap<String, String>> {
+        let decoded: Vec<String> = split_path(path).into_iter().map(percent_decode).collect();
+        let mut params = HashMap::new();
+        for (index, segment) in self.segments.iter().enumerate() {
+            match segment {
+                PathSegment::Literal(literal) => {
+                    if decoded.get(index) != Some(literal) {
+                        return None;
+                    }
+                }
+                PathSegment::Param(name) => {
+                    params.insert(name.clone(), decoded.get(index)?.clone());
+                }
+                PathSegment::Wildcard(name) => {
+                    params.insert(name.clone(), decoded[index.min(decoded.len())..].join("/"));
+                    return Some(params);
+                }
+            }
+        }
+        (decoded.len() == self.segments.len()).then_some(params)
+    }
+
+    /// 优先级键：逐段比较等级，越小越优先，因此字面量前缀最长的模式胜出
+    /// Priority key compared segment by segment, smaller first, so the longest literal prefix wins
+    fn priority(&self) -> Vec<u8> {
+        self.segments.iter().map(PathSegment::rank).collect()
+    }
+
+    /// 忽略参数名后的形状；形状相同的两个模式匹配完全相同的路径
+    /// Shape ignoring parameter names; two patterns with the same shape match exactly the same paths
+    fn shape(&self) -> Vec<PathSegment> {
+        self.segments.iter()
+            .map(|segment| match segment {
+                PathSegment::Literal(_) => segment.clone(),
+                PathSegment::Param(_) => PathSegment::Param(String::new()),
+                PathSegment::Wildcard(_) => PathSegment::Wildcard(String::new()),
+            })
+            .collect()
+    }
+}
+
+/// 已编译的路由
+/// Compiled route
+#[derive(Debug, Clone)]
+struct CompiledRoute {
+    /// 路由表中的键
+    key: String,
+    /// 方法
+    method: HttpMethod,
+    /// 路径模式
+    pattern: RoutePattern,
+}
+
+/// 拆分路径段，忽略开头和末尾的 `/`
+/// Split a path into segments, ignoring the leading and trailing `/`
+fn split_path(path: &str) -> Vec<&str> {
+    let path = path.split(['?', '#']).next().unwrap_or_default();
+    let trimmed = path.strip_prefix('/').unwrap_or(path);
+    let trimmed = trimmed.strip_suffix('/').unwrap_or(trimmed);
+    if trimmed.is_empty() {
+        Vec::new()
+    } else {
+        trimmed.split('/').collect()
+    }
+}
+
+/// 校验路径参数名
+/// Validate a path parameter name
+fn validate_param_name(name: &str, path: &str) -> Result<String, GatewayError> {
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return Err(GatewayError::RoutingError(format!("无效的路径参数名 `{}`: {}", name, path)));
+    }
+    Ok(name.to_string())
+}
+
+/// 百分号解码单个路径段；无效的转义按原样保留
+/// Percent-decode a single path segment, keeping invalid escapes as they are
+fn percent_decode(segment: &str) -> String {
+    let bytes = segment.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let escaped = (bytes[index] == b'%')
+            .then(|| segment.get(index + 1..index + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                index += 3;
+            }
+            None => {
+                decoded.push(bytes[index]);
+                index += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
 }
 
 /// HTTP 方法
 /// HTTP Method
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum HttpMethod {
     GET,
     POST,
@@ -53,6 +584,31 @@ pub enum HttpMethod {
     HEAD,
 }
 
+impl HttpMethod {
+    /// 是否为可安全重试的幂等方法
+    /// Whether the method is idempotent and therefore safe to retry
+    pub fn is_idempotent(&self) -> bool {
+        matches!(self, HttpMethod::GET | HttpMethod::HEAD | HttpMethod::PUT | HttpMethod::DELETE)
+    }
+}
+
+impl std::str::FromStr for HttpMethod {
+    type Err = GatewayError;
+
+    fn from_str(method: &str) -> Result<Self, GatewayError> {
+        match method.to_ascii_uppercase().as_str() {
+            "GET" => Ok(HttpMethod::GET),
+            "POST" => Ok(HttpMethod::POST),
+            "PUT" => Ok(HttpMethod::PUT),
+            "DELETE" => Ok(HttpMethod::DELETE),
+            "PATCH" => Ok(HttpMethod::PATCH),
+            "OPTIONS" => Ok(HttpMethod::OPTIONS),
+            "HEAD" => Ok(HttpMethod::HEAD),
+            _ => Err(GatewayError::RoutingError(format!("不支持的 HTTP 方法: {}", method))),
+        }
+    }
+}
+
 impl fmt::Display for HttpMethod {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -67,13 +623,145 @@ impl fmt::Display for HttpMethod {
     }
 }
 
+/// 中间件对请求的处理结果
+/// Outcome of a middleware request hook
+#[derive(Debug, Clone)]
+pub enum MiddlewareAction {
+    /// 继续执行后续中间件并转发请求
+    Continue,
+    /// 直接返回该响应，跳过后续中间件和转发
+    ShortCircuit(Response),
+}
+
 /// 中间件接口
 /// Middleware Interface
+///
+/// `on_request` 按注册顺序执行（先全局、后路由），`on_response` 按相反顺序执行，
+/// 且只对已执行过 `on_request` 的中间件调用，即使后面的中间件短路也是如此。
+/// 钩子返回的错误或 panic 会被转换为 500 响应。
+/// `on_request` runs in registration order (global first, then per route) and `on_response` runs in reverse
+/// for every middleware whose `on_request` ran, even when a later one short-circuits. Errors and panics
+/// raised by either hook become 500 responses.
 pub trait Middleware: Send + Sync {
     /// 处理请求
-    fn handle(&self, request: &mut Request) -> Result<(), GatewayError>;
+    fn on_request(&self, _request: &mut Request) -> Result<MiddlewareAction, GatewayError> {
+        Ok(MiddlewareAction::Continue)
+    }
     /// 处理响应
-    fn handle_response(&self, response: &mut Response) -> Result<(), GatewayError>;
+    fn on_response(&self, _request: &Request, _response: &mut Response) -> Result<(), GatewayError> {
+        Ok(())
+    }
+}
+
+/// 请求 ID 中间件
+/// Request ID middleware
+///
+/// 请求未携带该头部时生成 UUID，并在响应中回写同一个 ID。
+/// Generates a UUID when the request lacks the header and echoes the ID on the response.
+#[derive(Debug, Clone)]
+pub struct RequestIdMiddleware {
+    /// 头部名称
+    pub header: String,
+}
+
+impl Default for RequestIdMiddleware {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RequestIdMiddleware {
+    /// 使用 `X-Request-Id` 头部创建
+    /// Create using the `X-Request-Id` header
+    pub fn new() -> Self {
+        Self::with_header("X-Request-Id")
+    }
+
+    /// 使用指定头部创建
+    /// Create using the given header
+    pub fn with_header(header: impl Into<String>) -> Self {
+        Self { header: header.into() }
+    }
+}
+
+impl Middleware for RequestIdMiddleware {
+    fn on_request(&self, request: &mut Request) -> Result<MiddlewareAction, GatewayError> {
+        if request.header(&self.header).is_none_or(str::is_empty) {
+            request.headers.retain(|name, _| !name.eq_ignore_ascii_case(&self.header));
+            request.headers.insert(self.header.clone(), uuid::Uuid::new_v4().to_string());
+        }
+        Ok(MiddlewareAction::Continue)
+    }
+
+    fn on_response(&self, request: &Request, response: &mut Response) -> Result<(), GatewayError> {
+        if let Some(request_id) = request.header(&self.header) {
+            response.headers.insert(self.header.clone(), request_id.to_string());
+        }
+        Ok(())
+    }
+}
+
+/// 访问日志中间件，每个请求写入一条结构化日志
+/// Access log middleware writing one structured entry per request
+///
+/// 5xx 响应记为 `Error`，4xx 记为 `Warn`，其余记为 `Info`。
+/// 5xx responses are logged at `Error`, 4xx at `Warn` and everything else at `Info`.
+pub struct AccessLogMiddleware {
+    /// 日志记录器
+    logger: Arc<StructuredLogger>,
+    /// 用于关联日志的请求 ID 头部
+    request_id_header: String,
+}
+
+impl AccessLogMiddleware {
+    /// 创建访问日志中间件
+    /// Create an access log middleware
+    pub fn new(logger: Arc<StructuredLogger>) -> Self {
+        Self { logger, request_id_header: "X-Request-Id".to_string() }
+    }
+
+    /// 使用指定的请求 ID 头部
+    /// Use the given request ID header
+    pub fn with_request_id_header(self, header: impl Into<String>) -> Self {
+        Self { request_id_header: header.into(), ..self }
+    }
+}
+
+impl Middleware for AccessLogMiddleware {
+    fn on_response(&self, request: &Request, response: &mut Response) -> Result<(), GatewayError> {
+        let level = match response.status_code {
+            500.. => LogLevel::Error,
+            400..=499 => LogLevel::Warn,
+            _ => LogLevel::Info,
+        };
+        if !self.logger.enabled(level) {
+            return Ok(());
+        }
+
+        let mut fields = HashMap::from([
+            ("method".to_string(), serde_json::Value::from(request.method.to_string())),
+            ("path".to_string(), serde_json::Value::from(request.path.clone())),
+            ("status".to_string(), serde_json::Value::from(response.status_code)),
+            ("client_ip".to_string(), serde_json::Value::from(request.client_ip.clone())),
+            ("duration_ms".to_string(), serde_json::Value::from(response.processing_time.as_secs_f64() * 1000.0)),
+        ]);
+        if let Some(request_id) = request.header(&self.request_id_header) {
+            fields.insert("request_id".to_string(), serde_json::Value::from(request_id));
+        }
+
+        // 日志写入失败不影响响应
+        let _ = self.logger.push(LogEntry {
+            timestamp: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default(),
+            level,
+            message: format!("{} {} {}", request.method, request.path, response.status_code),
+            fields,
+            trace_id: None,
+            span_id: None,
+            module: Some(module_path!().to_string()),
+            target: "api_gateway::access".to_string(),
+        });
+        Ok(())
+    }
 }
 
 /// 请求
@@ -92,6 +780,30 @@ pub struct Request {
     pub body: Option<Vec<u8>>,
     /// 客户端 IP
     pub client_ip: String,
+    /// 路由匹配时捕获的路径参数
+    pub path_params: HashMap<String, String>,
+    /// 认证中间件验证通过的调用方
+    pub principal: Option<Principal>,
+}
+
+impl Request {
+    /// 路由匹配时捕获的路径参数
+    /// Path parameters captured by route matching
+    pub fn path_params(&self) -> &HashMap<String, String> {
+        &self.path_params
+    }
+
+    /// 认证中间件验证通过的调用方
+    /// Principal verified by an authentication middleware
+    pub fn principal(&self) -> Option<&Principal> {
+        self.principal.as_ref()
+    }
+
+    /// 按名称查找头部，忽略大小写
+    /// Look up a header by name, ignoring case
+    pub fn header(&self, name: &str) -> Option<&str> {
+        header_value(&self.headers, name)
+    }
 }
 
 /// 响应
@@ -116,6 +828,291 @@ pub struct LoadBalancer {
     pub instances: Vec<ServiceInstance>,
     /// 策略
     pub strategy: LoadBalancingStrategy,
+    /// 各实例进行中的请求数，按地址索引
+    pub in_flight: Arc<Mutex<HashMap<String, usize>>>,
+    /// 各实例的熔断器，熔断中的实例会被降低优先级
+    pub circuit_breaker: Arc<CircuitBreaker>,
+    /// 各实例的健康检查状态，被摘除的实例不会被选中
+    pub health: Arc<UpstreamHealth>,
+    /// 策略的选择状态
+    selection: Mutex<SelectionState>,
+}
+
+/// 熔断器配置
+/// Circuit breaker configuration
+#[derive(Debug, Clone)]
+pub struct CircuitBreakerConfig {
+    /// 触发熔断的失败率（0.0 ~ 1.0）
+    pub failure_rate_threshold: f64,
+    /// 统计失败率的滚动窗口
+    pub window: Duration,
+    /// 窗口内至少需要的请求数，不足时不熔断
+    pub minimum_requests: u32,
+    /// 熔断持续时间，之后进入半开状态
+    pub open_duration: Duration,
+    /// 半开状态下允许的试探请求数，全部成功后恢复闭合
+    pub half_open_max_requests: u32,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            failure_rate_threshold: 0.5,
+            window: Duration::from_secs(30),
+            minimum_requests: 10,
+            open_duration: Duration::from_secs(30),
+            half_open_max_requests: 3,
+        }
+    }
+}
+
+/// 熔断状态
+/// Circuit state
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CircuitState {
+    /// 闭合，正常放行
+    Closed,
+    /// 断开，直接拒绝
+    Open,
+    /// 半开，放行有限的试探请求
+    HalfOpen,
+}
+
+/// 熔断器计数
+/// Circuit breaker counters
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CircuitBreakerMetrics {
+    /// 熔断次数
+    pub opened: u64,
+    /// 被直接拒绝的请求数
+    pub short_circuited: u64,
+    /// 半开状态下放行的试探请求数
+    pub half_open_trials: u64,
+    /// 恢复闭合的次数
+    pub closed: u64,
+}
+
+/// 单个上游实例的熔断状态
+/// Circuit of a single upstream
+#[derive(Debug)]
+struct UpstreamCircuit {
+    state: CircuitState,
+    /// 窗口内的请求结果：(时间, 是否成功)
+    outcomes: VecDeque<(Instant, bool)>,
+    /// `outcomes` 中失败的数量，随进出窗口增减
+    failures: usize,
+    /// 断开的时刻
+    opened_at: Instant,
+    /// 半开状态下进行中的试探请求数
+    trials_in_flight: u32,
+    /// 半开状态下成功的试探请求数
+    trial_successes: u32,
+}
+
+/// 按上游实例地址维护的熔断器
+/// Circuit breaker keyed by upstream address
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    /// 配置
+    pub config: CircuitBreakerConfig,
+    /// 时钟
+    pub clock: Arc<dyn GatewayClock>,
+    circuits: Mutex<HashMap<String, UpstreamCircuit>>,
+    metrics: Mutex<CircuitBreakerMetrics>,
+}
+
+/// 熔断器放行一个请求的凭证
+/// A permit for one request admitted by the circuit breaker
+///
+/// 用 [`record`](Self::record) 报告请求结果；未报告就被丢弃时（例如请求被取消）计为失败，半开状态的试探名额随之释放。
+/// Report the outcome with [`record`](Self::record); a permit dropped without a report, such as when the request
+/// is cancelled, counts as a failure, which also releases its half-open trial slot.
+#[derive(Debug)]
+#[must_use = "丢弃凭证会把请求计为失败"]
+pub struct CircuitPermit<'a> {
+    breaker: &'a CircuitBreaker,
+    address: String,
+    recorded: bool,
+}
+
+impl CircuitPermit<'_> {
+    /// 报告请求结果并按需切换状态，状态发生变化时返回新状态
+    /// Report the request outcome and switch state when needed, returning the new state on a transition
+    pub fn record(mut self, success: bool) -> Option<CircuitState> {
+        self.recorded = true;
+        self.breaker.record(&self.address, success)
+    }
+}
+
+impl Drop for CircuitPermit<'_> {
+    fn drop(&mut self) {
+        if !self.recorded {
+            log::debug!("发往上游 {} 的请求未报告结果，计为失败", self.address);
+            self.breaker.record(&self.address, false);
+        }
+    }
+}
+
+/// 上游主动健康检查配置
+/// Active upstream health check configuration
+#[derive(Debug, Clone)]
+pub struct UpstreamHealthConfig {
+    /// 探测路径，以 GET 请求访问
+    pub path: String,
+    /// 检查间隔
+    pub interval: Duration,
+    /// 单次探测的超时
+    pub timeout: Duration,
+    /// 被摘除的实例重新加入前需要连续通过的次数
+    pub healthy_threshold: u32,
+    /// 实例被摘除前需要连续失败的次数
+    pub unhealthy_threshold: u32,
+    /// 同时被摘除的实例最多占实例池的百分比；至少可以摘除一个实例，但从不摘除最后一个可用实例
+    pub max_ejection_percent: u32,
+    /// 基于真实流量的异常检测，`None` 表示只依赖主动检查
+    pub outlier_detection: Option<OutlierDetection>,
+}
+
+impl Default for UpstreamHealthConfig {
+    fn default() -> Self {
+        Self {
+            path: "/health".to_string(),
+            interval: Duration::from_secs(10),
+            timeout: Duration::from_secs(2),
+            healthy_threshold: 2,
+            unhealthy_threshold: 3,
+            max_ejection_percent: 50,
+            outlier_detection: None,
+        }
+    }
+}
+
+/// 被动异常检测规则
+/// Passive outlier detection rule
+///
+/// 转发的请求连续出现 5xx 或转发错误时立即摘除实例。主动检查可以提前让它重新加入；否则摘除 `ejection_interval`
+/// 后实例重新接收流量，下一个请求作为探测：成功则重新加入，失败则再次摘除。
+/// Ejects an instance as soon as forwarded requests see consecutive 5xx responses or errors. Active checks may
+/// re-admit it early; otherwise after `ejection_interval` the instance receives traffic again and the next request
+/// acts as a probe, re-admitting it on success and ejecting it again on failure.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutlierDetection {
+    /// 触发摘除的连续失败次数
+    pub consecutive_failures: u32,
+    /// 摘除后多久放行探测请求
+    pub ejection_interval: Duration,
+}
+
+impl Default for OutlierDetection {
+    fn default() -> Self {
+        Self { consecutive_failures: 5, ejection_interval: Duration::from_secs(30) }
+    }
+}
+
+/// 上游健康检查计数
+/// Upstream health counters
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UpstreamHealthMetrics {
+    /// 主动检查失败导致的摘除次数
+    pub ejections: u64,
+    /// 异常检测导致的摘除次数
+    pub outlier_ejections: u64,
+    /// 重新加入的次数
+    pub readmissions: u64,
+}
+
+/// 单个上游实例的健康状态
+/// Health state of a single upstream
+#[derive(Debug, Default)]
+struct UpstreamHealthState {
+    ejected: bool,
+    /// 异常检测摘除的时刻，主动检查摘除的实例为 `None`
+    outlier_ejected_at: Option<Instant>,
+    /// 主动检查连续通过的次数
+    consecutive_successes: u32,
+    /// 主动检查连续失败的次数
+    consecutive_failures: u32,
+    /// 真实流量连续失败的次数
+    passive_failures: u32,
+}
+
+/// 按上游实例地址维护的健康状态
+/// Upstream health keyed by address
+///
+/// 主动检查连续失败 `unhealthy_threshold` 次的实例被摘除，连续通过 `healthy_threshold` 次后重新加入。
+/// 摘除会使被摘除的实例超过 `max_ejection_percent` 时不摘除，实例继续接收流量。
+/// An instance failing `unhealthy_threshold` consecutive active checks is ejected and re-admitted after passing
+/// `healthy_threshold` consecutive checks. An ejection that would exceed `max_ejection_percent` is skipped and the
+/// instance keeps receiving traffic.
+#[derive(Debug)]
+pub struct UpstreamHealth {
+    /// 配置
+    pub config: UpstreamHealthConfig,
+    /// 时钟
+    pub clock: Arc<dyn GatewayClock>,
+    states: Mutex<HashMap<String, UpstreamHealthState>>,
+    metrics: Mutex<UpstreamHealthMetrics>,
+}
+
+/// 把上游实例池的健康状态接入 [`crate::monitoring_advanced::HealthChecker`]
+/// Exposes the health of an upstream pool to [`crate::monitoring_advanced::HealthChecker`]
+///
+/// 没有实例被摘除时健康，部分被摘除时降级，全部被摘除时不健康。
+/// Healthy when no instance is ejected, degraded when some are and unhealthy when all are.
+#[derive(Debug, Clone)]
+pub struct UpstreamPoolHealthCheck {
+    /// 实例地址
+    pub addresses: Vec<String>,
+    health: Arc<UpstreamHealth>,
+}
+
+/// 负载均衡策略的选择状态
+/// Selection state of the load balancing strategies
+#[derive(Debug, Default)]
+struct SelectionState {
+    /// 轮询位置
+    next_index: usize,
+    /// 平滑加权轮询的当前权重，按地址索引
+    current_weights: HashMap<String, i64>,
+}
+
+/// 选中的上游实例；释放时减少该实例的进行中请求数
+/// A picked upstream; dropping it decrements the instance's in-flight count
+#[derive(Debug)]
+pub struct UpstreamGuard {
+    /// 选中的实例
+    pub instance: ServiceInstance,
+    in_flight: Arc<Mutex<HashMap<String, usize>>>,
+}
+
+impl std::ops::Deref for UpstreamGuard {
+    type Target = ServiceInstance;
+
+    fn deref(&self) -> &ServiceInstance {
+        &self.instance
+    }
+}
+
+impl Drop for UpstreamGuard {
+    fn drop(&mut self) {
+        if let Ok(mut in_flight) = self.in_flight.lock()
+            && let Some(count) = in_flight.get_mut(&self.instance.address)
+        {
+            *count = count.saturating_sub(1);
+        }
+    }
+}
+
+/// 路由上进行中的请求；释放时减少该路由的进行中请求数
+struct RouteInFlight<'a> {
+    gateway: &'a ApiGatewayManager,
+    route: String,
+}
+
+impl Drop for RouteInFlight<'_> {
+    fn drop(&mut self) {
+        self.gateway.adjust_route_in_flight(&self.route, false);
+    }
 }
 
 /// 服务实例
@@ -146,12 +1143,76 @@ pub enum LoadBalancingStrategy {
 
 /// 限流器
 /// Rate Limiter
+///
+/// 按限流键（客户端 IP、请求头或路由）为每个调用方维护独立的令牌桶。
+/// Keeps an independent token bucket per caller, keyed by client IP, a request header or the route.
 #[derive(Debug)]
 pub struct RateLimiter {
-    /// 限制配置
+    /// 按限流键覆盖的限制配置
     pub limits: HashMap<String, RateLimit>,
+    /// 未单独配置的键使用的默认限制
+    pub default_limit: RateLimit,
     /// 令牌桶
     pub token_buckets: Arc<Mutex<HashMap<String, TokenBucket>>>,
+    /// 限流键的来源
+    pub key_source: RateLimitKeySource,
+    /// 最多跟踪的键数，超出时淘汰最久未使用的桶
+    pub max_tracked_keys: usize,
+    /// 空闲超过该时长的桶会被淘汰
+    pub idle_timeout: Duration,
+    /// 可信代理的地址；只有来自这些地址的请求才按 `X-Forwarded-For` 确定客户端
+    pub trusted_proxies: HashSet<String>,
+    /// 时钟
+    pub clock: Arc<dyn GatewayClock>,
+}
+
+/// 限流键的来源
+/// Source of the rate limit key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RateLimitKeySource {
+    /// 客户端 IP
+    ClientIp,
+    /// 指定请求头的值（如 `X-Api-Key`），缺失时退回客户端 IP
+    ///
+    /// `X-Forwarded-For` 只在请求来自可信代理时生效，从右向左取第一个不是可信代理的地址。
+    /// `X-Forwarded-For` is only honored for requests from trusted proxies, taking the right-most hop that is not
+    /// a trusted proxy.
+    Header(String),
+    /// 匹配到的路由
+    Route,
+}
+
+/// 限流判定结果
+/// Rate limit decision
+#[derive(Debug, Clone, PartialEq)]
+pub struct RateLimitDecision {
+    /// 是否放行
+    pub allowed: bool,
+    /// 桶容量
+    pub limit: u32,
+    /// 剩余令牌数
+    pub remaining: u32,
+    /// 被拒绝时距离下一个令牌可用的时间
+    pub retry_after: Option<Duration>,
+}
+
+/// 网关时钟
+/// Gateway clock
+pub trait GatewayClock: fmt::Debug + Send + Sync {
+    /// 当前时刻
+    /// Current instant
+    fn now(&self) -> Instant;
+}
+
+/// 系统时钟
+/// System clock
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemGatewayClock;
+
+impl GatewayClock for SystemGatewayClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
 }
 
 /// 速率限制
@@ -172,36 +1233,661 @@ pub struct RateLimit {
 pub struct TokenBucket {
     /// 容量
     pub capacity: u32,
-    /// 当前令牌数
-    pub tokens: u32,
+    /// 当前令牌数，按经过的时间连续补充
+    pub tokens: f64,
     /// 最后更新时间
     pub last_update: Instant,
     /// 填充速率
     pub refill_rate: f64,
 }
 
-/// 缓存
-/// Cache
-#[derive(Debug)]
-pub struct Cache {
-    /// 缓存存储
-    pub storage: Arc<Mutex<HashMap<String, CacheEntry>>>,
-    /// TTL 配置
-    pub default_ttl: Duration,
+/// JWT 验证密钥
+/// JWT verification key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JwtKey {
+    /// HMAC-SHA256 共享密钥
+    Hs256(Vec<u8>),
+    /// RSA 公钥，PKCS#1 `RSAPublicKey` 的 DER 编码
+    Rs256(Vec<u8>),
 }
 
-/// 缓存条目
-/// Cache Entry
-#[derive(Debug, Clone)]
+impl JwtKey {
+    /// 该密钥对应的 `alg` 取值
+    /// The `alg` value this key verifies
+    pub fn algorithm(&self) -> &'static str {
+        match self {
+            JwtKey::Hs256(_) => "HS256",
+            JwtKey::Rs256(_) => "RS256",
+        }
+    }
+
+    /// 校验签名
+    /// Verify a signature
+    fn verify(&self, message: &[u8], signature: &[u8]) -> bool {
+        match self {
+            JwtKey::Hs256(secret) => {
+                ring::hmac::verify(&ring::hmac::Key::new(ring::hmac::HMAC_SHA256, secret), message, signature).is_ok()
+            }
+            JwtKey::Rs256(public_key) => {
+                ring::signature::UnparsedPublicKey::new(&ring::signature::RSA_PKCS1_2048_8192_SHA256, public_key)
+                    .verify(message, signature)
+                    .is_ok()
+            }
+        }
+    }
+}
+
+/// JWT 密钥集，按 `kid` 索引
+/// JWT key set indexed by `kid`
+///
+/// 克隆共享同一份密钥，因此可以在中间件注册后继续添加新密钥、移除旧密钥完成轮换。
+/// Clones share the same keys, so keys can be rotated after the middleware has been registered.
+#[derive(Debug, Clone, Default)]
+pub struct JwtKeySet {
+    /// 密钥
+    keys: Arc<RwLock<HashMap<String, JwtKey>>>,
+}
+
+impl JwtKeySet {
+    /// 创建空密钥集
+    /// Create an empty key set
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 添加或替换密钥
+    /// Add or replace a key
+    pub fn insert(&self, kid: impl Into<String>, key: JwtKey) {
+        if let Ok(mut keys) = self.keys.write() {
+            keys.insert(kid.into(), key);
+        }
+    }
+
+    /// 移除密钥，返回是否存在
+    /// Remove a key, returning whether it existed
+    pub fn remove(&self, kid: &str) -> bool {
+        self.keys.write().is_ok_and(|mut keys| keys.remove(kid).is_some())
+    }
+
+    /// 当前的密钥 ID
+    /// Current key IDs
+    pub fn key_ids(&self) -> Vec<String> {
+        let mut ids: Vec<String> = self.keys.read().map(|keys| keys.keys().cloned().collect()).unwrap_or_default();
+        ids.sort();
+        ids
+    }
+
+    /// 用指定 `kid` 的密钥校验；未指定 `kid` 时尝试全部同算法的密钥
+    /// Verify with the key named by `kid`, or with every key of the algorithm when `kid` is absent
+    fn verify(&self, kid: Option<&str>, algorithm: &str, message: &[u8], signature: &[u8]) -> Result<(), JwtError> {
+        let keys = self.keys.read().map_err(|_| JwtError::UnknownKey("密钥集锁已损坏".to_string()))?;
+        let candidates: Vec<&JwtKey> = match kid {
+            Some(kid) => vec![keys.get(kid).ok_or_else(|| JwtError::UnknownKey(kid.to_string()))?],
+            None => keys.values().collect(),
+        };
+        let mut candidates = candidates.into_iter().filter(|key| key.algorithm() == algorithm).peekable();
+        if candidates.peek().is_none() {
+            return Err(JwtError::UnknownKey(format!("没有 {} 密钥", algorithm)));
+        }
+        if candidates.any(|key| key.verify(message, signature)) {
+            Ok(())
+        } else {
+            Err(JwtError::InvalidSignature)
+        }
+    }
+}
+
+/// JWT 验证错误
+/// JWT verification error
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum JwtError {
+    /// 缺少令牌
+    #[error("缺少 Bearer 令牌")]
+    MissingToken,
+    /// 令牌格式错误
+    #[error("令牌格式错误: {0}")]
+    Malformed(String),
+    /// 不支持的算法
+    #[error("不支持的签名算法: {0}")]
+    UnsupportedAlgorithm(String),
+    /// 找不到密钥
+    #[error("找不到验证密钥: {0}")]
+    UnknownKey(String),
+    /// 签名无效
+    #[error("签名无效")]
+    InvalidSignature,
+    /// 令牌已过期
+    #[error("令牌已过期")]
+    Expired,
+    /// 令牌尚未生效
+    #[error("令牌尚未生效")]
+    NotYetValid,
+    /// 签发方不匹配
+    #[error("签发方不匹配")]
+    InvalidIssuer,
+    /// 受众不匹配
+    #[error("受众不匹配")]
+    InvalidAudience,
+}
+
+impl JwtError {
+    /// 用于 `WWW-Authenticate` 头部的 ASCII 描述
+    /// ASCII description for the `WWW-Authenticate` header
+    pub fn description(&self) -> &'static str {
+        match self {
+            JwtError::MissingToken => "missing token",
+            JwtError::Malformed(_) => "malformed token",
+            JwtError::UnsupportedAlgorithm(_) => "unsupported algorithm",
+            JwtError::UnknownKey(_) => "unknown key",
+            JwtError::InvalidSignature => "invalid signature",
+            JwtError::Expired => "token expired",
+            JwtError::NotYetValid => "token not yet valid",
+            JwtError::InvalidIssuer => "invalid issuer",
+            JwtError::InvalidAudience => "invalid audience",
+        }
+    }
+}
+
+/// JWT 认证配置
+/// JWT authentication configuration
+#[derive(Debug, Clone)]
+pub struct JwtAuthConfig {
+    /// 要求的签发方（`iss`）
+    pub issuer: Option<String>,
+    /// 要求的受众（`aud`）
+    pub audience: Option<String>,
+    /// 校验 `exp`/`nbf` 时允许的时钟偏差
+    pub leeway: Duration,
+    /// 授权范围所在的声明，取值可以是空格分隔的字符串或字符串数组
+    pub scope_claim: String,
+    /// `WWW-Authenticate` 中的 realm
+    pub realm: String,
+}
+
+impl Default for JwtAuthConfig {
+    fn default() -> Self {
+        Self {
+            issuer: None,
+            audience: None,
+            leeway: Duration::from_secs(30),
+            scope_claim: "scope".to_string(),
+            realm: "api".to_string(),
+        }
+    }
+}
+
+/// JWT 认证中间件
+/// JWT authentication middleware
+///
+/// 校验 `Authorization: Bearer` 令牌（HS256/RS256）以及 `exp`、`nbf`、`iss`、`aud`，
+/// 成功后把调用方写入 [`Request::principal`]。缺少或无效的令牌返回 401，授权范围不足返回 403。
+/// 按路由要求不同的授权范围时，把带有相应 `required_scopes` 的实例注册为路由中间件。
+/// Verifies `Authorization: Bearer` tokens (HS256/RS256) along with `exp`, `nbf`, `iss` and `aud`, then
+/// stores the caller in [`Request::principal`]. Missing or invalid tokens yield 401 and insufficient
+/// scope yields 403. For per-route scopes, register instances with their own `required_scopes` as route
+/// middlewares.
+#[derive(Debug, Clone)]
+pub struct JwtAuthMiddleware {
+    /// 验证密钥
+    keys: JwtKeySet,
+    /// 认证配置
+    config: JwtAuthConfig,
+    /// 必需的授权范围
+    required_scopes: Vec<String>,
+}
+
+impl JwtAuthMiddleware {
+    /// 创建 JWT 认证中间件
+    /// Create a JWT authentication middleware
+    pub fn new(keys: JwtKeySet, config: JwtAuthConfig) -> Self {
+        Self { keys, config, required_scopes: Vec::new() }
+    }
+
+    /// 要求令牌包含全部指定的授权范围
+    /// Require every given scope to be present in the token
+    pub fn require_scopes<I, S>(self, scopes: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self { required_scopes: scopes.into_iter().map(Into::into).collect(), ..self }
+    }
+
+    /// 校验令牌并返回其中的调用方
+    /// Verify a token and return its principal
+    pub fn verify(&self, token: &str) -> Result<Principal, JwtError> {
+        let mut parts = token.split('.');
+        let (Some(header), Some(payload), Some(signature), None) = (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return Err(JwtError::Malformed("令牌必须由三段组成".to_string()));
+        };
+        let header = decode_jwt_json(header)?;
+        let algorithm = header.get("alg").and_then(serde_json::Value::as_str).unwrap_or_default();
+        if algorithm != "HS256" && algorithm != "RS256" {
+            return Err(JwtError::UnsupportedAlgorithm(algorithm.to_string()));
+        }
+        let signature = URL_SAFE_NO_PAD.decode(signature).map_err(|e| JwtError::Malformed(e.to_string()))?;
+        let signing_input = &token[..header_and_payload_len(token)];
+        self.keys.verify(
+            header.get("kid").and_then(serde_json::Value::as_str),
+            algorithm,
+            signing_input.as_bytes(),
+            &signature,
+        )?;
+
+        let claims = decode_jwt_json(payload)?;
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs_f64();
+        let leeway = self.config.leeway.as_secs_f64();
+        // 时间声明可以是整数、小数或负数；存在但不是数字的声明使令牌无效，而不是当作缺失
+        let numeric = |name: &str| match claims.get(name) {
+            None => Ok(None),
+            Some(value) => value.as_f64()
+                .map(Some)
+                .ok_or_else(|| JwtError::Malformed(format!("声明 {} 不是数字", name))),
+        };
+        let (exp, nbf) = (numeric("exp")?, numeric("nbf")?);
+        numeric("iat")?;
+        if exp.is_some_and(|exp| now >= exp + leeway) {
+            return Err(JwtError::Expired);
+        }
+        if nbf.is_some_and(|nbf| now + leeway < nbf) {
+            return Err(JwtError::NotYetValid);
+        }
+        let issuer = claims.get("iss").and_then(serde_json::Value::as_str).map(str::to_string);
+        if let Some(expected) = &self.config.issuer
+            && issuer.as_ref() != Some(expected)
+        {
+            return Err(JwtError::InvalidIssuer);
+        }
+        if let Some(expected) = &self.config.audience {
+            let matches = match claims.get("aud") {
+                Some(serde_json::Value::String(audience)) => audience == expected,
+                Some(serde_json::Value::Array(audiences)) => audiences.iter().any(|audience| audience == expected),
+                _ => false,
+            };
+            if !matches {
+                return Err(JwtError::InvalidAudience);
+            }
+        }
+
+        let scopes = match claims.get(&self.config.scope_claim) {
+            Some(serde_json::Value::String(scopes)) => scopes.split_whitespace().map(str::to_string).collect(),
+            Some(serde_json::Value::Array(scopes)) => {
+                scopes.iter().filter_map(serde_json::Value::as_str).map(str::to_string).collect()
+            }
+            _ => Vec::new(),
+        };
+        Ok(Principal {
+            subject: claims.get("sub").and_then(serde_json::Value::as_str).unwrap_or_default().to_string(),
+            issuer,
+            scopes,
+        })
+    }
+
+    /// 构造带 `WWW-Authenticate` 头部的拒绝响应
+    /// Build a rejection carrying a `WWW-Authenticate` header
+    fn reject(&self, status_code: u16, challenge: String, message: String) -> MiddlewareAction {
+        let mut headers = HashMap::new();
+        headers.insert("WWW-Authenticate".to_string(), challenge);
+        MiddlewareAction::ShortCircuit(Response {
+            status_code,
+            headers,
+            body: Some(message.into_bytes()),
+            processing_time: Duration::ZERO,
+        })
+    }
+}
+
+impl Middleware for JwtAuthMiddleware {
+    fn on_request(&self, request: &mut Request) -> Result<MiddlewareAction, GatewayError> {
+        let realm = &self.config.realm;
+        let token = request.header("Authorization")
+            .and_then(|value| value.split_once(' '))
+            .filter(|(scheme, _)| scheme.eq_ignore_ascii_case("Bearer"))
+            .map(|(_, token)| token.trim());
+        let principal = match token.ok_or(JwtError::MissingToken).and_then(|token| self.verify(token)) {
+            Ok(principal) => principal,
+            Err(JwtError::MissingToken) => {
+                return Ok(self.reject(401, format!("Bearer realm=\"{}\"", realm), JwtError::MissingToken.to_string()));
+            }
+            Err(error) => {
+                let challenge = format!(
+                    "Bearer realm=\"{}\", error=\"invalid_token\", error_description=\"{}\"",
+                    realm,
+                    error.description()
+                );
+                return Ok(self.reject(401, challenge, error.to_string()));
+            }
+        };
+
+        let missing: Vec<&str> = self.required_scopes.iter()
+            .filter(|scope| !principal.has_scope(scope))
+            .map(String::as_str)
+            .collect();
+        if !missing.is_empty() {
+            let challenge = format!(
+                "Bearer realm=\"{}\", error=\"insufficient_scope\", scope=\"{}\"",
+                realm,
+                self.required_scopes.join(" ")
+            );
+            return Ok(self.reject(403, challenge, format!("缺少授权范围: {}", missing.join(" "))));
+        }
+
+        request.principal = Some(principal);
+        Ok(MiddlewareAction::Continue)
+    }
+}
+
+/// 签名输入（头部和载荷两段）的长度
+/// Length of the signing input, i.e. the header and payload segments
+fn header_and_payload_len(token: &str) -> usize {
+    token.rfind('.').unwrap_or(token.len())
+}
+
+/// 解码 base64url 编码的 JSON 段
+/// Decode a base64url encoded JSON segment
+fn decode_jwt_json(segment: &str) -> Result<serde_json::Map<String, serde_json::Value>, JwtError> {
+    let bytes = URL_SAFE_NO_PAD.decode(segment).map_err(|e| JwtError::Malformed(e.to_string()))?;
+    serde_json::from_slice(&bytes).map_err(|e| JwtError::Malformed(e.to_string()))
+}
+
+/// 上游客户端，负责把请求发送到选中的服务实例
+/// Upstream client sending requests to the selected service instance
+#[async_trait::async_trait]
+pub trait UpstreamClient: Send + Sync {
+    /// 发送请求
+    /// Send a request
+    async fn send(&self, request: &Request, instance: &ServiceInstance) -> Result<Response, GatewayError>;
+
+    /// 打开流式连接，返回网关一端的帧通道；默认不支持
+    /// Open a streaming connection and return the gateway's end of the frame channel; unsupported by default
+    async fn open_stream(&self, _request: &Request, instance: &ServiceInstance) -> Result<StreamChannel, GatewayError> {
+        Err(GatewayError::ServiceError(format!("上游 {} 不支持流式连接", instance.address)))
+    }
+}
+
+/// 固定响应的上游客户端
+/// Upstream client returning a fixed response
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StaticUpstream;
+
+#[async_trait::async_trait]
+impl UpstreamClient for StaticUpstream {
+    async fn send(&self, _request: &Request, _instance: &ServiceInstance) -> Result<Response, GatewayError> {
+        // 简化的请求转发实现
+        // 实际应用中应该使用 HTTP 客户端库
+        Ok(Response {
+            status_code: 200,
+            headers: HashMap::new(),
+            body: Some(b"Hello from WebAssembly 2.0!".to_vec()),
+            processing_time: Duration::from_millis(10),
+        })
+    }
+}
+
+/// 缓存
+/// Cache
+///
+/// 以（方法、路径、有效查询参数、`Vary` 列出的请求头）为键缓存上游响应，
+/// 有效期取自 `Cache-Control` 的 `s-maxage`/`max-age`，缺失时使用路由的 `cache_ttl`。
+/// Caches upstream responses keyed by method, path, significant query parameters and the request headers
+/// listed in `Vary`. Freshness comes from `s-maxage`/`max-age` in `Cache-Control`, falling back to the
+/// route's `cache_ttl`.
+///
+/// 带 `Authorization` 或 Cookie 的请求只存入和读取上游声明为 `public` 的响应，其他情况绕过缓存。
+/// Requests carrying `Authorization` or cookies only store and read responses marked `public`; otherwise they
+/// bypass the cache.
+#[derive(Debug)]
+pub struct Cache {
+    /// 缓存存储
+    pub storage: Arc<Mutex<HashMap<String, CacheEntry>>>,
+    /// `set` 未指定 TTL 时使用的时长
+    pub default_ttl: Duration,
+    /// 缓存策略
+    pub config: CacheConfig,
+    /// 各基础键的响应声明的 `Vary` 头部（小写、已排序）
+    vary_index: Mutex<HashMap<String, Vec<String>>>,
+    /// 时钟
+    clock: Arc<dyn GatewayClock>,
+    /// 命中次数
+    hits: AtomicU64,
+    /// 未命中次数
+    misses: AtomicU64,
+    /// 返回过期响应的次数
+    stale: AtomicU64,
+}
+
+/// 缓存策略
+/// Cache policy
+#[derive(Debug, Clone)]
+pub struct CacheConfig {
+    /// 可缓存的方法，默认只有 GET
+    pub cacheable_methods: Vec<HttpMethod>,
+    /// 参与缓存键的查询参数，`None` 表示全部参数
+    pub significant_query_params: Option<Vec<String>>,
+    /// 过期后在重新验证期间仍可返回旧响应的时长，`None` 表示不启用；
+    /// 响应中的 `stale-while-revalidate` 指令优先于该值
+    pub stale_while_revalidate: Option<Duration>,
+    /// 最大条目数
+    pub max_entries: usize,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            cacheable_methods: vec![HttpMethod::GET],
+            significant_query_params: None,
+            stale_while_revalidate: None,
+            max_entries: 10_000,
+        }
+    }
+}
+
+/// 缓存条目
+/// Cache Entry
+#[derive(Debug, Clone)]
 pub struct CacheEntry {
     /// 值
     pub value: Vec<u8>,
+    /// 状态码
+    pub status_code: u16,
+    /// 响应头部
+    pub headers: HashMap<String, String>,
+    /// 请求路径，用于按前缀清除
+    pub path: String,
+    /// 存入时间
+    pub stored_at: Instant,
     /// 过期时间
     pub expires_at: Instant,
+    /// 过期后仍可返回旧响应的截止时间
+    pub stale_until: Instant,
+    /// 是否已有请求在重新验证
+    pub revalidating: bool,
     /// 访问次数
     pub access_count: u64,
 }
 
+/// 缓存查找结果
+/// Cache lookup result
+#[derive(Debug, Clone)]
+pub enum CacheLookup {
+    /// 新鲜的缓存响应
+    Hit(Response),
+    /// 已过期、正由其他请求重新验证的响应
+    Stale(Response),
+    /// 需要请求上游
+    Miss,
+    /// 请求不可缓存，直接请求上游
+    Bypass,
+}
+
+/// 缓存统计
+/// Cache statistics
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CacheStats {
+    /// 命中次数
+    pub hits: u64,
+    /// 未命中次数
+    pub misses: u64,
+    /// 返回过期响应的次数
+    pub stale: u64,
+}
+
+/// 网关计数
+/// Gateway counters
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GatewayMetrics {
+    /// 发往上游的请求次数，包含重试
+    pub upstream_attempts: u64,
+    /// 重试次数
+    pub retries: u64,
+    /// 用完全部尝试次数仍失败的请求数
+    pub retries_exhausted: u64,
+    /// 因截止时间放弃重试的请求数
+    pub deadline_exceeded: u64,
+}
+
+/// 网关计数的原子存储
+/// Atomic storage behind [`GatewayMetrics`]
+#[derive(Debug, Default)]
+struct GatewayCounters {
+    upstream_attempts: AtomicU64,
+    retries: AtomicU64,
+    retries_exhausted: AtomicU64,
+    deadline_exceeded: AtomicU64,
+}
+
+/// 网关指标快照，供健康检查端点序列化输出
+/// Gateway metrics snapshot, serializable for the health endpoint
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GatewayMetricsSummary {
+    /// 各路由的指标，按路由模板排序
+    pub routes: Vec<RouteMetricsSummary>,
+    /// 各上游实例的指标，按地址排序
+    pub upstreams: Vec<UpstreamMetricsSummary>,
+    /// 重试计数
+    pub retries: GatewayMetrics,
+}
+
+/// 单个路由的指标
+/// Metrics of a single route
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RouteMetricsSummary {
+    /// 路由模板，如 `/modules/{id}`；未匹配的请求记为 `unmatched`
+    pub route: String,
+    /// 请求数
+    pub requests: u64,
+    /// 按状态码类别（`2xx`、`5xx` 等）的请求数
+    pub status_classes: BTreeMap<String, u64>,
+    /// 进行中的请求数
+    pub in_flight: u64,
+    /// 被限流拒绝的请求数
+    pub rate_limited: u64,
+    /// 缓存命中次数
+    pub cache_hits: u64,
+    /// 缓存未命中次数
+    pub cache_misses: u64,
+    /// 重试次数
+    pub retries: u64,
+    /// 上游超时和请求截止时间到期的次数
+    pub timeouts: u64,
+    /// 分流路由各版本按状态码类别的请求数，用于比较版本间的错误率
+    pub versions: BTreeMap<String, BTreeMap<String, u64>>,
+    /// 平均延迟（毫秒）
+    pub mean_latency_ms: Option<f64>,
+}
+
+/// 单个上游实例的指标
+/// Metrics of a single upstream
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct UpstreamMetricsSummary {
+    /// 实例地址
+    pub upstream: String,
+    /// 转发的请求数
+    pub requests: u64,
+    /// 按状态码类别的请求数，转发失败记为 `error`
+    pub status_classes: BTreeMap<String, u64>,
+    /// 熔断次数
+    pub circuit_opens: u64,
+    /// 当前熔断状态
+    pub circuit_state: Option<CircuitState>,
+    /// 被健康检查或异常检测摘除的次数
+    pub ejections: u64,
+    /// 当前是否被摘除
+    pub ejected: bool,
+}
+
+/// 请求总数，标签：`route`、`method`、`status_class`
+const GATEWAY_REQUESTS: &str = "gateway_requests_total";
+/// 请求处理时长（秒），标签：`route`、`method`
+const GATEWAY_REQUEST_DURATION: &str = "gateway_request_duration_seconds";
+/// 进行中的请求数，标签：`route`
+const GATEWAY_IN_FLIGHT: &str = "gateway_in_flight_requests";
+/// 限流拒绝数，标签：`route`
+const GATEWAY_RATE_LIMITED: &str = "gateway_rate_limited_total";
+/// 缓存命中数，标签：`route`
+const GATEWAY_CACHE_HITS: &str = "gateway_cache_hits_total";
+/// 缓存未命中数，标签：`route`
+const GATEWAY_CACHE_MISSES: &str = "gateway_cache_misses_total";
+/// 重试次数，标签：`route`
+const GATEWAY_RETRIES: &str = "gateway_retries_total";
+/// 超时次数，标签：`route`、`kind`（`upstream` 为单次上游超时，`deadline` 为请求截止时间）
+const GATEWAY_TIMEOUTS: &str = "gateway_timeouts_total";
+/// 上游请求数，标签：`upstream`、`status_class`
+const GATEWAY_UPSTREAM_REQUESTS: &str = "gateway_upstream_requests_total";
+/// 分流路由按版本的请求数，标签：`route`、`version`、`status_class`
+const GATEWAY_VERSION_REQUESTS: &str = "gateway_route_version_requests_total";
+/// 熔断次数，标签：`upstream`
+const GATEWAY_CIRCUIT_OPENS: &str = "gateway_circuit_opens_total";
+/// 上游摘除次数，标签：`upstream`、`reason`（`active` 或 `outlier`）
+const GATEWAY_UPSTREAM_EJECTIONS: &str = "gateway_upstream_ejections_total";
+/// 上游重新加入次数，标签：`upstream`
+const GATEWAY_UPSTREAM_READMISSIONS: &str = "gateway_upstream_readmissions_total";
+/// 未匹配任何路由的请求使用的路由标签
+const UNMATCHED_ROUTE: &str = "unmatched";
+
+/// 可被缓存的响应状态码
+const CACHEABLE_STATUS_CODES: [u16; 7] = [200, 203, 204, 300, 301, 404, 410];
+
+/// 解析后的 `Cache-Control` 指令
+/// Parsed `Cache-Control` directives
+#[derive(Debug, Clone, Default, PartialEq)]
+struct CacheControl {
+    no_store: bool,
+    no_cache: bool,
+    private: bool,
+    public: bool,
+    max_age: Option<Duration>,
+    s_maxage: Option<Duration>,
+    stale_while_revalidate: Option<Duration>,
+}
+
+impl CacheControl {
+    /// 解析头部值，无法识别的指令被忽略
+    /// Parse a header value, ignoring unknown directives
+    fn parse(value: &str) -> Self {
+        let mut control = Self::default();
+        for directive in value.split(',') {
+            let (name, argument) = match directive.split_once('=') {
+                Some((name, argument)) => (name.trim(), Some(argument.trim().trim_matches('"'))),
+                None => (directive.trim(), None),
+            };
+            let seconds = argument.and_then(|a| a.parse::<u64>().ok()).map(Duration::from_secs);
+            match name.to_ascii_lowercase().as_str() {
+                "no-store" => control.no_store = true,
+                "no-cache" => control.no_cache = true,
+                "private" => control.private = true,
+                "public" => control.public = true,
+                "max-age" => control.max_age = seconds,
+                "s-maxage" => control.s_maxage = seconds,
+                "stale-while-revalidate" => control.stale_while_revalidate = seconds,
+                _ => {}
+            }
+        }
+        control
+    }
+}
+
 /// API 网关错误
 /// API Gateway Error
 #[derive(Debug, Error)]
@@ -218,6 +1904,44 @@ pub enum GatewayError {
     /// 服务错误
     #[error("服务错误: {0}")]
     ServiceError(String),
+    /// 中间件错误
+    #[error("中间件错误: {0}")]
+    MiddlewareError(String),
+    /// 消息体转换错误
+    #[error("消息体转换错误: {0}")]
+    TransformError(String),
+    /// 配置错误
+    #[error("配置错误: {0}")]
+    ConfigError(String),
+    /// 请求不合法
+    #[error("请求不合法: {0}")]
+    BadRequest(String),
+    /// 请求体过大
+    #[error("请求体过大: {0}")]
+    PayloadTooLarge(String),
+    /// 请求头部过大
+    #[error("请求头部过大: {0}")]
+    HeadersTooLarge(String),
+    /// 上游超时
+    #[error("上游超时: {0}")]
+    Timeout(String),
+}
+
+impl GatewayError {
+    /// 该错误对应的 HTTP 状态码
+    /// HTTP status code for this error
+    pub fn status_code(&self) -> u16 {
+        match self {
+            GatewayError::BadRequest(_) => 400,
+            GatewayError::RoutingError(_) => 404,
+            GatewayError::PayloadTooLarge(_) => 413,
+            GatewayError::RateLimitError(_) => 429,
+            GatewayError::HeadersTooLarge(_) => 431,
+            GatewayError::ServiceError(_) | GatewayError::TransformError(_) => 502,
+            GatewayError::Timeout(_) => 504,
+            GatewayError::CacheError(_) | GatewayError::MiddlewareError(_) | GatewayError::ConfigError(_) => 500,
+        }
+    }
 }
 
 impl Default for ApiGatewayManager {
@@ -231,168 +1955,4658 @@ impl ApiGatewayManager {
     pub fn new() -> Self {
         Self {
             routes: Arc::new(Mutex::new(HashMap::new())),
+            route_patterns: Arc::new(Mutex::new(Vec::new())),
             middlewares: Vec::new(),
+            named_middlewares: HashMap::new(),
             load_balancer: LoadBalancer::new(),
             rate_limiter: RateLimiter::new(),
             cache: Cache::new(),
+            upstream: Arc::new(StaticUpstream),
+            metrics: GatewayCounters::default(),
+            metrics_collector: Arc::new(gateway_metrics_collector()),
+            route_in_flight: Mutex::new(HashMap::new()),
+            health_check_task: Mutex::new(None),
+            cors: None,
+            limits: RequestLimits::default(),
         }
     }
 
+    /// 设置请求大小和超时的默认限制
+    /// Set the default request size and timeout limits
+    pub fn set_request_limits(&mut self, limits: RequestLimits) {
+        self.limits = limits;
+    }
+
+    /// 设置全局 CORS 策略，对未配置策略的路由生效
+    /// Set the global CORS policy applied to routes without their own
+    pub fn set_cors_policy(&mut self, policy: CorsPolicy) -> Result<(), GatewayError> {
+        policy.validate()?;
+        self.cors = Some(policy);
+        Ok(())
+    }
+
+    /// 使用指定的上游客户端
+    /// Use the given upstream client
+    pub fn with_upstream(self, upstream: Arc<dyn UpstreamClient>) -> Self {
+        Self { upstream, ..self }
+    }
+
+    /// 把网关指标写入指定的指标收集器，例如监控管理器共用的收集器
+    /// Record gateway metrics into the given collector, e.g. the one shared with the monitoring manager
+    pub fn with_metrics_collector(self, metrics_collector: Arc<MetricsCollector>) -> Self {
+        register_gateway_metrics(&metrics_collector);
+        Self { metrics_collector, ..self }
+    }
+
+    /// 添加全局中间件，对所有请求按添加顺序执行
+    /// Add a global middleware that runs for every request in insertion order
+    pub fn add_middleware(&mut self, middleware: impl Middleware + 'static) {
+        self.middlewares.push(Arc::new(middleware));
+    }
+
+    /// 按名称注册中间件，供路由的 `middlewares` 引用
+    /// Register a named middleware that routes can reference from `middlewares`
+    pub fn register_middleware(&mut self, name: impl Into<String>, middleware: impl Middleware + 'static) {
+        self.named_middlewares.insert(name.into(), Arc::new(middleware));
+    }
+
     /// 添加路由
+    ///
+    /// 路由引用的中间件必须已经注册。
+    /// 同一方法下与已有路由形状相同（能以相同优先级匹配同一路径）的路由会被拒绝。
+    /// A route whose shape equals an existing route of the same method, so that both would match the same
+    /// paths with equal priority, is rejected.
     pub fn add_route(&mut self, route: Route) -> Result<(), GatewayError> {
+        let pattern = RoutePattern::parse(&route.path)?;
         let key = format!("{}:{}", route.method.clone(), route.path.clone());
+        if let Some(name) = route.middlewares.iter().find(|name| !self.named_middlewares.contains_key(*name)) {
+            return Err(GatewayError::RoutingError(format!("路由 {} 引用了未注册的中间件 {}", key, name)));
+        }
+        if let Some(cors) = &route.cors {
+            cors.validate()?;
+        }
+        if let Some(split) = &route.split {
+            split.validate()?;
+        }
         let mut routes = self.routes.lock().unwrap();
+        let mut patterns = self.route_patterns.lock().unwrap();
+        if let Some(existing) = patterns.iter()
+            .find(|existing| existing.method == route.method && existing.pattern.shape() == pattern.shape())
+        {
+            return Err(GatewayError::RoutingError(format!("路由 {} 与已注册的 {} 冲突", key, existing.key)));
+        }
+
+        let position = patterns.partition_point(|existing| existing.pattern.priority() <= pattern.priority());
+        patterns.insert(position, CompiledRoute { key: key.clone(), method: route.method.clone(), pattern });
         routes.insert(key, route);
         Ok(())
     }
 
+    /// 运行时调整路由的分流权重
+    /// Adjust a route's traffic split weights at runtime
+    ///
+    /// 只影响之后到达的请求，进行中的请求继续使用开始时的路由配置。未列出的版本保持原权重。
+    /// Only requests arriving afterwards are affected; in-flight requests keep the route configuration they
+    /// started with. Versions not listed keep their weight.
+    pub fn set_route_weights(&self, method: HttpMethod, path: &str, weights: &[(&str, u32)]) -> Result<(), GatewayError> {
+        let key = format!("{}:{}", method, path);
+        let mut routes = self.routes.lock().unwrap();
+        let split = routes.get_mut(&key)
+            .ok_or_else(|| GatewayError::RoutingError(format!("路由不存在: {}", key)))?
+            .split
+            .as_mut()
+            .ok_or_else(|| GatewayError::ConfigError(format!("路由 {} 没有配置分流", key)))?;
+        let mut updated = split.clone();
+        for (version, weight) in weights {
+            updated.groups.iter_mut()
+                .find(|group| group.version == *version)
+                .ok_or_else(|| GatewayError::ConfigError(format!("路由 {} 没有版本 {}", key, version)))?
+                .weight = *weight;
+        }
+        updated.validate()?;
+        *split = updated;
+        Ok(())
+    }
+
     /// 处理请求
+    ///
+    /// 中间件、路由和转发中的错误都会被转换为对应状态码的响应，
+    /// 已执行过 `on_request` 的中间件总能以相反顺序看到最终响应。
+    /// Failures in middleware, routing and forwarding are turned into responses with the matching status
+    /// code, so every middleware whose `on_request` ran sees the final response in reverse order.
     pub async fn handle_request(&self, mut request: Request) -> Result<Response, GatewayError> {
         let start_time = Instant::now();
-
-        // 应用中间件
-        for middleware in &self.middlewares {
-            middleware.handle(&mut request)?;
+        if let Some(mut response) = self.preflight(&request) {
+            response.processing_time = start_time.elapsed();
+            return Ok(response);
         }
+        let mut entered = Vec::new();
+        let mut matched_route = None;
+        let mut route_cors = None;
+        let mut in_flight = None;
 
-        // 限流检查
-        self.rate_limiter.check_limit(&request.client_ip)?;
+        let mut response = match run_request_middlewares(&self.middlewares, &mut request, &mut entered) {
+            Some(response) => response,
+            None => match self.find_route(&request) {
+                Err(error) => error_response(&error),
+                Ok((route, path_params)) => {
+                    request.path_params = path_params;
+                    in_flight = Some(self.enter_route(&route.path));
+                    matched_route = Some(route.path.clone());
+                    route_cors = route.cors.clone();
+                    match self.route_middlewares(&route) {
+                        Err(error) => error_response(&error),
+                        Ok(chain) => match run_request_middlewares(&chain, &mut request, &mut entered) {
+                            Some(response) => response,
+                            None => self.dispatch(&mut request, &route).await.unwrap_or_else(|error| error_response(&error)),
+                        },
+                    }
+                }
+            },
+        };
 
-        // 路由匹配
-        let route = self.find_route(&request)?;
+        response.processing_time = start_time.elapsed();
+        for middleware in entered.iter().rev() {
+            if let Err(error) = call_middleware(|| middleware.on_response(&request, &mut response)) {
+                response = error_response(&error);
+            }
+        }
+        if let (Some(policy), Some(origin)) = (route_cors.as_ref().or(self.cors.as_ref()), request.header("Origin")) {
+            policy.apply(origin, &mut response.headers);
+        }
+        response.processing_time = start_time.elapsed();
 
-        // 负载均衡选择服务实例
-        let instance = self.load_balancer.select_instance(&route.target_service)?;
+        // 未匹配的请求归入同一个标签，避免原始路径撑大标签基数
+        drop(in_flight);
+        let route = matched_route.as_deref().unwrap_or(UNMATCHED_ROUTE);
+        let method = request.method.to_string();
+        let status_class = status_class(response.status_code);
+        self.inc_metric(GATEWAY_REQUESTS, &[("route", route), ("method", &method), ("status_class", &status_class)]);
+        let _ = self.metrics_collector.observe(
+            GATEWAY_REQUEST_DURATION,
+            response.processing_time.as_secs_f64(),
+            metric_labels(&[("route", route), ("method", &method)]),
+        );
+        Ok(response)
+    }
 
-        // 发送请求到后端服务
-        let response = self.forward_request(&request, instance).await?;
+    /// 按请求所匹配路由的 `max_body_bytes` 从连接读取请求体，超限时在读完之前返回 413
+    /// Read the request body from the connection under the matched route's `max_body_bytes`, failing with a 413
+    /// before the whole body is read when it is over the limit
+    ///
+    /// 没有匹配路由的请求使用网关的默认限制。
+    /// Requests matching no route use the gateway defaults.
+    pub async fn read_body<R>(&self, request: &mut Request, reader: R) -> Result<(), GatewayError>
+    where
+        R: tokio::io::AsyncRead + Unpin,
+    {
+        let limits = match self.find_route(request) {
+            Ok((route, _)) => self.limits.with_overrides(&route.limit_overrides()),
+            Err(_) => self.limits.clone(),
+        };
+        limits.read_body(request, reader).await
+    }
 
-        let processing_time = start_time.elapsed();
+    /// 处理流式请求：WebSocket 升级或流式路由
+    /// Handle a streaming request, i.e. a WebSocket upgrade or a request to a streaming route
+    ///
+    /// 与 [`handle_request`](Self::handle_request) 一样执行中间件、限流、熔断和 CORS，`on_response` 看到的是握手响应；
+    /// 缓存和响应体转换不适用。升级后由后台任务在客户端与上游之间双向中继帧，执行单帧大小限制和空闲超时
+    /// （读取和转发都计入），并把关闭帧传给对端。
+    /// Middlewares, rate limiting, circuit breaking and CORS apply as in [`handle_request`](Self::handle_request)
+    /// and `on_response` sees the handshake response; caching and body transforms do not apply. After the upgrade
+    /// a background task relays frames both ways, enforcing per-direction frame limits and the idle timeout on
+    /// both reads and forwards, and propagating close frames.
+    pub async fn handle_stream(&self, mut request: Request) -> StreamUpgrade {
+        let mut entered = Vec::new();
+        let mut session = None;
+        let mut route_cors = None;
+        let mut response = match run_request_middlewares(&self.middlewares, &mut request, &mut entered) {
+            Some(response) => response,
+            None => match self.find_route(&request) {
+                Err(error) => error_response(&error),
+                Ok((route, path_params)) => {
+                    request.path_params = path_params;
+                    route_cors = route.cors.clone();
+                    match self.route_middlewares(&route) {
+                        Err(error) => error_response(&error),
+                        Ok(chain) => match run_request_middlewares(&chain, &mut request, &mut entered) {
+                            Some(response) => response,
+                            None => match self.open_stream(&request, &route).await {
+                                Ok((response, opened)) => {
+                                    session = opened;
+                                    response
+                                }
+                                Err(error) => error_response(&error),
+                            },
+                        },
+                    }
+                }
+            },
+        };
 
-        Ok(Response {
-            status_code: response.status_code,
-            headers: response.headers,
-            body: response.body,
-            processing_time,
-        })
+        for middleware in entered.iter().rev() {
+            if let Err(error) = call_middleware(|| middleware.on_response(&request, &mut response)) {
+                response = error_response(&error);
+            }
+        }
+        if let (Some(policy), Some(origin)) = (route_cors.as_ref().or(self.cors.as_ref()), request.header("Origin")) {
+            policy.apply(origin, &mut response.headers);
+        }
+        match session {
+            Some((channel, relay)) => StreamUpgrade::Accepted(StreamSession { response, channel, relay }),
+            None => StreamUpgrade::Rejected(response),
+        }
     }
 
-    /// 查找路由
-    fn find_route(&self, request: &Request) -> Result<Route, GatewayError> {
-        let key = format!("{}:{}", request.method, request.path);
-        let routes = self.routes.lock().unwrap();
-        routes.get(&key)
-            .cloned()
-            .ok_or_else(|| GatewayError::RoutingError(format!("未找到路由: {}", key)))
-    }
+    /// 校验握手、执行限流和熔断检查、连接上游并启动中继；被限流或熔断时只返回拒绝响应
+    /// Validate the handshake, apply rate limiting and circuit breaking, connect upstream and start the relay;
+    /// a rate-limited or circuit-broken request yields only the rejection response
+    async fn open_stream(
+        &self,
+        request: &Request,
+        route: &Route,
+    ) -> Result<(Response, Option<(StreamChannel, tokio::task::JoinHandle<StreamSummary>)>), GatewayError> {
+        // 只接受 WebSocket 升级；其它协议的升级请求不能当作普通流处理
+        let websocket = match request.header("Upgrade").map(str::trim) {
+            None => false,
+            Some(upgrade) if upgrade.eq_ignore_ascii_case("websocket") => true,
+            Some(upgrade) => return Err(GatewayError::BadRequest(format!("不支持升级到 {}", upgrade))),
+        };
+        if !websocket && route.streaming.is_none() {
+            return Err(GatewayError::BadRequest(format!("路由 {} 不是流式路由", route.path)));
+        }
+        let mut headers = HashMap::new();
+        let status_code = if websocket {
+            let connection_upgrade = request.header("Connection")
+                .is_some_and(|connection| connection.split(',').any(|token| token.trim().eq_ignore_ascii_case("upgrade")));
+            if request.method != HttpMethod::GET || !connection_upgrade {
+                return Err(GatewayError::BadRequest("WebSocket 升级需要 GET 请求和 Connection: Upgrade".to_string()));
+            }
+            let key = request.header("Sec-WebSocket-Key")
+                .ok_or_else(|| GatewayError::BadRequest("缺少 Sec-WebSocket-Key".to_string()))?;
+            if request.header("Sec-WebSocket-Version").map(str::trim) != Some("13") {
+                return Err(GatewayError::BadRequest("仅支持 WebSocket 版本 13".to_string()));
+            }
+            headers.insert("Upgrade".to_string(), "websocket".to_string());
+            headers.insert("Connection".to_string(), "Upgrade".to_string());
+            headers.insert("Sec-WebSocket-Accept".to_string(), websocket_accept(key));
+            101
+        } else {
+            headers.insert("Cache-Control".to_string(), "no-cache".to_string());
+            200
+        };
 
-    /// 转发请求
-    #[allow(unused_variables)]
-    async fn forward_request(&self, request: &Request, instance: &ServiceInstance) -> Result<Response, GatewayError> {
-        // 简化的请求转发实现
-        // 实际应用中应该使用 HTTP 客户端库
-        Ok(Response {
-            status_code: 200,
-            headers: HashMap::new(),
-            body: Some(b"Hello from WebAssembly 2.0!".to_vec()),
-            processing_time: Duration::from_millis(10),
-        })
-    }
-}
+        let decision = match self.check_rate_limit(request, route) {
+            Ok(decision) => decision,
+            Err(response) => return Ok((response, None)),
+        };
+        headers.insert("X-RateLimit-Limit".to_string(), decision.limit.to_string());
+        headers.insert("X-RateLimit-Remaining".to_string(), decision.remaining.to_string());
 
-impl Default for LoadBalancer {
-    fn default() -> Self {
-        Self::new()
+        // 熔断器只判断连接能否建立，连接建立后的中继不计入
+        let upstream = self.load_balancer.pick()?;
+        let permit = match self.load_balancer.circuit_breaker.try_acquire(&upstream.address) {
+            Ok(permit) => permit,
+            Err(retry_after) => return Ok((circuit_open_response(&upstream.address, retry_after), None)),
+        };
+        let opened = self.upstream.open_stream(request, &upstream).await;
+        if permit.record(opened.is_ok()) == Some(CircuitState::Open) {
+            self.inc_metric(GATEWAY_CIRCUIT_OPENS, &[("upstream", &upstream.address)]);
+        }
+        let upstream_channel = opened?;
+        let config = route.streaming.clone().unwrap_or_default();
+        let (client_end, gateway_end) = StreamChannel::pair(config.buffer);
+        let relay = tokio::spawn(relay_stream(gateway_end, upstream_channel, config, upstream));
+        let response = Response { status_code, headers, body: None, processing_time: Duration::ZERO };
+        Ok((response, Some((client_end, relay))))
     }
-}
 
-impl LoadBalancer {
-    /// 创建新的负载均衡器
-    #[allow(unused_variables)]
-    pub fn new() -> Self {
-        Self {
-            instances: Vec::new(),
-            strategy: LoadBalancingStrategy::RoundRobin,
+    /// 处理 CORS 预检请求，不经过中间件也不转发到上游
+    /// Answer a CORS preflight request without running middlewares or forwarding upstream
+    ///
+    /// 只有带 `Origin` 和 `Access-Control-Request-Method` 的 `OPTIONS` 请求才是预检请求，
+    /// 所请求的方法没有对应路由时按普通请求处理。
+    /// Only an `OPTIONS` request carrying `Origin` and `Access-Control-Request-Method` is a preflight; when no
+    /// route serves the requested method the request is handled like any other.
+    fn preflight(&self, request: &Request) -> Option<Response> {
+        if request.method != HttpMethod::OPTIONS {
+            return None;
         }
-    }
+        let origin = request.header("Origin")?;
+        let requested_method: HttpMethod = request.header("Access-Control-Request-Method")?.trim().parse().ok()?;
+        let probe = Request { method: requested_method.clone(), ..request.clone() };
+        let (route, _) = self.find_route(&probe).ok()?;
+        let policy = route.cors.as_ref().or(self.cors.as_ref())?;
 
-    /// 选择服务实例
-    #[allow(unused_variables)]
-    pub fn select_instance(&self, service_name: &str) -> Result<&ServiceInstance, GatewayError> {
-        // 简化的负载均衡实现
-        self.instances.first()
-            .ok_or(GatewayError::ServiceError("没有可用的服务实例".to_string()))
+        let requested_headers: Vec<&str> = request.header("Access-Control-Request-Headers")
+            .map(|headers| headers.split(',').map(str::trim).filter(|header| !header.is_empty()).collect())
+            .unwrap_or_default();
+        let mut headers = HashMap::new();
+        let allowed = policy.allows_origin(origin)
+            && policy.allowed_methods.contains(&requested_method)
+            && requested_headers.iter().all(|header| policy.allows_header(header));
+        if !allowed {
+            headers.insert("Vary".to_string(), "Origin".to_string());
+            return Some(Response {
+                status_code: 403,
+                headers,
+                body: Some(b"CORS preflight rejected".to_vec()),
+                processing_time: Duration::ZERO,
+            });
+        }
+
+        policy.apply(origin, &mut headers);
+        let methods: Vec<String> = policy.allowed_methods.iter().map(HttpMethod::to_string).collect();
+        headers.insert("Access-Control-Allow-Methods".to_string(), methods.join(", "));
+        if !requested_headers.is_empty() {
+            headers.insert("Access-Control-Allow-Headers".to_string(), requested_headers.join(", "));
+        }
+        if let Some(max_age) = policy.max_age {
+            headers.insert("Access-Control-Max-Age".to_string(), max_age.as_secs().to_string());
+        }
+        headers.remove("Access-Control-Expose-Headers");
+        Some(Response { status_code: 204, headers, body: None, processing_time: Duration::ZERO })
     }
-}
 
-impl Default for RateLimiter {
-    fn default() -> Self {
-        Self::new()
+    /// 增加路由进行中的请求数，返回的守卫释放时减少，请求被取消时同样如此
+    /// Count a request as in flight on a route; the returned guard decrements it when dropped, cancellation included
+    fn enter_route(&self, route: &str) -> RouteInFlight<'_> {
+        self.adjust_route_in_flight(route, true);
+        RouteInFlight { gateway: self, route: route.to_string() }
     }
-}
 
-impl RateLimiter {
-    /// 创建新的限流器
-    #[allow(unused_variables)]
-    pub fn new() -> Self {
-        Self {
-            limits: HashMap::new(),
-            token_buckets: Arc::new(Mutex::new(HashMap::new())),
-        }
+    /// 调整路由进行中的请求数并更新对应的仪表盘
+    /// Adjust a route's in-flight count and update its gauge
+    fn adjust_route_in_flight(&self, route: &str, entering: bool) {
+        let count = {
+            let mut in_flight = self.route_in_flight.lock().unwrap();
+            let count = in_flight.entry(route.to_string()).or_default();
+            *count = if entering { *count + 1 } else { count.saturating_sub(1) };
+            *count
+        };
+        let _ = self.metrics_collector.set_gauge(GATEWAY_IN_FLIGHT, count as f64, metric_labels(&[("route", route)]));
     }
 
-    /// 检查限流
-    #[allow(unused_variables)]
-    pub fn check_limit(&self, client_ip: &str) -> Result<(), GatewayError> {
-        // 简化的限流检查实现
-        Ok(())
+    /// 增加网关计数器；指标写入失败不影响请求处理
+    /// Increment a gateway counter; failing to record never affects request handling
+    fn inc_metric(&self, name: &str, labels: &[(&str, &str)]) {
+        let _ = self.metrics_collector.inc_counter(name, 1, metric_labels(labels));
     }
-}
 
-impl Default for Cache {
-    fn default() -> Self {
-        Self::new()
+    /// 解析路由引用的中间件
+    /// Resolve the middlewares referenced by a route
+    fn route_middlewares(&self, route: &Route) -> Result<Vec<Arc<dyn Middleware>>, GatewayError> {
+        route.middlewares.iter()
+            .map(|name| {
+                self.named_middlewares.get(name)
+                    .cloned()
+                    .ok_or_else(|| GatewayError::MiddlewareError(format!("未注册的中间件: {}", name)))
+            })
+            .collect()
+    }
+
+    /// 对已匹配路由的请求执行限流、熔断检查并转发
+    /// Apply rate limiting and circuit breaking to a routed request and forward it
+    async fn dispatch(&self, request: &mut Request, route: &Route) -> Result<Response, GatewayError> {
+        let limits = self.limits.with_overrides(&route.limit_overrides());
+        limits.check(request)?;
+        if let Some(transform) = &route.request_transform {
+            transform.transform_body(&mut request.headers, &mut request.body, "请求")?;
+        }
+        let request = &*request;
+
+        let decision = match self.check_rate_limit(request, route) {
+            Ok(decision) => decision,
+            Err(response) => return Ok(response),
+        };
+
+        // 先选定版本，缓存按版本区分，避免把一个版本的响应返回给分到另一版本的客户端
+        let group = route.split.as_ref().and_then(|split| split.choose(request));
+        let version = group.map(|group| group.version.as_str());
+
+        // 缓存命中时不访问上游
+        let cached = match self.cache.lookup_version(request, version) {
+            CacheLookup::Hit(response) | CacheLookup::Stale(response) => {
+                self.inc_metric(GATEWAY_CACHE_HITS, &[("route", &route.path)]);
+                Some(response)
+            }
+            CacheLookup::Miss => {
+                self.inc_metric(GATEWAY_CACHE_MISSES, &[("route", &route.path)]);
+                None
+            }
+            CacheLookup::Bypass => None,
+        };
+        let response = match cached {
+            Some(response) => response,
+            None => {
+                let forwarded = self.forward_with_retries(request, route, group, &limits).await;
+                if let Some(group) = group {
+                    let status_class = match &forwarded {
+                        Ok(response) => status_class(response.status_code),
+                        Err(error) => status_class(error.status_code()),
+                    };
+                    self.inc_metric(GATEWAY_VERSION_REQUESTS, &[
+                        ("route", &route.path),
+                        ("version", &group.version),
+                        ("status_class", &status_class),
+                    ]);
+                }
+                match &forwarded {
+                    Ok(response) => {
+                        self.cache.store_version(request, version, response, route.cache_ttl);
+                    }
+                    Err(_) => self.cache.release_version(request, version),
+                }
+                forwarded?
+            }
+        };
+
+        let mut response = response;
+        if let Some(transform) = &route.response_transform {
+            transform.transform_body(&mut response.headers, &mut response.body, "上游响应")?;
+            response.status_code = transform.map_status(response.status_code);
+        }
+
+        let mut headers = response.headers;
+        headers.insert("X-RateLimit-Limit".to_string(), decision.limit.to_string());
+        headers.insert("X-RateLimit-Remaining".to_string(), decision.remaining.to_string());
+
+        Ok(Response {
+            status_code: response.status_code,
+            headers,
+            body: response.body,
+            processing_time: response.processing_time,
+        })
+    }
+
+    /// 限流检查，超限时返回带 `Retry-After` 的 429 响应
+    /// Apply rate limiting, returning a 429 response with `Retry-After` when the limit is exceeded
+    fn check_rate_limit(&self, request: &Request, route: &Route) -> Result<RateLimitDecision, Response> {
+        let decision = self.rate_limiter.check(&self.rate_limiter.key_for(request, route));
+        if decision.allowed {
+            return Ok(decision);
+        }
+        self.inc_metric(GATEWAY_RATE_LIMITED, &[("route", &route.path)]);
+        let mut headers = HashMap::new();
+        let retry_after = decision.retry_after.unwrap_or_default();
+        // Retry-After 以整秒表示，向上取整
+        let retry_after_secs = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+        headers.insert("Retry-After".to_string(), retry_after_secs.to_string());
+        headers.insert("X-RateLimit-Limit".to_string(), decision.limit.to_string());
+        headers.insert("X-RateLimit-Remaining".to_string(), "0".to_string());
+        Err(Response {
+            status_code: 429,
+            headers,
+            body: Some(b"Too Many Requests".to_vec()),
+            processing_time: Duration::ZERO,
+        })
+    }
+
+    /// 选择上游实例并转发，熔断中的实例直接返回 503，超过 `upstream_timeout` 或到达请求截止时间仍未响应计为失败
+    /// Pick an upstream and forward the request; an open circuit yields a 503 and an upstream silent past
+    /// `upstream_timeout` or the request deadline counts as a failure
+    async fn forward_to_upstream(
+        &self,
+        request: &Request,
+        route: &Route,
+        group: Option<&UpstreamGroup>,
+        upstream_timeout: Duration,
+        deadline: Option<Instant>,
+    ) -> Result<Response, GatewayError> {
+        // 负载均衡选择服务实例（分流时只在所选组内选择），响应完成或出错时释放
+        let upstream = match group {
+            Some(group) => self.load_balancer.pick_from(&group.instances)?,
+            None => self.load_balancer.pick()?,
+        };
+        let permit = match self.load_balancer.circuit_breaker.try_acquire(&upstream.address) {
+            Ok(permit) => permit,
+            Err(retry_after) => return Ok(circuit_open_response(&upstream.address, retry_after)),
+        };
+
+        // 发送请求到后端服务，5xx、转发错误和超时计为失败；截止时间在这里生效，熔断器才能记录被截断的尝试
+        let remaining = deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()));
+        let deadline_first = remaining.is_some_and(|remaining| remaining < upstream_timeout);
+        let attempt_timeout = remaining.map_or(upstream_timeout, |remaining| remaining.min(upstream_timeout));
+        let forwarded = tokio::time::timeout(attempt_timeout, self.forward_request(request, &upstream))
+            .await
+            .unwrap_or_else(|_| {
+                if deadline_first {
+                    self.inc_metric(GATEWAY_TIMEOUTS, &[("route", &route.path), ("kind", "deadline")]);
+                    return Err(GatewayError::Timeout("请求超过截止时间".to_string()));
+                }
+                self.inc_metric(GATEWAY_TIMEOUTS, &[("route", &route.path), ("kind", "upstream")]);
+                Err(GatewayError::Timeout(format!("上游 {} 超过 {:?} 未响应", upstream.address, upstream_timeout)))
+            });
+        let transition = permit.record(forwarded.as_ref().is_ok_and(|response| response.status_code < 500));
+        if transition == Some(CircuitState::Open) {
+            self.inc_metric(GATEWAY_CIRCUIT_OPENS, &[("upstream", &upstream.address)]);
+        }
+        let failed = !forwarded.as_ref().is_ok_and(|response| response.status_code < 500);
+        let pool_size = self.load_balancer.instances.len();
+        let ejection = self.load_balancer.health.record_passive(&upstream.address, failed, pool_size);
+        self.record_health_transition(&upstream.address, ejection, "outlier");
+        let status_class = match &forwarded {
+            Ok(response) => status_class(response.status_code),
+            Err(_) => "error".to_string(),
+        };
+        self.inc_metric(GATEWAY_UPSTREAM_REQUESTS, &[("upstream", &upstream.address), ("status_class", &status_class)]);
+        drop(upstream);
+        forwarded
+    }
+
+    /// 按路由的重试策略转发，每次尝试都重新选择上游实例
+    /// Forward under the route's retry policy, picking an upstream afresh for every attempt
+    ///
+    /// 分流选中的上游组在全部尝试中保持不变。
+    /// The upstream group chosen by the traffic split stays the same across attempts.
+    ///
+    /// 请求截止时间由全部尝试共享：截止时间到达时正在进行的尝试以 504 结束，并和上游超时一样计为熔断器的一次失败；
+    /// 下一次退避会越过截止时间时不再重试，返回最后一次的结果。
+    /// The request deadline is shared by all attempts: an attempt still running when it expires ends with a 504
+    /// and, like an upstream timeout, counts as a circuit breaker failure; no retry is made when the next backoff
+    /// would overrun the deadline, returning the last outcome.
+    async fn forward_with_retries(
+        &self,
+        request: &Request,
+        route: &Route,
+        group: Option<&UpstreamGroup>,
+        limits: &RequestLimits,
+    ) -> Result<Response, GatewayError> {
+        let policy = route.retry.as_ref().filter(|policy| policy.allows(request));
+        let deadline = policy.and_then(|policy| policy.deadline)
+            .into_iter()
+            .chain(limits.request_deadline)
+            .min()
+            .map(|deadline| Instant::now() + deadline);
+        let mut attempt = 1;
+        loop {
+            self.metrics.upstream_attempts.fetch_add(1, Ordering::Relaxed);
+            let forwarded = self.forward_to_upstream(request, route, group, limits.upstream_timeout, deadline).await;
+            // 与熔断器一致：5xx 和转发错误视为失败
+            if forwarded.as_ref().is_ok_and(|response| response.status_code < 500) {
+                return forwarded;
+            }
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                self.metrics.deadline_exceeded.fetch_add(1, Ordering::Relaxed);
+                return forwarded;
+            }
+            let Some(policy) = policy else {
+                return forwarded;
+            };
+            if attempt >= policy.max_attempts.max(1) {
+                self.metrics.retries_exhausted.fetch_add(1, Ordering::Relaxed);
+                return forwarded;
+            }
+            let backoff = policy.backoff(attempt);
+            if deadline.is_some_and(|deadline| Instant::now() + backoff >= deadline) {
+                self.metrics.deadline_exceeded.fetch_add(1, Ordering::Relaxed);
+                return forwarded;
+            }
+            tokio::time::sleep(backoff).await;
+            self.metrics.retries.fetch_add(1, Ordering::Relaxed);
+            self.inc_metric(GATEWAY_RETRIES, &[("route", &route.path)]);
+            attempt += 1;
+        }
+    }
+
+    /// 网关计数
+    /// Gateway counters
+    pub fn metrics(&self) -> GatewayMetrics {
+        GatewayMetrics {
+            upstream_attempts: self.metrics.upstream_attempts.load(Ordering::Relaxed),
+            retries: self.metrics.retries.load(Ordering::Relaxed),
+            retries_exhausted: self.metrics.retries_exhausted.load(Ordering::Relaxed),
+            deadline_exceeded: self.metrics.deadline_exceeded.load(Ordering::Relaxed),
+        }
+    }
+
+    /// 对全部上游实例执行一轮主动健康检查，按阈值摘除或重新加入实例
+    /// Run one round of active health checks, ejecting or re-admitting instances by threshold
+    pub async fn check_upstreams(&self) -> Vec<HealthCheckResult> {
+        let config = &self.load_balancer.health.config;
+        let probe = Request {
+            method: HttpMethod::GET,
+            path: config.path.clone(),
+            headers: HashMap::new(),
+            query_params: HashMap::new(),
+            body: None,
+            client_ip: "gateway".to_string(),
+            path_params: HashMap::new(),
+            principal: None,
+        };
+        let mut results = Vec::with_capacity(self.load_balancer.instances.len());
+        for instance in &self.load_balancer.instances {
+            let started = Instant::now();
+            let outcome = tokio::time::timeout(config.timeout, self.upstream.send(&probe, instance)).await;
+            let (passed, details, error) = match outcome {
+                Ok(Ok(response)) => {
+                    ((200..400).contains(&response.status_code), Some(format!("HTTP {}", response.status_code)), None)
+                }
+                Ok(Err(e)) => (false, None, Some(e.to_string())),
+                Err(_) => (false, None, Some(format!("超过 {:?} 未响应", config.timeout))),
+            };
+            let transition = self.load_balancer.health.record_check(&instance.address, passed, self.load_balancer.instances.len());
+            self.record_health_transition(&instance.address, transition, "active");
+            results.push(HealthCheckResult {
+                name: instance.address.clone(),
+                status: if passed { HealthStatus::Healthy } else { HealthStatus::Unhealthy },
+                response_time: started.elapsed(),
+                details,
+                error,
+                timestamp: SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs(),
+                attempts: 1,
+            });
+        }
+        results
+    }
+
+    /// 启动后台任务，每隔 `interval` 执行一轮主动健康检查；网关释放后任务自行结束
+    /// Spawn a background task running active health checks every `interval`; it ends once the gateway is dropped
+    pub fn start_health_checks(self: &Arc<Self>) {
+        let gateway: Weak<Self> = Arc::downgrade(self);
+        let interval = self.load_balancer.health.config.interval.max(Duration::from_millis(1));
+        let handle = tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                let Some(gateway) = gateway.upgrade() else {
+                    break;
+                };
+                gateway.check_upstreams().await;
+            }
+        });
+        if let Ok(mut task) = self.health_check_task.lock()
+            && let Some(previous) = task.replace(handle)
+        {
+            previous.abort();
+        }
+    }
+
+    /// 停止后台健康检查任务
+    /// Stop the background health check task
+    pub fn stop_health_checks(&self) {
+        if let Ok(mut task) = self.health_check_task.lock()
+            && let Some(handle) = task.take()
+        {
+            handle.abort();
+        }
+    }
+
+    /// 供 [`crate::monitoring_advanced::HealthChecker`] 注册的上游实例池检查
+    /// Upstream pool check for registration with [`crate::monitoring_advanced::HealthChecker`]
+    pub fn upstream_health_check(&self) -> UpstreamPoolHealthCheck {
+        UpstreamPoolHealthCheck {
+            addresses: self.load_balancer.instances.iter().map(|instance| instance.address.clone()).collect(),
+            health: Arc::clone(&self.load_balancer.health),
+        }
+    }
+
+    /// 记录并计数实例的摘除与重新加入
+    /// Log and count an instance being ejected or re-admitted
+    fn record_health_transition(&self, address: &str, transition: Option<HealthStatus>, reason: &str) {
+        match transition {
+            Some(HealthStatus::Unhealthy) => {
+                log::warn!("上游 {} 被摘除（{}）", address, reason);
+                self.inc_metric(GATEWAY_UPSTREAM_EJECTIONS, &[("upstream", address), ("reason", reason)]);
+            }
+            Some(HealthStatus::Healthy) => {
+                log::info!("上游 {} 通过健康检查，重新加入", address);
+                self.inc_metric(GATEWAY_UPSTREAM_READMISSIONS, &[("upstream", address)]);
+            }
+            _ => {}
+        }
+    }
+
+    /// 汇总指标收集器中的网关指标
+    /// Summarize the gateway metrics held by the metrics collector
+    pub fn metrics_summary(&self) -> GatewayMetricsSummary {
+        let mut routes: BTreeMap<String, RouteMetricsSummary> = BTreeMap::new();
+        let mut upstreams: BTreeMap<String, UpstreamMetricsSummary> = BTreeMap::new();
+        if let Ok(metrics) = self.metrics_collector.metrics.lock() {
+            for metric in metrics.values() {
+                let value = match metric.value {
+                    MetricValue::Integer(value) => u64::try_from(value).unwrap_or(0),
+                    MetricValue::Float(value) => value.max(0.0) as u64,
+                    MetricValue::Distribution(_) => continue,
+                };
+                let label = |name: &str| metric.labels.get(name).cloned().unwrap_or_default();
+                match metric.name.as_str() {
+                    name @ (GATEWAY_REQUESTS | GATEWAY_IN_FLIGHT | GATEWAY_RATE_LIMITED | GATEWAY_CACHE_HITS
+                    | GATEWAY_CACHE_MISSES | GATEWAY_RETRIES | GATEWAY_TIMEOUTS | GATEWAY_VERSION_REQUESTS) => {
+                        let route = label("route");
+                        let summary = routes.entry(route.clone())
+                            .or_insert_with(|| RouteMetricsSummary { route, ..Default::default() });
+                        match name {
+                            GATEWAY_REQUESTS => {
+                                summary.requests += value;
+                                *summary.status_classes.entry(label("status_class")).or_default() += value;
+                            }
+                            GATEWAY_IN_FLIGHT => summary.in_flight = value,
+                            GATEWAY_RATE_LIMITED => summary.rate_limited += value,
+                            GATEWAY_CACHE_HITS => summary.cache_hits += value,
+                            GATEWAY_CACHE_MISSES => summary.cache_misses += value,
+                            GATEWAY_RETRIES => summary.retries += value,
+                            GATEWAY_TIMEOUTS => summary.timeouts += value,
+                            _ => {
+                                *summary.versions.entry(label("version")).or_default()
+                                    .entry(label("status_class")).or_default() += value;
+                            }
+                        }
+                    }
+                    name @ (GATEWAY_UPSTREAM_REQUESTS | GATEWAY_CIRCUIT_OPENS | GATEWAY_UPSTREAM_EJECTIONS) => {
+                        let upstream = label("upstream");
+                        let summary = upstreams.entry(upstream.clone())
+                            .or_insert_with(|| UpstreamMetricsSummary { upstream, ..Default::default() });
+                        match name {
+                            GATEWAY_UPSTREAM_REQUESTS => {
+                                summary.requests += value;
+                                *summary.status_classes.entry(label("status_class")).or_default() += value;
+                            }
+                            GATEWAY_CIRCUIT_OPENS => summary.circuit_opens += value,
+                            _ => summary.ejections += value,
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        if let Ok(histograms) = self.metrics_collector.histograms.lock()
+            && let Some(histogram) = histograms.get(GATEWAY_REQUEST_DURATION)
+        {
+            let mut latency: HashMap<&str, (f64, u64)> = HashMap::new();
+            for series in histogram.series.values() {
+                let route = series.labels.get("route").map(String::as_str).unwrap_or_default();
+                let (sum, count) = latency.entry(route).or_default();
+                *sum += series.sum;
+                *count += series.count;
+            }
+            for (route, (sum, count)) in latency {
+                if let Some(summary) = routes.get_mut(route)
+                    && count > 0
+                {
+                    summary.mean_latency_ms = Some(sum * 1000.0 / count as f64);
+                }
+            }
+        }
+
+        let states = self.circuit_states();
+        for summary in upstreams.values_mut() {
+            summary.circuit_state = states.get(&summary.upstream).copied();
+            summary.ejected = self.load_balancer.health.is_ejected(&summary.upstream);
+        }
+        GatewayMetricsSummary {
+            routes: routes.into_values().collect(),
+            upstreams: upstreams.into_values().collect(),
+            retries: self.metrics(),
+        }
+    }
+
+    /// 各上游实例的熔断状态
+    /// Circuit state of every upstream
+    pub fn circuit_states(&self) -> HashMap<String, CircuitState> {
+        self.load_balancer.circuit_breaker.circuit_states()
+    }
+
+    /// 查找路由，返回匹配的路由和捕获的路径参数
+    /// Find the route and the captured path parameters
+    fn find_route(&self, request: &Request) -> Result<(Route, HashMap<String, String>), GatewayError> {
+        let routes = self.routes.lock().unwrap();
+        let patterns = self.route_patterns.lock().unwrap();
+        patterns.iter()
+            .filter(|compiled| compiled.method == request.method)
+            .find_map(|compiled| {
+                let params = compiled.pattern.matches(&request.path)?;
+                routes.get(&compiled.key).map(|route| (route.clone(), params))
+            })
+            // 直接写入 `routes` 的路由只做精确匹配
+            .or_else(|| {
+                routes.get(&format!("{}:{}", request.method, request.path)).map(|route| (route.clone(), HashMap::new()))
+            })
+            .ok_or_else(|| GatewayError::RoutingError(format!("未找到路由: {}:{}", request.method, request.path)))
+    }
+
+    /// 转发请求
+    async fn forward_request(&self, request: &Request, instance: &ServiceInstance) -> Result<Response, GatewayError> {
+        self.upstream.send(request, instance).await
     }
 }
 
-impl Cache {
-    /// 创建新的缓存
-    #[allow(unused_variables)]
-    pub fn new() -> Self {
-        Self {
-            storage: Arc::new(Mutex::new(HashMap::new())),
-            default_ttl: Duration::from_secs(300), // 5分钟
+/// 依次执行中间件的 `on_request`，记录已进入的中间件；短路或出错时返回响应
+/// Run `on_request` hooks in order, recording entered middlewares; returns a response on short-circuit or error
+fn run_request_middlewares(
+    chain: &[Arc<dyn Middleware>],
+    request: &mut Request,
+    entered: &mut Vec<Arc<dyn Middleware>>,
+) -> Option<Response> {
+    for middleware in chain {
+        entered.push(Arc::clone(middleware));
+        match call_middleware(|| middleware.on_request(request)) {
+            Ok(MiddlewareAction::Continue) => {}
+            Ok(MiddlewareAction::ShortCircuit(response)) => return Some(response),
+            Err(error) => return Some(error_response(&error)),
         }
     }
+    None
+}
 
-    /// 获取缓存
-    #[allow(unused_variables)]
-    pub fn get(&self, key: &str) -> Option<Vec<u8>> {
-        let mut storage = self.storage.lock().unwrap();
-        if let Some(entry) = storage.get_mut(key) {
-            if entry.expires_at > Instant::now() {
-                entry.access_count += 1;
-                return Some(entry.value.clone());
-            } else {
-                storage.remove(key);
+/// 调用中间件钩子，panic 会被转换为中间件错误
+/// Invoke a middleware hook, converting a panic into a middleware error
+fn call_middleware<T>(hook: impl FnOnce() -> Result<T, GatewayError>) -> Result<T, GatewayError> {
+    panic::catch_unwind(AssertUnwindSafe(hook))
+        .unwrap_or_else(|_| Err(GatewayError::MiddlewareError("中间件发生 panic".to_string())))
+}
+
+/// 创建网关默认使用的指标收集器
+/// Create the metrics collector a gateway uses by default
+fn gateway_metrics_collector() -> MetricsCollector {
+    let collector = MetricsCollector::new(MetricsConfig {
+        enabled: true,
+        collection_interval: Duration::from_secs(10),
+        retention_period: Duration::from_secs(3600),
+        export_format: ExportFormat::Prometheus,
+    });
+    register_gateway_metrics(&collector);
+    collector
+}
+
+/// 在收集器中注册网关的计数器和直方图
+/// Register the gateway counters and histogram with a collector
+fn register_gateway_metrics(collector: &MetricsCollector) {
+    let metadata = |description: &str, unit: Option<&str>| MetricMetadata {
+        description: description.to_string(),
+        unit: unit.map(str::to_string),
+        help: None,
+    };
+    for (name, description) in [
+        (GATEWAY_REQUESTS, "Gateway requests by route, method and status class"),
+        (GATEWAY_RATE_LIMITED, "Requests rejected by the rate limiter"),
+        (GATEWAY_CACHE_HITS, "Responses served from the gateway cache"),
+        (GATEWAY_CACHE_MISSES, "Cacheable requests that missed the gateway cache"),
+        (GATEWAY_RETRIES, "Upstream retries"),
+        (GATEWAY_TIMEOUTS, "Upstream timeouts and expired request deadlines"),
+        (GATEWAY_UPSTREAM_REQUESTS, "Requests forwarded to each upstream by status class"),
+        (GATEWAY_VERSION_REQUESTS, "Requests of traffic-split routes by version and status class"),
+        (GATEWAY_CIRCUIT_OPENS, "Times an upstream circuit opened"),
+        (GATEWAY_UPSTREAM_EJECTIONS, "Upstreams ejected by health checks or outlier detection"),
+        (GATEWAY_UPSTREAM_READMISSIONS, "Ejected upstreams re-admitted after passing health checks"),
+    ] {
+        let _ = collector.register_counter(name, metadata(description, None));
+    }
+    let _ = collector.register_histogram(
+        GATEWAY_REQUEST_DURATION,
+        BucketLayout::Exponential { start: 0.001, factor: 2.0, count: 14 },
+        metadata("Gateway request latency", Some("seconds")),
+    );
+}
+
+/// 构造指标标签
+/// Build metric labels
+fn metric_labels(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+    pairs.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect()
+}
+
+/// 状态码类别，如 `2xx`
+/// Status code class, e.g. `2xx`
+fn status_class(status_code: u16) -> String {
+    format!("{}xx", status_code / 100)
+}
+
+/// 计算 `Sec-WebSocket-Accept`（RFC 6455 第 4.2.2 节）
+/// Compute `Sec-WebSocket-Accept` (RFC 6455 section 4.2.2)
+fn websocket_accept(key: &str) -> String {
+    const WEBSOCKET_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+    let digest = ring::digest::digest(
+        &ring::digest::SHA1_FOR_LEGACY_USE_ONLY,
+        format!("{}{}", key.trim(), WEBSOCKET_GUID).as_bytes(),
+    );
+    base64::engine::general_purpose::STANDARD.encode(digest.as_ref())
+}
+
+/// 在客户端和上游之间中继帧，直到一方关闭、空闲超时或帧超过限制；中继期间占用上游实例
+/// Relay frames between client and upstream until either side closes, the idle timeout fires or a frame exceeds
+/// its limit; the upstream stays in flight for the whole session
+async fn relay_stream(
+    mut client: StreamChannel,
+    mut upstream: StreamChannel,
+    config: StreamingConfig,
+    _in_flight: UpstreamGuard,
+) -> StreamSummary {
+    let close = |code: u16, reason: &str| StreamFrame::Close { code, reason: reason.to_string() };
+    // 对端不读取时发送同样受空闲超时约束，不会让中继永远阻塞在满缓冲上
+    let send = |sender: &tokio::sync::mpsc::Sender<StreamFrame>, frame: StreamFrame| {
+        let sender = sender.clone();
+        async move { matches!(tokio::time::timeout(config.idle_timeout, sender.send(frame)).await, Ok(Ok(()))) }
+    };
+    let mut frames_to_upstream = 0;
+    let mut frames_to_client = 0;
+    // 每个方向各自计时，只有一端在发送时另一端的空闲仍会触发超时
+    let mut client_deadline = tokio::time::Instant::now() + config.idle_timeout;
+    let mut upstream_deadline = client_deadline;
+    let close_reason = loop {
+        let next = tokio::time::timeout_at(client_deadline.min(upstream_deadline), async {
+            tokio::select! {
+                frame = client.receiver.recv() => (StreamDirection::ClientToUpstream, frame),
+                frame = upstream.receiver.recv() => (StreamDirection::UpstreamToClient, frame),
+            }
+        })
+        .await;
+        let (direction, frame) = match next {
+            Ok(next) => next,
+            Err(_) => {
+                send(&client.sender, close(1001, "idle timeout")).await;
+                send(&upstream.sender, close(1001, "idle timeout")).await;
+                break StreamCloseReason::IdleTimeout;
             }
+        };
+        let (target, limit, closed) = match direction {
+            StreamDirection::ClientToUpstream => (&upstream.sender, config.max_client_frame, StreamCloseReason::ClientClosed),
+            StreamDirection::UpstreamToClient => (&client.sender, config.max_upstream_frame, StreamCloseReason::UpstreamClosed),
+        };
+        let read_at = tokio::time::Instant::now() + config.idle_timeout;
+        match direction {
+            StreamDirection::ClientToUpstream => client_deadline = read_at,
+            StreamDirection::UpstreamToClient => upstream_deadline = read_at,
         }
-        None
+        match frame {
+            // 对端断开时向另一端发送关闭帧
+            None => {
+                send(target, close(1001, "peer disconnected")).await;
+                break closed;
+            }
+            Some(frame @ StreamFrame::Close { .. }) => {
+                send(target, frame).await;
+                break closed;
+            }
+            Some(frame) => {
+                let size = match &frame {
+                    StreamFrame::Text(text) => text.len(),
+                    StreamFrame::Binary(data) => data.len(),
+                    StreamFrame::Close { .. } => 0,
+                };
+                if size > limit {
+                    log::warn!("流式帧 {} 字节超过 {} 字节限制，关闭连接", size, limit);
+                    send(&client.sender, close(1009, "frame too large")).await;
+                    send(&upstream.sender, close(1009, "frame too large")).await;
+                    break StreamCloseReason::FrameTooLarge(direction);
+                }
+                if !send(target, frame).await {
+                    if target.is_closed() {
+                        break closed;
+                    }
+                    log::warn!("流式帧 {:?} 方向超过 {:?} 未被接收，关闭连接", direction, config.idle_timeout);
+                    send(&client.sender, close(1001, "idle timeout")).await;
+                    send(&upstream.sender, close(1001, "idle timeout")).await;
+                    break StreamCloseReason::IdleTimeout;
+                }
+                match direction {
+                    StreamDirection::ClientToUpstream => frames_to_upstream += 1,
+                    StreamDirection::UpstreamToClient => frames_to_client += 1,
+                }
+            }
+        }
+    };
+    StreamSummary { frames_to_upstream, frames_to_client, close_reason }
+}
+
+impl StreamChannel {
+    /// 创建相互连接的两端，每个方向缓冲 `buffer` 帧
+    /// Create two connected ends buffering `buffer` frames per direction
+    pub fn pair(buffer: usize) -> (StreamChannel, StreamChannel) {
+        let (left_sender, right_receiver) = tokio::sync::mpsc::channel(buffer.max(1));
+        let (right_sender, left_receiver) = tokio::sync::mpsc::channel(buffer.max(1));
+        (
+            StreamChannel { sender: left_sender, receiver: left_receiver },
+            StreamChannel { sender: right_sender, receiver: right_receiver },
+        )
     }
+}
 
-    /// 设置缓存
-    #[allow(unused_variables)]
-    pub fn set(&self, key: String, value: Vec<u8>, ttl: Option<Duration>) -> Result<(), GatewayError> {
-        let ttl = ttl.unwrap_or(self.default_ttl);
-        let entry = CacheEntry {
-            value,
-            expires_at: Instant::now() + ttl,
-            access_count: 0,
+/// 熔断中的上游对应的 503 响应，`Retry-After` 向上取整且至少为 1 秒
+/// 503 response for an upstream with an open circuit; `Retry-After` is rounded up to at least one second
+fn circuit_open_response(address: &str, retry_after: Duration) -> Response {
+    let mut headers = HashMap::new();
+    let retry_after_secs = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+    headers.insert("Retry-After".to_string(), retry_after_secs.max(1).to_string());
+    Response {
+        status_code: 503,
+        headers,
+        body: Some(format!("上游 {} 已熔断", address).into_bytes()),
+        processing_time: Duration::ZERO,
+    }
+}
+
+/// 由网关错误构造响应
+/// Build a response from a gateway error
+fn error_response(error: &GatewayError) -> Response {
+    Response {
+        status_code: error.status_code(),
+        headers: HashMap::new(),
+        body: Some(error.to_string().into_bytes()),
+        processing_time: Duration::ZERO,
+    }
+}
+
+impl CorsPolicy {
+    /// 校验策略：源模式必须合法，且 `*` 不能与 `allow_credentials` 同时使用
+    /// Validate the policy: origin patterns must be well formed and `*` cannot be combined with credentials
+    pub fn validate(&self) -> Result<(), GatewayError> {
+        for origin in &self.allowed_origins {
+            if origin == "*" {
+                if self.allow_credentials {
+                    return Err(GatewayError::ConfigError("CORS 策略不能同时允许任意源和携带凭据".to_string()));
+                }
+                continue;
+            }
+            let Some((scheme, host)) = origin.split_once("://") else {
+                return Err(GatewayError::ConfigError(format!("CORS 源缺少协议: {}", origin)));
+            };
+            let valid_host = match host.strip_prefix("*.") {
+                Some(suffix) => !suffix.is_empty() && !suffix.contains('*'),
+                None => !host.is_empty() && !host.contains('*'),
+            };
+            if scheme.is_empty() || !valid_host || host.contains('/') {
+                return Err(GatewayError::ConfigError(format!("无效的 CORS 源: {}", origin)));
+            }
+        }
+        Ok(())
+    }
+
+    /// 源是否被允许：整串相等，或匹配 `scheme://*.suffix` 形式的子域名后缀
+    /// Whether the origin is allowed, by whole-string equality or a `scheme://*.suffix` subdomain pattern
+    pub fn allows_origin(&self, origin: &str) -> bool {
+        self.allowed_origins.iter().any(|allowed| {
+            if allowed == "*" || allowed.eq_ignore_ascii_case(origin) {
+                return true;
+            }
+            let Some((scheme, suffix)) = allowed.split_once("://*.") else {
+                return false;
+            };
+            let origin = origin.to_ascii_lowercase();
+            origin.strip_prefix(&format!("{}://", scheme.to_ascii_lowercase()))
+                .and_then(|host| host.strip_suffix(&format!(".{}", suffix.to_ascii_lowercase())))
+                .is_some_and(|subdomain| {
+                    !subdomain.is_empty()
+                        && subdomain.split('.').all(|label| {
+                            !label.is_empty() && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+                        })
+                })
+        })
+    }
+
+    /// 请求头是否被预检允许
+    /// Whether a request header is allowed by preflight
+    fn allows_header(&self, header: &str) -> bool {
+        self.allowed_headers.iter().any(|allowed| allowed == "*" || allowed.eq_ignore_ascii_case(header))
+    }
+
+    /// 源匹配时写入 `Access-Control-Allow-*` 响应头
+    /// Add the `Access-Control-Allow-*` response headers when the origin matches
+    fn apply(&self, origin: &str, headers: &mut HashMap<String, String>) {
+        if !self.allows_origin(origin) {
+            return;
+        }
+        let any_origin = !self.allow_credentials && self.allowed_origins.iter().any(|allowed| allowed == "*");
+        if any_origin {
+            headers.insert("Access-Control-Allow-Origin".to_string(), "*".to_string());
+        } else {
+            headers.insert("Access-Control-Allow-Origin".to_string(), origin.to_string());
+            // 响应随 Origin 变化，缓存必须区分
+            match headers.iter_mut().find(|(name, _)| name.eq_ignore_ascii_case("Vary")) {
+                Some((_, vary)) if !vary.split(',').any(|field| field.trim().eq_ignore_ascii_case("Origin")) => {
+                    vary.push_str(", Origin");
+                }
+                Some(_) => {}
+                None => {
+                    headers.insert("Vary".to_string(), "Origin".to_string());
+                }
+            }
+        }
+        if self.allow_credentials {
+            headers.insert("Access-Control-Allow-Credentials".to_string(), "true".to_string());
+        }
+        if !self.exposed_headers.is_empty() {
+            headers.insert("Access-Control-Expose-Headers".to_string(), self.exposed_headers.join(", "));
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// 请求是否可以按该策略重试
+    /// Whether the request may be retried under this policy
+    pub fn allows(&self, request: &Request) -> bool {
+        request.method.is_idempotent()
+            || self.idempotency_key_header.as_deref().is_some_and(|header| request.header(header).is_some())
+    }
+
+    /// 第 `attempt` 次尝试失败后的退避时长，已加入抖动
+    /// Backoff after the given failed attempt, with jitter applied
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let exponential = self.base_backoff.saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)));
+        let backoff = exponential.min(self.max_backoff);
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        if jitter == 0.0 {
+            return backoff;
+        }
+        backoff.mul_f64(1.0 - jitter * rand::random::<f64>())
+    }
+}
+
+impl TrafficSplit {
+    /// 校验分流配置：至少一个组，版本名唯一且组内有实例，权重之和大于零，`canary` 指向已有版本
+    /// Validate the split: at least one group, unique versions with instances, a positive total weight and a
+    /// known `canary`
+    pub fn validate(&self) -> Result<(), GatewayError> {
+        if self.groups.is_empty() {
+            return Err(GatewayError::ConfigError("分流至少需要一个上游组".to_string()));
+        }
+        let mut versions = HashSet::new();
+        for group in &self.groups {
+            if !versions.insert(group.version.as_str()) {
+                return Err(GatewayError::ConfigError(format!("分流版本 {} 重复", group.version)));
+            }
+            if group.instances.is_empty() {
+                return Err(GatewayError::ConfigError(format!("分流版本 {} 没有上游实例", group.version)));
+            }
+        }
+        if self.groups.iter().map(|group| u64::from(group.weight)).sum::<u64>() == 0 {
+            return Err(GatewayError::ConfigError("分流权重之和必须大于零".to_string()));
+        }
+        if let Some(canary) = self.canary.as_deref().filter(|canary| !versions.contains(canary)) {
+            return Err(GatewayError::ConfigError(format!("灰度版本 {} 不存在", canary)));
+        }
+        Ok(())
+    }
+
+    /// 为请求选择上游组，没有任何组时返回 `None`
+    /// Choose the upstream group of a request, or `None` when there are no groups
+    ///
+    /// 未经 [`validate`](Self::validate) 的配置不会导致 panic：权重之和为零时使用第一个组。
+    /// An unvalidated split never panics: with a zero total weight the first group is used.
+    pub fn choose(&self, request: &Request) -> Option<&UpstreamGroup> {
+        let forced = self.override_header.as_deref()
+            .and_then(|header| request.header(header))
+            .map(str::trim)
+            .and_then(|value| {
+                let version = if value.eq_ignore_ascii_case("always") { self.canary.as_deref()? } else { value };
+                self.groups.iter().find(|group| group.version == version)
+            });
+        if forced.is_some() {
+            return forced;
+        }
+        let total: u64 = self.groups.iter().map(|group| u64::from(group.weight)).sum();
+        if total == 0 {
+            return self.groups.first();
+        }
+
+        // 在 [0, 1) 上取点并按累计权重落到组上；粘性分配的点只取决于客户端键
+        let point = match &self.sticky {
+            Some(sticky) => (fnv1a(sticky.key_for(request).as_bytes()) % 10_000) as f64 / 10_000.0,
+            None => rand::random::<f64>(),
+        };
+        let target = point * total as f64;
+        let mut cumulative = 0;
+        for group in self.groups.iter().filter(|group| group.weight > 0) {
+            cumulative += u64::from(group.weight);
+            if target < cumulative as f64 {
+                return Some(group);
+            }
+        }
+        self.groups.iter().rfind(|group| group.weight > 0)
+    }
+}
+
+impl StickyKey {
+    /// 提取请求的客户端键
+    /// Extract the client key of a request
+    fn key_for<'a>(&self, request: &'a Request) -> &'a str {
+        let key = match self {
+            StickyKey::ClientIp => None,
+            StickyKey::Header(name) => request.header(name).map(str::trim).filter(|value| !value.is_empty()),
+            StickyKey::Principal => request.principal().map(|principal| principal.subject.as_str()),
         };
+        key.unwrap_or(&request.client_ip)
+    }
+}
 
-        let mut storage = self.storage.lock().unwrap();
-        storage.insert(key, entry);
+impl RequestLimits {
+    /// 用路由的覆盖值替换对应的默认值
+    /// Replace the defaults with the route's overrides
+    pub fn with_overrides(&self, overrides: &RouteLimits) -> RequestLimits {
+        RequestLimits {
+            max_body_bytes: overrides.max_body_bytes.unwrap_or(self.max_body_bytes),
+            max_header_count: overrides.max_header_count.unwrap_or(self.max_header_count),
+            max_header_bytes: overrides.max_header_bytes.unwrap_or(self.max_header_bytes),
+            upstream_timeout: overrides.upstream_timeout.unwrap_or(self.upstream_timeout),
+            request_deadline: overrides.request_deadline.or(self.request_deadline),
+        }
+    }
+
+    /// 从连接读取请求体，读到的字节数超过 `max_body_bytes` 时立即停止并返回 413
+    /// Read the request body from the connection, stopping with a 413 as soon as it exceeds `max_body_bytes`
+    ///
+    /// 声明的 `Content-Length` 超过限制时不读取任何字节；声明了长度时只读取该长度，否则最多读取上限加一个字节。
+    /// A declared `Content-Length` over the limit is rejected before reading anything; with a declared length only
+    /// that many bytes are read, otherwise at most one byte past the limit.
+    pub async fn read_body<R>(&self, request: &mut Request, reader: R) -> Result<(), GatewayError>
+    where
+        R: tokio::io::AsyncRead + Unpin,
+    {
+        use tokio::io::AsyncReadExt;
+
+        let declared = self.declared_length(request)?;
+        let too_large = |bytes: usize| {
+            GatewayError::PayloadTooLarge(format!("请求体 {} 字节，超过上限 {}", bytes, self.max_body_bytes))
+        };
+        if let Some(declared) = declared.filter(|&declared| declared > self.max_body_bytes) {
+            return Err(too_large(declared));
+        }
+        let cap = declared.unwrap_or(self.max_body_bytes.saturating_add(1));
+        let mut body = Vec::with_capacity(declared.unwrap_or(0));
+        reader.take(cap as u64).read_to_end(&mut body).await
+            .map_err(|error| GatewayError::BadRequest(format!("读取请求体失败: {}", error)))?;
+        if body.len() > self.max_body_bytes {
+            return Err(too_large(body.len()));
+        }
+        if let Some(declared) = declared.filter(|&declared| body.len() < declared) {
+            return Err(GatewayError::BadRequest(format!("请求体不完整: 声明 {} 字节，读到 {}", declared, body.len())));
+        }
+        request.body = Some(body);
+        Ok(())
+    }
+
+    /// 请求声明的 `Content-Length`；无法解析的值返回 400，超出 `usize` 的值返回 413
+    /// The request's declared `Content-Length`; an unparsable value is a 400 and one beyond `usize` a 413
+    fn declared_length(&self, request: &Request) -> Result<Option<usize>, GatewayError> {
+        let Some(length) = request.header("Content-Length") else {
+            return Ok(None);
+        };
+        match length.trim().parse::<usize>() {
+            Ok(length) => Ok(Some(length)),
+            Err(error) if *error.kind() == std::num::IntErrorKind::PosOverflow => Err(GatewayError::PayloadTooLarge(
+                format!("请求体 {} 字节，超过上限 {}", length.trim(), self.max_body_bytes),
+            )),
+            Err(_) => Err(GatewayError::BadRequest(format!("无效的 Content-Length: {}", length))),
+        }
+    }
+
+    /// 检查请求头部和请求体的大小
+    /// Check the size of the request headers and body
+    ///
+    /// 声明的 `Content-Length` 超过限制时直接拒绝，不必等到读完请求体；无法解析的 `Content-Length` 返回 400。
+    /// A declared `Content-Length` over the limit is rejected without waiting for the body to be read, and an
+    /// unparsable `Content-Length` is a bad request.
+    pub fn check(&self, request: &Request) -> Result<(), GatewayError> {
+        if request.headers.len() > self.max_header_count {
+            return Err(GatewayError::HeadersTooLarge(format!(
+                "头部个数 {} 超过上限 {}", request.headers.len(), self.max_header_count
+            )));
+        }
+        let header_bytes: usize = request.headers.iter().map(|(name, value)| name.len() + value.len()).sum();
+        if header_bytes > self.max_header_bytes {
+            return Err(GatewayError::HeadersTooLarge(format!(
+                "头部共 {} 字节，超过上限 {}", header_bytes, self.max_header_bytes
+            )));
+        }
+        let declared = self.declared_length(request)?;
+        let body_bytes = declared.into_iter().chain(request.body.as_ref().map(Vec::len)).max().unwrap_or(0);
+        if body_bytes > self.max_body_bytes {
+            return Err(GatewayError::PayloadTooLarge(format!(
+                "请求体 {} 字节，超过上限 {}", body_bytes, self.max_body_bytes
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl TransformSpec {
+    /// 依次应用规则
+    /// Apply the rules in order
+    pub fn apply(&self, mut value: serde_json::Value) -> Result<serde_json::Value, GatewayError> {
+        for rule in &self.rules {
+            match rule {
+                TransformRule::Rename { from, to } => {
+                    if let Some(moved) = take_json_path(&mut value, from) {
+                        insert_json_path(&mut value, to, moved)?;
+                    }
+                }
+                TransformRule::Remove { path } => {
+                    take_json_path(&mut value, path);
+                }
+                TransformRule::Add { path, value: added } => insert_json_path(&mut value, path, added.clone())?,
+                TransformRule::Wrap { field } => {
+                    let mut envelope = serde_json::Value::Object(serde_json::Map::new());
+                    insert_json_path(&mut envelope, field, value)?;
+                    value = envelope;
+                }
+                TransformRule::Unwrap { field } => {
+                    value = take_json_path(&mut value, field)
+                        .ok_or_else(|| GatewayError::TransformError(format!("信封字段 {} 不存在", field)))?;
+                }
+            }
+        }
+        Ok(value)
+    }
+
+    /// 映射状态码，未列出的状态码保持不变
+    /// Map a status code; unlisted codes are kept
+    pub fn map_status(&self, status_code: u16) -> u16 {
+        self.status_map.get(&status_code).copied().unwrap_or(status_code)
+    }
+
+    /// 转换 JSON 消息体并同步 `Content-Length`，`side` 用于错误信息
+    /// Transform a JSON body and keep `Content-Length` in sync; `side` names the body in errors
+    fn transform_body(
+        &self,
+        headers: &mut HashMap<String, String>,
+        body: &mut Option<Vec<u8>>,
+        side: &str,
+    ) -> Result<(), GatewayError> {
+        let is_json = header_value(headers, "Content-Type").is_some_and(|value| value.to_ascii_lowercase().contains("json"));
+        let Some(bytes) = body.as_ref().filter(|bytes| is_json && !self.rules.is_empty() && !bytes.is_empty()) else {
+            return Ok(());
+        };
+
+        let value: serde_json::Value = serde_json::from_slice(bytes)
+            .map_err(|e| GatewayError::TransformError(format!("{}不是合法的 JSON: {}", side, e)))?;
+        let transformed = serde_json::to_vec(&self.apply(value)?)
+            .map_err(|e| GatewayError::TransformError(e.to_string()))?;
+        if let Some((_, length)) = headers.iter_mut().find(|(name, _)| name.eq_ignore_ascii_case("Content-Length")) {
+            *length = transformed.len().to_string();
+        }
+        *body = Some(transformed);
         Ok(())
     }
 }
 
+/// 取出 `.` 分隔路径上的值
+/// Take the value at a dot separated path
+fn take_json_path(value: &mut serde_json::Value, path: &str) -> Option<serde_json::Value> {
+    let (parent, field) = match path.rsplit_once('.') {
+        Some((parent, field)) => (parent.split('.').try_fold(value, |value, segment| value.get_mut(segment))?, field),
+        None => (value, path),
+    };
+    parent.as_object_mut()?.remove(field)
+}
+
+/// 在 `.` 分隔路径上写入值，按需创建中间对象
+/// Write a value at a dot separated path, creating intermediate objects as needed
+fn insert_json_path(value: &mut serde_json::Value, path: &str, inserted: serde_json::Value) -> Result<(), GatewayError> {
+    let not_object = || GatewayError::TransformError(format!("无法写入字段 {}: 上级不是对象", path));
+    let mut segments = path.split('.').peekable();
+    let mut current = value;
+    while let Some(segment) = segments.next() {
+        let object = current.as_object_mut().ok_or_else(not_object)?;
+        if segments.peek().is_none() {
+            object.insert(segment.to_string(), inserted);
+            return Ok(());
+        }
+        current = object.entry(segment.to_string()).or_insert_with(|| serde_json::Value::Object(serde_json::Map::new()));
+    }
+    Err(GatewayError::TransformError("字段路径为空".to_string()))
+}
+
+impl Default for LoadBalancer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LoadBalancer {
+    /// 创建新的负载均衡器
+    pub fn new() -> Self {
+        Self {
+            instances: Vec::new(),
+            strategy: LoadBalancingStrategy::RoundRobin,
+            in_flight: Arc::new(Mutex::new(HashMap::new())),
+            circuit_breaker: Arc::new(CircuitBreaker::new(CircuitBreakerConfig::default())),
+            health: Arc::new(UpstreamHealth::new(UpstreamHealthConfig::default())),
+            selection: Mutex::new(SelectionState::default()),
+        }
+    }
+
+    /// 使用指定策略创建负载均衡器
+    /// Create a load balancer with the given strategy
+    pub fn with_strategy(strategy: LoadBalancingStrategy) -> Self {
+        Self { strategy, ..Self::new() }
+    }
+
+    /// 设置实例的健康状态，不健康或被健康检查摘除的实例不会被选中
+    /// Set an instance's health flag; unhealthy instances, like ejected ones, are never picked
+    pub fn set_healthy(&mut self, address: &str, healthy: bool) -> Result<(), GatewayError> {
+        let instance = self.instances.iter_mut()
+            .find(|instance| instance.address == address)
+            .ok_or_else(|| GatewayError::ServiceError(format!("服务实例不存在: {}", address)))?;
+        instance.healthy = healthy;
+        Ok(())
+    }
+
+    /// 实例进行中的请求数
+    /// Number of in-flight requests on an instance
+    pub fn in_flight(&self, address: &str) -> usize {
+        self.in_flight.lock().unwrap().get(address).copied().unwrap_or(0)
+    }
+
+    /// 按策略选择实例并计入进行中的请求，返回的守卫释放时计数减一
+    /// Pick an instance by strategy and count the request as in flight until the returned guard is dropped
+    pub fn pick(&self) -> Result<UpstreamGuard, GatewayError> {
+        self.pick_among(None)
+    }
+
+    /// 只在给定地址的实例中按策略选择，用于分流到某个上游组
+    /// Pick by strategy among the instances with the given addresses only, used to route to an upstream group
+    pub fn pick_from(&self, addresses: &[String]) -> Result<UpstreamGuard, GatewayError> {
+        self.pick_among(Some(addresses))
+    }
+
+    fn pick_among(&self, addresses: Option<&[String]>) -> Result<UpstreamGuard, GatewayError> {
+        let instance = self.select_instance_by_strategy(addresses)?.clone();
+        *self.in_flight.lock().unwrap().entry(instance.address.clone()).or_default() += 1;
+        Ok(UpstreamGuard { instance, in_flight: Arc::clone(&self.in_flight) })
+    }
+
+    /// 选择服务实例
+    ///
+    /// 与 [`LoadBalancer::pick`] 使用相同的策略，但不计入进行中的请求。
+    /// Uses the same strategy as [`LoadBalancer::pick`] without counting the request as in flight.
+    #[allow(unused_variables)]
+    pub fn select_instance(&self, service_name: &str) -> Result<&ServiceInstance, GatewayError> {
+        self.select_instance_by_strategy(None)
+    }
+
+    fn select_instance_by_strategy(&self, addresses: Option<&[String]>) -> Result<&ServiceInstance, GatewayError> {
+        let healthy: Vec<&ServiceInstance> = self.instances.iter()
+            .filter(|instance| addresses.is_none_or(|addresses| addresses.contains(&instance.address)))
+            .filter(|instance| instance.healthy && !self.health.is_ejected(&instance.address))
+            .collect();
+        if healthy.is_empty() {
+            return Err(GatewayError::ServiceError("没有可用的服务实例".to_string()));
+        }
+        // 熔断中的实例降低优先级；全部熔断时仍从健康实例中选择，由网关直接拒绝
+        let available: Vec<&ServiceInstance> = healthy.iter()
+            .copied()
+            .filter(|instance| self.circuit_breaker.is_available(&instance.address))
+            .collect();
+        let healthy = if available.is_empty() { healthy } else { available };
+        let mut selection = self.selection.lock().unwrap();
+
+        let chosen = match self.strategy {
+            LoadBalancingStrategy::RoundRobin => {
+                let index = selection.next_index % healthy.len();
+                selection.next_index = selection.next_index.wrapping_add(1);
+                healthy[index]
+            }
+            LoadBalancingStrategy::WeightedRoundRobin => {
+                // 平滑加权轮询：每次为所有实例加上权重，选出当前权重最大者并减去总权重
+                let weighted: Vec<&ServiceInstance> = healthy.iter().copied().filter(|instance| instance.weight > 0).collect();
+                if weighted.is_empty() {
+                    return Err(GatewayError::ServiceError("没有权重大于零的服务实例".to_string()));
+                }
+                let total: i64 = weighted.iter().map(|instance| i64::from(instance.weight)).sum();
+                let mut best: Option<(&ServiceInstance, i64)> = None;
+                for instance in weighted {
+                    let current = selection.current_weights.entry(instance.address.clone()).or_default();
+                    *current += i64::from(instance.weight);
+                    if best.is_none_or(|(_, weight)| *current > weight) {
+                        best = Some((instance, *current));
+                    }
+                }
+                let (instance, _) = best.expect("至少有一个实例");
+                *selection.current_weights.get_mut(&instance.address).expect("已记录当前权重") -= total;
+                instance
+            }
+            LoadBalancingStrategy::LeastConnections => {
+                // 进行中请求数相同时轮流选择，避免总是落在第一个实例
+                let in_flight = self.in_flight.lock().unwrap();
+                let start = selection.next_index;
+                selection.next_index = selection.next_index.wrapping_add(1);
+                (0..healthy.len())
+                    .map(|offset| healthy[(start + offset) % healthy.len()])
+                    .min_by_key(|instance| in_flight.get(&instance.address).copied().unwrap_or(0))
+                    .expect("至少有一个实例")
+            }
+            LoadBalancingStrategy::Random => healthy[rand::random_range(0..healthy.len())],
+        };
+        Ok(chosen)
+    }
+}
+
+impl UpstreamHealth {
+    /// 创建上游健康状态
+    /// Create upstream health state
+    pub fn new(config: UpstreamHealthConfig) -> Self {
+        Self {
+            config,
+            clock: Arc::new(SystemGatewayClock),
+            states: Mutex::new(HashMap::new()),
+            metrics: Mutex::new(UpstreamHealthMetrics::default()),
+        }
+    }
+
+    /// 使用指定时钟
+    /// Use the given clock
+    pub fn with_clock(self, clock: Arc<dyn GatewayClock>) -> Self {
+        Self { clock, ..self }
+    }
+
+    /// 实例是否已被摘除；异常检测摘除满 `ejection_interval` 的实例不再算作摘除，等待探测请求
+    /// Whether the instance is ejected; an outlier ejected for `ejection_interval` no longer counts, awaiting a probe
+    pub fn is_ejected(&self, address: &str) -> bool {
+        let now = self.clock.now();
+        self.states.lock().unwrap().get(address).is_some_and(|state| self.excluded(state, now))
+    }
+
+    /// 已被摘除的实例地址
+    /// Addresses of the ejected instances
+    pub fn ejected(&self) -> Vec<String> {
+        let now = self.clock.now();
+        let mut ejected: Vec<String> = self.states.lock().unwrap().iter()
+            .filter(|(_, state)| self.excluded(state, now))
+            .map(|(address, _)| address.clone())
+            .collect();
+        ejected.sort();
+        ejected
+    }
+
+    /// 记录一次主动检查结果；摘除时返回 `Unhealthy`，重新加入时返回 `Healthy`
+    /// Record an active check result, returning `Unhealthy` on ejection and `Healthy` on re-admission
+    ///
+    /// `pool_size` 是实例池的大小，用于限制同时被摘除的实例数。
+    /// `pool_size` is the size of the instance pool, used to cap how many instances are ejected at once.
+    pub fn record_check(&self, address: &str, passed: bool, pool_size: usize) -> Option<HealthStatus> {
+        let mut states = self.states.lock().unwrap();
+        let may_eject = self.may_eject(&states, pool_size);
+        let state = states.entry(address.to_string()).or_default();
+        if passed {
+            state.consecutive_failures = 0;
+            state.consecutive_successes = state.consecutive_successes.saturating_add(1);
+            if state.ejected && state.consecutive_successes >= self.config.healthy_threshold.max(1) {
+                *state = UpstreamHealthState::default();
+                self.metrics.lock().unwrap().readmissions += 1;
+                return Some(HealthStatus::Healthy);
+            }
+        } else {
+            state.consecutive_successes = 0;
+            state.consecutive_failures = state.consecutive_failures.saturating_add(1);
+            if !state.ejected && state.consecutive_failures >= self.config.unhealthy_threshold.max(1) {
+                if !may_eject {
+                    log::warn!("上游 {} 未通过健康检查，但摘除会超过上限 {}%，保留", address, self.config.max_ejection_percent);
+                    return None;
+                }
+                state.ejected = true;
+                self.metrics.lock().unwrap().ejections += 1;
+                return Some(HealthStatus::Unhealthy);
+            }
+        }
+        None
+    }
+
+    /// 记录一次真实请求的结果；异常检测触发摘除时返回 `Unhealthy`，探测请求成功使实例重新加入时返回 `Healthy`
+    /// Record the outcome of a forwarded request, returning `Unhealthy` when outlier detection ejects the instance
+    /// and `Healthy` when a probe request re-admits it
+    pub fn record_passive(&self, address: &str, failed: bool, pool_size: usize) -> Option<HealthStatus> {
+        let rule = self.config.outlier_detection.as_ref()?;
+        let now = self.clock.now();
+        let mut states = self.states.lock().unwrap();
+        let may_eject = self.may_eject(&states, pool_size);
+        let state = states.entry(address.to_string()).or_default();
+        // 摘除期满后的请求是探测请求
+        let probing = state.ejected && state.outlier_ejected_at.is_some() && !self.excluded(state, now);
+        if !failed {
+            state.passive_failures = 0;
+            if probing {
+                *state = UpstreamHealthState::default();
+                self.metrics.lock().unwrap().readmissions += 1;
+                return Some(HealthStatus::Healthy);
+            }
+            return None;
+        }
+        state.passive_failures = state.passive_failures.saturating_add(1);
+        if probing {
+            // 探测失败时重新开始计时；实例仍计入摘除数，不受上限影响
+            state.outlier_ejected_at = Some(now);
+            self.metrics.lock().unwrap().outlier_ejections += 1;
+            return Some(HealthStatus::Unhealthy);
+        }
+        if state.ejected || state.passive_failures < rule.consecutive_failures.max(1) {
+            return None;
+        }
+        if !may_eject {
+            log::warn!("上游 {} 连续失败，但摘除会超过上限 {}%，保留", address, self.config.max_ejection_percent);
+            return None;
+        }
+        *state = UpstreamHealthState { ejected: true, outlier_ejected_at: Some(now), ..UpstreamHealthState::default() };
+        self.metrics.lock().unwrap().outlier_ejections += 1;
+        Some(HealthStatus::Unhealthy)
+    }
+
+    /// 实例当前是否被排除在流量之外
+    fn excluded(&self, state: &UpstreamHealthState, now: Instant) -> bool {
+        let interval = self.config.outlier_detection.as_ref().map(|rule| rule.ejection_interval);
+        match (state.ejected, state.outlier_ejected_at, interval) {
+            (false, _, _) => false,
+            (true, Some(ejected_at), Some(interval)) => now.saturating_duration_since(ejected_at) < interval,
+            (true, _, _) => true,
+        }
+    }
+
+    /// 再摘除一个实例是否仍在 `max_ejection_percent` 之内，且实例池中至少留下一个实例；等待探测的实例仍计入摘除数
+    fn may_eject(&self, states: &HashMap<String, UpstreamHealthState>, pool_size: usize) -> bool {
+        let percent = self.config.max_ejection_percent.min(100) as usize;
+        let cap = (pool_size * percent / 100).max(1).min(pool_size.saturating_sub(1));
+        states.values().filter(|state| state.ejected).count() < cap
+    }
+
+    /// 计数快照
+    /// Snapshot of the counters
+    pub fn metrics(&self) -> UpstreamHealthMetrics {
+        *self.metrics.lock().unwrap()
+    }
+}
+
+impl HealthCheck for UpstreamPoolHealthCheck {
+    fn check(&self) -> Result<HealthCheckResult, HealthCheckError> {
+        let started = Instant::now();
+        let ejected: Vec<&String> = self.addresses.iter().filter(|address| self.health.is_ejected(address)).collect();
+        let status = if ejected.is_empty() {
+            HealthStatus::Healthy
+        } else if ejected.len() == self.addresses.len() {
+            HealthStatus::Unhealthy
+        } else {
+            HealthStatus::Degraded
+        };
+        Ok(HealthCheckResult {
+            name: self.get_name(),
+            status,
+            response_time: started.elapsed(),
+            details: Some(format!("{}/{} 个上游可用", self.addresses.len() - ejected.len(), self.addresses.len())),
+            error: (!ejected.is_empty())
+                .then(|| format!("已摘除: {}", ejected.iter().map(|address| address.as_str()).collect::<Vec<_>>().join(", "))),
+            timestamp: SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs(),
+            attempts: 1,
+        })
+    }
+
+    fn get_name(&self) -> String {
+        "gateway_upstreams".to_string()
+    }
+
+    fn get_description(&self) -> String {
+        "API 网关上游实例池".to_string()
+    }
+}
+
+impl CircuitBreaker {
+    /// 创建熔断器
+    /// Create a circuit breaker
+    pub fn new(config: CircuitBreakerConfig) -> Self {
+        Self {
+            config,
+            clock: Arc::new(SystemGatewayClock),
+            circuits: Mutex::new(HashMap::new()),
+            metrics: Mutex::new(CircuitBreakerMetrics::default()),
+        }
+    }
+
+    /// 使用指定时钟
+    /// Use the given clock
+    pub fn with_clock(self, clock: Arc<dyn GatewayClock>) -> Self {
+        Self { clock, ..self }
+    }
+
+    /// 实例当前是否可以接收请求（不占用半开试探名额）
+    /// Whether an upstream can take a request right now, without using a half-open trial slot
+    pub fn is_available(&self, address: &str) -> bool {
+        let now = self.clock.now();
+        let circuits = self.circuits.lock().unwrap();
+        match circuits.get(address) {
+            None => true,
+            Some(circuit) => match circuit.state {
+                CircuitState::Closed => true,
+                CircuitState::Open => now.saturating_duration_since(circuit.opened_at) >= self.config.open_duration,
+                CircuitState::HalfOpen => circuit.trials_in_flight < self.config.half_open_max_requests,
+            },
+        }
+    }
+
+    /// 申请向实例发送一个请求，放行时返回报告结果用的凭证；被拒绝时返回建议的重试等待时间
+    /// Ask to send a request to an upstream, returning the permit that reports its outcome; a rejection carries
+    /// the suggested retry delay
+    pub fn try_acquire(&self, address: &str) -> Result<CircuitPermit<'_>, Duration> {
+        let now = self.clock.now();
+        let mut circuits = self.circuits.lock().unwrap();
+        let mut metrics = self.metrics.lock().unwrap();
+        let circuit = circuits.entry(address.to_string()).or_insert_with(|| UpstreamCircuit {
+            state: CircuitState::Closed,
+            outcomes: VecDeque::new(),
+            failures: 0,
+            opened_at: now,
+            trials_in_flight: 0,
+            trial_successes: 0,
+        });
+
+        if circuit.state == CircuitState::Open {
+            let open_for = now.saturating_duration_since(circuit.opened_at);
+            if open_for < self.config.open_duration {
+                metrics.short_circuited += 1;
+                return Err(self.config.open_duration - open_for);
+            }
+            circuit.state = CircuitState::HalfOpen;
+            circuit.trials_in_flight = 0;
+            circuit.trial_successes = 0;
+            log::info!("上游 {} 熔断结束，进入半开状态", address);
+        }
+        if circuit.state == CircuitState::HalfOpen {
+            if circuit.trials_in_flight >= self.config.half_open_max_requests {
+                metrics.short_circuited += 1;
+                return Err(Duration::from_secs(1));
+            }
+            circuit.trials_in_flight += 1;
+            metrics.half_open_trials += 1;
+        }
+        Ok(CircuitPermit { breaker: self, address: address.to_string(), recorded: false })
+    }
+
+    /// 记录请求结果并按需切换状态，状态发生变化时返回新状态；只由 [`CircuitPermit`] 调用
+    fn record(&self, address: &str, success: bool) -> Option<CircuitState> {
+        let now = self.clock.now();
+        let mut circuits = self.circuits.lock().unwrap();
+        let mut metrics = self.metrics.lock().unwrap();
+        let circuit = circuits.get_mut(address)?;
+        let previous = circuit.state;
+
+        match circuit.state {
+            CircuitState::Closed => {
+                circuit.outcomes.push_back((now, success));
+                circuit.failures += usize::from(!success);
+                while let Some((_, expired)) = circuit.outcomes.front()
+                    .filter(|(at, _)| now.saturating_duration_since(*at) > self.config.window)
+                    .copied()
+                {
+                    circuit.outcomes.pop_front();
+                    circuit.failures -= usize::from(!expired);
+                }
+                let total = circuit.outcomes.len();
+                let failures = circuit.failures;
+                if total >= self.config.minimum_requests as usize
+                    && failures as f64 / total as f64 >= self.config.failure_rate_threshold
+                {
+                    log::warn!("上游 {} 失败率 {}/{} 超过阈值，熔断", address, failures, total);
+                    circuit.state = CircuitState::Open;
+                    circuit.opened_at = now;
+                    circuit.outcomes.clear();
+                    circuit.failures = 0;
+                    metrics.opened += 1;
+                }
+            }
+            CircuitState::HalfOpen => {
+                circuit.trials_in_flight = circuit.trials_in_flight.saturating_sub(1);
+                if !success {
+                    log::warn!("上游 {} 试探请求失败，重新熔断", address);
+                    circuit.state = CircuitState::Open;
+                    circuit.opened_at = now;
+                    metrics.opened += 1;
+                } else {
+                    circuit.trial_successes += 1;
+                    if circuit.trial_successes >= self.config.half_open_max_requests {
+                        log::info!("上游 {} 试探请求全部成功，恢复闭合", address);
+                        circuit.state = CircuitState::Closed;
+                        circuit.outcomes.clear();
+                        circuit.failures = 0;
+                        metrics.closed += 1;
+                    }
+                }
+            }
+            // 熔断前已发出的请求不影响状态
+            CircuitState::Open => {}
+        }
+        (circuit.state != previous).then_some(circuit.state)
+    }
+
+    /// 各实例的熔断状态
+    /// Circuit state of every upstream
+    pub fn circuit_states(&self) -> HashMap<String, CircuitState> {
+        self.circuits.lock().unwrap().iter().map(|(address, circuit)| (address.clone(), circuit.state)).collect()
+    }
+
+    /// 熔断器计数
+    /// Circuit breaker counters
+    pub fn metrics(&self) -> CircuitBreakerMetrics {
+        *self.metrics.lock().unwrap()
+    }
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RateLimiter {
+    /// 创建新的限流器，默认按客户端 IP 每秒 100 个请求
+    pub fn new() -> Self {
+        Self {
+            limits: HashMap::new(),
+            default_limit: RateLimit {
+                requests_per_second: 100,
+                burst_limit: 100,
+                window_size: Duration::from_secs(1),
+            },
+            token_buckets: Arc::new(Mutex::new(HashMap::new())),
+            key_source: RateLimitKeySource::ClientIp,
+            max_tracked_keys: 10_000,
+            idle_timeout: Duration::from_secs(300),
+            trusted_proxies: HashSet::new(),
+            clock: Arc::new(SystemGatewayClock),
+        }
+    }
+
+    /// 使用指定时钟
+    /// Use the given clock
+    pub fn with_clock(self, clock: Arc<dyn GatewayClock>) -> Self {
+        Self { clock, ..self }
+    }
+
+    /// 信任这些代理地址转发的 `X-Forwarded-For`
+    /// Trust `X-Forwarded-For` added by these proxy addresses
+    pub fn with_trusted_proxies(self, proxies: impl IntoIterator<Item = String>) -> Self {
+        Self { trusted_proxies: proxies.into_iter().collect(), ..self }
+    }
+
+    /// 按配置的来源提取请求的限流键
+    /// Extract the rate limit key of a request from the configured source
+    pub fn key_for(&self, request: &Request, route: &Route) -> String {
+        match &self.key_source {
+            RateLimitKeySource::ClientIp => request.client_ip.clone(),
+            RateLimitKeySource::Header(name) if name.eq_ignore_ascii_case("x-forwarded-for") => self.forwarded_client(request),
+            RateLimitKeySource::Header(name) => request.header(name)
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+                .unwrap_or_else(|| request.client_ip.clone()),
+            RateLimitKeySource::Route => format!("{}:{}", route.method, route.path),
+        }
+    }
+
+    /// 按 `X-Forwarded-For` 确定的客户端地址
+    ///
+    /// 客户端可以在请求头左侧写入任意地址，只有可信代理追加在右侧的地址可信，所以从右向左跳过可信代理。
+    fn forwarded_client(&self, request: &Request) -> String {
+        if !self.trusted_proxies.contains(&request.client_ip) {
+            return request.client_ip.clone();
+        }
+        let hops: Vec<&str> = request.header("x-forwarded-for")
+            .map(|value| value.split(',').map(str::trim).collect())
+            .unwrap_or_default();
+        let mut client = request.client_ip.as_str();
+        for hop in hops.into_iter().rev() {
+            if hop.is_empty() {
+                break;
+            }
+            client = hop;
+            if !self.trusted_proxies.contains(hop) {
+                break;
+            }
+        }
+        client.to_string()
+    }
+
+    /// 为键消耗一个令牌并返回判定结果，桶在首次使用时创建
+    /// Take one token for a key and return the decision; buckets are created on first use
+    pub fn check(&self, key: &str) -> RateLimitDecision {
+        let now = self.clock.now();
+        let limit = self.limits.get(key).unwrap_or(&self.default_limit);
+        let capacity = limit.burst_limit.max(1);
+        let refill_rate = f64::from(limit.requests_per_second);
+        let mut buckets = self.token_buckets.lock().unwrap();
+
+        if !buckets.contains_key(key) && buckets.len() >= self.max_tracked_keys.max(1) {
+            self.make_room(&mut buckets, now);
+        }
+        let bucket = buckets.entry(key.to_string()).or_insert_with(|| TokenBucket {
+            capacity,
+            tokens: f64::from(capacity),
+            last_update: now,
+            refill_rate,
+        });
+
+        let elapsed = now.saturating_duration_since(bucket.last_update).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * bucket.refill_rate).min(f64::from(bucket.capacity));
+        bucket.last_update = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            RateLimitDecision {
+                allowed: true,
+                limit: bucket.capacity,
+                remaining: bucket.tokens.floor() as u32,
+                retry_after: None,
+            }
+        } else {
+            let retry_after = if bucket.refill_rate > 0.0 {
+                Duration::from_secs_f64((1.0 - bucket.tokens) / bucket.refill_rate)
+            } else {
+                self.idle_timeout
+            };
+            RateLimitDecision {
+                allowed: false,
+                limit: bucket.capacity,
+                remaining: 0,
+                retry_after: Some(retry_after),
+            }
+        }
+    }
+
+    /// 桶数达到上限时清理空闲桶，仍然超限时一次淘汰最久未使用的八分之一
+    ///
+    /// 批量淘汰使扫描全部桶的开销分摊到之后的新键上，而不是每个新键都扫描一次。
+    fn make_room(&self, buckets: &mut HashMap<String, TokenBucket>, now: Instant) {
+        buckets.retain(|_, bucket| now.saturating_duration_since(bucket.last_update) < self.idle_timeout);
+        if buckets.len() < self.max_tracked_keys.max(1) {
+            return;
+        }
+        let mut by_age: Vec<(Instant, String)> = buckets.iter().map(|(key, bucket)| (bucket.last_update, key.clone())).collect();
+        let evict = (by_age.len() / 8).max(1);
+        by_age.select_nth_unstable(evict - 1);
+        for (_, key) in &by_age[..evict] {
+            buckets.remove(key);
+        }
+    }
+
+    /// 检查限流
+    pub fn check_limit(&self, key: &str) -> Result<(), GatewayError> {
+        let decision = self.check(key);
+        if decision.allowed {
+            Ok(())
+        } else {
+            Err(GatewayError::RateLimitError(format!(
+                "{} 超出速率限制，{:?} 后重试",
+                key,
+                decision.retry_after.unwrap_or_default(),
+            )))
+        }
+    }
+
+    /// 淘汰空闲超过 `idle_timeout` 的桶，返回淘汰数量
+    /// Evict buckets idle for longer than `idle_timeout`, returning how many were removed
+    pub fn evict_idle(&self) -> usize {
+        let now = self.clock.now();
+        let mut buckets = self.token_buckets.lock().unwrap();
+        let before = buckets.len();
+        buckets.retain(|_, bucket| now.saturating_duration_since(bucket.last_update) < self.idle_timeout);
+        before - buckets.len()
+    }
+}
+
+impl Default for Cache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Cache {
+    /// 创建新的缓存
+    pub fn new() -> Self {
+        Self::with_config(CacheConfig::default())
+    }
+
+    /// 使用指定策略创建缓存
+    /// Create a cache with the given policy
+    pub fn with_config(config: CacheConfig) -> Self {
+        Self {
+            storage: Arc::new(Mutex::new(HashMap::new())),
+            default_ttl: Duration::from_secs(300), // 5分钟
+            config,
+            vary_index: Mutex::new(HashMap::new()),
+            clock: Arc::new(SystemGatewayClock),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            stale: AtomicU64::new(0),
+        }
+    }
+
+    /// 使用指定时钟
+    /// Use the given clock
+    pub fn with_clock(self, clock: Arc<dyn GatewayClock>) -> Self {
+        Self { clock, ..self }
+    }
+
+    /// 获取缓存
+    pub fn get(&self, key: &str) -> Option<Vec<u8>> {
+        let now = self.clock.now();
+        let mut storage = self.storage.lock().unwrap();
+        if let Some(entry) = storage.get_mut(key) {
+            if entry.expires_at > now {
+                entry.access_count += 1;
+                return Some(entry.value.clone());
+            } else {
+                storage.remove(key);
+            }
+        }
+        None
+    }
+
+    /// 设置缓存
+    pub fn set(&self, key: String, value: Vec<u8>, ttl: Option<Duration>) -> Result<(), GatewayError> {
+        let now = self.clock.now();
+        let expires_at = now + ttl.unwrap_or(self.default_ttl);
+        let entry = CacheEntry {
+            value,
+            status_code: 200,
+            headers: HashMap::new(),
+            path: key.clone(),
+            stored_at: now,
+            expires_at,
+            stale_until: expires_at,
+            revalidating: false,
+            access_count: 0,
+        };
+
+        self.insert_entry(key, entry, now);
+        Ok(())
+    }
+
+    /// 查找请求的缓存响应
+    /// Look up the cached response for a request
+    ///
+    /// 条目过期但仍在 stale-while-revalidate 窗口内时，第一个请求得到 `Miss` 并负责重新验证，
+    /// 其余请求在其完成前得到 `Stale`。
+    /// Once an entry expires but is still inside its stale-while-revalidate window, the first caller gets
+    /// `Miss` and revalidates while everyone else gets `Stale` until it finishes.
+    pub fn lookup(&self, request: &Request) -> CacheLookup {
+        self.lookup_version(request, None)
+    }
+
+    /// 查找分流到某个版本的请求的缓存响应，各版本的条目互不共享
+    /// Look up the cached response for a request routed to a split version; versions never share entries
+    pub fn lookup_version(&self, request: &Request, version: Option<&str>) -> CacheLookup {
+        let Some(key) = self.lookup_key(request, version) else {
+            return CacheLookup::Bypass;
+        };
+        let now = self.clock.now();
+        let mut storage = self.storage.lock().unwrap();
+        let outcome = match storage.get_mut(&key) {
+            // 带凭据的请求只共享上游声明为 public 的响应
+            Some(entry) if has_credentials(request) && !is_public(&entry.headers) => return CacheLookup::Bypass,
+            Some(entry) if now < entry.expires_at => {
+                entry.access_count += 1;
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                return CacheLookup::Hit(cached_response(entry, now, "HIT"));
+            }
+            Some(entry) if now < entry.stale_until => {
+                if entry.revalidating {
+                    entry.access_count += 1;
+                    self.stale.fetch_add(1, Ordering::Relaxed);
+                    return CacheLookup::Stale(cached_response(entry, now, "STALE"));
+                }
+                entry.revalidating = true;
+                CacheLookup::Miss
+            }
+            Some(_) => {
+                storage.remove(&key);
+                CacheLookup::Miss
+            }
+            None => CacheLookup::Miss,
+        };
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        outcome
+    }
+
+    /// 按 `Cache-Control` 存入上游响应，返回是否已缓存
+    /// Store an upstream response according to `Cache-Control`, returning whether it was cached
+    ///
+    /// `route_ttl` 在响应未声明 `max-age` 时使用，两者都缺失时不缓存。
+    /// `route_ttl` applies when the response declares no `max-age`; without either nothing is cached.
+    pub fn store(&self, request: &Request, response: &Response, route_ttl: Option<Duration>) -> bool {
+        self.store_version(request, None, response, route_ttl)
+    }
+
+    /// 按 `Cache-Control` 存入某个分流版本返回的上游响应
+    /// Store an upstream response returned by a split version according to `Cache-Control`
+    pub fn store_version(
+        &self,
+        request: &Request,
+        version: Option<&str>,
+        response: &Response,
+        route_ttl: Option<Duration>,
+    ) -> bool {
+        let Some(base) = self.base_key(request, version) else {
+            return false;
+        };
+        let control = header_value(&response.headers, "Cache-Control")
+            .map(CacheControl::parse)
+            .unwrap_or_default();
+        let vary = header_value(&response.headers, "Vary").unwrap_or_default();
+        let ttl = control.s_maxage.or(control.max_age).or(route_ttl).unwrap_or_default();
+        if has_credentials(request) && !control.public {
+            // 响应可能只属于这个用户，既不存入也不影响其他用户的条目
+            self.release_version(request, version);
+            return false;
+        }
+        let cacheable = CACHEABLE_STATUS_CODES.contains(&response.status_code)
+            && !control.no_store
+            && !control.private
+            && !control.no_cache
+            && vary.trim() != "*"
+            && !ttl.is_zero();
+        if !cacheable {
+            // 上游故障时保留旧条目，供后续请求在过期窗口内继续使用
+            if response.status_code >= 500 {
+                self.release_version(request, version);
+            } else {
+                self.remove_for(request, &base);
+            }
+            return false;
+        }
+
+        let mut vary_headers: Vec<String> = vary.split(',')
+            .map(|name| name.trim().to_ascii_lowercase())
+            .filter(|name| !name.is_empty())
+            .collect();
+        vary_headers.sort();
+        vary_headers.dedup();
+        let key = full_cache_key(&base, &vary_headers, request);
+        self.vary_index.lock().unwrap().insert(base, vary_headers);
+
+        let now = self.clock.now();
+        let stale_window = self.config.stale_while_revalidate
+            .map(|configured| control.stale_while_revalidate.unwrap_or(configured))
+            .unwrap_or_default();
+        let entry = CacheEntry {
+            value: response.body.clone().unwrap_or_default(),
+            status_code: response.status_code,
+            headers: response.headers.clone(),
+            path: request_path(request).to_string(),
+            stored_at: now,
+            expires_at: now + ttl,
+            stale_until: now + ttl + stale_window,
+            revalidating: false,
+            access_count: 0,
+        };
+        self.insert_entry(key, entry, now);
+        true
+    }
+
+    /// 放弃重新验证，之后的请求可以再次尝试
+    /// Abandon a revalidation so that a later request may try again
+    pub fn release(&self, request: &Request) {
+        self.release_version(request, None)
+    }
+
+    /// 放弃某个分流版本的重新验证
+    /// Abandon the revalidation of a split version
+    pub fn release_version(&self, request: &Request, version: Option<&str>) {
+        let Some(key) = self.lookup_key(request, version) else {
+            return;
+        };
+        if let Some(entry) = self.storage.lock().unwrap().get_mut(&key) {
+            entry.revalidating = false;
+        }
+    }
+
+    /// 清除路径位于该前缀下的全部条目，返回清除的条目数
+    /// Purge every entry whose path lies under the prefix, returning how many were removed
+    ///
+    /// 前缀按路径段匹配：`/api` 会清除 `/api` 和 `/api/users`，但不会清除 `/apis`。
+    /// Prefixes match whole segments: `/api` purges `/api` and `/api/users` but not `/apis`.
+    pub fn purge_prefix(&self, prefix: &str) -> usize {
+        let prefix = prefix.trim_end_matches('/');
+        let mut storage = self.storage.lock().unwrap();
+        let before = storage.len();
+        storage.retain(|_, entry| {
+            let under_prefix = prefix.is_empty()
+                || entry.path == prefix
+                || entry.path.strip_prefix(prefix).is_some_and(|rest| rest.starts_with('/'));
+            !under_prefix
+        });
+        before - storage.len()
+    }
+
+    /// 命中、未命中和过期响应计数
+    /// Hit, miss and stale counters
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            stale: self.stale.load(Ordering::Relaxed),
+        }
+    }
+
+    /// 不含 `Vary` 部分的缓存键，分流时附带所选版本，请求不可缓存时返回 `None`
+    /// Cache key without the `Vary` part, carrying the split version if any, or `None` when the request is not cacheable
+    fn base_key(&self, request: &Request, version: Option<&str>) -> Option<String> {
+        if !self.config.cacheable_methods.contains(&request.method) {
+            return None;
+        }
+        if request.header("Cache-Control").is_some_and(|value| CacheControl::parse(value).no_store) {
+            return None;
+        }
+        let mut query: Vec<(&String, &String)> = request.query_params.iter()
+            .filter(|(name, _)| {
+                self.config.significant_query_params.as_ref().is_none_or(|significant| significant.contains(name))
+            })
+            .collect();
+        query.sort();
+        let query = query.iter().map(|(name, value)| format!("{}={}", name, value)).collect::<Vec<_>>().join("&");
+        let key = format!("{} {}?{}", request.method, request_path(request), query);
+        Some(match version {
+            Some(version) => format!("{} @{}", key, version),
+            None => key,
+        })
+    }
+
+    /// 结合已知的 `Vary` 头部计算完整缓存键
+    /// Full cache key including the known `Vary` headers
+    fn lookup_key(&self, request: &Request, version: Option<&str>) -> Option<String> {
+        let base = self.base_key(request, version)?;
+        let vary_headers = self.vary_index.lock().unwrap().get(&base).cloned().unwrap_or_default();
+        Some(full_cache_key(&base, &vary_headers, request))
+    }
+
+    /// 删除请求对应的条目
+    /// Remove the entry for a request
+    fn remove_for(&self, request: &Request, base: &str) {
+        let vary_headers = self.vary_index.lock().unwrap().get(base).cloned().unwrap_or_default();
+        self.storage.lock().unwrap().remove(&full_cache_key(base, &vary_headers, request));
+    }
+
+    /// 插入条目，超出容量时先清理过期条目，再淘汰最早过期的条目
+    /// Insert an entry; at capacity drop dead entries first, then the one expiring soonest
+    fn insert_entry(&self, key: String, entry: CacheEntry, now: Instant) {
+        let mut storage = self.storage.lock().unwrap();
+        if !storage.contains_key(&key) && storage.len() >= self.config.max_entries {
+            storage.retain(|_, existing| existing.stale_until > now);
+            if storage.len() >= self.config.max_entries
+                && let Some(oldest) = storage.iter().min_by_key(|(_, existing)| existing.expires_at).map(|(k, _)| k.clone())
+            {
+                storage.remove(&oldest);
+            }
+        }
+        storage.insert(key, entry);
+    }
+}
+
+/// 请求路径，不含查询串和片段
+/// Request path without query string or fragment
+fn request_path(request: &Request) -> &str {
+    request.path.split(['?', '#']).next().unwrap_or_default()
+}
+
+/// 忽略大小写查找头部
+/// Look up a header ignoring case
+fn header_value<'a>(headers: &'a HashMap<String, String>, name: &str) -> Option<&'a str> {
+    headers.iter()
+        .find(|(header, _)| header.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.as_str())
+}
+
+/// 请求是否携带 `Authorization` 或 Cookie 凭据
+/// Whether the request carries `Authorization` or cookie credentials
+fn has_credentials(request: &Request) -> bool {
+    request.header("Authorization").is_some() || request.header("Cookie").is_some()
+}
+
+/// 响应是否声明了 `Cache-Control: public`
+fn is_public(headers: &HashMap<String, String>) -> bool {
+    header_value(headers, "Cache-Control").is_some_and(|value| CacheControl::parse(value).public)
+}
+
+/// 64 位 FNV-1a 哈希，结果与进程和编译器版本无关
+/// 64-bit FNV-1a hash, stable across processes and compiler versions
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| (hash ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01b3))
+}
+
+/// 在基础键后附加 `Vary` 头部的取值
+/// Append the values of the `Vary` headers to the base key
+fn full_cache_key(base: &str, vary_headers: &[String], request: &Request) -> String {
+    let mut key = base.to_string();
+    for name in vary_headers {
+        key.push('|');
+        key.push_str(name);
+        key.push('=');
+        key.push_str(request.header(name).unwrap_or_default());
+    }
+    key
+}
+
+/// 由缓存条目构造响应，附带 `Age` 和 `X-Cache` 头部
+/// Build a response from an entry with `Age` and `X-Cache` headers
+fn cached_response(entry: &CacheEntry, now: Instant, status: &str) -> Response {
+    let mut headers = entry.headers.clone();
+    headers.insert("Age".to_string(), now.saturating_duration_since(entry.stored_at).as_secs().to_string());
+    headers.insert("X-Cache".to_string(), status.to_string());
+    Response {
+        status_code: entry.status_code,
+        headers,
+        body: Some(entry.value.clone()),
+        processing_time: Duration::ZERO,
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[allow(deprecated)]
+    fn route(method: HttpMethod, path: &str, target_service: &str) -> Route {
+        Route {
+            path: path.to_string(),
+            method,
+            target_service: target_service.to_string(),
+            middlewares: Vec::new(),
+            timeout: Duration::ZERO,
+            limits: RouteLimits::default(),
+            cache_ttl: None,
+            request_transform: None,
+            response_transform: None,
+            retry: None,
+            cors: None,
+            streaming: None,
+            split: None,
+        }
+    }
+
+    fn request(method: HttpMethod, path: &str) -> Request {
+        Request {
+            method,
+            path: path.to_string(),
+            headers: HashMap::new(),
+            query_params: HashMap::new(),
+            body: None,
+            client_ip: "127.0.0.1".to_string(),
+            path_params: HashMap::new(),
+            principal: None,
+        }
+    }
+
+    fn resolve(gateway: &ApiGatewayManager, path: &str) -> Option<(String, HashMap<String, String>)> {
+        gateway.find_route(&request(HttpMethod::GET, path)).ok()
+            .map(|(route, params)| (route.target_service, params))
+    }
+
+    #[test]
+    fn test_nested_path_captures() {
+        let mut gateway = ApiGatewayManager::new();
+        gateway.add_route(route(HttpMethod::GET, "/modules/{id}/versions/{ver}", "versions")).unwrap();
+        gateway.add_route(route(HttpMethod::GET, "/modules/{id}", "module")).unwrap();
+        gateway.add_route(route(HttpMethod::GET, "/modules/latest", "latest")).unwrap();
+
+        let (target, params) = resolve(&gateway, "/modules/42/versions/1.2.0").unwrap();
+        assert_eq!(target, "versions");
+        assert_eq!(params["id"], "42");
+        assert_eq!(params["ver"], "1.2.0");
+        assert_eq!(resolve(&gateway, "/modules/42").unwrap().0, "module");
+        // 字面量优先于参数
+        assert_eq!(resolve(&gateway, "/modules/latest").unwrap().0, "latest");
+        assert!(resolve(&gateway, "/modules/42/versions").is_none());
+        assert!(gateway.find_route(&request(HttpMethod::POST, "/modules/42")).is_err());
+    }
+
+    #[test]
+    fn test_wildcard_tail_and_priority() {
+        let mut gateway = ApiGatewayManager::new();
+        gateway.add_route(route(HttpMethod::GET, "/static/*path", "static")).unwrap();
+        gateway.add_route(route(HttpMethod::GET, "/static/{file}/meta", "meta")).unwrap();
+
+        let (target, params) = resolve(&gateway, "/static/css/site/app.css").unwrap();
+        assert_eq!(target, "static");
+        assert_eq!(params["path"], "css/site/app.css");
+        assert_eq!(resolve(&gateway, "/static").unwrap().1["path"], "");
+        assert_eq!(resolve(&gateway, "/static/logo.png/meta").unwrap().0, "meta");
+
+        assert!(RoutePattern::parse("/static/*path/more").is_err());
+        assert!(RoutePattern::parse("/a/{id}/{id}").is_err());
+        assert!(RoutePattern::parse("/a/{id").is_err());
+    }
+
+    #[test]
+    fn test_trailing_slash_and_percent_encoding() {
+        let mut gateway = ApiGatewayManager::new();
+        gateway.add_route(route(HttpMethod::GET, "/users/{name}/", "user")).unwrap();
+        gateway.add_route(route(HttpMethod::GET, "/", "root")).unwrap();
+
+        assert_eq!(resolve(&gateway, "/users/ada").unwrap().1["name"], "ada");
+        assert_eq!(resolve(&gateway, "/users/ada/").unwrap().1["name"], "ada");
+        assert_eq!(resolve(&gateway, "/users/ada%20lovelace").unwrap().1["name"], "ada lovelace");
+        assert_eq!(resolve(&gateway, "/users/a%2Fb").unwrap().1["name"], "a/b");
+        assert_eq!(resolve(&gateway, "/users/%E4%B8%AD%zz").unwrap().1["name"], "中%zz");
+        assert_eq!(resolve(&gateway, "/").unwrap().0, "root");
+        assert_eq!(resolve(&gateway, "").unwrap().0, "root");
+    }
+
+    #[test]
+    fn test_conflicting_routes_are_rejected() {
+        let mut gateway = ApiGatewayManager::new();
+        gateway.add_route(route(HttpMethod::GET, "/modules/{id}", "a")).unwrap();
+        let error = gateway.add_route(route(HttpMethod::GET, "/modules/{name}/", "b")).unwrap_err();
+        assert!(error.to_string().contains("GET:/modules/{id}"), "{}", error);
+        assert!(gateway.add_route(route(HttpMethod::GET, "/modules/{id}", "c")).is_err());
+        gateway.add_route(route(HttpMethod::DELETE, "/modules/{name}", "d")).unwrap();
+        gateway.add_route(route(HttpMethod::GET, "/modules/*rest", "e")).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_handle_request_exposes_path_params() {
+        let mut gateway = ApiGatewayManager::new();
+        gateway.load_balancer.instances.push(ServiceInstance {
+            address: "127.0.0.1:9000".to_string(),
+            weight: 1,
+            healthy: true,
+        });
+        gateway.add_route(route(HttpMethod::GET, "/modules/{id}", "module")).unwrap();
+        let response = gateway.handle_request(request(HttpMethod::GET, "/modules/7")).await.unwrap();
+        assert_eq!(response.status_code, 200);
+
+        let mut direct = request(HttpMethod::GET, "/modules/7");
+        let (_, params) = gateway.find_route(&direct).unwrap();
+        direct.path_params = params;
+        assert_eq!(direct.path_params().get("id").map(String::as_str), Some("7"));
+    }
+
+    #[derive(Debug)]
+    struct ManualClock {
+        start: Instant,
+        offset: Mutex<Duration>,
+    }
+
+    impl ManualClock {
+        fn new() -> Arc<Self> {
+            Arc::new(Self { start: Instant::now(), offset: Mutex::new(Duration::ZERO) })
+        }
+
+        fn advance(&self, by: Duration) {
+            *self.offset.lock().unwrap() += by;
+        }
+    }
+
+    impl GatewayClock for ManualClock {
+        fn now(&self) -> Instant {
+            self.start + *self.offset.lock().unwrap()
+        }
+    }
+
+    fn rate_limiter(requests_per_second: u32, burst_limit: u32) -> (RateLimiter, Arc<ManualClock>) {
+        let clock = ManualClock::new();
+        let mut limiter = RateLimiter::new().with_clock(clock.clone());
+        limiter.default_limit = RateLimit {
+            requests_per_second,
+            burst_limit,
+            window_size: Duration::from_secs(1),
+        };
+        (limiter, clock)
+    }
+
+    #[test]
+    fn test_rate_limit_keys_are_independent_and_burst_is_honored() {
+        let (limiter, _clock) = rate_limiter(1, 3);
+        for remaining in [2, 1, 0] {
+            let decision = limiter.check("alice");
+            assert!(decision.allowed);
+            assert_eq!(decision.remaining, remaining);
+        }
+        let denied = limiter.check("alice");
+        assert!(!denied.allowed);
+        assert_eq!(denied.retry_after, Some(Duration::from_secs(1)));
+        assert!(limiter.check_limit("alice").is_err());
+
+        assert!(limiter.check("bob").allowed);
+        assert_eq!(limiter.check("bob").remaining, 1);
+    }
+
+    #[test]
+    fn test_rate_limit_refills_over_time() {
+        let (limiter, clock) = rate_limiter(2, 2);
+        assert!(limiter.check("k").allowed);
+        assert!(limiter.check("k").allowed);
+        let denied = limiter.check("k");
+        assert_eq!(denied.retry_after, Some(Duration::from_millis(500)));
+
+        clock.advance(Duration::from_millis(250));
+        assert!(!limiter.check("k").allowed);
+        clock.advance(Duration::from_millis(250));
+        assert!(limiter.check("k").allowed);
+        // 补充不超过桶容量
+        clock.advance(Duration::from_secs(60));
+        assert_eq!(limiter.check("k").remaining, 1);
+    }
+
+    #[test]
+    fn test_rate_limit_evicts_least_recently_used_and_idle_buckets() {
+        let (mut limiter, clock) = rate_limiter(1, 1);
+        limiter.max_tracked_keys = 2;
+        limiter.check("a");
+        clock.advance(Duration::from_millis(10));
+        limiter.check("b");
+        clock.advance(Duration::from_millis(10));
+        assert!(!limiter.check("a").allowed);
+        clock.advance(Duration::from_millis(10));
+        limiter.check("c");
+        {
+            let buckets = limiter.token_buckets.lock().unwrap();
+            assert!(buckets.contains_key("a") && buckets.contains_key("c"));
+            assert!(!buckets.contains_key("b"));
+        }
+
+        clock.advance(limiter.idle_timeout);
+        assert_eq!(limiter.evict_idle(), 2);
+        assert!(limiter.token_buckets.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_forwarded_for_is_trusted_only_from_configured_proxies() {
+        let route = route(HttpMethod::GET, "/modules/{id}", "module");
+        let forwarded = |peer: &str, chain: &str| {
+            let mut request = request(HttpMethod::GET, "/modules/1");
+            request.client_ip = peer.to_string();
+            request.headers.insert("x-forwarded-for".to_string(), chain.to_string());
+            request
+        };
+        let mut limiter = RateLimiter::new().with_trusted_proxies(["10.0.0.1".to_string(), "10.0.0.2".to_string()]);
+        limiter.key_source = RateLimitKeySource::Header("X-Forwarded-For".to_string());
+
+        // 不是来自可信代理时忽略请求头
+        assert_eq!(limiter.key_for(&forwarded("203.0.113.9", "1.1.1.1"), &route), "203.0.113.9");
+        // 客户端伪造的左侧地址被忽略，取最右侧不可信的一跳
+        assert_eq!(limiter.key_for(&forwarded("10.0.0.1", "1.1.1.1, 198.51.100.7, 10.0.0.2"), &route), "198.51.100.7");
+        assert_eq!(limiter.key_for(&forwarded("10.0.0.1", "10.0.0.2"), &route), "10.0.0.2");
+    }
+
+    #[tokio::test]
+    async fn test_gateway_returns_429_with_rate_limit_headers() {
+        let mut gateway = ApiGatewayManager::new();
+        gateway.load_balancer.instances.push(ServiceInstance {
+            address: "127.0.0.1:9000".to_string(),
+            weight: 1,
+            healthy: true,
+        });
+        gateway.add_route(route(HttpMethod::GET, "/modules/{id}", "module")).unwrap();
+        gateway.rate_limiter.default_limit = RateLimit {
+            requests_per_second: 1,
+            burst_limit: 2,
+            window_size: Duration::from_secs(1),
+        };
+        gateway.rate_limiter.key_source = RateLimitKeySource::Header("X-Api-Key".to_string());
+        let keyed = |key: &str| {
+            let mut request = request(HttpMethod::GET, "/modules/1");
+            request.headers.insert("x-api-key".to_string(), key.to_string());
+            request
+        };
+
+        let first = gateway.handle_request(keyed("one")).await.unwrap();
+        assert_eq!(first.headers["X-RateLimit-Remaining"], "1");
+        gateway.handle_request(keyed("one")).await.unwrap();
+        let limited = gateway.handle_request(keyed("one")).await.unwrap();
+        assert_eq!(limited.status_code, 429);
+        assert_eq!(limited.headers["Retry-After"], "1");
+        assert_eq!(limited.headers["X-RateLimit-Remaining"], "0");
+        assert_eq!(gateway.handle_request(keyed("two")).await.unwrap().status_code, 200);
+
+        gateway.rate_limiter.key_source = RateLimitKeySource::Route;
+        assert_eq!(gateway.rate_limiter.key_for(&keyed("one"), &route(HttpMethod::GET, "/modules/{id}", "module")), "GET:/modules/{id}");
+    }
+
+    fn balancer(strategy: LoadBalancingStrategy, weights: &[(&str, u32)]) -> LoadBalancer {
+        let mut balancer = LoadBalancer::with_strategy(strategy);
+        balancer.instances = weights.iter()
+            .map(|(address, weight)| ServiceInstance { address: address.to_string(), weight: *weight, healthy: true })
+            .collect();
+        balancer
+    }
+
+    #[test]
+    fn test_smooth_weighted_round_robin_distribution() {
+        let two = balancer(LoadBalancingStrategy::WeightedRoundRobin, &[("a", 5), ("b", 1)]);
+        let picks: Vec<String> = (0..10_000).map(|_| two.pick().unwrap().address.clone()).collect();
+        let a = picks.iter().filter(|address| *address == "a").count();
+        // 每 6 次为一轮，1666 轮之后余下的 4 次中有 3 次选中 a
+        assert_eq!(a, 1666 * 5 + 3);
+        // 平滑算法把 b 穿插在一轮中间，而不是先连续 5 次选中 a
+        assert_eq!(picks[..6], ["a", "a", "a", "b", "a", "a"]);
+        assert!(picks.windows(6).all(|window| window.iter().any(|address| address == "b")));
+
+        let three = balancer(LoadBalancingStrategy::WeightedRoundRobin, &[("x", 5), ("y", 3), ("z", 2)]);
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for _ in 0..10_000 {
+            *counts.entry(three.pick().unwrap().address.clone()).or_default() += 1;
+        }
+        assert!((counts["x"] as f64 / 10_000.0 - 0.5).abs() < 0.01);
+        assert!((counts["y"] as f64 / 10_000.0 - 0.3).abs() < 0.01);
+        assert!((counts["z"] as f64 / 10_000.0 - 0.2).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_least_connections_prefers_idle_upstream() {
+        let balancer = balancer(LoadBalancingStrategy::LeastConnections, &[("a", 1), ("b", 1)]);
+        let first = balancer.pick().unwrap();
+        let second = balancer.pick().unwrap();
+        assert_ne!(first.address, second.address);
+        let busy = first.address.clone();
+        drop(second);
+        // 被释放的实例空闲，之后的选择都落在它上面
+        for _ in 0..3 {
+            let guard = balancer.pick().unwrap();
+            assert_ne!(guard.address, busy);
+        }
+        assert_eq!(balancer.in_flight(&busy), 1);
+        drop(first);
+        assert_eq!(balancer.in_flight(&busy), 0);
+    }
+
+    #[test]
+    fn test_unhealthy_upstream_is_excluded() {
+        for strategy in [
+            LoadBalancingStrategy::RoundRobin,
+            LoadBalancingStrategy::WeightedRoundRobin,
+            LoadBalancingStrategy::LeastConnections,
+            LoadBalancingStrategy::Random,
+        ] {
+            let mut balancer = balancer(strategy, &[("a", 1), ("b", 1)]);
+            balancer.set_healthy("a", false).unwrap();
+            assert!((0..100).all(|_| balancer.pick().unwrap().address == "b"));
+            balancer.set_healthy("b", false).unwrap();
+            assert!(balancer.pick().is_err());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_gateway_releases_upstream_after_response() {
+        let mut gateway = ApiGatewayManager::new();
+        gateway.load_balancer = balancer(LoadBalancingStrategy::LeastConnections, &[("127.0.0.1:9000", 1)]);
+        gateway.add_route(route(HttpMethod::GET, "/ping", "ping")).unwrap();
+        assert_eq!(gateway.handle_request(request(HttpMethod::GET, "/ping")).await.unwrap().status_code, 200);
+        assert_eq!(gateway.load_balancer.in_flight("127.0.0.1:9000"), 0);
+    }
+
+    fn circuit_breaker(clock: &Arc<ManualClock>) -> Arc<CircuitBreaker> {
+        let clock: Arc<dyn GatewayClock> = clock.clone();
+        Arc::new(CircuitBreaker::new(CircuitBreakerConfig {
+            failure_rate_threshold: 0.5,
+            window: Duration::from_secs(10),
+            minimum_requests: 4,
+            open_duration: Duration::from_secs(5),
+            half_open_max_requests: 2,
+        }).with_clock(clock))
+    }
+
+    #[test]
+    fn test_circuit_breaker_opens_on_failure_rate() {
+        let clock = ManualClock::new();
+        let breaker = circuit_breaker(&clock);
+        for success in [true, false, true] {
+            breaker.try_acquire("a").unwrap().record(success);
+        }
+        assert_eq!(breaker.circuit_states()["a"], CircuitState::Closed);
+
+        // 窗口外的结果不计入失败率
+        clock.advance(Duration::from_secs(11));
+        for success in [false, true, true] {
+            breaker.try_acquire("a").unwrap().record(success);
+        }
+        assert_eq!(breaker.circuit_states()["a"], CircuitState::Closed);
+        breaker.try_acquire("a").unwrap().record(false);
+        assert_eq!(breaker.circuit_states()["a"], CircuitState::Open);
+
+        clock.advance(Duration::from_secs(2));
+        assert_eq!(breaker.try_acquire("a").err(), Some(Duration::from_secs(3)));
+        assert!(!breaker.is_available("a"));
+        assert!(breaker.try_acquire("b").unwrap().record(true).is_none());
+        assert_eq!(breaker.metrics(), CircuitBreakerMetrics { opened: 1, short_circuited: 1, half_open_trials: 0, closed: 0 });
+    }
+
+    #[test]
+    fn test_circuit_breaker_half_open_recovery() {
+        let clock = ManualClock::new();
+        let breaker = circuit_breaker(&clock);
+        for _ in 0..4 {
+            breaker.try_acquire("a").unwrap().record(false);
+        }
+        clock.advance(Duration::from_secs(5));
+        assert!(breaker.is_available("a"));
+
+        // 半开状态只放行有限的试探请求，失败则重新熔断
+        let first = breaker.try_acquire("a").unwrap();
+        let second = breaker.try_acquire("a").unwrap();
+        assert_eq!(breaker.circuit_states()["a"], CircuitState::HalfOpen);
+        assert!(breaker.try_acquire("a").is_err());
+        first.record(true);
+        second.record(false);
+        assert_eq!(breaker.circuit_states()["a"], CircuitState::Open);
+
+        clock.advance(Duration::from_secs(5));
+        for _ in 0..2 {
+            breaker.try_acquire("a").unwrap().record(true);
+        }
+        assert_eq!(breaker.circuit_states()["a"], CircuitState::Closed);
+        let metrics = breaker.metrics();
+        assert_eq!((metrics.opened, metrics.half_open_trials, metrics.closed), (2, 4, 1));
+    }
+
+    #[test]
+    fn test_dropped_permit_counts_as_failure_and_frees_trial_slot() {
+        let clock = ManualClock::new();
+        let breaker = circuit_breaker(&clock);
+        for _ in 0..4 {
+            breaker.try_acquire("a").unwrap().record(false);
+        }
+        clock.advance(Duration::from_secs(5));
+
+        // 半开试探请求被取消：凭证被丢弃，计为失败并重新熔断，而不是一直占用试探名额
+        let trial = breaker.try_acquire("a").unwrap();
+        drop(trial);
+        assert_eq!(breaker.circuit_states()["a"], CircuitState::Open);
+        clock.advance(Duration::from_secs(5));
+        assert!(breaker.try_acquire("a").unwrap().record(true).is_none());
+        assert_eq!(breaker.circuit_states()["a"], CircuitState::HalfOpen);
+    }
+
+    #[tokio::test]
+    async fn test_gateway_fast_fails_open_upstream() {
+        let clock = ManualClock::new();
+        let mut gateway = ApiGatewayManager::new();
+        gateway.load_balancer = balancer(LoadBalancingStrategy::RoundRobin, &[("a", 1), ("b", 1)]);
+        gateway.load_balancer.circuit_breaker = circuit_breaker(&clock);
+        gateway.add_route(route(HttpMethod::GET, "/ping", "ping")).unwrap();
+        let breaker = Arc::clone(&gateway.load_balancer.circuit_breaker);
+        for address in ["a", "b"] {
+            for _ in 0..4 {
+                breaker.try_acquire(address).unwrap().record(false);
+            }
+            clock.advance(Duration::from_secs(1));
+        }
+
+        let response = gateway.handle_request(request(HttpMethod::GET, "/ping")).await.unwrap();
+        assert_eq!(response.status_code, 503);
+        assert_eq!(response.headers["Retry-After"], "3");
+
+        // a 先结束熔断，负载均衡优先选择它进行试探
+        clock.advance(Duration::from_secs(3));
+        for _ in 0..2 {
+            let response = gateway.handle_request(request(HttpMethod::GET, "/ping")).await.unwrap();
+            assert_eq!(response.status_code, 200);
+        }
+        let states = gateway.circuit_states();
+        assert_eq!(states["a"], CircuitState::Closed);
+        assert_eq!(states["b"], CircuitState::Open);
+        assert_eq!(gateway.load_balancer.in_flight("a"), 0);
+    }
+
+    /// 记录钩子调用顺序的中间件
+    struct RecordingMiddleware {
+        name: &'static str,
+        events: Arc<Mutex<Vec<String>>>,
+        short_circuit: Option<u16>,
+        fail_request: bool,
+    }
+
+    impl RecordingMiddleware {
+        fn new(name: &'static str, events: &Arc<Mutex<Vec<String>>>) -> Self {
+            Self { name, events: Arc::clone(events), short_circuit: None, fail_request: false }
+        }
+    }
+
+    impl Middleware for RecordingMiddleware {
+        fn on_request(&self, _request: &mut Request) -> Result<MiddlewareAction, GatewayError> {
+            self.events.lock().unwrap().push(format!("{}:request", self.name));
+            if self.fail_request {
+                return Err(GatewayError::MiddlewareError(format!("{} failed", self.name)));
+            }
+            Ok(match self.short_circuit {
+                Some(status_code) => MiddlewareAction::ShortCircuit(Response {
+                    status_code,
+                    headers: HashMap::new(),
+                    body: None,
+                    processing_time: Duration::ZERO,
+                }),
+                None => MiddlewareAction::Continue,
+            })
+        }
+
+        fn on_response(&self, _request: &Request, response: &mut Response) -> Result<(), GatewayError> {
+            self.events.lock().unwrap().push(format!("{}:response:{}", self.name, response.status_code));
+            Ok(())
+        }
+    }
+
+    fn middleware_gateway() -> (ApiGatewayManager, Arc<Mutex<Vec<String>>>) {
+        let mut gateway = ApiGatewayManager::new();
+        gateway.load_balancer = balancer(LoadBalancingStrategy::RoundRobin, &[("a", 1)]);
+        (gateway, Arc::new(Mutex::new(Vec::new())))
+    }
+
+    fn drain(events: &Arc<Mutex<Vec<String>>>) -> Vec<String> {
+        std::mem::take(&mut *events.lock().unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_middleware_chain_order_global_then_route() {
+        let (mut gateway, events) = middleware_gateway();
+        gateway.add_middleware(RecordingMiddleware::new("outer", &events));
+        gateway.add_middleware(RecordingMiddleware::new("inner", &events));
+        gateway.register_middleware("audit", RecordingMiddleware::new("audit", &events));
+        let mut audited = route(HttpMethod::GET, "/audited", "audited");
+        audited.middlewares = vec!["audit".to_string()];
+        gateway.add_route(audited).unwrap();
+        gateway.add_route(route(HttpMethod::GET, "/plain", "plain")).unwrap();
+
+        let response = gateway.handle_request(request(HttpMethod::GET, "/audited")).await.unwrap();
+        assert_eq!(response.status_code, 200);
+        assert_eq!(drain(&events), [
+            "outer:request", "inner:request", "audit:request",
+            "audit:response:200", "inner:response:200", "outer:response:200",
+        ]);
+
+        // 路由中间件只作用于引用它的路由，全局中间件也会看到 404
+        gateway.handle_request(request(HttpMethod::GET, "/plain")).await.unwrap();
+        assert_eq!(drain(&events), ["outer:request", "inner:request", "inner:response:200", "outer:response:200"]);
+        let missing = gateway.handle_request(request(HttpMethod::GET, "/missing")).await.unwrap();
+        assert_eq!(missing.status_code, 404);
+        assert_eq!(drain(&events), ["outer:request", "inner:request", "inner:response:404", "outer:response:404"]);
+
+        let mut unknown = route(HttpMethod::GET, "/unknown", "unknown");
+        unknown.middlewares = vec!["missing".to_string()];
+        assert!(gateway.add_route(unknown).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_short_circuit_unwinds_entered_middlewares() {
+        let (mut gateway, events) = middleware_gateway();
+        gateway.add_middleware(RecordingMiddleware::new("outer", &events));
+        gateway.register_middleware("auth", RecordingMiddleware { short_circuit: Some(401), ..RecordingMiddleware::new("auth", &events) });
+        gateway.register_middleware("audit", RecordingMiddleware::new("audit", &events));
+        let mut protected = route(HttpMethod::GET, "/protected", "protected");
+        protected.middlewares = vec!["auth".to_string(), "audit".to_string()];
+        gateway.add_route(protected).unwrap();
+
+        let response = gateway.handle_request(request(HttpMethod::GET, "/protected")).await.unwrap();
+        assert_eq!(response.status_code, 401);
+        assert!(response.body.is_none());
+        assert_eq!(drain(&events), ["outer:request", "auth:request", "auth:response:401", "outer:response:401"]);
+        assert_eq!(gateway.load_balancer.in_flight("a"), 0);
+    }
+
+    struct PanickingMiddleware;
+
+    impl Middleware for PanickingMiddleware {
+        fn on_response(&self, _request: &Request, _response: &mut Response) -> Result<(), GatewayError> {
+            panic!("middleware bug");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_middleware_errors_become_500_responses() {
+        let (mut gateway, events) = middleware_gateway();
+        gateway.add_middleware(RecordingMiddleware::new("outer", &events));
+        gateway.add_middleware(RecordingMiddleware { fail_request: true, ..RecordingMiddleware::new("broken", &events) });
+        gateway.add_route(route(HttpMethod::GET, "/ping", "ping")).unwrap();
+
+        let response = gateway.handle_request(request(HttpMethod::GET, "/ping")).await.unwrap();
+        assert_eq!(response.status_code, 500);
+        assert_eq!(drain(&events), ["outer:request", "broken:request", "broken:response:500", "outer:response:500"]);
+
+        let (mut gateway, events) = middleware_gateway();
+        gateway.add_middleware(RecordingMiddleware::new("outer", &events));
+        gateway.add_middleware(PanickingMiddleware);
+        gateway.add_route(route(HttpMethod::GET, "/ping", "ping")).unwrap();
+        for _ in 0..2 {
+            let response = gateway.handle_request(request(HttpMethod::GET, "/ping")).await.unwrap();
+            assert_eq!(response.status_code, 500);
+        }
+        assert_eq!(drain(&events), ["outer:request", "outer:response:500", "outer:request", "outer:response:500"]);
+    }
+
+    struct CapturingProcessor {
+        entries: Arc<Mutex<Vec<LogEntry>>>,
+    }
+
+    impl crate::monitoring_advanced::LogProcessor for CapturingProcessor {
+        fn process(&self, entry: &LogEntry) -> Result<(), crate::monitoring_advanced::LoggingError> {
+            self.entries.lock().unwrap().push(entry.clone());
+            Ok(())
+        }
+
+        fn flush(&self) -> Result<(), crate::monitoring_advanced::LoggingError> {
+            Ok(())
+        }
+
+        fn close(&self) -> Result<(), crate::monitoring_advanced::LoggingError> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_request_id_and_access_log_middlewares() {
+        let logger = Arc::new(StructuredLogger::new(crate::monitoring_advanced::LoggingConfig {
+            level: LogLevel::Info,
+            format: crate::monitoring_advanced::LogFormat::Text,
+            targets: Vec::new(),
+            target_levels: Vec::new(),
+            buffer_size: 1,
+            flush_interval: Duration::from_secs(60),
+        }));
+        let entries = Arc::new(Mutex::new(Vec::new()));
+        logger.add_processor(Box::new(CapturingProcessor { entries: Arc::clone(&entries) }));
+
+        let (mut gateway, _) = middleware_gateway();
+        gateway.add_middleware(RequestIdMiddleware::new());
+        gateway.add_middleware(AccessLogMiddleware::new(Arc::clone(&logger)));
+        gateway.add_route(route(HttpMethod::GET, "/ping", "ping")).unwrap();
+
+        let response = gateway.handle_request(request(HttpMethod::GET, "/ping")).await.unwrap();
+        let request_id = response.headers["X-Request-Id"].clone();
+        assert!(uuid::Uuid::parse_str(&request_id).is_ok());
+
+        let mut traced = request(HttpMethod::GET, "/missing");
+        traced.headers.insert("x-request-id".to_string(), "req-42".to_string());
+        let response = gateway.handle_request(traced).await.unwrap();
+        assert_eq!(response.headers["X-Request-Id"], "req-42");
+
+        // 运行时中按容量触发的刷新在阻塞线程池上异步执行，这里显式刷新后再检查
+        logger.flush().unwrap();
+        let entries = entries.lock().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].message, "GET /ping 200");
+        assert_eq!(entries[0].fields["request_id"], request_id.as_str());
+        assert!(matches!(entries[1].level, LogLevel::Warn));
+        assert_eq!(entries[1].fields["status"], 404);
+        assert_eq!(entries[1].fields["request_id"], "req-42");
+    }
+
+    /// 记录调用次数并返回固定头部的上游
+    struct CountingUpstream {
+        calls: AtomicU64,
+        status_code: u16,
+        headers: Vec<(&'static str, &'static str)>,
+        body: Option<&'static str>,
+        last_request_body: Mutex<Option<Vec<u8>>>,
+    }
+
+    impl CountingUpstream {
+        fn new(headers: &[(&'static str, &'static str)]) -> Arc<Self> {
+            Arc::new(Self {
+                calls: AtomicU64::new(0),
+                status_code: 200,
+                headers: headers.to_vec(),
+                body: None,
+                last_request_body: Mutex::new(None),
+            })
+        }
+
+        fn json(status_code: u16, body: &'static str) -> Arc<Self> {
+            Arc::new(Self {
+                calls: AtomicU64::new(0),
+                status_code,
+                headers: vec![("Content-Type", "application/json"), ("Content-Length", "0")],
+                body: Some(body),
+                last_request_body: Mutex::new(None),
+            })
+        }
+
+        fn calls(&self) -> u64 {
+            self.calls.load(Ordering::SeqCst)
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl UpstreamClient for CountingUpstream {
+        async fn send(&self, request: &Request, _instance: &ServiceInstance) -> Result<Response, GatewayError> {
+            let call = self.calls.fetch_add(1, Ordering::SeqCst) + 1;
+            *self.last_request_body.lock().unwrap() = request.body.clone();
+            Ok(Response {
+                status_code: self.status_code,
+                headers: self.headers.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect(),
+                body: Some(self.body.map_or_else(|| format!("{} #{}", request.path, call), str::to_string).into_bytes()),
+                processing_time: Duration::ZERO,
+            })
+        }
+    }
+
+    fn caching_gateway(upstream: &Arc<CountingUpstream>, clock: &Arc<ManualClock>, config: CacheConfig) -> ApiGatewayManager {
+        let mut gateway = ApiGatewayManager::new().with_upstream(upstream.clone());
+        gateway.load_balancer = balancer(LoadBalancingStrategy::RoundRobin, &[("a", 1)]);
+        gateway.cache = Cache::with_config(config).with_clock(clock.clone());
+        gateway.add_route(route(HttpMethod::GET, "/modules/*rest", "modules")).unwrap();
+        gateway.add_route(route(HttpMethod::POST, "/modules/*rest", "modules")).unwrap();
+        gateway
+    }
+
+    fn body(response: &Response) -> String {
+        String::from_utf8(response.body.clone().unwrap_or_default()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_cache_hit_skips_upstream_and_honors_vary() {
+        let upstream = CountingUpstream::new(&[("Cache-Control", "public, max-age=60"), ("Vary", "Accept-Language")]);
+        let clock = ManualClock::new();
+        let gateway = caching_gateway(&upstream, &clock, CacheConfig {
+            significant_query_params: Some(vec!["page".to_string()]),
+            ..CacheConfig::default()
+        });
+
+        let first = gateway.handle_request(request(HttpMethod::GET, "/modules/math")).await.unwrap();
+        let mut tracked = request(HttpMethod::GET, "/modules/math");
+        tracked.query_params.insert("utm_source".to_string(), "mail".to_string());
+        let second = gateway.handle_request(tracked).await.unwrap();
+        assert_eq!(upstream.calls(), 1);
+        assert_eq!(body(&second), body(&first));
+        assert_eq!(second.headers["X-Cache"], "HIT");
+        assert!(second.headers.contains_key("X-RateLimit-Limit"));
+
+        let mut paged = request(HttpMethod::GET, "/modules/math");
+        paged.query_params.insert("page".to_string(), "2".to_string());
+        gateway.handle_request(paged).await.unwrap();
+        let mut localized = request(HttpMethod::GET, "/modules/math");
+        localized.headers.insert("Accept-Language".to_string(), "zh-CN".to_string());
+        gateway.handle_request(localized.clone()).await.unwrap();
+        gateway.handle_request(localized).await.unwrap();
+        assert_eq!(upstream.calls(), 3);
+        assert_eq!(gateway.cache.stats(), CacheStats { hits: 2, misses: 3, stale: 0 });
+    }
+
+    #[tokio::test]
+    async fn test_no_store_and_unsafe_methods_bypass_cache() {
+        let upstream = CountingUpstream::new(&[("Cache-Control", "no-store")]);
+        let clock = ManualClock::new();
+        let gateway = caching_gateway(&upstream, &clock, CacheConfig::default());
+        for _ in 0..2 {
+            gateway.handle_request(request(HttpMethod::GET, "/modules/math")).await.unwrap();
+        }
+        assert_eq!(upstream.calls(), 2);
+
+        let upstream = CountingUpstream::new(&[("Cache-Control", "max-age=60")]);
+        let gateway = caching_gateway(&upstream, &clock, CacheConfig::default());
+        for _ in 0..2 {
+            gateway.handle_request(request(HttpMethod::POST, "/modules/math")).await.unwrap();
+        }
+        assert_eq!(upstream.calls(), 2);
+        assert!(gateway.cache.storage.lock().unwrap().is_empty());
+
+        let gateway = caching_gateway(&upstream, &clock, CacheConfig {
+            cacheable_methods: vec![HttpMethod::GET, HttpMethod::POST],
+            ..CacheConfig::default()
+        });
+        for _ in 0..2 {
+            gateway.handle_request(request(HttpMethod::POST, "/modules/math")).await.unwrap();
+        }
+        assert_eq!(upstream.calls(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_credentialed_responses_are_not_shared_unless_public() {
+        let bearer = |token: &str| {
+            let mut request = request(HttpMethod::GET, "/modules/private");
+            request.headers.insert("Authorization".to_string(), format!("Bearer {}", token));
+            request
+        };
+        let upstream = CountingUpstream::new(&[("Cache-Control", "max-age=60")]);
+        let clock = ManualClock::new();
+        let gateway = caching_gateway(&upstream, &clock, CacheConfig::default());
+        let alice = gateway.handle_request(bearer("alice")).await.unwrap();
+        let bob = gateway.handle_request(bearer("bob")).await.unwrap();
+        assert_eq!(upstream.calls(), 2);
+        assert_ne!(body(&alice), body(&bob));
+        assert!(gateway.cache.storage.lock().unwrap().is_empty());
+
+        // 匿名请求缓存的响应也不会返回给带凭据的请求
+        gateway.handle_request(request(HttpMethod::GET, "/modules/private")).await.unwrap();
+        gateway.handle_request(bearer("alice")).await.unwrap();
+        assert_eq!(upstream.calls(), 4);
+
+        let upstream = CountingUpstream::new(&[("Cache-Control", "public, max-age=60")]);
+        let gateway = caching_gateway(&upstream, &clock, CacheConfig::default());
+        gateway.handle_request(bearer("alice")).await.unwrap();
+        let shared = gateway.handle_request(bearer("bob")).await.unwrap();
+        assert_eq!(upstream.calls(), 1);
+        assert_eq!(shared.headers["X-Cache"], "HIT");
+    }
+
+    #[tokio::test]
+    async fn test_cache_ttl_expiry_and_stale_while_revalidate() {
+        // 未声明 max-age 时使用路由的默认 TTL
+        let upstream = CountingUpstream::new(&[]);
+        let clock = ManualClock::new();
+        let mut gateway = caching_gateway(&upstream, &clock, CacheConfig::default());
+        let mut short_lived = route(HttpMethod::GET, "/status", "status");
+        short_lived.cache_ttl = Some(Duration::from_secs(5));
+        gateway.add_route(short_lived).unwrap();
+        gateway.handle_request(request(HttpMethod::GET, "/status")).await.unwrap();
+        clock.advance(Duration::from_secs(4));
+        let cached = gateway.handle_request(request(HttpMethod::GET, "/status")).await.unwrap();
+        assert_eq!(cached.headers["Age"], "4");
+        clock.advance(Duration::from_secs(1));
+        gateway.handle_request(request(HttpMethod::GET, "/status")).await.unwrap();
+        assert_eq!(upstream.calls(), 2);
+
+        let upstream = CountingUpstream::new(&[("Cache-Control", "max-age=10")]);
+        let cache = Cache::with_config(CacheConfig {
+            stale_while_revalidate: Some(Duration::from_secs(30)),
+            ..CacheConfig::default()
+        })
+        .with_clock(clock.clone());
+        let page = request(HttpMethod::GET, "/modules/math");
+        assert!(cache.store(&page, &upstream_response(&upstream, &page).await, None));
+        clock.advance(Duration::from_secs(15));
+
+        // 第一个请求负责重新验证，期间其他请求拿到旧响应
+        assert!(matches!(cache.lookup(&page), CacheLookup::Miss));
+        let CacheLookup::Stale(stale) = cache.lookup(&page) else { panic!("expected stale response") };
+        assert_eq!(stale.headers["X-Cache"], "STALE");
+        assert_eq!(body(&stale), "/modules/math #1");
+        assert!(cache.store(&page, &upstream_response(&upstream, &page).await, None));
+        let CacheLookup::Hit(fresh) = cache.lookup(&page) else { panic!("expected fresh response") };
+        assert_eq!(body(&fresh), "/modules/math #2");
+
+        clock.advance(Duration::from_secs(41));
+        assert!(matches!(cache.lookup(&page), CacheLookup::Miss));
+        assert_eq!(cache.stats(), CacheStats { hits: 1, misses: 2, stale: 1 });
+    }
+
+    async fn upstream_response(upstream: &CountingUpstream, request: &Request) -> Response {
+        let instance = ServiceInstance { address: "a".to_string(), weight: 1, healthy: true };
+        upstream.send(request, &instance).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_purge_by_path_prefix() {
+        let upstream = CountingUpstream::new(&[("Cache-Control", "max-age=300")]);
+        let clock = ManualClock::new();
+        let gateway = caching_gateway(&upstream, &clock, CacheConfig::default());
+        for path in ["/modules/math", "/modules/math/v2", "/modules/mathx", "/modules/text"] {
+            gateway.handle_request(request(HttpMethod::GET, path)).await.unwrap();
+        }
+
+        assert_eq!(gateway.cache.purge_prefix("/modules/math/"), 2);
+        for path in ["/modules/math", "/modules/math/v2", "/modules/mathx", "/modules/text"] {
+            gateway.handle_request(request(HttpMethod::GET, path)).await.unwrap();
+        }
+        assert_eq!(upstream.calls(), 6);
+        assert_eq!(gateway.cache.purge_prefix("/"), 4);
+    }
+
+    fn transforming_gateway(upstream: &Arc<CountingUpstream>, config: serde_json::Value) -> ApiGatewayManager {
+        let mut gateway = ApiGatewayManager::new().with_upstream(upstream.clone());
+        gateway.load_balancer = balancer(LoadBalancingStrategy::RoundRobin, &[("a", 1)]);
+        let mut transformed = route(HttpMethod::POST, "/modules", "modules");
+        transformed.request_transform = serde_json::from_value(config["request"].clone()).unwrap();
+        transformed.response_transform = serde_json::from_value(config["response"].clone()).unwrap();
+        gateway.add_route(transformed).unwrap();
+        gateway
+    }
+
+    fn json_request(body: &str) -> Request {
+        let mut request = request(HttpMethod::POST, "/modules");
+        request.headers.insert("content-type".to_string(), "application/json; charset=utf-8".to_string());
+        request.body = Some(body.as_bytes().to_vec());
+        request
+    }
+
+    fn json_body(body: &Option<Vec<u8>>) -> serde_json::Value {
+        serde_json::from_slice(body.as_deref().unwrap()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_request_transform_renames_and_adds_nested_fields() {
+        let upstream = CountingUpstream::json(200, r#"{"ok":true}"#);
+        let gateway = transforming_gateway(&upstream, serde_json::json!({
+            "request": {"rules": [
+                {"op": "rename", "from": "moduleName", "to": "module.name"},
+                {"op": "remove", "path": "debug"},
+                {"op": "add", "path": "meta.source.gateway", "value": "edge-1"},
+            ]},
+        }));
+
+        let response = gateway.handle_request(json_request(r#"{"moduleName":"math","debug":true}"#)).await.unwrap();
+        assert_eq!(response.status_code, 200);
+        let forwarded = upstream.last_request_body.lock().unwrap().clone();
+        assert_eq!(json_body(&forwarded), serde_json::json!({
+            "module": {"name": "math"},
+            "meta": {"source": {"gateway": "edge-1"}},
+        }));
+
+        // 非 JSON 消息体原样转发
+        let mut plain = json_request("moduleName=math");
+        plain.headers.insert("content-type".to_string(), "text/plain".to_string());
+        gateway.handle_request(plain).await.unwrap();
+        assert_eq!(upstream.last_request_body.lock().unwrap().as_deref(), Some(&b"moduleName=math"[..]));
+    }
+
+    #[tokio::test]
+    async fn test_response_transform_unwraps_envelope_and_maps_status() {
+        let upstream = CountingUpstream::json(201, r#"{"data":{"id":7,"internal":"x"},"trace":"t-1"}"#);
+        let gateway = transforming_gateway(&upstream, serde_json::json!({
+            "response": {
+                "rules": [{"op": "unwrap", "field": "data"}, {"op": "remove", "path": "internal"}, {"op": "wrap", "field": "result"}],
+                "status_map": {"201": 200},
+            },
+        }));
+
+        let response = gateway.handle_request(json_request("{}")).await.unwrap();
+        assert_eq!(response.status_code, 200);
+        assert_eq!(json_body(&response.body), serde_json::json!({"result": {"id": 7}}));
+        assert_eq!(response.headers["Content-Length"], response.body.as_ref().unwrap().len().to_string());
+    }
+
+    #[tokio::test]
+    async fn test_malformed_json_with_transform_returns_502() {
+        let upstream = CountingUpstream::json(200, r#"{"data": "#);
+        let gateway = transforming_gateway(&upstream, serde_json::json!({
+            "request": {"rules": [{"op": "wrap", "field": "payload"}]},
+            "response": {"rules": [{"op": "unwrap", "field": "data"}]},
+        }));
+
+        let response = gateway.handle_request(json_request("{not json")).await.unwrap();
+        assert_eq!(response.status_code, 502);
+        assert!(body(&response).contains("请求不是合法的 JSON"));
+        assert_eq!(upstream.calls(), 0);
+
+        let response = gateway.handle_request(json_request(r#"{"id":1}"#)).await.unwrap();
+        assert_eq!(response.status_code, 502);
+        assert!(body(&response).contains("上游响应不是合法的 JSON"));
+        assert_eq!(upstream.calls(), 1);
+    }
+
+    /// 仅用于测试的 2048 位 RSA 私钥（PKCS#8 DER，base64）
+    const TEST_RSA_PKCS8: &str = concat!(
+        "MIIEuwIBADANBgkqhkiG9w0BAQEFAASCBKUwggShAgEAAoIBAQCbfp4vtioZzsqZKY7hamWgXrL0dpLuaFxhfLThy+SMBZch",
+        "NKCAQAUexKZXCdvjoXEVpmA/9+8AGE46EERuv73oOf7mlzWVPsL7b+1eJ7uxpGEeZqPumKRTIPlqpRqcQVX8efDDMm5CZ7hs",
+        "3P/iHnBX1/tkpAytI1NhTRUCxfaIIsYrsngfaDvp3BRIiifEr1r83CfboF08OmvkfwlZSFc3HpND0+DrYn6HIYA8XFqNySxa",
+        "cb6eTE1BgjQMtF/o0PLYWALwJP2g4mYh4+G6dMQ/90+pGgu+oaMEwgAk0HIeSOr4Pn5W5KiEtaXlQLXfwOMBQ0UB0LaHv3Pi",
+        "zA7cLR2dAgMBAAECggEAAi5LRgGzjctxMFG9ZRr1aZjlFfmobjs8zx83zZJAVoMTj2MqEce+v/jjw6+/NkOYOZoCRT4TIONh",
+        "DCkYlJxlgPbN75T/quQHnw1+/PA+DoFEMsV0wiQWQJY4o/lvKfGJT7Jey+tj/ClxPub17clzK5WQuzoedWUxRlmVlFrgw3Wt",
+        "H1pvemPpHMnbG9447nAKOORtHsZ+Q2wAccRUcFWY4myTHAU/9jSapWgyKtNr3P/ypUiZ1beRb/l7b3nwWMG0Ldk0KQ3WpQvJ",
+        "ZPNGmFwB9Vk6b/FBLXt0sxMLSScJUBqHv2Sr0D8M9jGlDDklqUxcOQ1ds8JA3WPQFNIdrClo7wKBgQDUVhx97y/d3CJD5VQ+",
+        "txAgKAccUcGzcNhHqb+XLXTk6EFdKNhkOFoW/1tywqKM0usBUXwqx3bJQPHGjHqfB8c5viQcO2h4O5MdI8xhRC6o6M73tU9B",
+        "5vmPNg9I9a646OYaH9ECs7F8NC4jAbMlxCpC24RBqCR7gSDPyxW97hpKYwKBgQC7eDitCtZsP65sbomRC36lSefXWqFVShRV",
+        "lzsMa/d/2z8BrAi4r1jvoJr0iuBHUqnoCNgdbdCxBEDv8w3v2M7J+/hjx4lP6C7+hGFvM/WHKkPWNeK1BvdzDOyjimTylvSo",
+        "qwGYKWNkCrtu32nyBTVfRzxdsWqy+ow4gL/hWsN3/wJ/QLH7/GLkMrTfu9Aj2vfr8eBASmCepA292BfgSC+Rbi1VvQy04dRJ",
+        "nrgvJMgovr+E2uH48w/TIX6oK9ahhdLY4L8OQYvYSMneCQLfQsODc/N7G/4iJ/I4F2phPdmdYrmTYmJPTINzkGRVqlTaQKeE",
+        "ONqpcVfJxdeSJ7244iWp3QKBgQCjADIPS2gK8Qfg4e+c/OieIPeDiRrUBLhPf1HpSAsH0YMkGgfvRQ9IM1XoIMRHX3m59c6r",
+        "L+uProoj2CqbC+EpQHvFjh43K0OJK2NohD4My1P2ch3vluIa1/b9b3y8bULHUQDu47W2q5ozGfCNbbftYwv6zyNeAXlgO5ob",
+        "AIcK0wKBgCuqBi9dCNo+yPrYj7ChRN0YrtkSykVcC9ETz2mo+F+/44YnwT/K6lFX41BaJyCKe95+vwwvwuqZP73S2p7yfktH",
+        "5Y/C6Y45qHOQYGIE1W5OGobapjEaTGK0rGJ47jhwuE/tz0wsB80lZ32WsMj4d1rrfkukTBgvKf0ykH+WijsW",
+    );
+
+    fn unix_now() -> u64 {
+        SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
+    }
+
+    fn encode_jwt(header: serde_json::Value, claims: serde_json::Value, sign: impl Fn(&[u8]) -> Vec<u8>) -> String {
+        let signing_input = format!(
+            "{}.{}",
+            URL_SAFE_NO_PAD.encode(header.to_string()),
+            URL_SAFE_NO_PAD.encode(claims.to_string())
+        );
+        let signature = sign(signing_input.as_bytes());
+        format!("{}.{}", signing_input, URL_SAFE_NO_PAD.encode(signature))
+    }
+
+    fn hs256_token(kid: &str, secret: &[u8], claims: serde_json::Value) -> String {
+        let key = ring::hmac::Key::new(ring::hmac::HMAC_SHA256, secret);
+        encode_jwt(serde_json::json!({"alg": "HS256", "typ": "JWT", "kid": kid}), claims, |input| {
+            ring::hmac::sign(&key, input).as_ref().to_vec()
+        })
+    }
+
+    fn rsa_key_pair() -> ring::signature::RsaKeyPair {
+        let der = base64::engine::general_purpose::STANDARD.decode(TEST_RSA_PKCS8).unwrap();
+        ring::signature::RsaKeyPair::from_pkcs8(&der).unwrap()
+    }
+
+    fn rs256_token(key_pair: &ring::signature::RsaKeyPair, claims: serde_json::Value) -> String {
+        encode_jwt(serde_json::json!({"alg": "RS256", "typ": "JWT"}), claims, |input| {
+            let mut signature = vec![0; key_pair.public().modulus_len()];
+            key_pair.sign(&ring::signature::RSA_PKCS1_SHA256, &ring::rand::SystemRandom::new(), input, &mut signature)
+                .unwrap();
+            signature
+        })
+    }
+
+    fn claims(scope: &str) -> serde_json::Value {
+        serde_json::json!({
+            "sub": "user-1",
+            "iss": "https://auth.example",
+            "aud": ["gateway", "console"],
+            "scope": scope,
+            "exp": unix_now() + 300,
+            "nbf": unix_now() - 10,
+        })
+    }
+
+    fn jwt_middleware(keys: &JwtKeySet) -> JwtAuthMiddleware {
+        JwtAuthMiddleware::new(keys.clone(), JwtAuthConfig {
+            issuer: Some("https://auth.example".to_string()),
+            audience: Some("gateway".to_string()),
+            ..JwtAuthConfig::default()
+        })
+    }
+
+    /// 记录下游看到的调用方
+    struct PrincipalProbe(Arc<Mutex<Option<Principal>>>);
+
+    impl Middleware for PrincipalProbe {
+        fn on_request(&self, request: &mut Request) -> Result<MiddlewareAction, GatewayError> {
+            *self.0.lock().unwrap() = request.principal().cloned();
+            Ok(MiddlewareAction::Continue)
+        }
+    }
+
+    fn authenticated(path: &str, token: &str) -> Request {
+        let mut request = request(HttpMethod::GET, path);
+        request.headers.insert("authorization".to_string(), format!("Bearer {}", token));
+        request
+    }
+
+    #[tokio::test]
+    async fn test_jwt_valid_tokens_attach_principal() {
+        let key_pair = rsa_key_pair();
+        let keys = JwtKeySet::new();
+        keys.insert("hmac-1", JwtKey::Hs256(b"shared-secret".to_vec()));
+        keys.insert("rsa-1", JwtKey::Rs256(key_pair.public().as_ref().to_vec()));
+        let seen = Arc::new(Mutex::new(None));
+        let (mut gateway, _) = middleware_gateway();
+        gateway.register_middleware("auth", jwt_middleware(&keys).require_scopes(["modules:read"]));
+        gateway.register_middleware("probe", PrincipalProbe(Arc::clone(&seen)));
+        let mut protected = route(HttpMethod::GET, "/modules/{id}", "modules");
+        protected.middlewares = vec!["auth".to_string(), "probe".to_string()];
+        gateway.add_route(protected).unwrap();
+
+        let token = hs256_token("hmac-1", b"shared-secret", claims("modules:read modules:write"));
+        let response = gateway.handle_request(authenticated("/modules/7", &token)).await.unwrap();
+        assert_eq!(response.status_code, 200);
+        let principal = seen.lock().unwrap().take().unwrap();
+        assert_eq!(principal.subject, "user-1");
+        assert_eq!(principal.issuer.as_deref(), Some("https://auth.example"));
+        assert!(principal.has_scope("modules:write"));
+
+        // 没有 kid 的 RS256 令牌会尝试全部 RSA 密钥
+        let token = rs256_token(&key_pair, claims("modules:read"));
+        let response = gateway.handle_request(authenticated("/modules/7", &token)).await.unwrap();
+        assert_eq!(response.status_code, 200);
+        assert_eq!(seen.lock().unwrap().as_ref().map(|p| p.scopes.clone()), Some(vec!["modules:read".to_string()]));
+    }
+
+    #[tokio::test]
+    async fn test_jwt_rejects_missing_expired_and_forged_tokens() {
+        let keys = JwtKeySet::new();
+        keys.insert("hmac-1", JwtKey::Hs256(b"shared-secret".to_vec()));
+        let (mut gateway, _) = middleware_gateway();
+        gateway.add_middleware(jwt_middleware(&keys));
+        gateway.add_route(route(HttpMethod::GET, "/modules/{id}", "modules")).unwrap();
+
+        let response = gateway.handle_request(request(HttpMethod::GET, "/modules/7")).await.unwrap();
+        assert_eq!(response.status_code, 401);
+        assert_eq!(response.headers["WWW-Authenticate"], r#"Bearer realm="api""#);
+
+        let mut expired = claims("");
+        expired["exp"] = serde_json::json!(unix_now() - 120);
+        let token = hs256_token("hmac-1", b"shared-secret", expired);
+        let response = gateway.handle_request(authenticated("/modules/7", &token)).await.unwrap();
+        assert_eq!(response.status_code, 401);
+        assert_eq!(
+            response.headers["WWW-Authenticate"],
+            r#"Bearer realm="api", error="invalid_token", error_description="token expired""#
+        );
+
+        let forged = hs256_token("hmac-1", b"guessed-secret", claims(""));
+        assert_eq!(jwt_middleware(&keys).verify(&forged), Err(JwtError::InvalidSignature));
+        let response = gateway.handle_request(authenticated("/modules/7", &forged)).await.unwrap();
+        assert_eq!(response.status_code, 401);
+
+        let unsigned = encode_jwt(serde_json::json!({"alg": "none"}), claims(""), |_| Vec::new());
+        assert_eq!(jwt_middleware(&keys).verify(&unsigned), Err(JwtError::UnsupportedAlgorithm("none".to_string())));
+    }
+
+    #[test]
+    fn test_jwt_time_claims_must_be_numeric() {
+        let keys = JwtKeySet::new();
+        keys.insert("hmac-1", JwtKey::Hs256(b"shared-secret".to_vec()));
+        let middleware = jwt_middleware(&keys);
+        let verify = |name: &str, value: serde_json::Value| {
+            let mut claims = claims("");
+            claims[name] = value;
+            middleware.verify(&hs256_token("hmac-1", b"shared-secret", claims))
+        };
+
+        assert_eq!(verify("exp", serde_json::json!(unix_now() as f64 - 120.5)), Err(JwtError::Expired));
+        assert!(verify("exp", serde_json::json!(unix_now() as f64 + 300.5)).is_ok());
+        assert!(verify("nbf", serde_json::json!(-10)).is_ok());
+        assert!(verify("iat", serde_json::json!(-10)).is_ok());
+        for name in ["exp", "nbf", "iat"] {
+            assert!(matches!(verify(name, serde_json::json!("soon")), Err(JwtError::Malformed(_))), "{}", name);
+            assert!(matches!(verify(name, serde_json::Value::Null), Err(JwtError::Malformed(_))), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_jwt_audience_issuer_and_key_rotation() {
+        let keys = JwtKeySet::new();
+        keys.insert("2024", JwtKey::Hs256(b"old-secret".to_vec()));
+        let middleware = jwt_middleware(&keys);
+
+        let mut foreign = claims("");
+        foreign["aud"] = serde_json::json!("billing");
+        assert_eq!(middleware.verify(&hs256_token("2024", b"old-secret", foreign)), Err(JwtError::InvalidAudience));
+        let mut other_issuer = claims("");
+        other_issuer["iss"] = serde_json::json!("https://evil.example");
+        assert_eq!(middleware.verify(&hs256_token("2024", b"old-secret", other_issuer)), Err(JwtError::InvalidIssuer));
+
+        // 轮换：新密钥加入后新旧令牌都可用，移除旧密钥后旧令牌失效
+        let old_token = hs256_token("2024", b"old-secret", claims(""));
+        keys.insert("2025", JwtKey::Hs256(b"new-secret".to_vec()));
+        assert!(middleware.verify(&old_token).is_ok());
+        assert!(middleware.verify(&hs256_token("2025", b"new-secret", claims(""))).is_ok());
+        assert!(keys.remove("2024"));
+        assert_eq!(keys.key_ids(), ["2025"]);
+        assert_eq!(middleware.verify(&old_token), Err(JwtError::UnknownKey("2024".to_string())));
+    }
+
+    #[tokio::test]
+    async fn test_jwt_insufficient_scope_returns_403() {
+        let keys = JwtKeySet::new();
+        keys.insert("hmac-1", JwtKey::Hs256(b"shared-secret".to_vec()));
+        let (mut gateway, _) = middleware_gateway();
+        gateway.register_middleware("read", jwt_middleware(&keys).require_scopes(["modules:read"]));
+        gateway.register_middleware("admin", jwt_middleware(&keys).require_scopes(["modules:read", "modules:admin"]));
+        let mut read = route(HttpMethod::GET, "/modules/{id}", "modules");
+        read.middlewares = vec!["read".to_string()];
+        let mut admin = route(HttpMethod::DELETE, "/modules/{id}", "modules");
+        admin.middlewares = vec!["admin".to_string()];
+        gateway.add_route(read).unwrap();
+        gateway.add_route(admin).unwrap();
+
+        let token = hs256_token("hmac-1", b"shared-secret", claims("modules:read"));
+        let response = gateway.handle_request(authenticated("/modules/7", &token)).await.unwrap();
+        assert_eq!(response.status_code, 200);
+
+        let mut delete = authenticated("/modules/7", &token);
+        delete.method = HttpMethod::DELETE;
+        let response = gateway.handle_request(delete).await.unwrap();
+        assert_eq!(response.status_code, 403);
+        assert_eq!(
+            response.headers["WWW-Authenticate"],
+            r#"Bearer realm="api", error="insufficient_scope", scope="modules:read modules:admin""#
+        );
+        assert!(body(&response).contains("modules:admin"));
+    }
+
+    /// 前若干次调用失败的上游，记录每次调用的实例
+    struct FlakyUpstream {
+        failures: u64,
+        calls: AtomicU64,
+        instances: Mutex<Vec<String>>,
+    }
+
+    impl FlakyUpstream {
+        fn new(failures: u64) -> Arc<Self> {
+            Arc::new(Self { failures, calls: AtomicU64::new(0), instances: Mutex::new(Vec::new()) })
+        }
+
+        fn instances(&self) -> Vec<String> {
+            self.instances.lock().unwrap().clone()
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl UpstreamClient for FlakyUpstream {
+        async fn send(&self, _request: &Request, instance: &ServiceInstance) -> Result<Response, GatewayError> {
+            let call = self.calls.fetch_add(1, Ordering::SeqCst);
+            self.instances.lock().unwrap().push(instance.address.clone());
+            if call < self.failures {
+                return Err(GatewayError::ServiceError(format!("连接 {} 被拒绝", instance.address)));
+            }
+            Ok(Response { status_code: 200, headers: HashMap::new(), body: None, processing_time: Duration::ZERO })
+        }
+    }
+
+    fn retrying_gateway(upstream: &Arc<FlakyUpstream>, policy: RetryPolicy) -> ApiGatewayManager {
+        let mut gateway = ApiGatewayManager::new().with_upstream(upstream.clone());
+        gateway.load_balancer = balancer(LoadBalancingStrategy::RoundRobin, &[("a", 1), ("b", 1)]);
+        for method in [HttpMethod::GET, HttpMethod::POST] {
+            let mut retried = route(method, "/modules/{id}", "modules");
+            retried.retry = Some(policy.clone());
+            gateway.add_route(retried).unwrap();
+        }
+        gateway
+    }
+
+    #[tokio::test]
+    async fn test_retry_succeeds_on_another_upstream() {
+        let upstream = FlakyUpstream::new(1);
+        let gateway = retrying_gateway(&upstream, RetryPolicy {
+            base_backoff: Duration::from_millis(1),
+            ..RetryPolicy::default()
+        });
+
+        let response = gateway.handle_request(request(HttpMethod::GET, "/modules/7")).await.unwrap();
+        assert_eq!(response.status_code, 200);
+        assert_eq!(upstream.instances(), ["a", "b"]);
+        assert_eq!(gateway.metrics(), GatewayMetrics {
+            upstream_attempts: 2,
+            retries: 1,
+            retries_exhausted: 0,
+            deadline_exceeded: 0,
+        });
+    }
+
+    #[tokio::test]
+    async fn test_non_idempotent_post_requires_idempotency_key() {
+        let upstream = FlakyUpstream::new(1);
+        let gateway = retrying_gateway(&upstream, RetryPolicy {
+            base_backoff: Duration::from_millis(1),
+            idempotency_key_header: Some("Idempotency-Key".to_string()),
+            ..RetryPolicy::default()
+        });
+
+        let response = gateway.handle_request(request(HttpMethod::POST, "/modules/7")).await.unwrap();
+        assert_eq!(response.status_code, 502);
+        assert_eq!(upstream.instances().len(), 1);
+        assert_eq!(gateway.metrics().retries, 0);
+
+        let upstream = FlakyUpstream::new(1);
+        let gateway = retrying_gateway(&upstream, RetryPolicy {
+            base_backoff: Duration::from_millis(1),
+            idempotency_key_header: Some("Idempotency-Key".to_string()),
+            ..RetryPolicy::default()
+        });
+        let mut keyed = request(HttpMethod::POST, "/modules/7");
+        keyed.headers.insert("idempotency-key".to_string(), "req-1".to_string());
+        let response = gateway.handle_request(keyed).await.unwrap();
+        assert_eq!(response.status_code, 200);
+        assert_eq!(upstream.instances().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_deadline_cuts_retries_short() {
+        let upstream = FlakyUpstream::new(u64::MAX);
+        let gateway = retrying_gateway(&upstream, RetryPolicy {
+            max_attempts: 10,
+            base_backoff: Duration::from_millis(40),
+            max_backoff: Duration::from_secs(1),
+            jitter: 0.0,
+            deadline: Some(Duration::from_millis(100)),
+            idempotency_key_header: None,
+        });
+
+        // 第一次失败后退避 40ms，第二次失败后的 80ms 退避会越过 100ms 的截止时间
+        let response = gateway.handle_request(request(HttpMethod::GET, "/modules/7")).await.unwrap();
+        assert_eq!(response.status_code, 502);
+        assert_eq!(upstream.instances().len(), 2);
+        let metrics = gateway.metrics();
+        assert_eq!((metrics.upstream_attempts, metrics.retries), (2, 1));
+        assert_eq!((metrics.retries_exhausted, metrics.deadline_exceeded), (0, 1));
+    }
+
+    #[test]
+    fn test_retry_backoff_grows_and_is_capped() {
+        let policy = RetryPolicy {
+            base_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_millis(250),
+            jitter: 0.0,
+            ..RetryPolicy::default()
+        };
+        assert_eq!(policy.backoff(1), Duration::from_millis(100));
+        assert_eq!(policy.backoff(2), Duration::from_millis(200));
+        assert_eq!(policy.backoff(3), Duration::from_millis(250));
+        assert_eq!(policy.backoff(40), Duration::from_millis(250));
+
+        let jittered = RetryPolicy { jitter: 0.5, ..policy };
+        assert!((0..100).map(|_| jittered.backoff(1)).all(|backoff| {
+            (Duration::from_millis(50)..=Duration::from_millis(100)).contains(&backoff)
+        }));
+    }
+
+    fn counter(gateway: &ApiGatewayManager, name: &str, labels: &[(&str, &str)]) -> i64 {
+        match gateway.metrics_collector.get_metric(name, &metric_labels(labels)).map(|metric| metric.value) {
+            Some(MetricValue::Integer(value)) => value,
+            other => panic!("{} {:?}: unexpected {:?}", name, labels, other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_gateway_metrics_per_route_and_upstream() {
+        let upstream = CountingUpstream::new(&[("Cache-Control", "max-age=60")]);
+        let gateway = caching_gateway(&upstream, &ManualClock::new(), CacheConfig::default());
+        for (method, path) in [
+            (HttpMethod::GET, "/modules/a"),
+            (HttpMethod::GET, "/modules/a"),
+            (HttpMethod::GET, "/modules/b"),
+            (HttpMethod::POST, "/modules/a"),
+            (HttpMethod::GET, "/nowhere"),
+        ] {
+            gateway.handle_request(request(method, path)).await.unwrap();
+        }
+
+        let route = "/modules/*rest";
+        assert_eq!(counter(&gateway, GATEWAY_REQUESTS, &[("route", route), ("method", "GET"), ("status_class", "2xx")]), 3);
+        assert_eq!(counter(&gateway, GATEWAY_REQUESTS, &[("route", route), ("method", "POST"), ("status_class", "2xx")]), 1);
+        assert_eq!(counter(&gateway, GATEWAY_REQUESTS, &[("route", "unmatched"), ("method", "GET"), ("status_class", "4xx")]), 1);
+        // POST 不可缓存，不计入未命中
+        assert_eq!(counter(&gateway, GATEWAY_CACHE_HITS, &[("route", route)]), 1);
+        assert_eq!(counter(&gateway, GATEWAY_CACHE_MISSES, &[("route", route)]), 2);
+        assert_eq!(counter(&gateway, GATEWAY_UPSTREAM_REQUESTS, &[("upstream", "a"), ("status_class", "2xx")]), 3);
+        let in_flight = gateway.metrics_collector.get_metric(GATEWAY_IN_FLIGHT, &metric_labels(&[("route", route)]));
+        assert!(matches!(in_flight.map(|metric| metric.value), Some(MetricValue::Float(value)) if value == 0.0));
+        let histograms = gateway.metrics_collector.histograms.lock().unwrap();
+        let latency = &histograms[GATEWAY_REQUEST_DURATION];
+        assert_eq!(latency.series.values().map(|series| series.count).sum::<u64>(), 5);
+        drop(histograms);
+
+        // 标签只使用路由模板，原始路径不会出现
+        let metrics = gateway.metrics_collector.metrics.lock().unwrap();
+        assert!(metrics.values().flat_map(|metric| metric.labels.values()).all(|value| !value.starts_with("/modules/a")));
+        drop(metrics);
+
+        let summary = gateway.metrics_summary();
+        let routes: Vec<&str> = summary.routes.iter().map(|route| route.route.as_str()).collect();
+        assert_eq!(routes, [route, "unmatched"]);
+        let modules = &summary.routes[0];
+        assert_eq!((modules.requests, modules.cache_hits, modules.cache_misses, modules.in_flight), (4, 1, 2, 0));
+        assert_eq!(modules.status_classes, BTreeMap::from([("2xx".to_string(), 4)]));
+        assert!(modules.mean_latency_ms.is_some());
+        assert_eq!(summary.upstreams[0].upstream, "a");
+        assert_eq!(summary.upstreams[0].requests, 3);
+        let json = serde_json::to_value(&summary).unwrap();
+        assert_eq!(json["routes"][1]["status_classes"]["4xx"], 1);
+    }
+
+    #[tokio::test]
+    async fn test_gateway_metrics_rate_limit_and_circuit_opens() {
+        let clock = ManualClock::new();
+        let upstream = FlakyUpstream::new(u64::MAX);
+        let mut gateway = ApiGatewayManager::new().with_upstream(upstream.clone());
+        gateway.load_balancer = balancer(LoadBalancingStrategy::RoundRobin, &[("a", 1)]);
+        gateway.load_balancer.circuit_breaker = circuit_breaker(&clock);
+        gateway.rate_limiter.default_limit = RateLimit {
+            requests_per_second: 1,
+            burst_limit: 5,
+            window_size: Duration::from_secs(1),
+        };
+        gateway.add_route(route(HttpMethod::GET, "/ping", "ping")).unwrap();
+
+        // 4 次失败触发熔断，第 5 次被熔断拒绝，第 6 次被限流
+        let mut statuses = Vec::new();
+        for _ in 0..6 {
+            statuses.push(gateway.handle_request(request(HttpMethod::GET, "/ping")).await.unwrap().status_code);
+        }
+        assert_eq!(statuses, [502, 502, 502, 502, 503, 429]);
+
+        assert_eq!(counter(&gateway, GATEWAY_CIRCUIT_OPENS, &[("upstream", "a")]), 1);
+        assert_eq!(counter(&gateway, GATEWAY_UPSTREAM_REQUESTS, &[("upstream", "a"), ("status_class", "error")]), 4);
+        assert_eq!(counter(&gateway, GATEWAY_RATE_LIMITED, &[("route", "/ping")]), 1);
+        assert_eq!(counter(&gateway, GATEWAY_REQUESTS, &[("route", "/ping"), ("method", "GET"), ("status_class", "5xx")]), 5);
+
+        let summary = gateway.metrics_summary();
+        assert_eq!(summary.routes[0].rate_limited, 1);
+        assert_eq!(summary.upstreams[0].circuit_opens, 1);
+        assert_eq!(summary.upstreams[0].circuit_state, Some(CircuitState::Open));
+        assert_eq!(summary.upstreams[0].status_classes, BTreeMap::from([("error".to_string(), 4)]));
+    }
+
+    /// 可按实例切换健康状态的上游，不健康的实例对所有请求返回 503
+    struct SwitchableUpstream {
+        down: Mutex<HashSet<String>>,
+        probes: AtomicU64,
+    }
+
+    impl SwitchableUpstream {
+        fn new() -> Arc<Self> {
+            Arc::new(Self { down: Mutex::new(HashSet::new()), probes: AtomicU64::new(0) })
+        }
+
+        fn set_down(&self, address: &str, down: bool) {
+            let mut instances = self.down.lock().unwrap();
+            if down {
+                instances.insert(address.to_string());
+            } else {
+                instances.remove(address);
+            }
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl UpstreamClient for SwitchableUpstream {
+        async fn send(&self, request: &Request, instance: &ServiceInstance) -> Result<Response, GatewayError> {
+            if request.path == "/health" {
+                self.probes.fetch_add(1, Ordering::SeqCst);
+            }
+            let status_code = if self.down.lock().unwrap().contains(&instance.address) { 503 } else { 200 };
+            Ok(Response {
+                status_code,
+                headers: HashMap::new(),
+                body: Some(instance.address.clone().into_bytes()),
+                processing_time: Duration::ZERO,
+            })
+        }
+    }
+
+    fn health_checked_gateway(upstream: &Arc<SwitchableUpstream>, config: UpstreamHealthConfig) -> ApiGatewayManager {
+        let mut gateway = ApiGatewayManager::new().with_upstream(upstream.clone());
+        gateway.load_balancer = balancer(LoadBalancingStrategy::RoundRobin, &[("a", 1), ("b", 1)]);
+        gateway.load_balancer.health = Arc::new(UpstreamHealth::new(config));
+        gateway.add_route(route(HttpMethod::GET, "/ping", "ping")).unwrap();
+        gateway
+    }
+
+    async fn served_by(gateway: &ApiGatewayManager, requests: usize) -> Vec<String> {
+        let mut served = Vec::new();
+        for _ in 0..requests {
+            let response = gateway.handle_request(request(HttpMethod::GET, "/ping")).await.unwrap();
+            served.push(body(&response));
+        }
+        served
+    }
+
+    #[tokio::test]
+    async fn test_active_health_checks_eject_and_readmit() {
+        let upstream = SwitchableUpstream::new();
+        let gateway = health_checked_gateway(&upstream, UpstreamHealthConfig {
+            healthy_threshold: 3,
+            unhealthy_threshold: 2,
+            ..UpstreamHealthConfig::default()
+        });
+        let pool = gateway.upstream_health_check();
+
+        upstream.set_down("b", true);
+        let results = gateway.check_upstreams().await;
+        assert_eq!(results.iter().map(|result| result.status).collect::<Vec<_>>(), [HealthStatus::Healthy, HealthStatus::Unhealthy]);
+        assert_eq!(results[1].details.as_deref(), Some("HTTP 503"));
+        assert!(gateway.load_balancer.health.ejected().is_empty());
+
+        // 第二次连续失败后摘除
+        gateway.check_upstreams().await;
+        assert_eq!(gateway.load_balancer.health.ejected(), ["b"]);
+        assert!(served_by(&gateway, 4).await.iter().all(|address| address == "a"));
+        assert_eq!(pool.check().unwrap().status, HealthStatus::Degraded);
+        assert_eq!(counter(&gateway, GATEWAY_UPSTREAM_EJECTIONS, &[("upstream", "b"), ("reason", "active")]), 1);
+
+        // 恢复后需要连续通过 3 次才重新加入
+        upstream.set_down("b", false);
+        for _ in 0..2 {
+            gateway.check_upstreams().await;
+            assert!(gateway.load_balancer.health.is_ejected("b"));
+        }
+        gateway.check_upstreams().await;
+        assert!(!gateway.load_balancer.health.is_ejected("b"));
+        assert!(served_by(&gateway, 4).await.contains(&"b".to_string()));
+        assert_eq!(pool.check().unwrap().status, HealthStatus::Healthy);
+        assert_eq!(counter(&gateway, GATEWAY_UPSTREAM_READMISSIONS, &[("upstream", "b")]), 1);
+        assert_eq!(gateway.load_balancer.health.metrics(), UpstreamHealthMetrics {
+            ejections: 1,
+            outlier_ejections: 0,
+            readmissions: 1,
+        });
+    }
+
+    #[tokio::test]
+    async fn test_outlier_detection_ejects_on_5xx_burst() {
+        let upstream = SwitchableUpstream::new();
+        let gateway = health_checked_gateway(&upstream, UpstreamHealthConfig {
+            healthy_threshold: 1,
+            outlier_detection: Some(OutlierDetection { consecutive_failures: 3, ..OutlierDetection::default() }),
+            ..UpstreamHealthConfig::default()
+        });
+        upstream.set_down("b", true);
+
+        // 轮询下 b 每隔一个请求出现一次，第 3 次 503 后立即摘除，无需等待主动检查
+        let mut statuses = Vec::new();
+        for _ in 0..6 {
+            statuses.push(gateway.handle_request(request(HttpMethod::GET, "/ping")).await.unwrap().status_code);
+        }
+        assert_eq!(statuses, [200, 503, 200, 503, 200, 503]);
+        assert_eq!(gateway.load_balancer.health.ejected(), ["b"]);
+        assert!(served_by(&gateway, 4).await.iter().all(|address| address == "a"));
+        assert_eq!(counter(&gateway, GATEWAY_UPSTREAM_EJECTIONS, &[("upstream", "b"), ("reason", "outlier")]), 1);
+        let summary = gateway.metrics_summary();
+        let b = summary.upstreams.iter().find(|upstream| upstream.upstream == "b").unwrap();
+        assert_eq!((b.ejections, b.ejected), (1, true));
+
+        upstream.set_down("b", false);
+        gateway.check_upstreams().await;
+        assert!(gateway.load_balancer.health.ejected().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_outlier_ejection_is_capped_and_probed_after_interval() {
+        let upstream = SwitchableUpstream::new();
+        let clock = ManualClock::new();
+        let mut gateway = health_checked_gateway(&upstream, UpstreamHealthConfig {
+            outlier_detection: Some(OutlierDetection { consecutive_failures: 1, ejection_interval: Duration::from_secs(30) }),
+            ..UpstreamHealthConfig::default()
+        });
+        gateway.load_balancer.health = Arc::new(
+            UpstreamHealth::new(gateway.load_balancer.health.config.clone()).with_clock(clock.clone()),
+        );
+        upstream.set_down("a", true);
+        upstream.set_down("b", true);
+
+        // 两个实例都失败时只摘除一个，最后一个实例继续接收流量
+        served_by(&gateway, 4).await;
+        assert_eq!(gateway.load_balancer.health.ejected(), ["a"]);
+        assert!(served_by(&gateway, 2).await.iter().all(|address| address == "b"));
+
+        // 摘除期满后放行探测请求：失败重新摘除，成功重新加入
+        clock.advance(Duration::from_secs(30));
+        assert!(gateway.load_balancer.health.ejected().is_empty());
+        served_by(&gateway, 2).await;
+        assert_eq!(gateway.load_balancer.health.ejected(), ["a"]);
+
+        upstream.set_down("a", false);
+        upstream.set_down("b", false);
+        clock.advance(Duration::from_secs(30));
+        assert!(served_by(&gateway, 2).await.contains(&"a".to_string()));
+        assert!(gateway.load_balancer.health.ejected().is_empty());
+        assert_eq!(counter(&gateway, GATEWAY_UPSTREAM_READMISSIONS, &[("upstream", "a")]), 1);
+        assert_eq!(gateway.load_balancer.health.metrics(), UpstreamHealthMetrics {
+            ejections: 0,
+            outlier_ejections: 2,
+            readmissions: 1,
+        });
+    }
+
+    #[tokio::test]
+    async fn test_background_health_checks_run_on_interval() {
+        let upstream = SwitchableUpstream::new();
+        let gateway = Arc::new(health_checked_gateway(&upstream, UpstreamHealthConfig {
+            interval: Duration::from_millis(10),
+            unhealthy_threshold: 2,
+            ..UpstreamHealthConfig::default()
+        }));
+        upstream.set_down("a", true);
+        gateway.start_health_checks();
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !gateway.load_balancer.health.is_ejected("a") && Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        assert_eq!(gateway.load_balancer.health.ejected(), ["a"]);
+        gateway.stop_health_checks();
+        let probes = upstream.probes.load(Ordering::SeqCst);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(upstream.probes.load(Ordering::SeqCst), probes);
+    }
+
+    fn cors_policy(origins: &[&str]) -> CorsPolicy {
+        CorsPolicy {
+            allowed_origins: origins.iter().map(|origin| origin.to_string()).collect(),
+            allowed_methods: vec![HttpMethod::GET, HttpMethod::PUT],
+            allowed_headers: vec!["Content-Type".to_string(), "X-Api-Key".to_string()],
+            exposed_headers: vec!["X-Request-Id".to_string()],
+            allow_credentials: true,
+            max_age: Some(Duration::from_secs(600)),
+        }
+    }
+
+    fn from_origin(method: HttpMethod, path: &str, origin: &str) -> Request {
+        let mut request = request(method, path);
+        request.headers.insert("origin".to_string(), origin.to_string());
+        request
+    }
+
+    fn cors_headers(response: &Response) -> Vec<&str> {
+        let mut names: Vec<&str> = response.headers.keys()
+            .map(String::as_str)
+            .filter(|name| name.starts_with("Access-Control-"))
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[tokio::test]
+    async fn test_cors_echoes_allowed_origins() {
+        let upstream = CountingUpstream::new(&[("Vary", "Accept-Language")]);
+        let mut gateway = ApiGatewayManager::new().with_upstream(upstream.clone());
+        gateway.load_balancer = balancer(LoadBalancingStrategy::RoundRobin, &[("a", 1)]);
+        gateway.set_cors_policy(cors_policy(&["https://app.example.com", "https://*.example.org"])).unwrap();
+        gateway.add_route(route(HttpMethod::GET, "/modules/{id}", "modules")).unwrap();
+
+        let response = gateway.handle_request(from_origin(HttpMethod::GET, "/modules/7", "https://app.example.com")).await.unwrap();
+        assert_eq!(response.status_code, 200);
+        assert_eq!(response.headers["Access-Control-Allow-Origin"], "https://app.example.com");
+        assert_eq!(response.headers["Access-Control-Allow-Credentials"], "true");
+        assert_eq!(response.headers["Access-Control-Expose-Headers"], "X-Request-Id");
+        assert_eq!(response.headers["Vary"], "Accept-Language, Origin");
+
+        let response = gateway.handle_request(from_origin(HttpMethod::GET, "/modules/7", "https://eu.api.example.org")).await.unwrap();
+        assert_eq!(response.headers["Access-Control-Allow-Origin"], "https://eu.api.example.org");
+
+        // 请求不带 Origin 时不添加 CORS 头部
+        let response = gateway.handle_request(request(HttpMethod::GET, "/modules/7")).await.unwrap();
+        assert!(cors_headers(&response).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_cors_disallowed_origins_get_no_headers() {
+        let mut gateway = ApiGatewayManager::new();
+        gateway.load_balancer = balancer(LoadBalancingStrategy::RoundRobin, &[("a", 1)]);
+        gateway.set_cors_policy(cors_policy(&["https://app.example.com", "https://*.example.org"])).unwrap();
+        gateway.add_route(route(HttpMethod::GET, "/modules/{id}", "modules")).unwrap();
+
+        for origin in [
+            "https://app.example.com.evil.io",
+            "https://evil-app.example.com",
+            "http://app.example.com",
+            "https://example.org",
+            "https://evilexample.org",
+            "https://a.example.org.evil.io",
+            "https://x/.example.org",
+        ] {
+            let response = gateway.handle_request(from_origin(HttpMethod::GET, "/modules/7", origin)).await.unwrap();
+            assert_eq!(response.status_code, 200);
+            assert!(cors_headers(&response).is_empty(), "{} => {:?}", origin, response.headers);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cors_preflight_short_circuits() {
+        let upstream = CountingUpstream::new(&[]);
+        let (mut gateway, events) = middleware_gateway();
+        gateway = gateway.with_upstream(upstream.clone());
+        gateway.add_middleware(RecordingMiddleware::new("global", &events));
+        let mut modules = route(HttpMethod::PUT, "/modules/{id}", "modules");
+        modules.cors = Some(cors_policy(&["https://app.example.com"]));
+        gateway.add_route(modules).unwrap();
+
+        let mut preflight = from_origin(HttpMethod::OPTIONS, "/modules/7", "https://app.example.com");
+        preflight.headers.insert("access-control-request-method".to_string(), "PUT".to_string());
+        preflight.headers.insert("access-control-request-headers".to_string(), "content-type, x-api-key".to_string());
+        let response = gateway.handle_request(preflight.clone()).await.unwrap();
+        assert_eq!(response.status_code, 204);
+        assert_eq!(response.headers["Access-Control-Allow-Origin"], "https://app.example.com");
+        assert_eq!(response.headers["Access-Control-Allow-Methods"], "GET, PUT");
+        assert_eq!(response.headers["Access-Control-Allow-Headers"], "content-type, x-api-key");
+        assert_eq!(response.headers["Access-Control-Max-Age"], "600");
+        assert_eq!(response.headers["Access-Control-Allow-Credentials"], "true");
+        assert!(response.body.is_none());
+        assert_eq!(upstream.calls(), 0);
+        assert!(drain(&events).is_empty());
+
+        // 请求头或源不被允许时拒绝预检
+        preflight.headers.insert("access-control-request-headers".to_string(), "x-forbidden".to_string());
+        let response = gateway.handle_request(preflight).await.unwrap();
+        assert_eq!(response.status_code, 403);
+        assert!(cors_headers(&response).is_empty());
+        let mut foreign = from_origin(HttpMethod::OPTIONS, "/modules/7", "https://evil.example");
+        foreign.headers.insert("access-control-request-method".to_string(), "PUT".to_string());
+        assert_eq!(gateway.handle_request(foreign).await.unwrap().status_code, 403);
+        assert_eq!(upstream.calls(), 0);
+    }
+
+    #[test]
+    fn test_cors_wildcard_with_credentials_is_rejected() {
+        let mut gateway = ApiGatewayManager::new();
+        let error = gateway.set_cors_policy(cors_policy(&["*"])).unwrap_err();
+        assert!(matches!(error, GatewayError::ConfigError(_)), "{}", error);
+
+        let mut wildcard = route(HttpMethod::GET, "/public", "public");
+        wildcard.cors = Some(cors_policy(&["https://app.example.com", "*"]));
+        assert!(matches!(gateway.add_route(wildcard.clone()), Err(GatewayError::ConfigError(_))));
+        assert!(CorsPolicy { allowed_origins: vec!["app.example.com".to_string()], ..CorsPolicy::default() }.validate().is_err());
+        assert!(CorsPolicy { allowed_origins: vec!["https://*.*.example.com".to_string()], ..CorsPolicy::default() }.validate().is_err());
+
+        // 不携带凭据时允许任意源，响应使用 `*`
+        wildcard.cors = Some(CorsPolicy { allow_credentials: false, ..cors_policy(&["*"]) });
+        gateway.add_route(wildcard).unwrap();
+        let mut headers = HashMap::new();
+        gateway.routes.lock().unwrap()["GET:/public"].cors.as_ref().unwrap().apply("https://any.site", &mut headers);
+        assert_eq!(headers["Access-Control-Allow-Origin"], "*");
+        assert!(!headers.contains_key("Vary"));
+        assert!(!headers.contains_key("Access-Control-Allow-Credentials"));
+    }
+
+    /// 原样回显帧的上游，收到关闭帧时回显后结束
+    struct EchoUpstream {
+        opened: AtomicU64,
+    }
+
+    #[async_trait::async_trait]
+    impl UpstreamClient for EchoUpstream {
+        async fn send(&self, _request: &Request, _instance: &ServiceInstance) -> Result<Response, GatewayError> {
+            Err(GatewayError::ServiceError("只支持流式连接".to_string()))
+        }
+
+        async fn open_stream(&self, _request: &Request, _instance: &ServiceInstance) -> Result<StreamChannel, GatewayError> {
+            self.opened.fetch_add(1, Ordering::SeqCst);
+            let (gateway_end, mut upstream_end) = StreamChannel::pair(8);
+            tokio::spawn(async move {
+                while let Some(frame) = upstream_end.receiver.recv().await {
+                    let close = matches!(frame, StreamFrame::Close { .. });
+                    if upstream_end.sender.send(frame).await.is_err() || close {
+                        break;
+                    }
+                }
+            });
+            Ok(gateway_end)
+        }
+    }
+
+    fn streaming_gateway(config: StreamingConfig) -> (ApiGatewayManager, Arc<EchoUpstream>, Arc<Mutex<Vec<String>>>) {
+        let upstream = Arc::new(EchoUpstream { opened: AtomicU64::new(0) });
+        let (mut gateway, events) = middleware_gateway();
+        gateway = gateway.with_upstream(upstream.clone());
+        gateway.add_middleware(RecordingMiddleware::new("global", &events));
+        let mut socket = route(HttpMethod::GET, "/modules/{id}/socket", "modules");
+        socket.streaming = Some(config);
+        // 流式路由跳过响应体转换
+        socket.response_transform = Some(TransformSpec { status_map: HashMap::from([(101, 500)]), ..TransformSpec::default() });
+        gateway.add_route(socket).unwrap();
+        gateway.add_route(route(HttpMethod::GET, "/modules/{id}", "modules")).unwrap();
+        (gateway, upstream, events)
+    }
+
+    fn websocket_request(path: &str) -> Request {
+        let mut request = request(HttpMethod::GET, path);
+        for (name, value) in [
+            ("upgrade", "websocket"),
+            ("connection", "Upgrade"),
+            ("sec-websocket-key", "dGhlIHNhbXBsZSBub25jZQ=="),
+            ("sec-websocket-version", "13"),
+        ] {
+            request.headers.insert(name.to_string(), value.to_string());
+        }
+        request
+    }
+
+    fn accepted(upgrade: StreamUpgrade) -> StreamSession {
+        match upgrade {
+            StreamUpgrade::Accepted(session) => session,
+            StreamUpgrade::Rejected(response) => panic!("stream rejected: {} {}", response.status_code, body(&response)),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_websocket_upgrade_relays_frames_round_trip() {
+        let (gateway, upstream, events) = streaming_gateway(StreamingConfig::default());
+        let mut session = accepted(gateway.handle_stream(websocket_request("/modules/7/socket")).await);
+        assert_eq!(session.response.status_code, 101);
+        assert_eq!(session.response.headers["Sec-WebSocket-Accept"], "s3pPLMBiTxaQ9kYGzzhZRbK+xOo=");
+        assert_eq!(drain(&events), ["global:request", "global:response:101"]);
+
+        for frame in [StreamFrame::Text("hello".to_string()), StreamFrame::Binary(vec![1, 2, 3])] {
+            session.channel.sender.send(frame.clone()).await.unwrap();
+            assert_eq!(session.channel.receiver.recv().await, Some(frame));
+        }
+        assert_eq!(gateway.load_balancer.in_flight("a"), 1);
+        let close = StreamFrame::Close { code: 1000, reason: "bye".to_string() };
+        session.channel.sender.send(close.clone()).await.unwrap();
+        let summary = session.relay.await.unwrap();
+        assert_eq!(summary, StreamSummary {
+            frames_to_upstream: 2,
+            frames_to_client: 2,
+            close_reason: StreamCloseReason::ClientClosed,
+        });
+        assert_eq!(gateway.load_balancer.in_flight("a"), 0);
+        assert_eq!(upstream.opened.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_stream_idle_timeout_closes_session() {
+        let (gateway, _, _) = streaming_gateway(StreamingConfig {
+            idle_timeout: Duration::from_millis(50),
+            ..StreamingConfig::default()
+        });
+        // 没有 Upgrade 头部的流式路由请求按分块流处理
+        let mut session = accepted(gateway.handle_stream(request(HttpMethod::GET, "/modules/7/socket")).await);
+        assert_eq!(session.response.status_code, 200);
+        session.channel.sender.send(StreamFrame::Text("data: 1".to_string())).await.unwrap();
+        assert_eq!(session.channel.receiver.recv().await, Some(StreamFrame::Text("data: 1".to_string())));
+
+        assert_eq!(
+            session.channel.receiver.recv().await,
+            Some(StreamFrame::Close { code: 1001, reason: "idle timeout".to_string() })
+        );
+        assert_eq!(session.relay.await.unwrap().close_reason, StreamCloseReason::IdleTimeout);
+    }
+
+    #[tokio::test]
+    async fn test_stream_frame_size_limit_is_enforced() {
+        let (gateway, _, _) = streaming_gateway(StreamingConfig { max_client_frame: 8, ..StreamingConfig::default() });
+        let mut session = accepted(gateway.handle_stream(websocket_request("/modules/7/socket")).await);
+        session.channel.sender.send(StreamFrame::Binary(vec![0; 8])).await.unwrap();
+        assert_eq!(session.channel.receiver.recv().await, Some(StreamFrame::Binary(vec![0; 8])));
+
+        session.channel.sender.send(StreamFrame::Binary(vec![0; 9])).await.unwrap();
+        assert_eq!(
+            session.channel.receiver.recv().await,
+            Some(StreamFrame::Close { code: 1009, reason: "frame too large".to_string() })
+        );
+        let summary = session.relay.await.unwrap();
+        assert_eq!(summary.close_reason, StreamCloseReason::FrameTooLarge(StreamDirection::ClientToUpstream));
+        assert_eq!(summary.frames_to_upstream, 1);
+    }
+
+    #[tokio::test]
+    async fn test_stream_rejections_run_middlewares_without_upstream() {
+        let (mut gateway, upstream, events) = streaming_gateway(StreamingConfig::default());
+        let StreamUpgrade::Rejected(response) = gateway.handle_stream(request(HttpMethod::GET, "/modules/7")).await else {
+            panic!("buffered route must not be upgraded");
+        };
+        assert_eq!(response.status_code, 400);
+
+        let mut old_version = websocket_request("/modules/7/socket");
+        old_version.headers.insert("sec-websocket-version".to_string(), "8".to_string());
+        let StreamUpgrade::Rejected(response) = gateway.handle_stream(old_version).await else {
+            panic!("unsupported version must be rejected");
+        };
+        assert_eq!(response.status_code, 400);
+
+        // 不支持的升级协议和缺少 Connection: Upgrade 的 WebSocket 请求都不会当作流处理
+        let mut h2c = websocket_request("/modules/7/socket");
+        h2c.headers.insert("upgrade".to_string(), "h2c".to_string());
+        let mut keep_alive = websocket_request("/modules/7/socket");
+        keep_alive.headers.insert("connection".to_string(), "keep-alive".to_string());
+        for rejected in [h2c, keep_alive] {
+            let StreamUpgrade::Rejected(response) = gateway.handle_stream(rejected).await else {
+                panic!("invalid upgrade must be rejected");
+            };
+            assert_eq!(response.status_code, 400);
+        }
+        drain(&events);
+
+        // 中间件短路时不会升级
+        let mut blocking = RecordingMiddleware::new("auth", &events);
+        blocking.short_circuit = Some(401);
+        gateway.add_middleware(blocking);
+        let StreamUpgrade::Rejected(response) = gateway.handle_stream(websocket_request("/modules/7/socket")).await else {
+            panic!("short-circuited request must not be upgraded");
+        };
+        assert_eq!(response.status_code, 401);
+        assert_eq!(drain(&events), ["global:request", "auth:request", "auth:response:401", "global:response:401"]);
+        assert_eq!(upstream.opened.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn test_stream_applies_rate_limit_circuit_breaker_and_cors() {
+        let (mut gateway, upstream, _) = streaming_gateway(StreamingConfig::default());
+        gateway.set_cors_policy(cors_policy(&["https://app.example.com"])).unwrap();
+        gateway.rate_limiter.default_limit = RateLimit {
+            requests_per_second: 1,
+            burst_limit: 1,
+            window_size: Duration::from_secs(1),
+        };
+        let mut upgrade = websocket_request("/modules/7/socket");
+        upgrade.headers.insert("origin".to_string(), "https://app.example.com".to_string());
+        let session = accepted(gateway.handle_stream(upgrade.clone()).await);
+        assert_eq!(session.response.headers["Access-Control-Allow-Origin"], "https://app.example.com");
+        assert_eq!(session.response.headers["X-RateLimit-Remaining"], "0");
+
+        let StreamUpgrade::Rejected(response) = gateway.handle_stream(upgrade.clone()).await else {
+            panic!("rate-limited upgrade must be rejected");
+        };
+        assert_eq!(response.status_code, 429);
+        assert_eq!(response.headers["Access-Control-Allow-Origin"], "https://app.example.com");
+
+        let clock = ManualClock::new();
+        gateway.load_balancer.circuit_breaker = circuit_breaker(&clock);
+        for _ in 0..4 {
+            gateway.load_balancer.circuit_breaker.try_acquire("a").unwrap().record(false);
+        }
+        let mut other_client = websocket_request("/modules/8/socket");
+        other_client.client_ip = "10.0.0.9".to_string();
+        let StreamUpgrade::Rejected(response) = gateway.handle_stream(other_client).await else {
+            panic!("upgrade to an open circuit must be rejected");
+        };
+        assert_eq!(response.status_code, 503);
+        assert_eq!(upstream.opened.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_stream_idle_timeout_covers_stalled_sends() {
+        let (gateway, _, _) = streaming_gateway(StreamingConfig {
+            idle_timeout: Duration::from_millis(50),
+            buffer: 1,
+            ..StreamingConfig::default()
+        });
+        // 客户端只发送不读取，回显的帧填满发往客户端的缓冲后中继不能一直阻塞
+        let session = accepted(gateway.handle_stream(websocket_request("/modules/7/socket")).await);
+        for index in 0..2 {
+            session.channel.sender.send(StreamFrame::Text(index.to_string())).await.unwrap();
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        let summary = tokio::time::timeout(Duration::from_secs(5), session.relay).await
+            .expect("relay must not block on a stalled client")
+            .unwrap();
+        assert_eq!(summary.close_reason, StreamCloseReason::IdleTimeout);
+        assert_eq!(summary.frames_to_client, 1);
+    }
+
+    #[tokio::test]
+    async fn test_stream_idle_timeout_covers_a_silent_client() {
+        let (client, mut client_end) = StreamChannel::pair(8);
+        let (upstream, upstream_end) = StreamChannel::pair(8);
+        let guard = balancer(LoadBalancingStrategy::RoundRobin, &[("a", 1)]).pick().unwrap();
+        let config = StreamingConfig { idle_timeout: Duration::from_millis(50), ..StreamingConfig::default() };
+        let relay = tokio::spawn(relay_stream(client, upstream, config, guard));
+
+        // 上游持续推送，客户端只读不发，客户端方向的空闲仍会关闭连接
+        let pusher = tokio::spawn(async move {
+            for index in 0..100 {
+                if upstream_end.sender.send(StreamFrame::Text(index.to_string())).await.is_err() {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        });
+        let reader = tokio::spawn(async move { while client_end.receiver.recv().await.is_some() {} });
+
+        let summary = tokio::time::timeout(Duration::from_secs(5), relay).await
+            .expect("relay must close a client that never sends")
+            .unwrap();
+        assert_eq!(summary.close_reason, StreamCloseReason::IdleTimeout);
+        assert!(summary.frames_to_client > 0);
+        assert_eq!(summary.frames_to_upstream, 0);
+        reader.await.unwrap();
+        pusher.abort();
+    }
+
+    /// 延迟固定时长后返回 200 的上游
+    struct SlowUpstream {
+        delay: Duration,
+        calls: AtomicU64,
+    }
+
+    #[async_trait::async_trait]
+    impl UpstreamClient for SlowUpstream {
+        async fn send(&self, _request: &Request, _instance: &ServiceInstance) -> Result<Response, GatewayError> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            tokio::time::sleep(self.delay).await;
+            Ok(Response { status_code: 200, headers: HashMap::new(), body: None, processing_time: Duration::ZERO })
+        }
+    }
+
+    fn limited_gateway(delay: Duration, limits: RequestLimits) -> (ApiGatewayManager, Arc<SlowUpstream>) {
+        let upstream = Arc::new(SlowUpstream { delay, calls: AtomicU64::new(0) });
+        let mut gateway = ApiGatewayManager::new().with_upstream(upstream.clone());
+        gateway.load_balancer = balancer(LoadBalancingStrategy::RoundRobin, &[("a", 1)]);
+        gateway.set_request_limits(limits);
+        gateway.add_route(route(HttpMethod::POST, "/modules", "modules")).unwrap();
+        (gateway, upstream)
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_request_leaves_in_flight_gauge() {
+        let (gateway, upstream) = limited_gateway(Duration::from_secs(5), RequestLimits::default());
+        let mut pending = Box::pin(gateway.handle_request(request(HttpMethod::POST, "/modules")));
+        // 推进请求直到它停在上游调用中
+        tokio::select! {
+            _ = &mut pending => panic!("上游延迟期间请求不应完成"),
+            _ = async {
+                while upstream.calls.load(Ordering::SeqCst) == 0 {
+                    tokio::task::yield_now().await;
+                }
+            } => {}
+        }
+        assert_eq!(route_summary(&gateway, "/modules").in_flight, 1);
+        assert_eq!(gateway.load_balancer.in_flight("a"), 1);
+
+        drop(pending);
+        assert_eq!(route_summary(&gateway, "/modules").in_flight, 0);
+        assert_eq!(gateway.load_balancer.in_flight("a"), 0);
+    }
+
+    #[tokio::test]
+    async fn test_read_body_stops_at_the_limit() {
+        let (mut gateway, _) = limited_gateway(Duration::ZERO, RequestLimits { max_body_bytes: 16, ..RequestLimits::default() });
+        gateway.add_route(Route {
+            limits: RouteLimits { max_body_bytes: Some(64), ..RouteLimits::default() },
+            ..route(HttpMethod::PUT, "/modules/{id}", "modules")
+        }).unwrap();
+
+        let mut small = request(HttpMethod::POST, "/modules");
+        gateway.read_body(&mut small, &b"hello"[..]).await.unwrap();
+        assert_eq!(small.body.as_deref(), Some(&b"hello"[..]));
+
+        // 未声明长度的请求体最多读取上限加一个字节
+        let (mut client, server) = tokio::io::duplex(8);
+        let writer = tokio::spawn(async move {
+            use tokio::io::AsyncWriteExt;
+            client.write_all(&[0; 17]).await.unwrap();
+            client
+        });
+        let error = gateway.read_body(&mut request(HttpMethod::POST, "/modules"), server).await.unwrap_err();
+        assert!(matches!(error, GatewayError::PayloadTooLarge(_)));
+        drop(writer.await.unwrap());
+
+        // 声明的长度超限时不读取
+        let mut declared = request(HttpMethod::POST, "/modules");
+        declared.headers.insert("content-length".to_string(), "1048576".to_string());
+        let (_client, server) = tokio::io::duplex(8);
+        assert_eq!(gateway.read_body(&mut declared, server).await.unwrap_err().status_code(), 413);
+
+        // 路由覆盖的上限同样生效
+        let mut upload = request(HttpMethod::PUT, "/modules/7");
+        gateway.read_body(&mut upload, &[0u8; 64][..]).await.unwrap();
+        assert_eq!(upload.body.map(|body| body.len()), Some(64));
+    }
+
+    #[tokio::test]
+    #[allow(deprecated)]
+    async fn test_deprecated_route_timeout_still_applies() {
+        let (mut gateway, _upstream) = limited_gateway(Duration::from_secs(5), RequestLimits::default());
+        gateway.add_route(Route { timeout: Duration::from_millis(20), ..route(HttpMethod::PUT, "/modules/{id}", "modules") })
+            .unwrap();
+        let response = gateway.handle_request(request(HttpMethod::PUT, "/modules/7")).await.unwrap();
+        assert_eq!(response.status_code, 504);
+        assert_eq!(route_summary(&gateway, "/modules/{id}").timeouts, 1);
+
+        let overridden = Route {
+            timeout: Duration::from_millis(20),
+            limits: RouteLimits { upstream_timeout: Some(Duration::from_secs(1)), ..RouteLimits::default() },
+            ..route(HttpMethod::PUT, "/modules/{id}", "modules")
+        };
+        assert_eq!(overridden.limit_overrides().upstream_timeout, Some(Duration::from_secs(1)));
+    }
+
+    fn route_summary(gateway: &ApiGatewayManager, route: &str) -> RouteMetricsSummary {
+        gateway.metrics_summary().routes.into_iter().find(|summary| summary.route == route).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_oversize_body_and_headers_are_rejected_before_forwarding() {
+        let (gateway, upstream) = limited_gateway(Duration::ZERO, RequestLimits {
+            max_body_bytes: 16,
+            max_header_count: 2,
+            ..RequestLimits::default()
+        });
+
+        let mut oversize = request(HttpMethod::POST, "/modules");
+        oversize.body = Some(vec![0; 17]);
+        assert_eq!(gateway.handle_request(oversize).await.unwrap().status_code, 413);
+
+        // 只声明了 Content-Length 的请求体同样按声明的长度拒绝
+        let mut declared = request(HttpMethod::POST, "/modules");
+        declared.headers.insert("content-length".to_string(), "1048576".to_string());
+        assert_eq!(gateway.handle_request(declared).await.unwrap().status_code, 413);
+
+        let mut headers = request(HttpMethod::POST, "/modules");
+        for name in ["a", "b", "c"] {
+            headers.headers.insert(format!("x-{}", name), "1".to_string());
+        }
+        assert_eq!(gateway.handle_request(headers).await.unwrap().status_code, 431);
+
+        let mut within = request(HttpMethod::POST, "/modules");
+        within.body = Some(vec![0; 16]);
+        assert_eq!(gateway.handle_request(within).await.unwrap().status_code, 200);
+        assert_eq!(upstream.calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_malformed_content_length_is_a_bad_request() {
+        let (gateway, upstream) = limited_gateway(Duration::ZERO, RequestLimits { max_body_bytes: 16, ..RequestLimits::default() });
+        let with_length = |length: &str| {
+            let mut request = request(HttpMethod::POST, "/modules");
+            request.headers.insert("content-length".to_string(), length.to_string());
+            request
+        };
+
+        let limits = RequestLimits::default();
+        assert!(matches!(limits.check(&with_length("12abc")), Err(GatewayError::BadRequest(_))));
+        assert!(matches!(limits.check(&with_length("-1")), Err(GatewayError::BadRequest(_))));
+        assert!(matches!(limits.check(&with_length("99999999999999999999999")), Err(GatewayError::PayloadTooLarge(_))));
+        let error = limits.read_body(&mut with_length("twelve"), &b"hello"[..]).await.unwrap_err();
+        assert!(matches!(error, GatewayError::BadRequest(_)));
+
+        assert_eq!(gateway.handle_request(with_length("twelve")).await.unwrap().status_code, 400);
+        assert_eq!(upstream.calls.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn test_slow_upstream_times_out_and_releases_in_flight() {
+        let (gateway, upstream) = limited_gateway(Duration::from_secs(5), RequestLimits {
+            upstream_timeout: Duration::from_millis(20),
+            ..RequestLimits::default()
+        });
+
+        let response = gateway.handle_request(request(HttpMethod::POST, "/modules")).await.unwrap();
+        assert_eq!(response.status_code, 504);
+        assert_eq!(upstream.calls.load(Ordering::SeqCst), 1);
+        assert_eq!(gateway.load_balancer.in_flight("a"), 0);
+        let summary = route_summary(&gateway, "/modules");
+        assert_eq!((summary.in_flight, summary.timeouts), (0, 1));
+        assert_eq!(summary.status_classes.get("5xx"), Some(&1));
+    }
+
+    #[tokio::test]
+    async fn test_route_limits_override_gateway_defaults() {
+        let (mut gateway, _) = limited_gateway(Duration::from_millis(50), RequestLimits {
+            max_body_bytes: 16,
+            upstream_timeout: Duration::from_millis(10),
+            ..RequestLimits::default()
+        });
+        let mut uploads = route(HttpMethod::POST, "/uploads", "modules");
+        uploads.limits = RouteLimits {
+            max_body_bytes: Some(1024),
+            upstream_timeout: Some(Duration::from_secs(1)),
+            ..RouteLimits::default()
+        };
+        gateway.add_route(uploads).unwrap();
+
+        let mut upload = request(HttpMethod::POST, "/uploads");
+        upload.body = Some(vec![0; 512]);
+        assert_eq!(gateway.handle_request(upload.clone()).await.unwrap().status_code, 200);
+
+        upload.path = "/modules".to_string();
+        assert_eq!(gateway.handle_request(upload).await.unwrap().status_code, 413);
+        assert_eq!(gateway.handle_request(request(HttpMethod::POST, "/modules")).await.unwrap().status_code, 504);
+    }
+
+    #[tokio::test]
+    async fn test_request_deadline_is_shared_across_retries() {
+        let (mut gateway, upstream) = limited_gateway(Duration::from_secs(5), RequestLimits {
+            upstream_timeout: Duration::from_millis(30),
+            ..RequestLimits::default()
+        });
+        let mut retried = route(HttpMethod::GET, "/modules/{id}", "modules");
+        retried.retry = Some(RetryPolicy {
+            max_attempts: 10,
+            base_backoff: Duration::from_millis(1),
+            jitter: 0.0,
+            ..RetryPolicy::default()
+        });
+        retried.limits.request_deadline = Some(Duration::from_millis(100));
+        gateway.add_route(retried).unwrap();
+
+        let started = Instant::now();
+        let response = gateway.handle_request(request(HttpMethod::GET, "/modules/7")).await.unwrap();
+        assert_eq!(response.status_code, 504);
+        assert!(started.elapsed() < Duration::from_millis(500));
+        let attempts = upstream.calls.load(Ordering::SeqCst);
+        assert!((2..10).contains(&attempts), "attempts: {}", attempts);
+        assert_eq!(gateway.metrics().deadline_exceeded, 1);
+        assert_eq!(gateway.load_balancer.in_flight("a"), 0);
+        assert_eq!(route_summary(&gateway, "/modules/{id}").timeouts, attempts);
+    }
+
+    #[tokio::test]
+    async fn test_deadline_during_half_open_trial_reopens_circuit() {
+        let clock = ManualClock::new();
+        let (mut gateway, _upstream) = limited_gateway(Duration::from_secs(5), RequestLimits {
+            upstream_timeout: Duration::from_secs(10),
+            request_deadline: Some(Duration::from_millis(50)),
+            ..RequestLimits::default()
+        });
+        gateway.load_balancer.circuit_breaker = circuit_breaker(&clock);
+        for _ in 0..4 {
+            gateway.load_balancer.circuit_breaker.try_acquire("a").unwrap().record(false);
+        }
+        clock.advance(Duration::from_secs(5));
+
+        let response = gateway.handle_request(request(HttpMethod::POST, "/modules")).await.unwrap();
+        assert_eq!(response.status_code, 504);
+        // 被截止时间截断的试探请求计为失败，重新熔断而不是一直占用半开名额
+        assert_eq!(gateway.circuit_states()["a"], CircuitState::Open);
+        assert_eq!(gateway.metrics().deadline_exceeded, 1);
+    }
+
+    fn split(weights: &[(&str, u32, &[&str])]) -> TrafficSplit {
+        TrafficSplit {
+            groups: weights.iter()
+                .map(|(version, weight, instances)| UpstreamGroup {
+                    version: version.to_string(),
+                    weight: *weight,
+                    instances: instances.iter().map(|address| address.to_string()).collect(),
+                })
+                .collect(),
+            sticky: None,
+            override_header: Some("X-Canary".to_string()),
+            canary: Some("v2".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_sticky_hash_is_stable_fnv1a() {
+        assert_eq!(fnv1a(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(fnv1a(b"foobar"), 0x85944171f73967e8);
+    }
+
+    #[tokio::test]
+    async fn test_cached_responses_are_kept_per_version() {
+        let upstream = FlakyUpstream::new(0);
+        let mut gateway = ApiGatewayManager::new().with_upstream(upstream.clone());
+        gateway.load_balancer = balancer(LoadBalancingStrategy::RoundRobin, &[("v1-a", 1), ("v1-b", 1), ("v2-a", 1)]);
+        let mut modules = route(HttpMethod::GET, "/modules/{id}", "modules");
+        modules.split = Some(split(&[("v1", 50, &["v1-a", "v1-b"]), ("v2", 50, &["v2-a"])]));
+        modules.cache_ttl = Some(Duration::from_secs(60));
+        gateway.add_route(modules).unwrap();
+
+        let forced = |version: &str| {
+            let mut request = request(HttpMethod::GET, "/modules/7");
+            request.headers.insert("x-canary".to_string(), version.to_string());
+            request
+        };
+        gateway.handle_request(forced("v1")).await.unwrap();
+        let response = gateway.handle_request(forced("v2")).await.unwrap();
+        assert_ne!(response.headers.get("X-Cache").map(String::as_str), Some("HIT"));
+        assert_eq!(upstream.instances().last().unwrap(), "v2-a");
+        let response = gateway.handle_request(forced("v1")).await.unwrap();
+        assert_eq!(response.headers.get("X-Cache").map(String::as_str), Some("HIT"));
+        assert_eq!(upstream.instances().len(), 2);
+    }
+
+    fn canary_gateway(upstream: Arc<dyn UpstreamClient>, split: TrafficSplit) -> ApiGatewayManager {
+        let mut gateway = ApiGatewayManager::new().with_upstream(upstream);
+        gateway.load_balancer = balancer(LoadBalancingStrategy::RoundRobin, &[("v1-a", 1), ("v1-b", 1), ("v2-a", 1)]);
+        gateway.rate_limiter.default_limit = RateLimit {
+            requests_per_second: 10_000,
+            burst_limit: 10_000,
+            window_size: Duration::from_secs(1),
+        };
+        let mut modules = route(HttpMethod::GET, "/modules/{id}", "modules");
+        modules.split = Some(split);
+        gateway.add_route(modules).unwrap();
+        gateway
+    }
+
+    fn versions_of(upstream: &FlakyUpstream) -> (usize, usize) {
+        let instances = upstream.instances();
+        let v2 = instances.iter().filter(|address| address.starts_with("v2")).count();
+        (instances.len() - v2, v2)
+    }
+
+    #[tokio::test]
+    async fn test_traffic_split_distribution_within_tolerance() {
+        let upstream = FlakyUpstream::new(0);
+        let gateway = canary_gateway(upstream.clone(), split(&[("v1", 95, &["v1-a", "v1-b"]), ("v2", 5, &["v2-a"])]));
+        for _ in 0..4000 {
+            gateway.handle_request(request(HttpMethod::GET, "/modules/7")).await.unwrap();
+        }
+
+        let (v1, v2) = versions_of(&upstream);
+        assert_eq!(v1 + v2, 4000);
+        // 期望 200 次，容差约 4 个标准差
+        assert!((145..=255).contains(&v2), "v2 received {} requests", v2);
+        // 组内仍按负载均衡策略在实例间轮询；轮询位置由各组共用，因此只是大致均分
+        let v1_a = upstream.instances().iter().filter(|address| *address == "v1-a").count();
+        assert!(v1_a.abs_diff(v1 / 2) <= v1 / 10, "v1-a received {} of {}", v1_a, v1);
+
+        let summary = gateway.metrics_summary();
+        let versions = &summary.routes.iter().find(|route| route.route == "/modules/{id}").unwrap().versions;
+        assert_eq!(versions["v2"]["2xx"], v2 as u64);
+        assert_eq!(versions["v1"]["2xx"], v1 as u64);
+    }
+
+    #[tokio::test]
+    async fn test_sticky_split_keeps_client_on_one_version() {
+        let upstream = FlakyUpstream::new(0);
+        let mut sticky = split(&[("v1", 50, &["v1-a", "v1-b"]), ("v2", 50, &["v2-a"])]);
+        sticky.sticky = Some(StickyKey::Header("X-User-Id".to_string()));
+        let gateway = canary_gateway(upstream.clone(), sticky);
+
+        let mut seen = HashSet::new();
+        for user in 0..40 {
+            let mut versions = HashSet::new();
+            for _ in 0..5 {
+                let mut request = request(HttpMethod::GET, "/modules/7");
+                request.headers.insert("x-user-id".to_string(), format!("user-{}", user));
+                gateway.handle_request(request).await.unwrap();
+                versions.insert(upstream.instances().last().unwrap()[..2].to_string());
+            }
+            assert_eq!(versions.len(), 1, "user-{} hit {:?}", user, versions);
+            seen.extend(versions);
+        }
+        assert_eq!(seen.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_canary_header_overrides_weights() {
+        let upstream = FlakyUpstream::new(0);
+        let gateway = canary_gateway(upstream.clone(), split(&[("v1", 100, &["v1-a", "v1-b"]), ("v2", 0, &["v2-a"])]));
+
+        for value in ["always", "v2"] {
+            let mut request = request(HttpMethod::GET, "/modules/7");
+            request.headers.insert("x-canary".to_string(), value.to_string());
+            gateway.handle_request(request).await.unwrap();
+        }
+        // 未知版本按权重分配
+        let mut unknown = request(HttpMethod::GET, "/modules/7");
+        unknown.headers.insert("x-canary".to_string(), "v9".to_string());
+        gateway.handle_request(unknown).await.unwrap();
+        gateway.handle_request(request(HttpMethod::GET, "/modules/7")).await.unwrap();
+        assert_eq!(versions_of(&upstream), (2, 2));
+        assert_eq!(&upstream.instances()[..2], ["v2-a", "v2-a"]);
+    }
+
+    #[test]
+    fn test_degenerate_splits_are_rejected_and_never_panic() {
+        let mut gateway = ApiGatewayManager::new();
+        for degenerate in [split(&[]), split(&[("v1", 0, &["v1-a"]), ("v2", 0, &["v2-a"])])] {
+            let mut modules = route(HttpMethod::GET, "/modules/{id}", "modules");
+            modules.split = Some(degenerate.clone());
+            assert!(matches!(gateway.add_route(modules), Err(GatewayError::ConfigError(_))));
+
+            let chosen = degenerate.choose(&request(HttpMethod::GET, "/modules/7"));
+            assert_eq!(chosen.map(|group| group.version.as_str()), degenerate.groups.first().map(|group| group.version.as_str()));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_set_route_weights_applies_without_dropping_in_flight() {
+        let upstream = Arc::new(SlowUpstream { delay: Duration::from_millis(50), calls: AtomicU64::new(0) });
+        let gateway = canary_gateway(upstream.clone(), split(&[("v1", 100, &["v1-a", "v1-b"]), ("v2", 0, &["v2-a"])]));
+
+        let (in_flight, updated) = tokio::join!(
+            gateway.handle_request(request(HttpMethod::GET, "/modules/7")),
+            async {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                gateway.set_route_weights(HttpMethod::GET, "/modules/{id}", &[("v1", 0), ("v2", 100)])
+            },
+        );
+        updated.unwrap();
+        assert_eq!(in_flight.unwrap().status_code, 200);
+        gateway.handle_request(request(HttpMethod::GET, "/modules/7")).await.unwrap();
+
+        let summary = gateway.metrics_summary();
+        let versions = &summary.routes.iter().find(|route| route.route == "/modules/{id}").unwrap().versions;
+        assert_eq!((versions["v1"]["2xx"], versions["v2"]["2xx"]), (1, 1));
+        assert_eq!(gateway.load_balancer.in_flight("v1-a") + gateway.load_balancer.in_flight("v2-a"), 0);
+
+        // 无效的权重不会生效
+        assert!(gateway.set_route_weights(HttpMethod::GET, "/modules/{id}", &[("v2", 0)]).is_err());
+        assert!(gateway.set_route_weights(HttpMethod::GET, "/modules/{id}", &[("v3", 10)]).is_err());
+        assert!(gateway.set_route_weights(HttpMethod::GET, "/missing", &[("v1", 10)]).is_err());
+        let routes = gateway.routes.lock().unwrap();
+        let weights: Vec<u32> = routes["GET:/modules/{id}"].split.as_ref().unwrap().groups.iter().map(|group| group.weight).collect();
+        assert_eq!(weights, [0, 100]);
+    }
+}
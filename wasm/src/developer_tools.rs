@@ -10,10 +10,15 @@
 use crate::types::*;
 use crate::webassembly_2_0::*;
 use crate::security_advanced::*;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 use std::path::{Path, PathBuf};
 use std::fs;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread::JoinHandle;
 use std::time::{Duration, Instant};
 use thiserror::Error;
 
@@ -102,6 +107,8 @@ pub struct CodeGenerator {
     pub template_engine: TemplateEngine,
     /// 代码风格配置
     pub code_style: CodeStyle,
+    /// 生成模式
+    pub generation_mode: GenerationMode,
 }
 
 impl Default for CodeGenerator {
@@ -118,6 +125,7 @@ impl CodeGenerator {
             output_directory: PathBuf::from("generated"),
             template_engine: TemplateEngine::new(),
             code_style: CodeStyle::default(),
+            generation_mode: GenerationMode::default(),
         }
     }
 
@@ -130,6 +138,37 @@ impl CodeGenerator {
         Ok(())
     }
 
+    /// 按生成模式输出生成的代码，写入磁盘时记录最终文件路径
+    /// Emit generated code according to the generation mode, recording the final path when written to disk
+    fn emit(&self, mut generated_code: GeneratedCode) -> Result<GeneratedCode, DeveloperToolsError> {
+        let GenerationMode::WriteToDisk { overwrite } = &self.generation_mode else {
+            return Ok(generated_code);
+        };
+
+        let file_path = self.output_directory.join(&generated_code.file_name);
+        if file_path.exists() {
+            match overwrite {
+                OverwritePolicy::Overwrite => {}
+                OverwritePolicy::Error => return Err(DeveloperToolsError::FileExists(file_path)),
+                OverwritePolicy::Backup => {
+                    fs::rename(&file_path, backup_path(&file_path))
+                        .map_err(|e| DeveloperToolsError::FileSystemError(e.to_string()))?;
+                }
+            }
+        }
+
+        fs::write(&file_path, &generated_code.content)
+            .map_err(|e| DeveloperToolsError::FileSystemError(e.to_string()))?;
+        generated_code.file_path = Some(file_path);
+        Ok(generated_code)
+    }
+
+    /// 按 `code_style` 格式化生成的代码
+    /// Format generated code according to `code_style`
+    pub fn format_code(&self, code: &str, language: &ProgrammingLanguage) -> String {
+        CodeFormatter::new(&self.code_style, language).format(code)
+    }
+
     /// 生成 WebAssembly 模块代码
     /// Generate WebAssembly module code
     pub fn generate_wasm_module(&self, spec: ModuleSpecification) -> Result<GeneratedCode, DeveloperToolsError> {
@@ -138,37 +177,129 @@ impl CodeGenerator {
         
         let generated_code = GeneratedCode {
             file_name: format!("{}.rs", spec.name),
-            content: code,
+            content: self.format_code(&code, &ProgrammingLanguage::Rust),
             language: ProgrammingLanguage::Rust,
             module_type: ModuleType::WebAssembly,
+            file_path: None,
         };
 
         // 保存生成的文件
-        let file_path = self.output_directory.join(&generated_code.file_name);
-        fs::write(&file_path, &generated_code.content)
-            .map_err(|e| DeveloperToolsError::FileSystemError(e.to_string()))?;
-
-        Ok(generated_code)
+        self.emit(generated_code)
     }
 
     /// 生成绑定代码
     /// Generate binding code
     pub fn generate_bindings(&self, spec: BindingSpecification) -> Result<GeneratedCode, DeveloperToolsError> {
-        let template = self.template_engine.get_template("bindings")?;
-        let code = self.template_engine.render_template(template, &spec)?;
-        
-        let generated_code = GeneratedCode {
-            file_name: format!("{}_bindings.rs", spec.module_name),
-            content: code,
-            language: ProgrammingLanguage::Rust,
-            module_type: ModuleType::Bindings,
+        let generated_code = match spec.binding_type {
+            BindingType::Python => GeneratedCode {
+                file_name: format!("{}_bindings.py", spec.module_name),
+                content: self.format_code(&self.generate_python_bindings(&spec), &ProgrammingLanguage::Python),
+                language: ProgrammingLanguage::Python,
+                module_type: ModuleType::Bindings,
+                file_path: None,
+            },
+            _ => {
+                let template = self.template_engine.get_template("bindings")?;
+                GeneratedCode {
+                    file_name: format!("{}_bindings.rs", spec.module_name),
+                    content: self.format_code(&self.template_engine.render_template(template, &spec)?, &ProgrammingLanguage::Rust),
+                    language: ProgrammingLanguage::Rust,
+                    module_type: ModuleType::Bindings,
+                    file_path: None,
+                }
+            }
         };
 
-        let file_path = self.output_directory.join(&generated_code.file_name);
-        fs::write(&file_path, &generated_code.content)
-            .map_err(|e| DeveloperToolsError::FileSystemError(e.to_string()))?;
+        self.emit(generated_code)
+    }
 
-        Ok(generated_code)
+    /// 生成基于 wasmtime Python 包的绑定代码
+    /// Generate Python bindings using the wasmtime package
+    fn generate_python_bindings(&self, spec: &BindingSpecification) -> String {
+        let class_name = python_class_name(&spec.module_name);
+        let memories: Vec<&ExportSpecification> = spec.exports.iter()
+            .filter(|export| matches!(export.export_type, ExportTypeSpecification::Memory))
+            .collect();
+
+        let mut code = String::new();
+        code.push_str("# 自动生成的 Python 绑定\n");
+        code.push_str(&format!("# 模块: {}\n\n", spec.module_name));
+        if spec.functions.iter().any(|function| function.multi_value) {
+            code.push_str("from typing import Tuple\n\n");
+        }
+        code.push_str("from wasmtime import Engine, Instance, Module, Store\n\n\n");
+
+        code.push_str(&format!("class {}:\n", class_name));
+        code.push_str(&format!("    {}\n\n", python_docstring(&format!("{} WebAssembly 模块绑定", spec.module_name))));
+        code.push_str("    def __init__(self, store: Store, instance: Instance) -> None:\n");
+        code.push_str("        self._store = store\n");
+        code.push_str("        self._instance = instance\n");
+        code.push_str("        self._exports = instance.exports(store)\n\n");
+
+        code.push_str("    @classmethod\n");
+        code.push_str(&format!("    def load(cls, path: str) -> \"{}\":\n", class_name));
+        code.push_str(&format!("        {}\n", python_docstring("从 .wasm 文件加载模块并实例化")));
+        code.push_str("        engine = Engine()\n");
+        code.push_str("        store = Store(engine)\n");
+        code.push_str("        module = Module.from_file(engine, path)\n");
+        code.push_str("        instance = Instance(store, module, [])\n");
+        code.push_str("        return cls(store, instance)\n");
+
+        for function in &spec.functions {
+            let method_name = python_identifier(&function.name);
+            let params: Vec<(String, &ParameterSpecification)> = function.parameters.iter()
+                .map(|param| (python_identifier(&param.name), param))
+                .collect();
+            let signature: String = params.iter()
+                .map(|(name, param)| format!(", {}: {}", name, python_type(&param.parameter_type)))
+                .collect();
+            let results = function.result_types();
+            let return_annotation = match results.as_slice() {
+                [] => "None".to_string(),
+                [single] if !function.multi_value => python_type(single).to_string(),
+                many => format!("Tuple[{}]", many.iter().map(python_type).collect::<Vec<_>>().join(", ")),
+            };
+
+            let mut doc = function.description.clone();
+            if !params.is_empty() {
+                doc.push('\n');
+                for (name, param) in &params {
+                    doc.push_str(&format!("\n:param {}: {}", name, param.description));
+                }
+            }
+
+            code.push('\n');
+            code.push_str(&format!("    def {}(self{}) -> {}:\n", method_name, signature, return_annotation));
+            code.push_str(&format!("        {}\n", python_docstring(&doc).replace('\n', "\n        ").replace("        \n", "\n")));
+            let args: String = params.iter()
+                .map(|(name, param)| format!(", {}", python_conversion(&param.parameter_type, name)))
+                .collect();
+            let call = format!("self._exports[\"{}\"](self._store{})", function.name, args);
+            match results.len() {
+                0 => code.push_str(&format!("        {}\n", call)),
+                1 if !function.multi_value => code.push_str(&format!("        return {}\n", call)),
+                _ => code.push_str(&format!("        return tuple({})\n", call)),
+            }
+        }
+
+        if let Some(default_memory) = memories.first() {
+            let default_memory = &default_memory.name;
+            code.push('\n');
+            code.push_str(&format!(
+                "    def read_bytes(self, offset: int, length: int, memory: str = \"{}\") -> bytes:\n",
+                default_memory,
+            ));
+            code.push_str(&format!("        {}\n", python_docstring("从导出内存读取字节")));
+            code.push_str("        return bytes(self._exports[memory].read(self._store, offset, offset + length))\n\n");
+            code.push_str(&format!(
+                "    def write_bytes(self, offset: int, data: bytes, memory: str = \"{}\") -> None:\n",
+                default_memory,
+            ));
+            code.push_str(&format!("        {}\n", python_docstring("向导出内存写入字节")));
+            code.push_str("        self._exports[memory].write(self._store, data, offset)\n");
+        }
+
+        code
     }
 
     /// 生成测试代码
@@ -179,19 +310,257 @@ impl CodeGenerator {
         
         let generated_code = GeneratedCode {
             file_name: format!("{}_tests.rs", spec.module_name),
-            content: code,
+            content: self.format_code(&code, &ProgrammingLanguage::Rust),
             language: ProgrammingLanguage::Rust,
             module_type: ModuleType::Tests,
+            file_path: None,
         };
 
-        let file_path = self.output_directory.join(&generated_code.file_name);
-        fs::write(&file_path, &generated_code.content)
-            .map_err(|e| DeveloperToolsError::FileSystemError(e.to_string()))?;
+        self.emit(generated_code)
+    }
 
-        Ok(generated_code)
+    /// 生成 TypeScript 声明文件
+    /// Generate TypeScript declaration file
+    pub fn generate_typescript_defs(&self, spec: &ModuleSpecification) -> Result<GeneratedCode, DeveloperToolsError> {
+        let mut code = String::new();
+        code.push_str("// 自动生成的 TypeScript 声明文件\n");
+        code.push_str(&format!("// 模块: {}\n", spec.name));
+        if !spec.description.is_empty() {
+            code.push_str(&format!("// 描述: {}\n", spec.description));
+        }
+
+        for function in &spec.functions {
+            code.push('\n');
+            code.push_str("/**\n");
+            if !function.description.is_empty() {
+                code.push_str(&format!(" * {}\n", function.description));
+            }
+            for param in &function.parameters {
+                code.push_str(&format!(" * @param {} {}\n", escape_typescript_identifier(&param.name), param.description));
+            }
+            code.push_str(" */\n");
+
+            let params: Vec<String> = function.parameters.iter()
+                .map(|param| format!(
+                    "{}{}: {}",
+                    escape_typescript_identifier(&param.name),
+                    if param.required { "" } else { "?" },
+                    typescript_type(&param.parameter_type),
+                ))
+                .collect();
+            let results = function.result_types();
+            let return_type = match results.as_slice() {
+                [] => "void".to_string(),
+                [single] if !function.multi_value => typescript_type(single).to_string(),
+                many => format!("[{}]", many.iter().map(typescript_type).collect::<Vec<_>>().join(", ")),
+            };
+            code.push_str(&format!(
+                "export function {}({}): {};\n",
+                escape_typescript_identifier(&function.name),
+                params.join(", "),
+                return_type,
+            ));
+        }
+
+        if !spec.exports.is_empty() {
+            code.push('\n');
+        }
+        for export in &spec.exports {
+            let name = escape_typescript_identifier(&export.name);
+            match &export.export_type {
+                ExportTypeSpecification::Function(target) => {
+                    // 与函数同名的导出已经由函数签名覆盖
+                    if target != &export.name && spec.functions.iter().any(|f| &f.name == target) {
+                        code.push_str(&format!(
+                            "export const {}: typeof {};\n",
+                            name,
+                            escape_typescript_identifier(target),
+                        ));
+                    }
+                }
+                ExportTypeSpecification::Memory => {
+                    code.push_str(&format!("export const {}: WebAssembly.Memory;\n", name));
+                }
+                ExportTypeSpecification::Table => {
+                    code.push_str(&format!("export const {}: WebAssembly.Table;\n", name));
+                }
+                ExportTypeSpecification::Global(_) => {
+                    code.push_str(&format!("export const {}: WebAssembly.Global;\n", name));
+                }
+            }
+        }
+
+        let generated_code = GeneratedCode {
+            file_name: format!("{}.d.ts", spec.name),
+            content: self.format_code(&code, &ProgrammingLanguage::TypeScript),
+            language: ProgrammingLanguage::TypeScript,
+            module_type: ModuleType::Bindings,
+            file_path: None,
+        };
+
+        self.emit(generated_code)
     }
 }
 
+/// 生成备份文件路径：在原文件名后追加时间戳，必要时再追加序号避免冲突
+/// Backup path: the original file name plus a timestamp suffix, with a counter on collision
+fn backup_path(file_path: &Path) -> PathBuf {
+    let timestamp = chrono::Local::now().format("%Y%m%d%H%M%S%3f");
+    let file_name = file_path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+    let mut candidate = file_path.with_file_name(format!("{}.{}.bak", file_name, timestamp));
+    let mut counter = 1;
+    while candidate.exists() {
+        candidate = file_path.with_file_name(format!("{}.{}-{}.bak", file_name, timestamp, counter));
+        counter += 1;
+    }
+    candidate
+}
+
+/// 代码生成模式
+/// Code generation mode
+#[derive(Debug, Clone)]
+pub enum GenerationMode {
+    /// 写入输出目录
+    WriteToDisk {
+        /// 目标文件已存在时的处理策略
+        overwrite: OverwritePolicy,
+    },
+    /// 仅在内存中返回生成结果，不访问文件系统
+    InMemory,
+}
+
+impl Default for GenerationMode {
+    fn default() -> Self {
+        Self::WriteToDisk { overwrite: OverwritePolicy::Overwrite }
+    }
+}
+
+/// 目标文件已存在时的覆盖策略
+/// Policy for existing target files
+#[derive(Debug, Clone, PartialEq)]
+pub enum OverwritePolicy {
+    /// 直接覆盖
+    Overwrite,
+    /// 返回 `FileExists` 错误
+    Error,
+    /// 将旧文件重命名为带时间戳后缀的备份
+    Backup,
+}
+
+/// Python 关键字
+/// Python keywords
+const PYTHON_KEYWORDS: &[&str] = &[
+    "False", "None", "True", "and", "as", "assert", "async", "await", "break", "class",
+    "continue", "def", "del", "elif", "else", "except", "finally", "for", "from", "global",
+    "if", "import", "in", "is", "lambda", "nonlocal", "not", "or", "pass", "raise", "return",
+    "try", "while", "with", "yield", "self",
+];
+
+/// 将 WebAssembly 值类型映射为 Python 类型注解
+/// Map a WebAssembly value type to a Python type annotation
+fn python_type(value_type: &ValueType) -> &'static str {
+    match value_type {
+        ValueType::I32 | ValueType::I64 | ValueType::I128 | ValueType::U128 => "int",
+        ValueType::F32 | ValueType::F64 => "float",
+        ValueType::V128 => "bytes",
+        ValueType::FuncRef | ValueType::ExternRef => "object",
+    }
+}
+
+/// 生成参数类型转换表达式
+/// Generate the parameter conversion expression
+fn python_conversion(value_type: &ValueType, name: &str) -> String {
+    match value_type {
+        ValueType::I32 | ValueType::I64 | ValueType::I128 | ValueType::U128 => format!("int({})", name),
+        ValueType::F32 | ValueType::F64 => format!("float({})", name),
+        _ => name.to_string(),
+    }
+}
+
+/// 转义 Python 标识符
+/// Escape a Python identifier
+fn python_identifier(name: &str) -> String {
+    let mut identifier: String = name.chars()
+        .map(|c| if c.is_alphanumeric() || c == '_' { c } else { '_' })
+        .collect();
+    if identifier.is_empty() || identifier.starts_with(|c: char| c.is_ascii_digit()) {
+        identifier.insert(0, '_');
+    }
+    if PYTHON_KEYWORDS.contains(&identifier.as_str()) {
+        identifier.push('_');
+    }
+    identifier
+}
+
+/// 由模块名生成 Python 类名（CamelCase）
+/// Derive a CamelCase Python class name from the module name
+fn python_class_name(module_name: &str) -> String {
+    let name: String = python_identifier(module_name)
+        .split('_')
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            chars.next()
+                .map(|first| first.to_uppercase().chain(chars).collect::<String>())
+                .unwrap_or_default()
+        })
+        .collect();
+    if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("Module{}", name)
+    } else {
+        name
+    }
+}
+
+/// 生成 Python 文档字符串
+/// Build a Python docstring literal
+fn python_docstring(text: &str) -> String {
+    let escaped = text.replace('\\', "\\\\").replace("\"\"\"", "\\\"\\\"\\\"");
+    if escaped.contains('\n') {
+        format!("\"\"\"{}\n\"\"\"", escaped)
+    } else {
+        format!("\"\"\"{}\"\"\"", escaped)
+    }
+}
+
+/// TypeScript 保留字
+/// TypeScript reserved words
+const TYPESCRIPT_RESERVED_WORDS: &[&str] = &[
+    "break", "case", "catch", "class", "const", "continue", "debugger", "default", "delete",
+    "do", "else", "enum", "export", "extends", "false", "finally", "for", "function", "if",
+    "import", "in", "instanceof", "new", "null", "return", "super", "switch", "this", "throw",
+    "true", "try", "typeof", "var", "void", "while", "with", "implements", "interface", "let",
+    "package", "private", "protected", "public", "static", "yield", "await", "any", "boolean",
+    "number", "string", "symbol", "type", "bigint", "unknown", "never", "object",
+];
+
+/// 将 WebAssembly 值类型映射为 TypeScript 类型
+/// Map a WebAssembly value type to a TypeScript type
+fn typescript_type(value_type: &ValueType) -> &'static str {
+    match value_type {
+        ValueType::I32 | ValueType::F32 | ValueType::F64 => "number",
+        ValueType::I64 | ValueType::I128 | ValueType::U128 => "bigint",
+        ValueType::V128 => "Uint8Array",
+        ValueType::FuncRef => "Function | null",
+        ValueType::ExternRef => "unknown",
+    }
+}
+
+/// 转义 TypeScript 标识符（非法字符替换为下划线，保留字追加下划线）
+/// Escape a TypeScript identifier
+fn escape_typescript_identifier(name: &str) -> String {
+    let mut identifier: String = name.chars()
+        .map(|c| if c.is_alphanumeric() || c == '_' || c == '$' { c } else { '_' })
+        .collect();
+    if identifier.is_empty() || identifier.starts_with(|c: char| c.is_ascii_digit()) {
+        identifier.insert(0, '_');
+    }
+    if TYPESCRIPT_RESERVED_WORDS.contains(&identifier.as_str()) {
+        identifier.push('_');
+    }
+    identifier
+}
+
 /// 模块规范
 /// Module Specification
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -226,10 +595,25 @@ pub struct FunctionSpecification {
     pub return_type: Option<ValueType>,
     /// 是否支持多值返回
     pub multi_value: bool,
+    /// 多值返回时 `return_type` 之后的其余返回值类型
+    #[serde(default)]
+    pub additional_results: Vec<ValueType>,
     /// 是否支持尾调用
     pub tail_call: bool,
 }
 
+impl FunctionSpecification {
+    /// 获取全部返回值类型
+    /// Get all result types
+    pub fn result_types(&self) -> Vec<ValueType> {
+        let mut results: Vec<ValueType> = self.return_type.iter().cloned().collect();
+        if self.multi_value {
+            results.extend(self.additional_results.iter().cloned());
+        }
+        results
+    }
+}
+
 /// 参数规范
 /// Parameter Specification
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -356,6 +740,9 @@ pub struct BindingSpecification {
     pub target_language: ProgrammingLanguage,
     /// 函数列表
     pub functions: Vec<FunctionSpecification>,
+    /// 导出列表（用于生成内存读写辅助方法）
+    #[serde(default)]
+    pub exports: Vec<ExportSpecification>,
 }
 
 /// 绑定类型
@@ -406,12 +793,36 @@ pub struct TestCaseSpecification {
     pub name: String,
     /// 测试描述
     pub description: String,
+    /// 被测函数的导出名称（未导出时按函数名查找）
+    #[serde(default)]
+    pub export_name: String,
     /// 输入参数
     pub inputs: Vec<Value>,
-    /// 期望输出
-    pub expected_output: Option<Value>,
+    /// 期望输出（全部返回值）
+    pub expected_output: Option<Vec<Value>>,
     /// 测试类型
     pub test_case_type: TestCaseType,
+    /// `Error` 类型用例期望的陷阱，陷阱信息必须包含该文本（例如 `"整数除零"`）
+    #[serde(default)]
+    pub expected_error: Option<String>,
+    /// 快照名称，设置后将实际输出与快照文件比较
+    #[serde(default)]
+    pub snapshot_name: Option<String>,
+    /// 需要一并写入快照的内存范围
+    #[serde(default)]
+    pub snapshot_memory: Option<SnapshotMemoryRange>,
+}
+
+/// 快照中记录的内存范围
+/// Memory range recorded in a snapshot
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotMemoryRange {
+    /// 内存索引
+    pub memory_index: u32,
+    /// 起始偏移
+    pub offset: u32,
+    /// 字节数
+    pub length: u32,
 }
 
 /// 测试用例类型
@@ -440,6 +851,8 @@ pub struct GeneratedCode {
     pub language: ProgrammingLanguage,
     /// 模块类型
     pub module_type: ModuleType,
+    /// 写入磁盘后的文件路径（内存模式下为空）
+    pub file_path: Option<PathBuf>,
 }
 
 /// 编程语言
@@ -526,21 +939,250 @@ impl TemplateEngine {
 
     /// 渲染模板
     /// Render template
+    ///
+    /// 支持 `{{field.path}}` 变量查找、`{{#each list}}...{{/each}}` 循环
+    /// 以及 `{{#if field}}...{{else}}...{{/if}}` 条件；独占一行的块标签不会输出空行。
+    /// Supports `{{field.path}}` lookups, `{{#each list}}` loops and
+    /// `{{#if field}}` conditionals over the serialized data.
     pub fn render_template<T: Serialize>(&self, template: &str, data: &T) -> Result<String, DeveloperToolsError> {
-        // 简单的模板渲染实现
-        // 实际应用中应该使用更强大的模板引擎如 Handlebars
-        let template_str = template.to_owned();
-        let data_json = serde_json::to_string(data)
+        let data_json = serde_json::to_value(data)
             .map_err(|e| DeveloperToolsError::SerializationError(e.to_string()))?;
-        
-        // 简单的占位符替换
-        let rendered = template_str
-            .replace("{{MODULE_NAME}}", "example_module")
-            .replace("{{MODULE_DESCRIPTION}}", "Generated WebAssembly module")
-            .replace("{{MODULE_DATA}}", &data_json);
-        
-        Ok(rendered)
+
+        let nodes = parse_template(template)?;
+        let mut output = String::with_capacity(template.len());
+        render_nodes(&nodes, &[TemplateScope::root(&data_json)], &mut output)?;
+
+        Ok(output)
+    }
+}
+
+/// 模板语法节点
+/// Template syntax node
+#[derive(Debug, Clone, PartialEq)]
+enum TemplateNode {
+    /// 原样输出的文本
+    Text(String),
+    /// 变量占位符
+    Variable(String),
+    /// 循环块
+    Each(String, Vec<TemplateNode>),
+    /// 条件块（真分支、假分支）
+    If(String, Vec<TemplateNode>, Vec<TemplateNode>),
+}
+
+/// 渲染作用域
+/// Render scope
+struct TemplateScope<'a> {
+    /// 当前数据
+    value: &'a serde_json::Value,
+    /// 循环下标
+    index: Option<usize>,
+}
+
+impl<'a> TemplateScope<'a> {
+    fn root(value: &'a serde_json::Value) -> Self {
+        Self { value, index: None }
+    }
+}
+
+/// 尚未闭合的模板块
+/// Template block that has not been closed yet
+struct OpenTemplateBlock {
+    /// 块类型（each / if）
+    kind: String,
+    /// 块参数
+    argument: String,
+    /// 真分支（或循环体）
+    then_branch: Vec<TemplateNode>,
+    /// 假分支
+    else_branch: Vec<TemplateNode>,
+    /// 是否已进入 else 分支
+    in_else: bool,
+}
+
+impl OpenTemplateBlock {
+    fn current_branch(&mut self) -> &mut Vec<TemplateNode> {
+        if self.in_else { &mut self.else_branch } else { &mut self.then_branch }
+    }
+}
+
+/// 将模板解析为语法树
+/// Parse template into syntax tree
+fn parse_template(template: &str) -> Result<Vec<TemplateNode>, DeveloperToolsError> {
+    let mut stack: Vec<OpenTemplateBlock> = Vec::new();
+    let mut root = Vec::new();
+    let mut rest = template;
+
+    while let Some(start) = rest.find("{{") {
+        let end = rest[start..].find("}}")
+            .map(|offset| start + offset)
+            .ok_or_else(|| DeveloperToolsError::TemplateRenderError("未闭合的占位符 `{{`".to_string()))?;
+        let tag = rest[start + 2..end].trim();
+        let mut text = &rest[..start];
+        let mut after = &rest[end + 2..];
+
+        let is_block_tag = tag.starts_with('#') || tag.starts_with('/') || tag == "else";
+        if is_block_tag {
+            // 独占一行的块标签连同其所在行一起移除
+            let line_start = text.rfind('\n').map(|i| i + 1).unwrap_or(0);
+            let line_end = after.find('\n');
+            let leading_blank = text[line_start..].trim().is_empty();
+            let trailing_blank = after[..line_end.unwrap_or(after.len())].trim().is_empty();
+            if leading_blank && trailing_blank {
+                text = &text[..line_start];
+                after = line_end.map(|i| &after[i + 1..]).unwrap_or("");
+            }
+        }
+
+        let branch = match stack.last_mut() {
+            Some(block) => block.current_branch(),
+            None => &mut root,
+        };
+        if !text.is_empty() {
+            branch.push(TemplateNode::Text(text.to_string()));
+        }
+
+        if let Some(block) = tag.strip_prefix('#') {
+            let (kind, argument) = block.split_once(char::is_whitespace)
+                .map(|(kind, argument)| (kind, argument.trim()))
+                .unwrap_or((block, ""));
+            if kind != "each" && kind != "if" {
+                return Err(DeveloperToolsError::TemplateRenderError(format!("不支持的块标签: #{}", kind)));
+            }
+            if argument.is_empty() {
+                return Err(DeveloperToolsError::TemplateRenderError(format!("块标签 #{} 缺少参数", kind)));
+            }
+            stack.push(OpenTemplateBlock {
+                kind: kind.to_string(),
+                argument: argument.to_string(),
+                then_branch: Vec::new(),
+                else_branch: Vec::new(),
+                in_else: false,
+            });
+        } else if let Some(kind) = tag.strip_prefix('/') {
+            let kind = kind.trim();
+            let block = stack.pop()
+                .ok_or_else(|| DeveloperToolsError::TemplateRenderError(format!("多余的结束标签: /{}", kind)))?;
+            if block.kind != kind {
+                return Err(DeveloperToolsError::TemplateRenderError(
+                    format!("结束标签 /{} 与 #{} 不匹配", kind, block.kind),
+                ));
+            }
+            let node = if block.kind == "each" {
+                TemplateNode::Each(block.argument, block.then_branch)
+            } else {
+                TemplateNode::If(block.argument, block.then_branch, block.else_branch)
+            };
+            match stack.last_mut() {
+                Some(parent) => parent.current_branch().push(node),
+                None => root.push(node),
+            }
+        } else if tag == "else" {
+            match stack.last_mut() {
+                Some(block) if block.kind == "if" && !block.in_else => block.in_else = true,
+                _ => return Err(DeveloperToolsError::TemplateRenderError("{{else}} 不在 #if 块中".to_string())),
+            }
+        } else {
+            branch.push(TemplateNode::Variable(tag.to_string()));
+        }
+
+        rest = after;
+    }
+
+    if let Some(block) = stack.last() {
+        return Err(DeveloperToolsError::TemplateRenderError(format!("块 #{} {} 未闭合", block.kind, block.argument)));
+    }
+    if !rest.is_empty() {
+        root.push(TemplateNode::Text(rest.to_string()));
+    }
+
+    Ok(root)
+}
+
+/// 在作用域链中查找变量，由内向外
+/// Look up a variable through the scope chain, innermost first
+fn lookup_template_value(path: &str, scopes: &[TemplateScope<'_>]) -> Result<serde_json::Value, DeveloperToolsError> {
+    let innermost = scopes.last()
+        .ok_or_else(|| DeveloperToolsError::TemplateRenderError(path.to_string()))?;
+    if path == "this" || path == "." {
+        return Ok(innermost.value.clone());
+    }
+    if path == "@index" {
+        return innermost.index
+            .map(serde_json::Value::from)
+            .ok_or_else(|| DeveloperToolsError::TemplateRenderError("@index 只能在 #each 中使用".to_string()));
+    }
+
+    let path = path.strip_prefix("this.").unwrap_or(path);
+    for scope in scopes.iter().rev() {
+        let found = path.split('.').try_fold(scope.value, |value, segment| match value {
+            serde_json::Value::Object(map) => map.get(segment),
+            serde_json::Value::Array(items) => segment.parse::<usize>().ok().and_then(|i| items.get(i)),
+            _ => None,
+        });
+        if let Some(value) = found {
+            return Ok(value.clone());
+        }
+    }
+
+    Err(DeveloperToolsError::TemplateRenderError(format!("未知的模板变量: {}", path)))
+}
+
+/// 判断模板值是否为真
+/// Whether a template value is truthy
+fn template_value_is_truthy(value: &serde_json::Value) -> bool {
+    match value {
+        serde_json::Value::Null => false,
+        serde_json::Value::Bool(b) => *b,
+        serde_json::Value::Number(n) => n.as_f64().is_some_and(|n| n != 0.0),
+        serde_json::Value::String(s) => !s.is_empty(),
+        serde_json::Value::Array(items) => !items.is_empty(),
+        serde_json::Value::Object(_) => true,
+    }
+}
+
+/// 渲染语法树
+/// Render syntax tree
+fn render_nodes(
+    nodes: &[TemplateNode],
+    scopes: &[TemplateScope<'_>],
+    output: &mut String,
+) -> Result<(), DeveloperToolsError> {
+    for node in nodes {
+        match node {
+            TemplateNode::Text(text) => output.push_str(text),
+            TemplateNode::Variable(path) => match lookup_template_value(path, scopes)? {
+                serde_json::Value::Null => {}
+                serde_json::Value::String(s) => output.push_str(&s),
+                other => output.push_str(&other.to_string()),
+            },
+            TemplateNode::If(path, then_branch, else_branch) => {
+                let value = lookup_template_value(path, scopes)?;
+                let branch = if template_value_is_truthy(&value) { then_branch } else { else_branch };
+                render_nodes(branch, scopes, output)?;
+            }
+            TemplateNode::Each(path, body) => {
+                let list = lookup_template_value(path, scopes)?;
+                let items = match &list {
+                    serde_json::Value::Array(items) => items.as_slice(),
+                    serde_json::Value::Null => &[],
+                    _ => {
+                        return Err(DeveloperToolsError::TemplateRenderError(
+                            format!("#each 的参数不是列表: {}", path),
+                        ));
+                    }
+                };
+                for (index, item) in items.iter().enumerate() {
+                    let mut inner: Vec<TemplateScope<'_>> = scopes.iter()
+                        .map(|scope| TemplateScope { value: scope.value, index: scope.index })
+                        .collect();
+                    inner.push(TemplateScope { value: item, index: Some(index) });
+                    render_nodes(body, &inner, output)?;
+                }
+            }
+        }
     }
+    Ok(())
 }
 
 /// 代码风格
@@ -571,6 +1213,380 @@ impl Default for CodeStyle {
     }
 }
 
+/// 源码片段类型
+/// Source segment kind
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SegmentKind {
+    /// 代码
+    Code,
+    /// 字符串字面量（含引号）
+    Str,
+    /// 注释
+    Comment,
+}
+
+/// 跨行词法状态
+/// Lexer state carried across lines
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum LexState {
+    /// 处于代码中
+    Code,
+    /// 处于字符串中（结束定界符，是否为原始字符串）
+    Str { close: String, raw: bool },
+    /// 处于块注释中
+    BlockComment,
+}
+
+/// 代码格式化器：按 `CodeStyle` 重新缩进、折行并调整引号与尾随逗号，不改动字符串字面量
+/// Code formatter applying `CodeStyle` without touching string literals
+struct CodeFormatter<'a> {
+    style: &'a CodeStyle,
+    language: &'a ProgrammingLanguage,
+}
+
+/// 生成器输出使用的缩进单位（空格数）
+const SOURCE_INDENT: usize = 4;
+
+impl<'a> CodeFormatter<'a> {
+    fn new(style: &'a CodeStyle, language: &'a ProgrammingLanguage) -> Self {
+        Self { style, language }
+    }
+
+    fn supported(&self) -> bool {
+        matches!(
+            self.language,
+            ProgrammingLanguage::Rust | ProgrammingLanguage::TypeScript
+                | ProgrammingLanguage::JavaScript | ProgrammingLanguage::Python
+        )
+    }
+
+    fn indent_unit(&self) -> String {
+        if self.style.use_tabs { "\t".to_string() } else { " ".repeat(self.style.indent_size) }
+    }
+
+    fn display_width(&self, line: &str) -> usize {
+        line.chars().map(|c| if c == '\t' { self.style.indent_size } else { 1 }).sum()
+    }
+
+    fn format(&self, code: &str) -> String {
+        if !self.supported() {
+            return code.to_string();
+        }
+
+        let mut state = LexState::Code;
+        let mut output: Vec<String> = Vec::new();
+        // 以行尾开括号开始的多行列表，记录其中是否出现过逗号
+        let mut open_lists: Vec<bool> = Vec::new();
+
+        for line in code.lines() {
+            if state != LexState::Code {
+                // 行首处于多行字符串或块注释中，原样保留
+                self.lex_line(line, &mut state);
+                output.push(line.to_string());
+                continue;
+            }
+            if line.trim().is_empty() {
+                output.push(String::new());
+                continue;
+            }
+
+            let content = line.trim_start();
+            let leading = &line[..line.len() - content.len()];
+            let columns: usize = leading.chars().map(|c| if c == '\t' { SOURCE_INDENT } else { 1 }).sum();
+            let indent = format!(
+                "{}{}",
+                self.indent_unit().repeat(columns / SOURCE_INDENT),
+                " ".repeat(columns % SOURCE_INDENT),
+            );
+
+            let mut segments = self.lex_line(content, &mut state);
+            self.convert_quotes(&mut segments);
+            let content: String = segments.iter().map(|(_, text)| text.as_str()).collect();
+
+            let code_only: String = segments.iter()
+                .filter(|(kind, _)| *kind == SegmentKind::Code)
+                .map(|(_, text)| text.as_str())
+                .collect();
+            let first_code = code_only.trim_start().chars().next();
+            let last_code = code_only.trim_end().chars().last();
+
+            // 多行列表结束：根据风格设置补充或移除尾随逗号
+            if matches!(first_code, Some(')') | Some(']'))
+                && let Some(has_comma) = open_lists.pop()
+                && has_comma
+                && let Some(previous) = output.iter_mut().rev().find(|l| !l.trim().is_empty())
+            {
+                let trimmed = previous.trim_end();
+                if self.style.trailing_comma && !trimmed.ends_with(',') && !trimmed.ends_with(['(', '[']) {
+                    *previous = format!("{},", trimmed);
+                } else if !self.style.trailing_comma && trimmed.ends_with(',') {
+                    *previous = trimmed[..trimmed.len() - 1].to_string();
+                }
+            }
+            if last_code == Some(',')
+                && let Some(has_comma) = open_lists.last_mut()
+            {
+                *has_comma = true;
+            }
+
+            let formatted = format!("{}{}", indent, content);
+            if self.display_width(&formatted) > self.style.line_length
+                && let Some(wrapped) = self.wrap_line(&indent, &segments)
+            {
+                output.extend(wrapped);
+            } else {
+                output.push(formatted);
+                if matches!(last_code, Some('(') | Some('[')) && !matches!(first_code, Some(')') | Some(']')) {
+                    open_lists.push(false);
+                }
+            }
+        }
+
+        let mut result = output.join("\n");
+        if code.ends_with('\n') {
+            result.push('\n');
+        }
+        result
+    }
+
+    /// 对一行进行词法切分
+    /// Split one line into code/string/comment segments
+    fn lex_line(&self, line: &str, state: &mut LexState) -> Vec<(SegmentKind, String)> {
+        let chars: Vec<char> = line.chars().collect();
+        let mut segments: Vec<(SegmentKind, String)> = Vec::new();
+        let mut push = |kind: SegmentKind, text: String| {
+            if text.is_empty() {
+                return;
+            }
+            match segments.last_mut() {
+                Some((last_kind, last_text)) if *last_kind == kind && kind == SegmentKind::Code => last_text.push_str(&text),
+                _ => segments.push((kind, text)),
+            }
+        };
+        let starts_with = |i: usize, pattern: &str| {
+            pattern.chars().enumerate().all(|(offset, c)| chars.get(i + offset) == Some(&c))
+        };
+
+        // 从 i 开始扫描字符串内容直到结束定界符，返回 (结束位置, 是否闭合)
+        let scan_string = |mut i: usize, close: &str, raw: bool| -> (usize, bool) {
+            while i < chars.len() {
+                if !raw && chars[i] == '\\' {
+                    i += 2;
+                    continue;
+                }
+                if starts_with(i, close) {
+                    return (i + close.chars().count(), true);
+                }
+                i += 1;
+            }
+            (chars.len(), false)
+        };
+
+        let mut i = 0;
+        while i < chars.len() {
+            match state.clone() {
+                LexState::Str { close, raw } => {
+                    let (end, closed) = scan_string(i, &close, raw);
+                    push(SegmentKind::Str, chars[i..end].iter().collect());
+                    if closed {
+                        *state = LexState::Code;
+                    }
+                    i = end;
+                }
+                LexState::BlockComment => {
+                    let start = i;
+                    while i < chars.len() && !starts_with(i, "*/") {
+                        i += 1;
+                    }
+                    if i < chars.len() {
+                        i += 2;
+                        *state = LexState::Code;
+                    }
+                    push(SegmentKind::Comment, chars[start..i].iter().collect());
+                }
+                LexState::Code => {
+                    let c = chars[i];
+                    let line_comment = match self.language {
+                        ProgrammingLanguage::Python => c == '#',
+                        _ => starts_with(i, "//"),
+                    };
+                    if line_comment {
+                        push(SegmentKind::Comment, chars[i..].iter().collect());
+                        break;
+                    }
+                    if !matches!(self.language, ProgrammingLanguage::Python) && starts_with(i, "/*") {
+                        *state = LexState::BlockComment;
+                        continue;
+                    }
+
+                    // (起始定界符长度, 结束定界符, 是否为原始字符串)
+                    let opening = match self.language {
+                        ProgrammingLanguage::Python if starts_with(i, "\"\"\"") || starts_with(i, "'''") => {
+                            Some((3, chars[i..i + 3].iter().collect::<String>(), false))
+                        }
+                        ProgrammingLanguage::Python => matches!(c, '"' | '\'').then(|| (1, c.to_string(), false)),
+                        ProgrammingLanguage::Rust => rust_string_opening(&chars, i),
+                        _ => matches!(c, '"' | '\'' | '`').then(|| (1, c.to_string(), false)),
+                    };
+
+                    if let Some((open_len, close, raw)) = opening {
+                        let (end, closed) = scan_string(i + open_len, &close, raw);
+                        push(SegmentKind::Str, chars[i..end].iter().collect());
+                        if !closed {
+                            *state = LexState::Str { close, raw };
+                        }
+                        i = end;
+                    } else if matches!(self.language, ProgrammingLanguage::Rust)
+                        && c == '\''
+                        && let Some(len) = rust_char_literal_len(&chars[i..])
+                    {
+                        push(SegmentKind::Str, chars[i..i + len].iter().collect());
+                        i += len;
+                    } else {
+                        push(SegmentKind::Code, c.to_string());
+                        i += 1;
+                    }
+                }
+            }
+        }
+
+        segments
+    }
+
+    /// 在 Python/TypeScript 中按风格转换简单字符串的引号
+    fn convert_quotes(&self, segments: &mut [(SegmentKind, String)]) {
+        if !matches!(
+            self.language,
+            ProgrammingLanguage::Python | ProgrammingLanguage::TypeScript | ProgrammingLanguage::JavaScript
+        ) {
+            return;
+        }
+        let (from, to) = if self.style.single_quotes { ('"', '\'') } else { ('\'', '"') };
+        for (kind, text) in segments.iter_mut() {
+            if *kind != SegmentKind::Str {
+                continue;
+            }
+            let Some(inner) = text.strip_prefix(from).and_then(|rest| rest.strip_suffix(from)) else {
+                continue;
+            };
+            if !inner.contains([from, to, '\\']) {
+                *text = format!("{}{}{}", to, inner, to);
+            }
+        }
+    }
+
+    /// 将过长的行在括号列表处折行
+    /// Wrap an overlong line at a bracketed argument list
+    fn wrap_line(&self, indent: &str, segments: &[(SegmentKind, String)]) -> Option<Vec<String>> {
+        let chars: Vec<(char, bool)> = segments.iter()
+            .flat_map(|(kind, text)| text.chars().map(move |c| (c, *kind == SegmentKind::Code)))
+            .collect();
+        let track_angles = !matches!(self.language, ProgrammingLanguage::Python);
+
+        // 找到最外层、包含顶层逗号的括号对
+        let mut stack: Vec<(usize, usize)> = Vec::new();
+        let mut best: Option<(usize, usize, usize)> = None;
+        for (i, (c, is_code)) in chars.iter().enumerate() {
+            if !is_code {
+                continue;
+            }
+            match c {
+                '(' | '[' => stack.push((i, stack.len())),
+                ')' | ']' => {
+                    if let Some((open, depth)) = stack.pop()
+                        && !split_top_level(&chars[open + 1..i], track_angles).is_empty()
+                        && best.is_none_or(|(_, _, best_depth)| depth < best_depth)
+                    {
+                        best = Some((open, i, depth));
+                    }
+                }
+                _ => {}
+            }
+        }
+        let (open, close, _) = best?;
+
+        let items = split_top_level(&chars[open + 1..close], track_angles);
+        let head: String = chars[..=open].iter().map(|(c, _)| c).collect();
+        let tail: String = chars[close..].iter().map(|(c, _)| c).collect();
+        let item_indent = format!("{}{}", indent, self.indent_unit());
+
+        let mut lines = vec![format!("{}{}", indent, head.trim_end())];
+        let last = items.len() - 1;
+        for (index, item) in items.iter().enumerate() {
+            let comma = if index < last || self.style.trailing_comma { "," } else { "" };
+            lines.push(format!("{}{}{}", item_indent, item, comma));
+        }
+        lines.push(format!("{}{}", indent, tail));
+        Some(lines)
+    }
+}
+
+/// 按顶层逗号切分列表内容，返回去除空白的非空元素；没有顶层逗号时返回空
+fn split_top_level(chars: &[(char, bool)], track_angles: bool) -> Vec<String> {
+    let mut items = Vec::new();
+    let mut current = String::new();
+    let mut depth = 0usize;
+    let mut angle_depth = 0usize;
+    let mut saw_comma = false;
+    let mut previous = ' ';
+
+    for (c, is_code) in chars {
+        if *is_code {
+            match c {
+                '(' | '[' | '{' => depth += 1,
+                ')' | ']' | '}' => depth = depth.saturating_sub(1),
+                '<' if track_angles && (previous.is_alphanumeric() || previous == ':') => angle_depth += 1,
+                '>' if track_angles && previous != '-' && previous != '=' => angle_depth = angle_depth.saturating_sub(1),
+                ',' if depth == 0 && angle_depth == 0 => {
+                    saw_comma = true;
+                    items.push(std::mem::take(&mut current));
+                    previous = *c;
+                    continue;
+                }
+                _ => {}
+            }
+        }
+        current.push(*c);
+        previous = *c;
+    }
+    items.push(current);
+
+    if !saw_comma {
+        return Vec::new();
+    }
+    items.into_iter()
+        .map(|item| item.trim().to_string())
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
+/// 识别 Rust 字符串起始，返回 (起始定界符长度, 结束定界符, 是否为原始字符串)
+fn rust_string_opening(chars: &[char], i: usize) -> Option<(usize, String, bool)> {
+    if chars[i] == '"' {
+        return Some((1, "\"".to_string(), false));
+    }
+    let preceded_by_ident = i > 0 && (chars[i - 1].is_alphanumeric() || chars[i - 1] == '_');
+    if chars[i] != 'r' || preceded_by_ident {
+        return None;
+    }
+    let hashes = chars[i + 1..].iter().take_while(|c| **c == '#').count();
+    (chars.get(i + 1 + hashes) == Some(&'"'))
+        .then(|| (hashes + 2, format!("\"{}", "#".repeat(hashes)), true))
+}
+
+/// Rust 字符字面量长度（`'a'`、`'\n'`、`'\u{1F600}'`），生命周期返回 None
+fn rust_char_literal_len(chars: &[char]) -> Option<usize> {
+    match chars {
+        ['\'', '\\', 'u', '{', ..] => chars.iter().position(|c| *c == '}')
+            .filter(|end| chars.get(end + 1) == Some(&'\''))
+            .map(|end| end + 2),
+        ['\'', '\\', _, '\'', ..] => Some(4),
+        ['\'', '\\', 'x', _, _, '\'', ..] => Some(6),
+        ['\'', c, '\'', ..] if *c != '\\' => Some(3),
+        _ => None,
+    }
+}
+
 /// WebAssembly 调试器
 /// WebAssembly Debugger
 #[derive(Debug)]
@@ -581,6 +1597,8 @@ pub struct WasmDebugger {
     pub debug_sessions: HashMap<String, DebugSession>,
     /// 调试配置
     pub debug_config: DebugConfiguration,
+    /// 已解析的断点条件（按断点ID）
+    compiled_conditions: HashMap<u32, DebugExpression>,
 }
 
 impl Default for WasmDebugger {
@@ -597,51 +1615,150 @@ impl WasmDebugger {
             breakpoints: Vec::new(),
             debug_sessions: HashMap::new(),
             debug_config: DebugConfiguration::default(),
+            compiled_conditions: HashMap::new(),
         }
     }
 
-    /// 设置断点
-    /// Set breakpoint
-    pub fn set_breakpoint(&mut self, breakpoint: Breakpoint) {
+    /// 设置断点，条件表达式在此时解析，语法错误立即返回
+    /// Set breakpoint; the condition is parsed here so syntax errors surface immediately
+    pub fn set_breakpoint(&mut self, breakpoint: Breakpoint) -> Result<(), DeveloperToolsError> {
+        match &breakpoint.condition {
+            Some(condition) => {
+                let expression = DebugExpression::parse(condition)?;
+                self.compiled_conditions.insert(breakpoint.id, expression);
+            }
+            None => {
+                self.compiled_conditions.remove(&breakpoint.id);
+            }
+        }
         self.breakpoints.push(breakpoint);
+        Ok(())
     }
 
-    /// 启动调试会话
-    /// Start debug session
-    pub fn start_debug_session(&mut self, session_id: String, module: WebAssembly2Module) -> Result<(), DeveloperToolsError> {
-        let session = DebugSession {
+    /// 启动调试会话：把模块加载到独立的运行时，并在入口函数的第一条指令前就绪
+    /// Start debug session: load the module into its own runtime, ready before the entry function's first instruction
+    pub fn start_debug_session(
+        &mut self,
+        session_id: String,
+        module: WebAssembly2Module,
+        function_index: u32,
+        args: Vec<Value>,
+    ) -> Result<(), DeveloperToolsError> {
+        let mut runtime = WebAssembly2Runtime::new();
+        let module_id = runtime.load_module(module.clone())
+            .map_err(|e| DeveloperToolsError::RuntimeError(e.to_string()))?;
+        let execution = runtime.start_execution(&module_id, function_index, args)
+            .map_err(|e| DeveloperToolsError::RuntimeError(e.to_string()))?;
+
+        let mut session = DebugSession {
             id: session_id.clone(),
             module,
             state: DebugState::Running,
+            current_function: function_index,
             current_instruction: 0,
             call_stack: Vec::new(),
             variables: HashMap::new(),
             watch_expressions: Vec::new(),
+            runtime,
+            execution: Some(execution),
+            return_values: None,
         };
+        session.refresh();
         
         self.debug_sessions.insert(session_id, session);
         Ok(())
     }
 
-    /// 继续执行
-    /// Continue execution
+    /// 继续执行，直到命中条件成立的断点、函数返回或发生陷阱
+    /// Continue execution until a breakpoint whose condition holds, completion, or a trap
     pub fn continue_execution(&mut self, session_id: &str) -> Result<(), DeveloperToolsError> {
-        if let Some(session) = self.debug_sessions.get_mut(session_id) {
-            session.state = DebugState::Running;
-        }
-        Ok(())
+        let Some(mut session) = self.debug_sessions.remove(session_id) else {
+            return Ok(());
+        };
+        // 已暂停的会话先越过当前所在的断点
+        let mut check_breakpoints = !matches!(session.state, DebugState::Paused);
+        let result = loop {
+            let Some(frame) = session.execution.as_ref().and_then(|execution| execution.current_frame()) else {
+                break Ok(());
+            };
+            if check_breakpoints {
+                match self.should_break(&session, frame.function_index, frame.instruction_index) {
+                    Ok(true) => {
+                        session.state = DebugState::Paused;
+                        break Ok(());
+                    }
+                    Ok(false) => {}
+                    Err(e) => break Err(e),
+                }
+            }
+            check_breakpoints = true;
+            session.execute_instruction();
+        };
+        session.refresh();
+        self.debug_sessions.insert(session_id.to_string(), session);
+        result
     }
 
-    /// 单步执行
-    /// Step execution
-    pub fn step_execution(&mut self, session_id: &str) -> Result<(), DeveloperToolsError> {
-        if let Some(session) = self.debug_sessions.get_mut(session_id) {
-            session.current_instruction += 1;
-            session.state = DebugState::Paused;
+    /// 判断会话在给定位置是否应当暂停
+    /// Whether the session should pause at the given location
+    fn should_break(
+        &self,
+        session: &DebugSession,
+        function_index: u32,
+        instruction_index: u32,
+    ) -> Result<bool, DeveloperToolsError> {
+        for breakpoint in &self.breakpoints {
+            if !breakpoint.enabled
+                || breakpoint.module_id != session.module.id
+                || breakpoint.function_index != function_index
+                || breakpoint.instruction_index != instruction_index
+            {
+                continue;
+            }
+            let hit = match (&breakpoint.condition, self.compiled_conditions.get(&breakpoint.id)) {
+                (None, _) => true,
+                (Some(_), Some(expression)) => expression.is_true(|name| session.lookup(name))?,
+                // 直接修改 `breakpoints` 添加的断点没有预先解析
+                (Some(condition), None) => DebugExpression::parse(condition)?.is_true(|name| session.lookup(name))?,
+            };
+            if hit {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// 在会话当前状态上求值表达式
+    /// Evaluate an expression against the current session state
+    pub fn evaluate(&self, session_id: &str, expression: &str) -> Result<Value, DeveloperToolsError> {
+        let session = self.debug_sessions.get(session_id)
+            .ok_or_else(|| DeveloperToolsError::DebugSessionNotFound(session_id.to_string()))?;
+        DebugExpression::parse(expression)?.evaluate(|name| session.lookup(name))
+    }
+
+    /// 单步执行：执行一条指令后暂停
+    /// Step execution: execute exactly one instruction, then pause
+    pub fn step_execution(&mut self, session_id: &str) -> Result<(), DeveloperToolsError> {
+        if let Some(session) = self.debug_sessions.get_mut(session_id)
+            && session.execution.is_some()
+        {
+            session.execute_instruction();
+            if session.execution.is_some() {
+                session.state = DebugState::Paused;
+            }
+            session.refresh();
         }
         Ok(())
     }
 
+    /// 获取调用栈（最外层在前）
+    /// Get call stack, outermost frame first
+    pub fn get_call_stack(&self, session_id: &str) -> Result<Vec<StackFrame>, DeveloperToolsError> {
+        self.debug_sessions.get(session_id)
+            .map(|session| session.call_stack.clone())
+            .ok_or_else(|| DeveloperToolsError::DebugSessionNotFound(session_id.to_string()))
+    }
+
     /// 获取变量值
     /// Get variable value
     pub fn get_variable_value(&self, session_id: &str, variable_name: &str) -> Option<Value> {
@@ -650,10 +1767,18 @@ impl WasmDebugger {
             .cloned()
     }
 
-    /// 设置变量值
-    /// Set variable value
+    /// 设置变量值；`localN` 会直接写入当前帧的局部变量
+    /// Set variable value; `localN` writes through to the current frame's locals
     pub fn set_variable_value(&mut self, session_id: &str, variable_name: String, value: Value) -> Result<(), DeveloperToolsError> {
         if let Some(session) = self.debug_sessions.get_mut(session_id) {
+            let local = variable_name.strip_prefix("local")
+                .and_then(|index| index.parse::<usize>().ok())
+                .and_then(|index| {
+                    session.execution.as_mut()?.frames.last_mut()?.locals.get_mut(index)
+                });
+            if let Some(local) = local {
+                *local = value;
+            }
             session.variables.insert(variable_name, value);
         }
         Ok(())
@@ -688,6 +1813,8 @@ pub struct DebugSession {
     pub module: WebAssembly2Module,
     /// 调试状态
     pub state: DebugState,
+    /// 当前函数
+    pub current_function: u32,
     /// 当前指令
     pub current_instruction: u32,
     /// 调用栈
@@ -696,6 +1823,334 @@ pub struct DebugSession {
     pub variables: HashMap<String, Value>,
     /// 监视表达式
     pub watch_expressions: Vec<String>,
+    /// 会话独占的运行时
+    pub runtime: WebAssembly2Runtime,
+    /// 进行中的执行，函数返回或陷阱后为 None
+    pub execution: Option<WebAssembly2Execution>,
+    /// 入口函数的返回值
+    pub return_values: Option<Vec<Value>>,
+}
+
+impl DebugSession {
+    /// 执行一条指令，并根据结果更新调试状态
+    /// Execute one instruction and update the debug state accordingly
+    fn execute_instruction(&mut self) {
+        let Some(execution) = self.execution.as_mut() else {
+            return;
+        };
+        match self.runtime.step(execution) {
+            Ok(StepOutcome::Continue) => {}
+            Ok(StepOutcome::Returned(values)) => {
+                self.execution = None;
+                self.call_stack.clear();
+                self.return_values = Some(values);
+                self.state = DebugState::Stopped;
+            }
+            Err(e) => {
+                // 保留陷阱发生时的调用栈
+                self.call_stack = execution.call_stack(&self.module);
+                self.execution = None;
+                self.state = DebugState::Error(e.to_string());
+            }
+        }
+    }
+
+    /// 用当前帧的位置、局部变量与操作数栈刷新会话状态
+    /// Refresh location and variables from the current frame's locals and operand stack
+    fn refresh(&mut self) {
+        let Some(execution) = &self.execution else {
+            return;
+        };
+        let Some(frame) = execution.current_frame() else {
+            return;
+        };
+        self.current_function = frame.function_index;
+        self.current_instruction = frame.instruction_index;
+        self.call_stack = execution.call_stack(&self.module);
+
+        self.variables.clear();
+        for (index, value) in frame.locals.iter().enumerate() {
+            self.variables.insert(format!("local{}", index), *value);
+        }
+        for (index, value) in execution.frame_stack().iter().enumerate() {
+            self.variables.insert(format!("stack{}", index), *value);
+        }
+    }
+
+    /// 当前帧的操作数栈（自底向上）
+    /// Operand stack of the current frame, bottom first
+    pub fn operand_stack(&self) -> &[Value] {
+        self.execution.as_ref().map(|execution| execution.frame_stack()).unwrap_or(&[])
+    }
+
+    /// 查找表达式中的名称：`instruction_index`、`function_index`、`localN`、`stackN` 或会话变量
+    /// Resolve a name used in an expression
+    fn lookup(&self, name: &str) -> Option<Value> {
+        let frame = self.execution.as_ref().and_then(|execution| execution.current_frame());
+        let indexed = |prefix: &str| name.strip_prefix(prefix).and_then(|index| index.parse::<usize>().ok());
+        match name {
+            "instruction_index" => Some(Value::I64(frame.map_or(self.current_instruction, |f| f.instruction_index) as i64)),
+            "function_index" => Some(Value::I64(frame.map_or(self.current_function, |f| f.function_index) as i64)),
+            _ => {
+                if let (Some(frame), Some(index)) = (frame, indexed("local")) {
+                    return frame.locals.get(index).copied();
+                }
+                if let (Some(_), Some(index)) = (frame, indexed("stack")) {
+                    return self.operand_stack().get(index).copied();
+                }
+                self.variables.get(name).copied()
+            }
+        }
+    }
+}
+
+/// 调试表达式，用于断点条件与即时求值
+/// Debug expression used by breakpoint conditions and ad-hoc evaluation
+///
+/// 支持数字与布尔字面量、变量、比较运算符、`!`、`&&`、`||` 以及括号；
+/// 优先级从低到高依次为 `||`、`&&`、比较、一元运算。
+/// Supports literals, variables, comparisons, `!`, `&&`, `||` and parentheses.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DebugExpression {
+    /// 字面量
+    Literal(Value),
+    /// 变量
+    Variable(String),
+    /// 逻辑非
+    Not(Box<DebugExpression>),
+    /// 取负
+    Negate(Box<DebugExpression>),
+    /// 二元运算
+    Binary(DebugOperator, Box<DebugExpression>, Box<DebugExpression>),
+}
+
+/// 调试表达式二元运算符
+/// Debug expression binary operator
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DebugOperator {
+    /// `||`
+    Or,
+    /// `&&`
+    And,
+    /// `==`
+    Eq,
+    /// `!=`
+    Ne,
+    /// `<`
+    Lt,
+    /// `<=`
+    Le,
+    /// `>`
+    Gt,
+    /// `>=`
+    Ge,
+}
+
+/// 表达式词法单元
+#[derive(Debug, Clone, PartialEq)]
+enum DebugToken {
+    Identifier(String),
+    Number(Value),
+    Operator(&'static str),
+    LeftParen,
+    RightParen,
+}
+
+/// 表达式中参与比较的数值
+#[derive(Debug, Clone, Copy)]
+enum DebugNumber {
+    Int(i128),
+    Float(f64),
+}
+
+impl DebugNumber {
+    fn from_value(value: &Value) -> Result<Self, DeveloperToolsError> {
+        match value {
+            Value::I32(v) => Ok(Self::Int(*v as i128)),
+            Value::I64(v) => Ok(Self::Int(*v as i128)),
+            Value::I128(v) => Ok(Self::Int(*v)),
+            Value::U128(v) => i128::try_from(*v).map(Self::Int)
+                .map_err(|_| DeveloperToolsError::ExpressionError(format!("数值超出范围: {}", v))),
+            Value::F32(v) => Ok(Self::Float(*v as f64)),
+            Value::F64(v) => Ok(Self::Float(*v)),
+            other => Err(DeveloperToolsError::ExpressionError(format!("无法比较的值: {:?}", other))),
+        }
+    }
+
+    fn as_f64(self) -> f64 {
+        match self {
+            Self::Int(v) => v as f64,
+            Self::Float(v) => v,
+        }
+    }
+}
+
+impl DebugExpression {
+    /// 解析表达式
+    /// Parse an expression
+    pub fn parse(source: &str) -> Result<Self, DeveloperToolsError> {
+        let tokens = tokenize_debug_expression(source)?;
+        let mut position = 0;
+        let expression = Self::parse_or(&tokens, &mut position)?;
+        match tokens.get(position) {
+            None => Ok(expression),
+            Some(token) => Err(DeveloperToolsError::ExpressionError(format!("多余的符号: {:?}", token))),
+        }
+    }
+
+    fn parse_or(tokens: &[DebugToken], position: &mut usize) -> Result<Self, DeveloperToolsError> {
+        let mut left = Self::parse_and(tokens, position)?;
+        while tokens.get(*position) == Some(&DebugToken::Operator("||")) {
+            *position += 1;
+            let right = Self::parse_and(tokens, position)?;
+            left = Self::Binary(DebugOperator::Or, Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_and(tokens: &[DebugToken], position: &mut usize) -> Result<Self, DeveloperToolsError> {
+        let mut left = Self::parse_comparison(tokens, position)?;
+        while tokens.get(*position) == Some(&DebugToken::Operator("&&")) {
+            *position += 1;
+            let right = Self::parse_comparison(tokens, position)?;
+            left = Self::Binary(DebugOperator::And, Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_comparison(tokens: &[DebugToken], position: &mut usize) -> Result<Self, DeveloperToolsError> {
+        let mut left = Self::parse_unary(tokens, position)?;
+        loop {
+            let operator = match tokens.get(*position) {
+                Some(DebugToken::Operator("==")) => DebugOperator::Eq,
+                Some(DebugToken::Operator("!=")) => DebugOperator::Ne,
+                Some(DebugToken::Operator("<")) => DebugOperator::Lt,
+                Some(DebugToken::Operator("<=")) => DebugOperator::Le,
+                Some(DebugToken::Operator(">")) => DebugOperator::Gt,
+                Some(DebugToken::Operator(">=")) => DebugOperator::Ge,
+                _ => return Ok(left),
+            };
+            *position += 1;
+            let right = Self::parse_unary(tokens, position)?;
+            left = Self::Binary(operator, Box::new(left), Box::new(right));
+        }
+    }
+
+    fn parse_unary(tokens: &[DebugToken], position: &mut usize) -> Result<Self, DeveloperToolsError> {
+        let token = tokens.get(*position)
+            .ok_or_else(|| DeveloperToolsError::ExpressionError("表达式意外结束".to_string()))?;
+        *position += 1;
+        match token {
+            DebugToken::Operator("!") => Ok(Self::Not(Box::new(Self::parse_unary(tokens, position)?))),
+            DebugToken::Operator("-") => Ok(Self::Negate(Box::new(Self::parse_unary(tokens, position)?))),
+            DebugToken::Number(value) => Ok(Self::Literal(*value)),
+            DebugToken::Identifier(name) if name == "true" => Ok(Self::Literal(Value::I32(1))),
+            DebugToken::Identifier(name) if name == "false" => Ok(Self::Literal(Value::I32(0))),
+            DebugToken::Identifier(name) => Ok(Self::Variable(name.clone())),
+            DebugToken::LeftParen => {
+                let inner = Self::parse_or(tokens, position)?;
+                if tokens.get(*position) != Some(&DebugToken::RightParen) {
+                    return Err(DeveloperToolsError::ExpressionError("缺少右括号".to_string()));
+                }
+                *position += 1;
+                Ok(inner)
+            }
+            other => Err(DeveloperToolsError::ExpressionError(format!("意外的符号: {:?}", other))),
+        }
+    }
+
+    /// 求值表达式；比较与逻辑运算的结果为 `Value::I32(0 | 1)`
+    /// Evaluate the expression; comparisons and boolean operators yield `Value::I32(0 | 1)`
+    pub fn evaluate(&self, lookup: impl Fn(&str) -> Option<Value> + Copy) -> Result<Value, DeveloperToolsError> {
+        let boolean = |b: bool| Value::I32(b as i32);
+        match self {
+            Self::Literal(value) => Ok(*value),
+            Self::Variable(name) => lookup(name)
+                .ok_or_else(|| DeveloperToolsError::ExpressionError(format!("未定义的变量: {}", name))),
+            Self::Not(inner) => Ok(boolean(!inner.is_true(lookup)?)),
+            Self::Negate(inner) => match DebugNumber::from_value(&inner.evaluate(lookup)?)? {
+                DebugNumber::Int(v) => v.checked_neg().map(Value::I128)
+                    .ok_or_else(|| DeveloperToolsError::ExpressionError(format!("数值超出范围: -{}", v))),
+                DebugNumber::Float(v) => Ok(Value::F64(-v)),
+            },
+            Self::Binary(DebugOperator::Or, left, right) => Ok(boolean(left.is_true(lookup)? || right.is_true(lookup)?)),
+            Self::Binary(DebugOperator::And, left, right) => Ok(boolean(left.is_true(lookup)? && right.is_true(lookup)?)),
+            Self::Binary(operator, left, right) => {
+                let left = DebugNumber::from_value(&left.evaluate(lookup)?)?;
+                let right = DebugNumber::from_value(&right.evaluate(lookup)?)?;
+                let ordering = match (left, right) {
+                    (DebugNumber::Int(a), DebugNumber::Int(b)) => Some(a.cmp(&b)),
+                    (a, b) => a.as_f64().partial_cmp(&b.as_f64()),
+                };
+                let result = match operator {
+                    DebugOperator::Eq => ordering == Some(std::cmp::Ordering::Equal),
+                    DebugOperator::Ne => ordering != Some(std::cmp::Ordering::Equal),
+                    DebugOperator::Lt => ordering == Some(std::cmp::Ordering::Less),
+                    DebugOperator::Le => matches!(ordering, Some(std::cmp::Ordering::Less | std::cmp::Ordering::Equal)),
+                    DebugOperator::Gt => ordering == Some(std::cmp::Ordering::Greater),
+                    DebugOperator::Ge => matches!(ordering, Some(std::cmp::Ordering::Greater | std::cmp::Ordering::Equal)),
+                    DebugOperator::Or | DebugOperator::And => unreachable!(),
+                };
+                Ok(boolean(result))
+            }
+        }
+    }
+
+    /// 求值并按非零判断真假
+    /// Evaluate and test for a non-zero result
+    pub fn is_true(&self, lookup: impl Fn(&str) -> Option<Value> + Copy) -> Result<bool, DeveloperToolsError> {
+        match DebugNumber::from_value(&self.evaluate(lookup)?)? {
+            DebugNumber::Int(v) => Ok(v != 0),
+            DebugNumber::Float(v) => Ok(v != 0.0),
+        }
+    }
+}
+
+/// 将表达式切分为词法单元
+fn tokenize_debug_expression(source: &str) -> Result<Vec<DebugToken>, DeveloperToolsError> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit() || (c == '.' && chars.get(i + 1).is_some_and(|c| c.is_ascii_digit())) {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '.' || chars[i] == '_') {
+                i += 1;
+            }
+            let text: String = chars[start..i].iter().filter(|c| **c != '_').collect();
+            let value = if text.contains('.') {
+                text.parse::<f64>().map(Value::F64).ok()
+            } else {
+                text.parse::<i64>().map(Value::I64).ok()
+            };
+            tokens.push(DebugToken::Number(value.ok_or_else(|| {
+                DeveloperToolsError::ExpressionError(format!("无效的数字: {}", text))
+            })?));
+        } else if c.is_alphabetic() || c == '_' || c == '$' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_' || chars[i] == '$' || chars[i] == '.') {
+                i += 1;
+            }
+            tokens.push(DebugToken::Identifier(chars[start..i].iter().collect()));
+        } else if c == '(' {
+            tokens.push(DebugToken::LeftParen);
+            i += 1;
+        } else if c == ')' {
+            tokens.push(DebugToken::RightParen);
+            i += 1;
+        } else {
+            let two: String = chars[i..(i + 2).min(chars.len())].iter().collect();
+            let operator = ["||", "&&", "==", "!=", "<=", ">="].into_iter().find(|op| *op == two)
+                .or_else(|| ["<", ">", "!", "-"].into_iter().find(|op| op.starts_with(c)))
+                .ok_or_else(|| DeveloperToolsError::ExpressionError(format!("无法识别的字符: {}", c)))?;
+            i += operator.len();
+            tokens.push(DebugToken::Operator(operator));
+        }
+    }
+    Ok(tokens)
 }
 
 /// 调试状态
@@ -708,8 +2163,8 @@ pub enum DebugState {
     Paused,
     /// 停止
     Stopped,
-    /// 错误
-    Error,
+    /// 错误（陷阱信息）
+    Error(String),
 }
 
 /// 调试配置
@@ -759,6 +2214,20 @@ pub struct WasmProfiler {
     pub performance_data: HashMap<ModuleId, PerformanceData>,
     /// 分析配置
     pub analysis_config: AnalysisConfiguration,
+    /// 后台内存采样线程
+    memory_samplers: HashMap<ModuleId, MemorySampler>,
+}
+
+/// 后台内存采样线程句柄
+/// Background memory sampler handle
+#[derive(Debug)]
+struct MemorySampler {
+    /// 停止信号，丢弃发送端同样会让线程退出
+    stop: mpsc::Sender<()>,
+    /// 线程句柄
+    handle: JoinHandle<()>,
+    /// 采样线程写入的快照
+    snapshots: Arc<Mutex<Vec<MemoryUsageSnapshot>>>,
 }
 
 impl Default for WasmProfiler {
@@ -774,6 +2243,7 @@ impl WasmProfiler {
         Self {
             performance_data: HashMap::new(),
             analysis_config: AnalysisConfiguration::default(),
+            memory_samplers: HashMap::new(),
         }
     }
 
@@ -786,6 +2256,7 @@ impl WasmProfiler {
             function_calls: HashMap::new(),
             memory_usage: Vec::new(),
             execution_times: Vec::new(),
+            stack_samples: HashMap::new(),
         };
         
         self.performance_data.insert(module_id, performance_data);
@@ -812,28 +2283,335 @@ impl WasmProfiler {
             call_data.average_time = Duration::from_millis(call_data.total_time.as_millis() as u64 / call_data.call_count);
             call_data.min_time = call_data.min_time.min(execution_time);
             call_data.max_time = call_data.max_time.max(execution_time);
+
+            data.execution_times.push(ExecutionTimeSnapshot {
+                timestamp: Instant::now(),
+                execution_time,
+            });
+            retain_latest(&mut data.execution_times, self.analysis_config.max_snapshots);
+        }
+    }
+
+    /// 启动后台内存采样线程，按 `sampling_interval` 调用 `source` 读取内存使用量
+    /// Spawn a background thread sampling `source` every `sampling_interval`
+    ///
+    /// 未启用内存分析时不启动线程；同一模块已有采样线程时先停止旧线程。
+    pub fn start_sampling(
+        &mut self,
+        module_id: ModuleId,
+        source: impl Fn() -> u64 + Send + 'static,
+    ) -> Result<(), DeveloperToolsError> {
+        if !self.analysis_config.memory_analysis_enabled {
+            return Ok(());
+        }
+        if !self.performance_data.contains_key(&module_id) {
+            self.start_profiling(module_id.clone())?;
+        }
+        self.stop_sampling(&module_id);
+
+        let interval = self.analysis_config.sampling_interval;
+        let retention = self.analysis_config.max_snapshots;
+        let snapshots = Arc::new(Mutex::new(Vec::new()));
+        let (stop, stopped) = mpsc::channel::<()>();
+        let buffer = snapshots.clone();
+        let handle = std::thread::Builder::new()
+            .name(format!("wasm-memory-sampler-{}", module_id.id))
+            .spawn(move || loop {
+                let snapshot = MemoryUsageSnapshot {
+                    timestamp: Instant::now(),
+                    memory_usage: source(),
+                };
+                if let Ok(mut buffer) = buffer.lock() {
+                    buffer.push(snapshot);
+                    retain_latest(&mut buffer, retention);
+                }
+                match stopped.recv_timeout(interval) {
+                    Err(mpsc::RecvTimeoutError::Timeout) => continue,
+                    _ => break,
+                }
+            })
+            .map_err(|e| DeveloperToolsError::RuntimeError(e.to_string()))?;
+
+        self.memory_samplers.insert(module_id, MemorySampler { stop, handle, snapshots });
+        Ok(())
+    }
+
+    /// 停止后台内存采样并等待线程退出，快照并入 `memory_usage`
+    /// Stop background sampling, join the thread and merge its snapshots into `memory_usage`
+    pub fn stop_sampling(&mut self, module_id: &ModuleId) {
+        let Some(sampler) = self.memory_samplers.remove(module_id) else {
+            return;
+        };
+        let _ = sampler.stop.send(());
+        let _ = sampler.handle.join();
+
+        if let Some(data) = self.performance_data.get_mut(module_id)
+            && let Ok(mut snapshots) = sampler.snapshots.lock()
+        {
+            data.memory_usage.append(&mut snapshots);
+            retain_latest(&mut data.memory_usage, self.analysis_config.max_snapshots);
+        }
+    }
+
+    /// 当前内存快照：已合并的历史加上仍在运行的采样线程写入的快照
+    /// Memory history including snapshots from a still-running sampler
+    fn memory_history(&self, data: &PerformanceData) -> Vec<MemoryUsageSnapshot> {
+        let mut history = data.memory_usage.clone();
+        if let Some(sampler) = self.memory_samplers.get(&data.module_id)
+            && let Ok(snapshots) = sampler.snapshots.lock()
+        {
+            history.extend(snapshots.iter().cloned());
+        }
+        retain_latest(&mut history, self.analysis_config.max_snapshots);
+        history
+    }
+
+    /// 记录一次调用栈采样；未对该模块启动分析时直接忽略
+    /// Record a call stack sample; ignored unless profiling was started for the module
+    pub fn record_stack_sample(&mut self, module_id: &ModuleId, call_stack: &[StackFrame], weight: Duration) {
+        if call_stack.is_empty() {
+            return;
+        }
+        if let Some(data) = self.performance_data.get_mut(module_id) {
+            let path = call_stack.iter()
+                .map(|frame| match frame.function_name.is_empty() {
+                    true => format!("func{}", frame.function_index),
+                    false => frame.function_name.clone(),
+                })
+                .collect();
+            *data.stack_samples.entry(path).or_insert(Duration::ZERO) += weight;
+        }
+    }
+
+    /// 在运行时中执行函数，并按 `sampling_interval` 采样解释器调用栈
+    /// Execute a function on the runtime, sampling the interpreter call stack every `sampling_interval`
+    ///
+    /// 未对该模块启动分析时直接调用 `execute_function`，不产生任何采样开销。
+    pub fn profile_function(
+        &mut self,
+        runtime: &mut WebAssembly2Runtime,
+        module_id: &ModuleId,
+        function_index: u32,
+        args: Vec<Value>,
+    ) -> Result<Vec<Value>, DeveloperToolsError> {
+        if !self.performance_data.contains_key(module_id) {
+            return runtime.execute_function(module_id, function_index, args)
+                .map_err(|e| DeveloperToolsError::RuntimeError(e.to_string()));
+        }
+        let module = runtime.modules.get(module_id).cloned()
+            .ok_or_else(|| DeveloperToolsError::RuntimeError(format!("模块未加载: {}", module_id.id)))?;
+        let mut execution = runtime.start_execution(module_id, function_index, args)
+            .map_err(|e| DeveloperToolsError::RuntimeError(e.to_string()))?;
+
+        let start = Instant::now();
+        let mut last_sample = start;
+        let mut sampled_stack = execution.call_stack(&module);
+        let result = loop {
+            // 采样时刻的调用栈承担距上次采样的全部时间
+            let now = Instant::now();
+            if now.duration_since(last_sample) >= self.analysis_config.sampling_interval {
+                sampled_stack = execution.call_stack(&module);
+                self.record_stack_sample(module_id, &sampled_stack, now.duration_since(last_sample));
+                last_sample = now;
+            }
+            match runtime.step(&mut execution) {
+                Ok(StepOutcome::Continue) => {}
+                outcome => {
+                    self.record_stack_sample(module_id, &sampled_stack, last_sample.elapsed());
+                    break outcome;
+                }
+            }
+        };
+
+        self.record_function_call(module_id, function_index, start.elapsed());
+        match result {
+            Ok(StepOutcome::Returned(values)) => Ok(values),
+            Ok(StepOutcome::Continue) => unreachable!(),
+            Err(e) => Err(DeveloperToolsError::RuntimeError(e.to_string())),
+        }
+    }
+
+    /// 导出 Brendan Gregg 折叠栈格式（`main;compute;inner 123`），权重为采样时间（纳秒）
+    /// Export folded stacks for flamegraph tools, weighted by sampled time in nanoseconds
+    pub fn export_folded_stacks(&self, module_id: &ModuleId) -> String {
+        let Some(data) = self.performance_data.get(module_id) else {
+            return String::new();
+        };
+        let mut lines: Vec<String> = data.stack_samples.iter()
+            .map(|(path, weight)| format!("{} {}", path.join(";"), weight.as_nanos()))
+            .collect();
+        lines.sort();
+        lines.iter().map(|line| format!("{}\n", line)).collect()
+    }
+
+    /// 导出 speedscope 采样格式 JSON
+    /// Export the sampled profile in speedscope JSON format
+    pub fn export_speedscope_json(&self, module_id: &ModuleId) -> String {
+        let mut stacks: Vec<(&Vec<String>, &Duration)> = self.performance_data.get(module_id)
+            .map(|data| data.stack_samples.iter().collect())
+            .unwrap_or_default();
+        stacks.sort();
+
+        let mut frames: Vec<&str> = Vec::new();
+        let mut frame_ids: HashMap<&str, usize> = HashMap::new();
+        let mut samples = Vec::new();
+        let mut weights = Vec::new();
+        for (path, weight) in &stacks {
+            let sample: Vec<usize> = path.iter()
+                .map(|name| *frame_ids.entry(name.as_str()).or_insert_with(|| {
+                    frames.push(name.as_str());
+                    frames.len() - 1
+                }))
+                .collect();
+            samples.push(sample);
+            weights.push(weight.as_nanos() as u64);
         }
+
+        let name = format!("module-{}", module_id.id);
+        serde_json::json!({
+            "$schema": "https://www.speedscope.app/file-format-schema.json",
+            "name": name,
+            "exporter": "wasm::developer_tools",
+            "shared": {
+                "frames": frames.iter().map(|name| serde_json::json!({ "name": name })).collect::<Vec<_>>(),
+            },
+            "profiles": [{
+                "type": "sampled",
+                "name": name,
+                "unit": "nanoseconds",
+                "startValue": 0,
+                "endValue": weights.iter().sum::<u64>(),
+                "samples": samples,
+                "weights": weights,
+            }],
+        })
+        .to_string()
     }
 
     /// 生成性能报告
     /// Generate performance report
     pub fn generate_performance_report(&self, module_id: &ModuleId) -> Option<PerformanceReport> {
         let data = self.performance_data.get(module_id)?;
+        let memory_usage_history = self.memory_history(data);
+        let memory_statistics = MemoryStatistics::from_snapshots(&memory_usage_history);
+        let (hotspots_by_time, hotspots_by_calls) = if self.analysis_config.hotspot_analysis_enabled {
+            self.analyze_hotspots(data)
+        } else {
+            (Vec::new(), Vec::new())
+        };
+
+        let mut recommendations = self.generate_recommendations(data, memory_statistics.as_ref());
+        recommendations.extend(self.hotspot_recommendations(&hotspots_by_time, &hotspots_by_calls));
         
         Some(PerformanceReport {
             module_id: module_id.clone(),
             total_execution_time: data.start_time.elapsed(),
             function_calls: data.function_calls.clone(),
-            memory_usage_history: data.memory_usage.clone(),
+            memory_usage_history,
             execution_time_history: data.execution_times.clone(),
-            recommendations: self.generate_recommendations(data),
+            recommendations,
+            memory_statistics,
+            hotspots_by_time,
+            hotspots_by_calls,
         })
     }
 
+    /// 计算热点：按总时间与调用次数分别排序取前 N，忽略占比低于阈值的函数
+    /// Rank hotspots by total time and by call count, dropping functions below the share threshold
+    fn analyze_hotspots(&self, data: &PerformanceData) -> (Vec<Hotspot>, Vec<Hotspot>) {
+        let total_time: Duration = data.function_calls.values().map(|call| call.total_time).sum();
+        let total_calls: u64 = data.function_calls.values().map(|call| call.call_count).sum();
+        let share = |part: f64, whole: f64| if whole > 0.0 { part / whole * 100.0 } else { 0.0 };
+
+        let hotspots: Vec<Hotspot> = data.function_calls.values()
+            .map(|call| Hotspot {
+                function_index: call.function_index,
+                total_time: call.total_time,
+                call_count: call.call_count,
+                average_time: call.total_time.checked_div(call.call_count as u32).unwrap_or_default(),
+                time_percentage: share(call.total_time.as_secs_f64(), total_time.as_secs_f64()),
+                call_percentage: share(call.call_count as f64, total_calls as f64),
+            })
+            .collect();
+
+        let top_n = self.analysis_config.hotspot_top_n;
+        let min_percentage = self.analysis_config.hotspot_min_percentage;
+        let mut by_time: Vec<Hotspot> = hotspots.iter()
+            .filter(|hotspot| hotspot.time_percentage >= min_percentage)
+            .cloned()
+            .collect();
+        by_time.sort_by(|a, b| b.total_time.cmp(&a.total_time).then(a.function_index.cmp(&b.function_index)));
+        by_time.truncate(top_n);
+
+        let mut by_calls: Vec<Hotspot> = hotspots.into_iter()
+            .filter(|hotspot| hotspot.call_percentage >= min_percentage)
+            .collect();
+        by_calls.sort_by(|a, b| b.call_count.cmp(&a.call_count).then(a.function_index.cmp(&b.function_index)));
+        by_calls.truncate(top_n);
+
+        (by_time, by_calls)
+    }
+
+    /// 针对热点生成优化建议
+    /// Generate recommendations for hotspots
+    fn hotspot_recommendations(&self, by_time: &[Hotspot], by_calls: &[Hotspot]) -> Vec<OptimizationRecommendation> {
+        let mut recommendations = Vec::new();
+
+        for hotspot in by_time.iter().filter(|hotspot| hotspot.time_percentage >= 50.0) {
+            recommendations.push(OptimizationRecommendation {
+                recommendation_type: OptimizationType::Performance,
+                severity: RecommendationSeverity::High,
+                description: format!(
+                    "函数 {} 占总执行时间的 {:.1}%",
+                    hotspot.function_index,
+                    hotspot.time_percentage
+                ),
+                suggestion: "优先优化该热点函数，考虑改进算法或使用 SIMD 指令".to_string(),
+            });
+        }
+
+        // 调用极其频繁但单次很短的小函数：调用开销占主导
+        for hotspot in by_calls.iter().filter(|hotspot| {
+            hotspot.call_percentage >= 50.0 && hotspot.average_time <= self.analysis_config.small_function_threshold
+        }) {
+            recommendations.push(OptimizationRecommendation {
+                recommendation_type: OptimizationType::Efficiency,
+                severity: RecommendationSeverity::Medium,
+                description: format!(
+                    "函数 {} 占全部调用的 {:.1}% (平均 {:?})",
+                    hotspot.function_index,
+                    hotspot.call_percentage,
+                    hotspot.average_time
+                ),
+                suggestion: "该函数短小且调用频繁，考虑内联或使用尾调用优化减少调用开销".to_string(),
+            });
+        }
+
+        recommendations
+    }
+
     /// 生成优化建议
     /// Generate optimization recommendations
-    fn generate_recommendations(&self, data: &PerformanceData) -> Vec<OptimizationRecommendation> {
+    fn generate_recommendations(
+        &self,
+        data: &PerformanceData,
+        memory_statistics: Option<&MemoryStatistics>,
+    ) -> Vec<OptimizationRecommendation> {
         let mut recommendations = Vec::new();
+
+        if let Some(statistics) = memory_statistics
+            && statistics.growing
+        {
+            recommendations.push(OptimizationRecommendation {
+                recommendation_type: OptimizationType::Memory,
+                severity: RecommendationSeverity::Medium,
+                description: format!(
+                    "内存使用持续增长 ({} -> {} 字节，{} 个样本)",
+                    statistics.first, statistics.last, statistics.samples
+                ),
+                suggestion: "检查是否存在内存泄漏，或及时释放不再使用的线性内存".to_string(),
+            });
+        }
         
         // 分析函数调用数据
         for call_data in data.function_calls.values() {
@@ -882,6 +2660,8 @@ pub struct PerformanceData {
     pub memory_usage: Vec<MemoryUsageSnapshot>,
     /// 执行时间历史
     pub execution_times: Vec<ExecutionTimeSnapshot>,
+    /// 调用栈采样：函数名路径（最外层在前）到累计采样时间
+    pub stack_samples: HashMap<Vec<String>, Duration>,
 }
 
 /// 函数调用数据
@@ -938,6 +2718,97 @@ pub struct PerformanceReport {
     pub execution_time_history: Vec<ExecutionTimeSnapshot>,
     /// 优化建议
     pub recommendations: Vec<OptimizationRecommendation>,
+    /// 内存统计（没有内存快照时为 None）
+    pub memory_statistics: Option<MemoryStatistics>,
+    /// 按总执行时间排序的热点
+    pub hotspots_by_time: Vec<Hotspot>,
+    /// 按调用次数排序的热点
+    pub hotspots_by_calls: Vec<Hotspot>,
+}
+
+/// 性能热点
+/// Performance hotspot
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hotspot {
+    /// 函数索引
+    pub function_index: u32,
+    /// 总执行时间
+    pub total_time: Duration,
+    /// 调用次数
+    pub call_count: u64,
+    /// 平均执行时间
+    pub average_time: Duration,
+    /// 占全部函数执行时间的百分比
+    pub time_percentage: f64,
+    /// 占全部调用次数的百分比
+    pub call_percentage: f64,
+}
+
+/// 内存使用统计
+/// Memory usage statistics
+#[derive(Debug, Clone, PartialEq)]
+pub struct MemoryStatistics {
+    /// 样本数
+    pub samples: usize,
+    /// 最小值
+    pub min: u64,
+    /// 最大值
+    pub max: u64,
+    /// 平均值
+    pub mean: f64,
+    /// 第一个样本
+    pub first: u64,
+    /// 最后一个样本
+    pub last: u64,
+    /// 是否呈增长趋势
+    pub growing: bool,
+}
+
+impl MemoryStatistics {
+    /// 最少需要的样本数，少于此数不判断趋势
+    const MIN_TREND_SAMPLES: usize = 4;
+    /// 拟合增长量超过平均值的该比例即视为增长
+    const GROWTH_THRESHOLD: f64 = 0.1;
+
+    /// 从快照计算统计；增长趋势取最小二乘斜率在整个窗口上的增量
+    /// Compute statistics; the trend uses the least-squares slope over the window
+    pub fn from_snapshots(snapshots: &[MemoryUsageSnapshot]) -> Option<Self> {
+        let first = snapshots.first()?.memory_usage;
+        let last = snapshots.last()?.memory_usage;
+        let values: Vec<f64> = snapshots.iter().map(|s| s.memory_usage as f64).collect();
+        let n = values.len() as f64;
+        let mean = values.iter().sum::<f64>() / n;
+
+        let growing = if values.len() >= Self::MIN_TREND_SAMPLES {
+            let mean_x = (n - 1.0) / 2.0;
+            let (covariance, variance) = values.iter().enumerate()
+                .fold((0.0, 0.0), |(cov, var), (x, y)| {
+                    let dx = x as f64 - mean_x;
+                    (cov + dx * (y - mean), var + dx * dx)
+                });
+            let growth = covariance / variance * (n - 1.0);
+            growth > mean * Self::GROWTH_THRESHOLD
+        } else {
+            false
+        };
+
+        Some(Self {
+            samples: snapshots.len(),
+            min: snapshots.iter().map(|s| s.memory_usage).min().unwrap_or(first),
+            max: snapshots.iter().map(|s| s.memory_usage).max().unwrap_or(first),
+            mean,
+            first,
+            last,
+            growing,
+        })
+    }
+}
+
+/// 只保留最近的 `limit` 个元素
+fn retain_latest<T>(items: &mut Vec<T>, limit: usize) {
+    if items.len() > limit {
+        items.drain(..items.len() - limit);
+    }
 }
 
 /// 优化建议
@@ -994,6 +2865,14 @@ pub struct AnalysisConfiguration {
     pub function_analysis_enabled: bool,
     /// 是否启用热点分析
     pub hotspot_analysis_enabled: bool,
+    /// 每个模块保留的内存与执行时间快照数量上限
+    pub max_snapshots: usize,
+    /// 热点列表长度
+    pub hotspot_top_n: usize,
+    /// 进入热点列表的最低占比（百分比）
+    pub hotspot_min_percentage: f64,
+    /// 平均执行时间不超过该值的函数视为小函数
+    pub small_function_threshold: Duration,
 }
 
 impl Default for AnalysisConfiguration {
@@ -1003,6 +2882,10 @@ impl Default for AnalysisConfiguration {
             memory_analysis_enabled: true,
             function_analysis_enabled: true,
             hotspot_analysis_enabled: true,
+            max_snapshots: 1024,
+            hotspot_top_n: 10,
+            hotspot_min_percentage: 1.0,
+            small_function_threshold: Duration::from_micros(10),
         }
     }
 }
@@ -1015,6 +2898,8 @@ pub struct WasmTestFramework {
     pub test_suites: HashMap<String, TestSuite>,
     /// 测试配置
     pub test_config: TestConfiguration,
+    /// 运行时模板，每个测试用例在其副本中加载模块后执行
+    pub runtime: WebAssembly2Runtime,
 }
 
 impl Default for WasmTestFramework {
@@ -1030,6 +2915,7 @@ impl WasmTestFramework {
         Self {
             test_suites: HashMap::new(),
             test_config: TestConfiguration::default(),
+            runtime: WebAssembly2Runtime::new(),
         }
     }
 
@@ -1047,8 +2933,54 @@ impl WasmTestFramework {
         Ok(())
     }
 
+    /// 根据函数规范生成模糊测试用例
+    /// Generate fuzz test cases from a function specification
+    ///
+    /// 每个参数按 `fuzz_boundary_probability` 的概率取边界值，否则取随机值；含边界值的用例标记为
+    /// `Boundary`。用例不设期望输出，只检查是否发生陷阱。相同的种子总是生成相同的用例。
+    /// Each parameter takes a boundary value with probability `fuzz_boundary_probability`; cases
+    /// are left without expected output, and the same seed always reproduces the same cases.
+    pub fn generate_fuzz_cases(&self, spec: &FunctionSpecification, count: usize, seed: u64) -> Vec<TestCaseSpecification> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let probability = self.test_config.fuzz_boundary_probability.clamp(0.0, 1.0);
+
+        (0..count)
+            .map(|index| {
+                let mut has_boundary = false;
+                let inputs = spec.parameters.iter()
+                    .map(|parameter| {
+                        let boundaries = boundary_values(&parameter.parameter_type);
+                        if !boundaries.is_empty() && rng.random_bool(probability) {
+                            has_boundary = true;
+                            boundaries[rng.random_range(0..boundaries.len())]
+                        } else {
+                            random_value(&parameter.parameter_type, &mut rng)
+                        }
+                    })
+                    .collect();
+
+                TestCaseSpecification {
+                    name: format!("{}_fuzz_{}", spec.name, index),
+                    description: format!("模糊测试用例 (seed {}, #{})", seed, index),
+                    export_name: spec.name.clone(),
+                    inputs,
+                    expected_output: None,
+                    test_case_type: if has_boundary { TestCaseType::Boundary } else { TestCaseType::Normal },
+                    expected_error: None,
+                    snapshot_name: None,
+                    snapshot_memory: None,
+                }
+            })
+            .collect()
+    }
+
     /// 运行测试套件
     /// Run test suite
+    ///
+    /// 启用并行时最多 `max_parallel` 个工作线程同时执行用例，每个用例使用独立的运行时并受
+    /// `timeout` 限制；结果按用例原始顺序返回。
+    /// Cases run on up to `max_parallel` workers, each with its own runtime and bounded by `timeout`;
+    /// results keep the original case order.
     pub fn run_test_suite(&mut self, suite_name: &str, module: &WebAssembly2Module) -> Result<TestSuiteResult, DeveloperToolsError> {
         let test_cases = {
             let suite = self.test_suites.get(suite_name)
@@ -1057,18 +2989,45 @@ impl WasmTestFramework {
         };
 
         let start_time = Instant::now();
-        let mut results = Vec::new();
-
-        for test_case in &test_cases {
-            let result = self.run_test_case(test_case, module)?;
-            results.push(result);
-        }
+        let workers = if self.test_config.parallel_enabled { self.test_config.max_parallel.max(1) } else { 1 };
+        let next_case = AtomicUsize::new(0);
+        let slots: Mutex<Vec<Option<TestCaseResult>>> = Mutex::new(vec![None; test_cases.len()]);
+        let coverage = self.test_config.coverage_enabled.then(|| Arc::new(Mutex::new(HashMap::new())));
+        let framework = &*self;
+        std::thread::scope(|scope| {
+            for _ in 0..workers.min(test_cases.len()) {
+                scope.spawn(|| loop {
+                    let index = next_case.fetch_add(1, Ordering::Relaxed);
+                    let Some(test_case) = test_cases.get(index) else {
+                        break;
+                    };
+                    let result = framework.run_test_case(test_case, module, coverage.as_ref());
+                    if let Ok(mut slots) = slots.lock() {
+                        slots[index] = Some(result);
+                    }
+                });
+            }
+        });
+        let results: Vec<TestCaseResult> = slots.into_inner()
+            .map_err(|_| DeveloperToolsError::RuntimeError("测试工作线程异常退出".to_string()))?
+            .into_iter()
+            .collect::<Option<_>>()
+            .ok_or_else(|| DeveloperToolsError::RuntimeError("测试工作线程异常退出".to_string()))?;
 
         let execution_time = start_time.elapsed();
+        let coverage = match coverage {
+            Some(executed) => {
+                let executed = executed.lock()
+                    .map_err(|_| DeveloperToolsError::RuntimeError("覆盖率数据已损坏".to_string()))?;
+                Some(CoverageReport::from_executed(module, &executed))
+            }
+            None => None,
+        };
         
         // 更新测试套件
         if let Some(suite) = self.test_suites.get_mut(suite_name) {
             suite.execution_time = execution_time;
+            suite.test_results = results.clone();
         }
 
         Ok(TestSuiteResult {
@@ -1077,49 +3036,289 @@ impl WasmTestFramework {
             total_execution_time: execution_time,
             passed_count: results.iter().filter(|r| r.passed).count(),
             failed_count: results.iter().filter(|r| !r.passed).count(),
+            coverage,
         })
     }
 
-    /// 运行测试用例
-    /// Run test case
-    #[allow(unused_variables)]
-    fn run_test_case(&self, test_case: &TestCaseSpecification, module: &WebAssembly2Module) -> Result<TestCaseResult, DeveloperToolsError> {
+    /// 运行测试用例：在独立的运行时中执行被测函数并比较全部返回值
+    /// Run test case: execute the target function on a fresh runtime and compare all results
+    ///
+    /// `Error` 类型的用例以发生陷阱为通过条件，给出 `expected_error` 时陷阱信息还必须与之匹配；
+    /// 其余类型发生陷阱即失败。找不到被测函数、模块加载失败和超时属于测试框架错误，任何类型的用例都失败。
+    /// 未给出期望输出的用例只要正常返回即通过。
+    /// `Error` cases pass only when the function traps with the `expected_error`, if one is given; any other case
+    /// fails on a trap. A missing function, a load failure or a timeout is a harness failure and fails every case.
+    /// Cases without an expected output pass whenever the function returns normally.
+    fn run_test_case(
+        &self,
+        test_case: &TestCaseSpecification,
+        module: &WebAssembly2Module,
+        coverage: Option<&InstructionCoverage>,
+    ) -> TestCaseResult {
         let start_time = Instant::now();
-        
-        // 模拟测试执行
-        let actual_output = match test_case.test_case_type {
-            TestCaseType::Normal => {
-                // 正常测试逻辑
-                Some(Value::I32(42))
-            }
-            TestCaseType::Boundary => {
-                // 边界测试逻辑
-                Some(Value::I32(0))
-            }
-            TestCaseType::Error => {
-                // 错误测试逻辑
-                None
-            }
-            TestCaseType::Performance => {
-                // 性能测试逻辑
-                Some(Value::I32(100))
+        let outcome = self.execute_test_case(test_case, module, coverage);
+        let execution_time = start_time.elapsed();
+
+        let expects_trap = matches!(test_case.test_case_type, TestCaseType::Error);
+        let mut snapshot_status = None;
+        let (passed, actual_output, error_message) = match outcome {
+            TestCaseOutcome::Returned(values, _) if expects_trap => {
+                (false, Some(values), Some("期望发生陷阱，但函数正常返回".to_string()))
             }
+            TestCaseOutcome::Returned(values, memory) => match &test_case.expected_output {
+                Some(expected) if *expected != values => {
+                    let message = format!("输出不匹配: 期望 {:?}, 实际 {:?}", expected, values);
+                    (false, Some(values), Some(message))
+                }
+                // 未给出期望输出时只检查是否发生陷阱
+                _ => match &test_case.snapshot_name {
+                    Some(snapshot_name) => {
+                        let content = snapshot_content(&values, test_case.snapshot_memory.as_ref().zip(memory.as_deref()));
+                        let (status, error_message) = self.check_snapshot(snapshot_name, &content);
+                        let passed = !matches!(status, SnapshotStatus::Mismatched);
+                        snapshot_status = Some(status);
+                        (passed, Some(values), error_message)
+                    }
+                    None => (true, Some(values), None),
+                },
+            },
+            TestCaseOutcome::Trapped(message) => match &test_case.expected_error {
+                Some(expected) if expects_trap && !message.contains(expected.as_str()) => {
+                    (false, None, Some(format!("陷阱不匹配: 期望 {}, 实际 {}", expected, message)))
+                }
+                _ => (expects_trap, None, Some(message)),
+            },
+            TestCaseOutcome::Failed(message) => (false, None, Some(format!("测试框架错误: {}", message))),
         };
 
-        let execution_time = start_time.elapsed();
-        let passed = actual_output == test_case.expected_output;
-
-        Ok(TestCaseResult {
+        TestCaseResult {
             test_name: test_case.name.clone(),
             passed,
             execution_time,
-            expected_output: test_case.expected_output,
+            expected_output: test_case.expected_output.clone(),
             actual_output,
-            error_message: if passed { None } else { Some("Test failed".to_string()) },
+            error_message,
+            snapshot_status,
+        }
+    }
+
+    /// 比较快照：快照不存在时创建，启用 `update_snapshots` 时覆盖，不一致时返回统一差异格式的错误信息
+    /// Compare against a stored snapshot: create it when missing, rewrite it in update mode,
+    /// and report a unified diff on mismatch
+    fn check_snapshot(&self, snapshot_name: &str, content: &str) -> (SnapshotStatus, Option<String>) {
+        let path = self.test_config.snapshot_directory.join(format!("{}.snap.json", snapshot_name));
+        let write = |status: SnapshotStatus| {
+            let result = fs::create_dir_all(&self.test_config.snapshot_directory)
+                .and_then(|_| fs::write(&path, content));
+            match result {
+                Ok(()) => (status, None),
+                Err(e) => (SnapshotStatus::Mismatched, Some(format!("无法写入快照 {}: {}", path.display(), e))),
+            }
+        };
+
+        match fs::read_to_string(&path) {
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => write(SnapshotStatus::New),
+            Err(e) => (SnapshotStatus::Mismatched, Some(format!("无法读取快照 {}: {}", path.display(), e))),
+            Ok(stored) if stored == content => (SnapshotStatus::Matched, None),
+            Ok(_) if self.test_config.update_snapshots => write(SnapshotStatus::Updated),
+            Ok(stored) => {
+                let diff = unified_diff(&stored, content, &format!("snapshots/{}", snapshot_name), "actual");
+                (SnapshotStatus::Mismatched, Some(format!("快照不匹配: {}\n{}", snapshot_name, diff)))
+            }
+        }
+    }
+
+    /// 在运行时模板的副本中加载模块并执行被测函数
+    /// Load the module into a copy of the runtime template and execute the target function
+    fn execute_test_case(
+        &self,
+        test_case: &TestCaseSpecification,
+        module: &WebAssembly2Module,
+        coverage: Option<&InstructionCoverage>,
+    ) -> TestCaseOutcome {
+        let Some(function_index) = resolve_test_target(module, &test_case.export_name) else {
+            return TestCaseOutcome::Failed(format!("未找到被测函数: {}", test_case.export_name));
+        };
+        let mut runtime = self.runtime.clone();
+        let module_id = match runtime.load_module(module.clone()) {
+            Ok(module_id) => module_id,
+            Err(e) => return TestCaseOutcome::Failed(e.to_string()),
+        };
+
+        let timeout = self.test_config.timeout;
+        runtime.execution_deadline = Some(Instant::now() + timeout);
+        runtime.coverage = coverage.cloned();
+        match runtime.execute_function(&module_id, function_index, test_case.inputs.clone()) {
+            Ok(values) => match &test_case.snapshot_memory {
+                Some(range) => match read_memory_range(&runtime, &module_id, range) {
+                    Ok(bytes) => TestCaseOutcome::Returned(values, Some(bytes)),
+                    Err(message) => TestCaseOutcome::Failed(message),
+                },
+                None => TestCaseOutcome::Returned(values, None),
+            },
+            Err(WebAssembly2Error::Trap(message)) => TestCaseOutcome::Trapped(message),
+            Err(WebAssembly2Error::DeadlineExceeded) => {
+                TestCaseOutcome::Failed(format!("执行超时 (timed out after {:?})", timeout))
+            }
+            Err(e) => TestCaseOutcome::Failed(e.to_string()),
+        }
+    }
+}
+
+/// 读取执行结束后指定范围的内存
+/// Read a memory range after execution
+fn read_memory_range(runtime: &WebAssembly2Runtime, module_id: &ModuleId, range: &SnapshotMemoryRange) -> Result<Vec<u8>, String> {
+    let memory = runtime.modules.get(module_id)
+        .and_then(|module| module.memories.get(range.memory_index as usize))
+        .ok_or_else(|| format!("无效的内存索引: {}", range.memory_index))?;
+    let start = range.offset as usize;
+    let end = start + range.length as usize;
+    memory.data.get(start..end)
+        .map(|bytes| bytes.to_vec())
+        .ok_or_else(|| format!("内存范围越界: {}..{}", start, end))
+}
+
+/// 生成快照的规范 JSON 文本：键按字母序排列，浮点数使用固定的文本格式
+/// Canonical snapshot JSON: keys in sorted order and a fixed float text format
+fn snapshot_content(values: &[Value], memory: Option<(&SnapshotMemoryRange, &[u8])>) -> String {
+    let outputs: Vec<serde_json::Value> = values.iter()
+        .map(|value| {
+            let (value_type, value) = match value {
+                Value::I32(v) => ("i32", serde_json::json!(v)),
+                Value::I64(v) => ("i64", serde_json::json!(v)),
+                Value::F32(v) => ("f32", serde_json::json!(format!("{:?}", v))),
+                Value::F64(v) => ("f64", serde_json::json!(format!("{:?}", v))),
+                Value::I128(v) => ("i128", serde_json::json!(v.to_string())),
+                Value::U128(v) => ("u128", serde_json::json!(v.to_string())),
+                Value::V128(bytes) => ("v128", serde_json::json!(hex_bytes(bytes))),
+                Value::FuncRef(v) => ("funcref", serde_json::json!(v)),
+                Value::ExternRef(v) => ("externref", serde_json::json!(v)),
+            };
+            let mut entry = serde_json::Map::new();
+            entry.insert("type".to_string(), serde_json::json!(value_type));
+            entry.insert("value".to_string(), value);
+            serde_json::Value::Object(entry)
         })
+        .collect();
+
+    let mut snapshot = serde_json::Map::new();
+    if let Some((range, bytes)) = memory {
+        let mut entry = serde_json::Map::new();
+        entry.insert("bytes".to_string(), serde_json::json!(hex_bytes(bytes)));
+        entry.insert("length".to_string(), serde_json::json!(range.length));
+        entry.insert("memory_index".to_string(), serde_json::json!(range.memory_index));
+        entry.insert("offset".to_string(), serde_json::json!(range.offset));
+        snapshot.insert("memory".to_string(), serde_json::Value::Object(entry));
+    }
+    snapshot.insert("outputs".to_string(), serde_json::Value::Array(outputs));
+
+    let mut content = serde_json::to_string_pretty(&serde_json::Value::Object(snapshot)).unwrap_or_default();
+    content.push('\n');
+    content
+}
+
+/// 字节序列的十六进制文本
+/// Hex text of a byte sequence
+fn hex_bytes(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// 生成两段文本按行比较的统一差异格式
+/// Line-based unified diff between two texts
+fn unified_diff(old: &str, new: &str, old_label: &str, new_label: &str) -> String {
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+
+    // 最长公共子序列表，lcs[i][j] 为 old[i..] 与 new[j..] 的公共行数
+    let mut lcs = vec![vec![0usize; new_lines.len() + 1]; old_lines.len() + 1];
+    for i in (0..old_lines.len()).rev() {
+        for j in (0..new_lines.len()).rev() {
+            lcs[i][j] = if old_lines[i] == new_lines[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut diff = format!(
+        "--- {}\n+++ {}\n@@ -1,{} +1,{} @@\n",
+        old_label,
+        new_label,
+        old_lines.len(),
+        new_lines.len(),
+    );
+    let (mut i, mut j) = (0, 0);
+    while i < old_lines.len() || j < new_lines.len() {
+        if i < old_lines.len() && j < new_lines.len() && old_lines[i] == new_lines[j] {
+            diff.push_str(&format!(" {}\n", old_lines[i]));
+            i += 1;
+            j += 1;
+        } else if i < old_lines.len() && (j == new_lines.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            diff.push_str(&format!("-{}\n", old_lines[i]));
+            i += 1;
+        } else {
+            diff.push_str(&format!("+{}\n", new_lines[j]));
+            j += 1;
+        }
+    }
+    diff
+}
+
+/// 指定类型的边界值
+/// Boundary values of a value type
+fn boundary_values(value_type: &ValueType) -> Vec<Value> {
+    match value_type {
+        ValueType::I32 => [0, 1, -1, i32::MIN, i32::MAX].into_iter().map(Value::I32).collect(),
+        ValueType::I64 => [0, 1, -1, i64::MIN, i64::MAX].into_iter().map(Value::I64).collect(),
+        ValueType::F32 => [0.0, 1.0, -1.0, f32::MIN, f32::MAX, f32::NAN, f32::INFINITY, f32::NEG_INFINITY]
+            .into_iter().map(Value::F32).collect(),
+        ValueType::F64 => [0.0, 1.0, -1.0, f64::MIN, f64::MAX, f64::NAN, f64::INFINITY, f64::NEG_INFINITY]
+            .into_iter().map(Value::F64).collect(),
+        ValueType::I128 => [0, 1, -1, i128::MIN, i128::MAX].into_iter().map(Value::I128).collect(),
+        ValueType::U128 => [0, 1, u128::MAX].into_iter().map(Value::U128).collect(),
+        ValueType::V128 => vec![Value::V128([0; 16]), Value::V128([0xff; 16])],
+        ValueType::FuncRef => vec![Value::FuncRef(None), Value::FuncRef(Some(0))],
+        ValueType::ExternRef => vec![Value::ExternRef(None), Value::ExternRef(Some(0))],
     }
 }
 
+/// 指定类型的随机值
+/// Random value of a value type
+fn random_value(value_type: &ValueType, rng: &mut StdRng) -> Value {
+    match value_type {
+        ValueType::I32 => Value::I32(rng.random()),
+        ValueType::I64 => Value::I64(rng.random()),
+        ValueType::F32 => Value::F32(rng.random_range(-1.0e6..1.0e6)),
+        ValueType::F64 => Value::F64(rng.random_range(-1.0e12..1.0e12)),
+        ValueType::I128 => Value::I128(rng.random()),
+        ValueType::U128 => Value::U128(rng.random()),
+        ValueType::V128 => Value::V128(rng.random()),
+        ValueType::FuncRef => Value::FuncRef(Some(rng.random())),
+        ValueType::ExternRef => Value::ExternRef(Some(rng.random())),
+    }
+}
+
+/// 单个测试用例的执行结果
+/// Outcome of executing one test case
+enum TestCaseOutcome {
+    /// 正常返回（以及需要快照的内存内容）
+    Returned(Vec<Value>, Option<Vec<u8>>),
+    /// 发生陷阱
+    Trapped(String),
+    /// 无法执行或超时
+    Failed(String),
+}
+
+/// 按导出名称查找被测函数，未导出时按函数名称查找
+/// Resolve the function under test by export name, falling back to the function name
+fn resolve_test_target(module: &WebAssembly2Module, name: &str) -> Option<u32> {
+    module.exports.iter()
+        .find(|export| export.name == name && matches!(export.export_type, WebAssembly2ExportType::Function))
+        .map(|export| export.index)
+        .or_else(|| module.functions.iter().position(|function| function.name == name).map(|index| index as u32))
+}
+
 /// 测试套件
 /// Test Suite
 #[derive(Debug)]
@@ -1148,6 +3347,104 @@ pub struct TestSuiteResult {
     pub passed_count: usize,
     /// 失败数量
     pub failed_count: usize,
+    /// 指令覆盖率（启用覆盖率时）
+    pub coverage: Option<CoverageReport>,
+}
+
+/// 指令覆盖率报告
+/// Instruction coverage report
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CoverageReport {
+    /// 模块名称
+    pub module_name: String,
+    /// 各函数覆盖率
+    pub functions: Vec<FunctionCoverage>,
+    /// 已执行指令数
+    pub covered_instructions: usize,
+    /// 指令总数
+    pub total_instructions: usize,
+    /// 覆盖率百分比
+    pub coverage_percentage: f64,
+}
+
+/// 单个函数的指令覆盖率
+/// Instruction coverage of a single function
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FunctionCoverage {
+    /// 函数索引
+    pub function_index: u32,
+    /// 函数名称
+    pub function_name: String,
+    /// 已执行指令数
+    pub covered_instructions: usize,
+    /// 指令总数
+    pub total_instructions: usize,
+    /// 各指令执行次数，下标为指令索引
+    pub hit_counts: Vec<u64>,
+    /// 从未执行的指令索引
+    pub uncovered_instructions: Vec<u32>,
+}
+
+impl CoverageReport {
+    /// 根据已执行的 (函数索引, 指令索引) 计数生成报告
+    /// Build a report from execution counts keyed by (function index, instruction index)
+    pub fn from_executed(module: &WebAssembly2Module, executed: &HashMap<(u32, u32), u64>) -> Self {
+        let functions: Vec<FunctionCoverage> = module.functions.iter().enumerate()
+            .map(|(function_index, function)| {
+                let function_index = function_index as u32;
+                let hit_counts: Vec<u64> = (0..function.body.len() as u32)
+                    .map(|instruction_index| executed.get(&(function_index, instruction_index)).copied().unwrap_or(0))
+                    .collect();
+                let uncovered_instructions: Vec<u32> = hit_counts.iter().enumerate()
+                    .filter(|(_, hits)| **hits == 0)
+                    .map(|(instruction_index, _)| instruction_index as u32)
+                    .collect();
+                FunctionCoverage {
+                    function_index,
+                    function_name: function.name.clone(),
+                    covered_instructions: hit_counts.len() - uncovered_instructions.len(),
+                    total_instructions: hit_counts.len(),
+                    hit_counts,
+                    uncovered_instructions,
+                }
+            })
+            .collect();
+
+        let covered_instructions = functions.iter().map(|f| f.covered_instructions).sum();
+        let total_instructions = functions.iter().map(|f| f.total_instructions).sum();
+        let coverage_percentage = if total_instructions > 0 {
+            covered_instructions as f64 / total_instructions as f64 * 100.0
+        } else {
+            0.0
+        };
+
+        Self {
+            module_name: module.name.clone(),
+            functions,
+            covered_instructions,
+            total_instructions,
+            coverage_percentage,
+        }
+    }
+
+    /// 导出为类 LCOV 格式，每个函数一条记录，行号为从 1 开始的指令序号
+    /// Export in an LCOV-like format: one record per function, line numbers are 1-based instruction positions
+    pub fn to_lcov_like(&self) -> String {
+        let mut output = String::new();
+        for function in &self.functions {
+            output.push_str(&format!("SF:{}/{}\n", self.module_name, function.function_name));
+            let entry_hits = function.hit_counts.first().copied().unwrap_or(0);
+            output.push_str(&format!("FN:1,{}\n", function.function_name));
+            output.push_str(&format!("FNDA:{},{}\n", entry_hits, function.function_name));
+            for (instruction_index, hits) in function.hit_counts.iter().enumerate() {
+                output.push_str(&format!("DA:{},{}\n", instruction_index + 1, hits));
+            }
+            output.push_str(&format!("LF:{}\n", function.total_instructions));
+            output.push_str(&format!("LH:{}\n", function.covered_instructions));
+            output.push_str("end_of_record\n");
+        }
+        output
+    }
 }
 
 /// 测试用例结果
@@ -1161,11 +3458,27 @@ pub struct TestCaseResult {
     /// 执行时间
     pub execution_time: Duration,
     /// 期望输出
-    pub expected_output: Option<Value>,
-    /// 实际输出
-    pub actual_output: Option<Value>,
+    pub expected_output: Option<Vec<Value>>,
+    /// 实际输出（发生陷阱时为 None）
+    pub actual_output: Option<Vec<Value>>,
     /// 错误消息
     pub error_message: Option<String>,
+    /// 快照比较结果（未使用快照时为空）
+    pub snapshot_status: Option<SnapshotStatus>,
+}
+
+/// 快照比较结果
+/// Snapshot comparison status
+#[derive(Debug, Clone, PartialEq)]
+pub enum SnapshotStatus {
+    /// 首次运行，已创建快照
+    New,
+    /// 与快照一致
+    Matched,
+    /// 更新模式下已重写快照
+    Updated,
+    /// 与快照不一致
+    Mismatched,
 }
 
 /// 测试配置
@@ -1180,6 +3493,12 @@ pub struct TestConfiguration {
     pub max_parallel: usize,
     /// 是否启用覆盖率报告
     pub coverage_enabled: bool,
+    /// 模糊测试时参数取边界值的概率
+    pub fuzz_boundary_probability: f64,
+    /// 快照目录
+    pub snapshot_directory: PathBuf,
+    /// 是否用实际输出重写不一致的快照
+    pub update_snapshots: bool,
 }
 
 impl Default for TestConfiguration {
@@ -1189,6 +3508,9 @@ impl Default for TestConfiguration {
             parallel_enabled: true,
             max_parallel: 4,
             coverage_enabled: false,
+            fuzz_boundary_probability: 0.25,
+            snapshot_directory: PathBuf::from("snapshots"),
+            update_snapshots: false,
         }
     }
 }
@@ -1228,16 +3550,95 @@ impl DocGenerator {
         Ok(())
     }
 
-    /// 生成 API 文档
-    /// Generate API documentation
-    pub fn generate_api_docs(&self, module: &WebAssembly2Module) -> Result<(), DeveloperToolsError> {
-        let api_doc = self.create_api_documentation(module);
+    /// 生成 API 文档，按配置的格式写入 `api.md` 或 `api.html` 并返回文件路径
+    /// Generate API documentation as `api.md` or `api.html` depending on the configured format
+    pub fn generate_api_docs(&self, module: &WebAssembly2Module) -> Result<PathBuf, DeveloperToolsError> {
+        let (file_name, api_doc) = match &self.doc_config.format {
+            DocumentationFormat::Markdown => ("api.md", self.create_api_documentation(module)),
+            DocumentationFormat::HTML => ("api.html", self.create_html_documentation(module)),
+            other => return Err(DeveloperToolsError::UnsupportedFormat(format!("{:?}", other))),
+        };
         
-        let file_path = self.output_directory.join("api.md");
+        let file_path = self.output_directory.join(file_name);
         fs::write(&file_path, &api_doc)
             .map_err(|e| DeveloperToolsError::FileSystemError(e.to_string()))?;
 
-        Ok(())
+        Ok(file_path)
+    }
+
+    /// 创建 HTML API 文档：独立页面，包含函数导航栏、函数锚点与参数/返回值表格
+    /// Create HTML API documentation: a standalone page with a function sidebar, anchors and signature tables
+    fn create_html_documentation(&self, module: &WebAssembly2Module) -> String {
+        let name = html_escape(&module.name);
+        let mut doc = String::new();
+
+        doc.push_str("<!DOCTYPE html>\n<html lang=\"zh-CN\">\n<head>\n<meta charset=\"utf-8\">\n");
+        doc.push_str(&format!("<title>{} API 文档</title>\n", name));
+        doc.push_str(&format!("<style>\n{}</style>\n", theme_stylesheet(&self.doc_config.theme)));
+        doc.push_str("</head>\n");
+        doc.push_str(&format!("<body class=\"{}\">\n", theme_class(&self.doc_config.theme)));
+
+        // 导航栏
+        doc.push_str("<nav class=\"sidebar\">\n<h2>函数列表</h2>\n<ul>\n");
+        for function in &module.functions {
+            doc.push_str(&format!(
+                "<li><a href=\"#fn-{}\">{}</a></li>\n",
+                function.index,
+                html_escape(&function.name),
+            ));
+        }
+        doc.push_str("</ul>\n</nav>\n");
+
+        doc.push_str("<main>\n");
+        doc.push_str(&format!("<h1>{} API 文档</h1>\n", name));
+        doc.push_str(&format!("<p>模块ID: {}</p>\n", module.id.id));
+
+        doc.push_str("<h2>支持的功能</h2>\n<ul>\n");
+        for feature in &module.features {
+            doc.push_str(&format!("<li>{:?}</li>\n", feature));
+        }
+        doc.push_str("</ul>\n");
+
+        for function in &module.functions {
+            doc.push_str(&format!(
+                "<section class=\"function\" id=\"fn-{}\">\n<h3>{}</h3>\n<p>函数索引: {}</p>\n",
+                function.index,
+                html_escape(&function.name),
+                function.index,
+            ));
+            let tables = [("参数", &function.params), ("返回值", &function.results)];
+            for (title, types) in tables {
+                if types.is_empty() {
+                    continue;
+                }
+                doc.push_str(&format!("<h4>{}</h4>\n<table>\n<tr><th>#</th><th>类型</th></tr>\n", title));
+                for (i, value_type) in types.iter().enumerate() {
+                    doc.push_str(&format!("<tr><td>{}</td><td>{:?}</td></tr>\n", i, value_type));
+                }
+                doc.push_str("</table>\n");
+            }
+            if self.doc_config.include_examples {
+                doc.push_str(&format!(
+                    "<h4>反汇编</h4>\n<pre><code class=\"language-wat\">{}</code></pre>\n",
+                    html_escape(&function.to_wat()),
+                ));
+                doc.push_str(&format!(
+                    "<h4>调用示例</h4>\n<pre><code class=\"language-rust\">{}</code></pre>\n",
+                    html_escape(&call_example(function)),
+                ));
+            }
+            doc.push_str("</section>\n");
+        }
+
+        if self.doc_config.include_diagrams && !module.functions.is_empty() {
+            doc.push_str(&format!(
+                "<h2>调用关系图</h2>\n<pre class=\"mermaid\">\n{}</pre>\n",
+                html_escape(&mermaid_call_graph(module)),
+            ));
+        }
+
+        doc.push_str("</main>\n</body>\n</html>\n");
+        doc
     }
 
     /// 创建 API 文档
@@ -1278,12 +3679,140 @@ impl DocGenerator {
                 }
                 doc.push('\n');
             }
+
+            // 反汇编与调用示例
+            if self.doc_config.include_examples {
+                doc.push_str(&format!("#### 反汇编\n\n```wat\n{}```\n\n", function.to_wat()));
+                doc.push_str(&format!("#### 调用示例\n\n```rust\n{}\n```\n\n", call_example(function)));
+            }
+        }
+
+        // 调用关系图
+        if self.doc_config.include_diagrams && !module.functions.is_empty() {
+            doc.push_str(&format!("## 调用关系图\n\n```mermaid\n{}```\n", mermaid_call_graph(module)));
         }
         
         doc
     }
 }
 
+/// 生成调用函数的示例代码，参数使用对应类型的占位值
+/// Generate an example call with placeholder arguments matching the signature
+fn call_example(function: &WebAssembly2Function) -> String {
+    let arguments: Vec<&str> = function.params.iter()
+        .map(|param_type| match param_type {
+            ValueType::I32 => "Value::I32(0)",
+            ValueType::I64 => "Value::I64(0)",
+            ValueType::F32 => "Value::F32(0.0)",
+            ValueType::F64 => "Value::F64(0.0)",
+            ValueType::FuncRef => "Value::FuncRef(None)",
+            ValueType::ExternRef => "Value::ExternRef(None)",
+            ValueType::I128 => "Value::I128(0)",
+            ValueType::U128 => "Value::U128(0)",
+            ValueType::V128 => "Value::V128([0; 16])",
+        })
+        .collect();
+    format!(
+        "let results = runtime.execute_function(&module_id, {}, vec![{}])?;",
+        function.index,
+        arguments.join(", "),
+    )
+}
+
+/// 收集指令序列中直接调用的函数索引（包括 try/catch 块内的调用）
+/// Collect directly called function indices, including calls inside try/catch blocks
+fn collect_callees(instructions: &[WebAssembly2Instruction], callees: &mut BTreeSet<u32>) {
+    for instruction in instructions {
+        match instruction {
+            WebAssembly2Instruction::Call(index) | WebAssembly2Instruction::ReturnCall(index) => {
+                callees.insert(*index);
+            }
+            WebAssembly2Instruction::TryCatch(block) => {
+                collect_callees(&block.try_instructions, callees);
+                collect_callees(&block.catch_instructions, callees);
+            }
+            WebAssembly2Instruction::TryCatchAll(block) => {
+                collect_callees(&block.try_instructions, callees);
+                collect_callees(&block.catch_all_instructions, callees);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// 生成模块函数调用关系的 Mermaid 图
+/// Generate a Mermaid graph of function calls within the module
+fn mermaid_call_graph(module: &WebAssembly2Module) -> String {
+    let mut graph = String::from("graph TD\n");
+    for (index, function) in module.functions.iter().enumerate() {
+        graph.push_str(&format!("    f{}[\"{}\"]\n", index, function.name.replace('"', "#quot;")));
+    }
+    for (index, function) in module.functions.iter().enumerate() {
+        let mut callees = BTreeSet::new();
+        collect_callees(&function.body, &mut callees);
+        for callee in callees {
+            graph.push_str(&format!("    f{} --> f{}\n", index, callee));
+        }
+    }
+    graph
+}
+
+/// 转义 HTML 特殊字符
+/// Escape HTML special characters
+fn html_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// 主题对应的 `<body>` 类名
+/// `<body>` class of a theme
+fn theme_class(theme: &DocumentationTheme) -> &'static str {
+    match theme {
+        DocumentationTheme::Default => "theme-default",
+        DocumentationTheme::Dark => "theme-dark",
+        DocumentationTheme::Light => "theme-light",
+        DocumentationTheme::Custom(_) => "theme-custom",
+    }
+}
+
+/// 主题对应的内嵌样式表
+/// Embedded stylesheet of a theme
+fn theme_stylesheet(theme: &DocumentationTheme) -> String {
+    let layout = "body { display: flex; margin: 0; font-family: sans-serif; }\n\
+        .sidebar { width: 16rem; padding: 1rem; }\n\
+        main { flex: 1; padding: 1rem 2rem; }\n\
+        table { border-collapse: collapse; }\n\
+        th, td { border: 1px solid; padding: 0.25rem 0.75rem; }\n";
+    let colors = match theme {
+        DocumentationTheme::Default => {
+            ".theme-default { background: #fdfdfd; color: #222; }\n.theme-default .sidebar { background: #f0f2f5; }\n"
+                .to_string()
+        }
+        DocumentationTheme::Dark => {
+            ".theme-dark { background: #1e1e1e; color: #ddd; }\n.theme-dark .sidebar { background: #252526; }\n\
+             .theme-dark a { color: #6cb6ff; }\n"
+                .to_string()
+        }
+        DocumentationTheme::Light => {
+            ".theme-light { background: #fff; color: #111; }\n.theme-light .sidebar { background: #fafafa; }\n"
+                .to_string()
+        }
+        // 防止自定义样式提前结束 <style> 元素
+        DocumentationTheme::Custom(css) => format!("{}\n", css.replace("</", "<\\/")),
+    };
+    format!("{}{}", layout, colors)
+}
+
 /// 文档配置
 /// Documentation Configuration
 #[derive(Debug, Clone)]
@@ -1374,58 +3903,120 @@ impl ProjectManager {
         Ok(())
     }
 
-    /// 初始化项目
-    /// Initialize project
-    pub fn initialize_project(&self, project_name: String) -> Result<(), DeveloperToolsError> {
+    /// 初始化项目：按项目配置与脚手架选项生成可构建的项目骨架，返回生成的文件路径
+    /// Initialize project: generate a buildable skeleton from the project configuration and
+    /// scaffold options, returning the generated file paths
+    pub fn initialize_project(&self, options: &ScaffoldOptions) -> Result<Vec<PathBuf>, DeveloperToolsError> {
         let project_path = self.project_path.as_ref()
             .ok_or(DeveloperToolsError::ProjectPathNotSet)?;
 
-        // 创建 Cargo.toml
-        let cargo_toml = self.create_cargo_toml(&project_name);
-        let cargo_path = project_path.join("Cargo.toml");
-        fs::write(&cargo_path, &cargo_toml)
-            .map_err(|e| DeveloperToolsError::FileSystemError(e.to_string()))?;
+        let mut files = vec![
+            ("Cargo.toml", self.create_cargo_toml(options)),
+            ("README.md", self.create_readme(options)),
+            ("src/lib.rs", self.create_lib_rs(options)),
+            ("examples/basic_example.rs", self.create_example()),
+            ("tests/integration_test.rs", self.create_integration_test()),
+        ];
+        if options.with_benches {
+            files.push(("benches/performance_bench.rs", self.create_bench()));
+        }
+        if options.with_ci {
+            files.push((".github/workflows/ci.yml", self.create_ci_workflow(options)));
+        }
 
-        // 创建 README.md
-        let readme = self.create_readme(&project_name);
-        let readme_path = project_path.join("README.md");
-        fs::write(&readme_path, &readme)
-            .map_err(|e| DeveloperToolsError::FileSystemError(e.to_string()))?;
+        let mut written = Vec::with_capacity(files.len());
+        for (relative_path, content) in files {
+            let file_path = project_path.join(relative_path);
+            if let Some(parent) = file_path.parent() {
+                fs::create_dir_all(parent)
+                    .map_err(|e| DeveloperToolsError::FileSystemError(e.to_string()))?;
+            }
+            fs::write(&file_path, content)
+                .map_err(|e| DeveloperToolsError::FileSystemError(e.to_string()))?;
+            written.push(file_path);
+        }
 
-        Ok(())
+        Ok(written)
+    }
+
+    /// 项目名称对应的 crate 标识符
+    /// Crate identifier of the project name
+    fn crate_ident(&self) -> String {
+        self.project_config.project_name.replace('-', "_")
     }
 
     /// 创建 Cargo.toml
     /// Create Cargo.toml
-    fn create_cargo_toml(&self, project_name: &str) -> String {
-        format!(r#"[package]
+    fn create_cargo_toml(&self, options: &ScaffoldOptions) -> String {
+        let config = &self.project_config;
+        let mut toml = format!(r#"[package]
 name = "{}"
-version = "0.1.0"
+version = "{}"
+authors = ["{}"]
+license = "{}"
+description = "{}"
 edition = "2024"
 rust-version = "1.90"
 
-[dependencies]
-wasm = {{ path = "wasm" }}
-
 [lib]
 crate-type = ["cdylib", "rlib"]
 
+[dependencies]
+"#,
+            toml_escape(&config.project_name),
+            toml_escape(&config.project_version),
+            toml_escape(&config.author),
+            toml_escape(&config.license),
+            toml_escape(&config.description),
+        );
+
+        if options.with_wasm_bindgen {
+            toml.push_str("wasm-bindgen = \"0.2\"\n");
+        }
+        if options.with_benches {
+            toml.push_str("\n[dev-dependencies]\ncriterion = \"0.7\"\n");
+        }
+
+        toml.push_str(r#"
 [[example]]
 name = "basic_example"
 path = "examples/basic_example.rs"
-
+"#);
+        if options.with_benches {
+            toml.push_str(r#"
 [[bench]]
 name = "performance_bench"
 harness = false
-"#, project_name)
+"#);
+        }
+        if options.with_wasm_bindgen {
+            toml.push_str(r#"
+[package.metadata.wasm-pack.profile.release]
+wasm-opt = ["-O"]
+"#);
+        }
+        toml
     }
 
     /// 创建 README.md
     /// Create README.md
-    fn create_readme(&self, project_name: &str) -> String {
+    fn create_readme(&self, options: &ScaffoldOptions) -> String {
+        let config = &self.project_config;
+        let mut commands = String::from("# 编译项目\ncargo build\n\n# 运行示例\ncargo run --example basic_example\n\n");
+        if options.with_benches {
+            commands.push_str("# 运行基准测试\ncargo bench\n\n");
+        }
+        commands.push_str("# 运行测试\ncargo test\n");
+        if options.with_wasm_bindgen {
+            commands.push_str("\n# 构建 npm 包\nwasm-pack build --target web\n");
+        }
+
         format!(r#"# {}
 
-WebAssembly 2.0 + Rust 1.90 项目
+{}
+
+作者: {}
+许可证: {}
 
 ## 特性
 
@@ -1438,23 +4029,124 @@ WebAssembly 2.0 + Rust 1.90 项目
 ## 快速开始
 
 ```bash
-# 编译项目
-cargo build
+{}```
 
-# 运行示例
-cargo run --example basic_example
+## 文档
 
-# 运行基准测试
-cargo bench
+详细的 API 文档请查看 `docs/` 目录。
+"#, config.project_name, config.description, config.author, config.license, commands)
+    }
 
-# 运行测试
-cargo test
-```
+    /// 创建 src/lib.rs
+    /// Create src/lib.rs
+    fn create_lib_rs(&self, options: &ScaffoldOptions) -> String {
+        let description = &self.project_config.description;
+        if options.with_wasm_bindgen {
+            format!(r#"//! {}
+
+use wasm_bindgen::prelude::*;
+
+/// 返回问候语
+#[wasm_bindgen]
+pub fn greet(name: &str) -> String {{
+    format!("Hello, {{}}!", name)
+}}
+"#, description)
+        } else {
+            format!(r#"//! {}
+
+/// 返回问候语
+pub fn greet(name: &str) -> String {{
+    format!("Hello, {{}}!", name)
+}}
+"#, description)
+        }
+    }
 
-## 文档
+    /// 创建 examples/basic_example.rs
+    /// Create examples/basic_example.rs
+    fn create_example(&self) -> String {
+        format!(r#"fn main() {{
+    println!("{{}}", {}::greet("WebAssembly"));
+}}
+"#, self.crate_ident())
+    }
 
-详细的 API 文档请查看 `docs/` 目录。
-"#, project_name)
+    /// 创建 benches/performance_bench.rs
+    /// Create benches/performance_bench.rs
+    fn create_bench(&self) -> String {
+        format!(r#"use criterion::{{criterion_group, criterion_main, Criterion}};
+use std::hint::black_box;
+
+fn bench_greet(c: &mut Criterion) {{
+    c.bench_function("greet", |b| b.iter(|| {}::greet(black_box("WebAssembly"))));
+}}
+
+criterion_group!(benches, bench_greet);
+criterion_main!(benches);
+"#, self.crate_ident())
+    }
+
+    /// 创建 tests/integration_test.rs
+    /// Create tests/integration_test.rs
+    fn create_integration_test(&self) -> String {
+        format!(r#"#[test]
+fn greet_returns_greeting() {{
+    assert_eq!({}::greet("WebAssembly"), "Hello, WebAssembly!");
+}}
+"#, self.crate_ident())
+    }
+
+    /// 创建 CI 工作流
+    /// Create CI workflow
+    fn create_ci_workflow(&self, options: &ScaffoldOptions) -> String {
+        let mut workflow = String::from(r#"name: CI
+
+on: [push, pull_request]
+
+jobs:
+  test:
+    runs-on: ubuntu-latest
+    steps:
+      - uses: actions/checkout@v4
+      - uses: dtolnay/rust-toolchain@stable
+        with:
+          targets: wasm32-unknown-unknown
+      - run: cargo test
+      - run: cargo build --target wasm32-unknown-unknown
+"#);
+        if options.with_wasm_bindgen {
+            workflow.push_str("      - run: cargo install wasm-pack\n      - run: wasm-pack build --target web\n");
+        }
+        workflow
+    }
+}
+
+/// 转义 TOML 基本字符串中的特殊字符
+/// Escape special characters in a TOML basic string
+fn toml_escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// 项目脚手架选项
+/// Project scaffold options
+#[derive(Debug, Clone)]
+pub struct ScaffoldOptions {
+    /// 是否生成基准测试
+    pub with_benches: bool,
+    /// 是否使用 wasm-bindgen 导出函数并生成 wasm-pack 配置
+    pub with_wasm_bindgen: bool,
+    /// 是否生成 CI 工作流
+    pub with_ci: bool,
+}
+
+impl Default for ScaffoldOptions {
+    fn default() -> Self {
+        Self {
+            with_benches: true,
+            with_wasm_bindgen: true,
+            with_ci: false,
+        }
     }
 }
 
@@ -1508,78 +4200,1165 @@ pub enum DeveloperToolsError {
     /// 项目路径不存在
     #[error("项目路径不存在: {0}")]
     ProjectPathNotFound(String),
+    /// 模板渲染错误
+    #[error("模板渲染错误: {0}")]
+    TemplateRenderError(String),
+    /// 调试表达式错误
+    #[error("表达式错误: {0}")]
+    ExpressionError(String),
+    /// 调试会话未找到
+    #[error("调试会话未找到: {0}")]
+    DebugSessionNotFound(String),
+    /// 运行时错误
+    #[error("运行时错误: {0}")]
+    RuntimeError(String),
+    /// 不支持的文档格式
+    #[error("不支持的文档格式: {0}")]
+    UnsupportedFormat(String),
+    /// 文件已存在
+    #[error("文件已存在: {}", .0.display())]
+    FileExists(PathBuf),
 }
 
-// 创建模板文件内容
-// const WASM_MODULE_TEMPLATE: &str = r#"
-// // 自动生成的 WebAssembly 模块
-// // 模块名称: {{MODULE_NAME}}
-// // 描述: {{MODULE_DESCRIPTION}}
-// 
-// use wasm::webassembly_2_0::*;
-// use wasm::types::*;
-// 
-// /// {{MODULE_NAME}} 模块
-// pub struct {{MODULE_NAME}}Module {
-//     pub module: WebAssembly2Module,
-// }
-// 
-// impl {{MODULE_NAME}}Module {
-//     /// 创建新模块
-//     pub fn new() -> Self {
-//         let mut module = WebAssembly2Module::new("{{MODULE_NAME}}".to_string());
-//         
-//         // 启用特性
-//         {{#each features}}
-//         module.enable_feature(WebAssembly2Features::{{this}});
-//         {{/each}}
-//         
-//         Self { module }
-//     }
-// }
-// "#;
-
-// const BINDINGS_TEMPLATE: &str = r#"
-// // 自动生成的绑定代码
-// // 模块: {{MODULE_NAME}}
-// 
-// use wasm::types::*;
-// 
-// /// {{MODULE_NAME}} 绑定
-// pub mod {{MODULE_NAME}}_bindings {
-//     use super::*;
-//     
-//     // 绑定函数
-//     {{#each functions}}
-//     pub fn {{name}}() -> Result<Value, Box<dyn std::error::Error>> {
-//         // 绑定实现
-//         Ok(Value::I32(0))
-//     }
-//     {{/each}}
-// }
-// "#;
-
-// const TESTS_TEMPLATE: &str = r#"
-// // 自动生成的测试代码
-// // 模块: {{MODULE_NAME}}
-// 
-// #[cfg(test)]
-// mod tests {
-//     use super::*;
-//     use wasm::webassembly_2_0::*;
-//     
-//     #[test]
-//     fn test_{{MODULE_NAME}}_module() {
-//         let module = {{MODULE_NAME}}Module::new();
-//         assert!(!module.module.functions.is_empty());
-//     }
-//     
-//     {{#each test_cases}}
-//     #[test]
-//     fn test_{{name}}() {
-//         // 测试实现
-//         assert!(true);
-//     }
-//     {{/each}}
-// }
-// "#;
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn function_spec(name: &str, multi_value: bool) -> FunctionSpecification {
+        FunctionSpecification {
+            name: name.to_string(),
+            description: format!("{} 函数", name),
+            parameters: vec![
+                ParameterSpecification {
+                    name: "a".to_string(),
+                    parameter_type: ValueType::I32,
+                    description: "左操作数".to_string(),
+                    required: true,
+                },
+                ParameterSpecification {
+                    name: "b".to_string(),
+                    parameter_type: ValueType::I32,
+                    description: "右操作数".to_string(),
+                    required: true,
+                },
+            ],
+            return_type: Some(ValueType::I32),
+            multi_value,
+            additional_results: if multi_value { vec![ValueType::I32] } else { Vec::new() },
+            tail_call: false,
+        }
+    }
+
+    fn module_spec() -> ModuleSpecification {
+        ModuleSpecification {
+            name: "calculator".to_string(),
+            description: "简单计算器".to_string(),
+            functions: vec![function_spec("add", false), function_spec("divmod", true)],
+            imports: Vec::new(),
+            exports: Vec::new(),
+            features: vec![WebAssembly2Features::MultiValue],
+            security_policy: None,
+        }
+    }
+
+    #[test]
+    fn test_render_wasm_module_template() {
+        let engine = TemplateEngine::new();
+        let template = engine.get_template("wasm_module").unwrap();
+        let code = engine.render_template(template, &module_spec()).unwrap();
+
+        assert!(code.contains("pub struct calculatorModule"));
+        assert!(code.contains("module.enable_feature(WebAssembly2Features::MultiValue);"));
+        assert!(code.contains("pub fn add(&self, runtime: &mut WebAssembly2Runtime, a: Value, b: Value)"));
+        assert!(code.contains("pub fn divmod(&self, runtime: &mut WebAssembly2Runtime, a: Value, b: Value)"));
+        assert!(code.contains("runtime.execute_function(&self.module.id, 1, vec![a, b, ])"));
+        assert_eq!(code.matches("支持多值返回").count(), 1);
+        assert!(!code.contains("{{"));
+    }
+
+    #[test]
+    fn test_render_loops_conditionals_and_paths() {
+        let engine = TemplateEngine::new();
+        let data = serde_json::json!({
+            "module": { "name": "demo" },
+            "items": [
+                { "name": "x", "enabled": true },
+                { "name": "y", "enabled": false },
+            ],
+        });
+        let template = "{{module.name}}:\n{{#each items}}\n- {{@index}} {{name}} {{#if enabled}}on{{else}}off{{/if}} ({{module.name}})\n{{/each}}\n";
+
+        let rendered = engine.render_template(template, &data).unwrap();
+        assert_eq!(rendered, "demo:\n- 0 x on (demo)\n- 1 y off (demo)\n");
+    }
+
+    #[test]
+    fn test_render_unknown_placeholder_names_key() {
+        let engine = TemplateEngine::new();
+        let result = engine.render_template("{{#each functions}}{{retrun_type}}{{/each}}", &module_spec());
+
+        match result {
+            Err(DeveloperToolsError::TemplateRenderError(message)) => assert!(message.contains("retrun_type")),
+            other => panic!("expected TemplateRenderError, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_render_rejects_unbalanced_blocks() {
+        let engine = TemplateEngine::new();
+        let data = serde_json::json!({ "items": [] });
+
+        assert!(matches!(
+            engine.render_template("{{#each items}}", &data),
+            Err(DeveloperToolsError::TemplateRenderError(_))
+        ));
+        assert!(matches!(
+            engine.render_template("{{#if items}}{{/each}}", &data),
+            Err(DeveloperToolsError::TemplateRenderError(_))
+        ));
+    }
+
+    #[test]
+    fn test_generate_wasm_module_uses_specification() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut generator = CodeGenerator::new();
+        generator.set_output_directory(dir.path().to_path_buf()).unwrap();
+
+        let generated = generator.generate_wasm_module(module_spec()).unwrap();
+        assert_eq!(generated.file_name, "calculator.rs");
+        assert!(generated.content.contains("\"add\".to_string()"));
+        assert!(generated.content.contains("\"divmod\".to_string()"));
+        assert_eq!(fs::read_to_string(dir.path().join("calculator.rs")).unwrap(), generated.content);
+    }
+
+    #[test]
+    fn test_generate_typescript_defs() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut generator = CodeGenerator::new();
+        generator.set_output_directory(dir.path().to_path_buf()).unwrap();
+
+        let mut spec = module_spec();
+        let mut timestamp = function_spec("delete", false);
+        timestamp.description = "删除并返回时间戳".to_string();
+        timestamp.parameters.truncate(1);
+        timestamp.parameters[0].required = false;
+        timestamp.return_type = Some(ValueType::I64);
+        spec.functions.push(timestamp);
+        spec.exports = vec![
+            ExportSpecification {
+                name: "memory".to_string(),
+                export_type: ExportTypeSpecification::Memory,
+                description: "线性内存".to_string(),
+            },
+            ExportSpecification {
+                name: "table".to_string(),
+                export_type: ExportTypeSpecification::Table,
+                description: "函数表".to_string(),
+            },
+            ExportSpecification {
+                name: "sum".to_string(),
+                export_type: ExportTypeSpecification::Function("add".to_string()),
+                description: "add 的别名".to_string(),
+            },
+        ];
+
+        let generated = generator.generate_typescript_defs(&spec).unwrap();
+        assert_eq!(generated.file_name, "calculator.d.ts");
+        assert!(matches!(generated.language, ProgrammingLanguage::TypeScript));
+        assert_eq!(
+            generated.content,
+            "// 自动生成的 TypeScript 声明文件\n\
+             // 模块: calculator\n\
+             // 描述: 简单计算器\n\
+             \n\
+             /**\n * add 函数\n * @param a 左操作数\n * @param b 右操作数\n */\n\
+             export function add(a: number, b: number): number;\n\
+             \n\
+             /**\n * divmod 函数\n * @param a 左操作数\n * @param b 右操作数\n */\n\
+             export function divmod(a: number, b: number): [number, number];\n\
+             \n\
+             /**\n * 删除并返回时间戳\n * @param a 左操作数\n */\n\
+             export function delete_(a?: number): bigint;\n\
+             \n\
+             export const memory: WebAssembly.Memory;\n\
+             export const table: WebAssembly.Table;\n\
+             export const sum: typeof add;\n"
+        );
+        assert!(dir.path().join("calculator.d.ts").exists());
+    }
+
+    /// 简单的 Python 结构检查：缩进为 4 的倍数、冒号结尾的行后有更深缩进、括号配对
+    fn assert_python_structure(code: &str) {
+        let lines: Vec<&str> = code.lines().filter(|line| !line.trim().is_empty()).collect();
+        let mut in_docstring = false;
+        for (i, line) in lines.iter().enumerate() {
+            let indent = line.len() - line.trim_start().len();
+            assert_eq!(indent % 4, 0, "bad indentation: {:?}", line);
+            assert!(!line.contains('\t'), "tab in line: {:?}", line);
+            if line.matches("\"\"\"").count() == 1 {
+                in_docstring = !in_docstring;
+            }
+            if !in_docstring && line.trim_end().ends_with(':') && !line.trim_start().starts_with('#') {
+                let next = lines.get(i + 1).expect("block without body");
+                let next_indent = next.len() - next.trim_start().len();
+                assert!(next_indent > indent, "block not indented after {:?}", line);
+            }
+        }
+        assert!(!in_docstring, "unterminated docstring");
+        for (open, close) in [('(', ')'), ('[', ']'), ('{', '}')] {
+            assert_eq!(code.matches(open).count(), code.matches(close).count());
+        }
+    }
+
+    #[test]
+    fn test_generate_python_bindings_golden() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut generator = CodeGenerator::new();
+        generator.set_output_directory(dir.path().to_path_buf()).unwrap();
+
+        let mut scale = function_spec("scale", false);
+        scale.description = "按比例缩放".to_string();
+        scale.parameters[1].parameter_type = ValueType::F64;
+        scale.return_type = Some(ValueType::F64);
+
+        let spec = BindingSpecification {
+            module_name: "calculator".to_string(),
+            binding_type: BindingType::Python,
+            target_language: ProgrammingLanguage::Python,
+            functions: vec![function_spec("add", false), scale],
+            exports: vec![ExportSpecification {
+                name: "memory".to_string(),
+                export_type: ExportTypeSpecification::Memory,
+                description: "线性内存".to_string(),
+            }],
+        };
+
+        let generated = generator.generate_bindings(spec).unwrap();
+        assert_eq!(generated.file_name, "calculator_bindings.py");
+        assert!(matches!(generated.language, ProgrammingLanguage::Python));
+        assert_python_structure(&generated.content);
+        assert_eq!(generated.content, include_str!("../tests/golden/calculator_bindings.py"));
+    }
+
+    #[test]
+    fn test_generate_wasm_module_applies_code_style() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut generator = CodeGenerator::new();
+        generator.set_output_directory(dir.path().to_path_buf()).unwrap();
+
+        generator.code_style = CodeStyle { line_length: 120, ..CodeStyle::default() };
+        let wide = generator.generate_wasm_module(module_spec()).unwrap().content;
+
+        generator.code_style = CodeStyle {
+            indent_size: 4,
+            use_tabs: true,
+            line_length: 80,
+            trailing_comma: false,
+            single_quotes: false,
+        };
+        let narrow = generator.generate_wasm_module(module_spec()).unwrap().content;
+
+        assert!(!wide.contains('\t'));
+        assert!(wide.contains(
+            "\n    pub fn add(&self, runtime: &mut WebAssembly2Runtime, a: Value, b: Value) -> Result<Vec<Value>, WebAssembly2Error> {\n"
+        ));
+        assert!(wide.contains("            vec![ValueType::I32],\n        ));"));
+
+        assert!(narrow.contains(
+            "\n\tpub fn add(\n\t\t&self,\n\t\truntime: &mut WebAssembly2Runtime,\n\t\ta: Value,\n\t\tb: Value\n\t) -> Result<Vec<Value>, WebAssembly2Error> {\n"
+        ));
+        assert!(narrow.contains("\t\t\tvec![ValueType::I32]\n\t\t));"));
+        assert!(narrow.contains("\t\tlet mut module = WebAssembly2Module::new(\"calculator\".to_string());"));
+        for line in narrow.lines() {
+            let width: usize = line.chars().map(|c| if c == '\t' { 4 } else { 1 }).sum();
+            assert!(width <= 80, "line too long: {:?}", line);
+        }
+    }
+
+    #[test]
+    fn test_generate_python_bindings_applies_code_style() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut generator = CodeGenerator::new();
+        generator.set_output_directory(dir.path().to_path_buf()).unwrap();
+        generator.code_style = CodeStyle {
+            indent_size: 2,
+            use_tabs: false,
+            line_length: 80,
+            trailing_comma: true,
+            single_quotes: true,
+        };
+
+        let spec = BindingSpecification {
+            module_name: "calculator".to_string(),
+            binding_type: BindingType::Python,
+            target_language: ProgrammingLanguage::Python,
+            functions: vec![function_spec("add", false)],
+            exports: vec![ExportSpecification {
+                name: "memory".to_string(),
+                export_type: ExportTypeSpecification::Memory,
+                description: "线性内存".to_string(),
+            }],
+        };
+        let code = generator.generate_bindings(spec).unwrap().content;
+
+        assert!(code.contains("\n    return self._exports['add'](self._store, int(a), int(b))\n"));
+        assert!(code.contains(
+            "\n  def read_bytes(\n    self,\n    offset: int,\n    length: int,\n    memory: str = 'memory',\n  ) -> bytes:\n"
+        ));
+        assert!(code.contains("\n    \"\"\"add 函数\n"));
+        assert!(!code.contains("\"memory\""));
+    }
+
+    /// 入口函数 `main(counter) = counter + 2 + 3`，共 5 条指令
+    fn debug_module() -> WebAssembly2Module {
+        let mut module = WebAssembly2Module::new("debuggee".to_string());
+        let mut function = WebAssembly2Function::new(0, "main".to_string(), vec![ValueType::I32], vec![ValueType::I32]);
+        function.body = vec![
+            WebAssembly2Instruction::LocalGet(0),
+            WebAssembly2Instruction::I32Const(2),
+            WebAssembly2Instruction::I32Add,
+            WebAssembly2Instruction::I32Const(3),
+            WebAssembly2Instruction::I32Add,
+        ];
+        module.functions.push(function);
+        module
+    }
+
+    fn conditional_breakpoint(module: &WebAssembly2Module, condition: Option<&str>) -> Breakpoint {
+        Breakpoint {
+            id: 1,
+            module_id: module.id.clone(),
+            function_index: 0,
+            instruction_index: 2,
+            condition: condition.map(str::to_string),
+            enabled: true,
+        }
+    }
+
+    #[test]
+    fn test_conditional_breakpoint_stops_only_when_true() {
+        let module = debug_module();
+        let mut debugger = WasmDebugger::new();
+        debugger.set_breakpoint(conditional_breakpoint(&module, Some("local0 > 10 && function_index == 0"))).unwrap();
+
+        debugger.start_debug_session("low".to_string(), module.clone(), 0, vec![Value::I32(5)]).unwrap();
+        debugger.continue_execution("low").unwrap();
+        let session = &debugger.debug_sessions["low"];
+        assert!(matches!(session.state, DebugState::Stopped));
+        assert_eq!(session.return_values, Some(vec![Value::I32(10)]));
+
+        debugger.start_debug_session("high".to_string(), module, 0, vec![Value::I32(11)]).unwrap();
+        debugger.continue_execution("high").unwrap();
+        let session = &debugger.debug_sessions["high"];
+        assert!(matches!(session.state, DebugState::Paused));
+        assert_eq!(session.current_instruction, 2);
+        assert_eq!(debugger.evaluate("high", "instruction_index == 2").unwrap(), Value::I32(1));
+
+        debugger.continue_execution("high").unwrap();
+        assert!(matches!(debugger.debug_sessions["high"].state, DebugState::Stopped));
+    }
+
+    #[test]
+    fn test_breakpoint_condition_errors() {
+        let module = debug_module();
+        let mut debugger = WasmDebugger::new();
+
+        let result = debugger.set_breakpoint(conditional_breakpoint(&module, Some("local0 >")));
+        assert!(matches!(result, Err(DeveloperToolsError::ExpressionError(_))));
+        assert!(debugger.breakpoints.is_empty());
+
+        debugger.set_breakpoint(conditional_breakpoint(&module, Some("missing == 1"))).unwrap();
+        debugger.start_debug_session("s".to_string(), module, 0, vec![Value::I32(0)]).unwrap();
+        match debugger.continue_execution("s") {
+            Err(DeveloperToolsError::ExpressionError(message)) => assert!(message.contains("missing")),
+            other => panic!("expected ExpressionError, got {:?}", other),
+        }
+        assert!(matches!(debugger.evaluate("nope", "1"), Err(DeveloperToolsError::DebugSessionNotFound(_))));
+    }
+
+    #[test]
+    fn test_debugger_steps_real_instructions() {
+        let module = debug_module();
+        let mut debugger = WasmDebugger::new();
+        debugger.set_breakpoint(conditional_breakpoint(&module, None)).unwrap();
+        debugger.start_debug_session("s".to_string(), module, 0, vec![Value::I32(7)]).unwrap();
+
+        debugger.continue_execution("s").unwrap();
+        let session = &debugger.debug_sessions["s"];
+        assert!(matches!(session.state, DebugState::Paused));
+        assert_eq!((session.current_function, session.current_instruction), (0, 2));
+        assert_eq!(session.operand_stack(), &[Value::I32(7), Value::I32(2)]);
+        assert_eq!(debugger.get_variable_value("s", "local0"), Some(Value::I32(7)));
+        assert_eq!(debugger.get_variable_value("s", "stack1"), Some(Value::I32(2)));
+
+        debugger.step_execution("s").unwrap();
+        let session = &debugger.debug_sessions["s"];
+        assert!(matches!(session.state, DebugState::Paused));
+        assert_eq!(session.current_instruction, 3);
+        assert_eq!(session.operand_stack(), &[Value::I32(9)]);
+        assert_eq!(debugger.get_variable_value("s", "stack1"), None);
+
+        debugger.set_variable_value("s", "local0".to_string(), Value::I32(100)).unwrap();
+        assert_eq!(debugger.evaluate("s", "local0").unwrap(), Value::I32(100));
+
+        debugger.continue_execution("s").unwrap();
+        let session = &debugger.debug_sessions["s"];
+        assert!(matches!(session.state, DebugState::Stopped));
+        assert_eq!(session.return_values, Some(vec![Value::I32(12)]));
+    }
+
+    #[test]
+    fn test_debugger_reports_trap() {
+        let mut module = WebAssembly2Module::new("trap".to_string());
+        let mut function = WebAssembly2Function::new(0, "div".to_string(), Vec::new(), vec![ValueType::I32]);
+        function.body = vec![
+            WebAssembly2Instruction::I32Const(1),
+            WebAssembly2Instruction::I32Const(0),
+            WebAssembly2Instruction::I32Div,
+        ];
+        module.functions.push(function);
+
+        let mut debugger = WasmDebugger::new();
+        debugger.start_debug_session("s".to_string(), module, 0, Vec::new()).unwrap();
+        debugger.continue_execution("s").unwrap();
+        match &debugger.debug_sessions["s"].state {
+            DebugState::Error(message) => assert!(message.contains("整数除零")),
+            other => panic!("expected Error state, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_debug_expression_precedence() {
+        let mut debugger = WasmDebugger::new();
+        debugger.start_debug_session("s".to_string(), debug_module(), 0, vec![Value::I32(0)]).unwrap();
+        debugger.set_variable_value("s", "x".to_string(), Value::F64(2.5)).unwrap();
+
+        // && 优先于 ||：若从左到右结合结果为 0
+        assert_eq!(debugger.evaluate("s", "1 == 1 || 0 == 1 && 0 == 1").unwrap(), Value::I32(1));
+        assert_eq!(debugger.evaluate("s", "(1 == 1 || 0 == 1) && 0 == 1").unwrap(), Value::I32(0));
+        assert_eq!(debugger.evaluate("s", "!(x > 3) && -x < -2").unwrap(), Value::I32(1));
+        assert_eq!(debugger.evaluate("s", "x").unwrap(), Value::F64(2.5));
+        assert_eq!(
+            DebugExpression::parse("a < 1 || b").unwrap(),
+            DebugExpression::Binary(
+                DebugOperator::Or,
+                Box::new(DebugExpression::Binary(
+                    DebugOperator::Lt,
+                    Box::new(DebugExpression::Variable("a".to_string())),
+                    Box::new(DebugExpression::Literal(Value::I64(1))),
+                )),
+                Box::new(DebugExpression::Variable("b".to_string())),
+            )
+        );
+    }
+
+    /// A → B → C 的嵌套调用，C 计算 2 + 3
+    fn nested_call_module() -> WebAssembly2Module {
+        let mut module = WebAssembly2Module::new("nested".to_string());
+        let bodies = [
+            ("A", vec![WebAssembly2Instruction::Call(1)]),
+            ("B", vec![WebAssembly2Instruction::I32Const(0), WebAssembly2Instruction::Call(2), WebAssembly2Instruction::I32Add]),
+            ("C", vec![WebAssembly2Instruction::I32Const(2), WebAssembly2Instruction::I32Const(3), WebAssembly2Instruction::I32Add]),
+        ];
+        for (index, (name, body)) in bodies.into_iter().enumerate() {
+            let mut function = WebAssembly2Function::new(index as u32, name.to_string(), Vec::new(), vec![ValueType::I32]);
+            function.body = body;
+            module.functions.push(function);
+        }
+        module
+    }
+
+    #[test]
+    fn test_call_stack_for_nested_calls() {
+        let module = nested_call_module();
+        let mut debugger = WasmDebugger::new();
+        debugger.set_breakpoint(Breakpoint {
+            id: 1,
+            module_id: module.id.clone(),
+            function_index: 2,
+            instruction_index: 1,
+            condition: None,
+            enabled: true,
+        }).unwrap();
+        debugger.start_debug_session("s".to_string(), module.clone(), 0, Vec::new()).unwrap();
+        assert_eq!(debugger.get_call_stack("s").unwrap().len(), 1);
+
+        debugger.continue_execution("s").unwrap();
+        let frames: Vec<(String, u32, u32)> = debugger.get_call_stack("s").unwrap().into_iter()
+            .map(|frame| {
+                assert_eq!(frame.module_id, module.id);
+                (frame.function_name, frame.function_index, frame.call_address)
+            })
+            .collect();
+        assert_eq!(frames, vec![
+            ("A".to_string(), 0, 0),
+            ("B".to_string(), 1, 0),
+            ("C".to_string(), 2, 1),
+        ]);
+
+        debugger.continue_execution("s").unwrap();
+        let session = &debugger.debug_sessions["s"];
+        assert_eq!(session.return_values, Some(vec![Value::I32(5)]));
+        assert!(session.call_stack.is_empty());
+        assert!(matches!(debugger.get_call_stack("missing"), Err(DeveloperToolsError::DebugSessionNotFound(_))));
+    }
+
+    #[test]
+    fn test_call_depth_is_capped() {
+        let mut module = WebAssembly2Module::new("recursive".to_string());
+        let mut function = WebAssembly2Function::new(0, "forever".to_string(), Vec::new(), Vec::new());
+        function.body = vec![WebAssembly2Instruction::Call(0)];
+        module.functions.push(function);
+
+        let mut runtime = WebAssembly2Runtime::new();
+        runtime.max_call_depth = 8;
+        let module_id = runtime.load_module(module).unwrap();
+        let mut execution = runtime.start_execution(&module_id, 0, Vec::new()).unwrap();
+        let error = loop {
+            if let Err(error) = runtime.step(&mut execution) {
+                break error;
+            }
+        };
+        assert!(matches!(error, WebAssembly2Error::Trap(message) if message.contains("调用栈溢出")));
+        assert_eq!(execution.frames.len(), 8);
+    }
+
+    #[test]
+    fn test_export_folded_stacks_and_speedscope() {
+        let module = nested_call_module();
+        let frame = |index: u32| StackFrame {
+            function_name: module.functions[index as usize].name.clone(),
+            function_index: index,
+            module_id: module.id.clone(),
+            call_address: 0,
+        };
+        let mut profiler = WasmProfiler::new();
+        profiler.record_stack_sample(&module.id, &[frame(0)], Duration::from_micros(5));
+        assert!(profiler.export_folded_stacks(&module.id).is_empty());
+
+        profiler.start_profiling(module.id.clone()).unwrap();
+        profiler.record_stack_sample(&module.id, &[frame(0), frame(1), frame(2)], Duration::from_nanos(300));
+        profiler.record_stack_sample(&module.id, &[frame(0), frame(1)], Duration::from_nanos(100));
+        profiler.record_stack_sample(&module.id, &[frame(0), frame(1), frame(2)], Duration::from_nanos(200));
+        assert_eq!(profiler.export_folded_stacks(&module.id), "A;B 100\nA;B;C 500\n");
+
+        let speedscope: serde_json::Value = serde_json::from_str(&profiler.export_speedscope_json(&module.id)).unwrap();
+        assert_eq!(speedscope["shared"]["frames"], serde_json::json!([{ "name": "A" }, { "name": "B" }, { "name": "C" }]));
+        let profile = &speedscope["profiles"][0];
+        assert_eq!(profile["type"], "sampled");
+        assert_eq!(profile["samples"], serde_json::json!([[0, 1], [0, 1, 2]]));
+        assert_eq!(profile["weights"], serde_json::json!([100, 500]));
+        assert_eq!(profile["endValue"], 600);
+    }
+
+    #[test]
+    fn test_profile_function_samples_nested_stacks() {
+        let module = nested_call_module();
+        let mut runtime = WebAssembly2Runtime::new();
+        let module_id = runtime.load_module(module).unwrap();
+
+        let mut profiler = WasmProfiler::new();
+        profiler.analysis_config.sampling_interval = Duration::ZERO;
+        // 未启动分析：正常执行但不采样
+        assert_eq!(profiler.profile_function(&mut runtime, &module_id, 0, Vec::new()).unwrap(), vec![Value::I32(5)]);
+        assert!(profiler.export_folded_stacks(&module_id).is_empty());
+
+        profiler.start_profiling(module_id.clone()).unwrap();
+        let started = Instant::now();
+        profiler.profile_function(&mut runtime, &module_id, 0, Vec::new()).unwrap();
+        let elapsed = started.elapsed();
+
+        let folded = profiler.export_folded_stacks(&module_id);
+        let weights: HashMap<&str, u128> = folded.lines()
+            .map(|line| {
+                let (stack, weight) = line.rsplit_once(' ').unwrap();
+                (stack, weight.parse().unwrap())
+            })
+            .collect();
+        assert_eq!(weights.keys().copied().collect::<std::collections::BTreeSet<_>>(), ["A", "A;B", "A;B;C"].into());
+        let total: u128 = weights.values().sum();
+        assert!(total > 0 && total <= elapsed.as_nanos());
+        assert_eq!(profiler.performance_data[&module_id].function_calls[&0].call_count, 1);
+    }
+
+    #[test]
+    fn test_background_memory_sampling_detects_growth() {
+        use std::sync::atomic::{AtomicU64, Ordering};
+
+        let module_id = ModuleId::new();
+        let mut profiler = WasmProfiler::new();
+        profiler.analysis_config.sampling_interval = Duration::from_millis(5);
+
+        let usage = Arc::new(AtomicU64::new(1_000));
+        let source = usage.clone();
+        profiler.start_sampling(module_id.clone(), move || source.fetch_add(100, Ordering::SeqCst)).unwrap();
+        std::thread::sleep(Duration::from_millis(60));
+        let live = profiler.generate_performance_report(&module_id).unwrap();
+        assert!(!live.memory_usage_history.is_empty());
+        profiler.stop_sampling(&module_id);
+
+        let snapshots = &profiler.performance_data[&module_id].memory_usage;
+        assert!(snapshots.len() >= 4, "only {} snapshots", snapshots.len());
+        for pair in snapshots.windows(2) {
+            assert!(pair[1].timestamp.duration_since(pair[0].timestamp) >= Duration::from_millis(5));
+            assert_eq!(pair[1].memory_usage, pair[0].memory_usage + 100);
+        }
+
+        // 线程已停止，不再产生新快照
+        let count = snapshots.len();
+        std::thread::sleep(Duration::from_millis(15));
+        let report = profiler.generate_performance_report(&module_id).unwrap();
+        assert_eq!(report.memory_usage_history.len(), count);
+        let statistics = report.memory_statistics.unwrap();
+        assert_eq!(statistics.min, 1_000);
+        assert_eq!(statistics.max, 1_000 + 100 * (count as u64 - 1));
+        assert!(statistics.growing);
+        assert!(report.recommendations.iter().any(|r| matches!(r.recommendation_type, OptimizationType::Memory)));
+    }
+
+    #[test]
+    fn test_memory_sampling_retention_and_flat_usage() {
+        let module_id = ModuleId::new();
+        let mut profiler = WasmProfiler::new();
+        profiler.analysis_config.sampling_interval = Duration::from_millis(1);
+        profiler.analysis_config.max_snapshots = 3;
+
+        profiler.start_sampling(module_id.clone(), || 4_096).unwrap();
+        std::thread::sleep(Duration::from_millis(30));
+        profiler.stop_sampling(&module_id);
+
+        let report = profiler.generate_performance_report(&module_id).unwrap();
+        assert_eq!(report.memory_usage_history.len(), 3);
+        let statistics = report.memory_statistics.unwrap();
+        assert_eq!((statistics.min, statistics.max, statistics.mean), (4_096, 4_096, 4_096.0));
+        assert!(!statistics.growing);
+        assert!(report.recommendations.is_empty());
+
+        profiler.analysis_config.memory_analysis_enabled = false;
+        let other = ModuleId::new();
+        profiler.start_sampling(other.clone(), || 1).unwrap();
+        assert!(profiler.generate_performance_report(&other).is_none());
+    }
+
+    #[test]
+    fn test_hotspot_ranking_and_percentages() {
+        let module_id = ModuleId::new();
+        let mut profiler = WasmProfiler::new();
+        profiler.analysis_config.hotspot_top_n = 2;
+        profiler.start_profiling(module_id.clone()).unwrap();
+
+        profiler.record_function_call(&module_id, 0, Duration::from_millis(600));
+        for _ in 0..3 {
+            profiler.record_function_call(&module_id, 1, Duration::from_millis(100));
+        }
+        for _ in 0..96 {
+            profiler.record_function_call(&module_id, 2, Duration::from_micros(1));
+        }
+        profiler.record_function_call(&module_id, 3, Duration::from_millis(5));
+
+        let report = profiler.generate_performance_report(&module_id).unwrap();
+        let total_ms = 600.0 + 300.0 + 0.096 + 5.0;
+
+        let by_time: Vec<u32> = report.hotspots_by_time.iter().map(|h| h.function_index).collect();
+        assert_eq!(by_time, vec![0, 1]);
+        assert!((report.hotspots_by_time[0].time_percentage - 600.0 / total_ms * 100.0).abs() < 1e-9);
+        assert!((report.hotspots_by_time[1].time_percentage - 300.0 / total_ms * 100.0).abs() < 1e-9);
+
+        // 函数 0 与 3 各占 1/101 < 1% 的调用，被阈值过滤
+        let by_calls: Vec<(u32, u64)> = report.hotspots_by_calls.iter().map(|h| (h.function_index, h.call_count)).collect();
+        assert_eq!(by_calls, vec![(2, 96), (1, 3)]);
+        assert!((report.hotspots_by_calls[0].call_percentage - 96.0 / 101.0 * 100.0).abs() < 1e-9);
+        assert_eq!(report.hotspots_by_calls[0].average_time, Duration::from_micros(1));
+
+        assert!(report.recommendations.iter().any(|r| {
+            matches!(r.recommendation_type, OptimizationType::Performance) && r.description.contains("函数 0 占总执行时间的 66.3%")
+        }));
+        assert!(report.recommendations.iter().any(|r| {
+            matches!(r.recommendation_type, OptimizationType::Efficiency) && r.suggestion.contains("尾调用")
+                && r.description.starts_with("函数 2 占全部调用")
+        }));
+
+        profiler.analysis_config.hotspot_analysis_enabled = false;
+        let report = profiler.generate_performance_report(&module_id).unwrap();
+        assert!(report.hotspots_by_time.is_empty() && report.hotspots_by_calls.is_empty());
+    }
+
+    /// 导出 `add(a, b)`、`div(a, b)` 与多值返回的 `sum_diff(a, b)`
+    fn arithmetic_module() -> WebAssembly2Module {
+        let mut module = WebAssembly2Module::new("arithmetic".to_string());
+        module.enable_feature(WebAssembly2Features::MultiValue);
+        let binary = |instruction: WebAssembly2Instruction| vec![
+            WebAssembly2Instruction::LocalGet(0),
+            WebAssembly2Instruction::LocalGet(1),
+            instruction,
+        ];
+        let mut sum_diff = binary(WebAssembly2Instruction::I32Add);
+        sum_diff.extend(binary(WebAssembly2Instruction::I32Sub));
+        let functions = [
+            ("add", binary(WebAssembly2Instruction::I32Add), 1),
+            ("div", binary(WebAssembly2Instruction::I32Div), 1),
+            ("sum_diff", sum_diff, 2),
+        ];
+        for (index, (name, body, results)) in functions.into_iter().enumerate() {
+            let mut function = WebAssembly2Function::new(
+                index as u32,
+                format!("${}", name),
+                vec![ValueType::I32, ValueType::I32],
+                vec![ValueType::I32; results],
+            );
+            function.body = body;
+            module.functions.push(function);
+            module.exports.push(WebAssembly2Export {
+                name: name.to_string(),
+                export_type: WebAssembly2ExportType::Function,
+                index: index as u32,
+            });
+        }
+        module
+    }
+
+    fn test_case(name: &str, export_name: &str, inputs: [i32; 2], expected: Option<Vec<i32>>, test_case_type: TestCaseType) -> TestCaseSpecification {
+        TestCaseSpecification {
+            name: name.to_string(),
+            description: String::new(),
+            export_name: export_name.to_string(),
+            inputs: inputs.iter().map(|v| Value::I32(*v)).collect(),
+            expected_output: expected.map(|values| values.into_iter().map(Value::I32).collect()),
+            test_case_type,
+            expected_error: None,
+            snapshot_name: None,
+            snapshot_memory: None,
+        }
+    }
+
+    #[test]
+    fn test_framework_executes_module_functions() {
+        let mut framework = WasmTestFramework::new();
+        framework.create_test_suite("arithmetic".to_string(), TestSpecification {
+            module_name: "arithmetic".to_string(),
+            test_type: TestType::Unit,
+            test_cases: vec![
+                test_case("add_passes", "add", [2, 3], Some(vec![5]), TestCaseType::Normal),
+                test_case("add_wrong", "add", [2, 3], Some(vec![6]), TestCaseType::Normal),
+                test_case("sum_diff", "sum_diff", [7, 3], Some(vec![10, 4]), TestCaseType::Boundary),
+                test_case("div_by_zero", "div", [1, 0], None, TestCaseType::Error),
+                test_case("div_no_trap", "div", [4, 2], None, TestCaseType::Error),
+                test_case("div_trap_unexpected", "div", [1, 0], Some(vec![0]), TestCaseType::Normal),
+                test_case("missing", "mul", [1, 1], Some(vec![1]), TestCaseType::Normal),
+                TestCaseSpecification {
+                    expected_error: Some("整数除零".to_string()),
+                    ..test_case("div_expected_trap", "div", [1, 0], None, TestCaseType::Error)
+                },
+                TestCaseSpecification {
+                    expected_error: Some("整数溢出".to_string()),
+                    ..test_case("div_other_trap", "div", [1, 0], None, TestCaseType::Error)
+                },
+                test_case("missing_error", "mul", [1, 1], None, TestCaseType::Error),
+            ],
+        }).unwrap();
+
+        let result = framework.run_test_suite("arithmetic", &arithmetic_module()).unwrap();
+        let outcomes: Vec<(&str, bool)> = result.test_results.iter().map(|r| (r.test_name.as_str(), r.passed)).collect();
+        assert_eq!(outcomes, vec![
+            ("add_passes", true),
+            ("add_wrong", false),
+            ("sum_diff", true),
+            ("div_by_zero", true),
+            ("div_no_trap", false),
+            ("div_trap_unexpected", false),
+            ("missing", false),
+            ("div_expected_trap", true),
+            ("div_other_trap", false),
+            ("missing_error", false),
+        ]);
+        assert_eq!((result.passed_count, result.failed_count), (4, 6));
+
+        let wrong = &result.test_results[1];
+        assert_eq!(wrong.actual_output, Some(vec![Value::I32(5)]));
+        assert!(wrong.error_message.as_ref().unwrap().contains("输出不匹配"));
+        assert!(result.test_results[5].error_message.as_ref().unwrap().contains("整数除零"));
+        assert!(result.test_results[6].error_message.as_ref().unwrap().contains("mul"));
+        assert!(result.test_results[8].error_message.as_ref().unwrap().contains("陷阱不匹配"));
+        // 找不到被测函数是测试框架错误，即使期望发生陷阱也不算通过
+        assert!(result.test_results[9].error_message.as_ref().unwrap().starts_with("测试框架错误"));
+    }
+
+    #[test]
+    fn test_framework_runs_cases_in_parallel_with_timeout() {
+        let mut module = arithmetic_module();
+        let mut spin = WebAssembly2Function::new(3, "$spin".to_string(), vec![ValueType::I32, ValueType::I32], vec![ValueType::I32]);
+        spin.body = vec![
+            WebAssembly2Instruction::LocalGet(0),
+            WebAssembly2Instruction::LocalGet(1),
+            WebAssembly2Instruction::ReturnCall(3),
+        ];
+        module.functions.push(spin);
+        module.exports.push(WebAssembly2Export {
+            name: "spin".to_string(),
+            export_type: WebAssembly2ExportType::Function,
+            index: 3,
+        });
+
+        let mut framework = WasmTestFramework::new();
+        framework.test_config.timeout = Duration::from_millis(200);
+        framework.test_config.max_parallel = 4;
+        framework.create_test_suite("parallel".to_string(), TestSpecification {
+            module_name: "arithmetic".to_string(),
+            test_type: TestType::Unit,
+            test_cases: vec![
+                test_case("spin_1", "spin", [0, 0], Some(vec![0]), TestCaseType::Normal),
+                test_case("add", "add", [2, 3], Some(vec![5]), TestCaseType::Normal),
+                test_case("spin_2", "spin", [0, 0], None, TestCaseType::Error),
+                test_case("sum_diff", "sum_diff", [7, 3], Some(vec![10, 4]), TestCaseType::Normal),
+                test_case("spin_3", "spin", [0, 0], Some(vec![0]), TestCaseType::Normal),
+            ],
+        }).unwrap();
+
+        let started = Instant::now();
+        let result = framework.run_test_suite("parallel", &module).unwrap();
+        let parallel_elapsed = started.elapsed();
+
+        let names: Vec<&str> = result.test_results.iter().map(|r| r.test_name.as_str()).collect();
+        assert_eq!(names, vec!["spin_1", "add", "spin_2", "sum_diff", "spin_3"]);
+        let passed: Vec<bool> = result.test_results.iter().map(|r| r.passed).collect();
+        assert_eq!(passed, vec![false, true, false, true, false]);
+        for index in [0, 2, 4] {
+            assert!(result.test_results[index].error_message.as_ref().unwrap().contains("timed out after 200ms"));
+        }
+        assert!(parallel_elapsed < Duration::from_millis(550), "{:?}", parallel_elapsed);
+
+        framework.test_config.parallel_enabled = false;
+        let started = Instant::now();
+        let serial = framework.run_test_suite("parallel", &module).unwrap();
+        assert!(started.elapsed() >= Duration::from_millis(600));
+        assert_eq!(serial.passed_count, 2);
+    }
+
+    #[test]
+    fn test_framework_reports_partial_branch_coverage() {
+        let mut module = WebAssembly2Module::new("branches".to_string());
+        let mut pick = WebAssembly2Function::new(0, "$pick".to_string(), vec![ValueType::I32], vec![ValueType::I32]);
+        pick.body = vec![
+            WebAssembly2Instruction::LocalGet(0),
+            WebAssembly2Instruction::BrIf(4),
+            WebAssembly2Instruction::I32Const(10),
+            WebAssembly2Instruction::Br(7),
+            WebAssembly2Instruction::I32Const(5),
+            WebAssembly2Instruction::I32Const(15),
+            WebAssembly2Instruction::I32Add,
+        ];
+        module.functions.push(pick);
+        module.exports.push(WebAssembly2Export {
+            name: "pick".to_string(),
+            export_type: WebAssembly2ExportType::Function,
+            index: 0,
+        });
+
+        let mut framework = WasmTestFramework::new();
+        framework.create_test_suite("branches".to_string(), TestSpecification {
+            module_name: "branches".to_string(),
+            test_type: TestType::Unit,
+            test_cases: vec![TestCaseSpecification {
+                name: "pick_false".to_string(),
+                description: String::new(),
+                export_name: "pick".to_string(),
+                inputs: vec![Value::I32(0)],
+                expected_output: Some(vec![Value::I32(10)]),
+                test_case_type: TestCaseType::Normal,
+                expected_error: None,
+                snapshot_name: None,
+                snapshot_memory: None,
+            }],
+        }).unwrap();
+
+        let result = framework.run_test_suite("branches", &module).unwrap();
+        assert_eq!(result.passed_count, 1);
+        assert!(result.coverage.is_none());
+
+        framework.test_config.coverage_enabled = true;
+        let coverage = framework.run_test_suite("branches", &module).unwrap().coverage.unwrap();
+        let function = &coverage.functions[0];
+        assert_eq!((function.covered_instructions, function.total_instructions), (4, 7));
+        assert_eq!(function.uncovered_instructions, vec![4, 5, 6]);
+        assert!((coverage.coverage_percentage - 400.0 / 7.0).abs() < 1e-9);
+
+        let lcov = coverage.to_lcov_like();
+        assert!(lcov.starts_with("SF:branches/$pick\nFN:1,$pick\nFNDA:1,$pick\n"));
+        assert!(lcov.contains("DA:4,1\nDA:5,0\nDA:6,0\nDA:7,0\nLF:7\nLH:4\nend_of_record\n"));
+    }
+
+    #[test]
+    fn test_generate_fuzz_cases_is_reproducible_and_hits_boundaries() {
+        let mut framework = WasmTestFramework::new();
+        let mut spec = function_spec("div", false);
+        spec.parameters[1].parameter_type = ValueType::F64;
+
+        let cases = framework.generate_fuzz_cases(&spec, 200, 42);
+        assert_eq!(format!("{:?}", cases), format!("{:?}", framework.generate_fuzz_cases(&spec, 200, 42)));
+        assert_ne!(format!("{:?}", cases), format!("{:?}", framework.generate_fuzz_cases(&spec, 200, 43)));
+        assert!(cases.iter().all(|case| case.expected_output.is_none() && case.export_name == "div"));
+        assert!(cases.iter().any(|case| matches!(case.test_case_type, TestCaseType::Normal)));
+        assert!(cases.iter().any(|case| case.inputs[0] == Value::I32(i32::MIN)));
+        assert!(cases.iter().any(|case| matches!(case.inputs[1], Value::F64(v) if v.is_nan())));
+        assert!(cases.iter().any(|case| case.inputs[1] == Value::F64(f64::INFINITY)));
+
+        framework.test_config.fuzz_boundary_probability = 1.0;
+        let boundaries = boundary_values(&ValueType::I32);
+        let spec = function_spec("div", false);
+        let cases = framework.generate_fuzz_cases(&spec, 50, 7);
+        assert!(cases.iter().all(|case| matches!(case.test_case_type, TestCaseType::Boundary)));
+        assert!(cases.iter().flat_map(|case| &case.inputs).all(|input| boundaries.contains(input)));
+
+        // 边界值中的除数 0 会触发陷阱，其余用例只要正常返回即通过
+        framework.create_test_suite("fuzz".to_string(), TestSpecification {
+            module_name: "arithmetic".to_string(),
+            test_type: TestType::Security,
+            test_cases: cases.clone(),
+        }).unwrap();
+        let result = framework.run_test_suite("fuzz", &arithmetic_module()).unwrap();
+        for (case, outcome) in cases.iter().zip(&result.test_results) {
+            let traps = case.inputs[1] == Value::I32(0) || case.inputs == [Value::I32(i32::MIN), Value::I32(-1)];
+            assert_eq!(outcome.passed, !traps, "{:?}", case.inputs);
+        }
+        assert!(result.failed_count > 0);
+    }
+
+    #[test]
+    fn test_generate_html_api_docs() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut generator = DocGenerator::new();
+        generator.output_directory = dir.path().to_path_buf();
+
+        let mut module = WebAssembly2Module::new("math".to_string());
+        module.functions.push(WebAssembly2Function::new(
+            0,
+            "add<i32>&co".to_string(),
+            vec![ValueType::I32, ValueType::I32],
+            vec![ValueType::I32],
+        ));
+        module.functions.push(WebAssembly2Function::new(1, "reset".to_string(), Vec::new(), Vec::new()));
+
+        assert_eq!(generator.generate_api_docs(&module).unwrap(), dir.path().join("api.md"));
+
+        generator.doc_config.format = DocumentationFormat::HTML;
+        generator.doc_config.theme = DocumentationTheme::Dark;
+        let path = generator.generate_api_docs(&module).unwrap();
+        assert_eq!(path, dir.path().join("api.html"));
+        let html = fs::read_to_string(path).unwrap();
+
+        assert!(html.contains("<body class=\"theme-dark\">"));
+        assert!(html.contains(".theme-dark {"));
+        assert!(html.contains("<li><a href=\"#fn-0\">add&lt;i32&gt;&amp;co</a></li>"));
+        assert!(html.contains("<li><a href=\"#fn-1\">reset</a></li>"));
+        assert!(html.contains("<section class=\"function\" id=\"fn-0\">\n<h3>add&lt;i32&gt;&amp;co</h3>"));
+        assert!(html.contains("<section class=\"function\" id=\"fn-1\">"));
+        assert!(!html.contains("add<i32>"));
+        assert_eq!(html.matches("<tr><td>").count(), 3);
+        assert!(html.contains("<h4>参数</h4>\n<table>\n<tr><th>#</th><th>类型</th></tr>\n<tr><td>0</td><td>I32</td></tr>\n<tr><td>1</td><td>I32</td></tr>\n</table>"));
+
+        generator.doc_config.theme = DocumentationTheme::Custom("main { color: red; }</style>".to_string());
+        let html = fs::read_to_string(generator.generate_api_docs(&module).unwrap()).unwrap();
+        assert!(html.contains("<body class=\"theme-custom\">"));
+        assert!(html.contains("main { color: red; }<\\/style>"));
+
+        generator.doc_config.format = DocumentationFormat::PDF;
+        assert!(matches!(generator.generate_api_docs(&module), Err(DeveloperToolsError::UnsupportedFormat(_))));
+    }
+
+    fn call_chain_module() -> WebAssembly2Module {
+        let mut module = WebAssembly2Module::new("chain".to_string());
+        // 固定模块 ID 以保证快照稳定
+        module.id.id = 1;
+        let mut double_sum = WebAssembly2Function::new(0, "$double_sum".to_string(), vec![ValueType::I32, ValueType::F64], vec![ValueType::I32]);
+        double_sum.body = vec![
+            WebAssembly2Instruction::LocalGet(0),
+            WebAssembly2Instruction::Call(1),
+            WebAssembly2Instruction::I32Const(2),
+            WebAssembly2Instruction::I32Mul,
+        ];
+        let mut increment = WebAssembly2Function::new(1, "$increment".to_string(), vec![ValueType::I32], vec![ValueType::I32]);
+        increment.body = vec![
+            WebAssembly2Instruction::LocalGet(0),
+            WebAssembly2Instruction::I32Const(1),
+            WebAssembly2Instruction::I32Add,
+        ];
+        module.functions.push(double_sum);
+        module.functions.push(increment);
+        module
+    }
+
+    #[test]
+    fn test_api_docs_include_disassembly_examples_and_call_graph() {
+        let mut generator = DocGenerator::new();
+        let module = call_chain_module();
+        let markdown = generator.create_api_documentation(&module);
+        assert_eq!(markdown, include_str!("../tests/golden/chain_api.md"));
+
+        generator.doc_config.format = DocumentationFormat::HTML;
+        let html = generator.create_html_documentation(&module);
+        assert!(html.contains("<pre class=\"mermaid\">\ngraph TD\n    f0[&quot;$double_sum&quot;]\n"));
+        assert!(html.contains("    f0 --&gt; f1\n</pre>"));
+        assert!(html.contains("runtime.execute_function(&amp;module_id, 1, vec![Value::I32(0)])?;"));
+
+        generator.doc_config.include_examples = false;
+        generator.doc_config.include_diagrams = false;
+        let markdown = generator.create_api_documentation(&module);
+        assert!(!markdown.contains("```"));
+
+        // 空函数体与无调用关系的模块
+        generator.doc_config.include_examples = true;
+        generator.doc_config.include_diagrams = true;
+        let mut module = WebAssembly2Module::new("empty".to_string());
+        module.functions.push(WebAssembly2Function::new(0, "noop".to_string(), Vec::new(), Vec::new()));
+        let markdown = generator.create_api_documentation(&module);
+        assert!(markdown.contains("```wat\n(func $noop)\n```"));
+        assert!(markdown.contains("runtime.execute_function(&module_id, 0, vec![])?;"));
+        assert!(markdown.contains("```mermaid\ngraph TD\n    f0[\"noop\"]\n```"));
+        assert!(!generator.create_api_documentation(&WebAssembly2Module::new("none".to_string())).contains("mermaid"));
+    }
+
+    #[test]
+    fn test_initialize_project_scaffolds_buildable_skeleton() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut manager = ProjectManager::new();
+        manager.set_project_path(dir.path()).unwrap();
+        manager.project_config = ProjectConfiguration {
+            project_name: "hello-wasm".to_string(),
+            project_version: "0.2.0".to_string(),
+            author: "Ada <ada@example.com>".to_string(),
+            license: "Apache-2.0".to_string(),
+            description: "问候 \"WebAssembly\" 的示例".to_string(),
+        };
+
+        let files = manager.initialize_project(&ScaffoldOptions::default()).unwrap();
+        let expected = [
+            "Cargo.toml",
+            "README.md",
+            "src/lib.rs",
+            "examples/basic_example.rs",
+            "tests/integration_test.rs",
+            "benches/performance_bench.rs",
+        ];
+        assert_eq!(files, expected.iter().map(|path| dir.path().join(path)).collect::<Vec<_>>());
+        assert!(files.iter().all(|path| path.is_file()));
+        assert!(!dir.path().join(".github").exists());
+
+        let cargo_toml = fs::read_to_string(dir.path().join("Cargo.toml")).unwrap();
+        assert!(cargo_toml.contains("name = \"hello-wasm\"\nversion = \"0.2.0\"\nauthors = [\"Ada <ada@example.com>\"]\nlicense = \"Apache-2.0\"\n"));
+        assert!(cargo_toml.contains("description = \"问候 \\\"WebAssembly\\\" 的示例\""));
+        assert!(cargo_toml.contains("wasm-bindgen = \"0.2\""));
+        assert!(cargo_toml.contains("[[bench]]\nname = \"performance_bench\""));
+        assert!(cargo_toml.contains("[package.metadata.wasm-pack.profile.release]"));
+        assert!(fs::read_to_string(dir.path().join("src/lib.rs")).unwrap().contains("#[wasm_bindgen]\npub fn greet"));
+        assert!(fs::read_to_string(dir.path().join("benches/performance_bench.rs")).unwrap().contains("hello_wasm::greet"));
+    }
+
+    #[test]
+    fn test_initialize_project_follows_scaffold_options() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut manager = ProjectManager::new();
+        manager.set_project_path(dir.path()).unwrap();
+        let options = ScaffoldOptions { with_benches: false, with_wasm_bindgen: false, with_ci: true };
+        let files = manager.initialize_project(&options).unwrap();
+        assert!(files.contains(&dir.path().join(".github/workflows/ci.yml")));
+        assert!(!dir.path().join("benches").exists());
+        let cargo_toml = fs::read_to_string(dir.path().join("Cargo.toml")).unwrap();
+        assert!(!cargo_toml.contains("[[bench]]") && !cargo_toml.contains("wasm-bindgen"));
+        assert!(!fs::read_to_string(dir.path().join("src/lib.rs")).unwrap().contains("wasm_bindgen"));
+    }
+
+    #[test]
+    fn test_generation_modes_and_overwrite_policies() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut generator = CodeGenerator::new();
+        generator.set_output_directory(dir.path().to_path_buf()).unwrap();
+        let target = dir.path().join("calculator.rs");
+
+        generator.generation_mode = GenerationMode::InMemory;
+        let generated = generator.generate_wasm_module(module_spec()).unwrap();
+        assert_eq!(generated.file_path, None);
+        assert!(!generated.content.is_empty());
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 0);
+
+        generator.generation_mode = GenerationMode::WriteToDisk { overwrite: OverwritePolicy::Error };
+        let generated = generator.generate_wasm_module(module_spec()).unwrap();
+        assert_eq!(generated.file_path.as_deref(), Some(target.as_path()));
+        match generator.generate_wasm_module(module_spec()) {
+            Err(DeveloperToolsError::FileExists(path)) => assert_eq!(path, target),
+            other => panic!("unexpected result: {:?}", other.map(|code| code.file_name)),
+        }
+
+        fs::write(&target, "// 旧内容").unwrap();
+        generator.generation_mode = GenerationMode::WriteToDisk { overwrite: OverwritePolicy::Overwrite };
+        generator.generate_wasm_module(module_spec()).unwrap();
+        assert_eq!(fs::read_to_string(&target).unwrap(), generated.content);
+
+        fs::write(&target, "// 旧内容").unwrap();
+        generator.generation_mode = GenerationMode::WriteToDisk { overwrite: OverwritePolicy::Backup };
+        generator.generate_wasm_module(module_spec()).unwrap();
+        generator.generate_wasm_module(module_spec()).unwrap();
+        assert_eq!(fs::read_to_string(&target).unwrap(), generated.content);
+        let mut backups: Vec<String> = fs::read_dir(dir.path()).unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .filter(|name| name != "calculator.rs")
+            .collect();
+        backups.sort();
+        assert_eq!(backups.len(), 2);
+        assert!(backups.iter().all(|name| name.starts_with("calculator.rs.") && name.ends_with(".bak")));
+        let contents: Vec<String> = backups.iter().map(|name| fs::read_to_string(dir.path().join(name)).unwrap()).collect();
+        assert!(contents.contains(&"// 旧内容".to_string()));
+        assert!(contents.contains(&generated.content));
+    }
+
+    #[test]
+    fn test_snapshot_creation_mismatch_and_update() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut module = arithmetic_module();
+        let mut memory = WebAssembly2Memory::new(0, 1, None, WebAssembly2MemoryType::Standard);
+        memory.data[..4].copy_from_slice(&[0xde, 0xad, 0xbe, 0xef]);
+        module.memories.push(memory);
+
+        let mut framework = WasmTestFramework::new();
+        framework.test_config.snapshot_directory = dir.path().join("snapshots");
+        let mut case = test_case("sum_diff_snapshot", "sum_diff", [7, 3], None, TestCaseType::Normal);
+        case.snapshot_name = Some("sum_diff".to_string());
+        case.snapshot_memory = Some(SnapshotMemoryRange { memory_index: 0, offset: 0, length: 4 });
+        let suite = |inputs: [i32; 2]| TestSpecification {
+            module_name: "arithmetic".to_string(),
+            test_type: TestType::Unit,
+            test_cases: vec![TestCaseSpecification {
+                inputs: inputs.iter().map(|v| Value::I32(*v)).collect(),
+                ..case.clone()
+            }],
+        };
+
+        framework.create_test_suite("snapshot".to_string(), suite([7, 3])).unwrap();
+        let result = framework.run_test_suite("snapshot", &module).unwrap();
+        assert!(result.test_results[0].passed);
+        assert_eq!(result.test_results[0].snapshot_status, Some(SnapshotStatus::New));
+        let path = dir.path().join("snapshots/sum_diff.snap.json");
+        let stored = fs::read_to_string(&path).unwrap();
+        assert_eq!(stored, "{\n  \"memory\": {\n    \"bytes\": \"deadbeef\",\n    \"length\": 4,\n    \"memory_index\": 0,\n    \"offset\": 0\n  },\n  \"outputs\": [\n    {\n      \"type\": \"i32\",\n      \"value\": 10\n    },\n    {\n      \"type\": \"i32\",\n      \"value\": 4\n    }\n  ]\n}\n");
+
+        let result = framework.run_test_suite("snapshot", &module).unwrap();
+        assert_eq!(result.test_results[0].snapshot_status, Some(SnapshotStatus::Matched));
+
+        framework.create_test_suite("snapshot".to_string(), suite([7, 2])).unwrap();
+        let result = framework.run_test_suite("snapshot", &module).unwrap();
+        let outcome = &result.test_results[0];
+        assert!(!outcome.passed);
+        assert_eq!(outcome.snapshot_status, Some(SnapshotStatus::Mismatched));
+        let message = outcome.error_message.as_ref().unwrap();
+        assert!(message.contains("--- snapshots/sum_diff\n+++ actual\n"));
+        assert!(message.contains("-      \"value\": 4\n+      \"value\": 5\n"));
+        assert!(message.contains("-      \"value\": 10\n+      \"value\": 9\n"));
+        assert!(message.contains("       \"type\": \"i32\",\n"));
+        assert_eq!(fs::read_to_string(&path).unwrap(), stored);
+
+        framework.test_config.update_snapshots = true;
+        let result = framework.run_test_suite("snapshot", &module).unwrap();
+        assert!(result.test_results[0].passed);
+        assert_eq!(result.test_results[0].snapshot_status, Some(SnapshotStatus::Updated));
+        assert!(fs::read_to_string(&path).unwrap().contains("\"value\": 5"));
+    }
+}
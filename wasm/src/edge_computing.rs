@@ -3,12 +3,18 @@
 //! 本模块提供了边缘计算场景下的 WebAssembly 2.0 支持
 
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, VecDeque};
-use std::sync::{Arc, Mutex};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BinaryHeap, HashMap};
+use std::fmt;
+use std::sync::{Arc, Mutex, Weak};
 use std::time::Duration;
 use chrono::{DateTime, Utc};
+use sha2::{Digest, Sha256};
 use thiserror::Error;
 
+use crate::intelligent_caching::{CacheConfig, CachePolicy, CompressionPolicy, EvictionPolicy, IntelligentCacheManager};
+use crate::types::Instruction;
+
 /// 边缘计算管理器
 /// Edge Computing Manager
 #[derive(Debug)]
@@ -23,6 +29,414 @@ pub struct EdgeComputingManager {
     pub network_manager: NetworkManager,
     /// 配置
     pub config: EdgeComputingConfig,
+    /// 时钟
+    pub clock: Arc<dyn EdgeClock>,
+    /// 心跳统计
+    pub heartbeat_stats: Arc<Mutex<HeartbeatStats>>,
+    /// 死信列表：用尽尝试次数的任务
+    pub dead_letters: Arc<Mutex<Vec<FailedTask>>>,
+    /// 卸载决策配置
+    pub placement_config: PlacementConfig,
+    /// 幂等任务的结果缓存
+    pub result_cache: Arc<IntelligentCacheManager>,
+    /// 结果缓存条目的 TTL
+    pub result_cache_ttl: Duration,
+    /// 后台心跳监控任务
+    heartbeat_monitor_task: Mutex<Option<tokio::task::JoinHandle<()>>>,
+}
+
+/// 边缘计算时钟
+/// Edge clock
+pub trait EdgeClock: fmt::Debug + Send + Sync {
+    /// 当前时间
+    /// Current time
+    fn now(&self) -> DateTime<Utc>;
+}
+
+/// 系统时钟
+/// System clock
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemEdgeClock;
+
+impl EdgeClock for SystemEdgeClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// 失败重试结果
+/// Retry Outcome
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum RetryOutcome {
+    /// 任务已退回队列等待重试
+    Retrying {
+        /// 重试的节点；未能分配时为空，任务在队列中等待
+        node_id: Option<String>,
+        /// 退避结束、可以再次分发的时间
+        retry_at: DateTime<Utc>,
+    },
+    /// 尝试次数已用尽，任务移入死信列表
+    DeadLettered {
+        /// 允许的最大尝试次数
+        attempts: u32,
+    },
+}
+
+/// 失败的尝试
+/// Attempt Record
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AttemptRecord {
+    /// 第几次尝试，从 1 开始
+    pub attempt: u32,
+    /// 执行该次尝试的节点
+    pub node_id: Option<String>,
+    /// 失败原因
+    pub error: String,
+    /// 失败时间
+    pub failed_at: DateTime<Utc>,
+}
+
+/// 死信任务
+/// Failed Task
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FailedTask {
+    /// 任务，`attempt_history` 中保存每次尝试的错误
+    pub task: EdgeTask,
+    /// 最终状态：尝试次数用尽为 `Failed`，等待硬件能力超时为 `Timeout`
+    pub status: TaskExecutionStatus,
+    /// 移入死信列表的时间
+    pub dead_lettered_at: DateTime<Utc>,
+}
+
+impl FailedTask {
+    /// 每次尝试的记录
+    pub fn attempts(&self) -> &[AttemptRecord] {
+        &self.task.attempt_history
+    }
+}
+
+/// 指令成本模型
+/// Instruction Cost Model
+///
+/// 以周期数估算指令序列的计算成本，用于卸载决策。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CostModel {
+    /// 常量、局部变量读写和返回
+    pub basic: u64,
+    /// 加减法
+    pub arithmetic: u64,
+    /// 乘法
+    pub multiply: u64,
+    /// 除法
+    pub divide: u64,
+    /// 内存加载和存储
+    pub memory: u64,
+    /// 跳转
+    pub branch: u64,
+    /// 函数调用
+    pub call: u64,
+}
+
+impl Default for CostModel {
+    fn default() -> Self {
+        Self { basic: 1, arithmetic: 1, multiply: 3, divide: 20, memory: 4, branch: 2, call: 10 }
+    }
+}
+
+impl CostModel {
+    /// 单条指令的周期数
+    pub fn instruction_cost(&self, instruction: &Instruction) -> u64 {
+        match instruction {
+            Instruction::I32Const(_) | Instruction::I64Const(_) | Instruction::F32Const(_) | Instruction::F64Const(_)
+            | Instruction::GetLocal(_) | Instruction::SetLocal(_) | Instruction::Return => self.basic,
+            Instruction::I32Add | Instruction::I32Sub | Instruction::I64Add | Instruction::I64Sub => self.arithmetic,
+            Instruction::I32Mul | Instruction::I64Mul => self.multiply,
+            Instruction::I32Div | Instruction::I64Div => self.divide,
+            Instruction::I32Load { .. } | Instruction::I32Store { .. } => self.memory,
+            Instruction::Br(_) | Instruction::BrIf(_) => self.branch,
+            Instruction::Call(_) => self.call,
+        }
+    }
+
+    /// 指令序列执行 `iterations` 次的总周期数
+    pub fn estimate(&self, instructions: &[Instruction], iterations: u64) -> u64 {
+        instructions.iter().map(|instruction| self.instruction_cost(instruction)).sum::<u64>().saturating_mul(iterations)
+    }
+}
+
+/// 计算任务画像
+/// Task Profile
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskProfile {
+    /// 预计计算成本（周期数）
+    pub compute_cost: u64,
+    /// 输入数据大小 (字节)
+    pub input_bytes: u64,
+    /// 输出数据大小 (字节)
+    pub output_bytes: u64,
+    /// 延迟预算
+    pub latency_budget: Option<Duration>,
+    /// 允许存放数据的地区代码，为空时不限制
+    pub allowed_regions: Vec<String>,
+    /// 数据来源的位置
+    pub origin: Option<GeographicLocation>,
+}
+
+impl TaskProfile {
+    /// 按计算成本创建画像
+    pub fn new(compute_cost: u64) -> Self {
+        Self {
+            compute_cost,
+            input_bytes: 0,
+            output_bytes: 0,
+            latency_budget: None,
+            allowed_regions: Vec::new(),
+            origin: None,
+        }
+    }
+
+    /// 用成本模型估算指令序列执行 `iterations` 次的计算成本
+    pub fn from_instructions(cost_model: &CostModel, instructions: &[Instruction], iterations: u64) -> Self {
+        Self::new(cost_model.estimate(instructions, iterations))
+    }
+
+    /// 设置输入输出数据大小
+    pub fn with_data(mut self, input_bytes: u64, output_bytes: u64) -> Self {
+        self.input_bytes = input_bytes;
+        self.output_bytes = output_bytes;
+        self
+    }
+
+    /// 设置延迟预算
+    pub fn with_latency_budget(mut self, latency_budget: Duration) -> Self {
+        self.latency_budget = Some(latency_budget);
+        self
+    }
+
+    /// 限制数据只能存放在给定地区
+    pub fn with_allowed_regions(mut self, regions: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.allowed_regions = regions.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// 设置数据来源的位置
+    pub fn with_origin(mut self, origin: GeographicLocation) -> Self {
+        self.origin = Some(origin);
+        self
+    }
+
+    /// 数据能否存放在给定地区
+    pub fn allows_region(&self, region_code: &str) -> bool {
+        self.allowed_regions.is_empty() || self.allowed_regions.iter().any(|region| region == region_code)
+    }
+
+    fn transfer_bytes(&self) -> u64 {
+        self.input_bytes.saturating_add(self.output_bytes)
+    }
+}
+
+/// 云端执行入口
+/// Cloud Endpoint
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CloudEndpoint {
+    /// 所在地区代码
+    pub region_code: String,
+    /// 计算能力（周期/秒）
+    pub cycles_per_second: f64,
+    /// 上下行带宽 (Mbps)
+    pub bandwidth_mbps: f64,
+    /// 往返延迟
+    pub round_trip_latency: Duration,
+}
+
+/// 卸载决策权重
+/// Placement Weights
+///
+/// 候选目标的得分为目标权重乘以各时间分量（秒）的加权和，越低越好。目标权重用于表达时间之外的
+/// 代价，例如本地计算耗电、云端计费。
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PlacementWeights {
+    /// 本地执行权重
+    pub local: f64,
+    /// 边缘执行权重
+    pub edge: f64,
+    /// 云端执行权重
+    pub cloud: f64,
+    /// 计算时间权重
+    pub compute: f64,
+    /// 数据传输时间权重
+    pub transfer: f64,
+    /// 网络延迟权重
+    pub network: f64,
+    /// 排队时间权重
+    pub queue_wait: f64,
+}
+
+impl Default for PlacementWeights {
+    fn default() -> Self {
+        Self { local: 2.0, edge: 1.0, cloud: 1.5, compute: 1.0, transfer: 1.0, network: 1.0, queue_wait: 1.0 }
+    }
+}
+
+/// 卸载决策配置
+/// Placement Configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlacementConfig {
+    /// 本地设备的计算能力（周期/秒）
+    pub local_cycles_per_second: f64,
+    /// 边缘节点每 GHz 主频的计算能力（周期/秒）
+    pub edge_cycles_per_ghz: f64,
+    /// 云端回退目标，未配置时只在本地和边缘之间选择
+    pub cloud: Option<CloudEndpoint>,
+    /// 权重
+    pub weights: PlacementWeights,
+}
+
+impl Default for PlacementConfig {
+    fn default() -> Self {
+        Self {
+            local_cycles_per_second: 5e8,
+            edge_cycles_per_ghz: 1e9,
+            cloud: None,
+            weights: PlacementWeights::default(),
+        }
+    }
+}
+
+/// 执行目标
+/// Placement Target
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PlacementTarget {
+    /// 本地执行
+    Local,
+    /// 边缘节点
+    Edge {
+        /// 节点ID
+        node_id: String,
+    },
+    /// 云端
+    Cloud {
+        /// 地区代码
+        region_code: String,
+    },
+}
+
+/// 预计完成时间的组成
+/// Placement Breakdown
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PlacementBreakdown {
+    /// 计算时间
+    pub compute: Duration,
+    /// 输入输出数据的传输时间
+    pub transfer: Duration,
+    /// 网络往返延迟
+    pub network: Duration,
+    /// 在目标上的排队时间
+    pub queue_wait: Duration,
+}
+
+impl PlacementBreakdown {
+    /// 预计完成时间
+    pub fn total(&self) -> Duration {
+        self.compute.saturating_add(self.transfer).saturating_add(self.network).saturating_add(self.queue_wait)
+    }
+}
+
+/// 候选执行目标
+/// Placement Candidate
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlacementCandidate {
+    /// 目标
+    pub target: PlacementTarget,
+    /// 预计完成时间
+    pub estimated_completion: Duration,
+    /// 完成时间的组成
+    pub breakdown: PlacementBreakdown,
+    /// 加权得分，越低越好
+    pub score: f64,
+    /// 是否在延迟预算内；没有预算时总为 true
+    pub within_budget: bool,
+}
+
+impl PlacementCandidate {
+    fn new(target: PlacementTarget, breakdown: PlacementBreakdown, weights: &PlacementWeights, latency_budget: Option<Duration>) -> Self {
+        let target_weight = match target {
+            PlacementTarget::Local => weights.local,
+            PlacementTarget::Edge { .. } => weights.edge,
+            PlacementTarget::Cloud { .. } => weights.cloud,
+        };
+        let score = target_weight * (weights.compute * breakdown.compute.as_secs_f64()
+            + weights.transfer * breakdown.transfer.as_secs_f64()
+            + weights.network * breakdown.network.as_secs_f64()
+            + weights.queue_wait * breakdown.queue_wait.as_secs_f64());
+        let estimated_completion = breakdown.total();
+        Self {
+            target,
+            estimated_completion,
+            breakdown,
+            score,
+            within_budget: latency_budget.is_none_or(|budget| estimated_completion <= budget),
+        }
+    }
+}
+
+/// 卸载决策
+/// Placement Decision
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlacementDecision {
+    /// 选中的目标
+    pub chosen: PlacementCandidate,
+    /// 参与比较的全部目标，不包括被数据驻留约束排除的目标
+    pub candidates: Vec<PlacementCandidate>,
+}
+
+impl PlacementDecision {
+    /// 选中的目标
+    pub fn target(&self) -> &PlacementTarget {
+        &self.chosen.target
+    }
+
+    /// 预计完成时间
+    pub fn estimated_completion(&self) -> Duration {
+        self.chosen.estimated_completion
+    }
+}
+
+/// 时刻加上时长，超出可表示范围时取最大时刻
+fn saturating_add(time: DateTime<Utc>, duration: Duration) -> DateTime<Utc> {
+    chrono::Duration::from_std(duration).ok()
+        .and_then(|duration| time.checked_add_signed(duration))
+        .unwrap_or(DateTime::<Utc>::MAX_UTC)
+}
+
+/// 秒数转换为 `Duration`，非有限值视为无限长
+fn seconds(value: f64) -> Duration {
+    Duration::try_from_secs_f64(value).unwrap_or(Duration::MAX)
+}
+
+/// 按带宽 (Mbps) 估算传输时间；带宽为 0 时视为无法传输
+fn transfer_time(bytes: u64, bandwidth_mbps: f64) -> Duration {
+    if bytes == 0 {
+        Duration::ZERO
+    } else {
+        seconds(bytes as f64 * 8.0 / (bandwidth_mbps * 1e6))
+    }
+}
+
+/// 心跳统计
+/// Heartbeat Statistics
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HeartbeatStats {
+    /// 收到的心跳数
+    pub heartbeats: u64,
+    /// 节点因心跳超时转为离线的次数
+    pub offline_transitions: u64,
+    /// 离线节点恢复心跳的次数
+    pub recoveries: u64,
+    /// 从离线节点转移到其他节点的任务数
+    pub rescheduled_tasks: u64,
+    /// 从离线节点退回队列但没有找到新节点的任务数
+    pub requeued_tasks: u64,
 }
 
 /// 边缘节点
@@ -43,6 +457,8 @@ pub struct EdgeNode {
     pub connection_status: ConnectionStatus,
     /// 最后心跳时间
     pub last_heartbeat: DateTime<Utc>,
+    /// 声明的硬件能力
+    pub capabilities: Vec<Capability>,
 }
 
 /// 地理位置
@@ -61,6 +477,20 @@ pub struct GeographicLocation {
     pub region_code: String,
 }
 
+impl GeographicLocation {
+    /// 地球平均半径 (km)
+    const EARTH_RADIUS_KM: f64 = 6371.0;
+
+    /// 按半正矢公式计算两点间的大圆距离 (km)，忽略海拔
+    pub fn distance_km(&self, other: &GeographicLocation) -> f64 {
+        let (lat1, lat2) = (self.latitude.to_radians(), other.latitude.to_radians());
+        let d_lat = lat2 - lat1;
+        let d_lon = (other.longitude - self.longitude).to_radians();
+        let a = (d_lat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (d_lon / 2.0).sin().powi(2);
+        2.0 * Self::EARTH_RADIUS_KM * a.sqrt().min(1.0).asin()
+    }
+}
+
 /// 硬件规格
 /// Hardware Specifications
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -83,7 +513,7 @@ pub struct HardwareSpecifications {
 
 /// 特殊硬件
 /// Special Hardware
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum SpecialHardware {
     /// AI 加速器
     AiAccelerator,
@@ -95,6 +525,79 @@ pub enum SpecialHardware {
     CryptoChip,
     /// 实时处理器
     RealTimeProcessor,
+    /// 压缩加速器
+    CompressionAccelerator,
+}
+
+/// 硬件能力
+/// Capability
+///
+/// 节点对外声明、任务可以要求的硬件能力，每项都必须有对应的硬件规格支撑。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Capability {
+    /// GPU
+    Gpu,
+    /// AI 加速器
+    AiAccelerator,
+    /// 图像处理器
+    ImageProcessor,
+    /// 传感器接口
+    SensorInterface,
+    /// 加密加速
+    Encryption,
+    /// 压缩加速
+    Compression,
+    /// 实时处理
+    RealTime,
+}
+
+impl From<&SpecialHardware> for Capability {
+    fn from(hardware: &SpecialHardware) -> Self {
+        match hardware {
+            SpecialHardware::AiAccelerator => Capability::AiAccelerator,
+            SpecialHardware::ImageProcessor => Capability::ImageProcessor,
+            SpecialHardware::SensorInterface => Capability::SensorInterface,
+            SpecialHardware::CryptoChip => Capability::Encryption,
+            SpecialHardware::RealTimeProcessor => Capability::RealTime,
+            SpecialHardware::CompressionAccelerator => Capability::Compression,
+        }
+    }
+}
+
+impl HardwareSpecifications {
+    /// 硬件规格能够支撑的全部能力
+    pub fn capabilities(&self) -> Vec<Capability> {
+        let mut capabilities: Vec<Capability> = self.special_hardware.iter().map(Capability::from).collect();
+        if self.gpu_support {
+            capabilities.push(Capability::Gpu);
+        }
+        capabilities.sort();
+        capabilities.dedup();
+        capabilities
+    }
+}
+
+impl EdgeNode {
+    /// 节点是否声明了全部给定能力
+    pub fn has_capabilities(&self, required: &[Capability]) -> bool {
+        required.iter().all(|capability| self.capabilities.contains(capability))
+    }
+
+    /// 检查声明的能力没有重复且都有硬件规格支撑
+    pub fn validate_capabilities(&self) -> Result<(), EdgeComputingError> {
+        let supported = self.hardware_specs.capabilities();
+        for (index, capability) in self.capabilities.iter().enumerate() {
+            if self.capabilities[..index].contains(capability) {
+                return Err(EdgeComputingError::ConfigurationError(format!("节点 {} 重复声明能力 {:?}", self.id, capability)));
+            }
+            if !supported.contains(capability) {
+                return Err(EdgeComputingError::ConfigurationError(format!(
+                    "节点 {} 声明了能力 {:?}，但硬件规格不支持", self.id, capability
+                )));
+            }
+        }
+        Ok(())
+    }
 }
 
 /// 资源状态
@@ -148,11 +651,217 @@ pub struct TaskScheduler {
     /// 调度策略
     pub scheduling_strategy: SchedulingStrategy,
     /// 任务队列
-    pub task_queue: Arc<Mutex<VecDeque<EdgeTask>>>,
+    pub task_queue: Arc<Mutex<TaskQueue>>,
     /// 运行中的任务
     pub running_tasks: Arc<Mutex<HashMap<String, EdgeTask>>>,
     /// 任务历史
     pub task_history: Arc<Mutex<Vec<TaskExecutionRecord>>>,
+    /// 节点评分权重，运行时可调整
+    pub scoring_weights: Arc<Mutex<SchedulingWeights>>,
+    /// 提交时截止时间已过的任务的处理策略
+    pub late_policy: LateTaskPolicy,
+}
+
+/// 迟到任务策略
+/// Late Task Policy
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LateTaskPolicy {
+    /// 提交时拒绝
+    Reject,
+    /// 照常入队，分发时标记 `deadline_missed`
+    DispatchFlagged,
+}
+
+/// 任务队列
+/// Task Queue
+///
+/// 按优先级分层的最早截止时间优先（EDF）队列：有截止时间的任务先于所有尽力而为（无截止时间）的
+/// 任务；有截止时间的任务先比较优先级，同一优先级内截止时间越早越先分发；尽力而为的任务只在没有
+/// 可分发的截止任务时运行，按优先级和提交顺序分发。
+#[derive(Debug, Default)]
+pub struct TaskQueue {
+    heap: BinaryHeap<QueuedTask>,
+    next_sequence: u64,
+}
+
+/// 队列中的任务及其提交序号
+#[derive(Debug)]
+struct QueuedTask {
+    sequence: u64,
+    task: EdgeTask,
+}
+
+impl QueuedTask {
+    /// 分发顺序，`Greater` 表示先分发
+    fn dispatch_order(&self, other: &Self) -> Ordering {
+        self.task.deadline.is_some().cmp(&other.task.deadline.is_some())
+            .then_with(|| self.task.priority.cmp(&other.task.priority))
+            .then_with(|| match (self.task.deadline, other.task.deadline) {
+                (Some(a), Some(b)) => b.cmp(&a),
+                _ => Ordering::Equal,
+            })
+            .then_with(|| other.sequence.cmp(&self.sequence))
+    }
+}
+
+impl PartialEq for QueuedTask {
+    fn eq(&self, other: &Self) -> bool {
+        self.sequence == other.sequence
+    }
+}
+
+impl Eq for QueuedTask {}
+
+impl PartialOrd for QueuedTask {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for QueuedTask {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.dispatch_order(other)
+    }
+}
+
+impl TaskQueue {
+    /// 创建空队列
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 入队
+    pub fn push(&mut self, task: EdgeTask) {
+        let sequence = self.next_sequence;
+        self.next_sequence += 1;
+        self.heap.push(QueuedTask { sequence, task });
+    }
+
+    /// 取出下一个应分发的任务
+    pub fn pop(&mut self) -> Option<EdgeTask> {
+        self.heap.pop().map(|queued| queued.task)
+    }
+
+    /// 按分发顺序取出第一个满足条件的任务
+    pub fn pop_first(&mut self, mut predicate: impl FnMut(&EdgeTask) -> bool) -> Option<EdgeTask> {
+        let mut skipped = Vec::new();
+        let found = loop {
+            match self.heap.pop() {
+                Some(queued) if predicate(&queued.task) => break Some(queued.task),
+                Some(queued) => skipped.push(queued),
+                None => break None,
+            }
+        };
+        self.heap.extend(skipped);
+        found
+    }
+
+    /// 查看下一个应分发的任务
+    pub fn peek(&self) -> Option<&EdgeTask> {
+        self.heap.peek().map(|queued| &queued.task)
+    }
+
+    /// 队列深度
+    pub fn len(&self) -> usize {
+        self.heap.len()
+    }
+
+    /// 队列是否为空
+    pub fn is_empty(&self) -> bool {
+        self.heap.is_empty()
+    }
+
+    /// 取出满足条件的任务，按提交顺序返回
+    pub fn drain_where(&mut self, mut predicate: impl FnMut(&EdgeTask) -> bool) -> Vec<EdgeTask> {
+        let (mut taken, kept): (Vec<QueuedTask>, Vec<QueuedTask>) =
+            std::mem::take(&mut self.heap).into_vec().into_iter().partition(|queued| predicate(&queued.task));
+        self.heap = kept.into();
+        taken.sort_by_key(|queued| queued.sequence);
+        taken.into_iter().map(|queued| queued.task).collect()
+    }
+
+    /// 按分发顺序逐个修改队列中的任务；修改不应影响优先级和截止时间
+    pub fn for_each_mut(&mut self, mut f: impl FnMut(&mut EdgeTask)) {
+        let mut queued = std::mem::take(&mut self.heap).into_vec();
+        queued.sort_by(|a, b| b.dispatch_order(a));
+        for entry in &mut queued {
+            f(&mut entry.task);
+        }
+        self.heap = queued.into();
+    }
+
+    /// 按分发顺序列出队列中的任务
+    pub fn ordered(&self) -> Vec<&EdgeTask> {
+        let mut queued: Vec<&QueuedTask> = self.heap.iter().collect();
+        queued.sort_by(|a, b| b.dispatch_order(a));
+        queued.into_iter().map(|queued| &queued.task).collect()
+    }
+}
+
+/// 队列指标
+/// Queue Metrics
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueueMetrics {
+    /// 队列深度
+    pub depth: usize,
+    /// 各优先级的队列深度
+    pub depth_by_priority: BTreeMap<TaskPriority, usize>,
+    /// 有截止时间的任务数
+    pub deadline_tasks: usize,
+    /// 预计会错过截止时间的任务数
+    pub projected_late_tasks: usize,
+    /// 预计迟到时间总和
+    pub projected_lateness: Duration,
+    /// 单个任务的最大预计迟到时间
+    pub max_projected_lateness: Duration,
+}
+
+/// 节点评分权重
+/// Scheduling Weights
+///
+/// 节点得分为各项的加权和，越低越好：距离按每 1000 km 计 1，负载为 CPU 与内存使用率中较高者
+/// （0–1），链路延迟按每 100 ms 计 1。
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SchedulingWeights {
+    /// 任务来源到节点的距离权重
+    pub distance: f64,
+    /// 节点当前负载权重
+    pub load: f64,
+    /// 测得的链路延迟权重
+    pub latency: f64,
+}
+
+impl SchedulingWeights {
+    /// 调度策略对应的默认权重
+    pub fn for_strategy(strategy: &SchedulingStrategy) -> Self {
+        match strategy {
+            SchedulingStrategy::NearestNodeFirst => Self { distance: 1.0, load: 0.0, latency: 0.0 },
+            SchedulingStrategy::LatencyOptimization => Self { distance: 0.5, load: 0.5, latency: 1.0 },
+            SchedulingStrategy::LoadBalancing
+            | SchedulingStrategy::ResourceOptimization
+            | SchedulingStrategy::CostOptimization => Self { distance: 0.5, load: 1.0, latency: 0.5 },
+        }
+    }
+}
+
+/// 节点分配记录
+/// Node Assignment
+///
+/// 记录调度器为任务选中的节点和得分的各项组成，便于审计。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NodeAssignment {
+    /// 选中的节点
+    pub node_id: String,
+    /// 加权得分，越低越好
+    pub score: f64,
+    /// 任务来源到节点的距离 (km)，任务没有来源位置时为 0
+    pub distance_km: f64,
+    /// 节点负载 (0–1)
+    pub load: f64,
+    /// 链路延迟 (ms)，没有测量值时按距离估算
+    pub link_latency_ms: f64,
+    /// 分配时间
+    pub assigned_at: DateTime<Utc>,
 }
 
 /// 调度策略
@@ -195,6 +904,182 @@ pub struct EdgeTask {
     pub created_at: DateTime<Utc>,
     /// 截止时间
     pub deadline: Option<DateTime<Utc>>,
+    /// 任务来源（客户端）的位置
+    #[serde(default)]
+    pub origin: Option<GeographicLocation>,
+    /// 调度器的节点分配记录
+    #[serde(default)]
+    pub assignment: Option<NodeAssignment>,
+    /// 失败的尝试次数，包括节点离线导致的失败
+    #[serde(default)]
+    pub attempts: u32,
+    /// 最大尝试次数，为空时使用配置中的 `max_retry_count + 1`
+    #[serde(default)]
+    pub max_attempts: Option<u32>,
+    /// 每次失败尝试的记录
+    #[serde(default)]
+    pub attempt_history: Vec<AttemptRecord>,
+    /// 重试退避结束前不分发
+    #[serde(default)]
+    pub not_before: Option<DateTime<Utc>>,
+    /// 所属租户，用于配额统计；为空时不受配额限制
+    #[serde(default)]
+    pub tenant: Option<String>,
+    /// 结果只取决于调用输入，可以复用缓存的结果
+    #[serde(default)]
+    pub idempotent: bool,
+    /// 调用信息，幂等任务以其内容哈希作为结果缓存键
+    #[serde(default)]
+    pub invocation: Option<TaskInvocation>,
+    /// 执行节点必须具备的硬件能力
+    #[serde(default)]
+    pub required_capabilities: Vec<Capability>,
+    /// 等待所需硬件能力的最长时间，超时后任务失败；为空时一直等待
+    #[serde(default)]
+    pub capability_timeout: Option<Duration>,
+    /// 开始等待节点的时间
+    #[serde(default)]
+    pub waiting_since: Option<DateTime<Utc>>,
+    /// 任务尚未分配节点的原因
+    #[serde(default)]
+    pub pending_reason: Option<String>,
+    /// 分发时截止时间是否已过
+    #[serde(default)]
+    pub deadline_missed: bool,
+}
+
+/// 任务提交结果
+/// Task Submission
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TaskSubmission {
+    /// 已在节点上预留资源并入队
+    Scheduled {
+        /// 分配的节点
+        node_id: String,
+    },
+    /// 暂时没有节点能满足需求，以未分配状态入队等待
+    Waiting {
+        /// 等待原因
+        reason: String,
+    },
+    /// 没有在线节点具备所需的硬件能力，以未分配状态入队等待
+    WaitingForCapability {
+        /// 任务要求的能力
+        required: Vec<Capability>,
+    },
+    /// 幂等任务命中结果缓存，没有进入调度器
+    Cached {
+        /// 缓存的结果
+        result: EdgeTaskResult,
+    },
+}
+
+/// 任务结果
+/// Edge Task Result
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EdgeTaskResult {
+    /// 任务ID
+    pub task_id: String,
+    /// 执行节点，来自缓存的结果没有节点
+    pub node_id: Option<String>,
+    /// 输出数据
+    pub output: Vec<u8>,
+    /// 是否来自结果缓存
+    pub served_from_cache: bool,
+    /// 完成时间
+    pub completed_at: DateTime<Utc>,
+}
+
+/// 任务调用：结果只取决于这些输入的任务可以复用缓存的结果
+/// Task Invocation
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TaskInvocation {
+    /// 模块ID
+    pub module_id: String,
+    /// 模块版本
+    pub module_version: String,
+    /// 函数名
+    pub function: String,
+    /// 调用参数
+    pub args: serde_json::Value,
+    /// 输入数据的 SHA-256 摘要（十六进制）
+    pub input_digest: String,
+}
+
+impl TaskInvocation {
+    /// 以输入数据的摘要创建调用
+    pub fn new(
+        module_id: impl Into<String>,
+        module_version: impl Into<String>,
+        function: impl Into<String>,
+        args: serde_json::Value,
+        input: &[u8],
+    ) -> Self {
+        Self {
+            module_id: module_id.into(),
+            module_version: module_version.into(),
+            function: function.into(),
+            args,
+            input_digest: hex(&Sha256::digest(input)),
+        }
+    }
+
+    /// 内容哈希：对象键排序后的参数与模块、函数和输入摘要一起计算，参数书写顺序不影响结果
+    pub fn content_hash(&self) -> String {
+        let mut hasher = Sha256::new();
+        for part in [&self.module_id, &self.module_version, &self.function, &canonical_json(&self.args), &self.input_digest] {
+            hasher.update((part.len() as u64).to_le_bytes());
+            hasher.update(part.as_bytes());
+        }
+        hex(&hasher.finalize())
+    }
+}
+
+/// 对象键按字典序排列的 JSON 文本
+fn canonical_json(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::Object(map) => {
+            let mut entries: Vec<(&String, &serde_json::Value)> = map.iter().collect();
+            entries.sort_by(|a, b| a.0.cmp(b.0));
+            let fields: Vec<String> = entries.into_iter()
+                .map(|(key, value)| format!("{}:{}", serde_json::Value::String(key.clone()), canonical_json(value)))
+                .collect();
+            format!("{{{}}}", fields.join(","))
+        }
+        serde_json::Value::Array(items) => {
+            format!("[{}]", items.iter().map(canonical_json).collect::<Vec<_>>().join(","))
+        }
+        other => other.to_string(),
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+impl TaskSubmission {
+    /// 分配的节点；等待中和命中缓存的任务没有节点
+    pub fn node_id(&self) -> Option<&str> {
+        match self {
+            TaskSubmission::Scheduled { node_id } => Some(node_id),
+            TaskSubmission::Waiting { .. } | TaskSubmission::WaitingForCapability { .. } | TaskSubmission::Cached { .. } => None,
+        }
+    }
+}
+
+impl EdgeTask {
+    /// 结果缓存键；只有标记为幂等且带有调用信息的任务才有
+    pub fn result_cache_key(&self) -> Option<String> {
+        if !self.idempotent {
+            return None;
+        }
+        self.invocation.as_ref().map(|invocation| format!("edge-result:{}", invocation.content_hash()))
+    }
+
+    /// 截止时间在给定时刻是否已过
+    pub fn is_past_deadline(&self, now: DateTime<Utc>) -> bool {
+        self.deadline.is_some_and(|deadline| deadline < now)
+    }
 }
 
 /// 任务类型
@@ -341,10 +1226,12 @@ pub struct TaskExecutionRecord {
 
 /// 任务执行状态
 /// Task Execution Status
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum TaskExecutionStatus {
     /// 等待中
     Pending,
+    /// 等待具备所需硬件能力的节点
+    WaitingForCapability,
     /// 运行中
     Running,
     /// 已完成
@@ -395,6 +1282,82 @@ pub struct ResourceManager {
     pub allocation_strategy: ResourceAllocationStrategy,
     /// 资源监控
     pub resource_monitor: ResourceMonitor,
+    /// 按任务 ID 记录的资源预留
+    pub reservations: Arc<Mutex<HashMap<String, Reservation>>>,
+    /// 租户配额
+    pub tenant_quotas: Arc<Mutex<HashMap<String, TenantQuota>>>,
+}
+
+/// 资源预留
+/// Resource Reservation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Reservation {
+    /// 任务ID
+    pub task_id: String,
+    /// 节点ID
+    pub node_id: String,
+    /// 所属租户
+    pub tenant: Option<String>,
+    /// 预留的 CPU 核心数
+    pub cpu_cores: u32,
+    /// 预留的内存 (MB)
+    pub memory: u64,
+    /// 预留的存储 (GB)
+    pub storage: u64,
+}
+
+/// 租户配额，限制租户在整个集群中同时持有的预留总量
+/// Tenant Quota
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TenantQuota {
+    /// CPU 核心数上限
+    pub max_cpu_cores: u32,
+    /// 内存上限 (MB)
+    pub max_memory: u64,
+    /// 存储上限 (GB)
+    pub max_storage: u64,
+}
+
+/// 节点资源使用情况
+/// Node Utilization
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NodeUtilization {
+    /// 总资源
+    pub total: AvailableResources,
+    /// 已预留资源
+    pub reserved: AvailableResources,
+    /// CPU 预留比例
+    pub cpu_ratio: f64,
+    /// 内存预留比例
+    pub memory_ratio: f64,
+    /// 存储预留比例
+    pub storage_ratio: f64,
+}
+
+/// 租户资源使用情况
+/// Tenant Usage
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TenantUsage {
+    /// 持有的预留数
+    pub reservations: usize,
+    /// 预留的 CPU 核心数
+    pub cpu_cores: u32,
+    /// 预留的内存 (MB)
+    pub memory: u64,
+    /// 预留的存储 (GB)
+    pub storage: u64,
+    /// 配额，未配置时为空
+    pub quota: Option<TenantQuota>,
+}
+
+/// 资源使用报告
+/// Resource Utilization
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResourceUtilization {
+    /// 各节点的使用情况
+    pub nodes: BTreeMap<String, NodeUtilization>,
+    /// 各租户的使用情况
+    pub tenants: BTreeMap<String, TenantUsage>,
 }
 
 /// 资源池
@@ -557,6 +1520,10 @@ pub struct EdgeComputingConfig {
     pub load_balancing_strategy: LoadBalancingStrategy,
     /// 故障转移策略
     pub failover_strategy: FailoverStrategy,
+    /// 连续错过多少个心跳间隔后将节点标记为离线
+    pub max_missed_heartbeats: u32,
+    /// 首次重试的退避时间，之后每次翻倍
+    pub retry_backoff: Duration,
 }
 
 /// 负载均衡策略
@@ -596,54 +1563,548 @@ impl EdgeComputingManager {
             resource_manager: ResourceManager::new(),
             network_manager: NetworkManager::new(),
             config,
+            clock: Arc::new(SystemEdgeClock),
+            heartbeat_stats: Arc::new(Mutex::new(HeartbeatStats::default())),
+            dead_letters: Arc::new(Mutex::new(Vec::new())),
+            placement_config: PlacementConfig::default(),
+            result_cache: Arc::new(Self::default_result_cache()),
+            result_cache_ttl: Duration::from_secs(600),
+            heartbeat_monitor_task: Mutex::new(None),
         }
     }
 
-    /// 注册边缘节点
-    pub fn register_edge_node(&self, node: EdgeNode) -> Result<(), EdgeComputingError> {
-        let mut nodes = self.edge_nodes.lock().unwrap();
-        nodes.insert(node.id.clone(), node);
-        Ok(())
+    /// 使用指定的时钟
+    pub fn with_clock(self, clock: Arc<dyn EdgeClock>) -> Self {
+        Self { clock, ..self }
     }
 
-    /// 提交任务
-    pub fn submit_task(&self, task: EdgeTask) -> Result<String, EdgeComputingError> {
-        // 选择最佳节点
-        let best_node = self.select_best_node(&task)?;
-        
-        // 分配资源
-        self.resource_manager.allocate_resources(&best_node, &task)?;
-        
-        // 调度任务
-        self.task_scheduler.schedule_task(task, &best_node)?;
-        
-        Ok(best_node)
+    /// 使用指定的缓存保存幂等任务的结果，条目在 `ttl` 后过期；容量与字节预算由缓存自身的策略决定
+    pub fn with_result_cache(self, result_cache: Arc<IntelligentCacheManager>, ttl: Duration) -> Self {
+        Self { result_cache, result_cache_ttl: ttl, ..self }
     }
 
-    /// 选择最佳节点
-    fn select_best_node(&self, task: &EdgeTask) -> Result<String, EdgeComputingError> {
-        let nodes = self.edge_nodes.lock().unwrap();
-        
-        // 简化的节点选择逻辑
-        for (node_id, node) in nodes.iter() {
-            if self.can_execute_task(node, task) {
-                return Ok(node_id.clone());
+    /// 默认的结果缓存：最多 1024 个条目、64 MiB
+    fn default_result_cache() -> IntelligentCacheManager {
+        const MAX_ENTRIES: usize = 1024;
+        IntelligentCacheManager::new(CacheConfig {
+            default_max_size: MAX_ENTRIES,
+            cleanup_interval: Duration::from_secs(60),
+            sweep_batch_size: 100,
+            statistics_interval: Duration::from_secs(60),
+            compression_enabled: false,
+            warmup_enabled: false,
+            shard_count: 4,
+        })
+        .with_policy(CachePolicy {
+            name: "edge-results".to_string(),
+            max_size: MAX_ENTRIES,
+            max_bytes: Some(64 * 1024 * 1024),
+            default_ttl: Duration::from_secs(600),
+            eviction_policy: EvictionPolicy::LRU,
+            compression_policy: CompressionPolicy::None,
+            compression_threshold: 1024,
+            compression_level: None,
+            refresh_on_read: false,
+        })
+    }
+
+    /// 使用指定的卸载决策配置
+    pub fn with_placement_config(self, placement_config: PlacementConfig) -> Self {
+        Self { placement_config, ..self }
+    }
+
+    /// 注册边缘节点，并以节点当前的可用资源建立资源池
+    ///
+    /// 声明的能力必须有硬件规格支撑；注册后为等待中的任务重新尝试分配节点。
+    pub fn register_edge_node(&self, node: EdgeNode) -> Result<(), EdgeComputingError> {
+        node.validate_capabilities()?;
+        self.resource_manager.register_pool(&node.id, node.resource_status.available_resources.clone());
+        self.edge_nodes.lock().unwrap().insert(node.id.clone(), node);
+        self.place_waiting_tasks();
+        Ok(())
+    }
+
+    /// 是否有在线节点具备任务要求的全部能力
+    fn capability_available(&self, task: &EdgeTask) -> bool {
+        task.required_capabilities.is_empty() || self.edge_nodes.lock().unwrap().values().any(|node| {
+            matches!(node.connection_status, ConnectionStatus::Online) && node.has_capabilities(&task.required_capabilities)
+        })
+    }
+
+    /// 将等待硬件能力超时的任务移入死信列表，返回移出的任务数
+    pub fn expire_capability_waits(&self) -> usize {
+        let now = self.clock.now();
+        let timed_out = |task: &EdgeTask| {
+            task.assignment.is_none() && match (task.capability_timeout, task.waiting_since) {
+                (Some(timeout), Some(since)) => now - since >= chrono::Duration::from_std(timeout).unwrap_or(chrono::Duration::MAX),
+                _ => false,
             }
+        };
+        let expired = self.task_scheduler.task_queue.lock().unwrap()
+            .drain_where(|task| timed_out(task) && !self.capability_available(task));
+
+        let count = expired.len();
+        for mut task in expired {
+            log::warn!("任务 {} 等待能力 {:?} 超时，移入死信列表", task.id, task.required_capabilities);
+            task.attempt_history.push(AttemptRecord {
+                attempt: task.attempts + 1,
+                node_id: None,
+                error: format!("等待具备能力 {:?} 的节点超时", task.required_capabilities),
+                failed_at: now,
+            });
+            self.dead_letters.lock().unwrap().push(FailedTask { task, status: TaskExecutionStatus::Timeout, dead_lettered_at: now });
         }
-        
-        Err(EdgeComputingError::NoSuitableNode)
+        count
     }
 
-    /// 检查节点是否可以执行任务
-    fn can_execute_task(&self, node: &EdgeNode, task: &EdgeTask) -> bool {
-        // 检查资源是否足够
-        let available = &node.resource_status.available_resources;
-        let required = &task.resource_requirements;
-        
-        available.available_cpu_cores >= required.min_cpu_cores &&
-        available.available_memory >= required.min_memory &&
-        available.available_storage >= required.min_storage &&
-        available.available_bandwidth >= required.network_bandwidth
+    /// 任务当前状态；不在队列、运行中或死信列表中的任务返回 `None`
+    pub fn task_status(&self, task_id: &str) -> Option<TaskExecutionStatus> {
+        if self.task_scheduler.running_tasks.lock().unwrap().contains_key(task_id) {
+            return Some(TaskExecutionStatus::Running);
+        }
+        let queued = self.task_scheduler.task_queue.lock().unwrap().ordered().into_iter().find(|task| task.id == task_id).cloned();
+        if let Some(task) = queued {
+            return Some(if task.assignment.is_none() && !self.capability_available(&task) {
+                TaskExecutionStatus::WaitingForCapability
+            } else {
+                TaskExecutionStatus::Pending
+            });
+        }
+        self.dead_letters.lock().unwrap().iter().find(|failed| failed.task.id == task_id).map(|failed| failed.status.clone())
+    }
+
+    /// 提交任务
+    ///
+    /// 任务在选中的节点上预留资源后入队；暂时没有节点能满足需求时，任务以未分配状态入队并记录原因，
+    /// 在其他任务释放资源后重新尝试。超出租户配额的提交直接拒绝。
+    pub fn submit_task(&self, mut task: EdgeTask) -> Result<TaskSubmission, EdgeComputingError> {
+        // 幂等任务命中结果缓存时不经过调度器
+        if let Some(key) = task.result_cache_key()
+            && let Some(output) = self.result_cache.get(&key)
+        {
+            log::debug!("幂等任务 {} 命中结果缓存", task.id);
+            return Ok(TaskSubmission::Cached {
+                result: EdgeTaskResult {
+                    task_id: task.id,
+                    node_id: None,
+                    output,
+                    served_from_cache: true,
+                    completed_at: self.clock.now(),
+                },
+            });
+        }
+
+        // 迟到任务只在入队时按管理器的时钟检查一次
+        let now = self.clock.now();
+        self.resource_manager.check_quota(&task)?;
+
+        // 选择最佳节点并预留资源，分配记录随任务保存
+        let submission = match self.reserve_on_best_node(&task) {
+            Ok(assignment) => {
+                let submission = TaskSubmission::Scheduled { node_id: assignment.node_id.clone() };
+                task.assignment = Some(assignment);
+                submission
+            }
+            Err(e @ (EdgeComputingError::NoSuitableNode | EdgeComputingError::InsufficientResources)) => {
+                task.waiting_since = Some(self.clock.now());
+                if self.capability_available(&task) {
+                    let reason = e.to_string();
+                    log::info!("任务 {} 暂时没有可用节点，进入等待: {}", task.id, reason);
+                    task.pending_reason = Some(reason.clone());
+                    TaskSubmission::Waiting { reason }
+                } else {
+                    let required = task.required_capabilities.clone();
+                    log::info!("任务 {} 要求的能力 {:?} 没有在线节点具备，进入等待", task.id, required);
+                    task.pending_reason = Some(format!("没有在线节点具备能力 {:?}", required));
+                    TaskSubmission::WaitingForCapability { required }
+                }
+            }
+            Err(e) => return Err(e),
+        };
+
+        // 调度任务；迟到任务被拒绝时释放刚才的预留，否则预留会一直占用节点资源
+        let task_id = task.id.clone();
+        if let Err(e) = self.task_scheduler.schedule_task_at(task, now) {
+            self.resource_manager.release_resources(&task_id);
+            return Err(e);
+        }
+
+        Ok(submission)
+    }
+
+    /// 选择最佳节点并在其上为任务预留资源
+    ///
+    /// 优先排除任务此前失败过的节点；没有其他候选节点时再考虑这些节点。
+    fn reserve_on_best_node(&self, task: &EdgeTask) -> Result<NodeAssignment, EdgeComputingError> {
+        let failed_nodes: Vec<&str> = task.attempt_history.iter().filter_map(|attempt| attempt.node_id.as_deref()).collect();
+        let assignment = {
+            let nodes = self.edge_nodes.lock().unwrap();
+            let select = |exclude_failed: bool| {
+                // 负载随预留变化：注册时的使用率之外，剩余容量按已预留的比例计入
+                let load = |node: &EdgeNode| {
+                    let reported = TaskScheduler::reported_load(node);
+                    reported + (1.0 - reported) * self.resource_manager.reserved_ratio(&node.id)
+                };
+                self.task_scheduler.select_node_at(task, &nodes, &self.network_manager, self.clock.now(), load, |node| {
+                    !(exclude_failed && failed_nodes.contains(&node.id.as_str())) && self.resource_manager.can_reserve(&node.id, task)
+                })
+            };
+            match select(true) {
+                Err(EdgeComputingError::NoSuitableNode) if !failed_nodes.is_empty() => select(false),
+                result => result,
+            }
+        }?;
+        self.resource_manager.allocate_resources(&assignment.node_id, task)?;
+        Ok(assignment)
+    }
+
+    /// 结束任务（完成、失败、超时或取消），释放其资源预留并为等待中的任务重新分配节点
+    ///
+    /// 尚未分发的任务同样从队列中移除，避免已释放预留的任务之后仍被分发。
+    pub fn finish_task(&self, task_id: &str, status: TaskExecutionStatus) -> Result<Reservation, EdgeComputingError> {
+        let reservation = self.resource_manager.release_resources(task_id)
+            .ok_or_else(|| EdgeComputingError::TaskNotFound(task_id.to_string()))?;
+        if self.task_scheduler.running_tasks.lock().unwrap().remove(task_id).is_none() {
+            self.task_scheduler.task_queue.lock().unwrap().drain_where(|task| task.id == task_id);
+        }
+        log::debug!("任务 {} 以 {:?} 状态结束，释放节点 {} 上的预留", task_id, status, reservation.node_id);
+        self.place_waiting_tasks();
+        Ok(reservation)
+    }
+
+    /// 已分发的任务执行成功并产生输出：按完成结束任务，随后将幂等任务的输出写入结果缓存
+    ///
+    /// 只接受运行中的任务；尚未分发的任务返回 `TaskNotFound`。
+    pub fn complete_task(&self, task_id: &str, output: Vec<u8>) -> Result<EdgeTaskResult, EdgeComputingError> {
+        let task = self.task_scheduler.running_tasks.lock().unwrap().get(task_id).cloned()
+            .ok_or_else(|| EdgeComputingError::TaskNotFound(task_id.to_string()))?;
+        let reservation = self.finish_task(task_id, TaskExecutionStatus::Completed)?;
+
+        if let Some(key) = task.result_cache_key()
+            && let Err(e) = self.result_cache.set(key, output.clone(), Some(self.result_cache_ttl), None)
+        {
+            log::warn!("任务 {} 的结果未能写入缓存: {}", task_id, e);
+        }
+
+        Ok(EdgeTaskResult {
+            task_id: task_id.to_string(),
+            node_id: Some(reservation.node_id),
+            output,
+            served_from_cache: false,
+            completed_at: self.clock.now(),
+        })
+    }
+
+    /// 按分发顺序为队列中未分配节点的任务重新尝试预留资源，返回成功分配的任务数
+    pub fn place_waiting_tasks(&self) -> usize {
+        self.expire_capability_waits();
+        let mut placed = 0;
+        self.task_scheduler.task_queue.lock().unwrap().for_each_mut(|task| {
+            if task.assignment.is_some() {
+                return;
+            }
+            match self.reserve_on_best_node(task) {
+                Ok(assignment) => {
+                    log::info!("等待中的任务 {} 分配到节点 {}", task.id, assignment.node_id);
+                    task.assignment = Some(assignment);
+                    task.pending_reason = None;
+                    task.waiting_since = None;
+                    placed += 1;
+                }
+                Err(e) => task.pending_reason = Some(e.to_string()),
+            }
+        });
+        placed
+    }
+
+    /// 记录节点心跳；离线节点恢复心跳后重新成为候选节点，但不会收回已转移的任务
+    pub fn record_heartbeat(&self, node_id: &str) -> Result<(), EdgeComputingError> {
+        let recovered = {
+            let mut nodes = self.edge_nodes.lock().unwrap();
+            let node = nodes.get_mut(node_id).ok_or(EdgeComputingError::NodeNotFound)?;
+            node.last_heartbeat = self.clock.now();
+
+            let mut stats = self.heartbeat_stats.lock().unwrap();
+            stats.heartbeats += 1;
+            let recovered = matches!(node.connection_status, ConnectionStatus::Offline);
+            if recovered {
+                node.connection_status = ConnectionStatus::Online;
+                stats.recoveries += 1;
+                log::info!("边缘节点 {} 恢复心跳，重新上线", node_id);
+            }
+            recovered
+        };
+        // 恢复的节点可能具备等待中的任务所需的能力
+        if recovered {
+            self.place_waiting_tasks();
+        }
+        Ok(())
+    }
+
+    /// 将连续错过 `max_missed_heartbeats` 个心跳间隔的在线节点标记为离线，并转移其上的任务；
+    /// 同时处理等待硬件能力超时的任务。返回本轮转为离线的节点
+    pub fn check_heartbeats(&self) -> Vec<String> {
+        let now = self.clock.now();
+        let timeout = self.config.heartbeat_interval.checked_mul(self.config.max_missed_heartbeats.max(1)).unwrap_or(Duration::MAX);
+        let timeout = chrono::Duration::from_std(timeout).unwrap_or(chrono::Duration::MAX);
+
+        let mut offline = Vec::new();
+        {
+            let mut nodes = self.edge_nodes.lock().unwrap();
+            for node in nodes.values_mut() {
+                if matches!(node.connection_status, ConnectionStatus::Online) && now - node.last_heartbeat > timeout {
+                    node.connection_status = ConnectionStatus::Offline;
+                    offline.push(node.id.clone());
+                }
+            }
+        }
+        offline.sort();
+
+        for node_id in &offline {
+            log::warn!("边缘节点 {} 超过 {} 个心跳间隔未响应，标记为离线", node_id, self.config.max_missed_heartbeats);
+            self.heartbeat_stats.lock().unwrap().offline_transitions += 1;
+            self.rescue_tasks(node_id);
+        }
+        self.expire_capability_waits();
+        offline
+    }
+
+    /// 将分配给离线节点的排队和运行中任务退回队列，按故障转移策略重新调度
+    ///
+    /// 只有 `FailoverStrategy::Automatic` 会立即为任务选择新节点；其余策略下任务以未分配状态留在队列中。
+    fn rescue_tasks(&self, node_id: &str) {
+        let assigned_to_node = |task: &EdgeTask| task.assignment.as_ref().is_some_and(|assignment| assignment.node_id == node_id);
+        let mut tasks = self.task_scheduler.task_queue.lock().unwrap().drain_where(assigned_to_node);
+        {
+            let mut running_tasks = self.task_scheduler.running_tasks.lock().unwrap();
+            let running: Vec<String> = running_tasks.values().filter(|task| assigned_to_node(task)).map(|task| task.id.clone()).collect();
+            tasks.extend(running.iter().filter_map(|task_id| running_tasks.remove(task_id)));
+        }
+
+        for task in tasks {
+            self.resource_manager.release_resources(&task.id);
+            let outcome = self.retry_or_dead_letter(task, Some(node_id.to_string()), format!("节点 {} 离线", node_id));
+            let mut stats = self.heartbeat_stats.lock().unwrap();
+            match outcome {
+                RetryOutcome::Retrying { node_id: Some(_), .. } => stats.rescheduled_tasks += 1,
+                RetryOutcome::Retrying { node_id: None, .. } => stats.requeued_tasks += 1,
+                RetryOutcome::DeadLettered { .. } => {}
+            }
+        }
+    }
+
+    /// 取出下一个已分配节点且退避时间已到的任务，并记为运行中
+    pub fn dispatch_next(&self) -> Option<EdgeTask> {
+        let task = self.task_scheduler.dispatch_next_where(self.clock.now(), |task| task.assignment.is_some())?;
+        self.task_scheduler.running_tasks.lock().unwrap().insert(task.id.clone(), task.clone());
+        Some(task)
+    }
+
+    /// 报告任务在当前节点上执行失败：释放预留，在尝试次数未用尽时按指数退避重试，否则移入死信列表
+    pub fn fail_task(&self, task_id: &str, error: impl Into<String>) -> Result<RetryOutcome, EdgeComputingError> {
+        let running = self.task_scheduler.running_tasks.lock().unwrap().remove(task_id);
+        let task = running
+            .or_else(|| self.task_scheduler.task_queue.lock().unwrap().drain_where(|task| task.id == task_id).pop())
+            .ok_or_else(|| EdgeComputingError::TaskNotFound(task_id.to_string()))?;
+
+        self.resource_manager.release_resources(task_id);
+        let node_id = task.assignment.as_ref().map(|assignment| assignment.node_id.clone());
+        Ok(self.retry_or_dead_letter(task, node_id, error.into()))
+    }
+
+    /// 记录一次失败的尝试并决定重试或移入死信列表
+    ///
+    /// 重试保留原截止时间，使 EDF 顺序不因故障转移而改变；只有 `FailoverStrategy::Automatic` 会立即为任务
+    /// 选择新节点，其余策略下任务以未分配状态留在队列中。
+    fn retry_or_dead_letter(&self, mut task: EdgeTask, node_id: Option<String>, error: String) -> RetryOutcome {
+        let now = self.clock.now();
+        task.attempts += 1;
+        task.assignment = None;
+        task.attempt_history.push(AttemptRecord { attempt: task.attempts, node_id: node_id.clone(), error, failed_at: now });
+        let source = node_id.as_deref().unwrap_or("未分配节点");
+
+        let max_attempts = task.max_attempts.unwrap_or(self.config.max_retry_count + 1).max(1);
+        if task.attempts >= max_attempts {
+            log::error!("任务 {} 在 {} 上失败，{} 次尝试已用尽，移入死信列表", task.id, source, task.attempts);
+            self.dead_letters.lock().unwrap().push(FailedTask { status: TaskExecutionStatus::Failed, dead_lettered_at: now, task });
+            return RetryOutcome::DeadLettered { attempts: max_attempts };
+        }
+
+        let exponent = (task.attempts - 1).min(16);
+        let backoff = self.config.retry_backoff.saturating_mul(1 << exponent);
+        let retry_at = saturating_add(now, backoff);
+        task.not_before = Some(retry_at);
+
+        let placement = match self.config.failover_strategy {
+            FailoverStrategy::Automatic => self.reserve_on_best_node(&task),
+            FailoverStrategy::Manual | FailoverStrategy::None => Err(EdgeComputingError::TaskSchedulingFailed(
+                "故障转移策略不允许自动重新调度".to_string(),
+            )),
+        };
+        let retry_node = match placement {
+            Ok(assignment) => {
+                log::warn!("任务 {} 在 {} 上失败，{:?} 后在 {} 上重试（第 {} 次）", task.id, source, backoff, assignment.node_id, task.attempts + 1);
+                let retry_node = assignment.node_id.clone();
+                task.assignment = Some(assignment);
+                task.pending_reason = None;
+                Some(retry_node)
+            }
+            Err(e) => {
+                log::warn!("任务 {} 在 {} 上失败，退回队列，未能重新分配: {}", task.id, source, e);
+                task.pending_reason = Some(e.to_string());
+                task.waiting_since = Some(now);
+                None
+            }
+        };
+        // 已入队的任务不再经过迟到检查，截止时间已过时在分发时标记
+        self.task_scheduler.task_queue.lock().unwrap().push(task);
+        RetryOutcome::Retrying { node_id: retry_node, retry_at }
+    }
+
+    /// 用尽尝试次数的任务及其每次尝试的错误
+    pub fn failed_tasks(&self) -> Vec<FailedTask> {
+        self.dead_letters.lock().unwrap().clone()
+    }
+
+    /// 心跳统计快照
+    pub fn heartbeat_stats(&self) -> HeartbeatStats {
+        self.heartbeat_stats.lock().unwrap().clone()
+    }
+
+    /// 启动后台任务，每隔 `heartbeat_interval` 检查一轮心跳；管理器释放后任务自行结束
+    ///
+    /// 须在 tokio 运行时中调用，否则返回 `ConfigurationError`。
+    pub fn start_heartbeat_monitor(self: &Arc<Self>) -> Result<(), EdgeComputingError> {
+        let runtime = tokio::runtime::Handle::try_current()
+            .map_err(|e| EdgeComputingError::ConfigurationError(format!("心跳监控需要 tokio 运行时: {}", e)))?;
+        let manager: Weak<Self> = Arc::downgrade(self);
+        let interval = self.config.heartbeat_interval.max(Duration::from_millis(1));
+        let handle = runtime.spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                let Some(manager) = manager.upgrade() else {
+                    break;
+                };
+                manager.check_heartbeats();
+            }
+        });
+        if let Ok(mut task) = self.heartbeat_monitor_task.lock()
+            && let Some(previous) = task.replace(handle)
+        {
+            previous.abort();
+        }
+        Ok(())
+    }
+
+    /// 停止后台心跳监控任务
+    pub fn stop_heartbeat_monitor(&self) {
+        if let Ok(mut task) = self.heartbeat_monitor_task.lock()
+            && let Some(handle) = task.take()
+        {
+            handle.abort();
+        }
+    }
+
+    /// 决定计算在本地、边缘节点还是云端执行
+    ///
+    /// 分别估算各目标的计算、传输、网络延迟和排队时间，按权重打分选出得分最低者；数据驻留约束之外的
+    /// 地区不参与比较。有延迟预算时只在预算内的目标中选择，全部超出预算时选预计完成最早的目标。
+    /// 本地执行不传输数据，始终满足驻留约束。
+    pub fn decide_placement(&self, task_profile: TaskProfile) -> PlacementDecision {
+        let config = &self.placement_config;
+        let mut candidates = vec![self.local_candidate(&task_profile)];
+        candidates.extend(self.edge_candidates(&task_profile));
+        if let Some(cloud) = &config.cloud {
+            if task_profile.allows_region(&cloud.region_code) {
+                candidates.push(PlacementCandidate::new(
+                    PlacementTarget::Cloud { region_code: cloud.region_code.clone() },
+                    PlacementBreakdown {
+                        compute: seconds(task_profile.compute_cost as f64 / cloud.cycles_per_second),
+                        transfer: transfer_time(task_profile.transfer_bytes(), cloud.bandwidth_mbps),
+                        network: cloud.round_trip_latency,
+                        queue_wait: Duration::ZERO,
+                    },
+                    &config.weights,
+                    task_profile.latency_budget,
+                ));
+            } else {
+                log::debug!("云端 {} 不在允许的数据驻留地区内，跳过", cloud.region_code);
+            }
+        }
+
+        let chosen = candidates.iter()
+            .filter(|candidate| candidate.within_budget)
+            .min_by(|a, b| a.score.total_cmp(&b.score))
+            .or_else(|| candidates.iter().min_by_key(|candidate| candidate.estimated_completion))
+            .cloned()
+            .expect("本地执行始终是候选目标");
+        PlacementDecision { chosen, candidates }
+    }
+
+    /// 本地执行：不传输数据，计算能力有限
+    fn local_candidate(&self, task_profile: &TaskProfile) -> PlacementCandidate {
+        let config = &self.placement_config;
+        PlacementCandidate::new(
+            PlacementTarget::Local,
+            PlacementBreakdown {
+                compute: seconds(task_profile.compute_cost as f64 / config.local_cycles_per_second),
+                transfer: Duration::ZERO,
+                network: Duration::ZERO,
+                queue_wait: Duration::ZERO,
+            },
+            &config.weights,
+            task_profile.latency_budget,
+        )
+    }
+
+    /// 在线且位于允许地区的边缘节点
+    fn edge_candidates(&self, task_profile: &TaskProfile) -> Vec<PlacementCandidate> {
+        let config = &self.placement_config;
+        let queue_wait = self.queued_work_by_node();
+        let nodes = self.edge_nodes.lock().unwrap();
+        let mut node_ids: Vec<&String> = nodes.keys().collect();
+        node_ids.sort();
+
+        node_ids.into_iter().filter_map(|node_id| {
+            let node = &nodes[node_id];
+            if !matches!(node.connection_status, ConnectionStatus::Online) {
+                return None;
+            }
+            if !task_profile.allows_region(&node.location.region_code) {
+                log::debug!("边缘节点 {} 位于 {}，不在允许的数据驻留地区内", node_id, node.location.region_code);
+                return None;
+            }
+            let distance_km = task_profile.origin.as_ref().map(|origin| origin.distance_km(&node.location)).unwrap_or(0.0);
+            let link_latency_ms = self.network_manager.link_latency(node_id)
+                .map(|latency| latency as f64)
+                .unwrap_or(distance_km * NetworkManager::ESTIMATED_LATENCY_MS_PER_KM);
+            let cycles_per_second = config.edge_cycles_per_ghz * node.hardware_specs.cpu_frequency;
+            Some(PlacementCandidate::new(
+                PlacementTarget::Edge { node_id: node_id.clone() },
+                PlacementBreakdown {
+                    compute: seconds(task_profile.compute_cost as f64 / cycles_per_second),
+                    transfer: transfer_time(task_profile.transfer_bytes(), node.resource_status.available_resources.available_bandwidth as f64),
+                    network: seconds(link_latency_ms / 1000.0),
+                    queue_wait: queue_wait.get(node_id.as_str()).copied().unwrap_or_default(),
+                },
+                &config.weights,
+                task_profile.latency_budget,
+            ))
+        }).collect()
+    }
+
+    /// 各节点上排队和运行中任务的预计执行时间之和
+    fn queued_work_by_node(&self) -> HashMap<String, Duration> {
+        let mut work: HashMap<String, Duration> = HashMap::new();
+        let mut add = |task: &EdgeTask| {
+            if let Some(assignment) = &task.assignment {
+                *work.entry(assignment.node_id.clone()).or_default() += task.estimated_execution_time;
+            }
+        };
+        self.task_scheduler.task_queue.lock().unwrap().ordered().into_iter().for_each(&mut add);
+        self.task_scheduler.running_tasks.lock().unwrap().values().for_each(&mut add);
+        work
     }
 
     /// 获取节点状态
@@ -661,11 +2122,14 @@ impl EdgeComputingManager {
 
 impl Default for TaskScheduler {
     fn default() -> Self {
+        let scheduling_strategy = SchedulingStrategy::LoadBalancing;
         Self {
-            scheduling_strategy: SchedulingStrategy::LoadBalancing,
-            task_queue: Arc::new(Mutex::new(VecDeque::new())),
+            scoring_weights: Arc::new(Mutex::new(SchedulingWeights::for_strategy(&scheduling_strategy))),
+            scheduling_strategy,
+            task_queue: Arc::new(Mutex::new(TaskQueue::new())),
             running_tasks: Arc::new(Mutex::new(HashMap::new())),
             task_history: Arc::new(Mutex::new(Vec::new())),
+            late_policy: LateTaskPolicy::Reject,
         }
     }
 }
@@ -676,11 +2140,189 @@ impl TaskScheduler {
         Self::default()
     }
 
+    /// 设置调度策略，节点评分权重重置为该策略的默认权重
+    pub fn with_scheduling_strategy(mut self, strategy: SchedulingStrategy) -> Self {
+        self.set_weights(SchedulingWeights::for_strategy(&strategy));
+        self.scheduling_strategy = strategy;
+        self
+    }
+
+    /// 设置迟到任务策略
+    pub fn with_late_policy(mut self, policy: LateTaskPolicy) -> Self {
+        self.late_policy = policy;
+        self
+    }
+
+    /// 按迟到任务策略检查任务能否入队
+    pub fn admit(&self, task: &EdgeTask, now: DateTime<Utc>) -> Result<(), EdgeComputingError> {
+        if self.late_policy == LateTaskPolicy::Reject && task.is_past_deadline(now) {
+            return Err(EdgeComputingError::DeadlineMissed(task.id.clone()));
+        }
+        Ok(())
+    }
+
+    /// 取出下一个应分发的任务，截止时间已过的任务带上 `deadline_missed` 标记
+    pub fn dispatch_next(&self) -> Option<EdgeTask> {
+        self.dispatch_next_at(Utc::now())
+    }
+
+    /// 以给定时刻为当前时间取出下一个应分发的任务，跳过仍在重试退避中的任务
+    pub fn dispatch_next_at(&self, now: DateTime<Utc>) -> Option<EdgeTask> {
+        self.dispatch_next_where(now, |_| true)
+    }
+
+    /// 同 [`TaskScheduler::dispatch_next_at`]，任务还需满足 `ready`
+    pub fn dispatch_next_where(&self, now: DateTime<Utc>, ready: impl Fn(&EdgeTask) -> bool) -> Option<EdgeTask> {
+        let mut task = self.task_queue.lock().unwrap()
+            .pop_first(|task| task.not_before.is_none_or(|not_before| not_before <= now) && ready(task))?;
+        task.deadline_missed = task.is_past_deadline(now);
+        Some(task)
+    }
+
+    /// 队列深度
+    pub fn queue_depth(&self) -> usize {
+        self.task_queue.lock().unwrap().len()
+    }
+
+    /// 队列指标
+    pub fn queue_metrics(&self) -> QueueMetrics {
+        self.queue_metrics_at(Utc::now())
+    }
+
+    /// 以给定时刻为起点计算队列指标
+    ///
+    /// 预计迟到时间假设任务按分发顺序在单一执行通道上依次运行，各自耗时为执行时间估计。
+    pub fn queue_metrics_at(&self, now: DateTime<Utc>) -> QueueMetrics {
+        let queue = self.task_queue.lock().unwrap();
+        let mut metrics = QueueMetrics {
+            depth: queue.len(),
+            depth_by_priority: BTreeMap::new(),
+            deadline_tasks: 0,
+            projected_late_tasks: 0,
+            projected_lateness: Duration::ZERO,
+            max_projected_lateness: Duration::ZERO,
+        };
+
+        let mut finish = now;
+        for task in queue.ordered() {
+            *metrics.depth_by_priority.entry(task.priority).or_insert(0) += 1;
+            finish = saturating_add(finish, task.estimated_execution_time);
+            let Some(deadline) = task.deadline else { continue };
+            metrics.deadline_tasks += 1;
+            if let Ok(lateness) = (finish - deadline).to_std()
+                && !lateness.is_zero()
+            {
+                metrics.projected_late_tasks += 1;
+                metrics.projected_lateness = metrics.projected_lateness.saturating_add(lateness);
+                metrics.max_projected_lateness = metrics.max_projected_lateness.max(lateness);
+            }
+        }
+        metrics
+    }
+
+    /// 当前的节点评分权重
+    pub fn weights(&self) -> SchedulingWeights {
+        *self.scoring_weights.lock().unwrap()
+    }
+
+    /// 调整节点评分权重，之后的调度立即生效
+    pub fn set_weights(&self, weights: SchedulingWeights) {
+        *self.scoring_weights.lock().unwrap() = weights;
+    }
+
+    /// 在在线且资源足够的节点中选出得分最低的节点，得分相同时取 ID 最小的节点
+    pub fn select_node(
+        &self,
+        task: &EdgeTask,
+        nodes: &HashMap<String, EdgeNode>,
+        network: &NetworkManager,
+    ) -> Result<NodeAssignment, EdgeComputingError> {
+        self.select_node_where(task, nodes, network, |_| true)
+    }
+
+    /// 同 [`TaskScheduler::select_node`]，候选节点还需满足 `eligible`
+    pub fn select_node_where(
+        &self,
+        task: &EdgeTask,
+        nodes: &HashMap<String, EdgeNode>,
+        network: &NetworkManager,
+        eligible: impl Fn(&EdgeNode) -> bool,
+    ) -> Result<NodeAssignment, EdgeComputingError> {
+        self.select_node_at(task, nodes, network, Utc::now(), Self::reported_load, eligible)
+    }
+
+    /// 同 [`TaskScheduler::select_node_where`]，以给定时刻记录分配时间，节点负载（0 到 1）由 `load` 给出
+    pub fn select_node_at(
+        &self,
+        task: &EdgeTask,
+        nodes: &HashMap<String, EdgeNode>,
+        network: &NetworkManager,
+        now: DateTime<Utc>,
+        load: impl Fn(&EdgeNode) -> f64,
+        eligible: impl Fn(&EdgeNode) -> bool,
+    ) -> Result<NodeAssignment, EdgeComputingError> {
+        let weights = self.weights();
+        let mut candidates: Vec<&EdgeNode> = nodes.values()
+            .filter(|node| Self::can_execute_task(node, task) && eligible(node))
+            .collect();
+        candidates.sort_by(|a, b| a.id.cmp(&b.id));
+
+        let mut best: Option<NodeAssignment> = None;
+        for node in candidates {
+            let assignment = Self::score_node(node, task, network, &weights, load(node).clamp(0.0, 1.0), now);
+            if best.as_ref().is_none_or(|current| assignment.score < current.score) {
+                best = Some(assignment);
+            }
+        }
+        best.ok_or(EdgeComputingError::NoSuitableNode)
+    }
+
+    /// 节点注册时上报的 CPU 和内存使用率中较高的一个
+    pub fn reported_load(node: &EdgeNode) -> f64 {
+        (node.resource_status.cpu_usage.max(node.resource_status.memory_usage) / 100.0).clamp(0.0, 1.0)
+    }
+
+    /// 按权重为节点评分
+    fn score_node(
+        node: &EdgeNode,
+        task: &EdgeTask,
+        network: &NetworkManager,
+        weights: &SchedulingWeights,
+        load: f64,
+        now: DateTime<Utc>,
+    ) -> NodeAssignment {
+        let distance_km = task.origin.as_ref().map(|origin| origin.distance_km(&node.location)).unwrap_or(0.0);
+        let link_latency_ms = network.link_latency(&node.id)
+            .map(|latency| latency as f64)
+            .unwrap_or(distance_km * NetworkManager::ESTIMATED_LATENCY_MS_PER_KM);
+        let score = weights.distance * distance_km / 1000.0 + weights.load * load + weights.latency * link_latency_ms / 100.0;
+        NodeAssignment { node_id: node.id.clone(), score, distance_km, load, link_latency_ms, assigned_at: now }
+    }
+
+    /// 检查节点是否在线且资源足够
+    fn can_execute_task(node: &EdgeNode, task: &EdgeTask) -> bool {
+        let available = &node.resource_status.available_resources;
+        let required = &task.resource_requirements;
+
+        matches!(node.connection_status, ConnectionStatus::Online) &&
+        node.has_capabilities(&task.required_capabilities) &&
+        available.available_cpu_cores >= required.min_cpu_cores &&
+        available.available_memory >= required.min_memory &&
+        available.available_storage >= required.min_storage &&
+        available.available_bandwidth >= required.network_bandwidth
+    }
+
     /// 调度任务
     #[allow(unused_variables)]
     pub fn schedule_task(&self, task: EdgeTask, node_id: &str) -> Result<(), EdgeComputingError> {
+        self.schedule_task_at(task, Utc::now())
+    }
+
+    /// 以给定时刻为当前时间按迟到任务策略检查后入队；执行节点由任务的分配记录决定
+    pub fn schedule_task_at(&self, task: EdgeTask, now: DateTime<Utc>) -> Result<(), EdgeComputingError> {
+        self.admit(&task, now)?;
         let mut task_queue = self.task_queue.lock().unwrap();
-        task_queue.push_back(task);
+        task_queue.push(task);
         Ok(())
     }
 
@@ -736,6 +2378,8 @@ impl Default for ResourceManager {
     fn default() -> Self {
         Self {
             resource_pool: Arc::new(Mutex::new(HashMap::new())),
+            reservations: Arc::new(Mutex::new(HashMap::new())),
+            tenant_quotas: Arc::new(Mutex::new(HashMap::new())),
             allocation_strategy: ResourceAllocationStrategy::BestFit,
             resource_monitor: ResourceMonitor::default(),
         }
@@ -748,33 +2392,188 @@ impl ResourceManager {
         Self::default()
     }
 
+    /// 为节点建立资源池，已有的资源池保持不变
+    pub fn register_pool(&self, node_id: &str, total_resources: AvailableResources) {
+        let mut resource_pool = self.resource_pool.lock().unwrap();
+        resource_pool.entry(node_id.to_string()).or_insert_with(|| ResourcePool {
+            id: node_id.to_string(),
+            allocated_resources: AvailableResources {
+                available_cpu_cores: 0,
+                available_memory: 0,
+                available_storage: 0,
+                available_bandwidth: 0,
+            },
+            available_resources: total_resources.clone(),
+            total_resources,
+            utilization_rate: 0.0,
+        });
+    }
+
+    /// 设置租户配额
+    pub fn set_tenant_quota(&self, tenant: &str, quota: TenantQuota) {
+        self.tenant_quotas.lock().unwrap().insert(tenant.to_string(), quota);
+    }
+
+    /// 检查任务的需求是否会使其租户超出配额
+    pub fn check_quota(&self, task: &EdgeTask) -> Result<(), EdgeComputingError> {
+        let reservations = self.reservations.lock().unwrap();
+        self.check_quota_locked(task, &reservations)
+    }
+
+    fn check_quota_locked(&self, task: &EdgeTask, reservations: &HashMap<String, Reservation>) -> Result<(), EdgeComputingError> {
+        let Some(tenant) = task.tenant.as_deref() else {
+            return Ok(());
+        };
+        let Some(quota) = self.tenant_quotas.lock().unwrap().get(tenant).copied() else {
+            return Ok(());
+        };
+
+        let usage = Self::tenant_usage(tenant, reservations);
+        let required = &task.resource_requirements;
+        let checks = [
+            ("CPU 核心", usage.cpu_cores as u64, required.min_cpu_cores as u64, quota.max_cpu_cores as u64),
+            ("内存 (MB)", usage.memory, required.min_memory, quota.max_memory),
+            ("存储 (GB)", usage.storage, required.min_storage, quota.max_storage),
+        ];
+        for (resource, reserved, requested, limit) in checks {
+            if reserved.saturating_add(requested) > limit {
+                return Err(EdgeComputingError::QuotaExceeded {
+                    tenant: tenant.to_string(),
+                    resource: resource.to_string(),
+                    reserved,
+                    requested,
+                    limit,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn tenant_usage(tenant: &str, reservations: &HashMap<String, Reservation>) -> TenantUsage {
+        reservations.values()
+            .filter(|reservation| reservation.tenant.as_deref() == Some(tenant))
+            .fold(TenantUsage::default(), |mut usage, reservation| {
+                usage.reservations += 1;
+                usage.cpu_cores += reservation.cpu_cores;
+                usage.memory += reservation.memory;
+                usage.storage += reservation.storage;
+                usage
+            })
+    }
+
+    /// 节点当前剩余资源能否满足任务需求
+    pub fn can_reserve(&self, node_id: &str, task: &EdgeTask) -> bool {
+        let resource_pool = self.resource_pool.lock().unwrap();
+        resource_pool.get(node_id).is_some_and(|pool| Self::fits(pool, task))
+    }
+
+    /// 节点已预留的 CPU 和内存占其资源池比例中较高的一个，没有资源池时为 0
+    pub fn reserved_ratio(&self, node_id: &str) -> f64 {
+        let resource_pool = self.resource_pool.lock().unwrap();
+        let Some(pool) = resource_pool.get(node_id) else {
+            return 0.0;
+        };
+        let ratio = |used: f64, total: f64| if total > 0.0 { (used / total).clamp(0.0, 1.0) } else { 0.0 };
+        let (total, reserved) = (&pool.total_resources, &pool.allocated_resources);
+        ratio(reserved.available_cpu_cores as f64, total.available_cpu_cores as f64)
+            .max(ratio(reserved.available_memory as f64, total.available_memory as f64))
+    }
+
+    fn fits(pool: &ResourcePool, task: &EdgeTask) -> bool {
+        let required = &task.resource_requirements;
+        pool.available_resources.available_cpu_cores >= required.min_cpu_cores &&
+        pool.available_resources.available_memory >= required.min_memory &&
+        pool.available_resources.available_storage >= required.min_storage
+    }
+
+    /// 释放任务的资源预留，返回被释放的预留
+    pub fn release_resources(&self, task_id: &str) -> Option<Reservation> {
+        let mut resource_pool = self.resource_pool.lock().unwrap();
+        let reservation = self.reservations.lock().unwrap().remove(task_id)?;
+        if let Some(pool) = resource_pool.get_mut(&reservation.node_id) {
+            pool.allocated_resources.available_cpu_cores -= reservation.cpu_cores;
+            pool.allocated_resources.available_memory -= reservation.memory;
+            pool.allocated_resources.available_storage -= reservation.storage;
+            pool.available_resources.available_cpu_cores += reservation.cpu_cores;
+            pool.available_resources.available_memory += reservation.memory;
+            pool.available_resources.available_storage += reservation.storage;
+            Self::update_utilization_rate(pool);
+        }
+        Some(reservation)
+    }
+
     /// 分配资源
+    ///
+    /// 在同一把锁内检查节点剩余资源与租户配额并记录预留，避免并发提交超额占用。
     pub fn allocate_resources(&self, node_id: &str, task: &EdgeTask) -> Result<(), EdgeComputingError> {
         let mut resource_pool = self.resource_pool.lock().unwrap();
-        
-        if let Some(pool) = resource_pool.get_mut(node_id) {
-            let required = &task.resource_requirements;
-            
-            // 检查资源是否足够
-            if pool.available_resources.available_cpu_cores >= required.min_cpu_cores &&
-               pool.available_resources.available_memory >= required.min_memory {
-                
-                // 分配资源
-                pool.available_resources.available_cpu_cores -= required.min_cpu_cores;
-                pool.available_resources.available_memory -= required.min_memory;
-                pool.allocated_resources.available_cpu_cores += required.min_cpu_cores;
-                pool.allocated_resources.available_memory += required.min_memory;
-                
-                // 更新利用率
-                pool.utilization_rate = (pool.allocated_resources.available_cpu_cores as f64) / (pool.total_resources.available_cpu_cores as f64);
-                
-                Ok(())
-            } else {
-                Err(EdgeComputingError::InsufficientResources)
-            }
-        } else {
-            Err(EdgeComputingError::NodeNotFound)
+        let mut reservations = self.reservations.lock().unwrap();
+
+        let pool = resource_pool.get_mut(node_id).ok_or(EdgeComputingError::NodeNotFound)?;
+        if reservations.contains_key(&task.id) {
+            return Err(EdgeComputingError::TaskSchedulingFailed(format!("任务 {} 已持有资源预留", task.id)));
+        }
+        self.check_quota_locked(task, &reservations)?;
+
+        // 检查资源是否足够
+        if !Self::fits(pool, task) {
+            return Err(EdgeComputingError::InsufficientResources);
         }
+
+        // 分配资源
+        let required = &task.resource_requirements;
+        pool.available_resources.available_cpu_cores -= required.min_cpu_cores;
+        pool.available_resources.available_memory -= required.min_memory;
+        pool.available_resources.available_storage -= required.min_storage;
+        pool.allocated_resources.available_cpu_cores += required.min_cpu_cores;
+        pool.allocated_resources.available_memory += required.min_memory;
+        pool.allocated_resources.available_storage += required.min_storage;
+        Self::update_utilization_rate(pool);
+
+        reservations.insert(task.id.clone(), Reservation {
+            task_id: task.id.clone(),
+            node_id: node_id.to_string(),
+            tenant: task.tenant.clone(),
+            cpu_cores: required.min_cpu_cores,
+            memory: required.min_memory,
+            storage: required.min_storage,
+        });
+        Ok(())
+    }
+
+    /// 更新利用率
+    fn update_utilization_rate(pool: &mut ResourcePool) {
+        pool.utilization_rate = (pool.allocated_resources.available_cpu_cores as f64) / (pool.total_resources.available_cpu_cores as f64);
+    }
+
+    /// 各节点和各租户的资源使用情况
+    pub fn utilization(&self) -> ResourceUtilization {
+        let resource_pool = self.resource_pool.lock().unwrap();
+        let reservations = self.reservations.lock().unwrap();
+        let quotas = self.tenant_quotas.lock().unwrap();
+
+        let ratio = |used: f64, total: f64| if total > 0.0 { used / total } else { 0.0 };
+        let nodes = resource_pool.iter().map(|(node_id, pool)| {
+            let (total, reserved) = (&pool.total_resources, &pool.allocated_resources);
+            (node_id.clone(), NodeUtilization {
+                cpu_ratio: ratio(reserved.available_cpu_cores as f64, total.available_cpu_cores as f64),
+                memory_ratio: ratio(reserved.available_memory as f64, total.available_memory as f64),
+                storage_ratio: ratio(reserved.available_storage as f64, total.available_storage as f64),
+                total: total.clone(),
+                reserved: reserved.clone(),
+            })
+        }).collect();
+
+        let mut tenants: BTreeMap<String, TenantUsage> = BTreeMap::new();
+        let tenant_names = reservations.values().filter_map(|reservation| reservation.tenant.as_deref()).chain(quotas.keys().map(String::as_str));
+        for tenant in tenant_names {
+            tenants.entry(tenant.to_string()).or_insert_with(|| TenantUsage {
+                quota: quotas.get(tenant).copied(),
+                ..Self::tenant_usage(tenant, &reservations)
+            });
+        }
+
+        ResourceUtilization { nodes, tenants }
     }
 }
 
@@ -819,10 +2618,23 @@ impl Default for NetworkManager {
 }
 
 impl NetworkManager {
+    /// 没有测量值时按距离估算链路延迟 (ms/km)，约为光纤中往返一次的时间
+    pub const ESTIMATED_LATENCY_MS_PER_KM: f64 = 0.01;
+
     /// 创建新的网络管理器
     pub fn new() -> Self {
         Self::default()
     }
+
+    /// 记录到节点的链路统计
+    pub fn record_statistics(&self, node_id: &str, statistics: NetworkStatistics) {
+        self.network_monitor.network_stats.lock().unwrap().insert(node_id.to_string(), statistics);
+    }
+
+    /// 测得的到节点的平均链路延迟 (ms)
+    pub fn link_latency(&self, node_id: &str) -> Option<u64> {
+        self.network_monitor.network_stats.lock().unwrap().get(node_id).map(|stats| stats.average_latency)
+    }
 }
 
 /// 错误类型定义
@@ -848,4 +2660,1012 @@ pub enum EdgeComputingError {
     /// 配置错误
     #[error("配置错误: {0}")]
     ConfigurationError(String),
+    /// 提交时截止时间已过
+    #[error("任务截止时间已过: {0}")]
+    DeadlineMissed(String),
+    /// 任务不存在
+    #[error("任务不存在: {0}")]
+    TaskNotFound(String),
+    /// 超出租户配额
+    #[error("租户 {tenant} 的{resource}配额不足: 已预留 {reserved}，申请 {requested}，上限 {limit}")]
+    QuotaExceeded {
+        /// 租户
+        tenant: String,
+        /// 超出配额的资源
+        resource: String,
+        /// 已预留的数量
+        reserved: u64,
+        /// 本次申请的数量
+        requested: u64,
+        /// 配额上限
+        limit: u64,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> EdgeComputingConfig {
+        EdgeComputingConfig {
+            enabled: true,
+            heartbeat_interval: Duration::from_secs(30),
+            task_timeout: Duration::from_secs(60),
+            max_retry_count: 3,
+            load_balancing_strategy: LoadBalancingStrategy::Geographic,
+            failover_strategy: FailoverStrategy::Automatic,
+            max_missed_heartbeats: 3,
+            retry_backoff: Duration::from_secs(1),
+        }
+    }
+
+    fn location(latitude: f64, longitude: f64) -> GeographicLocation {
+        GeographicLocation {
+            latitude,
+            longitude,
+            altitude: 0.0,
+            timezone: "UTC".to_string(),
+            region_code: "test".to_string(),
+        }
+    }
+
+    fn node(id: &str, location: GeographicLocation, cpu_usage: f64) -> EdgeNode {
+        EdgeNode {
+            id: id.to_string(),
+            name: id.to_string(),
+            location,
+            hardware_specs: HardwareSpecifications {
+                cpu_cores: 8,
+                cpu_frequency: 2.5,
+                memory_size: 16_384,
+                storage_size: 256,
+                network_bandwidth: 1000,
+                gpu_support: false,
+                special_hardware: Vec::new(),
+            },
+            resource_status: ResourceStatus {
+                cpu_usage,
+                memory_usage: cpu_usage,
+                storage_usage: 10.0,
+                network_usage: 10.0,
+                available_resources: AvailableResources {
+                    available_cpu_cores: 4,
+                    available_memory: 8192,
+                    available_storage: 128,
+                    available_bandwidth: 500,
+                },
+            },
+            connection_status: ConnectionStatus::Online,
+            last_heartbeat: Utc::now(),
+            capabilities: Vec::new(),
+        }
+    }
+
+    fn task(id: &str, origin: GeographicLocation) -> EdgeTask {
+        EdgeTask {
+            id: id.to_string(),
+            name: id.to_string(),
+            task_type: TaskType::Computation,
+            priority: TaskPriority::Medium,
+            resource_requirements: ResourceRequirements {
+                min_cpu_cores: 1,
+                recommended_cpu_cores: 2,
+                min_memory: 256,
+                recommended_memory: 512,
+                min_storage: 1,
+                network_bandwidth: 10,
+                special_hardware: Vec::new(),
+            },
+            latency_requirements: LatencyRequirements {
+                max_latency: 100,
+                target_latency: 20,
+                latency_type: LatencyType::EndToEnd,
+            },
+            data_dependencies: Vec::new(),
+            estimated_execution_time: Duration::from_millis(50),
+            created_at: Utc::now(),
+            deadline: None,
+            origin: Some(origin),
+            assignment: None,
+            attempts: 0,
+            max_attempts: None,
+            attempt_history: Vec::new(),
+            not_before: None,
+            tenant: None,
+            idempotent: false,
+            invocation: None,
+            required_capabilities: Vec::new(),
+            capability_timeout: None,
+            waiting_since: None,
+            pending_reason: None,
+            deadline_missed: false,
+        }
+    }
+
+    /// 北京的任务；near-light 在天津且负载低，far-idle 在广州且空闲，near-busy 在北京但接近满载
+    fn scenario() -> (HashMap<String, EdgeNode>, EdgeTask) {
+        let nodes = [
+            node("near-light", location(39.08, 117.20), 20.0),
+            node("far-idle", location(23.13, 113.26), 0.0),
+            node("near-busy", location(39.91, 116.40), 95.0),
+        ];
+        let nodes = nodes.into_iter().map(|node| (node.id.clone(), node)).collect();
+        (nodes, task("task-1", location(39.90, 116.41)))
+    }
+
+    #[test]
+    fn test_haversine_distance() {
+        let beijing = location(39.90, 116.41);
+        let shanghai = location(31.23, 121.47);
+        let distance = beijing.distance_km(&shanghai);
+        assert!((distance - 1067.0).abs() < 10.0, "distance was {distance}");
+        assert!(beijing.distance_km(&beijing).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_nearby_lightly_loaded_node_wins() {
+        let (nodes, task) = scenario();
+        let scheduler = TaskScheduler::new();
+        let network = NetworkManager::new();
+
+        let assignment = scheduler.select_node(&task, &nodes, &network).unwrap();
+        assert_eq!(assignment.node_id, "near-light");
+        assert!(assignment.distance_km > 50.0 && assignment.distance_km < 200.0);
+        assert!((assignment.load - 0.2).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_changing_weights_flips_decision() {
+        let (nodes, task) = scenario();
+        let scheduler = TaskScheduler::new();
+        let network = NetworkManager::new();
+
+        scheduler.set_weights(SchedulingWeights { distance: 1.0, load: 0.0, latency: 0.0 });
+        assert_eq!(scheduler.select_node(&task, &nodes, &network).unwrap().node_id, "near-busy");
+
+        scheduler.set_weights(SchedulingWeights { distance: 0.0, load: 1.0, latency: 0.0 });
+        assert_eq!(scheduler.select_node(&task, &nodes, &network).unwrap().node_id, "far-idle");
+    }
+
+    #[test]
+    fn test_measured_latency_overrides_estimate() {
+        let (nodes, task) = scenario();
+        let scheduler = TaskScheduler::new();
+        let network = NetworkManager::new();
+        scheduler.set_weights(SchedulingWeights { distance: 0.0, load: 0.0, latency: 1.0 });
+        network.record_statistics("near-light", NetworkStatistics {
+            bytes_sent: 0,
+            bytes_received: 0,
+            packets_sent: 0,
+            packets_received: 0,
+            packets_dropped: 0,
+            average_latency: 250,
+            bandwidth_utilization: 0.0,
+        });
+
+        let assignment = scheduler.select_node(&task, &nodes, &network).unwrap();
+        assert_eq!(assignment.node_id, "near-busy");
+        assert_eq!(network.link_latency("near-light"), Some(250));
+    }
+
+    #[test]
+    fn test_ties_broken_by_node_id() {
+        let nodes: HashMap<String, EdgeNode> = ["node-c", "node-a", "node-b"]
+            .into_iter()
+            .map(|id| (id.to_string(), node(id, location(0.0, 0.0), 50.0)))
+            .collect();
+        let scheduler = TaskScheduler::new();
+        let network = NetworkManager::new();
+
+        let assignment = scheduler.select_node(&task("task-1", location(1.0, 1.0)), &nodes, &network).unwrap();
+        assert_eq!(assignment.node_id, "node-a");
+    }
+
+    #[test]
+    fn test_offline_nodes_are_not_eligible() {
+        let (mut nodes, task) = scenario();
+        for node in nodes.values_mut() {
+            node.connection_status = ConnectionStatus::Offline;
+        }
+        let result = TaskScheduler::new().select_node(&task, &nodes, &NetworkManager::new());
+        assert!(matches!(result, Err(EdgeComputingError::NoSuitableNode)));
+    }
+
+    #[test]
+    fn test_submit_task_records_assignment() {
+        let manager = EdgeComputingManager::new(config());
+        let (nodes, task) = scenario();
+        for node in nodes.into_values() {
+            manager.register_edge_node(node).unwrap();
+        }
+
+        let submission = manager.submit_task(task).unwrap();
+        assert_eq!(submission.node_id(), Some("near-light"));
+        let queue = manager.task_scheduler.task_queue.lock().unwrap();
+        let assignment = queue.peek().unwrap().assignment.as_ref().unwrap();
+        assert_eq!(assignment.node_id, "near-light");
+        assert!(assignment.score > 0.0);
+    }
+
+    fn queued_task(id: &str, priority: TaskPriority, deadline: Option<DateTime<Utc>>) -> EdgeTask {
+        let mut task = task(id, location(0.0, 0.0));
+        task.priority = priority;
+        task.deadline = deadline;
+        task.estimated_execution_time = Duration::from_secs(60);
+        task
+    }
+
+    #[test]
+    fn test_dispatch_order_is_edf_within_priority() {
+        let now = Utc::now();
+        let minutes = |m: i64| Some(now + chrono::Duration::minutes(m));
+        let scheduler = TaskScheduler::new();
+        for task in [
+            queued_task("medium-best-effort", TaskPriority::Medium, None),
+            queued_task("medium-30", TaskPriority::Medium, minutes(30)),
+            queued_task("high-best-effort", TaskPriority::High, None),
+            queued_task("low-5", TaskPriority::Low, minutes(5)),
+            queued_task("medium-10", TaskPriority::Medium, minutes(10)),
+            queued_task("high-60", TaskPriority::High, minutes(60)),
+            queued_task("medium-best-effort-2", TaskPriority::Medium, None),
+            queued_task("critical-90", TaskPriority::Critical, minutes(90)),
+            queued_task("high-20", TaskPriority::High, minutes(20)),
+        ] {
+            scheduler.schedule_task(task, "node-a").unwrap();
+        }
+        assert_eq!(scheduler.queue_depth(), 9);
+
+        let order: Vec<String> = std::iter::from_fn(|| scheduler.dispatch_next_at(now)).map(|task| task.id).collect();
+        // 截止任务全部先于尽力而为的任务
+        assert_eq!(order, [
+            "critical-90",
+            "high-20",
+            "high-60",
+            "medium-10",
+            "medium-30",
+            "low-5",
+            "high-best-effort",
+            "medium-best-effort",
+            "medium-best-effort-2",
+        ]);
+        assert_eq!(scheduler.queue_depth(), 0);
+    }
+
+    #[test]
+    fn test_late_submission_rejected_by_default() {
+        let manager = EdgeComputingManager::new(config());
+        let (nodes, mut task) = scenario();
+        for node in nodes.into_values() {
+            manager.register_edge_node(node).unwrap();
+        }
+        task.deadline = Some(Utc::now() - chrono::Duration::minutes(1));
+
+        let result = manager.submit_task(task);
+        assert!(matches!(result, Err(EdgeComputingError::DeadlineMissed(id)) if id == "task-1"));
+        assert_eq!(manager.task_scheduler.queue_depth(), 0);
+        let pools = manager.resource_manager.resource_pool.lock().unwrap();
+        assert_eq!(pools["near-light"].allocated_resources.available_cpu_cores, 0);
+    }
+
+    #[test]
+    fn test_late_submission_dispatched_with_flag() {
+        let now = Utc::now();
+        let scheduler = TaskScheduler::new().with_late_policy(LateTaskPolicy::DispatchFlagged);
+        scheduler.schedule_task(queued_task("late", TaskPriority::Medium, Some(now - chrono::Duration::minutes(1))), "node-a").unwrap();
+        scheduler.schedule_task(queued_task("on-time", TaskPriority::Medium, Some(now + chrono::Duration::hours(1))), "node-a").unwrap();
+
+        let late = scheduler.dispatch_next_at(now).unwrap();
+        assert_eq!(late.id, "late");
+        assert!(late.deadline_missed);
+        let on_time = scheduler.dispatch_next_at(now).unwrap();
+        assert!(!on_time.deadline_missed);
+    }
+
+    #[test]
+    fn test_queue_metrics_project_lateness() {
+        let now = Utc::now();
+        let scheduler = TaskScheduler::new();
+        // 每个任务预计 60 秒；按顺序完成于 60 秒、120 秒、180 秒
+        scheduler.schedule_task(queued_task("a", TaskPriority::High, Some(now + chrono::Duration::seconds(90))), "node-a").unwrap();
+        scheduler.schedule_task(queued_task("b", TaskPriority::High, Some(now + chrono::Duration::seconds(100))), "node-a").unwrap();
+        scheduler.schedule_task(queued_task("c", TaskPriority::Low, None), "node-a").unwrap();
+
+        let metrics = scheduler.queue_metrics_at(now);
+        assert_eq!(metrics.depth, 3);
+        assert_eq!(metrics.depth_by_priority[&TaskPriority::High], 2);
+        assert_eq!(metrics.depth_by_priority[&TaskPriority::Low], 1);
+        assert_eq!(metrics.deadline_tasks, 2);
+        assert_eq!(metrics.projected_late_tasks, 1);
+        assert_eq!(metrics.projected_lateness, Duration::from_secs(20));
+        assert_eq!(metrics.max_projected_lateness, Duration::from_secs(20));
+    }
+
+    #[derive(Debug)]
+    struct ManualClock {
+        now: Mutex<DateTime<Utc>>,
+    }
+
+    impl ManualClock {
+        fn new() -> Arc<Self> {
+            Arc::new(Self { now: Mutex::new(Utc::now()) })
+        }
+
+        fn advance(&self, by: Duration) {
+            *self.now.lock().unwrap() += chrono::Duration::from_std(by).unwrap();
+        }
+    }
+
+    impl EdgeClock for ManualClock {
+        fn now(&self) -> DateTime<Utc> {
+            *self.now.lock().unwrap()
+        }
+    }
+
+    fn monitored_manager() -> (EdgeComputingManager, Arc<ManualClock>) {
+        let clock = ManualClock::new();
+        let manager = EdgeComputingManager::new(config()).with_clock(clock.clone());
+        let (nodes, _) = scenario();
+        for mut node in nodes.into_values() {
+            node.last_heartbeat = clock.now();
+            manager.register_edge_node(node).unwrap();
+        }
+        (manager, clock)
+    }
+
+    #[test]
+    fn test_heartbeat_monitor_requires_a_runtime_and_timeout_saturates() {
+        let (manager, clock) = monitored_manager();
+        let manager = Arc::new(manager);
+        assert!(matches!(manager.start_heartbeat_monitor(), Err(EdgeComputingError::ConfigurationError(_))));
+
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_time().build().unwrap();
+        runtime.block_on(async { manager.start_heartbeat_monitor() }).unwrap();
+        manager.stop_heartbeat_monitor();
+
+        // 间隔乘以次数溢出时视为永不超时
+        let mut config = config();
+        config.heartbeat_interval = Duration::MAX;
+        config.max_missed_heartbeats = 3;
+        let saturated = EdgeComputingManager::new(config).with_clock(clock.clone());
+        let (nodes, _) = scenario();
+        for mut node in nodes.into_values() {
+            node.last_heartbeat = clock.now();
+            saturated.register_edge_node(node).unwrap();
+        }
+        clock.advance(Duration::from_secs(86_400));
+        assert!(saturated.check_heartbeats().is_empty());
+    }
+
+    #[test]
+    fn test_missed_heartbeats_move_tasks_and_recovery_does_not_reclaim() {
+        let (manager, clock) = monitored_manager();
+        let (_, task) = scenario();
+        assert_eq!(manager.submit_task(task).unwrap().node_id(), Some("near-light"));
+
+        // 两个间隔未响应：仍在容忍范围内
+        clock.advance(Duration::from_secs(60));
+        manager.record_heartbeat("far-idle").unwrap();
+        manager.record_heartbeat("near-busy").unwrap();
+        assert!(manager.check_heartbeats().is_empty());
+
+        // 四个间隔未响应：超过 3 个间隔的阈值
+        clock.advance(Duration::from_secs(60));
+        manager.record_heartbeat("far-idle").unwrap();
+        manager.record_heartbeat("near-busy").unwrap();
+        assert_eq!(manager.check_heartbeats(), ["near-light"]);
+        assert!(matches!(manager.get_node_status("near-light").unwrap().connection_status, ConnectionStatus::Offline));
+
+        {
+            let queue = manager.task_scheduler.task_queue.lock().unwrap();
+            assert_eq!(queue.len(), 1);
+            let task = queue.peek().unwrap();
+            assert_eq!(task.attempts, 1);
+            assert_eq!(task.assignment.as_ref().unwrap().node_id, "near-busy");
+        }
+        {
+            let pools = manager.resource_manager.resource_pool.lock().unwrap();
+            assert_eq!(pools["near-light"].allocated_resources.available_cpu_cores, 0);
+            assert_eq!(pools["near-busy"].allocated_resources.available_cpu_cores, 1);
+        }
+        let stats = manager.heartbeat_stats();
+        assert_eq!(stats.offline_transitions, 1);
+        assert_eq!(stats.rescheduled_tasks, 1);
+        assert_eq!(stats.requeued_tasks, 0);
+
+        // 恢复心跳：重新上线，但任务留在新节点上
+        clock.advance(Duration::from_secs(10));
+        manager.record_heartbeat("near-light").unwrap();
+        assert!(matches!(manager.get_node_status("near-light").unwrap().connection_status, ConnectionStatus::Online));
+        assert!(manager.check_heartbeats().is_empty());
+        assert_eq!(manager.heartbeat_stats().recoveries, 1);
+        let queue = manager.task_scheduler.task_queue.lock().unwrap();
+        assert_eq!(queue.peek().unwrap().assignment.as_ref().unwrap().node_id, "near-busy");
+        drop(queue);
+
+        // 恢复的节点重新成为候选节点
+        let (_, mut next) = scenario();
+        next.id = "task-2".to_string();
+        assert_eq!(manager.submit_task(next).unwrap().node_id(), Some("near-light"));
+    }
+
+    #[test]
+    fn test_tasks_requeued_unassigned_when_no_healthy_node() {
+        let (manager, clock) = monitored_manager();
+        let (_, task) = scenario();
+        manager.submit_task(task).unwrap();
+
+        clock.advance(Duration::from_secs(120));
+        assert_eq!(manager.check_heartbeats(), ["far-idle", "near-busy", "near-light"]);
+
+        let queue = manager.task_scheduler.task_queue.lock().unwrap();
+        let task = queue.peek().unwrap();
+        assert_eq!(task.attempts, 1);
+        assert!(task.assignment.is_none());
+        assert_eq!(manager.heartbeat_stats().requeued_tasks, 1);
+    }
+
+    #[test]
+    fn test_heartbeat_for_unknown_node() {
+        let (manager, _clock) = monitored_manager();
+        assert!(matches!(manager.record_heartbeat("missing"), Err(EdgeComputingError::NodeNotFound)));
+    }
+
+    fn sized_task(id: &str, cpu_cores: u32, tenant: Option<&str>) -> EdgeTask {
+        let mut task = task(id, location(39.90, 116.41));
+        task.resource_requirements.min_cpu_cores = cpu_cores;
+        task.tenant = tenant.map(str::to_string);
+        task
+    }
+
+    fn single_node_manager() -> EdgeComputingManager {
+        let manager = EdgeComputingManager::new(config());
+        manager.register_edge_node(node("node-a", location(39.90, 116.41), 10.0)).unwrap();
+        manager
+    }
+
+    #[test]
+    fn test_oversubscription_waits_until_release() {
+        let manager = single_node_manager();
+
+        let first = manager.submit_task(sized_task("big-1", 3, None)).unwrap();
+        assert_eq!(first.node_id(), Some("node-a"));
+        let second = manager.submit_task(sized_task("big-2", 3, None)).unwrap();
+        let TaskSubmission::Waiting { reason } = second else {
+            panic!("second task should wait, got {second:?}");
+        };
+        assert_eq!(reason, EdgeComputingError::NoSuitableNode.to_string());
+
+        let utilization = manager.resource_manager.utilization();
+        assert_eq!(utilization.nodes["node-a"].reserved.available_cpu_cores, 3);
+        assert!((utilization.nodes["node-a"].cpu_ratio - 0.75).abs() < 1e-9);
+
+        let running = manager.task_scheduler.dispatch_next().unwrap();
+        assert_eq!(running.id, "big-1");
+        {
+            let queue = manager.task_scheduler.task_queue.lock().unwrap();
+            let waiting = queue.peek().unwrap();
+            assert!(waiting.assignment.is_none());
+            assert!(waiting.pending_reason.is_some());
+        }
+
+        let released = manager.finish_task("big-1", TaskExecutionStatus::Completed).unwrap();
+        assert_eq!((released.node_id.as_str(), released.cpu_cores), ("node-a", 3));
+        {
+            let queue = manager.task_scheduler.task_queue.lock().unwrap();
+            let placed = queue.peek().unwrap();
+            assert_eq!(placed.assignment.as_ref().unwrap().node_id, "node-a");
+            assert!(placed.pending_reason.is_none());
+        }
+        assert_eq!(manager.resource_manager.utilization().nodes["node-a"].reserved.available_cpu_cores, 3);
+
+        manager.finish_task("big-2", TaskExecutionStatus::Failed).unwrap();
+        assert_eq!(manager.resource_manager.utilization().nodes["node-a"].reserved.available_cpu_cores, 0);
+        // 未分发即结束的任务不再留在队列中
+        assert_eq!(manager.task_scheduler.queue_depth(), 0);
+        assert!(manager.task_scheduler.dispatch_next().is_none());
+        assert_eq!(manager.task_status("big-2"), None);
+        assert!(matches!(
+            manager.finish_task("big-2", TaskExecutionStatus::Completed),
+            Err(EdgeComputingError::TaskNotFound(id)) if id == "big-2"
+        ));
+    }
+
+    #[test]
+    fn test_tenant_quota_rejects_excess_submissions() {
+        let manager = single_node_manager();
+        manager.resource_manager.set_tenant_quota("acme", TenantQuota { max_cpu_cores: 2, max_memory: 4096, max_storage: 64 });
+
+        manager.submit_task(sized_task("acme-1", 1, Some("acme"))).unwrap();
+        manager.submit_task(sized_task("acme-2", 1, Some("acme"))).unwrap();
+        let error = manager.submit_task(sized_task("acme-3", 1, Some("acme"))).unwrap_err();
+        assert!(matches!(
+            &error,
+            EdgeComputingError::QuotaExceeded { tenant, reserved: 2, requested: 1, limit: 2, .. } if tenant == "acme"
+        ));
+        let message = error.to_string();
+        assert!(message.contains("acme") && message.contains("CPU"), "{message}");
+        assert_eq!(manager.task_scheduler.queue_depth(), 2);
+
+        // 其他租户不受影响
+        let other = manager.submit_task(sized_task("other-1", 1, Some("other"))).unwrap();
+        assert_eq!(other.node_id(), Some("node-a"));
+
+        let utilization = manager.resource_manager.utilization();
+        let acme = &utilization.tenants["acme"];
+        assert_eq!((acme.reservations, acme.cpu_cores), (2, 2));
+        assert_eq!(acme.quota.unwrap().max_cpu_cores, 2);
+        assert_eq!(utilization.tenants["other"].cpu_cores, 1);
+
+        // 释放后配额恢复
+        manager.finish_task("acme-1", TaskExecutionStatus::Completed).unwrap();
+        assert!(manager.submit_task(sized_task("acme-3", 1, Some("acme"))).is_ok());
+    }
+
+    #[test]
+    fn test_admission_uses_the_manager_clock_only() {
+        let (manager, clock) = two_node_manager();
+        // 管理器的时钟落后一小时：按它的时间任务尚未过截止时间，入队时不再按系统时间重新检查
+        *clock.now.lock().unwrap() -= chrono::Duration::hours(1);
+        let mut on_time = sized_task("on-time", 1, None);
+        on_time.deadline = Some(Utc::now() - chrono::Duration::minutes(30));
+        assert!(manager.submit_task(on_time).is_ok());
+        assert_eq!(manager.task_scheduler.queue_depth(), 1);
+
+        // 按管理器时间已迟到的任务入队时被拒绝，预留随之释放
+        let mut late = sized_task("late", 1, None);
+        late.deadline = Some(clock.now() - chrono::Duration::minutes(1));
+        assert!(matches!(manager.submit_task(late), Err(EdgeComputingError::DeadlineMissed(_))));
+        let reservations = manager.resource_manager.reservations.lock().unwrap();
+        assert_eq!(reservations.keys().collect::<Vec<_>>(), ["on-time"]);
+        assert_eq!(manager.task_scheduler.queue_depth(), 1);
+    }
+
+    #[test]
+    fn test_scoring_counts_reservations_and_uses_the_clock() {
+        let clock = ManualClock::new();
+        let manager = EdgeComputingManager::new(config()).with_clock(clock.clone());
+        manager.register_edge_node(node("node-a", location(39.90, 116.41), 10.0)).unwrap();
+        manager.register_edge_node(node("node-b", location(39.90, 116.41), 10.0)).unwrap();
+
+        // 两个节点完全相同，第一个任务的预留使 node-a 的负载升高，第二个任务落到 node-b
+        let first = manager.submit_task(sized_task("first", 2, None)).unwrap();
+        let second = manager.submit_task(sized_task("second", 2, None)).unwrap();
+        assert_eq!((first.node_id(), second.node_id()), (Some("node-a"), Some("node-b")));
+
+        // 各自被选中时节点上还没有预留，负载只有注册时的 10%
+        let queue = manager.task_scheduler.task_queue.lock().unwrap();
+        for task in queue.ordered() {
+            let assignment = task.assignment.as_ref().unwrap();
+            assert_eq!(assignment.assigned_at, clock.now());
+            assert!((assignment.load - 0.1).abs() < 1e-9);
+        }
+        assert!((manager.resource_manager.reserved_ratio("node-a") - 0.5).abs() < 1e-9);
+    }
+
+    fn two_node_manager() -> (EdgeComputingManager, Arc<ManualClock>) {
+        let clock = ManualClock::new();
+        let manager = EdgeComputingManager::new(config()).with_clock(clock.clone());
+        manager.register_edge_node(node("node-a", location(39.90, 116.41), 10.0)).unwrap();
+        manager.register_edge_node(node("node-b", location(39.08, 117.20), 10.0)).unwrap();
+        (manager, clock)
+    }
+
+    fn retried_task(max_attempts: u32, clock: &ManualClock) -> EdgeTask {
+        let mut task = task("retried", location(39.90, 116.41));
+        task.max_attempts = Some(max_attempts);
+        task.deadline = Some(clock.now() + chrono::Duration::hours(1));
+        task
+    }
+
+    #[test]
+    fn test_failover_to_second_node_after_failure() {
+        let (manager, clock) = two_node_manager();
+        let task = retried_task(3, &clock);
+        let deadline = task.deadline;
+        manager.submit_task(task).unwrap();
+
+        let first = manager.dispatch_next().unwrap();
+        assert_eq!(first.assignment.as_ref().unwrap().node_id, "node-a");
+        let outcome = manager.fail_task("retried", "node-a crashed").unwrap();
+        assert_eq!(outcome, RetryOutcome::Retrying {
+            node_id: Some("node-b".to_string()),
+            retry_at: clock.now() + chrono::Duration::seconds(1),
+        });
+
+        // 退避期间不分发
+        assert!(manager.dispatch_next().is_none());
+        clock.advance(Duration::from_secs(1));
+        let retry = manager.dispatch_next().unwrap();
+        assert_eq!(retry.assignment.as_ref().unwrap().node_id, "node-b");
+        assert_eq!(retry.deadline, deadline);
+        assert_eq!(retry.attempts, 1);
+        assert_eq!(retry.attempt_history[0].node_id.as_deref(), Some("node-a"));
+        assert_eq!(retry.attempt_history[0].error, "node-a crashed");
+
+        manager.finish_task("retried", TaskExecutionStatus::Completed).unwrap();
+        assert!(manager.failed_tasks().is_empty());
+        assert_eq!(manager.resource_manager.utilization().nodes["node-a"].reserved.available_cpu_cores, 0);
+    }
+
+    #[test]
+    fn test_task_failing_everywhere_is_dead_lettered() {
+        let (manager, clock) = two_node_manager();
+        manager.submit_task(retried_task(2, &clock)).unwrap();
+
+        let first = manager.dispatch_next().unwrap();
+        manager.fail_task(&first.id, "boom on a").unwrap();
+        clock.advance(Duration::from_secs(1));
+        let second = manager.dispatch_next().unwrap();
+        assert_eq!(second.assignment.as_ref().unwrap().node_id, "node-b");
+        let outcome = manager.fail_task(&second.id, "boom on b").unwrap();
+        assert_eq!(outcome, RetryOutcome::DeadLettered { attempts: 2 });
+
+        let failed = manager.failed_tasks();
+        assert_eq!(failed.len(), 1);
+        let attempts: Vec<(Option<&str>, &str)> = failed[0].attempts().iter()
+            .map(|attempt| (attempt.node_id.as_deref(), attempt.error.as_str()))
+            .collect();
+        assert_eq!(attempts, [(Some("node-a"), "boom on a"), (Some("node-b"), "boom on b")]);
+        assert_eq!(manager.task_scheduler.queue_depth(), 0);
+        let utilization = manager.resource_manager.utilization();
+        assert!(utilization.nodes.values().all(|node| node.reserved.available_cpu_cores == 0));
+    }
+
+    #[test]
+    fn test_retry_falls_back_to_failed_node_without_alternatives() {
+        let clock = ManualClock::new();
+        let manager = EdgeComputingManager::new(config()).with_clock(clock.clone());
+        manager.register_edge_node(node("node-a", location(39.90, 116.41), 10.0)).unwrap();
+        manager.submit_task(retried_task(3, &clock)).unwrap();
+
+        manager.dispatch_next().unwrap();
+        manager.fail_task("retried", "transient").unwrap();
+        assert!(manager.dispatch_next().is_none());
+        clock.advance(Duration::from_secs(1));
+        manager.dispatch_next().unwrap();
+
+        // 第二次失败的退避时间翻倍
+        let outcome = manager.fail_task("retried", "transient").unwrap();
+        assert_eq!(outcome, RetryOutcome::Retrying {
+            node_id: Some("node-a".to_string()),
+            retry_at: clock.now() + chrono::Duration::seconds(2),
+        });
+    }
+
+    fn placement_manager() -> EdgeComputingManager {
+        let manager = EdgeComputingManager::new(config());
+        let mut edge = node("edge-1", location(39.90, 116.41), 10.0);
+        edge.location.region_code = "cn-north".to_string();
+        manager.register_edge_node(edge).unwrap();
+        manager
+    }
+
+    #[test]
+    fn test_cost_model_estimate() {
+        let model = CostModel::default();
+        let instructions = [Instruction::GetLocal(0), Instruction::I32Const(2), Instruction::I32Mul, Instruction::I32Div, Instruction::Call(1)];
+        assert_eq!(model.estimate(&instructions, 1), 1 + 1 + 3 + 20 + 10);
+        assert_eq!(TaskProfile::from_instructions(&model, &instructions, 1000).compute_cost, 35_000);
+    }
+
+    #[test]
+    fn test_tiny_task_stays_local() {
+        let manager = placement_manager();
+        let model = CostModel::default();
+        let profile = TaskProfile::from_instructions(&model, &[Instruction::I32Const(1), Instruction::I32Add], 100)
+            .with_data(1024, 64)
+            .with_origin(location(39.08, 117.20));
+
+        let decision = manager.decide_placement(profile);
+        assert_eq!(decision.target(), &PlacementTarget::Local);
+        assert_eq!(decision.chosen.breakdown.transfer, Duration::ZERO);
+        assert_eq!(decision.candidates.len(), 2);
+    }
+
+    #[test]
+    fn test_heavy_task_goes_to_edge() {
+        let manager = placement_manager();
+        let profile = TaskProfile::new(10_000_000_000).with_data(1_000_000, 1_000);
+
+        let decision = manager.decide_placement(profile);
+        assert_eq!(decision.target(), &PlacementTarget::Edge { node_id: "edge-1".to_string() });
+        // 2.5 GHz 节点：计算 4 秒；500 Mbps 传输约 16 毫秒
+        assert_eq!(decision.chosen.breakdown.compute, Duration::from_secs(4));
+        assert!(decision.chosen.breakdown.transfer > Duration::from_millis(15));
+        assert_eq!(decision.estimated_completion(), decision.chosen.breakdown.total());
+    }
+
+    #[test]
+    fn test_queue_wait_counts_against_edge() {
+        let manager = placement_manager();
+        let mut queued = task("queued", location(39.90, 116.41));
+        queued.estimated_execution_time = Duration::from_secs(60);
+        manager.submit_task(queued).unwrap();
+
+        let decision = manager.decide_placement(TaskProfile::new(10_000_000_000));
+        let edge = decision.candidates.iter().find(|candidate| candidate.target != PlacementTarget::Local).unwrap();
+        // 本地 20 秒，得分 40；边缘计算 4 秒 + 排队 60 秒，得分 64
+        assert_eq!(edge.breakdown.queue_wait, Duration::from_secs(60));
+        assert_eq!(decision.target(), &PlacementTarget::Local);
+    }
+
+    #[test]
+    fn test_latency_budget_forces_local_despite_cost() {
+        let manager = placement_manager();
+        // 本地 0.5 秒，得分 1.0；边缘计算 0.1 秒 + 传输 0.48 秒，得分 0.58 但超出预算
+        let profile = TaskProfile::new(250_000_000).with_data(30_000_000, 0);
+
+        let unconstrained = manager.decide_placement(profile.clone());
+        assert!(matches!(unconstrained.target(), PlacementTarget::Edge { .. }));
+
+        let decision = manager.decide_placement(profile.with_latency_budget(Duration::from_millis(550)));
+        assert_eq!(decision.target(), &PlacementTarget::Local);
+        assert!(decision.chosen.within_budget);
+        let edge = decision.candidates.iter().find(|candidate| candidate.target != PlacementTarget::Local).unwrap();
+        assert!(!edge.within_budget);
+        assert!(edge.score < decision.chosen.score);
+    }
+
+    #[test]
+    fn test_residency_excludes_disallowed_regions() {
+        let manager = placement_manager().with_placement_config(PlacementConfig {
+            cloud: Some(CloudEndpoint {
+                region_code: "us-east".to_string(),
+                cycles_per_second: 1e11,
+                bandwidth_mbps: 1000.0,
+                round_trip_latency: Duration::from_millis(80),
+            }),
+            ..PlacementConfig::default()
+        });
+        let mut eu = node("edge-eu", location(48.85, 2.35), 10.0);
+        eu.location.region_code = "eu-west".to_string();
+        manager.register_edge_node(eu).unwrap();
+        let profile = TaskProfile::new(100_000_000_000).with_origin(location(39.90, 116.41));
+
+        // 不受限制时云端最快
+        let decision = manager.decide_placement(profile.clone());
+        assert_eq!(decision.target(), &PlacementTarget::Cloud { region_code: "us-east".to_string() });
+
+        // 只允许欧洲：北京边缘节点和美国云端都不参与比较
+        let decision = manager.decide_placement(profile.clone().with_allowed_regions(["eu-west"]));
+        assert_eq!(decision.target(), &PlacementTarget::Edge { node_id: "edge-eu".to_string() });
+        let targets: Vec<&PlacementTarget> = decision.candidates.iter().map(|candidate| &candidate.target).collect();
+        assert_eq!(targets, [&PlacementTarget::Local, &PlacementTarget::Edge { node_id: "edge-eu".to_string() }]);
+
+        // 没有任何允许地区的远端目标时留在本地
+        let decision = manager.decide_placement(profile.with_allowed_regions(["ap-south"]));
+        assert_eq!(decision.target(), &PlacementTarget::Local);
+        assert_eq!(decision.candidates.len(), 1);
+    }
+
+    fn image_task(id: &str, idempotent: bool, args: serde_json::Value) -> EdgeTask {
+        let mut task = task(id, location(39.90, 116.41));
+        task.idempotent = idempotent;
+        task.invocation = Some(TaskInvocation::new("image-tools", "1.2.0", "resize", args, b"raw image bytes"));
+        task
+    }
+
+    /// 提交任务；进入调度器的任务立即分发并以给定输出完成，返回执行次数的增量
+    fn run(manager: &EdgeComputingManager, task: EdgeTask, output: &[u8]) -> (EdgeTaskResult, usize) {
+        match manager.submit_task(task).unwrap() {
+            TaskSubmission::Cached { result } => (result, 0),
+            TaskSubmission::Scheduled { .. } => {
+                let dispatched = manager.dispatch_next().unwrap();
+                (manager.complete_task(&dispatched.id, output.to_vec()).unwrap(), 1)
+            }
+            other => panic!("unexpected submission {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_identical_idempotent_submissions_execute_once() {
+        let manager = single_node_manager();
+        let args = serde_json::json!({"width": 640, "height": 480});
+
+        let (first, first_runs) = run(&manager, image_task("resize-1", true, args.clone()), b"thumbnail");
+        assert!(!first.served_from_cache);
+        assert_eq!(first.node_id.as_deref(), Some("node-a"));
+
+        // 参数书写顺序不同，内容相同
+        let reordered = serde_json::json!({"height": 480, "width": 640});
+        let (second, second_runs) = run(&manager, image_task("resize-2", true, reordered), b"recomputed");
+        assert_eq!(first_runs + second_runs, 1);
+        assert!(second.served_from_cache);
+        assert_eq!(second.task_id, "resize-2");
+        assert_eq!(second.output, b"thumbnail");
+        assert_eq!(manager.task_scheduler.queue_depth(), 0);
+        assert_eq!(manager.result_cache.get_statistics().hits, 1);
+    }
+
+    #[test]
+    fn test_completing_undispatched_task_is_rejected() {
+        let manager = single_node_manager();
+        let args = serde_json::json!({"width": 640});
+        let submission = manager.submit_task(image_task("resize-1", true, args.clone())).unwrap();
+        assert!(matches!(submission, TaskSubmission::Scheduled { .. }));
+
+        assert!(matches!(
+            manager.complete_task("resize-1", b"premature".to_vec()),
+            Err(EdgeComputingError::TaskNotFound(id)) if id == "resize-1"
+        ));
+        // 任务仍在队列中，输出也没有写入缓存
+        assert_eq!(manager.task_scheduler.queue_depth(), 1);
+        assert_eq!(manager.result_cache.get_statistics().entry_count, 0);
+
+        let dispatched = manager.dispatch_next().unwrap();
+        let result = manager.complete_task(&dispatched.id, b"thumbnail".to_vec()).unwrap();
+        assert_eq!(result.node_id.as_deref(), Some("node-a"));
+        let (cached, runs) = run(&manager, image_task("resize-2", true, args), b"recomputed");
+        assert_eq!((cached.output.as_slice(), runs), (b"thumbnail".as_slice(), 0));
+    }
+
+    #[test]
+    fn test_different_argument_misses_cache() {
+        let manager = single_node_manager();
+        run(&manager, image_task("resize-1", true, serde_json::json!({"width": 640})), b"small");
+
+        let (result, runs) = run(&manager, image_task("resize-2", true, serde_json::json!({"width": 1280})), b"large");
+        assert_eq!(runs, 1);
+        assert!(!result.served_from_cache);
+        assert_eq!(result.output, b"large");
+
+        let mut other_input = image_task("resize-3", true, serde_json::json!({"width": 640}));
+        other_input.invocation = Some(TaskInvocation::new("image-tools", "1.2.0", "resize", serde_json::json!({"width": 640}), b"other image"));
+        assert_eq!(run(&manager, other_input, b"other").1, 1);
+    }
+
+    #[test]
+    fn test_non_idempotent_tasks_never_use_cache() {
+        let manager = single_node_manager();
+        let args = serde_json::json!({"width": 640});
+
+        let runs: usize = ["resize-1", "resize-2"].into_iter()
+            .map(|id| run(&manager, image_task(id, false, args.clone()), b"thumbnail").1)
+            .sum();
+        assert_eq!(runs, 2);
+        let stats = manager.result_cache.get_statistics();
+        assert_eq!((stats.hits, stats.misses, stats.entry_count), (0, 0, 0));
+    }
+
+    #[test]
+    fn test_cached_results_expire_after_ttl() {
+        let manager = single_node_manager().with_result_cache(Arc::new(EdgeComputingManager::default_result_cache()), Duration::ZERO);
+        let args = serde_json::json!({"width": 640});
+        run(&manager, image_task("resize-1", true, args.clone()), b"thumbnail");
+        assert_eq!(run(&manager, image_task("resize-2", true, args), b"thumbnail").1, 1);
+    }
+
+    fn gpu_node(id: &str, location: GeographicLocation) -> EdgeNode {
+        let mut node = node(id, location, 10.0);
+        node.hardware_specs.gpu_support = true;
+        node.capabilities = vec![Capability::Gpu];
+        node
+    }
+
+    fn gpu_task(id: &str) -> EdgeTask {
+        let mut task = task(id, location(39.90, 116.41));
+        task.required_capabilities = vec![Capability::Gpu];
+        task
+    }
+
+    #[test]
+    fn test_gpu_task_skips_cpu_only_nodes() {
+        let manager = EdgeComputingManager::new(config());
+        manager.register_edge_node(node("cpu-near", location(39.90, 116.41), 10.0)).unwrap();
+        manager.register_edge_node(gpu_node("gpu-far", location(31.23, 121.47))).unwrap();
+
+        assert_eq!(manager.submit_task(gpu_task("render")).unwrap().node_id(), Some("gpu-far"));
+        let cpu_task = task("plain", location(39.90, 116.41));
+        assert_eq!(manager.submit_task(cpu_task).unwrap().node_id(), Some("cpu-near"));
+    }
+
+    #[test]
+    fn test_gpu_task_waits_until_gpu_node_registers() {
+        let manager = single_node_manager();
+
+        let submission = manager.submit_task(gpu_task("render")).unwrap();
+        assert_eq!(submission, TaskSubmission::WaitingForCapability { required: vec![Capability::Gpu] });
+        assert_eq!(manager.task_status("render"), Some(TaskExecutionStatus::WaitingForCapability));
+        assert!(manager.dispatch_next().is_none());
+
+        manager.register_edge_node(gpu_node("gpu-1", location(39.90, 116.41))).unwrap();
+        assert_eq!(manager.task_status("render"), Some(TaskExecutionStatus::Pending));
+        let dispatched = manager.dispatch_next().unwrap();
+        assert_eq!(dispatched.id, "render");
+        assert_eq!(dispatched.assignment.unwrap().node_id, "gpu-1");
+        assert_eq!(manager.task_status("render"), Some(TaskExecutionStatus::Running));
+    }
+
+    #[test]
+    fn test_capability_wait_reevaluated_on_recovery_and_times_out() {
+        let clock = ManualClock::new();
+        let manager = EdgeComputingManager::new(config()).with_clock(clock.clone());
+        let mut gpu = gpu_node("gpu-1", location(39.90, 116.41));
+        gpu.last_heartbeat = clock.now();
+        manager.register_edge_node(gpu).unwrap();
+
+        clock.advance(Duration::from_secs(120));
+        assert_eq!(manager.check_heartbeats(), ["gpu-1"]);
+
+        let waiting = gpu_task("waits");
+        assert!(matches!(manager.submit_task(waiting).unwrap(), TaskSubmission::WaitingForCapability { .. }));
+        let mut bounded = gpu_task("bounded");
+        bounded.capability_timeout = Some(Duration::from_secs(30));
+        manager.submit_task(bounded).unwrap();
+
+        // 超时的任务移入死信列表，没有超时的任务继续等待
+        clock.advance(Duration::from_secs(30));
+        assert!(manager.check_heartbeats().is_empty());
+        assert_eq!(manager.task_status("bounded"), Some(TaskExecutionStatus::Timeout));
+        let failed = manager.failed_tasks();
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].attempts()[0].node_id, None);
+
+        manager.record_heartbeat("gpu-1").unwrap();
+        assert_eq!(manager.task_status("waits"), Some(TaskExecutionStatus::Pending));
+        assert_eq!(manager.dispatch_next().unwrap().assignment.unwrap().node_id, "gpu-1");
+    }
+
+    #[test]
+    fn test_registration_validates_capabilities() {
+        let manager = EdgeComputingManager::new(config());
+
+        let mut unsupported = node("cpu-only", location(0.0, 0.0), 10.0);
+        unsupported.capabilities = vec![Capability::Gpu];
+        let error = manager.register_edge_node(unsupported).unwrap_err();
+        assert!(matches!(&error, EdgeComputingError::ConfigurationError(message) if message.contains("cpu-only")));
+
+        let mut duplicated = node("crypto", location(0.0, 0.0), 10.0);
+        duplicated.hardware_specs.special_hardware = vec![SpecialHardware::CryptoChip];
+        duplicated.capabilities = vec![Capability::Encryption, Capability::Encryption];
+        assert!(matches!(manager.register_edge_node(duplicated), Err(EdgeComputingError::ConfigurationError(_))));
+        assert!(manager.get_all_nodes_status().is_empty());
+
+        let mut valid = node("crypto", location(0.0, 0.0), 10.0);
+        valid.hardware_specs.special_hardware = vec![SpecialHardware::CryptoChip, SpecialHardware::CompressionAccelerator];
+        valid.capabilities = valid.hardware_specs.capabilities();
+        assert_eq!(valid.capabilities, [Capability::Encryption, Capability::Compression]);
+        manager.register_edge_node(valid).unwrap();
+    }
+
+    #[test]
+    fn test_weights_follow_scheduling_strategy() {
+        let default = TaskScheduler::new();
+        assert_eq!(default.weights(), SchedulingWeights::for_strategy(&default.scheduling_strategy));
+
+        let nearest = TaskScheduler::new().with_scheduling_strategy(SchedulingStrategy::NearestNodeFirst);
+        assert_eq!(nearest.weights(), SchedulingWeights { distance: 1.0, load: 0.0, latency: 0.0 });
+        let (nodes, task) = scenario();
+        assert_eq!(nearest.select_node(&task, &nodes, &NetworkManager::new()).unwrap().node_id, "near-busy");
+    }
+
+    #[test]
+    fn test_queue_metrics_saturate_on_huge_estimates() {
+        let now = Utc::now();
+        let scheduler = TaskScheduler::new();
+        let mut huge = queued_task("huge", TaskPriority::High, Some(now + chrono::Duration::seconds(10)));
+        huge.estimated_execution_time = Duration::MAX;
+        scheduler.schedule_task(huge, "node-a").unwrap();
+        let mut after = queued_task("after", TaskPriority::Medium, Some(now + chrono::Duration::seconds(20)));
+        after.estimated_execution_time = Duration::from_secs(u64::MAX / 4);
+        scheduler.schedule_task(after, "node-a").unwrap();
+
+        let metrics = scheduler.queue_metrics_at(now);
+        assert_eq!(metrics.projected_late_tasks, 2);
+        assert!(metrics.max_projected_lateness > Duration::from_secs(365 * 24 * 3600));
+    }
+
+    #[test]
+    fn test_huge_retry_backoff_saturates() {
+        let clock = ManualClock::new();
+        let manager = EdgeComputingManager::new(EdgeComputingConfig { retry_backoff: Duration::MAX, ..config() })
+            .with_clock(clock.clone());
+        manager.register_edge_node(node("node-a", location(39.90, 116.41), 10.0)).unwrap();
+        manager.submit_task(retried_task(3, &clock)).unwrap();
+        manager.dispatch_next().unwrap();
+
+        let outcome = manager.fail_task("retried", "boom").unwrap();
+        assert!(matches!(outcome, RetryOutcome::Retrying { retry_at, .. } if retry_at == DateTime::<Utc>::MAX_UTC));
+        assert!(manager.dispatch_next().is_none());
+    }
 }
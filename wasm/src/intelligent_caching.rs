@@ -3,638 +3,5499 @@
 //! 本模块提供了智能缓存、性能优化和资源管理功能
 
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap};
-use std::sync::{Arc, Mutex, RwLock};
-use std::time::{Duration, Instant};
+use crate::monitoring_advanced::{BucketLayout, MetricMetadata, MetricsCollector};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering};
+use std::future::Future;
+use std::sync::{Arc, Condvar, Mutex, RwLock, Weak};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use thiserror::Error;
 
 /// 智能缓存管理器
 /// Intelligent Cache Manager
+///
+/// 条目和统计按分片存放，原来的公开字段 `storage` 和 `statistics` 已移除：
+/// 读取条目使用 [`Self::entries`] / [`Self::entry`]，统计使用 [`Self::get_statistics`] / [`Self::shard_statistics`]。
+/// Entries and statistics live in shards and the former public `storage` and `statistics` fields have been removed:
+/// read entries through [`Self::entries`] / [`Self::entry`] and statistics through [`Self::get_statistics`] /
+/// [`Self::shard_statistics`].
 #[derive(Debug)]
 pub struct IntelligentCacheManager {
-    /// 缓存存储
-    pub storage: Arc<RwLock<HashMap<String, CacheEntry>>>,
     /// 缓存策略
     pub policies: HashMap<String, CachePolicy>,
-    /// 统计信息
-    pub statistics: Arc<Mutex<CacheStatistics>>,
     /// 配置
     pub config: CacheConfig,
+    /// 按键哈希路由的分片，各自持有存储、驱逐索引和统计
+    shards: Box<[CacheShard]>,
+    /// 全部分片的条目数和字节数
+    totals: CacheTotals,
+    /// 写入时计算条目占用字节数
+    weigher: Arc<dyn Weigher>,
+    /// 判断过期使用的时钟
+    clock: Arc<dyn CacheClock>,
+    /// 后台过期清理任务
+    sweeper_task: Mutex<Option<tokio::task::JoinHandle<()>>>,
+    /// 本地缓存之后的存储后端，`None` 表示只使用本地缓存
+    backend: Option<Arc<dyn CacheBackend>>,
+    /// 写入后端的方式
+    write_mode: WriteMode,
+    /// 回写模式下尚未写入后端的操作，按发生顺序排列，同一个键只保留最后一次
+    pending_writes: Mutex<Vec<PendingWrite>>,
+    /// 预测预取，`None` 表示未启用
+    prefetch: Option<Prefetcher>,
+    /// 后台预取任务
+    prefetch_task: Mutex<Option<tokio::task::JoinHandle<()>>>,
+    /// 负缓存：确认不存在的键和加载失败的键
+    negative: Mutex<NegativeCache>,
+    /// 是否记录过负缓存条目，未记录时写入不必获取负缓存的锁
+    negative_in_use: AtomicBool,
+    /// 正在加载的键，同一个键的并发调用等待同一次加载
+    in_flight: Mutex<HashMap<String, Arc<Flight>>>,
+    /// 单次加载的最长时间，超时后等待者收到错误，之后的调用重新加载
+    load_timeout: Duration,
+    /// 插入、命中、驱逐和移除事件的钩子
+    hooks: CacheHooks,
 }
 
-/// 缓存条目
-/// Cache Entry
-#[derive(Debug, Clone)]
-pub struct CacheEntry {
-    /// 值
-    pub value: Vec<u8>,
-    /// 创建时间
-    pub created_at: Instant,
-    /// 最后访问时间
-    pub last_accessed: Instant,
-    /// 访问次数
-    pub access_count: u64,
-    /// TTL
-    pub ttl: Duration,
-    /// 优先级
-    pub priority: CachePriority,
-    /// 标签
-    pub tags: Vec<String>,
+/// 一次进行中的加载，完成后唤醒同步和异步的等待者
+#[derive(Debug)]
+struct Flight {
+    started: Instant,
+    result: Mutex<Option<Result<Vec<u8>, CacheError>>>,
+    done: Condvar,
+    notify: tokio::sync::Notify,
 }
 
-/// 缓存策略
-/// Cache Policy
-#[derive(Debug, Clone)]
-pub struct CachePolicy {
-    /// 策略名称
-    pub name: String,
-    /// 最大大小
-    pub max_size: usize,
-    /// 默认 TTL
-    pub default_ttl: Duration,
-    /// 驱逐策略
-    pub eviction_policy: EvictionPolicy,
-    /// 压缩策略
-    pub compression_policy: CompressionPolicy,
+impl Flight {
+    fn result(&self) -> Option<Result<Vec<u8>, CacheError>> {
+        self.result.lock().unwrap().clone()
+    }
 }
 
-/// 驱逐策略
-/// Eviction Policy
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum EvictionPolicy {
-    /// 最近最少使用
-    LRU,
-    /// 最少使用频率
-    LFU,
-    /// 先进先出
-    FIFO,
-    /// 基于时间
-    TTL,
-    /// 随机
-    Random,
+/// 加载者持有的守卫；加载者 panic 或被取消而未完成时，以 [`CacheError::LoadAborted`] 唤醒等待者并释放该键
+struct FlightGuard<'a> {
+    cache: &'a IntelligentCacheManager,
+    key: &'a str,
+    /// 完成后取出，`Drop` 时为 `None` 表示已公布结果
+    flight: Option<Arc<Flight>>,
 }
 
-/// 压缩策略
-/// Compression Policy
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum CompressionPolicy {
-    /// 不压缩
-    None,
-    /// Gzip 压缩
-    Gzip,
-    /// LZ4 压缩
-    LZ4,
-    /// 自适应压缩
-    Adaptive,
+/// 加入进行中加载的结果
+enum FlightJoin {
+    /// 上一次加载已完成并写入了缓存
+    Cached(Vec<u8>),
+    /// 等待其他调用者的加载
+    Waiter(Arc<Flight>),
+    /// 由当前调用者加载
+    Leader(Arc<Flight>),
 }
 
-/// 缓存优先级
-/// Cache Priority
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
-pub enum CachePriority {
-    /// 低优先级
-    Low = 1,
-    /// 中等优先级
-    Medium = 2,
-    /// 高优先级
-    High = 3,
-    /// 关键优先级
-    Critical = 4,
+impl<'a> FlightGuard<'a> {
+    fn new(cache: &'a IntelligentCacheManager, key: &'a str, flight: Arc<Flight>) -> Self {
+        Self { cache, key, flight: Some(flight) }
+    }
+
+    fn finish(mut self, result: Result<Vec<u8>, CacheError>) {
+        if let Some(flight) = self.flight.take() {
+            self.cache.finish_flight(self.key, &flight, result);
+        }
+    }
 }
 
-/// 缓存统计信息
-/// Cache Statistics
-#[derive(Debug, Clone)]
-pub struct CacheStatistics {
-    /// 命中次数
-    pub hits: u64,
-    /// 未命中次数
-    pub misses: u64,
-    /// 驱逐次数
-    pub evictions: u64,
-    /// 总大小
-    pub total_size: usize,
-    /// 条目数量
-    pub entry_count: usize,
-    /// 平均访问时间
-    pub avg_access_time: Duration,
+impl Drop for FlightGuard<'_> {
+    fn drop(&mut self) {
+        if let Some(flight) = self.flight.take() {
+            self.cache.finish_flight(self.key, &flight, Err(CacheError::LoadAborted));
+        }
+    }
 }
 
-/// 缓存配置
-/// Cache Configuration
-#[derive(Debug, Clone)]
-pub struct CacheConfig {
-    /// 默认最大大小
-    pub default_max_size: usize,
-    /// 清理间隔
-    pub cleanup_interval: Duration,
-    /// 统计间隔
-    pub statistics_interval: Duration,
-    /// 是否启用压缩
-    pub compression_enabled: bool,
-    /// 是否启用预热
-    pub warmup_enabled: bool,
+/// 分片可以超出其均分份额的比例（百分比），使热点分片在其他分片空闲时不必立即驱逐
+const SHARD_OVERFLOW_PERCENT: usize = 25;
+
+/// 缓存分片；锁顺序为存储、驱逐索引、统计
+/// A cache shard; locks are taken in the order storage, eviction index, statistics
+#[derive(Debug)]
+struct CacheShard {
+    storage: RwLock<HashMap<String, CacheEntry>>,
+    eviction: Mutex<EvictionState>,
+    statistics: Mutex<CacheStatistics>,
+    /// 写入次数，负缓存据此判断加载期间分片是否被写入过
+    writes: AtomicU64,
 }
 
-impl IntelligentCacheManager {
-    /// 创建新的智能缓存管理器
-    pub fn new(config: CacheConfig) -> Self {
+impl CacheShard {
+    fn new(policy: &CachePolicy, shard_count: usize, index: usize) -> Self {
+        let limits = ShardLimits::new(policy, shard_count, index);
         Self {
-            storage: Arc::new(RwLock::new(HashMap::new())),
-            policies: HashMap::new(),
-            statistics: Arc::new(Mutex::new(CacheStatistics {
-                hits: 0,
-                misses: 0,
-                evictions: 0,
-                total_size: 0,
-                entry_count: 0,
-                avg_access_time: Duration::ZERO,
-            })),
-            config,
+            storage: RwLock::new(HashMap::new()),
+            eviction: Mutex::new(EvictionState {
+                tracker: new_tracker(policy.eviction_policy, limits.max_entries),
+                policy: policy.clone(),
+                limits,
+                retired: HashMap::new(),
+                expiry: BTreeSet::new(),
+            }),
+            statistics: Mutex::new(CacheStatistics::new(policy.eviction_policy)),
+            writes: AtomicU64::new(0),
         }
     }
+}
 
-    /// 获取缓存值
-    pub fn get(&self, key: &str) -> Option<Vec<u8>> {
-        let start_time = Instant::now();
-        
-        let mut storage = self.storage.write().unwrap();
-        if let Some(entry) = storage.get_mut(key) {
-            // 检查是否过期
-            if entry.created_at.elapsed() < entry.ttl {
-                entry.last_accessed = Instant::now();
-                entry.access_count += 1;
-                
-                // 更新统计信息
-                let mut stats = self.statistics.lock().unwrap();
-                stats.hits += 1;
-                stats.avg_access_time = (stats.avg_access_time + start_time.elapsed()) / 2;
-                
-                return Some(entry.value.clone());
-            } else {
-                // 过期，移除条目
-                storage.remove(key);
-                let mut stats = self.statistics.lock().unwrap();
-                stats.evictions += 1;
-                stats.entry_count -= 1;
-            }
+/// 分片的容量：均分的份额和含溢出余量的硬上限
+/// Shard capacity: the even share and the hard limit including the overflow allowance
+#[derive(Debug, Clone, Copy)]
+struct ShardLimits {
+    share_entries: usize,
+    share_bytes: Option<usize>,
+    max_entries: usize,
+    max_bytes: Option<usize>,
+}
+
+impl ShardLimits {
+    /// 第 `index` 个分片的容量；单个分片时份额即整个容量，不留余量
+    ///
+    /// 份额之和恰好等于配置的容量；份额为零的分片（容量少于分片数时）仍可存放一个条目，只要总量未超出容量。
+    fn new(policy: &CachePolicy, shard_count: usize, index: usize) -> Self {
+        let allowance = |share: usize| {
+            if shard_count == 1 { share } else { share + (share * SHARD_OVERFLOW_PERCENT).div_ceil(100) }
+        };
+        let share_entries = split_share(policy.max_size.max(1), shard_count, index);
+        let share_bytes = policy.max_bytes.map(|budget| split_share(budget, shard_count, index));
+        Self {
+            share_entries,
+            share_bytes,
+            max_entries: allowance(share_entries).max(1),
+            max_bytes: share_bytes.map(allowance),
         }
-        
-        // 未命中
-        let mut stats = self.statistics.lock().unwrap();
-        stats.misses += 1;
+    }
+}
+
+/// 把 `total` 精确分给 `count` 个分片时第 `index` 个分片的份额，余数由前面的分片各多分一个
+fn split_share(total: usize, count: usize, index: usize) -> usize {
+    total / count + usize::from(index < total % count)
+}
+
+/// 按 `step` 放大 TTL 并限制在 `[ttl, max_ttl]` 内；`step` 为 NaN、无穷或乘积溢出时不会 panic
+fn scale_ttl(ttl: Duration, step: f64, max_ttl: Duration) -> Duration {
+    let scaled = ttl.as_secs_f64() * step.max(1.0);
+    Duration::try_from_secs_f64(scaled).unwrap_or(if scaled.is_nan() { ttl } else { Duration::MAX }).max(ttl).min(max_ttl)
+}
+
+/// 全部分片的条目数和字节数，不持有分片的锁即可读取
+#[derive(Debug, Default)]
+struct CacheTotals {
+    entries: AtomicUsize,
+    bytes: AtomicUsize,
+    peak_bytes: AtomicUsize,
+}
+
+/// 负缓存策略，两种行为默认都关闭
+/// Negative caching policy; both behaviors are off by default
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NegativeCachePolicy {
+    /// 加载器确认键不存在后，在该时长内直接返回不存在
+    pub absent_ttl: Option<Duration>,
+    /// 加载器出错后，在该时长内直接返回同样的错误，通常比 `absent_ttl` 更短
+    pub error_ttl: Option<Duration>,
+}
+
+/// 负缓存条目和按键前缀配置的策略
+#[derive(Debug, Default)]
+struct NegativeCache {
+    default: NegativeCachePolicy,
+    /// 按前缀覆盖的策略，最长的匹配前缀生效
+    prefixes: Vec<(String, NegativeCachePolicy)>,
+    entries: HashMap<String, NegativeEntry>,
+}
+
+#[derive(Debug, Clone)]
+struct NegativeEntry {
+    expires_at: Instant,
+    /// 缓存的加载错误，`None` 表示键不存在
+    error: Option<String>,
+}
+
+impl NegativeCache {
+    fn policy(&self, key: &str) -> NegativeCachePolicy {
+        self.prefixes.iter()
+            .filter(|(prefix, _)| key.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map_or(self.default, |(_, policy)| *policy)
+    }
+
+    /// 查找未过期的负缓存条目，顺便移除已过期的
+    fn lookup(&mut self, key: &str, now: Instant) -> Option<NegativeEntry> {
+        let entry = self.entries.get(key)?;
+        if now < entry.expires_at {
+            return Some(entry.clone());
+        }
+        self.entries.remove(key);
         None
     }
 
-    /// 设置缓存值
-    pub fn set(&self, key: String, value: Vec<u8>, ttl: Option<Duration>, priority: Option<CachePriority>) -> Result<(), CacheError> {
-        let ttl = ttl.unwrap_or(Duration::from_secs(300)); // 默认5分钟
-        let priority = priority.unwrap_or(CachePriority::Medium);
-        
-        let entry = CacheEntry {
-            value: value.clone(),
-            created_at: Instant::now(),
-            last_accessed: Instant::now(),
-            access_count: 0,
-            ttl,
-            priority,
-            tags: Vec::new(),
-        };
+    /// 记录负缓存条目，条目数达到 `limit` 时先移除过期的，再移除最早过期的
+    fn record(&mut self, key: &str, entry: NegativeEntry, now: Instant, limit: usize) {
+        if !self.entries.contains_key(key) && self.entries.len() >= limit {
+            self.entries.retain(|_, entry| now < entry.expires_at);
+            if self.entries.len() >= limit
+                && let Some(oldest) = self.entries.iter()
+                    .min_by_key(|(_, entry)| entry.expires_at)
+                    .map(|(key, _)| key.clone())
+            {
+                self.entries.remove(&oldest);
+            }
+        }
+        self.entries.insert(key.to_string(), entry);
+    }
+}
 
-        let mut storage = self.storage.write().unwrap();
-        
-        // 检查是否需要驱逐
-        if storage.len() >= self.config.default_max_size {
-            self.evict_entries(&mut storage)?;
+/// 按键加载缓存值，用于预热和预测预取
+/// Loads cache values by key, used for warming and predictive prefetch
+#[async_trait::async_trait]
+pub trait CacheLoader: fmt::Debug + Send + Sync {
+    /// 加载一个键，数据源中不存在时返回 `None`
+    /// Load a key, returning `None` when the source does not have it
+    async fn load(&self, key: &str) -> Result<Option<Vec<u8>>, CacheError>;
+}
+
+/// 预测预取配置
+/// Predictive prefetch configuration
+#[derive(Debug, Clone)]
+pub struct PrefetchConfig {
+    /// 最多跟踪的键数，超出时替换访问次数最少的键
+    pub max_tracked_keys: usize,
+    /// 每个键保留的访问时刻数
+    pub history_len: usize,
+    /// 判断周期至少需要的访问次数
+    pub min_samples: usize,
+    /// 访问间隔相对平均间隔的最大偏差，超过则不视为周期访问
+    pub tolerance: f64,
+    /// 在预测的访问时刻之前多久加载
+    pub lead_time: Duration,
+    /// 是否识别顺序访问（如 `page:1`、`page:2` 之后预取 `page:3`）
+    pub sequential: bool,
+    /// 后台预取任务的检查间隔
+    pub interval: Duration,
+}
+
+impl Default for PrefetchConfig {
+    fn default() -> Self {
+        Self {
+            max_tracked_keys: 128,
+            history_len: 8,
+            min_samples: 3,
+            tolerance: 0.2,
+            lead_time: Duration::from_millis(500),
+            sequential: true,
+            interval: Duration::from_millis(100),
         }
+    }
+}
 
-        storage.insert(key, entry);
-        
-        // 更新统计信息
-        let mut stats = self.statistics.lock().unwrap();
-        stats.entry_count += 1;
-        stats.total_size += value.len();
-        
-        Ok(())
+/// 预测预取的加载器和访问模式
+#[derive(Debug)]
+struct Prefetcher {
+    loader: Arc<dyn CacheLoader>,
+    predictor: Mutex<AccessPredictor>,
+}
+
+/// 跟踪访问次数最多的若干个键的访问时刻，识别周期访问和顺序访问
+/// Tracks access times of the most accessed keys and detects periodic and sequential access
+#[derive(Debug)]
+struct AccessPredictor {
+    config: PrefetchConfig,
+    keys: HashMap<String, AccessHistory>,
+    /// 各前缀最近一次访问的序号及其位数
+    sequences: HashMap<String, (u64, usize)>,
+    /// 按顺序访问预测出的下一个键，等待下一轮预取
+    sequential_due: Vec<String>,
+}
+
+#[derive(Debug, Default)]
+struct AccessHistory {
+    accesses: std::collections::VecDeque<Instant>,
+    count: u64,
+    /// 已经为哪个预测时刻预取过，避免重复加载
+    prefetched_for: Option<Instant>,
+}
+
+impl AccessPredictor {
+    fn new(config: PrefetchConfig) -> Self {
+        Self { config, keys: HashMap::new(), sequences: HashMap::new(), sequential_due: Vec::new() }
     }
 
-    /// 驱逐条目
-    fn evict_entries(&self, storage: &mut HashMap<String, CacheEntry>) -> Result<(), CacheError> {
-        // 简化的 LRU 驱逐策略
-        let mut entries: Vec<_> = storage.iter().map(|(k, v)| (k.clone(), v.clone())).collect();
-        entries.sort_by_key(|(_, entry)| entry.last_accessed);
-        
-        // 移除最旧的条目
-        if let Some((key, _)) = entries.first() {
-            storage.remove(key);
-            
-            let mut stats = self.statistics.lock().unwrap();
-            stats.evictions += 1;
-            stats.entry_count -= 1;
+    fn record(&mut self, key: &str, now: Instant) {
+        let limit = self.config.max_tracked_keys.max(1);
+        if !self.keys.contains_key(key) && self.keys.len() >= limit {
+            // 替换访问最少的键；一次性访问的键互相替换，不会挤掉热点键
+            let coldest = self.keys.iter()
+                .min_by_key(|(_, history)| history.count)
+                .map(|(key, _)| key.clone())
+                .expect("跟踪的键不为空");
+            self.keys.remove(&coldest);
+        }
+        let history = self.keys.entry(key.to_string()).or_default();
+        history.count += 1;
+        history.accesses.push_back(now);
+        while history.accesses.len() > self.config.history_len.max(2) {
+            history.accesses.pop_front();
+        }
+
+        if self.config.sequential {
+            self.record_sequence(key, limit);
         }
-        
-        Ok(())
     }
 
-    /// 获取统计信息
-    pub fn get_statistics(&self) -> CacheStatistics {
-        self.statistics.lock().unwrap().clone()
+    fn record_sequence(&mut self, key: &str, limit: usize) {
+        let digits = key.len() - key.trim_end_matches(|c: char| c.is_ascii_digit()).len();
+        let (prefix, number) = key.split_at(key.len() - digits);
+        let Ok(number) = number.parse::<u64>() else {
+            return;
+        };
+        if self.sequences.get(prefix).is_some_and(|(last, _)| last.checked_add(1) == Some(number)) {
+            let next = format!("{}{:0width$}", prefix, number + 1, width = digits);
+            if !self.sequential_due.contains(&next) && self.sequential_due.len() < limit {
+                self.sequential_due.push(next);
+            }
+        }
+        if self.sequences.contains_key(prefix) || self.sequences.len() < limit {
+            self.sequences.insert(prefix.to_string(), (number, digits));
+        }
     }
 
-    /// 清理过期条目
-    pub fn cleanup_expired(&self) -> Result<usize, CacheError> {
-        let mut storage = self.storage.write().unwrap();
-        let mut removed_count = 0;
-        
-        let _now = Instant::now();
-        let expired_keys: Vec<String> = storage
-            .iter()
-            .filter(|(_, entry)| entry.created_at.elapsed() >= entry.ttl)
-            .map(|(key, _)| key.clone())
+    /// 下一次访问的预测时刻：访问间隔都接近平均间隔时为最后一次访问加平均间隔
+    fn next_access(&self, history: &AccessHistory) -> Option<Instant> {
+        if history.accesses.len() < self.config.min_samples.max(2) {
+            return None;
+        }
+        let intervals: Vec<Duration> = history.accesses.iter()
+            .zip(history.accesses.iter().skip(1))
+            .map(|(earlier, later)| later.duration_since(*earlier))
             .collect();
-        
-        for key in expired_keys {
-            storage.remove(&key);
-            removed_count += 1;
+        let mean = intervals.iter().sum::<Duration>() / intervals.len() as u32;
+        if mean.is_zero() {
+            return None;
         }
-        
-        let mut stats = self.statistics.lock().unwrap();
-        stats.evictions += removed_count as u64;
-        stats.entry_count -= removed_count;
-        
-        Ok(removed_count)
+        let periodic = intervals.iter()
+            .all(|interval| interval.abs_diff(mean).as_secs_f64() <= mean.as_secs_f64() * self.config.tolerance);
+        periodic.then(|| *history.accesses.back().expect("访问记录不为空") + mean)
     }
-}
 
-/// 性能优化器
-/// Performance Optimizer
-pub struct PerformanceOptimizer {
-    /// 优化策略
-    pub strategies: Vec<Box<dyn OptimizationStrategy>>,
-    /// 性能指标
-    pub metrics: Arc<Mutex<HashMap<String, f64>>>,
-    /// 配置
-    pub config: OptimizationConfig,
+    /// 取出现在应该预取的键：顺序访问预测的键，以及预测访问时刻在提前量之内的周期访问键
+    fn due(&mut self, now: Instant) -> Vec<String> {
+        let mut due = std::mem::take(&mut self.sequential_due);
+        let lead_time = self.config.lead_time;
+        let predictions: Vec<(String, Instant)> = self.keys.iter()
+            .filter_map(|(key, history)| {
+                let predicted = self.next_access(history)?;
+                let within_lead = now <= predicted && predicted.duration_since(now) <= lead_time;
+                (within_lead && history.prefetched_for != Some(predicted)).then(|| (key.clone(), predicted))
+            })
+            .collect();
+        for (key, predicted) in predictions {
+            if let Some(history) = self.keys.get_mut(&key) {
+                history.prefetched_for = Some(predicted);
+            }
+            if !due.contains(&key) {
+                due.push(key);
+            }
+        }
+        due
+    }
 }
 
-/// 优化策略接口
-/// Optimization Strategy Interface
-pub trait OptimizationStrategy: Send + Sync {
-    /// 应用优化
-    fn optimize(&self, context: &OptimizationContext) -> Result<OptimizationResult, OptimizationError>;
-    /// 获取策略名称
-    fn get_name(&self) -> String;
-    /// 获取优先级
-    fn get_priority(&self) -> OptimizationPriority;
+/// 缓存存储后端
+/// Cache storage backend
+///
+/// 本地缓存作为前置层负责驱逐、过期和压缩，后端只保存原始值。
+/// The local cache stays in front and handles eviction, expiry and compression; backends store raw values.
+#[async_trait::async_trait]
+pub trait CacheBackend: fmt::Debug + Send + Sync {
+    /// 读取一个键
+    /// Read a key
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, CacheError>;
+    /// 写入一个键，`ttl` 为 `None` 时不过期
+    /// Write a key; a `None` ttl never expires
+    async fn put(&self, key: &str, value: &[u8], ttl: Option<Duration>) -> Result<(), CacheError>;
+    /// 删除一个键，返回键是否存在
+    /// Delete a key, returning whether it existed
+    async fn delete(&self, key: &str) -> Result<bool, CacheError>;
+    /// 列出以 `prefix` 开头的全部键
+    /// List every key starting with `prefix`
+    async fn scan_prefix(&self, prefix: &str) -> Result<Vec<String>, CacheError>;
 }
 
-/// 优化上下文
-/// Optimization Context
-#[derive(Debug, Clone)]
-pub struct OptimizationContext {
-    /// 当前性能指标
-    pub current_metrics: HashMap<String, f64>,
-    /// 目标性能指标
-    pub target_metrics: HashMap<String, f64>,
-    /// 资源限制
-    pub resource_limits: ResourceLimits,
-    /// 工作负载特征
-    pub workload_characteristics: WorkloadCharacteristics,
+/// 写入后端的方式
+/// How writes reach the backend
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum WriteMode {
+    /// 直写：本地写入后立即等待后端写入完成
+    #[default]
+    WriteThrough,
+    /// 回写：只写本地，后端写入排队到 [`IntelligentCacheManager::flush`]
+    WriteBack,
 }
 
-/// 优化结果
-/// Optimization Result
+/// 回写队列中的一次操作
 #[derive(Debug, Clone)]
-pub struct OptimizationResult {
-    /// 策略名称
-    pub strategy_name: String,
-    /// 优化建议
-    pub recommendations: Vec<OptimizationRecommendation>,
-    /// 预期改进
-    pub expected_improvement: f64,
-    /// 实施难度
-    pub implementation_difficulty: ImplementationDifficulty,
+enum PendingWrite {
+    Put { key: String, value: Vec<u8>, ttl: Option<Duration> },
+    Delete { key: String },
 }
 
-/// 优化建议
-/// Optimization Recommendation
-#[derive(Debug, Clone)]
-pub struct OptimizationRecommendation {
-    /// 建议类型
-    pub recommendation_type: RecommendationType,
-    /// 描述
-    pub description: String,
-    /// 预期收益
-    pub expected_benefit: f64,
-    /// 实施成本
-    pub implementation_cost: ImplementationCost,
-}
-
-/// 建议类型
-/// Recommendation Type
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum RecommendationType {
-    /// 内存优化
-    MemoryOptimization,
-    /// CPU 优化
-    CPUOptimization,
-    /// 网络优化
-    NetworkOptimization,
-    /// 缓存优化
-    CacheOptimization,
-    /// 算法优化
-    AlgorithmOptimization,
-}
+impl PendingWrite {
+    fn key(&self) -> &str {
+        match self {
+            PendingWrite::Put { key, .. } | PendingWrite::Delete { key } => key,
+        }
+    }
 
-/// 实施成本
-/// Implementation Cost
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
-pub enum ImplementationCost {
-    /// 低成本
-    Low = 1,
-    /// 中等成本
-    Medium = 2,
-    /// 高成本
-    High = 3,
-    /// 极高成本
-    VeryHigh = 4,
+    async fn apply(&self, backend: &dyn CacheBackend) -> Result<(), CacheError> {
+        match self {
+            PendingWrite::Put { key, value, ttl } => backend.put(key, value, *ttl).await,
+            PendingWrite::Delete { key } => backend.delete(key).await.map(|_| ()),
+        }
+    }
 }
 
-/// 实施难度
-/// Implementation Difficulty
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
-pub enum ImplementationDifficulty {
-    /// 简单
-    Easy = 1,
-    /// 中等
-    Medium = 2,
-    /// 困难
-    Hard = 3,
-    /// 非常困难
-    VeryHard = 4,
+/// 进程内存储后端，也是未配置后端时的默认实现
+/// In-process storage backend and the default when no backend is configured
+#[derive(Debug, Default)]
+pub struct MemoryBackend {
+    entries: RwLock<HashMap<String, StoredValue>>,
 }
 
-/// 优化优先级
-/// Optimization Priority
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
-pub enum OptimizationPriority {
-    /// 低优先级
-    Low = 1,
-    /// 中等优先级
-    Medium = 2,
-    /// 高优先级
-    High = 3,
-    /// 紧急优先级
-    Critical = 4,
-}
+/// 值和可选的过期时刻
+type StoredValue = (Vec<u8>, Option<Instant>);
 
-/// 资源限制
-/// Resource Limits
-#[derive(Debug, Clone)]
-pub struct ResourceLimits {
-    /// 最大内存
-    pub max_memory: usize,
-    /// 最大 CPU
-    pub max_cpu: f64,
-    /// 最大网络带宽
-    pub max_network_bandwidth: usize,
-    /// 最大磁盘空间
-    pub max_disk_space: usize,
+impl MemoryBackend {
+    /// 创建空的进程内后端
+    /// Create an empty in-process backend
+    pub fn new() -> Self {
+        Self::default()
+    }
 }
 
-/// 工作负载特征
-/// Workload Characteristics
-#[derive(Debug, Clone)]
-pub struct WorkloadCharacteristics {
-    /// 请求模式
-    pub request_pattern: RequestPattern,
-    /// 数据访问模式
-    pub data_access_pattern: DataAccessPattern,
-    /// 计算复杂度
-    pub computational_complexity: ComputationalComplexity,
-    /// 并发级别
-    pub concurrency_level: ConcurrencyLevel,
-}
+#[async_trait::async_trait]
+impl CacheBackend for MemoryBackend {
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, CacheError> {
+        let entries = self.entries.read().unwrap();
+        Ok(entries.get(key)
+            .filter(|(_, expires_at)| expires_at.is_none_or(|expires_at| Instant::now() < expires_at))
+            .map(|(value, _)| value.clone()))
+    }
 
-/// 请求模式
-/// Request Pattern
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum RequestPattern {
-    /// 均匀分布
-    Uniform,
-    /// 突发性
-    Bursty,
-    /// 周期性
-    Periodic,
-    /// 随机
-    Random,
-}
+    async fn put(&self, key: &str, value: &[u8], ttl: Option<Duration>) -> Result<(), CacheError> {
+        let expires_at = ttl.map(|ttl| Instant::now() + ttl);
+        self.entries.write().unwrap().insert(key.to_string(), (value.to_vec(), expires_at));
+        Ok(())
+    }
 
-/// 数据访问模式
-/// Data Access Pattern
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum DataAccessPattern {
-    /// 顺序访问
-    Sequential,
-    /// 随机访问
-    Random,
-    /// 局部性访问
-    Locality,
-    /// 热点访问
-    Hotspot,
-}
+    async fn delete(&self, key: &str) -> Result<bool, CacheError> {
+        Ok(self.entries.write().unwrap().remove(key).is_some())
+    }
 
-/// 计算复杂度
-/// Computational Complexity
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum ComputationalComplexity {
-    /// 低复杂度
-    Low,
-    /// 中等复杂度
-    Medium,
-    /// 高复杂度
-    High,
-    /// 极高复杂度
-    VeryHigh,
+    async fn scan_prefix(&self, prefix: &str) -> Result<Vec<String>, CacheError> {
+        let now = Instant::now();
+        let entries = self.entries.read().unwrap();
+        let mut keys: Vec<String> = entries.iter()
+            .filter(|(key, (_, expires_at))| key.starts_with(prefix) && expires_at.is_none_or(|expires_at| now < expires_at))
+            .map(|(key, _)| key.clone())
+            .collect();
+        keys.sort();
+        Ok(keys)
+    }
 }
 
-/// 并发级别
-/// Concurrency Level
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum ConcurrencyLevel {
-    /// 低并发
-    Low,
-    /// 中等并发
-    Medium,
-    /// 高并发
-    High,
-    /// 极高并发
-    VeryHigh,
+/// 基于 RESP 协议的后端，可连接 Redis 及兼容的服务
+/// Backend speaking RESP, for Redis and compatible servers
+///
+/// 使用单个连接串行发送命令；连接出错后丢弃，下一条命令重新连接。
+/// Commands are sent one at a time over a single connection, which is dropped on error and re-established by
+/// the next command.
+#[derive(Debug)]
+pub struct RespBackend {
+    address: String,
+    timeout: Duration,
+    connection: tokio::sync::Mutex<Option<tokio::io::BufStream<tokio::net::TcpStream>>>,
 }
 
-/// 优化配置
-/// Optimization Configuration
-#[derive(Debug, Clone)]
-pub struct OptimizationConfig {
-    /// 优化间隔
-    pub optimization_interval: Duration,
-    /// 是否启用自动优化
-    pub auto_optimization_enabled: bool,
-    /// 优化阈值
-    pub optimization_threshold: f64,
-    /// 最大优化建议数
-    pub max_recommendations: usize,
+/// RESP 回复
+#[derive(Debug, Clone, PartialEq)]
+enum RespValue {
+    Simple(String),
+    Error(String),
+    Integer(i64),
+    Bulk(Option<Vec<u8>>),
+    Array(Option<Vec<RespValue>>),
 }
 
-impl PerformanceOptimizer {
-    /// 创建新的性能优化器
-    pub fn new(config: OptimizationConfig) -> Self {
+impl RespBackend {
+    /// 连接到 `address`（`host:port`），连接在第一条命令时建立
+    /// Target `address` (`host:port`); the connection is made by the first command
+    pub fn new(address: impl Into<String>) -> Self {
         Self {
-            strategies: Vec::new(),
-            metrics: Arc::new(Mutex::new(HashMap::new())),
-            config,
+            address: address.into(),
+            timeout: Duration::from_secs(5),
+            connection: tokio::sync::Mutex::new(None),
         }
     }
 
-    /// 添加优化策略
-    pub fn add_strategy(&mut self, strategy: Box<dyn OptimizationStrategy>) {
-        self.strategies.push(strategy);
+    /// 设置每条命令（含建立连接）的超时
+    /// Set the timeout of each command, connecting included
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        Self { timeout, ..self }
     }
 
-    /// 执行优化分析
-    pub fn analyze(&self, context: &OptimizationContext) -> Result<Vec<OptimizationResult>, OptimizationError> {
-        let mut results = Vec::new();
-        
-        for strategy in &self.strategies {
-            match strategy.optimize(context) {
-                Ok(result) => results.push(result),
-                Err(e) => {
-                    eprintln!("优化策略 {} 执行失败: {:?}", strategy.get_name(), e);
-                }
+    async fn command(&self, args: &[&[u8]]) -> Result<RespValue, CacheError> {
+        let mut connection = self.connection.lock().await;
+        let reply = tokio::time::timeout(self.timeout, async {
+            if connection.is_none() {
+                let stream = tokio::net::TcpStream::connect(&self.address).await?;
+                *connection = Some(tokio::io::BufStream::new(stream));
+            }
+            let stream = connection.as_mut().expect("连接已建立");
+            write_resp_command(stream, args).await?;
+            read_resp(stream).await
+        })
+        .await;
+        match reply {
+            Ok(Ok(RespValue::Error(message))) => Err(CacheError::BackendError(message)),
+            Ok(Ok(value)) => Ok(value),
+            Ok(Err(error)) => {
+                *connection = None;
+                Err(CacheError::BackendError(error.to_string()))
+            }
+            Err(_) => {
+                *connection = None;
+                Err(CacheError::BackendError(format!("命令超过 {:?} 未返回", self.timeout)))
             }
         }
-        
-        // 按优先级排序
-        results.sort_by_key(|r| r.implementation_difficulty);
-        
-        // 限制结果数量
-        results.truncate(self.config.max_recommendations);
-        
-        Ok(results)
+    }
+}
+
+#[async_trait::async_trait]
+impl CacheBackend for RespBackend {
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, CacheError> {
+        match self.command(&[b"GET", key.as_bytes()]).await? {
+            RespValue::Bulk(value) => Ok(value),
+            other => Err(unexpected_reply("GET", &other)),
+        }
     }
 
-    /// 更新性能指标
-    pub fn update_metrics(&self, metrics: HashMap<String, f64>) {
-        let mut current_metrics = self.metrics.lock().unwrap();
-        for (key, value) in metrics {
-            current_metrics.insert(key, value);
+    async fn put(&self, key: &str, value: &[u8], ttl: Option<Duration>) -> Result<(), CacheError> {
+        let reply = match ttl {
+            Some(ttl) => {
+                let millis = ttl.as_millis().max(1).to_string();
+                self.command(&[b"SET", key.as_bytes(), value, b"PX", millis.as_bytes()]).await?
+            }
+            None => self.command(&[b"SET", key.as_bytes(), value]).await?,
+        };
+        match reply {
+            RespValue::Simple(_) => Ok(()),
+            other => Err(unexpected_reply("SET", &other)),
         }
     }
-}
 
-/// 内存优化策略
-/// Memory Optimization Strategy
-#[derive(Debug)]
-pub struct MemoryOptimizationStrategy;
+    async fn delete(&self, key: &str) -> Result<bool, CacheError> {
+        match self.command(&[b"DEL", key.as_bytes()]).await? {
+            RespValue::Integer(removed) => Ok(removed > 0),
+            other => Err(unexpected_reply("DEL", &other)),
+        }
+    }
 
-impl OptimizationStrategy for MemoryOptimizationStrategy {
-    fn optimize(&self, _context: &OptimizationContext) -> Result<OptimizationResult, OptimizationError> {
-        let recommendations = vec![
-            OptimizationRecommendation {
-                recommendation_type: RecommendationType::MemoryOptimization,
-                description: "启用内存池管理".to_string(),
-                expected_benefit: 0.15,
-                implementation_cost: ImplementationCost::Medium,
-            },
-            OptimizationRecommendation {
-                recommendation_type: RecommendationType::MemoryOptimization,
-                description: "优化数据结构布局".to_string(),
-                expected_benefit: 0.10,
-                implementation_cost: ImplementationCost::High,
-            },
-        ];
+    async fn scan_prefix(&self, prefix: &str) -> Result<Vec<String>, CacheError> {
+        let mut pattern = String::with_capacity(prefix.len() + 1);
+        for c in prefix.chars() {
+            if matches!(c, '*' | '?' | '[' | ']' | '\\') {
+                pattern.push('\\');
+            }
+            pattern.push(c);
+        }
+        pattern.push('*');
 
-        Ok(OptimizationResult {
-            strategy_name: "Memory Optimization".to_string(),
-            recommendations,
-            expected_improvement: 0.25,
-            implementation_difficulty: ImplementationDifficulty::Medium,
-        })
+        // SCAN 可能重复返回同一个键，游标回到 0 时结束
+        let mut keys = BTreeSet::new();
+        let mut cursor = "0".to_string();
+        loop {
+            let reply = self.command(&[b"SCAN", cursor.as_bytes(), b"MATCH", pattern.as_bytes(), b"COUNT", b"100"]).await?;
+            let RespValue::Array(Some(parts)) = &reply else {
+                return Err(unexpected_reply("SCAN", &reply));
+            };
+            let [RespValue::Bulk(Some(next)), RespValue::Array(Some(batch))] = parts.as_slice() else {
+                return Err(unexpected_reply("SCAN", &reply));
+            };
+            for key in batch {
+                if let RespValue::Bulk(Some(key)) = key {
+                    keys.insert(String::from_utf8_lossy(key).into_owned());
+                }
+            }
+            cursor = String::from_utf8_lossy(next).into_owned();
+            if cursor == "0" {
+                return Ok(keys.into_iter().collect());
+            }
+        }
     }
+}
 
-    fn get_name(&self) -> String {
-        "Memory Optimization".to_string()
-    }
+fn unexpected_reply(command: &str, reply: &RespValue) -> CacheError {
+    CacheError::BackendError(format!("{} 返回了意外的回复: {:?}", command, reply))
+}
 
-    fn get_priority(&self) -> OptimizationPriority {
-        OptimizationPriority::High
+/// 以 RESP 数组形式发送一条命令
+async fn write_resp_command<W>(writer: &mut W, args: &[&[u8]]) -> std::io::Result<()>
+where
+    W: tokio::io::AsyncWrite + Unpin,
+{
+    use tokio::io::AsyncWriteExt;
+    let mut buffer = format!("*{}\r\n", args.len()).into_bytes();
+    for arg in args {
+        buffer.extend_from_slice(format!("${}\r\n", arg.len()).as_bytes());
+        buffer.extend_from_slice(arg);
+        buffer.extend_from_slice(b"\r\n");
     }
+    writer.write_all(&buffer).await?;
+    writer.flush().await
 }
 
-/// 缓存优化策略
-/// Cache Optimization Strategy
-#[derive(Debug)]
-pub struct CacheOptimizationStrategy;
+/// 批量字符串的最大长度，与 Redis 的 `proto-max-bulk-len` 默认值相同
+const RESP_MAX_BULK_LEN: usize = 512 * 1024 * 1024;
 
-impl OptimizationStrategy for CacheOptimizationStrategy {
-    fn optimize(&self, _context: &OptimizationContext) -> Result<OptimizationResult, OptimizationError> {
-        let recommendations = vec![
-            OptimizationRecommendation {
-                recommendation_type: RecommendationType::CacheOptimization,
-                description: "调整缓存大小和策略".to_string(),
-                expected_benefit: 0.20,
-                implementation_cost: ImplementationCost::Low,
-            },
-            OptimizationRecommendation {
-                recommendation_type: RecommendationType::CacheOptimization,
-                description: "实现智能预取".to_string(),
-                expected_benefit: 0.12,
-                implementation_cost: ImplementationCost::Medium,
-            },
-        ];
+/// 读取批量字符串时预先分配的最大容量，更长的值随读取增长
+const RESP_BULK_PREALLOCATION: usize = 64 * 1024;
 
-        Ok(OptimizationResult {
-            strategy_name: "Cache Optimization".to_string(),
-            recommendations,
-            expected_improvement: 0.32,
-            implementation_difficulty: ImplementationDifficulty::Easy,
-        })
+/// 读取一个 RESP 值
+///
+/// 声明的长度超过 [`RESP_MAX_BULK_LEN`] 的批量字符串在分配内存前即被拒绝。
+fn read_resp<'a, R>(reader: &'a mut R) -> std::pin::Pin<Box<dyn std::future::Future<Output = std::io::Result<RespValue>> + Send + 'a>>
+where
+    R: tokio::io::AsyncBufRead + Unpin + Send,
+{
+    Box::pin(async move {
+        use tokio::io::{AsyncBufReadExt, AsyncReadExt};
+        let invalid = |message: String| std::io::Error::new(std::io::ErrorKind::InvalidData, message);
+        let mut line = String::new();
+        if reader.read_line(&mut line).await? == 0 {
+            return Err(std::io::ErrorKind::UnexpectedEof.into());
+        }
+        let line = line.trim_end_matches(['\r', '\n']);
+        let Some(kind) = line.chars().next() else {
+            return Err(invalid("空的 RESP 行".to_string()));
+        };
+        let body = &line[1..];
+        let length = || body.parse::<i64>().map_err(|_| invalid(format!("无效的 RESP 长度: {}", body)));
+        match kind {
+            '+' => Ok(RespValue::Simple(body.to_string())),
+            '-' => Ok(RespValue::Error(body.to_string())),
+            ':' => Ok(RespValue::Integer(length()?)),
+            '$' => {
+                let Ok(len) = usize::try_from(length()?) else {
+                    return Ok(RespValue::Bulk(None));
+                };
+                if len > RESP_MAX_BULK_LEN {
+                    return Err(invalid(format!("批量字符串长度 {} 超过上限 {}", len, RESP_MAX_BULK_LEN)));
+                }
+                let mut value = Vec::with_capacity(len.min(RESP_BULK_PREALLOCATION) + 2);
+                if (&mut *reader).take(len as u64 + 2).read_to_end(&mut value).await? < len + 2 {
+                    return Err(std::io::ErrorKind::UnexpectedEof.into());
+                }
+                value.truncate(len);
+                Ok(RespValue::Bulk(Some(value)))
+            }
+            '*' => {
+                let Ok(len) = usize::try_from(length()?) else {
+                    return Ok(RespValue::Array(None));
+                };
+                let mut items = Vec::with_capacity(len.min(1024));
+                for _ in 0..len {
+                    items.push(read_resp(reader).await?);
+                }
+                Ok(RespValue::Array(Some(items)))
+            }
+            other => Err(invalid(format!("未知的 RESP 类型标记: {}", other))),
+        }
+    })
+}
+
+/// 条目被缓存丢弃的原因
+/// Why the cache discarded an entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EvictionReason {
+    /// 超出容量或字节预算
+    Capacity,
+    /// TTL 到期，在读取时或由清理任务移除
+    Ttl,
+    /// 调用 [`IntelligentCacheManager::remove`] 或失效方法
+    Manual,
+    /// 切换到容量更小的策略
+    PolicySwitch,
+    /// 存储的压缩值无法解压，读取时按未命中处理并移除
+    Corrupted,
+}
+
+impl EvictionReason {
+    /// 指标标签中使用的名称
+    /// Name used in metric labels
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Capacity => "capacity",
+            Self::Ttl => "ttl",
+            Self::Manual => "manual",
+            Self::PolicySwitch => "policy_switch",
+            Self::Corrupted => "corrupted",
+        }
     }
+}
 
-    fn get_name(&self) -> String {
-        "Cache Optimization".to_string()
+/// 缓存事件钩子，各方法默认什么也不做
+/// Cache event hook; every method does nothing by default
+///
+/// 每个事件都带有键和条目，条目的 `weight` 是占用的字节数。
+/// Every event carries the key and the entry, whose `weight` is the bytes it occupies.
+pub trait CacheHook: fmt::Debug + Send + Sync {
+    /// 写入一个条目，包括覆盖已有的键
+    /// An entry was written, including overwrites of an existing key
+    fn on_insert(&self, _key: &str, _entry: &CacheEntry) {}
+
+    /// 读取命中一个条目
+    /// A read hit an entry
+    fn on_hit(&self, _key: &str, _entry: &CacheEntry) {}
+
+    /// 缓存丢弃了一个条目，随后还会触发 [`on_remove`](Self::on_remove)
+    /// The cache discarded an entry; [`on_remove`](Self::on_remove) follows
+    fn on_evict(&self, _key: &str, _entry: &CacheEntry, _reason: EvictionReason) {}
+
+    /// 一个条目离开缓存，包括被覆盖的旧值
+    /// An entry left the cache, including values replaced by an overwrite
+    fn on_remove(&self, _key: &str, _entry: &CacheEntry) {}
+}
+
+/// 已注册的钩子及其连续失败次数
+#[derive(Debug)]
+struct RegisteredHook {
+    hook: Arc<dyn CacheHook>,
+    /// 连续 panic 的次数，成功处理一个事件后清零
+    failures: AtomicU32,
+    disabled: AtomicBool,
+}
+
+/// 钩子注册表
+#[derive(Debug)]
+struct CacheHooks {
+    hooks: RwLock<Vec<RegisteredHook>>,
+    /// 是否有启用的钩子；没有时事件在进入注册表前就被跳过
+    active: AtomicBool,
+    /// 钩子连续 panic 多少次后被禁用
+    failure_limit: u32,
+    /// 钩子 panic 的总次数
+    failures: AtomicU64,
+}
+
+impl Default for CacheHooks {
+    fn default() -> Self {
+        Self {
+            hooks: RwLock::new(Vec::new()),
+            active: AtomicBool::new(false),
+            failure_limit: 3,
+            failures: AtomicU64::new(0),
+        }
+    }
+}
+
+impl CacheHooks {
+    fn is_active(&self) -> bool {
+        self.active.load(Ordering::Relaxed)
     }
 
-    fn get_priority(&self) -> OptimizationPriority {
-        OptimizationPriority::Medium
+    /// 依次调用启用的钩子，捕获 panic
+    fn dispatch(&self, event: &str, call: impl Fn(&dyn CacheHook)) {
+        let hooks = self.hooks.read().unwrap();
+        let mut disabled_any = false;
+        for registered in hooks.iter().filter(|hook| !hook.disabled.load(Ordering::Relaxed)) {
+            if panic::catch_unwind(AssertUnwindSafe(|| call(registered.hook.as_ref()))).is_ok() {
+                if registered.failures.load(Ordering::Relaxed) != 0 {
+                    registered.failures.store(0, Ordering::Relaxed);
+                }
+                continue;
+            }
+            self.failures.fetch_add(1, Ordering::Relaxed);
+            let failures = registered.failures.fetch_add(1, Ordering::Relaxed) + 1;
+            log::warn!("缓存钩子 {:?} 处理 {} 事件时 panic（连续第 {} 次）", registered.hook, event, failures);
+            if failures >= self.failure_limit {
+                log::warn!("缓存钩子 {:?} 已被禁用", registered.hook);
+                registered.disabled.store(true, Ordering::Relaxed);
+                disabled_any = true;
+            }
+        }
+        if disabled_any {
+            let active = hooks.iter().any(|hook| !hook.disabled.load(Ordering::Relaxed));
+            self.active.store(active, Ordering::Relaxed);
+        }
     }
 }
 
-/// 错误类型定义
-/// Error Type Definitions
+/// 缓存写入次数的计数器名称
+pub const CACHE_INSERTS: &str = "cache_inserts_total";
+/// 缓存命中次数的计数器名称
+pub const CACHE_HITS: &str = "cache_hits_total";
+/// 缓存驱逐次数的计数器名称，按原因分标签
+pub const CACHE_EVICTIONS: &str = "cache_evictions_total";
+/// 条目离开缓存次数的计数器名称
+pub const CACHE_REMOVALS: &str = "cache_removals_total";
+/// 写入条目大小的直方图名称
+pub const CACHE_ENTRY_BYTES: &str = "cache_entry_size_bytes";
+/// 被驱逐条目访问次数的直方图名称
+pub const CACHE_EVICTED_ACCESSES: &str = "cache_evicted_entry_accesses";
 
-#[derive(Debug, Error)]
-pub enum CacheError {
-    /// 存储错误
-    #[error("缓存存储错误: {0}")]
-    StorageError(String),
-    /// 配置错误
-    #[error("缓存配置错误: {0}")]
-    ConfigurationError(String),
-    /// 序列化错误
-    #[error("缓存序列化错误: {0}")]
-    SerializationError(String),
+/// 把缓存事件发布到 [`MetricsCollector`] 的钩子
+/// Hook publishing cache events to a [`MetricsCollector`]
+///
+/// 计数器和直方图带有 `cache` 标签，驱逐计数器另带 `reason` 标签。
+/// Counters and histograms carry a `cache` label, and evictions also a `reason` label.
+#[derive(Debug)]
+pub struct MetricsCacheHook {
+    collector: Arc<MetricsCollector>,
+    cache: String,
 }
 
-#[derive(Debug, Error)]
-pub enum OptimizationError {
-    /// 策略错误
-    #[error("优化策略错误: {0}")]
-    StrategyError(String),
-    /// 分析错误
-    #[error("性能分析错误: {0}")]
-    AnalysisError(String),
-    /// 配置错误
-    #[error("优化配置错误: {0}")]
-    ConfigurationError(String),
+impl MetricsCacheHook {
+    /// 创建钩子并在收集器中注册缓存的计数器和直方图
+    /// Create the hook and register the cache counters and histograms with the collector
+    pub fn new(collector: Arc<MetricsCollector>, cache: impl Into<String>) -> Self {
+        let metadata = |description: &str, unit: Option<&str>| MetricMetadata {
+            description: description.to_string(),
+            unit: unit.map(str::to_string),
+            help: None,
+        };
+        for (name, description) in [
+            (CACHE_INSERTS, "Entries written to the cache"),
+            (CACHE_HITS, "Cache reads that hit an entry"),
+            (CACHE_EVICTIONS, "Entries discarded by the cache by reason"),
+            (CACHE_REMOVALS, "Entries that left the cache, including overwritten values"),
+        ] {
+            let _ = collector.register_counter(name, metadata(description, None));
+        }
+        let _ = collector.register_histogram(
+            CACHE_ENTRY_BYTES,
+            BucketLayout::Exponential { start: 64.0, factor: 4.0, count: 10 },
+            metadata("Size of written cache entries", Some("bytes")),
+        );
+        let _ = collector.register_histogram(
+            CACHE_EVICTED_ACCESSES,
+            BucketLayout::Exponential { start: 1.0, factor: 2.0, count: 12 },
+            metadata("Accesses of evicted cache entries", None),
+        );
+        Self { collector, cache: cache.into() }
+    }
+
+    fn labels(&self) -> HashMap<String, String> {
+        HashMap::from([("cache".to_string(), self.cache.clone())])
+    }
+}
+
+impl CacheHook for MetricsCacheHook {
+    fn on_insert(&self, _key: &str, entry: &CacheEntry) {
+        let _ = self.collector.inc_counter(CACHE_INSERTS, 1, self.labels());
+        let _ = self.collector.observe(CACHE_ENTRY_BYTES, entry.weight as f64, self.labels());
+    }
+
+    fn on_hit(&self, _key: &str, _entry: &CacheEntry) {
+        let _ = self.collector.inc_counter(CACHE_HITS, 1, self.labels());
+    }
+
+    fn on_evict(&self, _key: &str, entry: &CacheEntry, reason: EvictionReason) {
+        let mut labels = self.labels();
+        labels.insert("reason".to_string(), reason.as_str().to_string());
+        let _ = self.collector.inc_counter(CACHE_EVICTIONS, 1, labels);
+        let _ = self.collector.observe(CACHE_EVICTED_ACCESSES, entry.access_count as f64, self.labels());
+    }
+
+    fn on_remove(&self, _key: &str, _entry: &CacheEntry) {
+        let _ = self.collector.inc_counter(CACHE_REMOVALS, 1, self.labels());
+    }
+}
+
+/// 缓存时钟
+/// Cache clock
+pub trait CacheClock: fmt::Debug + Send + Sync {
+    /// 当前时刻
+    /// Current instant
+    fn now(&self) -> Instant;
+}
+
+/// 系统时钟
+/// System clock
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemCacheClock;
+
+impl CacheClock for SystemCacheClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// 缓存条目
+/// Cache Entry
+#[derive(Debug, Clone)]
+pub struct CacheEntry {
+    /// 值
+    pub value: Vec<u8>,
+    /// 创建时间
+    pub created_at: Instant,
+    /// 最后访问时间
+    pub last_accessed: Instant,
+    /// 访问次数
+    pub access_count: u64,
+    /// TTL
+    pub ttl: Duration,
+    /// 过期时刻；启用读取续期时每次命中后顺延一个 TTL
+    pub expires_at: Instant,
+    /// 优先级
+    pub priority: CachePriority,
+    /// 标签
+    pub tags: Vec<String>,
+    /// 写入时由 [`Weigher`] 计算的占用字节数
+    pub weight: usize,
+    /// `value` 是否为带压缩头部的压缩数据
+    pub compressed: bool,
+    /// 由预测预取写入且尚未被读取
+    pub prefetched: bool,
+}
+
+/// 计算缓存条目占用的字节数，在写入时调用一次
+/// Computes the bytes a cache entry occupies; called once at insert
+pub trait Weigher: fmt::Debug + Send + Sync {
+    /// 条目的字节数
+    fn weigh(&self, key: &str, value: &[u8]) -> usize;
+}
+
+/// 默认的计重方式：序列化后值的长度
+/// Default weigher: the length of the serialized value
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ValueLengthWeigher;
+
+impl Weigher for ValueLengthWeigher {
+    fn weigh(&self, _key: &str, value: &[u8]) -> usize {
+        value.len()
+    }
+}
+
+/// 缓存策略
+/// Cache Policy
+#[derive(Debug, Clone)]
+pub struct CachePolicy {
+    /// 策略名称
+    pub name: String,
+    /// 最大大小
+    pub max_size: usize,
+    /// 全部条目的字节预算，`None` 表示只限制条目数
+    pub max_bytes: Option<usize>,
+    /// 默认 TTL
+    pub default_ttl: Duration,
+    /// 驱逐策略
+    pub eviction_policy: EvictionPolicy,
+    /// 压缩策略，需要 `CacheConfig::compression_enabled` 同时开启
+    pub compression_policy: CompressionPolicy,
+    /// 只压缩超过该字节数的值
+    pub compression_threshold: usize,
+    /// 压缩级别，`None` 使用算法的默认级别（Gzip 6，Zstd 3）
+    pub compression_level: Option<i32>,
+    /// 命中时把过期时刻顺延一个 TTL（滑动过期）
+    pub refresh_on_read: bool,
+}
+
+/// 驱逐策略
+/// Eviction Policy
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum EvictionPolicy {
+    /// 最近最少使用
+    LRU,
+    /// 最少使用频率；计数定期减半，过去的热点不会永久占用缓存
+    LFU,
+    /// 自适应替换（ARC），在最近使用和频繁使用之间自适应分配容量，能抵抗一次性扫描
+    ARC,
+    /// 先进先出
+    FIFO,
+    /// 基于时间
+    TTL,
+    /// 随机
+    Random,
+}
+
+/// 压缩策略
+/// Compression Policy
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CompressionPolicy {
+    /// 不压缩
+    None,
+    /// Gzip 压缩
+    Gzip,
+    /// Zstandard 压缩
+    Zstd,
+    /// LZ4 压缩，暂不支持：启用压缩时超过阈值的值按原样存储
+    LZ4,
+    /// 自适应压缩：分别尝试 Zstd 和 Gzip，保留较小的结果
+    Adaptive,
+}
+
+/// 缓存优先级
+/// Cache Priority
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CachePriority {
+    /// 低优先级
+    Low = 1,
+    /// 中等优先级
+    Medium = 2,
+    /// 高优先级
+    High = 3,
+    /// 关键优先级
+    Critical = 4,
+}
+
+/// 缓存统计信息
+/// Cache Statistics
+#[derive(Debug, Clone)]
+pub struct CacheStatistics {
+    /// 命中次数
+    pub hits: u64,
+    /// 未命中次数
+    pub misses: u64,
+    /// 驱逐次数，包含过期移除
+    pub evictions: u64,
+    /// 因过期被移除的条目数
+    pub expirations: u64,
+    /// 当前字节数，按 [`Weigher`] 计算
+    pub total_size: usize,
+    /// 字节数的历史最高值
+    pub peak_size: usize,
+    /// 条目数量
+    pub entry_count: usize,
+    /// 平均访问时间
+    pub avg_access_time: Duration,
+    /// 当前生效的驱逐策略
+    pub eviction_policy: EvictionPolicy,
+    /// 各驱逐策略生效期间的统计，切换策略后保留之前策略的统计
+    pub policy_statistics: HashMap<EvictionPolicy, PolicyStatistics>,
+    /// 以压缩形式写入的值的个数
+    pub compressed_writes: u64,
+    /// 压缩累计节省的字节数
+    pub bytes_saved: u64,
+    /// 压缩值的平均压缩比（压缩后大小 / 原始大小）
+    pub avg_compression_ratio: f64,
+    /// 本地未命中、由后端返回的次数
+    pub backend_hits: u64,
+    /// 后端操作失败的次数，失败时退回只使用本地缓存
+    pub backend_errors: u64,
+    /// 预测预取写入的条目数
+    pub prefetch_issued: u64,
+    /// 预取的条目在移除前被读取的次数
+    pub prefetch_hits: u64,
+    /// 预取的条目未被读取就被驱逐、过期、覆盖或删除的次数
+    pub prefetch_wasted: u64,
+    /// 命中负缓存（确认不存在或缓存的加载错误）的次数，不计入 `hits` 和 `misses`
+    pub negative_hits: u64,
+}
+
+impl CacheStatistics {
+    fn new(eviction_policy: EvictionPolicy) -> Self {
+        Self {
+            hits: 0,
+            misses: 0,
+            evictions: 0,
+            expirations: 0,
+            total_size: 0,
+            peak_size: 0,
+            entry_count: 0,
+            avg_access_time: Duration::ZERO,
+            eviction_policy,
+            policy_statistics: HashMap::new(),
+            compressed_writes: 0,
+            bytes_saved: 0,
+            avg_compression_ratio: 0.0,
+            backend_hits: 0,
+            backend_errors: 0,
+            prefetch_issued: 0,
+            prefetch_hits: 0,
+            prefetch_wasted: 0,
+            negative_hits: 0,
+        }
+    }
+
+    /// 合并一个分片的统计；平均值按命中次数和压缩写入次数加权
+    fn merge(&mut self, shard: &CacheStatistics) {
+        let hits = self.hits + shard.hits;
+        if hits > 0 {
+            let weighted = self.avg_access_time.as_secs_f64() * self.hits as f64
+                + shard.avg_access_time.as_secs_f64() * shard.hits as f64;
+            self.avg_access_time = Duration::from_secs_f64(weighted / hits as f64);
+        }
+        let compressed_writes = self.compressed_writes + shard.compressed_writes;
+        if compressed_writes > 0 {
+            self.avg_compression_ratio = (self.avg_compression_ratio * self.compressed_writes as f64
+                + shard.avg_compression_ratio * shard.compressed_writes as f64)
+                / compressed_writes as f64;
+        }
+        self.hits = hits;
+        self.compressed_writes = compressed_writes;
+        self.misses += shard.misses;
+        self.evictions += shard.evictions;
+        self.expirations += shard.expirations;
+        self.total_size += shard.total_size;
+        self.entry_count += shard.entry_count;
+        self.bytes_saved += shard.bytes_saved;
+        self.backend_hits += shard.backend_hits;
+        self.backend_errors += shard.backend_errors;
+        self.prefetch_issued += shard.prefetch_issued;
+        self.prefetch_hits += shard.prefetch_hits;
+        self.prefetch_wasted += shard.prefetch_wasted;
+        self.negative_hits += shard.negative_hits;
+    }
+}
+
+/// 单个驱逐策略的统计
+/// Statistics of a single eviction policy
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PolicyStatistics {
+    /// 因容量不足被策略驱逐的条目数
+    pub evictions: u64,
+    /// ARC 幽灵列表命中次数，即刚被驱逐的键再次写入
+    pub ghost_hits: u64,
+    /// LFU 计数减半的次数
+    pub frequency_agings: u64,
+}
+
+/// 当前策略和按该策略维护的驱逐索引
+/// The active policy and the eviction index maintained under it
+#[derive(Debug)]
+struct EvictionState {
+    policy: CachePolicy,
+    /// 按分片数划分后的容量
+    limits: ShardLimits,
+    tracker: Box<dyn EvictionTracker>,
+    /// 已停用策略的统计
+    retired: HashMap<EvictionPolicy, PolicyStatistics>,
+    /// 按过期时间排序的键，清理过期条目时只访问已过期的部分
+    expiry: BTreeSet<(Instant, String)>,
+}
+
+impl EvictionState {
+    /// 停止跟踪条目的过期时间
+    fn forget_expiry(&mut self, key: &str, entry: &CacheEntry) {
+        self.expiry.remove(&(entry.expires_at, key.to_string()));
+    }
+}
+
+/// 驱逐索引：跟踪驻留的键并选出被驱逐者
+/// Eviction index: tracks resident keys and picks victims
+///
+/// 写入新键时依次调用 `prepare`、若干次 `victim`（直到有空位）和 `insert`。
+/// Inserting a new key calls `prepare`, then `victim` until there is room, then `insert`.
+trait EvictionTracker: fmt::Debug + Send + Sync {
+    /// 新键写入前调用，ARC 在这里识别幽灵命中并调整目标
+    fn prepare(&mut self, _key: &str) {}
+    /// 选出并停止跟踪一个驻留的键
+    fn victim(&mut self, entries: &HashMap<String, CacheEntry>) -> Option<String>;
+    /// 开始跟踪新写入的键
+    fn insert(&mut self, key: &str);
+    /// 记录一次命中
+    fn access(&mut self, key: &str);
+    /// 停止跟踪被删除或过期的键
+    fn remove(&mut self, key: &str);
+    /// 切换策略时从已有条目重建索引，默认视为新写入
+    fn restore(&mut self, key: &str, _entry: &CacheEntry) {
+        self.insert(key);
+    }
+    /// 策略统计
+    fn statistics(&self) -> PolicyStatistics;
+}
+
+/// 缓存配置
+/// Cache Configuration
+#[derive(Debug, Clone)]
+pub struct CacheConfig {
+    /// 默认最大大小
+    pub default_max_size: usize,
+    /// 清理间隔，后台过期清理任务按此间隔运行
+    pub cleanup_interval: Duration,
+    /// 后台清理每次持锁最多移除的过期条目数
+    pub sweep_batch_size: usize,
+    /// 统计间隔
+    pub statistics_interval: Duration,
+    /// 是否启用压缩
+    pub compression_enabled: bool,
+    /// 是否启用预热
+    pub warmup_enabled: bool,
+    /// 存储分片数，向上取整为 2 的幂；1 表示所有键共用一把锁
+    pub shard_count: usize,
+}
+
+/// 默认的存储分片数
+const DEFAULT_SHARD_COUNT: usize = 16;
+
+impl Default for CacheConfig {
+    /// 新增字段时，使用 `..CacheConfig::default()` 的调用方不必修改
+    fn default() -> Self {
+        Self {
+            default_max_size: 1000,
+            cleanup_interval: Duration::from_secs(60),
+            sweep_batch_size: 100,
+            statistics_interval: Duration::from_secs(60),
+            compression_enabled: false,
+            warmup_enabled: false,
+            shard_count: DEFAULT_SHARD_COUNT,
+        }
+    }
+}
+
+impl IntelligentCacheManager {
+    /// 创建新的智能缓存管理器
+    ///
+    /// 默认使用 LRU 驱逐，容量为 `config.default_max_size`，默认 TTL 为 5 分钟。
+    /// Defaults to LRU eviction with `config.default_max_size` entries and a five minute TTL.
+    pub fn new(config: CacheConfig) -> Self {
+        let policy = CachePolicy {
+            name: "default".to_string(),
+            max_size: config.default_max_size,
+            max_bytes: None,
+            default_ttl: Duration::from_secs(300),
+            eviction_policy: EvictionPolicy::LRU,
+            compression_policy: CompressionPolicy::None,
+            compression_threshold: 1024,
+            compression_level: None,
+            refresh_on_read: false,
+        };
+        let shard_count = config.shard_count.max(1).next_power_of_two();
+        Self {
+            policies: HashMap::new(),
+            config,
+            shards: (0..shard_count).map(|index| CacheShard::new(&policy, shard_count, index)).collect(),
+            totals: CacheTotals::default(),
+            weigher: Arc::new(ValueLengthWeigher),
+            clock: Arc::new(SystemCacheClock),
+            sweeper_task: Mutex::new(None),
+            backend: None,
+            write_mode: WriteMode::default(),
+            pending_writes: Mutex::new(Vec::new()),
+            prefetch: None,
+            prefetch_task: Mutex::new(None),
+            negative: Mutex::new(NegativeCache::default()),
+            negative_in_use: AtomicBool::new(false),
+            in_flight: Mutex::new(HashMap::new()),
+            load_timeout: Duration::from_secs(30),
+            hooks: CacheHooks::default(),
+        }
+    }
+
+    /// 注册事件钩子
+    /// Register an event hook
+    pub fn with_hook(self, hook: Arc<dyn CacheHook>) -> Self {
+        self.add_hook(hook);
+        self
+    }
+
+    /// 设置钩子连续 panic 多少次后被禁用，默认 3 次
+    /// Set how many consecutive panics disable a hook; 3 by default
+    pub fn with_hook_failure_limit(mut self, limit: u32) -> Self {
+        self.hooks.failure_limit = limit.max(1);
+        self
+    }
+
+    /// 运行时注册事件钩子
+    /// Register an event hook at runtime
+    ///
+    /// 钩子在触发事件的操作中同步调用，此时持有该键所在分片的锁，因此不能再调用缓存本身。
+    /// 钩子 panic 时被捕获并计数，达到失败上限后该钩子被禁用，不影响缓存操作和其他钩子。
+    /// Hooks run synchronously inside the operation raising the event while the key's shard is locked, so they
+    /// must not call back into the cache. A panicking hook is caught and counted, and disabled once it reaches the
+    /// failure limit, without affecting the cache operation or other hooks.
+    pub fn add_hook(&self, hook: Arc<dyn CacheHook>) {
+        self.hooks.hooks.write().unwrap().push(RegisteredHook {
+            hook,
+            failures: AtomicU32::new(0),
+            disabled: AtomicBool::new(false),
+        });
+        self.hooks.active.store(true, Ordering::Relaxed);
+    }
+
+    /// 钩子 panic 的总次数
+    /// Total number of hook panics
+    pub fn hook_failures(&self) -> u64 {
+        self.hooks.failures.load(Ordering::Relaxed)
+    }
+
+    /// 因 panic 过多而被禁用的钩子数
+    /// Number of hooks disabled after too many panics
+    pub fn disabled_hooks(&self) -> usize {
+        self.hooks.hooks.read().unwrap().iter().filter(|hook| hook.disabled.load(Ordering::Relaxed)).count()
+    }
+
+    /// 设置单次加载的最长时间，默认 30 秒
+    /// Set the longest a single load may take; 30 seconds by default
+    pub fn with_load_timeout(self, load_timeout: Duration) -> Self {
+        Self { load_timeout, ..self }
+    }
+
+    /// 设置 [`get_or_load`](Self::get_or_load) 默认的负缓存策略
+    /// Set the default negative caching policy of [`get_or_load`](Self::get_or_load)
+    pub fn with_negative_caching(self, policy: NegativeCachePolicy) -> Self {
+        self.negative.lock().unwrap().default = policy;
+        self
+    }
+
+    /// 为以 `prefix` 开头的键设置负缓存策略，多个前缀匹配时最长的生效
+    /// Set the negative caching policy of keys starting with `prefix`; the longest matching prefix wins
+    pub fn set_negative_policy(&self, prefix: impl Into<String>, policy: NegativeCachePolicy) {
+        let prefix = prefix.into();
+        let mut negative = self.negative.lock().unwrap();
+        negative.prefixes.retain(|(existing, _)| *existing != prefix);
+        negative.prefixes.push((prefix, policy));
+    }
+
+    /// 启用预测预取：跟踪读取的键，在预测的访问时刻之前用 `loader` 加载
+    /// Enable predictive prefetch: track read keys and load them with `loader` just before their predicted access
+    ///
+    /// 预取由 [`prefetch_due`](Self::prefetch_due) 执行，或由 [`start_prefetcher`](Self::start_prefetcher) 在后台定期执行。
+    /// Prefetching runs in [`prefetch_due`](Self::prefetch_due), or periodically in the background via
+    /// [`start_prefetcher`](Self::start_prefetcher).
+    pub fn with_prefetch(self, config: PrefetchConfig, loader: Arc<dyn CacheLoader>) -> Self {
+        let prefetch = Prefetcher { loader, predictor: Mutex::new(AccessPredictor::new(config)) };
+        Self { prefetch: Some(prefetch), ..self }
+    }
+
+    /// 在本地缓存之后使用指定的存储后端，由异步方法 [`fetch`](Self::fetch)、[`store`](Self::store) 等访问
+    /// Put the given storage backend behind the local cache, used by the async methods such as
+    /// [`fetch`](Self::fetch) and [`store`](Self::store)
+    pub fn with_backend(self, backend: Box<dyn CacheBackend>) -> Self {
+        Self { backend: Some(Arc::from(backend)), ..self }
+    }
+
+    /// 设置写入后端的方式
+    /// Set how writes reach the backend
+    pub fn with_write_mode(self, write_mode: WriteMode) -> Self {
+        Self { write_mode, ..self }
+    }
+
+    /// 使用指定的时钟判断过期
+    /// Use the given clock for expiry
+    pub fn with_clock(self, clock: Arc<dyn CacheClock>) -> Self {
+        Self { clock, ..self }
+    }
+
+    /// 使用指定的计重方式，只影响之后写入的条目
+    /// Use the given weigher; only entries inserted afterwards are affected
+    pub fn with_weigher(self, weigher: impl Weigher + 'static) -> Self {
+        Self { weigher: Arc::new(weigher), ..self }
+    }
+
+    /// 使用指定的缓存策略
+    /// Use the given cache policy
+    pub fn with_policy(self, policy: CachePolicy) -> Self {
+        self.set_policy(policy);
+        self
+    }
+
+    /// 当前生效的缓存策略
+    /// The active cache policy
+    pub fn policy(&self) -> CachePolicy {
+        self.shards[0].eviction.lock().unwrap().policy.clone()
+    }
+
+    /// 运行时切换缓存策略
+    /// Switch the cache policy at runtime
+    ///
+    /// 已有条目全部保留，按新策略重建驱逐索引：LRU/FIFO 按最后访问时间排列，LFU 以访问次数作为初始频率，
+    /// ARC 把访问过的条目放入频繁列表；ARC 的幽灵列表和 LFU 的老化进度从零开始。新容量更小时立即按新策略驱逐多余条目。
+    /// Existing entries are kept and the eviction index is rebuilt under the new policy: LRU/FIFO order by last
+    /// access, LFU seeds frequencies from access counts and ARC puts accessed entries on its frequent list; ARC
+    /// ghost lists and LFU aging start afresh. A smaller capacity evicts the surplus under the new policy at once.
+    ///
+    /// 容量和字节预算在分片之间均分，分片可以超出份额 [`SHARD_OVERFLOW_PERCENT`]%，只要总量未超出预算；
+    /// 因此分片数大于 1 时总量最多超出预算同样的比例，驱逐也只在各分片内按策略进行。
+    /// Capacity and byte budget are split evenly across shards. A shard may exceed its share by
+    /// [`SHARD_OVERFLOW_PERCENT`]% while the total stays within budget, so with more than one shard the total can
+    /// overshoot by at most that fraction and eviction follows the policy within each shard.
+    pub fn set_policy(&self, policy: CachePolicy) {
+        for (index, shard) in self.shards.iter().enumerate() {
+            let limits = ShardLimits::new(&policy, self.shards.len(), index);
+            let mut storage = shard.storage.write().unwrap();
+            let mut eviction = shard.eviction.lock().unwrap();
+            let previous = eviction.policy.eviction_policy;
+            let retired = eviction.tracker.statistics();
+            let merged = eviction.retired.entry(previous).or_default();
+            merged.evictions += retired.evictions;
+            merged.ghost_hits += retired.ghost_hits;
+            merged.frequency_agings += retired.frequency_agings;
+
+            let mut tracker = new_tracker(policy.eviction_policy, limits.max_entries);
+            let mut entries: Vec<(&String, &CacheEntry)> = storage.iter().collect();
+            entries.sort_by_key(|(_, entry)| entry.last_accessed);
+            for (key, entry) in entries {
+                tracker.restore(key, entry);
+            }
+            eviction.tracker = tracker;
+            eviction.policy = policy.clone();
+            eviction.limits = limits;
+
+            let mut stats = shard.statistics.lock().unwrap();
+            stats.eviction_policy = policy.eviction_policy;
+            while self.needs_eviction(&eviction, storage.len(), stats.total_size, 0, 0) {
+                if !self.evict_one(&mut storage, &mut eviction, &mut stats, EvictionReason::PolicySwitch) {
+                    break;
+                }
+            }
+        }
+    }
+
+    /// 键所在的分片
+    fn shard(&self, key: &str) -> &CacheShard {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        &self.shards[hasher.finish() as usize & (self.shards.len() - 1)]
+    }
+
+    /// 分片再写入 `incoming` 个条目、`incoming_bytes` 字节前是否需要先驱逐
+    ///
+    /// 超出分片的硬上限时必须驱逐；超出份额但未到硬上限时，只有总量也超出时才驱逐。
+    fn needs_eviction(
+        &self,
+        eviction: &EvictionState,
+        entries: usize,
+        bytes: usize,
+        incoming: usize,
+        incoming_bytes: usize,
+    ) -> bool {
+        let limits = &eviction.limits;
+        let (entries, bytes) = (entries + incoming, bytes + incoming_bytes);
+        if entries > limits.max_entries || limits.max_bytes.is_some_and(|max| bytes > max) {
+            return true;
+        }
+        let over_share = entries > limits.share_entries || limits.share_bytes.is_some_and(|share| bytes > share);
+        over_share && eviction.policy.exceeds(
+            self.totals.entries.load(Ordering::Relaxed) + incoming,
+            self.totals.bytes.load(Ordering::Relaxed) + incoming_bytes,
+        )
+    }
+
+    /// 记录一个条目写入分片
+    fn admit(&self, stats: &mut CacheStatistics, weight: usize) {
+        stats.entry_count += 1;
+        stats.total_size += weight;
+        stats.peak_size = stats.peak_size.max(stats.total_size);
+        self.totals.entries.fetch_add(1, Ordering::Relaxed);
+        let bytes = self.totals.bytes.fetch_add(weight, Ordering::Relaxed) + weight;
+        self.totals.peak_bytes.fetch_max(bytes, Ordering::Relaxed);
+    }
+
+    /// 记录一个条目离开分片；`reason` 为 `None` 表示被覆盖
+    fn release(&self, stats: &mut CacheStatistics, key: &str, entry: &CacheEntry, reason: Option<EvictionReason>) {
+        if self.hooks.is_active() {
+            if let Some(reason) = reason {
+                self.hooks.dispatch("evict", |hook| hook.on_evict(key, entry, reason));
+            }
+            self.hooks.dispatch("remove", |hook| hook.on_remove(key, entry));
+        }
+        stats.entry_count -= 1;
+        stats.total_size -= entry.weight;
+        if entry.prefetched {
+            stats.prefetch_wasted += 1;
+        }
+        self.totals.entries.fetch_sub(1, Ordering::Relaxed);
+        self.totals.bytes.fetch_sub(entry.weight, Ordering::Relaxed);
+    }
+
+    /// 条目的平均年龄（距写入的时长），没有条目时为零
+    /// Mean age of the entries since they were written; zero when the cache is empty
+    pub fn mean_entry_age(&self) -> Duration {
+        let now = self.clock.now();
+        let (mut total, mut count) = (Duration::ZERO, 0u32);
+        for shard in self.shards.iter() {
+            for entry in shard.storage.read().unwrap().values() {
+                total += now.saturating_duration_since(entry.created_at);
+                count += 1;
+            }
+        }
+        if count == 0 { Duration::ZERO } else { total / count }
+    }
+
+    /// 条目数
+    /// Number of entries
+    pub fn len(&self) -> usize {
+        self.totals.entries.load(Ordering::Relaxed)
+    }
+
+    /// 是否没有条目
+    /// Whether the cache holds no entries
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 键对应条目的副本，不计为访问，也不检查是否过期
+    /// A copy of the key's entry; not counted as an access and not checked for expiry
+    pub fn entry(&self, key: &str) -> Option<CacheEntry> {
+        self.shard(key).storage.read().unwrap().get(key).cloned()
+    }
+
+    /// 全部条目的副本，按键排序；逐个分片读取，不是同一时刻的快照
+    /// Copies of every entry sorted by key; shards are read one at a time, so this is not a point-in-time snapshot
+    pub fn entries(&self) -> Vec<(String, CacheEntry)> {
+        let mut entries: Vec<(String, CacheEntry)> = self.shards.iter()
+            .flat_map(|shard| {
+                let storage = shard.storage.read().unwrap();
+                storage.iter().map(|(key, entry)| (key.clone(), entry.clone())).collect::<Vec<_>>()
+            })
+            .collect();
+        entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+        entries
+    }
+
+    /// 获取缓存值
+    ///
+    /// 过期的条目在读取时移除并计为未命中，不必等待清理；无法解压的条目同样按未命中处理并移除。
+    /// Expired entries are removed on read and count as misses without waiting for a sweep; so are entries whose
+    /// stored value fails to decompress.
+    pub fn get(&self, key: &str) -> Option<Vec<u8>> {
+        let start_time = Instant::now();
+        let now = self.clock.now();
+        if let Some(prefetch) = &self.prefetch {
+            prefetch.predictor.lock().unwrap().record(key, now);
+        }
+        
+        let shard = self.shard(key);
+        let mut storage = shard.storage.write().unwrap();
+        if let Some(entry) = storage.get_mut(key) {
+            // 检查是否过期
+            if !entry.is_expired(now) {
+                entry.last_accessed = now;
+                entry.access_count += 1;
+                let mut eviction = shard.eviction.lock().unwrap();
+                if eviction.policy.refresh_on_read {
+                    eviction.forget_expiry(key, entry);
+                    entry.expires_at = now + entry.ttl;
+                    eviction.expiry.insert((entry.expires_at, key.to_string()));
+                }
+                eviction.tracker.access(key);
+                drop(eviction);
+                if self.hooks.is_active() {
+                    self.hooks.dispatch("hit", |hook| hook.on_hit(key, entry));
+                }
+                
+                // 更新统计信息
+                let stored = entry.value.clone();
+                let compressed = entry.compressed;
+                let mut stats = shard.statistics.lock().unwrap();
+                stats.hits += 1;
+                if std::mem::take(&mut entry.prefetched) {
+                    stats.prefetch_hits += 1;
+                }
+                stats.avg_access_time = (stats.avg_access_time + start_time.elapsed()) / 2;
+                drop(stats);
+                drop(storage);
+                
+                // 在锁外解压
+                if !compressed {
+                    return Some(stored);
+                }
+                return match decompress(&stored) {
+                    Ok(value) => Some(value),
+                    Err(error) => {
+                        self.discard_corrupted(key, &stored, error);
+                        None
+                    }
+                };
+            } else {
+                // 过期，移除条目
+                let expired = storage.remove(key).expect("条目存在");
+                let mut eviction = shard.eviction.lock().unwrap();
+                eviction.tracker.remove(key);
+                eviction.forget_expiry(key, &expired);
+                drop(eviction);
+                let mut stats = shard.statistics.lock().unwrap();
+                stats.evictions += 1;
+                stats.expirations += 1;
+                self.release(&mut stats, key, &expired, Some(EvictionReason::Ttl));
+            }
+        }
+        
+        // 未命中
+        let mut stats = shard.statistics.lock().unwrap();
+        stats.misses += 1;
+        None
+    }
+
+    /// 移除解压失败的条目，并把这次读取从命中改记为未命中；条目在解压期间已被覆盖时只改记统计
+    fn discard_corrupted(&self, key: &str, stored: &[u8], error: CacheError) {
+        log::warn!("缓存键 {} 的值无法解压，已移除: {}", key, error);
+        let shard = self.shard(key);
+        let mut storage = shard.storage.write().unwrap();
+        let mut eviction = shard.eviction.lock().unwrap();
+        let mut stats = shard.statistics.lock().unwrap();
+        stats.hits -= 1;
+        stats.misses += 1;
+        if storage.get(key).is_some_and(|entry| entry.value == stored)
+            && let Some(entry) = storage.remove(key)
+        {
+            eviction.tracker.remove(key);
+            eviction.forget_expiry(key, &entry);
+            stats.evictions += 1;
+            self.release(&mut stats, key, &entry, Some(EvictionReason::Corrupted));
+        }
+    }
+
+    /// 设置缓存值
+    ///
+    /// 覆盖已有的键视为一次访问；写入前按当前策略驱逐，直到条目数和字节预算都容得下新值。
+    /// 单个值超过整个缓存的字节预算时返回 [`CacheError::ValueTooLarge`]，缓存保持不变；未超过预算但超过所在分片
+    /// 上限的值会驱逐该分片的全部条目后单独存放。`ttl` 缺省时使用策略的默认 TTL。
+    /// Overwriting an existing key counts as an access. Before inserting, entries are evicted under the active
+    /// policy until both the entry limit and the byte budget fit the new value; a single value larger than the
+    /// whole cache's byte budget fails with [`CacheError::ValueTooLarge`] and leaves the cache untouched, while a
+    /// value within the budget but above its shard's limit evicts the rest of that shard and is stored alone. A
+    /// missing `ttl` falls back to the policy's default TTL. Expired entries not yet swept are dropped before anything is evicted, so
+    /// they never count against the budget.
+    ///
+    /// 启用压缩时，超过阈值的值按策略的算法压缩后存储；压缩失败或压缩后不比原值小的值按原样存储。字节预算按存储后的大小计算。
+    /// With compression enabled, values above the threshold are stored compressed with the policy's algorithm,
+    /// unless compressing fails or does not make them smaller. The byte budget counts stored sizes.
+    pub fn set(&self, key: String, value: Vec<u8>, ttl: Option<Duration>, priority: Option<CachePriority>) -> Result<(), CacheError> {
+        self.insert(key, value, ttl, priority, false)
+    }
+
+    fn insert(
+        &self,
+        key: String,
+        value: Vec<u8>,
+        ttl: Option<Duration>,
+        priority: Option<CachePriority>,
+        prefetched: bool,
+    ) -> Result<(), CacheError> {
+        // 压缩在加锁前完成
+        let shard = self.shard(&key);
+        let (algorithm, threshold, level) = {
+            let eviction = shard.eviction.lock().unwrap();
+            let policy = &eviction.policy;
+            (policy.compression_policy, policy.compression_threshold, policy.compression_level)
+        };
+        let compressed = if self.config.compression_enabled && value.len() > threshold {
+            compress(algorithm, level, &value).unwrap_or_else(|error| {
+                log::debug!("键 {} 压缩失败，按原样存储: {}", key, error);
+                None
+            })
+        } else {
+            None
+        };
+        let original_len = value.len();
+        let (value, compressed) = match compressed {
+            Some(stored) => (stored, true),
+            None => (value, false),
+        };
+        let weight = self.weigher.weigh(&key, &value);
+        let mut storage = shard.storage.write().unwrap();
+        let mut eviction = shard.eviction.lock().unwrap();
+        if let Some(budget) = eviction.policy.max_bytes.filter(|budget| weight > *budget) {
+            return Err(CacheError::ValueTooLarge { size: weight, budget });
+        }
+        let ttl = ttl.unwrap_or(eviction.policy.default_ttl);
+        let priority = priority.unwrap_or(CachePriority::Medium);
+        let now = self.clock.now();
+
+        // 覆盖时先移除旧值，以便按新值的大小腾出空间；访问记录随条目保留
+        let mut stats = shard.statistics.lock().unwrap();
+        let previous = storage.remove(&key);
+        match &previous {
+            Some(entry) => {
+                eviction.tracker.remove(&key);
+                eviction.forget_expiry(&key, entry);
+                self.release(&mut stats, &key, entry, None);
+            }
+            None => eviction.tracker.prepare(&key),
+        }
+        if self.needs_eviction(&eviction, storage.len(), stats.total_size, 1, weight) {
+            self.remove_expired(&mut storage, &mut eviction, &mut stats, now, usize::MAX);
+        }
+        // 分片已空时只可能是其他分片占满了总量或值本身超过分片上限，允许写入
+        while !storage.is_empty() && self.needs_eviction(&eviction, storage.len(), stats.total_size, 1, weight) {
+            if !self.evict_one(&mut storage, &mut eviction, &mut stats, EvictionReason::Capacity) {
+                return Err(CacheError::StorageError("驱逐索引与存储不一致".to_string()));
+            }
+        }
+
+        let entry = CacheEntry {
+            value,
+            created_at: now,
+            last_accessed: now,
+            access_count: previous.as_ref().map_or(0, |entry| entry.access_count + 1),
+            ttl,
+            expires_at: now + ttl,
+            priority,
+            tags: previous.map(|entry| entry.tags).unwrap_or_default(),
+            weight,
+            compressed,
+            prefetched,
+        };
+        if entry.access_count > 0 {
+            eviction.tracker.restore(&key, &entry);
+        } else {
+            eviction.tracker.insert(&key);
+        }
+        eviction.expiry.insert((entry.expires_at, key.clone()));
+        self.admit(&mut stats, weight);
+        if compressed {
+            stats.compressed_writes += 1;
+            stats.bytes_saved += (original_len - entry.value.len()) as u64;
+            let ratio = entry.value.len() as f64 / original_len as f64;
+            stats.avg_compression_ratio += (ratio - stats.avg_compression_ratio) / stats.compressed_writes as f64;
+        }
+        if self.hooks.is_active() {
+            self.hooks.dispatch("insert", |hook| hook.on_insert(&key, &entry));
+        }
+        // 先增加写入次数再清除负缓存：同时进行的加载要么看到写入次数变化而不记录负缓存，要么其负缓存条目在这里被清除
+        shard.writes.fetch_add(1, Ordering::SeqCst);
+        if self.negative_in_use.load(Ordering::SeqCst) {
+            self.negative.lock().unwrap().entries.remove(&key);
+        }
+        storage.insert(key, entry);
+        Ok(())
+    }
+
+    /// 按当前策略驱逐一个条目，索引中没有可驱逐的键时返回 `false`
+    /// Evict one entry under the active policy, returning `false` when the index has nothing to evict
+    fn evict_one(
+        &self,
+        storage: &mut HashMap<String, CacheEntry>,
+        eviction: &mut EvictionState,
+        stats: &mut CacheStatistics,
+        reason: EvictionReason,
+    ) -> bool {
+        let Some(key) = eviction.tracker.victim(storage) else {
+            return false;
+        };
+        if let Some(entry) = storage.remove(&key) {
+            eviction.forget_expiry(&key, &entry);
+            stats.evictions += 1;
+            self.release(stats, &key, &entry, Some(reason));
+        }
+        true
+    }
+
+    /// 从本地缓存移除一个键，返回键是否存在；不访问后端
+    /// Remove a key from the local cache, returning whether it existed; the backend is not touched
+    pub fn remove(&self, key: &str) -> bool {
+        let shard = self.shard(key);
+        let mut storage = shard.storage.write().unwrap();
+        let Some(entry) = storage.remove(key) else {
+            return false;
+        };
+        // 与驱逐一样在持有驱逐索引的锁时更新统计，计数不会与并发的驱逐交错
+        let mut eviction = shard.eviction.lock().unwrap();
+        let mut stats = shard.statistics.lock().unwrap();
+        eviction.tracker.remove(key);
+        eviction.forget_expiry(key, &entry);
+        self.release(&mut stats, key, &entry, Some(EvictionReason::Manual));
+        true
+    }
+
+    /// 读取缓存值，本地未命中时读取后端并写回本地
+    /// Read a value, falling back to the backend on a local miss and populating the local cache
+    ///
+    /// 后端出错时记录错误并按未命中处理。
+    /// Backend errors are counted and treated as a miss.
+    pub async fn fetch(&self, key: &str) -> Option<Vec<u8>> {
+        if let Some(value) = self.get(key) {
+            return Some(value);
+        }
+        let backend = self.backend.as_ref()?;
+        match backend.get(key).await {
+            Ok(Some(value)) => {
+                self.shard(key).statistics.lock().unwrap().backend_hits += 1;
+                // 放不进本地（如超过字节预算）时仍返回后端的值
+                if let Err(error) = self.set(key.to_string(), value.clone(), None, None) {
+                    log::debug!("缓存键 {} 未写回本地: {}", key, error);
+                }
+                Some(value)
+            }
+            Ok(None) => None,
+            Err(error) => {
+                self.record_backend_error("读取", key, &error);
+                None
+            }
+        }
+    }
+
+    /// 写入本地缓存，并按写入方式写入后端
+    /// Write to the local cache and, depending on the write mode, to the backend
+    ///
+    /// 本地写入失败时直接返回错误，不写后端。直写模式下后端出错只记录，不影响本地写入的结果；
+    /// 回写模式下操作排队到 [`flush`](Self::flush)。后端收到原始值和实际生效的 TTL。
+    /// A failed local write returns the error without touching the backend. In write-through mode backend
+    /// errors are only counted and do not fail the call; in write-back mode the write is queued for
+    /// [`flush`](Self::flush). The backend receives the uncompressed value and the effective TTL.
+    pub async fn store(&self, key: String, value: Vec<u8>, ttl: Option<Duration>, priority: Option<CachePriority>) -> Result<(), CacheError> {
+        let Some(backend) = &self.backend else {
+            return self.set(key, value, ttl, priority);
+        };
+        let ttl = ttl.unwrap_or_else(|| self.shard(&key).eviction.lock().unwrap().policy.default_ttl);
+        self.set(key.clone(), value.clone(), Some(ttl), priority)?;
+        let write = PendingWrite::Put { key, value, ttl: Some(ttl) };
+        match self.write_mode {
+            WriteMode::WriteThrough => {
+                if let Err(error) = write.apply(backend.as_ref()).await {
+                    self.record_backend_error("写入", write.key(), &error);
+                }
+            }
+            WriteMode::WriteBack => self.enqueue(write),
+        }
+        Ok(())
+    }
+
+    /// 从本地缓存和后端删除一个键，返回本地是否存在该键
+    /// Delete a key from the local cache and the backend, returning whether it existed locally
+    pub async fn invalidate(&self, key: &str) -> bool {
+        let removed = self.remove(key);
+        if let Some(backend) = &self.backend {
+            let delete = PendingWrite::Delete { key: key.to_string() };
+            match self.write_mode {
+                WriteMode::WriteThrough => {
+                    if let Err(error) = delete.apply(backend.as_ref()).await {
+                        self.record_backend_error("删除", key, &error);
+                    }
+                }
+                WriteMode::WriteBack => self.enqueue(delete),
+            }
+        }
+        removed
+    }
+
+    /// 删除本地缓存和后端中以 `prefix` 开头的全部键，返回删除的键数
+    /// Delete every key starting with `prefix` locally and in the backend, returning how many keys were removed
+    pub async fn invalidate_prefix(&self, prefix: &str) -> Result<usize, CacheError> {
+        let mut keys: BTreeSet<String> = BTreeSet::new();
+        for shard in self.shards.iter() {
+            keys.extend(shard.storage.read().unwrap().keys().filter(|key| key.starts_with(prefix)).cloned());
+        }
+        if let Some(backend) = &self.backend {
+            keys.extend(backend.scan_prefix(prefix).await?);
+        }
+        for key in &keys {
+            self.invalidate(key).await;
+        }
+        Ok(keys.len())
+    }
+
+    /// 按顺序把回写队列写入后端，返回写入的操作数
+    /// Apply the write-back queue to the backend in order, returning how many operations were applied
+    ///
+    /// 遇到错误时停止，失败的操作和之后的操作保留在队列中等待下次刷新。
+    /// Stops at the first error, keeping the failed operation and everything after it queued for the next flush.
+    pub async fn flush(&self) -> Result<usize, CacheError> {
+        let Some(backend) = &self.backend else {
+            return Ok(0);
+        };
+        let pending = std::mem::take(&mut *self.pending_writes.lock().unwrap());
+        for (applied, write) in pending.iter().enumerate() {
+            if let Err(error) = write.apply(backend.as_ref()).await {
+                self.record_backend_error("回写", write.key(), &error);
+                let mut queue = self.pending_writes.lock().unwrap();
+                let newer: Vec<PendingWrite> = std::mem::take(&mut *queue);
+                // 刷新期间的新操作排在未完成的操作之后，并覆盖同一个键的旧操作
+                queue.extend(pending.into_iter().skip(applied));
+                for write in newer {
+                    queue.retain(|queued| queued.key() != write.key());
+                    queue.push(write);
+                }
+                return Err(error);
+            }
+        }
+        Ok(pending.len())
+    }
+
+    /// 回写队列中等待写入后端的操作数
+    /// Number of operations waiting in the write-back queue
+    pub fn pending_writes(&self) -> usize {
+        self.pending_writes.lock().unwrap().len()
+    }
+
+    fn enqueue(&self, write: PendingWrite) {
+        let mut queue = self.pending_writes.lock().unwrap();
+        queue.retain(|queued| queued.key() != write.key());
+        queue.push(write);
+    }
+
+    fn record_backend_error(&self, operation: &str, key: &str, error: &CacheError) {
+        log::warn!("缓存后端{}键 {} 失败，退回本地缓存: {}", operation, key, error);
+        self.shard(key).statistics.lock().unwrap().backend_errors += 1;
+    }
+
+    /// 各分片各自的统计，按分片顺序排列；策略统计只在 [`Self::get_statistics`] 中汇总
+    /// Per-shard statistics in shard order; policy statistics are only aggregated by [`Self::get_statistics`]
+    pub fn shard_statistics(&self) -> Vec<CacheStatistics> {
+        self.shards.iter().map(|shard| shard.statistics.lock().unwrap().clone()).collect()
+    }
+
+    /// 获取统计信息，按需汇总全部分片
+    /// Get the statistics, aggregated across shards on demand
+    pub fn get_statistics(&self) -> CacheStatistics {
+        let mut stats = CacheStatistics::new(self.shards[0].eviction.lock().unwrap().policy.eviction_policy);
+        for shard in self.shards.iter() {
+            let eviction = shard.eviction.lock().unwrap();
+            stats.merge(&shard.statistics.lock().unwrap());
+            let current = eviction.tracker.statistics();
+            let policies = eviction.retired.iter().chain([(&eviction.policy.eviction_policy, &current)]);
+            for (policy, shard_stats) in policies {
+                let merged = stats.policy_statistics.entry(*policy).or_default();
+                merged.evictions += shard_stats.evictions;
+                merged.ghost_hits += shard_stats.ghost_hits;
+                merged.frequency_agings += shard_stats.frequency_agings;
+            }
+        }
+        stats.peak_size = self.totals.peak_bytes.load(Ordering::Relaxed);
+        stats
+    }
+
+    /// 清理过期条目
+    pub fn cleanup_expired(&self) -> Result<usize, CacheError> {
+        Ok(self.sweep_expired(usize::MAX))
+    }
+
+    /// 移除至多 `limit` 个过期条目，返回移除数量；每次调用对每个分片只持锁一次
+    /// Remove at most `limit` expired entries, returning how many were removed; holds each shard's lock once per
+    /// call
+    pub fn sweep_expired(&self, limit: usize) -> usize {
+        let now = self.clock.now();
+        let mut removed = 0;
+        for shard in self.shards.iter() {
+            if removed >= limit {
+                break;
+            }
+            let mut storage = shard.storage.write().unwrap();
+            let mut eviction = shard.eviction.lock().unwrap();
+            let mut stats = shard.statistics.lock().unwrap();
+            removed += self.remove_expired(&mut storage, &mut eviction, &mut stats, now, limit - removed);
+        }
+        removed
+    }
+
+    /// 按过期索引移除至多 `limit` 个过期条目
+    fn remove_expired(
+        &self,
+        storage: &mut HashMap<String, CacheEntry>,
+        eviction: &mut EvictionState,
+        stats: &mut CacheStatistics,
+        now: Instant,
+        limit: usize,
+    ) -> usize {
+        // 过期索引按时间排序，只取出已过期的前缀，不扫描整个分片
+        let mut removed = 0;
+        while removed < limit
+            && let Some(first) = eviction.expiry.first()
+            && first.0 <= now
+        {
+            let (_, key) = eviction.expiry.pop_first().expect("索引非空");
+            if let Some(entry) = storage.remove(&key) {
+                self.release(stats, &key, &entry, Some(EvictionReason::Ttl));
+            }
+            eviction.tracker.remove(&key);
+            removed += 1;
+        }
+        stats.evictions += removed as u64;
+        stats.expirations += removed as u64;
+        removed
+    }
+
+    /// 启动后台任务，每隔 `cleanup_interval` 分批移除过期条目；缓存释放后任务自行结束
+    /// Spawn a background task removing expired entries in batches every `cleanup_interval`; it ends once the
+    /// cache is dropped
+    ///
+    /// 每批最多移除 `sweep_batch_size` 个条目，批次之间释放锁并让出执行权，避免长时间阻塞读写。
+    /// Each batch removes at most `sweep_batch_size` entries and the lock is released and the task yields between
+    /// batches, keeping lock hold times short.
+    pub fn start_sweeper(self: &Arc<Self>) {
+        let cache: Weak<Self> = Arc::downgrade(self);
+        let interval = self.config.cleanup_interval.max(Duration::from_millis(1));
+        let batch = self.config.sweep_batch_size.max(1);
+        let handle = tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                loop {
+                    let Some(cache) = cache.upgrade() else {
+                        return;
+                    };
+                    if cache.sweep_expired(batch) < batch {
+                        break;
+                    }
+                    drop(cache);
+                    tokio::task::yield_now().await;
+                }
+            }
+        });
+        if let Ok(mut task) = self.sweeper_task.lock()
+            && let Some(previous) = task.replace(handle)
+        {
+            previous.abort();
+        }
+    }
+
+    /// 停止后台过期清理任务
+    /// Stop the background sweeper
+    pub fn stop_sweeper(&self) {
+        if let Ok(mut task) = self.sweeper_task.lock()
+            && let Some(handle) = task.take()
+        {
+            handle.abort();
+        }
+    }
+
+    /// 用 `loader` 加载尚未缓存的键，返回写入的条目数；加载失败时返回第一个错误
+    /// Load the keys not cached yet with `loader`, returning how many entries were written; stops at the first
+    /// loader error
+    ///
+    /// 预热不计入预测预取的统计，也不记录为访问。
+    /// Warming is not counted in the prefetch statistics and is not recorded as an access.
+    pub async fn warm<I>(&self, keys: I, loader: &dyn CacheLoader) -> Result<usize, CacheError>
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        let mut warmed = 0;
+        for key in keys {
+            let key = key.into();
+            if self.is_cached(&key) {
+                continue;
+            }
+            if let Some(value) = loader.load(&key).await? {
+                self.set(key, value, None, None)?;
+                warmed += 1;
+            }
+        }
+        Ok(warmed)
+    }
+
+    /// 预取预测即将被访问且尚未缓存的键，返回写入的条目数；未启用预测预取时什么也不做
+    /// Prefetch the keys predicted to be accessed soon that are not cached yet, returning how many entries were
+    /// written; does nothing unless predictive prefetch is enabled
+    pub async fn prefetch_due(&self) -> usize {
+        let Some(prefetch) = &self.prefetch else {
+            return 0;
+        };
+        let due = prefetch.predictor.lock().unwrap().due(self.clock.now());
+        let mut issued = 0;
+        for key in due {
+            if self.is_cached(&key) {
+                continue;
+            }
+            match prefetch.loader.load(&key).await {
+                Ok(Some(value)) => match self.insert(key.clone(), value, None, None, true) {
+                    Ok(()) => {
+                        self.shard(&key).statistics.lock().unwrap().prefetch_issued += 1;
+                        issued += 1;
+                    }
+                    Err(error) => log::debug!("预取的键 {} 未写入缓存: {}", key, error),
+                },
+                Ok(None) => {}
+                Err(error) => log::warn!("预取键 {} 失败: {}", key, error),
+            }
+        }
+        issued
+    }
+
+    /// 启动后台任务，每隔 `PrefetchConfig::interval` 执行一次 [`prefetch_due`](Self::prefetch_due)；缓存释放后任务自行结束
+    /// Spawn a background task running [`prefetch_due`](Self::prefetch_due) every `PrefetchConfig::interval`; it
+    /// ends once the cache is dropped
+    pub fn start_prefetcher(self: &Arc<Self>) {
+        let Some(prefetch) = &self.prefetch else {
+            return;
+        };
+        let interval = prefetch.predictor.lock().unwrap().config.interval.max(Duration::from_millis(1));
+        let cache: Weak<Self> = Arc::downgrade(self);
+        let handle = tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                let Some(cache) = cache.upgrade() else {
+                    return;
+                };
+                cache.prefetch_due().await;
+            }
+        });
+        if let Ok(mut task) = self.prefetch_task.lock()
+            && let Some(previous) = task.replace(handle)
+        {
+            previous.abort();
+        }
+    }
+
+    /// 停止后台预取任务
+    /// Stop the background prefetcher
+    pub fn stop_prefetcher(&self) {
+        if let Ok(mut task) = self.prefetch_task.lock()
+            && let Some(handle) = task.take()
+        {
+            handle.abort();
+        }
+    }
+
+    /// 读取缓存值，未命中时用 `loader` 加载并写入缓存
+    /// Read a value, loading it with `loader` and caching it on a miss
+    ///
+    /// 按键的负缓存策略，加载器确认不存在的键在 `absent_ttl` 内直接返回 `Ok(None)`，加载失败的键在 `error_ttl` 内直接返回
+    /// [`CacheError::CachedFailure`]，都不再调用加载器；命中负缓存计入 `negative_hits`。写入该键后负缓存立即失效。
+    /// Under the key's negative caching policy, keys the loader reported absent return `Ok(None)` for
+    /// `absent_ttl` and failed loads return [`CacheError::CachedFailure`] for `error_ttl`, both without calling
+    /// the loader again; such lookups count as `negative_hits`. Writing the key drops its negative entry at once.
+    pub async fn get_or_load(&self, key: &str, loader: &dyn CacheLoader) -> Result<Option<Vec<u8>>, CacheError> {
+        if let Some(cached) = self.negative_lookup(key) {
+            return cached;
+        }
+        if let Some(value) = self.get(key) {
+            return Ok(Some(value));
+        }
+
+        let writes = self.shard(key).writes.load(Ordering::SeqCst);
+        let loaded = loader.load(key).await;
+        if let Ok(Some(value)) = &loaded {
+            self.set(key.to_string(), value.clone(), None, None)?;
+        }
+        self.record_negative(key, &loaded, writes);
+        loaded
+    }
+
+    /// 查找未过期的负缓存条目，命中时计入 `negative_hits`
+    fn negative_lookup(&self, key: &str) -> Option<Result<Option<Vec<u8>>, CacheError>> {
+        if !self.negative_in_use.load(Ordering::SeqCst) {
+            return None;
+        }
+        let entry = self.negative.lock().unwrap().lookup(key, self.clock.now())?;
+        self.shard(key).statistics.lock().unwrap().negative_hits += 1;
+        Some(match entry.error {
+            Some(error) => Err(CacheError::CachedFailure(error)),
+            None => Ok(None),
+        })
+    }
+
+    /// 按键的负缓存策略记录不存在或加载失败的结果
+    ///
+    /// `writes` 是加载开始前分片的写入次数；加载期间分片被写入过时不记录，以免负缓存遮住刚写入的值。
+    fn record_negative(&self, key: &str, loaded: &Result<Option<Vec<u8>>, CacheError>, writes: u64) {
+        let now = self.clock.now();
+        let policy = self.negative.lock().unwrap().policy(key);
+        let entry = match loaded {
+            Ok(Some(_)) => None,
+            Ok(None) => policy.absent_ttl.map(|ttl| NegativeEntry { expires_at: now + ttl, error: None }),
+            Err(error) => policy.error_ttl.map(|ttl| NegativeEntry { expires_at: now + ttl, error: Some(error.to_string()) }),
+        };
+        if let Some(entry) = entry {
+            let limit = self.policy().max_size.max(1);
+            let mut negative = self.negative.lock().unwrap();
+            self.negative_in_use.store(true, Ordering::SeqCst);
+            if self.shard(key).writes.load(Ordering::SeqCst) == writes {
+                negative.record(key, entry, now, limit);
+            }
+        }
+    }
+
+    /// 读取缓存值，未命中时运行 `loader` 并写入缓存；同一个键的并发调用只运行一次加载（single-flight）
+    /// Read a value, running `loader` and caching its result on a miss; concurrent calls for the same key share a
+    /// single load
+    ///
+    /// 第一个未命中的调用者运行加载，其余调用者等待同一个结果。加载出错、panic 或超过 [`with_load_timeout`](Self::with_load_timeout)
+    /// 设置的时间时，等待者收到错误，该键随即释放，下一次调用重新加载。加载错误按键的负缓存策略（`error_ttl`）记录。
+    /// The first caller to miss runs the loader while the others await its result. When the load fails, panics or
+    /// exceeds the [`with_load_timeout`](Self::with_load_timeout) limit, waiters receive an error and the key is
+    /// released so the next call loads again. Load errors are negatively cached under the key's `error_ttl`.
+    pub async fn get_or_insert_with<F, Fut>(&self, key: &str, loader: F) -> Result<Vec<u8>, CacheError>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<Vec<u8>, CacheError>>,
+    {
+        if let Some(value) = self.get(key) {
+            return Ok(value);
+        }
+        if let Some(Err(error)) = self.negative_lookup(key) {
+            return Err(error);
+        }
+        let flight = match self.join_flight(key) {
+            FlightJoin::Cached(value) => return Ok(value),
+            FlightJoin::Leader(flight) => flight,
+            FlightJoin::Waiter(flight) => {
+                let deadline = flight.started + self.load_timeout;
+                loop {
+                    let notified = flight.notify.notified();
+                    tokio::pin!(notified);
+                    notified.as_mut().enable();
+                    if let Some(result) = flight.result() {
+                        return result;
+                    }
+                    let remaining = deadline.saturating_duration_since(Instant::now());
+                    if tokio::time::timeout(remaining, notified).await.is_err() {
+                        return flight.result().unwrap_or(Err(CacheError::LoadTimedOut(self.load_timeout)));
+                    }
+                }
+            }
+        };
+
+        let guard = FlightGuard::new(self, key, flight);
+        let writes = self.shard(key).writes.load(Ordering::SeqCst);
+        let loaded = tokio::time::timeout(self.load_timeout, loader())
+            .await
+            .unwrap_or(Err(CacheError::LoadTimedOut(self.load_timeout)));
+        let result = self.complete_load(key, loaded, writes);
+        guard.finish(result.clone());
+        result
+    }
+
+    /// [`get_or_insert_with`](Self::get_or_insert_with) 的同步版本，等待者阻塞当前线程
+    /// Synchronous [`get_or_insert_with`](Self::get_or_insert_with); waiters block the current thread
+    ///
+    /// 同步加载无法被中断：超时后等待者收到错误，之后的调用者接替加载，原加载的结果被丢弃。
+    /// A synchronous load cannot be interrupted: after the timeout, waiters receive an error and the next caller
+    /// takes over the load, discarding the original result.
+    pub fn get_or_insert_with_sync<F>(&self, key: &str, loader: F) -> Result<Vec<u8>, CacheError>
+    where
+        F: FnOnce() -> Result<Vec<u8>, CacheError>,
+    {
+        if let Some(value) = self.get(key) {
+            return Ok(value);
+        }
+        if let Some(Err(error)) = self.negative_lookup(key) {
+            return Err(error);
+        }
+        let flight = match self.join_flight(key) {
+            FlightJoin::Cached(value) => return Ok(value),
+            FlightJoin::Leader(flight) => flight,
+            FlightJoin::Waiter(flight) => {
+                let deadline = flight.started + self.load_timeout;
+                let mut result = flight.result.lock().unwrap();
+                while result.is_none() {
+                    let remaining = deadline.saturating_duration_since(Instant::now());
+                    if remaining.is_zero() {
+                        return Err(CacheError::LoadTimedOut(self.load_timeout));
+                    }
+                    result = flight.done.wait_timeout(result, remaining).unwrap().0;
+                }
+                return result.clone().expect("结果已写入");
+            }
+        };
+
+        let guard = FlightGuard::new(self, key, flight);
+        let writes = self.shard(key).writes.load(Ordering::SeqCst);
+        let result = self.complete_load(key, loader(), writes);
+        guard.finish(result.clone());
+        result
+    }
+
+    /// 加入键的进行中加载，没有或已超时时创建新的加载并成为加载者
+    ///
+    /// 创建前在持有加载表的锁时再查一次缓存：上一次加载可能在调用者未命中之后、加锁之前完成，
+    /// 加载者总是先写入缓存再从加载表移除该键，所以此时仍未命中就确实需要加载。
+    fn join_flight(&self, key: &str) -> FlightJoin {
+        let mut in_flight = self.in_flight.lock().unwrap();
+        if let Some(flight) = in_flight.get(key)
+            && flight.started.elapsed() < self.load_timeout
+        {
+            return FlightJoin::Waiter(flight.clone());
+        }
+        if let Some(value) = self.peek(key) {
+            return FlightJoin::Cached(value);
+        }
+        let flight = Arc::new(Flight {
+            started: Instant::now(),
+            result: Mutex::new(None),
+            done: Condvar::new(),
+            notify: tokio::sync::Notify::new(),
+        });
+        in_flight.insert(key.to_string(), flight.clone());
+        FlightJoin::Leader(flight)
+    }
+
+    /// 读取未过期的值，不计入统计也不更新访问记录
+    fn peek(&self, key: &str) -> Option<Vec<u8>> {
+        let (stored, compressed) = {
+            let storage = self.shard(key).storage.read().unwrap();
+            let entry = storage.get(key).filter(|entry| !entry.is_expired(self.clock.now()))?;
+            (entry.value.clone(), entry.compressed)
+        };
+        if compressed { decompress(&stored).ok() } else { Some(stored) }
+    }
+
+    /// 写入加载的值并记录失败；值放不进缓存时仍返回给调用者
+    fn complete_load(&self, key: &str, loaded: Result<Vec<u8>, CacheError>, writes: u64) -> Result<Vec<u8>, CacheError> {
+        match &loaded {
+            Ok(value) => {
+                if let Err(error) = self.set(key.to_string(), value.clone(), None, None) {
+                    log::debug!("加载的键 {} 未写入缓存: {}", key, error);
+                }
+            }
+            Err(error) => self.record_negative(key, &Err(error.clone()), writes),
+        }
+        loaded
+    }
+
+    /// 公布加载结果，唤醒等待者，并在该键仍属于这次加载时释放它
+    fn finish_flight(&self, key: &str, flight: &Arc<Flight>, result: Result<Vec<u8>, CacheError>) {
+        {
+            let mut in_flight = self.in_flight.lock().unwrap();
+            if in_flight.get(key).is_some_and(|current| Arc::ptr_eq(current, flight)) {
+                in_flight.remove(key);
+            }
+        }
+        flight.result.lock().unwrap().get_or_insert(result);
+        flight.done.notify_all();
+        flight.notify.notify_waiters();
+    }
+
+    /// 把未过期的条目写入快照文件，返回写入的条目数
+    /// Write the unexpired entries to a snapshot file, returning how many were written
+    ///
+    /// 文件由头部（魔数、版本、标志、写入时间、策略）和逐条记录组成，每条记录带长度前缀和 CRC32 校验；
+    /// 启用压缩时记录流整体用 Gzip 压缩。逐个分片流式写入本次调用独有的临时文件，完成后重命名为目标文件并同步所在目录，
+    /// 中途失败不会破坏已有的快照，并发的快照也不会互相覆盖临时文件。
+    /// The file holds a header (magic, version, flags, creation time, policy) followed by records, each length
+    /// prefixed and CRC32 checked; with compression enabled the record stream is gzipped. Shards are streamed one
+    /// at a time into a temporary file unique to the call, which is renamed over the target once complete before
+    /// the directory is synced, so a failure never damages an existing snapshot and concurrent snapshots never
+    /// share a temporary file.
+    pub fn snapshot_to(&self, path: impl AsRef<Path>) -> Result<usize, CacheError> {
+        let path = path.as_ref();
+        let temp = snapshot_temp_path(path);
+        match self.write_snapshot(&temp) {
+            Ok(written) => {
+                if let Err(error) = std::fs::rename(&temp, path) {
+                    let _ = std::fs::remove_file(&temp);
+                    return Err(io_error(error));
+                }
+                sync_parent_dir(path).map_err(io_error)?;
+                Ok(written)
+            }
+            Err(error) => {
+                let _ = std::fs::remove_file(&temp);
+                Err(error)
+            }
+        }
+    }
+
+    fn write_snapshot(&self, path: &Path) -> Result<usize, CacheError> {
+        let mut writer = BufWriter::new(File::create(path).map_err(io_error)?);
+        let policy = self.policy();
+        let compressed = self.config.compression_enabled;
+        let created_at = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        writer.write_all(SNAPSHOT_MAGIC).map_err(io_error)?;
+        writer.write_all(&[SNAPSHOT_VERSION, if compressed { SNAPSHOT_FLAG_GZIP } else { 0 }]).map_err(io_error)?;
+        writer.write_all(&(created_at.as_millis() as u64).to_le_bytes()).map_err(io_error)?;
+        write_str(&mut writer, &policy.name).map_err(io_error)?;
+        write_str(&mut writer, &format!("{:?}", policy.eviction_policy)).map_err(io_error)?;
+
+        let written = if compressed {
+            let mut encoder = flate2::write::GzEncoder::new(writer, flate2::Compression::default());
+            let written = self.write_records(&mut encoder)?;
+            writer = encoder.finish().map_err(io_error)?;
+            written
+        } else {
+            self.write_records(&mut writer)?
+        };
+        let file = writer.into_inner().map_err(|error| io_error(error.into_error()))?;
+        file.sync_all().map_err(io_error)?;
+        Ok(written)
+    }
+
+    fn write_records(&self, writer: &mut impl Write) -> Result<usize, CacheError> {
+        let mut written = 0;
+        for shard in self.shards.iter() {
+            // 只在复制条目时持有分片的锁，按最后访问时间排列以便恢复时保持新旧顺序
+            let now = self.clock.now();
+            let mut entries: Vec<(String, CacheEntry)> = shard.storage.read().unwrap()
+                .iter()
+                .filter(|(_, entry)| !entry.is_expired(now))
+                .map(|(key, entry)| (key.clone(), entry.clone()))
+                .collect();
+            entries.sort_by_key(|(_, entry)| entry.last_accessed);
+            for (key, entry) in entries {
+                let value = if entry.compressed { decompress(&entry.value)? } else { entry.value };
+                let payload = SnapshotRecord {
+                    key,
+                    value,
+                    remaining: entry.expires_at.duration_since(now),
+                    ttl: entry.ttl,
+                    access_count: entry.access_count,
+                    priority: entry.priority,
+                }
+                .encode();
+                writer.write_all(&(payload.len() as u32).to_le_bytes()).map_err(io_error)?;
+                writer.write_all(&payload).map_err(io_error)?;
+                writer.write_all(&checksum(&payload).to_le_bytes()).map_err(io_error)?;
+                written += 1;
+            }
+        }
+        Ok(written)
+    }
+
+    /// 从快照文件恢复条目
+    /// Restore entries from a snapshot file
+    ///
+    /// 跳过写入快照后已过期的条目；校验失败的记录计入 `corrupt` 并跳过，不影响其他记录；
+    /// 长度字段损坏或文件被截断时停止读取，已恢复的条目保留。超过当前字节预算的条目直接丢弃。
+    /// 条目保留剩余的 TTL、原 TTL 和访问次数，驱逐索引按访问次数重建。
+    /// Entries that expired since the snapshot are skipped; records failing their checksum count as `corrupt` and
+    /// are skipped without affecting the others, while a damaged length or a truncated file stops reading and
+    /// keeps what was restored. Entries over the current byte budget are dropped. Entries keep their remaining
+    /// TTL, original TTL and access count, and the eviction index is seeded from the access counts.
+    pub fn restore_from(&self, path: impl AsRef<Path>) -> Result<RestoreReport, CacheError> {
+        let mut reader = BufReader::new(File::open(path).map_err(io_error)?);
+        let header: [u8; 8] = read_array(&mut reader).map_err(io_error)?;
+        if &header[..6] != SNAPSHOT_MAGIC {
+            return Err(CacheError::SerializationError("不是缓存快照文件".to_string()));
+        }
+        if header[6] != SNAPSHOT_VERSION {
+            return Err(CacheError::SerializationError(format!("不支持的快照版本 {}", header[6])));
+        }
+        let compressed = header[7] & SNAPSHOT_FLAG_GZIP != 0;
+        let created_at = UNIX_EPOCH + Duration::from_millis(u64::from_le_bytes(read_array(&mut reader).map_err(io_error)?));
+        let mut report = RestoreReport {
+            restored: 0,
+            expired: 0,
+            corrupt: 0,
+            dropped: 0,
+            policy: read_str(&mut reader)?,
+            eviction_policy: read_str(&mut reader)?,
+            created_at,
+        };
+        let elapsed = SystemTime::now().duration_since(created_at).unwrap_or_default();
+
+        let mut records: Box<dyn Read> = if compressed {
+            Box::new(flate2::read::GzDecoder::new(reader))
+        } else {
+            Box::new(reader)
+        };
+        loop {
+            let (payload, expected) = match read_record(&mut records) {
+                Ok(Some(record)) => record,
+                Ok(None) => break,
+                Err(error) => {
+                    log::warn!("快照在第 {} 条记录之后无法继续读取: {}", report.restored + report.expired + report.corrupt + report.dropped, error);
+                    report.corrupt += 1;
+                    break;
+                }
+            };
+            let record = (checksum(&payload) == expected).then(|| SnapshotRecord::decode(&payload)).flatten();
+            let Some(record) = record else {
+                report.corrupt += 1;
+                continue;
+            };
+            let Some(remaining) = record.remaining.checked_sub(elapsed).filter(|remaining| !remaining.is_zero()) else {
+                report.expired += 1;
+                continue;
+            };
+            match self.set(record.key.clone(), record.value, Some(remaining), Some(record.priority)) {
+                Ok(()) => {
+                    self.restore_metadata(&record.key, record.ttl, record.access_count);
+                    report.restored += 1;
+                }
+                Err(CacheError::ValueTooLarge { .. }) => report.dropped += 1,
+                Err(error) => return Err(error),
+            }
+        }
+        if report.corrupt > 0 {
+            log::warn!("恢复缓存快照时跳过了 {} 条损坏的记录", report.corrupt);
+        }
+        Ok(report)
+    }
+
+    /// 恢复条目的原 TTL 和访问次数，并按访问次数重建该键的驱逐索引
+    fn restore_metadata(&self, key: &str, ttl: Duration, access_count: u64) {
+        let shard = self.shard(key);
+        let mut storage = shard.storage.write().unwrap();
+        if let Some(entry) = storage.get_mut(key) {
+            entry.ttl = ttl;
+            entry.access_count = access_count;
+            let mut eviction = shard.eviction.lock().unwrap();
+            eviction.tracker.remove(key);
+            eviction.tracker.restore(key, entry);
+        }
+    }
+
+    /// 本地缓存中是否有未过期的条目，不计为访问
+    fn is_cached(&self, key: &str) -> bool {
+        let now = self.clock.now();
+        self.shard(key).storage.read().unwrap().get(key).is_some_and(|entry| !entry.is_expired(now))
+    }
+}
+
+impl CacheEntry {
+    /// 在给定时刻是否已过期
+    /// Whether the entry has expired at the given instant
+    pub fn is_expired(&self, now: Instant) -> bool {
+        now >= self.expires_at
+    }
+}
+
+impl CachePolicy {
+    /// 给定的条目数和字节数是否超过条目数上限或字节预算
+    /// Whether the given entry count and bytes exceed the entry limit or the byte budget
+    fn exceeds(&self, entries: usize, bytes: usize) -> bool {
+        entries > self.max_size.max(1) || self.max_bytes.is_some_and(|budget| bytes > budget)
+    }
+}
+
+/// 快照文件的魔数
+const SNAPSHOT_MAGIC: &[u8; 6] = b"ICSNAP";
+/// 快照格式版本
+const SNAPSHOT_VERSION: u8 = 1;
+/// 快照头部标志：记录流经过 Gzip 压缩
+const SNAPSHOT_FLAG_GZIP: u8 = 1;
+/// 单条快照记录的最大长度，超过时视为长度字段损坏
+const SNAPSHOT_MAX_RECORD: usize = 256 << 20;
+
+/// 快照恢复的结果
+/// Outcome of restoring a snapshot
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RestoreReport {
+    /// 恢复的条目数
+    pub restored: usize,
+    /// 快照之后已过期而跳过的条目数
+    pub expired: usize,
+    /// 校验失败而跳过的记录数
+    pub corrupt: usize,
+    /// 超过当前字节预算而丢弃的条目数
+    pub dropped: usize,
+    /// 写入快照时的策略名称
+    pub policy: String,
+    /// 写入快照时的驱逐策略
+    pub eviction_policy: String,
+    /// 写入快照的时间
+    pub created_at: SystemTime,
+}
+
+/// 快照中的一个条目
+struct SnapshotRecord {
+    key: String,
+    value: Vec<u8>,
+    remaining: Duration,
+    ttl: Duration,
+    access_count: u64,
+    priority: CachePriority,
+}
+
+impl SnapshotRecord {
+    /// 记录内容：键、值（各带 u32 长度）、剩余 TTL 和原 TTL（毫秒）、访问次数、优先级
+    fn encode(&self) -> Vec<u8> {
+        let mut payload = Vec::with_capacity(8 + self.key.len() + self.value.len() + 25);
+        payload.extend_from_slice(&(self.key.len() as u32).to_le_bytes());
+        payload.extend_from_slice(self.key.as_bytes());
+        payload.extend_from_slice(&(self.value.len() as u32).to_le_bytes());
+        payload.extend_from_slice(&self.value);
+        payload.extend_from_slice(&(self.remaining.as_millis() as u64).to_le_bytes());
+        payload.extend_from_slice(&(self.ttl.as_millis() as u64).to_le_bytes());
+        payload.extend_from_slice(&self.access_count.to_le_bytes());
+        payload.push(self.priority as u8);
+        payload
+    }
+
+    fn decode(payload: &[u8]) -> Option<Self> {
+        let mut cursor = payload;
+        let mut take = |len: usize| -> Option<&[u8]> {
+            let (head, rest) = cursor.split_at_checked(len)?;
+            cursor = rest;
+            Some(head)
+        };
+        let key_len = u32::from_le_bytes(take(4)?.try_into().ok()?) as usize;
+        let key = String::from_utf8(take(key_len)?.to_vec()).ok()?;
+        let value_len = u32::from_le_bytes(take(4)?.try_into().ok()?) as usize;
+        let value = take(value_len)?.to_vec();
+        let remaining = Duration::from_millis(u64::from_le_bytes(take(8)?.try_into().ok()?));
+        let ttl = Duration::from_millis(u64::from_le_bytes(take(8)?.try_into().ok()?));
+        let access_count = u64::from_le_bytes(take(8)?.try_into().ok()?);
+        let priority = match take(1)?[0] {
+            1 => CachePriority::Low,
+            2 => CachePriority::Medium,
+            3 => CachePriority::High,
+            4 => CachePriority::Critical,
+            _ => return None,
+        };
+        cursor.is_empty().then_some(Self { key, value, remaining, ttl, access_count, priority })
+    }
+}
+
+fn io_error(error: std::io::Error) -> CacheError {
+    CacheError::StorageError(error.to_string())
+}
+
+/// 临时文件序号，同一进程内并发写入同一快照时各用各的临时文件
+static SNAPSHOT_TEMP_SEQUENCE: AtomicU64 = AtomicU64::new(0);
+
+/// 与快照同目录、按进程和序号区分的临时文件，写完后重命名为快照
+fn snapshot_temp_path(path: &Path) -> PathBuf {
+    let mut name = std::ffi::OsString::from(".");
+    name.push(path.file_name().unwrap_or_default());
+    name.push(format!(".{}-{}.tmp", std::process::id(), SNAPSHOT_TEMP_SEQUENCE.fetch_add(1, Ordering::Relaxed)));
+    path.with_file_name(name)
+}
+
+/// 同步快照所在目录，使重命名在断电后仍然生效；不支持打开目录的平台上跳过
+fn sync_parent_dir(path: &Path) -> std::io::Result<()> {
+    if cfg!(unix) {
+        let parent = path.parent().filter(|parent| !parent.as_os_str().is_empty()).unwrap_or(Path::new("."));
+        File::open(parent)?.sync_all()?;
+    }
+    Ok(())
+}
+
+fn write_str(writer: &mut impl Write, value: &str) -> std::io::Result<()> {
+    writer.write_all(&(value.len() as u32).to_le_bytes())?;
+    writer.write_all(value.as_bytes())
+}
+
+fn read_array<const N: usize>(reader: &mut impl Read) -> std::io::Result<[u8; N]> {
+    let mut bytes = [0; N];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn read_str(reader: &mut impl Read) -> Result<String, CacheError> {
+    let len = u32::from_le_bytes(read_array(reader).map_err(io_error)?) as usize;
+    if len > SNAPSHOT_MAX_RECORD {
+        return Err(CacheError::SerializationError("快照头部损坏".to_string()));
+    }
+    let mut bytes = vec![0; len];
+    reader.read_exact(&mut bytes).map_err(io_error)?;
+    String::from_utf8(bytes).map_err(|_| CacheError::SerializationError("快照头部损坏".to_string()))
+}
+
+/// 读取一条记录；`Ok(None)` 表示在记录边界正常结束
+/// Read one record; `Ok(None)` marks a clean end at a record boundary
+fn read_record(reader: &mut impl Read) -> std::io::Result<Option<(Vec<u8>, u32)>> {
+    let mut len = [0; 4];
+    let mut filled = 0;
+    while filled < len.len() {
+        match reader.read(&mut len[filled..])? {
+            0 if filled == 0 => return Ok(None),
+            0 => return Err(std::io::ErrorKind::UnexpectedEof.into()),
+            read => filled += read,
+        }
+    }
+    let len = u32::from_le_bytes(len) as usize;
+    if len > SNAPSHOT_MAX_RECORD {
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, format!("记录长度 {} 无效", len)));
+    }
+    let mut payload = vec![0; len];
+    reader.read_exact(&mut payload)?;
+    let checksum = u32::from_le_bytes(read_array(reader)?);
+    Ok(Some((payload, checksum)))
+}
+
+fn checksum(payload: &[u8]) -> u32 {
+    let mut crc = flate2::Crc::new();
+    crc.update(payload);
+    crc.sum()
+}
+
+/// 压缩数据的头部长度：1 字节算法标记 + 8 字节原始长度（小端）
+const COMPRESSION_HEADER_LEN: usize = 9;
+/// Gzip 的算法标记
+const GZIP_TAG: u8 = 1;
+/// Zstd 的算法标记
+const ZSTD_TAG: u8 = 2;
+
+/// 按策略压缩，返回带头部的压缩数据；不支持的策略或压缩后不更小时返回 `None`
+/// Compress under the policy, returning the framed data; `None` when the policy does not compress or the
+/// result is not smaller than the input
+fn compress(policy: CompressionPolicy, level: Option<i32>, value: &[u8]) -> Result<Option<Vec<u8>>, CacheError> {
+    let framed = match policy {
+        CompressionPolicy::None => return Ok(None),
+        CompressionPolicy::LZ4 => return Err(CacheError::CompressionError("不支持 LZ4 压缩".to_string())),
+        CompressionPolicy::Gzip => frame(GZIP_TAG, value, gzip(level, value)?),
+        CompressionPolicy::Zstd => frame(ZSTD_TAG, value, zstd_compress(level, value)?),
+        CompressionPolicy::Adaptive => {
+            let gzip = frame(GZIP_TAG, value, gzip(level, value)?);
+            let zstd = frame(ZSTD_TAG, value, zstd_compress(level, value)?);
+            if zstd.len() <= gzip.len() { zstd } else { gzip }
+        }
+    };
+    Ok((framed.len() < value.len()).then_some(framed))
+}
+
+fn gzip(level: Option<i32>, value: &[u8]) -> Result<Vec<u8>, CacheError> {
+    let level = level.map_or(6, |level| level.clamp(0, 9) as u32);
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::new(level));
+    encoder.write_all(value).map_err(|error| CacheError::CompressionError(error.to_string()))?;
+    encoder.finish().map_err(|error| CacheError::CompressionError(error.to_string()))
+}
+
+fn zstd_compress(level: Option<i32>, value: &[u8]) -> Result<Vec<u8>, CacheError> {
+    zstd::bulk::compress(value, level.unwrap_or(3)).map_err(|error| CacheError::CompressionError(error.to_string()))
+}
+
+fn frame(tag: u8, original: &[u8], payload: Vec<u8>) -> Vec<u8> {
+    let mut framed = Vec::with_capacity(COMPRESSION_HEADER_LEN + payload.len());
+    framed.push(tag);
+    framed.extend_from_slice(&(original.len() as u64).to_le_bytes());
+    framed.extend(payload);
+    framed
+}
+
+/// 按头部记录的算法解压，并校验原始长度
+/// Decompress with the algorithm recorded in the header and check the original length
+fn decompress(framed: &[u8]) -> Result<Vec<u8>, CacheError> {
+    if framed.len() < COMPRESSION_HEADER_LEN {
+        return Err(CacheError::CompressionError("压缩头部不完整".to_string()));
+    }
+    let (header, payload) = framed.split_at(COMPRESSION_HEADER_LEN);
+    let original_len = u64::from_le_bytes(header[1..].try_into().expect("头部长度已校验")) as usize;
+    let value = match header[0] {
+        GZIP_TAG => {
+            let mut value = Vec::with_capacity(original_len);
+            flate2::read::GzDecoder::new(payload)
+                .read_to_end(&mut value)
+                .map_err(|error| CacheError::CompressionError(error.to_string()))?;
+            value
+        }
+        ZSTD_TAG => zstd::bulk::decompress(payload, original_len)
+            .map_err(|error| CacheError::CompressionError(error.to_string()))?,
+        tag => return Err(CacheError::CompressionError(format!("未知的压缩算法标记 {}", tag))),
+    };
+    if value.len() != original_len {
+        return Err(CacheError::CompressionError(format!("解压后 {} 字节，头部记录 {} 字节", value.len(), original_len)));
+    }
+    Ok(value)
+}
+
+/// LFU 每经过 `容量 × 该系数` 次写入和命中，把全部频率减半
+const LFU_AGING_FACTOR: usize = 8;
+
+/// 按策略和容量创建驱逐索引
+/// Create the eviction index of a policy with the given capacity
+fn new_tracker(eviction_policy: EvictionPolicy, capacity: usize) -> Box<dyn EvictionTracker> {
+    let capacity = capacity.max(1);
+    match eviction_policy {
+        EvictionPolicy::LRU => Box::new(RecencyTracker { order: RecencyList::default(), touch_on_access: true, stats: PolicyStatistics::default() }),
+        EvictionPolicy::FIFO => Box::new(RecencyTracker { order: RecencyList::default(), touch_on_access: false, stats: PolicyStatistics::default() }),
+        EvictionPolicy::LFU => Box::new(LfuTracker {
+            frequencies: HashMap::new(),
+            order: BTreeSet::new(),
+            tick: 0,
+            operations: 0,
+            aging_period: capacity * LFU_AGING_FACTOR,
+            stats: PolicyStatistics::default(),
+        }),
+        EvictionPolicy::ARC => Box::new(ArcTracker {
+            capacity,
+            target: 0,
+            recent: RecencyList::default(),
+            frequent: RecencyList::default(),
+            recent_ghosts: RecencyList::default(),
+            frequent_ghosts: RecencyList::default(),
+            pending: None,
+            stats: PolicyStatistics::default(),
+        }),
+        policy @ (EvictionPolicy::TTL | EvictionPolicy::Random) => {
+            Box::new(ScanTracker { policy, stats: PolicyStatistics::default() })
+        }
+    }
+}
+
+/// 按最近使用顺序排列的键，队首最旧
+/// Keys in recency order, oldest first
+#[derive(Debug, Default)]
+struct RecencyList {
+    ticks: HashMap<String, u64>,
+    order: BTreeMap<u64, String>,
+    next_tick: u64,
+}
+
+impl RecencyList {
+    /// 放到队尾（最新），已存在时移动
+    fn push_back(&mut self, key: &str) {
+        self.remove(key);
+        self.next_tick += 1;
+        self.ticks.insert(key.to_string(), self.next_tick);
+        self.order.insert(self.next_tick, key.to_string());
+    }
+
+    fn pop_front(&mut self) -> Option<String> {
+        let (_, key) = self.order.pop_first()?;
+        self.ticks.remove(&key);
+        Some(key)
+    }
+
+    fn remove(&mut self, key: &str) -> bool {
+        match self.ticks.remove(key) {
+            Some(tick) => self.order.remove(&tick).is_some(),
+            None => false,
+        }
+    }
+
+    fn contains(&self, key: &str) -> bool {
+        self.ticks.contains_key(key)
+    }
+
+    fn len(&self) -> usize {
+        self.ticks.len()
+    }
+}
+
+/// LRU（命中时移到队尾）和 FIFO（只按写入顺序）
+/// LRU (hits move to the back) and FIFO (insertion order only)
+#[derive(Debug)]
+struct RecencyTracker {
+    order: RecencyList,
+    touch_on_access: bool,
+    stats: PolicyStatistics,
+}
+
+impl EvictionTracker for RecencyTracker {
+    fn victim(&mut self, _entries: &HashMap<String, CacheEntry>) -> Option<String> {
+        let key = self.order.pop_front()?;
+        self.stats.evictions += 1;
+        Some(key)
+    }
+
+    fn insert(&mut self, key: &str) {
+        self.order.push_back(key);
+    }
+
+    fn access(&mut self, key: &str) {
+        if self.touch_on_access {
+            self.order.push_back(key);
+        }
+    }
+
+    fn remove(&mut self, key: &str) {
+        self.order.remove(key);
+    }
+
+    fn statistics(&self) -> PolicyStatistics {
+        self.stats
+    }
+}
+
+/// 带老化的 LFU：驱逐频率最低的键，频率相同时驱逐最久未使用的键
+/// LFU with aging: evicts the lowest frequency, breaking ties by least recent use
+#[derive(Debug)]
+struct LfuTracker {
+    /// 键到（频率，最后使用时刻）
+    frequencies: HashMap<String, (u64, u64)>,
+    order: BTreeSet<(u64, u64, String)>,
+    tick: u64,
+    operations: usize,
+    aging_period: usize,
+    stats: PolicyStatistics,
+}
+
+impl LfuTracker {
+    fn set(&mut self, key: &str, frequency: u64) {
+        if let Some((old_frequency, old_tick)) = self.frequencies.remove(key) {
+            self.order.remove(&(old_frequency, old_tick, key.to_string()));
+        }
+        self.tick += 1;
+        self.frequencies.insert(key.to_string(), (frequency, self.tick));
+        self.order.insert((frequency, self.tick, key.to_string()));
+        self.operations += 1;
+        if self.operations >= self.aging_period {
+            self.age();
+        }
+    }
+
+    /// 全部频率减半（至少为 1），保留最后使用时刻
+    fn age(&mut self) {
+        self.operations = 0;
+        self.stats.frequency_agings += 1;
+        self.order.clear();
+        for (key, (frequency, tick)) in &mut self.frequencies {
+            *frequency = (*frequency / 2).max(1);
+            self.order.insert((*frequency, *tick, key.clone()));
+        }
+    }
+}
+
+impl EvictionTracker for LfuTracker {
+    fn victim(&mut self, _entries: &HashMap<String, CacheEntry>) -> Option<String> {
+        let (_, _, key) = self.order.pop_first()?;
+        self.frequencies.remove(&key);
+        self.stats.evictions += 1;
+        Some(key)
+    }
+
+    fn insert(&mut self, key: &str) {
+        self.set(key, 1);
+    }
+
+    fn access(&mut self, key: &str) {
+        if let Some(&(frequency, _)) = self.frequencies.get(key) {
+            self.set(key, frequency + 1);
+        }
+    }
+
+    fn remove(&mut self, key: &str) {
+        if let Some((frequency, tick)) = self.frequencies.remove(key) {
+            self.order.remove(&(frequency, tick, key.to_string()));
+        }
+    }
+
+    fn restore(&mut self, key: &str, entry: &CacheEntry) {
+        self.set(key, entry.access_count + 1);
+    }
+
+    fn statistics(&self) -> PolicyStatistics {
+        self.stats
+    }
+}
+
+/// 自适应替换缓存（Megiddo & Modha）
+/// Adaptive Replacement Cache (Megiddo & Modha)
+///
+/// `recent`（T1）保存只被访问过一次的键，`frequent`（T2）保存至少命中过一次的键；两个幽灵列表（B1、B2）
+/// 记住最近从 T1、T2 驱逐的键。幽灵命中说明对应列表过小，据此调整 T1 的目标大小 `target`。
+/// `recent` (T1) holds keys seen once and `frequent` (T2) keys hit at least once; the ghost lists (B1, B2)
+/// remember keys recently evicted from T1 and T2. A ghost hit means that list was too small and moves `target`,
+/// the desired size of T1, accordingly.
+#[derive(Debug)]
+struct ArcTracker {
+    capacity: usize,
+    target: usize,
+    recent: RecencyList,
+    frequent: RecencyList,
+    recent_ghosts: RecencyList,
+    frequent_ghosts: RecencyList,
+    /// 正在写入的键是否命中了幽灵列表，以及是否是 B2
+    pending: Option<bool>,
+    stats: PolicyStatistics,
+}
+
+impl EvictionTracker for ArcTracker {
+    fn prepare(&mut self, key: &str) {
+        self.pending = if self.recent_ghosts.contains(key) {
+            let delta = (self.frequent_ghosts.len() / self.recent_ghosts.len()).max(1);
+            self.target = (self.target + delta).min(self.capacity);
+            self.recent_ghosts.remove(key);
+            Some(false)
+        } else if self.frequent_ghosts.contains(key) {
+            let delta = (self.recent_ghosts.len() / self.frequent_ghosts.len()).max(1);
+            self.target = self.target.saturating_sub(delta);
+            self.frequent_ghosts.remove(key);
+            Some(true)
+        } else {
+            None
+        };
+        if self.pending.is_some() {
+            self.stats.ghost_hits += 1;
+        }
+    }
+
+    fn victim(&mut self, _entries: &HashMap<String, CacheEntry>) -> Option<String> {
+        let from_frequent_ghost = self.pending == Some(true);
+        let recent_len = self.recent.len();
+        let take_recent = recent_len > 0
+            && (recent_len > self.target || (from_frequent_ghost && recent_len == self.target) || self.frequent.len() == 0);
+        let key = if take_recent {
+            let key = self.recent.pop_front()?;
+            self.recent_ghosts.push_back(&key);
+            key
+        } else {
+            let key = self.frequent.pop_front()?;
+            self.frequent_ghosts.push_back(&key);
+            key
+        };
+        self.stats.evictions += 1;
+        Some(key)
+    }
+
+    fn insert(&mut self, key: &str) {
+        match self.pending.take() {
+            Some(_) => self.frequent.push_back(key),
+            None => self.recent.push_back(key),
+        }
+        // 幽灵列表的长度：T1 + B1 不超过容量，四个列表合计不超过两倍容量
+        while self.recent.len() + self.recent_ghosts.len() > self.capacity && self.recent_ghosts.pop_front().is_some() {}
+        while self.recent.len() + self.frequent.len() + self.recent_ghosts.len() + self.frequent_ghosts.len() > 2 * self.capacity
+            && self.frequent_ghosts.pop_front().is_some()
+        {}
+    }
+
+    fn access(&mut self, key: &str) {
+        if self.recent.remove(key) || self.frequent.contains(key) {
+            self.frequent.push_back(key);
+        }
+    }
+
+    fn remove(&mut self, key: &str) {
+        if !self.recent.remove(key) {
+            self.frequent.remove(key);
+        }
+    }
+
+    fn restore(&mut self, key: &str, entry: &CacheEntry) {
+        if entry.access_count > 0 {
+            self.frequent.push_back(key);
+        } else {
+            self.recent.push_back(key);
+        }
+    }
+
+    fn statistics(&self) -> PolicyStatistics {
+        self.stats
+    }
+}
+
+/// 按条目内容选择的策略：TTL 驱逐最先过期的条目，Random 随机驱逐
+/// Policies choosing from the entries themselves: TTL evicts the entry expiring first, Random a random one
+#[derive(Debug)]
+struct ScanTracker {
+    policy: EvictionPolicy,
+    stats: PolicyStatistics,
+}
+
+impl EvictionTracker for ScanTracker {
+    fn victim(&mut self, entries: &HashMap<String, CacheEntry>) -> Option<String> {
+        let key = if self.policy == EvictionPolicy::TTL {
+            entries.iter().min_by_key(|(_, entry)| entry.expires_at).map(|(key, _)| key.clone())?
+        } else if entries.is_empty() {
+            return None;
+        } else {
+            entries.keys().nth(rand::random_range(0..entries.len())).cloned()?
+        };
+        self.stats.evictions += 1;
+        Some(key)
+    }
+
+    fn insert(&mut self, _key: &str) {}
+
+    fn access(&mut self, _key: &str) {}
+
+    fn remove(&mut self, _key: &str) {}
+
+    fn statistics(&self) -> PolicyStatistics {
+        self.stats
+    }
+}
+
+/// 性能优化器
+/// Performance Optimizer
+pub struct PerformanceOptimizer {
+    /// 优化策略
+    pub strategies: Vec<Box<dyn OptimizationStrategy>>,
+    /// 性能指标
+    pub metrics: Arc<Mutex<HashMap<String, f64>>>,
+    /// 配置
+    pub config: OptimizationConfig,
+    /// 自适应缓存控制器，`None` 表示未接入缓存
+    adaptive: Mutex<Option<AdaptiveController>>,
+    /// 后台采样任务
+    adaptation_task: Mutex<Option<tokio::task::JoinHandle<()>>>,
+}
+
+/// 自适应缓存调整规则
+/// Adaptive cache tuning rules
+///
+/// 连续 `consecutive_windows` 个采样窗口的命中率低于 `target_hit_rate` 时调整一次：
+/// 窗口内过期移除多于容量驱逐且 TTL 未到上限时按 `ttl_step` 延长 TTL，否则切换到 `policy_sequence` 中的下一个驱逐策略。
+/// After `consecutive_windows` sampling windows in a row below `target_hit_rate`, one adjustment is made: when
+/// expirations outnumber capacity evictions in the window and the TTL is below its bound, the TTL grows by
+/// `ttl_step`; otherwise the cache moves to the next eviction policy in `policy_sequence`.
+#[derive(Debug, Clone)]
+pub struct AdaptiveCachePolicy {
+    /// 目标命中率
+    pub target_hit_rate: f64,
+    /// 连续多少个窗口低于目标后调整
+    pub consecutive_windows: usize,
+    /// 依次尝试的驱逐策略
+    pub policy_sequence: Vec<EvictionPolicy>,
+    /// TTL 下限
+    pub min_ttl: Duration,
+    /// TTL 上限
+    pub max_ttl: Duration,
+    /// 每次延长 TTL 的倍数
+    pub ttl_step: f64,
+}
+
+impl Default for AdaptiveCachePolicy {
+    fn default() -> Self {
+        Self {
+            target_hit_rate: 0.8,
+            consecutive_windows: 3,
+            policy_sequence: vec![EvictionPolicy::LRU, EvictionPolicy::ARC, EvictionPolicy::LFU],
+            min_ttl: Duration::from_secs(60),
+            max_ttl: Duration::from_secs(3600),
+            ttl_step: 2.0,
+        }
+    }
+}
+
+/// 自适应控制器做出的一次调整
+/// An adjustment made by the adaptive controller
+#[derive(Debug, Clone, PartialEq)]
+pub struct AdaptationDecision {
+    /// 采样窗口序号，从 1 开始
+    pub window: u64,
+    /// 采取的动作
+    pub action: AdaptationAction,
+    /// 窗口内的命中率
+    pub hit_rate: f64,
+    /// 窗口内每次访问对应的容量驱逐数
+    pub churn: f64,
+    /// 采样时条目的平均年龄
+    pub mean_entry_age: Duration,
+    /// 调整原因
+    pub rationale: String,
+}
+
+/// 自适应调整的动作
+/// Adaptive adjustment action
+#[derive(Debug, Clone, PartialEq)]
+pub enum AdaptationAction {
+    /// 切换驱逐策略
+    SwitchPolicy {
+        /// 原策略
+        from: EvictionPolicy,
+        /// 新策略
+        to: EvictionPolicy,
+    },
+    /// 调整默认 TTL
+    AdjustTtl {
+        /// 原 TTL
+        from: Duration,
+        /// 新 TTL
+        to: Duration,
+    },
+    /// 命中率仍低于目标，但已没有可用的调整
+    Exhausted,
+    /// 手动固定驱逐策略，停止自动调整
+    Pinned(EvictionPolicy),
+    /// 解除固定，恢复自动调整
+    Unpinned,
+}
+
+/// 采样状态和审计日志
+struct AdaptiveController {
+    cache: Arc<IntelligentCacheManager>,
+    policy: AdaptiveCachePolicy,
+    /// 上一次采样时的累计计数：命中、未命中、驱逐、过期
+    last: (u64, u64, u64, u64),
+    window: u64,
+    low_windows: usize,
+    pinned: Option<EvictionPolicy>,
+    decisions: Vec<AdaptationDecision>,
+}
+
+/// 优化策略接口
+/// Optimization Strategy Interface
+pub trait OptimizationStrategy: Send + Sync {
+    /// 应用优化
+    fn optimize(&self, context: &OptimizationContext) -> Result<OptimizationResult, OptimizationError>;
+    /// 获取策略名称
+    fn get_name(&self) -> String;
+    /// 获取优先级
+    fn get_priority(&self) -> OptimizationPriority;
+}
+
+/// 优化上下文
+/// Optimization Context
+#[derive(Debug, Clone)]
+pub struct OptimizationContext {
+    /// 当前性能指标
+    pub current_metrics: HashMap<String, f64>,
+    /// 目标性能指标
+    pub target_metrics: HashMap<String, f64>,
+    /// 资源限制
+    pub resource_limits: ResourceLimits,
+    /// 工作负载特征
+    pub workload_characteristics: WorkloadCharacteristics,
+}
+
+/// 优化结果
+/// Optimization Result
+#[derive(Debug, Clone)]
+pub struct OptimizationResult {
+    /// 策略名称
+    pub strategy_name: String,
+    /// 优化建议
+    pub recommendations: Vec<OptimizationRecommendation>,
+    /// 预期改进
+    pub expected_improvement: f64,
+    /// 实施难度
+    pub implementation_difficulty: ImplementationDifficulty,
+}
+
+/// 优化建议
+/// Optimization Recommendation
+#[derive(Debug, Clone)]
+pub struct OptimizationRecommendation {
+    /// 建议类型
+    pub recommendation_type: RecommendationType,
+    /// 描述
+    pub description: String,
+    /// 预期收益
+    pub expected_benefit: f64,
+    /// 实施成本
+    pub implementation_cost: ImplementationCost,
+}
+
+/// 建议类型
+/// Recommendation Type
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum RecommendationType {
+    /// 内存优化
+    MemoryOptimization,
+    /// CPU 优化
+    CPUOptimization,
+    /// 网络优化
+    NetworkOptimization,
+    /// 缓存优化
+    CacheOptimization,
+    /// 算法优化
+    AlgorithmOptimization,
+}
+
+/// 实施成本
+/// Implementation Cost
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ImplementationCost {
+    /// 低成本
+    Low = 1,
+    /// 中等成本
+    Medium = 2,
+    /// 高成本
+    High = 3,
+    /// 极高成本
+    VeryHigh = 4,
+}
+
+/// 实施难度
+/// Implementation Difficulty
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ImplementationDifficulty {
+    /// 简单
+    Easy = 1,
+    /// 中等
+    Medium = 2,
+    /// 困难
+    Hard = 3,
+    /// 非常困难
+    VeryHard = 4,
+}
+
+/// 优化优先级
+/// Optimization Priority
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+pub enum OptimizationPriority {
+    /// 低优先级
+    Low = 1,
+    /// 中等优先级
+    Medium = 2,
+    /// 高优先级
+    High = 3,
+    /// 紧急优先级
+    Critical = 4,
+}
+
+/// 资源限制
+/// Resource Limits
+#[derive(Debug, Clone)]
+pub struct ResourceLimits {
+    /// 最大内存
+    pub max_memory: usize,
+    /// 最大 CPU
+    pub max_cpu: f64,
+    /// 最大网络带宽
+    pub max_network_bandwidth: usize,
+    /// 最大磁盘空间
+    pub max_disk_space: usize,
+}
+
+/// 工作负载特征
+/// Workload Characteristics
+#[derive(Debug, Clone)]
+pub struct WorkloadCharacteristics {
+    /// 请求模式
+    pub request_pattern: RequestPattern,
+    /// 数据访问模式
+    pub data_access_pattern: DataAccessPattern,
+    /// 计算复杂度
+    pub computational_complexity: ComputationalComplexity,
+    /// 并发级别
+    pub concurrency_level: ConcurrencyLevel,
+}
+
+/// 请求模式
+/// Request Pattern
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum RequestPattern {
+    /// 均匀分布
+    Uniform,
+    /// 突发性
+    Bursty,
+    /// 周期性
+    Periodic,
+    /// 随机
+    Random,
+}
+
+/// 数据访问模式
+/// Data Access Pattern
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum DataAccessPattern {
+    /// 顺序访问
+    Sequential,
+    /// 随机访问
+    Random,
+    /// 局部性访问
+    Locality,
+    /// 热点访问
+    Hotspot,
+}
+
+/// 计算复杂度
+/// Computational Complexity
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ComputationalComplexity {
+    /// 低复杂度
+    Low,
+    /// 中等复杂度
+    Medium,
+    /// 高复杂度
+    High,
+    /// 极高复杂度
+    VeryHigh,
+}
+
+/// 并发级别
+/// Concurrency Level
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ConcurrencyLevel {
+    /// 低并发
+    Low,
+    /// 中等并发
+    Medium,
+    /// 高并发
+    High,
+    /// 极高并发
+    VeryHigh,
+}
+
+/// 优化配置
+/// Optimization Configuration
+#[derive(Debug, Clone)]
+pub struct OptimizationConfig {
+    /// 优化间隔
+    pub optimization_interval: Duration,
+    /// 是否启用自动优化
+    pub auto_optimization_enabled: bool,
+    /// 优化阈值
+    pub optimization_threshold: f64,
+    /// 最大优化建议数
+    pub max_recommendations: usize,
+}
+
+impl PerformanceOptimizer {
+    /// 创建新的性能优化器
+    pub fn new(config: OptimizationConfig) -> Self {
+        Self {
+            strategies: Vec::new(),
+            metrics: Arc::new(Mutex::new(HashMap::new())),
+            config,
+            adaptive: Mutex::new(None),
+            adaptation_task: Mutex::new(None),
+        }
+    }
+
+    /// 接入缓存，按 `policy` 根据采样的命中率自动调整驱逐策略和 TTL
+    /// Attach a cache whose eviction policy and TTL are tuned from sampled hit rates under `policy`
+    pub fn with_adaptive_cache(self, cache: Arc<IntelligentCacheManager>, policy: AdaptiveCachePolicy) -> Self {
+        *self.adaptive.lock().unwrap() = Some(AdaptiveController {
+            cache,
+            policy,
+            last: (0, 0, 0, 0),
+            window: 0,
+            low_windows: 0,
+            pinned: None,
+            decisions: Vec::new(),
+        });
+        self
+    }
+
+    /// 采样接入的缓存并按规则调整，返回本窗口的调整；未接入缓存时返回 `None`
+    /// Sample the attached cache and adjust it by the rules, returning this window's adjustment; `None` when no
+    /// cache is attached
+    pub fn sample(&self) -> Option<AdaptationDecision> {
+        let cache = self.adaptive.lock().unwrap().as_ref()?.cache.clone();
+        let stats = cache.get_statistics();
+        self.observe(&stats, cache.mean_entry_age())
+    }
+
+    /// 用一次统计快照结束一个采样窗口，返回本窗口的调整
+    /// Close a sampling window with a statistics snapshot, returning this window's adjustment
+    ///
+    /// 窗口内的指标按与上一次快照的差值计算，并写入 `metrics`（`cache_hit_rate`、`cache_churn`、
+    /// `cache_mean_entry_age_secs`）。没有访问的窗口不计入连续低命中率的窗口数。
+    /// Window metrics are deltas against the previous snapshot and are also written to `metrics`
+    /// (`cache_hit_rate`, `cache_churn`, `cache_mean_entry_age_secs`). Windows without accesses do not count
+    /// towards the run of low windows.
+    pub fn observe(&self, stats: &CacheStatistics, mean_entry_age: Duration) -> Option<AdaptationDecision> {
+        let mut guard = self.adaptive.lock().unwrap();
+        let controller = guard.as_mut()?;
+        let (hits, misses, evictions, expirations) = controller.last;
+        controller.last = (stats.hits, stats.misses, stats.evictions, stats.expirations);
+        controller.window += 1;
+
+        let hits = stats.hits.saturating_sub(hits);
+        let accesses = hits + stats.misses.saturating_sub(misses);
+        let expirations = stats.expirations.saturating_sub(expirations);
+        let capacity_evictions = stats.evictions.saturating_sub(evictions).saturating_sub(expirations);
+        if accesses == 0 {
+            return None;
+        }
+        let hit_rate = hits as f64 / accesses as f64;
+        let churn = capacity_evictions as f64 / accesses as f64;
+        self.update_metrics(HashMap::from([
+            ("cache_hit_rate".to_string(), hit_rate),
+            ("cache_churn".to_string(), churn),
+            ("cache_mean_entry_age_secs".to_string(), mean_entry_age.as_secs_f64()),
+        ]));
+
+        if controller.pinned.is_some() {
+            return None;
+        }
+        if hit_rate >= controller.policy.target_hit_rate {
+            controller.low_windows = 0;
+            return None;
+        }
+        controller.low_windows += 1;
+        if controller.low_windows < controller.policy.consecutive_windows.max(1) {
+            return None;
+        }
+        controller.low_windows = 0;
+
+        let current = controller.cache.policy();
+        let rules = &controller.policy;
+        let ttl = current.default_ttl.max(rules.min_ttl).min(rules.max_ttl);
+        let longer_ttl = scale_ttl(ttl, rules.ttl_step, rules.max_ttl);
+        let next_policy = rules.policy_sequence.iter()
+            .position(|policy| *policy == current.eviction_policy)
+            .map_or(rules.policy_sequence.first(), |index| rules.policy_sequence.get(index + 1))
+            .copied();
+        let summary = format!(
+            "命中率 {:.2} 连续 {} 个窗口低于目标 {:.2}；窗口内过期 {} 次、容量驱逐 {} 次",
+            hit_rate, rules.consecutive_windows, rules.target_hit_rate, expirations, capacity_evictions,
+        );
+        let (action, rationale) = if expirations > capacity_evictions && longer_ttl > current.default_ttl {
+            controller.cache.set_policy(CachePolicy { default_ttl: longer_ttl, ..current.clone() });
+            let action = AdaptationAction::AdjustTtl { from: current.default_ttl, to: longer_ttl };
+            (action, format!("{}，未命中主要来自过期，延长 TTL", summary))
+        } else if let Some(next) = next_policy {
+            controller.cache.set_policy(CachePolicy { eviction_policy: next, ..current.clone() });
+            let action = AdaptationAction::SwitchPolicy { from: current.eviction_policy, to: next };
+            (action, format!("{}，切换驱逐策略", summary))
+        } else if longer_ttl > current.default_ttl {
+            controller.cache.set_policy(CachePolicy { default_ttl: longer_ttl, ..current.clone() });
+            let action = AdaptationAction::AdjustTtl { from: current.default_ttl, to: longer_ttl };
+            (action, format!("{}，已没有后续驱逐策略，延长 TTL", summary))
+        } else {
+            (AdaptationAction::Exhausted, format!("{}，驱逐策略和 TTL 都已没有调整余地", summary))
+        };
+        let decision = AdaptationDecision {
+            window: controller.window,
+            action,
+            hit_rate,
+            churn,
+            mean_entry_age,
+            rationale,
+        };
+        controller.decisions.push(decision.clone());
+        Some(decision)
+    }
+
+    /// 全部调整记录，按时间先后排列
+    /// Every adjustment made, oldest first
+    pub fn decisions(&self) -> Vec<AdaptationDecision> {
+        self.adaptive.lock().unwrap().as_ref().map(|controller| controller.decisions.clone()).unwrap_or_default()
+    }
+
+    /// 手动固定驱逐策略并停止自动调整，直到 [`unpin_policy`](Self::unpin_policy)
+    /// Pin the eviction policy and stop adapting until [`unpin_policy`](Self::unpin_policy)
+    pub fn pin_policy(&self, eviction_policy: EvictionPolicy) -> Result<(), OptimizationError> {
+        let mut guard = self.adaptive.lock().unwrap();
+        let controller = guard.as_mut()
+            .ok_or_else(|| OptimizationError::ConfigurationError("未接入自适应缓存".to_string()))?;
+        controller.cache.set_policy(CachePolicy { eviction_policy, ..controller.cache.policy() });
+        controller.pinned = Some(eviction_policy);
+        controller.low_windows = 0;
+        controller.decisions.push(AdaptationDecision {
+            window: controller.window,
+            action: AdaptationAction::Pinned(eviction_policy),
+            hit_rate: 0.0,
+            churn: 0.0,
+            mean_entry_age: Duration::ZERO,
+            rationale: format!("手动固定驱逐策略为 {:?}", eviction_policy),
+        });
+        Ok(())
+    }
+
+    /// 解除手动固定，恢复自动调整
+    /// Remove the manual pin and resume adapting
+    pub fn unpin_policy(&self) {
+        let mut guard = self.adaptive.lock().unwrap();
+        if let Some(controller) = guard.as_mut()
+            && controller.pinned.take().is_some()
+        {
+            controller.decisions.push(AdaptationDecision {
+                window: controller.window,
+                action: AdaptationAction::Unpinned,
+                hit_rate: 0.0,
+                churn: 0.0,
+                mean_entry_age: Duration::ZERO,
+                rationale: "解除手动固定，恢复自动调整".to_string(),
+            });
+        }
+    }
+
+    /// 启用自动优化时启动后台任务，每隔 `optimization_interval` 调用一次 [`sample`](Self::sample)；优化器释放后任务自行结束
+    /// With auto optimization enabled, spawn a background task calling [`sample`](Self::sample) every
+    /// `optimization_interval`; it ends once the optimizer is dropped
+    pub fn start_adaptation(self: &Arc<Self>) {
+        if !self.config.auto_optimization_enabled {
+            return;
+        }
+        let optimizer: Weak<Self> = Arc::downgrade(self);
+        let interval = self.config.optimization_interval.max(Duration::from_millis(1));
+        let handle = tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            // 第一次 tick 立即返回，跳过它以采满一个完整窗口
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                let Some(optimizer) = optimizer.upgrade() else {
+                    return;
+                };
+                optimizer.sample();
+            }
+        });
+        if let Ok(mut task) = self.adaptation_task.lock()
+            && let Some(previous) = task.replace(handle)
+        {
+            previous.abort();
+        }
+    }
+
+    /// 停止后台采样任务
+    /// Stop the background sampling task
+    pub fn stop_adaptation(&self) {
+        if let Ok(mut task) = self.adaptation_task.lock()
+            && let Some(handle) = task.take()
+        {
+            handle.abort();
+        }
+    }
+
+    /// 添加优化策略
+    pub fn add_strategy(&mut self, strategy: Box<dyn OptimizationStrategy>) {
+        self.strategies.push(strategy);
+    }
+
+    /// 执行优化分析
+    pub fn analyze(&self, context: &OptimizationContext) -> Result<Vec<OptimizationResult>, OptimizationError> {
+        let mut results = Vec::new();
+        
+        for strategy in &self.strategies {
+            match strategy.optimize(context) {
+                Ok(result) => results.push(result),
+                Err(e) => {
+                    log::warn!("优化策略 {} 执行失败: {:?}", strategy.get_name(), e);
+                }
+            }
+        }
+        
+        // 按优先级排序
+        results.sort_by_key(|r| r.implementation_difficulty);
+        
+        // 限制结果数量
+        results.truncate(self.config.max_recommendations);
+        
+        Ok(results)
+    }
+
+    /// 更新性能指标
+    pub fn update_metrics(&self, metrics: HashMap<String, f64>) {
+        let mut current_metrics = self.metrics.lock().unwrap();
+        for (key, value) in metrics {
+            current_metrics.insert(key, value);
+        }
+    }
+}
+
+/// 内存优化策略
+/// Memory Optimization Strategy
+#[derive(Debug)]
+pub struct MemoryOptimizationStrategy;
+
+impl OptimizationStrategy for MemoryOptimizationStrategy {
+    fn optimize(&self, _context: &OptimizationContext) -> Result<OptimizationResult, OptimizationError> {
+        let recommendations = vec![
+            OptimizationRecommendation {
+                recommendation_type: RecommendationType::MemoryOptimization,
+                description: "启用内存池管理".to_string(),
+                expected_benefit: 0.15,
+                implementation_cost: ImplementationCost::Medium,
+            },
+            OptimizationRecommendation {
+                recommendation_type: RecommendationType::MemoryOptimization,
+                description: "优化数据结构布局".to_string(),
+                expected_benefit: 0.10,
+                implementation_cost: ImplementationCost::High,
+            },
+        ];
+
+        Ok(OptimizationResult {
+            strategy_name: "Memory Optimization".to_string(),
+            recommendations,
+            expected_improvement: 0.25,
+            implementation_difficulty: ImplementationDifficulty::Medium,
+        })
+    }
+
+    fn get_name(&self) -> String {
+        "Memory Optimization".to_string()
+    }
+
+    fn get_priority(&self) -> OptimizationPriority {
+        OptimizationPriority::High
+    }
+}
+
+/// 缓存优化策略
+/// Cache Optimization Strategy
+#[derive(Debug)]
+pub struct CacheOptimizationStrategy;
+
+impl OptimizationStrategy for CacheOptimizationStrategy {
+    fn optimize(&self, _context: &OptimizationContext) -> Result<OptimizationResult, OptimizationError> {
+        let recommendations = vec![
+            OptimizationRecommendation {
+                recommendation_type: RecommendationType::CacheOptimization,
+                description: "调整缓存大小和策略".to_string(),
+                expected_benefit: 0.20,
+                implementation_cost: ImplementationCost::Low,
+            },
+            OptimizationRecommendation {
+                recommendation_type: RecommendationType::CacheOptimization,
+                description: "实现智能预取".to_string(),
+                expected_benefit: 0.12,
+                implementation_cost: ImplementationCost::Medium,
+            },
+        ];
+
+        Ok(OptimizationResult {
+            strategy_name: "Cache Optimization".to_string(),
+            recommendations,
+            expected_improvement: 0.32,
+            implementation_difficulty: ImplementationDifficulty::Easy,
+        })
+    }
+
+    fn get_name(&self) -> String {
+        "Cache Optimization".to_string()
+    }
+
+    fn get_priority(&self) -> OptimizationPriority {
+        OptimizationPriority::Medium
+    }
+}
+
+/// 错误类型定义
+/// Error Type Definitions
+
+#[derive(Debug, Clone, Error)]
+pub enum CacheError {
+    /// 存储错误
+    #[error("缓存存储错误: {0}")]
+    StorageError(String),
+    /// 配置错误
+    #[error("缓存配置错误: {0}")]
+    ConfigurationError(String),
+    /// 序列化错误
+    #[error("缓存序列化错误: {0}")]
+    SerializationError(String),
+    /// 压缩或解压失败
+    #[error("缓存压缩错误: {0}")]
+    CompressionError(String),
+    /// 负缓存中记录的加载错误，在 `error_ttl` 内不会重新加载
+    #[error("缓存的加载失败: {0}")]
+    CachedFailure(String),
+    /// 加载超过时限未完成
+    #[error("缓存加载超过 {0:?} 未完成")]
+    LoadTimedOut(Duration),
+    /// 加载者 panic 或被取消，加载没有完成
+    #[error("缓存加载中止")]
+    LoadAborted,
+    /// 存储后端出错
+    #[error("缓存后端错误: {0}")]
+    BackendError(String),
+    /// 单个值超过整个字节预算
+    #[error("缓存值 {size} 字节，超过字节预算 {budget}")]
+    ValueTooLarge {
+        /// 值的字节数
+        size: usize,
+        /// 字节预算
+        budget: usize,
+    },
+}
+
+#[derive(Debug, Error)]
+pub enum OptimizationError {
+    /// 策略错误
+    #[error("优化策略错误: {0}")]
+    StrategyError(String),
+    /// 分析错误
+    #[error("性能分析错误: {0}")]
+    AnalysisError(String),
+    /// 配置错误
+    #[error("优化配置错误: {0}")]
+    ConfigurationError(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cache(eviction_policy: EvictionPolicy, max_size: usize) -> IntelligentCacheManager {
+        IntelligentCacheManager::new(CacheConfig { default_max_size: max_size, shard_count: 1, ..CacheConfig::default() })
+            .with_policy(policy(eviction_policy, max_size))
+    }
+
+    fn policy(eviction_policy: EvictionPolicy, max_size: usize) -> CachePolicy {
+        CachePolicy {
+            name: format!("{:?}", eviction_policy),
+            max_size,
+            max_bytes: None,
+            default_ttl: Duration::from_secs(300),
+            eviction_policy,
+            compression_policy: CompressionPolicy::None,
+            compression_threshold: 1024,
+            compression_level: None,
+            refresh_on_read: false,
+        }
+    }
+
+    /// 读取键，未命中时写入，返回是否命中
+    fn touch(cache: &IntelligentCacheManager, key: &str) -> bool {
+        if cache.get(key).is_some() {
+            return true;
+        }
+        cache.set(key.to_string(), key.as_bytes().to_vec(), None, None).unwrap();
+        false
+    }
+
+    fn contains(cache: &IntelligentCacheManager, key: &str) -> bool {
+        entries(cache).contains_key(key)
+    }
+
+    /// 全部分片中的条目
+    fn entries(cache: &IntelligentCacheManager) -> HashMap<String, CacheEntry> {
+        cache.shards.iter()
+            .flat_map(|shard| shard.storage.read().unwrap().clone())
+            .collect()
+    }
+
+    /// 每轮访问两遍热点键，其间穿插只访问一次的顺序扫描，返回热点键的命中率
+    fn scan_workload(eviction_policy: EvictionPolicy) -> f64 {
+        let cache = cache(eviction_policy, 100);
+        let (mut hits, mut lookups) = (0, 0);
+        for round in 0..30 {
+            for hot in (0..60).chain(0..60) {
+                lookups += 1;
+                hits += usize::from(touch(&cache, &format!("hot-{}", hot)));
+            }
+            for scan in 0..150 {
+                touch(&cache, &format!("scan-{}-{}", round, scan));
+            }
+        }
+        hits as f64 / lookups as f64
+    }
+
+    #[test]
+    fn test_every_policy_honors_capacity_and_accounting() {
+        for eviction_policy in [
+            EvictionPolicy::LRU,
+            EvictionPolicy::LFU,
+            EvictionPolicy::ARC,
+            EvictionPolicy::FIFO,
+            EvictionPolicy::TTL,
+            EvictionPolicy::Random,
+        ] {
+            let cache = cache(eviction_policy, 8);
+            for key in 0..20 {
+                touch(&cache, &format!("key-{}", key % 12));
+            }
+            cache.set("key-0".to_string(), vec![0; 10], None, None).unwrap();
+
+            let stats = cache.get_statistics();
+            let storage = entries(&cache);
+            assert_eq!(storage.len(), 8, "{:?}", eviction_policy);
+            assert_eq!(stats.entry_count, 8, "{:?}", eviction_policy);
+            assert_eq!(stats.total_size, storage.values().map(|entry| entry.value.len()).sum::<usize>());
+            assert_eq!(stats.policy_statistics[&eviction_policy].evictions, stats.evictions);
+        }
+    }
+
+    #[test]
+    fn test_arc_beats_lru_on_scans() {
+        let lru = scan_workload(EvictionPolicy::LRU);
+        let arc = scan_workload(EvictionPolicy::ARC);
+        // 每轮扫描都比容量大，LRU 每轮都会丢掉全部热点键，只有每轮第二遍命中
+        assert_eq!(lru, 0.5);
+        assert!(arc > 0.9, "ARC hot hit rate {:.2}", arc);
+    }
+
+    #[test]
+    fn test_arc_counts_ghost_hits() {
+        // a 命中后进入频繁列表；c 挤出只访问过一次的 b，b 随即在幽灵列表中命中
+        let cache = cache(EvictionPolicy::ARC, 2);
+        for key in ["a", "a", "b", "c", "b"] {
+            touch(&cache, key);
+        }
+        let stats = cache.get_statistics().policy_statistics[&EvictionPolicy::ARC];
+        assert_eq!(stats.ghost_hits, 1);
+        assert_eq!(stats.evictions, 2);
+    }
+
+    #[test]
+    fn test_lfu_keeps_hot_key_through_one_shot_burst() {
+        for (eviction_policy, survives) in [(EvictionPolicy::LFU, true), (EvictionPolicy::LRU, false)] {
+            let cache = cache(eviction_policy, 10);
+            for _ in 0..5 {
+                touch(&cache, "hot");
+            }
+            for burst in 0..100 {
+                touch(&cache, &format!("burst-{}", burst));
+            }
+            assert_eq!(contains(&cache, "hot"), survives, "{:?}", eviction_policy);
+        }
+    }
+
+    #[test]
+    fn test_lfu_aging_retires_stale_hot_key() {
+        let cache = cache(EvictionPolicy::LFU, 3);
+        for _ in 0..30 {
+            touch(&cache, "stale");
+        }
+        // 三个新键争夺剩下的两个位置；老化使过去的热点最终被淘汰
+        let mut rounds = 0;
+        while contains(&cache, "stale") {
+            for key in ["a", "b", "c"] {
+                touch(&cache, key);
+            }
+            rounds += 1;
+            assert!(rounds < 100, "stale key was never aged out");
+        }
+        for key in ["a", "b", "c"] {
+            touch(&cache, key);
+        }
+        assert!(["a", "b", "c"].iter().all(|key| touch(&cache, key)));
+        assert!(cache.get_statistics().policy_statistics[&EvictionPolicy::LFU].frequency_agings > 0);
+    }
+
+    #[test]
+    fn test_switching_policy_migrates_entries() {
+        let cache = cache(EvictionPolicy::LRU, 4);
+        for key in ["a", "b", "c", "d", "e"] {
+            touch(&cache, key);
+        }
+        for _ in 0..3 {
+            touch(&cache, "b");
+        }
+
+        // 切到 LFU 后条目保留，访问次数成为初始频率；容量缩小时立即按 LFU 驱逐
+        cache.set_policy(policy(EvictionPolicy::LFU, 2));
+        assert_eq!(cache.policy().eviction_policy, EvictionPolicy::LFU);
+        assert!(contains(&cache, "b"));
+        assert_eq!(cache.len(), 2);
+        touch(&cache, "f");
+        assert!(contains(&cache, "b"));
+
+        let stats = cache.get_statistics();
+        assert_eq!(stats.eviction_policy, EvictionPolicy::LFU);
+        assert_eq!(stats.policy_statistics[&EvictionPolicy::LRU].evictions, 1);
+        assert_eq!(stats.policy_statistics[&EvictionPolicy::LFU].evictions, 3);
+        assert_eq!(stats.entry_count, 2);
+    }
+
+    fn byte_cache(eviction_policy: EvictionPolicy, max_bytes: usize) -> IntelligentCacheManager {
+        let cache = cache(eviction_policy, 1000);
+        cache.set_policy(CachePolicy { max_bytes: Some(max_bytes), ..policy(eviction_policy, 1000) });
+        cache
+    }
+
+    fn keys(cache: &IntelligentCacheManager) -> Vec<String> {
+        let mut keys: Vec<String> = entries(cache).into_keys().collect();
+        keys.sort();
+        keys
+    }
+
+    #[test]
+    fn test_byte_budget_evicts_until_new_value_fits() {
+        let cache = byte_cache(EvictionPolicy::LRU, 100);
+        for (key, size) in [("a", 40), ("b", 30), ("c", 20)] {
+            cache.set(key.to_string(), vec![0; size], None, None).unwrap();
+        }
+        touch(&cache, "a");
+
+        // a 刚被访问，按 LRU 先驱逐 b；腾出 30 字节后 d 就放得下
+        cache.set("d".to_string(), vec![0; 40], None, None).unwrap();
+        assert_eq!(keys(&cache), ["a", "c", "d"]);
+        // 再按 c、a 的顺序驱逐，刚好腾出 60 字节后停止
+        cache.set("e".to_string(), vec![0; 60], None, None).unwrap();
+        assert_eq!(keys(&cache), ["d", "e"]);
+
+        let stats = cache.get_statistics();
+        assert_eq!((stats.total_size, stats.peak_size, stats.entry_count), (100, 100, 2));
+        assert_eq!(stats.evictions, 3);
+    }
+
+    #[test]
+    fn test_value_larger_than_budget_is_rejected() {
+        let cache = byte_cache(EvictionPolicy::LFU, 100);
+        cache.set("small".to_string(), vec![0; 60], None, None).unwrap();
+
+        let error = cache.set("huge".to_string(), vec![0; 101], None, None).unwrap_err();
+        assert!(matches!(error, CacheError::ValueTooLarge { size: 101, budget: 100 }));
+        assert_eq!(keys(&cache), ["small"]);
+        assert_eq!(cache.get_statistics().total_size, 60);
+
+        // 覆盖已有的键时按新值大小腾出空间，不会驱逐自身
+        cache.set("other".to_string(), vec![0; 30], None, None).unwrap();
+        cache.set("small".to_string(), vec![0; 80], None, None).unwrap();
+        assert_eq!(keys(&cache), ["small"]);
+        assert_eq!(cache.get_statistics().total_size, 80);
+    }
+
+    /// 计入键长度和固定开销的计重方式
+    #[derive(Debug)]
+    struct OverheadWeigher;
+
+    impl Weigher for OverheadWeigher {
+        fn weigh(&self, key: &str, value: &[u8]) -> usize {
+            key.len() + value.len() + 16
+        }
+    }
+
+    #[test]
+    fn test_custom_weigher_drives_accounting() {
+        let cache = byte_cache(EvictionPolicy::FIFO, 100).with_weigher(OverheadWeigher);
+        cache.set("abcd".to_string(), vec![0; 20], None, None).unwrap();
+        cache.set("ef".to_string(), vec![0; 10], None, None).unwrap();
+        assert_eq!(cache.get_statistics().total_size, 40 + 28);
+        assert_eq!(entries(&cache)["ef"].weight, 28);
+
+        // 需要 58 字节，FIFO 先驱逐最早写入的 abcd
+        cache.set("gh".to_string(), vec![0; 40], None, None).unwrap();
+        assert_eq!(keys(&cache), ["ef", "gh"]);
+        assert_eq!(cache.get_statistics().total_size, 28 + 58);
+    }
+
+    #[test]
+    fn test_concurrent_inserts_stay_within_budget() {
+        let cache = Arc::new(byte_cache(EvictionPolicy::ARC, 4096));
+        let threads: Vec<_> = (0..8)
+            .map(|thread| {
+                let cache = Arc::clone(&cache);
+                std::thread::spawn(move || {
+                    for i in 0..500 {
+                        let size = [16, 100, 700, 2000][(thread + i) % 4];
+                        cache.set(format!("{}-{}", thread, i % 50), vec![0; size], None, None).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        let stats = cache.get_statistics();
+        let stored: usize = entries(&cache).values().map(|entry| entry.weight).sum();
+        assert_eq!(stats.total_size, stored);
+        assert!(stats.peak_size <= 4096, "peak {} over budget", stats.peak_size);
+    }
+
+    fn compressing_cache(compression_policy: CompressionPolicy, threshold: usize) -> IntelligentCacheManager {
+        let mut cache = cache(EvictionPolicy::LRU, 100);
+        cache.config.compression_enabled = true;
+        cache.with_policy(CachePolicy {
+            compression_policy,
+            compression_threshold: threshold,
+            max_bytes: Some(64 * 1024),
+            ..policy(EvictionPolicy::LRU, 100)
+        })
+    }
+
+    fn stored(cache: &IntelligentCacheManager, key: &str) -> CacheEntry {
+        entries(cache)[key].clone()
+    }
+
+    #[test]
+    fn test_compressible_text_round_trips() {
+        let text = "wasm module cache entry; ".repeat(400).into_bytes();
+        for (compression_policy, tag) in [
+            (CompressionPolicy::Gzip, GZIP_TAG),
+            (CompressionPolicy::Zstd, ZSTD_TAG),
+            (CompressionPolicy::Adaptive, ZSTD_TAG),
+        ] {
+            let cache = compressing_cache(compression_policy, 256);
+            cache.set("text".to_string(), text.clone(), None, None).unwrap();
+
+            let entry = stored(&cache, "text");
+            assert!(entry.compressed, "{:?}", compression_policy);
+            assert_eq!(entry.value[0], tag);
+            assert!(entry.value.len() < text.len() / 10);
+            assert_eq!(cache.get("text"), Some(text.clone()));
+
+            // 字节预算和统计都按压缩后的大小计算
+            let stats = cache.get_statistics();
+            assert_eq!(stats.total_size, entry.value.len());
+            assert_eq!(stats.bytes_saved, (text.len() - entry.value.len()) as u64);
+            assert_eq!(stats.compressed_writes, 1);
+            assert!((stats.avg_compression_ratio - entry.value.len() as f64 / text.len() as f64).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_incompressible_values_are_stored_raw() {
+        let cache = compressing_cache(CompressionPolicy::Zstd, 256);
+        let noise: Vec<u8> = (0..4096).map(|_| rand::random::<u8>()).collect();
+        cache.set("noise".to_string(), noise.clone(), None, None).unwrap();
+
+        let entry = stored(&cache, "noise");
+        assert!(!entry.compressed);
+        assert_eq!(entry.value, noise);
+        assert_eq!(cache.get("noise"), Some(noise));
+        let stats = cache.get_statistics();
+        assert_eq!((stats.compressed_writes, stats.bytes_saved, stats.total_size), (0, 0, 4096));
+    }
+
+    #[test]
+    fn test_compression_threshold_boundary() {
+        let cache = compressing_cache(CompressionPolicy::Gzip, 512);
+        cache.set("at".to_string(), vec![b'a'; 512], None, None).unwrap();
+        cache.set("above".to_string(), vec![b'a'; 513], None, None).unwrap();
+        assert!(!stored(&cache, "at").compressed);
+        assert!(stored(&cache, "above").compressed);
+        assert_eq!(cache.get("above"), Some(vec![b'a'; 513]));
+
+        // 全局开关关闭时不压缩
+        let mut disabled = compressing_cache(CompressionPolicy::Gzip, 512);
+        disabled.config.compression_enabled = false;
+        disabled.set("above".to_string(), vec![b'a'; 513], None, None).unwrap();
+        assert!(!stored(&disabled, "above").compressed);
+    }
+
+    #[test]
+    fn test_undecompressible_entry_is_a_miss_and_dropped() {
+        let cache = compressing_cache(CompressionPolicy::Zstd, 256);
+        cache.set("text".to_string(), vec![b'a'; 4096], None, None).unwrap();
+        if let Some(entry) = cache.shards[0].storage.write().unwrap().get_mut("text") {
+            entry.value[0] = 7;
+        }
+
+        assert_eq!(cache.get("text"), None);
+        assert!(!contains(&cache, "text"));
+        let stats = cache.get_statistics();
+        assert_eq!((stats.hits, stats.misses, stats.entry_count, stats.total_size), (0, 1, 0, 0));
+        assert!(cache.shards[0].eviction.lock().unwrap().expiry.is_empty());
+    }
+
+    #[test]
+    fn test_failed_compression_stores_value_raw() {
+        let cache = compressing_cache(CompressionPolicy::LZ4, 256);
+        cache.set("text".to_string(), vec![b'a'; 4096], None, None).unwrap();
+        cache.set("small".to_string(), vec![b'a'; 16], None, None).unwrap();
+        assert_eq!(keys(&cache), ["small", "text"]);
+        assert!(!entries(&cache)["text"].compressed);
+        assert_eq!(cache.get("text"), Some(vec![b'a'; 4096]));
+        assert_eq!(cache.get_statistics().compressed_writes, 0);
+    }
+
+    #[test]
+    fn test_corrupt_compression_header_is_rejected() {
+        let framed = frame(ZSTD_TAG, b"hello", zstd_compress(None, b"hello").unwrap());
+        assert_eq!(decompress(&framed).unwrap(), b"hello");
+        let mut wrong_length = framed.clone();
+        wrong_length[1] = 9;
+        assert!(matches!(decompress(&wrong_length), Err(CacheError::CompressionError(_))));
+        assert!(matches!(decompress(&[7; 12]), Err(CacheError::CompressionError(_))));
+        assert!(matches!(decompress(&framed[..4]), Err(CacheError::CompressionError(_))));
+    }
+
+    #[derive(Debug)]
+    struct ManualClock {
+        start: Instant,
+        offset: Mutex<Duration>,
+    }
+
+    impl ManualClock {
+        fn new() -> Arc<Self> {
+            Arc::new(Self { start: Instant::now(), offset: Mutex::new(Duration::ZERO) })
+        }
+
+        fn advance(&self, by: Duration) {
+            *self.offset.lock().unwrap() += by;
+        }
+    }
+
+    impl CacheClock for ManualClock {
+        fn now(&self) -> Instant {
+            self.start + *self.offset.lock().unwrap()
+        }
+    }
+
+    fn expiring_cache(clock: &Arc<ManualClock>, refresh_on_read: bool) -> IntelligentCacheManager {
+        cache(EvictionPolicy::LRU, 100)
+            .with_clock(clock.clone())
+            .with_policy(CachePolicy {
+                default_ttl: Duration::from_secs(10),
+                refresh_on_read,
+                max_bytes: Some(100),
+                ..policy(EvictionPolicy::LRU, 100)
+            })
+    }
+
+    #[test]
+    fn test_expired_entries_are_misses_on_read() {
+        let clock = ManualClock::new();
+        let cache = expiring_cache(&clock, false);
+        cache.set("default".to_string(), b"v".to_vec(), None, None).unwrap();
+        cache.set("short".to_string(), b"v".to_vec(), Some(Duration::from_secs(2)), None).unwrap();
+
+        clock.advance(Duration::from_secs(2));
+        assert_eq!(cache.get("short"), None);
+        assert_eq!(cache.get("default"), Some(b"v".to_vec()));
+        clock.advance(Duration::from_secs(8));
+        assert_eq!(cache.get("default"), None);
+
+        let stats = cache.get_statistics();
+        assert_eq!((stats.hits, stats.misses, stats.expirations), (1, 2, 2));
+        assert_eq!((stats.entry_count, stats.total_size), (0, 0));
+    }
+
+    #[test]
+    fn test_refresh_on_read_slides_expiry() {
+        for (refresh_on_read, alive) in [(true, true), (false, false)] {
+            let clock = ManualClock::new();
+            let cache = expiring_cache(&clock, refresh_on_read);
+            cache.set("session".to_string(), b"v".to_vec(), None, None).unwrap();
+            for _ in 0..3 {
+                clock.advance(Duration::from_secs(6));
+                if cache.get("session").is_none() {
+                    break;
+                }
+            }
+            assert_eq!(contains(&cache, "session"), alive, "refresh_on_read: {}", refresh_on_read);
+        }
+    }
+
+    #[test]
+    fn test_expired_entries_do_not_count_against_budget() {
+        let clock = ManualClock::new();
+        let cache = expiring_cache(&clock, false);
+        cache.set("stale".to_string(), vec![0; 60], Some(Duration::from_secs(1)), None).unwrap();
+        cache.set("fresh".to_string(), vec![0; 30], None, None).unwrap();
+        clock.advance(Duration::from_secs(1));
+
+        // 过期条目先被清掉，未过期的 fresh 不会被驱逐
+        cache.set("new".to_string(), vec![0; 60], None, None).unwrap();
+        assert_eq!(keys(&cache), ["fresh", "new"]);
+        let stats = cache.get_statistics();
+        assert_eq!((stats.expirations, stats.evictions, stats.total_size), (1, 1, 90));
+    }
+
+    #[test]
+    fn test_sweep_removes_in_bounded_batches() {
+        let clock = ManualClock::new();
+        let cache = expiring_cache(&clock, false);
+        for key in 0..25 {
+            cache.set(format!("key-{}", key), vec![0], Some(Duration::from_secs(1)), None).unwrap();
+        }
+        cache.set("kept".to_string(), vec![0], None, None).unwrap();
+        clock.advance(Duration::from_secs(1));
+
+        assert_eq!(cache.sweep_expired(10), 10);
+        assert_eq!(cache.sweep_expired(10), 10);
+        assert_eq!(cache.sweep_expired(10), 5);
+        assert_eq!(cache.sweep_expired(10), 0);
+        assert_eq!(keys(&cache), ["kept"]);
+    }
+
+    #[test]
+    fn test_expiry_index_tracks_overwrites_refreshes_and_removals() {
+        let clock = ManualClock::new();
+        let cache = expiring_cache(&clock, true);
+        let index_len = || cache.shards[0].eviction.lock().unwrap().expiry.len();
+        cache.set("overwritten".to_string(), vec![0], Some(Duration::from_secs(1)), None).unwrap();
+        cache.set("overwritten".to_string(), vec![0], Some(Duration::from_secs(5)), None).unwrap();
+        cache.set("refreshed".to_string(), vec![0], Some(Duration::from_secs(2)), None).unwrap();
+        cache.set("removed".to_string(), vec![0], Some(Duration::from_secs(1)), None).unwrap();
+        cache.set("expiring".to_string(), vec![0], Some(Duration::from_secs(2)), None).unwrap();
+        assert!(cache.remove("removed"));
+        assert_eq!(index_len(), 3);
+
+        clock.advance(Duration::from_secs(1));
+        assert!(cache.get("refreshed").is_some());
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(cache.sweep_expired(usize::MAX), 1);
+        assert_eq!(keys(&cache), ["overwritten", "refreshed"]);
+        assert_eq!(index_len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_background_sweeper_removes_expired_entries() {
+        let clock = ManualClock::new();
+        let mut cache = expiring_cache(&clock, false);
+        cache.config.cleanup_interval = Duration::from_millis(5);
+        cache.config.sweep_batch_size = 3;
+        let cache = Arc::new(cache);
+        for key in 0..10 {
+            cache.set(format!("key-{}", key), vec![0], Some(Duration::from_secs(1)), None).unwrap();
+        }
+        cache.set("kept".to_string(), vec![0], None, None).unwrap();
+        cache.start_sweeper();
+
+        clock.advance(Duration::from_secs(1));
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while cache.len() > 1 && Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        assert_eq!(keys(&cache), ["kept"]);
+        assert_eq!(cache.get_statistics().expirations, 10);
+        cache.stop_sweeper();
+    }
+
+    /// 记录操作顺序、可切换为失败的后端
+    #[derive(Debug, Default)]
+    struct RecordingBackend {
+        inner: MemoryBackend,
+        log: Mutex<Vec<String>>,
+        failing: std::sync::atomic::AtomicBool,
+    }
+
+    impl RecordingBackend {
+        fn record(&self, op: String) -> Result<(), CacheError> {
+            if self.failing.load(std::sync::atomic::Ordering::SeqCst) {
+                return Err(CacheError::BackendError(format!("{} 失败", op)));
+            }
+            self.log.lock().unwrap().push(op);
+            Ok(())
+        }
+
+        fn fail(&self, failing: bool) {
+            self.failing.store(failing, std::sync::atomic::Ordering::SeqCst);
+        }
+
+        fn log(&self) -> Vec<String> {
+            self.log.lock().unwrap().clone()
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl CacheBackend for Arc<RecordingBackend> {
+        async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, CacheError> {
+            self.record(format!("get {}", key))?;
+            self.inner.get(key).await
+        }
+
+        async fn put(&self, key: &str, value: &[u8], ttl: Option<Duration>) -> Result<(), CacheError> {
+            self.record(format!("put {}={}", key, String::from_utf8_lossy(value)))?;
+            self.inner.put(key, value, ttl).await
+        }
+
+        async fn delete(&self, key: &str) -> Result<bool, CacheError> {
+            self.record(format!("delete {}", key))?;
+            self.inner.delete(key).await
+        }
+
+        async fn scan_prefix(&self, prefix: &str) -> Result<Vec<String>, CacheError> {
+            self.record(format!("scan {}", prefix))?;
+            self.inner.scan_prefix(prefix).await
+        }
+    }
+
+    fn backed_cache(write_mode: WriteMode) -> (IntelligentCacheManager, Arc<RecordingBackend>) {
+        let backend = Arc::new(RecordingBackend::default());
+        let cache = cache(EvictionPolicy::LRU, 2)
+            .with_backend(Box::new(backend.clone()))
+            .with_write_mode(write_mode);
+        (cache, backend)
+    }
+
+    #[tokio::test]
+    async fn test_write_through_reaches_backend_in_order() {
+        let (cache, backend) = backed_cache(WriteMode::WriteThrough);
+        cache.store("a".to_string(), b"1".to_vec(), None, None).await.unwrap();
+        cache.store("b".to_string(), b"2".to_vec(), None, None).await.unwrap();
+        cache.store("a".to_string(), b"3".to_vec(), None, None).await.unwrap();
+        assert!(cache.invalidate("b").await);
+
+        assert_eq!(backend.log(), ["put a=1", "put b=2", "put a=3", "delete b"]);
+        assert_eq!(cache.get("a"), Some(b"3".to_vec()));
+        assert_eq!(backend.inner.get("a").await.unwrap(), Some(b"3".to_vec()));
+        assert_eq!(backend.inner.get("b").await.unwrap(), None);
+        assert_eq!(cache.pending_writes(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_write_back_queues_until_flush() {
+        let (cache, backend) = backed_cache(WriteMode::WriteBack);
+        cache.store("a".to_string(), b"1".to_vec(), None, None).await.unwrap();
+        cache.store("b".to_string(), b"2".to_vec(), None, None).await.unwrap();
+        cache.store("a".to_string(), b"3".to_vec(), None, None).await.unwrap();
+        cache.invalidate("b").await;
+        assert!(backend.log().is_empty());
+        assert_eq!(cache.pending_writes(), 2);
+
+        assert_eq!(cache.flush().await.unwrap(), 2);
+        assert_eq!(backend.log(), ["put a=3", "delete b"]);
+        assert_eq!(cache.pending_writes(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_failed_flush_keeps_remaining_writes_queued() {
+        let (cache, backend) = backed_cache(WriteMode::WriteBack);
+        cache.store("a".to_string(), b"1".to_vec(), None, None).await.unwrap();
+        cache.store("b".to_string(), b"2".to_vec(), None, None).await.unwrap();
+        backend.fail(true);
+        assert!(matches!(cache.flush().await, Err(CacheError::BackendError(_))));
+        assert_eq!(cache.pending_writes(), 2);
+
+        backend.fail(false);
+        assert_eq!(cache.flush().await.unwrap(), 2);
+        assert_eq!(backend.log(), ["put a=1", "put b=2"]);
+        assert_eq!(cache.get_statistics().backend_errors, 1);
+    }
+
+    #[tokio::test]
+    async fn test_backend_errors_fall_back_to_local() {
+        let (cache, backend) = backed_cache(WriteMode::WriteThrough);
+        backend.fail(true);
+        cache.store("a".to_string(), b"1".to_vec(), None, None).await.unwrap();
+        assert_eq!(cache.fetch("a").await, Some(b"1".to_vec()));
+        assert_eq!(cache.fetch("missing").await, None);
+        assert_eq!(cache.get_statistics().backend_errors, 2);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_populates_local_from_backend() {
+        let (cache, backend) = backed_cache(WriteMode::WriteThrough);
+        backend.inner.put("remote", b"value", None).await.unwrap();
+
+        assert_eq!(cache.fetch("remote").await, Some(b"value".to_vec()));
+        assert_eq!(cache.get("remote"), Some(b"value".to_vec()));
+        assert_eq!(cache.fetch("remote").await, Some(b"value".to_vec()));
+        assert_eq!(backend.log(), ["get remote"]);
+        assert_eq!(cache.get_statistics().backend_hits, 1);
+    }
+
+    #[tokio::test]
+    async fn test_invalidate_prefix_covers_local_and_backend() {
+        let (cache, backend) = backed_cache(WriteMode::WriteThrough);
+        backend.inner.put("user:1", b"x", None).await.unwrap();
+        backend.inner.put("order:1", b"x", None).await.unwrap();
+        cache.store("user:2".to_string(), b"y".to_vec(), None, None).await.unwrap();
+
+        assert_eq!(cache.invalidate_prefix("user:").await.unwrap(), 2);
+        assert_eq!(backend.inner.scan_prefix("").await.unwrap(), ["order:1"]);
+        assert!(keys(&cache).is_empty());
+    }
+
+    /// 进程内的最小 RESP 服务，支持 GET、SET、DEL 和 SCAN
+    async fn resp_server() -> String {
+        use tokio::io::AsyncWriteExt;
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        tokio::spawn(async move {
+            let store: Arc<Mutex<BTreeMap<String, Vec<u8>>>> = Arc::default();
+            while let Ok((stream, _)) = listener.accept().await {
+                let store = store.clone();
+                tokio::spawn(async move {
+                    let mut stream = tokio::io::BufStream::new(stream);
+                    while let Ok(RespValue::Array(Some(args))) = read_resp(&mut stream).await {
+                        let args: Vec<Vec<u8>> = args.into_iter()
+                            .map(|arg| match arg {
+                                RespValue::Bulk(Some(arg)) => arg,
+                                other => panic!("unexpected argument {:?}", other),
+                            })
+                            .collect();
+                        let reply = resp_reply(&mut store.lock().unwrap(), &args);
+                        if stream.write_all(&reply).await.is_err() || stream.flush().await.is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+        });
+        address
+    }
+
+    fn resp_reply(store: &mut BTreeMap<String, Vec<u8>>, args: &[Vec<u8>]) -> Vec<u8> {
+        let key = String::from_utf8_lossy(&args[1]).into_owned();
+        match args[0].as_slice() {
+            b"GET" => match store.get(&key) {
+                Some(value) => {
+                    let mut reply = format!("${}\r\n", value.len()).into_bytes();
+                    reply.extend_from_slice(value);
+                    reply.extend_from_slice(b"\r\n");
+                    reply
+                }
+                None => b"$-1\r\n".to_vec(),
+            },
+            b"SET" => {
+                store.insert(key, args[2].clone());
+                b"+OK\r\n".to_vec()
+            }
+            b"DEL" => format!(":{}\r\n", store.remove(&key).map_or(0, |_| 1)).into_bytes(),
+            b"SCAN" => {
+                let prefix = String::from_utf8_lossy(&args[3]).trim_end_matches('*').to_string();
+                let keys: Vec<&String> = store.keys().filter(|key| key.starts_with(&prefix)).collect();
+                let mut reply = format!("*2\r\n$1\r\n0\r\n*{}\r\n", keys.len());
+                for key in keys {
+                    reply.push_str(&format!("${}\r\n{}\r\n", key.len(), key));
+                }
+                reply.into_bytes()
+            }
+            _ => b"-ERR unknown command\r\n".to_vec(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resp_backend_round_trip() {
+        let backend = RespBackend::new(resp_server().await);
+        assert_eq!(backend.get("missing").await.unwrap(), None);
+        backend.put("user:1", b"binary\r\nvalue", Some(Duration::from_secs(60))).await.unwrap();
+        backend.put("user:2", b"", None).await.unwrap();
+        backend.put("order:1", b"x", None).await.unwrap();
+
+        assert_eq!(backend.get("user:1").await.unwrap(), Some(b"binary\r\nvalue".to_vec()));
+        assert_eq!(backend.get("user:2").await.unwrap(), Some(Vec::new()));
+        assert_eq!(backend.scan_prefix("user:").await.unwrap(), ["user:1", "user:2"]);
+        assert!(backend.delete("user:1").await.unwrap());
+        assert!(!backend.delete("user:1").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_resp_rejects_oversized_bulk_before_allocating() {
+        let mut reply: &[u8] = b"$9223372036854775807\r\n";
+        let error = read_resp(&mut reply).await.unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+
+        let mut truncated: &[u8] = b"$10\r\nshort\r\n";
+        assert_eq!(read_resp(&mut truncated).await.unwrap_err().kind(), std::io::ErrorKind::UnexpectedEof);
+        let mut complete: &[u8] = b"$5\r\nvalue\r\n";
+        assert_eq!(read_resp(&mut complete).await.unwrap(), RespValue::Bulk(Some(b"value".to_vec())));
+    }
+
+    #[tokio::test]
+    async fn test_resp_backend_reports_unreachable_server() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        drop(listener);
+        let cache = cache(EvictionPolicy::LRU, 2).with_backend(Box::new(RespBackend::new(address)));
+
+        cache.store("a".to_string(), b"1".to_vec(), None, None).await.unwrap();
+        assert_eq!(cache.fetch("a").await, Some(b"1".to_vec()));
+        assert_eq!(cache.get_statistics().backend_errors, 1);
+    }
+
+    /// 按键名生成值并记录调用次数的加载器
+    #[derive(Debug, Default)]
+    struct CountingLoader {
+        calls: Mutex<Vec<String>>,
+    }
+
+    impl CountingLoader {
+        fn calls(&self) -> Vec<String> {
+            self.calls.lock().unwrap().clone()
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl CacheLoader for CountingLoader {
+        async fn load(&self, key: &str) -> Result<Option<Vec<u8>>, CacheError> {
+            self.calls.lock().unwrap().push(key.to_string());
+            if key.starts_with("error") {
+                return Err(CacheError::BackendError(format!("无法加载 {}", key)));
+            }
+            Ok((!key.starts_with("absent")).then(|| format!("value of {}", key).into_bytes()))
+        }
+    }
+
+    fn prefetching_cache(clock: &Arc<ManualClock>, loader: &Arc<CountingLoader>, config: PrefetchConfig) -> IntelligentCacheManager {
+        cache(EvictionPolicy::LRU, 100)
+            .with_clock(clock.clone())
+            .with_prefetch(config, loader.clone())
+    }
+
+    #[tokio::test]
+    async fn test_warm_loads_missing_keys_once() {
+        let loader = CountingLoader::default();
+        let cache = cache(EvictionPolicy::LRU, 10);
+        cache.set("cached".to_string(), b"old".to_vec(), None, None).unwrap();
+
+        let warmed = cache.warm(["a", "b", "cached", "absent"], &loader).await.unwrap();
+        assert_eq!(warmed, 2);
+        assert_eq!(loader.calls(), ["a", "b", "absent"]);
+        assert_eq!(cache.get("a"), Some(b"value of a".to_vec()));
+        assert_eq!(cache.get("b"), Some(b"value of b".to_vec()));
+        assert_eq!(cache.get("cached"), Some(b"old".to_vec()));
+
+        assert_eq!(cache.warm(["a", "b"], &loader).await.unwrap(), 0);
+        assert_eq!(loader.calls().len(), 3);
+        let stats = cache.get_statistics();
+        assert_eq!((stats.hits, stats.misses), (3, 0));
+        assert_eq!(stats.prefetch_issued, 0);
+    }
+
+    #[tokio::test]
+    async fn test_periodic_access_triggers_prefetch() {
+        let clock = ManualClock::new();
+        let loader = Arc::new(CountingLoader::default());
+        let config = PrefetchConfig { lead_time: Duration::from_secs(1), sequential: false, ..PrefetchConfig::default() };
+        let cache = prefetching_cache(&clock, &loader, config);
+
+        // 每 10 秒读取一次，条目在两次读取之间过期
+        for _ in 0..4 {
+            assert_eq!(cache.get("report"), None);
+            cache.set("report".to_string(), b"v".to_vec(), Some(Duration::from_secs(5)), None).unwrap();
+            assert_eq!(cache.prefetch_due().await, 0);
+            clock.advance(Duration::from_secs(5));
+            assert_eq!(cache.prefetch_due().await, 0);
+            clock.advance(Duration::from_millis(4500));
+        }
+
+        // 距离预测的访问时刻 0.5 秒，在提前量之内
+        assert_eq!(cache.prefetch_due().await, 1);
+        assert_eq!(loader.calls(), ["report"]);
+        assert_eq!(cache.prefetch_due().await, 0);
+        clock.advance(Duration::from_millis(500));
+        assert_eq!(cache.get("report"), Some(b"value of report".to_vec()));
+
+        let stats = cache.get_statistics();
+        assert_eq!((stats.prefetch_issued, stats.prefetch_hits, stats.prefetch_wasted), (1, 1, 0));
+    }
+
+    #[tokio::test]
+    async fn test_sequential_access_prefetches_next_key() {
+        let clock = ManualClock::new();
+        let loader = Arc::new(CountingLoader::default());
+        let cache = prefetching_cache(&clock, &loader, PrefetchConfig::default());
+
+        cache.get("page:08");
+        cache.get("page:09");
+        assert_eq!(cache.prefetch_due().await, 1);
+        assert_eq!(loader.calls(), ["page:10"]);
+
+        // 未读取就删除的预取计为浪费
+        assert!(cache.remove("page:10"));
+        let stats = cache.get_statistics();
+        assert_eq!((stats.prefetch_issued, stats.prefetch_hits, stats.prefetch_wasted), (1, 0, 1));
+    }
+
+    #[test]
+    fn test_prediction_tracks_bounded_number_of_keys() {
+        let clock = ManualClock::new();
+        let loader = Arc::new(CountingLoader::default());
+        let config = PrefetchConfig { max_tracked_keys: 4, ..PrefetchConfig::default() };
+        let cache = prefetching_cache(&clock, &loader, config);
+
+        for _ in 0..5 {
+            cache.get("hot");
+        }
+        for key in 0..100 {
+            cache.get(&format!("cold-{}", key));
+        }
+        let predictor = cache.prefetch.as_ref().unwrap().predictor.lock().unwrap();
+        assert_eq!(predictor.keys.len(), 4);
+        assert!(predictor.keys.contains_key("hot"));
+        assert!(predictor.sequences.len() <= 4);
+    }
+
+    #[tokio::test]
+    async fn test_prefetch_is_disabled_by_default() {
+        let cache = cache(EvictionPolicy::LRU, 10);
+        cache.get("page:1");
+        cache.get("page:2");
+        assert_eq!(cache.prefetch_due().await, 0);
+        assert!(cache.prefetch.is_none());
+    }
+
+    fn negative_cache(clock: &Arc<ManualClock>) -> IntelligentCacheManager {
+        cache(EvictionPolicy::LRU, 10)
+            .with_clock(clock.clone())
+            .with_negative_caching(NegativeCachePolicy {
+                absent_ttl: Some(Duration::from_secs(30)),
+                error_ttl: Some(Duration::from_secs(2)),
+            })
+    }
+
+    #[tokio::test]
+    async fn test_absent_keys_are_negatively_cached() {
+        let clock = ManualClock::new();
+        let loader = CountingLoader::default();
+        let cache = negative_cache(&clock);
+
+        assert_eq!(cache.get_or_load("absent-module", &loader).await.unwrap(), None);
+        assert_eq!(cache.get_or_load("absent-module", &loader).await.unwrap(), None);
+        assert_eq!(loader.calls(), ["absent-module"]);
+        assert_eq!(cache.get_statistics().negative_hits, 1);
+
+        cache.set("absent-module".to_string(), b"published".to_vec(), None, None).unwrap();
+        assert_eq!(cache.get_or_load("absent-module", &loader).await.unwrap(), Some(b"published".to_vec()));
+        assert_eq!(cache.get("absent-module"), Some(b"published".to_vec()));
+        let stats = cache.get_statistics();
+        assert_eq!((stats.hits, stats.negative_hits), (2, 1));
+        assert_eq!(loader.calls().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_negative_entries_expire() {
+        let clock = ManualClock::new();
+        let loader = CountingLoader::default();
+        let cache = negative_cache(&clock);
+
+        assert!(cache.get_or_load("error-backend", &loader).await.is_err());
+        assert!(matches!(cache.get_or_load("error-backend", &loader).await, Err(CacheError::CachedFailure(_))));
+        cache.get_or_load("absent", &loader).await.unwrap();
+
+        clock.advance(Duration::from_secs(2));
+        assert!(matches!(cache.get_or_load("error-backend", &loader).await, Err(CacheError::BackendError(_))));
+        assert_eq!(cache.get_or_load("absent", &loader).await.unwrap(), None);
+        clock.advance(Duration::from_secs(28));
+        cache.get_or_load("absent", &loader).await.unwrap();
+        assert_eq!(loader.calls(), ["error-backend", "absent", "error-backend", "absent"]);
+    }
+
+    #[tokio::test]
+    async fn test_negative_caching_is_off_by_default_and_per_prefix() {
+        let loader = CountingLoader::default();
+        let cache = cache(EvictionPolicy::LRU, 10);
+        cache.get_or_load("absent-a", &loader).await.unwrap();
+        cache.get_or_load("absent-a", &loader).await.unwrap();
+        assert_eq!(loader.calls().len(), 2);
+
+        cache.set_negative_policy("absent-m", NegativeCachePolicy { absent_ttl: Some(Duration::from_secs(60)), error_ttl: None });
+        cache.set_negative_policy("absent-module:", NegativeCachePolicy::default());
+        for key in ["absent-m1", "absent-m1", "absent-module:x", "absent-module:x"] {
+            cache.get_or_load(key, &loader).await.unwrap();
+        }
+        assert_eq!(loader.calls()[2..], ["absent-m1", "absent-module:x", "absent-module:x"]);
+        assert_eq!(cache.get_statistics().negative_hits, 1);
+    }
+
+    fn sharded_cache(shard_count: usize, max_size: usize, max_bytes: Option<usize>) -> IntelligentCacheManager {
+        let mut cache = cache(EvictionPolicy::LRU, max_size);
+        cache.config.shard_count = shard_count;
+        IntelligentCacheManager::new(cache.config.clone())
+            .with_policy(CachePolicy { max_bytes, ..policy(EvictionPolicy::LRU, max_size) })
+    }
+
+    #[test]
+    fn test_shard_count_rounds_up_to_power_of_two() {
+        assert_eq!(sharded_cache(0, 10, None).shards.len(), 1);
+        assert_eq!(sharded_cache(5, 10, None).shards.len(), 8);
+        assert_eq!(sharded_cache(16, 10, None).shards.len(), 16);
+    }
+
+    #[test]
+    fn test_shard_shares_add_up_to_the_configured_capacity() {
+        for (shard_count, max_size) in [(8, 1000), (8, 10), (16, 3), (4, 4)] {
+            let cache = sharded_cache(shard_count, max_size, None);
+            let shares: usize = cache.shards.iter().map(|shard| shard.eviction.lock().unwrap().limits.share_entries).sum();
+            assert_eq!(shares, max_size);
+        }
+        for (shard_count, max_bytes) in [(8, 1000), (8, 4095), (16, 7)] {
+            let cache = sharded_cache(shard_count, 1000, Some(max_bytes));
+            let shares: usize = cache.shards.iter()
+                .map(|shard| shard.eviction.lock().unwrap().limits.share_bytes.unwrap())
+                .sum();
+            assert_eq!(shares, max_bytes);
+        }
+    }
+
+    #[test]
+    fn test_sharded_statistics_aggregate_across_shards() {
+        let cache = sharded_cache(8, 1000, None);
+        for key in 0..200 {
+            cache.set(format!("key-{}", key), vec![0; 10], None, None).unwrap();
+        }
+        for key in 0..300 {
+            cache.get(&format!("key-{}", key));
+        }
+
+        let populated = cache.shards.iter().filter(|shard| !shard.storage.read().unwrap().is_empty()).count();
+        assert_eq!(populated, 8);
+        let stats = cache.get_statistics();
+        assert_eq!((stats.hits, stats.misses), (200, 100));
+        assert_eq!((stats.entry_count, stats.total_size, stats.peak_size), (200, 2000, 2000));
+        assert_eq!(cache.len(), 200);
+    }
+
+    #[test]
+    fn test_hot_shard_borrows_from_idle_shards() {
+        // 4 个分片，每个分片的份额为 100 字节，含余量的上限为 125 字节
+        let cache = sharded_cache(4, 1000, Some(400));
+        let hot: Vec<String> = (0..)
+            .map(|key| format!("key-{}", key))
+            .filter(|key| std::ptr::eq(cache.shard(key), &cache.shards[0]))
+            .take(20)
+            .collect();
+        for key in &hot {
+            cache.set(key.clone(), vec![0; 10], None, None).unwrap();
+        }
+
+        // 其他分片为空时，热点分片超出份额直到硬上限，之后按 LRU 驱逐
+        let shard_bytes = cache.shards[0].statistics.lock().unwrap().total_size;
+        assert_eq!(shard_bytes, 120);
+        assert!(contains(&cache, &hot[19]) && !contains(&cache, &hot[0]));
+        assert!(matches!(
+            cache.set("big".to_string(), vec![0; 401], None, None),
+            Err(CacheError::ValueTooLarge { size: 401, budget: 400 })
+        ));
+
+        // 超过分片上限但在总预算内的值驱逐分片内的其他条目后单独存放
+        let big = hot[0].clone();
+        cache.set(big.clone(), vec![0; 200], None, None).unwrap();
+        assert_eq!(cache.entry(&big).map(|entry| entry.weight), Some(200));
+        assert_eq!(cache.shard_statistics()[0].entry_count, 1);
+        assert_eq!(cache.entries().len(), 1);
+    }
+
+    #[test]
+    fn test_concurrent_mixed_access_stays_consistent() {
+        let cache = Arc::new(sharded_cache(16, 512, Some(64 * 1024)));
+        let threads: Vec<_> = (0..8)
+            .map(|thread| {
+                let cache = cache.clone();
+                std::thread::spawn(move || {
+                    for round in 0..2000 {
+                        let key = format!("key-{}", (thread * 7919 + round * 31) % 1024);
+                        if round % 3 == 0 {
+                            cache.set(key.clone(), key.as_bytes().repeat(4), None, None).unwrap();
+                        } else if let Some(value) = cache.get(&key) {
+                            assert_eq!(value, key.as_bytes().repeat(4));
+                        }
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        let stats = cache.get_statistics();
+        let stored = entries(&cache);
+        assert_eq!(stats.entry_count, stored.len());
+        assert_eq!(cache.len(), stored.len());
+        assert_eq!(stats.total_size, stored.values().map(|entry| entry.weight).sum::<usize>());
+        assert!(stored.len() <= 512 + 512 * SHARD_OVERFLOW_PERCENT / 100);
+        assert_eq!(stats.hits + stats.misses, 8 * 2000 - 8 * 667);
+        for shard in cache.shards.iter() {
+            let storage = shard.storage.read().unwrap();
+            let shard_stats = shard.statistics.lock().unwrap();
+            assert_eq!(shard_stats.entry_count, storage.len());
+            assert!(storage.keys().all(|key| std::ptr::eq(cache.shard(key), shard)));
+        }
+    }
+
+    #[test]
+    fn test_locked_shard_does_not_block_other_shards() {
+        let single = Arc::new(sharded_cache(1, 100, None));
+        let sharded = Arc::new(sharded_cache(16, 100, None));
+        for cache in [&single, &sharded] {
+            let other = (0..)
+                .map(|key| format!("key-{}", key))
+                .find(|key| cache.shards.len() == 1 || !std::ptr::eq(cache.shard(key), cache.shard("held")))
+                .unwrap();
+            let guard = cache.shard("held").storage.write().unwrap();
+            let (done_tx, done_rx) = std::sync::mpsc::channel();
+            let worker = {
+                let cache = cache.clone();
+                std::thread::spawn(move || {
+                    cache.set(other.clone(), b"v".to_vec(), None, None).unwrap();
+                    done_tx.send(cache.get(&other)).unwrap();
+                })
+            };
+            let finished = done_rx.recv_timeout(Duration::from_millis(200));
+            drop(guard);
+            worker.join().unwrap();
+            if cache.shards.len() == 1 {
+                assert!(finished.is_err(), "单锁时写入应等待被持有的锁");
+            } else {
+                assert_eq!(finished.unwrap(), Some(b"v".to_vec()));
+            }
+        }
+    }
+
+    /// 吞吐量对比，结果取决于机器的核数：`cargo test -- --ignored --nocapture shard_throughput`
+    #[test]
+    #[ignore = "吞吐量基准，结果取决于机器的核数，使用 --ignored 运行"]
+    fn test_shard_throughput_scales_with_shards() {
+        let throughput = |shard_count: usize| {
+            let cache = Arc::new(sharded_cache(shard_count, 4096, None));
+            let start = Instant::now();
+            let threads: Vec<_> = (0..8)
+                .map(|thread| {
+                    let cache = cache.clone();
+                    std::thread::spawn(move || {
+                        for round in 0..50_000 {
+                            let key = format!("key-{}", (thread * 7919 + round * 31) % 4096);
+                            if round % 4 == 0 {
+                                cache.set(key, vec![0; 32], None, None).unwrap();
+                            } else {
+                                cache.get(&key);
+                            }
+                        }
+                    })
+                })
+                .collect();
+            for thread in threads {
+                thread.join().unwrap();
+            }
+            8.0 * 50_000.0 / start.elapsed().as_secs_f64()
+        };
+        let baseline = throughput(1);
+        let sharded = throughput(16);
+        if std::thread::available_parallelism().is_ok_and(|cores| cores.get() >= 4) {
+            assert!(sharded > baseline, "单锁 {:.0} ops/s，16 个分片 {:.0} ops/s", baseline, sharded);
+        }
+    }
+
+    fn adaptive_optimizer(cache: &Arc<IntelligentCacheManager>) -> PerformanceOptimizer {
+        PerformanceOptimizer::new(OptimizationConfig {
+            optimization_interval: Duration::from_secs(60),
+            auto_optimization_enabled: true,
+            optimization_threshold: 0.1,
+            max_recommendations: 5,
+        })
+        .with_adaptive_cache(cache.clone(), AdaptiveCachePolicy {
+            target_hit_rate: 0.8,
+            consecutive_windows: 3,
+            policy_sequence: vec![EvictionPolicy::LRU, EvictionPolicy::ARC],
+            min_ttl: Duration::from_secs(60),
+            max_ttl: Duration::from_secs(900),
+            ttl_step: 2.0,
+        })
+    }
+
+    /// 按窗口累加的脚本化统计
+    struct ScriptedStatistics(CacheStatistics);
+
+    impl ScriptedStatistics {
+        fn new() -> Self {
+            Self(CacheStatistics::new(EvictionPolicy::LRU))
+        }
+
+        /// 追加一个窗口：命中、未命中、容量驱逐、过期
+        fn window(&mut self, hits: u64, misses: u64, evictions: u64, expirations: u64) -> &CacheStatistics {
+            self.0.hits += hits;
+            self.0.misses += misses;
+            self.0.evictions += evictions + expirations;
+            self.0.expirations += expirations;
+            &self.0
+        }
+    }
+
+    #[test]
+    fn test_policy_switches_after_consecutive_low_windows() {
+        let cache = Arc::new(cache(EvictionPolicy::LRU, 10));
+        let optimizer = adaptive_optimizer(&cache);
+        let mut script = ScriptedStatistics::new();
+        let age = Duration::from_secs(5);
+
+        // 低、低、高：连续计数被重置
+        assert_eq!(optimizer.observe(script.window(50, 50, 40, 0), age), None);
+        assert_eq!(optimizer.observe(script.window(50, 50, 40, 0), age), None);
+        assert_eq!(optimizer.observe(script.window(90, 10, 0, 0), age), None);
+        // 没有访问的窗口不计数
+        assert_eq!(optimizer.observe(script.window(0, 0, 0, 0), age), None);
+        assert_eq!(optimizer.observe(script.window(50, 50, 40, 0), age), None);
+        assert_eq!(optimizer.observe(script.window(60, 40, 40, 0), age), None);
+        assert_eq!(cache.policy().eviction_policy, EvictionPolicy::LRU);
+
+        let decision = optimizer.observe(script.window(70, 30, 40, 0), age).unwrap();
+        assert_eq!(decision.window, 7);
+        assert_eq!(decision.action, AdaptationAction::SwitchPolicy { from: EvictionPolicy::LRU, to: EvictionPolicy::ARC });
+        assert!((decision.hit_rate - 0.7).abs() < 1e-9);
+        assert!((decision.churn - 0.4).abs() < 1e-9);
+        assert!(decision.rationale.contains("容量驱逐 40"), "{}", decision.rationale);
+        assert_eq!(cache.policy().eviction_policy, EvictionPolicy::ARC);
+        assert_eq!(optimizer.decisions(), [decision]);
+        assert_eq!(optimizer.metrics.lock().unwrap()["cache_hit_rate"], 0.7);
+    }
+
+    #[test]
+    fn test_expiry_driven_misses_extend_ttl_within_bounds() {
+        let cache = Arc::new(cache(EvictionPolicy::LRU, 10));
+        let optimizer = adaptive_optimizer(&cache);
+        let mut script = ScriptedStatistics::new();
+
+        let mut actions = Vec::new();
+        for _ in 0..12 {
+            if let Some(decision) = optimizer.observe(script.window(10, 90, 0, 50), Duration::ZERO) {
+                actions.push(decision.action);
+            }
+        }
+        let secs = Duration::from_secs;
+        assert_eq!(actions, [
+            AdaptationAction::AdjustTtl { from: secs(300), to: secs(600) },
+            AdaptationAction::AdjustTtl { from: secs(600), to: secs(900) },
+            AdaptationAction::SwitchPolicy { from: EvictionPolicy::LRU, to: EvictionPolicy::ARC },
+            AdaptationAction::Exhausted,
+        ]);
+        assert_eq!(cache.policy().default_ttl, secs(900));
+    }
+
+    #[test]
+    fn test_extreme_ttl_rules_do_not_panic() {
+        let secs = Duration::from_secs;
+        assert_eq!(scale_ttl(secs(300), f64::INFINITY, secs(900)), secs(900));
+        assert_eq!(scale_ttl(Duration::ZERO, f64::INFINITY, secs(900)), Duration::ZERO);
+        assert_eq!(scale_ttl(secs(300), f64::NAN, secs(900)), secs(300));
+        assert_eq!(scale_ttl(Duration::MAX, 2.0, Duration::MAX), Duration::MAX);
+
+        let cache = Arc::new(cache(EvictionPolicy::LRU, 10));
+        let optimizer = PerformanceOptimizer::new(OptimizationConfig {
+            optimization_interval: Duration::from_secs(60),
+            auto_optimization_enabled: true,
+            optimization_threshold: 0.1,
+            max_recommendations: 5,
+        })
+        .with_adaptive_cache(cache.clone(), AdaptiveCachePolicy {
+            target_hit_rate: 0.8,
+            consecutive_windows: 1,
+            policy_sequence: Vec::new(),
+            min_ttl: secs(1200),
+            max_ttl: secs(600),
+            ttl_step: f64::INFINITY,
+        });
+        let mut script = ScriptedStatistics::new();
+        let decision = optimizer.observe(script.window(10, 90, 0, 50), Duration::ZERO).unwrap();
+        assert_eq!(decision.action, AdaptationAction::AdjustTtl { from: secs(300), to: secs(600) });
+    }
+
+    #[test]
+    fn test_pinned_policy_stops_adaptation() {
+        let cache = Arc::new(cache(EvictionPolicy::LRU, 10));
+        let optimizer = adaptive_optimizer(&cache);
+        let mut script = ScriptedStatistics::new();
+
+        optimizer.pin_policy(EvictionPolicy::LFU).unwrap();
+        for _ in 0..6 {
+            assert_eq!(optimizer.observe(script.window(10, 90, 50, 0), Duration::ZERO), None);
+        }
+        assert_eq!(cache.policy().eviction_policy, EvictionPolicy::LFU);
+
+        optimizer.unpin_policy();
+        for _ in 0..2 {
+            assert_eq!(optimizer.observe(script.window(10, 90, 50, 0), Duration::ZERO), None);
+        }
+        let decision = optimizer.observe(script.window(10, 90, 50, 0), Duration::ZERO).unwrap();
+        assert_eq!(decision.action, AdaptationAction::SwitchPolicy { from: EvictionPolicy::LFU, to: EvictionPolicy::LRU });
+        let actions: Vec<AdaptationAction> = optimizer.decisions().into_iter().map(|decision| decision.action).collect();
+        assert_eq!(actions[..2], [AdaptationAction::Pinned(EvictionPolicy::LFU), AdaptationAction::Unpinned]);
+    }
+
+    #[test]
+    fn test_sample_reads_attached_cache() {
+        let clock = ManualClock::new();
+        let cache = Arc::new(expiring_cache(&clock, false));
+        let optimizer = adaptive_optimizer(&cache);
+        cache.set("a".to_string(), b"v".to_vec(), None, None).unwrap();
+        clock.advance(Duration::from_secs(4));
+        cache.get("a");
+        cache.get("b");
+
+        assert_eq!(optimizer.sample(), None);
+        let metrics = optimizer.metrics.lock().unwrap();
+        assert_eq!(metrics["cache_hit_rate"], 0.5);
+        assert_eq!(metrics["cache_mean_entry_age_secs"], 4.0);
+        assert!(PerformanceOptimizer::new(optimizer.config.clone()).sample().is_none());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_misses_share_one_load() {
+        let clock = ManualClock::new();
+        let cache = Arc::new(expiring_cache(&clock, false));
+        cache.set("hot".to_string(), b"stale".to_vec(), Some(Duration::from_secs(1)), None).unwrap();
+        clock.advance(Duration::from_secs(1));
+
+        let loads = Arc::new(AtomicUsize::new(0));
+        let tasks: Vec<_> = (0..100)
+            .map(|_| {
+                let cache = cache.clone();
+                let loads = loads.clone();
+                tokio::spawn(async move {
+                    cache.get_or_insert_with("hot", || async move {
+                        loads.fetch_add(1, Ordering::SeqCst);
+                        tokio::time::sleep(Duration::from_millis(50)).await;
+                        Ok(b"fresh".to_vec())
+                    })
+                    .await
+                })
+            })
+            .collect();
+        for task in tasks {
+            assert_eq!(task.await.unwrap().unwrap(), b"fresh");
+        }
+        assert_eq!(loads.load(Ordering::SeqCst), 1);
+        assert_eq!(cache.get("hot"), Some(b"fresh".to_vec()));
+        assert!(cache.in_flight.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_failed_load_wakes_waiters_and_releases_key() {
+        let cache = Arc::new(cache(EvictionPolicy::LRU, 10));
+        let waiter = {
+            let cache = cache.clone();
+            tokio::spawn(async move {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                cache.get_or_insert_with("key", || async { Ok(b"unused".to_vec()) }).await
+            })
+        };
+        let leader = cache.get_or_insert_with("key", || async {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            Err(CacheError::BackendError("down".to_string()))
+        });
+        assert!(matches!(leader.await, Err(CacheError::BackendError(_))));
+        assert!(matches!(waiter.await.unwrap(), Err(CacheError::BackendError(_))));
+
+        let value = cache.get_or_insert_with("key", || async { Ok(b"recovered".to_vec()) }).await;
+        assert_eq!(value.unwrap(), b"recovered");
+    }
+
+    #[tokio::test]
+    async fn test_panicking_loader_does_not_poison_key() {
+        let cache = Arc::new(cache(EvictionPolicy::LRU, 10));
+        let leader = {
+            let cache = cache.clone();
+            tokio::spawn(async move {
+                cache.get_or_insert_with("key", || async {
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                    panic!("loader bug");
+                })
+                .await
+            })
+        };
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        let waiter = cache.get_or_insert_with("key", || async { Ok(b"unused".to_vec()) }).await;
+        assert!(matches!(waiter, Err(CacheError::LoadAborted)));
+        assert!(leader.await.unwrap_err().is_panic());
+
+        let value = cache.get_or_insert_with("key", || async { Ok(b"ok".to_vec()) }).await;
+        assert_eq!(value.unwrap(), b"ok");
+    }
+
+    #[tokio::test]
+    async fn test_stuck_loader_times_out() {
+        let cache = cache(EvictionPolicy::LRU, 10).with_load_timeout(Duration::from_millis(30));
+        let stuck = cache.get_or_insert_with("key", std::future::pending::<Result<Vec<u8>, CacheError>>);
+        assert!(matches!(stuck.await, Err(CacheError::LoadTimedOut(_))));
+        let value = cache.get_or_insert_with("key", || async { Ok(b"ok".to_vec()) }).await;
+        assert_eq!(value.unwrap(), b"ok");
+    }
+
+    #[tokio::test]
+    async fn test_load_failures_can_be_negatively_cached() {
+        let clock = ManualClock::new();
+        let cache = negative_cache(&clock);
+        let loads = AtomicUsize::new(0);
+        for _ in 0..3 {
+            let result = cache.get_or_insert_with("flaky", || async {
+                loads.fetch_add(1, Ordering::SeqCst);
+                Err(CacheError::BackendError("down".to_string()))
+            })
+            .await;
+            assert!(result.is_err());
+        }
+        assert_eq!(loads.load(Ordering::SeqCst), 1);
+        assert_eq!(cache.get_statistics().negative_hits, 2);
+
+        clock.advance(Duration::from_secs(2));
+        let value = cache.get_or_insert_with("flaky", || async { Ok(b"up".to_vec()) }).await;
+        assert_eq!(value.unwrap(), b"up");
+    }
+
+    #[test]
+    fn test_sync_variant_shares_one_load() {
+        let cache = Arc::new(cache(EvictionPolicy::LRU, 10));
+        let loads = Arc::new(AtomicUsize::new(0));
+        let threads: Vec<_> = (0..16)
+            .map(|_| {
+                let cache = cache.clone();
+                let loads = loads.clone();
+                std::thread::spawn(move || {
+                    cache.get_or_insert_with_sync("key", || {
+                        loads.fetch_add(1, Ordering::SeqCst);
+                        std::thread::sleep(Duration::from_millis(50));
+                        Ok(b"value".to_vec())
+                    })
+                })
+            })
+            .collect();
+        for thread in threads {
+            assert_eq!(thread.join().unwrap().unwrap(), b"value");
+        }
+        assert_eq!(loads.load(Ordering::SeqCst), 1);
+    }
+
+    fn snapshot_source(compression_enabled: bool) -> IntelligentCacheManager {
+        let mut cache = sharded_cache(4, 100, None);
+        cache.config.compression_enabled = compression_enabled;
+        IntelligentCacheManager::new(cache.config.clone())
+            .with_policy(policy(EvictionPolicy::LFU, 100))
+    }
+
+    #[test]
+    fn test_snapshot_round_trip_restores_hits_and_metadata() {
+        for compression_enabled in [false, true] {
+            let dir = tempfile::tempdir().unwrap();
+            let path = dir.path().join("cache.snap");
+            let source = snapshot_source(compression_enabled);
+            for key in 0..20 {
+                source.set(format!("key-{}", key), vec![key as u8; 2048], None, Some(CachePriority::High)).unwrap();
+            }
+            for _ in 0..3 {
+                source.get("key-7");
+            }
+
+            assert_eq!(source.snapshot_to(&path).unwrap(), 20);
+            let files: Vec<_> = std::fs::read_dir(dir.path()).unwrap().map(|entry| entry.unwrap().file_name()).collect();
+            assert_eq!(files, ["cache.snap"]);
+
+            let restored = snapshot_source(compression_enabled);
+            let report = restored.restore_from(&path).unwrap();
+            assert_eq!((report.restored, report.expired, report.corrupt, report.dropped), (20, 0, 0, 0));
+            assert_eq!((report.policy.as_str(), report.eviction_policy.as_str()), ("LFU", "LFU"));
+            for key in 0..20 {
+                assert_eq!(restored.get(&format!("key-{}", key)), Some(vec![key as u8; 2048]));
+            }
+            let entry = entries(&restored).remove("key-7").unwrap();
+            assert_eq!((entry.access_count, entry.priority, entry.ttl), (4, CachePriority::High, Duration::from_secs(300)));
+            assert_eq!(restored.get_statistics().hits, 20);
+        }
+    }
+
+    #[test]
+    fn test_restore_skips_entries_expired_since_snapshot() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cache.snap");
+        let clock = ManualClock::new();
+        let source = expiring_cache(&clock, false);
+        source.set("long".to_string(), b"v".to_vec(), None, None).unwrap();
+        source.set("short".to_string(), b"v".to_vec(), Some(Duration::from_millis(2_050)), None).unwrap();
+        source.set("gone".to_string(), b"v".to_vec(), Some(Duration::from_secs(1)), None).unwrap();
+        clock.advance(Duration::from_secs(2));
+        assert_eq!(source.snapshot_to(&path).unwrap(), 2);
+
+        std::thread::sleep(Duration::from_millis(100));
+        let restored = expiring_cache(&ManualClock::new(), false);
+        let report = restored.restore_from(&path).unwrap();
+        assert_eq!((report.restored, report.expired), (1, 1));
+        assert_eq!(restored.get("long"), Some(b"v".to_vec()));
+        assert_eq!(restored.get("short"), None);
+    }
+
+    #[test]
+    fn test_restore_skips_corrupt_middle_record() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cache.snap");
+        let source = cache(EvictionPolicy::LRU, 10);
+        for key in ["first", "middle", "last"] {
+            source.set(key.to_string(), format!("{}-value", key).into_bytes(), None, None).unwrap();
+            source.get(key);
+        }
+        source.snapshot_to(&path).unwrap();
+
+        let mut bytes = std::fs::read(&path).unwrap();
+        let offset = bytes.windows(12).position(|window| window == b"middle-value").unwrap();
+        bytes[offset] ^= 0xff;
+        std::fs::write(&path, &bytes).unwrap();
+
+        let restored = cache(EvictionPolicy::LRU, 10);
+        let report = restored.restore_from(&path).unwrap();
+        assert_eq!((report.restored, report.corrupt), (2, 1));
+        assert_eq!(restored.get("first"), Some(b"first-value".to_vec()));
+        assert_eq!(restored.get("middle"), None);
+        assert_eq!(restored.get("last"), Some(b"last-value".to_vec()));
+
+        // 截断的文件保留截断点之前的条目
+        std::fs::write(&path, &bytes[..bytes.len() - 3]).unwrap();
+        let truncated = cache(EvictionPolicy::LRU, 10);
+        let report = truncated.restore_from(&path).unwrap();
+        assert_eq!((report.restored, report.corrupt), (1, 2));
+    }
+
+    #[test]
+    fn test_restore_drops_entries_over_byte_budget() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cache.snap");
+        let source = cache(EvictionPolicy::LRU, 10);
+        source.set("small".to_string(), vec![0; 10], None, None).unwrap();
+        source.set("large".to_string(), vec![0; 500], None, None).unwrap();
+        source.snapshot_to(&path).unwrap();
+
+        let restored = cache(EvictionPolicy::LRU, 10)
+            .with_policy(CachePolicy { max_bytes: Some(100), ..policy(EvictionPolicy::LRU, 10) });
+        let report = restored.restore_from(&path).unwrap();
+        assert_eq!((report.restored, report.dropped, report.corrupt), (1, 1, 0));
+        assert!(contains(&restored, "small"));
+        assert!(!contains(&restored, "large"));
+    }
+
+    #[test]
+    fn test_snapshot_failure_keeps_existing_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cache.snap");
+        let source = cache(EvictionPolicy::LRU, 10);
+        source.set("key".to_string(), b"v".to_vec(), None, None).unwrap();
+        source.snapshot_to(&path).unwrap();
+        let before = std::fs::read(&path).unwrap();
+
+        let missing = dir.path().join("missing").join("cache.snap");
+        assert!(matches!(source.snapshot_to(&missing), Err(CacheError::StorageError(_))));
+        assert_eq!(std::fs::read(&path).unwrap(), before);
+
+        std::fs::write(&path, b"not a snapshot").unwrap();
+        assert!(matches!(source.restore_from(&path), Err(CacheError::SerializationError(_))));
+    }
+
+    #[test]
+    fn test_concurrent_snapshots_use_separate_temp_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cache.snap");
+        assert_ne!(snapshot_temp_path(&path), snapshot_temp_path(&path));
+        assert_eq!(snapshot_temp_path(&path).parent(), Some(dir.path()));
+
+        let source = cache(EvictionPolicy::LRU, 10);
+        source.set("key".to_string(), b"v".to_vec(), None, None).unwrap();
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| assert_eq!(source.snapshot_to(&path).unwrap(), 1));
+            }
+        });
+        assert_eq!(cache(EvictionPolicy::LRU, 10).restore_from(&path).unwrap().restored, 1);
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    /// 按顺序记录事件的钩子
+    #[derive(Debug, Default)]
+    struct RecordingHook {
+        events: Mutex<Vec<String>>,
+    }
+
+    impl RecordingHook {
+        fn take(&self) -> Vec<String> {
+            std::mem::take(&mut *self.events.lock().unwrap())
+        }
+    }
+
+    impl CacheHook for RecordingHook {
+        fn on_insert(&self, key: &str, entry: &CacheEntry) {
+            self.events.lock().unwrap().push(format!("insert {} {}", key, entry.weight));
+        }
+
+        fn on_hit(&self, key: &str, entry: &CacheEntry) {
+            self.events.lock().unwrap().push(format!("hit {} {}", key, entry.access_count));
+        }
+
+        fn on_evict(&self, key: &str, _entry: &CacheEntry, reason: EvictionReason) {
+            self.events.lock().unwrap().push(format!("evict {} {:?}", key, reason));
+        }
+
+        fn on_remove(&self, key: &str, _entry: &CacheEntry) {
+            self.events.lock().unwrap().push(format!("remove {}", key));
+        }
+    }
+
+    #[test]
+    fn test_hooks_receive_events_with_reasons() {
+        let hook = Arc::new(RecordingHook::default());
+        let cache = cache(EvictionPolicy::LRU, 2).with_hook(hook.clone());
+        cache.set("a".to_string(), vec![0; 3], None, None).unwrap();
+        cache.set("b".to_string(), vec![0; 4], None, None).unwrap();
+        cache.get("a");
+        cache.set("c".to_string(), vec![0; 5], None, None).unwrap();
+        assert_eq!(hook.take(), ["insert a 3", "insert b 4", "hit a 1", "evict b Capacity", "remove b", "insert c 5"]);
+
+        cache.set("c".to_string(), vec![0; 6], None, None).unwrap();
+        assert!(cache.remove("a"));
+        assert!(!cache.remove("a"));
+        assert_eq!(hook.take(), ["remove c", "insert c 6", "evict a Manual", "remove a"]);
+
+        cache.set("d".to_string(), vec![0], None, None).unwrap();
+        cache.set_policy(policy(EvictionPolicy::LRU, 1));
+        assert_eq!(hook.take(), ["insert d 1", "evict c PolicySwitch", "remove c"]);
+
+        let clock = ManualClock::new();
+        let expiring = expiring_cache(&clock, false).with_hook(hook.clone());
+        expiring.set("short".to_string(), vec![0], Some(Duration::from_secs(1)), None).unwrap();
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(expiring.get("short"), None);
+        assert_eq!(hook.take(), ["insert short 1", "evict short Ttl", "remove short"]);
+    }
+
+    #[tokio::test]
+    async fn test_sweeper_expirations_fire_ttl_evictions() {
+        let clock = ManualClock::new();
+        let hook = Arc::new(RecordingHook::default());
+        let mut cache = expiring_cache(&clock, false).with_hook(hook.clone());
+        cache.config.cleanup_interval = Duration::from_millis(5);
+        let cache = Arc::new(cache);
+        cache.set("short".to_string(), vec![0], Some(Duration::from_secs(1)), None).unwrap();
+        cache.set("kept".to_string(), vec![0], None, None).unwrap();
+        hook.take();
+        cache.start_sweeper();
+
+        clock.advance(Duration::from_secs(1));
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while cache.len() > 1 && Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        cache.stop_sweeper();
+        assert_eq!(hook.take(), ["evict short Ttl", "remove short"]);
+    }
+
+    /// 每次事件都 panic 的钩子
+    #[derive(Debug)]
+    struct PanickingHook;
+
+    impl CacheHook for PanickingHook {
+        fn on_insert(&self, _key: &str, _entry: &CacheEntry) {
+            panic!("钩子失败");
+        }
+    }
+
+    #[test]
+    fn test_panicking_hook_is_disabled_after_failure_limit() {
+        let hook = Arc::new(RecordingHook::default());
+        let cache = cache(EvictionPolicy::LRU, 10)
+            .with_hook_failure_limit(2)
+            .with_hook(Arc::new(PanickingHook))
+            .with_hook(hook.clone());
+        for key in ["a", "b", "c"] {
+            cache.set(key.to_string(), vec![0], None, None).unwrap();
+        }
+        assert_eq!(hook.take(), ["insert a 1", "insert b 1", "insert c 1"]);
+        assert_eq!((cache.hook_failures(), cache.disabled_hooks()), (2, 1));
+        assert_eq!(cache.get("c"), Some(vec![0]));
+        assert!(cache.hooks.is_active());
+
+        let only_panicking = self::cache(EvictionPolicy::LRU, 10)
+            .with_hook_failure_limit(1)
+            .with_hook(Arc::new(PanickingHook));
+        assert!(!self::cache(EvictionPolicy::LRU, 10).hooks.is_active());
+        only_panicking.set("a".to_string(), vec![0], None, None).unwrap();
+        assert!(!only_panicking.hooks.is_active());
+        assert_eq!(only_panicking.len(), 1);
+    }
+
+    #[test]
+    fn test_hook_success_resets_failure_count() {
+        let cache = cache(EvictionPolicy::LRU, 10)
+            .with_hook_failure_limit(2)
+            .with_hook(Arc::new(PanickingHook));
+        cache.set("a".to_string(), vec![0], None, None).unwrap();
+        cache.get("a");
+        cache.set("b".to_string(), vec![0], None, None).unwrap();
+        assert_eq!((cache.hook_failures(), cache.disabled_hooks()), (2, 0));
+
+        cache.set("c".to_string(), vec![0], None, None).unwrap();
+        assert_eq!((cache.hook_failures(), cache.disabled_hooks()), (3, 1));
+    }
+
+    #[test]
+    fn test_metrics_hook_publishes_counters_and_histograms() {
+        let collector = Arc::new(MetricsCollector::new(crate::monitoring_advanced::MetricsConfig {
+            enabled: true,
+            collection_interval: Duration::from_secs(10),
+            retention_period: Duration::from_secs(3600),
+            export_format: crate::monitoring_advanced::ExportFormat::Prometheus,
+        }));
+        let cache = cache(EvictionPolicy::LRU, 1)
+            .with_hook(Arc::new(MetricsCacheHook::new(collector.clone(), "modules")));
+        cache.set("a".to_string(), vec![0; 100], None, None).unwrap();
+        cache.get("a");
+        cache.get("a");
+        cache.set("b".to_string(), vec![0; 100], None, None).unwrap();
+        cache.remove("b");
+
+        let counter = |name: &str, labels: &[(&str, &str)]| {
+            let labels = labels.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect();
+            match collector.get_metric(name, &labels).map(|metric| metric.value) {
+                Some(crate::monitoring_advanced::MetricValue::Integer(value)) => value,
+                _ => 0,
+            }
+        };
+        assert_eq!(counter(CACHE_INSERTS, &[("cache", "modules")]), 2);
+        assert_eq!(counter(CACHE_HITS, &[("cache", "modules")]), 2);
+        assert_eq!(counter(CACHE_EVICTIONS, &[("cache", "modules"), ("reason", "capacity")]), 1);
+        assert_eq!(counter(CACHE_EVICTIONS, &[("cache", "modules"), ("reason", "manual")]), 1);
+        assert_eq!(counter(CACHE_REMOVALS, &[("cache", "modules")]), 2);
+        assert!(collector.quantile(CACHE_ENTRY_BYTES, 0.5).is_some());
+        assert!(collector.quantile(CACHE_EVICTED_ACCESSES, 0.5).is_some());
+    }
+
+    /// 加载期间把键写入缓存，再报告键不存在，模拟与加载并发的写入
+    #[derive(Debug)]
+    struct RacingWriteLoader {
+        cache: Arc<IntelligentCacheManager>,
+    }
+
+    #[async_trait::async_trait]
+    impl CacheLoader for RacingWriteLoader {
+        async fn load(&self, key: &str) -> Result<Option<Vec<u8>>, CacheError> {
+            self.cache.set(key.to_string(), b"published".to_vec(), None, None)?;
+            Ok(None)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_write_during_load_is_not_hidden_by_negative_entry() {
+        let clock = ManualClock::new();
+        let cache = Arc::new(negative_cache(&clock));
+        let loader = RacingWriteLoader { cache: cache.clone() };
+
+        assert_eq!(cache.get_or_load("module", &loader).await.unwrap(), None);
+        assert_eq!(cache.get_or_load("module", &CountingLoader::default()).await.unwrap(), Some(b"published".to_vec()));
+        assert_eq!(cache.get_statistics().negative_hits, 0);
+    }
+
+    #[test]
+    fn test_join_flight_returns_value_written_by_finished_load() {
+        let cache = cache(EvictionPolicy::LRU, 10);
+        // 调用者未命中之后、加入加载之前，上一次加载写入了值并已释放该键
+        cache.set("module".to_string(), b"loaded".to_vec(), None, None).unwrap();
+        assert!(matches!(cache.join_flight("module"), FlightJoin::Cached(value) if value == b"loaded"));
+        assert!(cache.in_flight.lock().unwrap().is_empty());
+
+        let FlightJoin::Leader(flight) = cache.join_flight("other") else {
+            panic!("未缓存的键应由调用者加载");
+        };
+        assert!(matches!(cache.join_flight("other"), FlightJoin::Waiter(waiter) if Arc::ptr_eq(&waiter, &flight)));
+    }
+
+    #[test]
+    fn test_finished_flight_guard_releases_its_flight() {
+        let cache = cache(EvictionPolicy::LRU, 10);
+        let FlightJoin::Leader(flight) = cache.join_flight("module") else {
+            panic!("未缓存的键应由调用者加载");
+        };
+        FlightGuard::new(&cache, "module", flight.clone()).finish(Ok(b"loaded".to_vec()));
+        assert_eq!(Arc::strong_count(&flight), 1);
+        assert!(cache.in_flight.lock().unwrap().is_empty());
+
+        let FlightJoin::Leader(flight) = cache.join_flight("other") else {
+            panic!("未缓存的键应由调用者加载");
+        };
+        drop(FlightGuard::new(&cache, "other", flight.clone()));
+        assert_eq!(Arc::strong_count(&flight), 1);
+        assert!(matches!(flight.result(), Some(Err(CacheError::LoadAborted))));
+    }
 }
@@ -152,13 +152,20 @@ pub use module_marketplace::{
 };
 
 pub use ai_optimization::{
-    AiOptimizationEngine, MachineLearningModel, NeuralNetworkModel,
+    AiOptimizationEngine, MachineLearningModel, NeuralNetworkModel, LinearRegressionModel,
+    DecisionTreeModel, RandomForestModel, MemoryOptimizationStrategy,
+    LatencyOptimizationStrategy, OnlineLearningBridge, SnapshotFeatureExtractor,
+    ParamGrid, GridSearchReport, ModelState, FeatureContribution,
     OptimizationContext, OptimizationResult, TrainingDataPoint
 };
 
 pub use edge_computing::{
     EdgeComputingManager, EdgeNode, EdgeTask, TaskScheduler,
-    ResourceManager, NetworkManager, GeographicLocation
+    ResourceManager, NetworkManager, GeographicLocation, SchedulingWeights, NodeAssignment,
+    TaskQueue, QueueMetrics, LateTaskPolicy, EdgeClock, SystemEdgeClock, HeartbeatStats,
+    TaskSubmission, Reservation, TenantQuota, ResourceUtilization, RetryOutcome, AttemptRecord, FailedTask,
+    CostModel, TaskProfile, PlacementConfig, PlacementDecision, PlacementTarget, EdgeTaskResult, TaskInvocation,
+    Capability, SpecialHardware, TaskExecutionStatus
 };
 
 pub use blockchain_web3::{
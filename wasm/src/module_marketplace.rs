@@ -2,10 +2,21 @@
 //!
 //! 本模块提供了 WebAssembly 模块市场、生态系统管理和模块分发功能
 
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use crate::security_advanced::{AdvancedSecurityManager, VerificationStatus};
+use crate::types::{FunctionType, ValueType};
+use crate::webassembly_2_0::WebAssembly2Features;
+use ring::rand::{SecureRandom, SystemRandom};
+use ring::signature::{ED25519, UnparsedPublicKey};
+use semver::{Op, Version, VersionReq};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
-use std::time::{ SystemTime};
+use sha2::{Digest, Sha256};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering as AtomicOrdering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, SystemTime};
 use thiserror::Error;
 
 /// 模块市场管理器
@@ -20,10 +31,128 @@ pub struct ModuleMarketplaceManager {
     pub rating_system: RatingSystem,
     /// 下载统计
     pub download_stats: Arc<Mutex<HashMap<String, DownloadStats>>>,
+    /// 名称、描述和标签的全文索引，随注册表增量更新
+    pub search_index: Arc<Mutex<SearchIndex>>,
+    /// 签名发布时上传的模块字节，按模块ID存储
+    pub module_blobs: Arc<Mutex<HashMap<String, Vec<u8>>>>,
+    /// 下载时执行代码完整性检查的安全管理器，`None` 表示只校验不拦截
+    pub security_manager: Option<Arc<AdvancedSecurityManager>>,
+    /// 按天分桶的下载计数
+    pub download_tracker: Arc<DownloadTracker>,
+    /// 时钟，用于下载分桶、热度计算和审计日志
+    pub clock: Arc<dyn MarketplaceClock>,
+    /// 每个模块名称的所有者，即第一次发布该名称的用户
+    pub module_owners: Arc<Mutex<HashMap<String, String>>>,
+    /// 分类树
+    pub category_tree: Arc<Mutex<CategoryTree>>,
+    /// 按模块名称记录的弃用信息
+    pub deprecations: Arc<Mutex<HashMap<String, Deprecation>>>,
+    /// 撤回和弃用操作的审计日志
+    pub audit_log: Arc<Mutex<Vec<AuditRecord>>>,
+    /// 草稿模块尚未解决的发布检查问题
+    pub draft_problems: Arc<Mutex<HashMap<String, Vec<ValidationProblem>>>>,
     /// 市场配置
     pub config: MarketplaceConfig,
 }
 
+/// 市场时钟
+/// Marketplace clock
+pub trait MarketplaceClock: std::fmt::Debug + Send + Sync {
+    /// 当前时间
+    /// Current time
+    fn now(&self) -> SystemTime;
+}
+
+/// 模块弃用信息
+/// Module deprecation
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Deprecation {
+    /// 弃用说明
+    pub message: String,
+    /// 建议改用的模块名称
+    pub replacement: Option<String>,
+    /// 弃用时间
+    pub deprecated_at: SystemTime,
+}
+
+/// 获取模块时附带的警告
+/// Warning attached to a fetched module
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ModuleWarning {
+    /// 该版本已撤回
+    Yanked {
+        /// 撤回原因
+        reason: String,
+    },
+    /// 该模块已弃用
+    Deprecated {
+        /// 弃用说明
+        message: String,
+        /// 建议改用的模块名称
+        replacement: Option<String>,
+    },
+}
+
+/// 获取到的模块及其警告
+/// A fetched module together with its warnings
+#[derive(Debug, Clone)]
+pub struct ModuleFetch {
+    /// 模块条目
+    pub module: ModuleEntry,
+    /// 撤回、弃用等警告，没有时为空
+    pub warnings: Vec<ModuleWarning>,
+}
+
+/// 审计日志中的一条记录
+/// One audit log record
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditRecord {
+    /// 操作时间
+    pub at: SystemTime,
+    /// 执行操作的用户
+    pub user_id: String,
+    /// 模块名称
+    pub module: String,
+    /// 操作
+    pub action: AuditAction,
+}
+
+/// 审计的操作
+/// Audited action
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum AuditAction {
+    /// 撤回版本
+    Yank {
+        /// 版本
+        version: String,
+        /// 撤回原因
+        reason: String,
+    },
+    /// 取消撤回
+    Unyank {
+        /// 版本
+        version: String,
+    },
+    /// 弃用模块
+    Deprecate {
+        /// 弃用说明
+        message: String,
+        /// 建议改用的模块名称
+        replacement: Option<String>,
+    },
+}
+
+/// 系统时钟
+/// System clock
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemMarketplaceClock;
+
+impl MarketplaceClock for SystemMarketplaceClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
 /// 模块条目
 /// Module Entry
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -44,6 +173,9 @@ pub struct ModuleEntry {
     pub tags: Vec<String>,
     /// 分类
     pub category: ModuleCategory,
+    /// 所属的分类树节点，保存节点标识，查询时再按当前的分类树解析
+    #[serde(default)]
+    pub categories: Vec<String>,
     /// 下载URL
     pub download_url: String,
     /// 文档URL
@@ -56,20 +188,105 @@ pub struct ModuleEntry {
     pub updated_at: SystemTime,
     /// 下载次数
     pub download_count: u64,
-    /// 评分
+    /// 评分，所有评分的算术平均
     pub rating: f64,
     /// 评分数量
     pub rating_count: u32,
+    /// 向全站平均收缩后的加权评分，评分少的模块更接近全站平均；没有评分时为 0
+    #[serde(default)]
+    pub weighted_rating: f64,
     /// 模块大小
     pub size: u64,
     /// 依赖关系
-    pub dependencies: Vec<ModuleDependency>,
+    pub dependencies: Vec<Dependency>,
+    /// 是否已撤回；撤回的版本不出现在搜索结果中，只有被精确指定时才会被依赖解析选中
+    #[serde(default)]
+    pub yanked: bool,
+    /// 撤回原因
+    #[serde(default)]
+    pub yank_reason: Option<String>,
+    /// 是否为草稿；草稿未通过发布检查，不出现在搜索结果和依赖解析中
+    #[serde(default)]
+    pub draft: bool,
+    /// 声明的导出名称，发布时与上传的模块字节核对
+    #[serde(default)]
+    pub exports: Vec<String>,
+    /// 该版本的更新说明，发布时填写
+    #[serde(default)]
+    pub changelog: Option<String>,
+    /// 发布者对模块字节和元数据的签名，`None` 表示未签名
+    #[serde(default)]
+    pub signature: Option<ModuleSignature>,
+    /// 去重后的累计下载次数，由市场在返回条目时填写
+    #[serde(default)]
+    pub downloads_total: u64,
+    /// 最近 7 天（含当天）去重后的下载次数，由市场在返回条目时填写
+    #[serde(default)]
+    pub downloads_last_7d: u64,
     /// 兼容性
     pub compatibility: CompatibilityInfo,
     /// 安全扫描结果
     pub security_scan: Option<SecurityScanResult>,
 }
 
+/// 模块的分离式签名
+/// Detached module signature
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ModuleSignature {
+    /// 签名的用户ID
+    pub signer: String,
+    /// 签名密钥的ID
+    pub key_id: String,
+    /// 对 [`signing_payload`] 的 Ed25519 签名
+    pub signature: Vec<u8>,
+}
+
+/// 发布者的 Ed25519 公钥
+/// A publisher's Ed25519 public key
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PublisherKey {
+    /// 密钥ID，公钥 SHA-256 摘要的前 8 字节（十六进制）
+    pub key_id: String,
+    /// 32 字节的公钥
+    pub public_key: Vec<u8>,
+    /// 注册时间
+    pub registered_at: SystemTime,
+    /// 撤销时间，`None` 表示仍然有效
+    pub revoked_at: Option<SystemTime>,
+}
+
+/// 发布者需要签名的内容：模块字节的摘要和元数据的摘要
+/// What a publisher signs: the digest of the module bytes and the digest of its metadata
+///
+/// 元数据摘要覆盖ID、名称、版本、作者、许可证和依赖，不含下载量、评分等发布后会变化的字段。
+/// The metadata digest covers the ID, name, version, author, license and dependencies, leaving out fields such as
+/// downloads and ratings that change after publishing.
+pub fn signing_payload(module: &ModuleEntry, bytes: &[u8]) -> Vec<u8> {
+    let metadata = serde_json::json!([
+        module.id,
+        module.name,
+        module.version,
+        module.author,
+        module.license,
+        module.dependencies,
+    ]);
+    format!(
+        "wasm-marketplace-signature-v1\n{}\n{}",
+        hex(&Sha256::digest(bytes)),
+        hex(&Sha256::digest(metadata.to_string().as_bytes())),
+    )
+    .into_bytes()
+}
+
+/// `version` 是否低于 `than`；任一版本无法解析时不视为更低
+fn is_older(version: &str, than: &str) -> bool {
+    matches!((Version::parse(version), Version::parse(than)), (Ok(version), Ok(than)) if version < than)
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
 /// 模块分类
 /// Module Category
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -94,16 +311,132 @@ pub enum ModuleCategory {
     Other,
 }
 
+/// 分类树的节点
+/// Category tree node
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CategoryNode {
+    /// 节点标识，由小写字母、数字和 `-` 组成
+    pub slug: String,
+    /// 显示名称
+    pub name: String,
+    /// 父节点标识，`None` 表示顶层节点
+    pub parent: Option<String>,
+}
+
+/// 分类树
+/// Category tree
+///
+/// 模块只保存节点标识，因此移动节点后模块不需要更新，按子树过滤时会包含节点新的位置下的全部后代。
+/// Modules only store node slugs, so moving a node needs no module updates; subtree filters follow the node's
+/// new position and include all of its descendants.
+#[derive(Debug, Clone, Default)]
+pub struct CategoryTree {
+    nodes: BTreeMap<String, CategoryNode>,
+}
+
+impl CategoryTree {
+    /// 创建空的分类树
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 添加节点，父节点须已存在
+    /// Add a node whose parent must already exist
+    pub fn add(&mut self, slug: &str, name: &str, parent: Option<&str>) -> Result<(), MarketplaceError> {
+        let valid = !slug.is_empty()
+            && slug.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+        if !valid {
+            return Err(MarketplaceError::InvalidCategory(format!("节点标识 {:?} 只能包含小写字母、数字和 -", slug)));
+        }
+        if self.nodes.contains_key(slug) {
+            return Err(MarketplaceError::InvalidCategory(format!("节点 {} 已存在", slug)));
+        }
+        if let Some(parent) = parent.filter(|parent| !self.nodes.contains_key(*parent)) {
+            return Err(MarketplaceError::UnknownCategory(parent.to_string()));
+        }
+        self.nodes.insert(slug.to_string(), CategoryNode {
+            slug: slug.to_string(),
+            name: name.to_string(),
+            parent: parent.map(str::to_string),
+        });
+        Ok(())
+    }
+
+    /// 把节点移动到新的父节点下，不能移动到自己的子树中
+    /// Move a node under a new parent; a node cannot move into its own subtree
+    pub fn move_node(&mut self, slug: &str, parent: Option<&str>) -> Result<(), MarketplaceError> {
+        if !self.nodes.contains_key(slug) {
+            return Err(MarketplaceError::UnknownCategory(slug.to_string()));
+        }
+        if let Some(parent) = parent {
+            if !self.nodes.contains_key(parent) {
+                return Err(MarketplaceError::UnknownCategory(parent.to_string()));
+            }
+            if self.ancestors(parent).any(|ancestor| ancestor == slug) {
+                return Err(MarketplaceError::InvalidCategory(format!("不能把 {} 移动到自己的子树 {} 中", slug, parent)));
+            }
+        }
+        if let Some(node) = self.nodes.get_mut(slug) {
+            node.parent = parent.map(str::to_string);
+        }
+        Ok(())
+    }
+
+    /// 获取节点
+    pub fn get(&self, slug: &str) -> Option<&CategoryNode> {
+        self.nodes.get(slug)
+    }
+
+    /// 全部节点，父节点排在子节点之前
+    /// Every node, parents before their children
+    pub fn nodes(&self) -> Vec<&CategoryNode> {
+        let mut ordered = Vec::with_capacity(self.nodes.len());
+        let mut pending: Vec<&CategoryNode> = self.nodes.values().filter(|node| node.parent.is_none()).collect();
+        pending.reverse();
+        while let Some(node) = pending.pop() {
+            ordered.push(node);
+            pending.extend(self.nodes.values().rev().filter(|child| child.parent.as_deref() == Some(&node.slug)));
+        }
+        ordered
+    }
+
+    /// 节点自身及其所有祖先，从节点自身开始；不在树中的标识只返回自身
+    /// The node itself followed by its ancestors; a slug missing from the tree yields only itself
+    pub fn ancestors<'a>(&'a self, slug: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        // 树中没有环，最多走过全部节点
+        std::iter::successors(Some(slug), |slug| self.nodes.get(*slug).and_then(|node| node.parent.as_deref()))
+            .take(self.nodes.len() + 1)
+    }
+
+    /// 节点自身及其所有后代的标识
+    /// The slugs of a node and all of its descendants
+    pub fn subtree(&self, slug: &str) -> HashSet<String> {
+        self.nodes.keys()
+            .filter(|candidate| self.ancestors(candidate).any(|ancestor| ancestor == slug))
+            .cloned()
+            .chain(std::iter::once(slug.to_string()))
+            .collect()
+    }
+
+    /// 节点的显示路径，如 `Runtime > SIMD`
+    /// Display path of a node, such as `Runtime > SIMD`
+    pub fn path(&self, slug: &str) -> String {
+        let mut names: Vec<&str> = self.ancestors(slug)
+            .map(|ancestor| self.nodes.get(ancestor).map_or(ancestor, |node| node.name.as_str()))
+            .collect();
+        names.reverse();
+        names.join(" > ")
+    }
+}
+
 /// 模块依赖
 /// Module Dependency
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ModuleDependency {
-    /// 依赖模块ID
-    pub module_id: String,
-    /// 版本要求
-    pub version_requirement: String,
-    /// 是否必需
-    pub required: bool,
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Dependency {
+    /// 依赖的模块名称
+    pub name: String,
+    /// 语义化版本要求，如 `^1.2`、`>=1.0, <2.0`
+    pub version_req: VersionReq,
 }
 
 /// 兼容性信息
@@ -120,6 +453,9 @@ pub struct CompatibilityInfo {
     pub min_memory: u64,
     /// 推荐内存
     pub recommended_memory: u64,
+    /// 运行所需的最低 WebAssembly 特性，取 [`WebAssembly2Features`] 的变体名称，如 `SimdInstructions`
+    #[serde(default)]
+    pub required_features: Vec<String>,
 }
 
 /// 安全扫描结果
@@ -172,6 +508,12 @@ pub struct Vulnerability {
 pub struct UserManager {
     /// 用户存储
     pub users: Arc<Mutex<HashMap<String, User>>>,
+    /// 每个用户注册的发布者公钥，包括已撤销的
+    pub publisher_keys: Arc<Mutex<HashMap<String, Vec<PublisherKey>>>>,
+    /// API 令牌，以令牌ID为键，只保存密钥的摘要
+    pub api_tokens: Arc<Mutex<HashMap<String, ApiToken>>>,
+    /// 时钟，用于令牌过期和最后使用时间
+    pub clock: Arc<dyn MarketplaceClock>,
     /// 权限管理
     pub permission_manager: PermissionManager,
 }
@@ -266,16 +608,106 @@ pub enum PermissionAction {
     Download,
 }
 
+/// API 令牌的权限范围
+/// API token scope
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum TokenScope {
+    /// 发布模块
+    Publish,
+    /// 下载模块
+    Download,
+    /// 撤回、取消撤回和弃用模块
+    Yank,
+    /// 包含全部权限范围，只能由管理员创建
+    Admin,
+}
+
+/// 已保存的 API 令牌
+/// A stored API token
+///
+/// 令牌只在创建时以明文返回一次，之后只保存其 SHA-256 摘要。
+/// The token is returned in plain text once at creation; afterwards only its SHA-256 digest is kept.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiToken {
+    /// 令牌ID，也是明文令牌的一部分
+    pub id: String,
+    /// 令牌所属的用户
+    pub user_id: String,
+    /// 权限范围
+    pub scopes: Vec<TokenScope>,
+    /// 明文令牌的 SHA-256 摘要（十六进制）
+    pub secret_hash: String,
+    /// 创建时间
+    pub created_at: SystemTime,
+    /// 过期时间，`None` 表示不过期
+    pub expires_at: Option<SystemTime>,
+    /// 撤销时间，`None` 表示仍然有效
+    pub revoked_at: Option<SystemTime>,
+    /// 最后一次通过认证的时间
+    pub last_used_at: Option<SystemTime>,
+}
+
+/// 新创建的令牌
+/// A newly issued token
+#[derive(Debug, Clone)]
+pub struct IssuedToken {
+    /// 令牌ID，用于列出和撤销
+    pub id: String,
+    /// 明文令牌，只在这里出现一次
+    pub secret: String,
+}
+
+/// 通过令牌认证的调用方
+/// A caller authenticated by token
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Principal {
+    /// 用户ID
+    pub user_id: String,
+    /// 使用的令牌ID
+    pub token_id: String,
+    /// 令牌的权限范围
+    pub scopes: Vec<TokenScope>,
+}
+
+impl Principal {
+    /// 是否拥有指定的权限范围，[`TokenScope::Admin`] 包含全部范围
+    /// Whether the principal holds the scope; [`TokenScope::Admin`] implies every scope
+    pub fn allows(&self, scope: TokenScope) -> bool {
+        self.scopes.contains(&scope) || self.scopes.contains(&TokenScope::Admin)
+    }
+
+    /// 要求指定的权限范围
+    /// Require the scope
+    pub fn require(&self, scope: TokenScope) -> Result<(), MarketplaceError> {
+        if self.allows(scope) { Ok(()) } else { Err(MarketplaceError::InsufficientScope(scope)) }
+    }
+}
+
+/// 明文令牌的前缀
+const API_TOKEN_PREFIX: &str = "wmt_";
+
 /// 评分系统
 /// Rating System
+///
+/// 每个用户对每个模块只保留一条评分，再次评分会替换原有评分。
+/// Each user keeps a single rating per module; rating again replaces the earlier one.
 #[derive(Debug)]
 pub struct RatingSystem {
-    /// 评分存储
+    /// 评分存储，按模块ID分组
     pub ratings: Arc<Mutex<HashMap<String, Vec<Rating>>>>,
+    /// 全站评分的总分和条数，随评分增删更新，作为加权评分的先验
+    totals: Arc<Mutex<RatingTotals>>,
     /// 评分配置
     pub config: RatingConfig,
 }
 
+/// 全站评分的累计值
+#[derive(Debug, Default)]
+struct RatingTotals {
+    sum: u64,
+    count: u64,
+}
+
 /// 评分
 /// Rating
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -308,8 +740,37 @@ pub struct RatingConfig {
     pub require_comment: bool,
     /// 最大评论长度
     pub max_comment_length: usize,
+    /// 加权评分中全站平均所占的虚拟评分条数，越大则评分少的模块越接近全站平均
+    pub prior_weight: f64,
+}
+
+/// 模块评分汇总
+/// Rating summary of a module
+#[derive(Debug, Clone, PartialEq)]
+pub struct RatingSummary {
+    /// 评分条数
+    pub count: u32,
+    /// 算术平均，没有评分时为 0
+    pub mean: f64,
+    /// 向全站平均收缩后的加权评分，没有评分时为 0
+    pub weighted: f64,
+    /// 各分值的评分条数，下标 0 对应最低分
+    pub histogram: Vec<u32>,
 }
 
+/// 删除用户时对其评分的处理方式
+/// What happens to a user's ratings when the user is deleted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeletedUserRatings {
+    /// 删除评分
+    Remove,
+    /// 保留分值和评论，去掉用户标识
+    Anonymize,
+}
+
+/// 匿名化评分使用的用户ID
+pub const ANONYMOUS_RATER: &str = "deleted-user";
+
 /// 下载统计
 /// Download Statistics
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -330,6 +791,203 @@ pub struct DownloadStats {
     pub download_trend: DownloadTrend,
 }
 
+/// 下载统计配置
+/// Download tracking configuration
+#[derive(Debug, Clone)]
+pub struct DownloadTrackingConfig {
+    /// 每日计数保留的天数
+    pub retention_days: u64,
+    /// 同一用户重复下载同一模块只计一次的时间窗口
+    pub dedupe_window: Duration,
+    /// 热度衰减的半衰期（天）
+    pub trending_half_life_days: f64,
+}
+
+impl Default for DownloadTrackingConfig {
+    fn default() -> Self {
+        Self {
+            retention_days: 90,
+            dedupe_window: Duration::from_secs(60),
+            trending_half_life_days: 2.0,
+        }
+    }
+}
+
+/// 模块的下载汇总
+/// Download summary of a module
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DownloadSummary {
+    /// 累计下载次数，不受保留天数限制
+    pub total: u64,
+    /// 最近 7 天（含当天）的下载次数
+    pub last_7d: u64,
+}
+
+/// 按模块版本（模块ID）和 UTC 日期分桶的下载计数
+/// Download counts per module version (module ID) bucketed by UTC day
+///
+/// 每个模块有自己的累计计数和按日期循环使用的每日桶，记录下载只对该模块的计数做原子加法，汇总和热度直接读取
+/// 这些计数；超出保留天数的桶在被新的日期复用前不再计入。同一用户在 `dedupe_window` 内对同一模块的重复下载
+/// （例如重试）不计数。
+/// Each module keeps its own running total and daily buckets reused cyclically by day, so recording a download
+/// is an atomic increment on that module's counters and summaries and trending read them directly; buckets past
+/// the retention period stop counting until a new day reuses them. Repeated downloads of a module by the same
+/// user within `dedupe_window`, such as retries, are not counted.
+#[derive(Debug)]
+pub struct DownloadTracker {
+    config: DownloadTrackingConfig,
+    /// 模块ID -> 该模块的计数
+    modules: RwLock<HashMap<String, Arc<ModuleDownloads>>>,
+}
+
+/// 一个模块的下载计数
+#[derive(Debug)]
+struct ModuleDownloads {
+    /// 累计下载次数
+    total: AtomicU64,
+    /// 第 `日期 % 桶数` 个桶保存该日期的计数，高 32 位是日期，低 32 位是次数
+    days: Box<[AtomicU64]>,
+    /// 下载过该模块的用户及其最近一次计数的时间，只在该模块内加锁
+    principals: Mutex<HashMap<String, SystemTime>>,
+}
+
+impl ModuleDownloads {
+    fn new(buckets: usize) -> Self {
+        Self {
+            total: AtomicU64::new(0),
+            days: (0..buckets).map(|_| AtomicU64::new(0)).collect(),
+            principals: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 给指定日期的桶加一，桶中是更早的日期时先清零；桶已被更晚的日期占用（时钟回拨）时不计入每日桶
+    fn increment_day(&self, day: u64) {
+        let bucket = &self.days[(day % self.days.len() as u64) as usize];
+        let mut current = bucket.load(AtomicOrdering::Relaxed);
+        loop {
+            let next = match current >> 32 {
+                bucket_day if bucket_day == day => current + u64::from((current as u32) < u32::MAX),
+                bucket_day if bucket_day < day => (day << 32) | 1,
+                _ => return,
+            };
+            match bucket.compare_exchange_weak(current, next, AtomicOrdering::Relaxed, AtomicOrdering::Relaxed) {
+                Ok(_) => return,
+                Err(actual) => current = actual,
+            }
+        }
+    }
+
+    /// 日期在 `from..=to` 内的非空桶
+    fn days_between(&self, from: u64, to: u64) -> impl Iterator<Item = (u64, u64)> + '_ {
+        self.days.iter()
+            .map(|bucket| bucket.load(AtomicOrdering::Relaxed))
+            .map(|packed| (packed >> 32, packed & u64::from(u32::MAX)))
+            .filter(move |(day, count)| *count > 0 && (from..=to).contains(day))
+    }
+}
+
+/// UTC 日期序号
+fn day_index(time: SystemTime) -> u64 {
+    time.duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default().as_secs() / 86_400
+}
+
+impl DownloadTracker {
+    /// 创建下载统计
+    pub fn new(config: DownloadTrackingConfig) -> Self {
+        Self { config, modules: RwLock::new(HashMap::new()) }
+    }
+
+    fn module(&self, module_id: &str) -> Option<Arc<ModuleDownloads>> {
+        self.modules.read().unwrap().get(module_id).cloned()
+    }
+
+    /// 保留期内最早的日期
+    fn oldest_day(&self, today: u64) -> u64 {
+        (today + 1).saturating_sub(self.config.retention_days.max(1))
+    }
+
+    /// 用户是否下载过模块
+    /// Whether a user has ever downloaded the module
+    pub fn has_downloaded(&self, module_id: &str, principal: &str) -> bool {
+        self.module(module_id).is_some_and(|module| module.principals.lock().unwrap().contains_key(principal))
+    }
+
+    /// 删除用户的下载记录，已计入的下载次数不变
+    /// Drop a user's download records, keeping the counts already recorded
+    pub fn forget_principal(&self, principal: &str) {
+        for module in self.modules.read().unwrap().values() {
+            module.principals.lock().unwrap().remove(principal);
+        }
+    }
+
+    /// 记录一次下载，在去重窗口内重复的下载返回 `false` 且不计数
+    /// Record a download, returning `false` without counting it when it repeats within the dedupe window
+    pub fn record(&self, module_id: &str, principal: &str, now: SystemTime) -> bool {
+        let module = match self.module(module_id) {
+            Some(module) => module,
+            None => self.modules.write().unwrap()
+                .entry(module_id.to_string())
+                .or_insert_with(|| Arc::new(ModuleDownloads::new(self.config.retention_days.max(1) as usize)))
+                .clone(),
+        };
+        {
+            let mut principals = module.principals.lock().unwrap();
+            let window = self.config.dedupe_window;
+            // 时钟回拨时也视为在窗口内
+            let within = |last: &SystemTime| now.duration_since(*last).map_or(true, |elapsed| elapsed < window);
+            if principals.get(principal).is_some_and(within) {
+                return false;
+            }
+            principals.insert(principal.to_string(), now);
+        }
+        module.total.fetch_add(1, AtomicOrdering::Relaxed);
+        module.increment_day(day_index(now));
+        true
+    }
+
+    /// 模块保留期内的每日计数，按日期升序
+    /// Daily counts of a module within the retention period, in ascending day order
+    pub fn daily_counts(&self, module_id: &str, now: SystemTime) -> Vec<(u64, u64)> {
+        let today = day_index(now);
+        let mut counts: Vec<(u64, u64)> = self.module(module_id)
+            .map(|module| module.days_between(self.oldest_day(today), today).collect())
+            .unwrap_or_default();
+        counts.sort_unstable();
+        counts
+    }
+
+    /// 模块的下载汇总
+    /// Download summary of a module
+    pub fn summary(&self, module_id: &str, now: SystemTime) -> DownloadSummary {
+        let today = day_index(now);
+        let week_start = self.oldest_day(today).max(today.saturating_sub(6));
+        self.module(module_id)
+            .map(|module| DownloadSummary {
+                total: module.total.load(AtomicOrdering::Relaxed),
+                last_7d: module.days_between(week_start, today).map(|(_, count)| count).sum(),
+            })
+            .unwrap_or_default()
+    }
+
+    /// 各模块的热度：每日计数乘以 `0.5^(天数 / 半衰期)` 后求和
+    /// Trending score of every module: daily counts weighted by `0.5^(age in days / half-life)` and summed
+    pub fn trending_scores(&self, now: SystemTime) -> HashMap<String, f64> {
+        let today = day_index(now);
+        let oldest = self.oldest_day(today);
+        let half_life = self.config.trending_half_life_days.max(f64::EPSILON);
+        let weights: Vec<f64> = (0..=today - oldest).map(|age| 0.5f64.powf(age as f64 / half_life)).collect();
+        self.modules.read().unwrap()
+            .iter()
+            .map(|(module_id, module)| {
+                let score = module.days_between(oldest, today)
+                    .map(|(day, count)| count as f64 * weights[(today - day) as usize])
+                    .sum();
+                (module_id.clone(), score)
+            })
+            .collect()
+    }
+}
+
 /// 下载趋势
 /// Download Trend
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -356,6 +1014,8 @@ pub struct MarketplaceConfig {
     pub allowed_licenses: Vec<String>,
     /// 自动安全扫描
     pub auto_security_scan: bool,
+    /// 是否只接受签名发布，开启后 [`ModuleMarketplaceManager::publish_module_with_token`] 返回错误
+    pub require_signatures: bool,
     /// 评分权重
     pub rating_weights: RatingWeights,
 }
@@ -384,19 +1044,258 @@ impl ModuleMarketplaceManager {
             user_manager: UserManager::new(),
             rating_system: RatingSystem::new(),
             download_stats: Arc::new(Mutex::new(HashMap::new())),
+            search_index: Arc::new(Mutex::new(SearchIndex::new())),
+            module_blobs: Arc::new(Mutex::new(HashMap::new())),
+            security_manager: None,
+            download_tracker: Arc::new(DownloadTracker::new(DownloadTrackingConfig::default())),
+            clock: Arc::new(SystemMarketplaceClock),
+            module_owners: Arc::new(Mutex::new(HashMap::new())),
+            category_tree: Arc::new(Mutex::new(CategoryTree::new())),
+            deprecations: Arc::new(Mutex::new(HashMap::new())),
+            audit_log: Arc::new(Mutex::new(Vec::new())),
+            draft_problems: Arc::new(Mutex::new(HashMap::new())),
             config,
         }
     }
 
+    /// 使用指定的时钟
+    /// Use the given clock
+    pub fn with_clock(self, clock: Arc<dyn MarketplaceClock>) -> Self {
+        let user_manager = UserManager { clock: clock.clone(), ..self.user_manager };
+        Self { clock, user_manager, ..self }
+    }
+
+    /// 用 API 令牌认证并要求指定的权限范围，返回令牌所属的用户ID
+    /// Authenticate an API token and require a scope, returning the token's user ID
+    pub fn authorize_token(&self, token: &str, scope: TokenScope) -> Result<String, MarketplaceError> {
+        let principal = self.user_manager.authenticate_token(token)?;
+        principal.require(scope)?;
+        Ok(principal.user_id)
+    }
+
+    /// 以 API 令牌发布模块，令牌须有 [`TokenScope::Publish`]
+    /// Publish a module with an API token holding [`TokenScope::Publish`]
+    #[allow(deprecated)]
+    pub fn publish_module_with_token(&self, module: ModuleEntry, token: &str) -> Result<String, MarketplaceError> {
+        let user_id = self.authorize_token(token, TokenScope::Publish)?;
+        self.publish_module(module, &user_id)
+    }
+
+    /// 以 API 令牌发布签名模块，令牌须有 [`TokenScope::Publish`]
+    /// Publish a signed module with an API token holding [`TokenScope::Publish`]
+    #[allow(deprecated)]
+    pub fn publish_signed_with_token(
+        &self,
+        module: ModuleEntry,
+        bytes: Vec<u8>,
+        signature: &[u8],
+        token: &str,
+    ) -> Result<String, MarketplaceError> {
+        let user_id = self.authorize_token(token, TokenScope::Publish)?;
+        self.publish_signed(module, bytes, signature, &user_id)
+    }
+
+    /// 以 API 令牌按草稿模式发布模块，令牌须有 [`TokenScope::Publish`]
+    /// Publish a module in draft mode with an API token holding [`TokenScope::Publish`]
+    #[allow(deprecated)]
+    pub fn publish_draft_with_token(
+        &self,
+        module: ModuleEntry,
+        bytes: Option<Vec<u8>>,
+        token: &str,
+    ) -> Result<Vec<ValidationProblem>, MarketplaceError> {
+        let user_id = self.authorize_token(token, TokenScope::Publish)?;
+        self.publish_draft(module, bytes, &user_id)
+    }
+
+    /// 以 API 令牌下载模块，令牌须有 [`TokenScope::Download`]
+    /// Download a module with an API token holding [`TokenScope::Download`]
+    pub fn download_module_with_token(&self, module_id: &str, token: &str) -> Result<ModuleFetch, MarketplaceError> {
+        let user_id = self.authorize_token(token, TokenScope::Download)?;
+        self.download_module(module_id, &user_id)
+    }
+
+    /// 以 API 令牌撤回版本，令牌须有 [`TokenScope::Yank`]
+    /// Yank a version with an API token holding [`TokenScope::Yank`]
+    #[allow(deprecated)]
+    pub fn yank_with_token(&self, name: &str, version: &str, reason: &str, token: &str) -> Result<(), MarketplaceError> {
+        let user_id = self.authorize_token(token, TokenScope::Yank)?;
+        self.yank(name, version, reason, &user_id)
+    }
+
+    /// 以 API 令牌取消撤回版本，令牌须有 [`TokenScope::Yank`]
+    /// Un-yank a version with an API token holding [`TokenScope::Yank`]
+    #[allow(deprecated)]
+    pub fn unyank_with_token(&self, name: &str, version: &str, token: &str) -> Result<(), MarketplaceError> {
+        let user_id = self.authorize_token(token, TokenScope::Yank)?;
+        self.unyank(name, version, &user_id)
+    }
+
+    /// 以 API 令牌弃用模块，令牌须有 [`TokenScope::Yank`]
+    /// Deprecate a module with an API token holding [`TokenScope::Yank`]
+    #[allow(deprecated)]
+    pub fn deprecate_with_token(
+        &self,
+        name: &str,
+        replacement: Option<&str>,
+        message: &str,
+        token: &str,
+    ) -> Result<(), MarketplaceError> {
+        let user_id = self.authorize_token(token, TokenScope::Yank)?;
+        self.deprecate(name, replacement, message, &user_id)
+    }
+
+    /// 使用指定的下载统计配置，已有的下载记录被清空
+    /// Use the given download tracking configuration, discarding recorded downloads
+    pub fn with_download_tracking(self, config: DownloadTrackingConfig) -> Self {
+        Self { download_tracker: Arc::new(DownloadTracker::new(config)), ..self }
+    }
+
+    /// 填写条目的下载汇总和按当前全站平均计算的加权评分
+    fn with_download_summary(&self, mut module: ModuleEntry) -> ModuleEntry {
+        let summary = self.download_tracker.summary(&module.id, self.clock.now());
+        module.downloads_total = summary.total;
+        module.downloads_last_7d = summary.last_7d;
+        module.weighted_rating = self.rating_system.weighted(module.rating, module.rating_count);
+        module
+    }
+
+    /// 下载时由安全管理器按其策略的安全级别拒绝签名无效或未签名的模块
+    /// Let the security manager refuse invalid or unsigned modules on download according to its policy level
+    pub fn with_security_manager(self, security_manager: Arc<AdvancedSecurityManager>) -> Self {
+        Self { security_manager: Some(security_manager), ..self }
+    }
+
     /// 发布模块
+    ///
+    /// 以已有的模块ID发布视为更新，全文索引中替换该模块的旧内容。
+    /// Publishing under an existing module ID is an update and replaces the module's old content in the index.
+    ///
+    /// 不带模块字节发布，得到未签名的条目；配置要求签名时返回 [`MarketplaceError::SignatureRequired`]。
+    /// 未通过发布检查时返回 [`MarketplaceError::ValidationFailed`]，列出全部问题。
+    /// Publishes without module bytes, producing an unsigned entry; fails with
+    /// [`MarketplaceError::SignatureRequired`] when the configuration requires signatures, and with
+    /// [`MarketplaceError::ValidationFailed`] listing every problem when the publish checks fail.
+    ///
+    /// 按调用方给出的用户ID发布，不检查令牌权限范围，请改用 [`Self::publish_module_with_token`]。
+    /// Trusts the caller-supplied user ID without checking a token's scope; use
+    /// [`Self::publish_module_with_token`] instead.
+    #[deprecated(note = "trusts the caller-supplied user ID; use `publish_module_with_token` instead")]
     pub fn publish_module(&self, module: ModuleEntry, user_id: &str) -> Result<String, MarketplaceError> {
+        if self.config.require_signatures {
+            return Err(MarketplaceError::SignatureRequired);
+        }
+        self.publish_entry(module, None, user_id, false)
+    }
+
+    /// 以草稿模式发布未签名的模块，返回尚未解决的问题
+    /// Publish an unsigned module in draft mode, returning the problems still to fix
+    ///
+    /// 未通过发布检查的条目仍会保存，但作为草稿不出现在搜索结果和依赖解析中；以同一模块ID再次发布修正后的
+    /// 条目即可转为正式发布。没有问题时直接正式发布并返回空列表。该模块ID已正式发布时，有问题的草稿不会替换
+    /// 正式条目，而是返回 [`MarketplaceError::ValidationFailed`]。
+    /// An entry failing the publish checks is still stored, but as a draft hidden from search and dependency
+    /// resolution; publishing a corrected entry under the same module ID makes it public. Without problems the
+    /// entry is published normally and the returned list is empty. A failing draft never replaces an entry
+    /// already published under the same ID; it fails with [`MarketplaceError::ValidationFailed`] instead.
+    ///
+    /// 按调用方给出的用户ID操作，不检查令牌权限范围，请改用 [`Self::publish_draft_with_token`]。
+    /// Trusts the caller-supplied user ID without checking a token's scope; use [`Self::publish_draft_with_token`] instead.
+    #[deprecated(note = "trusts the caller-supplied user ID; use `publish_draft_with_token` instead")]
+    pub fn publish_draft(
+        &self,
+        module: ModuleEntry,
+        bytes: Option<Vec<u8>>,
+        user_id: &str,
+    ) -> Result<Vec<ValidationProblem>, MarketplaceError> {
+        if self.config.require_signatures {
+            return Err(MarketplaceError::SignatureRequired);
+        }
+        let module_id = self.publish_entry(module, bytes, user_id, true)?;
+        Ok(self.draft_problems.lock().unwrap().get(&module_id).cloned().unwrap_or_default())
+    }
+
+    /// 发布带分离式签名的模块
+    /// Publish a module with a detached signature
+    ///
+    /// `signature` 是发布者用已注册的 Ed25519 私钥对 [`signing_payload`] 的签名；没有任何有效公钥能验证时返回
+    /// [`MarketplaceError::InvalidSignature`]。模块字节和签名随条目保存，模块大小取自字节长度。
+    /// `signature` is the publisher's Ed25519 signature over [`signing_payload`] made with a registered key; the
+    /// publish fails with [`MarketplaceError::InvalidSignature`] when no active key verifies it. The bytes and
+    /// signature are stored with the entry, and the module size is taken from the bytes.
+    ///
+    /// 按调用方给出的用户ID操作，不检查令牌权限范围，请改用 [`Self::publish_signed_with_token`]。
+    /// Trusts the caller-supplied user ID without checking a token's scope; use [`Self::publish_signed_with_token`] instead.
+    #[deprecated(note = "trusts the caller-supplied user ID; use `publish_signed_with_token` instead")]
+    pub fn publish_signed(
+        &self,
+        mut module: ModuleEntry,
+        bytes: Vec<u8>,
+        signature: &[u8],
+        user_id: &str,
+    ) -> Result<String, MarketplaceError> {
+        module.size = bytes.len() as u64;
+        let payload = signing_payload(&module, &bytes);
+        let key = self.user_manager.active_publisher_keys(user_id).into_iter()
+            .find(|key| UnparsedPublicKey::new(&ED25519, &key.public_key).verify(&payload, signature).is_ok())
+            .ok_or_else(|| MarketplaceError::InvalidSignature(format!("用户 {} 的有效公钥都无法验证该签名", user_id)))?;
+        module.signature = Some(ModuleSignature {
+            signer: user_id.to_string(),
+            key_id: key.key_id,
+            signature: signature.to_vec(),
+        });
+        self.publish_entry(module, Some(bytes), user_id, false)
+    }
+
+    /// 校验条目的签名
+    /// Verify an entry's signature
+    ///
+    /// 签名密钥须属于签名者且未被撤销，签名按存储的模块字节和条目当前的元数据校验。
+    /// The signing key must belong to the signer and not be revoked, and the signature is checked against the
+    /// stored module bytes and the entry's current metadata.
+    pub fn verify(&self, entry: &ModuleEntry) -> VerificationStatus {
+        let Some(signature) = &entry.signature else {
+            return VerificationStatus::Unsigned;
+        };
+        let Some(key) = self.user_manager.active_publisher_keys(&signature.signer).into_iter()
+            .find(|key| key.key_id == signature.key_id)
+        else {
+            return VerificationStatus::UnknownKey;
+        };
+        let blobs = self.module_blobs.lock().unwrap();
+        let Some(bytes) = blobs.get(&entry.id) else {
+            return VerificationStatus::BadSignature;
+        };
+        let payload = signing_payload(entry, bytes);
+        match UnparsedPublicKey::new(&ED25519, &key.public_key).verify(&payload, &signature.signature) {
+            Ok(()) => VerificationStatus::Verified,
+            Err(_) => VerificationStatus::BadSignature,
+        }
+    }
+
+    fn publish_entry(
+        &self,
+        mut module: ModuleEntry,
+        bytes: Option<Vec<u8>>,
+        user_id: &str,
+        draft: bool,
+    ) -> Result<String, MarketplaceError> {
         // 检查用户权限
         if !self.user_manager.has_permission(user_id, "module", PermissionAction::Publish) {
             return Err(MarketplaceError::PermissionDenied);
         }
+        // 已有所有者的模块名只能由所有者发布新版本
+        if self.module_owners.lock().unwrap().get(&module.name).is_some_and(|owner| owner != user_id) {
+            return Err(MarketplaceError::PermissionDenied);
+        }
 
-        // 验证模块
-        self.validate_module(&module)?;
+        // 验证模块，草稿模式下保留问题列表；有问题的草稿不能替换已正式发布的条目
+        let problems = self.validation_problems(&module, bytes.as_deref());
+        let replaces_published = || self.registry.lock().unwrap().get(&module.id).is_some_and(|existing| !existing.draft);
+        if !problems.is_empty() && (!draft || replaces_published()) {
+            return Err(MarketplaceError::ValidationFailed(problems));
+        }
+        module.draft = !problems.is_empty();
 
         // 安全扫描
         if self.config.auto_security_scan {
@@ -407,9 +1306,30 @@ impl ModuleMarketplaceManager {
             }
         }
 
+        let mut draft_problems = self.draft_problems.lock().unwrap();
+        if problems.is_empty() {
+            draft_problems.remove(&module.id);
+        } else {
+            draft_problems.insert(module.id.clone(), problems);
+        }
+        drop(draft_problems);
+
+        self.module_owners.lock().unwrap().entry(module.name.clone()).or_insert_with(|| user_id.to_string());
+        Ok(self.insert_entry(module, bytes))
+    }
+
+    /// 把已验证的条目写入注册表、搜索索引和模块字节存储，并初始化下载统计
+    fn insert_entry(&self, module: ModuleEntry, bytes: Option<Vec<u8>>) -> String {
         // 添加到注册表
         let module_id = module.id.clone();
         let mut registry = self.registry.lock().unwrap();
+        self.search_index.lock().unwrap().insert(&module);
+        let mut module_blobs = self.module_blobs.lock().unwrap();
+        match bytes {
+            Some(bytes) => module_blobs.insert(module_id.clone(), bytes),
+            None => module_blobs.remove(&module_id),
+        };
+        drop(module_blobs);
         registry.insert(module_id.clone(), module);
 
         // 初始化下载统计
@@ -424,51 +1344,254 @@ impl ModuleMarketplaceManager {
             download_trend: DownloadTrend::Stable,
         });
 
-        Ok(module_id)
+        module_id
     }
 
-    /// 搜索模块
-    pub fn search_modules(&self, query: &SearchQuery) -> Result<Vec<ModuleEntry>, MarketplaceError> {
+    /// 导出目录中选中的模块，供离线镜像导入
+    /// Export the selected modules of the catalog for an offline mirror to import
+    ///
+    /// 导出包含条目元数据、按内容寻址的模块字节、签名者的有效公钥、模块所有者、弃用信息、用到的分类和分类树节点。
+    /// The bundle holds the entry metadata, content-addressed module bytes, the signers' active public keys,
+    /// module owners, deprecations, and the categories and category tree nodes in use.
+    pub fn export_catalog(&self, filter: &CatalogFilter) -> CatalogBundle {
         let registry = self.registry.lock().unwrap();
-        let mut results: Vec<ModuleEntry> = registry.values().cloned().collect();
+        let module_blobs = self.module_blobs.lock().unwrap();
+        let mut modules: Vec<&ModuleEntry> = registry.values().filter(|module| filter.matches(module)).collect();
+        modules.sort_by(|a, b| a.id.cmp(&b.id));
+
+        let mut bundle = CatalogBundle { exported_at: self.clock.now(), ..CatalogBundle::default() };
+        for module in modules {
+            let blob = module_blobs.get(&module.id).map(|bytes| {
+                let digest = hex(&Sha256::digest(bytes));
+                bundle.blobs.entry(digest.clone()).or_insert_with(|| bytes.clone());
+                digest
+            });
+            if let Some(signature) = &module.signature {
+                let bundled = bundle.publisher_keys.iter()
+                    .any(|key| key.user_id == signature.signer && key.key.key_id == signature.key_id);
+                let key = self.user_manager.active_publisher_keys(&signature.signer).into_iter()
+                    .find(|key| key.key_id == signature.key_id);
+                if let Some(key) = key.filter(|_| !bundled) {
+                    bundle.publisher_keys.push(BundledPublisherKey { user_id: signature.signer.clone(), key });
+                }
+            }
+            if let Some(owner) = self.module_owners.lock().unwrap().get(&module.name) {
+                bundle.owners.insert(module.name.clone(), owner.clone());
+            }
+            if let Some(deprecation) = self.deprecations.lock().unwrap().get(&module.name) {
+                bundle.deprecations.insert(module.name.clone(), deprecation.clone());
+            }
+            if !bundle.categories.contains(&module.category) {
+                bundle.categories.push(module.category.clone());
+            }
+            bundle.entries.push(BundledModule { module: module.clone(), blob });
+        }
 
-        // 应用搜索过滤器
-        if let Some(category) = &query.category {
-            results.retain(|module| module.category == *category);
+        let category_tree = self.category_tree.lock().unwrap();
+        let used: HashSet<&str> = bundle.entries.iter()
+            .flat_map(|bundled| bundled.module.categories.iter())
+            .flat_map(|slug| category_tree.ancestors(slug))
+            .collect();
+        bundle.category_nodes = category_tree.nodes().into_iter()
+            .filter(|node| used.contains(node.slug.as_str()))
+            .cloned()
+            .collect();
+        drop(category_tree);
+        bundle
+    }
+
+    /// 导入导出的目录
+    /// Import an exported catalog
+    ///
+    /// 每个条目先独立校验：模块字节须与摘要一致，条目须通过本市场的发布检查，签名须能用本地已信任的签名者公钥验证。
+    /// 目录随附的公钥不会被安装或使用，镜像须事先通过 [`UserManager::register_publisher_key`] 信任签名者的密钥。
+    /// 校验通过后才写入，因此失败的条目不会影响已有目录；已存在的版本按 `policy` 跳过或覆盖，
+    /// 但未签名的条目不能覆盖已签名的版本。
+    /// Every entry is checked on its own first: the bytes must match their digest, the entry must pass this
+    /// marketplace's publish checks and its signature must verify with a key the signer already has registered
+    /// locally. Keys shipped in the bundle are never installed or used; a mirror trusts a signer's key through
+    /// [`UserManager::register_publisher_key`] beforehand. Only then is an entry written, so failed entries leave
+    /// the existing catalog untouched; versions already present are skipped or overwritten according to `policy`,
+    /// except that an unsigned entry never replaces a signed one.
+    pub fn import_catalog(&self, bundle: &CatalogBundle, policy: ImportPolicy) -> Result<ImportReport, MarketplaceError> {
+        if bundle.format_version != CATALOG_FORMAT_VERSION {
+            return Err(MarketplaceError::InvalidCatalog(format!("不支持的目录格式版本 {}", bundle.format_version)));
         }
 
-        if let Some(tags) = &query.tags {
-            results.retain(|module| {
-                tags.iter().any(|tag| module.tags.contains(tag))
-            });
+        // 先补上本地没有的分类树节点，已有节点保持本地的位置
+        {
+            let mut category_tree = self.category_tree.lock().unwrap();
+            for node in &bundle.category_nodes {
+                if category_tree.get(&node.slug).is_none()
+                    && let Err(error) = category_tree.add(&node.slug, &node.name, node.parent.as_deref())
+                {
+                    log::warn!("无法导入分类 {}: {}", node.slug, error);
+                }
+            }
         }
 
-        if let Some(min_rating) = query.min_rating {
-            results.retain(|module| module.rating >= min_rating);
+        // 同名模块按版本从低到高导入，目录中靠前的新版本不会被后面的旧版本覆盖
+        let mut entries: Vec<&BundledModule> = bundle.entries.iter().collect();
+        entries.sort_by_cached_key(|bundled| (bundled.module.name.clone(), Version::parse(&bundled.module.version).ok()));
+
+        let mut report = ImportReport::default();
+        for bundled in entries {
+            let module_id = bundled.module.id.clone();
+            let outcome = match self.check_import(bundle, bundled, policy) {
+                Ok(Some(reason)) => ImportOutcome::Skipped { reason },
+                Ok(None) => {
+                    if let Some(owner) = bundle.owners.get(&bundled.module.name) {
+                        self.module_owners.lock().unwrap().entry(bundled.module.name.clone()).or_insert_with(|| owner.clone());
+                    }
+                    if let Some(deprecation) = bundle.deprecations.get(&bundled.module.name) {
+                        self.deprecations.lock().unwrap().entry(bundled.module.name.clone()).or_insert_with(|| deprecation.clone());
+                    }
+                    let bytes = bundled.blob.as_ref().map(|digest| bundle.blobs[digest].clone());
+                    self.insert_entry(bundled.module.clone(), bytes);
+                    ImportOutcome::Imported
+                }
+                Err(error) => ImportOutcome::Failed { reason: error.to_string() },
+            };
+            report.entries.push(ImportedEntry { module_id, outcome });
         }
+        Ok(report)
+    }
 
-        // 排序
-        match query.sort_by {
-            SortBy::Rating => results.sort_by(|a, b| b.rating.partial_cmp(&a.rating).unwrap()),
-            SortBy::Downloads => results.sort_by(|a, b| b.download_count.cmp(&a.download_count)),
-            SortBy::Recent => results.sort_by(|a, b| b.updated_at.cmp(&a.updated_at)),
-            SortBy::Name => results.sort_by(|a, b| a.name.cmp(&b.name)),
+    /// 校验一个待导入的条目，返回 `Some(原因)` 表示跳过；覆盖模式下也不会用旧版本替换较新的已有条目
+    fn check_import(
+        &self,
+        bundle: &CatalogBundle,
+        bundled: &BundledModule,
+        policy: ImportPolicy,
+    ) -> Result<Option<String>, MarketplaceError> {
+        let module = &bundled.module;
+        let existing_signed = match self.registry.lock().unwrap().get(&module.id) {
+            Some(_) if policy == ImportPolicy::SkipExisting => return Ok(Some(format!("模块 {} 已存在", module.id))),
+            Some(existing) if is_older(&module.version, &existing.version) => {
+                return Ok(Some(format!("模块 {} 已有更新的版本 {}", module.id, existing.version)));
+            }
+            Some(existing) => existing.signature.is_some(),
+            None => false,
+        };
+        if existing_signed && module.signature.is_none() {
+            return Err(MarketplaceError::InvalidSignature(format!("未签名的条目不能覆盖已签名的模块 {}", module.id)));
         }
+        let bytes = match &bundled.blob {
+            Some(digest) => {
+                let bytes = bundle.blobs.get(digest)
+                    .ok_or_else(|| MarketplaceError::InvalidCatalog(format!("缺少模块字节 {}", digest)))?;
+                if hex(&Sha256::digest(bytes)) != *digest {
+                    return Err(MarketplaceError::InvalidCatalog(format!("模块字节与摘要 {} 不符", digest)));
+                }
+                Some(bytes)
+            }
+            None => None,
+        };
+        self.validate_module(module, bytes.map(Vec::as_slice))?;
+
+        let Some(signature) = &module.signature else {
+            return if self.config.require_signatures { Err(MarketplaceError::SignatureRequired) } else { Ok(None) };
+        };
+        // 只信任本地登记且未撤销的密钥，目录随附的公钥可能由任何人生成
+        let key = self.user_manager.active_publisher_keys(&signature.signer).into_iter()
+            .find(|key| key.key_id == signature.key_id)
+            .ok_or(MarketplaceError::PublisherKeyNotFound)?;
+        let bytes = bytes.ok_or_else(|| MarketplaceError::InvalidSignature("签名的模块缺少模块字节".to_string()))?;
+        UnparsedPublicKey::new(&ED25519, &key.public_key)
+            .verify(&signing_payload(module, bytes), &signature.signature)
+            .map_err(|_| MarketplaceError::InvalidSignature(format!("模块 {} 的签名无法验证", module.id)))?;
+        Ok(None)
+    }
+
+    /// 搜索模块
+    ///
+    /// 有关键词时只返回包含全部关键词的模块，并按 BM25 计算相关性分数；没有关键词时返回全部模块。
+    /// 排序相同时按 `secondary_sort` 排序，按相关性排序时默认以下载量作为第二排序键，最后按模块ID排序，
+    /// 因此顺序是确定的。
+    /// With keywords, only modules containing every keyword are returned, scored with BM25; without keywords
+    /// every module is returned. Ties are ordered by `secondary_sort`, which defaults to downloads when sorting by
+    /// relevance, and finally by module ID, so the order is deterministic.
+    ///
+    /// 结果按 `page_size` 分页。`next_cursor` 记录本页最后一项的排序键，下一页从排在它之后的模块开始，
+    /// 因此翻页期间发布的模块不会使已返回的模块重复出现或被跳过；相关性和热度分数随时间变化，按它们排序时只能保证近似稳定。
+    /// 游标只能用于过滤条件和排序方式相同的查询，否则返回 [`MarketplaceError::InvalidCursor`]。
+    /// Results are paged by `page_size`. `next_cursor` records the sort keys of the page's last item and the next
+    /// page starts after it, so modules published mid-walk never duplicate or skip already returned ones;
+    /// relevance and trending scores shift over time, so pages sorted by them are only approximately stable.
+    /// A cursor is only valid for a query with the same filters and sort, and fails otherwise with
+    /// [`MarketplaceError::InvalidCursor`].
+    pub fn search_modules(&self, query: &SearchQuery) -> Result<SearchResults, MarketplaceError> {
+        let fingerprint = query.fingerprint();
+        let after = query.cursor.as_deref()
+            .map(|cursor| SearchCursor::decode(cursor, &fingerprint))
+            .transpose()?;
+
+        // 先查询索引，没有匹配时不必锁定注册表
+        let scores: Option<HashMap<String, f64>> = match query.keywords.as_deref().filter(|keywords| !keywords.trim().is_empty()) {
+            Some(keywords) => {
+                let hits = self.search_index.lock().unwrap().search(keywords);
+                if hits.is_empty() {
+                    return Ok(SearchResults::default());
+                }
+                Some(hits.into_iter().collect())
+            }
+            None => None,
+        };
 
-        // 分页
-        let start = query.page * query.page_size;
-        let end = start + query.page_size;
-        if start < results.len() {
-            results = results[start..end.min(results.len())].to_vec();
+        let secondary = query.secondary_sort
+            .or((query.sort_by == SortBy::Relevance).then_some(SortBy::Downloads));
+        let sorts: Vec<SortBy> = std::iter::once(query.sort_by).chain(secondary).collect();
+        let trending = if sorts.contains(&SortBy::Trending) {
+            self.download_tracker.trending_scores(self.clock.now())
         } else {
-            results.clear();
+            HashMap::new()
+        };
+
+        let category_tree = self.category_tree.lock().unwrap().clone();
+        let subtrees: HashSet<String> = query.categories.iter().flat_map(|slug| category_tree.subtree(slug)).collect();
+
+        // 只克隆返回的一页
+        let registry = self.registry.lock().unwrap();
+        let candidates: Vec<(&ModuleEntry, f64)> = match &scores {
+            Some(scores) => scores.iter()
+                .filter_map(|(module_id, score)| registry.get(module_id).map(|module| (module, *score)))
+                .collect(),
+            None => registry.values().map(|module| (module, 0.0)).collect(),
+        };
+        let mut matches: Vec<(Vec<SortKey>, &ModuleEntry)> = candidates.into_iter()
+            .filter(|(module, _)| !module.yanked && !module.draft && query.matches(module, &subtrees))
+            .map(|(module, score)| (sorts.iter().map(|sort| sort.key(module, score, &trending, &self.rating_system)).collect(), module))
+            .collect();
+        let total_estimate = matches.len();
+        let mut facets = FacetCounts::default();
+        for (_, module) in &matches {
+            facets.add(module, &category_tree);
+        }
+        if let Some(after) = &after {
+            matches.retain(|(keys, module)| compare_position(keys, &module.id, &after.keys, &after.id) == Ordering::Greater);
         }
+        matches.sort_by(|(a_keys, a), (b_keys, b)| compare_position(a_keys, &a.id, b_keys, &b.id));
 
-        Ok(results)
+        let page_size = query.page_size.max(1);
+        let next_cursor = matches.get(page_size).and(matches.get(page_size - 1)).map(|(keys, module)| {
+            SearchCursor { query: fingerprint, keys: keys.clone(), id: module.id.clone() }.encode()
+        });
+        let items: Vec<ModuleEntry> = matches.into_iter().take(page_size).map(|(_, module)| module.clone()).collect();
+        drop(registry);
+        let items = items.into_iter().map(|module| self.with_download_summary(module)).collect();
+        Ok(SearchResults { items, next_cursor, total_estimate, facets })
     }
 
     /// 下载模块
-    pub fn download_module(&self, module_id: &str, user_id: &str) -> Result<ModuleEntry, MarketplaceError> {
+    ///
+    /// 下载前校验签名；配置了安全管理器时由其按安全级别决定是否拒绝，否则签名错误只记录警告。
+    /// The signature is verified first; with a security manager configured it decides by security level whether
+    /// to refuse, otherwise bad signatures are only logged.
+    ///
+    /// 同一用户在去重窗口内重复下载同一模块只计一次。撤回的版本仍可下载，结果中附带撤回原因。
+    /// Repeated downloads of a module by the same user within the dedupe window count once. Yanked versions can
+    /// still be downloaded, with the yank reason among the warnings.
+    pub fn download_module(&self, module_id: &str, user_id: &str) -> Result<ModuleFetch, MarketplaceError> {
         // 检查用户权限
         if !self.user_manager.has_permission(user_id, "module", PermissionAction::Download) {
             return Err(MarketplaceError::PermissionDenied);
@@ -479,60 +1602,416 @@ impl ModuleMarketplaceManager {
         let module = registry.get_mut(module_id)
             .ok_or(MarketplaceError::ModuleNotFound)?;
 
-        // 更新下载统计
-        module.download_count += 1;
-        let mut download_stats = self.download_stats.lock().unwrap();
-        if let Some(stats) = download_stats.get_mut(module_id) {
-            stats.total_downloads += 1;
-            stats.today_downloads += 1;
-            stats.week_downloads += 1;
-            stats.month_downloads += 1;
-            stats.last_download = Some(SystemTime::now());
+        // 校验签名
+        let status = self.verify(module);
+        match &self.security_manager {
+            Some(security_manager) => security_manager.check_integrity(module_id, status)
+                .map_err(|error| MarketplaceError::IntegrityCheckFailed(error.to_string()))?,
+            None if status == VerificationStatus::BadSignature => log::warn!("模块 {} 的签名校验失败", module_id),
+            None => {}
         }
 
-        Ok(module.clone())
-    }
-
-    /// 评分模块
-    pub fn rate_module(&self, module_id: &str, user_id: &str, rating: Rating) -> Result<(), MarketplaceError> {
-        // 检查用户权限
-        if !self.user_manager.has_permission(user_id, "module", PermissionAction::Rate) {
-            return Err(MarketplaceError::PermissionDenied);
+        // 更新下载统计
+        let now = self.clock.now();
+        if self.download_tracker.record(module_id, user_id, now) {
+            module.download_count += 1;
+            let mut download_stats = self.download_stats.lock().unwrap();
+            if let Some(stats) = download_stats.get_mut(module_id) {
+                stats.total_downloads += 1;
+                stats.today_downloads += 1;
+                stats.week_downloads += 1;
+                stats.month_downloads += 1;
+                stats.last_download = Some(now);
+            }
         }
 
-        // 验证评分
-        if rating.score < self.rating_system.config.min_score || 
-           rating.score > self.rating_system.config.max_score {
-            return Err(MarketplaceError::InvalidRating);
-        }
+        let module = module.clone();
+        drop(registry);
+        Ok(self.fetched(self.with_download_summary(module)))
+    }
 
-        // 添加评分
-        self.rating_system.add_rating(module_id, rating)?;
+    /// 获取模块，结果中附带撤回和弃用警告
+    /// Get a module with its yank and deprecation warnings
+    pub fn get_module(&self, module_id: &str) -> Result<ModuleFetch, MarketplaceError> {
+        let module = self.registry.lock().unwrap()
+            .get(module_id)
+            .cloned()
+            .ok_or(MarketplaceError::ModuleNotFound)?;
+        Ok(self.fetched(self.with_download_summary(module)))
+    }
 
-        // 更新模块评分
-        self.update_module_rating(module_id)?;
+    /// 撤回模块的一个版本
+    /// Yank one version of a module
+    ///
+    /// 撤回的版本不再出现在搜索结果中，依赖解析只在被精确指定时选中它，下载时附带撤回原因。
+    /// 只有模块所有者或管理员可以撤回，操作记入审计日志。
+    /// A yanked version disappears from search, is only chosen by dependency resolution when pinned exactly, and
+    /// carries the reason when downloaded. Only the module owner or an administrator may yank, and the action is
+    /// recorded in the audit log.
+    ///
+    /// 按调用方给出的用户ID操作，不检查令牌权限范围，请改用 [`Self::yank_with_token`]。
+    /// Trusts the caller-supplied user ID without checking a token's scope; use [`Self::yank_with_token`] instead.
+    #[deprecated(note = "trusts the caller-supplied user ID; use `yank_with_token` instead")]
+    pub fn yank(&self, name: &str, version: &str, reason: &str, user_id: &str) -> Result<(), MarketplaceError> {
+        self.set_yanked(name, version, Some(reason), user_id)?;
+        self.audit(user_id, name, AuditAction::Yank { version: version.to_string(), reason: reason.to_string() });
+        Ok(())
+    }
 
+    /// 取消撤回模块的一个版本
+    /// Un-yank one version of a module
+    ///
+    /// 按调用方给出的用户ID操作，不检查令牌权限范围，请改用 [`Self::unyank_with_token`]。
+    /// Trusts the caller-supplied user ID without checking a token's scope; use [`Self::unyank_with_token`] instead.
+    #[deprecated(note = "trusts the caller-supplied user ID; use `unyank_with_token` instead")]
+    pub fn unyank(&self, name: &str, version: &str, user_id: &str) -> Result<(), MarketplaceError> {
+        self.set_yanked(name, version, None, user_id)?;
+        self.audit(user_id, name, AuditAction::Unyank { version: version.to_string() });
         Ok(())
     }
 
+    /// 弃用模块的全部版本，之后每次获取都附带弃用说明和可选的替代模块
+    /// Deprecate every version of a module; each later fetch carries the message and optional replacement
+    ///
+    /// 只有模块所有者或管理员可以弃用，操作记入审计日志。
+    /// Only the module owner or an administrator may deprecate, and the action is recorded in the audit log.
+    ///
+    /// 按调用方给出的用户ID操作，不检查令牌权限范围，请改用 [`Self::deprecate_with_token`]。
+    /// Trusts the caller-supplied user ID without checking a token's scope; use [`Self::deprecate_with_token`] instead.
+    #[deprecated(note = "trusts the caller-supplied user ID; use `deprecate_with_token` instead")]
+    pub fn deprecate(
+        &self,
+        name: &str,
+        replacement: Option<&str>,
+        message: &str,
+        user_id: &str,
+    ) -> Result<(), MarketplaceError> {
+        if !self.registry.lock().unwrap().values().any(|module| module.name == name) {
+            return Err(MarketplaceError::ModuleNotFound);
+        }
+        self.authorize_owner(name, user_id)?;
+        self.deprecations.lock().unwrap().insert(name.to_string(), Deprecation {
+            message: message.to_string(),
+            replacement: replacement.map(str::to_string),
+            deprecated_at: self.clock.now(),
+        });
+        self.audit(user_id, name, AuditAction::Deprecate {
+            message: message.to_string(),
+            replacement: replacement.map(str::to_string),
+        });
+        Ok(())
+    }
+
+    /// 审计日志的副本，按时间先后排列
+    /// A copy of the audit log in chronological order
+    pub fn audit_records(&self) -> Vec<AuditRecord> {
+        self.audit_log.lock().unwrap().clone()
+    }
+
+    fn set_yanked(&self, name: &str, version: &str, reason: Option<&str>, user_id: &str) -> Result<(), MarketplaceError> {
+        let module_id = self.registry.lock().unwrap()
+            .values()
+            .find(|module| module.name == name && module.version == version)
+            .map(|module| module.id.clone())
+            .ok_or(MarketplaceError::ModuleNotFound)?;
+        self.authorize_owner(name, user_id)?;
+        if let Some(module) = self.registry.lock().unwrap().get_mut(&module_id) {
+            module.yanked = reason.is_some();
+            module.yank_reason = reason.map(str::to_string);
+        }
+        Ok(())
+    }
+
+    /// 只有模块所有者或管理员可以修改模块状态
+    fn authorize_owner(&self, name: &str, user_id: &str) -> Result<(), MarketplaceError> {
+        let is_admin = self.user_manager.users.lock().unwrap()
+            .get(user_id)
+            .is_some_and(|user| user.roles.contains(&UserRole::Administrator));
+        let is_owner = self.module_owners.lock().unwrap().get(name).is_some_and(|owner| owner == user_id);
+        if is_admin || is_owner { Ok(()) } else { Err(MarketplaceError::PermissionDenied) }
+    }
+
+    fn audit(&self, user_id: &str, module: &str, action: AuditAction) {
+        self.audit_log.lock().unwrap().push(AuditRecord {
+            at: self.clock.now(),
+            user_id: user_id.to_string(),
+            module: module.to_string(),
+            action,
+        });
+    }
+
+    /// 模块当前的撤回和弃用警告
+    fn warnings(&self, module: &ModuleEntry) -> Vec<ModuleWarning> {
+        let mut warnings = Vec::new();
+        if module.yanked {
+            warnings.push(ModuleWarning::Yanked { reason: module.yank_reason.clone().unwrap_or_default() });
+        }
+        if let Some(deprecation) = self.deprecations.lock().unwrap().get(&module.name) {
+            warnings.push(ModuleWarning::Deprecated {
+                message: deprecation.message.clone(),
+                replacement: deprecation.replacement.clone(),
+            });
+        }
+        warnings
+    }
+
+    fn fetched(&self, module: ModuleEntry) -> ModuleFetch {
+        ModuleFetch { warnings: self.warnings(&module), module }
+    }
+
+    /// 评分模块
+    ///
+    /// 只有下载过模块的用户可以评分；同一用户再次评分会替换原有评分。
+    /// Only users who downloaded the module may rate it; rating again replaces the user's earlier rating.
+    pub fn rate_module(&self, module_id: &str, user_id: &str, mut rating: Rating) -> Result<(), MarketplaceError> {
+        // 检查用户权限
+        if !self.user_manager.has_permission(user_id, "module", PermissionAction::Rate) {
+            return Err(MarketplaceError::PermissionDenied);
+        }
+
+        // 验证评分
+        if rating.score < self.rating_system.config.min_score || 
+           rating.score > self.rating_system.config.max_score {
+            return Err(MarketplaceError::InvalidRating);
+        }
+
+        if !self.download_tracker.has_downloaded(module_id, user_id) {
+            return Err(MarketplaceError::NotDownloaded);
+        }
+
+        // 添加评分
+        rating.module_id = module_id.to_string();
+        rating.user_id = user_id.to_string();
+        if self.rating_system.add_rating(module_id, rating)?.is_none()
+            && let Some(user) = self.user_manager.users.lock().unwrap().get_mut(user_id)
+        {
+            user.statistics.rating_count += 1;
+        }
+
+        // 只刷新被评分的模块；其他模块的加权评分在读取和排序时按当前全站平均计算
+        self.update_module_rating(module_id);
+
+        Ok(())
+    }
+
+    /// 模块的评分汇总
+    /// Rating summary of a module
+    pub fn rating_summary(&self, module_id: &str) -> RatingSummary {
+        self.rating_system.summary(module_id)
+    }
+
+    /// 删除用户，按 `ratings` 删除或匿名化其评分并重新计算评分
+    /// Delete a user, removing or anonymizing their ratings as `ratings` says and recomputing aggregates
+    pub fn delete_user(&self, user_id: &str, ratings: DeletedUserRatings) -> Result<(), MarketplaceError> {
+        if self.user_manager.users.lock().unwrap().remove(user_id).is_none() {
+            return Err(MarketplaceError::UserNotFound);
+        }
+        self.download_tracker.forget_principal(user_id);
+        self.rating_system.release_user(user_id, ratings);
+        self.update_module_ratings();
+        Ok(())
+    }
+
+    /// 解析模块及其依赖，返回按依赖顺序排列的具体版本
+    /// Resolve a module and its dependencies into concrete versions in dependency order
+    ///
+    /// 每个模块名称只选择一个版本，优先选择满足全部要求的最高版本，必要时回溯尝试较低的版本。
+    /// 撤回的版本只有被 `=x.y.z` 精确指定时才会被选中，选中的撤回或弃用版本在结果中附带警告。要求没有交集时返回
+    /// [`MarketplaceError::DependencyConflict`]，列出每个提出要求的模块；依赖成环时返回
+    /// [`MarketplaceError::DependencyCycle`]。
+    /// Each module name resolves to a single version, preferring the highest one satisfying every requirement and
+    /// backtracking to lower versions when needed. Yanked versions are only selected when pinned exactly with
+    /// `=x.y.z`, and selected yanked or deprecated versions carry warnings. Requirements with no common version fail with [`MarketplaceError::DependencyConflict`] naming
+    /// every requester, and dependency cycles with [`MarketplaceError::DependencyCycle`].
+    pub fn resolve(&self, name: &str, version_req: &VersionReq) -> Result<ResolutionPlan, MarketplaceError> {
+        let catalog = self.resolution_catalog();
+        let root = ResolutionRequirement {
+            requester: RESOLUTION_ROOT.to_string(),
+            name: name.to_string(),
+            version_req: version_req.clone(),
+        };
+        let mut steps = 0;
+        let state = solve(&catalog, ResolutionState::default(), VecDeque::from([root]), &mut steps)?;
+        let mut plan = ResolutionPlan::build(&catalog, &state, name)?;
+        let registry = self.registry.lock().unwrap();
+        for resolved in &mut plan.modules {
+            if let Some(module) = registry.get(&resolved.module_id) {
+                resolved.warnings = self.warnings(module);
+            }
+        }
+        Ok(plan)
+    }
+
+    /// 按名称分组的全部可解析版本，每组从高到低排列
+    fn resolution_catalog(&self) -> HashMap<String, Vec<ResolutionCandidate>> {
+        let registry = self.registry.lock().unwrap();
+        let mut catalog: HashMap<String, Vec<ResolutionCandidate>> = HashMap::new();
+        for module in registry.values().filter(|module| !module.draft) {
+            let Ok(version) = Version::parse(&module.version) else {
+                continue;
+            };
+            catalog.entry(module.name.clone()).or_default().push(ResolutionCandidate {
+                module_id: module.id.clone(),
+                version,
+                yanked: module.yanked,
+                dependencies: module.dependencies.clone(),
+            });
+        }
+        for versions in catalog.values_mut() {
+            versions.sort_by(|a, b| b.version.cmp(&a.version));
+        }
+        catalog
+    }
+
     /// 验证模块
-    fn validate_module(&self, module: &ModuleEntry) -> Result<(), MarketplaceError> {
+    fn validate_module(&self, module: &ModuleEntry, bytes: Option<&[u8]>) -> Result<(), MarketplaceError> {
+        let problems = self.validation_problems(module, bytes);
+        if problems.is_empty() { Ok(()) } else { Err(MarketplaceError::ValidationFailed(problems)) }
+    }
+
+    /// 比较同一模块的两个版本
+    /// Compare two versions of a module
+    ///
+    /// 比较许可证、依赖和大小，并在两个版本都存储了可解析的模块字节时比较导出函数的签名；
+    /// 更新说明包含 `from_version` 之后直到 `to_version`（含）的每个版本。
+    /// Compares license, dependencies and size, and the signatures of exported functions when both versions have
+    /// decodable module bytes stored; the changelog covers every version after `from_version` up to and
+    /// including `to_version`.
+    pub fn diff(&self, name: &str, from_version: &str, to_version: &str) -> Result<VersionDiff, MarketplaceError> {
+        let registry = self.registry.lock().unwrap();
+        let find = |version: &str| {
+            registry.values()
+                .find(|module| module.name == name && module.version == version)
+                .ok_or(MarketplaceError::ModuleNotFound)
+        };
+        let (from, to) = (find(from_version)?, find(to_version)?);
+        let parse = |module: &ModuleEntry| {
+            Version::parse(&module.version).map_err(|error| MarketplaceError::InvalidVersion(error.to_string()))
+        };
+        let (from_semver, to_semver) = (parse(from)?, parse(to)?);
+
+        let mut dependencies = Vec::new();
+        for old in &from.dependencies {
+            match to.dependencies.iter().find(|new| new.name == old.name) {
+                None => dependencies.push(DependencyChange::Removed {
+                    name: old.name.clone(),
+                    version_req: old.version_req.clone(),
+                }),
+                Some(new) if new.version_req != old.version_req => dependencies.push(DependencyChange::Changed {
+                    name: old.name.clone(),
+                    from: old.version_req.clone(),
+                    to: new.version_req.clone(),
+                    bump: VersionBump::between(&old.version_req, &new.version_req)?,
+                }),
+                Some(_) => {}
+            }
+        }
+        for new in to.dependencies.iter().filter(|new| !from.dependencies.iter().any(|old| old.name == new.name)) {
+            dependencies.push(DependencyChange::Added { name: new.name.clone(), version_req: new.version_req.clone() });
+        }
+
+        let module_blobs = self.module_blobs.lock().unwrap();
+        let signatures = |module: &ModuleEntry| module_blobs.get(&module.id).and_then(|bytes| export_signatures(bytes).ok());
+        let exports = signatures(from).zip(signatures(to)).map(|(old, new)| {
+            let mut changes = Vec::new();
+            for (name, old_type) in &old {
+                match new.get(name) {
+                    None => changes.push(ExportChange::Removed { name: name.clone(), ty: old_type.clone() }),
+                    Some(new_type) if new_type != old_type => changes.push(ExportChange::Changed {
+                        name: name.clone(),
+                        from: old_type.clone(),
+                        to: new_type.clone(),
+                    }),
+                    Some(_) => {}
+                }
+            }
+            for (name, new_type) in new.iter().filter(|(name, _)| !old.contains_key(*name)) {
+                changes.push(ExportChange::Added { name: name.clone(), ty: new_type.clone() });
+            }
+            changes
+        });
+
+        let mut changelog: Vec<(Version, String)> = registry.values()
+            .filter(|module| module.name == name)
+            .filter_map(|module| Some((Version::parse(&module.version).ok()?, module.changelog.clone()?)))
+            .filter(|(version, _)| *version > from_semver && *version <= to_semver)
+            .collect();
+        changelog.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        Ok(VersionDiff {
+            name: name.to_string(),
+            breaking: exports.iter().flatten().any(ExportChange::is_breaking)
+                || dependencies.iter().any(|change| {
+                    matches!(change, DependencyChange::Changed { bump, .. } if bump.is_breaking())
+                }),
+            license: (from.license != to.license).then(|| (from.license.clone(), to.license.clone())),
+            dependencies,
+            exports,
+            size_delta: to.size as i64 - from.size as i64,
+            changelog,
+            from_version: from_semver,
+            to_version: to_semver,
+        })
+    }
+
+    /// 发布检查，返回发现的全部问题
+    /// Publish checks, returning every problem found
+    pub fn validation_problems(&self, module: &ModuleEntry, bytes: Option<&[u8]>) -> Vec<ValidationProblem> {
+        let mut problems = Vec::new();
+
+        // 检查必需字段
+        for (field, value) in [("name", &module.name), ("description", &module.description)] {
+            if value.trim().is_empty() {
+                problems.push(ValidationProblem::MissingField(field.to_string()));
+            }
+        }
+
+        // 版本号必须是语义化版本
+        if let Err(error) = Version::parse(&module.version) {
+            problems.push(ValidationProblem::InvalidVersion(format!("{}: {}", module.version, error)));
+        }
+
+        // 许可证必须是可识别的 SPDX 表达式，并且能以允许的许可证满足
+        match SpdxExpression::parse(&module.license) {
+            Ok(expression) if expression.is_satisfied_by(&self.config.allowed_licenses) => {}
+            Ok(_) => problems.push(ValidationProblem::LicenseNotAllowed(module.license.clone())),
+            Err(error) => problems.push(ValidationProblem::InvalidLicense(error)),
+        }
+
         // 检查模块大小
-        if module.size > self.config.max_module_size {
-            return Err(MarketplaceError::ModuleTooLarge);
+        let size = bytes.map_or(module.size, |bytes| bytes.len() as u64);
+        if size > self.config.max_module_size {
+            problems.push(ValidationProblem::ModuleTooLarge { size, limit: self.config.max_module_size });
         }
 
-        // 检查许可证
-        if !self.config.allowed_licenses.contains(&module.license) {
-            return Err(MarketplaceError::LicenseNotAllowed);
+        // 所需特性必须是已知的 WebAssembly 2.0 特性
+        for feature in &module.compatibility.required_features {
+            if serde_json::from_value::<WebAssembly2Features>(serde_json::Value::String(feature.clone())).is_err() {
+                problems.push(ValidationProblem::UnknownFeature(feature.clone()));
+            }
         }
 
-        // 检查必需字段
-        if module.name.is_empty() || module.description.is_empty() {
-            return Err(MarketplaceError::InvalidModule);
+        // 分类必须在分类树中
+        let category_tree = self.category_tree.lock().unwrap();
+        for slug in module.categories.iter().filter(|slug| category_tree.get(slug).is_none()) {
+            problems.push(ValidationProblem::UnknownCategory(slug.clone()));
         }
+        drop(category_tree);
 
-        Ok(())
+        // 声明的导出必须存在于上传的模块中
+        if !module.exports.is_empty() {
+            match bytes.map(module_exports) {
+                None => problems.push(ValidationProblem::ExportsUnverifiable),
+                Some(Err(error)) => problems.push(ValidationProblem::MalformedModule(error)),
+                Some(Ok(actual)) => problems.extend(
+                    module.exports.iter()
+                        .filter(|export| !actual.contains(*export))
+                        .map(|export| ValidationProblem::MissingExport(export.clone())),
+                ),
+            }
+        }
+
+        problems
     }
 
     /// 执行安全扫描
@@ -540,183 +2019,3141 @@ impl ModuleMarketplaceManager {
         // 简化的安全扫描实现
         // 实际应用中应该集成真实的安全扫描工具
         Ok(SecurityScanResult {
-            scan_time: SystemTime::now(),
+            scan_time: self.clock.now(),
             security_level: SecurityLevel::Low,
             vulnerabilities: Vec::new(),
             scan_tools: vec!["wasmati".to_string(), "custom_scanner".to_string()],
         })
     }
 
-    /// 更新模块评分
-    fn update_module_rating(&self, module_id: &str) -> Result<(), MarketplaceError> {
-        let ratings = self.rating_system.get_ratings(module_id)?;
-        if ratings.is_empty() {
-            return Ok(());
+    /// 更新所有模块的评分
+    fn update_module_ratings(&self) {
+        let mut registry = self.registry.lock().unwrap();
+        for (module_id, module) in registry.iter_mut() {
+            let summary = self.rating_system.summary(module_id);
+            module.rating = summary.mean;
+            module.rating_count = summary.count;
+            module.weighted_rating = summary.weighted;
         }
+    }
 
-        let total_score: f64 = ratings.iter().map(|r| r.score as f64).sum();
-        let avg_rating = total_score / ratings.len() as f64;
+    /// 更新一个模块的评分
+    fn update_module_rating(&self, module_id: &str) {
+        let summary = self.rating_system.summary(module_id);
+        if let Some(module) = self.registry.lock().unwrap().get_mut(module_id) {
+            module.rating = summary.mean;
+            module.rating_count = summary.count;
+            module.weighted_rating = summary.weighted;
+        }
+    }
+}
 
-        let mut registry = self.registry.lock().unwrap();
-        if let Some(module) = registry.get_mut(module_id) {
-            module.rating = avg_rating;
-            module.rating_count = ratings.len() as u32;
+/// 用二进制解析器读出模块的导出名称
+fn module_exports(bytes: &[u8]) -> Result<HashSet<String>, String> {
+    let mut exports = HashSet::new();
+    for payload in wasmparser::Parser::new(0).parse_all(bytes) {
+        if let wasmparser::Payload::ExportSection(reader) = payload.map_err(|error| error.to_string())? {
+            for export in reader {
+                exports.insert(export.map_err(|error| error.to_string())?.name.to_string());
+            }
         }
+    }
+    Ok(exports)
+}
 
-        Ok(())
+/// 用二进制解析器读出模块导出函数的签名
+fn export_signatures(bytes: &[u8]) -> Result<BTreeMap<String, FunctionType>, String> {
+    let value_type = |ty: &wasmparser::ValType| match ty {
+        wasmparser::ValType::I32 => Ok(ValueType::I32),
+        wasmparser::ValType::I64 => Ok(ValueType::I64),
+        wasmparser::ValType::F32 => Ok(ValueType::F32),
+        wasmparser::ValType::F64 => Ok(ValueType::F64),
+        wasmparser::ValType::V128 => Ok(ValueType::V128),
+        wasmparser::ValType::Ref(ty) if *ty == wasmparser::RefType::FUNCREF => Ok(ValueType::FuncRef),
+        wasmparser::ValType::Ref(ty) if *ty == wasmparser::RefType::EXTERNREF => Ok(ValueType::ExternRef),
+        wasmparser::ValType::Ref(ty) => Err(format!("不支持的引用类型 {}", ty)),
+    };
+    let error = |error: wasmparser::BinaryReaderError| error.to_string();
+
+    // 类型段中的非函数类型记为 None，函数索引空间先是导入的函数，再是模块定义的函数
+    let mut types: Vec<Option<FunctionType>> = Vec::new();
+    let mut functions: Vec<u32> = Vec::new();
+    let mut exports = BTreeMap::new();
+    for payload in wasmparser::Parser::new(0).parse_all(bytes) {
+        match payload.map_err(error)? {
+            wasmparser::Payload::TypeSection(reader) => {
+                for group in reader {
+                    for sub_type in group.map_err(error)?.into_types() {
+                        types.push(match &sub_type.composite_type.inner {
+                            wasmparser::CompositeInnerType::Func(func) => Some(FunctionType {
+                                params: func.params().iter().map(value_type).collect::<Result<_, _>>()?,
+                                results: func.results().iter().map(value_type).collect::<Result<_, _>>()?,
+                            }),
+                            _ => None,
+                        });
+                    }
+                }
+            }
+            wasmparser::Payload::ImportSection(reader) => {
+                for import in reader {
+                    if let wasmparser::TypeRef::Func(type_index) = import.map_err(error)?.ty {
+                        functions.push(type_index);
+                    }
+                }
+            }
+            wasmparser::Payload::FunctionSection(reader) => {
+                for type_index in reader {
+                    functions.push(type_index.map_err(error)?);
+                }
+            }
+            wasmparser::Payload::ExportSection(reader) => {
+                for export in reader {
+                    let export = export.map_err(error)?;
+                    if export.kind != wasmparser::ExternalKind::Func {
+                        continue;
+                    }
+                    let ty = functions.get(export.index as usize)
+                        .and_then(|type_index| types.get(*type_index as usize).cloned().flatten())
+                        .ok_or_else(|| format!("导出 {} 引用了不存在的函数 {}", export.name, export.index))?;
+                    exports.insert(export.name.to_string(), ty);
+                }
+            }
+            _ => {}
+        }
     }
+    Ok(exports)
 }
 
-/// 搜索查询
-/// Search Query
-#[derive(Debug, Clone)]
-pub struct SearchQuery {
-    /// 关键词
-    pub keywords: Option<String>,
-    /// 分类
-    pub category: Option<ModuleCategory>,
-    /// 标签
-    pub tags: Option<Vec<String>>,
-    /// 最小评分
-    pub min_rating: Option<f64>,
-    /// 排序方式
-    pub sort_by: SortBy,
-    /// 页码
-    pub page: usize,
-    /// 页面大小
-    pub page_size: usize,
+/// 两个版本之间的差异
+/// Differences between two versions of a module
+#[derive(Debug, Clone, PartialEq)]
+pub struct VersionDiff {
+    /// 模块名称
+    pub name: String,
+    /// 旧版本
+    pub from_version: Version,
+    /// 新版本
+    pub to_version: Version,
+    /// 存在破坏性的导出变化（删除导出或修改签名）或依赖的不兼容升级，升级前需要检查调用方
+    pub breaking: bool,
+    /// 许可证变化，`(旧, 新)`
+    pub license: Option<(String, String)>,
+    /// 依赖变化
+    pub dependencies: Vec<DependencyChange>,
+    /// 导出函数的变化，任一版本没有可解析的模块字节时为 `None`
+    pub exports: Option<Vec<ExportChange>>,
+    /// 模块大小变化（字节）
+    pub size_delta: i64,
+    /// 旧版本之后直到新版本的更新说明，按版本升序
+    pub changelog: Vec<(Version, String)>,
 }
 
-/// 排序方式
-/// Sort By
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum SortBy {
-    /// 按评分排序
-    Rating,
-    /// 按下载量排序
-    Downloads,
-    /// 按更新时间排序
-    Recent,
-    /// 按名称排序
-    Name,
+/// 依赖的变化
+/// Dependency change
+#[derive(Debug, Clone, PartialEq)]
+pub enum DependencyChange {
+    /// 新增依赖
+    Added {
+        /// 依赖名称
+        name: String,
+        /// 版本要求
+        version_req: VersionReq,
+    },
+    /// 删除依赖
+    Removed {
+        /// 依赖名称
+        name: String,
+        /// 原来的版本要求
+        version_req: VersionReq,
+    },
+    /// 版本要求变化
+    Changed {
+        /// 依赖名称
+        name: String,
+        /// 原来的版本要求
+        from: VersionReq,
+        /// 新的版本要求
+        to: VersionReq,
+        /// 版本要求下限变化的级别
+        bump: VersionBump,
+    },
 }
 
-impl Default for UserManager {
-    fn default() -> Self {
-        Self::new()
-    }
+/// 版本要求变化的语义化版本级别，按要求的下限比较
+/// Semver level of a requirement change, comparing the requirements' lower bounds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VersionBump {
+    /// 主版本号变化，或 `0.x` 的次版本号变化，不兼容
+    Major,
+    /// 次版本号变化
+    Minor,
+    /// 修订号变化
+    Patch,
+    /// 下限不变，只有运算符或上限变化
+    Other,
 }
 
-impl UserManager {
-    /// 创建新的用户管理器
-    pub fn new() -> Self {
-        Self {
-            users: Arc::new(Mutex::new(HashMap::new())),
-            permission_manager: PermissionManager::new(),
-        }
+impl VersionBump {
+    /// 比较两个版本要求的下限
+    /// Compare the lower bounds of two requirements
+    ///
+    /// 下限是满足要求的最小版本：取每个比较器下限中最大的一个，`<` 和 `<=` 不限制下限，`>` 取紧随其后的版本。
+    /// 按语义化版本的兼容规则，`0.x` 的次版本号变化和 `0.0.x` 的修订号变化视为主版本变化。
+    /// The lower bound is the smallest version satisfying the requirement: the greatest bound among the
+    /// comparators, where `<` and `<=` impose none and `>` starts at the next version. Following semver
+    /// compatibility, a minor change of `0.x` and a patch change of `0.0.x` count as major.
+    ///
+    /// `>` 比较器的下一个版本超出 `u64` 时返回 [`MarketplaceError::InvalidVersion`]。
+    /// Fails with [`MarketplaceError::InvalidVersion`] when the version after a `>` comparator overflows `u64`.
+    pub fn between(from: &VersionReq, to: &VersionReq) -> Result<Self, MarketplaceError> {
+        Ok(match (Self::lower_bound(from)?, Self::lower_bound(to)?) {
+            (a, b) if a.0 != b.0 => Self::Major,
+            (a, b) if a.1 != b.1 => if a.0 == 0 { Self::Major } else { Self::Minor },
+            (a, b) if a.2 != b.2 => if (a.0, a.1) == (0, 0) { Self::Major } else { Self::Patch },
+            _ => Self::Other,
+        })
     }
 
-    /// 检查用户权限
-    pub fn has_permission(&self, user_id: &str, resource: &str, action: PermissionAction) -> bool {
-        let users = self.users.lock().unwrap();
-        if let Some(user) = users.get(user_id) {
-            self.permission_manager.check_permission(&user.roles, resource, action)
-        } else {
-            false
+    /// 是否为不兼容的变化
+    /// Whether the change is incompatible
+    pub fn is_breaking(&self) -> bool {
+        *self == Self::Major
+    }
+
+    /// 满足版本要求的最小版本号
+    fn lower_bound(req: &VersionReq) -> Result<(u64, u64, u64), MarketplaceError> {
+        let overflow = || MarketplaceError::InvalidVersion(format!("版本要求 {} 的下限超出范围", req));
+        let mut bound = (0, 0, 0);
+        for comparator in &req.comparators {
+            let (major, minor, patch) = (comparator.major, comparator.minor, comparator.patch);
+            let lower = match comparator.op {
+                Op::Less | Op::LessEq => (0, 0, 0),
+                Op::Greater => match (minor, patch) {
+                    (Some(minor), Some(patch)) => (major, minor, patch.checked_add(1).ok_or_else(overflow)?),
+                    (Some(minor), None) => (major, minor.checked_add(1).ok_or_else(overflow)?, 0),
+                    _ => (major.checked_add(1).ok_or_else(overflow)?, 0, 0),
+                },
+                _ => (major, minor.unwrap_or(0), patch.unwrap_or(0)),
+            };
+            bound = bound.max(lower);
         }
+        Ok(bound)
     }
 }
 
-impl Default for PermissionManager {
-    fn default() -> Self {
-        Self::new()
+/// 导出函数的变化
+/// Change of an exported function
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExportChange {
+    /// 新增导出
+    Added {
+        /// 导出名称
+        name: String,
+        /// 函数签名
+        ty: FunctionType,
+    },
+    /// 删除导出，破坏性变化
+    Removed {
+        /// 导出名称
+        name: String,
+        /// 原来的函数签名
+        ty: FunctionType,
+    },
+    /// 签名变化，破坏性变化
+    Changed {
+        /// 导出名称
+        name: String,
+        /// 原来的函数签名
+        from: FunctionType,
+        /// 新的函数签名
+        to: FunctionType,
+    },
+}
+
+impl ExportChange {
+    /// 是否会破坏现有调用方
+    /// Whether existing callers break
+    pub fn is_breaking(&self) -> bool {
+        !matches!(self, Self::Added { .. })
     }
 }
 
-impl PermissionManager {
-    /// 创建新的权限管理器
-    pub fn new() -> Self {
-        Self {
-            rules: Arc::new(Mutex::new(Vec::new())),
+/// 内置可识别的 SPDX 许可证标识
+const SPDX_LICENSES: &[&str] = &[
+    "0BSD", "AGPL-3.0-only", "AGPL-3.0-or-later", "Apache-2.0", "BSD-2-Clause", "BSD-3-Clause", "BSL-1.0",
+    "CC0-1.0", "GPL-2.0-only", "GPL-2.0-or-later", "GPL-3.0-only", "GPL-3.0-or-later", "ISC",
+    "LGPL-2.1-only", "LGPL-2.1-or-later", "LGPL-3.0-only", "LGPL-3.0-or-later", "MIT", "MPL-2.0",
+    "Unlicense", "Zlib",
+];
+
+/// SPDX 许可证表达式
+/// SPDX license expression
+///
+/// 支持内置列表中的许可证标识、`AND`、`OR` 和括号，`AND` 的优先级高于 `OR`。
+/// Supports the license identifiers of the built-in list, `AND`, `OR` and parentheses, with `AND` binding
+/// tighter than `OR`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SpdxExpression {
+    /// 单个许可证
+    License(String),
+    /// 同时遵守两边
+    And(Box<SpdxExpression>, Box<SpdxExpression>),
+    /// 任选一边
+    Or(Box<SpdxExpression>, Box<SpdxExpression>),
+}
+
+impl SpdxExpression {
+    /// 解析表达式
+    /// Parse an expression
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let spaced = text.replace('(', " ( ").replace(')', " ) ");
+        let tokens: Vec<&str> = spaced.split_whitespace().collect();
+        if tokens.is_empty() {
+            return Err("许可证为空".to_string());
+        }
+        let mut position = 0;
+        let expression = Self::parse_or(&tokens, &mut position)?;
+        match tokens.get(position) {
+            None => Ok(expression),
+            Some(token) => Err(format!("许可证表达式 {:?} 在 {:?} 处有多余内容", text, token)),
         }
     }
 
-    /// 检查权限
-    pub fn check_permission(&self, roles: &[UserRole], resource: &str, action: PermissionAction) -> bool {
-        let rules = self.rules.lock().unwrap();
-        for role in roles {
-            for rule in rules.iter() {
-                if rule.role == *role && rule.resource == resource && rule.action == action {
-                    return rule.allowed;
-                }
+    fn parse_or(tokens: &[&str], position: &mut usize) -> Result<Self, String> {
+        let mut expression = Self::parse_and(tokens, position)?;
+        while tokens.get(*position) == Some(&"OR") {
+            *position += 1;
+            expression = Self::Or(Box::new(expression), Box::new(Self::parse_and(tokens, position)?));
+        }
+        Ok(expression)
+    }
+
+    fn parse_and(tokens: &[&str], position: &mut usize) -> Result<Self, String> {
+        let mut expression = Self::parse_atom(tokens, position)?;
+        while tokens.get(*position) == Some(&"AND") {
+            *position += 1;
+            expression = Self::And(Box::new(expression), Box::new(Self::parse_atom(tokens, position)?));
+        }
+        Ok(expression)
+    }
+
+    fn parse_atom(tokens: &[&str], position: &mut usize) -> Result<Self, String> {
+        let token = *tokens.get(*position).ok_or("许可证表达式不完整")?;
+        *position += 1;
+        if token == "(" {
+            let expression = Self::parse_or(tokens, position)?;
+            if tokens.get(*position) != Some(&")") {
+                return Err("许可证表达式缺少 )".to_string());
             }
+            *position += 1;
+            return Ok(expression);
+        }
+        if SPDX_LICENSES.contains(&token) {
+            Ok(Self::License(token.to_string()))
+        } else {
+            Err(format!("无法识别的 SPDX 许可证标识 {:?}", token))
         }
-        false
     }
-}
 
-impl Default for RatingSystem {
-    fn default() -> Self {
-        Self {
-            ratings: Arc::new(Mutex::new(HashMap::new())),
-            config: RatingConfig {
-                min_score: 1,
-                max_score: 5,
-                require_comment: false,
-                max_comment_length: 1000,
-            },
+    /// 只使用允许的许可证能否满足表达式
+    /// Whether the expression can be satisfied using only the allowed licenses
+    pub fn is_satisfied_by(&self, allowed: &[String]) -> bool {
+        match self {
+            Self::License(license) => allowed.contains(license),
+            Self::And(left, right) => left.is_satisfied_by(allowed) && right.is_satisfied_by(allowed),
+            Self::Or(left, right) => left.is_satisfied_by(allowed) || right.is_satisfied_by(allowed),
         }
     }
 }
 
-impl RatingSystem {
-    /// 创建新的评分系统
-    pub fn new() -> Self {
-        Self::default()
+/// 发布检查发现的问题
+/// A problem found by the publish checks
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ValidationProblem {
+    /// 必需字段为空
+    #[error("缺少字段 {0}")]
+    MissingField(String),
+    /// 版本号不是语义化版本
+    #[error("无效版本 {0}")]
+    InvalidVersion(String),
+    /// 许可证不是可识别的 SPDX 表达式
+    #[error("{0}")]
+    InvalidLicense(String),
+    /// 许可证表达式无法以允许的许可证满足
+    #[error("许可证 {0} 不允许")]
+    LicenseNotAllowed(String),
+    /// 模块超过大小上限
+    #[error("模块大小 {size} 字节超过上限 {limit} 字节")]
+    ModuleTooLarge {
+        /// 模块大小
+        size: u64,
+        /// 上限
+        limit: u64,
+    },
+    /// 所需特性不是已知的 WebAssembly 2.0 特性
+    #[error("未知的 WebAssembly 特性 {0}")]
+    UnknownFeature(String),
+    /// 分类树中没有该节点
+    #[error("未知分类 {0}")]
+    UnknownCategory(String),
+    /// 声明了导出但没有上传模块字节
+    #[error("声明了导出但没有上传模块字节，无法核对")]
+    ExportsUnverifiable,
+    /// 模块字节无法解析
+    #[error("模块字节无法解析: {0}")]
+    MalformedModule(String),
+    /// 声明的导出在模块中不存在
+    #[error("模块中没有声明的导出 {0}")]
+    MissingExport(String),
+}
+
+/// 依赖解析的结果
+/// Result of dependency resolution
+#[derive(Debug, Clone)]
+pub struct ResolutionPlan {
+    /// 解析出的模块，依赖排在依赖它的模块之前，请求的模块在最后
+    pub modules: Vec<ResolvedModule>,
+}
+
+impl ResolutionPlan {
+    /// 指定名称解析出的版本
+    pub fn version_of(&self, name: &str) -> Option<&Version> {
+        self.modules.iter().find(|module| module.name == name).map(|module| &module.version)
     }
 
-    /// 添加评分
-    pub fn add_rating(&self, module_id: &str, rating: Rating) -> Result<(), MarketplaceError> {
-        let mut ratings = self.ratings.lock().unwrap();
-        let module_ratings = ratings.entry(module_id.to_string()).or_default();
-        module_ratings.push(rating);
-        Ok(())
+    /// 从请求的模块出发按依赖深度优先排序，遇到正在访问的模块即为循环
+    fn build(
+        catalog: &HashMap<String, Vec<ResolutionCandidate>>,
+        state: &ResolutionState,
+        root: &str,
+    ) -> Result<Self, MarketplaceError> {
+        let resolved = |name: &str| &catalog[name][state.selected[name]];
+        let mut modules = Vec::new();
+        let mut done: HashMap<String, bool> = HashMap::new();
+        // 栈中每项为 (名称, 下一个要访问的依赖序号)
+        let mut stack: Vec<(String, usize)> = vec![(root.to_string(), 0)];
+        done.insert(root.to_string(), false);
+        while let Some((name, next)) = stack.last_mut() {
+            let candidate = resolved(name);
+            let Some(dependency) = candidate.dependencies.get(*next) else {
+                let name = name.clone();
+                stack.pop();
+                done.insert(name.clone(), true);
+                modules.push(ResolvedModule {
+                    version: candidate.version.clone(),
+                    module_id: candidate.module_id.clone(),
+                    dependencies: candidate.dependencies.iter().map(|dependency| dependency.name.clone()).collect(),
+                    warnings: Vec::new(),
+                    name,
+                });
+                continue;
+            };
+            *next += 1;
+            match done.get(&dependency.name) {
+                Some(true) => {}
+                Some(false) => {
+                    let start = stack.iter().position(|(name, _)| *name == dependency.name).unwrap_or(0);
+                    let cycle = stack[start..].iter()
+                        .map(|(name, _)| name.as_str())
+                        .chain(std::iter::once(dependency.name.as_str()))
+                        .map(|name| format!("{} {}", name, resolved(name).version))
+                        .collect();
+                    return Err(MarketplaceError::DependencyCycle { cycle });
+                }
+                None => {
+                    done.insert(dependency.name.clone(), false);
+                    stack.push((dependency.name.clone(), 0));
+                }
+            }
+        }
+        Ok(Self { modules })
     }
+}
 
-    /// 获取评分
-    pub fn get_ratings(&self, module_id: &str) -> Result<Vec<Rating>, MarketplaceError> {
-        let ratings = self.ratings.lock().unwrap();
-        Ok(ratings.get(module_id).cloned().unwrap_or_default())
+/// 解析出的一个模块版本
+/// One resolved module version
+#[derive(Debug, Clone)]
+pub struct ResolvedModule {
+    /// 模块名称
+    pub name: String,
+    /// 选中的版本
+    pub version: Version,
+    /// 选中版本的模块ID
+    pub module_id: String,
+    /// 直接依赖的模块名称
+    pub dependencies: Vec<String>,
+    /// 选中版本的撤回和弃用警告
+    pub warnings: Vec<ModuleWarning>,
+}
+
+/// 解析请求本身作为要求方时的名称
+const RESOLUTION_ROOT: &str = "解析请求";
+/// 依赖解析最多处理的要求数，超过时放弃回溯
+const MAX_RESOLUTION_STEPS: usize = 10_000;
+
+/// 可供选择的一个版本
+#[derive(Debug)]
+struct ResolutionCandidate {
+    module_id: String,
+    version: Version,
+    yanked: bool,
+    dependencies: Vec<Dependency>,
+}
+
+/// 待处理的版本要求
+#[derive(Debug, Clone)]
+struct ResolutionRequirement {
+    /// 提出要求的模块，如 `app 1.0.0`
+    requester: String,
+    name: String,
+    version_req: VersionReq,
+}
+
+/// 回溯搜索的状态：已选中的版本序号和每个名称收到的要求
+#[derive(Debug, Clone, Default)]
+struct ResolutionState {
+    selected: BTreeMap<String, usize>,
+    requirements: BTreeMap<String, Vec<(String, VersionReq)>>,
+}
+
+/// 依次处理待处理的要求；名称未选定时从高到低尝试候选版本，失败时回溯
+///
+/// 回溯点保存在显式的栈上而不是递归调用中，依赖链再深也不会耗尽线程栈。
+fn solve(
+    catalog: &HashMap<String, Vec<ResolutionCandidate>>,
+    state: ResolutionState,
+    pending: VecDeque<ResolutionRequirement>,
+    steps: &mut usize,
+) -> Result<ResolutionState, MarketplaceError> {
+    let mut branches: Vec<ResolutionBranch> = Vec::new();
+    let mut step = advance(catalog, state, pending, steps);
+    loop {
+        let mut failure = match step {
+            ResolutionStep::Resolved(state) => return Ok(state),
+            ResolutionStep::Failed(error @ MarketplaceError::ResolutionTooComplex(_)) => return Err(error),
+            ResolutionStep::Failed(error) => Some(error),
+            ResolutionStep::Choose(branch) => {
+                branches.push(branch);
+                None
+            }
+        };
+        // 把失败交给最近的回溯点，候选版本用尽的回溯点本身也算失败，继续交给上一层
+        step = loop {
+            let Some(branch) = branches.last_mut() else {
+                return Err(failure.expect("回溯栈为空时必然已经失败"));
+            };
+            if let Some(error) = failure.take() {
+                branch.last_error = Some(error);
+            }
+            if let Some(next) = branch.next_attempt(catalog) {
+                break advance(catalog, next.0, next.1, steps);
+            }
+            let exhausted = branches.pop().expect("上面已取得栈顶");
+            failure = Some(exhausted.last_error.unwrap_or(MarketplaceError::NoMatchingVersion {
+                name: exhausted.requirement.name,
+                requirement: exhausted.requirement.version_req.to_string(),
+                requester: exhausted.requirement.requester,
+            }));
+        };
     }
 }
 
-/// 错误类型定义
-/// Error Type Definitions
+/// 处理要求直到需要在候选版本中选择、全部处理完或失败
+fn advance(
+    catalog: &HashMap<String, Vec<ResolutionCandidate>>,
+    mut state: ResolutionState,
+    mut pending: VecDeque<ResolutionRequirement>,
+    steps: &mut usize,
+) -> ResolutionStep {
+    while let Some(requirement) = pending.pop_front() {
+        *steps += 1;
+        if *steps > MAX_RESOLUTION_STEPS {
+            return ResolutionStep::Failed(MarketplaceError::ResolutionTooComplex(MAX_RESOLUTION_STEPS));
+        }
+        let requirements = state.requirements.entry(requirement.name.clone()).or_default();
+        requirements.push((requirement.requester.clone(), requirement.version_req.clone()));
+        let versions = catalog.get(&requirement.name).map(Vec::as_slice).unwrap_or_default();
 
-#[derive(Debug, Error)]
-pub enum MarketplaceError {
-    /// 模块未找到
-    #[error("模块未找到")]
-    ModuleNotFound,
+        if let Some(&index) = state.selected.get(&requirement.name) {
+            if requirement.version_req.matches(&versions[index].version) {
+                continue;
+            }
+            return ResolutionStep::Failed(MarketplaceError::DependencyConflict {
+                name: requirement.name,
+                requirements: requirements.iter()
+                    .map(|(requester, version_req)| format!("{} 要求 {}", requester, version_req))
+                    .collect(),
+            });
+        }
+
+        let candidates = versions.iter().enumerate()
+            .filter(|(_, candidate)| {
+                requirement.version_req.matches(&candidate.version)
+                    && (!candidate.yanked || pins(&requirement.version_req, &candidate.version))
+            })
+            .map(|(index, _)| index)
+            .rev()
+            .collect();
+        return ResolutionStep::Choose(ResolutionBranch { state, pending, requirement, candidates, last_error: None });
+    }
+    ResolutionStep::Resolved(state)
+}
+
+/// [`advance`] 的结果
+enum ResolutionStep {
+    Resolved(ResolutionState),
+    Failed(MarketplaceError),
+    Choose(ResolutionBranch),
+}
+
+/// 回溯点：选择候选版本之前的状态和尚未尝试的候选版本
+struct ResolutionBranch {
+    state: ResolutionState,
+    pending: VecDeque<ResolutionRequirement>,
+    requirement: ResolutionRequirement,
+    /// 尚未尝试的候选版本序号，末尾是下一个要尝试的
+    candidates: Vec<usize>,
+    last_error: Option<MarketplaceError>,
+}
+
+impl ResolutionBranch {
+    /// 选中下一个候选版本，返回选中后的状态和加入其依赖的待处理要求
+    fn next_attempt(
+        &mut self,
+        catalog: &HashMap<String, Vec<ResolutionCandidate>>,
+    ) -> Option<(ResolutionState, VecDeque<ResolutionRequirement>)> {
+        let index = self.candidates.pop()?;
+        let name = &self.requirement.name;
+        let candidate = &catalog[name][index];
+        let mut state = self.state.clone();
+        state.selected.insert(name.clone(), index);
+        let mut pending = self.pending.clone();
+        pending.extend(candidate.dependencies.iter().map(|dependency| ResolutionRequirement {
+            requester: format!("{} {}", name, candidate.version),
+            name: dependency.name.clone(),
+            version_req: dependency.version_req.clone(),
+        }));
+        Some((state, pending))
+    }
+}
+
+/// 要求是否用 `=x.y.z` 精确指定了该版本
+fn pins(version_req: &VersionReq, version: &Version) -> bool {
+    match version_req.comparators.as_slice() {
+        [comparator] => {
+            comparator.op == Op::Exact
+                && comparator.major == version.major
+                && comparator.minor == Some(version.minor)
+                && comparator.patch == Some(version.patch)
+                && comparator.pre == version.pre
+        }
+        _ => false,
+    }
+}
+
+/// 搜索查询
+/// Search Query
+#[derive(Debug, Clone)]
+pub struct SearchQuery {
+    /// 关键词
+    pub keywords: Option<String>,
+    /// 分类
+    pub category: Option<ModuleCategory>,
+    /// 分类树节点，模块属于任一节点或其后代即满足
+    pub categories: Vec<String>,
+    /// 标签，模块须包含全部标签
+    pub tags: Option<Vec<String>>,
+    /// 最小评分
+    pub min_rating: Option<f64>,
+    /// 许可证
+    pub license: Option<String>,
+    /// 排序方式
+    pub sort_by: SortBy,
+    /// 主排序相同时使用的排序方式
+    pub secondary_sort: Option<SortBy>,
+    /// 上一页返回的 `next_cursor`，`None` 表示第一页
+    pub cursor: Option<String>,
+    /// 页面大小
+    pub page_size: usize,
+}
+
+impl SearchQuery {
+    /// 模块是否满足分类、标签、评分和许可证过滤条件，`subtrees` 为 `categories` 各节点子树的并集
+    fn matches(&self, module: &ModuleEntry, subtrees: &HashSet<String>) -> bool {
+        self.category.as_ref().is_none_or(|category| module.category == *category)
+            && (self.categories.is_empty() || module.categories.iter().any(|slug| subtrees.contains(slug)))
+            && self.tags.as_ref().is_none_or(|tags| tags.iter().all(|tag| module.tags.contains(tag)))
+            && self.min_rating.is_none_or(|min_rating| module.rating >= min_rating)
+            && self.license.as_ref().is_none_or(|license| module.license == *license)
+    }
+
+    /// 关键词、过滤条件和排序方式的摘要，游标只对摘要相同的查询有效
+    fn fingerprint(&self) -> String {
+        let canonical = serde_json::json!([
+            self.keywords,
+            self.category,
+            self.categories,
+            self.tags,
+            self.min_rating,
+            self.license,
+            self.sort_by,
+            self.secondary_sort,
+        ]);
+        let digest = Sha256::digest(canonical.to_string().as_bytes());
+        digest[..8].iter().map(|byte| format!("{:02x}", byte)).collect()
+    }
+}
+
+/// 一页搜索结果
+/// One page of search results
+#[derive(Debug, Clone, Default)]
+pub struct SearchResults {
+    /// 本页的模块
+    pub items: Vec<ModuleEntry>,
+    /// 获取下一页的游标，`None` 表示没有更多结果
+    pub next_cursor: Option<String>,
+    /// 满足查询条件的模块总数
+    pub total_estimate: usize,
+    /// 满足查询条件的全部模块（不只本页）的分面计数
+    pub facets: FacetCounts,
+}
+
+/// 搜索结果的分面计数
+/// Facet counts of a search result set
+///
+/// 分类计数包含祖先节点：属于 `simd` 的模块同时计入其父节点 `runtime`，每个模块在每个节点最多计一次。
+/// Category counts roll up to ancestors: a module in `simd` also counts towards its parent `runtime`, at most
+/// once per node.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FacetCounts {
+    /// 分类树节点 -> 模块数
+    pub categories: BTreeMap<String, usize>,
+    /// 标签 -> 模块数
+    pub tags: BTreeMap<String, usize>,
+    /// 许可证 -> 模块数
+    pub licenses: BTreeMap<String, usize>,
+}
+
+impl FacetCounts {
+    fn add(&mut self, module: &ModuleEntry, category_tree: &CategoryTree) {
+        let categories: HashSet<&str> = module.categories.iter()
+            .flat_map(|slug| category_tree.ancestors(slug))
+            .collect();
+        for slug in categories {
+            *self.categories.entry(slug.to_string()).or_default() += 1;
+        }
+        let tags: HashSet<&String> = module.tags.iter().collect();
+        for tag in tags {
+            *self.tags.entry(tag.clone()).or_default() += 1;
+        }
+        *self.licenses.entry(module.license.clone()).or_default() += 1;
+    }
+}
+
+/// 搜索结果的一个排序键
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+enum SortKey {
+    Score(f64),
+    Trending(f64),
+    Rating(f64),
+    Downloads(u64),
+    /// 更新时间距 Unix 纪元的秒数和纳秒数
+    Recent(u64, u32),
+    Name(String),
+}
+
+impl SortKey {
+    /// 按排序方向比较同一种排序键：名称升序，其余降序
+    fn cmp_in_order(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (SortKey::Score(a), SortKey::Score(b))
+            | (SortKey::Trending(a), SortKey::Trending(b))
+            | (SortKey::Rating(a), SortKey::Rating(b)) => b.total_cmp(a),
+            (SortKey::Downloads(a), SortKey::Downloads(b)) => b.cmp(a),
+            (SortKey::Recent(a_secs, a_nanos), SortKey::Recent(b_secs, b_nanos)) => (b_secs, b_nanos).cmp(&(a_secs, a_nanos)),
+            (SortKey::Name(a), SortKey::Name(b)) => a.cmp(b),
+            _ => Ordering::Equal,
+        }
+    }
+}
+
+/// 结果的先后：依次比较排序键，最后按模块ID升序
+fn compare_position(a_keys: &[SortKey], a_id: &str, b_keys: &[SortKey], b_id: &str) -> Ordering {
+    a_keys.iter().zip(b_keys)
+        .map(|(a, b)| a.cmp_in_order(b))
+        .find(|ordering| ordering.is_ne())
+        .unwrap_or(Ordering::Equal)
+        .then_with(|| a_id.cmp(b_id))
+}
+
+/// 分页游标：查询摘要和上一页最后一项的排序位置
+#[derive(Debug, Serialize, Deserialize)]
+struct SearchCursor {
+    query: String,
+    keys: Vec<SortKey>,
+    id: String,
+}
+
+impl SearchCursor {
+    fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(serde_json::to_vec(self).unwrap_or_default())
+    }
+
+    fn decode(cursor: &str, fingerprint: &str) -> Result<Self, MarketplaceError> {
+        let cursor: Self = URL_SAFE_NO_PAD.decode(cursor).ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .ok_or_else(|| MarketplaceError::InvalidCursor("游标格式无效".to_string()))?;
+        if cursor.query != fingerprint {
+            return Err(MarketplaceError::InvalidCursor("游标属于过滤条件或排序方式不同的查询".to_string()));
+        }
+        Ok(cursor)
+    }
+}
+
+/// 排序方式
+/// Sort By
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum SortBy {
+    /// 按相关性排序，没有关键词时所有模块分数相同
+    Relevance,
+    /// 按评分排序
+    Rating,
+    /// 按下载量排序
+    Downloads,
+    /// 按更新时间排序
+    Recent,
+    /// 按名称排序
+    Name,
+    /// 按热度排序：近期每日下载量按天数衰减后求和，今天的集中下载胜过过去的稳定下载
+    Trending,
+}
+
+impl SortBy {
+    /// 模块在该排序方式下的排序键
+    fn key(&self, module: &ModuleEntry, score: f64, trending: &HashMap<String, f64>, ratings: &RatingSystem) -> SortKey {
+        match self {
+            SortBy::Trending => SortKey::Trending(trending.get(&module.id).copied().unwrap_or_default()),
+            SortBy::Relevance => SortKey::Score(score),
+            SortBy::Rating => SortKey::Rating(ratings.weighted(module.rating, module.rating_count)),
+            SortBy::Downloads => SortKey::Downloads(module.download_count),
+            SortBy::Recent => {
+                let since_epoch = module.updated_at.duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default();
+                SortKey::Recent(since_epoch.as_secs(), since_epoch.subsec_nanos())
+            }
+            SortBy::Name => SortKey::Name(module.name.clone()),
+        }
+    }
+}
+
+/// 索引字段的权重，依次为名称、标签、描述
+const FIELD_BOOSTS: [f64; 3] = [3.0, 2.0, 1.0];
+/// BM25 的词频饱和参数
+const BM25_K1: f64 = 1.2;
+/// BM25 的长度归一化参数
+const BM25_B: f64 = 0.75;
+
+/// 模块全文索引
+/// Module full-text index
+///
+/// 倒排索引覆盖名称、标签和描述三个字段，按 BM25F 计分：各字段的词频按字段长度归一化后乘以字段权重再合并。
+/// 模块发布或更新时增量替换，移除时只删除该模块的词项。
+/// An inverted index over the name, tags and description, scored with BM25F: per-field term frequencies are
+/// length-normalized, multiplied by the field boost and combined. Modules are replaced incrementally when
+/// published or updated, and removing one only touches its own terms.
+#[derive(Debug, Default)]
+pub struct SearchIndex {
+    /// 词项 -> 模块ID -> 各字段中的词频
+    postings: HashMap<String, HashMap<String, [u32; 3]>>,
+    /// 已索引的模块
+    documents: HashMap<String, IndexedDocument>,
+    /// 各字段的总词数，用于计算平均长度
+    total_lengths: [u64; 3],
+}
+
+/// 已索引模块的字段长度和词项，删除时据此清理倒排表
+#[derive(Debug)]
+struct IndexedDocument {
+    lengths: [u32; 3],
+    terms: Vec<String>,
+}
+
+impl SearchIndex {
+    /// 创建空索引
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 索引一个模块，替换同一模块ID之前的内容
+    /// Index a module, replacing any earlier content under the same module ID
+    pub fn insert(&mut self, module: &ModuleEntry) {
+        self.remove(&module.id);
+        let fields = [
+            tokenize(&module.name),
+            module.tags.iter().flat_map(|tag| tokenize(tag)).collect(),
+            tokenize(&module.description),
+        ];
+        let mut lengths = [0; 3];
+        let mut frequencies: HashMap<String, [u32; 3]> = HashMap::new();
+        for (field, tokens) in fields.into_iter().enumerate() {
+            lengths[field] = tokens.len() as u32;
+            self.total_lengths[field] += tokens.len() as u64;
+            for token in tokens {
+                frequencies.entry(token).or_default()[field] += 1;
+            }
+        }
+        let terms = frequencies.keys().cloned().collect();
+        for (term, counts) in frequencies {
+            self.postings.entry(term).or_default().insert(module.id.clone(), counts);
+        }
+        self.documents.insert(module.id.clone(), IndexedDocument { lengths, terms });
+    }
+
+    /// 从索引中移除一个模块，返回模块是否已索引
+    /// Remove a module from the index, returning whether it was indexed
+    pub fn remove(&mut self, module_id: &str) -> bool {
+        let Some(document) = self.documents.remove(module_id) else {
+            return false;
+        };
+        for (total, length) in self.total_lengths.iter_mut().zip(document.lengths) {
+            *total -= length as u64;
+        }
+        for term in document.terms {
+            if let Some(postings) = self.postings.get_mut(&term) {
+                postings.remove(module_id);
+                if postings.is_empty() {
+                    self.postings.remove(&term);
+                }
+            }
+        }
+        true
+    }
+
+    /// 已索引的模块数
+    pub fn len(&self) -> usize {
+        self.documents.len()
+    }
+
+    /// 索引是否为空
+    pub fn is_empty(&self) -> bool {
+        self.documents.is_empty()
+    }
+
+    /// 查找包含全部查询词的模块，返回模块ID和相关性分数，顺序不定
+    /// Find the modules containing every query term, returning module IDs with relevance scores in no
+    /// particular order
+    ///
+    /// 任一查询词不在索引中时立即返回空结果。
+    /// Returns at once with no results when any query term is not indexed.
+    pub fn search(&self, query: &str) -> Vec<(String, f64)> {
+        let mut terms = tokenize(query);
+        terms.sort();
+        terms.dedup();
+        let mut postings = Vec::with_capacity(terms.len());
+        for term in &terms {
+            match self.postings.get(term) {
+                Some(modules) => postings.push(modules),
+                None => return Vec::new(),
+            }
+        }
+        // 从最短的倒排表开始求交集
+        postings.sort_by_key(|modules| modules.len());
+        let Some((shortest, rest)) = postings.split_first() else {
+            return Vec::new();
+        };
+
+        let count = self.documents.len() as f64;
+        let average = self.total_lengths.map(|total| total as f64 / count);
+        shortest.keys()
+            .filter(|module_id| rest.iter().all(|modules| modules.contains_key(*module_id)))
+            .map(|module_id| {
+                let lengths = self.documents[module_id].lengths;
+                let score = postings.iter().map(|modules| {
+                    let frequency = modules.len() as f64;
+                    let idf = ((count - frequency + 0.5) / (frequency + 0.5) + 1.0).ln();
+                    let counts = modules[module_id];
+                    let weighted: f64 = (0..3)
+                        .filter(|field| counts[*field] > 0)
+                        .map(|field| {
+                            let norm = 1.0 - BM25_B + BM25_B * lengths[field] as f64 / average[field];
+                            FIELD_BOOSTS[field] * counts[field] as f64 / norm
+                        })
+                        .sum();
+                    idf * weighted * (BM25_K1 + 1.0) / (weighted + BM25_K1)
+                }).sum();
+                (module_id.clone(), score)
+            })
+            .collect()
+    }
+}
+
+/// 把文本切分为小写并取词干的词项；中日韩文字逐字成词
+/// Split text into lowercased, stemmed terms; CJK characters each form a term
+pub fn tokenize(text: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut word = String::new();
+    for ch in text.chars() {
+        if is_cjk(ch) {
+            flush_word(&mut word, &mut tokens);
+            tokens.push(ch.to_string());
+        } else if ch.is_alphanumeric() {
+            word.extend(ch.to_lowercase());
+        } else {
+            flush_word(&mut word, &mut tokens);
+        }
+    }
+    flush_word(&mut word, &mut tokens);
+    tokens
+}
+
+fn flush_word(word: &mut String, tokens: &mut Vec<String>) {
+    if !word.is_empty() {
+        tokens.push(stem(&std::mem::take(word)));
+    }
+}
+
+fn is_cjk(ch: char) -> bool {
+    matches!(ch as u32, 0x3040..=0x30FF | 0x3400..=0x4DBF | 0x4E00..=0x9FFF | 0xAC00..=0xD7AF | 0xF900..=0xFAFF)
+}
+
+/// 去掉常见的英文屈折后缀：复数、`-ing`、`-ed`
+/// Strip common English inflections: plurals, `-ing` and `-ed`
+fn stem(word: &str) -> String {
+    let has_vowel = |stem: &str| stem.chars().any(|ch| "aeiouy".contains(ch));
+    if let Some(stem) = word.strip_suffix("sses") {
+        return format!("{}ss", stem);
+    }
+    if let Some(stem) = word.strip_suffix("ies").filter(|stem| stem.len() >= 2) {
+        return format!("{}y", stem);
+    }
+    for suffix in ["ing", "ed"] {
+        if let Some(stem) = word.strip_suffix(suffix).filter(|stem| stem.len() >= 3 && has_vowel(stem)) {
+            return stem.to_string();
+        }
+    }
+    match word.strip_suffix('s') {
+        Some(stem) if word.len() > 3 && !stem.ends_with('s') && !stem.ends_with('u') => stem.to_string(),
+        _ => word.to_string(),
+    }
+}
+
+impl Default for UserManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl UserManager {
+    /// 创建新的用户管理器
+    pub fn new() -> Self {
+        Self {
+            users: Arc::new(Mutex::new(HashMap::new())),
+            publisher_keys: Arc::new(Mutex::new(HashMap::new())),
+            api_tokens: Arc::new(Mutex::new(HashMap::new())),
+            clock: Arc::new(SystemMarketplaceClock),
+            permission_manager: PermissionManager::new(),
+        }
+    }
+
+    /// 为用户注册一个 Ed25519 发布者公钥，返回密钥ID
+    /// Register an Ed25519 publisher public key for a user, returning its key ID
+    ///
+    /// 一个用户可以有多个有效密钥以便轮换；已撤销的密钥不能重新注册。
+    /// A user may hold several active keys for rotation; a revoked key cannot be registered again.
+    pub fn register_publisher_key(&self, user_id: &str, public_key: &[u8]) -> Result<String, MarketplaceError> {
+        if !self.users.lock().unwrap().contains_key(user_id) {
+            return Err(MarketplaceError::UserNotFound);
+        }
+        if public_key.len() != 32 {
+            return Err(MarketplaceError::InvalidPublicKey(format!("Ed25519 公钥应为 32 字节，实际为 {} 字节", public_key.len())));
+        }
+        let key_id = hex(&Sha256::digest(public_key)[..8]);
+        let mut publisher_keys = self.publisher_keys.lock().unwrap();
+        let keys = publisher_keys.entry(user_id.to_string()).or_default();
+        match keys.iter().find(|key| key.key_id == key_id) {
+            Some(key) if key.revoked_at.is_some() => {
+                Err(MarketplaceError::InvalidPublicKey(format!("密钥 {} 已被撤销", key_id)))
+            }
+            Some(_) => Ok(key_id),
+            None => {
+                keys.push(PublisherKey {
+                    key_id: key_id.clone(),
+                    public_key: public_key.to_vec(),
+                    registered_at: self.clock.now(),
+                    revoked_at: None,
+                });
+                Ok(key_id)
+            }
+        }
+    }
+
+    /// 撤销用户的发布者公钥，用它签名的模块之后校验为 [`VerificationStatus::UnknownKey`]
+    /// Revoke a user's publisher key; modules signed with it then verify as [`VerificationStatus::UnknownKey`]
+    pub fn revoke_publisher_key(&self, user_id: &str, key_id: &str) -> Result<(), MarketplaceError> {
+        let mut publisher_keys = self.publisher_keys.lock().unwrap();
+        let key = publisher_keys.get_mut(user_id)
+            .and_then(|keys| keys.iter_mut().find(|key| key.key_id == key_id && key.revoked_at.is_none()))
+            .ok_or(MarketplaceError::PublisherKeyNotFound)?;
+        key.revoked_at = Some(self.clock.now());
+        Ok(())
+    }
+
+    /// 用户仍然有效的发布者公钥
+    /// A user's publisher keys that are still active
+    pub fn active_publisher_keys(&self, user_id: &str) -> Vec<PublisherKey> {
+        self.publisher_keys.lock().unwrap()
+            .get(user_id)
+            .map(|keys| keys.iter().filter(|key| key.revoked_at.is_none()).cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// 为用户创建 API 令牌，`expiry` 为有效期，`None` 表示不过期
+    /// Issue an API token for a user, valid for `expiry` or indefinitely when `None`
+    ///
+    /// 明文令牌只在返回值中出现一次；包含 [`TokenScope::Admin`] 的令牌只能发给管理员。
+    /// The plain-text token only appears in the return value; tokens with [`TokenScope::Admin`] are only issued
+    /// to administrators.
+    pub fn create_token(
+        &self,
+        user_id: &str,
+        scopes: &[TokenScope],
+        expiry: Option<Duration>,
+    ) -> Result<IssuedToken, MarketplaceError> {
+        let is_admin = self.users.lock().unwrap()
+            .get(user_id)
+            .ok_or(MarketplaceError::UserNotFound)?
+            .roles.contains(&UserRole::Administrator);
+        if scopes.contains(&TokenScope::Admin) && !is_admin {
+            return Err(MarketplaceError::PermissionDenied);
+        }
+
+        let random = SystemRandom::new();
+        let mut id = [0u8; 8];
+        let mut secret = [0u8; 32];
+        random.fill(&mut id)
+            .and_then(|()| random.fill(&mut secret))
+            .map_err(|_| MarketplaceError::InvalidToken("无法生成随机数".to_string()))?;
+        let id = hex(&id);
+        let secret = format!("{}{}.{}", API_TOKEN_PREFIX, id, URL_SAFE_NO_PAD.encode(secret));
+
+        let now = self.clock.now();
+        let mut scopes = scopes.to_vec();
+        scopes.sort();
+        scopes.dedup();
+        self.api_tokens.lock().unwrap().insert(id.clone(), ApiToken {
+            id: id.clone(),
+            user_id: user_id.to_string(),
+            scopes,
+            secret_hash: hex(&Sha256::digest(secret.as_bytes())),
+            created_at: now,
+            expires_at: expiry.map(|expiry| now + expiry),
+            revoked_at: None,
+            last_used_at: None,
+        });
+        Ok(IssuedToken { id, secret })
+    }
+
+    /// 验证明文令牌，返回调用方
+    /// Authenticate a plain-text token, returning the caller
+    ///
+    /// 格式错误、未知、摘要不符、已撤销、已过期或所属用户已删除的令牌都返回 [`MarketplaceError::InvalidToken`]。
+    /// Malformed, unknown, mismatching, revoked or expired tokens, and tokens of deleted users, all fail with
+    /// [`MarketplaceError::InvalidToken`].
+    pub fn authenticate_token(&self, token: &str) -> Result<Principal, MarketplaceError> {
+        let invalid = |reason: &str| MarketplaceError::InvalidToken(reason.to_string());
+        let id = token.strip_prefix(API_TOKEN_PREFIX)
+            .and_then(|rest| rest.split_once('.'))
+            .map(|(id, _)| id)
+            .ok_or_else(|| invalid("格式错误"))?;
+        let now = self.clock.now();
+        let mut api_tokens = self.api_tokens.lock().unwrap();
+        let stored = api_tokens.get_mut(id).ok_or_else(|| invalid("未知令牌"))?;
+        let digest = hex(&Sha256::digest(token.as_bytes()));
+        // 逐字节比较全部内容，耗时与不同之处的位置无关
+        let matching = digest.len() == stored.secret_hash.len()
+            && digest.bytes().zip(stored.secret_hash.bytes()).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0;
+        if !matching {
+            return Err(invalid("令牌不符"));
+        }
+        if stored.revoked_at.is_some() {
+            return Err(invalid("令牌已撤销"));
+        }
+        if stored.expires_at.is_some_and(|expires_at| now >= expires_at) {
+            return Err(invalid("令牌已过期"));
+        }
+        if !self.users.lock().unwrap().contains_key(&stored.user_id) {
+            return Err(invalid("令牌所属的用户不存在"));
+        }
+        stored.last_used_at = Some(now);
+        Ok(Principal { user_id: stored.user_id.clone(), token_id: stored.id.clone(), scopes: stored.scopes.clone() })
+    }
+
+    /// 撤销用户的令牌
+    /// Revoke one of a user's tokens
+    pub fn revoke_token(&self, user_id: &str, token_id: &str) -> Result<(), MarketplaceError> {
+        let mut api_tokens = self.api_tokens.lock().unwrap();
+        let token = api_tokens.get_mut(token_id)
+            .filter(|token| token.user_id == user_id && token.revoked_at.is_none())
+            .ok_or_else(|| MarketplaceError::InvalidToken("未知令牌".to_string()))?;
+        token.revoked_at = Some(self.clock.now());
+        Ok(())
+    }
+
+    /// 用户的全部令牌，包括已撤销和已过期的，按创建时间排列
+    /// All of a user's tokens, including revoked and expired ones, by creation time
+    pub fn list_tokens(&self, user_id: &str) -> Vec<ApiToken> {
+        let mut tokens: Vec<ApiToken> = self.api_tokens.lock().unwrap()
+            .values()
+            .filter(|token| token.user_id == user_id)
+            .cloned()
+            .collect();
+        tokens.sort_by(|a, b| a.created_at.cmp(&b.created_at).then_with(|| a.id.cmp(&b.id)));
+        tokens
+    }
+
+    /// 检查用户权限
+    pub fn has_permission(&self, user_id: &str, resource: &str, action: PermissionAction) -> bool {
+        let users = self.users.lock().unwrap();
+        if let Some(user) = users.get(user_id) {
+            self.permission_manager.check_permission(&user.roles, resource, action)
+        } else {
+            false
+        }
+    }
+}
+
+impl Default for PermissionManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PermissionManager {
+    /// 创建新的权限管理器
+    pub fn new() -> Self {
+        Self {
+            rules: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    /// 检查权限
+    pub fn check_permission(&self, roles: &[UserRole], resource: &str, action: PermissionAction) -> bool {
+        let rules = self.rules.lock().unwrap();
+        for role in roles {
+            for rule in rules.iter() {
+                if rule.role == *role && rule.resource == resource && rule.action == action {
+                    return rule.allowed;
+                }
+            }
+        }
+        false
+    }
+}
+
+impl Default for RatingSystem {
+    fn default() -> Self {
+        Self {
+            ratings: Arc::new(Mutex::new(HashMap::new())),
+            totals: Arc::new(Mutex::new(RatingTotals::default())),
+            config: RatingConfig {
+                min_score: 1,
+                max_score: 5,
+                require_comment: false,
+                max_comment_length: 1000,
+                prior_weight: 5.0,
+            },
+        }
+    }
+}
+
+impl RatingSystem {
+    /// 创建新的评分系统
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 添加评分，替换同一用户对该模块的原有评分并将其返回
+    /// Add a rating, replacing and returning the same user's earlier rating of the module
+    pub fn add_rating(&self, module_id: &str, rating: Rating) -> Result<Option<Rating>, MarketplaceError> {
+        let mut ratings = self.ratings.lock().unwrap();
+        let module_ratings = ratings.entry(module_id.to_string()).or_default();
+        let mut totals = self.totals.lock().unwrap();
+        totals.sum += u64::from(rating.score);
+        match module_ratings.iter_mut().find(|existing| existing.user_id == rating.user_id) {
+            Some(existing) => {
+                totals.sum -= u64::from(existing.score);
+                Ok(Some(std::mem::replace(existing, rating)))
+            }
+            None => {
+                totals.count += 1;
+                module_ratings.push(rating);
+                Ok(None)
+            }
+        }
+    }
+
+    /// 获取评分
+    pub fn get_ratings(&self, module_id: &str) -> Result<Vec<Rating>, MarketplaceError> {
+        let ratings = self.ratings.lock().unwrap();
+        Ok(ratings.get(module_id).cloned().unwrap_or_default())
+    }
+
+    /// 模块的评分汇总
+    ///
+    /// 加权评分见 [`weighted`](Self::weighted)。
+    /// See [`weighted`](Self::weighted) for the weighted score.
+    pub fn summary(&self, module_id: &str) -> RatingSummary {
+        let ratings = self.ratings.lock().unwrap();
+        let (min_score, max_score) = (self.config.min_score, self.config.max_score);
+        let mut histogram = vec![0; usize::from(max_score.saturating_sub(min_score)) + 1];
+        let module_ratings = ratings.get(module_id).map(Vec::as_slice).unwrap_or_default();
+        for rating in module_ratings {
+            if let Some(bucket) = histogram.get_mut(usize::from(rating.score.saturating_sub(min_score))) {
+                *bucket += 1;
+            }
+        }
+        if module_ratings.is_empty() {
+            return RatingSummary { count: 0, mean: 0.0, weighted: 0.0, histogram };
+        }
+
+        let sum: f64 = module_ratings.iter().map(|rating| f64::from(rating.score)).sum();
+        let count = module_ratings.len() as u32;
+        let mean = sum / f64::from(count);
+        RatingSummary { count, mean, weighted: self.weighted(mean, count), histogram }
+    }
+
+    /// 以当前全站平均为先验的加权评分，没有评分时为 0
+    /// Weighted score using the current global mean as the prior; 0 without ratings
+    ///
+    /// 加权评分为 `(C × 全站平均 + 评分总和) / (C + 评分条数)`，`C` 为 [`RatingConfig::prior_weight`]。
+    /// 全站平均取自随评分增删维护的累计值，不需要遍历全部评分。
+    /// The weighted score is `(C × global mean + sum of scores) / (C + count)` with `C` the
+    /// [`RatingConfig::prior_weight`]. The global mean comes from running totals kept as ratings change, so no
+    /// rating is scanned.
+    pub fn weighted(&self, mean: f64, count: u32) -> f64 {
+        if count == 0 {
+            return 0.0;
+        }
+        let global_mean = {
+            let totals = self.totals.lock().unwrap();
+            if totals.count == 0 { mean } else { totals.sum as f64 / totals.count as f64 }
+        };
+        let (prior, count) = (self.config.prior_weight.max(0.0), f64::from(count));
+        (prior * global_mean + mean * count) / (prior + count)
+    }
+
+    /// 删除或匿名化用户的全部评分
+    /// Remove or anonymize every rating by a user
+    pub fn release_user(&self, user_id: &str, disposal: DeletedUserRatings) {
+        let mut ratings = self.ratings.lock().unwrap();
+        let mut totals = self.totals.lock().unwrap();
+        for module_ratings in ratings.values_mut() {
+            match disposal {
+                DeletedUserRatings::Remove => module_ratings.retain(|rating| {
+                    let keep = rating.user_id != user_id;
+                    if !keep {
+                        totals.sum -= u64::from(rating.score);
+                        totals.count -= 1;
+                    }
+                    keep
+                }),
+                DeletedUserRatings::Anonymize => {
+                    for rating in module_ratings.iter_mut().filter(|rating| rating.user_id == user_id) {
+                        rating.user_id = ANONYMOUS_RATER.to_string();
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// 目录归档的文件头
+const CATALOG_MAGIC: &[u8; 8] = b"WMCATLOG";
+/// 当前的目录格式版本
+pub const CATALOG_FORMAT_VERSION: u32 = 1;
+
+/// 导出目录时选择模块的条件
+/// Which modules to export from the catalog
+#[derive(Debug, Clone, Default)]
+pub struct CatalogFilter {
+    /// 只导出这些名称的模块，为空时导出全部
+    pub names: Vec<String>,
+    /// 只导出该分类的模块
+    pub category: Option<ModuleCategory>,
+    /// 是否导出已撤回的版本
+    pub include_yanked: bool,
+}
+
+impl CatalogFilter {
+    fn matches(&self, module: &ModuleEntry) -> bool {
+        (self.names.is_empty() || self.names.contains(&module.name))
+            && self.category.as_ref().is_none_or(|category| module.category == *category)
+            && (self.include_yanked || !module.yanked)
+            && !module.draft
+    }
+}
+
+/// 导出的目录
+/// An exported catalog
+///
+/// [`CatalogBundle::to_bytes`] 把目录写成单个归档：文件头、格式版本、JSON 清单，随后依次是清单中列出的模块字节。
+/// [`CatalogBundle::to_bytes`] writes the bundle as a single archive: a header, the format version, a JSON
+/// manifest, and then the module bytes listed in the manifest.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CatalogBundle {
+    /// 目录格式版本
+    pub format_version: u32,
+    /// 导出时间
+    pub exported_at: SystemTime,
+    /// 导出的模块
+    pub entries: Vec<BundledModule>,
+    /// 模块字节，以 SHA-256 摘要（十六进制）为键
+    #[serde(skip)]
+    pub blobs: BTreeMap<String, Vec<u8>>,
+    /// 签名者的发布者公钥，仅供导入方核对密钥ID；导入时不会安装，也不会用于校验签名
+    pub publisher_keys: Vec<BundledPublisherKey>,
+    /// 模块名称到所有者的映射
+    pub owners: BTreeMap<String, String>,
+    /// 模块名称到弃用信息的映射
+    pub deprecations: BTreeMap<String, Deprecation>,
+    /// 导出的模块用到的分类
+    pub categories: Vec<ModuleCategory>,
+    /// 导出的模块所属的分类树节点及其祖先，父节点排在子节点之前
+    #[serde(default)]
+    pub category_nodes: Vec<CategoryNode>,
+}
+
+impl Default for CatalogBundle {
+    fn default() -> Self {
+        Self {
+            format_version: CATALOG_FORMAT_VERSION,
+            exported_at: SystemTime::UNIX_EPOCH,
+            entries: Vec::new(),
+            blobs: BTreeMap::new(),
+            publisher_keys: Vec::new(),
+            owners: BTreeMap::new(),
+            deprecations: BTreeMap::new(),
+            categories: Vec::new(),
+            category_nodes: Vec::new(),
+        }
+    }
+}
+
+/// 目录中的一个模块
+/// One module in a catalog bundle
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundledModule {
+    /// 模块条目
+    pub module: ModuleEntry,
+    /// 模块字节的摘要，`None` 表示市场中没有存储字节
+    pub blob: Option<String>,
+}
+
+/// 目录中的一个发布者公钥
+/// One publisher key in a catalog bundle
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundledPublisherKey {
+    /// 公钥所属的用户
+    pub user_id: String,
+    /// 公钥
+    pub key: PublisherKey,
+}
+
+/// 归档清单中模块字节的位置
+#[derive(Debug, Serialize, Deserialize)]
+struct CatalogBlobRef {
+    digest: String,
+    len: u64,
+}
+
+#[derive(Serialize)]
+struct CatalogManifestOut<'a> {
+    #[serde(flatten)]
+    bundle: &'a CatalogBundle,
+    blobs: Vec<CatalogBlobRef>,
+}
+
+#[derive(Deserialize)]
+struct CatalogManifestIn {
+    #[serde(flatten)]
+    bundle: CatalogBundle,
+    blobs: Vec<CatalogBlobRef>,
+}
+
+impl CatalogBundle {
+    /// 写成单个归档
+    /// Write the bundle as a single archive
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let manifest = CatalogManifestOut {
+            bundle: self,
+            blobs: self.blobs.iter()
+                .map(|(digest, bytes)| CatalogBlobRef { digest: digest.clone(), len: bytes.len() as u64 })
+                .collect(),
+        };
+        let manifest = serde_json::to_vec(&manifest).expect("目录清单总能序列化");
+        let mut archive = Vec::with_capacity(20 + manifest.len() + self.blobs.values().map(Vec::len).sum::<usize>());
+        archive.extend_from_slice(CATALOG_MAGIC);
+        archive.extend_from_slice(&self.format_version.to_le_bytes());
+        archive.extend_from_slice(&(manifest.len() as u64).to_le_bytes());
+        archive.extend_from_slice(&manifest);
+        for bytes in self.blobs.values() {
+            archive.extend_from_slice(bytes);
+        }
+        archive
+    }
+
+    /// 读取归档，模块字节须与清单中的摘要一致
+    /// Read an archive; the module bytes must match the digests in the manifest
+    pub fn from_bytes(archive: &[u8]) -> Result<Self, MarketplaceError> {
+        let invalid = |reason: &str| MarketplaceError::InvalidCatalog(reason.to_string());
+        let rest = archive.strip_prefix(CATALOG_MAGIC.as_slice()).ok_or_else(|| invalid("文件头不符"))?;
+        let (version, rest) = rest.split_first_chunk::<4>().ok_or_else(|| invalid("归档被截断"))?;
+        let format_version = u32::from_le_bytes(*version);
+        if format_version != CATALOG_FORMAT_VERSION {
+            return Err(MarketplaceError::InvalidCatalog(format!("不支持的目录格式版本 {}", format_version)));
+        }
+        let (manifest_len, rest) = rest.split_first_chunk::<8>().ok_or_else(|| invalid("归档被截断"))?;
+        let manifest_len = usize::try_from(u64::from_le_bytes(*manifest_len)).map_err(|_| invalid("清单过大"))?;
+        let (manifest, mut rest) = rest.split_at_checked(manifest_len).ok_or_else(|| invalid("归档被截断"))?;
+        let manifest: CatalogManifestIn = serde_json::from_slice(manifest)
+            .map_err(|error| MarketplaceError::InvalidCatalog(format!("清单无法解析: {}", error)))?;
+
+        let mut bundle = manifest.bundle;
+        for blob in manifest.blobs {
+            let len = usize::try_from(blob.len).map_err(|_| invalid("模块字节过大"))?;
+            let (bytes, remaining) = rest.split_at_checked(len).ok_or_else(|| invalid("归档被截断"))?;
+            if hex(&Sha256::digest(bytes)) != blob.digest {
+                return Err(MarketplaceError::InvalidCatalog(format!("模块字节与摘要 {} 不符", blob.digest)));
+            }
+            bundle.blobs.insert(blob.digest, bytes.to_vec());
+            rest = remaining;
+        }
+        if !rest.is_empty() {
+            return Err(invalid("归档末尾有多余数据"));
+        }
+        Ok(bundle)
+    }
+}
+
+/// 导入时已存在的版本如何处理
+/// What to do with versions that already exist on import
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportPolicy {
+    /// 保留已有版本
+    SkipExisting,
+    /// 用导入的条目覆盖
+    Overwrite,
+}
+
+/// 导入报告
+/// Import report
+#[derive(Debug, Clone, Default)]
+pub struct ImportReport {
+    /// 每个条目的导入结果，顺序与目录一致
+    pub entries: Vec<ImportedEntry>,
+}
+
+impl ImportReport {
+    /// 指定模块的导入结果
+    pub fn outcome_of(&self, module_id: &str) -> Option<&ImportOutcome> {
+        self.entries.iter().find(|entry| entry.module_id == module_id).map(|entry| &entry.outcome)
+    }
+
+    /// 成功导入的条目数
+    pub fn imported(&self) -> usize {
+        self.entries.iter().filter(|entry| entry.outcome == ImportOutcome::Imported).count()
+    }
+}
+
+/// 一个条目的导入结果
+/// Import result of one entry
+#[derive(Debug, Clone)]
+pub struct ImportedEntry {
+    /// 模块ID
+    pub module_id: String,
+    /// 结果
+    pub outcome: ImportOutcome,
+}
+
+/// 导入结果
+/// Import outcome
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImportOutcome {
+    /// 已导入
+    Imported,
+    /// 按策略跳过
+    Skipped {
+        /// 原因
+        reason: String,
+    },
+    /// 校验失败，未写入
+    Failed {
+        /// 原因
+        reason: String,
+    },
+}
+
+/// 错误类型定义
+/// Error Type Definitions
+
+#[derive(Debug, Error)]
+pub enum MarketplaceError {
+    /// 模块未找到
+    #[error("模块未找到")]
+    ModuleNotFound,
     /// 权限被拒绝
     #[error("权限被拒绝")]
     PermissionDenied,
-    /// 模块过大
-    #[error("模块过大")]
-    ModuleTooLarge,
-    /// 许可证不允许
-    #[error("许可证不允许")]
-    LicenseNotAllowed,
-    /// 无效模块
-    #[error("无效模块")]
-    InvalidModule,
     /// 安全风险过高
     #[error("安全风险过高")]
     SecurityRiskTooHigh,
     /// 无效评分
     #[error("无效评分")]
     InvalidRating,
+    /// 分类树中没有该节点
+    #[error("未知分类: {0}")]
+    UnknownCategory(String),
+    /// 发布检查未通过
+    #[error("模块未通过发布检查: {}", .0.iter().map(ToString::to_string).collect::<Vec<_>>().join("; "))]
+    ValidationFailed(Vec<ValidationProblem>),
+    /// 分类节点无效或移动会形成环
+    #[error("无效分类: {0}")]
+    InvalidCategory(String),
+    /// 只有下载过模块的用户可以评分
+    #[error("用户未下载过该模块，不能评分")]
+    NotDownloaded,
     /// 用户未找到
     #[error("用户未找到")]
     UserNotFound,
+    /// 版本号或版本要求无效
+    #[error("无效版本: {0}")]
+    InvalidVersion(String),
+    /// 没有满足要求的版本
+    #[error("没有满足 {requester} 要求的 {name} {requirement} 版本")]
+    NoMatchingVersion {
+        /// 模块名称
+        name: String,
+        /// 版本要求
+        requirement: String,
+        /// 提出要求的模块
+        requester: String,
+    },
+    /// 同一模块的版本要求没有交集
+    #[error("模块 {name} 的版本要求相互冲突: {}", requirements.join("; "))]
+    DependencyConflict {
+        /// 模块名称
+        name: String,
+        /// 每项为提出要求的模块及其要求
+        requirements: Vec<String>,
+    },
+    /// 依赖成环
+    #[error("依赖循环: {}", cycle.join(" -> "))]
+    DependencyCycle {
+        /// 环上的模块版本，首尾相同
+        cycle: Vec<String>,
+    },
+    /// API 令牌无效、已撤销或已过期
+    #[error("无效的 API 令牌: {0}")]
+    InvalidToken(String),
+    /// API 令牌缺少所需的权限范围
+    #[error("API 令牌缺少 {0:?} 权限范围")]
+    InsufficientScope(TokenScope),
+    /// 公钥无效或已撤销
+    #[error("无效的发布者公钥: {0}")]
+    InvalidPublicKey(String),
+    /// 发布者公钥未找到
+    #[error("发布者公钥未找到")]
+    PublisherKeyNotFound,
+    /// 市场只接受签名发布
+    #[error("市场要求模块签名发布")]
+    SignatureRequired,
+    /// 发布时的签名无法验证
+    #[error("模块签名无效: {0}")]
+    InvalidSignature(String),
+    /// 下载时代码完整性检查未通过
+    #[error("{0}")]
+    IntegrityCheckFailed(String),
+    /// 目录归档无效
+    #[error("无效的目录归档: {0}")]
+    InvalidCatalog(String),
+    /// 分页游标无效或属于其他查询
+    #[error("无效的分页游标: {0}")]
+    InvalidCursor(String),
+    /// 依赖解析超过尝试上限
+    #[error("依赖解析处理了 {0} 个版本要求仍未完成")]
+    ResolutionTooComplex(usize),
+}
+
+#[cfg(test)]
+// 测试直接以用户ID调用已弃用的发布和撤回入口
+#[allow(deprecated)]
+mod tests {
+    use super::*;
+
+    fn marketplace() -> ModuleMarketplaceManager {
+        let marketplace = ModuleMarketplaceManager::new(MarketplaceConfig {
+            enabled: true,
+            max_module_size: 1 << 20,
+            allowed_licenses: vec!["MIT".to_string()],
+            auto_security_scan: false,
+            require_signatures: false,
+            rating_weights: RatingWeights {
+                functionality_weight: 0.2,
+                performance_weight: 0.2,
+                security_weight: 0.2,
+                documentation_weight: 0.2,
+                usability_weight: 0.2,
+            },
+        });
+        marketplace.user_manager.users.lock().unwrap().insert("dev".to_string(), User {
+            id: "dev".to_string(),
+            username: "dev".to_string(),
+            email: "dev@example.com".to_string(),
+            created_at: SystemTime::now(),
+            last_login: None,
+            roles: vec![UserRole::Developer],
+            statistics: UserStatistics {
+                published_modules: 0,
+                downloaded_modules: 0,
+                rating_count: 0,
+                contribution_score: 0,
+            },
+        });
+        marketplace.user_manager.permission_manager.rules.lock().unwrap().push(PermissionRule {
+            id: "publish".to_string(),
+            role: UserRole::Developer,
+            resource: "module".to_string(),
+            action: PermissionAction::Publish,
+            allowed: true,
+        });
+        marketplace
+    }
+
+    fn module(id: &str, name: &str, description: &str, tags: &[&str]) -> ModuleEntry {
+        ModuleEntry {
+            id: id.to_string(),
+            name: name.to_string(),
+            version: "1.0.0".to_string(),
+            description: description.to_string(),
+            author: "dev".to_string(),
+            license: "MIT".to_string(),
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            categories: Vec::new(),
+            category: ModuleCategory::Utilities,
+            download_url: format!("https://example.com/{}.wasm", id),
+            documentation_url: None,
+            source_url: None,
+            created_at: SystemTime::UNIX_EPOCH,
+            updated_at: SystemTime::UNIX_EPOCH,
+            download_count: 0,
+            rating: 0.0,
+            rating_count: 0,
+            weighted_rating: 0.0,
+            size: 1024,
+            dependencies: Vec::new(),
+            yanked: false,
+            yank_reason: None,
+            draft: false,
+            exports: Vec::new(),
+            changelog: None,
+            signature: None,
+            downloads_total: 0,
+            downloads_last_7d: 0,
+            compatibility: CompatibilityInfo {
+                wasm_versions: vec!["2.0".to_string()],
+                rust_versions: vec!["1.90".to_string()],
+                target_platforms: vec!["wasm32-unknown-unknown".to_string()],
+                min_memory: 0,
+                recommended_memory: 0,
+                required_features: Vec::new(),
+            },
+            security_scan: None,
+        }
+    }
+
+    fn query(keywords: &str, sort_by: SortBy) -> SearchQuery {
+        SearchQuery {
+            keywords: Some(keywords.to_string()),
+            category: None,
+            categories: Vec::new(),
+            tags: None,
+            min_rating: None,
+            license: None,
+            sort_by,
+            secondary_sort: None,
+            cursor: None,
+            page_size: 20,
+        }
+    }
+
+    fn ids(results: SearchResults) -> Vec<String> {
+        results.items.into_iter().map(|module| module.id).collect()
+    }
+
+    #[test]
+    fn test_tokenize_lowercases_and_stems() {
+        assert_eq!(tokenize("Parsing JSON-Files, quickly!"), ["pars", "json", "file", "quickly"]);
+        assert_eq!(tokenize("Libraries classes compressed"), ["library", "class", "compress"]);
+        assert_eq!(tokenize("图像 resize"), ["图", "像", "resize"]);
+        assert_eq!(tokenize("bus is"), ["bus", "is"]);
+    }
+
+    #[test]
+    fn test_name_match_outranks_description_match() {
+        let marketplace = marketplace();
+        marketplace.publish_module(
+            module("described", "fast-math", "Utilities that also handle image resizing", &["math"]),
+            "dev",
+        ).unwrap();
+        marketplace.publish_module(module("named", "image-resize", "Resample pictures", &["graphics"]), "dev").unwrap();
+        marketplace.publish_module(module("tagged", "thumbnailer", "Thumbnails for galleries", &["image"]), "dev").unwrap();
+
+        let results = ids(marketplace.search_modules(&query("image", SortBy::Relevance)).unwrap());
+        assert_eq!(results, ["named", "tagged", "described"]);
+    }
+
+    #[test]
+    fn test_multiple_terms_require_every_term() {
+        let marketplace = marketplace();
+        marketplace.publish_module(module("both", "png-codec", "Decode PNG images with SIMD", &[]), "dev").unwrap();
+        marketplace.publish_module(module("png", "png-meta", "Read PNG metadata", &[]), "dev").unwrap();
+        marketplace.publish_module(module("simd", "simd-math", "Vector math with SIMD", &[]), "dev").unwrap();
+
+        assert_eq!(ids(marketplace.search_modules(&query("PNG simd", SortBy::Relevance)).unwrap()), ["both"]);
+        assert!(marketplace.search_modules(&query("png unknown", SortBy::Relevance)).unwrap().items.is_empty());
+        assert_eq!(marketplace.search_modules(&query("png", SortBy::Relevance)).unwrap().items.len(), 2);
+    }
+
+    #[test]
+    fn test_index_updates_incrementally_on_publish() {
+        let marketplace = marketplace();
+        marketplace.publish_module(module("codec", "png-codec", "Decode PNG images", &[]), "dev").unwrap();
+        assert!(marketplace.search_modules(&query("jpeg", SortBy::Relevance)).unwrap().items.is_empty());
+
+        marketplace.publish_module(module("jpeg", "jpeg-codec", "Decode JPEG images", &[]), "dev").unwrap();
+        assert_eq!(ids(marketplace.search_modules(&query("jpeg", SortBy::Relevance)).unwrap()), ["jpeg"]);
+
+        // 以相同ID重新发布时替换旧内容
+        marketplace.publish_module(module("codec", "webp-codec", "Decode WebP images", &[]), "dev").unwrap();
+        assert!(marketplace.search_modules(&query("png", SortBy::Relevance)).unwrap().items.is_empty());
+        assert_eq!(ids(marketplace.search_modules(&query("webp", SortBy::Relevance)).unwrap()), ["codec"]);
+        assert_eq!(marketplace.search_index.lock().unwrap().len(), 2);
+
+        assert!(marketplace.search_index.lock().unwrap().remove("codec"));
+        assert!(marketplace.search_modules(&query("webp", SortBy::Relevance)).unwrap().items.is_empty());
+    }
+
+    #[test]
+    fn test_relevance_ties_and_other_sorts_use_secondary_keys() {
+        let marketplace = marketplace();
+        for (id, downloads) in [("a", 5), ("b", 50), ("c", 20)] {
+            let mut entry = module(id, &format!("{}-json", id), "Parse JSON", &[]);
+            entry.download_count = downloads;
+            entry.updated_at = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(100 - downloads);
+            marketplace.publish_module(entry, "dev").unwrap();
+        }
+
+        assert_eq!(ids(marketplace.search_modules(&query("json", SortBy::Relevance)).unwrap()), ["b", "c", "a"]);
+        assert_eq!(ids(marketplace.search_modules(&query("json", SortBy::Recent)).unwrap()), ["a", "c", "b"]);
+        let by_recency = SearchQuery { secondary_sort: Some(SortBy::Recent), ..query("json", SortBy::Relevance) };
+        assert_eq!(ids(marketplace.search_modules(&by_recency).unwrap()), ["a", "c", "b"]);
+        let everything = SearchQuery { keywords: None, ..query("", SortBy::Downloads) };
+        assert_eq!(ids(marketplace.search_modules(&everything).unwrap()), ["b", "c", "a"]);
+    }
+
+    fn versioned(name: &str, version: &str, dependencies: &[(&str, &str)]) -> ModuleEntry {
+        let mut entry = module(&format!("{}@{}", name, version), name, "Test module", &[]);
+        entry.version = version.to_string();
+        entry.dependencies = dependencies.iter()
+            .map(|(name, version_req)| Dependency {
+                name: name.to_string(),
+                version_req: VersionReq::parse(version_req).unwrap(),
+            })
+            .collect();
+        entry
+    }
+
+    fn publish_all(marketplace: &ModuleMarketplaceManager, modules: Vec<ModuleEntry>) {
+        for module in modules {
+            marketplace.publish_module(module, "dev").unwrap();
+        }
+    }
+
+    fn resolved(plan: &ResolutionPlan) -> Vec<String> {
+        plan.modules.iter().map(|module| format!("{} {}", module.name, module.version)).collect()
+    }
+
+    #[test]
+    fn test_diamond_dependency_shares_highest_common_version() {
+        let marketplace = marketplace();
+        publish_all(&marketplace, vec![
+            versioned("app", "1.0.0", &[("left", "^1"), ("right", "^1")]),
+            versioned("left", "1.0.0", &[("base", ">=1.1, <2")]),
+            versioned("right", "1.0.0", &[("base", "^1.0")]),
+            versioned("base", "1.0.0", &[]),
+            versioned("base", "1.2.0", &[]),
+            versioned("base", "1.3.0", &[]),
+            versioned("base", "2.0.0", &[]),
+        ]);
+
+        let plan = marketplace.resolve("app", &VersionReq::STAR).unwrap();
+        assert_eq!(resolved(&plan), ["base 1.3.0", "left 1.0.0", "right 1.0.0", "app 1.0.0"]);
+        assert_eq!(plan.modules[0].module_id, "base@1.3.0");
+    }
+
+    #[test]
+    fn test_backtracks_to_lower_version_to_avoid_conflict() {
+        let marketplace = marketplace();
+        publish_all(&marketplace, vec![
+            versioned("app", "1.0.0", &[("codec", "^1"), ("zlib", "^1")]),
+            versioned("codec", "1.1.0", &[("zlib", "^2")]),
+            versioned("codec", "1.0.0", &[("zlib", "^1")]),
+            versioned("zlib", "1.5.0", &[]),
+            versioned("zlib", "2.0.0", &[]),
+        ]);
+
+        let plan = marketplace.resolve("app", &VersionReq::STAR).unwrap();
+        assert_eq!(plan.version_of("codec"), Some(&Version::new(1, 0, 0)));
+        assert_eq!(plan.version_of("zlib"), Some(&Version::new(1, 5, 0)));
+    }
+
+    #[test]
+    fn test_deep_dependency_chain_does_not_recurse() {
+        // 每一层都是一个回溯点，递归实现会在这样深的链上耗尽测试线程的栈
+        let depth = 4_000;
+        let marketplace = marketplace();
+        publish_all(&marketplace, (0..depth).map(|level| {
+            let next = format!("m{}", level + 1);
+            let dependencies: &[(&str, &str)] = if level + 1 < depth { &[(next.as_str(), "^1")] } else { &[] };
+            versioned(&format!("m{}", level), "1.0.0", dependencies)
+        }).collect());
+
+        let plan = marketplace.resolve("m0", &VersionReq::STAR).unwrap();
+        assert_eq!(plan.modules.len(), depth);
+    }
+
+    #[test]
+    fn test_conflict_names_each_requester() {
+        let marketplace = marketplace();
+        publish_all(&marketplace, vec![
+            versioned("app", "1.0.0", &[("image", "^1"), ("zlib", "^1")]),
+            versioned("image", "1.0.0", &[("zlib", "^2")]),
+            versioned("zlib", "1.0.0", &[]),
+            versioned("zlib", "2.0.0", &[]),
+        ]);
+
+        let error = marketplace.resolve("app", &VersionReq::STAR).unwrap_err();
+        match &error {
+            MarketplaceError::DependencyConflict { name, requirements } => {
+                assert_eq!(name, "zlib");
+                assert_eq!(requirements, &["app 1.0.0 要求 ^1", "image 1.0.0 要求 ^2"]);
+            }
+            other => panic!("意外的错误: {:?}", other),
+        }
+        assert!(error.to_string().contains("image 1.0.0 要求 ^2"));
+
+        let missing = marketplace.resolve("zlib", &VersionReq::parse("^3").unwrap()).unwrap_err();
+        assert!(matches!(missing, MarketplaceError::NoMatchingVersion { .. }));
+    }
+
+    #[test]
+    fn test_cycle_is_reported_with_its_path() {
+        let marketplace = marketplace();
+        publish_all(&marketplace, vec![
+            versioned("app", "1.0.0", &[("a", "^1")]),
+            versioned("a", "1.0.0", &[("b", "^1")]),
+            versioned("b", "1.0.0", &[("a", "^1")]),
+        ]);
+
+        match marketplace.resolve("app", &VersionReq::STAR).unwrap_err() {
+            MarketplaceError::DependencyCycle { cycle } => assert_eq!(cycle, ["a 1.0.0", "b 1.0.0", "a 1.0.0"]),
+            other => panic!("意外的错误: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_yanked_versions_require_exact_pin() {
+        let marketplace = marketplace();
+        publish_all(&marketplace, vec![versioned("zlib", "1.2.0", &[]), versioned("zlib", "1.3.0", &[])]);
+        marketplace.yank("zlib", "1.3.0", "CVE-2026-0001", "dev").unwrap();
+
+        let plan = marketplace.resolve("zlib", &VersionReq::parse("^1").unwrap()).unwrap();
+        assert_eq!(plan.version_of("zlib"), Some(&Version::new(1, 2, 0)));
+        assert!(plan.modules[0].warnings.is_empty());
+        let pinned = marketplace.resolve("zlib", &VersionReq::parse("=1.3.0").unwrap()).unwrap();
+        assert_eq!(pinned.version_of("zlib"), Some(&Version::new(1, 3, 0)));
+        assert_eq!(pinned.modules[0].warnings, [ModuleWarning::Yanked { reason: "CVE-2026-0001".to_string() }]);
+
+        let invalid = ModuleEntry { version: "1.0".to_string(), ..versioned("bad", "1.0.0", &[]) };
+        assert!(matches!(
+            marketplace.publish_module(invalid, "dev"),
+            Err(MarketplaceError::ValidationFailed(problems)) if matches!(problems[..], [ValidationProblem::InvalidVersion(_)])
+        ));
+    }
+
+    #[test]
+    fn test_cursor_pages_stay_stable_across_inserts() {
+        let marketplace = marketplace();
+        for index in 0..25 {
+            marketplace.publish_module(module(&format!("m{:02}", index), &format!("module-{:02}", index), "Paged", &[]), "dev").unwrap();
+        }
+        let mut paged = SearchQuery { keywords: None, page_size: 10, ..query("", SortBy::Name) };
+
+        let first = marketplace.search_modules(&paged).unwrap();
+        assert_eq!(first.total_estimate, 25);
+        let mut seen = ids(first.clone());
+        assert_eq!(seen.first().map(String::as_str), Some("m00"));
+
+        // 一个插在已返回的范围内，一个插在之后
+        marketplace.publish_module(module("early", "module-05a", "Paged", &[]), "dev").unwrap();
+        marketplace.publish_module(module("late", "module-15a", "Paged", &[]), "dev").unwrap();
+
+        paged.cursor = first.next_cursor;
+        let second = marketplace.search_modules(&paged).unwrap();
+        assert_eq!(second.total_estimate, 27);
+        assert_eq!(second.items.len(), 10);
+        seen.extend(ids(second.clone()));
+
+        paged.cursor = second.next_cursor;
+        let third = marketplace.search_modules(&paged).unwrap();
+        assert_eq!(third.next_cursor, None);
+        seen.extend(ids(third));
+
+        let mut expected: Vec<String> = (0..25).map(|index| format!("m{:02}", index)).collect();
+        expected.insert(16, "late".to_string());
+        assert_eq!(seen, expected);
+    }
+
+    #[test]
+    fn test_ties_break_by_module_id_without_overlap() {
+        let marketplace = marketplace();
+        for index in (0..7).rev() {
+            marketplace.publish_module(module(&format!("m{}", index), "same-name", "Tied", &[]), "dev").unwrap();
+        }
+        let mut paged = SearchQuery { keywords: None, page_size: 3, ..query("", SortBy::Downloads) };
+        let mut seen = Vec::new();
+        loop {
+            let page = marketplace.search_modules(&paged).unwrap();
+            seen.extend(ids(page.clone()));
+            match page.next_cursor {
+                Some(cursor) => paged.cursor = Some(cursor),
+                None => break,
+            }
+        }
+        assert_eq!(seen, ["m0", "m1", "m2", "m3", "m4", "m5", "m6"]);
+    }
+
+    #[test]
+    fn test_cursor_from_another_query_is_rejected() {
+        let marketplace = marketplace();
+        for index in 0..3 {
+            marketplace.publish_module(module(&format!("m{}", index), &format!("json-{}", index), "Parse JSON", &[]), "dev").unwrap();
+        }
+        let paged = SearchQuery { page_size: 1, ..query("json", SortBy::Name) };
+        let cursor = marketplace.search_modules(&paged).unwrap().next_cursor;
+        assert!(cursor.is_some());
+
+        let resorted = SearchQuery { cursor: cursor.clone(), ..query("json", SortBy::Downloads) };
+        assert!(matches!(marketplace.search_modules(&resorted), Err(MarketplaceError::InvalidCursor(_))));
+        let refiltered = SearchQuery { cursor: cursor.clone(), min_rating: Some(1.0), ..paged.clone() };
+        assert!(matches!(marketplace.search_modules(&refiltered), Err(MarketplaceError::InvalidCursor(_))));
+        let garbage = SearchQuery { cursor: Some("not-a-cursor".to_string()), ..paged.clone() };
+        assert!(matches!(marketplace.search_modules(&garbage), Err(MarketplaceError::InvalidCursor(_))));
+
+        let resized = SearchQuery { cursor, page_size: 5, ..paged };
+        assert_eq!(ids(marketplace.search_modules(&resized).unwrap()), ["m1", "m2"]);
+    }
+
+    fn key_pair() -> ring::signature::Ed25519KeyPair {
+        let pkcs8 = ring::signature::Ed25519KeyPair::generate_pkcs8(&ring::rand::SystemRandom::new()).unwrap();
+        ring::signature::Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).unwrap()
+    }
+
+    /// 注册密钥并签名发布一个模块，返回密钥ID
+    fn publish_signed_module(marketplace: &ModuleMarketplaceManager, id: &str, bytes: &[u8]) -> String {
+        use ring::signature::KeyPair;
+        let key_pair = key_pair();
+        let key_id = marketplace.user_manager.register_publisher_key("dev", key_pair.public_key().as_ref()).unwrap();
+        let entry = ModuleEntry { size: bytes.len() as u64, ..module(id, id, "Signed module", &[]) };
+        let signature = key_pair.sign(&signing_payload(&entry, bytes));
+        marketplace.publish_signed(entry, bytes.to_vec(), signature.as_ref(), "dev").unwrap();
+        key_id
+    }
+
+    fn allow_downloads(marketplace: &ModuleMarketplaceManager) {
+        marketplace.user_manager.permission_manager.rules.lock().unwrap().push(PermissionRule {
+            id: "download".to_string(),
+            role: UserRole::Developer,
+            resource: "module".to_string(),
+            action: PermissionAction::Download,
+            allowed: true,
+        });
+    }
+
+    fn security_manager(security_level: crate::security_advanced::SecurityLevel) -> Arc<AdvancedSecurityManager> {
+        use crate::security_advanced::*;
+        use std::collections::HashSet;
+        let mut manager = AdvancedSecurityManager::new();
+        manager.add_policy(SecurityPolicy {
+            id: "marketplace".to_string(),
+            name: "marketplace".to_string(),
+            security_level,
+            enabled_threats: HashSet::new(),
+            memory_limits: MemoryLimits {
+                max_memory_size: 1 << 20,
+                max_stack_size: 1 << 16,
+                max_heap_size: 1 << 20,
+                memory_alignment: 8,
+            },
+            execution_time_limit: None,
+            function_call_limit: None,
+            allowed_imports: HashSet::new(),
+            forbidden_imports: HashSet::new(),
+            sandbox_config: SandboxConfig {
+                enabled: false,
+                allowed_syscalls: HashSet::new(),
+                filesystem_restrictions: FilesystemRestrictions {
+                    allowed_paths: Vec::new(),
+                    forbidden_paths: Vec::new(),
+                    read_only_paths: Vec::new(),
+                },
+                network_restrictions: NetworkRestrictions {
+                    allowed_domains: Vec::new(),
+                    allowed_ports: Vec::new(),
+                    forbidden_protocols: Vec::new(),
+                },
+            },
+        });
+        manager.set_active_policy("marketplace".to_string()).unwrap();
+        Arc::new(manager)
+    }
+
+    fn entry(marketplace: &ModuleMarketplaceManager, id: &str) -> ModuleEntry {
+        marketplace.registry.lock().unwrap()[id].clone()
+    }
+
+    #[test]
+    fn test_signed_publish_verifies_and_downloads() {
+        let marketplace = marketplace()
+            .with_security_manager(security_manager(crate::security_advanced::SecurityLevel::High));
+        allow_downloads(&marketplace);
+        let key_id = publish_signed_module(&marketplace, "signed", b"\0asm\x01\0\0\0");
+
+        let signed = entry(&marketplace, "signed");
+        assert_eq!(signed.signature.as_ref().map(|signature| signature.key_id.as_str()), Some(key_id.as_str()));
+        assert_eq!(signed.size, 8);
+        assert_eq!(marketplace.verify(&signed), VerificationStatus::Verified);
+        assert_eq!(marketplace.download_module("signed", "dev").unwrap().module.download_count, 1);
+
+        // 未签名的模块在高安全级别下无法下载
+        marketplace.publish_module(module("unsigned", "unsigned", "No signature", &[]), "dev").unwrap();
+        assert_eq!(marketplace.verify(&entry(&marketplace, "unsigned")), VerificationStatus::Unsigned);
+        assert!(matches!(
+            marketplace.download_module("unsigned", "dev"),
+            Err(MarketplaceError::IntegrityCheckFailed(_))
+        ));
+    }
+
+    #[test]
+    fn test_tampered_bytes_and_metadata_fail_verification() {
+        let marketplace = marketplace();
+        allow_downloads(&marketplace);
+        publish_signed_module(&marketplace, "signed", b"original bytes");
+
+        marketplace.module_blobs.lock().unwrap().insert("signed".to_string(), b"tampered bytes".to_vec());
+        assert_eq!(marketplace.verify(&entry(&marketplace, "signed")), VerificationStatus::BadSignature);
+        // 没有安全管理器时只记录警告
+        assert!(marketplace.download_module("signed", "dev").is_ok());
+        let guarded = ModuleMarketplaceManager {
+            security_manager: Some(security_manager(crate::security_advanced::SecurityLevel::High)),
+            ..marketplace
+        };
+        assert!(matches!(guarded.download_module("signed", "dev"), Err(MarketplaceError::IntegrityCheckFailed(_))));
+
+        guarded.module_blobs.lock().unwrap().insert("signed".to_string(), b"original bytes".to_vec());
+        let relicensed = ModuleEntry { license: "GPL-3.0".to_string(), ..entry(&guarded, "signed") };
+        assert_eq!(guarded.verify(&relicensed), VerificationStatus::BadSignature);
+
+        // 签名与上传的字节不符时拒绝发布
+        use ring::signature::KeyPair;
+        let key_pair = key_pair();
+        guarded.user_manager.register_publisher_key("dev", key_pair.public_key().as_ref()).unwrap();
+        let other = module("other", "other", "Mismatched", &[]);
+        let signature = key_pair.sign(&signing_payload(&other, b"signed bytes"));
+        assert!(matches!(
+            guarded.publish_signed(other, b"uploaded bytes".to_vec(), signature.as_ref(), "dev"),
+            Err(MarketplaceError::InvalidSignature(_))
+        ));
+    }
+
+    #[test]
+    fn test_publisher_key_times_come_from_the_clock() {
+        let clock = ManualClock::new();
+        let marketplace = marketplace().with_clock(clock.clone());
+        let key_id = publish_signed_module(&marketplace, "signed", b"module bytes");
+        let registered = clock.now();
+        clock.advance(DAY);
+        marketplace.user_manager.revoke_publisher_key("dev", &key_id).unwrap();
+
+        let key = marketplace.user_manager.publisher_keys.lock().unwrap()["dev"][0].clone();
+        assert_eq!((key.registered_at, key.revoked_at), (registered, Some(clock.now())));
+    }
+
+    #[test]
+    fn test_revoked_key_turns_verified_into_unknown_key() {
+        let marketplace = marketplace();
+        let key_id = publish_signed_module(&marketplace, "signed", b"module bytes");
+        assert_eq!(marketplace.verify(&entry(&marketplace, "signed")), VerificationStatus::Verified);
+
+        marketplace.user_manager.revoke_publisher_key("dev", &key_id).unwrap();
+        assert_eq!(marketplace.verify(&entry(&marketplace, "signed")), VerificationStatus::UnknownKey);
+        assert!(matches!(
+            marketplace.user_manager.revoke_publisher_key("dev", &key_id),
+            Err(MarketplaceError::PublisherKeyNotFound)
+        ));
+        let revoked = marketplace.user_manager.publisher_keys.lock().unwrap()["dev"][0].public_key.clone();
+        assert!(matches!(
+            marketplace.user_manager.register_publisher_key("dev", &revoked),
+            Err(MarketplaceError::InvalidPublicKey(_))
+        ));
+        assert!(matches!(
+            marketplace.user_manager.register_publisher_key("nobody", &[0; 32]),
+            Err(MarketplaceError::UserNotFound)
+        ));
+
+        let strict = ModuleMarketplaceManager {
+            config: MarketplaceConfig { require_signatures: true, ..marketplace.config.clone() },
+            ..marketplace
+        };
+        assert!(matches!(
+            strict.publish_module(module("unsigned", "unsigned", "No signature", &[]), "dev"),
+            Err(MarketplaceError::SignatureRequired)
+        ));
+    }
+
+    /// 可手动推进的时钟
+    #[derive(Debug)]
+    struct ManualClock {
+        now: Mutex<SystemTime>,
+    }
+
+    impl ManualClock {
+        /// 从某天的正午开始
+        fn new() -> Arc<Self> {
+            Arc::new(Self { now: Mutex::new(SystemTime::UNIX_EPOCH + Duration::from_secs(20_000 * 86_400 + 43_200)) })
+        }
+
+        fn advance(&self, by: Duration) {
+            *self.now.lock().unwrap() += by;
+        }
+    }
+
+    impl MarketplaceClock for ManualClock {
+        fn now(&self) -> SystemTime {
+            *self.now.lock().unwrap()
+        }
+    }
+
+    const DAY: Duration = Duration::from_secs(86_400);
+
+    /// 以不同用户记录多次下载
+    fn record_downloads(marketplace: &ModuleMarketplaceManager, module_id: &str, count: usize) {
+        for index in 0..count {
+            marketplace.download_tracker.record(module_id, &format!("user-{}", index), marketplace.clock.now());
+        }
+    }
+
+    #[test]
+    fn test_downloads_bucket_by_day_and_dedupe_retries() {
+        let clock = ManualClock::new();
+        let marketplace = marketplace().with_clock(clock.clone());
+        allow_downloads(&marketplace);
+        marketplace.publish_module(module("m", "counted", "Counted", &[]), "dev").unwrap();
+
+        let first = marketplace.download_module("m", "dev").unwrap().module;
+        assert_eq!((first.download_count, first.downloads_total, first.downloads_last_7d), (1, 1, 1));
+        clock.advance(Duration::from_secs(30));
+        assert_eq!(marketplace.download_module("m", "dev").unwrap().module.downloads_total, 1);
+        clock.advance(Duration::from_secs(31));
+        assert_eq!(marketplace.download_module("m", "dev").unwrap().module.downloads_total, 2);
+
+        let today = day_index(clock.now());
+        record_downloads(&marketplace, "m", 3);
+        clock.advance(DAY);
+        record_downloads(&marketplace, "m", 4);
+        assert_eq!(marketplace.download_tracker.daily_counts("m", clock.now()), [(today, 5), (today + 1, 4)]);
+    }
+
+    #[test]
+    fn test_seven_day_figure_rolls_off_and_retention_is_bounded() {
+        let clock = ManualClock::new();
+        let marketplace = marketplace()
+            .with_clock(clock.clone())
+            .with_download_tracking(DownloadTrackingConfig { retention_days: 10, ..DownloadTrackingConfig::default() });
+        record_downloads(&marketplace, "m", 5);
+        clock.advance(DAY * 3);
+        record_downloads(&marketplace, "m", 2);
+
+        let summary = |clock: &ManualClock| marketplace.download_tracker.summary("m", clock.now());
+        assert_eq!(summary(&clock), DownloadSummary { total: 7, last_7d: 7 });
+        clock.advance(DAY * 4);
+        assert_eq!(summary(&clock), DownloadSummary { total: 7, last_7d: 2 });
+        clock.advance(DAY * 3);
+        assert_eq!(summary(&clock), DownloadSummary { total: 7, last_7d: 0 });
+        assert_eq!(marketplace.download_tracker.daily_counts("m", clock.now()).len(), 1);
+        clock.advance(DAY * 3);
+        assert!(marketplace.download_tracker.daily_counts("m", clock.now()).is_empty());
+        assert_eq!(summary(&clock).total, 7);
+    }
+
+    #[test]
+    fn test_trending_order_flips_when_traffic_dries_up() {
+        let clock = ManualClock::new();
+        let marketplace = marketplace().with_clock(clock.clone());
+        marketplace.publish_module(module("steady", "steady", "Old favourite", &[]), "dev").unwrap();
+        marketplace.publish_module(module("rising", "rising", "Newcomer", &[]), "dev").unwrap();
+        marketplace.publish_module(module("quiet", "quiet", "Never downloaded", &[]), "dev").unwrap();
+        let trending = SearchQuery { keywords: None, ..query("", SortBy::Trending) };
+
+        for _ in 0..10 {
+            record_downloads(&marketplace, "steady", 10);
+            record_downloads(&marketplace, "rising", 3);
+            clock.advance(DAY);
+        }
+        assert_eq!(ids(marketplace.search_modules(&trending).unwrap()), ["steady", "rising", "quiet"]);
+
+        for _ in 0..5 {
+            record_downloads(&marketplace, "rising", 3);
+            clock.advance(DAY);
+        }
+        assert_eq!(ids(marketplace.search_modules(&trending).unwrap()), ["rising", "steady", "quiet"]);
+
+        // 今天的集中下载胜过过去的稳定流量
+        record_downloads(&marketplace, "quiet", 40);
+        let results = marketplace.search_modules(&trending).unwrap();
+        assert_eq!(results.items[0].id, "quiet");
+        assert_eq!(results.items[0].downloads_last_7d, 40);
+    }
+
+    /// 添加一个可以下载并评分的普通用户
+    fn add_rater(marketplace: &ModuleMarketplaceManager, user_id: &str) {
+        marketplace.user_manager.users.lock().unwrap().insert(user_id.to_string(), User {
+            id: user_id.to_string(),
+            username: user_id.to_string(),
+            email: format!("{}@example.com", user_id),
+            created_at: SystemTime::now(),
+            last_login: None,
+            roles: vec![UserRole::User],
+            statistics: UserStatistics {
+                published_modules: 0,
+                downloaded_modules: 0,
+                rating_count: 0,
+                contribution_score: 0,
+            },
+        });
+        let mut rules = marketplace.user_manager.permission_manager.rules.lock().unwrap();
+        if !rules.iter().any(|rule| rule.role == UserRole::User) {
+            for action in [PermissionAction::Download, PermissionAction::Rate] {
+                rules.push(PermissionRule {
+                    id: format!("user-{:?}", action),
+                    role: UserRole::User,
+                    resource: "module".to_string(),
+                    action,
+                    allowed: true,
+                });
+            }
+        }
+    }
+
+    fn rating(score: u8) -> Rating {
+        Rating {
+            id: format!("rating-{}", score),
+            module_id: String::new(),
+            user_id: String::new(),
+            score,
+            comment: None,
+            rated_at: SystemTime::UNIX_EPOCH,
+            helpfulness: None,
+        }
+    }
+
+    /// 让每个分值对应一个新用户下载并评分
+    fn rate_with_new_users(marketplace: &ModuleMarketplaceManager, module_id: &str, scores: &[u8]) {
+        for (index, score) in scores.iter().enumerate() {
+            let user_id = format!("{}-rater-{}", module_id, index);
+            add_rater(marketplace, &user_id);
+            marketplace.download_module(module_id, &user_id).unwrap();
+            marketplace.rate_module(module_id, &user_id, rating(*score)).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_rating_again_replaces_and_non_downloaders_are_rejected() {
+        let marketplace = marketplace();
+        marketplace.publish_module(module("m", "rated", "Rated", &[]), "dev").unwrap();
+        add_rater(&marketplace, "alice");
+
+        assert!(matches!(marketplace.rate_module("m", "alice", rating(5)), Err(MarketplaceError::NotDownloaded)));
+        marketplace.download_module("m", "alice").unwrap();
+        marketplace.rate_module("m", "alice", rating(5)).unwrap();
+        marketplace.rate_module("m", "alice", rating(2)).unwrap();
+
+        let summary = marketplace.rating_summary("m");
+        assert_eq!((summary.count, summary.mean, summary.histogram), (1, 2.0, vec![0, 1, 0, 0, 0]));
+        let stored = entry(&marketplace, "m");
+        assert_eq!((stored.rating, stored.rating_count), (2.0, 1));
+        assert_eq!(marketplace.user_manager.users.lock().unwrap()["alice"].statistics.rating_count, 1);
+    }
+
+    #[test]
+    fn test_weighted_score_keeps_single_review_below_established_module() {
+        let marketplace = marketplace();
+        for id in ["established", "newcomer", "weak"] {
+            marketplace.publish_module(module(id, id, "Rated module", &[]), "dev").unwrap();
+        }
+        rate_with_new_users(&marketplace, "established", &[[5u8; 10], [4u8; 10]].concat());
+        rate_with_new_users(&marketplace, "weak", &[2; 10]);
+        rate_with_new_users(&marketplace, "newcomer", &[5]);
+
+        let established = marketplace.rating_summary("established");
+        let newcomer = marketplace.rating_summary("newcomer");
+        assert_eq!((established.mean, newcomer.mean), (4.5, 5.0));
+        assert_eq!(established.histogram, [0, 0, 0, 10, 10]);
+        assert!(newcomer.weighted < established.weighted, "{} >= {}", newcomer.weighted, established.weighted);
+        let by_rating = SearchQuery { keywords: None, ..query("", SortBy::Rating) };
+        assert_eq!(ids(marketplace.search_modules(&by_rating).unwrap()), ["established", "newcomer", "weak"]);
+    }
+
+    #[test]
+    fn test_rating_updates_only_the_rated_module() {
+        let marketplace = marketplace();
+        for id in ["a", "b"] {
+            marketplace.publish_module(module(id, id, "Rated module", &[]), "dev").unwrap();
+        }
+        rate_with_new_users(&marketplace, "a", &[5]);
+        let stored_a = entry(&marketplace, "a").weighted_rating;
+        assert_eq!(stored_a, 5.0);
+
+        // 给 b 评分不会改写 a 的注册表条目，但 a 的加权评分在读取时按新的全站平均计算
+        rate_with_new_users(&marketplace, "b", &[1, 1, 1]);
+        assert_eq!(entry(&marketplace, "a").weighted_rating, stored_a);
+        let global_mean = (5.0 + 3.0) / 4.0;
+        let expected = (5.0 * global_mean + 5.0) / 6.0;
+        assert!((marketplace.rating_summary("a").weighted - expected).abs() < 1e-12);
+        assert!((marketplace.get_module("a").unwrap().module.weighted_rating - expected).abs() < 1e-12);
+        assert!((entry(&marketplace, "b").weighted_rating - (5.0 * global_mean + 3.0) / 8.0).abs() < 1e-12);
+
+        // 全站累计值随替换和删除评分更新
+        marketplace.rate_module("b", "b-rater-0", rating(5)).unwrap();
+        marketplace.delete_user("a-rater-0", DeletedUserRatings::Remove).unwrap();
+        let b = marketplace.rating_summary("b");
+        assert!((b.weighted - 7.0 / 3.0).abs() < 1e-12, "{}", b.weighted);
+        assert_eq!(marketplace.rating_summary("a").weighted, 0.0);
+    }
+
+    #[test]
+    fn test_deleting_user_removes_or_anonymizes_ratings() {
+        let marketplace = marketplace();
+        marketplace.publish_module(module("m", "rated", "Rated", &[]), "dev").unwrap();
+        rate_with_new_users(&marketplace, "m", &[5, 1, 3]);
+
+        marketplace.delete_user("m-rater-1", DeletedUserRatings::Remove).unwrap();
+        assert_eq!(entry(&marketplace, "m").rating, 4.0);
+        marketplace.delete_user("m-rater-0", DeletedUserRatings::Anonymize).unwrap();
+        let stored = entry(&marketplace, "m");
+        assert_eq!((stored.rating, stored.rating_count), (4.0, 2));
+        let ratings = marketplace.rating_system.get_ratings("m").unwrap();
+        assert!(ratings.iter().all(|rating| rating.user_id != "m-rater-0"));
+        assert!(!marketplace.download_tracker.has_downloaded("m", "m-rater-0"));
+        assert!(matches!(
+            marketplace.delete_user("m-rater-0", DeletedUserRatings::Remove),
+            Err(MarketplaceError::UserNotFound)
+        ));
+    }
+
+    #[test]
+    fn test_yanked_versions_leave_search_and_warn_on_download() {
+        let marketplace = marketplace();
+        allow_downloads(&marketplace);
+        publish_all(&marketplace, vec![versioned("zlib", "1.2.0", &[]), versioned("zlib", "1.3.0", &[])]);
+        marketplace.yank("zlib", "1.3.0", "Corrupts large inputs", "dev").unwrap();
+
+        let everything = SearchQuery { keywords: None, ..query("", SortBy::Name) };
+        assert_eq!(ids(marketplace.search_modules(&everything).unwrap()), ["zlib@1.2.0"]);
+        let fetched = marketplace.download_module("zlib@1.3.0", "dev").unwrap();
+        assert_eq!(fetched.warnings, [ModuleWarning::Yanked { reason: "Corrupts large inputs".to_string() }]);
+
+        marketplace.unyank("zlib", "1.3.0", "dev").unwrap();
+        assert!(marketplace.get_module("zlib@1.3.0").unwrap().warnings.is_empty());
+        assert_eq!(ids(marketplace.search_modules(&everything).unwrap()), ["zlib@1.2.0", "zlib@1.3.0"]);
+        let actions: Vec<AuditAction> = marketplace.audit_records().into_iter().map(|record| record.action).collect();
+        assert_eq!(actions, [
+            AuditAction::Yank { version: "1.3.0".to_string(), reason: "Corrupts large inputs".to_string() },
+            AuditAction::Unyank { version: "1.3.0".to_string() },
+        ]);
+    }
+
+    #[test]
+    fn test_deprecation_surfaces_on_every_fetch() {
+        let clock = ManualClock::new();
+        let marketplace = marketplace().with_clock(clock.clone());
+        publish_all(&marketplace, vec![versioned("left-pad", "1.0.0", &[]), versioned("left-pad", "1.1.0", &[])]);
+        marketplace.deprecate("left-pad", Some("pad"), "Use pad instead", "dev").unwrap();
+
+        let expected = ModuleWarning::Deprecated {
+            message: "Use pad instead".to_string(),
+            replacement: Some("pad".to_string()),
+        };
+        for module_id in ["left-pad@1.0.0", "left-pad@1.1.0"] {
+            assert_eq!(marketplace.get_module(module_id).unwrap().warnings, std::slice::from_ref(&expected));
+        }
+        let record = &marketplace.audit_records()[0];
+        assert_eq!((record.user_id.as_str(), record.module.as_str(), record.at), ("dev", "left-pad", clock.now()));
+    }
+
+    #[test]
+    fn test_only_owner_or_admin_may_yank_or_deprecate() {
+        let marketplace = marketplace();
+        publish_all(&marketplace, vec![versioned("zlib", "1.2.0", &[])]);
+        add_rater(&marketplace, "mallory");
+        add_rater(&marketplace, "admin");
+        marketplace.user_manager.users.lock().unwrap().get_mut("admin").unwrap().roles.push(UserRole::Administrator);
+
+        assert!(matches!(marketplace.yank("zlib", "1.2.0", "Mine now", "mallory"), Err(MarketplaceError::PermissionDenied)));
+        assert!(matches!(
+            marketplace.deprecate("zlib", None, "Abandoned", "mallory"),
+            Err(MarketplaceError::PermissionDenied)
+        ));
+        assert!(matches!(marketplace.yank("zlib", "9.9.9", "Missing", "dev"), Err(MarketplaceError::ModuleNotFound)));
+        assert!(!entry(&marketplace, "zlib@1.2.0").yanked);
+        assert!(marketplace.audit_records().is_empty());
+
+        marketplace.yank("zlib", "1.2.0", "Broken build", "admin").unwrap();
+        assert!(entry(&marketplace, "zlib@1.2.0").yanked);
+    }
+
+    #[test]
+    fn test_only_owner_may_publish_new_versions() {
+        let marketplace = marketplace();
+        publish_all(&marketplace, vec![versioned("zlib", "1.2.0", &[])]);
+        add_rater(&marketplace, "mallory");
+        marketplace.user_manager.users.lock().unwrap().get_mut("mallory").unwrap().roles.push(UserRole::Developer);
+
+        assert!(matches!(
+            marketplace.publish_module(versioned("zlib", "1.3.0", &[]), "mallory"),
+            Err(MarketplaceError::PermissionDenied)
+        ));
+        assert!(!marketplace.registry.lock().unwrap().contains_key("zlib@1.3.0"));
+
+        // 未被占用的名字可以发布，所有者随后可以继续发布
+        marketplace.publish_module(versioned("mallory-zip", "1.0.0", &[]), "mallory").unwrap();
+        marketplace.publish_module(versioned("zlib", "1.3.0", &[]), "dev").unwrap();
+        assert_eq!(marketplace.module_owners.lock().unwrap()["mallory-zip"], "mallory");
+    }
+
+    /// 发布一个签名模块和一个未签名模块，再发布一个不导出的模块
+    fn exporting_marketplace() -> ModuleMarketplaceManager {
+        let source = marketplace();
+        publish_signed_module(&source, "signed", b"\0asm signed");
+        source.publish_module(module("plain", "plain", "Unsigned module", &[]), "dev").unwrap();
+        source.publish_module(module("private", "private", "Stays behind", &[]), "dev").unwrap();
+        source.deprecate("plain", None, "Use signed", "dev").unwrap();
+        source
+    }
+
+    fn mirrored() -> CatalogFilter {
+        CatalogFilter { names: vec!["signed".to_string(), "plain".to_string()], ..CatalogFilter::default() }
+    }
+
+    /// 事先信任源市场中 dev 的发布者公钥的镜像
+    fn trusting_mirror(source: &ModuleMarketplaceManager) -> ModuleMarketplaceManager {
+        let mirror = marketplace();
+        for key in source.user_manager.active_publisher_keys("dev") {
+            mirror.user_manager.register_publisher_key("dev", &key.public_key).unwrap();
+        }
+        mirror
+    }
+
+    #[test]
+    fn test_catalog_round_trips_into_empty_marketplace() {
+        let source = exporting_marketplace();
+        let bundle = source.export_catalog(&mirrored());
+        assert_eq!(bundle.entries.len(), 2);
+        let bundle = CatalogBundle::from_bytes(&bundle.to_bytes()).unwrap();
+
+        let mirror = trusting_mirror(&source)
+            .with_security_manager(security_manager(crate::security_advanced::SecurityLevel::Medium));
+        allow_downloads(&mirror);
+        let report = mirror.import_catalog(&bundle, ImportPolicy::SkipExisting).unwrap();
+        assert_eq!(report.imported(), 2);
+
+        let everything = SearchQuery { keywords: None, ..query("", SortBy::Name) };
+        assert_eq!(ids(mirror.search_modules(&everything).unwrap()), ["plain", "signed"]);
+        assert_eq!(ids(mirror.search_modules(&query("signed", SortBy::Relevance)).unwrap()), ["signed"]);
+        assert_eq!(mirror.verify(&entry(&mirror, "signed")), VerificationStatus::Verified);
+        assert!(mirror.download_module("signed", "dev").is_ok());
+        assert_eq!(mirror.download_module("plain", "dev").unwrap().warnings, [ModuleWarning::Deprecated {
+            message: "Use signed".to_string(),
+            replacement: None,
+        }]);
+    }
+
+    #[test]
+    fn test_import_only_trusts_local_publisher_keys() {
+        let source = exporting_marketplace();
+        let bundle = source.export_catalog(&mirrored());
+        assert_eq!(bundle.publisher_keys.len(), 1);
+
+        // 镜像没有信任 dev 的密钥：签名条目被拒绝，随附的公钥也不会被安装
+        let mirror = marketplace();
+        let report = mirror.import_catalog(&bundle, ImportPolicy::SkipExisting).unwrap();
+        assert!(matches!(report.outcome_of("signed"), Some(ImportOutcome::Failed { reason }) if reason.contains("公钥未找到")));
+        assert_eq!(report.outcome_of("plain"), Some(&ImportOutcome::Imported));
+        assert!(mirror.user_manager.active_publisher_keys("dev").is_empty());
+
+        // 攻击者用自己的密钥重新签名并附上公钥，同样被拒绝
+        use ring::signature::KeyPair;
+        let forger = key_pair();
+        let mut forged = bundle.clone();
+        let bundled = forged.entries.iter_mut().find(|bundled| bundled.module.id == "signed").unwrap();
+        let bytes = forged.blobs[bundled.blob.as_ref().unwrap()].clone();
+        let forged_key_id = hex(&Sha256::digest(forger.public_key().as_ref())[..8]);
+        bundled.module.signature = Some(ModuleSignature {
+            signer: "dev".to_string(),
+            key_id: forged_key_id.clone(),
+            signature: forger.sign(&signing_payload(&bundled.module, &bytes)).as_ref().to_vec(),
+        });
+        forged.publisher_keys = vec![BundledPublisherKey {
+            user_id: "dev".to_string(),
+            key: PublisherKey {
+                key_id: forged_key_id,
+                public_key: forger.public_key().as_ref().to_vec(),
+                registered_at: SystemTime::UNIX_EPOCH,
+                revoked_at: None,
+            },
+        }];
+        let mirror = trusting_mirror(&source);
+        let report = mirror.import_catalog(&forged, ImportPolicy::SkipExisting).unwrap();
+        assert!(matches!(report.outcome_of("signed"), Some(ImportOutcome::Failed { .. })));
+        assert_eq!(mirror.user_manager.active_publisher_keys("dev").len(), 1);
+    }
+
+    #[test]
+    fn test_unsigned_entry_cannot_overwrite_signed_one() {
+        let source = exporting_marketplace();
+        let mirror = trusting_mirror(&source);
+        mirror.import_catalog(&source.export_catalog(&mirrored()), ImportPolicy::SkipExisting).unwrap();
+
+        let mut bundle = source.export_catalog(&mirrored());
+        for bundled in &mut bundle.entries {
+            bundled.module.signature = None;
+            bundled.module.description = "Stripped".to_string();
+        }
+        let report = mirror.import_catalog(&bundle, ImportPolicy::Overwrite).unwrap();
+        assert!(matches!(report.outcome_of("signed"), Some(ImportOutcome::Failed { reason }) if reason.contains("未签名")));
+        assert_eq!(report.outcome_of("plain"), Some(&ImportOutcome::Imported));
+        assert_eq!(entry(&mirror, "signed").description, "Signed module");
+        assert_eq!(mirror.verify(&entry(&mirror, "signed")), VerificationStatus::Verified);
+    }
+
+    #[test]
+    fn test_reimport_skips_or_overwrites_existing_versions() {
+        let source = exporting_marketplace();
+        let mirror = trusting_mirror(&source);
+        mirror.import_catalog(&source.export_catalog(&mirrored()), ImportPolicy::SkipExisting).unwrap();
+
+        source.registry.lock().unwrap().get_mut("plain").unwrap().description = "Updated upstream".to_string();
+        let bundle = source.export_catalog(&mirrored());
+        let report = mirror.import_catalog(&bundle, ImportPolicy::SkipExisting).unwrap();
+        assert!(report.entries.iter().all(|entry| matches!(entry.outcome, ImportOutcome::Skipped { .. })));
+        assert_eq!(entry(&mirror, "plain").description, "Unsigned module");
+
+        let report = mirror.import_catalog(&bundle, ImportPolicy::Overwrite).unwrap();
+        assert_eq!(report.imported(), 2);
+        assert_eq!(entry(&mirror, "plain").description, "Updated upstream");
+        assert_eq!(ids(mirror.search_modules(&query("upstream", SortBy::Relevance)).unwrap()), ["plain"]);
+    }
+
+    #[test]
+    fn test_import_never_replaces_a_newer_version() {
+        let source = exporting_marketplace();
+        let mirror = trusting_mirror(&source);
+        let mut bundle = source.export_catalog(&mirrored());
+        bundle.entries.retain(|bundled| bundled.module.id == "plain");
+        let mut newer = bundle.entries[0].clone();
+        newer.module.version = "1.1.0".to_string();
+        bundle.entries.insert(0, newer);
+
+        // 目录中新版本排在前面，导入后仍保留新版本
+        mirror.import_catalog(&bundle, ImportPolicy::Overwrite).unwrap();
+        assert_eq!(entry(&mirror, "plain").version, "1.1.0");
+
+        bundle.entries.remove(0);
+        let report = mirror.import_catalog(&bundle, ImportPolicy::Overwrite).unwrap();
+        assert!(matches!(report.outcome_of("plain"), Some(ImportOutcome::Skipped { .. })));
+        assert_eq!(entry(&mirror, "plain").version, "1.1.0");
+    }
+
+    #[test]
+    fn test_failed_entries_leave_existing_catalog_untouched() {
+        let source = exporting_marketplace();
+        let mirror = trusting_mirror(&source);
+        mirror.import_catalog(&source.export_catalog(&mirrored()), ImportPolicy::SkipExisting).unwrap();
+
+        // 篡改签名模块的元数据后签名校验失败，改用不允许的许可证后发布检查失败，已有条目都保持不变
+        let mut bundle = source.export_catalog(&mirrored());
+        for bundled in &mut bundle.entries {
+            bundled.module.description = "Tampered".to_string();
+            match bundled.module.id.as_str() {
+                "signed" => bundled.module.author = "mallory".to_string(),
+                _ => bundled.module.license = "GPL-3.0".to_string(),
+            }
+        }
+        let report = mirror.import_catalog(&bundle, ImportPolicy::Overwrite).unwrap();
+        assert!(matches!(report.outcome_of("signed"), Some(ImportOutcome::Failed { .. })));
+        assert!(matches!(report.outcome_of("plain"), Some(ImportOutcome::Failed { .. })));
+        assert_eq!(entry(&mirror, "signed").description, "Signed module");
+        assert_eq!(entry(&mirror, "plain").description, "Unsigned module");
+        assert_eq!(mirror.verify(&entry(&mirror, "signed")), VerificationStatus::Verified);
+
+        let mut archive = source.export_catalog(&mirrored()).to_bytes();
+        let last = archive.len() - 1;
+        archive[last] ^= 0xff;
+        assert!(matches!(CatalogBundle::from_bytes(&archive), Err(MarketplaceError::InvalidCatalog(_))));
+        let future = CatalogBundle { format_version: 2, ..CatalogBundle::default() };
+        assert!(matches!(mirror.import_catalog(&future, ImportPolicy::Overwrite), Err(MarketplaceError::InvalidCatalog(_))));
+    }
+
+    /// runtime > simd > simd-128、runtime > threads、graphics
+    fn categorized_marketplace() -> ModuleMarketplaceManager {
+        let mut marketplace = marketplace();
+        marketplace.config.allowed_licenses.push("Apache-2.0".to_string());
+        {
+            let mut tree = marketplace.category_tree.lock().unwrap();
+            tree.add("runtime", "Runtime", None).unwrap();
+            tree.add("simd", "SIMD", Some("runtime")).unwrap();
+            tree.add("simd-128", "128-bit", Some("simd")).unwrap();
+            tree.add("threads", "Threads", Some("runtime")).unwrap();
+            tree.add("graphics", "Graphics", None).unwrap();
+        }
+        let modules = [
+            ("vec", &["simd-128"][..], &["fast", "math"][..], "MIT"),
+            ("lanes", &["simd"][..], &["fast"][..], "MIT"),
+            ("pool", &["threads"][..], &["fast", "math"][..], "Apache-2.0"),
+            ("shader", &["graphics", "simd"][..], &["math"][..], "MIT"),
+        ];
+        for (id, categories, tags, license) in modules {
+            let entry = ModuleEntry {
+                categories: categories.iter().map(|slug| slug.to_string()).collect(),
+                license: license.to_string(),
+                ..module(id, id, "Categorized module", tags)
+            };
+            marketplace.publish_module(entry, "dev").unwrap();
+        }
+        marketplace
+    }
+
+    fn faceted(categories: &[&str], tags: &[&str], license: Option<&str>) -> SearchQuery {
+        SearchQuery {
+            keywords: None,
+            categories: categories.iter().map(|slug| slug.to_string()).collect(),
+            tags: (!tags.is_empty()).then(|| tags.iter().map(|tag| tag.to_string()).collect()),
+            license: license.map(str::to_string),
+            ..query("", SortBy::Name)
+        }
+    }
+
+    #[test]
+    fn test_category_filter_includes_descendants_after_moves() {
+        let marketplace = categorized_marketplace();
+        let search = |query: SearchQuery| ids(marketplace.search_modules(&query).unwrap());
+        assert_eq!(search(faceted(&["runtime"], &[], None)), ["lanes", "pool", "shader", "vec"]);
+        assert_eq!(search(faceted(&["simd"], &[], None)), ["lanes", "shader", "vec"]);
+        assert_eq!(marketplace.category_tree.lock().unwrap().path("simd-128"), "Runtime > SIMD > 128-bit");
+
+        // 移动节点后模块仍保留原节点标识，按新的位置解析
+        marketplace.category_tree.lock().unwrap().move_node("simd", Some("graphics")).unwrap();
+        assert_eq!(search(faceted(&["runtime"], &[], None)), ["pool"]);
+        assert_eq!(search(faceted(&["graphics"], &[], None)), ["lanes", "shader", "vec"]);
+        assert!(matches!(
+            marketplace.category_tree.lock().unwrap().move_node("graphics", Some("simd-128")),
+            Err(MarketplaceError::InvalidCategory(_))
+        ));
+        let unknown = ModuleEntry { categories: vec!["audio".to_string()], ..module("x", "x", "Unknown", &[]) };
+        assert!(matches!(
+            marketplace.publish_module(unknown, "dev"),
+            Err(MarketplaceError::ValidationFailed(problems)) if problems == [ValidationProblem::UnknownCategory("audio".to_string())]
+        ));
+    }
+
+    #[test]
+    fn test_facet_counts_match_result_set() {
+        let marketplace = categorized_marketplace();
+        let paged = SearchQuery { page_size: 1, ..faceted(&["runtime"], &[], None) };
+        let results = marketplace.search_modules(&paged).unwrap();
+        assert_eq!((results.items.len(), results.total_estimate), (1, 4));
+        let counts = |pairs: &[(&str, usize)]| pairs.iter().map(|(key, count)| (key.to_string(), *count)).collect();
+        assert_eq!(results.facets.categories, counts(&[
+            ("graphics", 1), ("runtime", 4), ("simd", 3), ("simd-128", 1), ("threads", 1),
+        ]));
+        assert_eq!(results.facets.tags, counts(&[("fast", 3), ("math", 3)]));
+        assert_eq!(results.facets.licenses, counts(&[("Apache-2.0", 1), ("MIT", 3)]));
+    }
+
+    #[test]
+    fn test_multiple_facets_intersect() {
+        let marketplace = categorized_marketplace();
+        let search = |query: SearchQuery| ids(marketplace.search_modules(&query).unwrap());
+        assert_eq!(search(faceted(&["simd"], &["fast", "math"], None)), ["vec"]);
+        assert_eq!(search(faceted(&["simd", "threads"], &["math"], None)), ["pool", "shader", "vec"]);
+        assert_eq!(search(faceted(&["runtime"], &["math"], Some("MIT"))), ["shader", "vec"]);
+        let results = marketplace.search_modules(&faceted(&[], &["fast"], Some("Apache-2.0"))).unwrap();
+        assert_eq!(results.facets.licenses.len(), 1);
+        assert_eq!(ids(results), ["pool"]);
+    }
+
+    #[test]
+    fn test_publish_token_succeeds_and_download_token_lacks_scope() {
+        let marketplace = marketplace();
+        let publish = marketplace.user_manager.create_token("dev", &[TokenScope::Publish], None).unwrap();
+        let download = marketplace.user_manager.create_token("dev", &[TokenScope::Download], None).unwrap();
+
+        let published = marketplace.publish_module_with_token(module("ci", "ci-built", "From CI", &[]), &publish.secret);
+        assert_eq!(published.unwrap(), "ci");
+        assert!(matches!(
+            marketplace.publish_module_with_token(module("other", "other", "From CI", &[]), &download.secret),
+            Err(MarketplaceError::InsufficientScope(TokenScope::Publish))
+        ));
+        assert!(matches!(
+            marketplace.yank_with_token("ci-built", "1.0.0", "Bad build", &publish.secret),
+            Err(MarketplaceError::InsufficientScope(TokenScope::Yank))
+        ));
+        assert!(matches!(
+            marketplace.user_manager.create_token("dev", &[TokenScope::Admin], None),
+            Err(MarketplaceError::PermissionDenied)
+        ));
+
+        let listed = marketplace.user_manager.list_tokens("dev");
+        let last_used = |id: &str| listed.iter().find(|token| token.id == id).unwrap().last_used_at;
+        assert!(last_used(&publish.id).is_some());
+        assert_eq!(listed.len(), 2);
+    }
+
+    #[test]
+    fn test_revoked_and_expired_tokens_fail_closed() {
+        let clock = ManualClock::new();
+        let marketplace = marketplace().with_clock(clock.clone());
+        let revoked = marketplace.user_manager.create_token("dev", &[TokenScope::Publish], None).unwrap();
+        let expiring = marketplace.user_manager
+            .create_token("dev", &[TokenScope::Publish], Some(Duration::from_secs(3600)))
+            .unwrap();
+
+        marketplace.user_manager.revoke_token("dev", &revoked.id).unwrap();
+        assert!(matches!(
+            marketplace.publish_module_with_token(module("a", "a", "Revoked", &[]), &revoked.secret),
+            Err(MarketplaceError::InvalidToken(_))
+        ));
+        assert!(marketplace.user_manager.authenticate_token(&expiring.secret).is_ok());
+        clock.advance(Duration::from_secs(3600));
+        assert!(matches!(marketplace.user_manager.authenticate_token(&expiring.secret), Err(MarketplaceError::InvalidToken(_))));
+
+        // 篡改密钥部分或伪造格式都被拒绝
+        let mut forged = expiring.secret.clone();
+        forged.pop();
+        forged.push('x');
+        for token in [forged.as_str(), "wmt_", "not-a-token", ""] {
+            assert!(matches!(marketplace.user_manager.authenticate_token(token), Err(MarketplaceError::InvalidToken(_))));
+        }
+    }
+
+    #[test]
+    fn test_stored_tokens_never_contain_plaintext() {
+        let marketplace = marketplace();
+        let issued = marketplace.user_manager.create_token("dev", &[TokenScope::Publish, TokenScope::Download], None).unwrap();
+        let (_, secret_part) = issued.secret.split_once('.').unwrap();
+
+        let stored = marketplace.user_manager.api_tokens.lock().unwrap();
+        let serialized = serde_json::to_string(&*stored).unwrap();
+        let debugged = format!("{:?}", *stored);
+        for form in [serialized, debugged] {
+            assert!(!form.contains(&issued.secret) && !form.contains(secret_part));
+        }
+        assert_eq!(stored[&issued.id].secret_hash, hex(&Sha256::digest(issued.secret.as_bytes())));
+    }
+
+    /// 导出函数 `add` 的最小模块
+    const ADD_MODULE: &[u8] = &[
+        0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, // 文件头
+        0x01, 0x04, 0x01, 0x60, 0x00, 0x00, // 类型段: () -> ()
+        0x03, 0x02, 0x01, 0x00, // 函数段
+        0x07, 0x07, 0x01, 0x03, b'a', b'd', b'd', 0x00, 0x00, // 导出段: "add"
+        0x0a, 0x04, 0x01, 0x02, 0x00, 0x0b, // 代码段
+    ];
+
+    fn problems(result: Result<String, MarketplaceError>) -> Vec<ValidationProblem> {
+        match result {
+            Err(MarketplaceError::ValidationFailed(problems)) => problems,
+            other => panic!("expected validation failure, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_spdx_expressions_parse_and_check_allowed_licenses() {
+        let mut marketplace = marketplace();
+        marketplace.config.allowed_licenses.push("Apache-2.0".to_string());
+        for license in ["MIT", "MIT OR GPL-3.0-only", "(MIT OR Apache-2.0) AND Apache-2.0"] {
+            let entry = ModuleEntry { license: license.to_string(), ..module("ok", "ok", "Licensed", &[]) };
+            assert!(marketplace.validation_problems(&entry, None).is_empty(), "{}", license);
+        }
+
+        let check = |license: &str| {
+            let entry = ModuleEntry { license: license.to_string(), ..module("bad", "bad", "Licensed", &[]) };
+            marketplace.validation_problems(&entry, None)
+        };
+        assert_eq!(check("MIT AND GPL-3.0-only"), [ValidationProblem::LicenseNotAllowed("MIT AND GPL-3.0-only".to_string())]);
+        for license in ["", "Proprietary", "MIT OR", "(MIT", "MIT Apache-2.0", "mit"] {
+            assert!(matches!(check(license)[..], [ValidationProblem::InvalidLicense(_)]), "{:?}", license);
+        }
+    }
+
+    #[test]
+    fn test_problems_are_collected_and_exports_are_checked() {
+        let marketplace = marketplace();
+        let mut entry = ModuleEntry {
+            license: "Proprietary".to_string(),
+            exports: vec!["add".to_string(), "sub".to_string()],
+            ..module("math", "math", "", &[])
+        };
+        entry.compatibility.required_features = vec!["SimdInstructions".to_string(), "Teleportation".to_string()];
+        assert_eq!(marketplace.validation_problems(&entry, Some(ADD_MODULE)), [
+            ValidationProblem::MissingField("description".to_string()),
+            ValidationProblem::InvalidLicense("无法识别的 SPDX 许可证标识 \"Proprietary\"".to_string()),
+            ValidationProblem::UnknownFeature("Teleportation".to_string()),
+            ValidationProblem::MissingExport("sub".to_string()),
+        ]);
+        assert!(matches!(
+            marketplace.validation_problems(&entry, Some(b"not wasm")).last(),
+            Some(ValidationProblem::MalformedModule(_))
+        ));
+        // 不带字节发布时无法核对导出
+        let unverifiable = marketplace.validation_problems(&entry, None);
+        assert_eq!(unverifiable.last(), Some(&ValidationProblem::ExportsUnverifiable));
+        assert_eq!(problems(marketplace.publish_module(entry, "dev")), unverifiable);
+    }
+
+    #[test]
+    fn test_oversized_modules_are_rejected() {
+        let mut marketplace = marketplace();
+        marketplace.config.max_module_size = 16;
+        let declared = ModuleEntry { size: 17, ..module("big", "big", "Too big", &[]) };
+        assert_eq!(problems(marketplace.publish_module(declared, "dev")), [
+            ValidationProblem::ModuleTooLarge { size: 17, limit: 16 },
+        ]);
+        // 上传了字节时以实际大小为准
+        let uploaded = ModuleEntry { size: 1, ..module("big", "big", "Too big", &[]) };
+        let found = marketplace.publish_draft(uploaded, Some(ADD_MODULE.to_vec()), "dev").unwrap();
+        assert_eq!(found, [ValidationProblem::ModuleTooLarge { size: ADD_MODULE.len() as u64, limit: 16 }]);
+    }
+
+    #[test]
+    fn test_drafts_stay_hidden_until_problems_are_fixed() {
+        let marketplace = marketplace();
+        let phantom = ModuleEntry { exports: vec!["mul".to_string()], ..module("calc", "calc", "Calculator", &[]) };
+        let found = marketplace.publish_draft(phantom, Some(ADD_MODULE.to_vec()), "dev").unwrap();
+        assert_eq!(found, [ValidationProblem::MissingExport("mul".to_string())]);
+
+        let everything = SearchQuery { keywords: None, ..query("", SortBy::Name) };
+        assert!(ids(marketplace.search_modules(&everything).unwrap()).is_empty());
+        assert!(ids(marketplace.search_modules(&query("calculator", SortBy::Relevance)).unwrap()).is_empty());
+        assert!(marketplace.resolve("calc", &VersionReq::STAR).is_err());
+        assert!(marketplace.get_module("calc").unwrap().module.draft);
+        assert_eq!(marketplace.draft_problems.lock().unwrap()["calc"].len(), 1);
+
+        let fixed = ModuleEntry { exports: vec!["add".to_string()], ..module("calc", "calc", "Calculator", &[]) };
+        assert!(marketplace.publish_draft(fixed, Some(ADD_MODULE.to_vec()), "dev").unwrap().is_empty());
+        assert_eq!(ids(marketplace.search_modules(&everything).unwrap()), ["calc"]);
+        assert!(marketplace.draft_problems.lock().unwrap().is_empty());
+
+        // 已正式发布后，有问题的草稿不能替换正式条目
+        let broken = ModuleEntry { exports: vec!["mul".to_string()], ..module("calc", "calc", "Broken", &[]) };
+        assert!(matches!(
+            marketplace.publish_draft(broken, Some(ADD_MODULE.to_vec()), "dev"),
+            Err(MarketplaceError::ValidationFailed(problems)) if problems == [ValidationProblem::MissingExport("mul".to_string())]
+        ));
+        let live = marketplace.get_module("calc").unwrap().module;
+        assert!(!live.draft);
+        assert_eq!(live.description, "Calculator");
+        assert_eq!(ids(marketplace.search_modules(&everything).unwrap()), ["calc"]);
+        assert!(marketplace.draft_problems.lock().unwrap().is_empty());
+    }
+
+    /// 用给定的导出函数签名生成模块字节
+    fn wasm_with_exports(exports: &[(&str, &[wasm_encoder::ValType], &[wasm_encoder::ValType])]) -> Vec<u8> {
+        use wasm_encoder::{CodeSection, ExportKind, ExportSection, Function, FunctionSection, Instruction, TypeSection};
+        let (mut types, mut functions, mut export_section, mut code) =
+            (TypeSection::new(), FunctionSection::new(), ExportSection::new(), CodeSection::new());
+        for (index, (name, params, results)) in exports.iter().enumerate() {
+            types.ty().function(params.iter().copied(), results.iter().copied());
+            functions.function(index as u32);
+            export_section.export(name, ExportKind::Func, index as u32);
+            let mut body = Function::new([]);
+            body.instruction(&Instruction::Unreachable).instruction(&Instruction::End);
+            code.function(&body);
+        }
+        let mut module = wasm_encoder::Module::new();
+        module.section(&types).section(&functions).section(&export_section).section(&code);
+        module.finish()
+    }
+
+    fn publish_version(
+        marketplace: &ModuleMarketplaceManager,
+        version: &str,
+        dependencies: &[(&str, &str)],
+        bytes: Vec<u8>,
+        changelog: &str,
+    ) {
+        let entry = ModuleEntry {
+            changelog: Some(changelog.to_string()),
+            size: bytes.len() as u64,
+            ..versioned("codec", version, dependencies)
+        };
+        assert!(marketplace.publish_draft(entry, Some(bytes), "dev").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_export_gaining_parameter_is_breaking() {
+        use wasm_encoder::ValType::{I32, I64};
+        let marketplace = marketplace();
+        let initial = wasm_with_exports(&[("encode", &[I32], &[I32]), ("reset", &[], &[])]);
+        let initial_size = initial.len() as i64;
+        publish_version(&marketplace, "1.0.0", &[], initial, "Initial");
+        publish_version(&marketplace, "1.1.0", &[], wasm_with_exports(&[
+            ("encode", &[I32], &[I32]),
+            ("reset", &[], &[]),
+            ("decode", &[I32], &[I64]),
+        ]), "Add decode");
+        let major = wasm_with_exports(&[("encode", &[I32, I32], &[I32]), ("decode", &[I32], &[I64])]);
+        let major_size = major.len() as i64;
+        publish_version(&marketplace, "2.0.0", &[], major, "Encode takes a level");
+
+        let additive = marketplace.diff("codec", "1.0.0", "1.1.0").unwrap();
+        assert!(!additive.breaking);
+        assert_eq!(additive.exports.unwrap(), [ExportChange::Added {
+            name: "decode".to_string(),
+            ty: FunctionType { params: vec![ValueType::I32], results: vec![ValueType::I64] },
+        }]);
+
+        let breaking = marketplace.diff("codec", "1.0.0", "2.0.0").unwrap();
+        assert!(breaking.breaking);
+        let exports = breaking.exports.unwrap();
+        assert!(exports.contains(&ExportChange::Changed {
+            name: "encode".to_string(),
+            from: FunctionType { params: vec![ValueType::I32], results: vec![ValueType::I32] },
+            to: FunctionType { params: vec![ValueType::I32, ValueType::I32], results: vec![ValueType::I32] },
+        }));
+        assert!(exports.iter().any(|change| matches!(change, ExportChange::Removed { name, .. } if name == "reset")));
+        let notes: Vec<&str> = breaking.changelog.iter().map(|(_, note)| note.as_str()).collect();
+        assert_eq!(notes, ["Add decode", "Encode takes a level"]);
+        assert_eq!(breaking.size_delta, major_size - initial_size);
+    }
+
+    #[test]
+    fn test_dependency_bumps_are_classified() {
+        let marketplace = marketplace();
+        let empty = wasm_with_exports(&[]);
+        publish_version(&marketplace, "1.0.0", &[("zlib", "^1.2"), ("log", "^0.4.1"), ("serde", "^1.0"), ("old", "^1")], empty.clone(), "One");
+        publish_version(&marketplace, "1.1.0", &[("zlib", "^2.0"), ("log", "^0.4.2"), ("serde", "^1.1"), ("new", "^3")], empty, "Two");
+
+        let diff = marketplace.diff("codec", "1.0.0", "1.1.0").unwrap();
+        let bump = |dependency: &str| diff.dependencies.iter().find_map(|change| match change {
+            DependencyChange::Changed { name, bump, .. } if name == dependency => Some(*bump),
+            _ => None,
+        });
+        assert_eq!(bump("zlib"), Some(VersionBump::Major));
+        assert_eq!(bump("serde"), Some(VersionBump::Minor));
+        assert_eq!(bump("log"), Some(VersionBump::Patch));
+        assert!(diff.dependencies.contains(&DependencyChange::Removed {
+            name: "old".to_string(),
+            version_req: VersionReq::parse("^1").unwrap(),
+        }));
+        assert!(diff.dependencies.contains(&DependencyChange::Added {
+            name: "new".to_string(),
+            version_req: VersionReq::parse("^3").unwrap(),
+        }));
+        assert_eq!(VersionBump::between(&VersionReq::parse("^1.2").unwrap(), &VersionReq::parse("~1.2").unwrap()).unwrap(), VersionBump::Other);
+        assert!(diff.breaking);
+        assert_eq!((diff.license, diff.exports), (None, Some(Vec::new())));
+        assert!(matches!(marketplace.diff("codec", "1.0.0", "9.0.0"), Err(MarketplaceError::ModuleNotFound)));
+    }
+
+    #[test]
+    fn test_deprecate_with_token_requires_yank_scope() {
+        let marketplace = marketplace();
+        let publish = marketplace.user_manager.create_token("dev", &[TokenScope::Publish], None).unwrap();
+        let yank = marketplace.user_manager.create_token("dev", &[TokenScope::Yank], None).unwrap();
+        marketplace.publish_module_with_token(module("ci", "ci-built", "From CI", &[]), &publish.secret).unwrap();
+
+        assert!(matches!(
+            marketplace.deprecate_with_token("ci-built", None, "Unmaintained", &publish.secret),
+            Err(MarketplaceError::InsufficientScope(TokenScope::Yank))
+        ));
+        marketplace.deprecate_with_token("ci-built", Some("ci-next"), "Unmaintained", &yank.secret).unwrap();
+        let records = marketplace.audit_records();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].user_id, "dev");
+        assert!(matches!(&records[0].action, AuditAction::Deprecate { replacement: Some(name), .. } if name == "ci-next"));
+    }
+
+    #[test]
+    fn test_version_bump_uses_requirement_minimum() {
+        let between = |from: &str, to: &str| {
+            VersionBump::between(&VersionReq::parse(from).unwrap(), &VersionReq::parse(to).unwrap()).unwrap()
+        };
+        assert_eq!(between(">=1.2, <2", ">=1.3, <2"), VersionBump::Minor);
+        assert_eq!(between("<2", "<3"), VersionBump::Other);
+        assert_eq!(between("<=1.4", ">=1.4"), VersionBump::Major);
+        assert_eq!(between(">1.2.3", ">=1.2.4"), VersionBump::Other);
+        assert_eq!(between(">1", "^2"), VersionBump::Other);
+        assert_eq!(between("^0.3", "^0.4"), VersionBump::Major);
+        assert_eq!(between("^0.0.3", "^0.0.4"), VersionBump::Major);
+        assert!(!between("^1.1", "^1.2").is_breaking());
+
+        let max = u64::MAX;
+        for overflowing in [format!(">{}", max), format!(">1.{}", max), format!(">1.2.{}", max)] {
+            assert!(matches!(
+                VersionBump::between(&VersionReq::parse("^1").unwrap(), &VersionReq::parse(&overflowing).unwrap()),
+                Err(MarketplaceError::InvalidVersion(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_zero_minor_dependency_bump_is_breaking() {
+        let marketplace = marketplace();
+        let empty = wasm_with_exports(&[]);
+        publish_version(&marketplace, "1.0.0", &[("log", "^0.4")], empty.clone(), "One");
+        publish_version(&marketplace, "1.0.1", &[("log", "^0.4.2")], empty.clone(), "Two");
+        publish_version(&marketplace, "1.0.2", &[("log", "^0.5")], empty, "Three");
+
+        assert!(!marketplace.diff("codec", "1.0.0", "1.0.1").unwrap().breaking);
+        assert!(marketplace.diff("codec", "1.0.1", "1.0.2").unwrap().breaking);
+    }
+
+    #[test]
+    fn test_concurrent_downloads_are_all_counted() {
+        let tracker = DownloadTracker::new(DownloadTrackingConfig::default());
+        let now = SystemTime::UNIX_EPOCH + DAY * 20_000;
+        std::thread::scope(|scope| {
+            for thread in 0..4 {
+                let tracker = &tracker;
+                scope.spawn(move || {
+                    for index in 0..250 {
+                        assert!(tracker.record("m", &format!("user-{}-{}", thread, index), now));
+                    }
+                });
+            }
+        });
+        assert_eq!(tracker.summary("m", now), DownloadSummary { total: 1000, last_7d: 1000 });
+        assert_eq!(tracker.daily_counts("m", now), [(20_000, 1000)]);
+        assert!(!tracker.record("m", "user-0-0", now));
+    }
 }
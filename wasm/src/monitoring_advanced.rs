@@ -10,10 +10,15 @@
 
 // use crate::types::*; // 暂时注释掉未使用的导入
 // use crate::webassembly_2_0::*; // 暂时注释掉未使用的导入
+use crate::types::{Value, ValueType};
+use crate::webassembly_2_0::{WebAssembly2Function, WebAssembly2Instruction, WebAssembly2Module, WebAssembly2Runtime};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
-use std::time::{Duration, SystemTime, UNIX_EPOCH}; // 移除未使用的 Instant
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use thiserror::Error;
 use tokio::time::interval;
 
@@ -39,14 +44,46 @@ pub struct AdvancedMonitoringManager {
 
 /// 指标收集器
 /// Metrics Collector
-#[derive(Debug)]
+///
+/// 克隆得到的收集器与原收集器共享同一份存储。
+/// Clones share the same underlying storage.
+#[derive(Debug, Clone)]
 pub struct MetricsCollector {
     /// 指标存储
     pub metrics: Arc<Mutex<HashMap<String, Metric>>>,
+    /// 直方图存储
+    pub histograms: Arc<Mutex<HashMap<String, Histogram>>>,
+    /// 已注册计数器的元数据
+    pub counters: Arc<Mutex<HashMap<String, MetricMetadata>>>,
     /// 指标配置
     pub config: MetricsConfig,
     /// 收集间隔
     pub collection_interval: Duration,
+    /// 时钟，用于时间戳与保留期判断
+    pub clock: Arc<dyn MetricsClock>,
+    /// 因超过保留期而被清理的序列总数
+    pub pruned_series: Arc<AtomicU64>,
+    /// 上次清理的时间（Unix 秒）
+    last_prune: Arc<AtomicU64>,
+}
+
+/// 指标时钟
+/// Metrics clock
+pub trait MetricsClock: std::fmt::Debug + Send + Sync {
+    /// 当前 Unix 时间（秒）
+    /// Current Unix time in seconds
+    fn now_secs(&self) -> u64;
+}
+
+/// 系统时钟
+/// System clock
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl MetricsClock for SystemClock {
+    fn now_secs(&self) -> u64 {
+        SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+    }
 }
 
 /// 指标
@@ -93,6 +130,105 @@ pub enum MetricValue {
     Distribution(Vec<f64>),
 }
 
+/// 直方图桶布局
+/// Histogram bucket layout
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum BucketLayout {
+    /// 指数桶：start, start*factor, start*factor^2, ...
+    Exponential { start: f64, factor: f64, count: usize },
+    /// 线性桶：start, start+width, start+2*width, ...
+    Linear { start: f64, width: f64, count: usize },
+    /// 自定义桶上界
+    Custom(Vec<f64>),
+}
+
+impl Default for BucketLayout {
+    fn default() -> Self {
+        Self::Exponential { start: 0.005, factor: 2.0, count: 12 }
+    }
+}
+
+impl BucketLayout {
+    /// 计算排序去重后的有限桶上界（`+Inf` 桶隐含在最后）
+    /// Compute sorted, deduplicated finite upper bounds (the `+Inf` bucket is implicit)
+    pub fn bounds(&self) -> Vec<f64> {
+        let mut bounds: Vec<f64> = match self {
+            Self::Exponential { start, factor, count } => {
+                (0..*count).map(|i| start * factor.powi(i as i32)).collect()
+            }
+            Self::Linear { start, width, count } => (0..*count).map(|i| start + width * i as f64).collect(),
+            Self::Custom(bounds) => bounds.clone(),
+        };
+        bounds.retain(|bound| bound.is_finite());
+        bounds.sort_by(f64::total_cmp);
+        bounds.dedup();
+        bounds
+    }
+}
+
+/// 直方图
+/// Histogram
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Histogram {
+    /// 指标名称
+    pub name: String,
+    /// 有限桶上界
+    pub bounds: Vec<f64>,
+    /// 元数据
+    pub metadata: MetricMetadata,
+    /// 按标签集合区分的序列
+    pub series: HashMap<String, HistogramSeries>,
+}
+
+/// 直方图的单个标签序列
+/// A single labelled histogram series
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistogramSeries {
+    /// 标签
+    pub labels: HashMap<String, String>,
+    /// 各有限桶的累计计数，与 `bounds` 一一对应
+    pub bucket_counts: Vec<u64>,
+    /// 观测值之和
+    pub sum: f64,
+    /// 观测次数（即 `+Inf` 桶的计数）
+    pub count: u64,
+    /// 最后一次观测的时间（Unix 秒）
+    pub last_updated: u64,
+}
+
+impl Histogram {
+    /// 按累计桶计数估算分位数，在桶内线性插值
+    /// Estimate a quantile from cumulative bucket counts, interpolating linearly within a bucket
+    ///
+    /// 落在 `+Inf` 桶中时返回最大的有限上界。
+    /// Ranks falling into the `+Inf` bucket return the largest finite bound.
+    pub fn quantile(&self, q: f64) -> Option<f64> {
+        let count: u64 = self.series.values().map(|series| series.count).sum();
+        if count == 0 || !(0.0..=1.0).contains(&q) {
+            return None;
+        }
+        let mut buckets = vec![0u64; self.bounds.len()];
+        for series in self.series.values() {
+            for (total, bucket) in buckets.iter_mut().zip(&series.bucket_counts) {
+                *total += bucket;
+            }
+        }
+
+        let rank = q * count as f64;
+        let mut previous_bound = 0.0_f64.min(self.bounds.first().copied().unwrap_or(0.0));
+        let mut previous_count = 0u64;
+        for (bound, cumulative) in self.bounds.iter().zip(&buckets) {
+            if *cumulative as f64 >= rank && *cumulative > previous_count {
+                let fraction = (rank - previous_count as f64) / (*cumulative - previous_count) as f64;
+                return Some(previous_bound + (bound - previous_bound) * fraction);
+            }
+            previous_bound = *bound;
+            previous_count = *cumulative;
+        }
+        self.bounds.last().copied()
+    }
+}
+
 /// 指标元数据
 /// Metric Metadata
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -141,10 +277,171 @@ pub struct DistributedTracer {
     pub config: TracingConfig,
     /// 活跃追踪
     pub active_traces: Arc<Mutex<HashMap<String, Trace>>>,
+    /// 已完成的追踪，超过容量时丢弃最早的
+    pub finished_traces: Arc<Mutex<VecDeque<Trace>>>,
+    /// 已完成追踪缓冲区容量
+    pub max_finished_traces: usize,
     /// 采样器
     pub sampler: SamplingStrategy,
+    /// 采样器运行时状态
+    sampling_state: Mutex<SamplingState>,
+}
+
+/// 未采样追踪的占位追踪ID，对其的所有操作都是空操作
+/// Placeholder trace id handed out for unsampled traces; every operation on it is a no-op
+pub const NOOP_TRACE_ID: &str = "00000000000000000000000000000000";
+
+/// 未采样追踪的占位跨度ID
+/// Placeholder span id handed out for unsampled traces
+pub const NOOP_SPAN_ID: &str = "0000000000000000";
+
+/// 跨度上下文，标识当前所在的追踪与跨度
+/// Span context identifying the current trace and span
+///
+/// `start_span` 会把新跨度设为当前跨度，`finish_span` 将其移除。在 [`SpanContext::scope`] 或
+/// [`SpanContext::task_scope`] 中运行的异步任务使用任务自己的跨度栈，跨 `.await` 换到其他工作线程后仍然有效；
+/// 作用域之外使用当前线程的跨度栈，只适用于同步代码。异步请求应在作用域中处理，跨越 `tokio::spawn` 时
+/// 先用 [`SpanContext::current`] 取出上下文，再在新任务中用 [`SpanContext::scope`] 恢复。
+/// `start_span` makes the new span current and `finish_span` clears it. Futures run inside
+/// [`SpanContext::scope`] or [`SpanContext::task_scope`] keep their own span stack, which follows the task
+/// across `.await` points and worker threads; outside a scope the calling thread's stack is used, which is
+/// only sound for synchronous code. Handle async requests inside a scope, and to cross a `tokio::spawn`
+/// capture [`SpanContext::current`] and restore it in the task with [`SpanContext::scope`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpanContext {
+    /// 追踪ID
+    pub trace_id: String,
+    /// 跨度ID
+    pub span_id: SpanId,
+}
+
+thread_local! {
+    /// 当前线程上已进入的跨度栈，只在任务作用域之外使用
+    static CURRENT_SPANS: std::cell::RefCell<Vec<SpanContext>> = const { std::cell::RefCell::new(Vec::new()) };
+}
+
+tokio::task_local! {
+    /// 当前异步任务的跨度栈，随任务在工作线程之间移动
+    static TASK_SPANS: std::cell::RefCell<Vec<SpanContext>>;
+}
+
+impl SpanContext {
+    /// 创建跨度上下文
+    /// Create a span context
+    pub fn new(trace_id: impl Into<String>, span_id: impl Into<SpanId>) -> Self {
+        Self { trace_id: trace_id.into(), span_id: span_id.into() }
+    }
+
+    /// 获取当前跨度上下文；在任务作用域中只看任务自己的跨度栈
+    /// Get the current span context; inside a task scope only the task's own stack is consulted
+    pub fn current() -> Option<SpanContext> {
+        Self::with_spans(|spans| spans.last().cloned())
+    }
+
+    /// 进入该上下文，守卫释放时退出
+    /// Enter this context until the guard is dropped
+    pub fn enter(&self) -> SpanContextGuard {
+        self.push_current();
+        SpanContextGuard { context: self.clone(), _not_send: std::marker::PhantomData }
+    }
+
+    /// 以该上下文为当前跨度运行异步任务，任务中开始的跨度记在任务自己的跨度栈上
+    /// Run a future with this context as the current span; spans started in it go on the task's own stack
+    pub async fn scope<F: std::future::Future>(self, future: F) -> F::Output {
+        TASK_SPANS.scope(std::cell::RefCell::new(vec![self]), future).await
+    }
+
+    /// 用空的跨度栈运行异步任务，每个请求一个作用域，任务中开始的追踪和跨度不会影响其他任务
+    /// Run a future with an empty span stack of its own; use one scope per request so traces and spans
+    /// started in it never leak into other tasks
+    pub async fn task_scope<F: std::future::Future>(future: F) -> F::Output {
+        TASK_SPANS.scope(std::cell::RefCell::new(Vec::new()), future).await
+    }
+
+    /// 在当前的跨度栈上执行操作：任务作用域中为任务的栈，否则为当前线程的栈
+    fn with_spans<R>(f: impl FnOnce(&mut Vec<SpanContext>) -> R) -> R {
+        let mut f = Some(f);
+        TASK_SPANS.try_with(|spans| (f.take().expect("called once"))(&mut spans.borrow_mut()))
+            .unwrap_or_else(|_| CURRENT_SPANS.with(|spans| (f.take().expect("called once"))(&mut spans.borrow_mut())))
+    }
+
+    fn push_current(&self) {
+        Self::with_spans(|spans| spans.push(self.clone()));
+    }
+
+    fn remove_current(trace_id: &str, span_id: Option<&str>) {
+        Self::with_spans(|spans| {
+            spans.retain(|context| {
+                context.trace_id != trace_id || span_id.is_some_and(|span_id| context.span_id != span_id)
+            });
+        });
+    }
+}
+
+/// 跨度上下文守卫
+/// Span context guard
+#[derive(Debug)]
+pub struct SpanContextGuard {
+    context: SpanContext,
+    /// 守卫绑定在进入时的线程上
+    _not_send: std::marker::PhantomData<*const ()>,
+}
+
+impl Drop for SpanContextGuard {
+    fn drop(&mut self) {
+        SpanContext::with_spans(|spans| {
+            if let Some(index) = spans.iter().rposition(|context| *context == self.context) {
+                spans.remove(index);
+            }
+        });
+    }
+}
+
+/// 采样决策
+/// Sampling decision
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SamplingDecision {
+    /// 记录并采样
+    RecordAndSample,
+    /// 丢弃
+    Drop,
+}
+
+/// 采样器运行时状态
+/// Sampler runtime state
+#[derive(Debug)]
+struct SamplingState {
+    /// 随机数生成器
+    rng: StdRng,
+    /// 速率限制令牌桶中的令牌数
+    tokens: f64,
+    /// 令牌桶上次补充的时间
+    last_refill: Instant,
+    /// 自适应采样当前窗口的开始时间
+    window_start: Instant,
+    /// 自适应采样当前窗口内的请求数
+    window_requests: u64,
+    /// 自适应采样当前的采样概率
+    adaptive_probability: f64,
+}
+
+impl SamplingState {
+    fn new(rng: StdRng, now: Instant) -> Self {
+        Self {
+            rng,
+            tokens: f64::NAN,
+            last_refill: now,
+            window_start: now,
+            window_requests: 0,
+            adaptive_probability: 1.0,
+        }
+    }
 }
 
+/// 跨度ID
+/// Span ID
+pub type SpanId = String;
+
 /// 追踪配置
 /// Tracing Configuration
 #[derive(Debug, Clone)]
@@ -171,9 +468,9 @@ pub struct Trace {
     pub parent_trace_id: Option<String>,
     /// 跨度列表
     pub spans: Vec<Span>,
-    /// 开始时间
+    /// 开始时间（Unix 毫秒）
     pub start_time: u64,
-    /// 结束时间
+    /// 结束时间（Unix 毫秒）
     pub end_time: Option<u64>,
     /// 状态
     pub status: TraceStatus,
@@ -184,12 +481,15 @@ pub struct Trace {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Span {
     /// 跨度ID
-    pub span_id: String,
+    pub span_id: SpanId,
+    /// 父跨度ID
+    #[serde(default)]
+    pub parent_span_id: Option<SpanId>,
     /// 操作名称
     pub operation_name: String,
-    /// 开始时间
+    /// 开始时间（Unix 毫秒）
     pub start_time: u64,
-    /// 结束时间
+    /// 结束时间（Unix 毫秒）
     pub end_time: Option<u64>,
     /// 标签
     pub tags: HashMap<String, String>,
@@ -245,10 +545,10 @@ pub enum SpanStatus {
 pub enum SamplingStrategy {
     /// 概率采样
     Probabilistic(f64),
-    /// 速率限制采样
+    /// 速率限制采样，每秒最多采样的追踪数
     RateLimiting(u32),
-    /// 自适应采样
-    Adaptive,
+    /// 自适应采样，目标为每秒采样的追踪数
+    Adaptive(f64),
 }
 
 /// 结构化日志记录器
@@ -259,7 +559,20 @@ pub struct StructuredLogger {
     /// 日志缓冲区
     pub log_buffer: Arc<Mutex<Vec<LogEntry>>>,
     /// 日志处理器
-    pub processors: Vec<Box<dyn LogProcessor>>,
+    pub processors: Arc<Mutex<Vec<RegisteredProcessor>>>,
+    /// 后台刷新任务
+    flusher: Mutex<Option<tokio::task::JoinHandle<()>>>,
+}
+
+/// 已注册的日志处理器及其错误计数
+/// Registered log processor with its error count
+pub struct RegisteredProcessor {
+    /// 日志处理器
+    pub processor: Box<dyn LogProcessor>,
+    /// 该处理器接收的最低级别
+    pub min_level: LogLevel,
+    /// 处理、刷新或关闭失败的次数
+    pub error_count: u64,
 }
 
 /// 日志配置
@@ -272,12 +585,33 @@ pub struct LoggingConfig {
     pub format: LogFormat,
     /// 输出目标
     pub targets: Vec<LogTarget>,
+    /// 按目标覆盖的日志级别，未列出的目标使用 `level`
+    pub target_levels: Vec<(LogTarget, LogLevel)>,
     /// 缓冲区大小
     pub buffer_size: usize,
     /// 刷新间隔
     pub flush_interval: Duration,
 }
 
+impl LoggingConfig {
+    /// 指定目标的生效级别
+    /// Effective level of the given target
+    pub fn level_for(&self, target: &LogTarget) -> LogLevel {
+        self.target_levels.iter()
+            .find(|(candidate, _)| candidate == target)
+            .map_or(self.level, |(_, level)| *level)
+    }
+
+    /// 所有目标中最低的生效级别，低于它的日志可直接丢弃
+    /// Lowest effective level over all targets; anything below it can be discarded early
+    pub fn min_level(&self) -> LogLevel {
+        self.targets.iter()
+            .map(|target| self.level_for(target))
+            .min()
+            .map_or(self.level, |level| level.min(self.level))
+    }
+}
+
 /// 日志级别
 /// Log Level
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
@@ -310,7 +644,7 @@ pub enum LogFormat {
 
 /// 日志目标
 /// Log Target
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum LogTarget {
     /// 标准输出
     Stdout,
@@ -370,9 +704,37 @@ pub struct AlertManager {
     /// 告警状态
     pub alert_states: Arc<Mutex<HashMap<String, AlertState>>>,
     /// 通知渠道
-    pub notification_channels: Vec<Box<dyn NotificationChannel>>,
+    pub notification_channels: Arc<Mutex<Vec<Box<dyn NotificationChannel>>>>,
     /// 告警配置
     pub config: AlertConfig,
+    /// 已解析的规则表达式，按规则ID索引
+    compiled_rules: Arc<Mutex<HashMap<String, AlertExpr>>>,
+    /// 聚合函数所需的指标历史样本
+    sample_history: Arc<Mutex<HashMap<String, SampleHistory>>>,
+    /// 后台评估任务
+    evaluator: Mutex<Option<tokio::task::JoinHandle<()>>>,
+    /// 静默规则
+    pub silences: Arc<Mutex<Vec<SilenceRule>>>,
+    /// 已编译的静默正则表达式，按模式缓存
+    silence_patterns: Arc<Mutex<HashMap<String, regex::Regex>>>,
+    /// 被静默抑制的通知数
+    pub suppressed_notifications: Arc<AtomicU64>,
+    /// 时钟，用于判断静默是否生效
+    pub clock: Arc<dyn MetricsClock>,
+    /// 按 (规则ID, 标签指纹) 记录的最近一次通知
+    notification_log: Arc<Mutex<HashMap<(String, u64), NotificationRecord>>>,
+    /// 超出 `max_alerts` 被丢弃的新告警数
+    pub dropped_alerts: Arc<AtomicU64>,
+}
+
+/// 最近一次发送的告警通知
+/// Last notification sent for an alert
+#[derive(Debug, Clone, Copy)]
+struct NotificationRecord {
+    /// 发送时间
+    sent_at: u64,
+    /// 发送时的严重程度
+    severity: AlertSeverity,
 }
 
 /// 告警规则
@@ -413,6 +775,9 @@ pub struct AlertState {
     pub evaluation_count: u32,
     /// 标签
     pub labels: HashMap<String, String>,
+    /// 最近一次通知是否被静默抑制
+    #[serde(default)]
+    pub suppressed: bool,
 }
 
 /// 告警状态类型
@@ -453,6 +818,8 @@ pub struct AlertConfig {
     pub max_alerts: usize,
     /// 静默配置
     pub silence_config: SilenceConfig,
+    /// 告警解决时是否也发送通知
+    pub send_resolved: bool,
 }
 
 /// 静默配置
@@ -469,6 +836,9 @@ pub struct SilenceConfig {
 /// Silence Rule
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SilenceRule {
+    /// 静默ID，添加时为空则自动生成
+    #[serde(default)]
+    pub id: String,
     /// 匹配器
     pub matchers: Vec<Matcher>,
     /// 静默开始时间
@@ -517,6 +887,9 @@ pub struct Alert {
     pub id: String,
     /// 规则ID
     pub rule_id: String,
+    /// 规则名称
+    #[serde(default)]
+    pub rule_name: String,
     /// 严重程度
     pub severity: AlertSeverity,
     /// 状态
@@ -736,6 +1109,9 @@ pub struct Correlation {
     pub correlation_coefficient: f64,
     /// 显著性
     pub significance: f64,
+    /// 对相关性的简要解读
+    #[serde(default)]
+    pub interpretation: String,
 }
 
 /// 异常
@@ -842,9 +1218,51 @@ pub struct HealthChecker {
     /// 健康检查配置
     pub config: HealthCheckConfig,
     /// 健康检查器
-    pub checkers: Vec<Box<dyn HealthCheck>>,
+    pub checkers: Arc<Mutex<Vec<Arc<dyn HealthCheck>>>>,
     /// 健康状态
     pub health_status: Arc<Mutex<HealthStatus>>,
+    /// 最近一次检查报告
+    pub last_report: Arc<Mutex<Option<HealthReport>>>,
+    /// 待确认的状态翻转：(候选状态, 连续次数)
+    pending_status: Arc<Mutex<(HealthStatus, u32)>>,
+    /// 状态变化监听器
+    listeners: Arc<Mutex<Vec<HealthStatusListener>>>,
+    /// 后台检查线程
+    runner: Mutex<Option<HealthLoop>>,
+}
+
+/// 后台检查线程及其停止信号
+/// Background check thread and its shutdown signal
+struct HealthLoop {
+    /// 释放后线程在下一次等待时退出
+    shutdown: std::sync::mpsc::Sender<()>,
+    handle: std::thread::JoinHandle<()>,
+}
+
+impl HealthLoop {
+    /// 发出停止信号并等待线程退出
+    fn shutdown(self) {
+        drop(self.shutdown);
+        if self.handle.join().is_err() {
+            log::warn!("健康检查线程异常退出");
+        }
+    }
+}
+
+/// 健康状态变化监听器
+/// Health status change listener
+pub type HealthStatusListener = Box<dyn Fn(&HealthStatusChanged) + Send + Sync>;
+
+/// 汇总健康状态发生变化
+/// The aggregate health status changed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HealthStatusChanged {
+    /// 原状态
+    pub from: HealthStatus,
+    /// 新状态
+    pub to: HealthStatus,
+    /// 未通过的检查名称
+    pub failing_checks: Vec<String>,
 }
 
 impl std::fmt::Debug for StructuredLogger {
@@ -852,7 +1270,7 @@ impl std::fmt::Debug for StructuredLogger {
         f.debug_struct("StructuredLogger")
             .field("config", &self.config)
             .field("log_buffer", &self.log_buffer)
-            .field("processors", &format!("{} processors", self.processors.len()))
+            .field("processors", &format!("{} processors", self.processors.lock().map(|p| p.len()).unwrap_or(0)))
             .finish()
     }
 }
@@ -862,7 +1280,10 @@ impl std::fmt::Debug for AlertManager {
         f.debug_struct("AlertManager")
             .field("rules", &self.rules)
             .field("alert_states", &self.alert_states)
-            .field("notification_channels", &format!("{} channels", self.notification_channels.len()))
+            .field("notification_channels", &format!(
+                "{} channels",
+                self.notification_channels.lock().map(|channels| channels.len()).unwrap_or(0),
+            ))
             .finish()
     }
 }
@@ -881,7 +1302,7 @@ impl std::fmt::Debug for HealthChecker {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("HealthChecker")
             .field("config", &self.config)
-            .field("checkers", &format!("{} checkers", self.checkers.len()))
+            .field("checkers", &format!("{} checkers", self.checkers.lock().map(|checkers| checkers.len()).unwrap_or(0)))
             .field("health_status", &self.health_status)
             .finish()
     }
@@ -897,8 +1318,10 @@ pub struct HealthCheckConfig {
     pub timeout: Duration,
     /// 重试次数
     pub retry_count: u32,
-    /// 健康阈值
+    /// 健康阈值：汇总为健康所需的通过检查比例
     pub health_threshold: f64,
+    /// 汇总状态翻转前需要连续得到相同结果的次数，用于防抖
+    pub flap_threshold: u32,
 }
 
 /// 健康检查接口
@@ -933,6 +1356,23 @@ pub struct HealthCheckResult {
     pub error: Option<String>,
     /// 时间戳
     pub timestamp: u64,
+    /// 尝试次数（含重试）
+    #[serde(default)]
+    pub attempts: u32,
+}
+
+/// 健康检查报告，可直接作为健康检查接口的响应体
+/// Health report, suitable as the body of a health endpoint response
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealthReport {
+    /// 汇总状态
+    pub status: HealthStatus,
+    /// 各项检查结果
+    pub checks: Vec<HealthCheckResult>,
+    /// 生成时间
+    pub timestamp: u64,
+    /// 全部检查耗时
+    pub duration: Duration,
 }
 
 /// 健康状态
@@ -987,7 +1427,7 @@ impl AdvancedMonitoringManager {
     /// 启动监控系统
     /// Start monitoring system
     pub async fn start(&mut self) -> Result<(), MonitoringError> {
-        println!("🔍 启动高级监控系统");
+        log::info!("🔍 启动高级监控系统");
         
         // 启动指标收集
         if self.config.metrics_config.enabled {
@@ -1011,7 +1451,7 @@ impl AdvancedMonitoringManager {
         // 启动健康检查
         self.start_health_checks().await?;
 
-        println!("✅ 高级监控系统启动完成");
+        log::info!("✅ 高级监控系统启动完成");
         Ok(())
     }
 
@@ -1117,7 +1557,7 @@ impl AdvancedMonitoringManager {
     /// Start distributed tracing
     async fn start_distributed_tracing(&mut self) -> Result<(), MonitoringError> {
         // 启动追踪收集和处理
-        println!("📊 分布式追踪系统已启动");
+        log::info!("📊 分布式追踪系统已启动");
         Ok(())
     }
 
@@ -1125,7 +1565,8 @@ impl AdvancedMonitoringManager {
     /// Start logging
     async fn start_logging(&mut self) -> Result<(), MonitoringError> {
         // 启动日志记录系统
-        println!("📝 结构化日志系统已启动");
+        self.logger.start_flusher();
+        log::info!("📝 结构化日志系统已启动");
         Ok(())
     }
 
@@ -1133,7 +1574,8 @@ impl AdvancedMonitoringManager {
     /// Start alert management
     async fn start_alert_management(&mut self) -> Result<(), MonitoringError> {
         // 启动告警规则评估和通知
-        println!("🚨 告警管理系统已启动");
+        self.alert_manager.start_evaluation(self.metrics_collector.clone());
+        log::info!("🚨 告警管理系统已启动");
         Ok(())
     }
 
@@ -1141,7 +1583,7 @@ impl AdvancedMonitoringManager {
     /// Start performance analysis
     async fn start_performance_analysis(&mut self) -> Result<(), MonitoringError> {
         // 启动性能分析和异常检测
-        println!("⚡ 性能分析系统已启动");
+        log::info!("⚡ 性能分析系统已启动");
         Ok(())
     }
 
@@ -1149,57 +1591,102 @@ impl AdvancedMonitoringManager {
     /// Start health checks
     async fn start_health_checks(&mut self) -> Result<(), MonitoringError> {
         // 启动健康检查
-        println!("🏥 健康检查系统已启动");
+        self.health_checker.start();
+        log::info!("🏥 健康检查系统已启动");
         Ok(())
     }
 
     /// 创建追踪
     /// Create trace
     pub fn create_trace(&mut self, operation_name: String) -> Result<String, MonitoringError> {
-        let trace_id = uuid::Uuid::new_v4().to_string();
-        let span_id = uuid::Uuid::new_v4().to_string();
+        let (trace_id, _root_span_id) = self.tracer.start_trace(&operation_name)?;
+        Ok(trace_id)
+    }
 
-        let span = Span {
-            span_id: span_id.clone(),
-            operation_name,
-            start_time: SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs(),
-            end_time: None,
-            tags: HashMap::new(),
-            logs: Vec::new(),
-            status: SpanStatus::Running,
-        };
+    /// 注册计数器
+    /// Register a counter
+    pub fn register_counter(&self, name: &str, metadata: MetricMetadata) -> Result<(), MonitoringError> {
+        self.metrics_collector.register_counter(name, metadata)
+    }
 
-        let trace = Trace {
-            trace_id: trace_id.clone(),
-            parent_trace_id: None,
-            spans: vec![span],
-            start_time: SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs(),
-            end_time: None,
-            status: TraceStatus::Running,
-        };
+    /// 增加计数器
+    /// Increment a counter
+    pub fn inc_counter(&self, name: &str, delta: u64, labels: HashMap<String, String>) -> Result<(), MonitoringError> {
+        self.metrics_collector.inc_counter(name, delta, labels)
+    }
 
-        let mut active_traces = self.tracer.active_traces.lock().unwrap();
-        active_traces.insert(trace_id.clone(), trace);
+    /// 设置仪表盘
+    /// Set a gauge
+    pub fn set_gauge(&self, name: &str, value: f64, labels: HashMap<String, String>) -> Result<(), MonitoringError> {
+        self.metrics_collector.set_gauge(name, value, labels)
+    }
 
-        Ok(trace_id)
+    /// 获取指标
+    /// Get a metric
+    pub fn get_metric(&self, name: &str, labels: &HashMap<String, String>) -> Option<Metric> {
+        self.metrics_collector.get_metric(name, labels)
     }
 
     /// 记录日志
     /// Log message
+    ///
+    /// 自动关联当前跨度上下文（见 [`SpanContext::current`]）。
+    /// The current span context (see [`SpanContext::current`]) is attached automatically.
     pub fn log(&self, level: LogLevel, message: String, fields: HashMap<String, serde_json::Value>) {
+        self.record_log(level, message, fields, SpanContext::current());
+    }
+
+    /// 在指定跨度中记录日志
+    /// Log message within an explicit span
+    pub fn log_in_span(
+        &self,
+        trace_id: &str,
+        span_id: &str,
+        level: LogLevel,
+        message: String,
+        fields: HashMap<String, serde_json::Value>,
+    ) {
+        self.record_log(level, message, fields, Some(SpanContext::new(trace_id, span_id)));
+    }
+
+    fn record_log(
+        &self,
+        level: LogLevel,
+        message: String,
+        fields: HashMap<String, serde_json::Value>,
+        context: Option<SpanContext>,
+    ) {
+        if !self.logger.enabled(level) {
+            return;
+        }
+        // 警告及以上级别同时写入活跃跨度的日志
+        if level >= LogLevel::Warn
+            && let Some(context) = &context
+        {
+            let span_fields = fields.iter()
+                .map(|(key, value)| match value {
+                    serde_json::Value::String(s) => (key.clone(), s.clone()),
+                    other => (key.clone(), other.to_string()),
+                })
+                .collect();
+            let _ = self.tracer.add_span_log(&context.trace_id, &context.span_id, level, &message, span_fields);
+        }
+
+        let (trace_id, span_id) = context.map(|context| (context.trace_id, context.span_id)).unzip();
         let log_entry = LogEntry {
             timestamp: SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs(),
             level,
             message,
             fields,
-            trace_id: None,
-            span_id: None,
+            trace_id,
+            span_id,
             module: None,
             target: "webassembly_monitoring".to_string(),
         };
 
-        let mut log_buffer = self.logger.log_buffer.lock().unwrap();
-        log_buffer.push(log_entry);
+        if let Err(e) = self.logger.push(log_entry) {
+            log::warn!("日志刷新失败: {}", e);
+        }
     }
 
     /// 获取监控状态
@@ -1238,211 +1725,5637 @@ impl MetricsCollector {
     /// 创建新的指标收集器
     /// Create new metrics collector
     pub fn new(config: MetricsConfig) -> Self {
+        Self::with_clock(config, Arc::new(SystemClock))
+    }
+
+    /// 使用指定时钟创建指标收集器
+    /// Create a metrics collector driven by the given clock
+    pub fn with_clock(config: MetricsConfig, clock: Arc<dyn MetricsClock>) -> Self {
+        let now = clock.now_secs();
         Self {
             metrics: Arc::new(Mutex::new(HashMap::new())),
+            histograms: Arc::new(Mutex::new(HashMap::new())),
+            counters: Arc::new(Mutex::new(HashMap::new())),
             config,
             collection_interval: Duration::from_secs(10),
+            clock,
+            pruned_series: Arc::new(AtomicU64::new(0)),
+            last_prune: Arc::new(AtomicU64::new(now)),
         }
     }
-}
 
-impl DistributedTracer {
-    /// 创建新的分布式追踪器
-    /// Create new distributed tracer
-    pub fn new(config: TracingConfig) -> Self {
-        Self {
-            config,
-            active_traces: Arc::new(Mutex::new(HashMap::new())),
-            sampler: SamplingStrategy::Probabilistic(0.1),
+    /// 立即清理超过保留期未更新的指标序列，返回本次清理的序列数
+    /// Drop every series not updated within the retention period and return how many were removed
+    ///
+    /// 直方图中过期的标签集合会被整体移除，下次观测时从零重新累计。
+    /// Stale histogram label sets are removed, so a later observation starts from zero.
+    pub fn prune_now(&self) -> Result<usize, MonitoringError> {
+        let now = self.clock.now_secs();
+        self.last_prune.store(now, Ordering::Relaxed);
+        let cutoff = now.saturating_sub(self.config.retention_period.as_secs());
+
+        let mut pruned = 0;
+        {
+            let mut metrics = self.metrics.lock()
+                .map_err(|_| MonitoringError::MetricsError("指标存储锁已损坏".to_string()))?;
+            let before = metrics.len();
+            metrics.retain(|_, metric| metric.timestamp >= cutoff);
+            pruned += before - metrics.len();
+        }
+        {
+            let mut histograms = self.histograms.lock()
+                .map_err(|_| MonitoringError::MetricsError("直方图存储锁已损坏".to_string()))?;
+            for histogram in histograms.values_mut() {
+                let before = histogram.series.len();
+                histogram.series.retain(|_, series| series.last_updated >= cutoff);
+                pruned += before - histogram.series.len();
+            }
         }
+
+        self.pruned_series.fetch_add(pruned as u64, Ordering::Relaxed);
+        Ok(pruned)
     }
-}
 
-impl StructuredLogger {
-    /// 创建新的结构化日志记录器
-    /// Create new structured logger
-    pub fn new(config: LoggingConfig) -> Self {
-        Self {
-            config,
-            log_buffer: Arc::new(Mutex::new(Vec::new())),
-            processors: Vec::new(),
+    /// 累计清理的序列数
+    /// Total number of series pruned so far
+    pub fn pruned_series_count(&self) -> u64 {
+        self.pruned_series.load(Ordering::Relaxed)
+    }
+
+    /// 最近一次写入任意指标或直方图的时间（秒）
+    /// Time in seconds of the most recent write to any metric or histogram
+    pub fn last_update_secs(&self) -> Option<u64> {
+        let metrics = self.metrics.lock().ok()?.values().map(|metric| metric.timestamp).max();
+        let histograms = self.histograms.lock().ok()?.values()
+            .flat_map(|histogram| histogram.series.values().map(|series| series.last_updated))
+            .max();
+        metrics.max(histograms)
+    }
+
+    /// 写入前按收集间隔节流地执行清理
+    /// Prune on write, throttled to once per collection interval
+    fn maybe_prune(&self) -> Result<(), MonitoringError> {
+        let now = self.clock.now_secs();
+        let last = self.last_prune.load(Ordering::Relaxed);
+        if now.saturating_sub(last) >= self.collection_interval.as_secs()
+            && self.last_prune.compare_exchange(last, now, Ordering::Relaxed, Ordering::Relaxed).is_ok()
+        {
+            self.prune_now()?;
         }
+        Ok(())
     }
-}
 
-impl AlertManager {
-    /// 创建新的告警管理器
-    /// Create new alert manager
-    pub fn new(config: AlertConfig) -> Self {
-        Self {
-            rules: Arc::new(Mutex::new(Vec::new())),
-            alert_states: Arc::new(Mutex::new(HashMap::new())),
-            notification_channels: Vec::new(),
-            config,
+    /// 注册计数器
+    /// Register a counter
+    pub fn register_counter(&self, name: &str, metadata: MetricMetadata) -> Result<(), MonitoringError> {
+        let mut counters = self.counters.lock()
+            .map_err(|_| MonitoringError::MetricsError("计数器注册表锁已损坏".to_string()))?;
+        counters.insert(name.to_string(), metadata);
+        Ok(())
+    }
+
+    /// 增加计数器，未注册的计数器会以空元数据自动注册并记录警告
+    /// Increment a counter; unregistered counters are auto-registered with empty metadata and a warning
+    pub fn inc_counter(&self, name: &str, delta: u64, labels: HashMap<String, String>) -> Result<(), MonitoringError> {
+        self.maybe_prune()?;
+        let metadata = {
+            let mut counters = self.counters.lock()
+                .map_err(|_| MonitoringError::MetricsError("计数器注册表锁已损坏".to_string()))?;
+            counters.entry(name.to_string())
+                .or_insert_with(|| {
+                    log::warn!("计数器 {} 未注册，已使用空元数据自动注册", name);
+                    MetricMetadata { description: String::new(), unit: None, help: None }
+                })
+                .clone()
+        };
+
+        let mut metrics = self.metrics.lock()
+            .map_err(|_| MonitoringError::MetricsError("指标存储锁已损坏".to_string()))?;
+        let metric = metrics.entry(series_key(name, &labels))
+            .or_insert_with(|| Metric {
+                name: name.to_string(),
+                metric_type: MetricType::Counter,
+                value: MetricValue::Integer(0),
+                labels,
+                timestamp: 0,
+                metadata,
+            });
+        match (&metric.metric_type, &mut metric.value) {
+            (MetricType::Counter, MetricValue::Integer(value)) => {
+                *value = value.saturating_add(i64::try_from(delta).unwrap_or(i64::MAX));
+            }
+            _ => return Err(MonitoringError::MetricsError(format!("指标 {} 不是计数器", name))),
         }
+        metric.timestamp = self.clock.now_secs();
+        Ok(())
     }
-}
 
-impl PerformanceAnalyzer {
-    /// 创建新的性能分析器
-    /// Create new performance analyzer
-    pub fn new(config: PerformanceConfig) -> Self {
-        Self {
-            performance_metrics: Arc::new(Mutex::new(HashMap::new())),
-            config,
-            analyzer: Box::new(StatisticalAnalyzer::new()),
+    /// 设置仪表盘的当前值
+    /// Set the current value of a gauge
+    pub fn set_gauge(&self, name: &str, value: f64, labels: HashMap<String, String>) -> Result<(), MonitoringError> {
+        self.maybe_prune()?;
+        let mut metrics = self.metrics.lock()
+            .map_err(|_| MonitoringError::MetricsError("指标存储锁已损坏".to_string()))?;
+        let timestamp = self.clock.now_secs();
+        let metric = metrics.entry(series_key(name, &labels))
+            .or_insert_with(|| Metric {
+                name: name.to_string(),
+                metric_type: MetricType::Gauge,
+                value: MetricValue::Float(value),
+                labels,
+                timestamp,
+                metadata: MetricMetadata { description: String::new(), unit: None, help: None },
+            });
+        if !matches!(metric.metric_type, MetricType::Gauge) {
+            return Err(MonitoringError::MetricsError(format!("指标 {} 不是仪表盘", name)));
         }
+        metric.value = MetricValue::Float(value);
+        metric.timestamp = timestamp;
+        Ok(())
     }
-}
 
-impl HealthChecker {
-    /// 创建新的健康检查器
-    /// Create new health checker
-    pub fn new(config: HealthCheckConfig) -> Self {
-        Self {
-            config,
-            checkers: Vec::new(),
-            health_status: Arc::new(Mutex::new(HealthStatus::Unknown)),
+    /// 获取指定名称与标签集合的指标
+    /// Get the metric with the given name and label set
+    pub fn get_metric(&self, name: &str, labels: &HashMap<String, String>) -> Option<Metric> {
+        self.metrics.lock().ok()?.get(&series_key(name, labels)).cloned()
+    }
+
+    /// 注册直方图
+    /// Register a histogram
+    pub fn register_histogram(&self, name: &str, buckets: BucketLayout, metadata: MetricMetadata) -> Result<(), MonitoringError> {
+        let bounds = buckets.bounds();
+        if bounds.is_empty() {
+            return Err(MonitoringError::MetricsError(format!("直方图 {} 没有有效的桶", name)));
         }
+        let mut histograms = self.histograms.lock()
+            .map_err(|_| MonitoringError::MetricsError("直方图存储锁已损坏".to_string()))?;
+        histograms.insert(name.to_string(), Histogram {
+            name: name.to_string(),
+            bounds,
+            metadata,
+            series: HashMap::new(),
+        });
+        Ok(())
     }
-}
 
-/// 统计分析器
-/// Statistical Analyzer
-#[derive(Debug)]
-pub struct StatisticalAnalyzer;
+    /// 记录一次观测值，按标签集合累计桶计数、总和与次数
+    /// Record an observation, accumulating bucket counts, sum and count per label set
+    pub fn observe(&self, name: &str, value: f64, labels: HashMap<String, String>) -> Result<(), MonitoringError> {
+        self.maybe_prune()?;
+        let now = self.clock.now_secs();
+        let mut histograms = self.histograms.lock()
+            .map_err(|_| MonitoringError::MetricsError("直方图存储锁已损坏".to_string()))?;
+        let histogram = histograms.get_mut(name)
+            .ok_or_else(|| MonitoringError::MetricsError(format!("未注册的直方图: {}", name)))?;
 
-impl Default for StatisticalAnalyzer {
-    fn default() -> Self {
-        Self::new()
+        let bucket_count = histogram.bounds.len();
+        let series = histogram.series.entry(series_key(name, &labels))
+            .or_insert_with(|| HistogramSeries {
+                labels,
+                bucket_counts: vec![0; bucket_count],
+                sum: 0.0,
+                count: 0,
+                last_updated: now,
+            });
+        for (bound, bucket) in histogram.bounds.iter().zip(series.bucket_counts.iter_mut()) {
+            if value <= *bound {
+                *bucket += 1;
+            }
+        }
+        series.sum += value;
+        series.count += 1;
+        series.last_updated = now;
+        Ok(())
     }
-}
 
-impl StatisticalAnalyzer {
-    /// 创建新的统计分析器
-    /// Create new statistical analyzer
-    pub fn new() -> Self {
-        Self
+    /// 按全部标签序列的合计估算直方图分位数
+    /// Estimate a histogram quantile across all label sets
+    pub fn quantile(&self, name: &str, q: f64) -> Option<f64> {
+        self.histograms.lock().ok()?.get(name)?.quantile(q)
     }
-}
 
-impl PerformanceAnalyzerEngine for StatisticalAnalyzer {
-    fn analyze(&self, _metrics: &[PerformanceMetric]) -> Result<PerformanceAnalysis, AnalysisError> {
-        // 简化的统计分析实现
-        Ok(PerformanceAnalysis {
-            id: uuid::Uuid::new_v4().to_string(),
-            analysis_time: SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs(),
-            results: AnalysisResults {
-                performance_score: 85.0,
-                bottlenecks: Vec::new(),
-                trends: Vec::new(),
-                correlations: Vec::new(),
-            },
-            recommendations: Vec::new(),
-            anomalies: Vec::new(),
-        })
+    /// 记录指标，同名且标签相同的序列会被替换
+    /// Record a metric, replacing the series with the same name and labels
+    pub fn record_metric(&self, metric: Metric) -> Result<(), MonitoringError> {
+        self.maybe_prune()?;
+        let mut metrics = self.metrics.lock()
+            .map_err(|_| MonitoringError::MetricsError("指标存储锁已损坏".to_string()))?;
+        metrics.insert(series_key(&metric.name, &metric.labels), metric);
+        Ok(())
     }
 
-    fn detect_anomalies(&self, _metrics: &[PerformanceMetric]) -> Result<Vec<Anomaly>, AnalysisError> {
-        // 简化的异常检测实现
-        Ok(Vec::new())
+    /// 以 Prometheus 文本格式导出全部指标
+    /// Export all metrics in the Prometheus text exposition format
+    pub fn export(&self) -> String {
+        match (self.metrics.lock(), self.histograms.lock()) {
+            (Ok(metrics), Ok(histograms)) => render_prometheus(&metrics, &histograms),
+            _ => String::new(),
+        }
     }
 
-    fn generate_report(&self, analysis: &PerformanceAnalysis) -> Result<PerformanceReport, AnalysisError> {
-        // 简化的报告生成实现
-        Ok(PerformanceReport {
-            id: uuid::Uuid::new_v4().to_string(),
-            analysis: analysis.clone(),
-            generated_at: SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs(),
-        })
+    /// 启动最小的 HTTP 服务，在 `/metrics` 上提供 Prometheus 格式的指标
+    /// Serve Prometheus metrics on `/metrics` with a minimal HTTP listener
+    ///
+    /// 绑定成功后返回实际监听地址与后台任务句柄。
+    /// Returns the bound address and the handle of the background accept loop.
+    pub async fn serve_metrics(
+        &self,
+        addr: &str,
+    ) -> Result<(std::net::SocketAddr, tokio::task::JoinHandle<()>), MonitoringError> {
+        let listener = tokio::net::TcpListener::bind(addr).await
+            .map_err(|e| MonitoringError::ConfigurationError(format!("无法监听 {}: {}", addr, e)))?;
+        let local_addr = listener.local_addr()
+            .map_err(|e| MonitoringError::ConfigurationError(e.to_string()))?;
+        let metrics = Arc::clone(&self.metrics);
+        let histograms = Arc::clone(&self.histograms);
+
+        let handle = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let metrics = Arc::clone(&metrics);
+                let histograms = Arc::clone(&histograms);
+                tokio::spawn(async move {
+                    // 单个连接的错误不影响监听循环
+                    let _ = handle_metrics_request(stream, metrics, histograms).await;
+                });
+            }
+        });
+
+        Ok((local_addr, handle))
     }
 }
 
-/// 性能报告
-/// Performance Report
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct PerformanceReport {
-    /// 报告ID
-    pub id: String,
-    /// 分析结果
-    pub analysis: PerformanceAnalysis,
-    /// 生成时间
-    pub generated_at: u64,
+/// 处理一次指标抓取请求
+/// Handle a single scrape request
+async fn handle_metrics_request(
+    mut stream: tokio::net::TcpStream,
+    metrics: Arc<Mutex<HashMap<String, Metric>>>,
+    histograms: Arc<Mutex<HashMap<String, Histogram>>>,
+) -> std::io::Result<()> {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    // 只读取请求头，最多 8 KiB
+    let mut request = Vec::new();
+    let mut buffer = [0u8; 1024];
+    while !request.windows(4).any(|window| window == b"\r\n\r\n") && request.len() < 8192 {
+        let read = stream.read(&mut buffer).await?;
+        if read == 0 {
+            break;
+        }
+        request.extend_from_slice(&buffer[..read]);
+    }
+
+    let request = String::from_utf8_lossy(&request);
+    let mut request_line = request.lines().next().unwrap_or_default().split_whitespace();
+    let (method, path) = (request_line.next(), request_line.next());
+
+    let (status, content_type, body) = match (method, path) {
+        (Some("GET"), Some("/metrics")) => {
+            let body = match (metrics.lock(), histograms.lock()) {
+                (Ok(metrics), Ok(histograms)) => render_prometheus(&metrics, &histograms),
+                _ => String::new(),
+            };
+            ("200 OK", "text/plain; version=0.0.4; charset=utf-8", body)
+        }
+        _ => ("404 Not Found", "text/plain; charset=utf-8", "not found\n".to_string()),
+    };
+
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body,
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
 }
 
-/// 错误类型定义
-/// Error Type Definitions
+/// 指标序列的存储键：名称加排序后的标签
+/// Storage key of a series: the name plus sorted labels
+fn series_key(name: &str, labels: &HashMap<String, String>) -> String {
+    if labels.is_empty() {
+        return name.to_string();
+    }
+    let mut pairs: Vec<_> = labels.iter().collect();
+    pairs.sort();
+    let pairs: Vec<String> = pairs.into_iter().map(|(key, value)| format!("{}={:?}", key, value)).collect();
+    format!("{}{{{}}}", name, pairs.join(","))
+}
 
-#[derive(Debug, Clone, Serialize, Deserialize, Error)]
-pub enum MonitoringError {
-    /// 配置错误
-    #[error("监控配置错误: {0}")]
-    ConfigurationError(String),
-    /// 指标错误
-    #[error("指标错误: {0}")]
-    MetricsError(String),
-    /// 追踪错误
-    #[error("追踪错误: {0}")]
-    TracingError(String),
-    /// 日志错误
-    #[error("日志错误: {0}")]
-    LoggingError(String),
-    /// 告警错误
-    #[error("告警错误: {0}")]
-    AlertError(String),
-    /// 分析错误
-    #[error("分析错误: {0}")]
-    AnalysisError(String),
+/// 将指标名称规范化为 Prometheus 允许的字符集 `[a-zA-Z_:][a-zA-Z0-9_:]*`
+/// Sanitize a metric name to the Prometheus character set `[a-zA-Z_:][a-zA-Z0-9_:]*`
+fn sanitize_metric_name(name: &str) -> String {
+    let mut sanitized: String = name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '_' || c == ':' { c } else { '_' })
+        .collect();
+    if sanitized.is_empty() || sanitized.starts_with(|c: char| c.is_ascii_digit()) {
+        sanitized.insert(0, '_');
+    }
+    sanitized
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Error)]
-pub enum LoggingError {
-    /// 处理器错误
-    #[error("日志处理器错误: {0}")]
-    ProcessorError(String),
-    /// 格式错误
-    #[error("日志格式错误: {0}")]
-    FormatError(String),
-    /// 输出错误
-    #[error("日志输出错误: {0}")]
-    OutputError(String),
+/// 将标签名称规范化为 `[a-zA-Z_][a-zA-Z0-9_]*`
+/// Sanitize a label name to `[a-zA-Z_][a-zA-Z0-9_]*`
+fn sanitize_label_name(name: &str) -> String {
+    sanitize_metric_name(name).replace(':', "_")
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Error)]
-pub enum NotificationError {
-    /// 发送错误
-    #[error("通知发送错误: {0}")]
-    SendError(String),
-    /// 配置错误
-    #[error("通知配置错误: {0}")]
-    ConfigurationError(String),
-    /// 连接错误
-    #[error("通知连接错误: {0}")]
-    ConnectionError(String),
+/// 转义标签值中的反斜杠、双引号与换行
+/// Escape backslashes, double quotes and newlines in a label value
+fn escape_label_value(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Error)]
-pub enum AnalysisError {
-    /// 分析错误
-    #[error("性能分析错误: {0}")]
-    AnalysisError(String),
-    /// 数据错误
-    #[error("分析数据错误: {0}")]
-    DataError(String),
-    /// 算法错误
-    #[error("分析算法错误: {0}")]
-    AlgorithmError(String),
+/// 转义 HELP 文本中的反斜杠与换行
+/// Escape backslashes and newlines in HELP text
+fn escape_help(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('\n', "\\n")
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Error)]
-pub enum HealthCheckError {
-    /// 检查错误
-    #[error("健康检查错误: {0}")]
-    CheckError(String),
-    /// 超时错误
-    #[error("健康检查超时: {0}")]
-    TimeoutError(String),
-    /// 连接错误
-    #[error("健康检查连接错误: {0}")]
-    ConnectionError(String),
+/// Prometheus 格式的样本值
+/// Sample value in Prometheus format
+fn format_sample_value(value: f64) -> String {
+    if value.is_nan() {
+        "NaN".to_string()
+    } else if value.is_infinite() {
+        if value > 0.0 { "+Inf" } else { "-Inf" }.to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+/// 渲染标签集合，标签按名称排序
+/// Render a label set sorted by name
+fn render_labels(labels: &[(String, String)]) -> String {
+    if labels.is_empty() {
+        return String::new();
+    }
+    let mut labels = labels.to_vec();
+    labels.sort();
+    let rendered: Vec<String> = labels.iter()
+        .map(|(name, value)| format!("{}=\"{}\"", name, escape_label_value(value)))
+        .collect();
+    format!("{{{}}}", rendered.join(","))
+}
+
+/// 指标在导出时使用的名称：规范化后按惯例追加单位后缀
+/// Exported metric name: sanitized, with the unit appended as a suffix by convention
+fn exported_metric_name(name: &str, unit: &str) -> String {
+    let name = sanitize_metric_name(name);
+    let unit = sanitize_label_name(unit);
+    if unit.is_empty() || name.ends_with(&format!("_{}", unit)) {
+        name
+    } else {
+        format!("{}_{}", name, unit)
+    }
+}
+
+/// HELP 与 TYPE 注释行
+/// HELP and TYPE comment lines
+fn render_family_header(name: &str, metadata: &MetricMetadata, type_name: &str) -> String {
+    let help = metadata.help.as_deref().unwrap_or(&metadata.description);
+    format!("# HELP {} {}\n# TYPE {} {}\n", name, escape_help(help), name, type_name)
+}
+
+/// 以 Prometheus 文本格式渲染指标与直方图，同名序列归为一组并按名称排序
+/// Render metrics and histograms in the Prometheus text format, grouping series by name in sorted order
+fn render_prometheus(metrics: &HashMap<String, Metric>, histograms: &HashMap<String, Histogram>) -> String {
+    let mut families: std::collections::BTreeMap<String, Vec<&Metric>> = std::collections::BTreeMap::new();
+    for metric in metrics.values() {
+        let name = match &metric.metadata.unit {
+            Some(unit) => exported_metric_name(&metric.name, unit),
+            None => sanitize_metric_name(&metric.name),
+        };
+        families.entry(name).or_default().push(metric);
+    }
+
+    let mut blocks: std::collections::BTreeMap<String, String> = std::collections::BTreeMap::new();
+    for (name, mut series) in families {
+        series.sort_by_key(|metric| series_key(&metric.name, &metric.labels));
+        let first = series[0];
+        let type_name = match first.metric_type {
+            MetricType::Counter => "counter",
+            MetricType::Gauge => "gauge",
+            MetricType::Histogram => "histogram",
+            MetricType::Summary => "summary",
+        };
+        let mut output = render_family_header(&name, &first.metadata, type_name);
+
+        for metric in series {
+            let labels: Vec<(String, String)> = metric.labels.iter()
+                .map(|(key, value)| (sanitize_label_name(key), value.clone()))
+                .collect();
+            let rendered_labels = render_labels(&labels);
+            match &metric.value {
+                MetricValue::Integer(value) => output.push_str(&format!("{}{} {}\n", name, rendered_labels, value)),
+                MetricValue::Float(value) => {
+                    output.push_str(&format!("{}{} {}\n", name, rendered_labels, format_sample_value(*value)));
+                }
+                MetricValue::Distribution(values) => {
+                    let sum: f64 = values.iter().sum();
+                    output.push_str(&format!("{}_sum{} {}\n", name, rendered_labels, format_sample_value(sum)));
+                    output.push_str(&format!("{}_count{} {}\n", name, rendered_labels, values.len()));
+                }
+            }
+        }
+        blocks.insert(name, output);
+    }
+
+    for histogram in histograms.values() {
+        let name = match &histogram.metadata.unit {
+            Some(unit) => exported_metric_name(&histogram.name, unit),
+            None => sanitize_metric_name(&histogram.name),
+        };
+        let mut output = render_family_header(&name, &histogram.metadata, "histogram");
+
+        let mut series: Vec<(&String, &HistogramSeries)> = histogram.series.iter().collect();
+        series.sort_by(|a, b| a.0.cmp(b.0));
+        for (_, series) in series {
+            let labels: Vec<(String, String)> = series.labels.iter()
+                .map(|(key, value)| (sanitize_label_name(key), value.clone()))
+                .collect();
+            let buckets = histogram.bounds.iter()
+                .map(|bound| format_sample_value(*bound))
+                .zip(series.bucket_counts.iter().copied())
+                .chain(std::iter::once(("+Inf".to_string(), series.count)));
+            for (le, count) in buckets {
+                let mut bucket_labels = labels.clone();
+                bucket_labels.push(("le".to_string(), le));
+                output.push_str(&format!("{}_bucket{} {}\n", name, render_labels(&bucket_labels), count));
+            }
+            let rendered_labels = render_labels(&labels);
+            output.push_str(&format!("{}_sum{} {}\n", name, rendered_labels, format_sample_value(series.sum)));
+            output.push_str(&format!("{}_count{} {}\n", name, rendered_labels, series.count));
+        }
+        blocks.entry(name).or_default().push_str(&output);
+    }
+
+    blocks.into_values().collect()
+}
+
+impl Span {
+    /// 跨度持续时间（毫秒），未结束时为 `None`
+    /// Span duration in milliseconds, `None` while still running
+    pub fn duration_ms(&self) -> Option<u64> {
+        self.end_time.map(|end| end.saturating_sub(self.start_time))
+    }
+}
+
+impl Trace {
+    /// 追踪持续时间（毫秒），未结束时为 `None`
+    /// Trace duration in milliseconds, `None` while still running
+    pub fn duration_ms(&self) -> Option<u64> {
+        self.end_time.map(|end| end.saturating_sub(self.start_time))
+    }
+}
+
+/// 当前 Unix 时间（毫秒）
+/// Current Unix time in milliseconds
+fn unix_millis() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as u64
+}
+
+/// 生成 128 位十六进制追踪ID
+/// Generate a 128-bit hex trace id
+fn new_trace_id() -> String {
+    uuid::Uuid::new_v4().simple().to_string()
+}
+
+/// 生成非零的 64 位十六进制跨度ID
+/// Generate a non-zero 64-bit hex span id
+fn new_span_id() -> SpanId {
+    format!("{:016x}", rand::random::<u64>().max(1))
+}
+
+/// 追踪导出格式
+/// Trace export format
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceExportFormat {
+    /// Jaeger JSON 批量格式
+    Jaeger,
+    /// OTLP/JSON 格式
+    Otlp,
+}
+
+/// 后台追踪导出选项
+/// Background trace exporter options
+#[derive(Debug, Clone)]
+pub struct TraceExporterOptions {
+    /// 导出格式
+    pub format: TraceExportFormat,
+    /// 每批最多导出的追踪数
+    pub batch_size: usize,
+    /// 导出间隔
+    pub interval: Duration,
+    /// 单批最大重试次数
+    pub max_retries: u32,
+    /// 首次重试前的等待时间，之后逐次翻倍
+    pub retry_backoff: Duration,
+}
+
+impl Default for TraceExporterOptions {
+    fn default() -> Self {
+        Self {
+            format: TraceExportFormat::Otlp,
+            batch_size: 128,
+            interval: Duration::from_secs(5),
+            max_retries: 3,
+            retry_backoff: Duration::from_millis(200),
+        }
+    }
+}
+
+/// 跨度日志级别的小写名称
+/// Lowercase name of a span log level
+fn log_level_name(level: &LogLevel) -> &'static str {
+    match level {
+        LogLevel::Trace => "trace",
+        LogLevel::Debug => "debug",
+        LogLevel::Info => "info",
+        LogLevel::Warn => "warn",
+        LogLevel::Error => "error",
+        LogLevel::Fatal => "fatal",
+    }
+}
+
+/// 按键排序的字符串键值对
+/// String key/value pairs sorted by key
+fn sorted_pairs(map: &HashMap<String, String>) -> Vec<(&String, &String)> {
+    let mut pairs: Vec<_> = map.iter().collect();
+    pairs.sort();
+    pairs
+}
+
+/// 以 Jaeger JSON 批量格式渲染追踪
+/// Render traces in the Jaeger JSON batch format
+fn render_jaeger_json(traces: &[Trace], config: &TracingConfig) -> serde_json::Value {
+    let jaeger_tag = |key: &str, value: &str| serde_json::json!({ "key": key, "type": "string", "value": value });
+
+    let data: Vec<serde_json::Value> = traces.iter().map(|trace| {
+        let spans: Vec<serde_json::Value> = trace.spans.iter().map(|span| {
+            let references: Vec<serde_json::Value> = span.parent_span_id.iter()
+                .map(|parent| serde_json::json!({
+                    "refType": "CHILD_OF",
+                    "traceID": trace.trace_id,
+                    "spanID": parent,
+                }))
+                .collect();
+            let mut tags: Vec<serde_json::Value> = sorted_pairs(&span.tags).into_iter()
+                .map(|(key, value)| jaeger_tag(key, value))
+                .collect();
+            if matches!(span.status, SpanStatus::Error) {
+                tags.push(serde_json::json!({ "key": "error", "type": "bool", "value": true }));
+            }
+            let logs: Vec<serde_json::Value> = span.logs.iter().map(|log| {
+                let mut fields = vec![
+                    jaeger_tag("event", &log.message),
+                    jaeger_tag("level", log_level_name(&log.level)),
+                ];
+                fields.extend(sorted_pairs(&log.fields).into_iter().map(|(key, value)| jaeger_tag(key, value)));
+                serde_json::json!({ "timestamp": log.timestamp * 1_000, "fields": fields })
+            }).collect();
+
+            serde_json::json!({
+                "traceID": trace.trace_id,
+                "spanID": span.span_id,
+                "operationName": span.operation_name,
+                "references": references,
+                "startTime": span.start_time * 1_000,
+                "duration": span.duration_ms().unwrap_or(0) * 1_000,
+                "tags": tags,
+                "logs": logs,
+                "processID": "p1",
+            })
+        }).collect();
+
+        serde_json::json!({
+            "traceID": trace.trace_id,
+            "spans": spans,
+            "processes": {
+                "p1": {
+                    "serviceName": config.service_name,
+                    "tags": [jaeger_tag("service.version", &config.service_version)],
+                },
+            },
+        })
+    }).collect();
+
+    serde_json::json!({ "data": data })
+}
+
+/// 以 OTLP/JSON 格式渲染追踪
+/// Render traces in the OTLP/JSON format
+fn render_otlp_json(traces: &[Trace], config: &TracingConfig) -> serde_json::Value {
+    let attribute = |key: &str, value: &str| serde_json::json!({ "key": key, "value": { "stringValue": value } });
+    let nanos = |millis: u64| (u128::from(millis) * 1_000_000).to_string();
+
+    let spans: Vec<serde_json::Value> = traces.iter().flat_map(|trace| trace.spans.iter().map(move |span| (trace, span)))
+        .map(|(trace, span)| {
+            let attributes: Vec<serde_json::Value> = sorted_pairs(&span.tags).into_iter()
+                .map(|(key, value)| attribute(key, value))
+                .collect();
+            let events: Vec<serde_json::Value> = span.logs.iter().map(|log| {
+                let mut attributes = vec![attribute("level", log_level_name(&log.level))];
+                attributes.extend(sorted_pairs(&log.fields).into_iter().map(|(key, value)| attribute(key, value)));
+                serde_json::json!({
+                    "timeUnixNano": nanos(log.timestamp),
+                    "name": log.message,
+                    "attributes": attributes,
+                })
+            }).collect();
+            // STATUS_CODE_UNSET = 0, STATUS_CODE_OK = 1, STATUS_CODE_ERROR = 2
+            let status_code = match span.status {
+                SpanStatus::Running => 0,
+                SpanStatus::Completed => 1,
+                SpanStatus::Error => 2,
+            };
+
+            let mut value = serde_json::json!({
+                "traceId": trace.trace_id,
+                "spanId": span.span_id,
+                "name": span.operation_name,
+                "kind": 1,
+                "startTimeUnixNano": nanos(span.start_time),
+                "endTimeUnixNano": nanos(span.end_time.unwrap_or(span.start_time)),
+                "attributes": attributes,
+                "events": events,
+                "status": { "code": status_code },
+            });
+            if let Some(parent) = &span.parent_span_id {
+                value["parentSpanId"] = serde_json::Value::from(parent.as_str());
+            }
+            value
+        })
+        .collect();
+
+    serde_json::json!({
+        "resourceSpans": [{
+            "resource": {
+                "attributes": [
+                    attribute("service.name", &config.service_name),
+                    attribute("service.version", &config.service_version),
+                ],
+            },
+            "scopeSpans": [{
+                "scope": { "name": "wasm", "version": env!("CARGO_PKG_VERSION") },
+                "spans": spans,
+            }],
+        }],
+    })
+}
+
+/// 拆分 `http://host[:port]/path` 端点，返回 (authority, 连接地址, 路径)
+/// Split an `http://host[:port]/path` endpoint into (authority, socket address, path)
+fn split_http_endpoint(endpoint: &str) -> Result<(&str, String, &str), String> {
+    let rest = endpoint.strip_prefix("http://")
+        .ok_or_else(|| format!("仅支持 http:// 端点: {}", endpoint))?;
+    let (authority, path) = match rest.find('/') {
+        Some(index) => (&rest[..index], &rest[index..]),
+        None => (rest, "/"),
+    };
+    if authority.is_empty() {
+        return Err(format!("端点缺少主机: {}", endpoint));
+    }
+    let address = if authority.contains(':') { authority.to_string() } else { format!("{}:80", authority) };
+    Ok((authority, address, path))
+}
+
+/// 导出追踪时单次请求的超时，端点接受连接后不响应时不会一直占住导出任务
+const TRACE_EXPORT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 以 HTTP/1.1 POST 发送 JSON 请求体，仅支持 `http://` 端点
+/// POST a JSON body over HTTP/1.1; only `http://` endpoints are supported
+///
+/// 连接、发送和读取响应都是 tokio 的异步 I/O，整个请求在 `timeout` 内未完成时返回错误。
+/// Connecting, sending and reading the response all use tokio's async I/O; the request fails when it
+/// does not complete within `timeout`.
+async fn post_json(endpoint: &str, body: &str, timeout: Duration) -> Result<(), MonitoringError> {
+    tokio::time::timeout(timeout, post_json_once(endpoint, body)).await
+        .map_err(|_| MonitoringError::TracingError(format!("追踪端点 {} 在 {:?} 内未响应", endpoint, timeout)))?
+}
+
+/// 发送请求并检查响应状态码
+async fn post_json_once(endpoint: &str, body: &str) -> Result<(), MonitoringError> {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let (authority, address, path) = split_http_endpoint(endpoint)
+        .map_err(MonitoringError::ConfigurationError)?;
+
+    let mut stream = tokio::net::TcpStream::connect(&address).await
+        .map_err(|e| MonitoringError::TracingError(format!("无法连接 {}: {}", address, e)))?;
+    let request = format!(
+        "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        path,
+        authority,
+        body.len(),
+        body,
+    );
+    stream.write_all(request.as_bytes()).await
+        .map_err(|e| MonitoringError::TracingError(e.to_string()))?;
+
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).await
+        .map_err(|e| MonitoringError::TracingError(e.to_string()))?;
+    let response = String::from_utf8_lossy(&response);
+    let status = response.lines().next()
+        .and_then(|line| line.split_whitespace().nth(1))
+        .and_then(|code| code.parse::<u16>().ok())
+        .ok_or_else(|| MonitoringError::TracingError("无效的 HTTP 响应".to_string()))?;
+    if (200..300).contains(&status) {
+        Ok(())
+    } else {
+        Err(MonitoringError::TracingError(format!("追踪端点返回状态码 {}", status)))
+    }
+}
+
+impl DistributedTracer {
+    /// 创建新的分布式追踪器
+    /// Create new distributed tracer
+    pub fn new(config: TracingConfig) -> Self {
+        Self {
+            active_traces: Arc::new(Mutex::new(HashMap::new())),
+            finished_traces: Arc::new(Mutex::new(VecDeque::new())),
+            max_finished_traces: 1024,
+            sampler: SamplingStrategy::Probabilistic(config.sampling_rate),
+            sampling_state: Mutex::new(SamplingState::new(StdRng::from_os_rng(), Instant::now())),
+            config,
+        }
+    }
+
+    /// 使用固定种子的随机数生成器，使采样结果可复现
+    /// Use a seeded RNG so sampling decisions are reproducible
+    pub fn with_sampling_seed(self, seed: u64) -> Self {
+        Self {
+            sampling_state: Mutex::new(SamplingState::new(StdRng::seed_from_u64(seed), Instant::now())),
+            ..self
+        }
+    }
+
+    /// 按采样策略决定是否记录新的追踪
+    /// Decide whether a new trace should be recorded under the sampling strategy
+    pub fn should_sample(&self, operation_name: &str) -> SamplingDecision {
+        self.should_sample_at(operation_name, Instant::now())
+    }
+
+    fn should_sample_at(&self, operation_name: &str, now: Instant) -> SamplingDecision {
+        if !self.config.enabled {
+            return SamplingDecision::Drop;
+        }
+        let Ok(mut state) = self.sampling_state.lock() else {
+            return SamplingDecision::Drop;
+        };
+
+        let sampled = match &self.sampler {
+            SamplingStrategy::Probabilistic(rate) => {
+                let rate = rate.clamp(0.0, 1.0);
+                state.rng.random_bool(rate)
+            }
+            SamplingStrategy::RateLimiting(per_second) => {
+                // 令牌桶：容量与补充速率都是每秒上限，初始为满桶
+                let capacity = f64::from(*per_second);
+                let elapsed = now.saturating_duration_since(state.last_refill).as_secs_f64();
+                state.tokens = if state.tokens.is_nan() {
+                    capacity
+                } else {
+                    (state.tokens + elapsed * capacity).min(capacity)
+                };
+                state.last_refill = now;
+                if state.tokens >= 1.0 {
+                    state.tokens -= 1.0;
+                    true
+                } else {
+                    false
+                }
+            }
+            SamplingStrategy::Adaptive(target_per_second) => {
+                // 每秒根据上个窗口的请求量重新计算采样概率
+                let elapsed = now.saturating_duration_since(state.window_start);
+                if elapsed >= Duration::from_secs(1) {
+                    let observed = state.window_requests as f64 / elapsed.as_secs_f64();
+                    state.adaptive_probability = if observed > 0.0 {
+                        (target_per_second / observed).clamp(0.0, 1.0)
+                    } else {
+                        1.0
+                    };
+                    state.window_start = now;
+                    state.window_requests = 0;
+                }
+                state.window_requests += 1;
+                let probability = state.adaptive_probability;
+                state.rng.random_bool(probability)
+            }
+        };
+
+        if sampled {
+            SamplingDecision::RecordAndSample
+        } else {
+            log::trace!("未采样的追踪: {}", operation_name);
+            SamplingDecision::Drop
+        }
+    }
+
+    /// 开始新的追踪并创建根跨度，返回 (追踪ID, 根跨度ID)
+    /// Start a new trace with a root span and return (trace id, root span id)
+    ///
+    /// 未被采样时返回 [`NOOP_TRACE_ID`] 与 [`NOOP_SPAN_ID`]，后续操作均为空操作；
+    /// 子跨度沿用所属追踪的采样决策。
+    /// Unsampled traces get [`NOOP_TRACE_ID`] and [`NOOP_SPAN_ID`], on which every later call is a no-op;
+    /// child spans inherit the decision of their trace.
+    pub fn start_trace(&self, operation_name: &str) -> Result<(String, SpanId), MonitoringError> {
+        if self.should_sample(operation_name) == SamplingDecision::Drop {
+            return Ok((NOOP_TRACE_ID.to_string(), NOOP_SPAN_ID.to_string()));
+        }
+        let trace_id = new_trace_id();
+        let span_id = new_span_id();
+        let now = unix_millis();
+
+        let trace = Trace {
+            trace_id: trace_id.clone(),
+            parent_trace_id: None,
+            spans: vec![Span {
+                span_id: span_id.clone(),
+                parent_span_id: None,
+                operation_name: operation_name.to_string(),
+                start_time: now,
+                end_time: None,
+                tags: HashMap::new(),
+                logs: Vec::new(),
+                status: SpanStatus::Running,
+            }],
+            start_time: now,
+            end_time: None,
+            status: TraceStatus::Running,
+        };
+
+        self.lock_active()?.insert(trace_id.clone(), trace);
+        SpanContext::new(trace_id.as_str(), span_id.as_str()).push_current();
+        Ok((trace_id, span_id))
+    }
+
+    /// 在活跃追踪中开始跨度，可指定父跨度
+    /// Start a span in an active trace, optionally under a parent span
+    pub fn start_span(
+        &self,
+        trace_id: &str,
+        operation_name: &str,
+        parent_span_id: Option<&str>,
+    ) -> Result<SpanId, MonitoringError> {
+        if trace_id == NOOP_TRACE_ID {
+            return Ok(NOOP_SPAN_ID.to_string());
+        }
+        let mut active_traces = self.lock_active()?;
+        let trace = active_traces.get_mut(trace_id)
+            .ok_or_else(|| MonitoringError::TracingError(format!("追踪不存在: {}", trace_id)))?;
+        if let Some(parent) = parent_span_id
+            && !trace.spans.iter().any(|span| span.span_id == parent)
+        {
+            return Err(MonitoringError::TracingError(format!("父跨度不存在: {}", parent)));
+        }
+
+        let span_id = new_span_id();
+        trace.spans.push(Span {
+            span_id: span_id.clone(),
+            parent_span_id: parent_span_id.map(str::to_string),
+            operation_name: operation_name.to_string(),
+            start_time: unix_millis(),
+            end_time: None,
+            tags: HashMap::new(),
+            logs: Vec::new(),
+            status: SpanStatus::Running,
+        });
+        SpanContext::new(trace_id, span_id.as_str()).push_current();
+        Ok(span_id)
+    }
+
+    /// 结束跨度并设置状态，返回持续时间（毫秒）
+    /// Finish a span with the given status and return its duration in milliseconds
+    pub fn finish_span(&self, trace_id: &str, span_id: &str, status: SpanStatus) -> Result<u64, MonitoringError> {
+        let duration = self.with_span(trace_id, span_id, |span| {
+            if span.end_time.is_some() {
+                return Err(MonitoringError::TracingError(format!("跨度已结束: {}", span.span_id)));
+            }
+            let end_time = unix_millis().max(span.start_time);
+            span.end_time = Some(end_time);
+            span.status = status;
+            Ok(end_time - span.start_time)
+        })?;
+        SpanContext::remove_current(trace_id, Some(span_id));
+        Ok(duration)
+    }
+
+    /// 为跨度添加标签
+    /// Add a tag to a span
+    pub fn add_span_tag(&self, trace_id: &str, span_id: &str, key: &str, value: &str) -> Result<(), MonitoringError> {
+        self.with_span(trace_id, span_id, |span| {
+            span.tags.insert(key.to_string(), value.to_string());
+            Ok(())
+        })
+    }
+
+    /// 为跨度追加日志
+    /// Append a log entry to a span
+    pub fn add_span_log(
+        &self,
+        trace_id: &str,
+        span_id: &str,
+        level: LogLevel,
+        message: &str,
+        fields: HashMap<String, String>,
+    ) -> Result<(), MonitoringError> {
+        self.with_span(trace_id, span_id, |span| {
+            span.logs.push(SpanLog {
+                timestamp: unix_millis(),
+                level,
+                message: message.to_string(),
+                fields,
+            });
+            Ok(())
+        })
+    }
+
+    /// 结束追踪并移入已完成缓冲区
+    /// Finish a trace and move it into the finished buffer
+    ///
+    /// 仍在运行的跨度会记录警告；任一跨度出错时追踪状态为 `Error`。
+    /// Spans still running are reported with a warning; any errored span marks the trace as `Error`.
+    pub fn finish_trace(&self, trace_id: &str) -> Result<Trace, MonitoringError> {
+        if trace_id == NOOP_TRACE_ID {
+            let now = unix_millis();
+            return Ok(Trace {
+                trace_id: NOOP_TRACE_ID.to_string(),
+                parent_trace_id: None,
+                spans: Vec::new(),
+                start_time: now,
+                end_time: Some(now),
+                status: TraceStatus::Cancelled,
+            });
+        }
+        let mut trace = self.lock_active()?.remove(trace_id)
+            .ok_or_else(|| MonitoringError::TracingError(format!("追踪不存在: {}", trace_id)))?;
+        SpanContext::remove_current(trace_id, None);
+
+        let open_spans: Vec<&str> = trace.spans.iter()
+            .filter(|span| span.end_time.is_none())
+            .map(|span| span.operation_name.as_str())
+            .collect();
+        if !open_spans.is_empty() {
+            log::warn!("追踪 {} 结束时仍有未关闭的跨度: {}", trace_id, open_spans.join(", "));
+        }
+
+        trace.end_time = Some(unix_millis().max(trace.start_time));
+        trace.status = if trace.spans.iter().any(|span| matches!(span.status, SpanStatus::Error)) {
+            TraceStatus::Error
+        } else {
+            TraceStatus::Completed
+        };
+
+        let mut finished = self.finished_traces.lock()
+            .map_err(|_| MonitoringError::TracingError("已完成追踪缓冲区锁已损坏".to_string()))?;
+        finished.push_back(trace.clone());
+        while finished.len() > self.max_finished_traces {
+            finished.pop_front();
+        }
+        Ok(trace)
+    }
+
+    /// 以 Jaeger JSON 批量格式导出已完成的追踪
+    /// Export finished traces in the Jaeger JSON batch format
+    pub fn export_jaeger_json(&self) -> String {
+        let traces: Vec<Trace> = self.finished_traces.lock()
+            .map(|finished| finished.iter().cloned().collect())
+            .unwrap_or_default();
+        serde_json::to_string_pretty(&render_jaeger_json(&traces, &self.config)).unwrap_or_default()
+    }
+
+    /// 以 OTLP/JSON 格式导出已完成的追踪
+    /// Export finished traces in the OTLP/JSON format
+    pub fn export_otlp_json(&self) -> String {
+        let traces: Vec<Trace> = self.finished_traces.lock()
+            .map(|finished| finished.iter().cloned().collect())
+            .unwrap_or_default();
+        serde_json::to_string_pretty(&render_otlp_json(&traces, &self.config)).unwrap_or_default()
+    }
+
+    /// 启动后台导出任务，按批将已完成的追踪 POST 到 `TracingConfig::endpoint`
+    /// Spawn a background task that POSTs finished traces to `TracingConfig::endpoint` in batches
+    ///
+    /// 已导出的追踪会从缓冲区移除；重试耗尽的批次放回缓冲区，下个周期再试。
+    /// Exported traces leave the buffer; a batch that exhausts its retries is put back for the next tick.
+    pub fn spawn_exporter(&self, options: TraceExporterOptions) -> Result<tokio::task::JoinHandle<()>, MonitoringError> {
+        let endpoint = self.config.endpoint.clone()
+            .ok_or_else(|| MonitoringError::ConfigurationError("未配置追踪端点".to_string()))?;
+        if options.batch_size == 0 {
+            return Err(MonitoringError::ConfigurationError("批大小必须大于 0".to_string()));
+        }
+        let finished = Arc::clone(&self.finished_traces);
+        let config = self.config.clone();
+        let capacity = self.max_finished_traces;
+
+        Ok(tokio::spawn(async move {
+            let mut ticker = interval(options.interval);
+            loop {
+                ticker.tick().await;
+                loop {
+                    let batch: Vec<Trace> = match finished.lock() {
+                        Ok(mut finished) => {
+                            let take = finished.len().min(options.batch_size);
+                            finished.drain(..take).collect()
+                        }
+                        Err(_) => return,
+                    };
+                    if batch.is_empty() {
+                        break;
+                    }
+
+                    let body = match options.format {
+                        TraceExportFormat::Jaeger => render_jaeger_json(&batch, &config),
+                        TraceExportFormat::Otlp => render_otlp_json(&batch, &config),
+                    }.to_string();
+
+                    let mut attempt = 0;
+                    let mut backoff = options.retry_backoff;
+                    let result = loop {
+                        match post_json(&endpoint, &body, TRACE_EXPORT_TIMEOUT).await {
+                            Ok(()) => break Ok(()),
+                            Err(e) if attempt >= options.max_retries => break Err(e),
+                            Err(_) => {
+                                attempt += 1;
+                                tokio::time::sleep(backoff).await;
+                                backoff *= 2;
+                            }
+                        }
+                    };
+
+                    if let Err(e) = result {
+                        log::warn!("追踪导出失败，{} 条追踪将在下个周期重试: {}", batch.len(), e);
+                        if let Ok(mut finished) = finished.lock() {
+                            for trace in batch.into_iter().rev() {
+                                finished.push_front(trace);
+                            }
+                            while finished.len() > capacity {
+                                finished.pop_front();
+                            }
+                        }
+                        break;
+                    }
+                }
+            }
+        }))
+    }
+
+    fn lock_active(&self) -> Result<std::sync::MutexGuard<'_, HashMap<String, Trace>>, MonitoringError> {
+        self.active_traces.lock()
+            .map_err(|_| MonitoringError::TracingError("活跃追踪锁已损坏".to_string()))
+    }
+
+    fn with_span<R: Default>(
+        &self,
+        trace_id: &str,
+        span_id: &str,
+        f: impl FnOnce(&mut Span) -> Result<R, MonitoringError>,
+    ) -> Result<R, MonitoringError> {
+        if trace_id == NOOP_TRACE_ID {
+            return Ok(R::default());
+        }
+        let mut active_traces = self.lock_active()?;
+        let span = active_traces.get_mut(trace_id)
+            .ok_or_else(|| MonitoringError::TracingError(format!("追踪不存在: {}", trace_id)))?
+            .spans.iter_mut()
+            .find(|span| span.span_id == span_id)
+            .ok_or_else(|| MonitoringError::TracingError(format!("跨度不存在: {}", span_id)))?;
+        f(span)
+    }
+}
+
+impl StructuredLogger {
+    /// 创建新的结构化日志记录器
+    /// Create new structured logger
+    ///
+    /// 日志目标中的每个 `File(path)` 都会自动注册一个 [`FileLogProcessor`]。
+    /// Every `File(path)` log target registers a [`FileLogProcessor`] automatically.
+    pub fn new(config: LoggingConfig) -> Self {
+        let processors = config.targets.iter()
+            .filter_map(|target| {
+                let processor: Box<dyn LogProcessor> = match target {
+                    LogTarget::Stdout => Box::new(ConsoleLogProcessor::new(ConsoleStream::Stdout, config.format.clone())),
+                    LogTarget::Stderr => Box::new(ConsoleLogProcessor::new(ConsoleStream::Stderr, config.format.clone())),
+                    LogTarget::File(path) => {
+                        Box::new(FileLogProcessor::new(FileLogProcessorConfig::new(path, config.format.clone())))
+                    }
+                    LogTarget::Remote(_) | LogTarget::Elasticsearch(_) => return None,
+                };
+                Some(RegisteredProcessor { processor, min_level: config.level_for(target), error_count: 0 })
+            })
+            .collect();
+        Self {
+            config,
+            log_buffer: Arc::new(Mutex::new(Vec::new())),
+            processors: Arc::new(Mutex::new(processors)),
+            flusher: Mutex::new(None),
+        }
+    }
+
+    /// 注册日志处理器，使用全局日志级别
+    /// Register a log processor at the global log level
+    pub fn add_processor(&self, processor: Box<dyn LogProcessor>) {
+        if let Ok(mut processors) = self.processors.lock() {
+            processors.push(RegisteredProcessor { processor, min_level: self.config.level, error_count: 0 });
+        }
+    }
+
+    /// 该级别的日志是否会被至少一个目标接收
+    /// Whether at least one target accepts entries at this level
+    pub fn enabled(&self, level: LogLevel) -> bool {
+        level >= self.config.min_level()
+    }
+
+    /// 各处理器的错误计数，顺序与注册顺序一致
+    /// Error count of each processor, in registration order
+    pub fn processor_error_counts(&self) -> Vec<u64> {
+        self.processors.lock()
+            .map(|processors| processors.iter().map(|p| p.error_count).collect())
+            .unwrap_or_default()
+    }
+
+    /// 追加日志条目，缓冲区达到 `buffer_size` 时立即刷新
+    /// Append an entry and flush immediately once the buffer reaches `buffer_size`
+    ///
+    /// 在 tokio 运行时中调用时，刷新交给阻塞线程池执行，调用方不等待写入；否则同步刷新。
+    /// Inside a tokio runtime the flush runs on the blocking pool and the caller does not wait for the
+    /// writes; otherwise it flushes synchronously.
+    pub fn push(&self, entry: LogEntry) -> Result<(), LoggingError> {
+        let buffered = {
+            let mut log_buffer = self.log_buffer.lock()
+                .map_err(|_| LoggingError::ProcessorError("日志缓冲区锁已损坏".to_string()))?;
+            log_buffer.push(entry);
+            log_buffer.len()
+        };
+        if buffered >= self.config.buffer_size {
+            match tokio::runtime::Handle::try_current() {
+                Ok(runtime) => {
+                    let log_buffer = Arc::clone(&self.log_buffer);
+                    let processors = Arc::clone(&self.processors);
+                    runtime.spawn_blocking(move || {
+                        if let Err(e) = flush_log_buffer(&log_buffer, &processors) {
+                            log::warn!("日志刷新失败: {}", e);
+                        }
+                    });
+                }
+                Err(_) => {
+                    self.flush()?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// 将缓冲区中的日志交给全部处理器并清空缓冲区，返回处理的条目数
+    /// Hand buffered entries to every processor, drain the buffer and return how many were processed
+    ///
+    /// 单个处理器失败只会增加其错误计数，不影响其他处理器。
+    /// A failing processor only bumps its own error count and never blocks the others.
+    pub fn flush(&self) -> Result<usize, LoggingError> {
+        flush_log_buffer(&self.log_buffer, &self.processors)
+    }
+
+    /// 启动后台任务，每隔 `flush_interval` 在阻塞线程池上刷新一次缓冲区
+    /// Spawn a background task that flushes the buffer on the blocking pool every `flush_interval`
+    pub fn start_flusher(&self) {
+        let log_buffer = Arc::clone(&self.log_buffer);
+        let processors = Arc::clone(&self.processors);
+        let flush_interval = self.config.flush_interval;
+        let handle = tokio::spawn(async move {
+            let mut ticker = interval(flush_interval);
+            // 第一次 tick 立即完成，跳过它
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                let (log_buffer, processors) = (Arc::clone(&log_buffer), Arc::clone(&processors));
+                match tokio::task::spawn_blocking(move || flush_log_buffer(&log_buffer, &processors)).await {
+                    Ok(Ok(_)) => {}
+                    Ok(Err(e)) => log::warn!("后台日志刷新失败: {}", e),
+                    Err(e) => log::warn!("后台日志刷新任务异常退出: {}", e),
+                }
+            }
+        });
+        if let Ok(mut flusher) = self.flusher.lock()
+            && let Some(previous) = flusher.replace(handle)
+        {
+            previous.abort();
+        }
+    }
+
+    /// 停止后台刷新，同步刷新剩余日志并关闭全部处理器
+    /// Stop the background flusher, flush what is left synchronously and close every processor
+    pub fn shutdown(&self) -> Result<(), LoggingError> {
+        if let Some(handle) = self.flusher.lock().ok().and_then(|mut flusher| flusher.take()) {
+            handle.abort();
+        }
+        self.flush()?;
+
+        let mut processors = self.processors.lock()
+            .map_err(|_| LoggingError::ProcessorError("日志处理器锁已损坏".to_string()))?;
+        let mut first_error = None;
+        for registered in processors.iter_mut() {
+            if let Err(e) = registered.processor.close() {
+                registered.error_count += 1;
+                first_error.get_or_insert(e);
+            }
+        }
+        first_error.map_or(Ok(()), Err)
+    }
+}
+
+/// 将日志缓冲区排空到全部处理器
+/// Drain the log buffer into every processor
+fn flush_log_buffer(
+    log_buffer: &Mutex<Vec<LogEntry>>,
+    processors: &Mutex<Vec<RegisteredProcessor>>,
+) -> Result<usize, LoggingError> {
+    // 先锁住处理器，保证并发刷新时条目顺序不变；缓冲区只在换出时加锁，写入期间可以继续追加
+    let mut processors = processors.lock()
+        .map_err(|_| LoggingError::ProcessorError("日志处理器锁已损坏".to_string()))?;
+    let entries = std::mem::take(
+        &mut *log_buffer.lock().map_err(|_| LoggingError::ProcessorError("日志缓冲区锁已损坏".to_string()))?,
+    );
+    if entries.is_empty() {
+        return Ok(0);
+    }
+
+    for registered in processors.iter_mut() {
+        let mut failures = 0;
+        for entry in entries.iter().filter(|entry| entry.level >= registered.min_level) {
+            if registered.processor.process(entry).is_err() {
+                failures += 1;
+            }
+        }
+        if registered.processor.flush().is_err() {
+            failures += 1;
+        }
+        if failures > 0 {
+            registered.error_count += failures;
+            log::warn!("日志处理器失败 {} 次", failures);
+        }
+    }
+    Ok(entries.len())
+}
+
+/// 文件同步策略
+/// File sync policy
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FsyncPolicy {
+    /// 从不主动同步，由操作系统决定
+    Never,
+    /// 每写入一条日志同步一次
+    EveryEntry,
+    /// 调用 `flush` 时同步
+    OnFlush,
+}
+
+/// 文件日志处理器配置
+/// File log processor configuration
+#[derive(Debug, Clone)]
+pub struct FileLogProcessorConfig {
+    /// 日志文件路径
+    pub path: std::path::PathBuf,
+    /// 输出格式
+    pub format: LogFormat,
+    /// 单个文件的最大字节数，超过后轮转
+    pub max_bytes: u64,
+    /// 保留的轮转文件数
+    pub max_files: usize,
+    /// 是否使用 gzip 压缩轮转文件
+    pub compress_rotated: bool,
+    /// 同步策略
+    pub fsync: FsyncPolicy,
+}
+
+impl FileLogProcessorConfig {
+    /// 使用默认轮转参数创建配置（10 MiB，保留 5 个文件）
+    /// Create a configuration with default rotation (10 MiB, 5 files kept)
+    pub fn new(path: impl Into<std::path::PathBuf>, format: LogFormat) -> Self {
+        Self {
+            path: path.into(),
+            format,
+            max_bytes: 10 * 1024 * 1024,
+            max_files: 5,
+            compress_rotated: false,
+            fsync: FsyncPolicy::OnFlush,
+        }
+    }
+
+    /// 第 `index` 个轮转文件的路径，如 `app.log.1` 或 `app.log.1.gz`
+    /// Path of the `index`-th rotated file, e.g. `app.log.1` or `app.log.1.gz`
+    pub fn rotated_path(&self, index: usize) -> std::path::PathBuf {
+        let mut name = self.path.clone().into_os_string();
+        name.push(format!(".{}", index));
+        if self.compress_rotated {
+            name.push(".gz");
+        }
+        name.into()
+    }
+}
+
+/// 按大小轮转的文件日志处理器
+/// Size-rotated file log processor
+///
+/// 写入前会检查日志文件是否仍存在；目录或文件在运行中被删除时会重新创建并继续写入，
+/// 重建后仍无法写入的条目计入 `dropped_entries`。
+/// Each write checks that the log file still exists; if the directory or file vanished it is
+/// recreated and writing continues, and entries that still cannot be written count as dropped.
+#[derive(Debug)]
+pub struct FileLogProcessor {
+    /// 处理器配置
+    pub config: FileLogProcessorConfig,
+    /// 当前打开的文件及其大小
+    state: Mutex<Option<(std::fs::File, u64)>>,
+    /// 丢弃的日志条目数
+    dropped_entries: AtomicU64,
+}
+
+impl FileLogProcessor {
+    /// 创建文件日志处理器，文件在首次写入时打开
+    /// Create a file log processor; the file is opened on first write
+    pub fn new(config: FileLogProcessorConfig) -> Self {
+        Self {
+            config,
+            state: Mutex::new(None),
+            dropped_entries: AtomicU64::new(0),
+        }
+    }
+
+    /// 丢弃的日志条目数
+    /// Number of dropped log entries
+    pub fn dropped_entries(&self) -> u64 {
+        self.dropped_entries.load(Ordering::Relaxed)
+    }
+
+    fn open(&self) -> std::io::Result<(std::fs::File, u64)> {
+        if let Some(parent) = self.config.path.parent()
+            && !parent.as_os_str().is_empty()
+        {
+            std::fs::create_dir_all(parent)?;
+        }
+        let file = std::fs::OpenOptions::new().create(true).append(true).open(&self.config.path)?;
+        let size = file.metadata()?.len();
+        Ok((file, size))
+    }
+
+    /// 轮转：删除最旧的文件，依次后移，当前文件变为 `.1`
+    /// Rotate: drop the oldest file, shift the rest and move the current file to `.1`
+    fn rotate(&self) -> std::io::Result<()> {
+        if self.config.max_files == 0 {
+            return std::fs::remove_file(&self.config.path);
+        }
+        let oldest = self.config.rotated_path(self.config.max_files);
+        if oldest.exists() {
+            std::fs::remove_file(&oldest)?;
+        }
+        for index in (1..self.config.max_files).rev() {
+            let from = self.config.rotated_path(index);
+            if from.exists() {
+                std::fs::rename(&from, self.config.rotated_path(index + 1))?;
+            }
+        }
+
+        let first = self.config.rotated_path(1);
+        if self.config.compress_rotated {
+            let mut input = std::fs::File::open(&self.config.path)?;
+            let output = std::fs::File::create(&first)?;
+            let mut encoder = flate2::write::GzEncoder::new(output, flate2::Compression::default());
+            std::io::copy(&mut input, &mut encoder)?;
+            encoder.finish()?.sync_all()?;
+            std::fs::remove_file(&self.config.path)
+        } else {
+            std::fs::rename(&self.config.path, first)
+        }
+    }
+
+    fn write_line(&self, line: &str) -> std::io::Result<()> {
+        use std::io::Write;
+
+        let mut state = self.state.lock()
+            .map_err(|_| std::io::Error::other("日志文件锁已损坏"))?;
+        if state.is_none() || !self.config.path.exists() {
+            *state = Some(self.open()?);
+        }
+
+        let line_len = line.len() as u64;
+        if let Some((_, size)) = state.as_ref()
+            && *size > 0
+            && *size + line_len > self.config.max_bytes
+        {
+            *state = None;
+            self.rotate()?;
+            *state = Some(self.open()?);
+        }
+
+        let (file, size) = state.as_mut().expect("日志文件已打开");
+        if let Err(e) = file.write_all(line.as_bytes()) {
+            *state = None;
+            return Err(e);
+        }
+        *size += line_len;
+        if self.config.fsync == FsyncPolicy::EveryEntry {
+            file.sync_data()?;
+        }
+        Ok(())
+    }
+}
+
+impl LogProcessor for FileLogProcessor {
+    fn process(&self, entry: &LogEntry) -> Result<(), LoggingError> {
+        let line = render(entry, &self.config.format) + "\n";
+        self.write_line(&line).map_err(|e| {
+            self.dropped_entries.fetch_add(1, Ordering::Relaxed);
+            LoggingError::OutputError(format!("{}: {}", self.config.path.display(), e))
+        })
+    }
+
+    fn flush(&self) -> Result<(), LoggingError> {
+        use std::io::Write;
+
+        let mut state = self.state.lock()
+            .map_err(|_| LoggingError::ProcessorError("日志文件锁已损坏".to_string()))?;
+        if let Some((file, _)) = state.as_mut() {
+            file.flush().map_err(|e| LoggingError::OutputError(e.to_string()))?;
+            if self.config.fsync != FsyncPolicy::Never {
+                file.sync_data().map_err(|e| LoggingError::OutputError(e.to_string()))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn close(&self) -> Result<(), LoggingError> {
+        self.flush()?;
+        if let Ok(mut state) = self.state.lock() {
+            *state = None;
+        }
+        Ok(())
+    }
+}
+
+/// 控制台输出流
+/// Console output stream
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConsoleStream {
+    /// 标准输出
+    Stdout,
+    /// 标准错误
+    Stderr,
+}
+
+/// 控制台日志处理器
+/// Console log processor
+#[derive(Debug)]
+pub struct ConsoleLogProcessor {
+    /// 输出流
+    pub stream: ConsoleStream,
+    /// 输出格式
+    pub format: LogFormat,
+}
+
+impl ConsoleLogProcessor {
+    /// 创建控制台日志处理器
+    /// Create console log processor
+    pub fn new(stream: ConsoleStream, format: LogFormat) -> Self {
+        Self { stream, format }
+    }
+}
+
+impl LogProcessor for ConsoleLogProcessor {
+    fn process(&self, entry: &LogEntry) -> Result<(), LoggingError> {
+        use std::io::Write;
+
+        let line = render(entry, &self.format);
+        let result = match self.stream {
+            ConsoleStream::Stdout => writeln!(std::io::stdout().lock(), "{}", line),
+            ConsoleStream::Stderr => writeln!(std::io::stderr().lock(), "{}", line),
+        };
+        result.map_err(|e| LoggingError::OutputError(e.to_string()))
+    }
+
+    fn flush(&self) -> Result<(), LoggingError> {
+        use std::io::Write;
+
+        let result = match self.stream {
+            ConsoleStream::Stdout => std::io::stdout().flush(),
+            ConsoleStream::Stderr => std::io::stderr().flush(),
+        };
+        result.map_err(|e| LoggingError::OutputError(e.to_string()))
+    }
+
+    fn close(&self) -> Result<(), LoggingError> {
+        self.flush()
+    }
+}
+
+/// 日志级别的大写名称
+/// Uppercase name of a log level
+fn log_level_label(level: &LogLevel) -> &'static str {
+    match level {
+        LogLevel::Trace => "TRACE",
+        LogLevel::Debug => "DEBUG",
+        LogLevel::Info => "INFO",
+        LogLevel::Warn => "WARN",
+        LogLevel::Error => "ERROR",
+        LogLevel::Fatal => "FATAL",
+    }
+}
+
+/// 将 Unix 秒格式化为 RFC 3339 UTC 时间
+/// Format Unix seconds as an RFC 3339 UTC timestamp
+fn rfc3339_seconds(timestamp: u64) -> String {
+    i64::try_from(timestamp).ok()
+        .and_then(|seconds| chrono::DateTime::from_timestamp(seconds, 0))
+        .map(|time| time.format("%Y-%m-%dT%H:%M:%SZ").to_string())
+        .unwrap_or_else(|| timestamp.to_string())
+}
+
+/// 转义反斜杠与控制字符，使输出保持单行
+/// Escape backslashes and control characters so the output stays on one line
+fn escape_log_text(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{{{:04x}}}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// logfmt 取值：包含空白、引号或等号时加引号
+/// logfmt value, quoted when it contains whitespace, quotes or `=`
+fn logfmt_value(value: &str) -> String {
+    let escaped = escape_log_text(value);
+    if value.is_empty() || value.chars().any(|c| c.is_whitespace() || c == '"' || c == '=') {
+        format!("\"{}\"", escaped.replace('"', "\\\""))
+    } else {
+        escaped
+    }
+}
+
+/// 按日志格式将条目渲染为单行文本（不含换行符）
+/// Render an entry as a single line (without trailing newline) in the given log format
+///
+/// - `JSON`：`LogEntry` 的 JSON 序列化，键按字母顺序排列
+/// - `Text`：`2024-01-01T00:00:00Z INFO target message key=value`
+/// - `Structured`：logfmt，如 `ts=... level=info target=... msg="..."`
+pub fn render(entry: &LogEntry, format: &LogFormat) -> String {
+    let mut fields: Vec<(&String, String)> = entry.fields.iter()
+        .map(|(key, value)| match value {
+            serde_json::Value::String(s) => (key, s.clone()),
+            other => (key, other.to_string()),
+        })
+        .collect();
+    fields.sort();
+
+    match format {
+        LogFormat::JSON => {
+            // 未启用 serde_json 的 preserve_order，`Value` 的对象是 BTreeMap，键按字母顺序输出
+            serde_json::to_value(entry)
+                .map(|value| value.to_string())
+                .unwrap_or_default()
+        }
+        LogFormat::Text => {
+            let mut line = format!(
+                "{} {} {} {}",
+                rfc3339_seconds(entry.timestamp),
+                log_level_label(&entry.level),
+                escape_log_text(&entry.target),
+                escape_log_text(&entry.message),
+            );
+            for (key, value) in fields {
+                line.push_str(&format!(" {}={}", escape_log_text(key), logfmt_value(&value)));
+            }
+            line
+        }
+        LogFormat::Structured => {
+            let mut pairs = vec![
+                ("ts".to_string(), rfc3339_seconds(entry.timestamp)),
+                ("level".to_string(), log_level_label(&entry.level).to_lowercase()),
+                ("target".to_string(), entry.target.clone()),
+                ("msg".to_string(), entry.message.clone()),
+            ];
+            pairs.extend(entry.trace_id.iter().map(|id| ("trace_id".to_string(), id.clone())));
+            pairs.extend(entry.span_id.iter().map(|id| ("span_id".to_string(), id.clone())));
+            pairs.extend(fields.into_iter().map(|(key, value)| (key.clone(), value)));
+            pairs.iter()
+                .map(|(key, value)| format!("{}={}", escape_log_text(key), logfmt_value(value)))
+                .collect::<Vec<_>>()
+                .join(" ")
+        }
+    }
+}
+
+impl AlertManager {
+    /// 创建新的告警管理器
+    /// Create new alert manager
+    pub fn new(config: AlertConfig) -> Self {
+        let silences = config.silence_config.silence_rules.clone();
+        Self {
+            rules: Arc::new(Mutex::new(Vec::new())),
+            alert_states: Arc::new(Mutex::new(HashMap::new())),
+            notification_channels: Arc::new(Mutex::new(Vec::new())),
+            config,
+            compiled_rules: Arc::new(Mutex::new(HashMap::new())),
+            sample_history: Arc::new(Mutex::new(HashMap::new())),
+            evaluator: Mutex::new(None),
+            silences: Arc::new(Mutex::new(silences)),
+            silence_patterns: Arc::new(Mutex::new(HashMap::new())),
+            suppressed_notifications: Arc::new(AtomicU64::new(0)),
+            clock: Arc::new(SystemClock),
+            notification_log: Arc::new(Mutex::new(HashMap::new())),
+            dropped_alerts: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// 使用指定时钟判断静默窗口
+    /// Use the given clock for silence windows
+    pub fn with_clock(self, clock: Arc<dyn MetricsClock>) -> Self {
+        Self { clock, ..self }
+    }
+
+    /// 添加静默规则并返回其ID，正则匹配器无效时拒绝
+    /// Add a silence and return its id, rejecting invalid regex matchers
+    pub fn add_silence(&self, mut silence: SilenceRule) -> Result<String, MonitoringError> {
+        if silence.end_time <= silence.start_time {
+            return Err(MonitoringError::AlertError("静默结束时间必须晚于开始时间".to_string()));
+        }
+        {
+            let mut patterns = self.silence_patterns.lock()
+                .map_err(|_| MonitoringError::AlertError("静默规则锁已损坏".to_string()))?;
+            for matcher in silence.matchers.iter().filter(|matcher| matcher.is_regex) {
+                compile_silence_pattern(&mut patterns, &matcher.value)?;
+            }
+        }
+        if silence.id.is_empty() {
+            silence.id = uuid::Uuid::new_v4().to_string();
+        }
+        let id = silence.id.clone();
+        let mut silences = self.silences.lock()
+            .map_err(|_| MonitoringError::AlertError("静默规则锁已损坏".to_string()))?;
+        silences.retain(|existing| existing.id != id);
+        silences.push(silence);
+        Ok(id)
+    }
+
+    /// 立即使静默失效
+    /// Expire a silence immediately
+    pub fn expire_silence(&self, id: &str) -> Result<(), MonitoringError> {
+        let now = self.clock.now_secs();
+        let mut silences = self.silences.lock()
+            .map_err(|_| MonitoringError::AlertError("静默规则锁已损坏".to_string()))?;
+        let silence = silences.iter_mut()
+            .find(|silence| silence.id == id)
+            .ok_or_else(|| MonitoringError::AlertError(format!("静默不存在: {}", id)))?;
+        silence.end_time = silence.end_time.min(now);
+        Ok(())
+    }
+
+    /// 列出当前生效的静默
+    /// List the silences in effect right now
+    pub fn list_active_silences(&self) -> Vec<SilenceRule> {
+        let now = self.clock.now_secs();
+        self.silences.lock()
+            .map(|silences| silences.iter().filter(|silence| silence_is_active(silence, now)).cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// 被静默抑制的通知总数
+    /// Total number of notifications suppressed by silences
+    pub fn suppressed_count(&self) -> u64 {
+        self.suppressed_notifications.load(Ordering::Relaxed)
+    }
+
+    /// 因超出 `max_alerts` 被丢弃的新告警总数
+    /// Total number of new alerts dropped because `max_alerts` was reached
+    pub fn dropped_count(&self) -> u64 {
+        self.dropped_alerts.load(Ordering::Relaxed)
+    }
+
+    fn dispatcher(&self) -> AlertDispatcher {
+        AlertDispatcher {
+            channels: Arc::clone(&self.notification_channels),
+            rules: Arc::clone(&self.rules),
+            states: Arc::clone(&self.alert_states),
+            silences: Arc::clone(&self.silences),
+            silence_patterns: Arc::clone(&self.silence_patterns),
+            suppressed: Arc::clone(&self.suppressed_notifications),
+            clock: Arc::clone(&self.clock),
+            notification_log: Arc::clone(&self.notification_log),
+            repeat_interval: self.config.repeat_interval,
+            send_resolved: self.config.send_resolved,
+        }
+    }
+
+    /// 添加告警规则，表达式无效时拒绝
+    /// Add an alert rule, rejecting it when the expression is invalid
+    pub fn add_rule(&self, rule: AlertRule) -> Result<(), MonitoringError> {
+        let expr = parse_alert_expression(&rule.expression)?;
+        let mut rules = self.rules.lock()
+            .map_err(|_| MonitoringError::AlertError("告警规则锁已损坏".to_string()))?;
+        let mut compiled = self.compiled_rules.lock()
+            .map_err(|_| MonitoringError::AlertError("告警规则锁已损坏".to_string()))?;
+        rules.retain(|existing| existing.id != rule.id);
+        compiled.insert(rule.id.clone(), expr);
+        rules.push(rule);
+        Ok(())
+    }
+
+    /// 添加通知渠道
+    /// Add a notification channel
+    pub fn add_notification_channel(&self, channel: Box<dyn NotificationChannel>) {
+        if let Ok(mut channels) = self.notification_channels.lock() {
+            channels.push(channel);
+        }
+    }
+
+    /// 按收集器时钟的当前时间评估全部规则，返回状态发生变化的告警
+    /// Evaluate every rule at the collector clock's current time and return the alerts whose state changed
+    ///
+    /// 告警触发时通知全部渠道，持续活跃期间每隔 `repeat_interval` 重复一次，严重程度升级时立即重发；
+    /// `send_resolved` 为真时解决后通知一次。命中生效静默的告警不发送通知，其状态标记为 `suppressed`。
+    /// 未结束的告警达到 `max_alerts` 后，新告警按严重程度从低到高丢弃。
+    /// Every channel is notified when an alert fires, again every `repeat_interval` while it stays active
+    /// and immediately on severity escalation; resolution is sent once when `send_resolved` is set.
+    /// Alerts matched by an active silence are not sent and their state is marked `suppressed`.
+    /// Once `max_alerts` alerts are open, new alerts are dropped lowest severity first.
+    pub fn evaluate(&self, collector: &MetricsCollector) -> Result<Vec<AlertState>, MonitoringError> {
+        let changed = evaluate_alert_rules(
+            &self.rules,
+            &self.compiled_rules,
+            &self.sample_history,
+            &self.alert_states,
+            collector,
+            self.config.max_alerts,
+            &self.dropped_alerts,
+        )?;
+        self.dispatcher().dispatch(&changed);
+        Ok(changed)
+    }
+
+    /// 启动后台任务，每隔 `evaluation_interval` 评估一次规则
+    /// Spawn a background task that evaluates the rules every `evaluation_interval`
+    pub fn start_evaluation(&self, collector: MetricsCollector) {
+        let rules = Arc::clone(&self.rules);
+        let compiled = Arc::clone(&self.compiled_rules);
+        let history = Arc::clone(&self.sample_history);
+        let states = Arc::clone(&self.alert_states);
+        let dispatcher = self.dispatcher();
+        let evaluation_interval = self.config.evaluation_interval;
+        let max_alerts = self.config.max_alerts;
+        let dropped = Arc::clone(&self.dropped_alerts);
+
+        let handle = tokio::spawn(async move {
+            let mut ticker = interval(evaluation_interval);
+            loop {
+                ticker.tick().await;
+                let (rules, compiled, history, states, dispatcher, collector, dropped) = (
+                    Arc::clone(&rules),
+                    Arc::clone(&compiled),
+                    Arc::clone(&history),
+                    Arc::clone(&states),
+                    dispatcher.clone(),
+                    collector.clone(),
+                    Arc::clone(&dropped),
+                );
+                // 通知渠道可能阻塞在网络请求上，放到阻塞线程池执行
+                let evaluation = tokio::task::spawn_blocking(move || {
+                    let changed = evaluate_alert_rules(
+                        &rules, &compiled, &history, &states, &collector, max_alerts, &dropped,
+                    )?;
+                    dispatcher.dispatch(&changed);
+                    Ok::<_, MonitoringError>(())
+                });
+                match evaluation.await {
+                    Ok(Err(e)) => log::warn!("告警规则评估失败: {}", e),
+                    Err(e) => log::warn!("告警规则评估任务失败: {}", e),
+                    Ok(Ok(())) => {}
+                }
+            }
+        });
+        if let Ok(mut evaluator) = self.evaluator.lock()
+            && let Some(previous) = evaluator.replace(handle)
+        {
+            previous.abort();
+        }
+    }
+}
+
+/// 告警通知分发所需的共享状态
+/// Shared state needed to dispatch alert notifications
+#[derive(Clone)]
+struct AlertDispatcher {
+    channels: Arc<Mutex<Vec<Box<dyn NotificationChannel>>>>,
+    rules: Arc<Mutex<Vec<AlertRule>>>,
+    states: Arc<Mutex<HashMap<String, AlertState>>>,
+    silences: Arc<Mutex<Vec<SilenceRule>>>,
+    silence_patterns: Arc<Mutex<HashMap<String, regex::Regex>>>,
+    suppressed: Arc<AtomicU64>,
+    clock: Arc<dyn MetricsClock>,
+    notification_log: Arc<Mutex<HashMap<(String, u64), NotificationRecord>>>,
+    repeat_interval: Duration,
+    send_resolved: bool,
+}
+
+impl AlertDispatcher {
+    /// 发送需要通知的告警：到期的活跃告警和本轮解决的告警，跳过被静默的告警
+    /// Send the alerts that are due: active alerts past their repeat interval and alerts resolved
+    /// this round, skipping silenced ones
+    fn dispatch(&self, changed: &[AlertState]) {
+        // 解决状态只会在状态变化时出现一次，因此解决通知至多发送一次
+        let mut notify: Vec<AlertState> = changed.iter()
+            .filter(|state| matches!(state.state, AlertStateType::Resolved))
+            .cloned()
+            .collect();
+        if let Ok(states) = self.states.lock() {
+            notify.extend(states.values().filter(|state| matches!(state.state, AlertStateType::Active)).cloned());
+        }
+        if notify.is_empty() {
+            return;
+        }
+        notify.sort_by(|a, b| a.alert_id.cmp(&b.alert_id));
+
+        let now = self.clock.now_secs();
+        let (Ok(channels), Ok(rules), Ok(mut silences), Ok(mut patterns), Ok(mut notified)) = (
+            self.channels.lock(),
+            self.rules.lock(),
+            self.silences.lock(),
+            self.silence_patterns.lock(),
+            self.notification_log.lock(),
+        ) else {
+            return;
+        };
+        // 清理已过期的静默
+        silences.retain(|silence| silence.end_time > now);
+
+        for state in notify {
+            let Some(rule) = rules.iter().find(|rule| rule.id == state.alert_id) else {
+                continue;
+            };
+            let mut labels = rule.labels.clone();
+            labels.extend(state.labels.clone());
+            let alert = Alert {
+                id: state.alert_id.clone(),
+                rule_id: rule.id.clone(),
+                rule_name: rule.name.clone(),
+                severity: rule.severity,
+                state: state.state.clone(),
+                start_time: state.start_time,
+                end_time: state.end_time,
+                labels,
+                annotations: rule.annotations.clone(),
+                description: rule.annotations.get("description").cloned().unwrap_or_else(|| rule.name.clone()),
+            };
+
+            let key = (rule.id.clone(), label_fingerprint(&alert.labels));
+            if matches!(alert.state, AlertStateType::Resolved) {
+                notified.remove(&key);
+                if !self.send_resolved {
+                    continue;
+                }
+            } else if let Some(record) = notified.get(&key)
+                && rule.severity <= record.severity
+                && now.saturating_sub(record.sent_at) < self.repeat_interval.as_secs()
+            {
+                continue;
+            }
+
+            let mut match_labels = alert.labels.clone();
+            match_labels.entry("alertname".to_string()).or_insert_with(|| rule.name.clone());
+            let silenced = silences.iter()
+                .filter(|silence| silence_is_active(silence, now))
+                .any(|silence| silence_matches(silence, &match_labels, &mut patterns));
+            if let Ok(mut states) = self.states.lock()
+                && let Some(current) = states.get_mut(&alert.id)
+            {
+                current.suppressed = silenced;
+            }
+            if silenced {
+                self.suppressed.fetch_add(1, Ordering::Relaxed);
+                continue;
+            }
+
+            for channel in channels.iter() {
+                if let Err(e) = channel.send_notification(&alert) {
+                    log::warn!("通知渠道 {} 发送告警 {} 失败: {}", channel.get_name(), alert.id, e);
+                }
+            }
+            if !matches!(alert.state, AlertStateType::Resolved) {
+                notified.insert(key, NotificationRecord { sent_at: now, severity: rule.severity });
+            }
+        }
+    }
+}
+
+/// 按排序后的标签计算稳定的 64 位指纹（FNV-1a）
+/// Stable 64-bit fingerprint of the sorted labels (FNV-1a)
+fn label_fingerprint(labels: &HashMap<String, String>) -> u64 {
+    let mut sorted: Vec<(&String, &String)> = labels.iter().collect();
+    sorted.sort();
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for (name, value) in sorted {
+        // 用分隔符避免 ("ab", "c") 与 ("a", "bc") 冲突
+        for byte in name.bytes().chain([0xff]).chain(value.bytes()).chain([0xfe]) {
+            hash ^= u64::from(byte);
+            hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+    hash
+}
+
+/// 静默在给定时刻是否生效
+/// Whether a silence is in effect at the given time
+fn silence_is_active(silence: &SilenceRule, now: u64) -> bool {
+    silence.start_time <= now && now < silence.end_time
+}
+
+/// 编译并缓存完整匹配的静默正则表达式
+/// Compile and cache a fully anchored silence regex
+fn compile_silence_pattern<'a>(
+    patterns: &'a mut HashMap<String, regex::Regex>,
+    pattern: &str,
+) -> Result<&'a regex::Regex, MonitoringError> {
+    if !patterns.contains_key(pattern) {
+        let regex = regex::Regex::new(&format!("^(?:{})$", pattern))
+            .map_err(|e| MonitoringError::AlertError(format!("无效的静默正则表达式 `{}`: {}", pattern, e)))?;
+        patterns.insert(pattern.to_string(), regex);
+    }
+    Ok(&patterns[pattern])
+}
+
+/// 静默的全部匹配器是否都匹配告警标签，缺失的标签视为空字符串
+/// Whether every matcher of a silence matches the alert labels; missing labels read as empty
+fn silence_matches(
+    silence: &SilenceRule,
+    labels: &HashMap<String, String>,
+    patterns: &mut HashMap<String, regex::Regex>,
+) -> bool {
+    silence.matchers.iter().all(|matcher| {
+        let value = labels.get(&matcher.name).map(String::as_str).unwrap_or("");
+        if matcher.is_regex {
+            compile_silence_pattern(patterns, &matcher.value).is_ok_and(|regex| regex.is_match(value))
+        } else {
+            value == matcher.value
+        }
+    })
+}
+
+/// Webhook 通知渠道配置
+/// Webhook notification channel configuration
+#[derive(Debug, Clone)]
+pub struct WebhookConfig {
+    /// 渠道名称
+    pub name: String,
+    /// 目标 URL，仅支持 `http://`
+    pub url: String,
+    /// HMAC-SHA256 签名密钥，为空时不签名
+    pub secret: Option<String>,
+    /// 签名请求头名称
+    pub signature_header: String,
+    /// 5xx 或连接错误时的最大重试次数
+    pub max_retries: u32,
+    /// 首次重试前的等待时间，之后逐次翻倍
+    pub initial_backoff: Duration,
+    /// 连接与读写超时
+    pub timeout: Duration,
+    /// 等待后台投递的告警数上限，队列已满时发送失败
+    pub queue_capacity: usize,
+    /// 释放最后一个克隆时等待投递的最长时间，超时后不再重试，剩余告警计为失败
+    pub shutdown_timeout: Duration,
+}
+
+impl WebhookConfig {
+    /// 使用默认参数创建配置
+    /// Create a configuration with default settings
+    pub fn new(name: impl Into<String>, url: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            url: url.into(),
+            secret: None,
+            signature_header: "X-Webhook-Signature".to_string(),
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(200),
+            timeout: Duration::from_secs(5),
+            queue_capacity: 256,
+            shutdown_timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+/// Webhook 通知渠道，以 JSON POST 告警
+/// Webhook notification channel that POSTs alerts as JSON
+///
+/// 配置密钥时请求头携带 `sha256=<hex>` 形式的 HMAC-SHA256 签名，签名内容为请求体。
+/// `send_notification` 只把告警放入队列，由后台线程发送和重试，调用方不会等待退避；
+/// 最后一个克隆释放时最多等待 `shutdown_timeout` 加一次请求超时，之后剩余告警不再投递、计为失败。
+/// With a secret configured, the signature header carries `sha256=<hex>`, the HMAC-SHA256 of the body.
+/// `send_notification` only queues the alert; a background thread sends and retries it, so callers never
+/// wait out the backoff. Dropping the last clone waits at most `shutdown_timeout` plus one request timeout;
+/// alerts still queued after that are not delivered and count as failed.
+#[derive(Debug, Clone)]
+pub struct WebhookChannel {
+    /// 渠道配置
+    pub config: WebhookConfig,
+    /// 后台投递线程，克隆共用同一个队列
+    worker: Arc<WebhookWorker>,
+}
+
+/// Webhook 后台投递线程及其队列
+#[derive(Debug)]
+struct WebhookWorker {
+    sender: Mutex<Option<std::sync::mpsc::SyncSender<String>>>,
+    handle: Mutex<Option<std::thread::JoinHandle<()>>>,
+    delivered: Arc<AtomicU64>,
+    failed: Arc<AtomicU64>,
+    shutdown: Arc<WebhookShutdown>,
+    shutdown_timeout: Duration,
+}
+
+impl Drop for WebhookWorker {
+    fn drop(&mut self) {
+        // 关闭队列后投递线程在截止时间前继续投递，之后放弃剩余告警并退出
+        self.shutdown.begin(self.shutdown_timeout);
+        drop(self.sender.get_mut().unwrap_or_else(std::sync::PoisonError::into_inner).take());
+        if let Some(handle) = self.handle.get_mut().unwrap_or_else(std::sync::PoisonError::into_inner).take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Webhook 投递线程的关闭信号，记录停止投递的截止时间
+#[derive(Debug, Default)]
+struct WebhookShutdown {
+    deadline: Mutex<Option<Instant>>,
+    signal: std::sync::Condvar,
+}
+
+impl WebhookShutdown {
+    /// 设置截止时间并唤醒正在退避的投递线程
+    fn begin(&self, timeout: Duration) {
+        *self.deadline.lock().unwrap_or_else(std::sync::PoisonError::into_inner) = Some(Instant::now() + timeout);
+        self.signal.notify_all();
+    }
+
+    /// 截止时间是否已过
+    fn expired(&self) -> bool {
+        self.deadline.lock().unwrap_or_else(std::sync::PoisonError::into_inner)
+            .is_some_and(|deadline| Instant::now() >= deadline)
+    }
+
+    /// 退避等待 `backoff`；关闭后等待结束时间会超过截止时间时立即返回 false
+    fn wait(&self, backoff: Duration) -> bool {
+        let until = Instant::now() + backoff;
+        let mut deadline = self.deadline.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+        loop {
+            if deadline.is_some_and(|deadline| until > deadline) {
+                return false;
+            }
+            let now = Instant::now();
+            if now >= until {
+                return true;
+            }
+            deadline = self.signal.wait_timeout(deadline, until - now)
+                .unwrap_or_else(std::sync::PoisonError::into_inner)
+                .0;
+        }
+    }
+}
+
+impl WebhookChannel {
+    /// 创建 Webhook 通知渠道并启动后台投递线程
+    /// Create webhook notification channel and start its delivery thread
+    pub fn new(config: WebhookConfig) -> Self {
+        let (sender, receiver) = std::sync::mpsc::sync_channel::<String>(config.queue_capacity.max(1));
+        let (delivered, failed) = (Arc::new(AtomicU64::new(0)), Arc::new(AtomicU64::new(0)));
+        let shutdown = Arc::new(WebhookShutdown::default());
+        let handle = {
+            let (config, delivered, failed) = (config.clone(), Arc::clone(&delivered), Arc::clone(&failed));
+            let shutdown = Arc::clone(&shutdown);
+            std::thread::spawn(move || {
+                let mut abandoned = 0;
+                for body in receiver {
+                    if shutdown.expired() {
+                        abandoned += 1;
+                        failed.fetch_add(1, Ordering::Relaxed);
+                        continue;
+                    }
+                    match Self::deliver(&config, &body, &shutdown) {
+                        Ok(()) => {
+                            delivered.fetch_add(1, Ordering::Relaxed);
+                        }
+                        Err(e) => {
+                            failed.fetch_add(1, Ordering::Relaxed);
+                            log::warn!("Webhook {} 投递失败: {}", config.name, e);
+                        }
+                    }
+                }
+                if abandoned > 0 {
+                    log::warn!("Webhook {} 关闭超时，{} 条告警未投递", config.name, abandoned);
+                }
+            })
+        };
+        let worker = WebhookWorker {
+            sender: Mutex::new(Some(sender)),
+            handle: Mutex::new(Some(handle)),
+            delivered,
+            failed,
+            shutdown,
+            shutdown_timeout: config.shutdown_timeout,
+        };
+        Self { config, worker: Arc::new(worker) }
+    }
+
+    /// 已投递成功的告警数
+    /// Number of alerts delivered successfully
+    pub fn delivered_count(&self) -> u64 {
+        self.worker.delivered.load(Ordering::Relaxed)
+    }
+
+    /// 重试耗尽或被拒绝而放弃的告警数
+    /// Number of alerts given up after exhausting retries or being rejected
+    pub fn failed_count(&self) -> u64 {
+        self.worker.failed.load(Ordering::Relaxed)
+    }
+
+    /// 告警的 JSON 负载
+    /// JSON payload of an alert
+    pub fn payload(alert: &Alert) -> serde_json::Value {
+        serde_json::json!({
+            "alert_id": alert.id,
+            "rule_id": alert.rule_id,
+            "rule_name": alert.rule_name,
+            "severity": alert.severity,
+            "state": alert.state,
+            "labels": alert.labels,
+            "annotations": alert.annotations,
+            "description": alert.description,
+            "start_time": alert.start_time,
+            "end_time": alert.end_time,
+        })
+    }
+
+    /// 发送一次请求，返回 HTTP 状态码
+    /// Send a single request and return the HTTP status code
+    fn post_once(config: &WebhookConfig, body: &str) -> Result<u16, NotificationError> {
+        use std::io::{Read, Write};
+
+        let (authority, address, path) = split_http_endpoint(&config.url)
+            .map_err(NotificationError::ConfigurationError)?;
+        let socket = std::net::ToSocketAddrs::to_socket_addrs(&address)
+            .map_err(|e| NotificationError::ConnectionError(format!("{}: {}", address, e)))?
+            .next()
+            .ok_or_else(|| NotificationError::ConnectionError(format!("无法解析地址: {}", address)))?;
+        let mut stream = std::net::TcpStream::connect_timeout(&socket, config.timeout)
+            .map_err(|e| NotificationError::ConnectionError(format!("{}: {}", address, e)))?;
+        let io_error = |e: std::io::Error| NotificationError::ConnectionError(e.to_string());
+        stream.set_read_timeout(Some(config.timeout)).map_err(io_error)?;
+        stream.set_write_timeout(Some(config.timeout)).map_err(io_error)?;
+
+        let signature = config.secret.as_ref()
+            .map(|secret| format!("{}: sha256={}\r\n", config.signature_header, hmac_sha256_hex(secret.as_bytes(), body.as_bytes())))
+            .unwrap_or_default();
+        let request = format!(
+            "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n{}Connection: close\r\n\r\n{}",
+            path,
+            authority,
+            body.len(),
+            signature,
+            body,
+        );
+        stream.write_all(request.as_bytes()).map_err(io_error)?;
+
+        let mut response = Vec::new();
+        stream.read_to_end(&mut response).map_err(io_error)?;
+        String::from_utf8_lossy(&response).lines().next()
+            .and_then(|line| line.split_whitespace().nth(1))
+            .and_then(|code| code.parse::<u16>().ok())
+            .ok_or_else(|| NotificationError::SendError("无效的 HTTP 响应".to_string()))
+    }
+
+    /// 发送负载，5xx 与连接错误按指数退避重试；关闭后退避会越过截止时间时不再重试
+    /// Send the payload, retrying 5xx responses and connection errors with exponential backoff; once shutting
+    /// down, a retry whose backoff would pass the deadline is not attempted
+    fn deliver(config: &WebhookConfig, body: &str, shutdown: &WebhookShutdown) -> Result<(), NotificationError> {
+        let mut backoff = config.initial_backoff;
+        let mut attempt = 0;
+        loop {
+            let error = match Self::post_once(config, body) {
+                Ok(status) if (200..300).contains(&status) => return Ok(()),
+                Ok(status) if status < 500 => {
+                    return Err(NotificationError::SendError(format!("{} 返回状态码 {}", config.url, status)));
+                }
+                Ok(status) => NotificationError::SendError(format!("{} 返回状态码 {}", config.url, status)),
+                Err(e @ NotificationError::ConnectionError(_)) => e,
+                Err(e) => return Err(e),
+            };
+            if attempt >= config.max_retries {
+                return Err(error);
+            }
+            attempt += 1;
+            if !shutdown.wait(backoff) {
+                return Err(error);
+            }
+            backoff *= 2;
+        }
+    }
+}
+
+impl NotificationChannel for WebhookChannel {
+    fn send_notification(&self, alert: &Alert) -> Result<(), NotificationError> {
+        let sender = self.worker.sender.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+        let sender = sender.as_ref()
+            .ok_or_else(|| NotificationError::SendError(format!("{} 的投递线程已停止", self.config.name)))?;
+        sender.try_send(Self::payload(alert).to_string()).map_err(|e| match e {
+            std::sync::mpsc::TrySendError::Full(_) => {
+                NotificationError::SendError(format!("{} 的投递队列已满（{} 条）", self.config.name, self.config.queue_capacity))
+            }
+            std::sync::mpsc::TrySendError::Disconnected(_) => {
+                NotificationError::SendError(format!("{} 的投递线程已停止", self.config.name))
+            }
+        })
+    }
+
+    fn get_name(&self) -> String {
+        self.config.name.clone()
+    }
+
+    fn test_connection(&self) -> Result<(), NotificationError> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        let ping = Alert {
+            id: "ping".to_string(),
+            rule_id: "test_connection".to_string(),
+            rule_name: "test_connection".to_string(),
+            severity: AlertSeverity::Info,
+            state: AlertStateType::Active,
+            start_time: now,
+            end_time: Some(now),
+            labels: HashMap::new(),
+            annotations: HashMap::new(),
+            description: "通知渠道连通性测试".to_string(),
+        };
+        // 连通性测试同步发送，直接返回结果
+        Self::deliver(&self.config, &Self::payload(&ping).to_string(), &WebhookShutdown::default())
+    }
+}
+
+/// 计算 HMAC-SHA256 并以十六进制返回
+/// Compute HMAC-SHA256 and return it as hex
+fn hmac_sha256_hex(key: &[u8], message: &[u8]) -> String {
+    use sha2::{Digest, Sha256};
+
+    const BLOCK_SIZE: usize = 64;
+    let mut block = [0u8; BLOCK_SIZE];
+    if key.len() > BLOCK_SIZE {
+        block[..32].copy_from_slice(&Sha256::digest(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+
+    let pad = |byte: u8| block.iter().map(|b| b ^ byte).collect::<Vec<u8>>();
+    let inner = Sha256::new().chain_update(pad(0x36)).chain_update(message).finalize();
+    let outer = Sha256::new().chain_update(pad(0x5c)).chain_update(inner).finalize();
+    outer.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// 告警表达式
+/// Alert expression
+///
+/// 语法：`cpu_usage{host="a"} > 80 and rate(requests_total, 1m) >= 5 or avg_over(latency, 30s) > 0.5`，
+/// `and` 优先级高于 `or`，可使用括号。选择器匹配多个序列时，任一序列满足比较即视为匹配。
+/// Grammar example above; `and` binds tighter than `or` and parentheses are allowed.
+/// A comparison matches when any series selected by its operand satisfies it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AlertExpr {
+    /// 逻辑与
+    And(Box<AlertExpr>, Box<AlertExpr>),
+    /// 逻辑或
+    Or(Box<AlertExpr>, Box<AlertExpr>),
+    /// 比较
+    Compare(AlertOperand, CompareOp, f64),
+}
+
+/// 告警表达式操作数
+/// Alert expression operand
+#[derive(Debug, Clone, PartialEq)]
+pub enum AlertOperand {
+    /// 指标当前值
+    Instant(MetricSelector),
+    /// 时间窗口内的平均值
+    AvgOver(MetricSelector, Duration),
+    /// 计数器在时间窗口内每秒的增长率
+    Rate(MetricSelector, Duration),
+}
+
+/// 指标选择器
+/// Metric selector
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetricSelector {
+    /// 指标名称
+    pub name: String,
+    /// 标签匹配器：(标签名, 是否相等匹配, 标签值)
+    pub matchers: Vec<(String, bool, String)>,
+}
+
+/// 比较运算符
+/// Comparison operator
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompareOp {
+    /// 大于
+    Gt,
+    /// 大于等于
+    Ge,
+    /// 小于
+    Lt,
+    /// 小于等于
+    Le,
+    /// 等于
+    Eq,
+    /// 不等于
+    Ne,
+}
+
+impl CompareOp {
+    fn apply(self, left: f64, right: f64) -> bool {
+        match self {
+            CompareOp::Gt => left > right,
+            CompareOp::Ge => left >= right,
+            CompareOp::Lt => left < right,
+            CompareOp::Le => left <= right,
+            CompareOp::Eq => left == right,
+            CompareOp::Ne => left != right,
+        }
+    }
+}
+
+impl MetricSelector {
+    fn matches(&self, name: &str, labels: &HashMap<String, String>) -> bool {
+        self.name == name
+            && self.matchers.iter().all(|(label, equal, value)| {
+                let actual = labels.get(label).map(String::as_str).unwrap_or("");
+                (actual == value) == *equal
+            })
+    }
+}
+
+impl AlertOperand {
+    fn selector(&self) -> &MetricSelector {
+        match self {
+            AlertOperand::Instant(selector)
+            | AlertOperand::AvgOver(selector, _)
+            | AlertOperand::Rate(selector, _) => selector,
+        }
+    }
+
+    fn window(&self) -> Option<Duration> {
+        match self {
+            AlertOperand::Instant(_) => None,
+            AlertOperand::AvgOver(_, window) | AlertOperand::Rate(_, window) => Some(*window),
+        }
+    }
+}
+
+impl AlertExpr {
+    fn operands(&self) -> Vec<&AlertOperand> {
+        match self {
+            AlertExpr::And(left, right) | AlertExpr::Or(left, right) => {
+                let mut operands = left.operands();
+                operands.extend(right.operands());
+                operands
+            }
+            AlertExpr::Compare(operand, _, _) => vec![operand],
+        }
+    }
+}
+
+/// 单个序列的历史样本
+/// Sample history of a single series
+#[derive(Debug, Clone)]
+struct SampleHistory {
+    name: String,
+    labels: HashMap<String, String>,
+    /// (Unix 秒, 值)
+    samples: VecDeque<(u64, f64)>,
+}
+
+/// 告警表达式词法单元
+/// Alert expression token
+#[derive(Debug, Clone, PartialEq)]
+enum AlertToken {
+    Ident(String),
+    Number(String),
+    Str(String),
+    Op(CompareOp),
+    Assign,
+    LParen,
+    RParen,
+    LBrace,
+    RBrace,
+    Comma,
+}
+
+fn tokenize_alert_expression(input: &str) -> Result<Vec<AlertToken>, MonitoringError> {
+    let invalid = |message: String| MonitoringError::AlertError(format!("无效的告警表达式 `{}`: {}", input, message));
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        match c {
+            c if c.is_whitespace() => i += 1,
+            '(' => { tokens.push(AlertToken::LParen); i += 1; }
+            ')' => { tokens.push(AlertToken::RParen); i += 1; }
+            '{' => { tokens.push(AlertToken::LBrace); i += 1; }
+            '}' => { tokens.push(AlertToken::RBrace); i += 1; }
+            ',' => { tokens.push(AlertToken::Comma); i += 1; }
+            '>' | '<' | '=' | '!' => {
+                let (token, width) = match (c, next) {
+                    ('>', Some('=')) => (AlertToken::Op(CompareOp::Ge), 2),
+                    ('<', Some('=')) => (AlertToken::Op(CompareOp::Le), 2),
+                    ('=', Some('=')) => (AlertToken::Op(CompareOp::Eq), 2),
+                    ('!', Some('=')) => (AlertToken::Op(CompareOp::Ne), 2),
+                    ('>', _) => (AlertToken::Op(CompareOp::Gt), 1),
+                    ('<', _) => (AlertToken::Op(CompareOp::Lt), 1),
+                    ('=', _) => (AlertToken::Assign, 1),
+                    _ => return Err(invalid(format!("位置 {} 处的 `!` 之后应为 `=`", i))),
+                };
+                tokens.push(token);
+                i += width;
+            }
+            '"' => {
+                let mut value = String::new();
+                i += 1;
+                loop {
+                    match chars.get(i) {
+                        Some('"') => break,
+                        Some('\\') => {
+                            value.push(*chars.get(i + 1).ok_or_else(|| invalid("字符串未闭合".to_string()))?);
+                            i += 2;
+                        }
+                        Some(c) => {
+                            value.push(*c);
+                            i += 1;
+                        }
+                        None => return Err(invalid("字符串未闭合".to_string())),
+                    }
+                }
+                tokens.push(AlertToken::Str(value));
+                i += 1;
+            }
+            c if c.is_ascii_digit() || (c == '-' && next.is_some_and(|n| n.is_ascii_digit())) || c == '.' => {
+                let start = i;
+                i += 1;
+                while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '.') {
+                    i += 1;
+                }
+                tokens.push(AlertToken::Number(chars[start..i].iter().collect()));
+            }
+            c if c.is_ascii_alphabetic() || c == '_' || c == ':' => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_ascii_alphanumeric() || matches!(chars[i], '_' | ':' | '.')) {
+                    i += 1;
+                }
+                tokens.push(AlertToken::Ident(chars[start..i].iter().collect()));
+            }
+            other => return Err(invalid(format!("位置 {} 处的意外字符 `{}`", i, other))),
+        }
+    }
+    Ok(tokens)
+}
+
+/// 解析告警表达式
+/// Parse an alert expression
+pub fn parse_alert_expression(input: &str) -> Result<AlertExpr, MonitoringError> {
+    let tokens = tokenize_alert_expression(input)?;
+    let mut parser = AlertParser { input, tokens: &tokens, position: 0 };
+    let expr = parser.parse_or()?;
+    match parser.peek() {
+        None => Ok(expr),
+        Some(token) => Err(parser.error(format!("多余的内容 {:?}", token))),
+    }
+}
+
+/// 告警表达式递归下降解析器
+/// Recursive-descent parser for alert expressions
+struct AlertParser<'a> {
+    input: &'a str,
+    tokens: &'a [AlertToken],
+    position: usize,
+}
+
+impl AlertParser<'_> {
+    fn error(&self, message: String) -> MonitoringError {
+        MonitoringError::AlertError(format!("无效的告警表达式 `{}`: {}", self.input, message))
+    }
+
+    fn peek(&self) -> Option<&AlertToken> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<AlertToken> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn expect(&mut self, expected: AlertToken) -> Result<(), MonitoringError> {
+        match self.next() {
+            Some(token) if token == expected => Ok(()),
+            other => Err(self.error(format!("期望 {:?}，实际为 {:?}", expected, other))),
+        }
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(AlertToken::Ident(ident)) if ident == keyword)
+    }
+
+    fn parse_or(&mut self) -> Result<AlertExpr, MonitoringError> {
+        let mut expr = self.parse_and()?;
+        while self.is_keyword("or") {
+            self.position += 1;
+            expr = AlertExpr::Or(Box::new(expr), Box::new(self.parse_and()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_and(&mut self) -> Result<AlertExpr, MonitoringError> {
+        let mut expr = self.parse_comparison()?;
+        while self.is_keyword("and") {
+            self.position += 1;
+            expr = AlertExpr::And(Box::new(expr), Box::new(self.parse_comparison()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_comparison(&mut self) -> Result<AlertExpr, MonitoringError> {
+        if self.peek() == Some(&AlertToken::LParen) {
+            self.position += 1;
+            let expr = self.parse_or()?;
+            self.expect(AlertToken::RParen)?;
+            return Ok(expr);
+        }
+
+        let operand = self.parse_operand()?;
+        let op = match self.next() {
+            Some(AlertToken::Op(op)) => op,
+            other => return Err(self.error(format!("期望比较运算符，实际为 {:?}", other))),
+        };
+        let threshold = match self.next() {
+            Some(AlertToken::Number(raw)) => raw.parse::<f64>()
+                .map_err(|_| self.error(format!("无效的数值 `{}`", raw)))?,
+            other => return Err(self.error(format!("期望数值，实际为 {:?}", other))),
+        };
+        Ok(AlertExpr::Compare(operand, op, threshold))
+    }
+
+    fn parse_operand(&mut self) -> Result<AlertOperand, MonitoringError> {
+        let name = match self.next() {
+            Some(AlertToken::Ident(name)) if name != "and" && name != "or" => name,
+            other => return Err(self.error(format!("期望指标名称，实际为 {:?}", other))),
+        };
+
+        if self.peek() != Some(&AlertToken::LParen) {
+            return self.parse_selector(name).map(AlertOperand::Instant);
+        }
+        if name != "avg_over" && name != "rate" {
+            return Err(self.error(format!("未知的函数 `{}`", name)));
+        }
+        self.position += 1;
+        let selector = match self.next() {
+            Some(AlertToken::Ident(metric)) => self.parse_selector(metric)?,
+            other => return Err(self.error(format!("期望指标名称，实际为 {:?}", other))),
+        };
+        self.expect(AlertToken::Comma)?;
+        let window = match self.next() {
+            Some(AlertToken::Number(raw)) => parse_alert_duration(&raw)
+                .ok_or_else(|| self.error(format!("无效的时间窗口 `{}`", raw)))?,
+            other => return Err(self.error(format!("期望时间窗口，实际为 {:?}", other))),
+        };
+        self.expect(AlertToken::RParen)?;
+
+        Ok(if name == "rate" {
+            AlertOperand::Rate(selector, window)
+        } else {
+            AlertOperand::AvgOver(selector, window)
+        })
+    }
+
+    fn parse_selector(&mut self, name: String) -> Result<MetricSelector, MonitoringError> {
+        let mut matchers = Vec::new();
+        if self.peek() == Some(&AlertToken::LBrace) {
+            self.position += 1;
+            while self.peek() != Some(&AlertToken::RBrace) {
+                let label = match self.next() {
+                    Some(AlertToken::Ident(label)) => label,
+                    other => return Err(self.error(format!("期望标签名，实际为 {:?}", other))),
+                };
+                let equal = match self.next() {
+                    Some(AlertToken::Assign) => true,
+                    Some(AlertToken::Op(CompareOp::Ne)) => false,
+                    other => return Err(self.error(format!("期望 `=` 或 `!=`，实际为 {:?}", other))),
+                };
+                let value = match self.next() {
+                    Some(AlertToken::Str(value)) => value,
+                    other => return Err(self.error(format!("期望带引号的标签值，实际为 {:?}", other))),
+                };
+                matchers.push((label, equal, value));
+                if self.peek() == Some(&AlertToken::Comma) {
+                    self.position += 1;
+                }
+            }
+            self.expect(AlertToken::RBrace)?;
+        }
+        Ok(MetricSelector { name, matchers })
+    }
+}
+
+/// 解析 `500ms`、`30s`、`5m`、`1h` 形式的时间窗口，纯数字按秒计
+/// Parse windows such as `500ms`, `30s`, `5m` or `1h`; bare numbers are seconds
+fn parse_alert_duration(raw: &str) -> Option<Duration> {
+    let split = raw.find(|c: char| c.is_ascii_alphabetic()).unwrap_or(raw.len());
+    let (value, unit) = raw.split_at(split);
+    let value: f64 = value.parse().ok()?;
+    let seconds = match unit {
+        "ms" => value / 1000.0,
+        "" | "s" => value,
+        "m" => value * 60.0,
+        "h" => value * 3600.0,
+        _ => return None,
+    };
+    (seconds > 0.0 && seconds.is_finite()).then(|| Duration::from_secs_f64(seconds))
+}
+
+fn metric_value_as_f64(value: &MetricValue) -> Option<f64> {
+    match value {
+        MetricValue::Integer(value) => Some(*value as f64),
+        MetricValue::Float(value) => Some(*value),
+        MetricValue::Distribution(_) => None,
+    }
+}
+
+/// 计算操作数在当前时刻的所有序列取值
+/// Compute the operand's value for every matching series at the current time
+fn evaluate_alert_operand(
+    operand: &AlertOperand,
+    metrics: &HashMap<String, Metric>,
+    history: &HashMap<String, SampleHistory>,
+    now: u64,
+) -> Vec<f64> {
+    match operand {
+        AlertOperand::Instant(selector) => metrics.values()
+            .filter(|metric| selector.matches(&metric.name, &metric.labels))
+            .filter_map(|metric| metric_value_as_f64(&metric.value))
+            .collect(),
+        AlertOperand::AvgOver(selector, window) | AlertOperand::Rate(selector, window) => {
+            let since = now.saturating_sub(window.as_secs());
+            history.values()
+                .filter(|series| selector.matches(&series.name, &series.labels))
+                .filter_map(|series| {
+                    let samples: Vec<(u64, f64)> = series.samples.iter()
+                        .copied()
+                        .filter(|(timestamp, _)| *timestamp >= since)
+                        .collect();
+                    if let AlertOperand::AvgOver(..) = operand {
+                        return (!samples.is_empty())
+                            .then(|| samples.iter().map(|(_, value)| value).sum::<f64>() / samples.len() as f64);
+                    }
+                    let (first, last) = (samples.first()?, samples.last()?);
+                    if last.0 <= first.0 {
+                        return None;
+                    }
+                    // 计数器下降视为重置，重置后的值整体计入增长
+                    let increase: f64 = samples.windows(2)
+                        .map(|pair| if pair[1].1 >= pair[0].1 { pair[1].1 - pair[0].1 } else { pair[1].1 })
+                        .sum();
+                    Some(increase / (last.0 - first.0) as f64)
+                })
+                .collect()
+        }
+    }
+}
+
+fn alert_expression_matches(
+    expr: &AlertExpr,
+    metrics: &HashMap<String, Metric>,
+    history: &HashMap<String, SampleHistory>,
+    now: u64,
+) -> bool {
+    match expr {
+        AlertExpr::And(left, right) => {
+            alert_expression_matches(left, metrics, history, now) && alert_expression_matches(right, metrics, history, now)
+        }
+        AlertExpr::Or(left, right) => {
+            alert_expression_matches(left, metrics, history, now) || alert_expression_matches(right, metrics, history, now)
+        }
+        AlertExpr::Compare(operand, op, threshold) => evaluate_alert_operand(operand, metrics, history, now)
+            .into_iter()
+            .any(|value| op.apply(value, *threshold)),
+    }
+}
+
+/// 评估全部告警规则并推进状态机：待处理 → 活跃 → 已解决
+/// Evaluate every alert rule and advance the pending → active → resolved state machine
+fn evaluate_alert_rules(
+    rules: &Mutex<Vec<AlertRule>>,
+    compiled: &Mutex<HashMap<String, AlertExpr>>,
+    history: &Mutex<HashMap<String, SampleHistory>>,
+    states: &Mutex<HashMap<String, AlertState>>,
+    collector: &MetricsCollector,
+    max_alerts: usize,
+    dropped: &AtomicU64,
+) -> Result<Vec<AlertState>, MonitoringError> {
+    fn poisoned<T>(_: T) -> MonitoringError {
+        MonitoringError::AlertError("告警状态锁已损坏".to_string())
+    }
+    let now = collector.clock.now_secs();
+    let mut rules = rules.lock().map_err(poisoned)?.clone();
+    // 高严重程度的规则先评估，容量不足时优先丢弃低严重程度的新告警
+    rules.sort_by_key(|rule| std::cmp::Reverse(rule.severity));
+    let mut compiled = compiled.lock().map_err(poisoned)?;
+
+    // 直接写入 `rules` 的规则在这里补充解析
+    for rule in &rules {
+        if !compiled.contains_key(&rule.id) {
+            match parse_alert_expression(&rule.expression) {
+                Ok(expr) => {
+                    compiled.insert(rule.id.clone(), expr);
+                }
+                Err(e) => log::warn!("跳过告警规则 {}: {}", rule.id, e),
+            }
+        }
+    }
+
+    let metrics = collector.metrics.lock()
+        .map_err(|_| MonitoringError::MetricsError("指标存储锁已损坏".to_string()))?
+        .clone();
+
+    // 记录聚合函数引用的序列样本，并丢弃超出最大窗口的旧样本
+    let mut history = history.lock().map_err(poisoned)?;
+    let windowed: Vec<&AlertOperand> = compiled.values()
+        .flat_map(|expr| expr.operands())
+        .filter(|operand| operand.window().is_some())
+        .collect();
+    let max_window = windowed.iter().filter_map(|operand| operand.window()).max().unwrap_or_default();
+    for (key, metric) in &metrics {
+        if !windowed.iter().any(|operand| operand.selector().matches(&metric.name, &metric.labels)) {
+            continue;
+        }
+        if let Some(value) = metric_value_as_f64(&metric.value) {
+            let series = history.entry(key.clone()).or_insert_with(|| SampleHistory {
+                name: metric.name.clone(),
+                labels: metric.labels.clone(),
+                samples: VecDeque::new(),
+            });
+            if series.samples.back().is_some_and(|(timestamp, _)| *timestamp == now) {
+                series.samples.pop_back();
+            }
+            series.samples.push_back((now, value));
+        }
+    }
+    let cutoff = now.saturating_sub(max_window.as_secs());
+    history.retain(|_, series| {
+        while series.samples.front().is_some_and(|(timestamp, _)| *timestamp < cutoff) {
+            series.samples.pop_front();
+        }
+        !series.samples.is_empty()
+    });
+
+    let mut states = states.lock().map_err(poisoned)?;
+    let mut changed = Vec::new();
+    for rule in &rules {
+        let Some(expr) = compiled.get(&rule.id) else {
+            continue;
+        };
+        let firing = alert_expression_matches(expr, &metrics, &history, now);
+        let current = states.get(&rule.id).map(|state| state.state.clone());
+
+        let next = match (current, firing) {
+            (None | Some(AlertStateType::Resolved), true)
+                if states.values().filter(|state| !matches!(state.state, AlertStateType::Resolved)).count() >= max_alerts =>
+            {
+                dropped.fetch_add(1, Ordering::Relaxed);
+                log::warn!("未结束的告警已达上限 {}，丢弃告警 {}", max_alerts, rule.id);
+                None
+            }
+            (None | Some(AlertStateType::Resolved), true) => Some(AlertState {
+                alert_id: rule.id.clone(),
+                state: AlertStateType::Pending,
+                start_time: now,
+                end_time: None,
+                last_evaluation_time: now,
+                evaluation_count: 0,
+                labels: rule.labels.clone(),
+                suppressed: false,
+            }),
+            // 待处理期间条件消失，直接撤销
+            (Some(AlertStateType::Pending), false) => {
+                states.remove(&rule.id);
+                None
+            }
+            (Some(AlertStateType::Active), false) => states.get(&rule.id).cloned().map(|state| AlertState {
+                state: AlertStateType::Resolved,
+                end_time: Some(now),
+                ..state
+            }),
+            _ => None,
+        };
+        if let Some(state) = next {
+            states.insert(rule.id.clone(), state.clone());
+            changed.push(state);
+        }
+
+        if let Some(state) = states.get_mut(&rule.id) {
+            if firing && matches!(state.state, AlertStateType::Pending)
+                && now.saturating_sub(state.start_time) >= rule.duration.as_secs()
+            {
+                state.state = AlertStateType::Active;
+                changed.retain(|previous: &AlertState| previous.alert_id != rule.id);
+                changed.push(state.clone());
+            }
+            state.last_evaluation_time = now;
+            state.evaluation_count += 1;
+        }
+    }
+
+    Ok(changed)
+}
+
+impl PerformanceAnalyzer {
+    /// 创建新的性能分析器
+    /// Create new performance analyzer
+    pub fn new(config: PerformanceConfig) -> Self {
+        Self {
+            performance_metrics: Arc::new(Mutex::new(HashMap::new())),
+            analyzer: Box::new(StatisticalAnalyzer::with_config(&config)),
+            config,
+        }
+    }
+}
+
+impl HealthChecker {
+    /// 创建新的健康检查器
+    /// Create new health checker
+    pub fn new(config: HealthCheckConfig) -> Self {
+        Self {
+            config,
+            checkers: Arc::new(Mutex::new(Vec::new())),
+            health_status: Arc::new(Mutex::new(HealthStatus::Unknown)),
+            last_report: Arc::new(Mutex::new(None)),
+            pending_status: Arc::new(Mutex::new((HealthStatus::Unknown, 0))),
+            listeners: Arc::new(Mutex::new(Vec::new())),
+            runner: Mutex::new(None),
+        }
+    }
+
+    /// 注册状态变化监听器，汇总状态每次翻转时调用
+    /// Register a listener called every time the aggregate status flips
+    pub fn on_status_change(&self, listener: impl Fn(&HealthStatusChanged) + Send + Sync + 'static) {
+        if let Ok(mut listeners) = self.listeners.lock() {
+            listeners.push(Box::new(listener));
+        }
+    }
+
+    fn runner(&self) -> HealthRunner {
+        HealthRunner {
+            config: self.config.clone(),
+            checkers: Arc::clone(&self.checkers),
+            health_status: Arc::clone(&self.health_status),
+            last_report: Arc::clone(&self.last_report),
+            pending_status: Arc::clone(&self.pending_status),
+            listeners: Arc::clone(&self.listeners),
+        }
+    }
+
+    /// 注册健康检查
+    /// Register a health check
+    pub fn register(&self, check: Box<dyn HealthCheck>) {
+        if let Ok(mut checkers) = self.checkers.lock() {
+            checkers.push(Arc::from(check));
+        }
+    }
+
+    /// 当前汇总健康状态
+    /// Current aggregate health status
+    pub fn status(&self) -> HealthStatus {
+        self.health_status.lock().map(|status| *status).unwrap_or(HealthStatus::Unknown)
+    }
+
+    /// 最近一次检查报告
+    /// Most recent health report
+    pub fn report(&self) -> Option<HealthReport> {
+        self.last_report.lock().ok()?.clone()
+    }
+
+    /// 执行全部检查并更新汇总状态
+    /// Run every check and update the aggregate status
+    ///
+    /// 每次尝试限时 `timeout`，出错、超时或不健康时最多重试 `retry_count` 次。
+    /// 任一检查不健康则整体不健康，否则任一降级或通过比例低于 `health_threshold` 则整体降级。
+    /// 汇总状态需连续 `flap_threshold` 次得到相同结果才会翻转，翻转时通知监听器。
+    /// Each attempt is bounded by `timeout` and retried up to `retry_count` times on error, timeout or
+    /// unhealthy status. Any unhealthy check makes the aggregate unhealthy, otherwise any degraded check or a
+    /// passing fraction below `health_threshold` makes it degraded.
+    /// The aggregate only flips after `flap_threshold` identical consecutive results, notifying the listeners.
+    pub fn run_all(&self) -> HealthReport {
+        self.runner().run()
+    }
+
+    /// 启动后台线程，每隔 `check_interval` 执行一次全部检查；已在运行的线程先停止
+    /// Start a background thread running every check each `check_interval`; a running thread is stopped first
+    pub fn start(&self) {
+        self.stop();
+        let runner = self.runner();
+        let check_interval = self.config.check_interval.max(Duration::from_millis(1));
+        let (shutdown, stopped) = std::sync::mpsc::channel::<()>();
+
+        let handle = std::thread::spawn(move || loop {
+            runner.run();
+            match stopped.recv_timeout(check_interval) {
+                Err(std::sync::mpsc::RecvTimeoutError::Timeout) => continue,
+                _ => break,
+            }
+        });
+        if let Ok(mut runner) = self.runner.lock() {
+            *runner = Some(HealthLoop { shutdown, handle });
+        }
+    }
+
+    /// 停止后台检查线程并等待它退出
+    /// Stop the background thread and wait for it to exit
+    ///
+    /// 正在执行的一轮检查会先完成，照常更新状态并通知监听器；返回后状态不再变化，监听器也不再被调用。
+    /// A round already running completes first, updating the status and notifying listeners as usual;
+    /// once this returns the status no longer changes and no listener is called.
+    pub fn stop(&self) {
+        let running = self.runner.lock().ok().and_then(|mut runner| runner.take());
+        if let Some(running) = running {
+            running.shutdown();
+        }
+    }
+}
+
+impl Drop for HealthChecker {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// 一轮健康检查所需的共享状态
+/// Shared state needed for one round of health checks
+#[derive(Clone)]
+struct HealthRunner {
+    config: HealthCheckConfig,
+    checkers: Arc<Mutex<Vec<Arc<dyn HealthCheck>>>>,
+    health_status: Arc<Mutex<HealthStatus>>,
+    last_report: Arc<Mutex<Option<HealthReport>>>,
+    pending_status: Arc<Mutex<(HealthStatus, u32)>>,
+    listeners: Arc<Mutex<Vec<HealthStatusListener>>>,
+}
+
+impl HealthRunner {
+    /// 执行全部检查，防抖后更新汇总状态并保存报告
+    /// Run every check, update the debounced aggregate status and store the report
+    fn run(&self) -> HealthReport {
+        let started = Instant::now();
+        let checkers: Vec<Arc<dyn HealthCheck>> = self.checkers.lock().map(|checkers| checkers.clone()).unwrap_or_default();
+        let checks: Vec<HealthCheckResult> = checkers.iter()
+            .map(|check| run_health_check_with_retry(check, self.config.timeout, self.config.retry_count))
+            .collect();
+        let observed = aggregate_health_status(checks.iter().map(|result| result.status), self.config.health_threshold);
+
+        let change = match (self.health_status.lock(), self.pending_status.lock()) {
+            (Ok(status), Ok(mut pending)) if observed == *status => {
+                *pending = (observed, 0);
+                None
+            }
+            (Ok(mut status), Ok(mut pending)) => {
+                *pending = if pending.0 == observed { (observed, pending.1 + 1) } else { (observed, 1) };
+                if pending.1 < self.config.flap_threshold.max(1) {
+                    None
+                } else {
+                    let from = std::mem::replace(&mut *status, observed);
+                    *pending = (observed, 0);
+                    Some(HealthStatusChanged {
+                        from,
+                        to: observed,
+                        failing_checks: checks.iter()
+                            .filter(|result| matches!(result.status, HealthStatus::Unhealthy | HealthStatus::Degraded))
+                            .map(|result| result.name.clone())
+                            .collect(),
+                    })
+                }
+            }
+            _ => None,
+        };
+        if let Some(change) = &change {
+            log::info!("健康状态从 {:?} 变为 {:?}", change.from, change.to);
+            if let Ok(listeners) = self.listeners.lock() {
+                listeners.iter().for_each(|listener| listener(change));
+            }
+        }
+
+        let report = HealthReport {
+            status: self.health_status.lock().map(|status| *status).unwrap_or(observed),
+            checks,
+            timestamp: SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs(),
+            duration: started.elapsed(),
+        };
+        if let Ok(mut last_report) = self.last_report.lock() {
+            *last_report = Some(report.clone());
+        }
+        report
+    }
+}
+
+/// 汇总健康状态：任一不健康则不健康，任一降级则降级；
+/// 其余情况下通过比例低于 `health_threshold` 时降级，没有已知结果时为未知
+/// Aggregate statuses: any unhealthy wins, then any degraded; otherwise a passing fraction below
+/// `health_threshold` degrades, and the result is unknown when no status is known
+fn aggregate_health_status(statuses: impl IntoIterator<Item = HealthStatus>, health_threshold: f64) -> HealthStatus {
+    let statuses: Vec<HealthStatus> = statuses.into_iter().collect();
+    let passing = statuses.iter().filter(|status| **status == HealthStatus::Healthy).count();
+    if statuses.contains(&HealthStatus::Unhealthy) {
+        HealthStatus::Unhealthy
+    } else if statuses.contains(&HealthStatus::Degraded) {
+        HealthStatus::Degraded
+    } else if passing == 0 {
+        HealthStatus::Unknown
+    } else if (passing as f64) < health_threshold * statuses.len() as f64 {
+        HealthStatus::Degraded
+    } else {
+        HealthStatus::Healthy
+    }
+}
+
+/// 带超时和重试地执行单项检查
+/// Run a single check with a timeout and retries
+///
+/// 检查在独立线程中执行；超时后不再等待该线程，它会在检查返回后自行结束。
+/// The check runs on its own thread; after a timeout the thread is abandoned and exits once the check returns.
+fn run_health_check_with_retry(check: &Arc<dyn HealthCheck>, timeout: Duration, retry_count: u32) -> HealthCheckResult {
+    let name = check.get_name();
+    let mut attempts = 0;
+    loop {
+        attempts += 1;
+        let started = Instant::now();
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let worker = Arc::clone(check);
+        std::thread::spawn(move || {
+            let _ = sender.send(worker.check());
+        });
+        let outcome = receiver.recv_timeout(timeout).unwrap_or_else(|_| {
+            Err(HealthCheckError::TimeoutError(format!("{} 超过 {:?} 未返回", name, timeout)))
+        });
+
+        let mut result = outcome.unwrap_or_else(|e| HealthCheckResult {
+            name: name.clone(),
+            status: HealthStatus::Unhealthy,
+            response_time: started.elapsed(),
+            details: None,
+            error: Some(e.to_string()),
+            timestamp: SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs(),
+            attempts,
+        });
+        result.attempts = attempts;
+        if result.status != HealthStatus::Unhealthy || attempts > retry_count {
+            return result;
+        }
+        log::debug!("健康检查 {} 第 {} 次尝试失败，重试", name, attempts);
+    }
+}
+
+/// 构造检查结果
+/// Build a check result
+fn health_check_result(name: &str, status: HealthStatus, started: Instant, details: String) -> HealthCheckResult {
+    HealthCheckResult {
+        name: name.to_string(),
+        status,
+        response_time: started.elapsed(),
+        details: Some(details),
+        error: None,
+        timestamp: SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs(),
+        attempts: 1,
+    }
+}
+
+/// 运行时健康检查：在运行时中调用一个返回常量的探测函数
+/// Runtime health check: calls a probe function returning a constant inside the runtime
+pub struct RuntimeHealthCheck {
+    /// 被检查的运行时
+    pub runtime: Arc<Mutex<WebAssembly2Runtime>>,
+    /// 探测调用的时限，超过则视为降级
+    pub timeout: Duration,
+    /// 已加载的探测模块
+    probe_module: Mutex<Option<crate::types::ModuleId>>,
+}
+
+impl RuntimeHealthCheck {
+    /// 创建运行时健康检查
+    /// Create a runtime health check
+    pub fn new(runtime: Arc<Mutex<WebAssembly2Runtime>>, timeout: Duration) -> Self {
+        Self { runtime, timeout, probe_module: Mutex::new(None) }
+    }
+}
+
+impl HealthCheck for RuntimeHealthCheck {
+    fn check(&self) -> Result<HealthCheckResult, HealthCheckError> {
+        let started = Instant::now();
+        let mut runtime = self.runtime.lock()
+            .map_err(|_| HealthCheckError::CheckError("运行时锁已损坏".to_string()))?;
+        let mut probe_module = self.probe_module.lock()
+            .map_err(|_| HealthCheckError::CheckError("探测模块锁已损坏".to_string()))?;
+        let module_id = match probe_module.as_ref() {
+            Some(module_id) if runtime.modules.contains_key(module_id) => module_id.clone(),
+            _ => {
+                let mut module = WebAssembly2Module::new("__health_probe__".to_string());
+                let mut function = WebAssembly2Function::new(0, "probe".to_string(), Vec::new(), vec![ValueType::I32]);
+                function.body = vec![WebAssembly2Instruction::I32Const(1)];
+                module.functions.push(function);
+                let module_id = runtime.load_module(module)
+                    .map_err(|e| HealthCheckError::CheckError(format!("加载探测模块失败: {}", e)))?;
+                *probe_module = Some(module_id.clone());
+                module_id
+            }
+        };
+
+        let previous_deadline = runtime.execution_deadline.replace(Instant::now() + self.timeout);
+        let outcome = runtime.execute_function(&module_id, 0, Vec::new());
+        runtime.execution_deadline = previous_deadline;
+        let elapsed = started.elapsed();
+        match outcome {
+            Ok(values) if values == [Value::I32(1)] && elapsed <= self.timeout => Ok(health_check_result(
+                &self.get_name(), HealthStatus::Healthy, started, format!("探测调用耗时 {:?}", elapsed),
+            )),
+            Ok(values) if values == [Value::I32(1)] => Ok(health_check_result(
+                &self.get_name(), HealthStatus::Degraded, started, format!("探测调用耗时 {:?}，超过 {:?}", elapsed, self.timeout),
+            )),
+            Ok(values) => Err(HealthCheckError::CheckError(format!("探测调用返回了意外结果: {:?}", values))),
+            Err(e) => Err(HealthCheckError::CheckError(format!("探测调用失败: {}", e))),
+        }
+    }
+
+    fn get_name(&self) -> String {
+        "runtime".to_string()
+    }
+
+    fn get_description(&self) -> String {
+        "WebAssembly 运行时能否在时限内完成一次简单调用".to_string()
+    }
+}
+
+/// 内存健康检查：比较进程常驻内存与上限
+/// Memory health check: compares the process resident set size with a limit
+#[derive(Debug, Clone)]
+pub struct MemoryHealthCheck {
+    /// 常驻内存上限（字节），超过则不健康
+    pub limit_bytes: u64,
+    /// 达到上限的该比例时视为降级
+    pub degraded_ratio: f64,
+}
+
+impl MemoryHealthCheck {
+    /// 创建内存健康检查，达到上限 90% 时降级
+    /// Create a memory health check that degrades at 90% of the limit
+    pub fn new(limit_bytes: u64) -> Self {
+        Self { limit_bytes, degraded_ratio: 0.9 }
+    }
+}
+
+/// 读取当前进程的常驻内存（字节）；不支持的平台返回 None
+/// Read the resident set size of the current process in bytes; None on unsupported platforms
+pub fn process_rss_bytes() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+    let kilobytes: u64 = line.trim_start_matches("VmRSS:").trim().trim_end_matches("kB").trim().parse().ok()?;
+    Some(kilobytes * 1024)
+}
+
+impl HealthCheck for MemoryHealthCheck {
+    fn check(&self) -> Result<HealthCheckResult, HealthCheckError> {
+        let started = Instant::now();
+        let Some(rss) = process_rss_bytes() else {
+            return Ok(health_check_result(&self.get_name(), HealthStatus::Unknown, started, "无法读取进程内存用量".to_string()));
+        };
+        let status = if rss > self.limit_bytes {
+            HealthStatus::Unhealthy
+        } else if rss as f64 >= self.limit_bytes as f64 * self.degraded_ratio {
+            HealthStatus::Degraded
+        } else {
+            HealthStatus::Healthy
+        };
+        Ok(health_check_result(&self.get_name(), status, started, format!("常驻内存 {} / {} 字节", rss, self.limit_bytes)))
+    }
+
+    fn get_name(&self) -> String {
+        "memory".to_string()
+    }
+
+    fn get_description(&self) -> String {
+        "进程常驻内存是否低于上限".to_string()
+    }
+}
+
+/// 指标新鲜度检查：收集器在最近一个间隔内是否有写入
+/// Metrics freshness check: whether the collector was written to within the last interval
+#[derive(Debug, Clone)]
+pub struct MetricsFreshnessCheck {
+    /// 被检查的收集器
+    pub collector: MetricsCollector,
+    /// 允许的最大数据年龄
+    pub max_age: Duration,
+}
+
+impl MetricsFreshnessCheck {
+    /// 创建新鲜度检查，最大年龄为收集器的收集间隔
+    /// Create a freshness check whose maximum age is the collector's collection interval
+    pub fn new(collector: MetricsCollector) -> Self {
+        let max_age = collector.collection_interval;
+        Self { collector, max_age }
+    }
+}
+
+impl HealthCheck for MetricsFreshnessCheck {
+    fn check(&self) -> Result<HealthCheckResult, HealthCheckError> {
+        let started = Instant::now();
+        let now = self.collector.clock.now_secs();
+        let result = match self.collector.last_update_secs() {
+            None => health_check_result(&self.get_name(), HealthStatus::Degraded, started, "尚未收集到任何指标".to_string()),
+            Some(updated) => {
+                let age = now.saturating_sub(updated);
+                let status = if age <= self.max_age.as_secs() { HealthStatus::Healthy } else { HealthStatus::Unhealthy };
+                health_check_result(&self.get_name(), status, started, format!("最近一次更新在 {} 秒前", age))
+            }
+        };
+        Ok(result)
+    }
+
+    fn get_name(&self) -> String {
+        "metrics_freshness".to_string()
+    }
+
+    fn get_description(&self) -> String {
+        "指标收集器是否在最近一个间隔内更新".to_string()
+    }
+}
+
+/// 默认阈值：指标名称与阈值
+/// Default thresholds: metric name and threshold
+const DEFAULT_PERFORMANCE_THRESHOLDS: [(&str, f64); 4] = [
+    ("cpu_usage", 80.0),
+    ("memory_usage", 85.0),
+    ("network_latency_ms", 200.0),
+    ("disk_usage", 90.0),
+];
+
+/// 统计分析器
+/// Statistical Analyzer
+///
+/// 将每条指标序列与阈值比较：在 `window_size` 内持续超过阈值的序列被识别为瓶颈。
+/// Compares every metric series with its threshold; series above it for at least `window_size` are bottlenecks.
+#[derive(Debug)]
+pub struct StatisticalAnalyzer {
+    /// 按指标名称的阈值
+    pub thresholds: HashMap<String, f64>,
+    /// 持续超限窗口，也是趋势拟合的窗口
+    pub window_size: Duration,
+    /// 拟合趋势所需的最少样本数
+    pub min_trend_samples: usize,
+    /// 变化率低于该值（每小时百分比）视为稳定
+    pub stable_change_rate: f64,
+    /// 需要计算相关性的序列对；为空时计算全部序列对
+    pub correlation_pairs: Vec<(String, String)>,
+    /// 未指定序列对时，序列数超过该值则跳过相关性分析
+    pub max_correlation_series: usize,
+    /// 报告相关性所需的最小 |r|
+    pub correlation_threshold: f64,
+    /// 对齐样本所用的时间桶
+    pub correlation_bucket: Duration,
+    /// 计算相关性所需的最少重叠时间桶数
+    pub min_correlation_samples: usize,
+}
+
+impl Default for StatisticalAnalyzer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StatisticalAnalyzer {
+    /// 创建使用默认阈值的统计分析器
+    /// Create new statistical analyzer with the default thresholds
+    pub fn new() -> Self {
+        Self {
+            thresholds: DEFAULT_PERFORMANCE_THRESHOLDS.iter()
+                .map(|(name, threshold)| (name.to_string(), *threshold))
+                .collect(),
+            window_size: Duration::from_secs(300),
+            min_trend_samples: 5,
+            stable_change_rate: 5.0,
+            correlation_pairs: Vec::new(),
+            max_correlation_series: 20,
+            correlation_threshold: 0.7,
+            correlation_bucket: Duration::from_secs(60),
+            min_correlation_samples: 5,
+        }
+    }
+
+    /// 在默认阈值上叠加配置中的阈值和窗口
+    /// Overlay the configured thresholds and window on the defaults
+    pub fn with_config(config: &PerformanceConfig) -> Self {
+        let mut analyzer = Self::new();
+        analyzer.thresholds.extend(config.thresholds.clone());
+        analyzer.window_size = config.window_size;
+        analyzer
+    }
+
+    /// 计算配置的序列对（或全部序列对）的相关性，只保留 |r| 不低于阈值的结果
+    /// Correlate the configured pairs (or every pair), keeping results whose |r| reaches the threshold
+    fn correlations(&self, series: &HashMap<String, Vec<&PerformanceMetric>>) -> Vec<Correlation> {
+        let pairs: Vec<(String, String)> = if self.correlation_pairs.is_empty() {
+            if series.len() > self.max_correlation_series {
+                log::debug!("序列数 {} 超过上限 {}，跳过相关性分析", series.len(), self.max_correlation_series);
+                return Vec::new();
+            }
+            let mut keys: Vec<&String> = series.keys().collect();
+            keys.sort();
+            keys.iter().enumerate()
+                .flat_map(|(i, first)| keys[i + 1..].iter().map(move |second| ((*first).clone(), (*second).clone())))
+                .collect()
+        } else {
+            self.correlation_pairs.clone()
+        };
+
+        let bucket = self.correlation_bucket.as_secs().max(1);
+        let bucketed = |key: &str| -> Option<BTreeMap<u64, f64>> {
+            let mut buckets: BTreeMap<u64, (f64, usize)> = BTreeMap::new();
+            for metric in series.get(key)? {
+                let entry = buckets.entry(metric.timestamp / bucket).or_default();
+                entry.0 += metric.value;
+                entry.1 += 1;
+            }
+            Some(buckets.into_iter().map(|(bucket, (sum, count))| (bucket, sum / count as f64)).collect())
+        };
+
+        let mut correlations = Vec::new();
+        for (metric1, metric2) in pairs {
+            let (Some(first), Some(second)) = (bucketed(&metric1), bucketed(&metric2)) else {
+                continue;
+            };
+            let aligned: Vec<(f64, f64)> = first.iter()
+                .filter_map(|(bucket, x)| second.get(bucket).map(|y| (*x, *y)))
+                .collect();
+            if aligned.len() < self.min_correlation_samples.max(3) {
+                continue;
+            }
+            let Some(r) = pearson_correlation(&aligned) else {
+                continue;
+            };
+            if r.abs() < self.correlation_threshold {
+                continue;
+            }
+            let interpretation = correlation_interpretation(&metric1, &metric2, r);
+            correlations.push(Correlation {
+                metric1,
+                metric2,
+                correlation_coefficient: r,
+                significance: correlation_significance(r, aligned.len()),
+                interpretation,
+            });
+        }
+        correlations
+    }
+}
+
+/// 皮尔逊相关系数；任一序列方差为零时返回 None
+/// Pearson correlation coefficient; None when either series has zero variance
+fn pearson_correlation(points: &[(f64, f64)]) -> Option<f64> {
+    let n = points.len() as f64;
+    let mean_x = points.iter().map(|(x, _)| x).sum::<f64>() / n;
+    let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / n;
+    let (mut sxx, mut syy, mut sxy) = (0.0, 0.0, 0.0);
+    for (x, y) in points {
+        sxx += (x - mean_x) * (x - mean_x);
+        syy += (y - mean_y) * (y - mean_y);
+        sxy += (x - mean_x) * (y - mean_y);
+    }
+    if sxx == 0.0 || syy == 0.0 {
+        return None;
+    }
+    Some((sxy / (sxx * syy).sqrt()).clamp(-1.0, 1.0))
+}
+
+/// 显著性：基于 Fisher z 变换的双侧检验，返回 1 - p
+/// Significance from a two-sided Fisher z test, returned as 1 - p
+fn correlation_significance(r: f64, samples: usize) -> f64 {
+    if r.abs() >= 1.0 {
+        return 1.0;
+    }
+    if samples <= 3 {
+        return 0.0;
+    }
+    let z = r.atanh() * ((samples - 3) as f64).sqrt();
+    1.0 - complementary_error_function(z.abs() / std::f64::consts::SQRT_2)
+}
+
+/// 互补误差函数的数值近似（Abramowitz–Stegun 7.1.26，误差小于 1.5e-7）
+/// Numerical approximation of the complementary error function (Abramowitz–Stegun 7.1.26, error below 1.5e-7)
+fn complementary_error_function(x: f64) -> f64 {
+    let t = 1.0 / (1.0 + 0.327_591_1 * x);
+    let polynomial = t * (0.254_829_592
+        + t * (-0.284_496_736 + t * (1.421_413_741 + t * (-1.453_152_027 + t * 1.061_405_429))));
+    polynomial * (-x * x).exp()
+}
+
+/// 对相关性的简要解读
+/// Short human-readable reading of a correlation
+fn correlation_interpretation(metric1: &str, metric2: &str, r: f64) -> String {
+    let strength = match r.abs() {
+        strength if strength >= 0.9 => "强",
+        strength if strength >= 0.7 => "较强",
+        _ => "中等",
+    };
+    if r > 0.0 {
+        format!("{} 与 {} {}正相关：一方升高时另一方通常也升高", metric1, metric2, strength)
+    } else {
+        format!("{} 与 {} {}负相关：一方升高时另一方通常降低", metric1, metric2, strength)
+    }
+}
+
+/// 按瓶颈类型计算得分时的权重
+/// Weight of a bottleneck type in the performance score
+fn bottleneck_weight(bottleneck_type: &BottleneckType) -> f64 {
+    match bottleneck_type {
+        BottleneckType::CPU | BottleneckType::Memory => 3.0,
+        BottleneckType::Network | BottleneckType::Database => 2.0,
+        BottleneckType::Disk | BottleneckType::Application => 1.0,
+    }
+}
+
+/// 根据指标名称推断所属的资源类别
+/// Infer the resource family of a metric from its name
+fn bottleneck_type_for(metric_name: &str) -> BottleneckType {
+    let name = metric_name.to_ascii_lowercase();
+    let has = |prefixes: &[&str]| prefixes.iter().any(|prefix| name.starts_with(prefix));
+    if has(&["cpu"]) {
+        BottleneckType::CPU
+    } else if has(&["memory", "mem_", "heap"]) {
+        BottleneckType::Memory
+    } else if has(&["network", "net_", "bandwidth", "packet"]) {
+        BottleneckType::Network
+    } else if has(&["disk", "io_"]) {
+        BottleneckType::Disk
+    } else if has(&["db_", "database", "query"]) {
+        BottleneckType::Database
+    } else {
+        BottleneckType::Application
+    }
+}
+
+/// 各类瓶颈的具体优化建议
+/// Concrete suggestions for each kind of bottleneck
+fn bottleneck_suggestions(bottleneck_type: &BottleneckType) -> Vec<String> {
+    let suggestions: &[&str] = match bottleneck_type {
+        BottleneckType::CPU => &["分析热点函数并优化计算密集路径", "将可并行的工作分散到更多工作线程", "增加 CPU 配额或横向扩容实例"],
+        BottleneckType::Memory => &["检查内存泄漏与长生命周期的大对象", "缩小缓存容量或启用淘汰策略", "提高内存限制或使用更紧凑的数据结构"],
+        BottleneckType::Network => &["合并小请求并启用压缩", "为远端调用增加本地缓存", "检查网络链路与连接池配置"],
+        BottleneckType::Disk => &["清理或归档旧数据", "将顺序小写入合并为批量写入", "扩容磁盘或迁移到更快的存储"],
+        BottleneckType::Database => &["为慢查询添加索引", "使用连接池与批量查询", "将读流量分流到只读副本"],
+        BottleneckType::Application => &["对相关代码路径做性能剖析", "检查队列积压与锁竞争"],
+    };
+    suggestions.iter().map(|suggestion| suggestion.to_string()).collect()
+}
+
+/// 对 (时间戳, 数值) 做最小二乘直线拟合，返回 (斜率, R²)；时间戳全部相同时返回 None
+/// Least-squares line fit over (timestamp, value), returning (slope, R²); None when every timestamp is equal
+fn least_squares_fit(points: &[(u64, f64)]) -> Option<(f64, f64)> {
+    let n = points.len() as f64;
+    let origin = points.first()?.0;
+    let xs: Vec<f64> = points.iter().map(|(timestamp, _)| (timestamp - origin) as f64).collect();
+    let mean_x = xs.iter().sum::<f64>() / n;
+    let mean_y = points.iter().map(|(_, value)| value).sum::<f64>() / n;
+    let (mut sxx, mut sxy, mut syy) = (0.0, 0.0, 0.0);
+    for (x, (_, y)) in xs.iter().zip(points) {
+        sxx += (x - mean_x) * (x - mean_x);
+        sxy += (x - mean_x) * (y - mean_y);
+        syy += (y - mean_y) * (y - mean_y);
+    }
+    if sxx == 0.0 {
+        return None;
+    }
+    let slope = sxy / sxx;
+    // 常数序列被直线完全解释
+    let r_squared = if syy == 0.0 { 1.0 } else { (sxy * sxy) / (sxx * syy) };
+    Some((slope, r_squared))
+}
+
+/// 上升趋势是否值得关注（内存增长、错误率上升）
+/// Whether an increasing trend is concerning (memory growth, rising error rate)
+fn is_concerning_trend(trend: &Trend) -> bool {
+    matches!(trend.direction, TrendDirection::Increasing)
+        && (matches!(bottleneck_type_for(&trend.metric_name), BottleneckType::Memory)
+            || trend.metric_name.to_ascii_lowercase().contains("error"))
+}
+
+/// 由值得关注的趋势生成一条资源调整建议
+/// Derive a resource adjustment recommendation from a concerning trend
+fn recommendation_for_trend(trend: &Trend) -> Recommendation {
+    Recommendation {
+        id: uuid::Uuid::new_v4().to_string(),
+        recommendation_type: RecommendationType::ResourceAdjustment,
+        priority: if trend.confidence >= 0.8 { RecommendationPriority::High } else { RecommendationPriority::Medium },
+        description: format!("{} 以每小时 {:.1}% 的速度持续上升，请在达到上限前调整资源或排查原因", trend.metric_name, trend.change_rate),
+        expected_impact: "避免资源耗尽或错误率继续恶化".to_string(),
+        implementation_difficulty: ImplementationDifficulty::Medium,
+    }
+}
+
+/// 由瓶颈生成一条建议
+/// Derive a recommendation from a bottleneck
+fn recommendation_for(bottleneck: &Bottleneck) -> Recommendation {
+    let (recommendation_type, implementation_difficulty) = match bottleneck.bottleneck_type {
+        BottleneckType::CPU => (RecommendationType::CodeOptimization, ImplementationDifficulty::Medium),
+        BottleneckType::Memory => (RecommendationType::ResourceAdjustment, ImplementationDifficulty::Medium),
+        BottleneckType::Network => (RecommendationType::ConfigurationOptimization, ImplementationDifficulty::Easy),
+        BottleneckType::Disk => (RecommendationType::ResourceAdjustment, ImplementationDifficulty::Easy),
+        BottleneckType::Database => (RecommendationType::PerformanceOptimization, ImplementationDifficulty::Medium),
+        BottleneckType::Application => (RecommendationType::ArchitectureImprovement, ImplementationDifficulty::Hard),
+    };
+    let priority = match bottleneck.severity {
+        severity if severity >= 0.75 => RecommendationPriority::Critical,
+        severity if severity >= 0.5 => RecommendationPriority::High,
+        severity if severity >= 0.25 => RecommendationPriority::Medium,
+        _ => RecommendationPriority::Low,
+    };
+    Recommendation {
+        id: uuid::Uuid::new_v4().to_string(),
+        recommendation_type,
+        priority,
+        description: format!("{}: {}", bottleneck.description, bottleneck.suggestions.first().cloned().unwrap_or_default()),
+        expected_impact: bottleneck.impact.clone(),
+        implementation_difficulty,
+    }
+}
+
+impl PerformanceAnalyzerEngine for StatisticalAnalyzer {
+    /// 按名称和标签把指标分成序列，找出最长的连续超限区间：持续至少 `window_size` 即为瓶颈，
+    /// 严重程度为超限区间均值超出阈值的比例（上限 1.0）。
+    /// 性能得分按类别加权：最近一个窗口的均值不超过阈值一半时满分，达到阈值时 50 分，达到 1.5 倍时 0 分。
+    /// Metrics are grouped into series by name and labels and the longest run above the threshold is found;
+    /// runs lasting at least `window_size` are bottlenecks whose severity is the run mean's excess over the
+    /// threshold as a fraction (capped at 1.0).
+    /// The score weights each series by family: full marks while the mean over the latest window stays at
+    /// half the threshold, 50 at the threshold and 0 at 1.5 times the threshold.
+    ///
+    /// 最近一个窗口内样本数不少于 `min_trend_samples` 的序列会拟合最小二乘直线：
+    /// 斜率相对均值的变化率（每小时百分比）低于 `stable_change_rate` 或 R² 低于 0.5 时为稳定，置信度取 R²。
+    /// Series with at least `min_trend_samples` samples in the latest window get a least-squares line;
+    /// the trend is stable when the slope relative to the mean (percent per hour) is below
+    /// `stable_change_rate` or R² is below 0.5, and the confidence is R².
+    fn analyze(&self, metrics: &[PerformanceMetric]) -> Result<PerformanceAnalysis, AnalysisError> {
+        let mut series: HashMap<String, Vec<&PerformanceMetric>> = HashMap::new();
+        for metric in metrics {
+            if !metric.value.is_finite() {
+                return Err(AnalysisError::DataError(format!("指标 {} 的值无效: {}", metric.name, metric.value)));
+            }
+            series.entry(series_key(&metric.name, &metric.labels)).or_default().push(metric);
+        }
+        let mut keys: Vec<&String> = series.keys().collect();
+        keys.sort();
+
+        let window = self.window_size.as_secs();
+        let mut bottlenecks = Vec::new();
+        let mut trends = Vec::new();
+        let (mut weighted_score, mut total_weight) = (0.0, 0.0);
+        for key in keys {
+            let mut samples = series[key].clone();
+            samples.sort_by_key(|metric| metric.timestamp);
+            let latest = samples[samples.len() - 1].timestamp;
+            let recent: Vec<(u64, f64)> = samples.iter()
+                .filter(|metric| metric.timestamp + window >= latest)
+                .map(|metric| (metric.timestamp, metric.value))
+                .collect();
+
+            if recent.len() >= self.min_trend_samples
+                && let Some((slope, r_squared)) = least_squares_fit(&recent)
+            {
+                let mean = recent.iter().map(|(_, value)| value).sum::<f64>() / recent.len() as f64;
+                let change_rate = if mean == 0.0 { 0.0 } else { slope * 3600.0 / mean.abs() * 100.0 };
+                let direction = if change_rate.abs() < self.stable_change_rate || r_squared < 0.5 {
+                    TrendDirection::Stable
+                } else if change_rate > 0.0 {
+                    TrendDirection::Increasing
+                } else {
+                    TrendDirection::Decreasing
+                };
+                trends.push(Trend {
+                    metric_name: key.clone(),
+                    direction,
+                    change_rate,
+                    confidence: r_squared,
+                });
+            }
+
+            let name = &samples[0].name;
+            let Some(&threshold) = self.thresholds.get(name) else {
+                continue;
+            };
+            let bottleneck_type = bottleneck_type_for(name);
+
+            // 最长的连续超限区间: (开始时间, 结束时间, 数值之和, 样本数)
+            let mut longest: Option<(u64, u64, f64, usize)> = None;
+            let mut run: Option<(u64, u64, f64, usize)> = None;
+            for metric in &samples {
+                if metric.value > threshold {
+                    let (start, _, sum, count) = run.unwrap_or((metric.timestamp, metric.timestamp, 0.0, 0));
+                    let current = (start, metric.timestamp, sum + metric.value, count + 1);
+                    run = Some(current);
+                    if longest.is_none_or(|(s, e, ..)| current.1 - current.0 > e - s) {
+                        longest = Some(current);
+                    }
+                } else {
+                    run = None;
+                }
+            }
+            if let Some((start, end, sum, count)) = longest
+                && end - start >= window
+            {
+                let mean = sum / count as f64;
+                let excess = if threshold > 0.0 { (mean - threshold) / threshold } else { 1.0 };
+                bottlenecks.push(Bottleneck {
+                    bottleneck_type: bottleneck_type.clone(),
+                    severity: excess.clamp(0.0, 1.0),
+                    description: format!("{} 持续 {} 秒超过阈值 {}", key, end - start, threshold),
+                    impact: format!("平均值 {:.2} 超出阈值 {:.0}%", mean, excess * 100.0),
+                    suggestions: bottleneck_suggestions(&bottleneck_type),
+                });
+            }
+
+            let recent_mean = recent.iter().map(|(_, value)| value).sum::<f64>() / recent.len() as f64;
+            let ratio = if threshold > 0.0 { recent_mean / threshold } else { 0.0 };
+            let weight = bottleneck_weight(&bottleneck_type);
+            weighted_score += weight * (1.5 - ratio).clamp(0.0, 1.0) * 100.0;
+            total_weight += weight;
+        }
+
+        bottlenecks.sort_by(|a, b| b.severity.total_cmp(&a.severity));
+        let correlations = self.correlations(&series);
+        let recommendations = bottlenecks.iter()
+            .map(recommendation_for)
+            .chain(trends.iter().filter(|trend| is_concerning_trend(trend)).map(recommendation_for_trend))
+            .collect();
+        Ok(PerformanceAnalysis {
+            id: uuid::Uuid::new_v4().to_string(),
+            analysis_time: SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs(),
+            results: AnalysisResults {
+                performance_score: if total_weight > 0.0 { weighted_score / total_weight } else { 100.0 },
+                bottlenecks,
+                trends,
+                correlations,
+            },
+            recommendations,
+            anomalies: Vec::new(),
+        })
+    }
+
+    fn detect_anomalies(&self, _metrics: &[PerformanceMetric]) -> Result<Vec<Anomaly>, AnalysisError> {
+        // 简化的异常检测实现
+        Ok(Vec::new())
+    }
+
+    fn generate_report(&self, analysis: &PerformanceAnalysis) -> Result<PerformanceReport, AnalysisError> {
+        // 简化的报告生成实现
+        Ok(PerformanceReport {
+            id: uuid::Uuid::new_v4().to_string(),
+            analysis: analysis.clone(),
+            generated_at: SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs(),
+        })
+    }
+}
+
+/// 性能报告
+/// Performance Report
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PerformanceReport {
+    /// 报告ID
+    pub id: String,
+    /// 分析结果
+    pub analysis: PerformanceAnalysis,
+    /// 生成时间
+    pub generated_at: u64,
+}
+
+/// 错误类型定义
+/// Error Type Definitions
+
+#[derive(Debug, Clone, Serialize, Deserialize, Error)]
+pub enum MonitoringError {
+    /// 配置错误
+    #[error("监控配置错误: {0}")]
+    ConfigurationError(String),
+    /// 指标错误
+    #[error("指标错误: {0}")]
+    MetricsError(String),
+    /// 追踪错误
+    #[error("追踪错误: {0}")]
+    TracingError(String),
+    /// 日志错误
+    #[error("日志错误: {0}")]
+    LoggingError(String),
+    /// 告警错误
+    #[error("告警错误: {0}")]
+    AlertError(String),
+    /// 分析错误
+    #[error("分析错误: {0}")]
+    AnalysisError(String),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Error)]
+pub enum LoggingError {
+    /// 处理器错误
+    #[error("日志处理器错误: {0}")]
+    ProcessorError(String),
+    /// 格式错误
+    #[error("日志格式错误: {0}")]
+    FormatError(String),
+    /// 输出错误
+    #[error("日志输出错误: {0}")]
+    OutputError(String),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Error)]
+pub enum NotificationError {
+    /// 发送错误
+    #[error("通知发送错误: {0}")]
+    SendError(String),
+    /// 配置错误
+    #[error("通知配置错误: {0}")]
+    ConfigurationError(String),
+    /// 连接错误
+    #[error("通知连接错误: {0}")]
+    ConnectionError(String),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Error)]
+pub enum AnalysisError {
+    /// 分析错误
+    #[error("性能分析错误: {0}")]
+    AnalysisError(String),
+    /// 数据错误
+    #[error("分析数据错误: {0}")]
+    DataError(String),
+    /// 算法错误
+    #[error("分析算法错误: {0}")]
+    AlgorithmError(String),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Error)]
+pub enum HealthCheckError {
+    /// 检查错误
+    #[error("健康检查错误: {0}")]
+    CheckError(String),
+    /// 超时错误
+    #[error("健康检查超时: {0}")]
+    TimeoutError(String),
+    /// 连接错误
+    #[error("健康检查连接错误: {0}")]
+    ConnectionError(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metrics_collector() -> MetricsCollector {
+        MetricsCollector::new(MetricsConfig {
+            enabled: true,
+            collection_interval: Duration::from_secs(10),
+            retention_period: Duration::from_secs(3600),
+            export_format: ExportFormat::Prometheus,
+        })
+    }
+
+    fn metric(name: &str, metric_type: MetricType, value: MetricValue, labels: &[(&str, &str)], unit: Option<&str>) -> Metric {
+        Metric {
+            name: name.to_string(),
+            metric_type,
+            value,
+            labels: labels.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+            timestamp: 0,
+            metadata: MetricMetadata {
+                description: format!("{} description", name),
+                unit: unit.map(str::to_string),
+                help: None,
+            },
+        }
+    }
+
+    #[test]
+    fn test_prometheus_export_renders_counter_and_gauge() {
+        let collector = metrics_collector();
+        collector.record_metric(metric(
+            "http.requests",
+            MetricType::Counter,
+            MetricValue::Integer(42),
+            &[("path", "/api/\"v1\""), ("method", "GET")],
+            None,
+        )).unwrap();
+        collector.record_metric(metric(
+            "http.requests",
+            MetricType::Counter,
+            MetricValue::Integer(3),
+            &[("path", "/health"), ("method", "GET")],
+            None,
+        )).unwrap();
+        collector.record_metric(metric("memory_usage", MetricType::Gauge, MetricValue::Float(1536.5), &[], Some("bytes"))).unwrap();
+
+        assert_eq!(collector.export(), concat!(
+            "# HELP http_requests http.requests description\n",
+            "# TYPE http_requests counter\n",
+            "http_requests{method=\"GET\",path=\"/api/\\\"v1\\\"\"} 42\n",
+            "http_requests{method=\"GET\",path=\"/health\"} 3\n",
+            "# HELP memory_usage_bytes memory_usage description\n",
+            "# TYPE memory_usage_bytes gauge\n",
+            "memory_usage_bytes 1536.5\n",
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_serve_metrics_over_http() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let collector = metrics_collector();
+        collector.record_metric(metric("up", MetricType::Gauge, MetricValue::Integer(1), &[], None)).unwrap();
+        let (addr, handle) = collector.serve_metrics("127.0.0.1:0").await.unwrap();
+
+        let request = |path: &'static str| async move {
+            let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+            stream.write_all(format!("GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).as_bytes()).await.unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).await.unwrap();
+            response
+        };
+
+        let response = request("/metrics").await;
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains("Content-Type: text/plain; version=0.0.4"));
+        assert!(response.ends_with("\r\n\r\n# HELP up up description\n# TYPE up gauge\nup 1\n"));
+        assert!(request("/").await.starts_with("HTTP/1.1 404 Not Found\r\n"));
+        handle.abort();
+    }
+
+    #[test]
+    fn test_histogram_buckets_and_quantile() {
+        let collector = metrics_collector();
+        let metadata = MetricMetadata {
+            description: "请求延迟".to_string(),
+            unit: Some("seconds".to_string()),
+            help: Some("Request latency".to_string()),
+        };
+        collector.register_histogram("latency", BucketLayout::Linear { start: 10.0, width: 10.0, count: 10 }, metadata).unwrap();
+        let labels: HashMap<String, String> = [("route".to_string(), "/api".to_string())].into();
+        for value in 1..=100 {
+            collector.observe("latency", value as f64, labels.clone()).unwrap();
+        }
+
+        let histograms = collector.histograms.lock().unwrap();
+        let series = histograms["latency"].series.values().next().unwrap();
+        assert_eq!(series.bucket_counts, vec![10, 20, 30, 40, 50, 60, 70, 80, 90, 100]);
+        assert_eq!((series.sum, series.count), (5050.0, 100));
+        drop(histograms);
+
+        let p95 = collector.quantile("latency", 0.95).unwrap();
+        assert!((p95 - 95.0).abs() < 1e-9, "{}", p95);
+        assert!((collector.quantile("latency", 0.5).unwrap() - 50.0).abs() < 1e-9);
+        assert!(collector.quantile("missing", 0.5).is_none());
+        assert!(collector.observe("missing", 1.0, HashMap::new()).is_err());
+
+        let exported = collector.export();
+        assert!(exported.starts_with("# HELP latency_seconds Request latency\n# TYPE latency_seconds histogram\n"));
+        assert!(exported.contains("latency_seconds_bucket{le=\"10\",route=\"/api\"} 10\n"));
+        assert!(exported.contains("latency_seconds_bucket{le=\"100\",route=\"/api\"} 100\n"));
+        assert!(exported.contains("latency_seconds_bucket{le=\"+Inf\",route=\"/api\"} 100\n"));
+        assert!(exported.ends_with("latency_seconds_sum{route=\"/api\"} 5050\nlatency_seconds_count{route=\"/api\"} 100\n"));
+
+        assert_eq!(BucketLayout::default().bounds().len(), 12);
+        assert_eq!(BucketLayout::Exponential { start: 1.0, factor: 2.0, count: 4 }.bounds(), vec![1.0, 2.0, 4.0, 8.0]);
+        assert_eq!(BucketLayout::Custom(vec![5.0, 1.0, 5.0, f64::INFINITY]).bounds(), vec![1.0, 5.0]);
+    }
+
+    #[test]
+    fn test_concurrent_counter_increments_are_summed() {
+        let collector = metrics_collector();
+        collector.register_counter("requests_total", MetricMetadata {
+            description: "Handled requests".to_string(),
+            unit: None,
+            help: None,
+        }).unwrap();
+        let labels: HashMap<String, String> = [("route".to_string(), "/api".to_string())].into_iter().collect();
+
+        std::thread::scope(|scope| {
+            for _ in 0..8 {
+                scope.spawn(|| {
+                    for _ in 0..1000 {
+                        collector.inc_counter("requests_total", 1, labels.clone()).unwrap();
+                    }
+                });
+            }
+        });
+
+        let metric = collector.get_metric("requests_total", &labels).unwrap();
+        assert!(matches!(metric.value, MetricValue::Integer(8000)));
+        assert_eq!(metric.metadata.description, "Handled requests");
+        assert!(collector.get_metric("requests_total", &HashMap::new()).is_none());
+    }
+
+    #[test]
+    fn test_gauges_overwrite_and_unregistered_counters_auto_register() {
+        let collector = metrics_collector();
+        collector.inc_counter("jobs_total", 3, HashMap::new()).unwrap();
+        assert!(collector.counters.lock().unwrap().contains_key("jobs_total"));
+        assert!(collector.set_gauge("jobs_total", 1.0, HashMap::new()).is_err());
+
+        collector.set_gauge("queue_depth", 4.0, HashMap::new()).unwrap();
+        collector.set_gauge("queue_depth", 2.5, HashMap::new()).unwrap();
+        let gauge = collector.get_metric("queue_depth", &HashMap::new()).unwrap();
+        assert!(matches!(gauge.value, MetricValue::Float(v) if v == 2.5));
+        assert!(collector.inc_counter("queue_depth", 1, HashMap::new()).is_err());
+    }
+
+    #[derive(Debug, Default)]
+    struct ManualClock(AtomicU64);
+
+    impl MetricsClock for ManualClock {
+        fn now_secs(&self) -> u64 {
+            self.0.load(Ordering::SeqCst)
+        }
+    }
+
+    #[test]
+    fn test_prune_now_drops_stale_series_and_keeps_fresh_ones() {
+        let clock = Arc::new(ManualClock(AtomicU64::new(1_000)));
+        let collector = MetricsCollector::with_clock(MetricsConfig {
+            enabled: true,
+            collection_interval: Duration::from_secs(10),
+            retention_period: Duration::from_secs(60),
+            export_format: ExportFormat::Prometheus,
+        }, clock.clone());
+        collector.register_histogram("latency_seconds", BucketLayout::Linear { start: 0.1, width: 0.1, count: 3 }, MetricMetadata {
+            description: "Latency".to_string(),
+            unit: None,
+            help: None,
+        }).unwrap();
+        let stale: HashMap<String, String> = [("route".to_string(), "/old".to_string())].into_iter().collect();
+        let fresh: HashMap<String, String> = [("route".to_string(), "/new".to_string())].into_iter().collect();
+
+        collector.inc_counter("requests_total", 1, stale.clone()).unwrap();
+        collector.observe("latency_seconds", 0.15, stale.clone()).unwrap();
+        clock.0.store(1_050, Ordering::SeqCst);
+        collector.inc_counter("requests_total", 1, fresh.clone()).unwrap();
+        collector.observe("latency_seconds", 0.25, fresh.clone()).unwrap();
+
+        clock.0.store(1_070, Ordering::SeqCst);
+        assert_eq!(collector.prune_now().unwrap(), 2);
+        assert_eq!(collector.pruned_series_count(), 2);
+        assert!(collector.get_metric("requests_total", &stale).is_none());
+        assert!(collector.get_metric("requests_total", &fresh).is_some());
+        let histograms = collector.histograms.lock().unwrap();
+        let series = &histograms["latency_seconds"].series;
+        assert_eq!(series.len(), 1);
+        assert!(series.contains_key(&series_key("latency_seconds", &fresh)));
+    }
+
+    #[test]
+    fn test_writes_prune_once_per_collection_interval() {
+        let clock = Arc::new(ManualClock(AtomicU64::new(0)));
+        let collector = MetricsCollector::with_clock(MetricsConfig {
+            enabled: true,
+            collection_interval: Duration::from_secs(10),
+            retention_period: Duration::from_secs(30),
+            export_format: ExportFormat::Prometheus,
+        }, clock.clone());
+        collector.set_gauge("queue_depth", 1.0, HashMap::new()).unwrap();
+        clock.0.store(29, Ordering::SeqCst);
+        collector.set_gauge("workers", 4.0, HashMap::new()).unwrap();
+
+        // 距上次清理不足一个收集间隔，过期的序列暂时保留
+        clock.0.store(31, Ordering::SeqCst);
+        collector.set_gauge("workers", 5.0, HashMap::new()).unwrap();
+        assert!(collector.get_metric("queue_depth", &HashMap::new()).is_some());
+        assert_eq!(collector.pruned_series_count(), 0);
+
+        clock.0.store(40, Ordering::SeqCst);
+        collector.set_gauge("workers", 6.0, HashMap::new()).unwrap();
+        assert!(collector.get_metric("queue_depth", &HashMap::new()).is_none());
+        assert!(collector.get_metric("workers", &HashMap::new()).is_some());
+        assert_eq!(collector.pruned_series_count(), 1);
+    }
+
+    fn tracer() -> DistributedTracer {
+        DistributedTracer::new(TracingConfig {
+            enabled: true,
+            sampling_rate: 1.0,
+            endpoint: None,
+            service_name: "test-service".to_string(),
+            service_version: "1.0.0".to_string(),
+        })
+    }
+
+    #[test]
+    fn test_span_lifecycle_with_sequential_children() {
+        let tracer = tracer();
+        let (trace_id, root) = tracer.start_trace("handle_request").unwrap();
+        let first = tracer.start_span(&trace_id, "load_module", Some(&root)).unwrap();
+        std::thread::sleep(Duration::from_millis(5));
+        let first_ms = tracer.finish_span(&trace_id, &first, SpanStatus::Completed).unwrap();
+        let second = tracer.start_span(&trace_id, "execute", Some(&root)).unwrap();
+        tracer.add_span_tag(&trace_id, &second, "function", "add").unwrap();
+        tracer.add_span_log(&trace_id, &second, LogLevel::Info, "started", HashMap::new()).unwrap();
+        std::thread::sleep(Duration::from_millis(5));
+        let second_ms = tracer.finish_span(&trace_id, &second, SpanStatus::Completed).unwrap();
+        let root_ms = tracer.finish_span(&trace_id, &root, SpanStatus::Completed).unwrap();
+        assert!(tracer.finish_span(&trace_id, &root, SpanStatus::Completed).is_err());
+        assert!(tracer.start_span(&trace_id, "orphan", Some("missing")).is_err());
+
+        assert!(first_ms >= 5 && second_ms >= 5);
+        assert!(root_ms >= first_ms + second_ms);
+
+        let trace = tracer.finish_trace(&trace_id).unwrap();
+        assert!(matches!(trace.status, TraceStatus::Completed));
+        assert!(tracer.active_traces.lock().unwrap().is_empty());
+        assert_eq!(tracer.finished_traces.lock().unwrap().len(), 1);
+        let children: Vec<&Span> = trace.spans.iter()
+            .filter(|span| span.parent_span_id.as_deref() == Some(root.as_str()))
+            .collect();
+        assert_eq!(children.len(), 2);
+        assert_eq!(children[0].operation_name, "load_module");
+        assert!(children[0].end_time.unwrap() <= children[1].start_time);
+        assert_eq!(children[1].tags["function"], "add");
+        assert_eq!(children[1].logs.len(), 1);
+        assert_eq!(trace.spans[0].duration_ms(), Some(root_ms));
+    }
+
+    #[test]
+    fn test_finished_trace_buffer_is_bounded() {
+        let mut tracer = tracer();
+        tracer.max_finished_traces = 2;
+        let ids: Vec<String> = (0..3).map(|i| {
+            let (trace_id, _) = tracer.start_trace(&format!("op{}", i)).unwrap();
+            tracer.finish_trace(&trace_id).unwrap();
+            trace_id
+        }).collect();
+
+        let finished = tracer.finished_traces.lock().unwrap();
+        assert_eq!(finished.len(), 2);
+        assert_eq!(finished[0].trace_id, ids[1]);
+        assert!(tracer.finish_trace(&ids[0]).is_err());
+    }
+
+    fn two_span_trace() -> Trace {
+        let trace_id = "4bf92f3577b34da6a3ce929d0e0e4736".to_string();
+        Trace {
+            trace_id,
+            parent_trace_id: None,
+            spans: vec![
+                Span {
+                    span_id: "00f067aa0ba902b7".to_string(),
+                    parent_span_id: None,
+                    operation_name: "handle_request".to_string(),
+                    start_time: 1_700_000_000_000,
+                    end_time: Some(1_700_000_000_250),
+                    tags: [("http.method".to_string(), "POST".to_string())].into_iter().collect(),
+                    logs: Vec::new(),
+                    status: SpanStatus::Completed,
+                },
+                Span {
+                    span_id: "53995c3f42cd8ad8".to_string(),
+                    parent_span_id: Some("00f067aa0ba902b7".to_string()),
+                    operation_name: "execute_function".to_string(),
+                    start_time: 1_700_000_000_010,
+                    end_time: Some(1_700_000_000_200),
+                    tags: [("function".to_string(), "add".to_string())].into_iter().collect(),
+                    logs: vec![SpanLog {
+                        timestamp: 1_700_000_000_150,
+                        level: LogLevel::Error,
+                        message: "trap".to_string(),
+                        fields: [("reason".to_string(), "unreachable".to_string())].into_iter().collect(),
+                    }],
+                    status: SpanStatus::Error,
+                },
+            ],
+            start_time: 1_700_000_000_000,
+            end_time: Some(1_700_000_000_250),
+            status: TraceStatus::Error,
+        }
+    }
+
+    #[test]
+    fn test_export_two_span_trace_golden() {
+        let tracer = tracer();
+        tracer.finished_traces.lock().unwrap().push_back(two_span_trace());
+
+        let jaeger: serde_json::Value = serde_json::from_str(&tracer.export_jaeger_json()).unwrap();
+        let expected: serde_json::Value = serde_json::from_str(include_str!("../tests/golden/two_span_trace.jaeger.json")).unwrap();
+        assert_eq!(jaeger, expected);
+
+        let otlp: serde_json::Value = serde_json::from_str(&tracer.export_otlp_json()).unwrap();
+        let mut expected: serde_json::Value = serde_json::from_str(include_str!("../tests/golden/two_span_trace.otlp.json")).unwrap();
+        expected["resourceSpans"][0]["scopeSpans"][0]["scope"]["version"] = env!("CARGO_PKG_VERSION").into();
+        assert_eq!(otlp, expected);
+    }
+
+    #[test]
+    fn test_generated_ids_are_valid_hex() {
+        let tracer = tracer();
+        let (trace_id, root) = tracer.start_trace("op").unwrap();
+        let child = tracer.start_span(&trace_id, "child", Some(&root)).unwrap();
+        let is_hex = |id: &str, len: usize| id.len() == len && id.chars().all(|c| c.is_ascii_hexdigit() && !c.is_ascii_uppercase());
+        assert!(is_hex(&trace_id, 32));
+        assert!(is_hex(&root, 16) && is_hex(&child, 16));
+        assert_ne!(root, "0000000000000000");
+    }
+
+    #[tokio::test]
+    async fn test_exporter_posts_batches_with_retry() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let mut bodies = Vec::new();
+            for attempt in 0..2 {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut request = Vec::new();
+                let mut buffer = [0u8; 4096];
+                loop {
+                    let read = stream.read(&mut buffer).await.unwrap();
+                    request.extend_from_slice(&buffer[..read]);
+                    let text = String::from_utf8_lossy(&request).to_string();
+                    if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                        let length: usize = head.lines()
+                            .find_map(|line| line.strip_prefix("Content-Length: "))
+                            .unwrap()
+                            .parse()
+                            .unwrap();
+                        if body.len() >= length {
+                            bodies.push(body.to_string());
+                            break;
+                        }
+                    }
+                }
+                let status = if attempt == 0 { "503 Service Unavailable" } else { "200 OK" };
+                stream.write_all(format!("HTTP/1.1 {}\r\nContent-Length: 0\r\n\r\n", status).as_bytes()).await.unwrap();
+            }
+            bodies
+        });
+
+        let mut tracer = tracer();
+        tracer.config.endpoint = Some(format!("http://{}/v1/traces", addr));
+        tracer.finished_traces.lock().unwrap().push_back(two_span_trace());
+        let handle = tracer.spawn_exporter(TraceExporterOptions {
+            format: TraceExportFormat::Otlp,
+            batch_size: 10,
+            interval: Duration::from_millis(10),
+            max_retries: 3,
+            retry_backoff: Duration::from_millis(1),
+        }).unwrap();
+
+        let bodies = tokio::time::timeout(Duration::from_secs(5), server).await.unwrap().unwrap();
+        handle.abort();
+        assert_eq!(bodies.len(), 2);
+        assert_eq!(bodies[0], bodies[1]);
+        let body: serde_json::Value = serde_json::from_str(&bodies[1]).unwrap();
+        assert_eq!(body["resourceSpans"][0]["scopeSpans"][0]["spans"].as_array().unwrap().len(), 2);
+        assert!(tracer.finished_traces.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_post_json_times_out_on_a_silent_endpoint() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            // 接受连接后既不读也不回
+            let (stream, _) = listener.accept().await.unwrap();
+            tokio::time::sleep(Duration::from_secs(5)).await;
+            drop(stream);
+        });
+
+        let started = Instant::now();
+        let error = post_json(&format!("http://{}/v1/traces", addr), "{}", Duration::from_millis(50)).await.unwrap_err();
+        assert!(started.elapsed() < Duration::from_secs(2));
+        assert!(error.to_string().contains("未响应"), "{}", error);
+        server.abort();
+    }
+
+    fn sampled_tracer(sampler: SamplingStrategy) -> DistributedTracer {
+        let mut tracer = tracer().with_sampling_seed(42);
+        tracer.sampler = sampler;
+        tracer
+    }
+
+    #[test]
+    fn test_probabilistic_sampling_rate() {
+        let tracer = sampled_tracer(SamplingStrategy::Probabilistic(0.25));
+        let sampled = (0..10_000)
+            .filter(|_| tracer.should_sample("op") == SamplingDecision::RecordAndSample)
+            .count();
+        assert!((2_200..=2_800).contains(&sampled), "sampled {}", sampled);
+    }
+
+    #[test]
+    fn test_rate_limiting_sampling_caps_per_second() {
+        let tracer = sampled_tracer(SamplingStrategy::RateLimiting(5));
+        let start = Instant::now();
+        let sampled_at = |now: Instant| (0..100)
+            .filter(|_| tracer.should_sample_at("op", now) == SamplingDecision::RecordAndSample)
+            .count();
+        assert_eq!(sampled_at(start), 5);
+        assert_eq!(sampled_at(start + Duration::from_millis(400)), 2);
+        assert_eq!(sampled_at(start + Duration::from_secs(10)), 5);
+    }
+
+    #[test]
+    fn test_adaptive_sampling_tracks_target_rate() {
+        let tracer = sampled_tracer(SamplingStrategy::Adaptive(10.0));
+        let start = Instant::now();
+        let sampled_at = |now: Instant| (0..1_000)
+            .filter(|_| tracer.should_sample_at("op", now) == SamplingDecision::RecordAndSample)
+            .count();
+        assert_eq!(sampled_at(start), 1_000);
+        let adjusted = sampled_at(start + Duration::from_secs(1));
+        assert!((2..=25).contains(&adjusted), "sampled {}", adjusted);
+    }
+
+    #[test]
+    fn test_unsampled_trace_is_a_noop_handle() {
+        let tracer = sampled_tracer(SamplingStrategy::Probabilistic(0.0));
+        let (trace_id, root) = tracer.start_trace("op").unwrap();
+        assert_eq!(trace_id, NOOP_TRACE_ID);
+        let child = tracer.start_span(&trace_id, "child", Some(&root)).unwrap();
+        tracer.add_span_tag(&trace_id, &child, "k", "v").unwrap();
+        assert_eq!(tracer.finish_span(&trace_id, &child, SpanStatus::Completed).unwrap(), 0);
+        assert!(tracer.finish_trace(&trace_id).unwrap().spans.is_empty());
+        assert!(tracer.active_traces.lock().unwrap().is_empty());
+        assert!(tracer.finished_traces.lock().unwrap().is_empty());
+    }
+
+    fn monitoring_manager() -> AdvancedMonitoringManager {
+        AdvancedMonitoringManager::new(MonitoringConfig {
+            enabled: true,
+            metrics_config: MetricsConfig {
+                enabled: true,
+                collection_interval: Duration::from_secs(10),
+                retention_period: Duration::from_secs(3600),
+                export_format: ExportFormat::Prometheus,
+            },
+            tracing_config: TracingConfig {
+                enabled: true,
+                sampling_rate: 1.0,
+                endpoint: None,
+                service_name: "test-service".to_string(),
+                service_version: "1.0.0".to_string(),
+            },
+            logging_config: LoggingConfig {
+                level: LogLevel::Info,
+                format: LogFormat::JSON,
+                targets: vec![LogTarget::Stdout],
+                target_levels: Vec::new(),
+                buffer_size: 1000,
+                flush_interval: Duration::from_secs(5),
+            },
+            alert_config: AlertConfig {
+                evaluation_interval: Duration::from_secs(30),
+                repeat_interval: Duration::from_secs(300),
+                max_alerts: 100,
+                silence_config: SilenceConfig {
+                    silence_rules: Vec::new(),
+                    default_silence_duration: Duration::from_secs(3600),
+                },
+                send_resolved: true,
+            },
+            performance_config: PerformanceConfig {
+                analysis_interval: Duration::from_secs(60),
+                window_size: Duration::from_secs(300),
+                thresholds: HashMap::new(),
+                anomaly_detection: AnomalyDetectionConfig {
+                    enabled: false,
+                    sensitivity: 0.95,
+                    algorithm: AnomalyDetectionAlgorithm::Statistical,
+                    training_data_size: 1000,
+                },
+            },
+            health_check_config: HealthCheckConfig {
+                check_interval: Duration::from_secs(30),
+                timeout: Duration::from_secs(5),
+                retry_count: 3,
+                health_threshold: 0.8,
+                flap_threshold: 1,
+            },
+        })
+    }
+
+    #[test]
+    fn test_logs_carry_current_span_ids() {
+        let manager = monitoring_manager();
+        let (trace_id, root) = manager.tracer.start_trace("request").unwrap();
+        let child = manager.tracer.start_span(&trace_id, "execute", Some(&root)).unwrap();
+        manager.log(LogLevel::Info, "inside child".to_string(), HashMap::new());
+        manager.log(LogLevel::Warn, "slow".to_string(), [("ms".to_string(), serde_json::json!(120))].into_iter().collect());
+        manager.tracer.finish_span(&trace_id, &child, SpanStatus::Completed).unwrap();
+        manager.log(LogLevel::Info, "inside root".to_string(), HashMap::new());
+        manager.tracer.finish_trace(&trace_id).unwrap();
+        manager.log(LogLevel::Info, "outside".to_string(), HashMap::new());
+
+        let logs = manager.logger.log_buffer.lock().unwrap();
+        assert_eq!(logs[0].span_id.as_deref(), Some(child.as_str()));
+        assert_eq!(logs[0].trace_id.as_deref(), Some(trace_id.as_str()));
+        assert_eq!(logs[2].span_id.as_deref(), Some(root.as_str()));
+        assert!(logs[3].trace_id.is_none() && logs[3].span_id.is_none());
+
+        let trace = manager.tracer.finished_traces.lock().unwrap()[0].clone();
+        let child_span = trace.spans.iter().find(|span| span.span_id == child).unwrap();
+        assert_eq!(child_span.logs.len(), 1);
+        assert_eq!(child_span.logs[0].message, "slow");
+        assert_eq!(child_span.logs[0].fields["ms"], "120");
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_span_context_crosses_spawn_boundaries() {
+        let manager = Arc::new(monitoring_manager());
+        let (trace_id, root) = manager.tracer.start_trace("request").unwrap();
+        let context = SpanContext::current().unwrap();
+        assert_eq!(context, SpanContext::new(trace_id.as_str(), root.as_str()));
+
+        let task_manager = Arc::clone(&manager);
+        tokio::spawn(context.scope(async move {
+            tokio::task::yield_now().await;
+            task_manager.log(LogLevel::Info, "in task".to_string(), HashMap::new());
+        })).await.unwrap();
+
+        let task_manager = Arc::clone(&manager);
+        tokio::spawn(async move {
+            task_manager.log(LogLevel::Info, "detached".to_string(), HashMap::new());
+        }).await.unwrap();
+
+        let explicit = SpanContext::new("a".repeat(32), "b".repeat(16));
+        {
+            let _guard = explicit.enter();
+            manager.log(LogLevel::Info, "guarded".to_string(), HashMap::new());
+        }
+        manager.log_in_span("c".repeat(32).as_str(), "d".repeat(16).as_str(), LogLevel::Info, "explicit".to_string(), HashMap::new());
+
+        let logs = manager.logger.log_buffer.lock().unwrap();
+        assert_eq!(logs[0].span_id.as_deref(), Some(root.as_str()));
+        assert!(logs[1].span_id.is_none());
+        assert_eq!(logs[2].trace_id, Some("a".repeat(32)));
+        assert_eq!(logs[3].span_id, Some("d".repeat(16)));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_requests_keep_their_own_span_stacks() {
+        let tracer = Arc::new(tracer());
+        let requests: Vec<_> = (0..8).map(|request| {
+            let tracer = Arc::clone(&tracer);
+            tokio::spawn(SpanContext::task_scope(async move {
+                let (trace_id, root) = tracer.start_trace(&format!("request{}", request)).unwrap();
+                for step in 0..5 {
+                    let child = tracer.start_span(&trace_id, "step", Some(&root)).unwrap();
+                    // 让出执行权，任务可能在其他工作线程上恢复
+                    tokio::task::yield_now().await;
+                    assert_eq!(SpanContext::current(), Some(SpanContext::new(trace_id.as_str(), child.as_str())), "step {}", step);
+                    tracer.finish_span(&trace_id, &child, SpanStatus::Completed).unwrap();
+                    tokio::task::yield_now().await;
+                    assert_eq!(SpanContext::current(), Some(SpanContext::new(trace_id.as_str(), root.as_str())));
+                }
+                tracer.finish_trace(&trace_id).unwrap();
+                assert_eq!(SpanContext::current(), None);
+            }))
+        }).collect();
+        for request in requests {
+            request.await.unwrap();
+        }
+        // 作用域中的跨度不会留在工作线程的跨度栈上
+        for _ in 0..16 {
+            assert_eq!(tokio::spawn(async { SpanContext::current() }).await.unwrap(), None);
+        }
+        assert_eq!(SpanContext::current(), None);
+    }
+
+    fn log_entry(index: usize) -> LogEntry {
+        LogEntry {
+            timestamp: 1_700_000_000,
+            level: LogLevel::Info,
+            message: format!("entry {:04}", index),
+            fields: HashMap::new(),
+            trace_id: None,
+            span_id: None,
+            module: None,
+            target: "test".to_string(),
+        }
+    }
+
+    fn read_log_lines(path: &std::path::Path, compressed: bool) -> Vec<String> {
+        use std::io::Read;
+
+        let mut content = String::new();
+        let file = std::fs::File::open(path).unwrap();
+        if compressed {
+            flate2::read::GzDecoder::new(file).read_to_string(&mut content).unwrap();
+        } else {
+            std::io::BufReader::new(file).read_to_string(&mut content).unwrap();
+        }
+        content.lines().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn test_file_log_processor_rotates_without_losing_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = FileLogProcessorConfig::new(dir.path().join("app.log"), LogFormat::Text);
+        config.max_bytes = 200;
+        config.max_files = 10;
+        let processor = FileLogProcessor::new(config.clone());
+
+        for index in 0..40 {
+            processor.process(&log_entry(index)).unwrap();
+        }
+        processor.close().unwrap();
+
+        assert!(config.rotated_path(1).ends_with("app.log.1"));
+        assert!(config.rotated_path(1).exists() && config.rotated_path(2).exists());
+        assert!(std::fs::metadata(config.rotated_path(1)).unwrap().len() <= 200);
+
+        let mut rotated: Vec<usize> = (1..=config.max_files).filter(|i| config.rotated_path(*i).exists()).collect();
+        rotated.reverse();
+        let mut lines: Vec<String> = rotated.into_iter().flat_map(|i| read_log_lines(&config.rotated_path(i), false)).collect();
+        lines.extend(read_log_lines(&config.path, false));
+        let expected: Vec<String> = (0..40).map(|i| format!("2023-11-14T22:13:20Z INFO test entry {:04}", i)).collect();
+        assert_eq!(lines, expected);
+        assert_eq!(processor.dropped_entries(), 0);
+    }
+
+    #[test]
+    fn test_file_log_processor_compresses_and_limits_rotated_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = FileLogProcessorConfig::new(dir.path().join("app.log"), LogFormat::JSON);
+        config.max_bytes = 300;
+        config.max_files = 2;
+        config.compress_rotated = true;
+        let processor = FileLogProcessor::new(config.clone());
+
+        for index in 0..30 {
+            processor.process(&log_entry(index)).unwrap();
+        }
+        processor.flush().unwrap();
+
+        assert!(config.rotated_path(1).ends_with("app.log.1.gz"));
+        assert!(config.rotated_path(2).exists());
+        assert!(!config.rotated_path(3).exists());
+        let newest_rotated = read_log_lines(&config.rotated_path(1), true);
+        let entry: LogEntry = serde_json::from_str(&newest_rotated[0]).unwrap();
+        assert!(entry.message.starts_with("entry "));
+    }
+
+    #[test]
+    fn test_file_log_processor_recreates_removed_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let log_dir = dir.path().join("logs");
+        let config = FileLogProcessorConfig::new(log_dir.join("app.log"), LogFormat::Structured);
+        let processor = FileLogProcessor::new(config.clone());
+
+        processor.process(&log_entry(0)).unwrap();
+        std::fs::remove_dir_all(&log_dir).unwrap();
+        processor.process(&log_entry(1)).unwrap();
+
+        let lines = read_log_lines(&config.path, false);
+        assert_eq!(lines, vec!["ts=2023-11-14T22:13:20Z level=info target=test msg=\"entry 0001\"".to_string()]);
+        assert_eq!(processor.dropped_entries(), 0);
+    }
+
+    #[test]
+    fn test_structured_logger_registers_file_targets() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("service.log");
+        let logger = StructuredLogger::new(LoggingConfig {
+            level: LogLevel::Info,
+            format: LogFormat::Text,
+            targets: vec![LogTarget::File(path.to_string_lossy().into_owned())],
+            target_levels: Vec::new(),
+            buffer_size: 100,
+            flush_interval: Duration::from_secs(1),
+        });
+        assert_eq!(logger.processors.lock().unwrap().len(), 1);
+
+        logger.log_buffer.lock().unwrap().extend((0..3).map(log_entry));
+        assert_eq!(logger.flush().unwrap(), 3);
+        assert!(logger.log_buffer.lock().unwrap().is_empty());
+        assert_eq!(read_log_lines(&path, false).len(), 3);
+    }
+
+    #[derive(Default)]
+    struct CountingProcessor {
+        processed: Arc<AtomicU64>,
+        closed: Arc<AtomicU64>,
+        failing: bool,
+    }
+
+    impl LogProcessor for CountingProcessor {
+        fn process(&self, _entry: &LogEntry) -> Result<(), LoggingError> {
+            if self.failing {
+                return Err(LoggingError::OutputError("sink down".to_string()));
+            }
+            self.processed.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+
+        fn flush(&self) -> Result<(), LoggingError> {
+            Ok(())
+        }
+
+        fn close(&self) -> Result<(), LoggingError> {
+            self.closed.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    fn buffered_logger(buffer_size: usize, flush_interval: Duration) -> (StructuredLogger, Arc<AtomicU64>, Arc<AtomicU64>) {
+        let logger = StructuredLogger::new(LoggingConfig {
+            level: LogLevel::Info,
+            format: LogFormat::Text,
+            targets: Vec::new(),
+            target_levels: Vec::new(),
+            buffer_size,
+            flush_interval,
+        });
+        let processor = CountingProcessor::default();
+        let (processed, closed) = (Arc::clone(&processor.processed), Arc::clone(&processor.closed));
+        logger.add_processor(Box::new(CountingProcessor { failing: true, ..Default::default() }));
+        logger.add_processor(Box::new(processor));
+        (logger, processed, closed)
+    }
+
+    #[test]
+    fn test_size_triggered_flush_isolates_failing_processors() {
+        let (logger, processed, _) = buffered_logger(3, Duration::from_secs(60));
+        logger.push(log_entry(0)).unwrap();
+        logger.push(log_entry(1)).unwrap();
+        assert_eq!(processed.load(Ordering::SeqCst), 0);
+        logger.push(log_entry(2)).unwrap();
+
+        assert_eq!(processed.load(Ordering::SeqCst), 3);
+        assert!(logger.log_buffer.lock().unwrap().is_empty());
+        assert_eq!(logger.processor_error_counts(), vec![3, 0]);
+    }
+
+    /// 收到放行信号才处理条目的处理器
+    struct GatedProcessor {
+        gate: Mutex<std::sync::mpsc::Receiver<()>>,
+        processed: Arc<AtomicU64>,
+    }
+
+    impl LogProcessor for GatedProcessor {
+        fn process(&self, _entry: &LogEntry) -> Result<(), LoggingError> {
+            self.gate.lock().unwrap().recv_timeout(Duration::from_secs(5))
+                .map_err(|_| LoggingError::OutputError("gate closed".to_string()))?;
+            self.processed.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+
+        fn flush(&self) -> Result<(), LoggingError> {
+            Ok(())
+        }
+
+        fn close(&self) -> Result<(), LoggingError> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_size_triggered_flush_does_not_block_the_runtime() {
+        let (logger, _, _) = buffered_logger(3, Duration::from_secs(60));
+        let (open, gate) = std::sync::mpsc::channel();
+        let processed = Arc::new(AtomicU64::new(0));
+        logger.add_processor(Box::new(GatedProcessor { gate: Mutex::new(gate), processed: Arc::clone(&processed) }));
+
+        // 写入在阻塞线程池上等待放行；同步刷新会在 push 中等到超时，放行信号要在 push 返回后才发出
+        for index in 0..3 {
+            logger.push(log_entry(index)).unwrap();
+        }
+        for _ in 0..3 {
+            open.send(()).unwrap();
+        }
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while processed.load(Ordering::SeqCst) < 3 && Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(processed.load(Ordering::SeqCst), 3);
+        assert!(logger.log_buffer.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_time_triggered_flush_and_shutdown() {
+        let (logger, processed, closed) = buffered_logger(1000, Duration::from_millis(20));
+        logger.start_flusher();
+        logger.push(log_entry(0)).unwrap();
+        logger.push(log_entry(1)).unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while processed.load(Ordering::SeqCst) < 2 && Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(processed.load(Ordering::SeqCst), 2);
+
+        logger.push(log_entry(2)).unwrap();
+        logger.shutdown().unwrap();
+        assert_eq!(processed.load(Ordering::SeqCst), 3);
+        assert_eq!(closed.load(Ordering::SeqCst), 1);
+        assert!(logger.flusher.lock().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_render_per_format() {
+        let mut entry = log_entry(7);
+        entry.message = "line one\nsaid \"hi\"".to_string();
+        entry.span_id = Some("00f067aa0ba902b7".to_string());
+        entry.fields = [
+            ("path".to_string(), serde_json::json!("C:\\tmp\\a b")),
+            ("count".to_string(), serde_json::json!(3)),
+            ("note".to_string(), serde_json::json!("x=\"y\"\nz")),
+        ].into_iter().collect();
+
+        let cases = [
+            (
+                LogFormat::Text,
+                "2023-11-14T22:13:20Z INFO test line one\\nsaid \"hi\" count=3 note=\"x=\\\"y\\\"\\nz\" path=\"C:\\\\tmp\\\\a b\"",
+            ),
+            (
+                LogFormat::Structured,
+                "ts=2023-11-14T22:13:20Z level=info target=test msg=\"line one\\nsaid \\\"hi\\\"\" span_id=00f067aa0ba902b7 count=3 note=\"x=\\\"y\\\"\\nz\" path=\"C:\\\\tmp\\\\a b\"",
+            ),
+            (
+                LogFormat::JSON,
+                r#"{"fields":{"count":3,"note":"x=\"y\"\nz","path":"C:\\tmp\\a b"},"level":"Info","message":"line one\nsaid \"hi\"","module":null,"span_id":"00f067aa0ba902b7","target":"test","timestamp":1700000000,"trace_id":null}"#,
+            ),
+        ];
+        for (format, expected) in cases {
+            let rendered = render(&entry, &format);
+            assert_eq!(rendered, expected, "{:?}", format);
+            assert!(!rendered.contains('\n'));
+        }
+    }
+
+    #[test]
+    fn test_level_filtering_and_target_overrides() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_target = LogTarget::File(dir.path().join("debug.log").to_string_lossy().into_owned());
+        let mut manager = monitoring_manager();
+        manager.logger = StructuredLogger::new(LoggingConfig {
+            level: LogLevel::Error,
+            format: LogFormat::Text,
+            targets: vec![LogTarget::Stderr, file_target.clone()],
+            target_levels: vec![(LogTarget::Stderr, LogLevel::Warn), (file_target.clone(), LogLevel::Debug)],
+            buffer_size: 100,
+            flush_interval: Duration::from_secs(1),
+        });
+        assert_eq!(manager.logger.config.level_for(&LogTarget::Stdout), LogLevel::Error);
+        assert_eq!(manager.logger.config.min_level(), LogLevel::Debug);
+
+        manager.log(LogLevel::Trace, "dropped".to_string(), HashMap::new());
+        manager.log(LogLevel::Debug, "debug".to_string(), HashMap::new());
+        assert_eq!(manager.logger.log_buffer.lock().unwrap().len(), 1);
+
+        let levels: Vec<LogLevel> = manager.logger.processors.lock().unwrap().iter().map(|p| p.min_level).collect();
+        assert_eq!(levels, vec![LogLevel::Warn, LogLevel::Debug]);
+        manager.logger.shutdown().unwrap();
+        let lines = read_log_lines(&dir.path().join("debug.log"), false);
+        assert_eq!(lines.len(), 1);
+        assert!(lines[0].ends_with("DEBUG webassembly_monitoring debug"));
+    }
+
+    fn alert_manager() -> AlertManager {
+        AlertManager::new(AlertConfig {
+            evaluation_interval: Duration::from_secs(10),
+            repeat_interval: Duration::from_secs(300),
+            max_alerts: 100,
+            silence_config: SilenceConfig {
+                silence_rules: Vec::new(),
+                default_silence_duration: Duration::from_secs(3600),
+            },
+            send_resolved: true,
+        })
+    }
+
+    fn alert_rule(id: &str, expression: &str, duration: Duration) -> AlertRule {
+        AlertRule {
+            id: id.to_string(),
+            name: id.to_string(),
+            expression: expression.to_string(),
+            duration,
+            severity: AlertSeverity::Warning,
+            labels: HashMap::new(),
+            annotations: HashMap::new(),
+        }
+    }
+
+    fn clocked_collector() -> (MetricsCollector, Arc<ManualClock>) {
+        let clock = Arc::new(ManualClock(AtomicU64::new(1_000)));
+        let collector = MetricsCollector::with_clock(MetricsConfig {
+            enabled: true,
+            collection_interval: Duration::from_secs(3600),
+            retention_period: Duration::from_secs(3600),
+            export_format: ExportFormat::Prometheus,
+        }, clock.clone());
+        (collector, clock)
+    }
+
+    #[test]
+    fn test_alert_pending_active_resolved_transitions() {
+        let (collector, clock) = clocked_collector();
+        let manager = alert_manager();
+        manager.add_rule(alert_rule("high_cpu", r#"cpu_usage{host="a"} > 80"#, Duration::from_secs(30))).unwrap();
+        let host = |name: &str| -> HashMap<String, String> { [("host".to_string(), name.to_string())].into_iter().collect() };
+        let state = || manager.alert_states.lock().unwrap().get("high_cpu").map(|state| state.state.clone());
+
+        collector.set_gauge("cpu_usage", 95.0, host("b")).unwrap();
+        assert!(manager.evaluate(&collector).unwrap().is_empty());
+        assert!(state().is_none());
+
+        collector.set_gauge("cpu_usage", 90.0, host("a")).unwrap();
+        let changed = manager.evaluate(&collector).unwrap();
+        assert_eq!(changed.len(), 1);
+        assert!(matches!(state(), Some(AlertStateType::Pending)));
+
+        clock.0.store(1_020, Ordering::SeqCst);
+        assert!(manager.evaluate(&collector).unwrap().is_empty());
+        assert!(matches!(state(), Some(AlertStateType::Pending)));
+
+        clock.0.store(1_030, Ordering::SeqCst);
+        assert_eq!(manager.evaluate(&collector).unwrap().len(), 1);
+        assert!(matches!(state(), Some(AlertStateType::Active)));
+
+        clock.0.store(1_040, Ordering::SeqCst);
+        collector.set_gauge("cpu_usage", 50.0, host("a")).unwrap();
+        let changed = manager.evaluate(&collector).unwrap();
+        assert!(matches!(changed[0].state, AlertStateType::Resolved));
+        assert_eq!(changed[0].start_time, 1_000);
+        assert_eq!(changed[0].end_time, Some(1_040));
+    }
+
+    #[test]
+    fn test_alert_pending_is_cancelled_and_aggregates_are_evaluated() {
+        let (collector, clock) = clocked_collector();
+        let manager = alert_manager();
+        manager.add_rule(alert_rule("flapping", "queue_depth > 10", Duration::from_secs(60))).unwrap();
+        manager.add_rule(alert_rule(
+            "traffic",
+            "rate(requests_total, 1m) > 5 and (avg_over(latency, 1m) >= 0.5 or latency > 2)",
+            Duration::ZERO,
+        )).unwrap();
+        let state = |id: &str| manager.alert_states.lock().unwrap().get(id).map(|state| state.state.clone());
+
+        collector.set_gauge("queue_depth", 20.0, HashMap::new()).unwrap();
+        collector.inc_counter("requests_total", 1, HashMap::new()).unwrap();
+        collector.set_gauge("latency", 0.9, HashMap::new()).unwrap();
+        manager.evaluate(&collector).unwrap();
+        assert!(matches!(state("flapping"), Some(AlertStateType::Pending)));
+        assert!(state("traffic").is_none());
+
+        clock.0.store(1_010, Ordering::SeqCst);
+        collector.set_gauge("queue_depth", 0.0, HashMap::new()).unwrap();
+        collector.inc_counter("requests_total", 100, HashMap::new()).unwrap();
+        collector.set_gauge("latency", 0.3, HashMap::new()).unwrap();
+        manager.evaluate(&collector).unwrap();
+        assert!(state("flapping").is_none());
+        assert!(matches!(state("traffic"), Some(AlertStateType::Active)));
+
+        clock.0.store(1_100, Ordering::SeqCst);
+        manager.evaluate(&collector).unwrap();
+        assert!(matches!(state("traffic"), Some(AlertStateType::Resolved)));
+    }
+
+    #[test]
+    fn test_invalid_alert_expressions_are_rejected() {
+        let manager = alert_manager();
+        for expression in [
+            "cpu_usage >",
+            "cpu_usage 80",
+            "cpu_usage > 80 and",
+            "median(cpu_usage, 1m) > 1",
+            "rate(requests_total, 5y) > 1",
+            r#"cpu_usage{host="a} > 1"#,
+            "(cpu_usage > 1",
+            "cpu_usage > 80 extra",
+        ] {
+            assert!(manager.add_rule(alert_rule("bad", expression, Duration::ZERO)).is_err(), "{}", expression);
+        }
+        assert!(manager.rules.lock().unwrap().is_empty());
+        assert_eq!(
+            parse_alert_expression(r#"a{x!="1", y="2"} <= 3"#).unwrap(),
+            AlertExpr::Compare(
+                AlertOperand::Instant(MetricSelector {
+                    name: "a".to_string(),
+                    matchers: vec![("x".to_string(), false, "1".to_string()), ("y".to_string(), true, "2".to_string())],
+                }),
+                CompareOp::Le,
+                3.0,
+            ),
+        );
+    }
+
+    /// 依次按给定状态码响应的 HTTP 服务，返回收到的请求原文
+    fn mock_http_server(statuses: Vec<u16>) -> (String, std::thread::JoinHandle<Vec<String>>) {
+        use std::io::{Read, Write};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hooks/alerts", listener.local_addr().unwrap());
+        let handle = std::thread::spawn(move || {
+            let mut requests = Vec::new();
+            for status in statuses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut request = Vec::new();
+                let mut buffer = [0u8; 4096];
+                loop {
+                    let read = stream.read(&mut buffer).unwrap();
+                    request.extend_from_slice(&buffer[..read]);
+                    let text = String::from_utf8_lossy(&request).to_string();
+                    if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                        let length: usize = head.lines()
+                            .find_map(|line| line.strip_prefix("Content-Length: "))
+                            .unwrap()
+                            .parse()
+                            .unwrap();
+                        if body.len() >= length {
+                            requests.push(text);
+                            break;
+                        }
+                    }
+                }
+                write!(stream, "HTTP/1.1 {} Mock\r\nContent-Length: 0\r\n\r\n", status).unwrap();
+            }
+            requests
+        });
+        (url, handle)
+    }
+
+    #[test]
+    fn test_hmac_sha256_matches_rfc4231() {
+        assert_eq!(
+            hmac_sha256_hex(b"Jefe", b"what do ya want for nothing?"),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843",
+        );
+    }
+
+    #[test]
+    fn test_webhook_channel_signs_payload_and_retries_server_errors() {
+        let (url, server) = mock_http_server(vec![503, 502, 200]);
+        let mut config = WebhookConfig::new("ops", url);
+        config.secret = Some("s3cret".to_string());
+        config.initial_backoff = Duration::from_millis(1);
+        let channel = WebhookChannel::new(config);
+
+        let alert = Alert {
+            id: "high_cpu".to_string(),
+            rule_id: "high_cpu".to_string(),
+            rule_name: "High CPU".to_string(),
+            severity: AlertSeverity::Critical,
+            state: AlertStateType::Active,
+            start_time: 1_000,
+            end_time: None,
+            labels: [("host".to_string(), "a".to_string())].into_iter().collect(),
+            annotations: [("summary".to_string(), "CPU above 80%".to_string())].into_iter().collect(),
+            description: "High CPU".to_string(),
+        };
+        channel.send_notification(&alert).unwrap();
+
+        let requests = server.join().unwrap();
+        assert_eq!(requests.len(), 3);
+        assert_eq!(requests[0], requests[2]);
+        let (head, body) = requests[2].split_once("\r\n\r\n").unwrap();
+        assert!(head.starts_with("POST /hooks/alerts HTTP/1.1\r\n"));
+        assert!(head.contains(&format!("X-Webhook-Signature: sha256={}", hmac_sha256_hex(b"s3cret", body.as_bytes()))));
+
+        let payload: serde_json::Value = serde_json::from_str(body).unwrap();
+        assert_eq!(payload["alert_id"], "high_cpu");
+        assert_eq!(payload["rule_name"], "High CPU");
+        assert_eq!(payload["severity"], "Critical");
+        assert_eq!(payload["state"], "Active");
+        assert_eq!(payload["labels"]["host"], "a");
+        assert_eq!(payload["annotations"]["summary"], "CPU above 80%");
+        assert_eq!(payload["start_time"], 1_000);
+        assert!(payload["end_time"].is_null());
+    }
+
+    #[test]
+    fn test_webhook_channel_gives_up_on_client_errors_and_exhausted_retries() {
+        let (url, server) = mock_http_server(vec![400]);
+        let channel = WebhookChannel::new(WebhookConfig::new("ops", url));
+        assert!(channel.test_connection().is_err());
+        let requests = server.join().unwrap();
+        assert_eq!(requests.len(), 1);
+        assert!(!requests[0].contains("X-Webhook-Signature"));
+        assert!(requests[0].contains("\"alert_id\":\"ping\""));
+
+        let (url, server) = mock_http_server(vec![500, 500]);
+        let mut config = WebhookConfig::new("ops", url);
+        config.max_retries = 1;
+        config.initial_backoff = Duration::from_millis(1);
+        assert!(WebhookChannel::new(config).test_connection().is_err());
+        assert_eq!(server.join().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_webhook_send_queues_instead_of_waiting_for_delivery() {
+        use std::io::{Read, Write};
+
+        // 端点在 send_notification 返回后才接受连接；同步发送会在读超时后失败
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut config = WebhookConfig::new("ops", format!("http://{}/hooks/alerts", listener.local_addr().unwrap()));
+        config.timeout = Duration::from_secs(2);
+        config.max_retries = 0;
+        config.queue_capacity = 1;
+        let channel = WebhookChannel::new(config);
+        let alert = Alert {
+            id: "queued".to_string(),
+            rule_id: "queued".to_string(),
+            rule_name: "Queued".to_string(),
+            severity: AlertSeverity::Warning,
+            state: AlertStateType::Active,
+            start_time: 1_000,
+            end_time: None,
+            labels: HashMap::new(),
+            annotations: HashMap::new(),
+            description: "Queued".to_string(),
+        };
+        channel.send_notification(&alert).unwrap();
+
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut request = Vec::new();
+        let mut buffer = [0u8; 4096];
+        while !request.ends_with(b"}") {
+            let read = stream.read(&mut buffer).unwrap();
+            request.extend_from_slice(&buffer[..read]);
+        }
+        // 投递线程正等待响应，队列还能再放一条，第三条被拒绝
+        channel.send_notification(&alert).unwrap();
+        assert!(matches!(channel.send_notification(&alert), Err(NotificationError::SendError(message)) if message.contains("已满")));
+        write!(stream, "HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n").unwrap();
+        drop(stream);
+
+        let (mut stream, _) = listener.accept().unwrap();
+        let read = stream.read(&mut buffer).unwrap();
+        assert!(String::from_utf8_lossy(&buffer[..read]).starts_with("POST /hooks/alerts"));
+        write!(stream, "HTTP/1.1 500 Oops\r\nContent-Length: 0\r\n\r\n").unwrap();
+        drop(stream);
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while channel.delivered_count() + channel.failed_count() < 2 && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        assert_eq!((channel.delivered_count(), channel.failed_count()), (1, 1));
+    }
+
+    #[test]
+    fn test_webhook_drop_gives_up_on_a_dead_endpoint_after_the_shutdown_timeout() {
+        // 端口关闭，每次连接立即被拒绝；不设截止时间时每条告警要退避 1 + 2 + 4 秒
+        let address = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        let mut config = WebhookConfig::new("ops", format!("http://{}/hooks/alerts", address));
+        config.initial_backoff = Duration::from_secs(1);
+        config.shutdown_timeout = Duration::from_millis(200);
+        let channel = WebhookChannel::new(config);
+        let alert = Alert {
+            id: "dead".to_string(),
+            rule_id: "dead".to_string(),
+            rule_name: "Dead".to_string(),
+            severity: AlertSeverity::Critical,
+            state: AlertStateType::Active,
+            start_time: 1_000,
+            end_time: None,
+            labels: HashMap::new(),
+            annotations: HashMap::new(),
+            description: "Dead endpoint".to_string(),
+        };
+        for _ in 0..10 {
+            channel.send_notification(&alert).unwrap();
+        }
+        let (delivered, failed) = (Arc::clone(&channel.worker.delivered), Arc::clone(&channel.worker.failed));
+
+        let start = Instant::now();
+        drop(channel);
+        assert!(start.elapsed() < Duration::from_secs(1), "释放渠道耗时 {:?}", start.elapsed());
+        assert_eq!((delivered.load(Ordering::Relaxed), failed.load(Ordering::Relaxed)), (0, 10));
+    }
+
+    struct RecordingChannel(Arc<Mutex<Vec<Alert>>>);
+
+    impl NotificationChannel for RecordingChannel {
+        fn send_notification(&self, alert: &Alert) -> Result<(), NotificationError> {
+            self.0.lock().unwrap().push(alert.clone());
+            Ok(())
+        }
+
+        fn get_name(&self) -> String {
+            "recording".to_string()
+        }
+
+        fn test_connection(&self) -> Result<(), NotificationError> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_alert_manager_dispatches_firing_and_resolution() {
+        let (collector, clock) = clocked_collector();
+        for send_resolved in [true, false] {
+            let mut manager = alert_manager();
+            manager.config.send_resolved = send_resolved;
+            let sent = Arc::new(Mutex::new(Vec::new()));
+            manager.add_notification_channel(Box::new(RecordingChannel(Arc::clone(&sent))));
+            let mut rule = alert_rule("queue", "queue_depth > 10", Duration::ZERO);
+            rule.annotations.insert("description".to_string(), "Queue is backing up".to_string());
+            manager.add_rule(rule).unwrap();
+
+            clock.0.store(1_000, Ordering::SeqCst);
+            collector.set_gauge("queue_depth", 20.0, HashMap::new()).unwrap();
+            manager.evaluate(&collector).unwrap();
+            clock.0.store(1_010, Ordering::SeqCst);
+            manager.evaluate(&collector).unwrap();
+            collector.set_gauge("queue_depth", 0.0, HashMap::new()).unwrap();
+            manager.evaluate(&collector).unwrap();
+
+            let sent = sent.lock().unwrap();
+            let states: Vec<&AlertStateType> = sent.iter().map(|alert| &alert.state).collect();
+            if send_resolved {
+                assert!(matches!(states[..], [AlertStateType::Active, AlertStateType::Resolved]));
+                assert_eq!(sent[1].end_time, Some(1_010));
+            } else {
+                assert!(matches!(states[..], [AlertStateType::Active]));
+            }
+            assert_eq!(sent[0].description, "Queue is backing up");
+        }
+    }
+
+    fn silence(matchers: Vec<Matcher>, start_time: u64, end_time: u64) -> SilenceRule {
+        SilenceRule {
+            id: String::new(),
+            matchers,
+            start_time,
+            end_time,
+            created_by: "ops".to_string(),
+            comment: "maintenance".to_string(),
+        }
+    }
+
+    fn disk_full_rule() -> AlertRule {
+        let mut rule = alert_rule("disk_full", "disk_usage > 90", Duration::ZERO);
+        rule.labels.insert("team".to_string(), "storage-eu".to_string());
+        rule
+    }
+
+    #[test]
+    fn test_exact_match_silence_suppresses_notification() {
+        let (collector, _clock, manager, sent) = shared_clock_alert_manager();
+        manager.add_rule(disk_full_rule()).unwrap();
+        let id = manager.add_silence(silence(vec![Matcher {
+            name: "alertname".to_string(),
+            value: "disk_full".to_string(),
+            is_regex: false,
+        }], 900, 2_000)).unwrap();
+        assert_eq!(manager.list_active_silences().len(), 1);
+        assert_eq!(manager.list_active_silences()[0].id, id);
+
+        collector.set_gauge("disk_usage", 95.0, HashMap::new()).unwrap();
+        let changed = manager.evaluate(&collector).unwrap();
+        assert!(matches!(changed[0].state, AlertStateType::Active));
+        assert!(sent.lock().unwrap().is_empty());
+        assert!(manager.alert_states.lock().unwrap()["disk_full"].suppressed);
+        assert_eq!(manager.suppressed_count(), 1);
+
+        // 不匹配的标签值不会静默
+        let other = manager.add_silence(silence(vec![Matcher {
+            name: "team".to_string(),
+            value: "storage".to_string(),
+            is_regex: false,
+        }], 900, 2_000)).unwrap();
+        manager.expire_silence(&id).unwrap();
+        assert_eq!(manager.list_active_silences()[0].id, other);
+        collector.set_gauge("disk_usage", 10.0, HashMap::new()).unwrap();
+        manager.evaluate(&collector).unwrap();
+        assert_eq!(sent.lock().unwrap().len(), 1);
+        assert!(!manager.alert_states.lock().unwrap()["disk_full"].suppressed);
+    }
+
+    #[test]
+    fn test_regex_silence_suppresses_notification() {
+        let (collector, _clock, manager, sent) = shared_clock_alert_manager();
+        manager.add_rule(disk_full_rule()).unwrap();
+        let invalid = silence(vec![Matcher { name: "team".to_string(), value: "(".to_string(), is_regex: true }], 900, 2_000);
+        assert!(manager.add_silence(invalid).is_err());
+        // 正则表达式需要完整匹配标签值
+        let partial = silence(vec![Matcher { name: "team".to_string(), value: "storage".to_string(), is_regex: true }], 900, 2_000);
+        manager.add_silence(partial).unwrap();
+        let regex = silence(vec![Matcher { name: "team".to_string(), value: "storage-.*".to_string(), is_regex: true }], 900, 2_000);
+        manager.add_silence(regex).unwrap();
+
+        collector.set_gauge("disk_usage", 95.0, HashMap::new()).unwrap();
+        manager.evaluate(&collector).unwrap();
+        assert!(sent.lock().unwrap().is_empty());
+        assert_eq!(manager.suppressed_count(), 1);
+        assert_eq!(manager.silence_patterns.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_expired_silence_stops_suppressing() {
+        let (collector, clock, manager, sent) = shared_clock_alert_manager();
+        manager.add_rule(disk_full_rule()).unwrap();
+        manager.add_silence(silence(vec![Matcher {
+            name: "alertname".to_string(),
+            value: "disk_full".to_string(),
+            is_regex: false,
+        }], 900, 1_100)).unwrap();
+
+        collector.set_gauge("disk_usage", 95.0, HashMap::new()).unwrap();
+        manager.evaluate(&collector).unwrap();
+        assert!(sent.lock().unwrap().is_empty());
+
+        clock.0.store(1_200, Ordering::SeqCst);
+        assert!(manager.list_active_silences().is_empty());
+        collector.set_gauge("disk_usage", 10.0, HashMap::new()).unwrap();
+        let changed = manager.evaluate(&collector).unwrap();
+        assert!(matches!(changed[0].state, AlertStateType::Resolved));
+        assert_eq!(sent.lock().unwrap().len(), 1);
+        assert!(manager.silences.lock().unwrap().is_empty());
+        assert_eq!(manager.suppressed_count(), 1);
+    }
+
+    fn shared_clock_alert_manager() -> (MetricsCollector, Arc<ManualClock>, AlertManager, Arc<Mutex<Vec<Alert>>>) {
+        let (collector, clock) = clocked_collector();
+        let shared: Arc<dyn MetricsClock> = clock.clone();
+        let manager = alert_manager().with_clock(shared);
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        manager.add_notification_channel(Box::new(RecordingChannel(Arc::clone(&sent))));
+        (collector, clock, manager, sent)
+    }
+
+    #[test]
+    fn test_firing_alert_repeats_once_per_repeat_interval() {
+        let (collector, clock, manager, sent) = shared_clock_alert_manager();
+        manager.add_rule(alert_rule("latency", "latency_ms > 500", Duration::ZERO)).unwrap();
+        collector.set_gauge("latency_ms", 900.0, HashMap::new()).unwrap();
+
+        // 20 个评估周期，每 60 秒一次，重复间隔 300 秒：在 0、300、600、900 秒通知
+        for tick in 0..20 {
+            clock.0.store(1_000 + tick * 60, Ordering::SeqCst);
+            manager.evaluate(&collector).unwrap();
+        }
+        assert_eq!(sent.lock().unwrap().len(), 4);
+        assert!(sent.lock().unwrap().iter().all(|alert| matches!(alert.state, AlertStateType::Active)));
+
+        collector.set_gauge("latency_ms", 100.0, HashMap::new()).unwrap();
+        for tick in 20..23 {
+            clock.0.store(1_000 + tick * 60, Ordering::SeqCst);
+            manager.evaluate(&collector).unwrap();
+        }
+        let sent = sent.lock().unwrap();
+        assert_eq!(sent.len(), 5);
+        assert!(matches!(sent[4].state, AlertStateType::Resolved));
+    }
+
+    #[test]
+    fn test_severity_escalation_renotifies_immediately() {
+        let (collector, clock, manager, sent) = shared_clock_alert_manager();
+        manager.add_rule(alert_rule("errors", "error_rate > 0.1", Duration::ZERO)).unwrap();
+        collector.set_gauge("error_rate", 0.5, HashMap::new()).unwrap();
+        manager.evaluate(&collector).unwrap();
+
+        clock.0.store(1_060, Ordering::SeqCst);
+        manager.evaluate(&collector).unwrap();
+        assert_eq!(sent.lock().unwrap().len(), 1);
+
+        let mut escalated = alert_rule("errors", "error_rate > 0.1", Duration::ZERO);
+        escalated.severity = AlertSeverity::Critical;
+        manager.add_rule(escalated).unwrap();
+        clock.0.store(1_120, Ordering::SeqCst);
+        manager.evaluate(&collector).unwrap();
+        clock.0.store(1_180, Ordering::SeqCst);
+        manager.evaluate(&collector).unwrap();
+
+        let sent = sent.lock().unwrap();
+        assert_eq!(sent.len(), 2);
+        assert_eq!(sent[1].severity, AlertSeverity::Critical);
+    }
+
+    #[test]
+    fn test_max_alerts_drops_lowest_severity_new_alerts() {
+        let (collector, _clock, mut manager, sent) = shared_clock_alert_manager();
+        manager.config.max_alerts = 2;
+        for (id, severity) in [("info", AlertSeverity::Info), ("critical", AlertSeverity::Critical), ("error", AlertSeverity::Error)] {
+            let mut rule = alert_rule(id, "load > 1", Duration::ZERO);
+            rule.severity = severity;
+            manager.add_rule(rule).unwrap();
+        }
+        collector.set_gauge("load", 4.0, HashMap::new()).unwrap();
+        manager.evaluate(&collector).unwrap();
+
+        let mut notified: Vec<String> = sent.lock().unwrap().iter().map(|alert| alert.rule_id.clone()).collect();
+        notified.sort();
+        assert_eq!(notified, ["critical", "error"]);
+        assert_eq!(manager.dropped_count(), 1);
+        assert!(!manager.alert_states.lock().unwrap().contains_key("info"));
+    }
+
+    #[test]
+    fn test_label_fingerprint_is_order_independent() {
+        let forward: HashMap<String, String> = [("a", "1"), ("b", "2")].iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        let mut backward = HashMap::new();
+        backward.insert("b".to_string(), "2".to_string());
+        backward.insert("a".to_string(), "1".to_string());
+        assert_eq!(label_fingerprint(&forward), label_fingerprint(&backward));
+
+        let shifted: HashMap<String, String> = [("ab", ""), ("", "2")].iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        assert_ne!(label_fingerprint(&forward), label_fingerprint(&shifted));
+        assert_eq!(label_fingerprint(&HashMap::new()), 0xcbf2_9ce4_8422_2325);
+    }
+
+    fn performance_series(name: &str, values: &[f64]) -> Vec<PerformanceMetric> {
+        values.iter().enumerate().map(|(i, value)| PerformanceMetric {
+            name: name.to_string(),
+            value: *value,
+            timestamp: 1_000 + i as u64 * 60,
+            labels: HashMap::new(),
+            metadata: PerformanceMetadata {
+                min_value: *value,
+                max_value: *value,
+                avg_value: *value,
+                percentiles: HashMap::new(),
+                sample_count: 1,
+            },
+        }).collect()
+    }
+
+    #[test]
+    fn test_sustained_threshold_breaches_become_bottlenecks() {
+        let analyzer = StatisticalAnalyzer::new();
+        let mut metrics = performance_series("cpu_usage", &[95.0; 7]);
+        metrics.extend(performance_series("memory_usage", &[90.0, 90.0, 90.0, 90.0, 90.0, 90.0]));
+        // 只超限 2 分钟，不足 5 分钟窗口
+        metrics.extend(performance_series("network_latency_ms", &[100.0, 400.0, 400.0, 400.0, 100.0, 100.0]));
+
+        let analysis = analyzer.analyze(&metrics).unwrap();
+        let bottlenecks = &analysis.results.bottlenecks;
+        assert_eq!(bottlenecks.len(), 2);
+        assert!(matches!(bottlenecks[0].bottleneck_type, BottleneckType::CPU));
+        assert!((bottlenecks[0].severity - 15.0 / 80.0).abs() < 1e-9);
+        assert!(matches!(bottlenecks[1].bottleneck_type, BottleneckType::Memory));
+        assert!((bottlenecks[1].severity - 5.0 / 85.0).abs() < 1e-9);
+        assert!(bottlenecks.iter().all(|bottleneck| !bottleneck.suggestions.is_empty()));
+
+        assert_eq!(analysis.recommendations.len(), 2);
+        assert!(matches!(analysis.recommendations[0].recommendation_type, RecommendationType::CodeOptimization));
+        assert_eq!(analysis.recommendations[0].priority, RecommendationPriority::Low);
+
+        let mut network = StatisticalAnalyzer::new();
+        network.window_size = Duration::from_secs(120);
+        let analysis = network.analyze(&performance_series("network_latency_ms", &[100.0, 400.0, 400.0, 400.0])).unwrap();
+        assert!(matches!(analysis.results.bottlenecks[0].bottleneck_type, BottleneckType::Network));
+        assert_eq!(analysis.results.bottlenecks[0].severity, 1.0);
+        assert_eq!(analysis.recommendations[0].priority, RecommendationPriority::Critical);
+    }
+
+    #[test]
+    fn test_performance_score_decreases_with_worse_metrics() {
+        let analyzer = StatisticalAnalyzer::with_config(&PerformanceConfig {
+            analysis_interval: Duration::from_secs(60),
+            window_size: Duration::from_secs(300),
+            thresholds: [("queue_depth".to_string(), 100.0)].into_iter().collect(),
+            anomaly_detection: AnomalyDetectionConfig {
+                enabled: false,
+                sensitivity: 0.5,
+                algorithm: AnomalyDetectionAlgorithm::Statistical,
+                training_data_size: 100,
+            },
+        });
+        assert_eq!(analyzer.analyze(&[]).unwrap().results.performance_score, 100.0);
+
+        let scores: Vec<f64> = [30.0, 60.0, 80.0, 95.0, 120.0].iter().map(|cpu| {
+            let mut metrics = performance_series("cpu_usage", &[*cpu; 6]);
+            metrics.extend(performance_series("queue_depth", &[cpu * 1.25; 6]));
+            analyzer.analyze(&metrics).unwrap().results.performance_score
+        }).collect();
+        assert_eq!(scores[0], 100.0);
+        assert!(scores.windows(2).all(|pair| pair[1] < pair[0]), "{:?}", scores);
+        assert_eq!(scores[4], 0.0);
+        assert!(analyzer.analyze(&performance_series("cpu_usage", &[f64::NAN])).is_err());
+    }
+
+    #[test]
+    fn test_growing_series_yields_increasing_trend() {
+        let analyzer = StatisticalAnalyzer::new();
+        let values: Vec<f64> = (0..10).map(|i| 50.0 + i as f64).collect();
+        let mut metrics = performance_series("memory_usage", &values);
+        // 样本数不足时不产生趋势
+        metrics.extend(performance_series("requests_total", &[1.0, 2.0, 3.0]));
+        let analysis = analyzer.analyze(&metrics).unwrap();
+
+        assert_eq!(analysis.results.trends.len(), 1);
+        let trend = &analysis.results.trends[0];
+        assert_eq!(trend.metric_name, "memory_usage");
+        assert!(matches!(trend.direction, TrendDirection::Increasing));
+        // 最近 5 分钟的均值 56.5，每小时增长 60
+        assert!((trend.change_rate - 6000.0 / 56.5).abs() < 1e-6, "{}", trend.change_rate);
+        assert!(trend.confidence > 0.99);
+        assert_eq!(analysis.recommendations.len(), 1);
+        assert!(matches!(analysis.recommendations[0].recommendation_type, RecommendationType::ResourceAdjustment));
+
+        let falling: Vec<f64> = values.iter().rev().copied().collect();
+        let analysis = analyzer.analyze(&performance_series("error_rate", &falling)).unwrap();
+        assert!(matches!(analysis.results.trends[0].direction, TrendDirection::Decreasing));
+        assert!(analysis.recommendations.is_empty());
+    }
+
+    #[test]
+    fn test_white_noise_yields_stable_trend() {
+        let mut analyzer = StatisticalAnalyzer::new();
+        analyzer.window_size = Duration::from_secs(3600);
+        let mut rng = StdRng::seed_from_u64(7);
+        let noise: Vec<f64> = (0..60).map(|_| 50.0 + rng.random_range(-5.0..5.0)).collect();
+        let analysis = analyzer.analyze(&performance_series("error_rate", &noise)).unwrap();
+
+        let trend = &analysis.results.trends[0];
+        assert!(matches!(trend.direction, TrendDirection::Stable), "{:?}", trend);
+        assert!(trend.confidence < 0.5);
+        assert!(analysis.recommendations.is_empty());
+
+        let flat = analyzer.analyze(&performance_series("cpu_usage", &[40.0; 6])).unwrap();
+        assert!(matches!(flat.results.trends[0].direction, TrendDirection::Stable));
+        assert_eq!(flat.results.trends[0].change_rate, 0.0);
+    }
+
+    #[test]
+    fn test_correlated_series_are_reported() {
+        let analyzer = StatisticalAnalyzer::new();
+        let memory: Vec<f64> = (0..10).map(|i| 50.0 + i as f64).collect();
+        let latency: Vec<f64> = memory.iter().map(|value| value * 2.0 + 100.0).collect();
+        let idle: Vec<f64> = memory.iter().map(|value| 100.0 - value).collect();
+        let mut metrics = performance_series("memory_usage", &memory);
+        metrics.extend(performance_series("network_latency_ms", &latency));
+        metrics.extend(performance_series("cpu_idle", &idle));
+        let analysis = analyzer.analyze(&metrics).unwrap();
+
+        let correlation = |first: &str, second: &str| analysis.results.correlations.iter()
+            .find(|c| (c.metric1 == first && c.metric2 == second) || (c.metric1 == second && c.metric2 == first))
+            .unwrap();
+        let positive = correlation("memory_usage", "network_latency_ms");
+        assert!((positive.correlation_coefficient - 1.0).abs() < 1e-9);
+        assert!(positive.significance > 0.99);
+        assert!(positive.interpretation.contains("正相关"));
+        let negative = correlation("cpu_idle", "memory_usage");
+        assert!((negative.correlation_coefficient + 1.0).abs() < 1e-9);
+        assert!(negative.interpretation.contains("负相关"));
+        assert_eq!(analysis.results.correlations.len(), 3);
+    }
+
+    #[test]
+    fn test_independent_or_sparse_series_have_no_strong_correlation() {
+        let mut analyzer = StatisticalAnalyzer::new();
+        let mut rng = StdRng::seed_from_u64(11);
+        let mut metrics = performance_series("cpu_usage", &(0..200).map(|_| rng.random_range(0.0..1.0)).collect::<Vec<_>>());
+        metrics.extend(performance_series("disk_usage", &(0..200).map(|_| rng.random_range(0.0..1.0)).collect::<Vec<_>>()));
+        assert!(analyzer.analyze(&metrics).unwrap().results.correlations.is_empty());
+
+        analyzer.correlation_threshold = 0.0;
+        analyzer.correlation_pairs = vec![("cpu_usage".to_string(), "disk_usage".to_string())];
+        let correlations = analyzer.analyze(&metrics).unwrap().results.correlations;
+        assert_eq!(correlations.len(), 1);
+        assert!(correlations[0].correlation_coefficient.abs() < 0.2, "{:?}", correlations[0]);
+
+        // 重叠的时间桶不足时不产生结果
+        let mut sparse = performance_series("cpu_usage", &[1.0, 2.0, 3.0, 4.0, 5.0]);
+        let mut shifted = performance_series("disk_usage", &[1.0, 2.0, 3.0, 4.0, 5.0]);
+        shifted.iter_mut().for_each(|metric| metric.timestamp += 180);
+        sparse.extend(shifted);
+        assert!(analyzer.analyze(&sparse).unwrap().results.correlations.is_empty());
+    }
+
+    struct MockHealthCheck {
+        name: &'static str,
+        statuses: Mutex<VecDeque<HealthStatus>>,
+        calls: Arc<AtomicU64>,
+        delay: Duration,
+    }
+
+    impl MockHealthCheck {
+        fn new(name: &'static str, statuses: &[HealthStatus]) -> Self {
+            Self {
+                name,
+                statuses: Mutex::new(statuses.iter().copied().collect()),
+                calls: Arc::new(AtomicU64::new(0)),
+                delay: Duration::ZERO,
+            }
+        }
+    }
+
+    impl HealthCheck for MockHealthCheck {
+        fn check(&self) -> Result<HealthCheckResult, HealthCheckError> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            std::thread::sleep(self.delay);
+            let mut statuses = self.statuses.lock().unwrap();
+            let status = if statuses.len() > 1 { statuses.pop_front().unwrap() } else { statuses[0] };
+            if status == HealthStatus::Unknown {
+                return Err(HealthCheckError::ConnectionError(self.name.to_string()));
+            }
+            Ok(health_check_result(self.name, status, Instant::now(), String::new()))
+        }
+
+        fn get_name(&self) -> String {
+            self.name.to_string()
+        }
+
+        fn get_description(&self) -> String {
+            "mock".to_string()
+        }
+    }
+
+    fn health_checker(retry_count: u32) -> HealthChecker {
+        HealthChecker::new(HealthCheckConfig {
+            check_interval: Duration::from_secs(30),
+            timeout: Duration::from_millis(200),
+            retry_count,
+            health_threshold: 0.8,
+            flap_threshold: 1,
+        })
+    }
+
+    #[test]
+    fn test_health_status_aggregation() {
+        use HealthStatus::*;
+        let checker = health_checker(0);
+        assert_eq!(checker.status(), Unknown);
+        assert_eq!(checker.run_all().status, Unknown);
+
+        checker.register(Box::new(MockHealthCheck::new("a", &[Healthy])));
+        assert_eq!(checker.run_all().status, Healthy);
+        checker.register(Box::new(MockHealthCheck::new("b", &[Degraded])));
+        assert_eq!(checker.run_all().status, Degraded);
+        checker.register(Box::new(MockHealthCheck::new("c", &[Unhealthy])));
+        let report = checker.run_all();
+        assert_eq!(report.status, Unhealthy);
+        assert_eq!(checker.status(), Unhealthy);
+        assert_eq!(report.checks.len(), 3);
+
+        let json = serde_json::to_value(checker.report().unwrap()).unwrap();
+        assert_eq!(json["status"], "Unhealthy");
+        assert_eq!(json["checks"][1]["name"], "b");
+    }
+
+    #[test]
+    fn test_health_check_retries_then_passes() {
+        use HealthStatus::*;
+        let checker = health_checker(2);
+        let flaky = MockHealthCheck::new("flaky", &[Unknown, Unhealthy, Healthy]);
+        let calls = Arc::clone(&flaky.calls);
+        checker.register(Box::new(flaky));
+        let report = checker.run_all();
+        assert_eq!(report.status, Healthy);
+        assert_eq!(report.checks[0].attempts, 3);
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+        // 重试次数用尽后保留最后一次的错误
+        let checker = health_checker(1);
+        let mut slow = MockHealthCheck::new("slow", &[Healthy]);
+        slow.delay = Duration::from_millis(500);
+        checker.register(Box::new(slow));
+        let report = checker.run_all();
+        assert_eq!(report.status, Unhealthy);
+        assert_eq!(report.checks[0].attempts, 2);
+        assert!(report.checks[0].error.as_deref().unwrap().contains("超时"));
+    }
+
+    #[test]
+    fn test_builtin_health_checks() {
+        let runtime = Arc::new(Mutex::new(WebAssembly2Runtime::new()));
+        let runtime_check = RuntimeHealthCheck::new(Arc::clone(&runtime), Duration::from_secs(1));
+        assert_eq!(runtime_check.check().unwrap().status, HealthStatus::Healthy);
+        assert_eq!(runtime_check.check().unwrap().status, HealthStatus::Healthy);
+        assert_eq!(runtime.lock().unwrap().modules.len(), 1);
+
+        if process_rss_bytes().is_some() {
+            assert_eq!(MemoryHealthCheck::new(u64::MAX).check().unwrap().status, HealthStatus::Healthy);
+            assert_eq!(MemoryHealthCheck::new(1).check().unwrap().status, HealthStatus::Unhealthy);
+        }
+
+        let (collector, clock) = clocked_collector();
+        let mut freshness = MetricsFreshnessCheck::new(collector.clone());
+        freshness.max_age = Duration::from_secs(60);
+        assert_eq!(freshness.check().unwrap().status, HealthStatus::Degraded);
+        collector.set_gauge("queue_depth", 1.0, HashMap::new()).unwrap();
+        clock.0.store(1_060, Ordering::SeqCst);
+        assert_eq!(freshness.check().unwrap().status, HealthStatus::Healthy);
+        clock.0.store(1_061, Ordering::SeqCst);
+        assert_eq!(freshness.check().unwrap().status, HealthStatus::Unhealthy);
+    }
+
+    #[test]
+    fn test_health_status_flips_after_consecutive_results() {
+        use HealthStatus::*;
+        let mut checker = health_checker(0);
+        checker.config.flap_threshold = 2;
+        checker.register(Box::new(MockHealthCheck::new("steady", &[Healthy])));
+        checker.register(Box::new(MockHealthCheck::new(
+            "toggle",
+            &[Healthy, Healthy, Unhealthy, Healthy, Unhealthy, Unhealthy, Healthy, Healthy],
+        )));
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let recorded = Arc::clone(&events);
+        checker.on_status_change(move |change| recorded.lock().unwrap().push(change.clone()));
+
+        let statuses: Vec<HealthStatus> = (0..8).map(|_| checker.run_all().status).collect();
+        assert_eq!(statuses, [Unknown, Healthy, Healthy, Healthy, Healthy, Unhealthy, Unhealthy, Healthy]);
+        let events = events.lock().unwrap();
+        assert_eq!(*events, [
+            HealthStatusChanged { from: Unknown, to: Healthy, failing_checks: Vec::new() },
+            HealthStatusChanged { from: Healthy, to: Unhealthy, failing_checks: vec!["toggle".to_string()] },
+            HealthStatusChanged { from: Unhealthy, to: Healthy, failing_checks: Vec::new() },
+        ]);
+    }
+
+    #[test]
+    fn test_health_threshold_requires_passing_fraction() {
+        use HealthStatus::*;
+        assert_eq!(aggregate_health_status([Healthy, Healthy, Healthy, Healthy, Unknown], 0.8), Healthy);
+        assert_eq!(aggregate_health_status([Healthy, Healthy, Healthy, Unknown], 0.8), Degraded);
+        assert_eq!(aggregate_health_status([Unknown, Unknown], 0.8), Unknown);
+        assert_eq!(aggregate_health_status([Healthy, Degraded, Unhealthy], 0.0), Unhealthy);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_health_loop_runs_until_stopped() {
+        let mut checker = health_checker(0);
+        checker.config.check_interval = Duration::from_millis(10);
+        let check = MockHealthCheck::new("loop", &[HealthStatus::Healthy]);
+        let calls = Arc::clone(&check.calls);
+        checker.register(Box::new(check));
+
+        checker.start();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        checker.stop();
+        assert_eq!(checker.status(), HealthStatus::Healthy);
+        let stopped_at = calls.load(Ordering::SeqCst);
+        assert!(stopped_at >= 2, "{}", stopped_at);
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert!(calls.load(Ordering::SeqCst) <= stopped_at + 1);
+    }
+
+    #[test]
+    fn test_no_status_change_is_reported_after_stop_returns() {
+        use HealthStatus::*;
+        let mut checker = health_checker(0);
+        checker.config.check_interval = Duration::from_millis(1);
+        let flapping: Vec<HealthStatus> = (0..1000).map(|round| if round % 2 == 0 { Healthy } else { Unhealthy }).collect();
+        checker.register(Box::new(MockHealthCheck::new("flapping", &flapping)));
+        let events = Arc::new(AtomicU64::new(0));
+        let recorded = Arc::clone(&events);
+        checker.on_status_change(move |_| {
+            recorded.fetch_add(1, Ordering::SeqCst);
+        });
+
+        checker.start();
+        std::thread::sleep(Duration::from_millis(30));
+        checker.stop();
+        let (status, reported) = (checker.status(), events.load(Ordering::SeqCst));
+        assert!(reported >= 2, "{}", reported);
+        std::thread::sleep(Duration::from_millis(30));
+        assert_eq!((checker.status(), events.load(Ordering::SeqCst)), (status, reported));
+    }
+
+    #[test]
+    fn test_health_loop_restarts_and_drop_join_the_thread() {
+        let mut checker = health_checker(0);
+        checker.config.check_interval = Duration::from_millis(5);
+        let check = MockHealthCheck::new("loop", &[HealthStatus::Healthy]);
+        let calls = Arc::clone(&check.calls);
+        checker.register(Box::new(check));
+
+        // 重复启动会先停止上一个线程，只剩一个线程在运行
+        checker.start();
+        checker.start();
+        std::thread::sleep(Duration::from_millis(50));
+        checker.stop();
+        let stopped_at = calls.load(Ordering::SeqCst);
+        std::thread::sleep(Duration::from_millis(30));
+        assert_eq!(calls.load(Ordering::SeqCst), stopped_at);
+
+        checker.start();
+        std::thread::sleep(Duration::from_millis(30));
+        drop(checker);
+        let dropped_at = calls.load(Ordering::SeqCst);
+        assert!(dropped_at > stopped_at);
+        std::thread::sleep(Duration::from_millis(30));
+        assert_eq!(calls.load(Ordering::SeqCst), dropped_at);
+    }
 }
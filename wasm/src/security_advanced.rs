@@ -10,7 +10,7 @@
 use crate::types::*;
 // use crate::webassembly_2_0::*; // 暂时注释掉未使用的导入
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant, SystemTime};
 use thiserror::Error;
@@ -83,6 +83,23 @@ pub struct SecurityEvent {
     pub details: String,
     /// 堆栈跟踪
     pub stack_trace: Vec<String>,
+    /// 触发事件的已认证调用方
+    #[serde(default)]
+    pub principal: Option<Principal>,
+}
+
+/// 模块签名校验的结果
+/// Outcome of verifying a module signature
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum VerificationStatus {
+    /// 签名有效且签名密钥仍然有效
+    Verified,
+    /// 签名密钥未注册或已撤销
+    UnknownKey,
+    /// 签名与模块内容或元数据不符
+    BadSignature,
+    /// 模块没有签名
+    Unsigned,
 }
 
 /// 安全严重程度
@@ -258,6 +275,30 @@ pub struct SecurityContext {
     pub parameters: HashMap<String, Value>,
     /// 调用栈
     pub call_stack: Vec<StackFrame>,
+    /// 线程/实例ID（共享内存场景下用于区分访问者）
+    pub thread_id: Option<u32>,
+    /// 已认证的调用方（如网关验证过的 JWT 主体）
+    pub principal: Option<Principal>,
+}
+
+/// 已认证的调用方
+/// Authenticated principal
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Principal {
+    /// 主体标识
+    pub subject: String,
+    /// 签发方
+    pub issuer: Option<String>,
+    /// 授权范围
+    pub scopes: Vec<String>,
+}
+
+impl Principal {
+    /// 是否拥有指定授权范围
+    /// Whether the principal holds the given scope
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scopes.iter().any(|granted| granted == scope)
+    }
 }
 
 /// 操作类型
@@ -268,6 +309,8 @@ pub enum OperationType {
     MemoryRead,
     /// 内存写入
     MemoryWrite,
+    /// 原子内存操作
+    AtomicAccess,
     /// 函数调用
     FunctionCall,
     /// 模块加载
@@ -609,6 +652,7 @@ impl AdvancedSecurityManager {
             stack_trace: context.call_stack.iter()
                 .map(|frame| format!("{}:{}", frame.function_name, frame.call_address))
                 .collect(),
+            principal: context.principal.clone(),
         };
 
         if let Ok(mut log) = self.event_log.lock() {
@@ -616,6 +660,47 @@ impl AdvancedSecurityManager {
         }
     }
 
+    /// 代码完整性检查：按活动策略的安全级别决定是否接受签名状态为 `status` 的模块
+    /// Code integrity check: decide from the active policy's security level whether a module whose signature
+    /// verified as `status` is accepted
+    ///
+    /// 低级别接受全部模块；中级别拒绝签名错误的模块；高级别及以上只接受用受信任密钥校验通过的模块，
+    /// 未签名和签名密钥未知的模块都被拒绝。没有活动策略时按中级别处理。拒绝时记录一条安全事件。
+    /// Low accepts every module; Medium refuses bad signatures; High and above only accept modules verified against
+    /// a trusted key, refusing unsigned modules and unknown signing keys alike. Without an active policy Medium
+    /// applies. Refusals are recorded as security events.
+    pub fn check_integrity(&self, module: &str, status: VerificationStatus) -> Result<(), SecurityError> {
+        let level = self.active_policy.as_ref()
+            .and_then(|policy_id| self.policies.get(policy_id))
+            .map_or(SecurityLevel::Medium, |policy| policy.security_level);
+        let accepted = match status {
+            VerificationStatus::Verified => true,
+            VerificationStatus::UnknownKey => level < SecurityLevel::High,
+            VerificationStatus::Unsigned => level < SecurityLevel::High,
+            VerificationStatus::BadSignature => level < SecurityLevel::Medium,
+        };
+        if accepted {
+            return Ok(());
+        }
+
+        let details = format!("安全级别 {:?} 拒绝签名状态为 {:?} 的模块 {}", level, status, module);
+        if let Ok(mut log) = self.event_log.lock() {
+            log.push(SecurityEvent {
+                id: self.generate_event_id(),
+                threat_type: ThreatType::CodeInjection,
+                severity: SecuritySeverity::Error,
+                timestamp: SystemTime::now(),
+                module_id: None,
+                function_index: None,
+                memory_address: None,
+                details: details.clone(),
+                stack_trace: Vec::new(),
+                principal: None,
+            });
+        }
+        Err(SecurityError::IntegrityCheckFailed(details))
+    }
+
     /// 生成事件ID
     /// Generate event ID
     fn generate_event_id(&self) -> u64 {
@@ -1044,6 +1129,9 @@ pub enum SecurityError {
     /// 安全策略冲突
     #[error("安全策略冲突")]
     PolicyConflict,
+    /// 代码完整性检查未通过
+    #[error("代码完整性检查未通过: {0}")]
+    IntegrityCheckFailed(String),
 }
 
 /// 内置威胁检测器实现
@@ -1121,3 +1209,436 @@ impl ThreatDetector for CodeInjectionDetector {
         "CodeInjectionDetector".to_string()
     }
 }
+
+/// 竞态条件检测器
+/// Race Condition Detector
+///
+/// 针对共享内存，按地址块记录最近的读写访问；来自不同线程、范围重叠且至少
+/// 有一方为写入的访问对会被判定为竞态，除非两者之间出现过覆盖该范围的原子操作。
+/// Tracks recent accesses per address block of a shared memory and flags
+/// overlapping read/write or write/write pairs from different threads that are
+/// not separated by an atomic operation on the same range.
+#[derive(Debug)]
+pub struct RaceConditionDetector {
+    /// 每个地址块保留的访问历史长度
+    pub history_window: usize,
+    /// 最多跟踪的地址块数量，超出时淘汰最久未访问的块
+    pub max_tracked_blocks: usize,
+    state: Mutex<RaceDetectorState>,
+}
+
+/// 地址块粒度（字节，以 2 的幂表示）
+const RACE_BLOCK_SHIFT: u32 = 3;
+/// 单次访问最多覆盖的地址块数量
+const RACE_MAX_BLOCKS_PER_ACCESS: u32 = 16;
+/// 未指定 `access_size` 参数时的默认访问宽度（字节）
+const RACE_DEFAULT_ACCESS_SIZE: u32 = 4;
+
+#[derive(Debug, Default)]
+struct RaceDetectorState {
+    blocks: HashMap<u32, AddressHistory>,
+    tick: u64,
+}
+
+#[derive(Debug, Default)]
+struct AddressHistory {
+    accesses: VecDeque<MemoryAccessRecord>,
+    conflicts: u32,
+    last_touched: u64,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct MemoryAccessRecord {
+    thread_id: u32,
+    start: u32,
+    end: u32,
+    is_write: bool,
+}
+
+impl MemoryAccessRecord {
+    fn overlaps(&self, other: &MemoryAccessRecord) -> bool {
+        self.start < other.end && other.start < self.end
+    }
+}
+
+impl Default for RaceConditionDetector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RaceConditionDetector {
+    /// 创建新的竞态条件检测器
+    /// Create new race condition detector
+    pub fn new() -> Self {
+        Self::with_limits(8, 1024)
+    }
+
+    /// 使用自定义历史窗口和跟踪上限创建检测器
+    /// Create detector with custom history window and tracking limit
+    pub fn with_limits(history_window: usize, max_tracked_blocks: usize) -> Self {
+        Self {
+            history_window: history_window.max(1),
+            max_tracked_blocks: max_tracked_blocks.max(1),
+            state: Mutex::new(RaceDetectorState::default()),
+        }
+    }
+
+    /// 当前跟踪的地址块数量
+    /// Number of address blocks currently tracked
+    pub fn tracked_blocks(&self) -> usize {
+        self.state.lock().map(|state| state.blocks.len()).unwrap_or(0)
+    }
+
+    fn access_size(context: &SecurityContext) -> u32 {
+        match context.parameters.get("access_size") {
+            Some(Value::I32(size)) if *size > 0 => *size as u32,
+            Some(Value::I64(size)) if *size > 0 => (*size).min(u32::MAX as i64) as u32,
+            _ => RACE_DEFAULT_ACCESS_SIZE,
+        }
+    }
+
+    fn evict_cold_blocks(&self, state: &mut RaceDetectorState) {
+        while state.blocks.len() > self.max_tracked_blocks {
+            let coldest = state.blocks.iter()
+                .min_by_key(|(_, history)| history.last_touched)
+                .map(|(block, _)| *block);
+            match coldest {
+                Some(block) => {
+                    state.blocks.remove(&block);
+                }
+                None => break,
+            }
+        }
+    }
+}
+
+impl ThreatDetector for RaceConditionDetector {
+    fn detect_threat(&self, context: &SecurityContext) -> Vec<ThreatDetection> {
+        let (Some(thread_id), Some(address)) = (context.thread_id, context.memory_address) else {
+            return Vec::new();
+        };
+        let is_write = match context.operation_type {
+            OperationType::MemoryRead => false,
+            OperationType::MemoryWrite => true,
+            OperationType::AtomicAccess => false,
+            _ => return Vec::new(),
+        };
+
+        let access = MemoryAccessRecord {
+            thread_id,
+            start: address,
+            end: address.saturating_add(Self::access_size(context)),
+            is_write,
+        };
+        let first_block = access.start >> RACE_BLOCK_SHIFT;
+        let last_block = (access.end.saturating_sub(1) >> RACE_BLOCK_SHIFT)
+            .min(first_block.saturating_add(RACE_MAX_BLOCKS_PER_ACCESS - 1));
+
+        let Ok(mut state) = self.state.lock() else {
+            return Vec::new();
+        };
+        state.tick += 1;
+        let tick = state.tick;
+
+        // 原子操作作为同步点：清除重叠范围内的历史访问
+        if matches!(context.operation_type, OperationType::AtomicAccess) {
+            for block in first_block..=last_block {
+                if let Some(history) = state.blocks.get_mut(&block) {
+                    history.accesses.retain(|record| !record.overlaps(&access));
+                    history.last_touched = tick;
+                }
+            }
+            return Vec::new();
+        }
+
+        let mut conflicting_threads = HashSet::new();
+        let mut write_write = false;
+        let mut max_conflicts = 0;
+
+        for block in first_block..=last_block {
+            let history = state.blocks.entry(block).or_default();
+            history.last_touched = tick;
+
+            let mut block_conflict = false;
+            for record in &history.accesses {
+                if record.thread_id != thread_id
+                    && (record.is_write || is_write)
+                    && record.overlaps(&access)
+                {
+                    block_conflict = true;
+                    write_write |= record.is_write && is_write;
+                    conflicting_threads.insert(record.thread_id);
+                }
+            }
+            if block_conflict {
+                history.conflicts += 1;
+                max_conflicts = max_conflicts.max(history.conflicts);
+            }
+
+            if history.accesses.len() >= self.history_window {
+                history.accesses.pop_front();
+            }
+            history.accesses.push_back(access);
+        }
+
+        self.evict_cold_blocks(&mut state);
+
+        if conflicting_threads.is_empty() {
+            return Vec::new();
+        }
+
+        // 同一地址上重复出现的冲突会提高置信度: 0.75, 0.875, 0.9375, ...
+        let confidence = 1.0 - 0.5_f64.powi(max_conflicts.min(16) as i32 + 1);
+        let mut threads: Vec<u32> = conflicting_threads.into_iter().collect();
+        threads.sort_unstable();
+
+        vec![ThreatDetection {
+            threat_type: ThreatType::RaceCondition,
+            severity: if write_write { SecuritySeverity::Error } else { SecuritySeverity::Warning },
+            confidence,
+            details: format!(
+                "线程 {} 对 0x{:X}..0x{:X} 的{}与线程 {:?} 的访问冲突，中间没有原子操作",
+                thread_id,
+                access.start,
+                access.end,
+                if is_write { "写入" } else { "读取" },
+                threads
+            ),
+            mitigation_suggestions: vec![
+                "使用原子指令访问共享内存".to_string(),
+                "通过 memory.atomic.wait/notify 进行同步".to_string(),
+            ],
+        }]
+    }
+
+    fn supported_threat_types(&self) -> Vec<ThreatType> {
+        vec![ThreatType::RaceCondition]
+    }
+
+    fn name(&self) -> String {
+        "RaceConditionDetector".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn access(thread_id: u32, operation_type: OperationType, address: u32, size: i32) -> SecurityContext {
+        let mut parameters = HashMap::new();
+        parameters.insert("access_size".to_string(), Value::I32(size));
+        SecurityContext {
+            module_id: None,
+            function_index: None,
+            memory_address: Some(address),
+            operation_type,
+            parameters,
+            call_stack: Vec::new(),
+            thread_id: Some(thread_id),
+            principal: None,
+        }
+    }
+
+    #[test]
+    fn test_race_detector_flags_concurrent_writers() {
+        let detector = RaceConditionDetector::new();
+
+        assert!(detector.detect_threat(&access(1, OperationType::MemoryWrite, 0x100, 4)).is_empty());
+        let first = detector.detect_threat(&access(2, OperationType::MemoryWrite, 0x100, 4));
+        assert_eq!(first.len(), 1);
+        assert_eq!(first[0].threat_type, ThreatType::RaceCondition);
+        assert_eq!(first[0].severity, SecuritySeverity::Error);
+
+        let second = detector.detect_threat(&access(1, OperationType::MemoryWrite, 0x100, 4));
+        assert_eq!(second.len(), 1);
+        assert!(second[0].confidence > first[0].confidence);
+    }
+
+    #[test]
+    fn test_race_detector_ignores_disjoint_ranges() {
+        let detector = RaceConditionDetector::new();
+
+        assert!(detector.detect_threat(&access(1, OperationType::MemoryWrite, 0x200, 4)).is_empty());
+        assert!(detector.detect_threat(&access(2, OperationType::MemoryRead, 0x204, 4)).is_empty());
+        assert!(detector.detect_threat(&access(2, OperationType::MemoryRead, 0x1FC, 4)).is_empty());
+    }
+
+    #[test]
+    fn test_race_detector_atomic_suppresses_report() {
+        let detector = RaceConditionDetector::new();
+
+        detector.detect_threat(&access(1, OperationType::MemoryWrite, 0x300, 4));
+        assert!(detector.detect_threat(&access(1, OperationType::AtomicAccess, 0x300, 4)).is_empty());
+        assert!(detector.detect_threat(&access(2, OperationType::MemoryRead, 0x300, 4)).is_empty());
+    }
+
+    #[test]
+    fn test_race_detector_evicts_cold_blocks() {
+        let detector = RaceConditionDetector::with_limits(4, 8);
+
+        for i in 0..64 {
+            detector.detect_threat(&access(1, OperationType::MemoryWrite, i * 64, 4));
+        }
+        assert_eq!(detector.tracked_blocks(), 8);
+    }
+
+    /// 对指定函数的调用报告一次低危威胁
+    struct CallWatcher(u32);
+
+    impl ThreatDetector for CallWatcher {
+        fn detect_threat(&self, context: &SecurityContext) -> Vec<ThreatDetection> {
+            if context.function_index != Some(self.0) {
+                return Vec::new();
+            }
+            vec![ThreatDetection {
+                threat_type: ThreatType::PrivilegeEscalation,
+                severity: SecuritySeverity::Warning,
+                confidence: 0.9,
+                details: "watched call".to_string(),
+                mitigation_suggestions: Vec::new(),
+            }]
+        }
+
+        fn supported_threat_types(&self) -> Vec<ThreatType> {
+            vec![ThreatType::PrivilegeEscalation]
+        }
+
+        fn name(&self) -> String {
+            "CallWatcher".to_string()
+        }
+    }
+
+    #[test]
+    fn test_runtime_security_events_carry_stack_trace() {
+        use crate::webassembly_2_0::*;
+
+        let mut module = WebAssembly2Module::new("nested".to_string());
+        let bodies = [
+            ("A", vec![WebAssembly2Instruction::Call(1)]),
+            ("B", vec![WebAssembly2Instruction::I32Const(1), WebAssembly2Instruction::Call(2), WebAssembly2Instruction::I32Add]),
+            ("C", vec![WebAssembly2Instruction::I32Const(2)]),
+        ];
+        for (index, (name, body)) in bodies.into_iter().enumerate() {
+            let mut function = WebAssembly2Function::new(index as u32, name.to_string(), Vec::new(), vec![ValueType::I32]);
+            function.body = body;
+            module.functions.push(function);
+        }
+
+        let mut manager = AdvancedSecurityManager::new();
+        manager.add_threat_detector(Box::new(CallWatcher(2)));
+        let manager = Arc::new(Mutex::new(manager));
+
+        let mut runtime = WebAssembly2Runtime::new();
+        runtime.security_manager = Some(manager.clone());
+        let module_id = runtime.load_module(module).unwrap();
+        assert_eq!(runtime.execute_function(&module_id, 0, Vec::new()).unwrap(), vec![Value::I32(3)]);
+
+        let manager = manager.lock().unwrap();
+        let events = manager.event_log.lock().unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].function_index, Some(2));
+        assert_eq!(events[0].stack_trace, vec!["A:0", "B:0", "C:1"]);
+    }
+
+    #[test]
+    fn test_security_events_record_principal() {
+        let mut manager = AdvancedSecurityManager::new();
+        manager.add_threat_detector(Box::new(CallWatcher(1)));
+        let principal = Principal {
+            subject: "user-1".to_string(),
+            issuer: Some("https://auth.example".to_string()),
+            scopes: vec!["modules:read".to_string()],
+        };
+        let context = SecurityContext {
+            module_id: None,
+            function_index: Some(1),
+            memory_address: None,
+            operation_type: OperationType::FunctionCall,
+            parameters: HashMap::new(),
+            call_stack: Vec::new(),
+            thread_id: None,
+            principal: Some(principal.clone()),
+        };
+        manager.perform_security_check(&context);
+
+        let events = manager.event_log.lock().unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].principal, Some(principal));
+    }
+
+    fn policy_with_level(security_level: SecurityLevel) -> SecurityPolicy {
+        SecurityPolicy {
+            id: format!("{:?}", security_level),
+            name: format!("{:?}", security_level),
+            security_level,
+            enabled_threats: HashSet::new(),
+            memory_limits: MemoryLimits {
+                max_memory_size: 1 << 20,
+                max_stack_size: 1 << 16,
+                max_heap_size: 1 << 20,
+                memory_alignment: 8,
+            },
+            execution_time_limit: None,
+            function_call_limit: None,
+            allowed_imports: HashSet::new(),
+            forbidden_imports: HashSet::new(),
+            sandbox_config: SandboxConfig {
+                enabled: false,
+                allowed_syscalls: HashSet::new(),
+                filesystem_restrictions: FilesystemRestrictions {
+                    allowed_paths: Vec::new(),
+                    forbidden_paths: Vec::new(),
+                    read_only_paths: Vec::new(),
+                },
+                network_restrictions: NetworkRestrictions {
+                    allowed_domains: Vec::new(),
+                    allowed_ports: Vec::new(),
+                    forbidden_protocols: Vec::new(),
+                },
+            },
+        }
+    }
+
+    #[test]
+    fn test_integrity_check_follows_security_level() {
+        use VerificationStatus::*;
+        let accepted = |level: Option<SecurityLevel>| {
+            let mut manager = AdvancedSecurityManager::new();
+            if let Some(level) = level {
+                manager.add_policy(policy_with_level(level));
+                manager.set_active_policy(format!("{:?}", level)).unwrap();
+            }
+            [Verified, UnknownKey, Unsigned, BadSignature]
+                .map(|status| manager.check_integrity("module", status).is_ok())
+        };
+        assert_eq!(accepted(Some(SecurityLevel::Low)), [true, true, true, true]);
+        assert_eq!(accepted(None), [true, true, true, false]);
+        assert_eq!(accepted(Some(SecurityLevel::High)), [true, false, false, false]);
+        assert_eq!(accepted(Some(SecurityLevel::Maximum)), [true, false, false, false]);
+
+        let manager = AdvancedSecurityManager::new();
+        assert!(matches!(manager.check_integrity("module", BadSignature), Err(SecurityError::IntegrityCheckFailed(_))));
+        let events = manager.event_log.lock().unwrap();
+        assert_eq!(events.len(), 1);
+        assert!(events[0].details.contains("module"));
+    }
+
+    #[test]
+    fn test_unknown_key_is_rejected_at_high_level() {
+        // 任何人都能用自己的密钥签名，密钥未知的签名在高级别不能代替受信任的签名
+        for level in [SecurityLevel::High, SecurityLevel::Maximum] {
+            let mut manager = AdvancedSecurityManager::new();
+            manager.add_policy(policy_with_level(level));
+            manager.set_active_policy(format!("{:?}", level)).unwrap();
+            let error = manager.check_integrity("self-signed", VerificationStatus::UnknownKey).unwrap_err();
+            assert!(matches!(&error, SecurityError::IntegrityCheckFailed(details) if details.contains("UnknownKey")), "{error:?}");
+            assert_eq!(manager.event_log.lock().unwrap().len(), 1);
+        }
+
+        let mut manager = AdvancedSecurityManager::new();
+        manager.add_policy(policy_with_level(SecurityLevel::Medium));
+        manager.set_active_policy("Medium".to_string()).unwrap();
+        assert!(manager.check_integrity("self-signed", VerificationStatus::UnknownKey).is_ok());
+    }
+}
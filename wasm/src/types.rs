@@ -620,7 +620,7 @@ impl Function {
 ///
 /// 定义函数的签名类型。
 /// Defines the signature type of a function.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct FunctionType {
     /// 参数类型 / Parameter Types
     pub params: Vec<ValueType>,
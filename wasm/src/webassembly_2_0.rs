@@ -11,9 +11,11 @@
 //!
 //! 基于 2024年12月发布的 WebAssembly 2.0 候选推荐标准
 
+use crate::security_advanced::{AdvancedSecurityManager, OperationType, SecurityContext, StackFrame};
 use crate::types::*;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 use thiserror::Error;
 
@@ -112,10 +114,13 @@ impl WebAssembly2Module {
         // 验证特性兼容性
         self.validate_feature_compatibility(&mut errors);
 
-        // 验证函数
+        // 验证函数（启用多值返回特性时允许多个返回值）
+        let multi_value = self.supports_feature(&WebAssembly2Features::MultiValue);
         for function in &self.functions {
-            if let Err(e) = function.validate() {
-                errors.push(e);
+            match function.validate() {
+                Err(ValidationError::MultiValueNotSupported) if multi_value => {}
+                Err(e) => errors.push(e),
+                Ok(()) => {}
             }
         }
 
@@ -206,6 +211,29 @@ impl WebAssembly2Function {
         }
     }
 
+    /// 输出 WAT 文本格式的函数定义
+    /// Print the function definition in WAT text format
+    ///
+    /// `Br`/`BrIf` 的跳转目标按指令索引输出。
+    /// Branch targets of `Br`/`BrIf` are printed as instruction indices.
+    pub fn to_wat(&self) -> String {
+        let mut header = format!("(func {}", wat_identifier(&self.name));
+        for (keyword, types) in [("param", &self.params), ("result", &self.results), ("local", &self.locals)] {
+            if !types.is_empty() {
+                let names: Vec<&str> = types.iter().map(wat_value_type).collect();
+                header.push_str(&format!(" ({} {})", keyword, names.join(" ")));
+            }
+        }
+        if self.body.is_empty() {
+            return format!("{})\n", header);
+        }
+
+        let mut wat = format!("{}\n", header);
+        write_wat_instructions(&self.body, 1, &mut wat);
+        wat.push_str(")\n");
+        wat
+    }
+
     /// 验证函数
     /// Validate function
     pub fn validate(&self) -> Result<(), ValidationError> {
@@ -273,7 +301,15 @@ pub enum WebAssembly2Instruction {
     I32Mul,
     I32Div,
     Call(u32),
+    /// 通过表间接调用（参数为表索引，被调函数索引从栈顶弹出）
+    CallIndirect(u32),
     Return,
+    LocalGet(u32),
+    LocalSet(u32),
+    /// 无条件跳转到函数体内的指令位置
+    Br(u32),
+    /// 弹出 i32，非零时跳转到函数体内的指令位置
+    BrIf(u32),
 
     /// WebAssembly 2.0 新指令
     /// WebAssembly 2.0 new instructions
@@ -357,6 +393,198 @@ pub enum WebAssembly2Instruction {
     StringAsUpper,
 }
 
+impl WebAssembly2Instruction {
+    /// 输出单条指令的 WAT 文本（块指令只输出块头）
+    /// Print a single instruction as WAT text (block instructions print their header only)
+    pub fn to_wat(&self) -> String {
+        use WebAssembly2Instruction as I;
+        let memarg = |offset: &u32| format!("offset={}", offset);
+        match self {
+            I::I32Const(value) => format!("i32.const {}", value),
+            I::I64Const(value) => format!("i64.const {}", value),
+            I::F32Const(value) => format!("f32.const {}", wat_float(*value as f64)),
+            I::F64Const(value) => format!("f64.const {}", wat_float(*value)),
+            I::I32Add => "i32.add".to_string(),
+            I::I32Sub => "i32.sub".to_string(),
+            I::I32Mul => "i32.mul".to_string(),
+            I::I32Div => "i32.div_s".to_string(),
+            I::Call(index) => format!("call {}", index),
+            I::CallIndirect(table) => format!("call_indirect {}", table),
+            I::Return => "return".to_string(),
+            I::LocalGet(index) => format!("local.get {}", index),
+            I::LocalSet(index) => format!("local.set {}", index),
+            I::Br(target) => format!("br {}", target),
+            I::BrIf(target) => format!("br_if {}", target),
+            I::MemoryCopy { src, dst, size } => format!("memory.copy {} {} {}", dst, src, size),
+            I::MemoryFill { addr, value, size } => format!("memory.fill {} {} {}", addr, value, size),
+            I::TableCopy { src_table, dst_table, src_offset, dst_offset, size } => {
+                format!("table.copy {} {} {} {} {}", dst_table, src_table, dst_offset, src_offset, size)
+            }
+            I::TableFill { table, offset, value, size } => {
+                let value = value.map_or_else(|| "ref.null".to_string(), |index| format!("ref.func {}", index));
+                format!("table.fill {} {} ({}) {}", table, offset, value, size)
+            }
+            I::ReturnCall(index) => format!("return_call {}", index),
+            I::ReturnCallIndirect(table) => format!("return_call_indirect {}", table),
+            I::ReturnValues(values) => {
+                let operands: Vec<String> = values.iter().map(|value| format!(" ({})", wat_const(value))).collect();
+                format!("return{}", operands.concat())
+            }
+            I::Throw(tag) => format!("throw {}", tag),
+            I::Rethrow => "rethrow 0".to_string(),
+            I::TryCatch(_) | I::TryCatchAll(_) => "try".to_string(),
+            I::V128Const(bytes) => {
+                let lanes: Vec<String> = bytes.iter().map(|byte| byte.to_string()).collect();
+                format!("v128.const i8x16 {}", lanes.join(" "))
+            }
+            I::V128Load { offset, align } => format!("v128.load offset={} align={}", offset, align),
+            I::V128Store { offset, align } => format!("v128.store offset={} align={}", offset, align),
+            I::V128Add => "v128.add".to_string(),
+            I::V128Sub => "v128.sub".to_string(),
+            I::V128Mul => "v128.mul".to_string(),
+            I::V128Div => "v128.div".to_string(),
+            I::V128And => "v128.and".to_string(),
+            I::V128Or => "v128.or".to_string(),
+            I::V128Xor => "v128.xor".to_string(),
+            I::V128Not => "v128.not".to_string(),
+            I::V128Shl => "v128.shl".to_string(),
+            I::V128Shr => "v128.shr".to_string(),
+            I::V128Eq => "v128.eq".to_string(),
+            I::V128Ne => "v128.ne".to_string(),
+            I::V128Lt => "v128.lt".to_string(),
+            I::V128Le => "v128.le".to_string(),
+            I::V128Gt => "v128.gt".to_string(),
+            I::V128Ge => "v128.ge".to_string(),
+            I::V128Load8x8S { offset } => format!("v128.load8x8_s {}", memarg(offset)),
+            I::V128Load8x8U { offset } => format!("v128.load8x8_u {}", memarg(offset)),
+            I::V128Load16x4S { offset } => format!("v128.load16x4_s {}", memarg(offset)),
+            I::V128Load16x4U { offset } => format!("v128.load16x4_u {}", memarg(offset)),
+            I::V128Load32x2S { offset } => format!("v128.load32x2_s {}", memarg(offset)),
+            I::V128Load32x2U { offset } => format!("v128.load32x2_u {}", memarg(offset)),
+            I::V128Store8x8 { offset } => format!("v128.store8x8 {}", memarg(offset)),
+            I::V128Store16x4 { offset } => format!("v128.store16x4 {}", memarg(offset)),
+            I::V128Store32x2 { offset } => format!("v128.store32x2 {}", memarg(offset)),
+            I::StringNew { encoding } => format!("string.new_{}", wat_encoding(encoding)),
+            I::StringMeasure { encoding } => format!("string.measure_{}", wat_encoding(encoding)),
+            I::StringEncode { encoding } => format!("string.encode_{}", wat_encoding(encoding)),
+            I::StringConcat => "string.concat".to_string(),
+            I::StringEq => "string.eq".to_string(),
+            I::StringAsWTF16 => "string.as_wtf16".to_string(),
+            I::StringFromWTF16 => "string.new_wtf16".to_string(),
+            I::StringFromWTF8Array => "string.new_wtf8_array".to_string(),
+            I::StringToWTF8Array => "string.encode_wtf8_array".to_string(),
+            I::StringConst(value) => format!("string.const {:?}", value),
+            I::StringMeasureWTF8 => "string.measure_wtf8".to_string(),
+            I::StringMeasureWTF16 => "string.measure_wtf16".to_string(),
+            I::StringEncodeWTF8 => "string.encode_wtf8".to_string(),
+            I::StringEncodeWTF16 => "string.encode_wtf16".to_string(),
+            I::StringConstWTF16(units) => {
+                let units: Vec<String> = units.iter().map(|unit| unit.to_string()).collect();
+                format!("string.const_wtf16 {}", units.join(" "))
+            }
+            I::StringConstWTF8Array(bytes) => {
+                let bytes: Vec<String> = bytes.iter().map(|byte| byte.to_string()).collect();
+                format!("string.const_wtf8_array {}", bytes.join(" "))
+            }
+            I::StringAsLower => "string.as_lower".to_string(),
+            I::StringAsUpper => "string.as_upper".to_string(),
+        }
+    }
+}
+
+/// 按缩进层级输出指令序列，展开 try/catch 块
+/// Print an instruction sequence at the given indent level, expanding try/catch blocks
+fn write_wat_instructions(instructions: &[WebAssembly2Instruction], depth: usize, wat: &mut String) {
+    let indent = "  ".repeat(depth);
+    for instruction in instructions {
+        match instruction {
+            WebAssembly2Instruction::TryCatch(block) => {
+                wat.push_str(&format!("{}try\n", indent));
+                write_wat_instructions(&block.try_instructions, depth + 1, wat);
+                wat.push_str(&format!("{}catch {}\n", indent, block.catch_label));
+                write_wat_instructions(&block.catch_instructions, depth + 1, wat);
+                wat.push_str(&format!("{}end\n", indent));
+            }
+            WebAssembly2Instruction::TryCatchAll(block) => {
+                wat.push_str(&format!("{}try\n", indent));
+                write_wat_instructions(&block.try_instructions, depth + 1, wat);
+                wat.push_str(&format!("{}catch_all\n", indent));
+                write_wat_instructions(&block.catch_all_instructions, depth + 1, wat);
+                wat.push_str(&format!("{}end\n", indent));
+            }
+            other => wat.push_str(&format!("{}{}\n", indent, other.to_wat())),
+        }
+    }
+}
+
+/// WAT 标识符，名称已带 `$` 前缀时保持不变
+/// WAT identifier; names that already start with `$` are kept as is
+fn wat_identifier(name: &str) -> String {
+    if name.starts_with('$') {
+        name.to_string()
+    } else {
+        format!("${}", name)
+    }
+}
+
+/// 值类型的 WAT 名称
+/// WAT name of a value type
+fn wat_value_type(value_type: &ValueType) -> &'static str {
+    match value_type {
+        ValueType::I32 => "i32",
+        ValueType::I64 => "i64",
+        ValueType::F32 => "f32",
+        ValueType::F64 => "f64",
+        ValueType::V128 => "v128",
+        ValueType::FuncRef => "funcref",
+        ValueType::ExternRef => "externref",
+        ValueType::I128 => "i128",
+        ValueType::U128 => "u128",
+    }
+}
+
+/// 浮点常量的 WAT 文本
+/// WAT text of a float constant
+fn wat_float(value: f64) -> String {
+    if value.is_nan() {
+        "nan".to_string()
+    } else if value.is_infinite() {
+        if value > 0.0 { "inf" } else { "-inf" }.to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+/// 常量值的 WAT 指令
+/// WAT instruction producing a constant value
+fn wat_const(value: &Value) -> String {
+    match value {
+        Value::I32(v) => format!("i32.const {}", v),
+        Value::I64(v) => format!("i64.const {}", v),
+        Value::F32(v) => format!("f32.const {}", wat_float(*v as f64)),
+        Value::F64(v) => format!("f64.const {}", wat_float(*v)),
+        Value::FuncRef(Some(index)) => format!("ref.func {}", index),
+        Value::FuncRef(None) => "ref.null func".to_string(),
+        Value::ExternRef(Some(v)) => format!("ref.extern {}", v),
+        Value::ExternRef(None) => "ref.null extern".to_string(),
+        Value::I128(v) => format!("i128.const {}", v),
+        Value::U128(v) => format!("u128.const {}", v),
+        Value::V128(bytes) => WebAssembly2Instruction::V128Const(*bytes).to_wat(),
+    }
+}
+
+/// 字符串编码的 WAT 后缀
+/// WAT suffix of a string encoding
+fn wat_encoding(encoding: &StringEncoding) -> &'static str {
+    match encoding {
+        StringEncoding::UTF8 => "utf8",
+        StringEncoding::UTF16 => "utf16",
+        StringEncoding::Latin1 => "latin1",
+        StringEncoding::WTF8 => "wtf8",
+        StringEncoding::WTF16 => "wtf16",
+    }
+}
+
 /// 字符串编码类型
 /// String encoding type
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -844,6 +1072,15 @@ pub enum WebAssembly2Error {
     /// 处理器中无效指令
     #[error("异常处理器中无效指令")]
     InvalidInstructionInHandler,
+    /// 参数数量不匹配
+    #[error("参数数量不匹配: 期望 {expected}, 实际 {actual}")]
+    ArgumentCountMismatch { expected: usize, actual: usize },
+    /// 执行陷阱
+    #[error("执行陷阱: {0}")]
+    Trap(String),
+    /// 超过执行截止时间
+    #[error("超过执行截止时间")]
+    DeadlineExceeded,
 }
 
 /// WebAssembly 2.0 运行时
@@ -858,6 +1095,14 @@ pub struct WebAssembly2Runtime {
     pub supported_features: Vec<WebAssembly2Features>,
     /// 性能统计
     pub performance_stats: PerformanceStats,
+    /// 最大调用深度
+    pub max_call_depth: usize,
+    /// 安全管理器，设置后每次函数调用前执行安全检查
+    pub security_manager: Option<Arc<Mutex<AdvancedSecurityManager>>>,
+    /// 执行截止时间，超过后 `execute_function` 中止执行
+    pub execution_deadline: Option<Instant>,
+    /// 指令覆盖率记录，键为 (函数索引, 指令索引)，值为执行次数
+    pub coverage: Option<InstructionCoverage>,
 }
 
 impl Default for WebAssembly2Runtime {
@@ -884,6 +1129,10 @@ impl WebAssembly2Runtime {
                 WebAssembly2Features::ReferenceTypes,
             ],
             performance_stats: PerformanceStats::new(),
+            max_call_depth: 1024,
+            security_manager: None,
+            execution_deadline: None,
+            coverage: None,
         }
     }
 
@@ -919,7 +1168,49 @@ impl WebAssembly2Runtime {
         args: Vec<Value>,
     ) -> Result<Vec<Value>, WebAssembly2Error> {
         let start = Instant::now();
+
+        // 执行函数
+        let result = self.execute_function_internal(module_id, function_index, args)?;
         
+        // 更新性能统计
+        let execution_time = start.elapsed();
+        self.performance_stats.record_execution(execution_time);
+        
+        Ok(result)
+    }
+
+    /// 内部函数执行
+    /// Internal function execution
+    fn execute_function_internal(
+        &mut self,
+        module_id: &ModuleId,
+        function_index: u32,
+        args: Vec<Value>,
+    ) -> Result<Vec<Value>, WebAssembly2Error> {
+        let mut execution = self.start_execution(module_id, function_index, args)?;
+        let mut steps: u64 = 0;
+        loop {
+            if let StepOutcome::Returned(values) = self.step(&mut execution)? {
+                return Ok(values);
+            }
+            // 每执行一批指令检查一次截止时间，避免频繁读取时钟
+            steps += 1;
+            if steps.is_multiple_of(DEADLINE_CHECK_INTERVAL)
+                && self.execution_deadline.is_some_and(|deadline| Instant::now() >= deadline)
+            {
+                return Err(WebAssembly2Error::DeadlineExceeded);
+            }
+        }
+    }
+
+    /// 准备一次可单步执行的函数调用
+    /// Prepare a function call that can be executed step by step
+    pub fn start_execution(
+        &self,
+        module_id: &ModuleId,
+        function_index: u32,
+        args: Vec<Value>,
+    ) -> Result<WebAssembly2Execution, WebAssembly2Error> {
         // 获取模块
         let module = self.modules.get(module_id)
             .ok_or_else(|| WebAssembly2Error::FeatureDependencyError {
@@ -934,60 +1225,348 @@ impl WebAssembly2Runtime {
                 required: "FunctionIndex".to_string(),
             })?;
 
-        // 克隆函数以避免借用冲突
-        let function_clone = function.clone();
-        let module_id_clone = module_id.clone();
+        // 获取执行环境
+        if !self.execution_environments.contains_key(module_id) {
+            return Err(WebAssembly2Error::FeatureDependencyError {
+                feature: "ExecutionEnvironment".to_string(),
+                required: "ModuleId".to_string(),
+            });
+        }
 
-        // 执行函数
-        let result = self.execute_function_internal(&module_id_clone, &function_clone, args)?;
-        
-        // 更新性能统计
-        let execution_time = start.elapsed();
-        self.performance_stats.record_execution(execution_time);
-        
-        Ok(result)
+        if args.len() != function.params.len() {
+            return Err(WebAssembly2Error::ArgumentCountMismatch {
+                expected: function.params.len(),
+                actual: args.len(),
+            });
+        }
+
+        let execution = WebAssembly2Execution {
+            module_id: module_id.clone(),
+            frames: vec![ExecutionFrame::new(function_index, function, args, 0, 0)],
+            stack: Vec::new(),
+        };
+        self.check_call(&execution, module)?;
+        Ok(execution)
     }
 
-    /// 内部函数执行
-    /// Internal function execution
-    fn execute_function_internal(
-        &mut self,
-        module_id: &ModuleId,
-        function: &WebAssembly2Function,
-        _args: Vec<Value>,
-    ) -> Result<Vec<Value>, WebAssembly2Error> {
-        // 获取执行环境
-        let _execution_env = self.execution_environments.get_mut(module_id)
+    /// 对刚进入的函数执行安全检查，调用栈随上下文一起提交
+    /// Run the security check for the function just entered, including the call stack
+    fn check_call(&self, execution: &WebAssembly2Execution, module: &WebAssembly2Module) -> Result<(), WebAssembly2Error> {
+        let (Some(manager), Some(frame)) = (&self.security_manager, execution.current_frame()) else {
+            return Ok(());
+        };
+        let context = SecurityContext {
+            module_id: Some(execution.module_id.clone()),
+            function_index: Some(frame.function_index),
+            memory_address: None,
+            operation_type: OperationType::FunctionCall,
+            parameters: HashMap::new(),
+            call_stack: execution.call_stack(module),
+            thread_id: None,
+            principal: None,
+        };
+        let result = manager.lock()
+            .map_err(|_| WebAssembly2Error::Trap("安全管理器不可用".to_string()))?
+            .perform_security_check(&context);
+        if result.blocked {
+            return Err(WebAssembly2Error::Trap(format!("安全检查阻止了对函数 {} 的调用", frame.function_index)));
+        }
+        Ok(())
+    }
+
+    /// 执行一条指令；函数体执行完毕或遇到返回指令时弹出调用帧
+    /// Execute exactly one instruction of the innermost frame
+    pub fn step(&mut self, execution: &mut WebAssembly2Execution) -> Result<StepOutcome, WebAssembly2Error> {
+        let module = self.modules.get(&execution.module_id)
             .ok_or_else(|| WebAssembly2Error::FeatureDependencyError {
-                feature: "ExecutionEnvironment".to_string(),
+                feature: "Module".to_string(),
                 required: "ModuleId".to_string(),
             })?;
-
-        // 执行指令
-        let mut stack: Vec<Value> = Vec::new();
-        let _exception_stack: Vec<ExceptionType> = Vec::new();
-        
-        for instruction in &function.body {
-            match instruction {
-                WebAssembly2Instruction::I32Const(value) => {
-                    stack.push(Value::I32(*value));
+        let frame = execution.frames.last_mut()
+            .ok_or_else(|| WebAssembly2Error::Trap("执行已结束".to_string()))?;
+        let function = module.functions.get(frame.function_index as usize)
+            .ok_or_else(|| WebAssembly2Error::Trap(format!("无效的函数索引: {}", frame.function_index)))?;
+
+        // 函数体末尾隐式返回
+        let Some(instruction) = function.body.get(frame.instruction_index as usize) else {
+            return execution.return_from_frame(function.results.len());
+        };
+        if let Some(coverage) = &self.coverage
+            && let Ok(mut coverage) = coverage.lock()
+        {
+            *coverage.entry((frame.function_index, frame.instruction_index)).or_insert(0) += 1;
+        }
+        frame.instruction_index += 1;
+
+        match instruction {
+            WebAssembly2Instruction::I32Const(value) => execution.stack.push(Value::I32(*value)),
+            WebAssembly2Instruction::I64Const(value) => execution.stack.push(Value::I64(*value)),
+            WebAssembly2Instruction::F32Const(value) => execution.stack.push(Value::F32(*value)),
+            WebAssembly2Instruction::F64Const(value) => execution.stack.push(Value::F64(*value)),
+            WebAssembly2Instruction::I32Add => {
+                let (a, b) = execution.pop_i32_pair()?;
+                execution.stack.push(Value::I32(a.wrapping_add(b)));
+            }
+            WebAssembly2Instruction::I32Sub => {
+                let (a, b) = execution.pop_i32_pair()?;
+                execution.stack.push(Value::I32(a.wrapping_sub(b)));
+            }
+            WebAssembly2Instruction::I32Mul => {
+                let (a, b) = execution.pop_i32_pair()?;
+                execution.stack.push(Value::I32(a.wrapping_mul(b)));
+            }
+            WebAssembly2Instruction::I32Div => {
+                let (a, b) = execution.pop_i32_pair()?;
+                if b == 0 {
+                    return Err(WebAssembly2Error::Trap("整数除零".to_string()));
                 }
-                WebAssembly2Instruction::I32Add => {
-                    if let (Some(Value::I32(b)), Some(Value::I32(a))) = (stack.pop(), stack.pop()) {
-                        stack.push(Value::I32(a + b));
-                    }
+                let quotient = a.checked_div(b)
+                    .ok_or_else(|| WebAssembly2Error::Trap("整数溢出".to_string()))?;
+                execution.stack.push(Value::I32(quotient));
+            }
+            WebAssembly2Instruction::LocalGet(index) => {
+                let value = *frame.locals.get(*index as usize)
+                    .ok_or_else(|| WebAssembly2Error::Trap(format!("无效的局部变量索引: {}", index)))?;
+                execution.stack.push(value);
+            }
+            WebAssembly2Instruction::LocalSet(index) => {
+                let index = *index as usize;
+                if index >= frame.locals.len() {
+                    return Err(WebAssembly2Error::Trap(format!("无效的局部变量索引: {}", index)));
                 }
-                WebAssembly2Instruction::Return => {
-                    break;
+                let value = execution.pop()?;
+                if let Some(frame) = execution.frames.last_mut() {
+                    frame.locals[index] = value;
                 }
-                _ => {
-                    // 其他指令的处理逻辑
+            }
+            WebAssembly2Instruction::Br(target) => {
+                frame.instruction_index = *target;
+            }
+            WebAssembly2Instruction::BrIf(target) => {
+                let condition = match execution.pop()? {
+                    Value::I32(condition) => condition,
+                    other => return Err(WebAssembly2Error::Trap(format!("类型不匹配: 期望 i32, 实际 {:?}", other))),
+                };
+                if condition != 0
+                    && let Some(frame) = execution.frames.last_mut()
+                {
+                    frame.instruction_index = *target;
                 }
             }
+            WebAssembly2Instruction::Call(callee_index) => {
+                execution.call(module, *callee_index, false, self.max_call_depth)?;
+                self.check_call(execution, module)?;
+            }
+            WebAssembly2Instruction::ReturnCall(callee_index) => {
+                execution.call(module, *callee_index, true, self.max_call_depth)?;
+                self.check_call(execution, module)?;
+            }
+            WebAssembly2Instruction::CallIndirect(table_index) | WebAssembly2Instruction::ReturnCallIndirect(table_index) => {
+                let element = match execution.pop()? {
+                    Value::I32(element) => element as u32,
+                    other => return Err(WebAssembly2Error::Trap(format!("类型不匹配: 期望 i32, 实际 {:?}", other))),
+                };
+                let table = module.tables.get(*table_index as usize)
+                    .ok_or_else(|| WebAssembly2Error::Trap(format!("无效的表索引: {}", table_index)))?;
+                let callee_index = match table.data.get(element as usize) {
+                    Some(Some(callee_index)) => *callee_index,
+                    Some(None) => return Err(WebAssembly2Error::Trap(format!("未初始化的表元素: {}", element))),
+                    None => return Err(WebAssembly2Error::Trap(format!("表元素越界: {}", element))),
+                };
+                let tail = matches!(instruction, WebAssembly2Instruction::ReturnCallIndirect(_));
+                execution.call(module, callee_index, tail, self.max_call_depth)?;
+                self.check_call(execution, module)?;
+            }
+            WebAssembly2Instruction::Return => {
+                return execution.return_from_frame(function.results.len());
+            }
+            WebAssembly2Instruction::ReturnValues(values) => {
+                execution.stack.extend(values.iter().copied());
+                return execution.return_from_frame(values.len());
+            }
+            _ => {
+                // 其他指令的处理逻辑
+            }
         }
 
-        // 返回结果
-        Ok(vec![stack.pop().unwrap_or(Value::I32(0))])
+        Ok(StepOutcome::Continue)
+    }
+}
+
+/// 共享的指令覆盖率记录，键为 (函数索引, 指令索引)，值为执行次数
+/// Shared instruction coverage counts keyed by (function index, instruction index)
+pub type InstructionCoverage = Arc<Mutex<HashMap<(u32, u32), u64>>>;
+
+/// 检查执行截止时间的指令间隔
+const DEADLINE_CHECK_INTERVAL: u64 = 1024;
+
+/// 调用帧
+/// Call frame
+#[derive(Debug, Clone)]
+pub struct ExecutionFrame {
+    /// 函数索引
+    pub function_index: u32,
+    /// 下一条待执行指令的索引
+    pub instruction_index: u32,
+    /// 局部变量（参数在前）
+    pub locals: Vec<Value>,
+    /// 进入帧时的操作数栈高度
+    pub stack_base: usize,
+    /// 调用者中发起调用的指令索引
+    pub call_site: u32,
+}
+
+impl ExecutionFrame {
+    /// 为函数创建调用帧，未传入的局部变量初始化为零值
+    /// Create a frame for the function, zero-initializing declared locals
+    fn new(
+        function_index: u32,
+        function: &WebAssembly2Function,
+        args: Vec<Value>,
+        stack_base: usize,
+        call_site: u32,
+    ) -> Self {
+        let mut locals = args;
+        locals.extend(function.locals.iter().map(zero_value));
+        Self {
+            function_index,
+            instruction_index: 0,
+            locals,
+            stack_base,
+            call_site,
+        }
+    }
+}
+
+/// 可单步执行的函数调用状态
+/// Resumable function execution state
+#[derive(Debug, Clone)]
+pub struct WebAssembly2Execution {
+    /// 模块ID
+    pub module_id: ModuleId,
+    /// 调用帧（最内层在末尾）
+    pub frames: Vec<ExecutionFrame>,
+    /// 操作数栈
+    pub stack: Vec<Value>,
+}
+
+/// 单步执行结果
+/// Step outcome
+#[derive(Debug, Clone, PartialEq)]
+pub enum StepOutcome {
+    /// 尚未结束
+    Continue,
+    /// 最外层函数返回
+    Returned(Vec<Value>),
+}
+
+impl WebAssembly2Execution {
+    /// 当前（最内层）调用帧
+    /// Current (innermost) frame
+    pub fn current_frame(&self) -> Option<&ExecutionFrame> {
+        self.frames.last()
+    }
+
+    /// 当前调用帧的操作数栈
+    /// Operand stack of the current frame
+    pub fn frame_stack(&self) -> &[Value] {
+        let base = self.current_frame().map(|frame| frame.stack_base).unwrap_or(0);
+        &self.stack[base.min(self.stack.len())..]
+    }
+
+    fn pop(&mut self) -> Result<Value, WebAssembly2Error> {
+        let base = self.current_frame().map(|frame| frame.stack_base).unwrap_or(0);
+        if self.stack.len() <= base {
+            return Err(WebAssembly2Error::Trap("操作数栈下溢".to_string()));
+        }
+        Ok(self.stack.pop().unwrap_or(Value::I32(0)))
+    }
+
+    fn pop_i32_pair(&mut self) -> Result<(i32, i32), WebAssembly2Error> {
+        match (self.pop()?, self.pop()?) {
+            (Value::I32(b), Value::I32(a)) => Ok((a, b)),
+            (b, a) => Err(WebAssembly2Error::Trap(format!("类型不匹配: 期望 i32, 实际 {:?} 和 {:?}", a, b))),
+        }
+    }
+
+    /// 调用栈（最外层在前）
+    /// Call stack, outermost frame first
+    pub fn call_stack(&self, module: &WebAssembly2Module) -> Vec<StackFrame> {
+        self.frames.iter()
+            .map(|frame| StackFrame {
+                function_name: module.functions.get(frame.function_index as usize)
+                    .map(|function| function.name.clone())
+                    .unwrap_or_default(),
+                function_index: frame.function_index,
+                module_id: self.module_id.clone(),
+                call_address: frame.call_site,
+            })
+            .collect()
+    }
+
+    /// 调用函数；尾调用会先移除当前帧并沿用其调用点
+    fn call(
+        &mut self,
+        module: &WebAssembly2Module,
+        callee_index: u32,
+        tail: bool,
+        max_depth: usize,
+    ) -> Result<(), WebAssembly2Error> {
+        let callee = module.functions.get(callee_index as usize)
+            .ok_or_else(|| WebAssembly2Error::Trap(format!("无效的函数索引: {}", callee_index)))?;
+        let (base, call_site) = match self.current_frame() {
+            // 指令索引已前移，调用指令位于前一位
+            Some(frame) => (frame.stack_base, frame.instruction_index.saturating_sub(1)),
+            None => (0, 0),
+        };
+        if self.stack.len() < base + callee.params.len() {
+            return Err(WebAssembly2Error::Trap("操作数栈下溢".to_string()));
+        }
+        let args = self.stack.split_off(self.stack.len() - callee.params.len());
+        let call_site = if tail {
+            self.stack.truncate(base);
+            self.frames.pop().map_or(call_site, |frame| frame.call_site)
+        } else {
+            call_site
+        };
+        if self.frames.len() >= max_depth {
+            return Err(WebAssembly2Error::Trap("调用栈溢出".to_string()));
+        }
+        self.frames.push(ExecutionFrame::new(callee_index, callee, args, self.stack.len(), call_site));
+        Ok(())
+    }
+
+    /// 弹出当前帧，把返回值交给调用者
+    fn return_from_frame(&mut self, result_count: usize) -> Result<StepOutcome, WebAssembly2Error> {
+        let frame = self.frames.pop()
+            .ok_or_else(|| WebAssembly2Error::Trap("执行已结束".to_string()))?;
+        if self.stack.len() < frame.stack_base + result_count {
+            return Err(WebAssembly2Error::Trap("返回值数量不足".to_string()));
+        }
+        let results = self.stack.split_off(self.stack.len() - result_count);
+        self.stack.truncate(frame.stack_base);
+        if self.frames.is_empty() {
+            Ok(StepOutcome::Returned(results))
+        } else {
+            self.stack.extend(results);
+            Ok(StepOutcome::Continue)
+        }
+    }
+}
+
+/// 值类型的零值
+/// Zero value of a value type
+fn zero_value(value_type: &ValueType) -> Value {
+    match value_type {
+        ValueType::I32 => Value::I32(0),
+        ValueType::I64 => Value::I64(0),
+        ValueType::F32 => Value::F32(0.0),
+        ValueType::F64 => Value::F64(0.0),
+        ValueType::FuncRef => Value::FuncRef(None),
+        ValueType::ExternRef => Value::ExternRef(None),
+        ValueType::I128 => Value::I128(0),
+        ValueType::U128 => Value::U128(0),
+        ValueType::V128 => Value::V128([0; 16]),
     }
 }
 
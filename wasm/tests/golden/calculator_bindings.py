@@ -0,0 +1,46 @@
+# 自动生成的 Python 绑定
+# 模块: calculator
+
+from wasmtime import Engine, Instance, Module, Store
+
+
+class Calculator:
+    """calculator WebAssembly 模块绑定"""
+
+    def __init__(self, store: Store, instance: Instance) -> None:
+        self._store = store
+        self._instance = instance
+        self._exports = instance.exports(store)
+
+    @classmethod
+    def load(cls, path: str) -> "Calculator":
+        """从 .wasm 文件加载模块并实例化"""
+        engine = Engine()
+        store = Store(engine)
+        module = Module.from_file(engine, path)
+        instance = Instance(store, module, [])
+        return cls(store, instance)
+
+    def add(self, a: int, b: int) -> int:
+        """add 函数
+
+        :param a: 左操作数
+        :param b: 右操作数
+        """
+        return self._exports["add"](self._store, int(a), int(b))
+
+    def scale(self, a: int, b: float) -> float:
+        """按比例缩放
+
+        :param a: 左操作数
+        :param b: 右操作数
+        """
+        return self._exports["scale"](self._store, int(a), float(b))
+
+    def read_bytes(self, offset: int, length: int, memory: str = "memory") -> bytes:
+        """从导出内存读取字节"""
+        return bytes(self._exports[memory].read(self._store, offset, offset + length))
+
+    def write_bytes(self, offset: int, data: bytes, memory: str = "memory") -> None:
+        """向导出内存写入字节"""
+        self._exports[memory].write(self._store, data, offset)